@@ -2,6 +2,7 @@
 
 pub mod any;
 pub mod ap_float;
+pub mod ap_int;
 pub mod aps_int;
 pub mod array_ref;
 pub mod bit;
@@ -0,0 +1,227 @@
+/*!
+# Arbitrary Precision Integer
+
+This file implements a class to represent arbitrary precision integral constant values and operations on them.
+
+- include <https://github.com/llvm/llvm-project/blob/main/llvm/include/llvm/ADT/APInt.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/llvm/lib/Support/APInt.cpp>
+*/
+
+use core::fmt;
+
+use crate::adt::small_vector::SmallVector;
+
+/// Class for arbitrary precision integers.
+///
+/// APInt is a functional replacement for common case unsigned integer type like `u32` or `u64`, but also allows non-byte-width integer sizes and large integer value types such as 3-bits, 15-bits, or more than 64-bits of precision. The value is stored as little-endian 64-bit words, and bits above `width` are always zero.
+///
+/// APInt does not carry a sign: the signedness of an operation is chosen by the caller, e.g. `sext_value` vs `zext_value`.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct APInt {
+    width: u32,
+    words: SmallVector<[u64; 1]>
+}
+
+impl APInt {
+    const WORD_BITS: u32 = u64::BITS;
+
+    /// Create a new APInt of `width` bits from the lower bits of `value`. If `is_signed` is true, `value` is sign extended to `width` bits.
+    pub fn new(width: u32, value: u64, is_signed: bool) -> Self {
+        assert!(width != 0, "Bitwidth too small");
+        let mut words = SmallVector::from_elem(0, Self::num_words(width));
+        words[0] = value;
+        if is_signed && (value as i64) < 0 {
+            for word in words.iter_mut().skip(1) {
+                *word = u64::MAX;
+            }
+        }
+        let mut output = Self { width, words };
+        output.clear_unused_bits();
+        output
+    }
+
+    /// Create a new APInt of `width` bits from little-endian 64-bit `words`. Missing words are zero, and extra bits are truncated.
+    pub fn from_words(width: u32, words: &[u64]) -> Self {
+        assert!(width != 0, "Bitwidth too small");
+        let mut output = Self {
+            width,
+            words: SmallVector::from_elem(0, Self::num_words(width))
+        };
+        for (dst, src) in output.words.iter_mut().zip(words) {
+            *dst = *src;
+        }
+        output.clear_unused_bits();
+        output
+    }
+
+    /// Create an APInt of `width` bits with all bits set to zero.
+    pub fn zero(width: u32) -> Self {
+        Self::new(width, 0, false)
+    }
+
+    fn num_words(width: u32) -> usize {
+        width.div_ceil(Self::WORD_BITS) as usize
+    }
+
+    fn clear_unused_bits(&mut self) {
+        let used = self.width % Self::WORD_BITS;
+        if used != 0 {
+            let last = self.words.len() - 1;
+            self.words[last] &= u64::MAX >> (Self::WORD_BITS - used);
+        }
+    }
+
+    /// Return the number of bits in the APInt.
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Return the little-endian 64-bit words holding the value.
+    pub fn words(&self) -> &[u64] {
+        &self.words
+    }
+
+    /// Return the value of the bit at `index`.
+    pub fn bit(&self, index: u32) -> bool {
+        assert!(index < self.width, "Bit position out of bounds!");
+        self.words[(index / Self::WORD_BITS) as usize]
+            >> (index % Self::WORD_BITS) & 1 == 1
+    }
+
+    /// Determine the sign of this APInt when interpreted as a signed value.
+    pub fn is_negative(&self) -> bool {
+        self.bit(self.width - 1)
+    }
+
+    /// Return true if the value is zero.
+    pub fn is_zero(&self) -> bool {
+        self.words.iter().all(|word| *word == 0)
+    }
+
+    /// Return the minimum number of bits needed to represent the value as an unsigned integer.
+    pub fn active_bits(&self) -> u32 {
+        for (index, word) in self.words.iter().enumerate().rev() {
+            if *word != 0 {
+                return index as u32 * Self::WORD_BITS
+                    + (Self::WORD_BITS - word.leading_zeros());
+            }
+        }
+        0
+    }
+
+    /// Return the minimum number of bits needed to represent the value as a signed integer.
+    pub fn significant_bits(&self) -> u32 {
+        if self.is_negative() {
+            self.not().active_bits() + 1
+        } else {
+            self.active_bits() + 1
+        }
+    }
+
+    /// Return the value zero extended to 64 bits, or None if it does not fit.
+    pub fn zext_value(&self) -> Option<u64> {
+        (self.active_bits() <= 64).then(|| self.words[0])
+    }
+
+    /// Return the value sign extended to 64 bits, or None if it does not fit.
+    pub fn sext_value(&self) -> Option<i64> {
+        if self.significant_bits() > 64 {
+            return None;
+        }
+        let value = self.words[0];
+        if self.width >= 64 {
+            return Some(value as i64);
+        }
+        let shift = 64 - self.width;
+        Some(((value << shift) as i64) >> shift)
+    }
+
+    /// Return the bitwise complement.
+    pub fn not(&self) -> Self {
+        let mut output = self.clone();
+        for word in output.words.iter_mut() {
+            *word = !*word;
+        }
+        output.clear_unused_bits();
+        output
+    }
+
+    /// Return the two's complement negation.
+    pub fn neg(&self) -> Self {
+        let mut output = self.not();
+        for word in output.words.iter_mut() {
+            let (sum, carry) = word.overflowing_add(1);
+            *word = sum;
+            if !carry {
+                break;
+            }
+        }
+        output.clear_unused_bits();
+        output
+    }
+
+    /// Divide the magnitude in place by `divisor`, returning the remainder.
+    fn div_rem_in_place(words: &mut [u64], divisor: u64) -> u64 {
+        let mut remainder = 0u128;
+        for word in words.iter_mut().rev() {
+            let current = (remainder << 64) | *word as u128;
+            *word = (current / divisor as u128) as u64;
+            remainder = current % divisor as u128;
+        }
+        remainder as u64
+    }
+
+    /// Convert the value to a string in `radix`, interpreting it as signed if `is_signed` is true. Radix 16 output is prefixed with `0x`.
+    pub fn to_string(&self, radix: u32, is_signed: bool) -> String {
+        assert!(matches!(radix, 2 | 8 | 10 | 16), "Radix should be 2, 8, 10, or 16!");
+        let negative = is_signed && self.is_negative();
+        let magnitude = if negative { self.neg() } else { self.clone() };
+        let mut words: Vec<u64> = magnitude.words.to_vec();
+        let mut digits = Vec::new();
+        loop {
+            let digit = Self::div_rem_in_place(&mut words, radix as u64);
+            digits.push(char::from_digit(digit as u32, radix).unwrap());
+            if words.iter().all(|word| *word == 0) {
+                break;
+            }
+        }
+        let mut output = String::with_capacity(digits.len() + 3);
+        if negative {
+            output.push('-');
+        }
+        if radix == 16 {
+            output.push_str("0x");
+        }
+        output.extend(digits.iter().rev());
+        output
+    }
+
+    /// Parse `string` as an integer in `radix` into an APInt of `width` bits. A leading `-` negates the value. Returns None if `string` contains an invalid digit.
+    pub fn from_str_radix(width: u32, string: &str, radix: u32) -> Option<Self> {
+        let (negative, digits) = match string.strip_prefix('-') {
+            Some(digits) => (true, digits),
+            None => (false, string)
+        };
+        if digits.is_empty() {
+            return None;
+        }
+        let mut words: Vec<u64> = vec![0; Self::num_words(width)];
+        for char in digits.chars() {
+            let mut carry = char.to_digit(radix)? as u128;
+            for word in words.iter_mut() {
+                let current = *word as u128 * radix as u128 + carry;
+                *word = current as u64;
+                carry = current >> 64;
+            }
+        }
+        let output = Self::from_words(width, &words);
+        Some(if negative { output.neg() } else { output })
+    }
+}
+
+impl fmt::Display for APInt {
+    /// Prints the value as an unsigned decimal integer.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.to_string(10, false))
+    }
+}
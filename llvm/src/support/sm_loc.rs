@@ -5,8 +5,21 @@ source code for use in diagnostics.
 - include <https://github.com/llvm/llvm-project/blob/main/llvm/include/llvm/Support/SMLoc.h>
 */
 
-/// Represents a location in source code.
+/// Represents a location in source code, as the byte offset into the buffer it was taken from.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct SMLoc {
+    offset: usize
+}
+
+impl SMLoc {
+    pub const fn from_offset(offset: usize) -> Self {
+        Self { offset }
+    }
+
+    /// Return the byte offset of this location into its buffer.
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
 }
 
 /**
@@ -16,5 +29,15 @@ SMRange is implemented using a half-open range, as is the convention in C++.
 In the string "abc", the range [1,3) represents the substring "bc", and the
 range [2,2) represents an empty range between the characters "b" and "c".
 */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
 pub struct SMRange {
+    pub start: SMLoc,
+    pub end: SMLoc
+}
+
+impl SMRange {
+    pub const fn new(start: SMLoc, end: SMLoc) -> Self {
+        assert!(start.offset <= end.offset, "Start must not be after end");
+        Self { start, end }
+    }
 }
@@ -2,33 +2,363 @@
 This file declares the SMDiagnostic and SourceManager classes. This provides a simple substrate for diagnostics, #include handling, and other low level things for simple parsers.
 
 - include <https://github.com/llvm/llvm-project/blob/main/llvm/include/llvm/Support/SourceManager.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/llvm/lib/Support/SourceMgr.cpp>
 */
 
-use crate::llvm::support::{
-    memory_buffer::MemoryBuffer,
-    sm_loc
-};
+use core::fmt;
+use std::io;
+
+use crate::support::sm_loc::{SMLoc, SMRange};
 
 /// This owns the files read by a parser, handles include stacks, and handles diagnostic wrangling.
+///
+/// Buffers are identified by the id returned when they are added. Ids start at 1, so that 0 is never a valid buffer, and the first buffer added is the main file.
+#[derive(Default)]
 pub struct SourceManager {
+    /// This is all of the buffers that we are reading from.
+    buffers: Vec<SrcBuffer>
 }
 
-enum DiagKind {
+/// The kinds of diagnostics the source manager prints.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DiagKind {
     Error,
     Warning,
     Remark,
     Note,
 }
 
+impl fmt::Display for DiagKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Error => "error",
+            Self::Warning => "warning",
+            Self::Remark => "remark",
+            Self::Note => "note"
+        })
+    }
+}
+
 struct SrcBuffer {
-    /// The memory buffer for the file.
-    buffer: Box<MemoryBuffer>,
+    /// The name of the file.
+    identifier: String,
+    /// The contents of the file.
+    buffer: String,
+    /// The byte offset of the start of each line of the file.
+    line_offsets: Vec<usize>
+}
+
+impl SrcBuffer {
+    fn new(buffer: String, identifier: String) -> Self {
+        let line_offsets = core::iter::once(0)
+            .chain(buffer.match_indices('\n').map(|(index, _)| index + 1))
+            .collect();
+        Self { identifier, buffer, line_offsets }
+    }
+
+    /// Return the 1-based line number of the byte at `offset`.
+    fn line_number(&self, offset: usize) -> usize {
+        self.line_offsets.partition_point(|start| *start <= offset)
+    }
+
+    /// Return the contents of the 1-based line `line`, without its line terminator.
+    fn line(&self, line: usize) -> &str {
+        let start = self.line_offsets[line - 1];
+        let end = self.line_offsets.get(line).map_or(self.buffer.len(), |end| end - 1);
+        self.buffer[start..end].trim_end_matches('\r')
+    }
+}
+
+impl SourceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a new source buffer to this source manager, named `identifier`, and return its id.
+    pub fn add_new_source_buffer(
+        &mut self,
+        buffer: impl Into<String>,
+        identifier: impl Into<String>
+    ) -> usize {
+        self.buffers.push(SrcBuffer::new(buffer.into(), identifier.into()));
+        self.buffers.len()
+    }
+
+    pub fn num_buffers(&self) -> usize {
+        self.buffers.len()
+    }
+
+    /// Return the id of the main file, the first buffer that was added.
+    pub fn main_file_id(&self) -> usize {
+        assert!(!self.buffers.is_empty(), "Source manager has no buffer");
+        1
+    }
+
+    fn src_buffer(&self, id: usize) -> &SrcBuffer {
+        assert!(self.is_valid_buffer_id(id), "Invalid buffer id {}", id);
+        &self.buffers[id - 1]
+    }
+
+    pub fn is_valid_buffer_id(&self, id: usize) -> bool {
+        id != 0 && id <= self.buffers.len()
+    }
+
+    /// Return the contents of the buffer `id`.
+    pub fn buffer(&self, id: usize) -> &str {
+        &self.src_buffer(id).buffer
+    }
+
+    /// Return the name of the buffer `id`.
+    pub fn buffer_identifier(&self, id: usize) -> &str {
+        &self.src_buffer(id).identifier
+    }
+
+    /// Return the id of the buffer named `identifier`, if any.
+    pub fn find_buffer(&self, identifier: &str) -> Option<usize> {
+        self.buffers.iter().position(|buffer| buffer.identifier == identifier)
+            .map(|index| index + 1)
+    }
+
+    /// Find the 1-based line and column number for the specified location in the buffer `id`.
+    pub fn line_and_column(&self, id: usize, loc: SMLoc) -> (usize, usize) {
+        let buffer = self.src_buffer(id);
+        let line = buffer.line_number(loc.offset());
+        (line, loc.offset() - buffer.line_offsets[line - 1] + 1)
+    }
+
+    /// Given a 1-based line and column number in the buffer `id`, return the corresponding location, or `None` if it is out of range.
+    pub fn find_loc_for_line_and_column(&self, id: usize, line: usize, column: usize)
+        -> Option<SMLoc>
+    {
+        let buffer = self.src_buffer(id);
+        if line == 0 || line > buffer.line_offsets.len() {
+            return None;
+        }
+        // A column of 0 designates the line as a whole.
+        let column = column.max(1);
+        let contents = buffer.line(line);
+        // The column may point one past the end of the line, at its terminator.
+        if column > contents.len() + 1 {
+            return None;
+        }
+        Some(SMLoc::from_offset(buffer.line_offsets[line - 1] + column - 1))
+    }
+
+    /**
+    Return an SMDiagnostic at the specified location in the buffer `id` with the specified string.
+
+    `ranges` are highlighted in the line of the location, and `fix_its` are printed below it. Ranges and fix-its on other lines are ignored.
+    */
+    pub fn get_message(
+        &self,
+        id: usize,
+        loc: SMLoc,
+        kind: DiagKind,
+        message: impl Into<String>,
+        ranges: &[SMRange],
+        fix_its: &[SMFixIt]
+    ) -> SMDiagnostic {
+        let buffer = self.src_buffer(id);
+        let (line, column) = self.line_and_column(id, loc);
+        let line_start = buffer.line_offsets[line - 1];
+        let line_contents = buffer.line(line);
+        let line_end = line_start + line_contents.len();
+
+        // Convert any ranges to column ranges that only intersect the line of the location.
+        let column_ranges = ranges.iter()
+            .filter(|range| range.end.offset() >= line_start && range.start.offset() <= line_end)
+            .map(|range| (
+                range.start.offset().max(line_start) - line_start,
+                range.end.offset().min(line_end) - line_start))
+            .collect();
+        let fix_its = fix_its.iter()
+            .filter(|fix_it| fix_it.range.start.offset() >= line_start
+                && fix_it.range.end.offset() <= line_end)
+            .map(|fix_it| SMFixIt::new(
+                SMRange::new(
+                    SMLoc::from_offset(fix_it.range.start.offset() - line_start),
+                    SMLoc::from_offset(fix_it.range.end.offset() - line_start)),
+                fix_it.text.clone()))
+            .collect();
+
+        SMDiagnostic {
+            filename: buffer.identifier.clone(),
+            line_no: line,
+            column_no: Some(column - 1),
+            kind,
+            message: message.into(),
+            line_contents: line_contents.to_string(),
+            ranges: column_ranges,
+            fix_its
+        }
+    }
+
+    /// Emit a message about the specified location in the buffer `id` with the specified string to `out`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn print_message(
+        &self,
+        out: &mut dyn io::Write,
+        id: usize,
+        loc: SMLoc,
+        kind: DiagKind,
+        message: impl Into<String>,
+        ranges: &[SMRange],
+        fix_its: &[SMFixIt]
+    ) -> io::Result<()> {
+        writeln!(out, "{}", self.get_message(id, loc, kind, message, ranges, fix_its))
+    }
 }
 
 /// Represents a single fixit, a replacement of one range of text with another.
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub struct SMFixIt {
+    range: SMRange,
+    text: String
+}
+
+impl SMFixIt {
+    pub fn new(range: SMRange, replacement: impl Into<String>) -> Self {
+        Self { range, text: replacement.into() }
+    }
+
+    /// Return a fix-it inserting `insertion` at `loc`.
+    pub fn insertion(loc: SMLoc, insertion: impl Into<String>) -> Self {
+        Self::new(SMRange::new(loc, loc), insertion)
+    }
+
+    pub fn range(&self) -> SMRange {
+        self.range
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
 }
 
 /// Instances of this class encapsulate one diagnostic report, allowing printing to a raw_ostream as a caret diagnostic.
+#[derive(Clone, Debug)]
 pub struct SMDiagnostic {
+    filename: String,
+    /// The 1-based line of the diagnostic, or 0 if it has none.
+    line_no: usize,
+    /// The 0-based column of the diagnostic, if it has a location.
+    column_no: Option<usize>,
+    kind: DiagKind,
+    message: String,
+    /// The contents of the line of the diagnostic, printed with a caret below the column.
+    line_contents: String,
+    /// The column ranges of the line to highlight.
+    ranges: Vec<(usize, usize)>,
+    /// The fix-its to print below the line, with column ranges.
+    fix_its: Vec<SMFixIt>
+}
+
+impl SMDiagnostic {
+    /// Create a diagnostic without a source line, located at `filename`, which may also include a line and column.
+    pub fn new(filename: impl Into<String>, kind: DiagKind, message: impl Into<String>) -> Self {
+        Self {
+            filename: filename.into(),
+            line_no: 0,
+            column_no: None,
+            kind,
+            message: message.into(),
+            line_contents: String::new(),
+            ranges: Vec::new(),
+            fix_its: Vec::new()
+        }
+    }
+
+    pub fn filename(&self) -> &str {
+        &self.filename
+    }
+
+    pub fn line_no(&self) -> usize {
+        self.line_no
+    }
+
+    pub fn column_no(&self) -> Option<usize> {
+        self.column_no
+    }
+
+    pub fn kind(&self) -> DiagKind {
+        self.kind
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn line_contents(&self) -> &str {
+        &self.line_contents
+    }
+
+    pub fn ranges(&self) -> &[(usize, usize)] {
+        &self.ranges
+    }
+
+    pub fn fix_its(&self) -> &[SMFixIt] {
+        &self.fix_its
+    }
+
+    /// Build the line showing the caret under the column of the diagnostic, with the ranges and the text removed by fix-its underlined.
+    fn caret_line(&self, column: usize) -> String {
+        let mut caret_line = vec![b' '; self.line_contents.len().max(column) + 1];
+        for &(start, end) in &self.ranges {
+            caret_line[start..end].fill(b'~');
+        }
+        for fix_it in self.fix_its.iter().filter(|fix_it| fix_it.text.is_empty()) {
+            caret_line[fix_it.range.start.offset()..fix_it.range.end.offset()].fill(b'~');
+        }
+        caret_line[column] = b'^';
+        // Keep the tabs of the source line so that the caret lines up with it.
+        for (index, byte) in self.line_contents.bytes().enumerate() {
+            if byte == b'\t' && caret_line[index] == b' ' {
+                caret_line[index] = b'\t';
+            }
+        }
+        String::from_utf8(caret_line).unwrap().trim_end().to_string()
+    }
+
+    /// Build the line showing the replacement text of the fix-its under the text they replace. Fix-its that would overlap a previous one are dropped.
+    fn fix_it_line(&self) -> String {
+        let mut fix_it_line = String::new();
+        for fix_it in &self.fix_its {
+            let start = fix_it.range.start.offset();
+            if fix_it.text.is_empty() || fix_it_line.len() > start {
+                continue;
+            }
+            // Pad with the tabs of the source line so that the text lines up with it.
+            let padding = (fix_it_line.len()..start).map(|index|
+                if self.line_contents.as_bytes().get(index) == Some(&b'\t') { '\t' } else { ' ' });
+            fix_it_line.extend(padding);
+            fix_it_line.push_str(&fix_it.text);
+        }
+        fix_it_line
+    }
+}
+
+impl fmt::Display for SMDiagnostic {
+    /// Print the diagnostic as `file:line:col: kind: message`, followed by the source line with a caret under the column, and the fix-its.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.filename.is_empty() {
+            f.write_str(&self.filename)?;
+            if self.line_no != 0 {
+                write!(f, ":{}", self.line_no)?;
+                if let Some(column) = self.column_no {
+                    write!(f, ":{}", column + 1)?;
+                }
+            }
+            f.write_str(": ")?;
+        }
+        write!(f, "{}: {}", self.kind, self.message)?;
+        let Some(column) = self.column_no else {
+            return Ok(());
+        };
+        write!(f, "\n{}\n{}", self.line_contents, self.caret_line(column))?;
+        let fix_it_line = self.fix_it_line();
+        if !fix_it_line.is_empty() {
+            write!(f, "\n{}", fix_it_line)?;
+        }
+        Ok(())
+    }
 }
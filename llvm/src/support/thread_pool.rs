@@ -16,5 +16,47 @@ It is possible to reuse one thread pool for different groups of tasks by groupin
 
 It is also possible for worker threads to submit new tasks and wait for them. Note that this may result in a deadlock in cases such as when a task (directly or indirectly) tries to wait for its own completion, or when all available threads are used up by tasks waiting for a task that has no thread left to run on (this includes waiting on the returned future). It should be generally safe to wait() for a group as long as groups do not form a cycle.
 */
+///
+/// Tasks are run by `broadcast`, which spawns scoped workers so that tasks may borrow from the caller. The pool bounds the number of workers rather than keeping threads alive between calls.
 pub struct ThreadPool {
+    /// The number of threads tasks are run on.
+    thread_count: usize
+}
+
+impl ThreadPool {
+    /// Create a pool running tasks on `thread_count` threads, or on as many threads as the hardware supports if `None`.
+    pub fn new(thread_count: Option<usize>) -> Self {
+        let thread_count = thread_count.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |count| count.get())
+        });
+        Self { thread_count: thread_count.max(1) }
+    }
+
+    /// Return the number of threads tasks are run on.
+    pub fn thread_count(&self) -> usize {
+        self.thread_count
+    }
+
+    /// Run `task` on `num_tasks` workers, capped at the number of threads of the pool, passing each the index of its worker, and wait for all of them to finish.
+    pub fn broadcast(&self, num_tasks: usize, task: impl Fn(usize) + Sync) {
+        let num_workers = num_tasks.min(self.thread_count);
+        if num_workers <= 1 {
+            if num_workers == 1 {
+                task(0);
+            }
+            return;
+        }
+        let task = &task;
+        std::thread::scope(|scope| {
+            for index in 0..num_workers {
+                scope.spawn(move || task(index));
+            }
+        });
+    }
+}
+
+impl Default for ThreadPool {
+    fn default() -> Self {
+        Self::new(None)
+    }
 }
@@ -0,0 +1,735 @@
+/*!
+# Attribute and Type Definitions
+
+This file expands the attribute and type definitions declared with `#[derive(AttrOrTypeDef)]` and `#[attr_or_type(...)]` into the parsers and printers `mlir-tblgen` generates from the `assemblyFormat` of ODS `AttrDef`s and `TypeDef`s.
+
+- lib
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/AttrOrTypeDefGen.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/AttrOrTypeFormatGen.cpp>
+*/
+
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote};
+use syn::{
+    Data, DeriveInput, Expr, Fields, GenericArgument, Ident, LitStr, PathArguments, Type
+};
+
+use crate::format::{self, Element, Spacing};
+
+// ----------------------------------------------------------------------
+// Specification
+// ----------------------------------------------------------------------
+
+/// A parameter of an attribute or type, i.e. a field of the struct.
+struct ParameterSpec {
+    ident: Ident,
+    r#type: Type,
+    /// The keyword of the parameter in `struct` directives.
+    name: String,
+    /// The `T` of a parameter of type `Option<T>`.
+    optional: Option<Type>,
+    /// The value of the parameter when it is omitted.
+    default: Option<TokenStream>
+}
+
+impl ParameterSpec {
+    fn from_field(ident: &Ident, r#type: &Type, attributes: &[syn::Attribute])
+        -> syn::Result<Self>
+    {
+        let mut name = ident.to_string();
+        let mut default = None;
+        for attribute in attributes.iter().filter(|attribute| attribute.path().is_ident("param")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    name = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("default") {
+                    default = Some(if meta.input.peek(syn::Token![=]) {
+                        let value: Expr = meta.value()?.parse()?;
+                        quote!(#value)
+                    } else {
+                        quote!(<#r#type as ::core::default::Default>::default())
+                    });
+                } else {
+                    return Err(meta.error("unknown parameter property"));
+                }
+                Ok(())
+            })?;
+        }
+        let optional = option_argument(r#type);
+        if optional.is_some() && default.is_some() {
+            return Err(syn::Error::new_spanned(
+                ident, "optional parameters can't have a default value"));
+        }
+        Ok(Self { ident: ident.clone(), r#type: r#type.clone(), name, optional, default })
+    }
+
+    /// Returns true if the parameter may be omitted.
+    fn is_optional(&self) -> bool {
+        self.optional.is_some() || self.default.is_some()
+    }
+
+    fn local(&self) -> Ident {
+        format_ident!("_{}", self.ident)
+    }
+
+    /// The type the parameter is parsed as.
+    fn parsed_type(&self) -> &Type {
+        self.optional.as_ref().unwrap_or(&self.r#type)
+    }
+
+    /// The condition under which an optional or defaulted parameter is printed.
+    fn is_present(&self) -> TokenStream {
+        let ident = &self.ident;
+        match &self.default {
+            Some(default) => quote!(self.#ident != #default),
+            None => quote!(self.#ident.is_some())
+        }
+    }
+}
+
+/// Returns `T` if `r#type` is `Option<T>`.
+fn option_argument(r#type: &Type) -> Option<Type> {
+    let Type::Path(path) = r#type else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first()? {
+        GenericArgument::Type(r#type) if arguments.args.len() == 1 => Some(r#type.clone()),
+        _ => None
+    }
+}
+
+struct DefSpec {
+    ident: Ident,
+    mnemonic: LitStr,
+    parameters: Vec<ParameterSpec>,
+    assembly_format: LitStr
+}
+
+impl DefSpec {
+    fn from_input(input: &DeriveInput) -> syn::Result<Self> {
+        let Data::Struct(data) = &input.data else {
+            return Err(syn::Error::new_spanned(
+                &input.ident, "`AttrOrTypeDef` can only be derived for structs"));
+        };
+        if !input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input.generics, "attributes and types can't be generic"));
+        }
+        let parameters = match &data.fields {
+            Fields::Named(fields) => fields.named.iter()
+                .map(|field| ParameterSpec::from_field(
+                    field.ident.as_ref().unwrap(), &field.ty, &field.attrs))
+                .collect::<syn::Result<Vec<_>>>()?,
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => return Err(syn::Error::new_spanned(
+                &input.ident, "the parameters of attributes and types must be named fields"))
+        };
+        let mut names = HashSet::new();
+        for parameter in &parameters {
+            if !names.insert(&parameter.name) {
+                return Err(syn::Error::new_spanned(&parameter.ident, format!(
+                    "duplicate parameter name '{}'", parameter.name)));
+            }
+        }
+
+        let mut mnemonic = None;
+        let mut assembly_format = None;
+        for attribute in input.attrs.iter().filter(|attribute| attribute.path().is_ident("attr_or_type")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("mnemonic") {
+                    mnemonic = Some(meta.value()?.parse()?);
+                } else if meta.path.is_ident("assembly_format") {
+                    assembly_format = Some(meta.value()?.parse()?);
+                } else {
+                    return Err(meta.error("unknown attribute or type property"));
+                }
+                Ok(())
+            })?;
+        }
+        Ok(Self {
+            ident: input.ident.clone(),
+            mnemonic: mnemonic.ok_or_else(|| syn::Error::new_spanned(
+                &input.ident, "missing mnemonic, e.g. `#[attr_or_type(mnemonic = \"encoding\")]`"))?,
+            parameters,
+            assembly_format: assembly_format.ok_or_else(|| syn::Error::new_spanned(
+                &input.ident, "missing assembly format, implement `AttrOrTypeDefinition` by hand instead"))?
+        })
+    }
+
+    fn lookup(&self, name: &str) -> Option<&ParameterSpec> {
+        self.parameters.iter().find(|parameter| parameter.ident == name)
+    }
+}
+
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let spec = DefSpec::from_input(&input)?;
+    let format = DefFormat::new(&spec)?;
+    let ident = &spec.ident;
+    let mnemonic = &spec.mnemonic;
+    let parser = format.generate_parser();
+    let printer = format.generate_printer();
+    Ok(quote! {
+        impl ::mlir::ir::attr_type_base::AttrOrTypeDefinition for #ident {
+            const MNEMONIC: &'static str = #mnemonic;
+
+            fn parse(parser: &mut dyn ::mlir::ir::dialect::implementation::DialectAsmParser)
+                -> ::mlir::asm_parser::parser::ParseResult<Self>
+            {
+                #parser
+            }
+
+            #[allow(unused_assignments)]
+            fn print(&self, printer: &mut dyn ::mlir::ir::dialect::implementation::DialectAsmPrinter)
+                -> ::core::fmt::Result
+            {
+                #printer
+            }
+        }
+    })
+}
+
+// ----------------------------------------------------------------------
+// Assembly Format
+// ----------------------------------------------------------------------
+
+/// An assembly format, validated against the parameters of its attribute or type.
+struct DefFormat<'s> {
+    spec: &'s DefSpec,
+    elements: Vec<Element>
+}
+
+/// Returns the parameters bound by an argument of a `struct` or `custom` directive.
+fn arguments<'s>(spec: &'s DefSpec, arguments: &[Element]) -> Vec<&'s ParameterSpec> {
+    arguments.iter().flat_map(|argument| match argument {
+        Element::Params => spec.parameters.iter().collect(),
+        Element::Variable { name, .. } => spec.lookup(name).into_iter().collect(),
+        _ => Vec::new()
+    }).collect()
+}
+
+/// Returns `name` converted from UpperCamelCase to snake_case.
+fn snake_case(name: &str) -> String {
+    let mut output = String::new();
+    let mut previous_was_lower = false;
+    for char in name.chars() {
+        if char.is_ascii_uppercase() {
+            if previous_was_lower {
+                output.push('_');
+            }
+            output.push(char.to_ascii_lowercase());
+        } else {
+            output.push(char);
+        }
+        previous_was_lower = char.is_ascii_lowercase() || char.is_ascii_digit();
+    }
+    output
+}
+
+impl<'s> DefFormat<'s> {
+    fn new(spec: &'s DefSpec) -> syn::Result<Self> {
+        let format = &spec.assembly_format;
+        let error = |message: String| syn::Error::new(format.span(), message);
+        let elements = format::parse(format)?;
+
+        // Check that each parameter is bound exactly once.
+        let mut seen = HashSet::new();
+        let mut bind = |parameter: &ParameterSpec| {
+            if !seen.insert(parameter.ident.to_string()) {
+                return Err(error(format!("duplicate parameter '{}'", parameter.ident)));
+            }
+            Ok(())
+        };
+        {
+            let mut check = |element: &Element, in_group: bool| -> syn::Result<()> {
+                match element {
+                    Element::Variable { name, .. } => {
+                        let parameter = spec.lookup(name).ok_or_else(|| error(format!(
+                            "'{}' is not a parameter", name)))?;
+                        if parameter.optional.is_some() && !in_group {
+                            return Err(error(format!(
+                                "optional parameter '{}' must be in an optional group, a 'struct' or a 'custom' directive",
+                                name)));
+                        }
+                        bind(parameter)
+                    },
+                    Element::Params => {
+                        for parameter in &spec.parameters {
+                            if parameter.optional.is_some() {
+                                return Err(error(format!(
+                                    "optional parameter '{}' must be in an optional group, a 'struct' or a 'custom' directive",
+                                    parameter.ident)));
+                            }
+                            bind(parameter)?;
+                        }
+                        Ok(())
+                    },
+                    Element::Struct(arguments) | Element::Custom { arguments, .. } => {
+                        for argument in arguments {
+                            if let Element::Variable { name, .. } = argument
+                                && spec.lookup(name).is_none()
+                            {
+                                return Err(error(format!("'{}' is not a parameter", name)));
+                            }
+                        }
+                        for parameter in self::arguments(spec, arguments) {
+                            bind(parameter)?;
+                        }
+                        Ok(())
+                    },
+                    Element::Type { .. } | Element::AttrDict { .. } => Err(error(
+                        "'type' and 'attr-dict' directives are only valid in operation formats".into())),
+                    Element::Literal(_) | Element::Optional(_) => Ok(())
+                }
+            };
+            for element in &elements {
+                let Element::Optional(group) = element else {
+                    check(element, false)?;
+                    continue;
+                };
+                let anchor = spec.lookup(Element::anchor(group).unwrap());
+                if !anchor.is_some_and(ParameterSpec::is_optional) {
+                    return Err(error(
+                        "the anchor of an optional group must be an optional or defaulted parameter".into()));
+                }
+                if !matches!(&group[0], Element::Literal(literal) if !literal.trim().is_empty()) {
+                    return Err(error(
+                        "the first element of an optional group must be a literal".into()));
+                }
+                for element in group {
+                    if !matches!(element, Element::Literal(_) | Element::Variable { .. }) {
+                        return Err(error(
+                            "optional groups may only contain literals and parameters".into()));
+                    }
+                    check(element, true)?;
+                }
+            }
+        }
+        for parameter in &spec.parameters {
+            if !seen.contains(&parameter.ident.to_string()) {
+                return Err(error(format!(
+                    "format is missing a reference to parameter '{}'", parameter.ident)));
+            }
+        }
+        Ok(Self { spec, elements })
+    }
+
+    // ----------------------------------------------------------------------
+    // Printer
+    // ----------------------------------------------------------------------
+
+    fn generate_printer(&self) -> TokenStream {
+        let mut spacing = Spacing::new();
+        let elements = self.elements.iter()
+            .map(|element| self.print_element(element, &mut spacing))
+            .collect::<Vec<_>>();
+        quote! {
+            use ::core::fmt::Write as _;
+            use ::mlir::ir::dialect::implementation::FieldPrinter as _;
+            #(#elements)*
+            ::core::result::Result::Ok(())
+        }
+    }
+
+    /// Print the value of `parameter`, skipping it if it is an optional parameter that is absent.
+    fn print_parameter(parameter: &ParameterSpec) -> TokenStream {
+        let ident = &parameter.ident;
+        if parameter.optional.is_some() {
+            quote! {
+                if let ::core::option::Option::Some(__value) = &self.#ident {
+                    __value.print(&mut *printer)?;
+                }
+            }
+        } else {
+            quote!(self.#ident.print(&mut *printer)?;)
+        }
+    }
+
+    fn print_element(&self, element: &Element, spacing: &mut Spacing) -> TokenStream {
+        let space = |spacing: &mut Spacing| spacing.element().then(|| quote! {
+            printer.write_char(' ')?;
+        });
+        match element {
+            Element::Literal(literal) => {
+                let text = spacing.literal(literal);
+                if text.is_empty() {
+                    return quote!();
+                }
+                quote!(printer.write_str(#text)?;)
+            },
+            Element::Variable { name, .. } => {
+                let space = space(spacing);
+                let print = Self::print_parameter(self.spec.lookup(name).unwrap());
+                quote!(#space #print)
+            },
+            Element::Params => {
+                let space = space(spacing);
+                let prints = self.spec.parameters.iter().enumerate().map(|(index, parameter)| {
+                    let separator = (index != 0).then(|| quote!(printer.write_str(", ")?;));
+                    let print = Self::print_parameter(parameter);
+                    quote!(#separator #print)
+                });
+                quote!(#space #(#prints)*)
+            },
+            Element::Struct(arguments) => {
+                let space = space(spacing);
+                let prints = self::arguments(self.spec, arguments).into_iter().map(|parameter| {
+                    let prefix = format!("{} = ", parameter.name);
+                    let print = Self::print_parameter(parameter);
+                    let print = quote! {
+                        if !__first {
+                            printer.write_str(", ")?;
+                        }
+                        __first = false;
+                        printer.write_str(#prefix)?;
+                        #print
+                    };
+                    if !parameter.is_optional() {
+                        return print;
+                    }
+                    let condition = parameter.is_present();
+                    quote! {
+                        if #condition {
+                            #print
+                        }
+                    }
+                });
+                quote! {
+                    #space
+                    let mut __first = true;
+                    #(#prints)*
+                }
+            },
+            Element::Custom { name, arguments } => {
+                let space = space(spacing);
+                let function = format_ident!("print_{}", snake_case(name));
+                let values = self::arguments(self.spec, arguments).into_iter()
+                    .map(|parameter| &parameter.ident);
+                quote! {
+                    #space
+                    #function(&mut *printer, #(&self.#values),*)?;
+                }
+            },
+            Element::Optional(group) => {
+                let condition = self.spec.lookup(Element::anchor(group).unwrap())
+                    .unwrap().is_present();
+                let elements = group.iter()
+                    .map(|element| self.print_element(element, spacing))
+                    .collect::<Vec<_>>();
+                quote! {
+                    if #condition {
+                        #(#elements)*
+                    }
+                }
+            },
+            Element::Type { .. } | Element::AttrDict { .. } => unreachable!()
+        }
+    }
+
+    // ----------------------------------------------------------------------
+    // Parser
+    // ----------------------------------------------------------------------
+
+    fn generate_parser(&self) -> TokenStream {
+        let locals = self.spec.parameters.iter().map(|parameter| {
+            let local = parameter.local();
+            let r#type = parameter.parsed_type();
+            quote! {
+                let mut #local: ::core::option::Option<#r#type> = ::core::option::Option::None;
+            }
+        });
+        let elements = self.elements.iter().map(|element| self.parse_element(element));
+        let fields = self.spec.parameters.iter().map(|parameter| {
+            let ident = &parameter.ident;
+            let local = parameter.local();
+            let value = if parameter.optional.is_some() {
+                quote!(#local)
+            } else if let Some(default) = &parameter.default {
+                quote!(#local.unwrap_or_else(|| #default))
+            } else {
+                let message = format!("missing parameter '{}'", parameter.name);
+                quote! {
+                    #local.ok_or_else(|| parser.emit_error(
+                        parser.current_location(), #message.into()))?
+                }
+            };
+            quote!(#ident: #value)
+        });
+        let construct = if self.spec.parameters.is_empty() {
+            quote!(Self)
+        } else {
+            quote!(Self { #(#fields),* })
+        };
+        quote! {
+            use ::mlir::ir::operation::implementation::AsmParser as _;
+            #(#locals)*
+            #(#elements)*
+            ::core::result::Result::Ok(#construct)
+        }
+    }
+
+    fn parse_literal(literal: &str) -> TokenStream {
+        if literal.trim().is_empty() {
+            return quote!();
+        }
+        if let Some(kind) = format::punctuation(literal) {
+            let kind = format_ident!("{}", kind);
+            return quote! {
+                parser.parse_token(::mlir::asm_parser::token::TokenKind::#kind)?;
+            };
+        }
+        quote!(parser.parse_keyword(#literal)?;)
+    }
+
+    fn parse_parameter(parameter: &ParameterSpec) -> TokenStream {
+        let local = parameter.local();
+        let r#type = parameter.parsed_type();
+        quote! {
+            #local = ::core::option::Option::Some(
+                <#r#type as ::mlir::ir::dialect::implementation::FieldParser>::parse(
+                    &mut *parser)?);
+        }
+    }
+
+    fn parse_element(&self, element: &Element) -> TokenStream {
+        match element {
+            Element::Literal(literal) => Self::parse_literal(literal),
+            Element::Variable { name, .. } =>
+                Self::parse_parameter(self.spec.lookup(name).unwrap()),
+            Element::Params => {
+                let parses = self.spec.parameters.iter().enumerate().map(|(index, parameter)| {
+                    let separator = (index != 0).then(|| quote!(parser.parse_comma()?;));
+                    let parse = Self::parse_parameter(parameter);
+                    quote!(#separator #parse)
+                });
+                quote!(#(#parses)*)
+            },
+            Element::Struct(arguments) => {
+                let cases = self::arguments(self.spec, arguments).into_iter().map(|parameter| {
+                    let name = &parameter.name;
+                    let local = parameter.local();
+                    let parse = Self::parse_parameter(parameter);
+                    quote! {
+                        #name => {
+                            if #local.is_some() {
+                                return ::core::result::Result::Err(parser.emit_error(
+                                    __location,
+                                    ::std::format!("struct has a duplicate parameter '{}'", __name)));
+                            }
+                            #parse
+                        }
+                    }
+                });
+                quote! {
+                    let mut __first = true;
+                    loop {
+                        let __location = parser.current_location();
+                        let ::core::option::Option::Some(__name) = parser.parse_optional_any_keyword() else {
+                            if __first {
+                                break;
+                            }
+                            return ::core::result::Result::Err(parser.emit_error(
+                                __location, "expected a parameter name in struct".into()));
+                        };
+                        parser.parse_token(::mlir::asm_parser::token::TokenKind::Equal)?;
+                        match __name.as_str() {
+                            #(#cases)*
+                            _ => return ::core::result::Result::Err(parser.emit_error(
+                                __location,
+                                ::std::format!("struct has no parameter '{}'", __name)))
+                        }
+                        __first = false;
+                        if !parser.parse_optional_comma() {
+                            break;
+                        }
+                    }
+                }
+            },
+            Element::Custom { name, arguments } => {
+                let function = format_ident!("parse_{}", snake_case(name));
+                let parameters = self::arguments(self.spec, arguments);
+                let values = parameters.iter()
+                    .map(|parameter| format_ident!("__{}", parameter.ident))
+                    .collect::<Vec<_>>();
+                let pattern = match values.as_slice() {
+                    [value] => quote!(#value),
+                    values => quote!((#(#values),*))
+                };
+                let assignments = parameters.iter().zip(&values).map(|(parameter, value)| {
+                    let local = parameter.local();
+                    if parameter.optional.is_some() {
+                        quote!(#local = #value;)
+                    } else {
+                        quote!(#local = ::core::option::Option::Some(#value);)
+                    }
+                });
+                quote! {
+                    let #pattern = #function(&mut *parser)?;
+                    #(#assignments)*
+                }
+            },
+            Element::Optional(group) => {
+                let Element::Literal(literal) = &group[0] else {
+                    unreachable!()
+                };
+                let condition = match format::punctuation(literal) {
+                    Some(kind) => {
+                        let kind = format_ident!("{}", kind);
+                        quote! {
+                            parser.parse_optional_token(
+                                ::mlir::asm_parser::token::TokenKind::#kind)
+                        }
+                    },
+                    None => quote!(parser.parse_optional_keyword(#literal))
+                };
+                let rest = group[1..].iter().map(|element| self.parse_element(element));
+                quote! {
+                    if #condition {
+                        #(#rest)*
+                    }
+                }
+            },
+            Element::Type { .. } | Element::AttrDict { .. } => unreachable!()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn tile() -> DeriveInput {
+        parse_quote! {
+            #[attr_or_type(
+                mnemonic = "tile",
+                assembly_format = "`<` struct(params) `>`"
+            )]
+            pub struct TileAttr {
+                sizes: Vec<u64>,
+                #[param(name = "innerOrder")]
+                inner_order: Option<AffineMap>,
+                #[param(default)]
+                padded: bool
+            }
+        }
+    }
+
+    fn error(input: DeriveInput) -> String {
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parse_parameters() {
+        let spec = DefSpec::from_input(&tile()).unwrap();
+        assert_eq!(spec.mnemonic.value(), "tile");
+        let [sizes, inner_order, padded] = &spec.parameters[..] else {
+            panic!("expected three parameters");
+        };
+        assert_eq!(sizes.name, "sizes");
+        assert!(!sizes.is_optional());
+        assert_eq!(inner_order.name, "innerOrder");
+        assert!(inner_order.optional.is_some() && inner_order.default.is_none());
+        assert_eq!(padded.name, "padded");
+        assert!(padded.optional.is_none() && padded.default.is_some());
+    }
+
+    #[test]
+    fn expand_struct_directive() {
+        let output = expand(tile()).unwrap().to_string();
+        assert!(output.contains("const MNEMONIC : & 'static str = \"tile\""));
+        assert!(output.contains("\"innerOrder\" =>"));
+        assert!(output.contains("printer . write_str (\"innerOrder = \")"));
+        assert!(output.contains("self . inner_order . is_some ()"));
+        assert!(output.contains(
+            "self . padded != < bool as :: core :: default :: Default > :: default ()"));
+        assert!(output.contains("missing parameter 'sizes'"));
+    }
+
+    #[test]
+    fn expand_optional_group_and_custom_directive() {
+        let output = expand(parse_quote! {
+            #[attr_or_type(
+                mnemonic = "vector",
+                assembly_format = "`<` custom<DimensionList>($shape) $element (`,` $scalable^)? `>`"
+            )]
+            pub struct VectorType {
+                shape: Vec<u64>,
+                element: Arc<dyn Type>,
+                #[param(default = false)]
+                scalable: bool
+            }
+        }).unwrap().to_string();
+        assert!(output.contains("parse_dimension_list (& mut * parser)"));
+        assert!(output.contains("print_dimension_list (& mut * printer , & self . shape)"));
+        assert!(output.contains("parse_optional_token (:: mlir :: asm_parser :: token :: TokenKind :: Comma)"));
+        assert!(output.contains("_scalable . unwrap_or_else (|| false)"));
+    }
+
+    #[test]
+    fn reject_invalid_specifications() {
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "$value")]
+            pub struct A(u64);
+        }).contains("must be named fields"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(assembly_format = "$value")]
+            pub struct A { value: u64 }
+        }).contains("missing mnemonic"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "$value")]
+            pub struct A {
+                #[param(default)]
+                value: Option<u64>
+            }
+        }).contains("optional parameters can't have a default value"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "params")]
+            pub struct A {
+                value: u64,
+                #[param(name = "value")]
+                other: u64
+            }
+        }).contains("duplicate parameter name 'value'"));
+    }
+
+    #[test]
+    fn reject_invalid_formats() {
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "`<` $value `>`")]
+            pub struct A { value: u64, other: u64 }
+        }).contains("missing a reference to parameter 'other'"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "$value $value")]
+            pub struct A { value: u64 }
+        }).contains("duplicate parameter 'value'"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "$value")]
+            pub struct A { value: Option<u64> }
+        }).contains("must be in an optional group"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "(`,` $value^)?")]
+            pub struct A { value: u64 }
+        }).contains("anchor of an optional group"));
+        assert!(error(parse_quote! {
+            #[attr_or_type(mnemonic = "a", assembly_format = "$value attr-dict")]
+            pub struct A { value: u64 }
+        }).contains("only valid in operation formats"));
+    }
+
+    #[test]
+    fn convert_to_snake_case() {
+        assert_eq!(snake_case("DimensionList"), "dimension_list");
+        assert_eq!(snake_case("Shape2d"), "shape2d");
+        assert_eq!(snake_case("single"), "single");
+    }
+}
@@ -0,0 +1,488 @@
+/*!
+# Declarative Assembly Formats
+
+This file parses the `assembly_format` strings of operation, attribute and type definitions into a list of format elements, and implements the spacing rules of the printers generated from them.
+
+- lib
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/FormatGen.h>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/FormatGen.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/OpFormatGen.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/AttrOrTypeFormatGen.cpp>
+*/
+
+use syn::LitStr;
+
+/// An element of an assembly format.
+#[derive(Clone, Debug)]
+pub enum Element {
+    /// A literal, e.g. `` `,` `` or `` `to` ``. The empty literal suppresses the space before the next element and the space literal forces one.
+    Literal(String),
+    /// A variable, e.g. `$lhs`, marked as the anchor of its optional group by a trailing `^`.
+    Variable { name: String, anchor: bool },
+    /// A `type($name)` directive.
+    Type { name: String },
+    /// An `attr-dict` or `attr-dict-with-keyword` directive.
+    AttrDict { with_keyword: bool },
+    /// An optional group, `(` elements `)` `?`, printed and parsed only if its anchor is present.
+    Optional(Vec<Element>),
+    /// A `params` directive, all the parameters of an attribute or type.
+    Params,
+    /// A `struct(...)` directive, the given parameters as `name = value` pairs. The arguments are variables or a `params` directive.
+    Struct(Vec<Element>),
+    /// A `custom<Name>(...)` directive, the given parameters parsed and printed by user functions. The arguments are variables.
+    Custom { name: String, arguments: Vec<Element> }
+}
+
+impl Element {
+    /// Return the name of the anchor of an optional group.
+    pub fn anchor(elements: &[Element]) -> Option<&str> {
+        elements.iter().find_map(|element| match element {
+            Self::Variable { name, anchor: true } => Some(name.as_str()),
+            _ => None
+        })
+    }
+}
+
+/// Visit `elements`, descending into optional groups and the arguments of directives.
+pub fn walk<'e>(elements: &'e [Element], visit: &mut impl FnMut(&'e Element)) {
+    for element in elements {
+        visit(element);
+        match element {
+            Element::Optional(elements)
+            | Element::Struct(elements)
+            | Element::Custom { arguments: elements, .. } => walk(elements, visit),
+            _ => {}
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// Lexing
+// ----------------------------------------------------------------------
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Literal(String),
+    Variable(String),
+    Keyword(String),
+    LParen,
+    RParen,
+    Less,
+    Greater,
+    Comma,
+    Question,
+    Caret
+}
+
+fn lex(format: &LitStr) -> syn::Result<Vec<Token>> {
+    let error = |message: String| syn::Error::new(format.span(), message);
+    let source = format.value();
+    let mut chars = source.chars().peekable();
+    let mut tokens = Vec::new();
+    while let Some(char) = chars.next() {
+        match char {
+            char if char.is_whitespace() => {},
+            '`' => {
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some('`') => break,
+                        Some(char) => literal.push(char),
+                        None => return Err(error(
+                            "unexpected end of file in literal".into()))
+                    }
+                }
+                tokens.push(Token::Literal(literal));
+            },
+            '$' => {
+                let mut name = String::new();
+                while let Some(&char) = chars.peek()
+                    && (char.is_ascii_alphanumeric() || char == '_')
+                {
+                    name.push(char);
+                    chars.next();
+                }
+                if name.is_empty() {
+                    return Err(error("expected variable name after '$'".into()));
+                }
+                tokens.push(Token::Variable(name));
+            },
+            '(' => tokens.push(Token::LParen),
+            ')' => tokens.push(Token::RParen),
+            '<' => tokens.push(Token::Less),
+            '>' => tokens.push(Token::Greater),
+            ',' => tokens.push(Token::Comma),
+            '?' => tokens.push(Token::Question),
+            '^' => tokens.push(Token::Caret),
+            char if char.is_ascii_alphabetic() => {
+                let mut keyword = char.to_string();
+                while let Some(&char) = chars.peek()
+                    && (char.is_ascii_alphanumeric() || char == '_' || char == '-')
+                {
+                    keyword.push(char);
+                    chars.next();
+                }
+                tokens.push(Token::Keyword(keyword));
+            },
+            char => return Err(error(format!("unexpected character '{}'", char)))
+        }
+    }
+    Ok(tokens)
+}
+
+// ----------------------------------------------------------------------
+// Parsing
+// ----------------------------------------------------------------------
+
+/// Parse the format string `format`.
+pub fn parse(format: &LitStr) -> syn::Result<Vec<Element>> {
+    let tokens = lex(format)?;
+    let mut parser = FormatParser { format, tokens: &tokens, position: 0 };
+    let elements = parser.parse_elements(false)?;
+    if parser.position != tokens.len() {
+        return Err(parser.error("unexpected ')' in format"));
+    }
+    Ok(elements)
+}
+
+struct FormatParser<'f> {
+    format: &'f LitStr,
+    tokens: &'f [Token],
+    position: usize
+}
+
+impl<'f> FormatParser<'f> {
+    fn error(&self, message: impl Into<String>) -> syn::Error {
+        syn::Error::new(self.format.span(), message.into())
+    }
+
+    fn peek(&self) -> Option<&'f Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<&'f Token> {
+        let token = self.tokens.get(self.position);
+        self.position += 1;
+        token
+    }
+
+    fn expect(&mut self, token: Token, message: &str) -> syn::Result<()> {
+        match self.next() {
+            Some(next) if *next == token => Ok(()),
+            _ => Err(self.error(message))
+        }
+    }
+
+    /// Parse elements up to the end of the format, or up to the `)` closing an optional group if `in_group` is true.
+    fn parse_elements(&mut self, in_group: bool) -> syn::Result<Vec<Element>> {
+        let mut elements = Vec::new();
+        while let Some(token) = self.peek() {
+            if *token == Token::RParen {
+                break;
+            }
+            elements.push(self.parse_element(in_group)?);
+        }
+        Ok(elements)
+    }
+
+    fn parse_element(&mut self, in_group: bool) -> syn::Result<Element> {
+        match self.next().unwrap() {
+            Token::Literal(literal) => {
+                if !is_valid_literal(literal) {
+                    return Err(self.error(format!(
+                        "expected valid literal but got '{}'", literal)));
+                }
+                Ok(Element::Literal(literal.clone()))
+            },
+            Token::Variable(name) => {
+                let anchor = self.peek() == Some(&Token::Caret);
+                if anchor {
+                    if !in_group {
+                        return Err(self.error(
+                            "'^' is only valid within an optional group"));
+                    }
+                    self.position += 1;
+                }
+                Ok(Element::Variable { name: name.clone(), anchor })
+            },
+            Token::Keyword(keyword) => match keyword.as_str() {
+                "attr-dict" => Ok(Element::AttrDict { with_keyword: false }),
+                "attr-dict-with-keyword" =>
+                    Ok(Element::AttrDict { with_keyword: true }),
+                "type" => {
+                    self.expect(Token::LParen, "expected '(' before argument list")?;
+                    let Some(Token::Variable(name)) = self.next() else {
+                        return Err(self.error(
+                            "expected a variable in 'type' directive"));
+                    };
+                    self.expect(Token::RParen, "expected ')' after argument list")?;
+                    Ok(Element::Type { name: name.clone() })
+                },
+                "params" => Ok(Element::Params),
+                "struct" => {
+                    let arguments = self.parse_arguments("struct")?;
+                    let has_params = arguments.iter()
+                        .any(|argument| matches!(argument, Element::Params));
+                    if has_params && arguments.len() != 1 {
+                        return Err(self.error(
+                            "'params' must be the only argument of a 'struct' directive"));
+                    }
+                    Ok(Element::Struct(arguments))
+                },
+                "custom" => {
+                    self.expect(Token::Less, "expected '<' before custom directive name")?;
+                    let Some(Token::Keyword(name)) = self.next() else {
+                        return Err(self.error("expected custom directive name identifier"));
+                    };
+                    self.expect(Token::Greater, "expected '>' after custom directive name")?;
+                    let arguments = self.parse_arguments("custom")?;
+                    if arguments.iter().any(|argument| matches!(argument, Element::Params)) {
+                        return Err(self.error(
+                            "'params' is not valid as an argument of a 'custom' directive"));
+                    }
+                    Ok(Element::Custom { name: name.clone(), arguments })
+                },
+                keyword => Err(self.error(format!(
+                    "unknown directive '{}' in format", keyword)))
+            },
+            Token::LParen => {
+                if in_group {
+                    return Err(self.error("optional groups can't be nested"));
+                }
+                let elements = self.parse_elements(true)?;
+                self.expect(Token::RParen, "expected ')' to end optional group")?;
+                self.expect(Token::Question, "expected '?' after optional group")?;
+                if elements.is_empty() {
+                    return Err(self.error(
+                        "optional group should contain at least one element"));
+                }
+                if Element::anchor(&elements).is_none() {
+                    return Err(self.error(
+                        "optional group has no anchor element, mark one with '^'"));
+                }
+                Ok(Element::Optional(elements))
+            },
+            token => Err(self.error(format!("unexpected token {:?} in format", token)))
+        }
+    }
+
+    /// Parse the parenthesised, comma-separated arguments of `directive`, which are variables or `params`.
+    fn parse_arguments(&mut self, directive: &str) -> syn::Result<Vec<Element>> {
+        self.expect(Token::LParen, "expected '(' before argument list")?;
+        let mut arguments = Vec::new();
+        loop {
+            match self.next() {
+                Some(Token::Variable(name)) => arguments.push(
+                    Element::Variable { name: name.clone(), anchor: false }),
+                Some(Token::Keyword(keyword)) if keyword == "params" =>
+                    arguments.push(Element::Params),
+                _ => return Err(self.error(format!(
+                    "expected a variable or 'params' in '{}' directive", directive)))
+            }
+            if self.peek() != Some(&Token::Comma) {
+                break;
+            }
+            self.position += 1;
+        }
+        self.expect(Token::RParen, "expected ')' after argument list")?;
+        Ok(arguments)
+    }
+}
+
+// ----------------------------------------------------------------------
+// Literals
+// ----------------------------------------------------------------------
+
+/// Returns true if `literal` is a keyword, i.e. a bare identifier.
+pub fn is_keyword(literal: &str) -> bool {
+    literal.chars().next().is_some_and(|char| char.is_ascii_alphabetic() || char == '_')
+        && literal.chars().all(|char|
+            char.is_ascii_alphanumeric() || char == '_' || char == '$' || char == '.')
+}
+
+/// Returns the name of the `TokenKind` variant of the punctuation `literal`.
+pub fn punctuation(literal: &str) -> Option<&'static str> {
+    Some(match literal {
+        "->" => "Arrow",
+        "@" => "At",
+        ":" => "Colon",
+        "," => "Comma",
+        "..." => "Ellipsis",
+        "=" => "Equal",
+        ">" => "Greater",
+        "{" => "LBrace",
+        "(" => "LParen",
+        "[" => "LSquare",
+        "<" => "Less",
+        "-" => "Minus",
+        "+" => "Plus",
+        "?" => "Question",
+        "}" => "RBrace",
+        ")" => "RParen",
+        "]" => "RSquare",
+        "*" => "Star",
+        "|" => "VerticalBar",
+        _ => return None
+    })
+}
+
+fn is_valid_literal(literal: &str) -> bool {
+    literal.is_empty() || literal == " " || is_keyword(literal)
+        || punctuation(literal).is_some()
+}
+
+// ----------------------------------------------------------------------
+// Printing
+// ----------------------------------------------------------------------
+
+/**
+The spacing state of a generated printer. A space is printed between consecutive elements, except after an opening bracket, before a closing bracket or a comma following punctuation, and around the empty literal.
+*/
+pub struct Spacing {
+    should_emit_space: bool,
+    last_was_punctuation: bool
+}
+
+impl Spacing {
+    pub fn new() -> Self {
+        Self { should_emit_space: true, last_was_punctuation: false }
+    }
+
+    /// Returns the text to print for `literal`, including the space preceding it, if any.
+    pub fn literal(&mut self, literal: &str) -> String {
+        if literal.is_empty() || literal == " " {
+            self.should_emit_space = false;
+            self.last_was_punctuation = literal.is_empty();
+            return literal.into();
+        }
+        let mut output = String::new();
+        if self.should_emit_space && self.should_emit_space_before(literal) {
+            output.push(' ');
+        }
+        output.push_str(literal);
+        self.should_emit_space = literal.len() != 1 || !"<({[".contains(literal);
+        self.last_was_punctuation = !is_keyword(literal);
+        output
+    }
+
+    fn should_emit_space_before(&self, literal: &str) -> bool {
+        if literal.len() != 1 && literal != "->" {
+            return true;
+        }
+        if self.last_was_punctuation {
+            return !">)}],".contains(literal);
+        }
+        !"<>(){}[],".contains(literal)
+    }
+
+    /// Returns true if a space must be printed before an element that is not a literal.
+    pub fn element(&mut self) -> bool {
+        let space = self.should_emit_space || !self.last_was_punctuation;
+        self.should_emit_space = true;
+        self.last_was_punctuation = false;
+        space
+    }
+
+    /// Update the state after an attribute dictionary, which prints its own leading space.
+    pub fn attr_dict(&mut self) {
+        self.last_was_punctuation = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proc_macro2::Span;
+
+    use super::*;
+
+    fn parse_str(format: &str) -> syn::Result<Vec<Element>> {
+        parse(&LitStr::new(format, Span::call_site()))
+    }
+
+    fn error(format: &str) -> String {
+        parse_str(format).unwrap_err().to_string()
+    }
+
+    /// Returns the text printed for `literals`, with `$` standing for a variable.
+    fn print(literals: &[&str]) -> String {
+        let mut spacing = Spacing::new();
+        let mut output = String::new();
+        for literal in literals {
+            if *literal == "$" {
+                if spacing.element() {
+                    output.push(' ');
+                }
+                output.push('$');
+            } else {
+                output.push_str(&spacing.literal(literal));
+            }
+        }
+        output
+    }
+
+    #[test]
+    fn parse_operation_format() {
+        let elements = parse_str("$lhs `,` $rhs attr-dict `:` type($result)").unwrap();
+        let [
+            Element::Variable { name: lhs, anchor: false },
+            Element::Literal(comma),
+            Element::Variable { name: rhs, anchor: false },
+            Element::AttrDict { with_keyword: false },
+            Element::Literal(colon),
+            Element::Type { name: result }
+        ] = &elements[..] else {
+            panic!("unexpected elements {:?}", elements);
+        };
+        assert_eq!((lhs.as_str(), rhs.as_str(), result.as_str()), ("lhs", "rhs", "result"));
+        assert_eq!((comma.as_str(), colon.as_str()), (",", ":"));
+    }
+
+    #[test]
+    fn parse_optional_groups_and_directives() {
+        let elements = parse_str(
+            "`<` struct(params) (`,` $inner^ custom<Dims>($a, $b))? `>`").unwrap();
+        assert!(matches!(&elements[1], Element::Struct(arguments)
+            if matches!(&arguments[..], [Element::Params])));
+        let Element::Optional(group) = &elements[2] else {
+            panic!("expected an optional group");
+        };
+        assert_eq!(Element::anchor(group), Some("inner"));
+        assert!(matches!(&group[2], Element::Custom { name, arguments }
+            if name == "Dims" && arguments.len() == 2));
+        let mut count = 0;
+        walk(&elements, &mut |_| count += 1);
+        assert_eq!(count, 10);
+    }
+
+    #[test]
+    fn reject_invalid_formats() {
+        assert!(error("`,").contains("unexpected end of file in literal"));
+        assert!(error("$").contains("expected variable name after '$'"));
+        assert!(error("`%`").contains("expected valid literal but got '%'"));
+        assert!(error("$a^").contains("'^' is only valid within an optional group"));
+        assert!(error("(`,` $a)?").contains("no anchor element"));
+        assert!(error("(`,` ($a^)?)?").contains("optional groups can't be nested"));
+        assert!(error("(`,` $a^)").contains("expected '?' after optional group"));
+        assert!(error("$a )").contains("unexpected ')' in format"));
+        assert!(error("struct($a, params)").contains("'params' must be the only argument"));
+        assert!(error("custom<Dims>(params)").contains("'params' is not valid"));
+        assert!(error("regions").contains("unknown directive 'regions'"));
+    }
+
+    #[test]
+    fn classify_literals() {
+        assert!(is_keyword("to") && is_keyword("_x.y$"));
+        assert!(!is_keyword("2x") && !is_keyword(""));
+        assert_eq!(punctuation("->"), Some("Arrow"));
+        assert_eq!(punctuation("to"), None);
+        assert!(is_valid_literal("") && is_valid_literal(" "));
+    }
+
+    #[test]
+    fn space_elements() {
+        assert_eq!(print(&["$", ",", "$", ":", "$"]), " $, $ : $");
+        assert_eq!(print(&["<", "$", "x", "$", ">"]), "<$ x $>");
+        assert_eq!(print(&["(", ")", "->", "$"]), "() -> $");
+        assert_eq!(print(&["$", "", "$"]), " $$");
+    }
+}
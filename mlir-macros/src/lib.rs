@@ -0,0 +1,135 @@
+/*!
+# Operation Definition Macros
+
+`#[derive(Op)]` defines an operation declaratively, in place of the Operation Definition Specification (ODS) TableGen files and `mlir-tblgen`, `#[derive(AttrOrTypeDef)]` the assembly format of an attribute or type, and `#[derive(PassOptions)]` the options of a pass.
+
+```ignore
+/// Integer addition.
+#[derive(Op)]
+#[op(
+    name = "arith.addi",
+    operands(lhs: signless_integer_like, rhs: signless_integer_like),
+    results(result: signless_integer_like),
+    traits(IsCommutative, SameOperandsAndResultType),
+    interfaces(InferTypeOpInterface),
+    assembly_format = "$lhs `,` $rhs attr-dict `:` type($result)"
+)]
+pub struct AddI(OpId);
+```
+
+The struct holds the handle of the operation and the macro generates:
+
+- `build`, creating the operation in the arena of a context from its operands, result types, attributes and successors,
+- an accessor per operand, result, attribute, region and successor,
+- an implementation of `Op`, whose `verify_invariants` checks the number of operands, results, regions and successors, the constraints of the types of the values, the attributes, the traits constraining types and the custom verifier,
+- `Op::parse` and `Op::print` if an assembly format is given,
+- implementations of the traits and compile-time checks that the interfaces are implemented.
+
+Properties of `#[op(...)]`:
+
+- `name = "dialect.op"`, the name of the operation.
+- `operands(...)` and `results(...)`, lists of `variadic? name (: constraint)? (= type)?` where `constraint` is a path to a `fn(&dyn Type) -> bool` predicate, such as the ones in `ir::operation::constraints`, and `type` is an expression building the type of the values, which makes it buildable.
+- `attributes(...)`, a list of `optional? enum? name: AttributeType` or `optional? typed name`. With `enum`, the type is an enum implementing `EnumAttribute` instead, taken by the builder and returned by the accessor, whose cases are printed and parsed as keywords by the assembly format. With `typed`, the attribute is any attribute with a type, the `TypedAttr` of ODS, held as an `Arc<dyn Attribute>`.
+- `regions(...)` and `successors(...)`, lists of names.
+- `traits(...)` and `interfaces(...)`, lists of trait paths. The `Op::region_kind` hook of operations implementing `RegionKindInterface` forwards to the interface.
+- `all_types_match(...)`, the names of operands, results and typed attributes that must have the same type; repeatable.
+- `types_match_with(from, to, transformer, "summary")`, an operand or result `from` whose type is mapped by `fn transformer(&dyn Type) -> Arc<dyn Type>` to the type of `to`, which the builder and parser then compute; repeatable.
+- `verifier`, to run `fn verify(&self, arena: &IRArena) -> LogicalResult` defined on the struct after the generated checks.
+- `assembly_format = "..."`, the declarative assembly format, e.g. `` "$lhs `,` $rhs attr-dict `:` type($result)" ``.
+- `custom_assembly_format`, to forward `Op::print` and `Op::parse` to `print_assembly` and `parse_assembly` defined on the struct instead.
+
+- include
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/OpBase.td>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/AttrTypeBase.td>
+- lib
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/OpDefinitionsGen.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/AttrOrTypeFormatGen.cpp>
+*/
+
+mod attr_or_type;
+mod format;
+mod op;
+mod pass_options;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput};
+
+/// Derive the definition of an operation from its `#[op(...)]` attributes.
+#[proc_macro_derive(Op, attributes(op))]
+pub fn derive_op(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    op::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Derive `AttrOrTypeDefinition`, the parser and the printer of an attribute or type, from its `#[attr_or_type(...)]` attributes. The fields of the struct are the parameters of the attribute or type.
+///
+/// ```ignore
+/// #[derive(AttrOrTypeDef)]
+/// #[attr_or_type(
+///     mnemonic = "tile",
+///     assembly_format = "`<` struct(params) `>`"
+/// )]
+/// pub struct TileAttr {
+///     sizes: Vec<u64>,
+///     #[param(name = "innerOrder")]
+///     inner_order: Option<AffineMap>,
+///     #[param(default)]
+///     padded: bool
+/// }
+/// ```
+///
+/// parses and prints as `#mydialect.tile<sizes = [4, 8], padded = true>`.
+///
+/// Properties of `#[attr_or_type(...)]`:
+///
+/// - `mnemonic = "..."`, the name following the dialect namespace.
+/// - `assembly_format = "..."`, the declarative assembly format.
+///
+/// Properties of `#[param(...)]` on fields:
+///
+/// - `name = "..."`, the keyword of the parameter in `struct` directives, the name of the field by default.
+/// - `default` or `default = expr`, the value of the parameter when it is omitted. A parameter equal to its default is omitted when printed. Parameters of type `Option<T>` are omitted when `None`.
+///
+/// The format is a sequence of:
+///
+/// - literals, e.g. `` `<` `` or `` `x` ``,
+/// - parameters, e.g. `$sizes`, whose types implement `FieldParser` and `FieldPrinter`,
+/// - `params`, all parameters separated by commas,
+/// - `struct($a, $b)` or `struct(params)`, comma-separated `name = value` pairs in any order,
+/// - `custom<Name>($a, $b)`, a call to `fn parse_name(parser: &mut dyn DialectAsmParser) -> ParseResult<(A, B)>`, which returns the value directly for a single parameter, and to `fn print_name(printer: &mut dyn DialectAsmPrinter, a: &A, b: &B) -> fmt::Result`,
+/// - optional groups, e.g. ``(`,` $inner_order^)?``, which start with a literal and are parsed if it is present, and printed if their anchor `^`, an optional or defaulted parameter, is present and not the default.
+#[proc_macro_derive(AttrOrTypeDef, attributes(attr_or_type, param))]
+pub fn derive_attr_or_type_def(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    attr_or_type::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Derive `PassOptions` and `Default` from the fields of a struct, each of which is an option of a pass.
+///
+/// ```ignore
+/// #[derive(Clone, Debug, PassOptions)]
+/// pub struct InlinerOptions {
+///     /// Maximum number of iterations when inlining within an SCC.
+///     #[option(default = 4)]
+///     max_iterations: u32,
+///     /// The default optimiser pipeline used for callables.
+///     #[option(argument = "default-pipeline", default = "canonicalize".to_string())]
+///     default_pipeline: String
+/// }
+/// ```
+///
+/// parses `max-iterations=8 default-pipeline={cse,canonicalize}` and prints as `{default-pipeline={cse,canonicalize} max-iterations=8}`.
+///
+/// Properties of `#[option(...)]` on fields:
+///
+/// - `argument = "..."`, the name of the option, the name of the field with underscores replaced by dashes by default.
+/// - `description = "..."`, the help of the option, the doc comment of the field by default.
+/// - `default = expr`, the value of the option when it is not given, `Default::default()` by default.
+/// - `value_enum`, for options holding an enum implementing `clap::ValueEnum`, or lists of them.
+///
+/// The other fields hold types implementing `OptionValue`, and fields of type `Vec<T>` are lists of comma-separated values.
+#[proc_macro_derive(PassOptions, attributes(option))]
+pub fn derive_pass_options(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    pass_options::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
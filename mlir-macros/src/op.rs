@@ -0,0 +1,1663 @@
+/*!
+# Operation Definitions
+
+This file expands the operation definitions declared with `#[derive(Op)]` and `#[op(...)]` into the builders, accessors, verifiers, parsers and printers `mlir-tblgen` generates from ODS.
+
+- lib
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/OpDefinitionsGen.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/OpFormatGen.cpp>
+*/
+
+use std::collections::{HashMap, HashSet};
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parenthesized,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    meta::ParseNestedMeta,
+    Data, DeriveInput, Expr, Ident, LitStr, Member, Path, Token, Type
+};
+
+use crate::format::{self, Element, Spacing};
+
+mod kw {
+    syn::custom_keyword!(variadic);
+    syn::custom_keyword!(optional);
+    syn::custom_keyword!(typed);
+}
+
+// ----------------------------------------------------------------------
+// Specification
+// ----------------------------------------------------------------------
+
+/// An operand or a result, `variadic? name (: constraint)? (= type)?`.
+struct ValueSpec {
+    name: Ident,
+    variadic: bool,
+    /// The predicate the types of the values must satisfy.
+    constraint: Option<Path>,
+    /// The expression building the type of the values, if it is fixed.
+    r#type: Option<Expr>
+}
+
+impl Parse for ValueSpec {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let variadic = input.peek(kw::variadic) && input.peek2(Ident);
+        if variadic {
+            input.parse::<kw::variadic>()?;
+        }
+        let name = input.parse()?;
+        let constraint = match input.parse::<Option<Token![:]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None
+        };
+        let r#type = match input.parse::<Option<Token![=]>>()? {
+            Some(_) => Some(input.parse()?),
+            None => None
+        };
+        Ok(Self { name, variadic, constraint, r#type })
+    }
+}
+
+impl ValueSpec {
+    /// Return the description of the constraint, derived from the name of its predicate, e.g. `signless-integer-like` for `signless_integer_like`.
+    fn description(&self) -> Option<String> {
+        self.constraint.as_ref().map(|constraint| constraint.segments.last()
+            .unwrap().ident.to_string().replace('_', "-"))
+    }
+
+    /// The local binding of the values in generated code.
+    fn local(&self) -> Ident {
+        format_ident!("_{}", self.name)
+    }
+
+    /// The local binding of the unresolved operands in generated parsers.
+    fn operands_local(&self) -> Ident {
+        format_ident!("_{}_operands", self.name)
+    }
+
+    /// The local binding of the types of the values in generated parsers and printers.
+    fn types_local(&self) -> Ident {
+        format_ident!("_{}_types", self.name)
+    }
+}
+
+/// An attribute, `optional? enum? name: Type` or `optional? typed name`.
+struct AttributeSpec {
+    name: Ident,
+    optional: bool,
+    /// Whether `r#type` is an enum implementing `EnumAttribute` rather than an attribute. Its cases are printed and parsed as keywords.
+    r#enum: bool,
+    /// Whether the attribute is any attribute with a type, the `TypedAttrInterface` of ODS, held as an `Arc<dyn Attribute>`. Its type may be matched with the types of operands and results.
+    typed: bool,
+    r#type: Type
+}
+
+impl Parse for AttributeSpec {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let optional = input.peek(kw::optional)
+            && (input.peek2(Ident) || input.peek2(Token![enum]));
+        if optional {
+            input.parse::<kw::optional>()?;
+        }
+        let typed = input.peek(kw::typed) && input.peek2(Ident);
+        if typed {
+            input.parse::<kw::typed>()?;
+            let name = input.parse()?;
+            let r#type = syn::parse_quote!(dyn ::mlir::ir::attribute::Attribute);
+            return Ok(Self { name, optional, r#enum: false, typed, r#type });
+        }
+        let r#enum = input.parse::<Option<Token![enum]>>()?.is_some();
+        let name = input.parse()?;
+        input.parse::<Token![:]>()?;
+        let r#type = input.parse()?;
+        Ok(Self { name, optional, r#enum, typed, r#type })
+    }
+}
+
+impl AttributeSpec {
+    /// The path of the `EnumAttribute` trait, as implemented by the type of enum attributes.
+    fn enum_trait(&self) -> TokenStream {
+        let r#type = &self.r#type;
+        quote!(<#r#type as ::mlir::ir::enum_attribute::EnumAttribute>)
+    }
+
+    /// The local binding of the type of a typed attribute in generated parsers.
+    fn type_local(&self) -> Ident {
+        format_ident!("_{}_type", self.name)
+    }
+}
+
+/// A constraint on the type of `to`, which must be the type of `from` transformed by `transformer`, `types_match_with(from, to, transformer, "summary")`.
+struct TypesMatchWith {
+    from: Ident,
+    to: Ident,
+    /// A path to a `fn(&dyn Type) -> Arc<dyn Type>` building the type of `to` from the type of `from`.
+    transformer: Path,
+    /// The description of the constraint, reported when it isn't satisfied.
+    summary: LitStr
+}
+
+impl Parse for TypesMatchWith {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let from = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let to = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let transformer = input.parse()?;
+        input.parse::<Token![,]>()?;
+        let summary = input.parse()?;
+        Ok(Self { from, to, transformer, summary })
+    }
+}
+
+/// The definition of an operation.
+struct OpSpec {
+    ident: Ident,
+    /// The field holding the handle of the operation.
+    member: Member,
+    name: LitStr,
+    operands: Vec<ValueSpec>,
+    results: Vec<ValueSpec>,
+    attributes: Vec<AttributeSpec>,
+    regions: Vec<Ident>,
+    successors: Vec<Ident>,
+    traits: Vec<Path>,
+    interfaces: Vec<Path>,
+    /// The groups of operands, results and typed attributes declared with `all_types_match`.
+    type_groups: Vec<Vec<Ident>>,
+    /// The constraints declared with `types_match_with`.
+    types_match_with: Vec<TypesMatchWith>,
+    /// Whether the operation defines `fn verify(&self, arena: &IRArena) -> LogicalResult` to check its custom invariants.
+    verifier: bool,
+    assembly_format: Option<LitStr>,
+    /// Whether the operation defines `print_assembly` and `parse_assembly` to print and parse its custom assembly form, instead of a declarative format.
+    custom_assembly_format: bool
+}
+
+/// What a name declared by an operation refers to.
+#[derive(Clone, Copy)]
+enum Variable<'s> {
+    Operand(&'s ValueSpec),
+    Result(&'s ValueSpec),
+    Attribute(&'s AttributeSpec),
+    Region(usize),
+    Successor(usize)
+}
+
+fn parse_list<T: Parse>(meta: &ParseNestedMeta) -> syn::Result<Vec<T>> {
+    let content;
+    parenthesized!(content in meta.input);
+    Ok(Punctuated::<T, Token![,]>::parse_terminated(&content)?.into_iter().collect())
+}
+
+impl OpSpec {
+    fn from_input(input: &DeriveInput) -> syn::Result<Self> {
+        let Data::Struct(data) = &input.data else {
+            return Err(syn::Error::new_spanned(
+                &input.ident, "`Op` can only be derived for structs"));
+        };
+        if !input.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &input.generics, "operations can't be generic"));
+        }
+        let mut fields = data.fields.iter();
+        let (Some(field), None) = (fields.next(), fields.next()) else {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "operations must have a single field holding their `OpId`"));
+        };
+        let member = match &field.ident {
+            Some(ident) => Member::Named(ident.clone()),
+            None => Member::Unnamed(0.into())
+        };
+
+        let mut name = None;
+        let mut spec = Self {
+            ident: input.ident.clone(),
+            member,
+            name: LitStr::new("", input.ident.span()),
+            operands: Vec::new(),
+            results: Vec::new(),
+            attributes: Vec::new(),
+            regions: Vec::new(),
+            successors: Vec::new(),
+            traits: Vec::new(),
+            interfaces: Vec::new(),
+            type_groups: Vec::new(),
+            types_match_with: Vec::new(),
+            verifier: false,
+            assembly_format: None,
+            custom_assembly_format: false
+        };
+        for attribute in input.attrs.iter().filter(|attribute| attribute.path().is_ident("op")) {
+            attribute.parse_nested_meta(|meta| {
+                let Some(key) = meta.path.get_ident() else {
+                    return Err(meta.error("unknown operation property"));
+                };
+                match key.to_string().as_str() {
+                    "name" => name = Some(meta.value()?.parse()?),
+                    "operands" => spec.operands = parse_list(&meta)?,
+                    "results" => spec.results = parse_list(&meta)?,
+                    "attributes" => spec.attributes = parse_list(&meta)?,
+                    "regions" => spec.regions = parse_list(&meta)?,
+                    "successors" => spec.successors = parse_list(&meta)?,
+                    "traits" => spec.traits = parse_list(&meta)?,
+                    "interfaces" => spec.interfaces = parse_list(&meta)?,
+                    "all_types_match" => spec.type_groups.push(parse_list(&meta)?),
+                    "types_match_with" => {
+                        let content;
+                        parenthesized!(content in meta.input);
+                        spec.types_match_with.push(content.parse()?);
+                    },
+                    "verifier" => spec.verifier = true,
+                    "assembly_format" =>
+                        spec.assembly_format = Some(meta.value()?.parse()?),
+                    "custom_assembly_format" => spec.custom_assembly_format = true,
+                    _ => return Err(meta.error("unknown operation property"))
+                }
+                Ok(())
+            })?;
+        }
+        spec.name = name.ok_or_else(|| syn::Error::new_spanned(
+            &input.ident, "missing operation name, e.g. `#[op(name = \"arith.addi\")]`"))?;
+        spec.validate()?;
+        Ok(spec)
+    }
+
+    fn validate(&self) -> syn::Result<()> {
+        if self.operands.iter().filter(|operand| operand.variadic).count() > 1 {
+            return Err(syn::Error::new_spanned(
+                &self.ident,
+                "operations with more than one variadic operand require operand segment sizes, which are not supported"));
+        }
+        if self.results.iter().filter(|result| result.variadic).count() > 1 {
+            return Err(syn::Error::new_spanned(
+                &self.ident,
+                "operations with more than one variadic result require result segment sizes, which are not supported"));
+        }
+
+        let mut names = HashSet::new();
+        let all_names = self.operands.iter().map(|operand| &operand.name)
+            .chain(self.results.iter().map(|result| &result.name))
+            .chain(self.attributes.iter().map(|attribute| &attribute.name))
+            .chain(&self.regions)
+            .chain(&self.successors);
+        for name in all_names {
+            if !names.insert(name.to_string()) {
+                return Err(syn::Error::new_spanned(
+                    name, format!("'{}' is declared more than once", name)));
+            }
+        }
+
+        for name in self.type_groups.iter().flatten() {
+            match self.lookup(&name.to_string()) {
+                Some(Variable::Operand(..) | Variable::Result(..)) => {},
+                Some(Variable::Attribute(attribute)) if attribute.typed => {},
+                _ => return Err(syn::Error::new_spanned(
+                    name, format!("'{}' is not an operand, a result or a typed attribute", name)))
+            }
+        }
+
+        for constraint in &self.types_match_with {
+            for name in [&constraint.from, &constraint.to] {
+                match self.lookup(&name.to_string()) {
+                    Some(Variable::Operand(value) | Variable::Result(value))
+                        if !value.variadic => {},
+                    _ => return Err(syn::Error::new_spanned(
+                        name, format!("'{}' is not a non-variadic operand or result", name)))
+                }
+            }
+        }
+
+        if self.custom_assembly_format && self.assembly_format.is_some() {
+            return Err(syn::Error::new_spanned(
+                &self.ident,
+                "operations can't have both an assembly format and a custom assembly format"));
+        }
+        Ok(())
+    }
+
+    fn lookup(&self, name: &str) -> Option<Variable<'_>> {
+        if let Some(index) = self.operands.iter().position(|operand| operand.name == name) {
+            return Some(Variable::Operand(&self.operands[index]));
+        }
+        if let Some(index) = self.results.iter().position(|result| result.name == name) {
+            return Some(Variable::Result(&self.results[index]));
+        }
+        if let Some(index) = self.attributes.iter()
+            .position(|attribute| attribute.name == name)
+        {
+            return Some(Variable::Attribute(&self.attributes[index]));
+        }
+        if let Some(index) = self.regions.iter().position(|region| region == name) {
+            return Some(Variable::Region(index));
+        }
+        self.successors.iter().position(|successor| successor == name)
+            .map(Variable::Successor)
+    }
+
+    /// Returns true if the operation declares the trait with the given name.
+    fn has_trait(&self, name: &str) -> bool {
+        self.traits.iter()
+            .any(|path| path.segments.last().is_some_and(|segment| segment.ident == name))
+    }
+
+    /// Return the non-variadic operand and the transformer the type of the result `name` is built from, if it is constrained by `types_match_with`.
+    fn transformed_from_operand(&self, name: &Ident) -> Option<(&Ident, &Path)> {
+        self.types_match_with.iter()
+            .filter(|constraint| constraint.to == *name)
+            .find(|constraint| self.operands.iter().any(|operand|
+                operand.name == constraint.from && !operand.variadic))
+            .map(|constraint| (&constraint.from, &constraint.transformer))
+    }
+
+    /// Return the groups of values and typed attributes whose types must match, along with the message reported when they don't.
+    fn matching_types(&self) -> Vec<(String, Vec<Variable<'_>>)> {
+        let mut groups = Vec::new();
+        if self.has_trait("SameOperandsAndResultType") {
+            groups.push((
+                "requires the same type for all operands and results".to_string(),
+                self.operands.iter().map(Variable::Operand)
+                    .chain(self.results.iter().map(Variable::Result)).collect()));
+        }
+        if self.has_trait("SameTypeOperands") {
+            groups.push((
+                "requires all operands to have the same type".to_string(),
+                self.operands.iter().map(Variable::Operand).collect()));
+        }
+        for group in &self.type_groups {
+            let names = group.iter().map(Ident::to_string).collect::<Vec<_>>();
+            groups.push((
+                format!("failed to verify that all of {{{}}} have same type",
+                    names.join(", ")),
+                names.iter().map(|name| self.lookup(name).unwrap()).collect()));
+        }
+        groups
+    }
+}
+
+/// Expand `#[derive(Op)]`.
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let spec = OpSpec::from_input(&input)?;
+    let format = spec.assembly_format.as_ref()
+        .map(|format| OpFormat::new(&spec, format))
+        .transpose()?;
+
+    let ident = &spec.ident;
+    let builder = generate_builder(&spec);
+    let accessors = generate_accessors(&spec);
+    let op = generate_op(&spec, format.as_ref());
+    let traits = &spec.traits;
+    let interfaces = &spec.interfaces;
+    let operand_segments = spec.operands.iter().map(|operand| operand.variadic);
+    let result_segments = spec.results.iter().map(|result| result.variadic);
+    Ok(quote! {
+        impl #ident {
+            /// Whether each operand group is variadic.
+            const OPERAND_SEGMENTS: &'static [bool] = &[#(#operand_segments),*];
+            /// Whether each result group is variadic.
+            const RESULT_SEGMENTS: &'static [bool] = &[#(#result_segments),*];
+
+            #builder
+            #accessors
+        }
+
+        #op
+
+        impl ::mlir::ir::operation::definition::Trait for #ident {}
+        #(impl #traits for #ident {})*
+
+        const _: () = {
+            #({
+                fn assert_implements<T: ?Sized + #interfaces>() {}
+                let _ = assert_implements::<#ident>;
+            })*
+        };
+    })
+}
+
+// ----------------------------------------------------------------------
+// Builders and Accessors
+// ----------------------------------------------------------------------
+
+fn generate_builder(spec: &OpSpec) -> TokenStream {
+    let mut parameters = Vec::new();
+    let mut inputs = Vec::new();
+    for operand in &spec.operands {
+        let name = &operand.name;
+        if operand.variadic {
+            parameters.push(quote!(#name: ::std::vec::Vec<::mlir::ir::value::Value>));
+            inputs.push(quote!(__inputs.extend(#name);));
+        } else {
+            parameters.push(quote!(#name: ::mlir::ir::value::Value));
+            inputs.push(quote!(__inputs.push(#name);));
+        }
+    }
+    let mut output_types = Vec::new();
+    for result in &spec.results {
+        let name = &result.name;
+        match (&result.r#type, result.variadic) {
+            (Some(r#type), false) => output_types.push(quote!(
+                __output_types.push(::std::sync::Arc::new(#r#type));)),
+            (_, true) => {
+                parameters.push(quote!(#name: ::std::vec::Vec<
+                    ::std::sync::Arc<dyn ::mlir::ir::r#type::Type>>));
+                output_types.push(quote!(__output_types.extend(#name);));
+            },
+            (None, false) => match spec.transformed_from_operand(name) {
+                // The type is built from the type of an operand.
+                Some((from, transformer)) => output_types.push(quote! {
+                    __output_types.push(#transformer(&**#from.r#type(context.arena())?));
+                }),
+                None => {
+                    parameters.push(quote!(#name: ::std::sync::Arc<dyn ::mlir::ir::r#type::Type>));
+                    output_types.push(quote!(__output_types.push(#name);));
+                }
+            }
+        }
+    }
+    let mut attributes = Vec::new();
+    for attribute in &spec.attributes {
+        let name = &attribute.name;
+        let key = name.to_string();
+        let (r#type, value) = if attribute.r#enum {
+            let enum_trait = attribute.enum_trait();
+            (attribute.r#type.to_token_stream(),
+                quote!(::std::sync::Arc::new(#enum_trait::to_attribute(#name))))
+        } else if attribute.typed {
+            (quote!(::std::sync::Arc<dyn ::mlir::ir::attribute::Attribute>), quote!(#name))
+        } else {
+            (attribute.r#type.to_token_stream(), quote!(::std::sync::Arc::new(#name)))
+        };
+        let push = quote! {
+            __attributes.push(::mlir::ir::attribute::NamedAttribute::new(
+                ::mlir::ir::builtins::attributes::StringAttribute::new(#key), #value));
+        };
+        if attribute.optional {
+            parameters.push(quote!(#name: ::core::option::Option<#r#type>));
+            attributes.push(quote!(if let Some(#name) = #name { #push }));
+        } else {
+            parameters.push(quote!(#name: #r#type));
+            attributes.push(push);
+        }
+    }
+    let successors = &spec.successors;
+    for successor in successors {
+        parameters.push(quote!(#successor: ::mlir::ir::arena::BlockId));
+    }
+    let num_regions = spec.regions.len();
+
+    let doc = format!("Build a `{}` operation in the arena of `context`.", spec.name.value());
+    quote! {
+        #[doc = #doc]
+        #[allow(clippy::too_many_arguments)]
+        pub fn build(
+            context: &mut ::mlir::ir::mlir_context::MLIRContext,
+            location: ::mlir::ir::location::Location,
+            #(#parameters),*
+        ) -> ::mlir::ir::arena::ArenaResult<Self> {
+            use ::mlir::ir::operation::definition::Op as _;
+            let mut __inputs = ::std::vec::Vec::new();
+            #(#inputs)*
+            let mut __output_types: ::std::vec::Vec<
+                ::std::sync::Arc<dyn ::mlir::ir::r#type::Type>> = ::std::vec::Vec::new();
+            #(#output_types)*
+            let mut __attributes = ::std::vec::Vec::new();
+            #(#attributes)*
+            let __operation = ::mlir::ir::operation::Operation::new(
+                location,
+                context.operation_name(Self::OPERATION_NAME),
+                __output_types,
+                __inputs,
+                ::mlir::ir::builtins::attributes::DictionaryAttribute::new(__attributes),
+                &[#(#successors),*]
+            );
+            let __operation = context.arena_mut().create_operation(__operation, #num_regions)?;
+            ::core::result::Result::Ok(Self::from_operation_unchecked(__operation))
+        }
+    }
+}
+
+fn generate_accessors(spec: &OpSpec) -> TokenStream {
+    let member = &spec.member;
+    let mut accessors = Vec::new();
+    let groups = [
+        (&spec.operands, quote!(OPERAND_SEGMENTS), quote!(__operation.num_inputs()),
+            "operand"),
+        (&spec.results, quote!(RESULT_SEGMENTS), quote!(__operation.num_outputs()),
+            "result")
+    ];
+    for (values, segments, count, kind) in groups {
+        for (index, value) in values.iter().enumerate() {
+            let name = &value.name;
+            let is_operand = kind == "operand";
+            let doc = format!("Return the `{}` {}{}.",
+                name, kind, if value.variadic { "s" } else { "" });
+            let segment = quote! {
+                ::mlir::ir::operation::definition::value_segment(
+                    Self::#segments, #count, #index)
+            };
+            accessors.push(match (is_operand, value.variadic) {
+                (true, true) => quote! {
+                    #[doc = #doc]
+                    pub fn #name<'a>(&self, arena: &'a ::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<&'a [::mlir::ir::value::Value]>
+                    {
+                        let __operation = arena.op(self.#member)?;
+                        ::core::result::Result::Ok(&__operation.inputs()[#segment])
+                    }
+                },
+                (true, false) => quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self, arena: &::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<::mlir::ir::value::Value>
+                    {
+                        let __operation = arena.op(self.#member)?;
+                        ::core::result::Result::Ok(__operation.input(#segment.start))
+                    }
+                },
+                (false, true) => quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self, arena: &::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<::std::vec::Vec<::mlir::ir::value::Value>>
+                    {
+                        let __operation = arena.op(self.#member)?;
+                        ::core::result::Result::Ok(#segment
+                            .map(|index| ::mlir::ir::value::Value::Output(__operation.output(index)))
+                            .collect())
+                    }
+                },
+                (false, false) => quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self, arena: &::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<::mlir::ir::value::Value>
+                    {
+                        let __operation = arena.op(self.#member)?;
+                        ::core::result::Result::Ok(::mlir::ir::value::Value::Output(
+                            __operation.output(#segment.start)))
+                    }
+                }
+            });
+        }
+    }
+    for attribute in &spec.attributes {
+        let name = &attribute.name;
+        let r#type = &attribute.r#type;
+        let key = name.to_string();
+        let doc = format!("Return the `{}` attribute.", key);
+        if attribute.r#enum {
+            let message = format!("missing or invalid '{}' attribute, the operation is not verified", key);
+            let value = quote! {
+                ::mlir::ir::operation::definition::enum_attribute_of::<#r#type>(
+                    arena.op(self.#member)?, #key)
+            };
+            accessors.push(if attribute.optional {
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self, arena: &::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<::core::option::Option<#r#type>>
+                    {
+                        ::core::result::Result::Ok(#value)
+                    }
+                }
+            } else {
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name(&self, arena: &::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<#r#type>
+                    {
+                        ::core::result::Result::Ok(#value.expect(#message))
+                    }
+                }
+            });
+            continue;
+        }
+        if attribute.typed {
+            let message = format!("missing '{}' attribute, the operation is not verified", key);
+            let value = quote! {
+                ::mlir::ir::operation::definition::named_attribute(
+                    arena.op(self.#member)?, #key)
+            };
+            accessors.push(if attribute.optional {
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name<'a>(&self, arena: &'a ::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<::core::option::Option<
+                            &'a ::std::sync::Arc<dyn ::mlir::ir::attribute::Attribute>>>
+                    {
+                        ::core::result::Result::Ok(#value)
+                    }
+                }
+            } else {
+                quote! {
+                    #[doc = #doc]
+                    pub fn #name<'a>(&self, arena: &'a ::mlir::ir::arena::IRArena)
+                        -> ::mlir::ir::arena::ArenaResult<
+                            &'a ::std::sync::Arc<dyn ::mlir::ir::attribute::Attribute>>
+                    {
+                        ::core::result::Result::Ok(#value.expect(#message))
+                    }
+                }
+            });
+            continue;
+        }
+        accessors.push(if attribute.optional {
+            quote! {
+                #[doc = #doc]
+                pub fn #name<'a>(&self, arena: &'a ::mlir::ir::arena::IRArena)
+                    -> ::mlir::ir::arena::ArenaResult<::core::option::Option<&'a #r#type>>
+                {
+                    let __operation = arena.op(self.#member)?;
+                    ::core::result::Result::Ok(
+                        ::mlir::ir::operation::definition::attribute_of::<#r#type>(
+                            __operation, #key))
+                }
+            }
+        } else {
+            let message = format!("missing or invalid '{}' attribute, the operation is not verified", key);
+            quote! {
+                #[doc = #doc]
+                pub fn #name<'a>(&self, arena: &'a ::mlir::ir::arena::IRArena)
+                    -> ::mlir::ir::arena::ArenaResult<&'a #r#type>
+                {
+                    let __operation = arena.op(self.#member)?;
+                    ::core::result::Result::Ok(
+                        ::mlir::ir::operation::definition::attribute_of::<#r#type>(
+                            __operation, #key).expect(#message))
+                }
+            }
+        });
+    }
+    for (index, region) in spec.regions.iter().enumerate() {
+        let doc = format!("Return the `{}` region.", region);
+        accessors.push(quote! {
+            #[doc = #doc]
+            pub fn #region(&self, arena: &::mlir::ir::arena::IRArena)
+                -> ::mlir::ir::arena::ArenaResult<::mlir::ir::arena::RegionId>
+            {
+                ::core::result::Result::Ok(arena.op(self.#member)?.region(#index))
+            }
+        });
+    }
+    for (index, successor) in spec.successors.iter().enumerate() {
+        let doc = format!("Return the `{}` successor.", successor);
+        accessors.push(quote! {
+            #[doc = #doc]
+            pub fn #successor(&self, arena: &::mlir::ir::arena::IRArena)
+                -> ::mlir::ir::arena::ArenaResult<::mlir::ir::arena::BlockId>
+            {
+                ::core::result::Result::Ok(arena.op(self.#member)?.successor(#index))
+            }
+        });
+    }
+    quote!(#(#accessors)*)
+}
+
+// ----------------------------------------------------------------------
+// Op Implementation
+// ----------------------------------------------------------------------
+
+/// Bind the values of each operand and result group of `operation` to the local of its group.
+fn bind_values(spec: &OpSpec) -> TokenStream {
+    let operands = spec.operands.iter().enumerate().map(|(index, operand)| {
+        let local = operand.local();
+        quote! {
+            let #local: &[::mlir::ir::value::Value] = &__operands[
+                ::mlir::ir::operation::definition::value_segment(
+                    Self::OPERAND_SEGMENTS, __operands.len(), #index)];
+        }
+    });
+    let results = spec.results.iter().enumerate().map(|(index, result)| {
+        let local = result.local();
+        quote! {
+            let #local: &[::mlir::ir::value::Value] = &__results[
+                ::mlir::ir::operation::definition::value_segment(
+                    Self::RESULT_SEGMENTS, __results.len(), #index)];
+        }
+    });
+    quote! {
+        let __operands = operation.inputs();
+        let __results: ::std::vec::Vec<::mlir::ir::value::Value> = operation.outputs()
+            .map(::mlir::ir::value::Value::Output).collect();
+        #(#operands)*
+        #(#results)*
+    }
+}
+
+fn generate_op(spec: &OpSpec, format: Option<&OpFormat>) -> TokenStream {
+    let ident = &spec.ident;
+    let name = &spec.name;
+    let member = &spec.member;
+    let traits = &spec.traits;
+    let attribute_names = spec.attributes.iter().map(|attribute| attribute.name.to_string());
+    let verifier = generate_verifier(spec);
+    // Interfaces answering runtime queries forward the hooks of `Op` to their implementation.
+    let region_kind = spec.interfaces.iter()
+        .find(|interface| interface.segments.last()
+            .is_some_and(|segment| segment.ident == "RegionKindInterface"))
+        .map(|interface| quote! {
+            fn region_kind(index: usize)
+                -> ::mlir::ir::region_kind_interface::RegionKind
+            {
+                <Self as #interface>::region_kind(index)
+            }
+        });
+    let has_custom_assembly_form = format.is_some() || spec.custom_assembly_format;
+    // A custom assembly format is implemented by hand as `print_assembly` and `parse_assembly`.
+    let custom_assembly = spec.custom_assembly_format.then(|| quote! {
+        fn print(
+            operation: &::mlir::ir::operation::Operation,
+            printer: &mut dyn ::mlir::ir::operation::implementation::OpAsmPrinter
+        ) -> ::core::fmt::Result {
+            Self::print_assembly(operation, printer)
+        }
+
+        fn parse<'a>(
+            parser: &mut dyn ::mlir::ir::operation::implementation::OpAsmParser<'a>,
+            state: &mut ::mlir::ir::operation::support::OperationState
+        ) -> ::mlir::asm_parser::parser::ParseResult {
+            Self::parse_assembly(parser, state)
+        }
+    });
+    let assembly = format.map(|format| {
+        let printer = format.generate_printer();
+        let parser = format.generate_parser();
+        quote! {
+            fn print(
+                operation: &::mlir::ir::operation::Operation,
+                printer: &mut dyn ::mlir::ir::operation::implementation::OpAsmPrinter
+            ) -> ::core::fmt::Result {
+                #printer
+            }
+
+            fn parse<'a>(
+                parser: &mut dyn ::mlir::ir::operation::implementation::OpAsmParser<'a>,
+                state: &mut ::mlir::ir::operation::support::OperationState
+            ) -> ::mlir::asm_parser::parser::ParseResult {
+                #parser
+            }
+        }
+    });
+    quote! {
+        impl ::mlir::ir::operation::definition::Op for #ident {
+            const OPERATION_NAME: &'static str = #name;
+            const HAS_CUSTOM_ASSEMBLY_FORM: bool = #has_custom_assembly_form;
+
+            fn from_operation_unchecked(operation: ::mlir::ir::arena::OpId) -> Self {
+                Self { #member: operation }
+            }
+
+            fn operation(&self) -> ::mlir::ir::arena::OpId {
+                self.#member
+            }
+
+            fn has_trait(id: ::core::any::TypeId) -> bool {
+                false #(|| id == ::core::any::TypeId::of::<dyn #traits>())*
+            }
+
+            fn attribute_names() -> &'static [&'static str] {
+                &[#(#attribute_names),*]
+            }
+
+            fn verify_invariants(
+                arena: &::mlir::ir::arena::IRArena,
+                operation: &::mlir::ir::operation::Operation
+            ) -> ::mlir::support::logical_result::LogicalResult {
+                #verifier
+            }
+
+            #region_kind
+            #assembly
+            #custom_assembly
+        }
+    }
+}
+
+fn generate_verifier(spec: &OpSpec) -> TokenStream {
+    let bind_values = bind_values(spec);
+    let num_regions = spec.regions.len();
+    let num_successors = spec.successors.len();
+
+    let mut constraints = Vec::new();
+    let groups = [
+        (&spec.operands, quote!(OPERAND_SEGMENTS), quote!(__operands), "operand"),
+        (&spec.results, quote!(RESULT_SEGMENTS), quote!(__results), "result")
+    ];
+    for (values, segments, all_values, kind) in groups {
+        for (index, value) in values.iter().enumerate() {
+            let (Some(constraint), Some(description)) = (&value.constraint, value.description())
+            else {
+                continue;
+            };
+            constraints.push(quote! {
+                for __index in ::mlir::ir::operation::definition::value_segment(
+                    Self::#segments, #all_values.len(), #index)
+                {
+                    ::mlir::ir::operation::definition::verify_type_constraint(
+                        operation, #kind, __index,
+                        &**#all_values[__index].r#type(arena)?,
+                        #constraint, #description)?;
+                }
+            });
+        }
+    }
+    for attribute in &spec.attributes {
+        let name = attribute.name.to_string();
+        let r#type = &attribute.r#type;
+        let optional = attribute.optional;
+        if attribute.typed {
+            constraints.push(quote! {
+                ::mlir::ir::operation::definition::verify_typed_attribute(
+                    operation, #name, #optional)?;
+            });
+            continue;
+        }
+        let description = quote!(#r#type).to_string().replace(' ', "");
+        let verify = if attribute.r#enum {
+            quote!(verify_enum_attribute)
+        } else {
+            quote!(verify_attribute)
+        };
+        constraints.push(quote! {
+            ::mlir::ir::operation::definition::#verify::<#r#type>(
+                operation, #name, #optional, #description)?;
+        });
+    }
+    for (message, members) in spec.matching_types() {
+        let mut locals = Vec::new();
+        let mut attributes = Vec::new();
+        for member in members {
+            match member {
+                Variable::Operand(value) | Variable::Result(value) => locals.push(value.local()),
+                Variable::Attribute(attribute) => attributes.push(attribute.name.to_string()),
+                _ => unreachable!()
+            }
+        }
+        constraints.push(quote! {
+            ::mlir::ir::operation::definition::verify_types_match(
+                arena, operation, &[#(#locals),*].concat(), &[#(#attributes),*], #message)?;
+        });
+    }
+    for constraint in &spec.types_match_with {
+        let local = |name: &Ident| match spec.lookup(&name.to_string()) {
+            Some(Variable::Operand(value) | Variable::Result(value)) => value.local(),
+            _ => unreachable!()
+        };
+        let from = local(&constraint.from);
+        let to = local(&constraint.to);
+        let transformer = &constraint.transformer;
+        let message = format!("failed to verify that {}", constraint.summary.value());
+        constraints.push(quote! {
+            ::mlir::ir::operation::definition::verify_types_match_with(
+                arena, operation, #from[0], #to[0], #transformer, #message)?;
+        });
+    }
+    let custom = spec.verifier.then(|| quote! {
+        Self::from_operation_unchecked(operation.id()).verify(arena)?;
+    });
+
+    quote! {
+        ::mlir::ir::operation::definition::verify_count(
+            operation, "operand", Self::OPERAND_SEGMENTS, operation.num_inputs())?;
+        ::mlir::ir::operation::definition::verify_count(
+            operation, "result", Self::RESULT_SEGMENTS, operation.num_outputs())?;
+        ::mlir::ir::operation::definition::verify_count(
+            operation, "region", &[false; #num_regions], operation.num_regions())?;
+        ::mlir::ir::operation::definition::verify_count(
+            operation, "successor", &[false; #num_successors], operation.num_successors())?;
+        #bind_values
+        #(#constraints)*
+        #custom
+        ::core::result::Result::Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// Assembly Format
+// ----------------------------------------------------------------------
+
+/// Where the parser of an assembly format gets the types of an operand or result group from.
+enum TypeSource<'s> {
+    /// The types are parsed by a `type` directive.
+    Parsed,
+    /// The types are built by an expression.
+    Built(&'s Expr),
+    /// The types are the type of the given value, parsed by a `type` directive.
+    Inferred(&'s ValueSpec),
+    /// The types are the type of the given typed attribute, parsed by the format.
+    Attribute(&'s AttributeSpec),
+    /// The types are built by the given transformer from the type of the given value, as constrained by `types_match_with`.
+    Transformed(&'s ValueSpec, &'s Path)
+}
+
+/// An assembly format, validated against the definition of its operation.
+struct OpFormat<'s> {
+    spec: &'s OpSpec,
+    elements: Vec<Element>,
+    type_sources: HashMap<String, TypeSource<'s>>
+}
+
+impl<'s> OpFormat<'s> {
+    fn new(spec: &'s OpSpec, format: &LitStr) -> syn::Result<Self> {
+        let error = |message: String| syn::Error::new(format.span(), message);
+        let elements = format::parse(format)?;
+
+        // Check what the variables refer to.
+        let mut seen = HashSet::new();
+        let mut parsed_types = HashSet::new();
+        let mut num_attr_dicts = 0;
+        let mut next_region = 0;
+        let mut next_successor = 0;
+        let mut result = Ok(());
+        format::walk(&elements, &mut |element| {
+            if result.is_err() {
+                return;
+            }
+            result = (|| {
+                match element {
+                    Element::Variable { name, .. } => {
+                        match spec.lookup(name) {
+                            None => return Err(error(format!(
+                                "expected variable to refer to an argument, region or successor, but got '{}'", name))),
+                            Some(Variable::Result(..)) => return Err(error(format!(
+                                "results can't be used as variables, use 'type(${})'", name))),
+                            Some(Variable::Region(index)) => {
+                                if index != next_region {
+                                    return Err(error(format!(
+                                        "region '{}' must appear in declaration order", name)));
+                                }
+                                next_region += 1;
+                            },
+                            Some(Variable::Successor(index)) => {
+                                if index != next_successor {
+                                    return Err(error(format!(
+                                        "successor '{}' must appear in declaration order", name)));
+                                }
+                                next_successor += 1;
+                            },
+                            _ => {}
+                        }
+                        if !seen.insert(name.clone()) {
+                            return Err(error(format!("'{}' is already bound", name)));
+                        }
+                    },
+                    Element::Type { name } => {
+                        if !matches!(spec.lookup(name),
+                            Some(Variable::Operand(..) | Variable::Result(..)))
+                        {
+                            return Err(error(format!(
+                                "type directive can only refer to operands and results, but got '{}'", name)));
+                        }
+                        if !parsed_types.insert(name.clone()) {
+                            return Err(error(format!("type of '{}' is already bound", name)));
+                        }
+                    },
+                    Element::AttrDict { .. } => num_attr_dicts += 1,
+                    Element::Params | Element::Struct(_) | Element::Custom { .. } =>
+                        return Err(error(
+                            "'params', 'struct' and 'custom' directives are only valid in attribute and type formats".into())),
+                    _ => {}
+                }
+                Ok(())
+            })();
+        });
+        result?;
+        if num_attr_dicts != 1 {
+            return Err(error("format must contain exactly one 'attr-dict' directive".into()));
+        }
+        for operand in &spec.operands {
+            if !seen.contains(&operand.name.to_string()) {
+                return Err(error(format!("operand '{}' not found in format", operand.name)));
+            }
+        }
+        for region in &spec.regions {
+            if !seen.contains(&region.to_string()) {
+                return Err(error(format!("region '{}' not found in format", region)));
+            }
+        }
+        for successor in &spec.successors {
+            if !seen.contains(&successor.to_string()) {
+                return Err(error(format!("successor '{}' not found in format", successor)));
+            }
+        }
+
+        // Check the optional groups.
+        for element in &elements {
+            let Element::Optional(group) = element else {
+                continue;
+            };
+            let anchor = Element::anchor(group).unwrap();
+            match spec.lookup(anchor) {
+                Some(Variable::Operand(operand)) if operand.variadic => {},
+                Some(Variable::Attribute(attribute)) if attribute.optional => {},
+                _ => return Err(error(format!(
+                    "the anchor of an optional group must be a variadic operand or an optional attribute, but got '{}'", anchor)))
+            }
+            match &group[0] {
+                Element::Literal(literal) if !literal.trim().is_empty() => {},
+                Element::Variable { name, .. } if matches!(spec.lookup(name),
+                    Some(Variable::Operand(operand)) if operand.variadic) => {},
+                _ => return Err(error(
+                    "the first element of an optional group must be a literal or a variadic operand".into()))
+            }
+            for element in group {
+                let valid = match element {
+                    Element::Literal(_) => true,
+                    Element::Variable { name, .. } | Element::Type { name } =>
+                        match spec.lookup(name) {
+                            Some(Variable::Operand(operand)) => operand.variadic,
+                            Some(Variable::Attribute(attribute)) =>
+                                attribute.optional && matches!(element, Element::Variable { .. }),
+                            _ => false
+                        },
+                    _ => false
+                };
+                if !valid {
+                    return Err(error(
+                        "optional groups may only contain literals, variadic operands, their types and optional attributes".into()));
+                }
+            }
+        }
+
+        // Find out where the types of the operands and results come from.
+        let matching_types = spec.matching_types();
+        let mut type_sources = HashMap::new();
+        for value in spec.operands.iter().chain(&spec.results) {
+            let name = value.name.to_string();
+            let source = if parsed_types.contains(&name) {
+                TypeSource::Parsed
+            } else if let Some(r#type) = &value.r#type {
+                TypeSource::Built(r#type)
+            } else if let Some(constraint) = spec.types_match_with.iter()
+                .find(|constraint| constraint.to == value.name)
+            {
+                let Some(Variable::Operand(from) | Variable::Result(from))
+                    = spec.lookup(&constraint.from.to_string())
+                else {
+                    unreachable!()
+                };
+                TypeSource::Transformed(from, &constraint.transformer)
+            } else {
+                let is_value = |member: &Variable| matches!(member,
+                    Variable::Operand(member) | Variable::Result(member)
+                        if member.name == value.name);
+                matching_types.iter()
+                    .filter(|(_, group)| group.iter().any(is_value))
+                    .flat_map(|(_, group)| group.iter())
+                    .find_map(|member| match member {
+                        _ if is_value(member) => None,
+                        Variable::Operand(member) | Variable::Result(member) => {
+                            if parsed_types.contains(&member.name.to_string())
+                                && !member.variadic
+                            {
+                                Some(TypeSource::Inferred(member))
+                            } else {
+                                member.r#type.as_ref().map(TypeSource::Built)
+                            }
+                        },
+                        // The type of a typed attribute is known once it is parsed, if it is always present.
+                        Variable::Attribute(attribute) if !attribute.optional
+                            && seen.contains(&attribute.name.to_string()) =>
+                            Some(TypeSource::Attribute(attribute)),
+                        _ => None
+                    })
+                    .ok_or_else(|| error(format!(
+                        "type of '{}' is not buildable and can't be inferred, add 'type(${})' to the format",
+                        name, name)))?
+            };
+            if !matches!(source, TypeSource::Parsed) && value.variadic
+                && spec.results.iter().any(|result| result.name == value.name)
+            {
+                return Err(error(format!(
+                    "type of variadic result '{}' must be bound by a type directive", name)));
+            }
+            type_sources.insert(name, source);
+        }
+        for constraint in &spec.types_match_with {
+            if matches!(type_sources[&constraint.from.to_string()], TypeSource::Transformed(..))
+                && matches!(type_sources[&constraint.to.to_string()], TypeSource::Transformed(..))
+            {
+                return Err(error(format!(
+                    "type of '{}' can't be built from the type of '{}', which is built from another type",
+                    constraint.to, constraint.from)));
+            }
+        }
+
+        Ok(Self { spec, elements, type_sources })
+    }
+
+    /// Return the names of the attributes printed by the format, which are elided from the attribute dictionary.
+    fn elided_attributes(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        format::walk(&self.elements, &mut |element| {
+            if let Element::Variable { name, .. } = element
+                && let Some(Variable::Attribute(..)) = self.spec.lookup(name)
+            {
+                names.push(name.clone());
+            }
+        });
+        names
+    }
+
+    // ----------------------------------------------------------------------
+    // Printer
+    // ----------------------------------------------------------------------
+
+    fn generate_printer(&self) -> TokenStream {
+        let bind_values = bind_values(self.spec);
+        let mut types = Vec::new();
+        format::walk(&self.elements, &mut |element| {
+            if let Element::Type { name } = element {
+                let value = match self.spec.lookup(name) {
+                    Some(Variable::Operand(value) | Variable::Result(value)) => value,
+                    _ => unreachable!()
+                };
+                let local = value.local();
+                let types_local = value.types_local();
+                types.push(quote! {
+                    let #types_local = ::mlir::ir::operation::definition::value_types(
+                        printer.arena(), #local).map_err(|_| ::core::fmt::Error)?;
+                });
+            }
+        });
+        let mut spacing = Spacing::new();
+        let elements = self.elements.iter()
+            .map(|element| self.print_element(element, &mut spacing));
+        quote! {
+            use ::core::fmt::Write as _;
+            use ::mlir::ir::operation::implementation::{AsmPrinter as _, OpAsmPrinter as _};
+            #bind_values
+            #(#types)*
+            #(#elements)*
+            ::core::result::Result::Ok(())
+        }
+    }
+
+    fn print_element(&self, element: &Element, spacing: &mut Spacing) -> TokenStream {
+        let space = |spacing: &mut Spacing| spacing.element().then(|| quote! {
+            printer.write_char(' ')?;
+        });
+        match element {
+            Element::Literal(literal) => {
+                let text = spacing.literal(literal);
+                if text.is_empty() {
+                    return quote!();
+                }
+                quote!(printer.write_str(#text)?;)
+            },
+            Element::Variable { name, .. } => {
+                let space = space(spacing);
+                let print = match self.spec.lookup(name).unwrap() {
+                    Variable::Operand(operand) => {
+                        let local = operand.local();
+                        if operand.variadic {
+                            quote!(printer.print_operands(#local)?;)
+                        } else {
+                            quote!(printer.print_operand(#local[0])?;)
+                        }
+                    },
+                    Variable::Attribute(attribute) if attribute.r#enum => {
+                        let key = attribute.name.to_string();
+                        let r#type = &attribute.r#type;
+                        let enum_trait = attribute.enum_trait();
+                        quote! {
+                            if let ::core::option::Option::Some(__value) =
+                                ::mlir::ir::operation::definition::enum_attribute_of::<#r#type>(
+                                    operation, #key)
+                            {
+                                printer.write_str(#enum_trait::stringify(__value))?;
+                            }
+                        }
+                    },
+                    Variable::Attribute(attribute) => {
+                        let key = attribute.name.to_string();
+                        quote! {
+                            if let ::core::option::Option::Some(__attribute) =
+                                ::mlir::ir::operation::definition::named_attribute(operation, #key)
+                            {
+                                printer.print_attribute(&**__attribute)?;
+                            }
+                        }
+                    },
+                    Variable::Region(index) => quote! {
+                        printer.print_region(operation.region(#index), true, true, false)?;
+                    },
+                    Variable::Successor(index) => quote! {
+                        printer.print_successor(operation.successor(#index))?;
+                    },
+                    Variable::Result(..) => unreachable!()
+                };
+                quote!(#space #print)
+            },
+            Element::Type { name } => {
+                let space = space(spacing);
+                let value = match self.spec.lookup(name).unwrap() {
+                    Variable::Operand(value) | Variable::Result(value) => value,
+                    _ => unreachable!()
+                };
+                let types_local = value.types_local();
+                let print = if value.variadic {
+                    quote!(printer.print_type_list(&#types_local)?;)
+                } else {
+                    quote!(printer.print_type(&*#types_local[0])?;)
+                };
+                quote!(#space #print)
+            },
+            Element::AttrDict { with_keyword } => {
+                spacing.attr_dict();
+                let elided = self.elided_attributes();
+                if *with_keyword {
+                    quote! {
+                        printer.print_optional_attr_dict_with_keyword(
+                            operation.attributes(), &[#(#elided),*])?;
+                    }
+                } else {
+                    quote! {
+                        printer.print_optional_attr_dict(
+                            operation.attributes(), &[#(#elided),*])?;
+                    }
+                }
+            },
+            Element::Optional(group) => {
+                let condition = match self.spec.lookup(Element::anchor(group).unwrap()).unwrap() {
+                    Variable::Operand(operand) => {
+                        let local = operand.local();
+                        quote!(!#local.is_empty())
+                    },
+                    Variable::Attribute(attribute) => {
+                        let key = attribute.name.to_string();
+                        quote! {
+                            ::mlir::ir::operation::definition::named_attribute(operation, #key)
+                                .is_some()
+                        }
+                    },
+                    _ => unreachable!()
+                };
+                let elements = group.iter()
+                    .map(|element| self.print_element(element, spacing))
+                    .collect::<Vec<_>>();
+                quote! {
+                    if #condition {
+                        #(#elements)*
+                    }
+                }
+            },
+            Element::Params | Element::Struct(_) | Element::Custom { .. } => unreachable!()
+        }
+    }
+
+    // ----------------------------------------------------------------------
+    // Parser
+    // ----------------------------------------------------------------------
+
+    fn generate_parser(&self) -> TokenStream {
+        let spec = self.spec;
+        let operands = spec.operands.iter().map(|operand| {
+            let local = operand.operands_local();
+            quote! {
+                let mut #local: ::std::vec::Vec<
+                    ::mlir::asm_parser::parser::UnresolvedOperand<'a>> = ::std::vec::Vec::new();
+            }
+        });
+        let parsed_types = spec.operands.iter().chain(&spec.results)
+            .filter(|value| matches!(self.type_sources[&value.name.to_string()], TypeSource::Parsed))
+            .map(|value| {
+                let local = value.types_local();
+                quote! {
+                    let mut #local: ::std::vec::Vec<
+                        ::std::sync::Arc<dyn ::mlir::ir::r#type::Type>> = ::std::vec::Vec::new();
+                }
+            })
+            .collect::<Vec<_>>();
+        let elements = self.elements.iter().map(|element| self.parse_element(element));
+
+        // Compute the types that are not parsed, then the types built from them.
+        let mut other_types = Vec::new();
+        let mut transformed_types = Vec::new();
+        for (is_operand, value) in spec.operands.iter().map(|operand| (true, operand))
+            .chain(spec.results.iter().map(|result| (false, result)))
+        {
+            let local = value.types_local();
+            let count = if is_operand {
+                let operands_local = value.operands_local();
+                quote!(#operands_local.len())
+            } else {
+                quote!(1)
+            };
+            match &self.type_sources[&value.name.to_string()] {
+                TypeSource::Parsed => {},
+                TypeSource::Built(r#type) => other_types.push(quote! {
+                    let __type: ::std::sync::Arc<dyn ::mlir::ir::r#type::Type>
+                        = ::std::sync::Arc::new(#r#type);
+                    let #local = ::std::vec![__type; #count];
+                }),
+                TypeSource::Inferred(source) => {
+                    let source = source.types_local();
+                    other_types.push(quote! {
+                        let #local = ::std::vec![#source[0].clone(); #count];
+                    });
+                },
+                TypeSource::Attribute(attribute) => {
+                    let source = attribute.type_local();
+                    other_types.push(quote! {
+                        let #local = ::std::vec![#source.clone(); #count];
+                    });
+                },
+                TypeSource::Transformed(source, transformer) => {
+                    let source = source.types_local();
+                    transformed_types.push(quote! {
+                        let #local = ::std::vec![#transformer(&*#source[0]); #count];
+                    });
+                }
+            }
+        }
+
+        let resolutions = spec.operands.iter().map(|operand| {
+            let operands_local = operand.operands_local();
+            let types_local = operand.types_local();
+            quote! {
+                parser.resolve_operands(
+                    &#operands_local, &#types_local, __location, &mut state.inputs)?;
+            }
+        });
+        let results = spec.results.iter().map(|result| {
+            let types_local = result.types_local();
+            quote!(state.add_types(#types_local);)
+        });
+
+        quote! {
+            use ::mlir::ir::operation::implementation::{AsmParser as _, OpAsmParser as _};
+            let __location = parser.current_location();
+            #(#operands)*
+            #(#parsed_types)*
+            #(#elements)*
+            #(#other_types)*
+            #(#transformed_types)*
+            #(#resolutions)*
+            #(#results)*
+            ::core::result::Result::Ok(())
+        }
+    }
+
+    fn parse_literal(literal: &str) -> TokenStream {
+        if literal.trim().is_empty() {
+            return quote!();
+        }
+        if let Some(kind) = format::punctuation(literal) {
+            let kind = format_ident!("{}", kind);
+            return quote! {
+                parser.parse_token(::mlir::asm_parser::token::TokenKind::#kind)?;
+            };
+        }
+        quote!(parser.parse_keyword(#literal)?;)
+    }
+
+    fn parse_element(&self, element: &Element) -> TokenStream {
+        match element {
+            Element::Literal(literal) => Self::parse_literal(literal),
+            Element::Variable { name, .. } => match self.spec.lookup(name).unwrap() {
+                Variable::Operand(operand) => {
+                    let local = operand.operands_local();
+                    if operand.variadic {
+                        quote!(#local = parser.parse_operand_list()?;)
+                    } else {
+                        quote!(#local.push(parser.parse_operand()?);)
+                    }
+                },
+                Variable::Attribute(attribute) if attribute.r#enum => {
+                    let key = attribute.name.to_string();
+                    let enum_trait = attribute.enum_trait();
+                    let expected = format!(
+                        "expected string or keyword containing one of the following enum values for attribute '{}' [{{}}]",
+                        key);
+                    let invalid = format!("invalid {} attribute specification: \"{{}}\"", key);
+                    quote! {
+                        let __attribute_location = parser.current_location();
+                        let ::core::option::Option::Some(__keyword) = parser
+                            .parse_optional_any_keyword()
+                            .or_else(|| parser.parse_optional_string())
+                        else {
+                            return ::core::result::Result::Err(parser.emit_error(
+                                __attribute_location,
+                                ::std::format!(#expected, #enum_trait::keywords())));
+                        };
+                        let ::core::option::Option::Some(__value)
+                            = #enum_trait::symbolise(&__keyword)
+                        else {
+                            return ::core::result::Result::Err(parser.emit_error(
+                                __attribute_location, ::std::format!(#invalid, __keyword)));
+                        };
+                        state.add_attribute(
+                            #key, ::std::sync::Arc::new(#enum_trait::to_attribute(__value)));
+                    }
+                },
+                Variable::Attribute(attribute) if attribute.typed => {
+                    let key = attribute.name.to_string();
+                    let type_local = attribute.type_local();
+                    quote! {
+                        let __attribute_location = parser.current_location();
+                        let __attribute = parser.parse_attribute()?;
+                        let ::core::option::Option::Some(#type_local) =
+                            ::mlir::ir::builtins::attribute_interfaces::typed_attribute_type(
+                                &*__attribute)
+                        else {
+                            return ::core::result::Result::Err(parser.emit_error(
+                                __attribute_location,
+                                "invalid kind of attribute specified".into()));
+                        };
+                        state.add_attribute(#key, __attribute);
+                    }
+                },
+                Variable::Attribute(attribute) => {
+                    let key = attribute.name.to_string();
+                    let r#type = &attribute.r#type;
+                    quote! {
+                        let __attribute_location = parser.current_location();
+                        let __attribute = parser.parse_attribute()?;
+                        if __attribute.downcast_ref::<#r#type>().is_none() {
+                            return ::core::result::Result::Err(parser.emit_error(
+                                __attribute_location,
+                                "invalid kind of attribute specified".into()));
+                        }
+                        state.add_attribute(#key, __attribute);
+                    }
+                },
+                Variable::Region(_) => quote! {
+                    let __region = parser.parse_region()?;
+                    state.add_region(__region);
+                },
+                Variable::Successor(_) => quote! {
+                    let __successor = parser.parse_successor()?;
+                    state.add_successor(__successor);
+                },
+                Variable::Result(..) => unreachable!()
+            },
+            Element::Type { name } => {
+                let value = match self.spec.lookup(name).unwrap() {
+                    Variable::Operand(value) | Variable::Result(value) => value,
+                    _ => unreachable!()
+                };
+                let local = value.types_local();
+                if value.variadic {
+                    quote!(#local = parser.parse_optional_type_list()?;)
+                } else {
+                    quote!(#local.push(parser.parse_type()?);)
+                }
+            },
+            Element::AttrDict { with_keyword: false } => quote! {
+                parser.parse_optional_attr_dict(&mut state.attributes)?;
+            },
+            Element::AttrDict { with_keyword: true } => quote! {
+                parser.parse_optional_attr_dict_with_keyword(&mut state.attributes)?;
+            },
+            Element::Optional(group) => {
+                let rest = group[1..].iter().map(|element| self.parse_element(element));
+                match &group[0] {
+                    Element::Literal(literal) => {
+                        let condition = match format::punctuation(literal) {
+                            Some(kind) => {
+                                let kind = format_ident!("{}", kind);
+                                quote! {
+                                    parser.parse_optional_token(
+                                        ::mlir::asm_parser::token::TokenKind::#kind)
+                                }
+                            },
+                            None => quote!(parser.parse_optional_keyword(#literal))
+                        };
+                        quote! {
+                            if #condition {
+                                #(#rest)*
+                            }
+                        }
+                    },
+                    Element::Variable { name, .. } => {
+                        let Some(Variable::Operand(operand)) = self.spec.lookup(name) else {
+                            unreachable!()
+                        };
+                        let local = operand.operands_local();
+                        quote! {
+                            if let ::core::option::Option::Some(__operand)
+                                = parser.parse_optional_operand()
+                            {
+                                #local.push(__operand?);
+                                while parser.parse_optional_comma() {
+                                    #local.push(parser.parse_operand()?);
+                                }
+                                #(#rest)*
+                            }
+                        }
+                    },
+                    _ => unreachable!()
+                }
+            },
+            Element::Params | Element::Struct(_) | Element::Custom { .. } => unreachable!()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn cmp() -> DeriveInput {
+        parse_quote! {
+            #[op(
+                name = "index.cmp",
+                operands(lhs: index = IndexType::new(), rhs: index = IndexType::new()),
+                results(result: i1 = IntegerType::new(1, Signedness::Signless)),
+                attributes(enum pred: IndexCmpPredicate, optional enum flags: Flags),
+                assembly_format = "$pred `(` $lhs `,` $rhs `)` attr-dict"
+            )]
+            pub struct Cmp(OpId);
+        }
+    }
+
+    #[test]
+    fn parse_enum_attributes() {
+        let spec = OpSpec::from_input(&cmp()).unwrap();
+        let [pred, flags] = &spec.attributes[..] else {
+            panic!("expected two attributes");
+        };
+        assert_eq!(pred.name, "pred");
+        assert!(pred.r#enum && !pred.optional);
+        assert_eq!(flags.name, "flags");
+        assert!(flags.r#enum && flags.optional);
+    }
+
+    #[test]
+    fn expand_enum_attributes() {
+        let output = expand(cmp()).unwrap().to_string();
+        assert!(output.contains("verify_enum_attribute :: < IndexCmpPredicate >"));
+        assert!(output.contains("enum_attribute_of :: < IndexCmpPredicate >"));
+        assert!(output.contains("symbolise (& __keyword)"));
+        assert!(output.contains("stringify (__value)"));
+        assert!(!output.contains("print_attribute"));
+    }
+
+    #[test]
+    fn expand_typed_attributes() {
+        let input = parse_quote! {
+            #[op(
+                name = "arith.constant",
+                attributes(typed value),
+                results(result),
+                all_types_match(value, result),
+                assembly_format = "attr-dict $value"
+            )]
+            pub struct Constant(OpId);
+        };
+        let spec = OpSpec::from_input(&input).unwrap();
+        assert!(spec.attributes[0].typed);
+        let output = expand(input).unwrap().to_string();
+        assert!(output.contains("verify_typed_attribute"));
+        assert!(output.contains("& [\"value\"]"));
+        // The type of the result is the type of the parsed attribute.
+        assert!(output.contains("typed_attribute_type"));
+        assert!(output.contains("_value_type . clone ()"));
+    }
+
+    #[test]
+    fn expand_types_match_with() {
+        let input = parse_quote! {
+            #[op(
+                name = "arith.cmpi",
+                operands(lhs, rhs),
+                results(result),
+                attributes(enum predicate: CmpIPredicate),
+                all_types_match(lhs, rhs),
+                types_match_with(lhs, result, i1_same_shape, "result has i1 element type"),
+                assembly_format = "$predicate `,` $lhs `,` $rhs attr-dict `:` type($lhs)"
+            )]
+            pub struct CmpI(OpId);
+        };
+        let output = expand(input).unwrap().to_string();
+        assert!(output.contains("verify_types_match_with"));
+        assert!(output.contains("failed to verify that result has i1 element type"));
+        // The result type is neither parsed nor a parameter of the builder.
+        assert!(output.contains("i1_same_shape (& * _lhs_types [0])"));
+        assert!(output.contains("__output_types . push (i1_same_shape (& * * lhs"));
+        assert!(!output.contains("result : :: std :: sync :: Arc"));
+    }
+
+    #[test]
+    fn reject_types_match_with_unknown_value() {
+        let input = parse_quote! {
+            #[op(
+                name = "arith.cmpi",
+                operands(lhs),
+                results(result),
+                types_match_with(lhs, other, i1_same_shape, "summary")
+            )]
+            pub struct CmpI(OpId);
+        };
+        assert!(expand(input).is_err());
+    }
+
+    #[test]
+    fn expand_custom_assembly_format() {
+        let input = parse_quote! {
+            #[op(name = "arith.select", operands(condition), custom_assembly_format)]
+            pub struct Select(OpId);
+        };
+        let output = expand(input).unwrap().to_string();
+        assert!(output.contains("HAS_CUSTOM_ASSEMBLY_FORM : bool = true"));
+        assert!(output.contains("Self :: print_assembly (operation , printer)"));
+        assert!(output.contains("Self :: parse_assembly (parser , state)"));
+
+        let input = parse_quote! {
+            #[op(
+                name = "arith.select",
+                operands(condition),
+                custom_assembly_format,
+                assembly_format = "$condition attr-dict"
+            )]
+            pub struct Select(OpId);
+        };
+        assert!(expand(input).is_err());
+    }
+}
@@ -0,0 +1,341 @@
+/*!
+# Pass Options
+
+This file expands the pass options declared with `#[derive(PassOptions)]` and `#[option(...)]` into the parser, the printer and the help of the options, in place of the `Option` and `ListOption` members `mlir-tblgen` generates from the `options` of ODS `Pass`es.
+
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/tools/mlir-tblgen/PassGen.cpp>
+*/
+
+use std::collections::HashSet;
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{
+    Data, DeriveInput, Expr, Fields, GenericArgument, Ident, LitStr, PathArguments, Type
+};
+
+// ----------------------------------------------------------------------
+// Specification
+// ----------------------------------------------------------------------
+
+/// An option of a pass, i.e. a field of the struct.
+struct OptionSpec {
+    ident: Ident,
+    r#type: Type,
+    /// The name of the option in textual pipelines.
+    argument: String,
+    description: String,
+    /// The expression of the default value of the option.
+    default: TokenStream,
+    /// The `T` of a list option of type `Vec<T>`.
+    element: Option<Type>,
+    /// Whether the values of the option are enums implementing `clap::ValueEnum`.
+    value_enum: bool
+}
+
+impl OptionSpec {
+    fn from_field(ident: &Ident, r#type: &Type, attributes: &[syn::Attribute])
+        -> syn::Result<Self>
+    {
+        let mut argument = ident.to_string().trim_start_matches("r#").replace('_', "-");
+        let mut description = None;
+        let mut default = quote!(<#r#type as ::core::default::Default>::default());
+        let mut value_enum = false;
+        for attribute in attributes.iter().filter(|attribute| attribute.path().is_ident("option")) {
+            attribute.parse_nested_meta(|meta| {
+                if meta.path.is_ident("argument") {
+                    argument = meta.value()?.parse::<LitStr>()?.value();
+                } else if meta.path.is_ident("description") {
+                    description = Some(meta.value()?.parse::<LitStr>()?.value());
+                } else if meta.path.is_ident("default") {
+                    let value: Expr = meta.value()?.parse()?;
+                    default = quote!(#value);
+                } else if meta.path.is_ident("value_enum") {
+                    value_enum = true;
+                } else {
+                    return Err(meta.error("unknown option property"));
+                }
+                Ok(())
+            })?;
+        }
+        // The description defaults to the doc comment of the field.
+        let description = description.unwrap_or_else(|| doc_comment(attributes));
+        let element = vec_argument(r#type);
+        Ok(Self {
+            ident: ident.clone(),
+            r#type: r#type.clone(),
+            argument,
+            description,
+            default,
+            element,
+            value_enum
+        })
+    }
+
+    /// The type implementing `OptionValue` of the value, or of the elements of a list option.
+    fn value_type(&self) -> TokenStream {
+        let r#type = self.element.as_ref().unwrap_or(&self.r#type);
+        match self.value_enum {
+            true => quote!(::mlir::pass::options::EnumValue<#r#type>),
+            false => quote!(#r#type)
+        }
+    }
+}
+
+/// Return the lines of the doc comment in `attributes` joined by spaces.
+fn doc_comment(attributes: &[syn::Attribute]) -> String {
+    let lines: Vec<String> = attributes.iter()
+        .filter(|attribute| attribute.path().is_ident("doc"))
+        .filter_map(|attribute| match &attribute.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value: Expr::Lit(syn::ExprLit { lit: syn::Lit::Str(line), .. }), ..
+            }) => Some(line.value().trim().to_string()),
+            _ => None
+        })
+        .filter(|line| !line.is_empty())
+        .collect();
+    lines.join(" ")
+}
+
+/// Return `T` if `type` is `Vec<T>`.
+fn vec_argument(r#type: &Type) -> Option<Type> {
+    let Type::Path(path) = r#type else {
+        return None;
+    };
+    let segment = path.path.segments.last()?;
+    if segment.ident != "Vec" {
+        return None;
+    }
+    let PathArguments::AngleBracketed(arguments) = &segment.arguments else {
+        return None;
+    };
+    match arguments.args.first() {
+        Some(GenericArgument::Type(r#type)) if arguments.args.len() == 1
+            => Some(r#type.clone()),
+        _ => None
+    }
+}
+
+// ----------------------------------------------------------------------
+// Expansion
+// ----------------------------------------------------------------------
+
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    let ident = &input.ident;
+    let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new_spanned(ident, "PassOptions can only be derived for structs"));
+    };
+    let Fields::Named(fields) = &data.fields else {
+        return Err(syn::Error::new_spanned(ident, "PassOptions requires named fields"));
+    };
+    let mut options = Vec::new();
+    let mut arguments = HashSet::new();
+    for field in &fields.named {
+        let option = OptionSpec::from_field(
+            field.ident.as_ref().unwrap(), &field.ty, &field.attrs)?;
+        if !arguments.insert(option.argument.clone()) {
+            return Err(syn::Error::new_spanned(field,
+                format!("duplicate option '{}'", option.argument)));
+        }
+        options.push(option);
+    }
+    // Options are printed sorted by argument to make the ordering deterministic.
+    options.sort_by(|lhs, rhs| lhs.argument.cmp(&rhs.argument));
+
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let defaults = options.iter().map(|option| {
+        let OptionSpec { ident, default, .. } = option;
+        quote!(#ident: #default)
+    });
+    let parsers = options.iter().map(expand_parser);
+    let printers = options.iter().map(expand_printer);
+    let infos = options.iter().map(expand_info);
+    let print = match options.is_empty() {
+        true => quote!(::core::result::Result::Ok(())),
+        false => quote! {
+            let mut __printer = ::mlir::pass::options::OptionsPrinter::new(os)?;
+            #(#printers)*
+            __printer.finish()
+        }
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::core::default::Default for #ident #type_generics #where_clause {
+            fn default() -> Self {
+                Self { #(#defaults),* }
+            }
+        }
+
+        impl #impl_generics ::mlir::pass::options::PassOptions for #ident #type_generics #where_clause {
+            fn parse_from_string(&mut self, options: &str)
+                -> ::mlir::support::logical_result::LogicalResult
+            {
+                for (__argument, __value) in ::mlir::pass::options::split_options(options)? {
+                    match __argument {
+                        #(#parsers)*
+                        _ => return ::core::result::Result::Err(
+                            ::std::format!("no such option '{}'", __argument).into())
+                    }
+                }
+                ::core::result::Result::Ok(())
+            }
+
+            fn print(&self, os: &mut dyn ::core::fmt::Write) -> ::core::fmt::Result {
+                #print
+            }
+
+            fn option_infos() -> ::std::vec::Vec<::mlir::pass::options::OptionInfo> {
+                let __defaults = <Self as ::core::default::Default>::default();
+                ::std::vec![#(#infos),*]
+            }
+        }
+    })
+}
+
+/// Expand the arm of the match on the argument of the option, assigning the parsed value to the field.
+fn expand_parser(option: &OptionSpec) -> TokenStream {
+    let OptionSpec { ident, argument, .. } = option;
+    let value_type = option.value_type();
+    let value = match (&option.element, option.value_enum) {
+        (None, false) => quote!(::mlir::pass::options::parse_option::<#value_type>(__argument, __value)?),
+        (None, true) => quote!(::mlir::pass::options::parse_option::<#value_type>(__argument, __value)?.0),
+        (Some(_), false) => quote!(::mlir::pass::options::parse_list_option::<#value_type>(__argument, __value)?),
+        (Some(_), true) => quote! {
+            ::mlir::pass::options::parse_list_option::<#value_type>(__argument, __value)?
+                .into_iter().map(|__value| __value.0).collect()
+        }
+    };
+    quote!(#argument => self.#ident = #value,)
+}
+
+/// Expand the printing of the option.
+fn expand_printer(option: &OptionSpec) -> TokenStream {
+    let OptionSpec { ident, argument, .. } = option;
+    match (&option.element, option.value_enum) {
+        (None, false) => quote!(__printer.option(#argument, &self.#ident)?;),
+        (None, true) => quote! {
+            __printer.option(#argument,
+                &::mlir::pass::options::EnumValue(::core::clone::Clone::clone(&self.#ident)))?;
+        },
+        (Some(_), false) => quote!(__printer.list_option(#argument, &self.#ident)?;),
+        (Some(_), true) => quote! {
+            __printer.list_option(#argument, &self.#ident.iter().cloned()
+                .map(::mlir::pass::options::EnumValue).collect::<::std::vec::Vec<_>>())?;
+        }
+    }
+}
+
+/// Expand the description of the option, printing its default value out of `__defaults`.
+fn expand_info(option: &OptionSpec) -> TokenStream {
+    let OptionSpec { ident, argument, description, .. } = option;
+    let value_type = option.value_type();
+    match (&option.element, option.value_enum) {
+        (None, false) => quote! {
+            ::mlir::pass::options::OptionInfo::scalar::<#value_type>(
+                #argument, #description, &__defaults.#ident)
+        },
+        (None, true) => quote! {
+            ::mlir::pass::options::OptionInfo::scalar::<#value_type>(
+                #argument, #description,
+                &::mlir::pass::options::EnumValue(__defaults.#ident))
+        },
+        (Some(_), false) => quote! {
+            ::mlir::pass::options::OptionInfo::list::<#value_type>(
+                #argument, #description, &__defaults.#ident)
+        },
+        (Some(_), true) => quote! {
+            ::mlir::pass::options::OptionInfo::list::<#value_type>(
+                #argument, #description, &__defaults.#ident.into_iter()
+                    .map(::mlir::pass::options::EnumValue).collect::<::std::vec::Vec<_>>())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use syn::parse_quote;
+
+    use super::*;
+
+    fn error(input: DeriveInput) -> String {
+        expand(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn parse_option_specifications() {
+        let field: syn::Field = parse_quote! {
+            /// Maximum number of iterations
+            /// when inlining within an SCC.
+            #[option(default = 4)]
+            max_iterations: u32
+        };
+        let option = OptionSpec::from_field(
+            field.ident.as_ref().unwrap(), &field.ty, &field.attrs).unwrap();
+        assert_eq!(option.argument, "max-iterations");
+        assert_eq!(option.description, "Maximum number of iterations when inlining within an SCC.");
+        assert_eq!(option.default.to_string(), "4");
+        assert!(option.element.is_none() && !option.value_enum);
+
+        let field: syn::Field = parse_quote! {
+            /// Ignored in favour of the description.
+            #[option(argument = "disable-patterns", description = "Disabled patterns.", value_enum)]
+            r#type: Vec<Pattern>
+        };
+        let option = OptionSpec::from_field(
+            field.ident.as_ref().unwrap(), &field.ty, &field.attrs).unwrap();
+        assert_eq!(option.argument, "disable-patterns");
+        assert_eq!(option.description, "Disabled patterns.");
+        assert_eq!(option.value_type().to_string(),
+                   ":: mlir :: pass :: options :: EnumValue < Pattern >");
+    }
+
+    #[test]
+    fn expand_sorted_options() {
+        let output = expand(parse_quote! {
+            pub struct InlinerOptions {
+                #[option(default = 4)]
+                max_iterations: u32,
+                #[option(argument = "default-pipeline", default = "canonicalize".to_string())]
+                default_pipeline: String,
+                op_pipelines: Vec<String>
+            }
+        }).unwrap().to_string();
+        assert!(output.contains("max_iterations : 4"));
+        assert!(output.contains("op_pipelines : < Vec < String > as :: core :: default :: Default > :: default ()"));
+        assert!(output.contains("parse_list_option :: < String > (__argument , __value) ?"));
+        let position = |argument: &str| output.find(&format!("\"{}\" =>", argument)).unwrap();
+        assert!(position("default-pipeline") < position("max-iterations"));
+        assert!(position("max-iterations") < position("op-pipelines"));
+    }
+
+    #[test]
+    fn expand_empty_options() {
+        let output = expand(parse_quote! {
+            pub struct NoOptions {}
+        }).unwrap().to_string();
+        assert!(output.contains("fn print (& self , os : & mut dyn :: core :: fmt :: Write) -> :: core :: fmt :: Result { :: core :: result :: Result :: Ok (()) }"));
+    }
+
+    #[test]
+    fn reject_invalid_options() {
+        assert_eq!(error(parse_quote! {
+            pub enum Options { A }
+        }), "PassOptions can only be derived for structs");
+        assert_eq!(error(parse_quote! {
+            pub struct Options(u32);
+        }), "PassOptions requires named fields");
+        assert_eq!(error(parse_quote! {
+            pub struct Options {
+                top_down: bool,
+                #[option(argument = "top-down")]
+                bottom_up: bool
+            }
+        }), "duplicate option 'top-down'");
+        assert_eq!(error(parse_quote! {
+            pub struct Options {
+                #[option(hidden)]
+                top_down: bool
+            }
+        }), "unknown option property");
+    }
+}
@@ -5,7 +5,10 @@
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Tools/mlir-opt/mlir_opt_main.cpp>
 */
 
-use clap::{ArgAction::SetTrue, Args, Parser, Subcommand, ValueEnum};
+use clap::{
+    ArgAction::SetTrue, Args, Command, CommandFactory, FromArgMatches, Parser, Subcommand,
+    ValueEnum
+};
 
 use llvm::{
     mlir::{
@@ -13,9 +16,16 @@ use llvm::{
         ir::{
             mlir_context::MLIRContext,
             dialect::registry::DialectRegistry,
+            diagnostics::{
+                SourceMgrDiagnosticHandler, SourceMgrDiagnosticVerifierHandler,
+                structured::{StructuredDiagnosticHandler, StructuredFormat}
+            },
             asm_state::{ParserConfig, FallbackAsmResourceMap, AsmState}
         },
-        pass::pass_manager::PassManager,
+        pass::{
+            pass_manager::PassManager,
+            pass_registry::PassPipelineCLParser
+        },
         support::timing,
     },
     llvm::support::source_manager::SourceManager
@@ -32,6 +42,7 @@ Perform the core processing behind `mlir-opt`:
 each chunk in an individual ModuleOp processed separately.
 - `verify_diagnostics` enables a verification mode where comments starting with
 `expected-(error|note|remark|warning)` are parsed in the input and matched against emitted diagnostics.
+- `diagnostics_format` is the format diagnostics are reported in, when not verifying them.
 - `verify_passes` enables the IR verifier in-between each pass in the pipeline.
 - `allow_unregistered_dialects` allows to parse and create operation without
 registering the Dialect in the MLIRContext.
@@ -48,6 +59,7 @@ pub fn mlir_opt_main(
     registry: &mut DialectRegistry,
     split_input_file: bool,
     verify_diagnostics: bool,
+    diagnostics_format: DiagnosticsFormat,
     verify_passes: bool,
     allow_unregistered_dialects: bool,
     preload_dialects_in_context: bool /* false */,
@@ -72,6 +84,7 @@ pub fn mlir_opt_main(
     registry: &mut DialectRegistry,
     split_input_file: bool,
     verify_diagnostics: bool,
+    diagnostics_format: DiagnosticsFormat,
     verify_passes: bool,
     allow_unregistered_dialects: bool,
     preload_dialects_in_context: bool,  //  = false,
@@ -107,7 +120,6 @@ pub fn mlir_opt_main(
     registerPassManagerCLOptions();
     registerDefaultTimingManagerCLOptions();
     DebugCounter::registerCLOptions();
-    PassPipelineCLParser pass_pipeline("", "Compiler passes to run", "p");
   
     // Build the list of dialects as a header for the --help message.
     let help_header = (tool_name + "\nAvailable Dialects: ").str();
@@ -119,7 +131,10 @@ pub fn mlir_opt_main(
     // Parse pass names in main to ensure static initialization completed.
     cl::ParseCommandLineOptions(argc, argv, help_header);
 
-    let mlir_opt = MLIROpt::parse();
+    let matches = command().get_matches();
+    let mlir_opt = MLIROpt::from_arg_matches(&matches)
+        .unwrap_or_else(|error| error.exit());
+    let pass_pipeline = PassPipelineCLParser::from_arg_matches(&matches)?;
   
     if mlir_opt.show_dialects {
         outs() << "Available Dialects:\n";
@@ -149,6 +164,7 @@ pub fn mlir_opt_main(
         pass_pipeline, registry,
         split_input_file,
         verify_diagnostics,
+        diagnostics_format,
         verify_passes,
         allow_unregistered_dialects,
         preload_dialects_in_context,
@@ -192,9 +208,9 @@ This typically parses the main source file, runs zero or more optimization passe
 */
 pub fn perform_actions(
     os: &raw_ostream,
-    verify_diagnostics: bool,
     verify_passes: bool,
-    source_manager: &std::shared_ptr<SourceManager>,
+    buffer: &str,
+    buffer_name: &str,
     context: *mut MLIRContext,
     pass_manager_setup_fn: PassPipelineFn,
     emit_bytecode: bool,
@@ -222,7 +238,7 @@ pub fn perform_actions(
     // Parse the input file and reset the context threading state.
     let parser_timing = timing.nest("Parser");
     let op =
-        parseSourceFileForTool(source_manager, config, implicit_module);
+        parse_source_file_for_tool(buffer, buffer_name, config, implicit_module);
     context.enable_multithreading(was_threading_enabled);
     if (!op) {
         return Err(());
@@ -262,8 +278,10 @@ it and print the result.
 */
 pub fn process_buffer(
     os: &raw_ostream,
-    owned_buffer: std::unique_ptr<MemoryBuffer>,
+    buffer: String,
+    buffer_name: &str,
     verify_diagnostics: bool,
+    diagnostics_format: DiagnosticsFormat,
     verify_passes: bool,
     allow_unregistered_dialects: bool,
     preload_dialects_in_context: bool,
@@ -274,9 +292,9 @@ pub fn process_buffer(
     thread_pool: *mut ThreadPool
 ) -> LogicalResult
 {
-    // Tell source_manager about this buffer, which is what the parser will pick up.
-    let source_manager = std::make_shared<SourceManager>();
-    source_manager.add_new_source_buffer(std::move(owned_buffer), SMLoc());
+    // Tell source_manager about this buffer, which is what the diagnostic handlers will pick up.
+    let mut source_manager = SourceManager::new();
+    source_manager.add_new_source_buffer(buffer.clone(), buffer_name);
 
     // Create a context just for the current buffer. Disable threading on creation
     // since we'll inject the thread-pool separately.
@@ -297,25 +315,39 @@ pub fn process_buffer(
 
     // If we are in verify diagnostics mode then we have a lot of work to do,
     // otherwise just perform the actions without worrying about it.
-    if (!verify_diagnostics) {
-        SourceManagerDiagnosticHandler SourceManagerHandler(*source_manager, &context);
-        return perform_actions(
-            os, verify_diagnostics, verify_passes, source_manager,
+    if !verify_diagnostics {
+        let format = match diagnostics_format {
+            DiagnosticsFormat::Text => {
+                let _source_manager_handler = SourceMgrDiagnosticHandler::new(source_manager, &context);
+                return perform_actions(
+                    os, verify_passes, &buffer, buffer_name,
+                    &context, pass_manager_setup_fn, emit_bytecode,
+                    implicit_module);
+            },
+            DiagnosticsFormat::Json => StructuredFormat::JsonLines,
+            DiagnosticsFormat::Sarif => StructuredFormat::Sarif
+        };
+        let structured_handler = StructuredDiagnosticHandler::new(&context, format);
+        structured_handler.set_tool_name("mlir-opt");
+        let result = perform_actions(
+            os, verify_passes, &buffer, buffer_name,
             &context, pass_manager_setup_fn, emit_bytecode,
             implicit_module);
+        structured_handler.finish()?;
+        return result;
     }
 
-    SourceManagerDiagnosticVerifierHandler SourceManagerHandler(*source_manager, &context);
+    let source_manager_handler = SourceMgrDiagnosticVerifierHandler::new(source_manager, &context);
 
     // Do any processing requested by command line flags.  We don't care whether
     // these actions succeed or fail, we only care what diagnostics they produce
     // and whether they match our expectations.
-    (void)perform_actions(os, verify_diagnostics, verify_passes, source_manager, &context,
+    let _ = perform_actions(os, verify_passes, &buffer, buffer_name, &context,
                         pass_manager_setup_fn, emit_bytecode, implicit_module);
 
     // Verify the diagnostic handler to make sure that each of the diagnostics
     // matched.
-    return SourceManagerHandler.verify();
+    source_manager_handler.verify()
 }
 
 LogicalResult
@@ -326,6 +358,7 @@ mlir::mlir_opt_main(
     registry: &mut DialectRegistry,
     split_input_file: bool,
     verify_diagnostics: bool,
+    diagnostics_format: DiagnosticsFormat,
     verify_passes: bool,
     allow_unregistered_dialects: bool,
     preload_dialects_in_context: bool,
@@ -350,7 +383,7 @@ mlir::mlir_opt_main(
         = |chunk_buffer: std::unique_ptr<MemoryBuffer>, os: &raw_ostream|
             process_buffer(
                 os, std::move(chunk_buffer), verify_diagnostics,
-                verify_passes, allow_unregistered_dialects,
+                diagnostics_format, verify_passes, allow_unregistered_dialects,
                 preload_dialects_in_context, emit_bytecode, implicit_module,
                 pass_manager_setup_fn, registry, thread_pool);
     };
@@ -366,6 +399,7 @@ LogicalResult mlir::mlir_opt_main(
     registry: &mut DialectRegistry,
     split_input_file: bool,
     verify_diagnostics: bool,
+    diagnostics_format: DiagnosticsFormat,
     verify_passes: bool,
     allow_unregistered_dialects: bool,
     preload_dialects_in_context: bool,
@@ -373,24 +407,19 @@ LogicalResult mlir::mlir_opt_main(
     implicit_module: bool,
     dump_pass_pipeline: bool
 ) {
-    let pass_manager_setup_fn = [&](PassManager &pm) {
-        let errorHandler = [&](const Twine &msg) {
-        emitError(UnknownLoc::get(pm.getContext())) << msg;
-        return Err(());
-        };
-        if (failed(pass_pipeline.addToPipeline(pm, errorHandler)))
-        return Err(());
-        if (dump_pass_pipeline) {
-        pm.dump();
-        errs() << "\n";
+    let pass_manager_setup_fn = |pm: &mut PassManager| -> LogicalResult {
+        pass_pipeline.add_to_pipeline(pm)?;
+        // Print the pipeline in its textual form, which can be given back to `--pass-pipeline`.
+        if dump_pass_pipeline {
+            pm.dump();
         }
-        return Ok(());
+        Ok(())
     };
     return mlir_opt_main(
         output_stream,
         std::move(buffer),
         pass_manager_setup_fn,
-        registry, split_input_file, verify_diagnostics, verify_passes,
+        registry, split_input_file, verify_diagnostics, diagnostics_format, verify_passes,
         allow_unregistered_dialects, preload_dialects_in_context,
         emit_bytecode, implicit_module);
 }
@@ -409,8 +438,11 @@ pub struct MLIROpt {
     #[arg(long, default_value_t = false)]
     split_input_file: bool,
     /// Check that emitted diagnostics match expected-* lines on the corresponding line.
-    #[arg(default_value_t = false)]
+    #[arg(long, default_value_t = false)]
     verify_diagnostics: bool,
+    /// The format diagnostics are reported in.
+    #[arg(long, value_enum, default_value_t = DiagnosticsFormat::Text)]
+    diagnostics_format: DiagnosticsFormat,
     /// Run the verifier after each transformation pass.
     #[arg(long = "verify-each", default_value_t = true)]
     verify_passes: bool,
@@ -430,3 +462,19 @@ pub struct MLIROpt {
     #[arg(long, default_value_t = false)]
     dump_pass_pipeline: bool
 }
+
+/// Return the command line of `mlir-opt`, with a flag per registered pass and pass pipeline, whose `--help` lists their options.
+fn command() -> Command {
+    PassPipelineCLParser::augment_args(MLIROpt::command(), "Compiler passes to run")
+}
+
+/// The formats `mlir-opt` can report diagnostics in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+pub enum DiagnosticsFormat {
+    /// Human-readable diagnostics, with the source line of their location.
+    Text,
+    /// One JSON object per diagnostic, each on its own line.
+    Json,
+    /// A SARIF log holding all of the diagnostics.
+    Sarif
+}
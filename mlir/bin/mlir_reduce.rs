@@ -1 +1,77 @@
+/*!
+# MLIR Reduce Driver Main
 
+This tool reduces a test case by running the reduction passes given on the command line, which are looked up in the pass registry, on the input file.
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Tools/mlir-reduce/MlirReduceMain.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Tools/mlir-reduce/MlirReduceMain.cpp>
+*/
+
+use std::{fs, io::Write};
+
+use clap::{Command, CommandFactory, FromArgMatches, Parser};
+
+use mlir::{
+    ir::{
+        asm_printer::print_operation,
+        asm_state::ParserConfig,
+        diagnostics::emit_error,
+        location::Location,
+        mlir_context::MLIRContext,
+        operation::support::PrintingFlags
+    },
+    parser::parse_source_file,
+    pass::{
+        pass_manager::{Nesting, PassManager},
+        pass_registry::PassPipelineCLParser
+    },
+    support::logical_result::LogicalResult
+};
+
+/// MLIR test case reduction tool.
+#[derive(Debug, Parser)]
+pub struct MLIRReduce {
+    /// <input file>
+    input_filename: String,
+    /// Output filename for the reduced test case.
+    #[arg(short = 'o', default_value = "-")]
+    output_filename: String
+}
+
+/// Return the command line of `mlir-reduce`, with a flag per registered pass and pass pipeline.
+fn command() -> Command {
+    PassPipelineCLParser::augment_args(MLIRReduce::command(), "Reduction Passes to Run")
+}
+
+/// Reduce the input file given on the command line with the reduction passes given on it, and write the result to the output file.
+pub fn mlir_reduce_main(context: &mut MLIRContext) -> LogicalResult {
+    let matches = command().get_matches();
+    let mlir_reduce = MLIRReduce::from_arg_matches(&matches)
+        .unwrap_or_else(|error| error.exit());
+    let parser = PassPipelineCLParser::from_arg_matches(&matches)?;
+
+    let module = {
+        let mut config = ParserConfig::new(context, true, None);
+        parse_source_file(&mlir_reduce.input_filename, &mut config)
+    };
+    let Some(module) = module else {
+        return Err(format!("failed to parse '{}'", mlir_reduce.input_filename).into());
+    };
+    let op = module.operation();
+
+    // Reduction pass pipeline.
+    let name = context.arena().op(op)?.name().as_str().to_string();
+    let mut pm = PassManager::new(name, Nesting::Explicit);
+    if let Err(error) = parser.add_to_pipeline(&mut pm) {
+        emit_error(context, Location::unknown(), error.to_string());
+        return Err(error);
+    }
+    pm.run(context, op)?;
+
+    let output = print_operation(context.arena(), op, PrintingFlags::default())?;
+    match mlir_reduce.output_filename.as_str() {
+        "-" => std::io::stdout().write_all(output.as_bytes())?,
+        filename => fs::write(filename, output)?
+    }
+    Ok(())
+}
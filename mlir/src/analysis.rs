@@ -6,4 +6,5 @@ pub mod call_graph;
 pub mod data_flow_framework;
 pub mod data_layout_analysis;
 pub mod liveness;
+pub mod presburger;
 pub mod slice_analysis;
@@ -0,0 +1,13 @@
+//! This file defines a library for Presburger arithmetic: integer relations and sets defined by affine constraints over integer variables, possibly with existentially quantified variables, and finite unions of them.
+//!
+//! The emptiness checks are exact over the integers, so the library can be used for the exact integer-set reasoning needed by dependence analysis, fusion legality checks and bounds tightening.
+//!
+//! - include <https://github.com/llvm/llvm-project/tree/main/mlir/include/mlir/Analysis/Presburger>
+//! - lib <https://github.com/llvm/llvm-project/tree/main/mlir/lib/Analysis/Presburger>
+
+pub mod fraction;
+pub mod integer_relation;
+pub mod matrix;
+pub mod presburger_relation;
+pub mod presburger_space;
+pub mod simplex;
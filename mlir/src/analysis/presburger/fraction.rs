@@ -0,0 +1,149 @@
+//! This is a simple class to represent fractions. It supports arithmetic, comparison and floor/ceil operations. The simplex uses it to represent the rational values it works with.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/Presburger/Fraction.h>
+
+use core::{
+    cmp::Ordering,
+    fmt,
+    ops::{Add, Div, Mul, Neg, Sub}
+};
+
+/// A class to represent fractions. The sign of the fraction is represented in the sign of the numerator; the denominator is always positive, and the fraction is always kept in its lowest terms, so that equal fractions compare equal structurally.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Fraction {
+    num: i128,
+    den: i128
+}
+
+fn gcd(lhs: i128, rhs: i128) -> i128 {
+    let (mut a, mut b) = (lhs.abs(), rhs.abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
+impl Fraction {
+    /// Construct the fraction `num / den`. The denominator is expected to be non-zero.
+    pub fn new(num: impl Into<i128>, den: impl Into<i128>) -> Self {
+        let (num, den) = (num.into(), den.into());
+        assert!(den != 0, "Fraction with a zero denominator");
+        let divisor = gcd(num, den) * den.signum();
+        Self { num: num / divisor, den: den / divisor }
+    }
+
+    pub const fn zero() -> Self {
+        Self { num: 0, den: 1 }
+    }
+
+    pub const fn one() -> Self {
+        Self { num: 1, den: 1 }
+    }
+
+    pub const fn num(&self) -> i128 {
+        self.num
+    }
+
+    pub const fn den(&self) -> i128 {
+        self.den
+    }
+
+    pub const fn is_integer(&self) -> bool {
+        self.den == 1
+    }
+
+    /// Return the largest integer not greater than the fraction.
+    pub fn floor(&self) -> i128 {
+        self.num.div_euclid(self.den)
+    }
+
+    /// Return the smallest integer not less than the fraction.
+    pub fn ceil(&self) -> i128 {
+        -(-self.num).div_euclid(self.den)
+    }
+
+    pub fn abs(&self) -> Self {
+        Self { num: self.num.abs(), den: self.den }
+    }
+}
+
+impl Default for Fraction {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl From<i64> for Fraction {
+    fn from(value: i64) -> Self {
+        Self { num: value.into(), den: 1 }
+    }
+}
+
+impl From<i128> for Fraction {
+    fn from(value: i128) -> Self {
+        Self { num: value, den: 1 }
+    }
+}
+
+impl Ord for Fraction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Denominators are positive, so cross-multiplying preserves the order.
+        (self.num * other.den).cmp(&(other.num * self.den))
+    }
+}
+
+impl PartialOrd for Fraction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Add for Fraction {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self::Output {
+        Self::new(self.num * other.den + other.num * self.den, self.den * other.den)
+    }
+}
+
+impl Sub for Fraction {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self::Output {
+        self + (-other)
+    }
+}
+
+impl Mul for Fraction {
+    type Output = Self;
+
+    fn mul(self, other: Self) -> Self::Output {
+        Self::new(self.num * other.num, self.den * other.den)
+    }
+}
+
+impl Div for Fraction {
+    type Output = Self;
+
+    fn div(self, other: Self) -> Self::Output {
+        Self::new(self.num * other.den, self.den * other.num)
+    }
+}
+
+impl Neg for Fraction {
+    type Output = Self;
+
+    fn neg(self) -> Self::Output {
+        Self { num: -self.num, den: self.den }
+    }
+}
+
+impl fmt::Display for Fraction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_integer() {
+            write!(f, "{}", self.num)
+        } else {
+            write!(f, "{}/{}", self.num, self.den)
+        }
+    }
+}
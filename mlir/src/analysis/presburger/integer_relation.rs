@@ -0,0 +1,1057 @@
+//! A class to represent a relation over integer tuples. A relation is represented as a constraint system over a space of tuples of integer valued variables supporting symbolic variables and existential quantification.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/Presburger/IntegerRelation.h>
+//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Analysis/Presburger/IntegerRelation.cpp>
+
+use core::{
+    fmt,
+    ops::{Deref, DerefMut}
+};
+
+use crate::{
+    analysis::presburger::{
+        matrix::Matrix,
+        presburger_space::{PresburgerSpace, VarKind},
+        simplex::{Direction, MaybeOptimum, Simplex}
+    },
+    support::math_extras::{floor_div, gcd}
+};
+
+/// The type of bound: equal, lower bound or upper bound.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundType {
+    EQ,
+    LB,
+    UB
+}
+
+/**
+An IntegerRelation represents the set of points from a PresburgerSpace that satisfy a list of affine constraints. Affine constraints can be inequalities or equalities in the form:
+
+```text
+Inequality: c_0*x_0 + c_1*x_1 + .... + c_{n-1}*x_{n-1} + c_n >= 0
+Equality  : c_0*x_0 + c_1*x_1 + .... + c_{n-1}*x_{n-1} + c_n == 0
+```
+
+where c_0, c_1, ..., c_n are integers and n is the total number of variables in the space.
+
+Such a relation corresponds to the set of integer points lying in a convex polyhedron. For example, consider the relation: `(x) -> (y) : (1 <= x <= 7, x = 2y)`. These can be thought of as the integer points in the polyhedron `{(x, y) | 1 <= x <= 7, x = 2y}`, which are `(2, 1)`, `(4, 2)` and `(6, 3)`.
+
+Constraints are stored as rows of a matrix, with one column per variable, in the order of the space, followed by a column for the constant term.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegerRelation {
+    space: PresburgerSpace,
+    /// Coefficients of affine equalities (in == 0 form).
+    equalities: Matrix,
+    /// Coefficients of affine inequalities (in >= 0 form).
+    inequalities: Matrix
+}
+
+impl IntegerRelation {
+    /// Constructs a relation with the specified space and no constraints, i.e. the universe of the space.
+    pub fn new(space: PresburgerSpace) -> Self {
+        Self {
+            space,
+            equalities: Matrix::new(0, space.num_vars() + 1),
+            inequalities: Matrix::new(0, space.num_vars() + 1)
+        }
+    }
+
+    /// Return a system with no constraints, i.e., one which is satisfied by all points.
+    pub fn universe(space: PresburgerSpace) -> Self {
+        Self::new(space)
+    }
+
+    /// Return an empty system containing an invalid equation `0 = 1`.
+    pub fn empty(space: PresburgerSpace) -> Self {
+        let mut relation = Self::new(space);
+        let mut invalid = vec![0; space.num_vars() + 1];
+        invalid[space.num_vars()] = 1;
+        relation.add_equality(&invalid);
+        relation
+    }
+
+    pub fn space(&self) -> &PresburgerSpace {
+        &self.space
+    }
+
+    pub fn num_domain_vars(&self) -> usize {
+        self.space.num_domain_vars()
+    }
+
+    pub fn num_range_vars(&self) -> usize {
+        self.space.num_range_vars()
+    }
+
+    pub fn num_symbol_vars(&self) -> usize {
+        self.space.num_symbol_vars()
+    }
+
+    pub fn num_local_vars(&self) -> usize {
+        self.space.num_local_vars()
+    }
+
+    pub fn num_dim_vars(&self) -> usize {
+        self.space.num_dim_vars()
+    }
+
+    pub fn num_dim_and_symbol_vars(&self) -> usize {
+        self.space.num_dim_and_symbol_vars()
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.space.num_vars()
+    }
+
+    /// Returns the number of columns in the constraint system, i.e. the number of variables plus one for the constant term.
+    pub fn num_cols(&self) -> usize {
+        self.space.num_vars() + 1
+    }
+
+    pub fn num_equalities(&self) -> usize {
+        self.equalities.num_rows()
+    }
+
+    pub fn num_inequalities(&self) -> usize {
+        self.inequalities.num_rows()
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.num_equalities() + self.num_inequalities()
+    }
+
+    pub fn equalities(&self) -> &Matrix {
+        &self.equalities
+    }
+
+    pub fn inequalities(&self) -> &Matrix {
+        &self.inequalities
+    }
+
+    pub fn equality(&self, index: usize) -> &[i64] {
+        self.equalities.row(index)
+    }
+
+    pub fn inequality(&self, index: usize) -> &[i64] {
+        self.inequalities.row(index)
+    }
+
+    /// Adds an equality from the coefficients specified in `eq`.
+    pub fn add_equality(&mut self, eq: &[i64]) {
+        self.equalities.append_extra_row(eq);
+    }
+
+    /// Adds an inequality (>= 0) from the coefficients specified in `in_eq`.
+    pub fn add_inequality(&mut self, in_eq: &[i64]) {
+        self.inequalities.append_extra_row(in_eq);
+    }
+
+    /// Adds a constant bound for the specified variable.
+    pub fn add_bound(&mut self, bound_type: BoundType, position: usize, value: i64) {
+        assert!(position < self.num_vars(), "Invalid position");
+        let mut row = vec![0; self.num_cols()];
+        match bound_type {
+            BoundType::EQ | BoundType::LB => {
+                row[position] = 1;
+                row[self.num_vars()] = -value;
+            },
+            BoundType::UB => {
+                row[position] = -1;
+                row[self.num_vars()] = value;
+            }
+        }
+        match bound_type {
+            BoundType::EQ => self.add_equality(&row),
+            BoundType::LB | BoundType::UB => self.add_inequality(&row)
+        }
+    }
+
+    pub fn remove_equality(&mut self, index: usize) {
+        self.equalities.remove_row(index);
+    }
+
+    pub fn remove_inequality(&mut self, index: usize) {
+        self.inequalities.remove_row(index);
+    }
+
+    /// Remove all the constraints of the relation.
+    pub fn clear_constraints(&mut self) {
+        self.equalities = Matrix::new(0, self.num_cols());
+        self.inequalities = Matrix::new(0, self.num_cols());
+    }
+
+    /// Insert `num` variables of the specified kind at position `position` among the variables of that kind. The coefficients of the new variables are initialised to zero. Returns the absolute column position of the first variable inserted.
+    pub fn insert_var(&mut self, kind: VarKind, position: usize, num: usize) -> usize {
+        let absolute_position = self.space.insert_var(kind, position, num);
+        self.equalities.insert_columns(absolute_position, num);
+        self.inequalities.insert_columns(absolute_position, num);
+        absolute_position
+    }
+
+    /// Append `num` variables of the specified kind after the last variable of that kind. Returns the absolute column position of the first variable appended.
+    pub fn append_var(&mut self, kind: VarKind, num: usize) -> usize {
+        self.insert_var(kind, self.space.num_vars_of_kind(kind), num)
+    }
+
+    /// Removes the variable at the absolute position `position`, along with its coefficients in the constraints.
+    pub fn remove_var(&mut self, position: usize) {
+        let kind = self.space.var_kind_at(position);
+        let relative_position = position - self.space.var_kind_offset(kind);
+        self.remove_var_range(kind, relative_position, relative_position + 1);
+    }
+
+    /// Removes the variables of the specified kind in the range `[start, end)`, relative to the variables of that kind.
+    pub fn remove_var_range(&mut self, kind: VarKind, start: usize, end: usize) {
+        let offset = self.space.var_kind_offset(kind);
+        self.space.remove_var_range(kind, start, end);
+        self.equalities.remove_columns(offset + start, end - start);
+        self.inequalities.remove_columns(offset + start, end - start);
+    }
+
+    /// Converts the variables of kind `source_kind` in the range `[start, end)` to variables of kind `target_kind`, appended after the existing variables of that kind. Converting variables to locals existentially quantifies them.
+    pub fn convert_var_kind(
+        &mut self,
+        source_kind: VarKind,
+        start: usize,
+        end: usize,
+        target_kind: VarKind
+    ) {
+        let num = end - start;
+        if num == 0 {
+            return;
+        }
+        let offset = self.space.var_kind_offset(source_kind) + start;
+        let columns = |matrix: &Matrix| matrix.rows()
+            .map(|row| row[offset..offset + num].to_vec())
+            .collect::<Vec<_>>();
+        let equality_columns = columns(&self.equalities);
+        let inequality_columns = columns(&self.inequalities);
+        self.remove_var_range(source_kind, start, end);
+        let position = self.append_var(target_kind, num);
+        for (index, values) in equality_columns.into_iter().enumerate() {
+            self.equalities.row_mut(index)[position..position + num].copy_from_slice(&values);
+        }
+        for (index, values) in inequality_columns.into_iter().enumerate() {
+            self.inequalities.row_mut(index)[position..position + num].copy_from_slice(&values);
+        }
+    }
+
+    /// Existentially quantify the variables of the specified kind in the range `[start, end)`, by converting them to locals.
+    pub fn convert_to_local(&mut self, kind: VarKind, start: usize, end: usize) {
+        self.convert_var_kind(kind, start, end, VarKind::Local);
+    }
+
+    /// Return the intersection of the two relations. The local variables of both relations are kept, those of `other` after those of `self`. The relations are expected to have compatible spaces.
+    pub fn intersect(&self, other: &Self) -> Self {
+        assert!(self.space.is_compatible(&other.space), "Spaces should be compatible");
+        let mut result = self.clone();
+        let num_other_locals = other.num_local_vars();
+        let local_offset = result.append_var(VarKind::Local, num_other_locals);
+        let num_non_locals = self.num_dim_and_symbol_vars();
+        let num_vars = result.num_vars();
+        let map_row = |row: &[i64]| {
+            let mut mapped = vec![0; num_vars + 1];
+            mapped[..num_non_locals].copy_from_slice(&row[..num_non_locals]);
+            mapped[local_offset..local_offset + num_other_locals]
+                .copy_from_slice(&row[num_non_locals..num_non_locals + num_other_locals]);
+            mapped[num_vars] = row[row.len() - 1];
+            mapped
+        };
+        for equality in other.equalities.rows() {
+            result.add_equality(&map_row(equality));
+        }
+        for inequality in other.inequalities.rows() {
+            result.add_inequality(&map_row(inequality));
+        }
+        result
+    }
+
+    /**
+    Adds a new local variable as the floordiv of an affine function of other variables, the coefficients of which are provided in `dividend`, with one coefficient per existing variable followed by the constant term. The variable `q = dividend floordiv divisor` is defined by the constraints `divisor * q <= dividend <= divisor * q + divisor - 1`. Returns the absolute position of the new variable.
+    */
+    pub fn add_local_floor_div(&mut self, dividend: &[i64], divisor: i64) -> usize {
+        assert_eq!(dividend.len(), self.num_cols(), "Incorrect dividend size");
+        assert!(divisor > 0, "Positive divisor expected");
+        let position = self.append_var(VarKind::Local, 1);
+        let mut dividend = dividend.to_vec();
+        dividend.insert(position, 0);
+
+        // dividend - divisor * q >= 0
+        let mut lower = dividend.clone();
+        lower[position] = -divisor;
+        self.add_inequality(&lower);
+        // -dividend + divisor * q + divisor - 1 >= 0
+        let mut upper = dividend.iter().map(|coeff| -coeff).collect::<Vec<_>>();
+        upper[position] = divisor;
+        *upper.last_mut().unwrap() += divisor - 1;
+        self.add_inequality(&upper);
+        position
+    }
+
+    /**
+    Returns the division representation of the local variable `local` (relative to the locals), as a dividend and a positive divisor such that the local is `dividend floordiv divisor`, if the constraints define it in this way. The dividend has one coefficient per variable followed by the constant term, and a zero coefficient for the local itself.
+
+    The division is found either from an equality `divisor * q = dividend`, or from a pair of inequalities `divisor * q <= dividend <= divisor * q + divisor - 1`.
+    */
+    pub fn local_division(&self, local: usize) -> Option<(Vec<i64>, i64)> {
+        assert!(local < self.num_local_vars(), "Invalid local variable");
+        let position = self.space.var_kind_offset(VarKind::Local) + local;
+
+        for equality in self.equalities.rows() {
+            let coeff = equality[position];
+            if coeff == 0 {
+                continue;
+            }
+            // Normalise the equality to `dividend - divisor * q = 0`.
+            let mut dividend = equality.iter().map(|value| value * -coeff.signum())
+                .collect::<Vec<_>>();
+            dividend[position] = 0;
+            return Some((dividend, coeff.abs()));
+        }
+
+        for lower in self.inequalities.rows().filter(|row| row[position] < 0) {
+            let divisor = -lower[position];
+            let is_upper = |upper: &&[i64]| upper.iter().zip(lower).enumerate()
+                .all(|(column, (upper, lower))| upper + lower == match column {
+                    column if column == self.num_vars() => divisor - 1,
+                    _ => 0
+                });
+            if self.inequalities.rows().any(|upper| is_upper(&upper)) {
+                let mut dividend = lower.to_vec();
+                dividend[position] = 0;
+                return Some((dividend, divisor));
+            }
+        }
+        None
+    }
+
+    /// Returns the division representations of all the local variables, if each of them has one and they do not depend on each other cyclically.
+    pub fn local_divisions(&self) -> Option<Vec<(Vec<i64>, i64)>> {
+        let divisions = (0..self.num_local_vars())
+            .map(|local| self.local_division(local))
+            .collect::<Option<Vec<_>>>()?;
+        let local_offset = self.space.var_kind_offset(VarKind::Local);
+        let mut resolved = vec![false; divisions.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (local, (dividend, _)) in divisions.iter().enumerate() {
+                if resolved[local] {
+                    continue;
+                }
+                let depends_on_unresolved = (0..divisions.len()).any(|other|
+                    dividend[local_offset + other] != 0 && !resolved[other]);
+                if !depends_on_unresolved {
+                    resolved[local] = true;
+                    changed = true;
+                }
+            }
+        }
+        resolved.iter().all(|resolved| *resolved).then_some(divisions)
+    }
+
+    /// Returns true if every local variable has a division representation.
+    pub fn has_only_div_locals(&self) -> bool {
+        self.local_divisions().is_some()
+    }
+
+    /**
+    Normalises the constraints by the GCD of their coefficients, tightening the inequalities: for an inequality `a . x + c >= 0` where the coefficients `a` have a GCD `g` greater than one, the integer points satisfy `(a / g) . x + floor(c / g) >= 0`.
+    */
+    pub fn gcd_tighten_inequalities(&mut self) {
+        let num_vars = self.num_vars();
+        for row in 0..self.num_inequalities() {
+            let inequality = self.inequalities.row_mut(row);
+            let divisor = inequality[..num_vars].iter()
+                .fold(0, |divisor, coeff| gcd(divisor, *coeff));
+            if divisor <= 1 {
+                continue;
+            }
+            for coeff in &mut inequality[..num_vars] {
+                *coeff /= divisor;
+            }
+            inequality[num_vars] = floor_div(inequality[num_vars], divisor);
+        }
+    }
+
+    /// Normalises each constraint by the GCD of its coefficients and constant term.
+    pub fn normalize_constraints_by_gcd(&mut self) {
+        for row in 0..self.num_equalities() {
+            self.equalities.normalize_row(row);
+        }
+        for row in 0..self.num_inequalities() {
+            self.inequalities.normalize_row(row);
+        }
+    }
+
+    /// Tighten and normalise the constraints, and remove duplicate and trivially true constraints.
+    pub fn simplify(&mut self) {
+        self.gcd_tighten_inequalities();
+        self.normalize_constraints_by_gcd();
+        let num_vars = self.num_vars();
+        let is_trivial = |row: &[i64]| row[..num_vars].iter().all(|coeff| *coeff == 0);
+        let mut equalities = Vec::<Vec<i64>>::new();
+        for equality in self.equalities.rows() {
+            let negated = equality.iter().map(|coeff| -coeff).collect::<Vec<_>>();
+            if (is_trivial(equality) && equality[num_vars] == 0)
+                || equalities.iter().any(|other| *other == equality || *other == negated)
+            {
+                continue;
+            }
+            equalities.push(equality.to_vec());
+        }
+        let mut inequalities = Vec::<Vec<i64>>::new();
+        for inequality in self.inequalities.rows() {
+            if (is_trivial(inequality) && inequality[num_vars] >= 0)
+                || inequalities.iter().any(|other| *other == inequality)
+            {
+                continue;
+            }
+            inequalities.push(inequality.to_vec());
+        }
+        self.clear_constraints();
+        for equality in &equalities {
+            self.add_equality(equality);
+        }
+        for inequality in &inequalities {
+            self.add_inequality(inequality);
+        }
+    }
+
+    /// Checks all rows of equality/inequality constraints for trivial contradictions (for example: 1 == 0, 0 >= 1), which may have surfaced after elimination.
+    pub fn is_obviously_empty(&self) -> bool {
+        let num_vars = self.num_vars();
+        self.equalities.rows().any(|row|
+            row[..num_vars].iter().all(|coeff| *coeff == 0) && row[num_vars] != 0)
+        || self.inequalities.rows().any(|row|
+            row[..num_vars].iter().all(|coeff| *coeff == 0) && row[num_vars] < 0)
+    }
+
+    /// Runs the GCD test on all equality constraints. Returns true if this test fails on any equality, i.e. if the GCD of the coefficients of an equality does not divide its constant term, so that it has no integer solution.
+    pub fn is_empty_by_gcd_test(&self) -> bool {
+        let num_vars = self.num_vars();
+        self.equalities.rows().any(|row| {
+            let divisor = row[..num_vars].iter().fold(0, |divisor, coeff| gcd(divisor, *coeff));
+            divisor != 0 && row[num_vars] % divisor != 0
+        })
+    }
+
+    /**
+    Checks for emptiness of the set by eliminating variables successively and using the GCD test (on all equality constraints) and checking for trivially invalid constraints. Returns true if the relation has no integer point, with the local variables existentially quantified.
+
+    The check is exact over the integers. The equalities are eliminated with unimodular changes of variables, and the variables of the inequalities with the Omega test: Fourier-Motzkin elimination when it is exact over the integers, and otherwise the real and dark shadows, and the splinters between them.
+    */
+    pub fn is_empty(&self) -> bool {
+        if self.is_obviously_empty() || self.is_empty_by_gcd_test() {
+            return true;
+        }
+        if Simplex::from_relation(self).is_empty() {
+            return true;
+        }
+        is_integer_empty(
+            self.equalities.rows().map(<[i64]>::to_vec).collect(),
+            self.inequalities.rows().map(<[i64]>::to_vec).collect())
+    }
+
+    /// Returns true if the point, with one value per dimension and symbol variable, is contained in the relation, for some values of the local variables.
+    pub fn contains_point(&self, point: &[i64]) -> bool {
+        assert_eq!(point.len(), self.num_dim_and_symbol_vars(), "Incorrect point size");
+        let mut relation = self.clone();
+        for (position, value) in point.iter().enumerate() {
+            relation.add_bound(BoundType::EQ, position, *value);
+        }
+        !relation.is_empty()
+    }
+
+    /// Find an integer sample point, with one value per variable, locals included, if the relation is not empty.
+    pub fn find_integer_sample(&self) -> Option<Vec<i64>> {
+        // Treat every variable as a set dimension, so that locals get a value as well.
+        let relation = Self {
+            space: PresburgerSpace::set(self.num_vars(), 0, 0),
+            ..self.clone()
+        };
+        relation.find_integer_lex_min().bounded()
+    }
+
+    /// Return the integer lexicographic minimum of the dimension and symbol variables, the local variables being existentially quantified.
+    pub fn find_integer_lex_min(&self) -> MaybeOptimum<Vec<i64>> {
+        self.find_integer_lex_opt(Direction::Down)
+    }
+
+    /// Return the integer lexicographic maximum of the dimension and symbol variables, the local variables being existentially quantified.
+    pub fn find_integer_lex_max(&self) -> MaybeOptimum<Vec<i64>> {
+        self.find_integer_lex_opt(Direction::Up)
+    }
+
+    /// Optimise the variables one after the other, fixing each one to its optimum before optimising the next one.
+    fn find_integer_lex_opt(&self, direction: Direction) -> MaybeOptimum<Vec<i64>> {
+        if self.is_empty() {
+            return MaybeOptimum::Empty;
+        }
+        let mut relation = self.clone();
+        let mut point = Vec::with_capacity(self.num_dim_and_symbol_vars());
+        for position in 0..self.num_dim_and_symbol_vars() {
+            let value = match relation.integer_optimum(direction, position) {
+                MaybeOptimum::Empty => unreachable!("The relation is known to be non-empty"),
+                MaybeOptimum::Unbounded => return MaybeOptimum::Unbounded,
+                MaybeOptimum::Bounded(value) => value
+            };
+            relation.add_bound(BoundType::EQ, position, value);
+            point.push(value);
+        }
+        MaybeOptimum::Bounded(point)
+    }
+
+    /**
+    Returns the tightest constant bound of the given type of the variable at position `position`, over the integer points of the relation. For `BoundType::EQ`, the bound is returned if the variable has a single value. Returns `None` if the relation is empty or the variable is unbounded.
+    */
+    pub fn constant_bound(&self, bound_type: BoundType, position: usize) -> Option<i64> {
+        match bound_type {
+            BoundType::LB => self.integer_optimum(Direction::Down, position).bounded(),
+            BoundType::UB => self.integer_optimum(Direction::Up, position).bounded(),
+            BoundType::EQ => {
+                let lower = self.constant_bound(BoundType::LB, position)?;
+                let upper = self.constant_bound(BoundType::UB, position)?;
+                (lower == upper).then_some(lower)
+            }
+        }
+    }
+
+    /**
+    Returns the integer optimum of the variable at position `position` in the given direction.
+
+    The optimum of the rational relaxation bounds the integer optimum, which is then found by searching for the tightest bound that keeps the relation non-empty. If the rational relaxation is unbounded and the relation has integer points, the integer points are unbounded too, as the integer hull of a rational polyhedron has the same recession cone.
+    */
+    fn integer_optimum(&self, direction: Direction, position: usize) -> MaybeOptimum<i64> {
+        if self.is_empty() {
+            return MaybeOptimum::Empty;
+        }
+        // Minimise `sign * x`, so that maximising is minimising `-x`.
+        let sign = match direction {
+            Direction::Down => 1,
+            Direction::Up => -1
+        };
+        let mut objective = vec![0; self.num_cols()];
+        objective[position] = sign;
+        let rational_minimum = match Simplex::from_relation(self)
+            .compute_optimum(Direction::Down, &objective)
+        {
+            MaybeOptimum::Empty => return MaybeOptimum::Empty,
+            MaybeOptimum::Unbounded => return MaybeOptimum::Unbounded,
+            MaybeOptimum::Bounded(minimum) => minimum
+        };
+
+        // Whether some integer point has `sign * x <= bound`.
+        let is_feasible = |bound: i64| {
+            let mut relation = self.clone();
+            let mut row = objective.iter().map(|coeff| -coeff).collect::<Vec<_>>();
+            row[self.num_vars()] = bound;
+            relation.add_inequality(&row);
+            !relation.is_empty()
+        };
+        // `low` is infeasible, and `high` is searched for with exponentially growing steps.
+        let mut low = rational_minimum.ceil() as i64 - 1;
+        let mut step = 1;
+        let mut high = low + step;
+        while !is_feasible(high) {
+            low = high;
+            step *= 2;
+            high = low + step;
+        }
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            if is_feasible(middle) {
+                high = middle;
+            } else {
+                low = middle;
+            }
+        }
+        MaybeOptimum::Bounded(sign * high)
+    }
+
+    /**
+    Eliminates the variable at the absolute position `position` using Fourier-Motzkin elimination, or Gaussian elimination if an equality involves it. The result is the rational projection of the relation, its real shadow. Returns true if the projection is exact over the integers, i.e. if every integer point of the result is the projection of an integer point of the relation.
+    */
+    pub fn fourier_motzkin_eliminate(&mut self, position: usize) -> bool {
+        assert!(position < self.num_vars(), "Invalid position");
+        let num_vars = self.num_vars();
+        let mut equalities = self.equalities.rows().map(<[i64]>::to_vec).collect::<Vec<_>>();
+        let mut inequalities = self.inequalities.rows().map(<[i64]>::to_vec).collect::<Vec<_>>();
+
+        let exact = match equalities.iter().position(|row| row[position] != 0) {
+            // Gaussian elimination: substitute the variable using the equality.
+            Some(index) => {
+                let pivot = equalities.remove(index);
+                let coeff = pivot[position];
+                for row in equalities.iter_mut().chain(inequalities.iter_mut()) {
+                    let factor = row[position];
+                    if factor == 0 {
+                        continue;
+                    }
+                    // Scaling by the absolute value of the pivot preserves the direction of inequalities.
+                    for (value, pivot_value) in row.iter_mut().zip(&pivot) {
+                        *value = *value * coeff.abs() - factor * coeff.signum() * pivot_value;
+                    }
+                }
+                coeff.abs() == 1
+            },
+            None => {
+                let (lower, upper, rest) = partition_bounds(inequalities, position);
+                let exact = lower.iter().all(|row| row[position] == 1)
+                    || upper.iter().all(|row| row[position] == -1);
+                inequalities = rest;
+                inequalities.extend(combine_bounds(&lower, &upper, position, false));
+                exact
+            }
+        };
+
+        self.clear_constraints();
+        for row in &equalities {
+            self.add_equality(row);
+        }
+        for row in &inequalities {
+            self.add_inequality(row);
+        }
+        self.remove_var(position);
+        debug_assert_eq!(self.num_vars(), num_vars - 1);
+        self.simplify();
+        exact
+    }
+
+    /**
+    Projects out the variables of the specified kind in the range `[start, end)`. The projection is exact over the integers: the variables are existentially quantified, and then eliminated wherever the elimination is exact.
+    */
+    pub fn project_out(&mut self, kind: VarKind, start: usize, end: usize) {
+        if kind != VarKind::Local {
+            self.convert_to_local(kind, start, end);
+        }
+        self.remove_redundant_local_vars();
+    }
+
+    /**
+    Eliminates the local variables which can be eliminated exactly over the integers: locals which appear in no constraint, locals with a unit coefficient in an equality, and locals for which Fourier-Motzkin elimination is exact.
+    */
+    pub fn remove_redundant_local_vars(&mut self) {
+        self.simplify();
+        let mut local = 0;
+        while local < self.num_local_vars() {
+            let position = self.space.var_kind_offset(VarKind::Local) + local;
+            let in_equality = self.equalities.rows().find(|row| row[position] != 0);
+            let removable = match in_equality {
+                Some(row) => row[position].abs() == 1,
+                None => self.inequalities.rows().all(|row| row[position] <= 1)
+                    || self.inequalities.rows().all(|row| row[position] >= -1)
+            };
+            if removable {
+                let exact = self.fourier_motzkin_eliminate(position);
+                debug_assert!(exact);
+            } else {
+                local += 1;
+            }
+        }
+    }
+}
+
+impl fmt::Display for IntegerRelation {
+    /// Prints the space, and then one constraint per line as its coefficients followed by `= 0` or `>= 0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Domain: {}, Range: {}, Symbols: {}, Locals: {}",
+            self.num_domain_vars(), self.num_range_vars(), self.num_symbol_vars(),
+            self.num_local_vars())?;
+        writeln!(f, "{} constraints", self.num_constraints())?;
+        for (matrix, relation) in [(&self.equalities, "="), (&self.inequalities, ">=")] {
+            for row in matrix.rows() {
+                for value in row {
+                    write!(f, "{} ", value)?;
+                }
+                writeln!(f, "{} 0", relation)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// Integer Emptiness
+// ----------------------------------------------------------------------
+
+/// Split the inequalities into the lower bounds of the variable at `position`, its upper bounds, and the inequalities which do not involve it.
+fn partition_bounds(inequalities: Vec<Vec<i64>>, position: usize)
+    -> (Vec<Vec<i64>>, Vec<Vec<i64>>, Vec<Vec<i64>>)
+{
+    let mut lower = Vec::new();
+    let mut upper = Vec::new();
+    let mut rest = Vec::new();
+    for row in inequalities {
+        match row[position].signum() {
+            1 => lower.push(row),
+            -1 => upper.push(row),
+            _ => rest.push(row)
+        }
+    }
+    (lower, upper, rest)
+}
+
+/**
+Combine each lower bound `a * x + l >= 0` of the variable at `position` with each upper bound `-b * x + u >= 0` into `b * l + a * u >= 0`, which is the real shadow. The dark shadow `b * l + a * u >= (a - 1) * (b - 1)` only contains points above which an integer value of the variable fits between the bounds.
+*/
+fn combine_bounds(lower: &[Vec<i64>], upper: &[Vec<i64>], position: usize, dark: bool)
+    -> Vec<Vec<i64>>
+{
+    let mut combined = Vec::with_capacity(lower.len() * upper.len());
+    for lower in lower {
+        for upper in upper {
+            let a = lower[position];
+            let b = -upper[position];
+            let mut row = lower.iter().zip(upper)
+                .map(|(lower, upper)| b * lower + a * upper)
+                .collect::<Vec<_>>();
+            if dark {
+                *row.last_mut().unwrap() -= (a - 1) * (b - 1);
+            }
+            combined.push(row);
+        }
+    }
+    combined
+}
+
+/// Returns `(g, s, t)` such that `s * a + t * b = g`, where `g` is the non-negative GCD of `a` and `b`.
+fn extended_gcd(a: i64, b: i64) -> (i64, i64, i64) {
+    let (mut old_r, mut r) = (a, b);
+    let (mut old_s, mut s) = (1, 0);
+    let (mut old_t, mut t) = (0, 1);
+    while r != 0 {
+        let quotient = old_r / r;
+        (old_r, r) = (r, old_r - quotient * r);
+        (old_s, s) = (s, old_s - quotient * s);
+        (old_t, t) = (t, old_t - quotient * t);
+    }
+    if old_r < 0 {
+        (-old_r, -old_s, -old_t)
+    } else {
+        (old_r, old_s, old_t)
+    }
+}
+
+/**
+Normalise the constraints by the GCD of their coefficients, removing the constraints without variables. Returns false if a constraint is found to have no integer solution.
+*/
+fn normalize_rows(equalities: &mut Vec<Vec<i64>>, inequalities: &mut Vec<Vec<i64>>) -> bool {
+    let mut feasible = true;
+    equalities.retain_mut(|row| {
+        let last = row.len() - 1;
+        let (coeffs, constant) = row.split_at_mut(last);
+        let divisor = coeffs.iter().fold(0, |divisor, coeff| gcd(divisor, *coeff));
+        if divisor == 0 || constant[0] % divisor != 0 {
+            feasible &= divisor == 0 && constant[0] == 0;
+            return false;
+        }
+        for value in row.iter_mut() {
+            *value /= divisor;
+        }
+        true
+    });
+    inequalities.retain_mut(|row| {
+        let last = row.len() - 1;
+        let (coeffs, constant) = row.split_at_mut(last);
+        let divisor = coeffs.iter().fold(0, |divisor, coeff| gcd(divisor, *coeff));
+        if divisor == 0 {
+            feasible &= constant[0] >= 0;
+            return false;
+        }
+        for coeff in coeffs.iter_mut() {
+            *coeff /= divisor;
+        }
+        constant[0] = floor_div(constant[0], divisor);
+        true
+    });
+    feasible
+}
+
+/**
+Returns true if the constraints, all of whose variables are existentially quantified, have no integer solution.
+
+Equalities are eliminated first. An equality with a unit coefficient is used to substitute its variable. Otherwise, a unimodular change of two of its variables, which maps integer points to integer points, replaces them with their GCD and a new variable, until a unit coefficient appears.
+
+Inequalities are then eliminated one variable at a time, following the Omega test. A variable which is only bounded on one side is dropped with its constraints. When Fourier-Motzkin elimination is exact, the real shadow replaces the constraints. Otherwise, the system is empty if the real shadow is, non-empty if the dark shadow is not, and in between, non-empty if and only if one of the splinters, the slices of the system close to a lower bound, is.
+*/
+fn is_integer_empty(mut equalities: Vec<Vec<i64>>, mut inequalities: Vec<Vec<i64>>) -> bool {
+    loop {
+        if !normalize_rows(&mut equalities, &mut inequalities) {
+            return true;
+        }
+
+        if let Some(mut equality) = equalities.pop() {
+            let num_vars = equality.len() - 1;
+            match (0..num_vars).find(|var| equality[*var].abs() == 1) {
+                Some(var) => {
+                    for row in equalities.iter_mut().chain(inequalities.iter_mut()) {
+                        // The coefficient of the equality is its own inverse.
+                        let factor = row[var] * equality[var];
+                        if factor == 0 {
+                            continue;
+                        }
+                        for (value, equality_value) in row.iter_mut().zip(&equality) {
+                            *value -= factor * equality_value;
+                        }
+                    }
+                },
+                None => {
+                    // A normalised equality without unit coefficients has at least two variables.
+                    let mut vars = (0..num_vars).filter(|var| equality[*var] != 0);
+                    let (p, q) = (vars.next().unwrap(), vars.next().unwrap());
+                    let (a, b) = (equality[p], equality[q]);
+                    let (g, s, t) = extended_gcd(a, b);
+                    // x_p = s * y - (b / g) * w and x_q = t * y + (a / g) * w.
+                    for row in equalities.iter_mut().chain(inequalities.iter_mut())
+                        .chain(core::iter::once(&mut equality))
+                    {
+                        let (row_p, row_q) = (row[p], row[q]);
+                        row[p] = row_p * s + row_q * t;
+                        row[q] = -row_p * (b / g) + row_q * (a / g);
+                    }
+                    equalities.push(equality);
+                }
+            }
+            continue;
+        }
+
+        let Some(num_vars) = inequalities.first().map(|row| row.len() - 1) else {
+            return false;
+        };
+        // Pick the variable to eliminate, preferring exact eliminations and then fewer combinations.
+        let mut best: Option<(bool, usize, usize)> = None;
+        let mut unbounded = None;
+        for var in 0..num_vars {
+            let num_lower = inequalities.iter().filter(|row| row[var] > 0).count();
+            let num_upper = inequalities.iter().filter(|row| row[var] < 0).count();
+            if num_lower + num_upper == 0 {
+                continue;
+            }
+            if num_lower == 0 || num_upper == 0 {
+                unbounded = Some(var);
+                break;
+            }
+            let exact = inequalities.iter().all(|row| row[var] <= 1)
+                || inequalities.iter().all(|row| row[var] >= -1);
+            let key = (!exact, num_lower * num_upper, var);
+            if best.map_or(true, |best| key < best) {
+                best = Some(key);
+            }
+        }
+        if let Some(var) = unbounded {
+            inequalities.retain(|row| row[var] == 0);
+            continue;
+        }
+        // Every remaining inequality is a constant one, which holds after normalisation.
+        let Some((inexact, _, var)) = best else {
+            return false;
+        };
+
+        let (lower, upper, rest) = partition_bounds(inequalities.clone(), var);
+        let mut real_shadow = rest.clone();
+        real_shadow.extend(combine_bounds(&lower, &upper, var, false));
+        if !inexact {
+            inequalities = real_shadow;
+            continue;
+        }
+        if is_integer_empty(Vec::new(), real_shadow) {
+            return true;
+        }
+        let mut dark_shadow = rest;
+        dark_shadow.extend(combine_bounds(&lower, &upper, var, true));
+        if !is_integer_empty(Vec::new(), dark_shadow) {
+            return false;
+        }
+        // Any integer point outside of the dark shadow is close to a lower bound `a * x + l >= 0`: it lies on a splinter `a * x + l = k` for some `0 <= k <= (m * a - a - m) / m`, where m is the largest coefficient of the variable in an upper bound.
+        let m = upper.iter().map(|row| -row[var]).max().unwrap();
+        for lower in &lower {
+            let a = lower[var];
+            for k in 0..=floor_div(m * a - a - m, m) {
+                let mut splinter = lower.clone();
+                splinter[num_vars] -= k;
+                if !is_integer_empty(vec![splinter], inequalities.clone()) {
+                    return false;
+                }
+            }
+        }
+        return true;
+    }
+}
+
+// ----------------------------------------------------------------------
+// IntegerPolyhedron
+// ----------------------------------------------------------------------
+
+/// An IntegerPolyhedron represents the set of points from a PresburgerSpace that satisfy a list of affine constraints. It is an IntegerRelation with no domain variables, whose dimension variables are the set dimensions.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IntegerPolyhedron(IntegerRelation);
+
+impl IntegerPolyhedron {
+    /// Constructs a polyhedron with the specified set space and no constraints.
+    pub fn new(space: PresburgerSpace) -> Self {
+        assert!(space.is_set(), "Expected a set space");
+        Self(IntegerRelation::new(space))
+    }
+
+    /// Return a system with no constraints, i.e., one which is satisfied by all points.
+    pub fn universe(space: PresburgerSpace) -> Self {
+        Self::new(space)
+    }
+
+    /// Return an empty system containing an invalid equation `0 = 1`.
+    pub fn empty(space: PresburgerSpace) -> Self {
+        assert!(space.is_set(), "Expected a set space");
+        Self(IntegerRelation::empty(space))
+    }
+
+    pub fn num_set_dim_vars(&self) -> usize {
+        self.0.num_range_vars()
+    }
+
+    /// Return the intersection of the two sets.
+    pub fn intersect(&self, other: &Self) -> Self {
+        Self(self.0.intersect(&other.0))
+    }
+
+    pub fn into_relation(self) -> IntegerRelation {
+        self.0
+    }
+}
+
+impl From<IntegerRelation> for IntegerPolyhedron {
+    fn from(relation: IntegerRelation) -> Self {
+        assert!(relation.space().is_set(), "Expected a set space");
+        Self(relation)
+    }
+}
+
+impl Deref for IntegerPolyhedron {
+    type Target = IntegerRelation;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for IntegerPolyhedron {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for IntegerPolyhedron {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Return the set of dimension `num_dims` defined by the given constraints.
+    fn polyhedron(num_dims: usize, equalities: &[&[i64]], inequalities: &[&[i64]])
+        -> IntegerPolyhedron
+    {
+        let mut polyhedron = IntegerPolyhedron::new(PresburgerSpace::set(num_dims, 0, 0));
+        for equality in equalities {
+            polyhedron.add_equality(equality);
+        }
+        for inequality in inequalities {
+            polyhedron.add_inequality(inequality);
+        }
+        polyhedron
+    }
+
+    #[test]
+    fn equalities_without_integer_solutions_are_empty() {
+        // 2x + 4y = 1
+        assert!(polyhedron(2, &[&[2, 4, -1]], &[]).is_empty());
+        // 2x + 4y = 6
+        assert!(!polyhedron(2, &[&[2, 4, -6]], &[]).is_empty());
+        // 3x = 2y, x = 1
+        assert!(polyhedron(2, &[&[3, -2, 0], &[1, 0, -1]], &[]).is_empty());
+        // 3x = 2y, 1 <= x <= 2
+        assert!(!polyhedron(2, &[&[3, -2, 0]], &[&[1, 0, -1], &[-1, 0, 2]]).is_empty());
+    }
+
+    #[test]
+    fn no_multiple_between_bounds_is_empty() {
+        // 1 <= 5x <= 4
+        assert!(polyhedron(1, &[], &[&[5, -1], &[-5, 4]]).is_empty());
+        // 1 <= 5x <= 9
+        let set = polyhedron(1, &[], &[&[5, -1], &[-5, 9]]);
+        assert!(!set.is_empty());
+        assert_eq!(set.find_integer_sample(), Some(vec![1]));
+    }
+
+    #[test]
+    fn dark_shadow_proves_emptiness() {
+        // 27 <= 11x + 13y <= 45, -10 <= 7x - 9y <= 4, from the Omega test paper, which has rational but no integer points.
+        let set = polyhedron(2, &[], &[&[11, 13, -27], &[-11, -13, 45], &[7, -9, 10], &[-7, 9, 4]]);
+        assert!(!Simplex::from_relation(&set).is_empty());
+        assert!(set.is_empty());
+    }
+
+    #[test]
+    fn splinters_find_the_only_integer_point() {
+        // 27 <= 11x + 13y <= 45, -10 <= 7x - 9y <= 5, whose only integer point is (2, 1).
+        let set = polyhedron(2, &[], &[&[11, 13, -27], &[-11, -13, 45], &[7, -9, 10], &[-7, 9, 5]]);
+        assert!(!set.is_empty());
+        assert_eq!(set.find_integer_sample(), Some(vec![2, 1]));
+        assert!(set.contains_point(&[2, 1]));
+        assert!(!set.contains_point(&[1, 2]));
+    }
+
+    #[test]
+    fn projection_keeps_the_integer_points() {
+        // y = 2x, 0 <= x <= 3, projected onto y.
+        let mut set = polyhedron(2, &[&[2, -1, 0]], &[&[1, 0, 0], &[-1, 0, 3]]);
+        set.project_out(VarKind::SET_DIM, 0, 1);
+        assert_eq!(set.num_set_dim_vars(), 1);
+        assert!(set.contains_point(&[4]));
+        assert!(!set.contains_point(&[3]));
+        assert!(!set.contains_point(&[8]));
+
+        // 0 <= x <= y <= 5, projected onto x, which Fourier-Motzkin eliminates exactly.
+        let mut set = polyhedron(2, &[], &[&[1, 0, 0], &[-1, 1, 0], &[0, -1, 5]]);
+        set.project_out(VarKind::SET_DIM, 1, 2);
+        assert_eq!(set.num_local_vars(), 0);
+        assert_eq!(set.constant_bound(BoundType::LB, 0), Some(0));
+        assert_eq!(set.constant_bound(BoundType::UB, 0), Some(5));
+    }
+
+    #[test]
+    fn local_floor_divisions_are_recovered() {
+        // 0 <= x <= 10, x = 2 * (x floordiv 2)
+        let mut set = polyhedron(1, &[], &[&[1, 0], &[-1, 10]]);
+        let local = set.add_local_floor_div(&[1, 0], 2);
+        let mut even = vec![0; set.num_cols()];
+        even[0] = 1;
+        even[local] = -2;
+        set.add_equality(&even);
+        assert!(set.contains_point(&[4]));
+        assert!(!set.contains_point(&[5]));
+        assert_eq!(set.local_division(0), Some((vec![1, 0, 0], 2)));
+    }
+
+    #[test]
+    fn integer_lex_min_and_max() {
+        // 2x >= 3, 3y >= 2x, x + y <= 15
+        let set = polyhedron(2, &[], &[&[2, 0, -3], &[-2, 3, 0], &[-1, -1, 15]]);
+        assert_eq!(set.find_integer_lex_min(), MaybeOptimum::Bounded(vec![2, 2]));
+        assert_eq!(set.find_integer_lex_max(), MaybeOptimum::Bounded(vec![9, 6]));
+    }
+
+    #[test]
+    fn lex_optima_of_unbounded_and_empty_sets() {
+        // x >= 0
+        let set = polyhedron(1, &[], &[&[1, 0]]);
+        assert_eq!(set.find_integer_lex_min(), MaybeOptimum::Bounded(vec![0]));
+        assert_eq!(set.find_integer_lex_max(), MaybeOptimum::Unbounded);
+        let set = IntegerPolyhedron::empty(PresburgerSpace::set(1, 0, 0));
+        assert_eq!(set.find_integer_lex_min(), MaybeOptimum::Empty);
+    }
+}
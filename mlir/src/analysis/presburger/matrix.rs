@@ -0,0 +1,161 @@
+//! This is a simple 2D matrix class that supports reading, writing, resizing, swapping rows, and swapping columns. The constraints of an integer relation are stored in matrices, one row per constraint.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/Presburger/Matrix.h>
+//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Analysis/Presburger/Matrix.cpp>
+
+use core::ops::{Index, IndexMut};
+
+use crate::support::math_extras::gcd;
+
+/// A dense matrix of integers, stored in row-major order.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct Matrix {
+    num_rows: usize,
+    num_columns: usize,
+    data: Vec<i64>
+}
+
+impl Matrix {
+    /// Construct a matrix with the specified number of rows and columns, initialised to zero.
+    pub fn new(num_rows: usize, num_columns: usize) -> Self {
+        Self { num_rows, num_columns, data: vec![0; num_rows * num_columns] }
+    }
+
+    /// Return the identity matrix of the specified dimension.
+    pub fn identity(dimension: usize) -> Self {
+        let mut matrix = Self::new(dimension, dimension);
+        for index in 0..dimension {
+            matrix[(index, index)] = 1;
+        }
+        matrix
+    }
+
+    pub fn num_rows(&self) -> usize {
+        self.num_rows
+    }
+
+    pub fn num_columns(&self) -> usize {
+        self.num_columns
+    }
+
+    pub fn row(&self, row: usize) -> &[i64] {
+        assert!(row < self.num_rows, "Row outside of range");
+        &self.data[row * self.num_columns..(row + 1) * self.num_columns]
+    }
+
+    pub fn row_mut(&mut self, row: usize) -> &mut [i64] {
+        assert!(row < self.num_rows, "Row outside of range");
+        &mut self.data[row * self.num_columns..(row + 1) * self.num_columns]
+    }
+
+    /// Return an iterator over the rows of the matrix.
+    pub fn rows(&self) -> impl Iterator<Item = &[i64]> + '_ {
+        (0..self.num_rows).map(|row| self.row(row))
+    }
+
+    /// Add a row at the bottom of the matrix and return its position.
+    pub fn append_extra_row(&mut self, elements: &[i64]) -> usize {
+        assert_eq!(elements.len(), self.num_columns, "Incorrect row length");
+        self.data.extend_from_slice(elements);
+        self.num_rows += 1;
+        self.num_rows - 1
+    }
+
+    pub fn remove_row(&mut self, row: usize) {
+        self.remove_rows(row, 1);
+    }
+
+    /// Remove the rows `[start, start + count)`.
+    pub fn remove_rows(&mut self, start: usize, count: usize) {
+        assert!(start + count <= self.num_rows, "Rows outside of range");
+        self.data.drain(start * self.num_columns..(start + count) * self.num_columns);
+        self.num_rows -= count;
+    }
+
+    /// Insert `count` columns of zeros before the column `position`.
+    pub fn insert_columns(&mut self, position: usize, count: usize) {
+        assert!(position <= self.num_columns, "Column outside of range");
+        if count == 0 {
+            return;
+        }
+        let mut data = Vec::with_capacity(self.num_rows * (self.num_columns + count));
+        for row in self.rows() {
+            data.extend_from_slice(&row[..position]);
+            data.extend(core::iter::repeat(0).take(count));
+            data.extend_from_slice(&row[position..]);
+        }
+        self.data = data;
+        self.num_columns += count;
+    }
+
+    /// Remove the columns `[start, start + count)`.
+    pub fn remove_columns(&mut self, start: usize, count: usize) {
+        assert!(start + count <= self.num_columns, "Columns outside of range");
+        if count == 0 {
+            return;
+        }
+        let mut data = Vec::with_capacity(self.num_rows * (self.num_columns - count));
+        for row in self.rows() {
+            data.extend_from_slice(&row[..start]);
+            data.extend_from_slice(&row[start + count..]);
+        }
+        self.data = data;
+        self.num_columns -= count;
+    }
+
+    pub fn swap_rows(&mut self, row: usize, other_row: usize) {
+        for column in 0..self.num_columns {
+            self.data.swap(row * self.num_columns + column, other_row * self.num_columns + column);
+        }
+    }
+
+    pub fn swap_columns(&mut self, column: usize, other_column: usize) {
+        for row in 0..self.num_rows {
+            self.data.swap(row * self.num_columns + column, row * self.num_columns + other_column);
+        }
+    }
+
+    /// Add `scale` multiples of the row `source` to the row `target`.
+    pub fn add_to_row(&mut self, source: usize, target: usize, scale: i64) {
+        if scale == 0 {
+            return;
+        }
+        for column in 0..self.num_columns {
+            let value = self[(source, column)];
+            self[(target, column)] += scale * value;
+        }
+    }
+
+    pub fn negate_row(&mut self, row: usize) {
+        for element in self.row_mut(row) {
+            *element = -*element;
+        }
+    }
+
+    /// Divide the row by the GCD of its elements, and return the GCD, which is zero if the row is.
+    pub fn normalize_row(&mut self, row: usize) -> i64 {
+        let divisor = self.row(row).iter().fold(0, |divisor, element| gcd(divisor, *element));
+        if divisor > 1 {
+            for element in self.row_mut(row) {
+                *element /= divisor;
+            }
+        }
+        divisor
+    }
+}
+
+impl Index<(usize, usize)> for Matrix {
+    type Output = i64;
+
+    fn index(&self, (row, column): (usize, usize)) -> &Self::Output {
+        assert!(row < self.num_rows && column < self.num_columns, "Index outside of range");
+        &self.data[row * self.num_columns + column]
+    }
+}
+
+impl IndexMut<(usize, usize)> for Matrix {
+    fn index_mut(&mut self, (row, column): (usize, usize)) -> &mut Self::Output {
+        assert!(row < self.num_rows && column < self.num_columns, "Index outside of range");
+        &mut self.data[row * self.num_columns + column]
+    }
+}
@@ -0,0 +1,431 @@
+//! A PresburgerRelation represents a union of IntegerRelations that live in the same PresburgerSpace with support for union, intersection, subtraction, and complement operations, as well as sampling.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/Presburger/PresburgerRelation.h>
+//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Analysis/Presburger/PresburgerRelation.cpp>
+
+use core::{
+    fmt,
+    ops::{Deref, DerefMut}
+};
+
+use crate::analysis::presburger::{
+    integer_relation::{IntegerPolyhedron, IntegerRelation},
+    presburger_space::{PresburgerSpace, VarKind},
+    simplex::MaybeOptimum
+};
+
+/**
+A PresburgerRelation is a union of IntegerRelations, called disjuncts, that live in the same PresburgerSpace. The space of the union has no local variables: each disjunct may have its own locals, which are existentially quantified within it.
+
+Note that there are no invariants guaranteed on the list of disjuncts other than that they are all in the same space, that is, they may overlap or be empty.
+*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PresburgerRelation {
+    space: PresburgerSpace,
+    disjuncts: Vec<IntegerRelation>
+}
+
+impl PresburgerRelation {
+    /// Return an empty relation of the specified space, which must not have local variables.
+    pub fn empty(space: PresburgerSpace) -> Self {
+        assert_eq!(space.num_local_vars(), 0, "PresburgerRelation cannot have local vars");
+        Self { space, disjuncts: Vec::new() }
+    }
+
+    /// Return a universe relation of the specified space, which must not have local variables.
+    pub fn universe(space: PresburgerSpace) -> Self {
+        let mut result = Self::empty(space);
+        result.union_in_place(IntegerRelation::universe(space));
+        result
+    }
+
+    pub fn space(&self) -> &PresburgerSpace {
+        &self.space
+    }
+
+    pub fn num_disjuncts(&self) -> usize {
+        self.disjuncts.len()
+    }
+
+    pub fn disjuncts(&self) -> &[IntegerRelation] {
+        &self.disjuncts
+    }
+
+    pub fn disjunct(&self, index: usize) -> &IntegerRelation {
+        &self.disjuncts[index]
+    }
+
+    /// Mutate this set, turning it into the union of this set and the given disjunct.
+    pub fn union_in_place(&mut self, disjunct: IntegerRelation) {
+        assert!(self.space.is_compatible(disjunct.space()), "Spaces should match");
+        self.disjuncts.push(disjunct);
+    }
+
+    /// Mutate this set, turning it into the union of this set and the given set.
+    pub fn union_in_place_set(&mut self, set: &Self) {
+        assert!(self.space.is_compatible(&set.space), "Spaces should match");
+        self.disjuncts.extend(set.disjuncts.iter().cloned());
+    }
+
+    /// Return the union of this set and the given set.
+    pub fn union_set(&self, set: &Self) -> Self {
+        let mut result = self.clone();
+        result.union_in_place_set(set);
+        result
+    }
+
+    /// Intersect this set with the given set, keeping the non-empty pairwise intersections of the disjuncts.
+    pub fn intersect(&self, set: &Self) -> Self {
+        assert!(self.space.is_compatible(&set.space), "Spaces should match");
+        let mut result = Self::empty(self.space);
+        for disjunct in &self.disjuncts {
+            for other in &set.disjuncts {
+                let intersection = disjunct.intersect(other);
+                if !intersection.is_empty() {
+                    result.union_in_place(intersection);
+                }
+            }
+        }
+        result
+    }
+
+    /**
+    Return the set difference of this set and the given set, i.e., return `this \ set`.
+
+    Each disjunct of `set` is subtracted from the pieces remaining so far. The local variables of `set` must be divisions, so that their values are determined by the other variables and the complement of a disjunct can be expressed without quantifiers.
+    */
+    pub fn subtract(&self, set: &Self) -> Self {
+        assert!(self.space.is_compatible(&set.space), "Spaces should match");
+        let mut result = Self::empty(self.space);
+        for disjunct in &self.disjuncts {
+            let mut pieces = vec![disjunct.clone()];
+            for other in &set.disjuncts {
+                pieces = pieces.iter()
+                    .flat_map(|piece| subtract_disjunct(piece, other))
+                    .collect();
+            }
+            result.disjuncts.extend(pieces);
+        }
+        result
+    }
+
+    /// Return the complement of this set. All local variables in the set must correspond to floor divisions.
+    pub fn complement(&self) -> Self {
+        Self::universe(self.space).subtract(self)
+    }
+
+    /// Return true if this set is a subset of the given set, and false otherwise.
+    pub fn is_subset_of(&self, set: &Self) -> bool {
+        self.subtract(set).is_integer_empty()
+    }
+
+    /// Return true if this set is equal to the given set, and false otherwise. All local variables in both sets must correspond to floor divisions.
+    pub fn is_equal(&self, set: &Self) -> bool {
+        assert!(self.space.is_compatible(&set.space), "Spaces should match");
+        self.is_subset_of(set) && set.is_subset_of(self)
+    }
+
+    /// Return true if all the sets in the union are known to be integer empty, false otherwise.
+    pub fn is_integer_empty(&self) -> bool {
+        self.disjuncts.iter().all(IntegerRelation::is_empty)
+    }
+
+    /// Return true if the set contains the given point, and false otherwise.
+    pub fn contains_point(&self, point: &[i64]) -> bool {
+        self.disjuncts.iter().any(|disjunct| disjunct.contains_point(point))
+    }
+
+    /// Find an integer sample from the given set, with one value per dimension and symbol variable, if one exists.
+    pub fn find_integer_sample(&self) -> Option<Vec<i64>> {
+        let num_vars = self.space.num_dim_and_symbol_vars();
+        self.disjuncts.iter()
+            .find_map(IntegerRelation::find_integer_sample)
+            .map(|mut sample| {
+                sample.truncate(num_vars);
+                sample
+            })
+    }
+
+    /// Return the integer lexicographic minimum of the set.
+    pub fn find_integer_lex_min(&self) -> MaybeOptimum<Vec<i64>> {
+        self.combine_lex_opt(IntegerRelation::find_integer_lex_min, |point, best| point < best)
+    }
+
+    /// Return the integer lexicographic maximum of the set.
+    pub fn find_integer_lex_max(&self) -> MaybeOptimum<Vec<i64>> {
+        self.combine_lex_opt(IntegerRelation::find_integer_lex_max, |point, best| point > best)
+    }
+
+    /// The optimum of the union is the best of the optima of the disjuncts, and it is unbounded if any of them is.
+    fn combine_lex_opt(
+        &self,
+        optimum: impl Fn(&IntegerRelation) -> MaybeOptimum<Vec<i64>>,
+        is_better: impl Fn(&Vec<i64>, &Vec<i64>) -> bool
+    ) -> MaybeOptimum<Vec<i64>> {
+        let mut result = MaybeOptimum::Empty;
+        for disjunct in &self.disjuncts {
+            match (optimum(disjunct), &result) {
+                (MaybeOptimum::Empty, _) => {},
+                (MaybeOptimum::Unbounded, _) => return MaybeOptimum::Unbounded,
+                (MaybeOptimum::Bounded(point), MaybeOptimum::Bounded(best))
+                    if !is_better(&point, best) => {},
+                (point, _) => result = point
+            }
+        }
+        result
+    }
+
+    /// Simplifies the representation of the set by removing the empty disjuncts and simplifying the constraints of the others.
+    pub fn simplify(&mut self) {
+        self.disjuncts.retain(|disjunct| !disjunct.is_empty());
+        for disjunct in &mut self.disjuncts {
+            disjunct.simplify();
+        }
+    }
+}
+
+impl From<IntegerRelation> for PresburgerRelation {
+    /// Create a relation from a single disjunct, the locals of which stay local to the disjunct.
+    fn from(disjunct: IntegerRelation) -> Self {
+        let mut result = Self::empty(disjunct.space().without_locals());
+        result.union_in_place(disjunct);
+        result
+    }
+}
+
+impl fmt::Display for PresburgerRelation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} disjuncts:", self.num_disjuncts())?;
+        for disjunct in &self.disjuncts {
+            write!(f, "{}", disjunct)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+Return the set difference `disjunct \ other` as a list of disjoint pieces.
+
+The local variables of `other` are first added to `disjunct` as the divisions they represent, so that the constraints of `other` can be expressed over the variables of `disjunct`. Then the complement of `other` is the union, over its constraints, of the points violating this constraint while satisfying the previous ones: the complement of the inequality `e >= 0` is `-e - 1 >= 0`, and the complement of the equality `e = 0` is `e - 1 >= 0` or `-e - 1 >= 0`.
+*/
+fn subtract_disjunct(disjunct: &IntegerRelation, other: &IntegerRelation) -> Vec<IntegerRelation> {
+    let divisions = other.local_divisions()
+        .expect("Subtraction is only supported for relations whose locals are divisions");
+    let num_non_locals = other.num_dim_and_symbol_vars();
+    let other_local_offset = other.space().var_kind_offset(VarKind::Local);
+
+    let mut current = disjunct.clone();
+    // The absolute position in `current` of each local of `other`, once added.
+    let mut positions = vec![None; divisions.len()];
+    while positions.iter().any(Option::is_none) {
+        for (local, (dividend, divisor)) in divisions.iter().enumerate() {
+            let dependencies_added = (0..divisions.len()).all(|other_local|
+                dividend[other_local_offset + other_local] == 0
+                    || positions[other_local].is_some());
+            if positions[local].is_some() || !dependencies_added {
+                continue;
+            }
+            let mapped = map_columns(dividend, num_non_locals, &positions, current.num_cols());
+            positions[local] = Some(current.add_local_floor_div(&mapped, *divisor));
+        }
+    }
+
+    let mut pieces = Vec::new();
+    let mut add_piece = |piece: IntegerRelation| if !piece.is_empty() {
+        pieces.push(piece);
+    };
+    for inequality in other.inequalities().rows() {
+        let inequality = map_columns(inequality, num_non_locals, &positions, current.num_cols());
+        let mut piece = current.clone();
+        piece.add_inequality(&complement(&inequality, true));
+        add_piece(piece);
+        current.add_inequality(&inequality);
+    }
+    for equality in other.equalities().rows() {
+        let equality = map_columns(equality, num_non_locals, &positions, current.num_cols());
+        let mut piece = current.clone();
+        piece.add_inequality(&complement(&equality, false));
+        add_piece(piece);
+        let mut piece = current.clone();
+        piece.add_inequality(&complement(&equality, true));
+        add_piece(piece);
+        current.add_equality(&equality);
+    }
+    pieces
+}
+
+/// Map a row over the columns of a relation to the columns of a relation with the same dimension and symbol variables, where the locals are at the given absolute positions.
+fn map_columns(
+    row: &[i64],
+    num_non_locals: usize,
+    positions: &[Option<usize>],
+    num_cols: usize
+) -> Vec<i64> {
+    let mut mapped = vec![0; num_cols];
+    mapped[..num_non_locals].copy_from_slice(&row[..num_non_locals]);
+    for (local, position) in positions.iter().enumerate() {
+        let coeff = row[num_non_locals + local];
+        if coeff != 0 {
+            mapped[position.expect("Local used before being added")] += coeff;
+        }
+    }
+    mapped[num_cols - 1] = row[row.len() - 1];
+    mapped
+}
+
+/// Return the inequality `-e - 1 >= 0` if `negate` and `e - 1 >= 0` otherwise, which hold when `e >= 0` does not, or when `e <= 0` does not.
+fn complement(row: &[i64], negate: bool) -> Vec<i64> {
+    let mut result = row.iter().map(|coeff| if negate { -coeff } else { *coeff })
+        .collect::<Vec<_>>();
+    *result.last_mut().unwrap() -= 1;
+    result
+}
+
+// ----------------------------------------------------------------------
+// PresburgerSet
+// ----------------------------------------------------------------------
+
+/// A PresburgerSet is a PresburgerRelation over a set space, i.e. a union of IntegerPolyhedrons.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PresburgerSet(PresburgerRelation);
+
+impl PresburgerSet {
+    /// Return an empty set of the specified set space.
+    pub fn empty(space: PresburgerSpace) -> Self {
+        assert!(space.is_set(), "Expected a set space");
+        Self(PresburgerRelation::empty(space))
+    }
+
+    /// Return a universe set of the specified set space.
+    pub fn universe(space: PresburgerSpace) -> Self {
+        assert!(space.is_set(), "Expected a set space");
+        Self(PresburgerRelation::universe(space))
+    }
+
+    pub fn num_set_dim_vars(&self) -> usize {
+        self.0.space().num_set_dim_vars()
+    }
+
+    /// Return the union of this set and the given set.
+    pub fn union_set(&self, set: &Self) -> Self {
+        Self(self.0.union_set(&set.0))
+    }
+
+    /// Return the intersection of this set and the given set.
+    pub fn intersect(&self, set: &Self) -> Self {
+        Self(self.0.intersect(&set.0))
+    }
+
+    /// Return the set difference `this \ set`.
+    pub fn subtract(&self, set: &Self) -> Self {
+        Self(self.0.subtract(&set.0))
+    }
+
+    /// Return the complement of this set.
+    pub fn complement(&self) -> Self {
+        Self(self.0.complement())
+    }
+
+    pub fn into_relation(self) -> PresburgerRelation {
+        self.0
+    }
+}
+
+impl From<IntegerPolyhedron> for PresburgerSet {
+    fn from(polyhedron: IntegerPolyhedron) -> Self {
+        Self(PresburgerRelation::from(polyhedron.into_relation()))
+    }
+}
+
+impl From<PresburgerRelation> for PresburgerSet {
+    fn from(relation: PresburgerRelation) -> Self {
+        assert!(relation.space().is_set(), "Expected a set space");
+        Self(relation)
+    }
+}
+
+impl Deref for PresburgerSet {
+    type Target = PresburgerRelation;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for PresburgerSet {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl fmt::Display for PresburgerSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Return the set of the integers in `[lower, upper]`.
+    fn interval(lower: i64, upper: i64) -> PresburgerSet {
+        let mut polyhedron = IntegerPolyhedron::new(PresburgerSpace::set(1, 0, 0));
+        polyhedron.add_inequality(&[1, -lower]);
+        polyhedron.add_inequality(&[-1, upper]);
+        PresburgerSet::from(polyhedron)
+    }
+
+    /// Return the set of the even integers.
+    fn evens() -> PresburgerSet {
+        let mut polyhedron = IntegerPolyhedron::new(PresburgerSpace::set(1, 0, 0));
+        let local = polyhedron.add_local_floor_div(&[1, 0], 2);
+        let mut even = vec![0; polyhedron.num_cols()];
+        even[0] = 1;
+        even[local] = -2;
+        polyhedron.add_equality(&even);
+        PresburgerSet::from(polyhedron)
+    }
+
+    #[test]
+    fn subtract_an_interval() {
+        let difference = interval(0, 10).subtract(&interval(3, 5));
+        assert!(difference.contains_point(&[2]));
+        assert!(difference.contains_point(&[6]));
+        assert!(!difference.contains_point(&[4]));
+        assert!(!difference.contains_point(&[11]));
+        assert!(difference.is_equal(&interval(0, 2).union_set(&interval(6, 10))));
+    }
+
+    #[test]
+    fn subtract_a_set_with_divisions() {
+        let odds = interval(0, 10).subtract(&evens());
+        assert!(odds.contains_point(&[3]));
+        assert!(!odds.contains_point(&[4]));
+        assert_eq!(odds.find_integer_lex_min(), MaybeOptimum::Bounded(vec![1]));
+        assert_eq!(odds.find_integer_lex_max(), MaybeOptimum::Bounded(vec![9]));
+    }
+
+    #[test]
+    fn complement_partitions_the_space() {
+        let set = interval(0, 10);
+        let complement = set.complement();
+        assert!(complement.contains_point(&[-1]));
+        assert!(!complement.contains_point(&[0]));
+        assert!(set.intersect(&complement).is_integer_empty());
+        assert!(PresburgerSet::universe(PresburgerSpace::set(1, 0, 0))
+            .is_subset_of(&set.union_set(&complement)));
+        assert!(complement.complement().is_equal(&set));
+    }
+
+    #[test]
+    fn optima_of_unions_and_intersections() {
+        let union = interval(0, 3).union_set(&interval(5, 8));
+        assert_eq!(union.find_integer_lex_min(), MaybeOptimum::Bounded(vec![0]));
+        assert_eq!(union.find_integer_lex_max(), MaybeOptimum::Bounded(vec![8]));
+        assert!(interval(0, 3).intersect(&interval(5, 8)).is_integer_empty());
+
+        let union = interval(5, 4).union_set(&interval(7, 9));
+        assert_eq!(union.find_integer_sample(), Some(vec![7]));
+    }
+}
@@ -0,0 +1,191 @@
+//! Classes representing space information like number of variables and kind of variables.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/Presburger/PresburgerSpace.h>
+//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Analysis/Presburger/PresburgerSpace.cpp>
+
+/// Kind of variable. Implementation wise SetDims are treated as Range vars, and spaces with no distinction between dimension vars are treated as relations with zero domain vars.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum VarKind {
+    Symbol,
+    Local,
+    Domain,
+    Range
+}
+
+impl VarKind {
+    /// The dimension variables of a set.
+    pub const SET_DIM: Self = Self::Range;
+}
+
+/**
+PresburgerSpace is the space of all possible values of a tuple of integer valued variables/variables. Each variable has one of the three types:
+
+- Dimension: Ordinary variables over which the space is represented.
+- Symbol: Symbol variables correspond to fixed but unknown values. Mathematically, a space with symbolic variables is like a family of spaces indexed by the symbolic variables.
+- Local: Local variables correspond to existentially quantified variables. For example, consider the space: `(x, exists q)` where x is a dimension variable and q is a local variable. Let us put the constraints: `1 <= x <= 7, x = 2q` on this space to get the set: `(x) : (exists q : q <= x <= 7, x = 2q)`. An assignment to symbolic and dimension variables is valid if there exists some assignment to the local variable `q` satisfying these constraints. For this example, the set is equivalent to `{2, 4, 6}`. Mathematically, existential quantification can be thought of as the result of projection. In this example, `q` is existentially quantified. This can be thought of as the result of projecting out `q` from the previous example set, which is an ordinary variable over which the set is defined.
+
+Dimension variables are further divided into Domain and Range variables to support building relations.
+
+Variables are stored in the following order:
+
+```text
+[Domain vars, Range vars, Symbol vars, Local vars]
+```
+
+A space with no domain variables is a set space.
+*/
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PresburgerSpace {
+    /// Number of variables corresponding to domain variables.
+    num_domain: usize,
+    /// Number of variables corresponding to range variables.
+    num_range: usize,
+    /// Number of variables corresponding to symbols (unknown but constant for analysis).
+    num_symbols: usize,
+    /// Number of variables corresponding to locals (variables corresponding to existentially quantified variables).
+    num_locals: usize
+}
+
+impl PresburgerSpace {
+    pub fn relation(
+        num_domain: usize,
+        num_range: usize,
+        num_symbols: usize,
+        num_locals: usize
+    ) -> Self {
+        Self { num_domain, num_range, num_symbols, num_locals }
+    }
+
+    pub fn set(num_dims: usize, num_symbols: usize, num_locals: usize) -> Self {
+        Self::relation(0, num_dims, num_symbols, num_locals)
+    }
+
+    pub fn num_domain_vars(&self) -> usize {
+        self.num_domain
+    }
+
+    pub fn num_range_vars(&self) -> usize {
+        self.num_range
+    }
+
+    pub fn num_set_dim_vars(&self) -> usize {
+        self.num_range
+    }
+
+    pub fn num_symbol_vars(&self) -> usize {
+        self.num_symbols
+    }
+
+    pub fn num_local_vars(&self) -> usize {
+        self.num_locals
+    }
+
+    pub fn num_dim_vars(&self) -> usize {
+        self.num_domain + self.num_range
+    }
+
+    pub fn num_dim_and_symbol_vars(&self) -> usize {
+        self.num_domain + self.num_range + self.num_symbols
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_domain + self.num_range + self.num_symbols + self.num_locals
+    }
+
+    /// Returns whether the space has no domain variables.
+    pub fn is_set(&self) -> bool {
+        self.num_domain == 0
+    }
+
+    /// Get the number of vars of the specified kind.
+    pub fn num_vars_of_kind(&self, kind: VarKind) -> usize {
+        match kind {
+            VarKind::Domain => self.num_domain,
+            VarKind::Range => self.num_range,
+            VarKind::Symbol => self.num_symbols,
+            VarKind::Local => self.num_locals
+        }
+    }
+
+    /// Return the index at which the specified kind of var starts.
+    pub fn var_kind_offset(&self, kind: VarKind) -> usize {
+        match kind {
+            VarKind::Domain => 0,
+            VarKind::Range => self.num_domain,
+            VarKind::Symbol => self.num_dim_vars(),
+            VarKind::Local => self.num_dim_and_symbol_vars()
+        }
+    }
+
+    /// Return the index at Which the specified kind of var ends.
+    pub fn var_kind_end(&self, kind: VarKind) -> usize {
+        self.var_kind_offset(kind) + self.num_vars_of_kind(kind)
+    }
+
+    /// Get the number of elements of the specified kind in the range `[start, end)`.
+    pub fn var_kind_overlap(&self, kind: VarKind, start: usize, end: usize) -> usize {
+        let kind_start = self.var_kind_offset(kind);
+        let kind_end = self.var_kind_end(kind);
+        end.min(kind_end).saturating_sub(start.max(kind_start))
+    }
+
+    /// Return the VarKind of the var at the specified position.
+    pub fn var_kind_at(&self, position: usize) -> VarKind {
+        assert!(position < self.num_vars(), "Position out of bounds");
+        [VarKind::Domain, VarKind::Range, VarKind::Symbol, VarKind::Local].into_iter()
+            .find(|kind| position < self.var_kind_end(*kind))
+            .unwrap()
+    }
+
+    /// Insert `num` variables of the specified kind at position `position` among the variables of that kind. Returns the absolute index of the first variable inserted.
+    pub fn insert_var(&mut self, kind: VarKind, position: usize, num: usize) -> usize {
+        assert!(position <= self.num_vars_of_kind(kind), "Position out of bounds");
+        let absolute_position = self.var_kind_offset(kind) + position;
+        match kind {
+            VarKind::Domain => self.num_domain += num,
+            VarKind::Range => self.num_range += num,
+            VarKind::Symbol => self.num_symbols += num,
+            VarKind::Local => self.num_locals += num
+        }
+        absolute_position
+    }
+
+    /// Removes variables of the specified kind in the column range `[start, end)`, relative to the variables of that kind.
+    pub fn remove_var_range(&mut self, kind: VarKind, start: usize, end: usize) {
+        assert!(start <= end && end <= self.num_vars_of_kind(kind), "Invalid range");
+        let num = end - start;
+        match kind {
+            VarKind::Domain => self.num_domain -= num,
+            VarKind::Range => self.num_range -= num,
+            VarKind::Symbol => self.num_symbols -= num,
+            VarKind::Local => self.num_locals -= num
+        }
+    }
+
+    /// Returns true if both the spaces are compatible i.e. if both spaces have the same number of variables of each kind (excluding locals).
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        self.num_domain == other.num_domain
+        && self.num_range == other.num_range
+        && self.num_symbols == other.num_symbols
+    }
+
+    /// Returns true if both the spaces are equal including local variables i.e. if both spaces have the same number of variables of each kind (including locals).
+    pub fn is_equal(&self, other: &Self) -> bool {
+        self == other
+    }
+
+    /// Returns the space without its local variables.
+    pub fn without_locals(&self) -> Self {
+        Self { num_locals: 0, ..*self }
+    }
+
+    /// Returns the set space of the domain variables, keeping the symbols.
+    pub fn domain_space(&self) -> Self {
+        Self::set(self.num_domain, self.num_symbols, 0)
+    }
+
+    /// Returns the set space of the range variables, keeping the symbols.
+    pub fn range_space(&self) -> Self {
+        Self::set(self.num_range, self.num_symbols, 0)
+    }
+}
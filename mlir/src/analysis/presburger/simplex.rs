@@ -0,0 +1,309 @@
+//! Functionality to perform analysis on an IntegerRelation. In particular, support for performing emptiness checks and computing optima over the rational relaxation of a set of constraints.
+//!
+//! The simplex works over the rationals: the constraints are solved with the two-phase primal simplex method, using Bland's rule to avoid cycling. Integer questions are answered on top of it by `IntegerRelation`.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/Presburger/Simplex.h>
+//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Analysis/Presburger/Simplex.cpp>
+
+use crate::analysis::presburger::{
+    fraction::Fraction,
+    integer_relation::IntegerRelation
+};
+
+/// The direction of an optimisation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    /// Maximise.
+    Up,
+    /// Minimise.
+    Down
+}
+
+/// The result of an optimisation: the constraints may be empty, the objective may be unbounded, or it has an optimum.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MaybeOptimum<T> {
+    Empty,
+    Unbounded,
+    Bounded(T)
+}
+
+impl<T> MaybeOptimum<T> {
+    pub fn is_empty(&self) -> bool {
+        matches!(self, Self::Empty)
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        matches!(self, Self::Unbounded)
+    }
+
+    pub fn is_bounded(&self) -> bool {
+        matches!(self, Self::Bounded(_))
+    }
+
+    /// Return the optimum, if it is bounded.
+    pub fn bounded(self) -> Option<T> {
+        match self {
+            Self::Bounded(value) => Some(value),
+            _ => None
+        }
+    }
+
+    pub fn map<U>(self, f: impl FnOnce(T) -> U) -> MaybeOptimum<U> {
+        match self {
+            Self::Empty => MaybeOptimum::Empty,
+            Self::Unbounded => MaybeOptimum::Unbounded,
+            Self::Bounded(value) => MaybeOptimum::Bounded(f(value))
+        }
+    }
+}
+
+/**
+A simplex over the rational relaxation of a set of affine constraints on free variables.
+
+Constraints are specified as coefficient vectors with one coefficient per variable followed by a constant term: the inequality `c` holds when `c[0] * x0 + ... + c[n - 1] * xn-1 + c[n] >= 0`, and the equality when the same expression is `0`.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct Simplex {
+    num_vars: usize,
+    equalities: Vec<Vec<i64>>,
+    inequalities: Vec<Vec<i64>>
+}
+
+/// The outcome of solving the linear program of a simplex.
+enum Solution {
+    Infeasible,
+    Unbounded,
+    /// The optimal value, and a point attaining it.
+    Optimal(Fraction, Vec<Fraction>)
+}
+
+impl Simplex {
+    /// Construct a simplex with `num_vars` variables and no constraints.
+    pub fn new(num_vars: usize) -> Self {
+        Self { num_vars, ..Self::default() }
+    }
+
+    /// Construct a simplex over the rational relaxation of the constraints of `relation`, with one variable per variable of the relation, locals included.
+    pub fn from_relation(relation: &IntegerRelation) -> Self {
+        let mut simplex = Self::new(relation.num_vars());
+        for equality in relation.equalities().rows() {
+            simplex.add_equality(equality);
+        }
+        for inequality in relation.inequalities().rows() {
+            simplex.add_inequality(inequality);
+        }
+        simplex
+    }
+
+    pub fn num_vars(&self) -> usize {
+        self.num_vars
+    }
+
+    /// Add the inequality `coeffs . x + constant >= 0`.
+    pub fn add_inequality(&mut self, coeffs: &[i64]) {
+        assert_eq!(coeffs.len(), self.num_vars + 1, "Incorrect number of coefficients");
+        self.inequalities.push(coeffs.to_vec());
+    }
+
+    /// Add the equality `coeffs . x + constant == 0`.
+    pub fn add_equality(&mut self, coeffs: &[i64]) {
+        assert_eq!(coeffs.len(), self.num_vars + 1, "Incorrect number of coefficients");
+        self.equalities.push(coeffs.to_vec());
+    }
+
+    /// Returns true if there is no rational point satisfying the constraints.
+    pub fn is_empty(&self) -> bool {
+        matches!(self.solve(None), Solution::Infeasible)
+    }
+
+    /// Return a rational point satisfying the constraints, if any.
+    pub fn find_rational_sample(&self) -> Option<Vec<Fraction>> {
+        match self.solve(None) {
+            Solution::Optimal(_, point) => Some(point),
+            _ => None
+        }
+    }
+
+    /// Compute the optimum of `coeffs . x + constant` in the given direction over the constraints.
+    pub fn compute_optimum(&self, direction: Direction, coeffs: &[i64])
+        -> MaybeOptimum<Fraction>
+    {
+        assert_eq!(coeffs.len(), self.num_vars + 1, "Incorrect number of coefficients");
+        // Maximising is minimising the negated objective.
+        let objective = match direction {
+            Direction::Down => coeffs.to_vec(),
+            Direction::Up => coeffs.iter().map(|coeff| -coeff).collect()
+        };
+        match self.solve(Some(&objective)) {
+            Solution::Infeasible => MaybeOptimum::Empty,
+            Solution::Unbounded => MaybeOptimum::Unbounded,
+            Solution::Optimal(value, _) => MaybeOptimum::Bounded(match direction {
+                Direction::Down => value,
+                Direction::Up => -value
+            })
+        }
+    }
+
+    /**
+    Minimise `objective` over the constraints, or only find a feasible point if there is no objective.
+
+    Each free variable `x` is split into two non-negative variables as `x = p - n`, and each inequality gets a non-negative slack variable, so that the constraints are in the standard form `A y = b, y >= 0`. Phase one finds a feasible basis by minimising the sum of an artificial variable per row, and phase two optimises the objective from there.
+    */
+    fn solve(&self, objective: Option<&[i64]>) -> Solution {
+        let num_vars = self.num_vars;
+        let num_slacks = self.inequalities.len();
+        let num_rows = self.equalities.len() + num_slacks;
+        let num_columns = 2 * num_vars + num_slacks;
+
+        let mut tableau = Tableau::new(num_rows, num_columns + num_rows);
+        let rows = self.equalities.iter().map(|row| (row, None))
+            .chain(self.inequalities.iter().enumerate().map(|(index, row)| (row, Some(index))));
+        for (index, (row, slack)) in rows.enumerate() {
+            // A positive right-hand side is needed for the artificial basis.
+            let sign = if row[num_vars] > 0 { -1 } else { 1 };
+            let tableau_row = &mut tableau.rows[index];
+            for var in 0..num_vars {
+                tableau_row[2 * var] = Fraction::from(sign * row[var]);
+                tableau_row[2 * var + 1] = Fraction::from(-sign * row[var]);
+            }
+            if let Some(slack) = slack {
+                tableau_row[2 * num_vars + slack] = Fraction::from(-sign);
+            }
+            tableau_row[num_columns + index] = Fraction::one();
+            tableau_row[num_columns + num_rows] = Fraction::from(-sign * row[num_vars]);
+            tableau.basis[index] = num_columns + index;
+        }
+
+        // Phase one: minimise the sum of the artificial variables.
+        let mut cost = vec![Fraction::zero(); num_columns + num_rows];
+        cost[num_columns..].fill(Fraction::one());
+        let bounded = tableau.minimise(&cost, num_columns + num_rows);
+        debug_assert!(bounded, "The phase one objective is bounded below");
+        if tableau.objective_value(&cost) > Fraction::zero() {
+            return Solution::Infeasible;
+        }
+        tableau.drive_out_artificials(num_columns);
+
+        // Phase two: minimise the objective over the original variables.
+        let mut cost = vec![Fraction::zero(); num_columns + num_rows];
+        if let Some(objective) = objective {
+            for var in 0..num_vars {
+                cost[2 * var] = Fraction::from(objective[var]);
+                cost[2 * var + 1] = Fraction::from(-objective[var]);
+            }
+            if !tableau.minimise(&cost, num_columns) {
+                return Solution::Unbounded;
+            }
+        }
+
+        let values = tableau.values();
+        let point = (0..num_vars)
+            .map(|var| values[2 * var] - values[2 * var + 1])
+            .collect::<Vec<_>>();
+        let value = match objective {
+            Some(objective) => point.iter().zip(objective)
+                .fold(Fraction::from(objective[num_vars]), |value, (x, coeff)|
+                    value + *x * Fraction::from(*coeff)),
+            None => Fraction::zero()
+        };
+        Solution::Optimal(value, point)
+    }
+}
+
+/// A dense simplex tableau `A y = b` with a basis, where the last column of each row holds `b`.
+struct Tableau {
+    rows: Vec<Vec<Fraction>>,
+    /// The basic column of each row.
+    basis: Vec<usize>,
+    num_columns: usize
+}
+
+impl Tableau {
+    fn new(num_rows: usize, num_columns: usize) -> Self {
+        Self {
+            rows: vec![vec![Fraction::zero(); num_columns + 1]; num_rows],
+            basis: vec![0; num_rows],
+            num_columns
+        }
+    }
+
+    /// Pivot the column `column` into the basis at the row `row`.
+    fn pivot(&mut self, row: usize, column: usize) {
+        let pivot = self.rows[row][column];
+        for element in self.rows[row].iter_mut() {
+            *element = *element / pivot;
+        }
+        let pivot_row = self.rows[row].clone();
+        for (index, other) in self.rows.iter_mut().enumerate() {
+            let factor = other[column];
+            if index == row || factor == Fraction::zero() {
+                continue;
+            }
+            for (element, pivot_element) in other.iter_mut().zip(&pivot_row) {
+                *element = *element - factor * *pivot_element;
+            }
+        }
+        self.basis[row] = column;
+    }
+
+    fn objective_value(&self, cost: &[Fraction]) -> Fraction {
+        self.rows.iter().zip(&self.basis).fold(Fraction::zero(), |value, (row, basic)|
+            value + cost[*basic] * row[self.num_columns])
+    }
+
+    /// Minimise `cost . y`, only letting the columns before `num_allowed` enter the basis. Returns false if the objective is unbounded below.
+    fn minimise(&mut self, cost: &[Fraction], num_allowed: usize) -> bool {
+        loop {
+            // Bland's rule: the entering column is the first one with a negative reduced cost.
+            let entering = (0..num_allowed).find(|column| {
+                let reduced_cost = self.rows.iter().zip(&self.basis)
+                    .fold(cost[*column], |reduced_cost, (row, basic)|
+                        reduced_cost - cost[*basic] * row[*column]);
+                reduced_cost < Fraction::zero()
+            });
+            let Some(entering) = entering else {
+                return true;
+            };
+            // The leaving row has the smallest ratio, ties being broken by the smallest basic column.
+            let leaving = (0..self.rows.len())
+                .filter(|row| self.rows[*row][entering] > Fraction::zero())
+                .min_by_key(|row| (
+                    self.rows[*row][self.num_columns] / self.rows[*row][entering],
+                    self.basis[*row]));
+            let Some(leaving) = leaving else {
+                return false;
+            };
+            self.pivot(leaving, entering);
+        }
+    }
+
+    /// Pivot the artificial variables, which are the columns from `num_columns` on and have a zero value, out of the basis. Rows where this is impossible are redundant, and removed.
+    fn drive_out_artificials(&mut self, num_columns: usize) {
+        let mut row = 0;
+        while row < self.rows.len() {
+            if self.basis[row] < num_columns {
+                row += 1;
+                continue;
+            }
+            match (0..num_columns).find(|column| self.rows[row][*column] != Fraction::zero()) {
+                Some(column) => {
+                    self.pivot(row, column);
+                    row += 1;
+                },
+                None => {
+                    self.rows.remove(row);
+                    self.basis.remove(row);
+                }
+            }
+        }
+    }
+
+    /// Return the value of every column at the current basic solution.
+    fn values(&self) -> Vec<Fraction> {
+        let mut values = vec![Fraction::zero(); self.num_columns];
+        for (row, basic) in self.rows.iter().zip(&self.basis) {
+            values[*basic] = row[self.num_columns];
+        }
+        values
+    }
+}
@@ -13,3 +13,4 @@ pub mod location_parser;
 pub mod parser;
 pub mod parser_state;
 pub mod token;
+pub mod type_parser;
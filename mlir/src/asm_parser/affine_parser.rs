@@ -4,49 +4,564 @@ This file implements a parser for Affine structures.
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/AffineParser.cpp>
 */
 
-#[allow(non_camel_case_types)]
-type unsigned = u16;
+use core::ops::{Deref, DerefMut};
 
+use llvm::support::sm_loc::SMLoc;
 use crate::{
-    mlir::{
-        asm_parser::parser,
-        ir::{
-            affine_expr::AffineExpr,
-            affine_map,
-            integer_set
-        },
-        support::logical_result::LogicalResult
+    asm_parser::{
+        parser::{AsParser, ParseResult, Parser},
+        token::{Keyword, Token, TokenKind}
     },
-    llvm::support::source_manager
+    ir::{
+        affine_expr::{self, AffineExpr, Kind},
+        affine_map::AffineMap,
+        integer_set::IntegerSet,
+        operation::implementation::Delimiter
+    }
 };
 
-/// Lower precedence ops (all at the same precedence level). LNoOp is false in
-/// the boolean sense.
+/// Lower precedence ops (all at the same precedence level).
+#[derive(Clone, Copy)]
 enum AffineLowPrecOp {
-    /// Null value.
-    LNoOp,
     Add,
     Sub
 }
 
-/// Higher precedence ops - all at the same precedence level. HNoOp is false
-/// in the boolean sense.
+/// Higher precedence ops - all at the same precedence level.
+#[derive(Clone, Copy)]
 enum AffineHighPrecOp {
-    /// Null value.
-    HNoOp,
     Mul,
     FloorDiv,
     CeilDiv,
     Mod
 }
 
+/// Either of the affine structures that share the dimension and symbol list syntax.
+pub enum AffineMapOrIntegerSet {
+    AffineMap(AffineMap),
+    IntegerSet(IntegerSet)
+}
+
+/// Returns true if the given token can be represented as an identifier.
+fn is_identifier(token: &Token) -> bool {
+    // We include only `inttype` and `bare_identifier` here since they are the only non-keyword tokens that can be used to represent an identifier.
+    token.is_any(&[TokenKind::BareIdentifier, TokenKind::IntType]) || token.is_keyword()
+}
+
 /**
 This is a specialised parser for affine structures (affine maps, affine expressions, and integer sets), maintaining the state transient to their bodies.
 */
-pub struct AffineParser {
-    allow_parsing_ssa_ids: bool,
-    parse_element: fn(bool) -> LogicalResult,  // function_ref
-    num_dim_operands: unsigned,  // = 0;
-    num_symbol_operands: unsigned, //= 0;
-    dims_and_symbols: Vec<(&'static str, AffineExpr)>
+pub struct AffineParser<'p, 'a, 'c> {
+    parser: &'p mut Parser<'a, 'c>,
+    dims_and_symbols: Vec<(&'a str, AffineExpr)>
+}
+
+impl<'p, 'a, 'c> Deref for AffineParser<'p, 'a, 'c> {
+    type Target = Parser<'a, 'c>;
+
+    fn deref(&self) -> &Self::Target {
+        self.parser
+    }
+}
+
+impl<'p, 'a, 'c> DerefMut for AffineParser<'p, 'a, 'c> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.parser
+    }
+}
+
+impl<'p, 'a, 'c> AsParser<'a, 'c> for AffineParser<'p, 'a, 'c> {
+    fn parser(&mut self) -> &mut Parser<'a, 'c> {
+        self.parser
+    }
+}
+
+impl<'p, 'a, 'c> AffineParser<'p, 'a, 'c> {
+    pub fn new(parser: &'p mut Parser<'a, 'c>) -> Self {
+        Self { parser, dims_and_symbols: Vec::new() }
+    }
+
+    /// Create an affine binary high precedence op expression (mul's, div's, mod). `operator_location` is the location of the op token to be used to report errors for non-conforming expressions.
+    fn affine_binary_high_prec_op_expr(
+        &self,
+        op: AffineHighPrecOp,
+        lhs: AffineExpr,
+        rhs: AffineExpr,
+        operator_location: SMLoc
+    ) -> ParseResult<AffineExpr> {
+        let (kind, name) = match op {
+            AffineHighPrecOp::Mul => {
+                if !lhs.is_symbolic_or_constant() && !rhs.is_symbolic_or_constant() {
+                    return Err(self.emit_error_at(operator_location,
+                        "non-affine expression: at least one of the multiply operands has to be either a constant or symbolic"));
+                }
+                return Ok(affine_expr::binary_operation(Kind::Mul, lhs, rhs));
+            }
+            AffineHighPrecOp::FloorDiv => (Kind::FloorDiv, "floordiv"),
+            AffineHighPrecOp::CeilDiv => (Kind::CeilDiv, "ceildiv"),
+            AffineHighPrecOp::Mod => (Kind::Mod, "mod")
+        };
+        if !rhs.is_symbolic_or_constant() {
+            return Err(self.emit_error_at(operator_location, format!(
+                "non-affine expression: right operand of {} has to be either a constant or symbolic",
+                name)));
+        }
+        Ok(affine_expr::binary_operation(kind, lhs, rhs))
+    }
+
+    /// Create an affine binary low precedence op expression (add, sub).
+    fn affine_binary_low_prec_op_expr(
+        &mut self,
+        op: AffineLowPrecOp,
+        lhs: AffineExpr,
+        rhs: AffineExpr
+    ) -> AffineExpr {
+        match op {
+            AffineLowPrecOp::Add => affine_expr::binary_operation(Kind::Add, lhs, rhs),
+            AffineLowPrecOp::Sub => {
+                let rhs = self.negate(rhs);
+                affine_expr::binary_operation(Kind::Add, lhs, rhs)
+            }
+        }
+    }
+
+    /// Return `expr` multiplied by -1.
+    fn negate(&mut self, expr: AffineExpr) -> AffineExpr {
+        let minus_one = affine_expr::constant(-1, self.context_ptr());
+        affine_expr::binary_operation(Kind::Mul, expr, minus_one)
+    }
+
+    /// Consume this token if it is a lower precedence affine op (there are only two precedence levels).
+    fn consume_if_low_prec_op(&mut self) -> Option<AffineLowPrecOp> {
+        let op = match self.token().kind() {
+            TokenKind::Plus => AffineLowPrecOp::Add,
+            TokenKind::Minus => AffineLowPrecOp::Sub,
+            _ => return None
+        };
+        self.consume_token();
+        Some(op)
+    }
+
+    /// Consume this token if it is a higher precedence affine op (there are only two precedence levels).
+    fn consume_if_high_prec_op(&mut self) -> Option<AffineHighPrecOp> {
+        let op = match self.token().kind() {
+            TokenKind::Star => AffineHighPrecOp::Mul,
+            TokenKind::Keyword(Keyword::FloorDiv) => AffineHighPrecOp::FloorDiv,
+            TokenKind::Keyword(Keyword::CeilDiv) => AffineHighPrecOp::CeilDiv,
+            TokenKind::Keyword(Keyword::Mod) => AffineHighPrecOp::Mod,
+            _ => return None
+        };
+        self.consume_token();
+        Some(op)
+    }
+
+    /**
+    Parse a high precedence op expression list: mul, div, and mod are high precedence binary ops, i.e., parse a
+
+    ```text
+    expr_1 op_1 expr_2 op_2 ... expr_n
+    ```
+
+    where op_1, op_2 are all a AffineHighPrecOp (mul, div, mod). All affine binary ops are left associative.
+
+    Given `llhs`, returns (llhs llhs_op lhs) op expr, implementing left associativity. If `llhs` is None, returns lhs op expr. `llhs_op_location` is the location of the llhs_op token that will be used to report an error for non-conforming expressions.
+    */
+    fn parse_affine_high_prec_op_expr(
+        &mut self,
+        llhs: Option<(AffineExpr, AffineHighPrecOp, SMLoc)>
+    ) -> ParseResult<AffineExpr> {
+        let lhs = self.parse_affine_operand_expr(llhs.is_some())?;
+
+        // Found an LHS. Parse the remaining expression.
+        let operator_location = self.token().location();
+        if let Some(op) = self.consume_if_high_prec_op() {
+            let lhs = match llhs {
+                Some((llhs, llhs_op, _)) =>
+                    self.affine_binary_high_prec_op_expr(
+                        llhs_op, llhs, lhs, operator_location)?,
+                // No LLHS, get RHS
+                None => lhs
+            };
+            return self.parse_affine_high_prec_op_expr(
+                Some((lhs, op, operator_location)));
+        }
+
+        // This is the last operand in this expression.
+        match llhs {
+            Some((llhs, llhs_op, llhs_op_location)) =>
+                self.affine_binary_high_prec_op_expr(
+                    llhs_op, llhs, lhs, llhs_op_location),
+            // No llhs, 'lhs' itself is the expression.
+            None => Ok(lhs)
+        }
+    }
+
+    /**
+    Parse an affine expression inside parentheses.
+
+    ```text
+    affine-expr ::= `(` affine-expr `)`
+    ```
+    */
+    fn parse_parenthetical_expr(&mut self) -> ParseResult<AffineExpr> {
+        self.parse_token(TokenKind::LParen, "expected '('")?;
+        if self.token().is(TokenKind::RParen) {
+            return Err(self.emit_error("no expression inside parentheses"));
+        }
+        let expr = self.parse_affine_expr()?;
+        self.parse_token(TokenKind::RParen, "expected ')'")?;
+        Ok(expr)
+    }
+
+    /**
+    Parse the negation expression.
+
+    ```text
+    affine-expr ::= `-` affine-expr
+    ```
+    */
+    fn parse_negate_expression(&mut self, has_lhs: bool) -> ParseResult<AffineExpr> {
+        self.parse_token(TokenKind::Minus, "expected '-'")?;
+
+        // Since negation has the highest precedence of all ops (including high precedence ops) but lower than parentheses, we are only going to use parse_affine_operand_expr instead of parse_affine_expr here.
+        let operand = self.parse_affine_operand_expr(has_lhs)
+            // Extra error message although parse_affine_operand_expr would have complained. Leads to a better diagnostic.
+            .map_err(|_| self.emit_error("missing operand of negation"))?;
+        Ok(self.negate(operand))
+    }
+
+    /**
+    Parse a bare id that may appear in an affine expression.
+
+    ```text
+    affine-expr ::= bare-id
+    ```
+    */
+    fn parse_bare_id_expr(&mut self) -> ParseResult<AffineExpr> {
+        if !is_identifier(&self.token()) {
+            return Err(self.emit_wrong_token_error("expected bare identifier"));
+        }
+
+        let name = self.token_spelling();
+        if let Some((_, expr)) = self.dims_and_symbols.iter()
+            .find(|(entry, _)| *entry == name)
+        {
+            let expr = expr.clone();
+            self.consume_token();
+            return Ok(expr);
+        }
+        Err(self.emit_wrong_token_error("use of undeclared identifier"))
+    }
+
+    /**
+    Parse a positive integral constant appearing in an affine expression.
+
+    ```text
+    affine-expr ::= integer-literal
+    ```
+    */
+    fn parse_integer_expr(&mut self) -> ParseResult<AffineExpr> {
+        let value = Token::uint64_integer_value(self.token_spelling())
+            .and_then(|value| i64::try_from(value).ok())
+            .ok_or_else(|| self.emit_error("constant too large for index"))?;
+        self.consume_token_of(TokenKind::Integer);
+        Ok(affine_expr::constant(value, self.context_ptr()))
+    }
+
+    /**
+    Parses an expression that can be a valid operand of an affine expression. `has_lhs` is true if the operand is the rhs of a binary operator, which is used to determine whether an error should be emitted for a missing right operand.
+
+    E.g.: for an expression without parentheses (like i + j + k + l), each of the four identifiers is an operand. For i + j*k + l, j*k is not an operand expression, it's an op expression and will be parsed via parse_affine_high_prec_op_expr(). However, for i + (j*k) + -l, (j*k) and -l are operand expressions.
+    */
+    fn parse_affine_operand_expr(&mut self, has_lhs: bool) -> ParseResult<AffineExpr> {
+        match self.token().kind() {
+            // TODO: Parse SSA ids once custom operations can bind them to dimensions and symbols.
+            TokenKind::Keyword(Keyword::Symbol) | TokenKind::PercentIdentifier =>
+                Err(self.emit_wrong_token_error("unexpected ssa identifier")),
+            TokenKind::Integer => self.parse_integer_expr(),
+            TokenKind::LParen => self.parse_parenthetical_expr(),
+            TokenKind::Minus => self.parse_negate_expression(has_lhs),
+            TokenKind::Keyword(Keyword::CeilDiv | Keyword::FloorDiv | Keyword::Mod) =>
+                // Try to treat these tokens as identifiers.
+                self.parse_bare_id_expr(),
+            TokenKind::Plus | TokenKind::Star => Err(self.emit_error(
+                if has_lhs {
+                    "missing right operand of binary operator"
+                } else {
+                    "missing left operand of binary operator"
+                })),
+            // If nothing matches, we try to treat this token as an identifier.
+            _ if is_identifier(&self.token()) => self.parse_bare_id_expr(),
+            _ => Err(self.emit_error(
+                if has_lhs {
+                    "missing right operand of binary operator"
+                } else {
+                    "expected affine expression"
+                }))
+        }
+    }
+
+    /**
+    Parse affine expressions that are bare-id's, integer constants, parenthetical affine expressions, and affine op expressions that are a composition of those.
+
+    All binary op's associate from left to right.
+
+    {add, sub} have lower precedence than {mul, div, and mod}.
+
+    Add, sub'are themselves at the same precedence level. Mul, floordiv, ceildiv, and mod are at the same higher precedence level. Negation has higher precedence than any binary op.
+
+    `llhs` is the affine expression appearing on the left of the one being parsed. This function will return ((llhs llhs_op lhs) op rhs) if `llhs` is Some, and lhs op rhs otherwise; if there is no rhs, llhs llhs_op lhs is returned if `llhs` is Some; otherwise lhs is returned. This is to deal with left associativity.
+
+    E.g.: when the expression is e1 + e2*e3 + e4, with e1 as llhs, this function will return the affine expr equivalent of (e1 + (e2*e3)) + e4, where (e2*e3) will be a single operand expression, LHS.
+    */
+    fn parse_affine_low_prec_op_expr(
+        &mut self,
+        llhs: Option<(AffineExpr, AffineLowPrecOp)>
+    ) -> ParseResult<AffineExpr> {
+        let lhs = self.parse_affine_operand_expr(llhs.is_some())?;
+
+        // Found an LHS. Deal with the ops.
+        if let Some(op) = self.consume_if_low_prec_op() {
+            let lhs = match llhs {
+                Some((llhs, llhs_op)) =>
+                    self.affine_binary_low_prec_op_expr(llhs_op, llhs, lhs),
+                // No LLHS, get RHS and form the expression.
+                None => lhs
+            };
+            return self.parse_affine_low_prec_op_expr(Some((lhs, op)));
+        }
+        let operator_location = self.token().location();
+        if let Some(op) = self.consume_if_high_prec_op() {
+            // We have a higher precedence op here. Get the rhs operand for the llhs through parse_affine_high_prec_op_expr.
+            let high_result = self.parse_affine_high_prec_op_expr(
+                Some((lhs, op, operator_location)))?;
+
+            // If llhs is None, the product forms the first operand of the yet to be found expression. If Some, the op to associate with llhs is llhs_op.
+            let expr = match llhs {
+                Some((llhs, llhs_op)) =>
+                    self.affine_binary_low_prec_op_expr(llhs_op, llhs, high_result),
+                None => high_result
+            };
+
+            // Recurse for subsequent low prec op's after the affine high prec op expression.
+            if let Some(next_op) = self.consume_if_low_prec_op() {
+                return self.parse_affine_low_prec_op_expr(Some((expr, next_op)));
+            }
+            return Ok(expr);
+        }
+        // Last operand in the expression list.
+        Ok(match llhs {
+            Some((llhs, llhs_op)) =>
+                self.affine_binary_low_prec_op_expr(llhs_op, llhs, lhs),
+            // No llhs, 'lhs' itself is the expression.
+            None => lhs
+        })
+    }
+
+    /**
+    Parse an affine expression.
+
+    ```text
+    affine-expr ::= `(` affine-expr `)`
+                  | `-` affine-expr
+                  | affine-expr `+` affine-expr
+                  | affine-expr `-` affine-expr
+                  | affine-expr `*` affine-expr
+                  | affine-expr `floordiv` affine-expr
+                  | affine-expr `ceildiv` affine-expr
+                  | affine-expr `mod` affine-expr
+                  | bare-id
+                  | integer-literal
+    ```
+
+    Additional conditions are checked depending on the production. For eg., one of the operands for `*` has to be either constant/symbolic; the second operand for floordiv, ceildiv, and mod has to be a positive integer.
+    */
+    pub fn parse_affine_expr(&mut self) -> ParseResult<AffineExpr> {
+        self.parse_affine_low_prec_op_expr(None)
+    }
+
+    /// Parse a dim or symbol from the lists appearing before the actual expressions of the affine map. Update our state to store the dimensional/symbolic identifier.
+    fn parse_identifier_definition(&mut self, id_expr: AffineExpr) -> ParseResult {
+        if !is_identifier(&self.token()) {
+            return Err(self.emit_wrong_token_error("expected bare identifier"));
+        }
+
+        let name = self.token_spelling();
+        if self.dims_and_symbols.iter().any(|(entry, _)| *entry == name) {
+            return Err(self.emit_error(format!("redefinition of identifier '{}'", name)));
+        }
+        self.consume_token();
+
+        self.dims_and_symbols.push((name, id_expr));
+        Ok(())
+    }
+
+    /// Parse the list of dimensional identifiers to an affine map.
+    fn parse_dim_id_list(&mut self) -> ParseResult<usize> {
+        let mut num_dims = 0;
+        self.parse_comma_separated_list(Delimiter::Paren, |this| {
+            let dimension = affine_expr::dimension(num_dims, this.context_ptr());
+            num_dims += 1;
+            this.parse_identifier_definition(dimension)
+        }, " in dimensional identifier list")?;
+        Ok(num_dims)
+    }
+
+    /// Parse the list of symbolic identifiers to an affine map.
+    fn parse_symbol_id_list(&mut self) -> ParseResult<usize> {
+        let mut num_symbols = 0;
+        self.parse_comma_separated_list(Delimiter::Square, |this| {
+            let symbol = affine_expr::symbol(num_symbols, this.context_ptr());
+            num_symbols += 1;
+            this.parse_identifier_definition(symbol)
+        }, " in symbol list")?;
+        Ok(num_symbols)
+    }
+
+    /// Parse the list of dimensional identifiers, followed by an optional list of symbolic identifiers.
+    fn parse_dim_and_optional_symbol_id_list(&mut self) -> ParseResult<(usize, usize)> {
+        let num_dims = self.parse_dim_id_list()?;
+        if self.token().is_not(TokenKind::LSquare) {
+            return Ok((num_dims, 0));
+        }
+        Ok((num_dims, self.parse_symbol_id_list()?))
+    }
+
+    /// Parses an ambiguous affine map or integer set definition inline.
+    pub fn parse_affine_map_or_integer_set_inline(&mut self)
+        -> ParseResult<AffineMapOrIntegerSet>
+    {
+        // List of dimensional and optional symbol identifiers.
+        let (num_dims, num_symbols) = self.parse_dim_and_optional_symbol_id_list()?;
+
+        if self.consume_if(TokenKind::Arrow) {
+            return Ok(AffineMapOrIntegerSet::AffineMap(
+                self.parse_affine_map_range(num_dims, num_symbols)?));
+        }
+
+        self.parse_token(TokenKind::Colon, "expected '->' or ':'")?;
+        Ok(AffineMapOrIntegerSet::IntegerSet(
+            self.parse_integer_set_constraints(num_dims, num_symbols)?))
+    }
+
+    /**
+    Parse the range and sizes affine map definition inline.
+
+    ```text
+    affine-map ::= dim-and-symbol-id-lists `->` multi-dim-affine-expr
+
+    multi-dim-affine-expr ::= `(` `)`
+    multi-dim-affine-expr ::= `(` affine-expr (`,` affine-expr)* `)`
+    ```
+    */
+    fn parse_affine_map_range(&mut self, num_dims: usize, num_symbols: usize)
+        -> ParseResult<AffineMap>
+    {
+        let mut exprs = Vec::new();
+        // Parse a multi-dimensional affine expression (a comma-separated list of 1-d affine expressions).
+        self.parse_comma_separated_list(Delimiter::Paren, |this| {
+            exprs.push(this.parse_affine_expr()?);
+            Ok(())
+        }, " in affine map range")?;
+
+        // Parsed a valid affine map.
+        Ok(AffineMap::new(num_dims, num_symbols, exprs, self.context_ptr()))
+    }
+
+    /**
+    Parse an affine constraint.
+
+    ```text
+    affine-constraint ::= affine-expr `>=` `affine-expr`
+                        | affine-expr `<=` `affine-expr`
+                        | affine-expr `==` `affine-expr`
+    ```
+
+    The constraint is normalised to
+
+    ```text
+    affine-constraint ::= affine-expr `>=` `0`
+                        | affine-expr `==` `0`
+    ```
+
+    before returning, along with whether it is an equality.
+    */
+    fn parse_affine_constraint(&mut self) -> ParseResult<(AffineExpr, bool)> {
+        let lhs = self.parse_affine_expr()?;
+
+        // affine-constraint ::= `affine-expr` `>=` `affine-expr`
+        if self.consume_if(TokenKind::Greater) && self.consume_if(TokenKind::Equal) {
+            let rhs = self.parse_affine_expr()?;
+            return Ok((self.affine_binary_low_prec_op_expr(AffineLowPrecOp::Sub, lhs, rhs), false));
+        }
+
+        // affine-constraint ::= `affine-expr` `<=` `affine-expr`
+        if self.consume_if(TokenKind::Less) && self.consume_if(TokenKind::Equal) {
+            let rhs = self.parse_affine_expr()?;
+            return Ok((self.affine_binary_low_prec_op_expr(AffineLowPrecOp::Sub, rhs, lhs), false));
+        }
+
+        // affine-constraint ::= `affine-expr` `==` `affine-expr`
+        if self.consume_if(TokenKind::Equal) && self.consume_if(TokenKind::Equal) {
+            let rhs = self.parse_affine_expr()?;
+            return Ok((self.affine_binary_low_prec_op_expr(AffineLowPrecOp::Sub, lhs, rhs), true));
+        }
+
+        Err(self.emit_error(
+            "expected '== affine-expr' or '>= affine-expr' at end of affine constraint"))
+    }
+
+    /**
+    Parse the constraints that are part of an integer set definition.
+
+    ```text
+    integer-set-inline
+        ::= dim-and-symbol-id-lists `:` '(' affine-constraint-conjunction? ')'
+    affine-constraint-conjunction ::= affine-constraint (`,` affine-constraint)*
+    ```
+    */
+    fn parse_integer_set_constraints(&mut self, num_dims: usize, num_symbols: usize)
+        -> ParseResult<IntegerSet>
+    {
+        let mut constraints = Vec::new();
+        let mut eq_flags = Vec::new();
+        // Parse a list of affine constraints (comma-separated).
+        self.parse_comma_separated_list(Delimiter::Paren, |this| {
+            let (constraint, is_eq) = this.parse_affine_constraint()?;
+            constraints.push(constraint);
+            eq_flags.push(is_eq);
+            Ok(())
+        }, " in integer set constraint list")?;
+
+        // If no constraints were parsed, then treat this as a degenerate 'true' case, i.e. 0 == 0.
+        if constraints.is_empty() {
+            constraints.push(affine_expr::constant(0, self.context_ptr()));
+            eq_flags.push(true);
+        }
+
+        // Parsed a valid integer set.
+        Ok(IntegerSet::new(num_dims, num_symbols, constraints, eq_flags))
+    }
+}
+
+impl<'a, 'c> Parser<'a, 'c> {
+    /// Parse a reference to an affine map.
+    pub fn parse_affine_map_reference(&mut self) -> ParseResult<AffineMap> {
+        let location = self.token().location();
+        match AffineParser::new(self).parse_affine_map_or_integer_set_inline()? {
+            AffineMapOrIntegerSet::AffineMap(map) => Ok(map),
+            AffineMapOrIntegerSet::IntegerSet(_) => Err(self.emit_error_at(
+                location, "expected AffineMap, but got IntegerSet"))
+        }
+    }
+
+    /// Parse a reference to an integer set.
+    pub fn parse_integer_set_reference(&mut self) -> ParseResult<IntegerSet> {
+        let location = self.token().location();
+        match AffineParser::new(self).parse_affine_map_or_integer_set_inline()? {
+            AffineMapOrIntegerSet::IntegerSet(set) => Ok(set),
+            AffineMapOrIntegerSet::AffineMap(_) => Err(self.emit_error_at(
+                location, "expected IntegerSet, but got AffineMap"))
+        }
+    }
 }
@@ -2,59 +2,371 @@
 This file is contains the interface to the MLIR assembly parser library.
 
 - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/AsmParser/AsmParser.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/Parser.cpp>
 */
 
+use core::ops::{Deref, DerefMut};
+use std::sync::Arc;
+
+use llvm::support::sm_loc::SMLoc;
 use crate::{
-    mlir::{
-        asm_parser::{
-            asm_parser_state::AsmParserState,
-            code_complete::AsmParserCodeCompleteContext
-        },
-        ir::{
-            asm_state::ParserConfig,
-            block::Block
-        }
+    asm_parser::{
+        parser::{AsParser, OperationParser, ParseResult, Parser},
+        parser_state::{ParserState, SymbolState},
+        token::{Keyword, Token, TokenKind}
     },
-    llvm::support::source_manager::SourceManager
+    ir::{
+        arena::BlockId,
+        asm_state::{AsmParsedResourceEntry, ParserConfig},
+        attribute::Attribute,
+        builtins::{
+            attributes::StringAttribute,
+            operations::ModuleOp
+        },
+        diagnostics::emit_warning,
+        location::Location,
+        r#type::Type
+    }
 };
 
+/// This parser handles entities that are only valid at the top level of the file.
+struct TopLevelOperationParser<'a, 'c> {
+    parser: OperationParser<'a, 'c>
+}
+
+impl<'a, 'c> Deref for TopLevelOperationParser<'a, 'c> {
+    type Target = OperationParser<'a, 'c>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.parser
+    }
+}
+
+impl<'a, 'c> DerefMut for TopLevelOperationParser<'a, 'c> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.parser
+    }
+}
+
+impl<'a, 'c> AsParser<'a, 'c> for TopLevelOperationParser<'a, 'c> {
+    fn parser(&mut self) -> &mut Parser<'a, 'c> {
+        self.parser.parser()
+    }
+}
+
+impl<'a, 'c> TopLevelOperationParser<'a, 'c> {
+    /// Parse a set of operations into the end of the body of the top level operation, along with the aliases and file metadata found at the top level.
+    fn parse(&mut self) -> ParseResult {
+        loop {
+            match self.token().kind() {
+                // We made it to the end of the file successfully.
+                TokenKind::Eof => return self.finalize(),
+
+                // Parse an attribute alias.
+                TokenKind::HashIdentifier => self.parse_attribute_alias_def()?,
+
+                // Parse a type alias.
+                TokenKind::ExclamationIdentifier => self.parse_type_alias_def()?,
+
+                // Parse a file-level metadata dictionary.
+                TokenKind::FileMetadataBegin => self.parse_file_metadata_dictionary()?,
+
+                // Parse a top-level operation. If we got to some other token, this must be an operation.
+                _ => self.parse_operation()?
+            }
+        }
+    }
+
+    /**
+    Parse an attribute alias declaration.
+
+    ```text
+    attribute-alias-def ::= '#' alias-name `=` attribute-value
+    ```
+    */
+    fn parse_attribute_alias_def(&mut self) -> ParseResult {
+        assert!(self.token().is(TokenKind::HashIdentifier));
+        let alias_name = &self.token_spelling()[1..];
+
+        // Check for redefinitions.
+        let symbols = &self.state.symbols;
+        if symbols.attribute_alias_definitions.contains_key(alias_name)
+            || symbols.location_alias_definitions.contains_key(alias_name)
+        {
+            return Err(self.emit_error(format!(
+                "redefinition of attribute alias id '{}'", alias_name)));
+        }
+
+        // Make sure this isn't invading the dialect attribute namespace.
+        if alias_name.contains('.') {
+            return Err(self.emit_error(
+                "attribute names with a '.' are reserved for dialect-defined names"));
+        }
+
+        self.consume_token_of(TokenKind::HashIdentifier);
+
+        // Parse the '='.
+        self.parse_token(
+            TokenKind::Equal, "expected '=' in attribute alias definition")?;
+
+        // Locations are not attributes, so aliases of them are recorded on their own.
+        if self.consume_if(TokenKind::Keyword(Keyword::Loc)) {
+            self.parse_token(TokenKind::LParen, "expected '(' in location")?;
+            let location = self.parse_location_instance()?;
+            self.parse_token(TokenKind::RParen, "expected ')' in location")?;
+            self.state.symbols.location_alias_definitions
+                .insert(alias_name.to_string(), location);
+            return Ok(());
+        }
+
+        // Parse the attribute value.
+        let attribute = self.parse_attribute(None)?;
+        self.state.symbols.attribute_alias_definitions
+            .insert(alias_name.to_string(), attribute);
+        Ok(())
+    }
+
+    /**
+    Parse a type alias declaration.
+
+    ```text
+    type-alias-def ::= '!' alias-name `=` type
+    ```
+    */
+    fn parse_type_alias_def(&mut self) -> ParseResult {
+        assert!(self.token().is(TokenKind::ExclamationIdentifier));
+        let alias_name = &self.token_spelling()[1..];
+
+        // Check for redefinitions.
+        if self.state.symbols.type_alias_definitions.contains_key(alias_name) {
+            return Err(self.emit_error(format!(
+                "redefinition of type alias id '{}'", alias_name)));
+        }
+
+        // Make sure this isn't invading the dialect type namespace.
+        if alias_name.contains('.') {
+            return Err(self.emit_error(
+                "type names with a '.' are reserved for dialect-defined names"));
+        }
+        self.consume_token_of(TokenKind::ExclamationIdentifier);
+
+        // Parse the '='.
+        self.parse_token(TokenKind::Equal, "expected '=' in type alias definition")?;
+
+        // Parse the type.
+        let alias_type = self.parse_type()?;
+
+        // Register this alias with the parser state.
+        self.state.symbols.type_alias_definitions
+            .insert(alias_name.to_string(), alias_type);
+        Ok(())
+    }
+
+    /**
+    Parse a top-level file metadata dictionary.
+
+    ```text
+    file-metadata-dict ::= '{-#' file-metadata-entry* `#-}'
+    ```
+    */
+    fn parse_file_metadata_dictionary(&mut self) -> ParseResult {
+        self.consume_token_of(TokenKind::FileMetadataBegin);
+        self.parse_comma_separated_list_until(TokenKind::FileMetadataEnd, |this| {
+            // Parse the key of the metadata dictionary.
+            let key_location = this.token().location();
+            let key = this.parse_optional_keyword().ok_or_else(|| this.emit_error(
+                "expected identifier key in file metadata dictionary"))?;
+            this.parse_token(TokenKind::Colon, "expected ':'")?;
+
+            // Process the metadata entry.
+            match key {
+                "dialect_resources" => this.parse_dialect_resource_file_metadata(),
+                "external_resources" => this.parse_external_resource_file_metadata(),
+                _ => Err(this.emit_error_at(key_location, format!(
+                    "unknown key '{}' in file metadata dictionary", key)))
+            }
+        }, true)
+    }
+
+    /// Parse a resource metadata dictionary, calling `parse_body` on the name and location of each of its groups after the opening `{` of the group.
+    fn parse_resource_file_metadata(
+        &mut self,
+        mut parse_body: impl FnMut(&mut Self, &'a str, SMLoc) -> ParseResult
+    ) -> ParseResult {
+        self.parse_token(TokenKind::LBrace, "expected '{'")?;
+        self.parse_comma_separated_list_until(TokenKind::RBrace, |this| {
+            // Parse the top-level name entry.
+            let name_location = this.token().location();
+            let name = this.parse_optional_keyword().ok_or_else(||
+                this.emit_error("expected identifier key for 'resource' entry"))?;
+            this.parse_token(TokenKind::Colon, "expected ':'")?;
+            this.parse_token(TokenKind::LBrace, "expected '{'")?;
+            parse_body(this, name, name_location)
+        }, true)
+    }
+
+    fn parse_dialect_resource_file_metadata(&mut self) -> ParseResult {
+        self.parse_resource_file_metadata(|this, name, name_location| {
+            // Check that the dialect can handle a resource entry.
+            if this.state.config.dialect_interface(name).is_none() {
+                return Err(this.emit_error_at(name_location, format!(
+                    "unexpected 'resource' section for dialect '{}'", name)));
+            }
+
+            this.parse_comma_separated_list_until(TokenKind::RBrace, |this| {
+                // Parse the name of the resource entry.
+                let key_location = this.token().location();
+                let handle = this.parse_resource_handle(name)?;
+                this.parse_token(TokenKind::Colon, "expected ':'")?;
+                let entry = this.parse_resource_entry(handle.key())?;
+
+                this.state.config.dialect_interface(name).unwrap()
+                    .parse_resource(&entry)
+                    .map_err(|error| this.emit_error_at(key_location, error.to_string()))
+            }, true)
+        })
+    }
+
+    fn parse_external_resource_file_metadata(&mut self) -> ParseResult {
+        self.parse_resource_file_metadata(|this, name, name_location| {
+            // TODO: Should we require handling external resources in some scenarios?
+            let is_handled = this.state.config.resource_parser(name).is_some()
+                || this.state.config.fallback_resource_map().is_some();
+            if !is_handled {
+                emit_warning(
+                    this.context(),
+                    this.encoded_source_location(name_location),
+                    format!("ignoring unknown external resources for '{}'", name));
+            }
+
+            this.parse_comma_separated_list_until(TokenKind::RBrace, |this| {
+                // Parse the name of the resource entry.
+                let key_location = this.token().location();
+                let key = match this.token().kind() {
+                    TokenKind::String => {
+                        let key = this.token().string_value();
+                        this.consume_token();
+                        key
+                    }
+                    _ => this.parse_optional_keyword()
+                        .ok_or_else(|| this.emit_error(
+                            "expected identifier key for 'external_resources' entry"))?
+                        .to_string()
+                };
+                this.parse_token(TokenKind::Colon, "expected ':'")?;
+                let entry = this.parse_resource_entry(key)?;
+
+                let config = &mut this.state.config;
+                let result = match config.resource_parser(name) {
+                    Some(parser) => parser.parse_resource(&entry),
+                    None => match config.fallback_resource_map() {
+                        Some(fallback) => fallback.parse_resource(name, &entry),
+                        None => return Ok(())
+                    }
+                };
+                result.map_err(|error| this.emit_error_at(key_location, error.to_string()))
+            }, true)
+        })
+    }
+
+    /// Parse the value of a resource entry with the given key. Values are either booleans or strings, hex blobs being spelled as strings.
+    fn parse_resource_entry(&mut self, key: impl Into<String>)
+        -> ParseResult<AsmParsedResourceEntry>
+    {
+        let token: Token = self.token();
+        let entry = match token.kind() {
+            TokenKind::Keyword(Keyword::True) => AsmParsedResourceEntry::from_bool(key, true),
+            TokenKind::Keyword(Keyword::False) => AsmParsedResourceEntry::from_bool(key, false),
+            TokenKind::String => AsmParsedResourceEntry::from_literal(key, token.string_value()),
+            _ => return Err(self.emit_wrong_token_error(
+                "expected 'true', 'false' or string literal resource value"))
+        };
+        self.consume_token();
+        Ok(entry)
+    }
+}
+
 /**
-This parses the file specified by the indicated `SourceManager` and appends parsed operations to the given block. If the block is non-empty, the operations are placed before the current terminator. If parsing is successful, success is returned. Otherwise, an error message is emitted through the error handler registered in the context, and failure is returned. If `sourceFileLoc` is non-null, it is populated with a file location representing the start of the source file that is being parsed. If `asmState` is non-null, it is populated with detailed information about the parsed IR (including exact locations for SSA uses and definitions). `asmState` should only be provided if this detailed information is desired. If `codeCompleteContext` is non-null, it is used to signal tracking of a code completion event (generally only ever useful for LSP or other high level language tooling).
+This parses the source `source`, named `filename`, and appends parsed operations to the given block. If parsing is successful, success is returned. Otherwise, an error message is emitted through the diagnostic engine of the context, and failure is returned.
 */
 pub fn parse_asm_source_file(
-    source_manager: &SourceManager,
-    block: *mut Block,
-    config: &ParserConfig,
-    asm_state: Option<AsmParserState>, // = nullptr,
-    code_complete_context: Option<AsmParserCodeCompleteContext> //= nullptr
-) -> Result<(), Box<dyn std::error::Error>> {
-    Ok(())
+    source: &str,
+    filename: &str,
+    block: BlockId,
+    config: &mut ParserConfig
+) -> ParseResult {
+    let mut symbols = SymbolState::new();
+    let state = ParserState::new(source, filename, config, &mut symbols);
+    let mut parser = Parser::new(state);
+
+    // Create a top-level operation to contain the parsed state.
+    let location = Location::file_line_col(StringAttribute::new(filename), 0, 0);
+    let top_level_op = ModuleOp::build(parser.context_mut(), location, None);
+    let top_level_op = parser.check(top_level_op)?;
+    let body = parser.check(top_level_op.body(parser.arena()))?;
+
+    let mut parser = TopLevelOperationParser {
+        parser: OperationParser::new(parser, top_level_op.operation(), body)
+    };
+    if let Err(error) = parser.parse() {
+        parser.parser.discard();
+        return Err(error);
+    }
+
+    // Splice the parsed operations over to the provided top-level block.
+    let arena = parser.arena_mut();
+    let result = arena.block(body)
+        .map(|body| body.operations().to_vec())
+        .and_then(|parsed_ops| parsed_ops.into_iter().try_for_each(|op| {
+            arena.remove(op)?;
+            arena.push_back(block, op)
+        }))
+        .and_then(|_| arena.erase(top_level_op.operation()));
+    parser.check(result)
 }
 
 /**
-This parses a single MLIR attribute to an MLIR context if it was valid. If not, an error message is emitted through a new SourceManagerDiagnosticHandler constructed from a new SourceManager with a single a MemoryBuffer wrapping
-`attrStr`. If the passed `attrStr` has additional tokens that were not part
-of the type, an error is emitted.
+Parse an attribute from the start of `text`, returning it along with the number of bytes of `text` that were read. Any characters after the attribute are left unparsed. Errors are emitted through the diagnostic engine of the context.
 */
-// TODO: Improve diagnostic reporting.
-Attribute parseAttribute(llvm::StringRef attrStr, MLIRContext *context);
+pub fn parse_attribute(text: &str, config: &mut ParserConfig)
+    -> ParseResult<(Arc<dyn Attribute>, usize)>
+{
+    parse_symbol(text, config, |parser| parser.parse_attribute(None))
+}
 
 /**
-This parses a single MLIR type to an MLIR context if it was valid.  If not,
-an error message is emitted through a new SourceManagerDiagnosticHandler
-constructed from a new SourceManager with a single a MemoryBuffer wrapping
-`typeStr`. If the passed `typeStr` has additional tokens that were not part
-of the type, an error is emitted.
+Parse a type from the start of `text`, returning it along with the number of bytes of `text` that were read.
 */
-// TODO: Improve diagnostic reporting.
-Type parseType(llvm::StringRef typeStr, MLIRContext *context);
-
+pub fn parse_type(text: &str, config: &mut ParserConfig)
+    -> ParseResult<(Arc<dyn Type>, usize)>
+{
+    parse_symbol(text, config, |parser| parser.parse_type())
+}
 
 /**
-This parses a single IntegerSet/AffineMap to an MLIR context if it was
-valid. If not, an error message is emitted through a new
-SourceManagerDiagnosticHandler constructed from a new SourceManager with a single
-MemoryBuffer wrapping `str`. If the passed `str` has additional tokens that
-were not part of the IntegerSet/AffineMap, a failure is returned.
+Parse a location of the form `loc(location-inst)` from the start of `text`, returning it along with the number of bytes of `text` that were read.
 */
-AffineMap parseAffineMap(llvm::StringRef str, MLIRContext *context);
+pub fn parse_location(text: &str, config: &mut ParserConfig)
+    -> ParseResult<(Location, usize)>
+{
+    parse_symbol(text, config, |parser| {
+        parser.parse_token(TokenKind::Keyword(Keyword::Loc), "expected 'loc' keyword")?;
+        parser.parse_token(TokenKind::LParen, "expected '(' in location")?;
+        let location = parser.parse_location_instance()?;
+        parser.parse_token(TokenKind::RParen, "expected ')' in location")?;
+        Ok(location)
+    })
+}
+
+/// Parse a single symbol from the start of `text` with `parse`, returning it along with the number of bytes consumed.
+fn parse_symbol<T>(
+    text: &str,
+    config: &mut ParserConfig,
+    parse: impl FnOnce(&mut Parser) -> ParseResult<T>
+) -> ParseResult<(T, usize)> {
+    let mut symbols = SymbolState::new();
+    let state = ParserState::new(text, "", config, &mut symbols);
+    let mut parser = Parser::new(state);
+    let symbol = parse(&mut parser)?;
+    Ok((symbol, parser.token().location().offset()))
+}
@@ -4,135 +4,1040 @@ This file implements the parser for the MLIR Types.
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/AttributeParser.cpp>
 */
 
+use std::{collections::HashSet, sync::Arc};
+
+use llvm::{
+    adt::ap_int::APInt,
+    support::sm_loc::SMLoc
+};
 use crate::{
-    mlir::{
-        asm_parser::{
-            asm_parser_impl,
-            asm_parser_state,
-            parser::Parser,
-            token::{Token, TokenKind}
-        },
-        ir::{
-            affine_map,
-            attribute::Attribute,
-            builtins::{
-                attributes,
-                dialect,
-                type_interfaces::ShapedType,
-                types
-            },
-            dialect::implementation,
-            dialect_resource_blob_manager,
-            integer_set,
-            operation::{
-                definition::OptionalParseResult,
-                support::NamedAttrList
+    asm_parser::{
+        parser::{AsParser, ParseResult, Parser},
+        token::{Keyword, Token, TokenKind}
+    },
+    ir::{
+        attribute::{Attribute, NamedAttribute},
+        builtins::{
+            attributes::{
+                AffineMapAttribute, ArrayAttribute, DenseArrayAttribute,
+                DenseIntOrFPElementsAttribute, DenseResourceElementsAttribute,
+                DictionaryAttribute, FloatAttribute, IntegerAttribute,
+                IntegerSetAttribute, StridedLayoutAttribute, StringAttribute,
+                SymbolRefAttribute, TypeAttribute, UnitAttribute
             },
-            r#type::Type,
+            type_interfaces::DYNAMIC,
+            types::{
+                ComplexType, FloatType, FloatTypeKind, IndexType, IntegerType,
+                RankedTensorType, Signedness, UnrankedTensorType, VectorType
+            }
         },
-        support::logical_result::LogicalResult
-    },
-    llvm::{
-        adt::string_extras,
-        support::endian
+        operation::implementation::Delimiter,
+        r#type::Type
     }
 };
 
-// impl<'a> Parser<'a> {
-//     /**
-//     Parse an arbitrary attribute.
+/// The bit width used to store the values of `index` typed attributes.
+const INDEX_STORAGE_BIT_WIDTH: usize = 64;
+
+/// Returns the bit width of an integer or index type, or None for any other type.
+fn integer_or_index_width(r#type: &dyn Type) -> Option<usize> {
+    if let Some(r#type) = r#type.downcast_ref::<IntegerType>() {
+        return Some(r#type.width());
+    }
+    r#type.downcast_ref::<IndexType>().map(|_| INDEX_STORAGE_BIT_WIDTH)
+}
+
+/// Returns true if `type` is the signless `i1` type.
+fn is_bool(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<IntegerType>()
+        .map_or(false, |r#type| r#type.width() == 1 && r#type.is_signless())
+}
+
+/// Returns true if `type` is an unsigned integer type.
+fn is_unsigned_integer(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<IntegerType>().map_or(false, IntegerType::is_unsigned)
+}
+
+/**
+Construct an APInt of the width of `type` from a decimal or hexadecimal integer literal `value`, the magnitude of the parsed literal. Returns None if the value does not fit.
+*/
+fn build_attribute_ap_int(r#type: &dyn Type, is_negative: bool, value: &APInt)
+    -> Option<APInt>
+{
+    // Extend or truncate the bitwidth to the right size.
+    let width = integer_or_index_width(r#type)?;
+    // The literal can be unnecessarily wide with leading zeros. This isn't a problem, but truncating off bits is bad.
+    if value.active_bits() as usize > width {
+        return None;
+    }
+    let result = APInt::from_words(width as u32, value.words());
+
+    if is_negative {
+        // The value is negative, we have an overflow if the sign bit is not set in the negated value.
+        let result = result.neg();
+        if !result.is_zero() && !result.is_negative() {
+            return None;
+        }
+        return Some(result);
+    }
+    let is_signed = r#type.downcast_ref::<IntegerType>()
+        .map_or(true, IntegerType::is_signed);
+    // The value is a positive signed integer or index, we have an overflow if the sign bit is set.
+    if is_signed && result.is_negative() {
+        return None;
+    }
+    Some(result)
+}
+
+/// Shift `value` right by `shift` bits, rounding to nearest with ties to even.
+fn round_shift(value: u64, shift: u32) -> u64 {
+    if shift >= u64::BITS {
+        return 0;
+    }
+    if shift == 0 {
+        return value;
+    }
+    let truncated = value >> shift;
+    let remainder = value & ((1 << shift) - 1);
+    let half = 1 << (shift - 1);
+    if remainder > half || (remainder == half && truncated & 1 == 1) {
+        truncated + 1
+    } else {
+        truncated
+    }
+}
+
+/// Convert `value` to the bit pattern of an IEEE-754 style binary format with `exponent_bits` exponent bits and `mantissa_bits` explicit mantissa bits, rounding to nearest even.
+fn ieee_float_bits(value: f64, exponent_bits: u32, mantissa_bits: u32) -> u64 {
+    let bits = value.to_bits();
+    let sign = (bits >> 63) << (exponent_bits + mantissa_bits);
+    let exponent = (bits >> 52 & 0x7FF) as i64;
+    let mantissa = bits & ((1 << 52) - 1);
+    let max_exponent = (1u64 << exponent_bits) - 1;
+    let infinity = max_exponent << mantissa_bits;
+    if exponent == 0x7FF {
+        // Keep NaNs quiet.
+        let quiet = if mantissa != 0 { 1 << (mantissa_bits - 1) } else { 0 };
+        return sign | infinity | quiet;
+    }
+    if exponent == 0 {
+        // Subnormal doubles are too small for any of the narrower formats.
+        return sign;
+    }
+    let bias = (max_exponent >> 1) as i64;
+    let unbiased = exponent - 1023;
+    if unbiased > bias {
+        return sign | infinity;
+    }
+    let shift = 52 - mantissa_bits;
+    let magnitude = if unbiased >= 1 - bias {
+        // A carry out of the mantissa correctly bumps the exponent, up to infinity.
+        round_shift(((unbiased + bias) as u64) << 52 | mantissa, shift)
+    } else {
+        // Subnormal results shift the implicit bit into the mantissa.
+        round_shift(mantissa | 1 << 52, shift + (1 - bias - unbiased) as u32)
+    };
+    sign | magnitude.min(infinity)
+}
+
+/// Returns the shape and element type of a shaped type, or None if `type` is not shaped. The shape is None for unranked types.
+fn shaped_type_parts(r#type: &dyn Type) -> Option<(Option<&[i64]>, &Arc<dyn Type>)> {
+    if let Some(r#type) = r#type.downcast_ref::<RankedTensorType>() {
+        return Some((Some(r#type.shape()), r#type.element_type()));
+    }
+    if let Some(r#type) = r#type.downcast_ref::<UnrankedTensorType>() {
+        return Some((None, r#type.element_type()));
+    }
+    if let Some(r#type) = r#type.downcast_ref::<VectorType>() {
+        return Some((Some(r#type.shape()), r#type.element_type()));
+    }
+    None
+}
+
+/// Write the element `value` at `index` into little-endian `data`. Elements are rounded up to whole bytes, except `i1` elements which are packed one bit each.
+fn write_element(data: &mut [u8], index: usize, value: &APInt) {
+    let width = value.width() as usize;
+    if width == 1 {
+        if !value.is_zero() {
+            data[index / 8] |= 1 << (index % 8);
+        }
+        return;
+    }
+    let num_bytes = width.div_ceil(8);
+    let start = index * num_bytes;
+    for (offset, byte) in data[start..start + num_bytes].iter_mut().enumerate() {
+        *byte = (value.words()[offset / 8] >> (offset % 8 * 8)) as u8;
+    }
+}
+
+/// Pack `values` into the raw data of a dense elements attribute, where each element consists of `num_parts` values, compressing it to a single element if all the elements are the same.
+fn pack_elements(values: &[APInt], num_parts: usize) -> (Vec<u8>, bool) {
+    let first = &values[..num_parts.min(values.len())];
+    let is_splat = !values.is_empty()
+        && values.chunks(num_parts).all(|element| element == first);
+    let values = if is_splat { first } else { values };
+    let width = values.first().map_or(8, |value| value.width() as usize);
+    let num_bytes = if width == 1 {
+        values.len().div_ceil(8)
+    } else {
+        values.len() * width.div_ceil(8)
+    };
+    let mut data = vec![0; num_bytes];
+    for (index, value) in values.iter().enumerate() {
+        write_element(&mut data, index, value);
+    }
+    (data, is_splat)
+}
+
+impl<'a, 'c> Parser<'a, 'c> {
+    /**
+    Parse an arbitrary attribute.
+
+    ```text
+    attribute-value ::= `unit`
+                      | bool-literal
+                      | integer-literal (`:` (index-type | integer-type))?
+                      | float-literal (`:` float-type)?
+                      | string-literal (`:` type)?
+                      | type
+                      | `[` `:` (integer-type | float-type) tensor-literal `]`
+                      | `[` (attribute-value (`,` attribute-value)*)? `]`
+                      | `{` (attribute-entry (`,` attribute-entry)*)? `}`
+                      | symbol-ref-id (`::` symbol-ref-id)*
+                      | `dense` `<` tensor-literal `>` `:`
+                        (tensor-type | vector-type)
+                      | `sparse` `<` attribute-value `,` attribute-value `>`
+                        `:` (tensor-type | vector-type)
+                      | `strided` `<` `[` comma-separated-int-or-question `]`
+                        (`,` `offset` `:` integer-literal)? `>`
+                      | extended-attribute
+    ```
+    */
+    pub fn parse_attribute(&mut self, r#type: Option<Arc<dyn Type>>)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        match self.token().kind() {
+            // Parse an AffineMap or IntegerSet attribute.
+            TokenKind::Keyword(Keyword::AffineMap) => {
+                self.consume_token();
+                self.parse_token(TokenKind::Less, "expected '<' in affine map")?;
+                let map = self.parse_affine_map_reference()?;
+                self.parse_token(TokenKind::Greater, "expected '>' in affine map")?;
+                Ok(Arc::new(AffineMapAttribute::new(map)))
+            }
+            TokenKind::Keyword(Keyword::AffineSet) => {
+                self.consume_token();
+                self.parse_token(TokenKind::Less, "expected '<' in integer set")?;
+                let set = self.parse_integer_set_reference()?;
+                self.parse_token(TokenKind::Greater, "expected '>' in integer set")?;
+                Ok(Arc::new(IntegerSetAttribute::new(set)))
+            }
+
+            // Parse an array attribute.
+            TokenKind::LSquare => {
+                let mut elements = Vec::new();
+                self.parse_comma_separated_list(Delimiter::Square, |this| {
+                    elements.push(this.parse_attribute(None)?);
+                    Ok(())
+                }, "")?;
+                Ok(Arc::new(ArrayAttribute::new(elements)))
+            }
+
+            // Parse a boolean attribute.
+            TokenKind::Keyword(Keyword::False) => {
+                self.consume_token();
+                Ok(Arc::new(IntegerAttribute::new(
+                    Arc::new(IntegerType::new(1, Signedness::Signless)),
+                    APInt::new(1, 0, false))))
+            }
+            TokenKind::Keyword(Keyword::True) => {
+                self.consume_token();
+                Ok(Arc::new(IntegerAttribute::new(
+                    Arc::new(IntegerType::new(1, Signedness::Signless)),
+                    APInt::new(1, 1, false))))
+            }
+
+            // Parse a dense elements attribute.
+            TokenKind::Keyword(Keyword::Dense) => self.parse_dense_elements_attr(r#type),
+
+            // Parse a dense resource elements attribute.
+            TokenKind::Keyword(Keyword::DenseResource) =>
+                self.parse_dense_resource_elements_attr(r#type),
+
+            // Parse a dense array attribute.
+            TokenKind::Keyword(Keyword::Array) => self.parse_dense_array_attr(),
+
+            // Parse a dictionary attribute.
+            TokenKind::LBrace => {
+                let elements = self.parse_attribute_dict()?;
+                Ok(Arc::new(DictionaryAttribute::new(elements)))
+            }
+
+            // Parse an extended attribute, i.e. alias or dialect attribute.
+            TokenKind::HashIdentifier => self.parse_extended_attr(r#type),
+
+            // Parse floating point and integer attributes.
+            TokenKind::FloatLiteral => self.parse_float_attr(r#type, false),
+            TokenKind::Integer => self.parse_dec_or_hex_attr(r#type, false),
+            TokenKind::Minus => {
+                self.consume_token_of(TokenKind::Minus);
+                if self.token().is(TokenKind::Integer) {
+                    return self.parse_dec_or_hex_attr(r#type, true);
+                }
+                if self.token().is(TokenKind::FloatLiteral) {
+                    return self.parse_float_attr(r#type, true);
+                }
+                Err(self.emit_wrong_token_error(
+                    "expected constant integer or floating point value"))
+            }
+
+            // Locations are not attributes, so they can't be used as attribute values.
+            TokenKind::Keyword(Keyword::Loc) =>
+                Err(self.emit_error("location attributes are not supported")),
+
+            // Parse a sparse elements attribute.
+            TokenKind::Keyword(Keyword::Sparse) =>
+                // TODO: Parse sparse elements attributes.
+                Err(self.emit_error("sparse elements attributes are not supported yet")),
+
+            // Parse a strided layout attribute.
+            TokenKind::Keyword(Keyword::Strided) => self.parse_strided_layout_attr(),
+
+            // Parse a string attribute.
+            TokenKind::String => {
+                let value = self.token().string_value();
+                self.consume_token_of(TokenKind::String);
+                // Parse the optional trailing colon type if one wasn't explicitly provided.
+                let mut r#type = r#type;
+                if r#type.is_none() && self.consume_if(TokenKind::Colon) {
+                    r#type = Some(self.parse_type()?);
+                }
+                Ok(Arc::new(match r#type {
+                    Some(r#type) => StringAttribute::with_type(value, r#type),
+                    None => StringAttribute::new(value)
+                }))
+            }
+
+            // Parse a symbol reference attribute.
+            TokenKind::AtIdentifier => {
+                let root_reference = self.token().symbol_reference();
+                self.consume_token_of(TokenKind::AtIdentifier);
+
+                // Parse any nested references.
+                let mut nested_references = Vec::new();
+                while self.token().is(TokenKind::Colon) {
+                    // Check for the '::' prefix.
+                    let offset = self.token().location().offset();
+                    self.consume_token_of(TokenKind::Colon);
+                    if !self.consume_if(TokenKind::Colon) {
+                        if !self.token().is_any(&[TokenKind::Eof, TokenKind::Error]) {
+                            self.reset_token(offset);
+                        }
+                        break;
+                    }
+
+                    // Parse the reference itself.
+                    if self.token().is_not(TokenKind::AtIdentifier) {
+                        return Err(self.emit_error(
+                            "expected nested symbol reference identifier"));
+                    }
+                    nested_references.push(
+                        StringAttribute::new(self.token().symbol_reference()));
+                    self.consume_token_of(TokenKind::AtIdentifier);
+                }
+                Ok(Arc::new(SymbolRefAttribute::new(
+                    StringAttribute::new(root_reference), nested_references)))
+            }
+
+            // Parse a 'unit' attribute.
+            TokenKind::Keyword(Keyword::Unit) => {
+                self.consume_token();
+                Ok(Arc::new(UnitAttribute::new()))
+            }
+
+            // Otherwise, this must be a type attribute.
+            _ => match self.parse_optional_type() {
+                Some(r#type) => Ok(Arc::new(TypeAttribute::new(r#type?))),
+                None => Err(self.emit_wrong_token_error("expected attribute value"))
+            }
+        }
+    }
+
+    /**
+    Attribute dictionary.
+
+    ```text
+    attribute-dict ::= `{` `}`
+                     | `{` attribute-entry (`,` attribute-entry)* `}`
+    attribute-entry ::= (bare-id | string-literal) `=` attribute-value
+    ```
+    */
+    pub fn parse_attribute_dict(&mut self) -> ParseResult<Vec<NamedAttribute>> {
+        let mut attributes = Vec::new();
+        let mut seen_keys = HashSet::new();
+        self.parse_comma_separated_list(Delimiter::Braces, |this| {
+            // The name of an attribute can either be a bare identifier, or a string.
+            let name = if this.token().is(TokenKind::String) {
+                this.token().string_value()
+            } else if this.token().is_any(&[TokenKind::BareIdentifier, TokenKind::IntType])
+                || this.token().is_keyword()
+            {
+                this.token_spelling().to_string()
+            } else {
+                return Err(this.emit_wrong_token_error("expected attribute name"));
+            };
+            if name.is_empty() {
+                return Err(this.emit_error("expected valid attribute name"));
+            }
+            if !seen_keys.insert(name.clone()) {
+                return Err(this.emit_error(format!(
+                    "duplicate key '{}' in dictionary attribute", name)));
+            }
+            this.consume_token();
+
+            // Try to parse the '=' for the attribute value.
+            let value: Arc<dyn Attribute> = if this.consume_if(TokenKind::Equal) {
+                this.parse_attribute(None)?
+            } else {
+                // If there is no '=', we treat this as a unit attribute.
+                Arc::new(UnitAttribute::new())
+            };
+            attributes.push(NamedAttribute::new(StringAttribute::new(name), value));
+            Ok(())
+        }, " in attribute dictionary")?;
+        Ok(attributes)
+    }
+
+    /// Return the bit pattern of the float `value` in the semantics of `type`.
+    fn float_bits(&self, location: SMLoc, r#type: &FloatType, value: f64)
+        -> ParseResult<APInt>
+    {
+        let bits = match r#type.kind() {
+            FloatTypeKind::Float8E5M2Type => ieee_float_bits(value, 5, 2),
+            FloatTypeKind::Float16Type => ieee_float_bits(value, 5, 10),
+            FloatTypeKind::BFloat16Type => ieee_float_bits(value, 8, 7),
+            FloatTypeKind::Float32Type => ieee_float_bits(value, 8, 23),
+            FloatTypeKind::Float64Type => value.to_bits(),
+            // TODO: Convert decimal literals to the remaining float semantics.
+            _ => return Err(self.emit_error_at(location, format!(
+                "decimal literals of type '{}' are not supported yet, use a hexadecimal literal",
+                r#type.keyword())))
+        };
+        Ok(APInt::new(r#type.width() as u32, bits, false))
+    }
+
+    /// Parse a float attribute.
+    fn parse_float_attr(&mut self, r#type: Option<Arc<dyn Type>>, is_negative: bool)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        let location = self.token().location();
+        let value = self.token().float_value().ok_or_else(||
+            self.emit_error("floating point value too large for attribute"))?;
+        self.consume_token_of(TokenKind::FloatLiteral);
+        let r#type = match r#type {
+            Some(r#type) => r#type,
+            // Default to F64 when no type is specified.
+            None if !self.consume_if(TokenKind::Colon) =>
+                Arc::new(FloatType::new(FloatTypeKind::Float64Type)),
+            None => self.parse_type()?
+        };
+        let float_type = r#type.downcast_ref::<FloatType>().ok_or_else(||
+            self.emit_error("floating point value not valid for specified type"))?;
+        let bits = self.float_bits(
+            location, float_type, if is_negative { -value } else { value })?;
+        Ok(Arc::new(FloatAttribute::new(r#type, bits)))
+    }
+
+    /// Parse a decimal or a hexadecimal literal, which can be either an integer or a float attribute.
+    fn parse_dec_or_hex_attr(&mut self, r#type: Option<Arc<dyn Type>>, is_negative: bool)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        let token = self.token();
+        let location = token.location();
+        self.consume_token_of(TokenKind::Integer);
+        let r#type = match r#type {
+            Some(r#type) => r#type,
+            // Default to i64 if not type is specified.
+            None if !self.consume_if(TokenKind::Colon) =>
+                Arc::new(IntegerType::new(64, Signedness::Signless)),
+            None => self.parse_type()?
+        };
 
-//     attribute-value ::= `unit`
-//                        | bool-literal
-//                        | integer-literal (`:` (index-type | integer-type))?
-//                        | float-literal (`:` float-type)?
-//                        | string-literal (`:` type)?
-//                        | type
-//                        | `[` `:` (integer-type | float-type) tensor-literal `]`
-//                        | `[` (attribute-value (`,` attribute-value)*)? `]`
-//                        | `{` (attribute-entry (`,` attribute-entry)*)? `}`
-//                        | symbol-ref-id (`::` symbol-ref-id)*
-//                        | `dense` `<` tensor-literal `>` `:`
-//                          (tensor-type | vector-type)
-//                        | `sparse` `<` attribute-value `,` attribute-value `>`
-//                          `:` (tensor-type | vector-type)
-//                        | `strided` `<` `[` comma-separated-int-or-question `]`
-//                          (`,` `offset` `:` integer-literal)? `>`
-//                        | extended-attribute
+        if let Some(float_type) = r#type.downcast_ref::<FloatType>() {
+            let bits = self.parse_float_from_integer_literal(
+                token, is_negative, float_type.width())?;
+            return Ok(Arc::new(FloatAttribute::new(r#type, bits)));
+        }
 
-//     */
-//     pub fn parse_attribute(&self, r#type: Type) -> impl Attribute {
-//     }
+        if integer_or_index_width(r#type.as_ref()).is_none() {
+            return Err(self.emit_error_at(
+                location, "integer literal not valid for specified type"));
+        }
 
-//     // T: Attribute | ArrayAttr | StringAttribute | SymbolRefAttr
-//     /// Parse an optional attribute with the provided type.
-//     pub fn parse_optional_attribute<T>(&self, attribute: &T, r#type: Type) -> OptionalParseResult {
+        if is_negative && is_unsigned_integer(r#type.as_ref()) {
+            return Err(self.emit_error_at(
+                location,
+                "negative integer literal not valid for unsigned integer type"));
+        }
 
-//     }
+        let value = self.integer_value(token)?;
+        let value = build_attribute_ap_int(r#type.as_ref(), is_negative, &value)
+            .ok_or_else(|| self.emit_error_at(
+                location, "integer constant out of range for attribute"))?;
+        Ok(Arc::new(IntegerAttribute::new(r#type, value)))
+    }
+
+    /// Parse a dense elements attribute.
+    fn parse_dense_elements_attr(&mut self, attr_type: Option<Arc<dyn Type>>)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        self.consume_token_of(TokenKind::Keyword(Keyword::Dense));
+        self.parse_token(TokenKind::Less, "expected '<' after 'dense'")?;
+
+        // Parse the literal data if necessary.
+        let mut literal_parser = TensorLiteralParser::new();
+        if !self.consume_if(TokenKind::Greater) {
+            literal_parser.parse(self, true)?;
+            self.parse_token(TokenKind::Greater, "expected '>'")?;
+        }
+
+        // If the type is specified `parse_elements_literal_type` will not parse a type.
+        let type_location = self.token().location();
+        let r#type = self.parse_elements_literal_type(attr_type)?;
+        literal_parser.attribute(self, type_location, r#type)
+    }
+
+    /**
+    Shaped type for elements attribute.
+
+    ```text
+    elements-literal-type ::= vector-type | ranked-tensor-type
+    ```
+
+    This method also checks the type has static shape.
+    */
+    fn parse_elements_literal_type(&mut self, r#type: Option<Arc<dyn Type>>)
+        -> ParseResult<Arc<dyn Type>>
+    {
+        // If the user didn't provide a type, parse the colon type for the literal.
+        let r#type = match r#type {
+            Some(r#type) => r#type,
+            None => {
+                self.parse_token(TokenKind::Colon, "expected ':'")?;
+                self.parse_type()?
+            }
+        };
+        let shape = match shaped_type_parts(r#type.as_ref()) {
+            Some((shape, _)) => shape,
+            None => return Err(self.emit_error("elements literal must be a shaped type"))
+        };
+        if !shape.map_or(false, |shape| !shape.contains(&DYNAMIC)) {
+            return Err(self.emit_error("elements literal type must have static shape"));
+        }
+        Ok(r#type)
+    }
+
+    /// Parse a dense resource elements attribute.
+    fn parse_dense_resource_elements_attr(&mut self, attr_type: Option<Arc<dyn Type>>)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        let location = self.token().location();
+        self.consume_token();
+        self.parse_token(TokenKind::Less, "expected '<' after 'dense_resource'")?;
+
+        // Parse the resource handle.
+        let handle = self.parse_resource_handle("builtin")?;
+        self.parse_token(TokenKind::Greater, "expected '>'")?;
+
+        // Parse the type of the attribute if the user didn't provide one.
+        let (type_location, r#type) = match attr_type {
+            Some(r#type) => (location, r#type),
+            None => {
+                let type_location = self.token().location();
+                self.parse_token(TokenKind::Colon, "expected ':'")?;
+                (type_location, self.parse_type()?)
+            }
+        };
+        if shaped_type_parts(r#type.as_ref()).is_none() {
+            return Err(self.emit_error_at(
+                type_location, "`dense_resource` expected a shaped type"));
+        }
+        Ok(Arc::new(DenseResourceElementsAttribute::new(r#type, handle)))
+    }
+
+    /**
+    Parse a dense array attribute.
+
+    ```text
+    dense-array-attribute ::= `array` `<` (integer-type | float-type)
+                              (`:` integer-literal-or-float-literal-list)? `>`
+    ```
+    */
+    fn parse_dense_array_attr(&mut self) -> ParseResult<Arc<dyn Attribute>> {
+        self.consume_token_of(TokenKind::Keyword(Keyword::Array));
+        self.parse_token(TokenKind::Less, "expected '<' after 'array'")?;
+
+        let type_location = self.token().location();
+        let element_type = self.parse_type()?;
+
+        // Only bool or integer and floating point elements divisible by bytes are supported.
+        let width = match (integer_or_index_width(element_type.as_ref()),
+                           element_type.downcast_ref::<FloatType>())
+        {
+            (Some(width), _) => width,
+            (None, Some(float_type)) => float_type.width(),
+            _ => return Err(self.emit_error_at(type_location, format!(
+                "expected integer or float type, got: {}", element_type)))
+        };
+        if !is_bool(element_type.as_ref()) && width % 8 != 0 {
+            return Err(self.emit_error_at(
+                type_location, "element type bitwidth must be a multiple of 8"));
+        }
+
+        let mut element_parser = DenseArrayElementParser::new(element_type);
+
+        // Check for empty list.
+        if self.consume_if(TokenKind::Greater) {
+            return Ok(element_parser.attribute());
+        }
+
+        self.parse_token(TokenKind::Colon, "expected ':' after dense array type")?;
+        if integer_or_index_width(element_parser.r#type.as_ref()).is_some() {
+            self.parse_comma_separated_list(Delimiter::None, |this| {
+                element_parser.parse_integer_element(this)
+            }, "")?;
+        } else {
+            self.parse_comma_separated_list(Delimiter::None, |this| {
+                element_parser.parse_float_element(this)
+            }, "")?;
+        }
+        self.parse_token(TokenKind::Greater, "expected '>' to close an array attribute")?;
+        Ok(element_parser.attribute())
+    }
+
+    /**
+    Parse a strided layout attribute.
+
+    ```text
+    strided-layout-attribute ::= `strided` `<` `[` stride-list `]`
+                                 (`,` `offset` `:` dimension)? `>`
+    ```
+    */
+    fn parse_strided_layout_attr(&mut self) -> ParseResult<Arc<dyn Attribute>> {
+        // Errors of the verifier are reported at the keyword token location.
+        let location = self.token().location();
+        self.consume_token_of(TokenKind::Keyword(Keyword::Strided));
+        self.parse_token(TokenKind::Less, "expected '<' after 'strided'")?;
+        self.parse_token(TokenKind::LSquare, "expected '['")?;
+
+        // Parse strides.
+        let mut strides = Vec::new();
+        if self.token().is_not(TokenKind::RSquare) {
+            loop {
+                strides.push(self.parse_stride_or_offset()?);
+                if !self.consume_if(TokenKind::Comma) {
+                    break;
+                }
+            }
+        }
+        self.parse_token(TokenKind::RSquare, "expected ']'")?;
+
+        // Check if we are parsing the offset, or if we are at the end of the attribute.
+        let mut offset = 0;
+        if !self.consume_if(TokenKind::Greater) {
+            self.parse_token(TokenKind::Comma, "expected ','")?;
+            self.parse_token(TokenKind::Keyword(Keyword::Offset),
+                             "expected 'offset' after comma")?;
+            self.parse_token(TokenKind::Colon, "expected ':' after 'offset'")?;
+            offset = self.parse_stride_or_offset()?;
+            self.parse_token(TokenKind::Greater, "expected '>'")?;
+        }
+
+        if strides.contains(&0) {
+            return Err(self.emit_error_at(location, "strides must not be zero"));
+        }
+        Ok(Arc::new(StridedLayoutAttribute::new(offset, strides)))
+    }
+
+    /// Parses either an integer token or a question mark token for a dynamic value. The integer token must fit into i64.
+    fn parse_stride_or_offset(&mut self) -> ParseResult<i64> {
+        if self.consume_if(TokenKind::Question) {
+            return Ok(DYNAMIC);
+        }
+
+        let location = self.token().location();
+        let is_negative = self.consume_if(TokenKind::Minus);
+        let value = self.token().is(TokenKind::Integer)
+            .then(|| Token::uint64_integer_value(self.token_spelling()))
+            .flatten()
+            .and_then(|value| i64::try_from(value).ok())
+            .ok_or_else(|| self.emit_error_at(
+                location, "expected a 64-bit signed integer or '?'"))?;
+        self.consume_token();
+        Ok(if is_negative { -value } else { value })
+    }
+}
+
+// ----------------------------------------------------------------------
+// TensorLiteralParser
+// ----------------------------------------------------------------------
 
-//     /// Parse an optional attribute that is demarcated by a specific token.
-//     pub fn parse_optional_attribute_with_token<T>(
-//         &self, kind: TokenKind, attribute: &mut T, r#type: Type
-//     ) -> OptionalParseResult {
-//         // if getToken().isNot(kind) {
-//         //     return None
-//         // }
+/// This class implements a parser for TensorLiterals. A tensor literal is either a single element (e.g, 5) or a multi-dimensional list of elements (e.g., [[5, 5]]).
+struct TensorLiteralParser<'a> {
+    /// The shape inferred from the parsed elements.
+    shape: Vec<i64>,
 
-//         // match self.parse_attribute(r#type) {
-//         //     Ok(parsed_attr) => {
-//         //         *attr = parsed_attr.cast<AttributeT>();
-//         //         return success()
-//         //     },
-//         //     Err(_) => return Err(())
-//         // }
-//     }
+    /// Storage used when parsing elements, this is a pair of <is_negated, token>.
+    storage: Vec<(bool, Token<'a>)>,
 
-//     /// Parse an attribute dictionary.
-//     pub fn parse_attribute_dict(&self, attributes: &NamedAttrList) -> LogicalResult {
+    /// Storage used when parsing elements that were stored as hex values.
+    hex_storage: Option<Token<'a>>
+}
 
-//     }
+impl<'a> TensorLiteralParser<'a> {
+    fn new() -> Self {
+        Self { shape: Vec::new(), storage: Vec::new(), hex_storage: None }
+    }
+
+    /// Parse the elements of a tensor literal. If `allow_hex` is true, the parser may also parse the elements as a hex string.
+    fn parse(&mut self, parser: &mut Parser<'a, '_>, allow_hex: bool) -> ParseResult {
+        // If hex is allowed, check for a string literal.
+        if allow_hex && parser.token().is(TokenKind::String) {
+            self.hex_storage = Some(parser.token());
+            parser.consume_token_of(TokenKind::String);
+            return Ok(());
+        }
+        // Otherwise, parse a list or an individual element.
+        if parser.token().is(TokenKind::LSquare) {
+            self.shape = self.parse_list(parser)?;
+            return Ok(());
+        }
+        self.parse_element(parser)
+    }
+
+    /// Build a dense attribute instance with the parsed elements and the given shaped type.
+    fn attribute(&self, parser: &mut Parser<'a, '_>, location: SMLoc, r#type: Arc<dyn Type>)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        let (shape, element_type) = shaped_type_parts(r#type.as_ref())
+            .expect("elements literal type must be shaped");
+        let shape = shape.expect("elements literal type must be ranked");
+        let num_elements: i64 = shape.iter().product();
+
+        // Handle complex types in the specific element type cases below.
+        let (scalar_type, num_parts) = match element_type.downcast_ref::<ComplexType>() {
+            Some(complex) => (complex.element_type().clone(), 2),
+            None => (element_type.clone(), 1)
+        };
+        let is_int_or_float = integer_or_index_width(scalar_type.as_ref()).is_some()
+            || scalar_type.downcast_ref::<FloatType>().is_some();
+
+        // Check to see if we parse the literal from a hex string.
+        if let Some(token) = self.hex_storage && is_int_or_float {
+            return Self::hex_attribute(
+                parser, location, r#type.clone(), token, scalar_type.as_ref(),
+                num_elements as usize, num_parts);
+        }
+
+        // Check that the parsed storage size has the same number of elements to the type, or is a known splat.
+        if !self.shape.is_empty() && self.shape != shape {
+            return Err(parser.emit_error_at(location, format!(
+                "inferred shape of elements literal ([{}]) does not match type ([{}])",
+                join_shape(&self.shape), join_shape(shape))));
+        }
+
+        // Handle the case where no elements were parsed.
+        if self.hex_storage.is_none() && self.storage.is_empty() && num_elements != 0 {
+            return Err(parser.emit_error_at(location, format!(
+                "parsed zero elements, but type ({}) expected at least 1", r#type)));
+        }
 
-//     /// Parse an extended attribute.
-//     pub fn parse_extended_attr(&self, r#type: Type) -> impl Attribute {
+        if !is_int_or_float {
+            // TODO: Build dense string elements attributes.
+            return Err(parser.emit_error_at(
+                location, "dense string elements attributes are not supported yet"));
+        }
 
-//     }
+        let values = if integer_or_index_width(scalar_type.as_ref()).is_some() {
+            self.int_attr_elements(parser, scalar_type.as_ref())?
+        } else {
+            let float_type = scalar_type.downcast_ref::<FloatType>().unwrap();
+            self.float_attr_elements(parser, float_type)?
+        };
+        if num_parts == 2 && values.len() % 2 != 0 {
+            return Err(parser.emit_error_at(
+                location, "expected complex elements, but parsed a scalar"));
+        }
 
-//     /// Parse a float attribute.
-//     pub fn parse_float_attr(&self, r#type: Type, is_negative: bool) -> impl Attribute {
+        // A single element literal is splatted to the whole shape.
+        let (raw_data, is_splat) = pack_elements(&values, num_parts);
+        Ok(Arc::new(DenseIntOrFPElementsAttribute::new(r#type, raw_data, is_splat)))
+    }
+
+    /// Build a dense elements attribute from the hex string in `token`.
+    fn hex_attribute(
+        parser: &mut Parser<'a, '_>,
+        location: SMLoc,
+        r#type: Arc<dyn Type>,
+        token: Token<'a>,
+        scalar_type: &dyn Type,
+        num_elements: usize,
+        num_parts: usize
+    ) -> ParseResult<Arc<dyn Attribute>> {
+        let data = token.hex_string_value().ok_or_else(|| parser.emit_error_at(
+            token.location(), "expected string containing hex digits starting with `0x`"))?;
+
+        let width = integer_or_index_width(scalar_type)
+            .or_else(|| scalar_type.downcast_ref::<FloatType>().map(FloatType::width))
+            .unwrap();
+        let size_of = |num_scalars: usize| if width == 1 {
+            num_scalars.div_ceil(8)
+        } else {
+            num_scalars * width.div_ceil(8)
+        };
+        // The data either holds every element, or a single element splatted to the whole shape.
+        let is_splat = if data.len() == size_of(num_elements * num_parts) {
+            num_elements == 1
+        } else if data.len() == size_of(num_parts) {
+            true
+        } else {
+            return Err(parser.emit_error_at(location, format!(
+                "elements hex data size is invalid for provided type: {}", r#type)));
+        };
+        Ok(Arc::new(DenseIntOrFPElementsAttribute::new(r#type, data, is_splat)))
+    }
 
-//     }
+    /// Get the parsed elements for an integer attribute.
+    fn int_attr_elements(&self, parser: &mut Parser<'a, '_>, element_type: &dyn Type)
+        -> ParseResult<Vec<APInt>>
+    {
+        let is_unsigned = is_unsigned_integer(element_type);
+        let mut values = Vec::with_capacity(self.storage.len());
+        for &(is_negative, token) in &self.storage {
+            let location = token.location();
+            if is_negative && is_unsigned {
+                return Err(parser.emit_error_at(location,
+                    "expected unsigned integer elements, but parsed negative value"));
+            }
 
-//     /// Parse a decimal or a hexadecimal literal, which can be either an integer
-//     /// or a float attribute.
-//     pub fn parse_dec_or_hex_attr(&self,r#type: Type, is_negative: bool) -> impl Attribute {
+            // Check to see if floating point values were parsed.
+            if token.is(TokenKind::FloatLiteral) {
+                return Err(parser.emit_error_at(location,
+                    "expected integer elements, but parsed floating-point"));
+            }
 
-//     }
+            if token.is_any(&[TokenKind::Keyword(Keyword::True),
+                              TokenKind::Keyword(Keyword::False)]) {
+                if !is_bool(element_type) {
+                    return Err(parser.emit_error_at(location,
+                        "expected i1 type for 'true' or 'false' values"));
+                }
+                values.push(APInt::new(
+                    1, token.is(TokenKind::Keyword(Keyword::True)) as u64, false));
+                continue;
+            }
 
-//     /// Parse a dense elements attribute.
-//     pub fn parse_dense_elements_attr(&self, r#type: Type) -> impl Attribute {
+            // Create APInt values for each element with the correct bitwidth.
+            let value = parser.integer_value(token)?;
+            let value = build_attribute_ap_int(element_type, is_negative, &value)
+                .ok_or_else(|| parser.emit_error_at(
+                    location, "integer constant out of range for type"))?;
+            values.push(value);
+        }
+        Ok(values)
+    }
 
-//     }
-//     pub fn parse_elements_literal_type(&self, r#type: Type) -> ShapedType {
+    /// Get the parsed elements for a float attribute.
+    fn float_attr_elements(&self, parser: &mut Parser<'a, '_>, element_type: &FloatType)
+        -> ParseResult<Vec<APInt>>
+    {
+        let mut values = Vec::with_capacity(self.storage.len());
+        for &(is_negative, token) in &self.storage {
+            // Handle hexadecimal float literals.
+            if token.is(TokenKind::Integer) && token.spelling().starts_with("0x") {
+                values.push(parser.parse_float_from_integer_literal(
+                    token, is_negative, element_type.width())?);
+                continue;
+            }
 
-//     }
+            // Check to see if any decimal integers or booleans were parsed.
+            if token.is_not(TokenKind::FloatLiteral) {
+                return Err(parser.emit_error_at(token.location(),
+                    "expected floating-point elements, but parsed integer"));
+            }
 
-//     /// Parse a dense resource elements attribute.
-//     pub fn parse_dense_resource_elements_attr(&self, r#type: Type) -> Attribute {
+            // Build the float values from tokens.
+            let value = token.float_value().ok_or_else(|| parser.emit_error_at(
+                token.location(), "floating point value too large for attribute"))?;
+            values.push(parser.float_bits(
+                token.location(), element_type, if is_negative { -value } else { value })?);
+        }
+        Ok(values)
+    }
 
-//     }
+    /// Parse a single element, returning failure if it isn't a valid element literal. For example:
+    /// parse_element(1) -> Success, 1
+    /// parse_element([1]) -> Failure
+    fn parse_element(&mut self, parser: &mut Parser<'a, '_>) -> ParseResult {
+        match parser.token().kind() {
+            // Parse a boolean element.
+            TokenKind::Keyword(Keyword::True | Keyword::False)
+            | TokenKind::FloatLiteral
+            | TokenKind::Integer
+            | TokenKind::String => {
+                self.storage.push((false, parser.token()));
+                parser.consume_token();
+            }
 
-//     /// Parse a DenseArrayAttr.
-//     pub fn parse_dense_array_attr(&self, r#type: Type) -> impl Attribute {
+            // Parse a signed integer or a negative floating-point element.
+            TokenKind::Minus => {
+                parser.consume_token_of(TokenKind::Minus);
+                if !parser.token().is_any(&[TokenKind::FloatLiteral, TokenKind::Integer]) {
+                    return Err(parser.emit_error(
+                        "expected integer or floating point literal"));
+                }
+                self.storage.push((true, parser.token()));
+                parser.consume_token();
+            }
 
-//     }
+            // Parse a complex element of the form '(' element ',' element ')'.
+            TokenKind::LParen => {
+                parser.consume_token_of(TokenKind::LParen);
+                self.parse_element(parser)?;
+                parser.parse_token(TokenKind::Comma, "expected ',' between complex elements")?;
+                self.parse_element(parser)?;
+                parser.parse_token(TokenKind::RParen, "expected ')' after complex elements")?;
+            }
 
-//     /// Parse a sparse elements attribute.
-//     pub fn parse_sparse_elements_attr(&self, r#type: Type) -> impl Attribute {
+            _ => return Err(parser.emit_error("expected element literal of primitive type"))
+        }
+        Ok(())
+    }
 
-//     }
+    /// Parse a list of either lists or elements, returning the dimensions of the parsed sub-tensors. For example:
+    /// parse_list([1, 2, 3]) -> Success, [3]
+    /// parse_list([[1, 2], [3, 4]]) -> Success, [2, 2]
+    /// parse_list([[1, 2], 3]) -> Failure
+    /// parse_list([[1, [2, 3]], [4, [5]]]) -> Failure
+    fn parse_list(&mut self, parser: &mut Parser<'a, '_>) -> ParseResult<Vec<i64>> {
+        let mut first = true;
+        let mut new_dims = Vec::new();
+        let mut size = 0;
+        parser.parse_comma_separated_list(Delimiter::Square, |parser| {
+            let this_dims = if parser.token().is(TokenKind::LSquare) {
+                self.parse_list(parser)?
+            } else {
+                self.parse_element(parser)?;
+                Vec::new()
+            };
+            size += 1;
+            if first {
+                new_dims = this_dims;
+                first = false;
+            } else if new_dims != this_dims {
+                return Err(parser.emit_error(
+                    "tensor literal is invalid; ranks are not consistent between elements"));
+            }
+            Ok(())
+        }, "")?;
 
-//     /// Parse a strided layout attribute.
-//     pub fn parse_strided_layout_attr(&self) -> impl Attribute {
+        // Return the sublists' dimensions with 'size' prepended.
+        let mut dims = vec![size];
+        dims.extend(new_dims);
+        Ok(dims)
+    }
+}
 
-//     }
-// }
+/// Print a shape as a comma-separated list for diagnostics.
+fn join_shape(shape: &[i64]) -> String {
+    shape.iter().map(i64::to_string).collect::<Vec<_>>().join(", ")
+}
+
+// ----------------------------------------------------------------------
+// DenseArrayElementParser
+// ----------------------------------------------------------------------
+
+/// This class provides an implementation of AsmParser, allowing to call back into the libMLIRIR-provided APIs for invoking attribute parsing code.
+struct DenseArrayElementParser {
+    /// The element type of the array.
+    r#type: Arc<dyn Type>,
+
+    /// The raw data of the array, one byte per bool element.
+    raw_data: Vec<u8>,
+
+    /// The number of elements parsed.
+    size: i64
+}
+
+impl DenseArrayElementParser {
+    fn new(r#type: Arc<dyn Type>) -> Self {
+        Self { r#type, raw_data: Vec::new(), size: 0 }
+    }
+
+    /// Append the raw data of an APInt to the result.
+    fn append(&mut self, value: &APInt) {
+        let num_bytes = (value.width() as usize).div_ceil(8);
+        for offset in 0..num_bytes {
+            self.raw_data.push((value.words()[offset / 8] >> (offset % 8 * 8)) as u8);
+        }
+        self.size += 1;
+    }
+
+    /// Parse an integer element.
+    fn parse_integer_element(&mut self, parser: &mut Parser) -> ParseResult {
+        let is_negative = parser.consume_if(TokenKind::Minus);
+
+        // Parse an integer literal as an APInt.
+        let token = parser.token();
+        let value = if token.is_any(&[TokenKind::Keyword(Keyword::True),
+                                      TokenKind::Keyword(Keyword::False)]) {
+            if !is_bool(self.r#type.as_ref()) {
+                return Err(parser.emit_error(
+                    "expected i1 type for 'true' or 'false' values"));
+            }
+            parser.consume_token();
+            APInt::new(8, token.is(TokenKind::Keyword(Keyword::True)) as u64, false)
+        } else if parser.consume_if(TokenKind::Integer) {
+            let value = parser.integer_value(token)?;
+            let value = build_attribute_ap_int(self.r#type.as_ref(), is_negative, &value)
+                .ok_or_else(|| parser.emit_error_at(
+                    token.location(), "integer constant out of range"))?;
+            // Bools are stored as bytes.
+            if value.width() == 1 {
+                APInt::new(8, value.words()[0], false)
+            } else {
+                value
+            }
+        } else {
+            return Err(parser.emit_error("expected integer literal"));
+        };
+        self.append(&value);
+        Ok(())
+    }
+
+    /// Parse a floating point element.
+    fn parse_float_element(&mut self, parser: &mut Parser) -> ParseResult {
+        let is_negative = parser.consume_if(TokenKind::Minus);
+        let token = parser.token();
+        let float_type = self.r#type.downcast_ref::<FloatType>().unwrap();
+        let value = if parser.consume_if(TokenKind::Integer) {
+            // Parse an integer literal as a float.
+            parser.parse_float_from_integer_literal(token, is_negative, float_type.width())?
+        } else if parser.consume_if(TokenKind::FloatLiteral) {
+            // Parse a floating point literal.
+            let value = token.float_value().ok_or_else(|| parser.emit_error_at(
+                token.location(), "floating point value too large for attribute"))?;
+            parser.float_bits(
+                token.location(), float_type, if is_negative { -value } else { value })?
+        } else {
+            return Err(parser.emit_error("expected integer or floating point literal"));
+        };
+        self.append(&value);
+        Ok(())
+    }
+
+    /// Convert the current contents to a dense array.
+    fn attribute(self) -> Arc<dyn Attribute> {
+        Arc::new(DenseArrayAttribute::new(self.r#type, self.size, self.raw_data))
+    }
+}
@@ -1,25 +1,367 @@
 /*!
+This file implements the parser for the dialect symbols, such as extended attributes and types.
+
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/DialectSymbolParser.cpp>
 */
 
+use std::{collections::HashMap, error::Error, sync::Arc};
+
+use llvm::{adt::ap_int::APInt, support::sm_loc::SMLoc};
 use crate::{
-    mlir::{
-        asm_parser::asm_parser_impl,
-        ir::{
-            builtins::types,
-            dialect::{
-                self,
-                implementation
-            },
-        }
+    asm_parser::{
+        parser::{ParseResult, Parser},
+        parser_state::SymbolState,
+        token::TokenKind
     },
-    llvm::support::source_manager
+    ir::{
+        attribute::Attribute,
+        builtins::{
+            attributes::{OpaqueAttribute, StringAttribute},
+            types::OpaqueType
+        },
+        dialect::implementation::DialectAsmParser,
+        mlir_context::MLIRContext,
+        operation::implementation::AsmParser,
+        r#type::Type
+    }
 };
 
-/**
-This class provides the main implementation of the DialectAsmParser that allows for dialects to parse attributes and types. This allows for dialect hooking into the main MLIR parsing logic.
-*/
-pub struct CustomDialectAsmParser {
+impl<'a, 'c> Parser<'a, 'c> {
+    /// Parse the body of a dialect symbol, which starts and ends with <>'s, and may be recursive. Return with the body, starting at `start`, the byte offset of the body within the buffer. The current token must be the opening `<`.
+    ///
+    /// ```text
+    /// pretty-dialect-sym-body ::= '<' pretty-dialect-sym-contents+ '>'
+    /// pretty-dialect-sym-contents ::= pretty-dialect-sym-body
+    ///                              | '(' pretty-dialect-sym-contents+ ')'
+    ///                              | '[' pretty-dialect-sym-contents+ ']'
+    ///                              | '{' pretty-dialect-sym-contents+ '}'
+    ///                              | '[^[<({>\])}\0]+'
+    /// ```
+    pub fn parse_dialect_symbol_body(&mut self, start: usize) -> ParseResult<&'a str> {
+        // Symbol bodies are a relatively unstructured format that contains a series of properly nested punctuation, with anything else in the middle. Scan ahead to find it and consume it if successful, otherwise emit an error.
+        let buffer = self.state.lexer.buffer();
+        let byte_at = |offset: usize| buffer.as_bytes().get(offset).copied().unwrap_or(0);
+        let mut current = self.token().location().offset();
+
+        // Scan over the nested punctuation, bailing out on error and consuming until we find the end. We know that we're currently looking at the '<', so we can go until we find the matching '>' character.
+        assert!(byte_at(current) == b'<');
+        let mut nested_punctuation: Vec<u8> = Vec::new();
+
+        // Functor used to emit an unbalanced punctuation error.
+        let emit_punct_error = |this: &Self, nested_punctuation: &[u8]| this.emit_error(
+            format!("unbalanced '{}' character in pretty dialect name",
+                *nested_punctuation.last().unwrap() as char));
+
+        loop {
+            let char = byte_at(current);
+            current += 1;
+            let expected = match char {
+                b'\0' => {
+                    // This also handles the EOF case.
+                    if !nested_punctuation.is_empty() {
+                        return Err(emit_punct_error(self, &nested_punctuation));
+                    }
+                    return Err(self.emit_error("unexpected nul or EOF in pretty dialect name"));
+                }
+                b'<' | b'[' | b'(' | b'{' => {
+                    nested_punctuation.push(char);
+                    continue;
+                }
+                b'-' => {
+                    // The sequence `->` is treated as special token.
+                    if byte_at(current) == b'>' {
+                        current += 1;
+                    }
+                    continue;
+                }
+                b'>' => b'<',
+                b']' => b'[',
+                b')' => b'(',
+                b'}' => b'{',
+                b'"' => {
+                    // Dispatch to the lexer to lex past strings.
+                    self.reset_token(current - 1);
+                    current = self.token().end_location().offset();
+
+                    // Otherwise, ensure this token was actually a string.
+                    if self.token().is_not(TokenKind::String) {
+                        return Err(self.emit_error("expected string in pretty dialect name"));
+                    }
+                    if nested_punctuation.is_empty() {
+                        break;
+                    }
+                    continue;
+                }
+                _ => continue
+            };
+
+            // Check for unbalanced punctuation.
+            if nested_punctuation.last() != Some(&expected) {
+                if nested_punctuation.is_empty() {
+                    nested_punctuation.push(expected);
+                }
+                return Err(emit_punct_error(self, &nested_punctuation));
+            }
+            nested_punctuation.pop();
+            if nested_punctuation.is_empty() {
+                break;
+            }
+        }
+
+        // Ok, we succeeded, remember where we stopped, reset the lexer to know it is consuming all this stuff, and return.
+        self.reset_token(current);
+        Ok(&buffer[start..current])
+    }
+
+    /// Parse an extended dialect symbol, i.e. either an alias reference or a dialect symbol in the pretty or verbose form. `aliases` selects the alias definitions the symbol may refer to, and `create_symbol` builds the symbol from the dialect namespace, the symbol data and the location of the symbol data.
+    fn parse_extended_symbol<T: ?Sized>(
+        &mut self,
+        aliases: fn(&SymbolState) -> &HashMap<String, Arc<T>>,
+        create_symbol: impl FnOnce(&mut Self, &'a str, &'a str, SMLoc) -> ParseResult<Arc<T>>
+    ) -> ParseResult<Arc<T>> {
+        let token = self.token();
+        let identifier = &token.spelling()[1..];
+
+        // Parse the dialect namespace.
+        let mut location = token.location();
+        self.consume_token();
+
+        // Check to see if this is a pretty name.
+        let (dialect_name, mut symbol_data) = identifier.split_once('.')
+            .unwrap_or((identifier, ""));
+        let is_pretty_name = !symbol_data.is_empty() || identifier.ends_with('.');
+
+        // Check to see if the symbol has trailing data, i.e. has an immediately following '<'.
+        let has_trailing_data = self.token().is(TokenKind::Less)
+            && token.end_location() == self.token().location();
+
+        // If there is no '<' token following this, and if the typename contains no dot, then we are parsing a symbol alias.
+        if !has_trailing_data && !is_pretty_name {
+            // Check for an alias for this type.
+            return match aliases(self.state.symbols).get(identifier) {
+                Some(symbol) => Ok(symbol.clone()),
+                None => Err(self.emit_wrong_token_error(
+                    format!("undefined symbol alias id '{}'", identifier)))
+            };
+        }
+
+        // If this isn't an alias, we are parsing a dialect-specific symbol. If the name contains a dot, then this is the "pretty" form. If not, it is the verbose form that looks like <...>.
+        if !is_pretty_name {
+            // Parse the body of the symbol, and drop the enclosing `<>`.
+            let body = self.parse_dialect_symbol_body(token.end_location().offset())?;
+            symbol_data = &body[1..body.len() - 1];
+            location = SMLoc::from_offset(token.end_location().offset() + 1);
+        } else {
+            let start = token.location().offset() + 1 + dialect_name.len() + 1;
+            location = SMLoc::from_offset(start);
+
+            // If the dialect's symbol is followed immediately by a <, then lex the body of it into the pretty name.
+            if has_trailing_data {
+                symbol_data = self.parse_dialect_symbol_body(start)?;
+            }
+        }
+
+        create_symbol(self, dialect_name, symbol_data, location)
+    }
+
+    /**
+    Parse an extended attribute.
+
+    ```text
+    extended-attribute ::= (dialect-attribute | attribute-alias)
+    dialect-attribute  ::= `#` dialect-namespace `<` attr-data `>`
+                           (`:` type)?
+                         | `#` alias-name pretty-dialect-sym-body? (`:` type)?
+    attribute-alias    ::= `#` alias-name
+    ```
+    */
+    pub fn parse_extended_attr(&mut self, r#type: Option<Arc<dyn Type>>)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        self.parse_extended_symbol(
+            |symbols| &symbols.attribute_alias_definitions,
+            |this, dialect_name, symbol_data, location| {
+                // Dispatch to the dialect if it was defined at runtime.
+                if let Some(dialect) = this.context().dynamic_dialect(dialect_name) {
+                    return this.parse_symbol_data(
+                        symbol_data, location,
+                        |parser| dialect.parse_attribute(parser));
+                }
+
+                // Parse an optional trailing colon type.
+                let mut attr_type = r#type;
+                if this.consume_if(TokenKind::Colon) {
+                    attr_type = Some(this.parse_type()?);
+                }
+
+                // Form a new opaque attribute.
+                Ok(Arc::new(OpaqueAttribute::new(
+                    StringAttribute::new(dialect_name),
+                    symbol_data.to_string(),
+                    attr_type)))
+            })
+    }
+
+    /**
+    Parse an extended type.
+
+    ```text
+    extended-type ::= (dialect-type | type-alias)
+    dialect-type  ::= `!` dialect-namespace `<` `"` type-data `"` `>`
+    dialect-type  ::= `!` alias-name pretty-dialect-attribute-body?
+    type-alias    ::= `!` alias-name
+    ```
+    */
+    pub fn parse_extended_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.parse_extended_symbol(
+            |symbols| &symbols.type_alias_definitions,
+            |this, dialect_name, symbol_data, location| {
+                // Dispatch to the dialect if it provides a type parsing hook, or was defined at runtime.
+                if let Some(type_parser) = this.state.config.type_parser(dialect_name) {
+                    return this.parse_symbol_data(
+                        symbol_data, location, |parser| type_parser(parser));
+                }
+                if let Some(dialect) = this.context().dynamic_dialect(dialect_name) {
+                    return this.parse_symbol_data(
+                        symbol_data, location, |parser| dialect.parse_type(parser));
+                }
+
+                // Otherwise, form a new opaque type.
+                Ok(Arc::new(OpaqueType::new(
+                    StringAttribute::new(dialect_name),
+                    symbol_data.to_string())))
+            })
+    }
+
+    /// Parse the data of a dialect symbol, starting at `location`, with the given dialect hook. The data is parsed in place, and the hook must consume all of it. The parser resumes after the symbol afterwards.
+    fn parse_symbol_data<T>(
+        &mut self,
+        symbol_data: &'a str,
+        location: SMLoc,
+        parse: impl FnOnce(&mut dyn DialectAsmParser) -> ParseResult<T>
+    ) -> ParseResult<T> {
+        let resume = self.token().location().offset();
+        let end = location.offset() + symbol_data.len();
+        self.reset_token(location.offset());
+
+        let result = parse(&mut CustomDialectAsmParser {
+            parser: &mut *self,
+            full_spec: symbol_data,
+            name_location: location
+        }).and_then(|value| {
+            // Only whitespace may separate the end of the data and the next token, otherwise the hook either stopped short of the end or parsed past it.
+            let token = self.token();
+            let buffer = self.state.lexer.buffer();
+            if buffer.get(end..token.location().offset())
+                .map_or(true, |gap| !gap.trim().is_empty())
+            {
+                return Err(self.emit_error_at(
+                    if token.location().offset() < end { token.location() }
+                    else { SMLoc::from_offset(end) },
+                    "unexpected characters at the end of the dialect symbol"));
+            }
+            Ok(value)
+        });
+        self.reset_token(resume);
+        result
+    }
+}
+
+// ----------------------------------------------------------------------
+// CustomDialectAsmParser
+// ----------------------------------------------------------------------
+
+/// The dialect parser handed to the hooks of dialects, parsing the data of a dialect symbol in place within the buffer.
+struct CustomDialectAsmParser<'p, 'a, 'c> {
+    parser: &'p mut Parser<'a, 'c>,
     /// The full symbol specification.
-    full_spec: &'static str
+    full_spec: &'a str,
+    /// The location of the start of the symbol data.
+    name_location: SMLoc
+}
+
+impl<'p, 'a, 'c> AsmParser for CustomDialectAsmParser<'p, 'a, 'c> {
+    fn context(&self) -> &MLIRContext {
+        self.parser.context()
+    }
+
+    fn current_location(&self) -> SMLoc {
+        self.parser.token().location()
+    }
+
+    fn name_location(&self) -> SMLoc {
+        self.name_location
+    }
+
+    fn emit_error(&self, location: SMLoc, message: String) -> Box<dyn Error> {
+        self.parser.emit_error_at(location, message)
+    }
+
+    fn parse_optional_token(&mut self, kind: TokenKind) -> bool {
+        self.parser.consume_if(kind)
+    }
+
+    fn parse_optional_keyword(&mut self, keyword: &str) -> bool {
+        // Check that the current token is the keyword.
+        if !self.parser.is_current_token_a_keyword()
+            || self.parser.token_spelling() != keyword
+        {
+            return false;
+        }
+        self.parser.consume_token();
+        true
+    }
+
+    fn parse_optional_any_keyword(&mut self) -> Option<String> {
+        if !self.parser.is_current_token_a_keyword() {
+            return None;
+        }
+        let keyword = self.parser.token_spelling().to_string();
+        self.parser.consume_token();
+        Some(keyword)
+    }
+
+    fn parse_optional_string(&mut self) -> Option<String> {
+        if self.parser.token().is_not(TokenKind::String) {
+            return None;
+        }
+        let string = self.parser.token().string_value();
+        self.parser.consume_token();
+        Some(string)
+    }
+
+    fn parse_optional_integer(&mut self) -> Option<ParseResult<APInt>> {
+        self.parser.parse_optional_integer()
+    }
+
+    fn parse_attribute(&mut self) -> ParseResult<Arc<dyn Attribute>> {
+        self.parser.parse_attribute(None)
+    }
+
+    fn parse_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.parser.parse_type()
+    }
+
+    fn parse_optional_type(&mut self) -> Option<ParseResult<Arc<dyn Type>>> {
+        self.parser.parse_optional_type()
+    }
+}
+
+impl<'p, 'a, 'c> DialectAsmParser for CustomDialectAsmParser<'p, 'a, 'c> {
+    fn full_symbol_spec(&self) -> &str {
+        self.full_spec
+    }
+
+    fn push_cyclic_parsing(&mut self, id: *const ()) -> bool {
+        let stack = &mut self.parser.state.cyclic_parsing_stack;
+        if stack.contains(&id) {
+            return false;
+        }
+        stack.push(id);
+        true
+    }
+
+    fn pop_cyclic_parsing(&mut self) {
+        self.parser.state.cyclic_parsing_stack.pop();
+    }
 }
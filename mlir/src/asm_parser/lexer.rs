@@ -6,58 +6,495 @@ This file declares the MLIR Lexer class.
   - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/Lexer.cpp>
 */
 
-use crate::{
-    mlir::{
-        asm_parser::{
-            asm_parser,
-            code_complete,
-            token::Token
-        },
-        ir::{
-            diagnostics,
-            location,
-            mlir_context::MLIRContext
-        }
-    },
-    llvm::{
-        adt::{
-            string_extras,
-            string_switch
-        },
-        support::source_manager::SourceManager
-    }
-};
+use llvm::support::sm_loc::SMLoc;
+use crate::asm_parser::token::{Keyword, Token, TokenKind};
 
 /// This class breaks up the current file into a token stream.
 pub struct Lexer<'a> {
-    source_manager: &'a SourceManager,
-    context: Option<MLIRContext>,
-    current_buffer: &'a str,
-    current_ptr: Option<char>,
-    /**
-    An optional code completion point within the input file, used to indicate the position of a code completion token.
-    */
-    code_complete_loc: Option<char>
+    buffer: &'a str,
+    /// The byte offset of the next character to lex.
+    current: usize,
+    /// The most recent lexing error, reported by the parser when it encounters an error token.
+    error: Option<(SMLoc, String)>
 }
 
 impl<'a> Lexer<'a> {
-    pub fn get_source_manager(&self) -> &SourceManager {
-        &self.source_manager
+    pub fn new(buffer: &'a str) -> Self {
+        Self { buffer, current: 0, error: None }
+    }
+
+    /// Return the buffer being lexed.
+    pub fn buffer(&self) -> &'a str {
+        self.buffer
+    }
+
+    /// Return the location and message of the most recent lexing error.
+    pub fn error(&self) -> Option<&(SMLoc, String)> {
+        self.error.as_ref()
+    }
+
+    /// Change the position of the lexer cursor. The next token we lex will start at the designated point in the input.
+    pub fn reset_pointer(&mut self, offset: usize) {
+        self.current = offset;
+    }
+
+    /// Returns the start of the buffer.
+    pub fn buffer_begin(&self) -> usize {
+        0
+    }
+
+    /// Return the byte at `offset`, or 0 past the end of the buffer, matching the nul terminator upstream relies on.
+    fn byte_at(&self, offset: usize) -> u8 {
+        self.buffer.as_bytes().get(offset).copied().unwrap_or(0)
+    }
+
+    fn peek(&self) -> u8 {
+        self.byte_at(self.current)
+    }
+
+    fn form_token(&self, kind: TokenKind, start: usize) -> Token<'a> {
+        Token::new(kind, &self.buffer[start..self.current], SMLoc::from_offset(start))
+    }
+
+    fn emit_error(&mut self, offset: usize, message: impl Into<String>) -> Token<'a> {
+        self.error = Some((SMLoc::from_offset(offset), message.into()));
+        // Error tokens cover the rest of the current character so the parser can point at it.
+        self.form_token(TokenKind::Error, offset.min(self.current))
+    }
+
+    pub fn lex_token(&mut self) -> Token<'a> {
+        loop {
+            let start = self.current;
+            if self.current >= self.buffer.len() {
+                return self.form_token(TokenKind::Eof, start);
+            }
+            let char = self.peek();
+            self.current += 1;
+            return match char {
+                // Handle whitespace.
+                b' ' | b'\t' | b'\n' | b'\r' => continue,
+
+                b'_' | b'a'..=b'z' | b'A'..=b'Z' => self.lex_bare_identifier_or_keyword(start),
+
+                b':' => self.form_token(TokenKind::Colon, start),
+                b',' => self.form_token(TokenKind::Comma, start),
+                b'.' => self.lex_ellipsis(start),
+                b'(' => self.form_token(TokenKind::LParen, start),
+                b')' => self.form_token(TokenKind::RParen, start),
+                b'{' => {
+                    if self.peek() == b'-' && self.byte_at(self.current + 1) == b'#' {
+                        self.current += 2;
+                        self.form_token(TokenKind::FileMetadataBegin, start)
+                    } else {
+                        self.form_token(TokenKind::LBrace, start)
+                    }
+                }
+                b'}' => self.form_token(TokenKind::RBrace, start),
+                b'[' => self.form_token(TokenKind::LSquare, start),
+                b']' => self.form_token(TokenKind::RSquare, start),
+                b'<' => self.form_token(TokenKind::Less, start),
+                b'>' => self.form_token(TokenKind::Greater, start),
+                b'=' => self.form_token(TokenKind::Equal, start),
+                b'+' => self.form_token(TokenKind::Plus, start),
+                b'*' => self.form_token(TokenKind::Star, start),
+                b'-' => {
+                    if self.peek() == b'>' {
+                        self.current += 1;
+                        self.form_token(TokenKind::Arrow, start)
+                    } else {
+                        self.form_token(TokenKind::Minus, start)
+                    }
+                }
+                b'?' => self.form_token(TokenKind::Question, start),
+                b'|' => self.form_token(TokenKind::VerticalBar, start),
+                b'/' => {
+                    if self.peek() == b'/' {
+                        self.skip_comment();
+                        continue;
+                    }
+                    self.emit_error(start, "unexpected character")
+                }
+                b'@' => self.lex_at_identifier(start),
+                b'#' => {
+                    if self.peek() == b'-' && self.byte_at(self.current + 1) == b'}' {
+                        self.current += 2;
+                        self.form_token(TokenKind::FileMetadataEnd, start)
+                    } else {
+                        self.lex_prefixed_identifier(start)
+                    }
+                }
+                b'!' | b'^' | b'%' => self.lex_prefixed_identifier(start),
+                b'"' => self.lex_string(start),
+                b'0'..=b'9' => self.lex_number(start),
+                _ => {
+                    // Skip the rest of a multi-byte character so the next token starts on a character boundary.
+                    while !self.buffer.is_char_boundary(self.current) {
+                        self.current += 1;
+                    }
+                    self.emit_error(start, "unexpected character")
+                }
+            };
+        }
+    }
+
+    /**
+    Lex a bare identifier or keyword that starts with a letter.
+
+    ```text
+    bare-id ::= (letter|[_]) (letter|digit|[_$.])*
+    integer-type ::= `[su]?i[1-9][0-9]*`
+    ```
+    */
+    fn lex_bare_identifier_or_keyword(&mut self, start: usize) -> Token<'a> {
+        // Match the rest of the identifier regex: [0-9a-zA-Z_.$]*
+        while matches!(self.peek(),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'$' | b'.')
+        {
+            self.current += 1;
+        }
+
+        // Check to see if this identifier is a keyword.
+        let spelling = &self.buffer[start..self.current];
+
+        let is_all_digit = |string: &str|
+            !string.is_empty() && string.bytes().all(|byte| byte.is_ascii_digit());
+
+        // Check for i123, si456, ui789.
+        let is_int_type = spelling.strip_prefix('i')
+            .or_else(|| spelling.strip_prefix("si"))
+            .or_else(|| spelling.strip_prefix("ui"))
+            .is_some_and(is_all_digit);
+        if is_int_type {
+            return self.form_token(TokenKind::IntType, start);
+        }
+
+        let kind = match Keyword::from_spelling(spelling) {
+            Some(keyword) => TokenKind::Keyword(keyword),
+            None => TokenKind::BareIdentifier
+        };
+        self.form_token(kind, start)
+    }
+
+    /// Skip a comment line, starting with a '//'.
+    fn skip_comment(&mut self) {
+        // Advance over the second '/' in a '//' comment.
+        assert!(self.peek() == b'/');
+        self.current += 1;
+
+        while self.current < self.buffer.len() {
+            match self.peek() {
+                // Newline is end of comment.
+                b'\n' | b'\r' => {
+                    self.current += 1;
+                    return;
+                }
+                // Skip over other characters.
+                _ => self.current += 1
+            }
+        }
+    }
+
+    /**
+    Lex an ellipsis.
+
+    ```text
+    ellipsis ::= '...'
+    ```
+    */
+    fn lex_ellipsis(&mut self, start: usize) -> Token<'a> {
+        if self.peek() != b'.' || self.byte_at(self.current + 1) != b'.' {
+            return self.emit_error(
+                self.current, "expected three consecutive dots for an ellipsis");
+        }
+        self.current += 2;
+        self.form_token(TokenKind::Ellipsis, start)
+    }
+
+    /**
+    Lex a number literal.
+
+    ```text
+    integer-literal ::= digit+ | `0x` hex_digit+
+    float-literal ::= [-+]?[0-9]+[.][0-9]*([eE][-+]?[0-9]+)?
+    ```
+    */
+    fn lex_number(&mut self, start: usize) -> Token<'a> {
+        assert!(self.byte_at(start).is_ascii_digit());
+
+        // Handle the hexadecimal case.
+        if self.byte_at(start) == b'0' && self.peek() == b'x' {
+            // If we have "0x" not followed by a hex digit, the "x" is lexed separately so "0xf32" is not a number.
+            if !self.byte_at(self.current + 1).is_ascii_hexdigit() {
+                return self.form_token(TokenKind::Integer, start);
+            }
+            self.current += 1;
+            while self.peek().is_ascii_hexdigit() {
+                self.current += 1;
+            }
+            return self.form_token(TokenKind::Integer, start);
+        }
+
+        // Handle the normal decimal case.
+        while self.peek().is_ascii_digit() {
+            self.current += 1;
+        }
+
+        if self.peek() != b'.' {
+            return self.form_token(TokenKind::Integer, start);
+        }
+        self.current += 1;
+
+        // Skip over [0-9]*([eE][-+]?[0-9]+)?
+        while self.peek().is_ascii_digit() {
+            self.current += 1;
+        }
+
+        if matches!(self.peek(), b'e' | b'E') {
+            let next = self.byte_at(self.current + 1);
+            if next.is_ascii_digit()
+            || (matches!(next, b'-' | b'+')
+                && self.byte_at(self.current + 2).is_ascii_digit())
+            {
+                self.current += 2;
+                while self.peek().is_ascii_digit() {
+                    self.current += 1;
+                }
+            }
+        }
+        self.form_token(TokenKind::FloatLiteral, start)
     }
 
     /**
-    Change the position of the lexer cursor.  The next token we lex will start
-    at the designated point in the input.
+    Lex an '@foo' identifier.
+
+    ```text
+    symbol-ref-id ::= `@` (bare-id | string-literal)
+    ```
     */
-    pub fn reset_pointer(&mut self, new_pointer: char) {
-        self.cur_ptr = Some(new_pointer);
+    fn lex_at_identifier(&mut self, start: usize) -> Token<'a> {
+        let char = self.peek();
+        self.current += 1;
+
+        // Try to parse a string literal, if present.
+        if char == b'"' {
+            let token = self.lex_string(start);
+            return if token.is(TokenKind::Error) {
+                token
+            } else {
+                self.form_token(TokenKind::AtIdentifier, start)
+            };
+        }
+
+        // Otherwise, these always start with a letter or underscore.
+        if !char.is_ascii_alphabetic() && char != b'_' {
+            self.current -= 1;
+            return self.emit_error(
+                self.current, "@ identifier expected to start with letter or '_'");
+        }
+
+        while matches!(self.peek(),
+            b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'_' | b'$' | b'.')
+        {
+            self.current += 1;
+        }
+        self.form_token(TokenKind::AtIdentifier, start)
+    }
+
+    /**
+    Lex an identifier that starts with a prefix followed by suffix-id.
+
+    ```text
+    attribute-id  ::= `#` suffix-id
+    ssa-id        ::= '%' suffix-id
+    block-id      ::= '^' suffix-id
+    type-id       ::= '!' suffix-id
+    suffix-id     ::= digit+ | (letter|id-punct) (letter|id-punct|digit)*
+    id-punct      ::= `$` | `.` | `_` | `-`
+    ```
+    */
+    fn lex_prefixed_identifier(&mut self, start: usize) -> Token<'a> {
+        let (kind, error_kind) = match self.byte_at(start) {
+            b'#' => (TokenKind::HashIdentifier, "invalid attribute name"),
+            b'%' => (TokenKind::PercentIdentifier, "invalid SSA name"),
+            b'^' => (TokenKind::CaretIdentifier, "invalid block name"),
+            b'!' => (TokenKind::ExclamationIdentifier, "invalid type identifier"),
+            _ => unreachable!("invalid caller")
+        };
+
+        // Parse suffix-id.
+        if self.peek().is_ascii_digit() {
+            // If suffix-id starts with a digit, the rest must be digits.
+            while self.peek().is_ascii_digit() {
+                self.current += 1;
+            }
+        } else if matches!(self.peek(),
+            b'a'..=b'z' | b'A'..=b'Z' | b'$' | b'.' | b'_' | b'-')
+        {
+            self.current += 1;
+            while matches!(self.peek(),
+                b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'$' | b'.' | b'_' | b'-')
+            {
+                self.current += 1;
+            }
+        } else {
+            return self.emit_error(self.current, error_kind);
+        }
+
+        self.form_token(kind, start)
     }
 
     /**
-    Return the code completion location of the lexer, or nullptr if there is
-    none.
+    Lex a string literal.
+
+    ```text
+    string-literal ::= '"' [^"\n\f\v\r]* '"'
+    ```
+
+    TODO: define escaping rules.
     */
-    pub const fn get_code_complete_loc(&self) -> Option<char> {
-        self.code_complete_loc
+    fn lex_string(&mut self, start: usize) -> Token<'a> {
+        assert!(self.byte_at(self.current - 1) == b'"');
+
+        loop {
+            if self.current >= self.buffer.len() {
+                return self.emit_error(self.current, "expected '\"' in string literal");
+            }
+            let char = self.peek();
+            self.current += 1;
+            match char {
+                b'"' => return self.form_token(TokenKind::String, start),
+                b'\n' | b'\x0B' | b'\x0C' => {
+                    self.current -= 1;
+                    return self.emit_error(
+                        self.current, "expected '\"' in string literal");
+                }
+                b'\\' => {
+                    // Handle explicitly a few escapes.
+                    let escaped = self.peek();
+                    if matches!(escaped, b'"' | b'\\' | b'n' | b't') {
+                        self.current += 1;
+                    } else if escaped.is_ascii_hexdigit()
+                        && self.byte_at(self.current + 1).is_ascii_hexdigit()
+                    {
+                        self.current += 2;
+                    } else {
+                        return self.emit_error(
+                            self.current - 1, "unknown escape in string literal");
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Lex `buffer` up to the end of file or the first error, and return the kind and spelling of each token.
+    fn lex(buffer: &str) -> Vec<(TokenKind, &str)> {
+        let mut lexer = Lexer::new(buffer);
+        let mut tokens = Vec::new();
+        loop {
+            let token = lexer.lex_token();
+            if token.is_any(&[TokenKind::Eof, TokenKind::Error]) {
+                return tokens;
+            }
+            tokens.push((token.kind(), token.spelling()));
+        }
+    }
+
+    /// Lex `buffer` up to the first error, and return its offset and message.
+    fn lex_error(buffer: &str) -> (usize, String) {
+        let mut lexer = Lexer::new(buffer);
+        while lexer.lex_token().is_not(TokenKind::Eof) {
+            if let Some((location, message)) = lexer.error() {
+                return (location.offset(), message.clone());
+            }
+        }
+        panic!("Expected a lexing error in {:?}", buffer);
+    }
+
+    #[test]
+    fn operations_are_split_into_tokens() {
+        use TokenKind::*;
+        assert_eq!(lex("%0:2 = \"test.op\"(%arg0) [^bb1] {value = 42 : i32} : (i32) -> f32 // users\n"), [
+            (PercentIdentifier, "%0"), (Colon, ":"), (Integer, "2"), (Equal, "="),
+            (String, "\"test.op\""), (LParen, "("), (PercentIdentifier, "%arg0"), (RParen, ")"),
+            (LSquare, "["), (CaretIdentifier, "^bb1"), (RSquare, "]"),
+            (LBrace, "{"), (BareIdentifier, "value"), (Equal, "="), (Integer, "42"), (Colon, ":"),
+            (IntType, "i32"), (RBrace, "}"), (Colon, ":"), (LParen, "("), (IntType, "i32"),
+            (RParen, ")"), (Arrow, "->"), (Keyword(super::Keyword::F32), "f32")
+        ]);
+    }
+
+    #[test]
+    fn identifiers_keep_their_prefix() {
+        use TokenKind::*;
+        assert_eq!(lex("#map0 @foo @\"sym bol\" !llvm.ptr ^bb0 %x.y-z foo.bar$ si8 ui16 i i0x"), [
+            (HashIdentifier, "#map0"), (AtIdentifier, "@foo"), (AtIdentifier, "@\"sym bol\""),
+            (ExclamationIdentifier, "!llvm.ptr"), (CaretIdentifier, "^bb0"),
+            (PercentIdentifier, "%x.y-z"), (BareIdentifier, "foo.bar$"), (IntType, "si8"),
+            (IntType, "ui16"), (BareIdentifier, "i"), (BareIdentifier, "i0x")
+        ]);
+        assert_eq!(lex_error("%)"), (1, "invalid SSA name".to_string()));
+        assert_eq!(lex_error("@1"), (1, "@ identifier expected to start with letter or '_'".to_string()));
+    }
+
+    #[test]
+    fn numbers_are_integers_or_floats() {
+        use TokenKind::*;
+        assert_eq!(lex("42 0x2A 1.5 1. 1.0e-3 2.5E+10 1.e5 4x8"), [
+            (Integer, "42"), (Integer, "0x2A"), (FloatLiteral, "1.5"), (FloatLiteral, "1."),
+            (FloatLiteral, "1.0e-3"), (FloatLiteral, "2.5E+10"), (FloatLiteral, "1.e5"),
+            (Integer, "4"), (BareIdentifier, "x8")
+        ]);
+        // "0x" not followed by a hex digit leaves the "x" to the next token.
+        assert_eq!(lex("0xi32"), [(Integer, "0"), (BareIdentifier, "xi32")]);
+        // An exponent without digits is not part of the literal.
+        assert_eq!(lex("1.0e"), [(FloatLiteral, "1.0"), (BareIdentifier, "e")]);
+    }
+
+    #[test]
+    fn strings_accept_the_supported_escapes() {
+        assert_eq!(lex(r#""a\"b\\\n\t\41""#), [(TokenKind::String, r#""a\"b\\\n\t\41""#)]);
+        assert_eq!(lex_error(r#""\q""#), (1, "unknown escape in string literal".to_string()));
+        assert_eq!(lex_error("\"abc"), (4, "expected '\"' in string literal".to_string()));
+        assert_eq!(lex_error("\"ab\ncd\""), (3, "expected '\"' in string literal".to_string()));
+    }
+
+    #[test]
+    fn punctuation_and_file_metadata() {
+        use TokenKind::*;
+        assert_eq!(lex("{-# ... #-} <?*|+-> -"), [
+            (FileMetadataBegin, "{-#"), (Ellipsis, "..."), (FileMetadataEnd, "#-}"),
+            (Less, "<"), (Question, "?"), (Star, "*"), (VerticalBar, "|"), (Plus, "+"),
+            (Arrow, "->"), (Minus, "-")
+        ]);
+        assert_eq!(lex_error(".."), (1, "expected three consecutive dots for an ellipsis".to_string()));
+        assert_eq!(lex_error("a / b"), (2, "unexpected character".to_string()));
+    }
+
+    #[test]
+    fn tokens_record_their_location() {
+        let mut lexer = Lexer::new("  foo // comment\n  %bar");
+        let foo = lexer.lex_token();
+        assert_eq!(foo.location().offset(), 2);
+        assert_eq!(foo.end_location().offset(), 5);
+        let bar = lexer.lex_token();
+        assert_eq!((bar.location().offset(), bar.spelling()), (19, "%bar"));
+        assert!(lexer.lex_token().is(TokenKind::Eof));
+        // Lexing resumes from the given offset.
+        lexer.reset_pointer(3);
+        assert_eq!(lexer.lex_token().spelling(), "oo");
+    }
+
+    #[test]
+    fn non_ascii_characters_are_rejected_whole() {
+        let mut lexer = Lexer::new("é)");
+        assert!(lexer.lex_token().is(TokenKind::Error));
+        assert_eq!(lexer.error().map(|(location, _)| location.offset()), Some(0));
+        assert!(lexer.lex_token().is(TokenKind::RParen));
     }
 }
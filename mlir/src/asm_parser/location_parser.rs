@@ -2,33 +2,164 @@
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/LocationParser.cpp>
 */
 
-use crate::mlir::{
+use crate::{
     asm_parser::{
-        parser::Parser,
-        token::Token
+        parser::{AsParser, ParseResult, Parser},
+        token::TokenKind
     },
-    ir::location::{Location, LocationAttribute},
-    support::logical_result::LogicalResult
+    ir::{
+        builtins::attributes::StringAttribute,
+        location::Location,
+        operation::implementation::Delimiter
+    }
 };
 
-impl<'a> Parser<'a> {
-    /// Parse a raw location instance.
-    pub fn parse_location_instance(&self, location: &Location) -> LogicalResult {
-        LogicalResult {}  // unimlemented
+impl<'a, 'c> Parser<'a, 'c> {
+    /**
+    Parse a raw location instance.
+
+    ```text
+    location-inst ::= `unknown`
+                    | string-literal (`:` integer-literal `:` integer-literal)?
+                    | string-literal `(` location-inst `)`
+                    | `callsite` `(` location-inst `at` location-inst `)`
+                    | `fused` (`<` attribute-value `>`)? `[` location-inst (`,` location-inst)* `]`
+                    | location-alias
+    ```
+    */
+    pub fn parse_location_instance(&mut self) -> ParseResult<Location> {
+        // Handle aliases.
+        if self.token().is(TokenKind::HashIdentifier) {
+            let identifier = &self.token_spelling()[1..];
+            if let Some(location) = self.state.symbols.location_alias_definitions
+                .get(identifier)
+            {
+                let location = location.clone();
+                self.consume_token_of(TokenKind::HashIdentifier);
+                return Ok(location);
+            }
+            let attribute = self.parse_extended_attr(None)?;
+            return Err(self.emit_error(format!(
+                "expected location attribute, but got {}", attribute)));
+        }
+
+        // Handle either name or filelinecol locations.
+        if self.token().is(TokenKind::String) {
+            return self.parse_name_or_file_line_col_location();
+        }
+
+        // Bare tokens required for other cases.
+        if self.token().is_not(TokenKind::BareIdentifier) {
+            return Err(self.emit_wrong_token_error("expected location instance"));
+        }
+
+        match self.token_spelling() {
+            // Check for the 'callsite' signifying a callsite location.
+            "callsite" => self.parse_call_site_location(),
+            // If the token is 'fused', then this is a fused location.
+            "fused" => self.parse_fused_location(),
+            // Check for a 'unknown' for an unknown location.
+            "unknown" => {
+                self.consume_token_of(TokenKind::BareIdentifier);
+                Ok(Location::unknown())
+            }
+            _ => Err(self.emit_wrong_token_error("expected location instance"))
+        }
     }
 
     /// Parse a callsite location instance.
-    pub fn parse_call_site_location(&self, location: &Location) -> LogicalResult {
-        LogicalResult {}  // unimlemented
+    pub fn parse_call_site_location(&mut self) -> ParseResult<Location> {
+        self.consume_token_of(TokenKind::BareIdentifier);
+
+        // Parse the '('.
+        self.parse_token(TokenKind::LParen, "expected '(' in callsite location")?;
+
+        // Parse the callee location.
+        let callee = self.parse_location_instance()?;
+
+        // Parse the 'at'.
+        if self.token().is_not(TokenKind::BareIdentifier) || self.token_spelling() != "at" {
+            return Err(self.emit_wrong_token_error("expected 'at' in callsite location"));
+        }
+        self.consume_token_of(TokenKind::BareIdentifier);
+
+        // Parse the caller location.
+        let caller = self.parse_location_instance()?;
+
+        // Parse the ')'.
+        self.parse_token(TokenKind::RParen, "expected ')' in callsite location")?;
+
+        // Return the callsite location.
+        Ok(Location::call_site(callee, caller))
     }
-  
+
     /// Parse a fused location instance.
-    pub fn parse_fused_location(&self, location: &Location) -> LogicalResult {
-        LogicalResult {} // unimlemented
+    pub fn parse_fused_location(&mut self) -> ParseResult<Location> {
+        self.consume_token_of(TokenKind::BareIdentifier);
+
+        // Try to parse the optional metadata.
+        let mut metadata = None;
+        if self.consume_if(TokenKind::Less) {
+            metadata = Some(self.parse_attribute(None)?);
+
+            // Parse the '>' token.
+            self.parse_token(
+                TokenKind::Greater, "expected '>' after fused location metadata")?;
+        }
+
+        let mut locations = Vec::new();
+        self.parse_comma_separated_list(Delimiter::Square, |this| {
+            locations.push(this.parse_location_instance()?);
+            Ok(())
+        }, " in fused location")?;
+
+        // Return the fused location.
+        Ok(Location::fused(locations, metadata))
     }
-  
+
     /// Parse a name or FileLineCol location instance.
-    pub fn parse_name_or_file_line_col_location(&self, location: &Location) -> LogicalResult {
-        LogicalResult {}  // unimlemented
+    pub fn parse_name_or_file_line_col_location(&mut self) -> ParseResult<Location> {
+        let string = self.token().string_value();
+        self.consume_token_of(TokenKind::String);
+
+        // If the next token is ':' this is a filelinecol location.
+        if self.consume_if(TokenKind::Colon) {
+            // Parse the line number.
+            let line = self.token().is(TokenKind::Integer)
+                .then(|| self.token().unsigned_integer_value())
+                .flatten()
+                .ok_or_else(|| self.emit_wrong_token_error(
+                    "expected integer line number in FileLineColLoc"))?;
+            self.consume_token_of(TokenKind::Integer);
+
+            // Parse the ':'.
+            self.parse_token(TokenKind::Colon, "expected ':' in FileLineColLoc")?;
+
+            // Parse the column number.
+            if self.token().is_not(TokenKind::Integer) {
+                return Err(self.emit_wrong_token_error(
+                    "expected integer column number in FileLineColLoc"));
+            }
+            let column = self.token().unsigned_integer_value().ok_or_else(||
+                self.emit_error("expected integer column number in FileLineColLoc"))?;
+            self.consume_token_of(TokenKind::Integer);
+
+            return Ok(Location::file_line_col(
+                StringAttribute::new(string), line as usize, column as usize));
+        }
+
+        // Otherwise, this is a NameLoc.
+
+        // Check for a child location.
+        let mut child = Location::unknown();
+        if self.consume_if(TokenKind::LParen) {
+            // Parse the child location.
+            child = self.parse_location_instance()?;
+
+            // Parse the closing ')'.
+            self.parse_token(
+                TokenKind::RParen, "expected ')' after child location of NameLoc")?;
+        }
+        Ok(Location::name(StringAttribute::new(string), child))
     }
 }
@@ -1,50 +1,1654 @@
 /*!
-This file declares the MLIR Lexer class.
+This file implements the parser for the MLIR textual form.
 
 - lib
   - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/Parser.h>
   - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/Parser.cpp>
 */
 
+use core::ops::{Deref, DerefMut};
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::Arc
+};
+
+use llvm::{
+    adt::ap_int::APInt,
+    support::sm_loc::SMLoc
+};
 use crate::{
-    mlir::{
-        asm_parser::{
-            asm_parser,
-            asm_parser_impl,
-            asm_parser_state,
-            code_complete,
-            parser_state::ParserState
-        },
-        ir::{
-            affine_map,
-            builders,
-            builtins::operations,
-            dialect,
-            operation::implementation,
-            verifier,
-        }
+    asm_parser::{
+        parser_state::ParserState,
+        token::{Keyword, Token, TokenKind}
     },
-    llvm::{
-        adt::{
-            bit,
-            dense_map,
-            scope_exit,
-            string_set
+    ir::{
+        arena::{ArenaResult, BlockId, IRArena, OpId, RegionId},
+        builtins::{
+            attributes::{DictionaryAttribute, StringAttribute},
+            types::FunctionType
         },
-        support::{
-            endian,
-            pretty_stack_trace,
-            source_manager
-        }
+        diagnostics::{Diagnostic, DiagnosticSeverity, emit_op_diagnostic},
+        location::Location,
+        mlir_context::MLIRContext,
+        attribute::{Attribute, NamedAttribute},
+        operation::{
+            Operation,
+            definition::IsTerminator,
+            implementation::{
+                AsmDialectResourceHandle, AsmParser, Delimiter, OpAsmParser
+            },
+            support::OperationState
+        },
+        r#type::Type,
+        value::{Input, Output, Value},
+        verifier::verify
     }
 };
 
-/**
-This class implement support for parsing global entities like attributes and types. It is intended to be subclassed by specialised subparsers that include state.
-*/
-pub struct Parser<'a> {
+/// The result of parsing. Errors have already been reported through the diagnostic engine of the context by the time they are returned.
+pub type ParseResult<T = ()> = Result<T, Box<dyn Error>>;
+
+/// This class implement support for parsing global entities like attributes and types. It is intended to be wrapped by specialised subparsers that include state.
+pub struct Parser<'a, 'c> {
+    /// The Parser is wrapped and reinstantiated. Do not add additional non-trivial state here, add it to the ParserState class.
+    pub state: ParserState<'a, 'c>
+}
+
+impl<'a, 'c> Parser<'a, 'c> {
+    pub fn new(state: ParserState<'a, 'c>) -> Self {
+        Self { state }
+    }
+
+    pub fn context(&self) -> &MLIRContext {
+        self.state.config.context()
+    }
+
+    pub fn context_mut(&mut self) -> &mut MLIRContext {
+        self.state.config.context_mut()
+    }
+
+    /// Return the context as the raw pointer expected by the affine constructors.
+    pub fn context_ptr(&mut self) -> *mut MLIRContext {
+        self.context_mut() as *mut MLIRContext
+    }
+
+    pub fn arena(&self) -> &IRArena {
+        self.context().arena()
+    }
+
+    pub fn arena_mut(&mut self) -> &mut IRArena {
+        self.context_mut().arena_mut()
+    }
+
+    /// Encode the specified source location information into an attribute for attachment to the IR.
+    pub fn encoded_source_location(&self, location: SMLoc) -> Location {
+        let buffer = self.state.lexer.buffer();
+        let prefix = &buffer[..location.offset().min(buffer.len())];
+        let line = prefix.matches('\n').count() + 1;
+        let line_start = prefix.rfind('\n').map_or(0, |index| index + 1);
+        let column = prefix.len() - line_start + 1;
+        Location::file_line_col(
+            StringAttribute::new(self.state.filename.clone()), line, column)
+    }
+
+    // ----------------------------------------------------------------------
+    // Error Handling
+    // ----------------------------------------------------------------------
+
+    /// Build an error diagnostic at `location`, without emitting it.
+    pub fn diagnostic_at(&self, location: SMLoc, message: impl Into<String>)
+        -> Diagnostic
+    {
+        Diagnostic::new(
+            self.encoded_source_location(location), DiagnosticSeverity::Error,
+            message)
+    }
+
+    /// Emit an error at the location of the current token and return it.
+    pub fn emit_error(&self, message: impl Into<String>) -> Box<dyn Error> {
+        self.emit_error_at(self.token().location(), message)
+    }
+
+    pub fn emit_error_at(&self, location: SMLoc, message: impl Into<String>)
+        -> Box<dyn Error>
+    {
+        self.emit_diagnostic(self.diagnostic_at(location, message))
+    }
+
+    /// Emit `diagnostic` and return it. If we hit a parse error in response to a lexer error, then the lexer error is reported instead, as it points at the actual problem.
+    pub fn emit_diagnostic(&self, diagnostic: Diagnostic) -> Box<dyn Error> {
+        let diagnostic = match (self.token().kind(), self.state.lexer.error()) {
+            (TokenKind::Error, Some((location, message))) =>
+                self.diagnostic_at(*location, message.clone()),
+            _ => diagnostic
+        };
+        self.context().diag_engine().emit(diagnostic.clone());
+        Box::new(diagnostic)
+    }
+
+    /// Emit an error about a "wrong token". If the current token is at the start of a source line, this will apply heuristics to back up and report the error at the end of the previous line, which is where the expected token is supposed to be.
+    pub fn emit_wrong_token_error(&self, message: impl Into<String>)
+        -> Box<dyn Error>
+    {
+        let mut location = self.token().location().offset();
+
+        // If the error is to be emitted at EOF, move it back one character.
+        if self.token().is(TokenKind::Eof) {
+            location = location.saturating_sub(1);
+        }
+
+        // This is the location we were originally asked to report the error at.
+        let original_location = SMLoc::from_offset(location);
+
+        // Use this slice to keep track of what we are going to back up through.
+        let buffer = self.state.lexer.buffer();
+        let mut start_of_buffer = &buffer[..location];
+
+        // Back up over entirely blank lines.
+        loop {
+            // Back up until we see a \n, but don't look past the buffer start.
+            start_of_buffer = start_of_buffer.trim_end_matches([' ', '\t']);
+
+            // For tokens with no preceding source line, just emit at the original location.
+            if start_of_buffer.is_empty() {
+                return self.emit_error_at(original_location, message);
+            }
+
+            // If we found something that isn't the end of line, then we're done.
+            if !start_of_buffer.ends_with(['\n', '\r']) {
+                return self.emit_error_at(
+                    SMLoc::from_offset(start_of_buffer.len()), message);
+            }
+
+            // Drop the \n so we emit the diagnostic at the end of the line.
+            start_of_buffer = &start_of_buffer[..start_of_buffer.len() - 1];
+
+            // Check to see if the preceding line has a comment on it. We assume that a `//` is the start of a comment, which is mostly correct.
+            let previous_line_start = start_of_buffer.rfind(['\n', '\r'])
+                .unwrap_or(0);
+            let previous_line = &start_of_buffer[previous_line_start..];
+
+            // If we find a // in the current line, then emit the diagnostic before it.
+            if let Some(comment_start) = previous_line.find("//") {
+                start_of_buffer = &start_of_buffer[
+                    ..previous_line_start + comment_start];
+            }
+        }
+    }
+
+    /// Report a failure of the arena, which indicates a bug in the parser rather than in the input, as an error at the current token.
+    pub fn check<T>(&self, result: ArenaResult<T>) -> ParseResult<T> {
+        result.map_err(|error| self.emit_error(error.to_string()))
+    }
+
+    // ----------------------------------------------------------------------
+    // Token Parsing
+    // ----------------------------------------------------------------------
+
+    /// Return the current token the parser is inspecting.
+    pub fn token(&self) -> Token<'a> {
+        self.state.current_token
+    }
+
+    pub fn token_spelling(&self) -> &'a str {
+        self.state.current_token.spelling()
+    }
+
+    /// Advance the current lexer onto the next token.
+    pub fn consume_token(&mut self) {
+        assert!(self.token().is_not(TokenKind::Eof)
+            && self.token().is_not(TokenKind::Error),
+            "Shouldn't advance past EOF or errors");
+        self.state.current_token = self.state.lexer.lex_token();
+    }
+
+    /// Advance the current lexer onto the next token, asserting what the expected current token is. This is preferred to the above method because it leads to more self-documenting code with better checking.
+    pub fn consume_token_of(&mut self, kind: TokenKind) {
+        assert!(self.token().is(kind), "Consumed an unexpected token");
+        self.consume_token();
+    }
+
+    /// Reset the parser to the given lexer position.
+    pub fn reset_token(&mut self, offset: usize) {
+        self.state.lexer.reset_pointer(offset);
+        self.state.current_token = self.state.lexer.lex_token();
+    }
+
+    /// If the current token has the specified kind, consume it and return true. If not, return false.
+    pub fn consume_if(&mut self, kind: TokenKind) -> bool {
+        if self.token().is_not(kind) {
+            return false;
+        }
+        self.consume_token_of(kind);
+        true
+    }
+
+    /// Consume the specified token if present and return success. On failure, output a diagnostic and return failure.
+    pub fn parse_token(&mut self, kind: TokenKind, message: impl Into<String>)
+        -> ParseResult
+    {
+        if self.consume_if(kind) {
+            return Ok(());
+        }
+        Err(self.emit_wrong_token_error(message))
+    }
+
+    /// Returns true if the current token corresponds to a keyword.
+    pub fn is_current_token_a_keyword(&self) -> bool {
+        self.token().is(TokenKind::BareIdentifier) || self.token().is_keyword()
+    }
+
+    /// Parse a keyword, if present, returning its spelling.
+    pub fn parse_optional_keyword(&mut self) -> Option<&'a str> {
+        // Check that the current token is a keyword.
+        if !self.is_current_token_a_keyword() {
+            return None;
+        }
+        let keyword = self.token_spelling();
+        self.consume_token();
+        Some(keyword)
+    }
+
+    // ----------------------------------------------------------------------
+    // Integer Parsing
+    // ----------------------------------------------------------------------
+
+    /// Parse an optional integer value from the stream. The returned integer is wide enough to hold the literal as a signed value.
+    pub fn parse_optional_integer(&mut self) -> Option<ParseResult<APInt>> {
+        if !self.token().is_any(&[TokenKind::Integer, TokenKind::Minus]) {
+            return None;
+        }
+        let negative = self.consume_if(TokenKind::Minus);
+        let token = self.token();
+        Some(self.parse_token(TokenKind::Integer, "expected integer value")
+            .and_then(|_| self.integer_value(token))
+            .map(|value| if negative { value.neg() } else { value }))
+    }
+
+    /// Return the value of the integer literal `token`, with a zero at the top so the value reads as positive when interpreted as signed.
+    pub fn integer_value(&self, token: Token<'a>) -> ParseResult<APInt> {
+        let spelling = token.spelling();
+        let (digits, radix) = match spelling.strip_prefix("0x") {
+            Some(digits) => (digits, 16),
+            None => (spelling, 10)
+        };
+        // Four bits per digit is enough for either radix.
+        let width = digits.len() as u32 * 4 + 1;
+        APInt::from_str_radix(width, digits, radix)
+            .ok_or_else(|| self.emit_error_at(
+                token.location(), "integer value too large"))
+    }
+
+    /// Parse a floating point value from an integer literal token, returning its bit pattern of `width` bits.
+    pub fn parse_float_from_integer_literal(
+        &self,
+        token: Token<'a>,
+        is_negative: bool,
+        width: usize
+    ) -> ParseResult<APInt> {
+        let location = token.location();
+        let spelling = token.spelling();
+        let is_hex = spelling.len() > 1 && spelling.as_bytes()[1] == b'x';
+        if !is_hex {
+            let mut diagnostic = self.diagnostic_at(
+                location,
+                "unexpected decimal integer literal for a floating point value");
+            diagnostic.attach_note(
+                None, "add a trailing dot to make the literal a float");
+            return Err(self.emit_diagnostic(diagnostic));
+        }
+        if is_negative {
+            return Err(self.emit_error_at(
+                location, "hexadecimal float literal should not have a leading minus"));
+        }
+        let value = Token::uint64_integer_value(spelling)
+            .ok_or_else(|| self.emit_error_at(
+                location, "hexadecimal float constant out of range for type"))?;
+        if width < 64 && value >> width != 0 {
+            return Err(self.emit_error_at(
+                location, "hexadecimal float constant out of range for type"));
+        }
+        Ok(APInt::new(width as u32, value, false))
+    }
+
+    // ----------------------------------------------------------------------
+    // Resource Parsing
+    // ----------------------------------------------------------------------
+
+    /// Parse a handle to a dialect resource within the assembly format of the dialect `namespace`.
+    pub fn parse_resource_handle(&mut self, namespace: &str)
+        -> ParseResult<AsmDialectResourceHandle>
+    {
+        let name_location = self.token().location();
+        let name = self.parse_optional_keyword().ok_or_else(||
+            self.emit_error("expected identifier key for 'resource' entry"))?;
+
+        // If this is the first time encountering this handle, ask the dialect to resolve a reference to this handle. This allows for us to remap the name of the handle if necessary.
+        if let Some((_, handle)) = self.state.symbols.dialect_resources
+            .get(namespace).and_then(|resources| resources.get(name))
+        {
+            return Ok(handle.clone());
+        }
+        let handle = match self.state.config.dialect_interface(namespace) {
+            Some(interface) => interface.declare_resource(name),
+            None => return Err(self.emit_error_at(name_location, format!(
+                "dialect '{}' does not expect resource handles", namespace)))
+        };
+        let handle = handle.ok_or_else(|| self.emit_error_at(
+            name_location,
+            format!("unknown 'resource' key '{}' for dialect '{}'", name, namespace)))?;
+        self.state.symbols.dialect_resources
+            .entry(namespace.to_string()).or_default()
+            .insert(name.to_string(), (handle.key().to_string(), handle.clone()));
+        Ok(handle)
+    }
+}
+
+/// Parsers that are built on a `Parser`, giving them the list parsing helpers with callbacks that have access to the full parser.
+pub trait AsParser<'a, 'c>: Sized {
+    fn parser(&mut self) -> &mut Parser<'a, 'c>;
+
+    /// Parse a list of comma-separated items with an optional delimiter. If a delimiter is provided, then an empty list is allowed. If not, then at least one element will be parsed.
+    ///
+    /// `context_message` is an optional message appended to "expected '('" sorts of diagnostics when parsing the delimeters.
+    fn parse_comma_separated_list(
+        &mut self,
+        delimiter: Delimiter,
+        mut parse_element: impl FnMut(&mut Self) -> ParseResult,
+        context_message: &str
+    ) -> ParseResult {
+        let (optional, brackets) = match delimiter {
+            Delimiter::None => (false, None),
+            Delimiter::Paren => (false, Some((TokenKind::LParen, TokenKind::RParen))),
+            Delimiter::Square => (false, Some((TokenKind::LSquare, TokenKind::RSquare))),
+            Delimiter::LessGreater => (false, Some((TokenKind::Less, TokenKind::Greater))),
+            Delimiter::Braces => (false, Some((TokenKind::LBrace, TokenKind::RBrace))),
+            Delimiter::OptionalParen =>
+                (true, Some((TokenKind::LParen, TokenKind::RParen))),
+            Delimiter::OptionalSquare =>
+                (true, Some((TokenKind::LSquare, TokenKind::RSquare))),
+            Delimiter::OptionalLessGreater =>
+                (true, Some((TokenKind::Less, TokenKind::Greater))),
+            Delimiter::OptionalBraces =>
+                (true, Some((TokenKind::LBrace, TokenKind::RBrace)))
+        };
+
+        if let Some((left, right)) = brackets {
+            if optional && self.parser().token().is_not(left) {
+                return Ok(());
+            }
+            self.parser().parse_token(
+                left, format!("expected '{}'{}", left.spelling(), context_message))?;
+            // Check for empty list.
+            if self.parser().consume_if(right) {
+                return Ok(());
+            }
+        }
+
+        // Non-empty case starts with an element.
+        parse_element(self)?;
+
+        // Otherwise we have a list of comma separated elements.
+        while self.parser().consume_if(TokenKind::Comma) {
+            parse_element(self)?;
+        }
+
+        match brackets {
+            Some((_, right)) => self.parser().parse_token(
+                right, format!("expected '{}'{}", right.spelling(), context_message)),
+            None => Ok(())
+        }
+    }
+
+    /// Parse a comma-separated list of elements, terminated with an arbitrary token. This allows empty lists if `allow_empty_list` is true.
+    ///
+    /// ```text
+    /// abstract-list ::= rightToken                  // if allowEmptyList == true
+    /// abstract-list ::= element (',' element)* rightToken
+    /// ```
+    fn parse_comma_separated_list_until(
+        &mut self,
+        right: TokenKind,
+        parse_element: impl FnMut(&mut Self) -> ParseResult,
+        allow_empty_list: bool
+    ) -> ParseResult {
+        // Handle the empty case.
+        if self.parser().token().is(right) {
+            if !allow_empty_list {
+                return Err(self.parser().emit_wrong_token_error("expected list element"));
+            }
+            self.parser().consume_token_of(right);
+            return Ok(());
+        }
+
+        self.parse_comma_separated_list(Delimiter::None, parse_element, "")?;
+        self.parser().parse_token(
+            right, format!("expected ',' or '{}'", right.spelling()))
+    }
+}
+
+impl<'a, 'c> AsParser<'a, 'c> for Parser<'a, 'c> {
+    fn parser(&mut self) -> &mut Parser<'a, 'c> {
+        self
+    }
+}
+
+// ----------------------------------------------------------------------
+// OperationParser
+// ----------------------------------------------------------------------
+
+/// This is the structure of a result specifier in the assembly syntax, including the name, number of results, and location.
+struct ResultRecord<'a> {
+    name: &'a str,
+    count: usize,
+    location: SMLoc
+}
+
+/// This is the representation of an operand reference.
+#[derive(Clone, Copy, Debug)]
+pub struct UnresolvedOperand<'a> {
+    /// Location of the SSA name.
+    pub location: SMLoc,
+    /// Value name without the `%`.
+    pub name: &'a str,
+    /// Result number of the value within its definition.
+    pub number: usize
+}
+
+/// This class represents a definition of a Value.
+#[derive(Clone, Copy)]
+struct ValueDefinition {
+    /// A value defined by this definition.
+    value: Value,
+
+    /// The source location for the value definition.
+    location: SMLoc
+}
+
+/// This struct represents an isolated SSA name scope. This scope may contain other nested non-isolated scopes. These scopes are used for operations that are known to be isolated to allow for reusing names within their regions, even if those names are used above.
+#[derive(Default)]
+struct IsolatedSSANameScope {
+    /// This keeps track of all of the SSA values we are tracking for each name scope, indexed by their name. This has one entry per result number.
+    values: HashMap<String, Vec<Option<ValueDefinition>>>,
+
+    /// This keeps track of all of the values defined by a specific name scope.
+    definitions_per_scope: Vec<HashSet<String>>
+}
+
+impl IsolatedSSANameScope {
+    /// Record that a definition was added at the current scope.
+    fn record_definition(&mut self, name: &str) {
+        self.definitions_per_scope.last_mut().unwrap().insert(name.to_string());
+    }
+
+    /// Push a nested name scope.
+    fn push_ssa_name_scope(&mut self) {
+        self.definitions_per_scope.push(HashSet::new());
+    }
+
+    /// Pop a nested name scope.
+    fn pop_ssa_name_scope(&mut self) {
+        for name in self.definitions_per_scope.pop().unwrap() {
+            self.values.remove(&name);
+        }
+    }
+}
+
+/// The target of a location alias that was referenced before it was defined.
+#[derive(Clone, Copy)]
+enum DeferredLocTarget {
+    Operation(OpId),
+    Input(Input)
+}
+
+/// Information about an operation or block input whose location refers to an alias that is defined later in the file.
+struct DeferredLocInfo<'a> {
+    target: DeferredLocTarget,
+    location: SMLoc,
+    identifier: &'a str
+}
+
+/// This class provides support for parsing operations and regions of operations.
+pub struct OperationParser<'a, 'c> {
+    parser: Parser<'a, 'c>,
+
+    /// This keeps track of the block names as well as the location of the first reference for each nested name scope. This is used to diagnose invalid block references and memorise them.
+    blocks_by_name: Vec<HashMap<&'a str, (Option<BlockId>, SMLoc)>>,
+    forward_ref: Vec<HashMap<BlockId, SMLoc>>,
+
+    /// This keeps track of all of the SSA values we are tracking for each name scope, indexed by their name. This has one entry per result number.
+    isolated_name_scopes: Vec<IsolatedSSANameScope>,
+
+    /// These are all of the placeholders we've made along with the location of their first reference, to allow checking for use of undefined values.
+    forward_ref_placeholders: HashMap<Value, SMLoc>,
+
+    /// Deferred locations: when parsing `loc(#loc42)` we add an entry to this list, and resolve it once the whole file has been parsed.
+    deferred_locs_references: Vec<DeferredLocInfo<'a>>,
+
+    /// The regions of operations being parsed, which are detached until their operation is created.
+    pending_regions: Vec<RegionId>,
+
+    /// Blocks that were referenced but never defined, kept until the parser is discarded.
+    orphan_blocks: Vec<BlockId>,
+
+    /// The block new operations are appended to.
+    insertion_block: BlockId,
+
+    /// The top level operation that holds all of the parsed operations.
+    top_level_op: OpId
+}
+
+impl<'a, 'c> Deref for OperationParser<'a, 'c> {
+    type Target = Parser<'a, 'c>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.parser
+    }
+}
+
+impl<'a, 'c> DerefMut for OperationParser<'a, 'c> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.parser
+    }
+}
+
+impl<'a, 'c> AsParser<'a, 'c> for OperationParser<'a, 'c> {
+    fn parser(&mut self) -> &mut Parser<'a, 'c> {
+        &mut self.parser
+    }
+}
+
+impl<'a, 'c> OperationParser<'a, 'c> {
+    /// Create a parser appending the parsed operations to `body`, the body of `top_level_op`.
+    pub fn new(parser: Parser<'a, 'c>, top_level_op: OpId, body: BlockId) -> Self {
+        let mut output = Self {
+            parser,
+            blocks_by_name: Vec::new(),
+            forward_ref: Vec::new(),
+            isolated_name_scopes: Vec::new(),
+            forward_ref_placeholders: HashMap::new(),
+            deferred_locs_references: Vec::new(),
+            pending_regions: Vec::new(),
+            orphan_blocks: Vec::new(),
+            insertion_block: body,
+            top_level_op
+        };
+        // The top level operation starts a new name scope.
+        output.push_ssa_name_scope(true);
+        output
+    }
+
+    /// Return the underlying parser.
+    pub fn into_parser(self) -> Parser<'a, 'c> {
+        self.parser
+    }
+
+    /// After parsing is finished, this function must be called to see if there were any remaining unresolved forward references.
+    pub fn finalize(&mut self) -> ParseResult {
+        // Check for any forward references that are left. If we find any, error out.
+        if !self.forward_ref_placeholders.is_empty() {
+            // Iteration over the map isn't deterministic, so sort by source location.
+            let mut errors: Vec<SMLoc> = self.forward_ref_placeholders.values()
+                .copied().collect();
+            errors.sort();
+            let mut output = None;
+            for location in errors {
+                output = Some(self.emit_error_at(
+                    location, "use of undeclared SSA value name"));
+            }
+            return Err(output.unwrap());
+        }
+
+        // Resolve the locations of any deferred operations.
+        for index in 0..self.deferred_locs_references.len() {
+            let DeferredLocInfo { target, location, identifier }
+                = self.deferred_locs_references[index];
+            let resolved = match self.state.symbols.location_alias_definitions
+                .get(identifier)
+            {
+                Some(resolved) => resolved.clone(),
+                None => return Err(match self.state.symbols
+                    .attribute_alias_definitions.get(identifier)
+                {
+                    Some(attribute) => self.emit_error_at(location, format!(
+                        "expected location, but found '{}'", attribute.as_ref())),
+                    None => self.emit_error_at(
+                        location, "operation location alias was never defined")
+                })
+            };
+            let result = match target {
+                DeferredLocTarget::Operation(op) => self.arena_mut().op_mut(op)
+                    .map(|op| op.set_location(resolved)),
+                DeferredLocTarget::Input(input) => self.arena_mut()
+                    .block_mut(input.owner())
+                    .map(|block| block.inputs[input.index()].location = resolved)
+            };
+            self.check(result)?;
+        }
+
+        // Pop the top level name scope.
+        self.pop_ssa_name_scope()?;
+
+        // Verify that the parsed operations are valid. The verifier reports its errors itself.
+        if self.state.config.should_verify_after_parse() {
+            verify(self.context(), self.top_level_op, true)?;
+        }
+        Ok(())
+    }
+
+    /// Erase everything created while parsing, after a parse failure. This includes the top level operation.
+    pub fn discard(mut self) {
+        for region in core::mem::take(&mut self.pending_regions).into_iter().rev() {
+            let _ = self.arena_mut().erase_region(region);
+        }
+        let top_level_op = self.top_level_op;
+        let _ = self.arena_mut().erase(top_level_op);
+        let placeholders: Vec<Value> = self.forward_ref_placeholders.keys()
+            .copied().collect();
+        for placeholder in placeholders {
+            if let Value::Output(output) = placeholder {
+                let _ = self.arena_mut().drop_all_references(output.owner());
+                let _ = self.arena_mut().erase(output.owner());
+            }
+        }
+        let mut blocks = core::mem::take(&mut self.orphan_blocks);
+        for forward_ref in core::mem::take(&mut self.forward_ref) {
+            blocks.extend(forward_ref.into_keys());
+        }
+        for block in blocks {
+            let _ = self.arena_mut().erase_block(block);
+        }
+    }
+
+    // ----------------------------------------------------------------------
+    // SSA Value Handling
+    // ----------------------------------------------------------------------
+
+    /// Push a new SSA name scope to the parser.
+    fn push_ssa_name_scope(&mut self, is_isolated: bool) {
+        self.blocks_by_name.push(HashMap::new());
+        self.forward_ref.push(HashMap::new());
+
+        // Push back a new name definition scope.
+        if is_isolated {
+            self.isolated_name_scopes.push(IsolatedSSANameScope::default());
+        }
+        self.isolated_name_scopes.last_mut().unwrap().push_ssa_name_scope();
+    }
+
+    /// Pop the last SSA name scope from the parser.
+    fn pop_ssa_name_scope(&mut self) -> ParseResult {
+        let forward_ref_in_current_scope = self.forward_ref.pop().unwrap();
+
+        // Verify that all referenced blocks were defined.
+        if !forward_ref_in_current_scope.is_empty() {
+            // Iteration over the map isn't deterministic, so sort by source location.
+            let mut errors: Vec<(SMLoc, BlockId)> = forward_ref_in_current_scope
+                .into_iter().map(|(block, location)| (location, block)).collect();
+            errors.sort();
+            let mut output = None;
+            for (location, block) in errors {
+                // Keep the block to allow for cleanup.
+                self.orphan_blocks.push(block);
+                output = Some(self.emit_error_at(
+                    location, "reference to an undefined block"));
+            }
+            return Err(output.unwrap());
+        }
+
+        // Pop the next nested namescope. If there is only one internal namescope, just pop the isolated scope.
+        let current_name_scope = self.isolated_name_scopes.last_mut().unwrap();
+        if current_name_scope.definitions_per_scope.len() == 1 {
+            self.isolated_name_scopes.pop();
+        } else {
+            current_name_scope.pop_ssa_name_scope();
+        }
+
+        self.blocks_by_name.pop();
+        Ok(())
+    }
+
+    /// Returns the entries of the SSA value `name` in the current isolated scope.
+    fn ssa_value_entry(&mut self, name: &str) -> &mut Vec<Option<ValueDefinition>> {
+        self.isolated_name_scopes.last_mut().unwrap()
+            .values.entry(name.to_string()).or_default()
+    }
+
+    /// Return true if `left` and `right` are the same type. Types are not uniqued, so types built separately are compared by their assembly form.
+    fn same_type(left: &Arc<dyn Type>, right: &Arc<dyn Type>) -> bool {
+        Arc::ptr_eq(left, right) || left.to_string() == right.to_string()
+    }
+
+    /// Register a definition of a value with the symbol table.
+    pub fn add_definition(&mut self, use_info: UnresolvedOperand<'a>, value: Value)
+        -> ParseResult
+    {
+        // If we already have an entry for this, check to see if it was a definition or a forward reference.
+        let existing = self.ssa_value_entry(use_info.name)
+            .get(use_info.number).copied().flatten();
+        if let Some(existing) = existing {
+            if !self.forward_ref_placeholders.contains_key(&existing.value) {
+                let mut diagnostic = self.diagnostic_at(
+                    use_info.location,
+                    format!("redefinition of SSA value '{}'", use_info.name));
+                diagnostic.attach_note(
+                    Some(self.encoded_source_location(existing.location)),
+                    "previously defined here");
+                return Err(self.emit_diagnostic(diagnostic));
+            }
+
+            let value_type = self.check(value.r#type(self.arena()))?.clone();
+            let existing_type = self.check(existing.value.r#type(self.arena()))?.clone();
+            if !Self::same_type(&existing_type, &value_type) {
+                let mut diagnostic = self.diagnostic_at(
+                    use_info.location,
+                    format!("definition of SSA value '{}#{}' has type {}",
+                        use_info.name, use_info.number, value_type));
+                diagnostic.attach_note(
+                    Some(self.encoded_source_location(existing.location)),
+                    format!("previously used here with type {}", existing_type));
+                return Err(self.emit_diagnostic(diagnostic));
+            }
+
+            // If it was a forward reference, update everything that used it to use the actual definition instead, delete the forward ref, and remove it from our set of forward references we track.
+            let result = self.arena_mut().replace_all_uses_with(existing.value, value);
+            self.check(result)?;
+            if let Value::Output(placeholder) = existing.value {
+                let result = self.arena_mut().erase(placeholder.owner());
+                self.check(result)?;
+            }
+            self.forward_ref_placeholders.remove(&existing.value);
+        }
+
+        // Record this definition for the current scope.
+        let entries = self.ssa_value_entry(use_info.name);
+        if entries.len() <= use_info.number {
+            entries.resize(use_info.number + 1, None);
+        }
+        entries[use_info.number] = Some(
+            ValueDefinition { value, location: use_info.location });
+        self.isolated_name_scopes.last_mut().unwrap()
+            .record_definition(use_info.name);
+        Ok(())
+    }
+
+    /// Parse an optional list of SSA uses into `results`.
+    pub fn parse_optional_ssa_use_list(
+        &mut self,
+        results: &mut Vec<UnresolvedOperand<'a>>
+    ) -> ParseResult {
+        if self.token().is_not(TokenKind::PercentIdentifier) {
+            return Ok(());
+        }
+        self.parse_comma_separated_list(Delimiter::None, |this| {
+            results.push(this.parse_ssa_use(true)?);
+            Ok(())
+        }, "")
+    }
+
     /**
-    The Parser is subclassed and reinstantiated. Do not add additional non-trivial state here, add it to the ParserState class.
+    Parse a SSA operand for an operation.
+
+    ```text
+    ssa-use ::= ssa-id
+    ```
     */
-    state: &'a ParserState<'a>
+    pub fn parse_ssa_use(&mut self, allow_result_number: bool)
+        -> ParseResult<UnresolvedOperand<'a>>
+    {
+        let token = self.token();
+        let mut result = UnresolvedOperand {
+            location: token.location(),
+            name: token.spelling(),
+            number: 0
+        };
+        self.parse_token(TokenKind::PercentIdentifier, "expected SSA operand")?;
+
+        // If we have an attribute ID, it is a result number.
+        if self.token().is(TokenKind::HashIdentifier) {
+            if !allow_result_number {
+                return Err(self.emit_error("result number not allowed in argument list"));
+            }
+            result.number = self.token().hash_identifier_number()
+                .ok_or_else(|| self.emit_error("invalid SSA value result number"))?;
+            self.consume_token_of(TokenKind::HashIdentifier);
+        }
+        Ok(result)
+    }
+
+    /// Given a reference to an SSA value and its type, return a reference. This returns an error on failure.
+    pub fn resolve_ssa_use(
+        &mut self,
+        use_info: UnresolvedOperand<'a>,
+        r#type: Arc<dyn Type>
+    ) -> ParseResult<Value> {
+        // If we have already seen a value of this name, return it.
+        let existing = self.ssa_value_entry(use_info.name)
+            .get(use_info.number).copied().flatten();
+        if let Some(existing) = existing {
+            // Check that the type matches the other uses.
+            let existing_type = self.check(existing.value.r#type(self.arena()))?.clone();
+            if Self::same_type(&existing_type, &r#type) {
+                return Ok(existing.value);
+            }
+            let mut diagnostic = self.diagnostic_at(
+                use_info.location,
+                format!("use of value '{}' expects different type than prior uses: {} vs {}",
+                    use_info.name, r#type, existing_type));
+            diagnostic.attach_note(
+                Some(self.encoded_source_location(existing.location)),
+                "prior use here");
+            return Err(self.emit_diagnostic(diagnostic));
+        }
+
+        // If the value has already been defined and this is an overly large result number, diagnose that.
+        let first = self.ssa_value_entry(use_info.name).first().copied().flatten();
+        if let Some(first) = first
+            && !self.forward_ref_placeholders.contains_key(&first.value)
+        {
+            return Err(self.emit_error_at(
+                use_info.location, "reference to invalid result number"));
+        }
+
+        // Otherwise, this is a forward reference. Create a placeholder and remember that we did so.
+        let result = self.create_forward_ref_placeholder(use_info.location, r#type)?;
+        let entries = self.ssa_value_entry(use_info.name);
+        if entries.len() <= use_info.number {
+            entries.resize(use_info.number + 1, None);
+        }
+        entries[use_info.number] = Some(
+            ValueDefinition { value: result, location: use_info.location });
+        Ok(result)
+    }
+
+    /// Create and remember a new placeholder for a forward reference.
+    fn create_forward_ref_placeholder(&mut self, location: SMLoc, r#type: Arc<dyn Type>)
+        -> ParseResult<Value>
+    {
+        // Forward references are always created as operations, because we just need something with a def/use chain.
+        let name = self.context().operation_name("builtin.unrealized_conversion_cast");
+        let operation = Operation::new(
+            self.encoded_source_location(location),
+            name,
+            [r#type],
+            Vec::new(),
+            DictionaryAttribute::default(),
+            &[]
+        );
+        let op = self.arena_mut().create_operation(operation, 0);
+        let op = self.check(op)?;
+        let value = Value::Output(Output::new(op, 0));
+        self.forward_ref_placeholders.insert(value, location);
+        Ok(value)
+    }
+
+    /// Parse an SSA use with an associated type.
+    ///
+    /// ```text
+    /// ssa-use-and-type ::= ssa-use `:` type
+    /// ```
+    pub fn parse_ssa_def_or_use_and_type(
+        &mut self,
+        mut action: impl FnMut(&mut Self, UnresolvedOperand<'a>, Arc<dyn Type>) -> ParseResult
+    ) -> ParseResult {
+        let use_info = self.parse_ssa_use(false)?;
+        self.parse_token(TokenKind::Colon, "expected ':' and type for SSA operand")?;
+        let r#type = self.parse_type()?;
+        action(self, use_info, r#type)
+    }
+
+    // ----------------------------------------------------------------------
+    // Operation Parsing
+    // ----------------------------------------------------------------------
+
+    /**
+    Parse an operation.
+
+    ```text
+    operation         ::= op-result-list?
+                          (generic-operation | custom-operation)
+                          trailing-location?
+    generic-operation ::= string-literal `(` ssa-use-list? `)`
+                          successor-list? (`(` region-list `)`)?
+                          attribute-dict? `:` function-type
+    custom-operation  ::= bare-id custom-operation-format
+    op-result-list    ::= op-result (`,` op-result)* `=`
+    op-result         ::= ssa-id (`:` integer-literal)
+    ```
+    */
+    pub fn parse_operation(&mut self) -> ParseResult {
+        let location = self.token().location();
+        let mut result_ids: Vec<ResultRecord<'a>> = Vec::new();
+        let mut num_expected_results = 0;
+        if self.token().is(TokenKind::PercentIdentifier) {
+            // Parse the group of result ids.
+            self.parse_comma_separated_list(Delimiter::None, |this| {
+                // Parse the next result id.
+                let name_token = this.token();
+                this.parse_token(
+                    TokenKind::PercentIdentifier, "expected valid ssa identifier")?;
+
+                // If the next token is a ':', we parse the expected result count.
+                let mut expected_sub_results = 1;
+                if this.consume_if(TokenKind::Colon) {
+                    // Check that the next token is an integer.
+                    if this.token().is_not(TokenKind::Integer) {
+                        return Err(this.emit_wrong_token_error(
+                            "expected integer number of results"));
+                    }
+
+                    // Check that number of results is > 0.
+                    match Token::uint64_integer_value(this.token_spelling()) {
+                        Some(value) if value >= 1 =>
+                            expected_sub_results = value as usize,
+                        _ => return Err(this.emit_error(
+                            "expected named operation to have at least 1 result"))
+                    }
+                    this.consume_token_of(TokenKind::Integer);
+                }
+
+                result_ids.push(ResultRecord {
+                    name: name_token.spelling(),
+                    count: expected_sub_results,
+                    location: name_token.location()
+                });
+                num_expected_results += expected_sub_results;
+                Ok(())
+            }, "")?;
+
+            self.parse_token(TokenKind::Equal, "expected '=' after SSA name")?;
+        }
+
+        let name_token = self.token();
+        let op = if name_token.is(TokenKind::BareIdentifier) || name_token.is_keyword() {
+            self.parse_custom_operation()?
+        } else if name_token.is(TokenKind::String) {
+            self.parse_generic_operation()?
+        } else {
+            return Err(self.emit_wrong_token_error("expected operation name in quotes"));
+        };
+
+        // If the operation had a name, register it.
+        if !result_ids.is_empty() {
+            let num_outputs = self.check(self.arena().op(op))?.num_outputs();
+            if num_outputs == 0 {
+                return Err(self.emit_error_at(
+                    location, "cannot name an operation with no results"));
+            }
+            if num_expected_results != num_outputs {
+                return Err(self.emit_error_at(location, format!(
+                    "operation defines {} results but was provided {} to bind",
+                    num_outputs, num_expected_results)));
+            }
+
+            // Add definitions for each of the result groups.
+            let mut index = 0;
+            for record in result_ids {
+                for number in 0..record.count {
+                    self.add_definition(
+                        UnresolvedOperand {
+                            location: record.location,
+                            name: record.name,
+                            number
+                        },
+                        Value::Output(Output::new(op, index)))?;
+                    index += 1;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+    Parse a single operation successor.
+
+    ```text
+    successor ::= block-id
+    ```
+    */
+    pub fn parse_successor(&mut self) -> ParseResult<BlockId> {
+        // Verify branch is identifier and get the matching block.
+        if self.token().is_not(TokenKind::CaretIdentifier) {
+            return Err(self.emit_wrong_token_error("expected block name"));
+        }
+        let block = self.block_named(self.token_spelling(), self.token().location());
+        self.consume_token();
+        Ok(block)
+    }
+
+    /**
+    Parse a comma-separated list of operation successors in brackets.
+
+    ```text
+    successor-list ::= `[` successor (`,` successor )* `]`
+    ```
+    */
+    pub fn parse_successors(&mut self) -> ParseResult<Vec<BlockId>> {
+        let mut destinations = Vec::new();
+        self.parse_comma_separated_list(Delimiter::Square, |this| {
+            destinations.push(this.parse_successor()?);
+            Ok(())
+        }, " in successor list")?;
+        Ok(destinations)
+    }
+
+    /// Parse an operation instance that is in the generic form.
+    pub fn parse_generic_operation(&mut self) -> ParseResult<OpId> {
+        // Get location information for the operation.
+        let source_location = self.encoded_source_location(self.token().location());
+
+        let name = self.token().string_value();
+        if name.is_empty() {
+            return Err(self.emit_error("empty operation name is invalid"));
+        }
+        if name.contains('\0') {
+            return Err(self.emit_error("null character not allowed in operation name"));
+        }
+
+        self.consume_token_of(TokenKind::String);
+
+        let name = self.context().operation_name(&name);
+
+        // Emit an error if the operation is unknown to the dynamic dialect it belongs to. Dynamic dialects do not allow unknown operations.
+        let dynamic_dialect = self.context().dynamic_dialect(name.dialect_namespace());
+        if let Some(dialect) = &dynamic_dialect
+            && dialect.lookup_op_definition(name.as_str()).is_none()
+        {
+            return Err(self.emit_error(format!(
+                "unregistered operation '{}' found in dynamic dialect ('{}') that does not allow unknown operations",
+                name.as_str(), dialect.namespace())));
+        }
+
+        // Emit an error if the dialect of the operation was not registered and unregistered dialects aren't allowed.
+        if !name.is_registered()
+            && dynamic_dialect.is_none()
+            && name.dialect_namespace() != "builtin"
+            && !self.context().allows_unregistered_dialects()
+        {
+            return Err(self.emit_error(
+                "operation being parsed with an unregistered dialect. If this is intended, please use -allow-unregistered-dialect with the MLIR tool used"));
+        }
+
+        // Parse the operand list, if not explicitly elided.
+        let mut operand_infos = Vec::new();
+        self.parse_token(TokenKind::LParen, "expected '(' to start operand list")?;
+        self.parse_optional_ssa_use_list(&mut operand_infos)?;
+        self.parse_token(TokenKind::RParen, "expected ')' to end operand list")?;
+
+        // Parse the successor list.
+        let mut successors = Vec::new();
+        if self.token().is(TokenKind::LSquare) {
+            // Check if the operation is not a known terminator.
+            if !name.might_have_trait::<dyn IsTerminator>() {
+                return Err(self.emit_error("successors in non-terminator"));
+            }
+            successors = self.parse_successors()?;
+        }
+
+        // Parse the region list. The regions are detached until the operation is created.
+        let first_region = self.pending_regions.len();
+        if self.consume_if(TokenKind::LParen) {
+            loop {
+                let region = self.arena_mut().create_region(None);
+                self.pending_regions.push(region);
+                self.parse_region(region, &[], false)?;
+                if !self.consume_if(TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.parse_token(TokenKind::RParen, "expected ')' to end region list")?;
+        }
+
+        let mut attributes = DictionaryAttribute::default();
+        if self.token().is(TokenKind::LBrace) {
+            attributes = DictionaryAttribute::new(self.parse_attribute_dict()?);
+        }
+
+        self.parse_token(TokenKind::Colon, "expected ':' followed by operation type")?;
+
+        let type_location = self.token().location();
+        let r#type = self.parse_type()?;
+        let function_type = r#type.downcast_ref::<FunctionType>()
+            .ok_or_else(|| self.emit_error_at(type_location, "expected function type"))?;
+        let output_types = function_type.outputs().to_vec();
+
+        // Check that we have the right number of types for the operands.
+        let operand_types = function_type.inputs().to_vec();
+        if operand_types.len() != operand_infos.len() {
+            return Err(self.emit_error_at(type_location, format!(
+                "expected {} operand type{} but had {}",
+                operand_infos.len(),
+                if operand_infos.len() == 1 { "" } else { "s" },
+                operand_types.len())));
+        }
+
+        // Resolve all of the operands.
+        let mut operands = Vec::with_capacity(operand_infos.len());
+        for (info, r#type) in operand_infos.into_iter().zip(operand_types) {
+            operands.push(self.resolve_ssa_use(info, r#type)?);
+        }
+
+        // Create the operation and try to parse a location for it.
+        let regions = self.pending_regions.split_off(first_region);
+        let operation = Operation::new(
+            source_location, name, output_types, operands, attributes, &successors);
+        let op = self.create_operation(operation, regions)?;
+        self.parse_trailing_location_specifier(DeferredLocTarget::Operation(op))?;
+
+        // Check the invariants of operations defined at runtime.
+        if let Some(dialect) = dynamic_dialect {
+            let operation = self.check(self.arena().op(op))?;
+            if let Err(error) = dialect.verify_operation(self.arena(), operation) {
+                return Err(match error.downcast::<Diagnostic>() {
+                    Ok(diagnostic) => {
+                        emit_op_diagnostic(self.context(), op, (*diagnostic).clone());
+                        diagnostic as Box<dyn Error>
+                    },
+                    Err(error) => self.emit_error(error.to_string())
+                });
+            }
+        }
+        Ok(op)
+    }
+
+    /// Create `operation` at the end of the current block, moving the blocks of the detached `regions` into its regions.
+    fn create_operation(&mut self, operation: Operation, regions: Vec<RegionId>)
+        -> ParseResult<OpId>
+    {
+        let insertion_block = self.insertion_block;
+        let arena = self.arena_mut();
+        let result = arena.create_operation(operation, regions.len())
+            .and_then(|op| {
+                for (index, region) in regions.into_iter().enumerate() {
+                    let target = arena.op(op)?.region(index);
+                    arena.take_body(target, region)?;
+                    arena.erase_region(region)?;
+                }
+                arena.push_back(insertion_block, op)?;
+                Ok(op)
+            });
+        self.check(result)
+    }
+
+    /// Parse an operation instance that is in the op-defined custom form.
+    pub fn parse_custom_operation(&mut self) -> ParseResult<OpId> {
+        let op_location = self.token().location();
+        let op_name = self.token_spelling();
+
+        let mut name = self.context().operation_name(op_name);
+        let mut tried = None;
+        if !name.is_registered() && !op_name.contains('.') {
+            // If the operation name has no namespace prefix we lookup the current default dialect.
+            let default_dialect = self.state.default_dialect_stack.last().unwrap();
+            let full_name = format!("{}.{}", default_dialect, op_name);
+            name = self.context().operation_name(&full_name);
+            tried = Some(full_name);
+        }
+
+        if !name.is_registered() {
+            let mut message = format!("custom op '{}' is unknown", op_name);
+            if let Some(tried) = tried {
+                message.push_str(&format!(" (tried '{}' as well)", tried));
+            }
+            return Err(self.emit_error_at(op_location, message));
+        }
+
+        let Some(parse_assembly_fn) = name.parse_assembly_fn() else {
+            return Err(self.emit_error_at(op_location, format!(
+                "operation '{}' has no custom assembly form, use the generic form",
+                name.as_str())));
+        };
+        self.consume_token();
+
+        // Parse the remainder of the operation with the hook of the operation. The regions it parses are detached until the operation is created.
+        let source_location = self.encoded_source_location(op_location);
+        let mut state = OperationState::new(source_location, name);
+        let first_region = self.pending_regions.len();
+        // On failure, the regions parsed so far stay pending and are erased when the parser is discarded.
+        parse_assembly_fn(
+            &mut CustomOpAsmParser { parser: &mut *self, name_location: op_location },
+            &mut state)?;
+        self.pending_regions.truncate(first_region);
+
+        // Create the operation and try to parse a location for it.
+        let operation = Operation::new(
+            state.location,
+            state.name,
+            state.types,
+            state.inputs,
+            DictionaryAttribute::new(state.attributes),
+            &state.successors);
+        let op = self.create_operation(operation, state.regions)?;
+        self.parse_trailing_location_specifier(DeferredLocTarget::Operation(op))?;
+        Ok(op)
+    }
+
+    /// Parse a location alias, that is a sequence looking like: `#loc42`. The alias may have already be defined or may be defined later, in which case an OpaqueLoc is used a placeholder.
+    fn parse_location_alias(&mut self, target: DeferredLocTarget)
+        -> ParseResult<Option<Location>>
+    {
+        let token = self.token();
+        self.consume_token_of(TokenKind::HashIdentifier);
+        let identifier = &token.spelling()[1..];
+        if identifier.contains('.') {
+            return Err(self.emit_error_at(token.location(), format!(
+                "expected location, but found dialect attribute: '#{}'", identifier)));
+        }
+
+        // If this alias can be resolved, do it now.
+        if let Some(location) = self.state.symbols
+            .location_alias_definitions.get(identifier)
+        {
+            return Ok(Some(location.clone()));
+        }
+        if let Some(attribute) = self.state.symbols
+            .attribute_alias_definitions.get(identifier)
+        {
+            return Err(self.emit_error_at(token.location(), format!(
+                "expected location, but found '{}'", attribute.as_ref())));
+        }
+
+        // Otherwise, remember this operation and resolve its location later.
+        self.deferred_locs_references.push(DeferredLocInfo {
+            target, location: token.location(), identifier });
+        Ok(None)
+    }
+
+    /**
+    Parse an optional trailing location and add it to the specifier Operation or Input if a location is present.
+
+    ```text
+    trailing-location ::= (`loc` (`(` location `)` | attribute-alias))?
+    ```
+    */
+    fn parse_trailing_location_specifier(&mut self, target: DeferredLocTarget)
+        -> ParseResult
+    {
+        // If there is a 'loc' we parse a trailing location.
+        if !self.consume_if(TokenKind::Keyword(Keyword::Loc)) {
+            return Ok(());
+        }
+        self.parse_token(TokenKind::LParen, "expected '(' in location")?;
+        let token = self.token();
+
+        // Check to see if we are parsing a location alias. We are parsing a location alias if the token is a hash identifier *without* a dot in it - the dot signifies a dialect attribute. Otherwise, we parse the location directly.
+        let location = if token.is(TokenKind::HashIdentifier)
+            && !token.spelling().contains('.')
+        {
+            self.parse_location_alias(target)?
+        } else {
+            Some(self.parse_location_instance()?)
+        };
+
+        self.parse_token(TokenKind::RParen, "expected ')' in location")?;
+
+        if let Some(location) = location {
+            let result = match target {
+                DeferredLocTarget::Operation(op) => self.arena_mut().op_mut(op)
+                    .map(|op| op.set_location(location)),
+                DeferredLocTarget::Input(input) => self.arena_mut()
+                    .block_mut(input.owner())
+                    .map(|block| block.inputs[input.index()].location = location)
+            };
+            self.check(result)?;
+        }
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Region Parsing
+    // ----------------------------------------------------------------------
+
+    /**
+    Parse a region into `region` with the provided entry block arguments. `is_isolated_name_scope` indicates if the naming scope of this region is isolated from those above.
+
+    ```text
+    region ::= '{' region-body
+    ```
+    */
+    pub fn parse_region(
+        &mut self,
+        region: RegionId,
+        entry_arguments: &[(UnresolvedOperand<'a>, Arc<dyn Type>)],
+        is_isolated_name_scope: bool
+    ) -> ParseResult {
+        // Parse the '{'.
+        self.parse_token(TokenKind::LBrace, "expected '{' to begin a region")?;
+
+        // Parse the region body.
+        if !entry_arguments.is_empty() || self.token().is_not(TokenKind::RBrace) {
+            self.parse_region_body(region, entry_arguments, is_isolated_name_scope)?;
+        }
+        self.consume_token_of(TokenKind::RBrace);
+        Ok(())
+    }
+
+    /**
+    Parse a region body into `region`.
+
+    ```text
+    region-body ::= block* '}'
+    ```
+    */
+    pub fn parse_region_body(
+        &mut self,
+        region: RegionId,
+        entry_arguments: &[(UnresolvedOperand<'a>, Arc<dyn Type>)],
+        is_isolated_name_scope: bool
+    ) -> ParseResult {
+        let insertion_block = self.insertion_block;
+
+        // Push a new named value scope.
+        self.push_ssa_name_scope(is_isolated_name_scope);
+
+        // Parse the first block directly to allow for it to be unnamed.
+        let block = self.arena_mut().create_block();
+        let result = self.arena_mut().push_back_block(region, block);
+        self.check(result)?;
+
+        // Add arguments to the entry block if we had the form with explicit names.
+        for (argument, r#type) in entry_arguments {
+            let location = self.encoded_source_location(argument.location);
+            let input = self.arena_mut().block_mut(block)
+                .map(|block| block.add_input(r#type.clone(), location));
+            let input = self.check(input)?;
+            self.add_definition(*argument, Value::Input(input))?;
+        }
+
+        self.parse_block(region, Some(block))?;
+
+        // Verify that no other arguments were parsed.
+        let num_inputs = self.check(self.arena().block(block))?.num_inputs();
+        if !entry_arguments.is_empty() && num_inputs > entry_arguments.len() {
+            return Err(self.emit_error("entry block arguments were already defined"));
+        }
+
+        // Parse the rest of the region.
+        while self.token().is_not(TokenKind::RBrace) {
+            self.parse_block(region, None)?;
+        }
+
+        // Pop the SSA value scope for this region.
+        self.pop_ssa_name_scope()?;
+
+        // Reset the original insertion point.
+        self.insertion_block = insertion_block;
+        Ok(())
+    }
+
+    /**
+    Block declaration. The block is appended to `region` unless `existing`, the first block of the region which may be unnamed, is given.
+
+    ```text
+    block ::= block-label? operation*
+    block-label    ::= block-id block-arg-list? `:`
+    block-id       ::= caret-id
+    block-arg-list ::= `(` ssa-id-and-type-list? `)`
+    ```
+    */
+    pub fn parse_block(&mut self, region: RegionId, existing: Option<BlockId>)
+        -> ParseResult<BlockId>
+    {
+        // The first block of a region may already exist, if it does the caret identifier is optional.
+        if let Some(block) = existing
+            && self.token().is_not(TokenKind::CaretIdentifier)
+        {
+            self.parse_block_body(block)?;
+            return Ok(block);
+        }
+
+        let name_location = self.token().location();
+        let name = self.token_spelling();
+        self.parse_token(TokenKind::CaretIdentifier, "expected block name")?;
+
+        // Define the block with the specified name.
+        let (defined, _) = self.block_info_by_name(name);
+
+        // If a block has yet to be set, this is a new definition. If the caller provided a block, use it. Otherwise create a new one.
+        let block = match defined {
+            None => {
+                let block = match existing {
+                    Some(block) => block,
+                    None => self.arena_mut().create_block()
+                };
+                self.set_block_info_by_name(name, block, name_location);
+                block
+            }
+            // Otherwise, the block has a forward declaration. Forward declarations are removed once defined, so if we are defining a existing block and it is not a forward declaration, then it is a redeclaration. Fail if the block was already defined.
+            Some(block) => {
+                if !self.erase_forward_ref(block) {
+                    return Err(self.emit_error_at(
+                        name_location, format!("redefinition of block '{}'", name)));
+                }
+                self.set_block_info_by_name(name, block, name_location);
+                block
+            }
+        };
+        if existing != Some(block) {
+            let result = self.arena_mut().push_back_block(region, block);
+            self.check(result)?;
+        }
+
+        // If an argument list is present, parse it.
+        if self.token().is(TokenKind::LParen) {
+            self.parse_optional_block_arg_list(block)?;
+        }
+        self.parse_token(TokenKind::Colon, "expected ':' after block name")?;
+
+        // Parse the body of the block.
+        self.parse_block_body(block)?;
+        Ok(block)
+    }
+
+    /// Parse a list of operations into the end of the given block.
+    fn parse_block_body(&mut self, block: BlockId) -> ParseResult {
+        // Set the insertion point to the end of the block to parse.
+        self.insertion_block = block;
+
+        // Parse the list of operations that make up the body of the block.
+        while self.token().is_not(TokenKind::CaretIdentifier)
+            && self.token().is_not(TokenKind::RBrace)
+        {
+            self.parse_operation()?;
+        }
+        Ok(())
+    }
+
+    /// Get the block with the specified name, creating it if it doesn't already exist. The location specified is the point of use, which allows us to diagnose references to blocks that are not defined precisely.
+    fn block_named(&mut self, name: &'a str, location: SMLoc) -> BlockId {
+        if let (Some(block), _) = self.block_info_by_name(name) {
+            return block;
+        }
+        let block = self.arena_mut().create_block();
+        self.set_block_info_by_name(name, block, location);
+        self.forward_ref.last_mut().unwrap().insert(block, location);
+        block
+    }
+
+    /// Returns the block and the location of its first reference for `name` in the current scope.
+    fn block_info_by_name(&self, name: &str) -> (Option<BlockId>, SMLoc) {
+        self.blocks_by_name.last().unwrap().get(name).copied()
+            .unwrap_or((None, SMLoc::default()))
+    }
+
+    fn set_block_info_by_name(&mut self, name: &'a str, block: BlockId, location: SMLoc) {
+        self.blocks_by_name.last_mut().unwrap().insert(name, (Some(block), location));
+    }
+
+    /// Erase any forward reference to the given block. Returns true if the block was a forward reference.
+    fn erase_forward_ref(&mut self, block: BlockId) -> bool {
+        self.forward_ref.last_mut().unwrap().remove(&block).is_some()
+    }
+
+    /**
+    Parse a (possibly empty) list of SSA operands, followed by a colon, then followed by a type list.
+
+    ```text
+    ssa-id-and-type-list ::= ssa-id-and-type (`,` ssa-id-and-type)*
+    ```
+    */
+    fn parse_optional_block_arg_list(&mut self, owner: BlockId) -> ParseResult {
+        if self.token().is(TokenKind::RBrace) {
+            return Ok(());
+        }
+
+        // If the block already has arguments, then we're handling the entry block. Parse and register the names for the arguments, but do not add them.
+        let num_inputs = self.check(self.arena().block(owner))?.num_inputs();
+        let defining_existing_args = num_inputs != 0;
+        let mut next_argument = 0;
+
+        self.parse_comma_separated_list(Delimiter::Paren, |this| {
+            this.parse_ssa_def_or_use_and_type(|this, use_info, r#type| {
+                // If we are defining existing arguments, ensure that the argument has already been created with the right type.
+                let input = if defining_existing_args {
+                    // Otherwise, ensure that this argument has already been created.
+                    if next_argument >= num_inputs {
+                        return Err(this.emit_error(
+                            "too many arguments specified in argument list"));
+                    }
+
+                    // Finally, make sure the existing argument has the correct type.
+                    let input = Input::new(owner, next_argument);
+                    let existing_type = this.check(
+                        Value::Input(input).r#type(this.arena()))?.clone();
+                    next_argument += 1;
+                    if !Self::same_type(&existing_type, &r#type) {
+                        return Err(this.emit_error(
+                            "argument and block argument type mismatch"));
+                    }
+                    input
+                } else {
+                    let location = this.encoded_source_location(use_info.location);
+                    let input = this.arena_mut().block_mut(owner)
+                        .map(|block| block.add_input(r#type, location));
+                    this.check(input)?
+                };
+
+                // If the argument has an explicit loc(...) specifier, parse and apply it.
+                this.parse_trailing_location_specifier(DeferredLocTarget::Input(input))?;
+
+                // Mark this block argument definition in the parser state if it was provided.
+                this.add_definition(use_info, Value::Input(input))
+            })
+        }, "")
+    }
+}
+
+// ----------------------------------------------------------------------
+// CustomOpAsmParser
+// ----------------------------------------------------------------------
+
+/// The parser handed to the `parse` hooks of operations, parsing the custom assembly form of an operation after its name.
+struct CustomOpAsmParser<'p, 'a, 'c> {
+    parser: &'p mut OperationParser<'a, 'c>,
+    /// The location of the name of the operation.
+    name_location: SMLoc
+}
+
+impl<'p, 'a, 'c> AsmParser for CustomOpAsmParser<'p, 'a, 'c> {
+    fn context(&self) -> &MLIRContext {
+        self.parser.context()
+    }
+
+    fn current_location(&self) -> SMLoc {
+        self.parser.token().location()
+    }
+
+    fn name_location(&self) -> SMLoc {
+        self.name_location
+    }
+
+    fn emit_error(&self, location: SMLoc, message: String) -> Box<dyn Error> {
+        self.parser.emit_error_at(location, message)
+    }
+
+    fn parse_optional_token(&mut self, kind: TokenKind) -> bool {
+        self.parser.consume_if(kind)
+    }
+
+    fn parse_optional_keyword(&mut self, keyword: &str) -> bool {
+        // Check that the current token is the keyword.
+        if !self.parser.is_current_token_a_keyword()
+            || self.parser.token_spelling() != keyword
+        {
+            return false;
+        }
+        self.parser.consume_token();
+        true
+    }
+
+    fn parse_optional_any_keyword(&mut self) -> Option<String> {
+        if !self.parser.is_current_token_a_keyword() {
+            return None;
+        }
+        let keyword = self.parser.token_spelling().to_string();
+        self.parser.consume_token();
+        Some(keyword)
+    }
+
+    fn parse_optional_string(&mut self) -> Option<String> {
+        if self.parser.token().is_not(TokenKind::String) {
+            return None;
+        }
+        let string = self.parser.token().string_value();
+        self.parser.consume_token();
+        Some(string)
+    }
+
+    fn parse_optional_integer(&mut self) -> Option<ParseResult<APInt>> {
+        self.parser.parse_optional_integer()
+    }
+
+    fn parse_attribute(&mut self) -> ParseResult<Arc<dyn Attribute>> {
+        self.parser.parse_attribute(None)
+    }
+
+    fn parse_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.parser.parse_type()
+    }
+
+    fn parse_optional_type(&mut self) -> Option<ParseResult<Arc<dyn Type>>> {
+        self.parser.parse_optional_type()
+    }
+}
+
+impl<'p, 'a, 'c> OpAsmParser<'a> for CustomOpAsmParser<'p, 'a, 'c> {
+    fn parse_optional_operand(&mut self)
+        -> Option<ParseResult<UnresolvedOperand<'a>>>
+    {
+        if self.parser.token().is_not(TokenKind::PercentIdentifier) {
+            return None;
+        }
+        Some(self.parser.parse_ssa_use(true))
+    }
+
+    fn resolve_operand(
+        &mut self,
+        operand: UnresolvedOperand<'a>,
+        r#type: Arc<dyn Type>
+    ) -> ParseResult<Value> {
+        self.parser.resolve_ssa_use(operand, r#type)
+    }
+
+    fn parse_optional_attr_dict(&mut self, attributes: &mut Vec<NamedAttribute>)
+        -> ParseResult
+    {
+        if self.parser.token().is(TokenKind::LBrace) {
+            attributes.extend(self.parser.parse_attribute_dict()?);
+        }
+        Ok(())
+    }
+
+    fn parse_region(&mut self) -> ParseResult<RegionId> {
+        let region = self.parser.arena_mut().create_region(None);
+        self.parser.pending_regions.push(region);
+        self.parser.parse_region(region, &[], false)?;
+        Ok(region)
+    }
+
+    fn parse_successor(&mut self) -> ParseResult<BlockId> {
+        self.parser.parse_successor()
+    }
 }
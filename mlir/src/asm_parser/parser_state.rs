@@ -1,79 +1,91 @@
 /*!
-This file declares the MLIR Lexer class.
+This file declares the state shared by the MLIR parsers.
 
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/ParserState.h>
 */
 
+use std::{collections::HashMap, sync::Arc};
+
 use crate::{
-    mlir::{
-        asm_parser::{
-            asm_parser_state::AsmParserState,
-            code_complete::AsmParserCodeCompleteContext,
-            lexer::Lexer,
-            token::Token
-        },
-        ir::{
-            attribute::Attribute,
-            operation::implementation::{
-                AsmDialectResourceHandle, OpAsmDialectInterface
-            },
-            r#type::Type
-        }
+    asm_parser::{
+        lexer::Lexer,
+        token::Token
     },
-    llvm::adt::{
-        dense_map::DenseMap,
-        string_map::StringMap,
+    ir::{
+        asm_state::ParserConfig,
+        attribute::Attribute,
+        location::Location,
+        operation::implementation::AsmDialectResourceHandle,
+        r#type::Type
     }
 };
 
 /// This class contains record of any parsed top-level symbols.
+#[derive(Default)]
 pub struct SymbolState {
     /// A map from attribute alias identifier to Attribute.
-    attribute_alias_definitions: StringMap<dyn Attribute>,
+    pub attribute_alias_definitions: HashMap<String, Arc<dyn Attribute>>,
+
+    /// A map from attribute alias identifier to Location. Locations are not attributes in this crate, so aliases of `loc(...)` values are recorded separately.
+    pub location_alias_definitions: HashMap<String, Location>,
 
     /// A map from type alias identifier to Type.
-    type_alias_definitions: StringMap<dyn Type>,
-
-    /**
-    A map of dialect resource keys to the resolved resource name and handle to use during parsing.
-    */
-    dialect_resources: DenseMap<
-        Option<OpAsmDialectInterface>,
-        StringMap<(String, AsmDialectResourceHandle)>
+    pub type_alias_definitions: HashMap<String, Arc<dyn Type>>,
+
+    /// A map of dialect resource keys to the resolved resource name and handle to use during parsing, keyed by the namespace of the owning dialect.
+    pub dialect_resources: HashMap<
+        String,
+        HashMap<String, (String, AsmDialectResourceHandle)>
     >
 }
 
-/**
-This class refers to all of the state maintained globally by the parser, such as the current lexer position etc.
-*/
-pub struct ParserState<'a> {
+impl SymbolState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+/// This class refers to all of the state maintained globally by the parser, such as the current lexer position etc.
+pub struct ParserState<'a, 'c> {
+    /// The configuration used to setup the parser.
+    pub config: &'a mut ParserConfig<'c>,
+
     /// The lexer for the source file we're parsing.
-    lexer: Lexer<'a>,
+    pub lexer: Lexer<'a>,
 
     /// This is the next token that hasn't been consumed yet.
-    current_token: Token,
+    pub current_token: Token<'a>,
 
     /// The current state for symbol parsing.
-    symbols: &'a SymbolState,
-
-    /// An optional pointer to a struct containing high level parser state to be
-    /// populated during parsing.
-    asm_state: Option<AsmParserState>,
-
-    /// An optional code completion context.
-    code_complete_context: Option<AsmParserCodeCompleteContext>,
-
-    /**
-    Contains the stack of default dialect to use when parsing regions.
-    A new dialect get pushed to the stack before parsing regions nested
-    under an operation implementing `OpAsmOpInterface`, and
-    popped when done. At the top-level we start with "builtin" as the
-    default, so that the top-level `module` operation parses as-is.
-    */
-    default_dialect_stack: Vec<&'static str>  // {"builtin"};
+    pub symbols: &'a mut SymbolState,
+
+    /// The name of the buffer being parsed, used in the locations of the parsed IR.
+    pub filename: String,
+
+    /// Contains the stack of default dialect to use when parsing regions. A new dialect get pushed to the stack before parsing regions nested under an operation implementing `OpAsmOpInterface`, and popped when done. At the top-level we start with "builtin" as the default, so that the top-level `module` operation parses as-is.
+    pub default_dialect_stack: Vec<String>,
+
+    /// The storages of the attributes and types whose bodies are currently being parsed, used by dialects to resolve self-references.
+    pub cyclic_parsing_stack: Vec<*const ()>
 }
 
-impl<'a> ParserState<'a> {
-    // /// The configuration used to setup the parser.
-    // const config: &'a ParserConfig;
+impl<'a, 'c> ParserState<'a, 'c> {
+    pub fn new(
+        source: &'a str,
+        filename: impl Into<String>,
+        config: &'a mut ParserConfig<'c>,
+        symbols: &'a mut SymbolState
+    ) -> Self {
+        let mut lexer = Lexer::new(source);
+        let current_token = lexer.lex_token();
+        Self {
+            config,
+            lexer,
+            current_token,
+            symbols,
+            filename: filename.into(),
+            default_dialect_stack: vec!["builtin".to_string()],
+            cyclic_parsing_stack: Vec::new()
+        }
+    }
 }
@@ -1,58 +1,450 @@
 /*!
-This file declares the MLIR Lexer class.
+This file declares the Token class, which represents a token in the MLIR syntax.
 
 - lib
   - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/Token.h>
   - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/Token.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/TokenKinds.def>
 */
 
-use crate::{
-    mlir::support::llvm,
-    llvm::{
-        adt::{
-            // optional,
-            string_extras,
-        },
-        support::sm_loc
-    }
-};
+use llvm::support::sm_loc::{SMLoc, SMRange};
 
 /// This represents a token in the MLIR syntax.
-pub struct Token {
+#[derive(Clone, Copy, Debug)]
+pub struct Token<'a> {
     /// Discriminator that indicates the sort of token this is.
     kind: TokenKind,
 
-    /**
-    A reference to the entire token contents; this is always a pointer into a memory buffer owned by the source manager.
-    */
-    spelling: &'static str
+    /// A reference to the entire token contents; this is always a slice of the buffer being lexed.
+    spelling: &'a str,
+
+    /// The byte offset of the token in the buffer being lexed.
+    location: SMLoc
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum TokenKind {
-  // #define TOK_MARKER(NAME) NAME,
-  // #define TOK_IDENTIFIER(NAME) NAME,
-  // #define TOK_LITERAL(NAME) NAME,
-  // #define TOK_PUNCTUATION(NAME, SPELLING) NAME,
-  // #define TOK_KEYWORD(SPELLING) kw_##SPELLING,
-  // #include "TokenKinds.def"
+    /* Markers */
+
+    /// End of file.
+    Eof,
+    /// A lexing error, which was recorded by the lexer.
+    Error,
+
+    /* Identifiers */
+
+    /// `foo`
+    BareIdentifier,
+    /// `@foo`
+    AtIdentifier,
+    /// `#foo`
+    HashIdentifier,
+    /// `%foo`
+    PercentIdentifier,
+    /// `^foo`
+    CaretIdentifier,
+    /// `!foo`
+    ExclamationIdentifier,
+
+    /* Literals */
+
+    /// `42.0`
+    FloatLiteral,
+    /// `42`
+    Integer,
+    /// `"foo"`
+    String,
+    /// `i4`, `si8`, `ui16`
+    IntType,
+
+    /* Punctuation */
+
+    /// `->`
+    Arrow,
+    /// `@`
+    At,
+    /// `:`
+    Colon,
+    /// `,`
+    Comma,
+    /// `...`
+    Ellipsis,
+    /// `=`
+    Equal,
+    /// `>`
+    Greater,
+    /// `{`
+    LBrace,
+    /// `(`
+    LParen,
+    /// `[`
+    LSquare,
+    /// `<`
+    Less,
+    /// `-`
+    Minus,
+    /// `+`
+    Plus,
+    /// `?`
+    Question,
+    /// `}`
+    RBrace,
+    /// `)`
+    RParen,
+    /// `]`
+    RSquare,
+    /// `*`
+    Star,
+    /// `|`
+    VerticalBar,
+    /// `{-#`
+    FileMetadataBegin,
+    /// `#-}`
+    FileMetadataEnd,
+
+    /* Keywords */
+
+    Keyword(Keyword)
+}
+
+/// The keywords of the MLIR syntax. Keywords are also valid bare identifiers wherever one is expected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Keyword {
+    AffineMap,
+    AffineSet,
+    Array,
+    Attributes,
+    BF16,
+    CeilDiv,
+    Complex,
+    Dense,
+    DenseResource,
+    F16,
+    F32,
+    F64,
+    F80,
+    F128,
+    F8E5M2,
+    F8E4M3FN,
+    False,
+    FloorDiv,
+    For,
+    Func,
+    Index,
+    Loc,
+    Max,
+    MemRef,
+    Min,
+    Mod,
+    None,
+    Offset,
+    Size,
+    Sparse,
+    Step,
+    Strided,
+    Symbol,
+    Tensor,
+    To,
+    True,
+    Tuple,
+    Type,
+    Unit,
+    Vector
+}
+
+/// The spelling of each keyword.
+const KEYWORDS: [(&str, Keyword); 40] = [
+    ("affine_map", Keyword::AffineMap),
+    ("affine_set", Keyword::AffineSet),
+    ("array", Keyword::Array),
+    ("attributes", Keyword::Attributes),
+    ("bf16", Keyword::BF16),
+    ("ceildiv", Keyword::CeilDiv),
+    ("complex", Keyword::Complex),
+    ("dense", Keyword::Dense),
+    ("dense_resource", Keyword::DenseResource),
+    ("f16", Keyword::F16),
+    ("f32", Keyword::F32),
+    ("f64", Keyword::F64),
+    ("f80", Keyword::F80),
+    ("f128", Keyword::F128),
+    ("f8E5M2", Keyword::F8E5M2),
+    ("f8E4M3FN", Keyword::F8E4M3FN),
+    ("false", Keyword::False),
+    ("floordiv", Keyword::FloorDiv),
+    ("for", Keyword::For),
+    ("func", Keyword::Func),
+    ("index", Keyword::Index),
+    ("loc", Keyword::Loc),
+    ("max", Keyword::Max),
+    ("memref", Keyword::MemRef),
+    ("min", Keyword::Min),
+    ("mod", Keyword::Mod),
+    ("none", Keyword::None),
+    ("offset", Keyword::Offset),
+    ("size", Keyword::Size),
+    ("sparse", Keyword::Sparse),
+    ("step", Keyword::Step),
+    ("strided", Keyword::Strided),
+    ("symbol", Keyword::Symbol),
+    ("tensor", Keyword::Tensor),
+    ("to", Keyword::To),
+    ("true", Keyword::True),
+    ("tuple", Keyword::Tuple),
+    ("type", Keyword::Type),
+    ("unit", Keyword::Unit),
+    ("vector", Keyword::Vector)
+];
+
+impl Keyword {
+    /// Return the keyword spelled `spelling`, if any.
+    pub fn from_spelling(spelling: &str) -> Option<Self> {
+        KEYWORDS.iter()
+            .find(|(keyword, _)| *keyword == spelling)
+            .map(|(_, keyword)| *keyword)
+    }
+
+    /// Return the spelling of this keyword.
+    pub fn spelling(&self) -> &'static str {
+        KEYWORDS.iter()
+            .find(|(_, keyword)| keyword == self)
+            .map(|(spelling, _)| *spelling)
+            .unwrap()
+    }
 }
 
-impl Token {
+impl TokenKind {
+    /// Given a punctuation or keyword token kind, return the spelling of the token as a string.
+    pub fn spelling(&self) -> &'static str {
+        match self {
+            Self::Arrow => "->",
+            Self::At => "@",
+            Self::Colon => ":",
+            Self::Comma => ",",
+            Self::Ellipsis => "...",
+            Self::Equal => "=",
+            Self::Greater => ">",
+            Self::LBrace => "{",
+            Self::LParen => "(",
+            Self::LSquare => "[",
+            Self::Less => "<",
+            Self::Minus => "-",
+            Self::Plus => "+",
+            Self::Question => "?",
+            Self::RBrace => "}",
+            Self::RParen => ")",
+            Self::RSquare => "]",
+            Self::Star => "*",
+            Self::VerticalBar => "|",
+            Self::FileMetadataBegin => "{-#",
+            Self::FileMetadataEnd => "#-}",
+            Self::Keyword(keyword) => keyword.spelling(),
+            _ => unreachable!("This token kind has no fixed spelling")
+        }
+    }
+}
+
+impl<'a> Token<'a> {
+    pub const fn new(kind: TokenKind, spelling: &'a str, location: SMLoc) -> Self {
+        Self { kind, spelling, location }
+    }
+
     /// Return the bytes that make up this token.
-    pub const fn spelling(&self) -> &'static str {
+    pub const fn spelling(&self) -> &'a str {
         self.spelling
     }
 
-    // Token classification.
+    /// Return the kind of this token.
     pub const fn kind(&self) -> TokenKind {
         self.kind
     }
-    
-    pub const fn is(&self, kind: TokenKind) -> bool {
-        match self.kind {
-            kind => true,
-            _ => false
+
+    pub fn is(&self, kind: TokenKind) -> bool {
+        self.kind == kind
+    }
+
+    pub fn is_not(&self, kind: TokenKind) -> bool {
+        self.kind != kind
+    }
+
+    pub fn is_any(&self, kinds: &[TokenKind]) -> bool {
+        kinds.contains(&self.kind)
+    }
+
+    /// Return true if this is one of the keyword token kinds (e.g. `affine_map`).
+    pub fn is_keyword(&self) -> bool {
+        matches!(self.kind, TokenKind::Keyword(_))
+    }
+
+    /// For an integer token, return its value as an u32. If it doesn't fit, return None.
+    pub fn unsigned_integer_value(&self) -> Option<u32> {
+        Self::uint64_integer_value(self.spelling)
+            .and_then(|value| u32::try_from(value).ok())
+    }
+
+    /// For an integer token, return its value as an u64. If it doesn't fit, return None.
+    pub fn uint64_integer_value(spelling: &str) -> Option<u64> {
+        match spelling.strip_prefix("0x") {
+            Some(digits) => u64::from_str_radix(digits, 16).ok(),
+            None => spelling.parse().ok()
+        }
+    }
+
+    /// For a floatliteral token, return its value as a f64. Returns None if the value does not fit.
+    pub fn float_value(&self) -> Option<f64> {
+        self.spelling.parse::<f64>().ok().filter(|value| value.is_finite())
+    }
+
+    /// For an inttype token, return its bitwidth.
+    pub fn int_type_bitwidth(&self) -> Option<usize> {
+        assert!(self.is(TokenKind::IntType));
+        let start = if self.spelling.starts_with('i') { 1 } else { 2 };
+        self.spelling[start..].parse().ok()
+    }
+
+    /// For an inttype token, return its signedness semantics: `None` means no signedness semantics; `Some(true)` means signed integer type; `Some(false)` means unsigned integer type.
+    pub fn int_type_signedness(&self) -> Option<bool> {
+        assert!(self.is(TokenKind::IntType));
+        match self.spelling.as_bytes()[0] {
+            b's' => Some(true),
+            b'u' => Some(false),
+            _ => None
+        }
+    }
+
+    /// Given a hash_identifier token like `#123`, try to parse the number out of the identifier, returning None if it is a named identifier like `#x` or if the integer doesn't fit.
+    pub fn hash_identifier_number(&self) -> Option<usize> {
+        assert!(self.is(TokenKind::HashIdentifier));
+        self.spelling[1..].parse().ok()
+    }
+
+    /// Given a token containing a string literal, return its value, including removing the quote characters and unescaping the contents of the string. The lexer has already verified that this token is valid.
+    pub fn string_value(&self) -> String {
+        assert!(self.is_any(&[TokenKind::String, TokenKind::AtIdentifier]));
+        // Start by dropping the quotes.
+        let mut bytes = &self.spelling.as_bytes()[1..self.spelling.len() - 1];
+        if self.is(TokenKind::AtIdentifier) {
+            bytes = &bytes[1..];
+        }
+
+        let mut output = Vec::with_capacity(bytes.len());
+        let mut index = 0;
+        while index < bytes.len() {
+            let char = bytes[index];
+            index += 1;
+            if char != b'\\' {
+                output.push(char);
+                continue;
+            }
+            let escaped = bytes[index];
+            index += 1;
+            match escaped {
+                b'"' | b'\\' => output.push(escaped),
+                b'n' => output.push(b'\n'),
+                b't' => output.push(b'\t'),
+                _ => {
+                    // Otherwise, this must be a hex escape.
+                    let high = (escaped as char).to_digit(16).unwrap();
+                    let low = (bytes[index] as char).to_digit(16).unwrap();
+                    index += 1;
+                    output.push((high << 4 | low) as u8);
+                }
+            }
+        }
+        String::from_utf8(output).unwrap_or_else(|error|
+            String::from_utf8_lossy(error.as_bytes()).into_owned())
+    }
+
+    /// Given a token containing a hex string literal, return its value or None if the token does not contain a valid hex string. A hex string literal is a string starting with `0x` and only containing hex digits.
+    pub fn hex_string_value(&self) -> Option<Vec<u8>> {
+        // Check for the '"0x' prefix.
+        let digits = self.spelling.strip_prefix("\"0x")?.strip_suffix('"')?;
+        if digits.is_empty() || digits.len() % 2 != 0 {
+            return None;
+        }
+        (0..digits.len()).step_by(2)
+            .map(|index| u8::from_str_radix(digits.get(index..index + 2)?, 16).ok())
+            .collect()
+    }
+
+    /// Given a token containing a symbol reference, return the unescaped string value.
+    pub fn symbol_reference(&self) -> String {
+        let name = &self.spelling[1..];
+        // Check to see if the reference is a string literal, or a bare identifier.
+        if name.starts_with('"') {
+            self.string_value()
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Location processing.
+    pub const fn location(&self) -> SMLoc {
+        self.location
+    }
+
+    pub fn end_location(&self) -> SMLoc {
+        SMLoc::from_offset(self.location.offset() + self.spelling.len())
+    }
+
+    pub fn location_range(&self) -> SMRange {
+        SMRange::new(self.location(), self.end_location())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(kind: TokenKind, spelling: &str) -> Token<'_> {
+        Token::new(kind, spelling, SMLoc::default())
+    }
+
+    #[test]
+    fn strings_are_unescaped() {
+        assert_eq!(token(TokenKind::String, r#""a\"b\\\n\t\41\E9""#).string_value(), "a\"b\\\n\tA\u{FFFD}");
+        assert_eq!(token(TokenKind::String, r#""\C3\A9""#).string_value(), "é");
+        assert_eq!(token(TokenKind::AtIdentifier, r#"@"sym bol""#).symbol_reference(), "sym bol");
+        assert_eq!(token(TokenKind::AtIdentifier, "@foo").symbol_reference(), "foo");
+    }
+
+    #[test]
+    fn hex_strings_hold_whole_bytes() {
+        assert_eq!(token(TokenKind::String, "\"0x0AfF\"").hex_string_value(), Some(vec![0x0A, 0xFF]));
+        assert_eq!(token(TokenKind::String, "\"0xABC\"").hex_string_value(), None);
+        assert_eq!(token(TokenKind::String, "\"0xZZ\"").hex_string_value(), None);
+        assert_eq!(token(TokenKind::String, "\"0x\"").hex_string_value(), None);
+        assert_eq!(token(TokenKind::String, "\"AB\"").hex_string_value(), None);
+    }
+
+    #[test]
+    fn integer_values_must_fit() {
+        assert_eq!(token(TokenKind::Integer, "0x2A").unsigned_integer_value(), Some(42));
+        assert_eq!(token(TokenKind::Integer, "4294967296").unsigned_integer_value(), None);
+        assert_eq!(Token::uint64_integer_value("18446744073709551615"), Some(u64::MAX));
+        assert_eq!(Token::uint64_integer_value("18446744073709551616"), None);
+        assert_eq!(token(TokenKind::FloatLiteral, "2.5E+10").float_value(), Some(2.5e10));
+        assert_eq!(token(TokenKind::FloatLiteral, "1.0e400").float_value(), None);
+    }
+
+    #[test]
+    fn integer_types_have_a_width_and_signedness() {
+        let types = [("i1", 1, None), ("si8", 8, Some(true)), ("ui64", 64, Some(false))];
+        for (spelling, width, signedness) in types {
+            let token = token(TokenKind::IntType, spelling);
+            assert_eq!(token.int_type_bitwidth(), Some(width));
+            assert_eq!(token.int_type_signedness(), signedness);
+        }
+        assert_eq!(token(TokenKind::HashIdentifier, "#12").hash_identifier_number(), Some(12));
+        assert_eq!(token(TokenKind::HashIdentifier, "#map").hash_identifier_number(), None);
+    }
+
+    #[test]
+    fn keywords_round_trip_through_their_spelling() {
+        for (spelling, keyword) in KEYWORDS {
+            assert_eq!(Keyword::from_spelling(spelling), Some(keyword));
+            assert_eq!(TokenKind::Keyword(keyword).spelling(), spelling);
         }
+        assert_eq!(Keyword::from_spelling("i32"), None);
     }
 }
@@ -1,3 +1,567 @@
 /*!
+This file implements the parser for the MLIR Types.
+
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/AsmParser/TypeParser.cpp>
 */
+
+use std::sync::Arc;
+
+use crate::{
+    asm_parser::{
+        parser::{AsParser, ParseResult, Parser},
+        token::{Keyword, Token, TokenKind}
+    },
+    ir::{
+        attribute::Attribute,
+        builtins::{
+            attributes::{AffineMapAttribute, StridedLayoutAttribute},
+            type_interfaces::DYNAMIC,
+            types::{
+                ComplexType, FloatType, FloatTypeKind, FunctionType, IndexType,
+                IntegerType, MemRefType, NoneType, OpaqueType, RankedTensorType,
+                Signedness, TupleType, UnrankedMemRefType, UnrankedTensorType,
+                VectorType
+            }
+        },
+        operation::implementation::Delimiter,
+        r#type::Type
+    }
+};
+
+/// Return true if the specified element type is ok in a tensor.
+fn is_valid_tensor_element_type(r#type: &Arc<dyn Type>) -> bool {
+    r#type.downcast_ref::<ComplexType>().is_some()
+    || r#type.downcast_ref::<FloatType>().is_some()
+    || r#type.downcast_ref::<IntegerType>().is_some()
+    || r#type.downcast_ref::<OpaqueType>().is_some()
+    || r#type.downcast_ref::<IndexType>().is_some()
+    || r#type.downcast_ref::<VectorType>().is_some()
+}
+
+impl<'a, 'c> Parser<'a, 'c> {
+    /// Optionally parse a type. Returns None if the current token cannot start a type.
+    pub fn parse_optional_type(&mut self) -> Option<ParseResult<Arc<dyn Type>>> {
+        // There are many different starting tokens for a type, check them here.
+        match self.token().kind() {
+            TokenKind::LParen
+            | TokenKind::IntType
+            | TokenKind::ExclamationIdentifier
+            | TokenKind::Keyword(
+                Keyword::MemRef | Keyword::Tensor | Keyword::Complex
+                | Keyword::Tuple | Keyword::Vector | Keyword::F8E5M2
+                | Keyword::F8E4M3FN | Keyword::BF16 | Keyword::F16
+                | Keyword::F32 | Keyword::F64 | Keyword::F80 | Keyword::F128
+                | Keyword::Index | Keyword::None
+            ) => Some(self.parse_type()),
+            _ => None
+        }
+    }
+
+    /**
+    Parse an arbitrary type.
+
+    ```text
+    type ::= function-type
+           | non-function-type
+    ```
+    */
+    pub fn parse_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        if self.token().is(TokenKind::LParen) {
+            return self.parse_function_type();
+        }
+        self.parse_non_function_type()
+    }
+
+    /**
+    Parse a function result type.
+
+    ```text
+    function-result-type ::= type-list-parens
+                           | non-function-type
+    ```
+    */
+    pub fn parse_function_result_types(&mut self) -> ParseResult<Vec<Arc<dyn Type>>> {
+        if self.token().is(TokenKind::LParen) {
+            return self.parse_type_list_parens();
+        }
+        Ok(vec![self.parse_non_function_type()?])
+    }
+
+    /**
+    Parse a list of types without an enclosing parenthesis. The list must have at least one member.
+
+    ```text
+    type-list-no-parens ::=  type (`,` type)*
+    ```
+    */
+    pub fn parse_type_list_no_parens(&mut self) -> ParseResult<Vec<Arc<dyn Type>>> {
+        let mut elements = Vec::new();
+        self.parse_comma_separated_list(Delimiter::None, |this| {
+            elements.push(this.parse_type()?);
+            Ok(())
+        }, "")?;
+        Ok(elements)
+    }
+
+    /**
+    Parse a parenthesized list of types.
+
+    ```text
+    type-list-parens ::= `(` `)`
+                       | `(` type-list-no-parens `)`
+    ```
+    */
+    pub fn parse_type_list_parens(&mut self) -> ParseResult<Vec<Arc<dyn Type>>> {
+        self.parse_token(TokenKind::LParen, "expected '('")?;
+
+        // Handle empty lists.
+        if self.consume_if(TokenKind::RParen) {
+            return Ok(Vec::new());
+        }
+
+        let elements = self.parse_type_list_no_parens()?;
+        self.parse_token(TokenKind::RParen, "expected ')'")?;
+        Ok(elements)
+    }
+
+    /// Parse a colon followed by a type.
+    pub fn parse_colon_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.parse_token(TokenKind::Colon, "expected ':'")?;
+        self.parse_type()
+    }
+
+    /**
+    Parse a complex type.
+
+    ```text
+    complex-type ::= `complex` `<` type `>`
+    ```
+    */
+    fn parse_complex_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.consume_token_of(TokenKind::Keyword(Keyword::Complex));
+
+        // Parse the '<'.
+        self.parse_token(TokenKind::Less, "expected '<' in complex type")?;
+
+        let element_type_location = self.token().location();
+        let element_type = self.parse_type()?;
+        self.parse_token(TokenKind::Greater, "expected '>' in complex type")?;
+        if element_type.downcast_ref::<FloatType>().is_none()
+            && element_type.downcast_ref::<IntegerType>().is_none()
+        {
+            return Err(self.emit_error_at(
+                element_type_location, "invalid element type for complex"));
+        }
+
+        Ok(Arc::new(ComplexType::new(element_type)))
+    }
+
+    /**
+    Parse a function type.
+
+    ```text
+    function-type ::= type-list-parens `->` function-result-type
+    ```
+    */
+    pub fn parse_function_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        assert!(self.token().is(TokenKind::LParen));
+
+        let inputs = self.parse_type_list_parens()?;
+        self.parse_token(TokenKind::Arrow, "expected '->' in function type")?;
+        let outputs = self.parse_function_result_types()?;
+
+        Ok(Arc::new(FunctionType::new(inputs, outputs)))
+    }
+
+    /**
+    Parse a non function type.
+
+    ```text
+    non-function-type ::= integer-type
+                        | index-type
+                        | float-type
+                        | extended-type
+                        | vector-type
+                        | tensor-type
+                        | memref-type
+                        | complex-type
+                        | tuple-type
+                        | none-type
+    index-type ::= `index`
+    float-type ::= `f16` | `bf16` | `f32` | `f64` | `f80` | `f128`
+    none-type ::= `none`
+    ```
+    */
+    pub fn parse_non_function_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        let float_kind = match self.token().kind() {
+            TokenKind::Keyword(Keyword::MemRef) => return self.parse_memref_type(),
+            TokenKind::Keyword(Keyword::Tensor) => return self.parse_tensor_type(),
+            TokenKind::Keyword(Keyword::Complex) => return self.parse_complex_type(),
+            TokenKind::Keyword(Keyword::Tuple) => return self.parse_tuple_type(),
+            TokenKind::Keyword(Keyword::Vector) => return self.parse_vector_type(),
+            // integer-type
+            TokenKind::IntType => {
+                let width = self.token().int_type_bitwidth()
+                    .ok_or_else(|| self.emit_error("invalid integer width"))?;
+                if width > IntegerType::MAX_WIDTH {
+                    return Err(self.emit_error(format!(
+                        "integer bitwidth is limited to {} bits",
+                        IntegerType::MAX_WIDTH)));
+                }
+                let signedness = match self.token().int_type_signedness() {
+                    None => Signedness::Signless,
+                    Some(true) => Signedness::Signed,
+                    Some(false) => Signedness::Unsigned
+                };
+                self.consume_token_of(TokenKind::IntType);
+                return Ok(Arc::new(IntegerType::new(width, signedness)));
+            }
+            // float-type
+            TokenKind::Keyword(Keyword::F8E5M2) => FloatTypeKind::Float8E5M2Type,
+            TokenKind::Keyword(Keyword::F8E4M3FN) => FloatTypeKind::Float8E4M3FNType,
+            TokenKind::Keyword(Keyword::BF16) => FloatTypeKind::BFloat16Type,
+            TokenKind::Keyword(Keyword::F16) => FloatTypeKind::Float16Type,
+            TokenKind::Keyword(Keyword::F32) => FloatTypeKind::Float32Type,
+            TokenKind::Keyword(Keyword::F64) => FloatTypeKind::Float64Type,
+            TokenKind::Keyword(Keyword::F80) => FloatTypeKind::Float80Type,
+            TokenKind::Keyword(Keyword::F128) => FloatTypeKind::Float128Type,
+            // index-type
+            TokenKind::Keyword(Keyword::Index) => {
+                self.consume_token();
+                return Ok(Arc::new(IndexType::new()));
+            }
+            // none-type
+            TokenKind::Keyword(Keyword::None) => {
+                self.consume_token();
+                return Ok(Arc::new(NoneType));
+            }
+            // extended type
+            TokenKind::ExclamationIdentifier => return self.parse_extended_type(),
+            _ => return Err(self.emit_wrong_token_error("expected non-function type"))
+        };
+        self.consume_token();
+        Ok(Arc::new(FloatType::new(float_kind)))
+    }
+
+    /**
+    Parse a memref type.
+
+    ```text
+    memref-type ::= ranked-memref-type | unranked-memref-type
+
+    ranked-memref-type ::= `memref` `<` dimension-list-ranked type
+                            (`,` layout-specification)? (`,` memory-space)? `>`
+
+    unranked-memref-type ::= `memref` `<*x` type (`,` memory-space)? `>`
+
+    stride-list ::= `[` (dimension (`,` dimension)*)? `]`
+    strided-layout ::= `offset:` dimension `,` `strides: ` stride-list
+    layout-specification ::= semi-affine-map | strided-layout | attribute
+    memory-space ::= integer-literal | attribute
+    ```
+    */
+    fn parse_memref_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        let location = self.token().location();
+        self.consume_token_of(TokenKind::Keyword(Keyword::MemRef));
+
+        self.parse_token(TokenKind::Less, "expected '<' in memref type")?;
+
+        let mut dimensions = None;
+        if self.consume_if(TokenKind::Star) {
+            // This is an unranked memref type.
+            self.parse_x_in_dimension_list()?;
+        } else {
+            dimensions = Some(self.parse_dimension_list_ranked(true, true)?);
+        }
+
+        // Parse the element type.
+        let element_type_location = self.token().location();
+        let element_type = self.parse_type()?;
+        // Check that memref is formed from allowed types.
+        if !MemRefType::is_valid_element_type(element_type.as_ref()) {
+            return Err(self.emit_error_at(
+                element_type_location, "invalid memref element type"));
+        }
+
+        // Parse the layout and the memory space if present. The layout is either an affine map or a strided layout, and any other attribute is the memory space, which comes last.
+        let mut layout: Option<Arc<dyn Attribute>> = None;
+        let mut memory_space: Option<Arc<dyn Attribute>> = None;
+        if !self.consume_if(TokenKind::Greater) {
+            self.parse_token(TokenKind::Comma, "expected ',' or '>' in memref type")?;
+            loop {
+                let attribute = self.parse_attribute(None)?;
+                let is_layout = attribute.downcast_ref::<AffineMapAttribute>().is_some()
+                    || attribute.downcast_ref::<StridedLayoutAttribute>().is_some();
+                if !is_layout {
+                    if memory_space.is_some() {
+                        return Err(self.emit_error(
+                            "multiple memory spaces specified in memref type"));
+                    }
+                    memory_space = Some(attribute);
+                } else if dimensions.is_none() {
+                    return Err(self.emit_error(
+                        "cannot have affine map for unranked memref type"));
+                } else if memory_space.is_some() {
+                    return Err(self.emit_error(
+                        "expected memory space to be last in memref type"));
+                } else {
+                    layout = Some(attribute);
+                }
+                if !self.consume_if(TokenKind::Comma) {
+                    break;
+                }
+            }
+            self.parse_token(TokenKind::Greater, "expected '>' in memref type")?;
+        }
+
+        let Some(dimensions) = dimensions else {
+            return Ok(Arc::new(UnrankedMemRefType::new(element_type, memory_space)));
+        };
+        if dimensions.iter().any(|dimension| *dimension < 0 && *dimension != DYNAMIC) {
+            return Err(self.emit_error_at(location, "invalid memref size"));
+        }
+        if let Some(layout) = &layout {
+            if let Some(map) = layout.downcast_ref::<AffineMapAttribute>() {
+                if map.value().num_dims() != dimensions.len() {
+                    return Err(self.emit_error_at(location, format!(
+                        "memref layout mismatch between rank and affine map: {} != {}",
+                        dimensions.len(), map.value().num_dims())));
+                }
+            }
+            if let Some(strided) = layout.downcast_ref::<StridedLayoutAttribute>() {
+                if strided.strides().len() != dimensions.len() {
+                    return Err(self.emit_error_at(location,
+                        "expected the number of strides to match the rank"));
+                }
+            }
+        }
+        Ok(Arc::new(MemRefType::new(dimensions, element_type, layout, memory_space)))
+    }
+
+    /**
+    Parse a tensor type.
+
+    ```text
+    tensor-type ::= `tensor` `<` dimension-list type `>`
+    dimension-list ::= dimension-list-ranked | `*x`
+    ```
+    */
+    fn parse_tensor_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.consume_token_of(TokenKind::Keyword(Keyword::Tensor));
+
+        self.parse_token(TokenKind::Less, "expected '<' in tensor type")?;
+
+        let mut dimensions = None;
+        if self.consume_if(TokenKind::Star) {
+            // This is an unranked tensor type.
+            self.parse_x_in_dimension_list()?;
+        } else {
+            dimensions = Some(self.parse_dimension_list_ranked(true, true)?);
+        }
+
+        // Parse the element type.
+        let element_type_location = self.token().location();
+        let element_type = self.parse_type()?;
+
+        // Parse an optional encoding attribute.
+        let mut encoding = None;
+        if self.consume_if(TokenKind::Comma) {
+            encoding = Some(self.parse_attribute(None)?);
+        }
+        self.parse_token(TokenKind::Greater, "expected '>' in tensor type")?;
+        if !is_valid_tensor_element_type(&element_type) {
+            return Err(self.emit_error_at(
+                element_type_location, "invalid tensor element type"));
+        }
+
+        match dimensions {
+            Some(dimensions) =>
+                Ok(Arc::new(RankedTensorType::new(dimensions, element_type, encoding))),
+            None => {
+                if encoding.is_some() {
+                    return Err(self.emit_error("cannot apply encoding to unranked tensor"));
+                }
+                Ok(Arc::new(UnrankedTensorType::new(element_type)))
+            }
+        }
+    }
+
+    /**
+    Parse a tuple type.
+
+    ```text
+    tuple-type ::= `tuple` `<` (type (`,` type)*)? `>`
+    ```
+    */
+    fn parse_tuple_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.consume_token_of(TokenKind::Keyword(Keyword::Tuple));
+
+        // Parse the '<'.
+        self.parse_token(TokenKind::Less, "expected '<' in tuple type")?;
+
+        // Check for an empty tuple by directly parsing '>'.
+        if self.consume_if(TokenKind::Greater) {
+            return Ok(Arc::new(TupleType::new(Vec::new())));
+        }
+
+        // Parse the element types and the '>'.
+        let types = self.parse_type_list_no_parens()?;
+        self.parse_token(TokenKind::Greater, "expected '>' in tuple type")?;
+
+        Ok(Arc::new(TupleType::new(types)))
+    }
+
+    /**
+    Parse a vector type.
+
+    ```text
+    vector-type ::= `vector` `<` vector-dim-list vector-element-type `>`
+    vector-dim-list := (static-dim-list `x`)?
+    static-dim-list ::= static-dim (`x` static-dim)*
+    static-dim ::= (decimal-literal | `[` decimal-literal `]`)
+    ```
+    */
+    fn parse_vector_type(&mut self) -> ParseResult<Arc<dyn Type>> {
+        self.consume_token_of(TokenKind::Keyword(Keyword::Vector));
+
+        self.parse_token(TokenKind::Less, "expected '<' in vector type")?;
+
+        let location = self.token().location();
+        let (dimensions, scalable_dims) = self.parse_vector_dimension_list()?;
+        if dimensions.iter().any(|dimension| *dimension <= 0) {
+            return Err(self.emit_error_at(
+                location, "vector types must have positive constant sizes"));
+        }
+
+        // Parse the element type.
+        let element_type_location = self.token().location();
+        let element_type = self.parse_type()?;
+        self.parse_token(TokenKind::Greater, "expected '>' in vector type")?;
+
+        if !VectorType::is_valid_element_type(element_type.as_ref()) {
+            return Err(self.emit_error_at(
+                element_type_location, "vector elements must be int/index/float type"));
+        }
+        Ok(Arc::new(VectorType::new(dimensions, element_type, scalable_dims)))
+    }
+
+    /**
+    Parse a dimension list in a vector type. This populates the dimension list, and returns whether each dimension is scalable.
+
+    ```text
+    vector-dim-list := (static-dim-list `x`)?
+    static-dim-list ::= static-dim (`x` static-dim)*
+    static-dim ::= (decimal-literal | `[` decimal-literal `]`)
+    ```
+    */
+    fn parse_vector_dimension_list(&mut self) -> ParseResult<(Vec<i64>, Vec<bool>)> {
+        let mut dimensions = Vec::new();
+        let mut scalable_dims = Vec::new();
+        while self.token().is_any(&[TokenKind::Integer, TokenKind::LSquare]) {
+            let scalable = self.consume_if(TokenKind::LSquare);
+            dimensions.push(self.parse_integer_in_dimension_list()?);
+            if scalable && !self.consume_if(TokenKind::RSquare) {
+                return Err(self.emit_wrong_token_error("missing ']' closing scalable dimension"));
+            }
+            scalable_dims.push(scalable);
+            // Make sure we have an 'x' or something like 'xbf32'.
+            self.parse_x_in_dimension_list()?;
+        }
+        Ok((dimensions, scalable_dims))
+    }
+
+    /**
+    Parse a dimension list of a tensor or memref type. This populates the dimension list, using `DYNAMIC` for the `?` dimensions if `allow_dynamic` is set and errors out on `?` otherwise. Parsing the trailing `x` is configurable.
+
+    ```text
+    dimension-list ::= eps | dimension (`x` dimension)*
+    dimension-list-with-trailing-x ::= (dimension `x`)*
+    dimension ::= `?` | decimal-literal
+    ```
+
+    When `allow_dynamic` is not set, this is used to parse:
+
+    ```text
+    static-dimension-list ::= eps | decimal-literal (`x` decimal-literal)*
+    static-dimension-list-with-trailing-x ::= (dimension `x`)*
+    ```
+    */
+    pub fn parse_dimension_list_ranked(
+        &mut self,
+        allow_dynamic: bool,
+        with_trailing_x: bool
+    ) -> ParseResult<Vec<i64>> {
+        let mut dimensions = Vec::new();
+        let mut parse_dimension = |this: &mut Self| -> ParseResult {
+            let location = this.token().location();
+            if this.consume_if(TokenKind::Question) {
+                if !allow_dynamic {
+                    return Err(this.emit_error_at(location, "expected static shape"));
+                }
+                dimensions.push(DYNAMIC);
+            } else {
+                dimensions.push(this.parse_integer_in_dimension_list()?);
+            }
+            Ok(())
+        };
+
+        if with_trailing_x {
+            while self.token().is_any(&[TokenKind::Integer, TokenKind::Question]) {
+                parse_dimension(self)?;
+                self.parse_x_in_dimension_list()?;
+            }
+            return Ok(dimensions);
+        }
+
+        if self.token().is_any(&[TokenKind::Integer, TokenKind::Question]) {
+            parse_dimension(self)?;
+            while self.token().is(TokenKind::BareIdentifier)
+                && self.token_spelling().starts_with('x')
+            {
+                self.parse_x_in_dimension_list()?;
+                parse_dimension(self)?;
+            }
+        }
+        Ok(dimensions)
+    }
+
+    fn parse_integer_in_dimension_list(&mut self) -> ParseResult<i64> {
+        // Hexadecimal integer literals (starting with `0x`) are not allowed in aggregate type declarations. Therefore, `0xf32` should be processed as a sequence of separate elements `0`, `xf32`.
+        let spelling = self.token_spelling();
+        if spelling.len() > 1 && spelling.as_bytes()[1] == b'x' {
+            // We can get here only if the token is an integer literal. Hexadecimal integer literals can only start with `0x` (`1x` wouldn't lex as a literal, just `1` would, at which point we don't get into this branch).
+            assert!(spelling.starts_with('0'), "Invalid integer literal");
+            let offset = self.token().location().offset() + 1;
+            self.reset_token(offset);
+            return Ok(0);
+        }
+
+        // Make sure this integer value is in bound and valid.
+        let dimension = Token::uint64_integer_value(spelling)
+            .and_then(|dimension| i64::try_from(dimension).ok())
+            .ok_or_else(|| self.emit_error("invalid dimension"))?;
+        self.consume_token_of(TokenKind::Integer);
+        Ok(dimension)
+    }
+
+    /**
+    Parse an 'x' token in a dimension list, handling the case where the x is juxtaposed with an element type, as in `xf32`, leaving the `f32` as the next token.
+    */
+    pub fn parse_x_in_dimension_list(&mut self) -> ParseResult {
+        if self.token().is_not(TokenKind::BareIdentifier)
+            || !self.token_spelling().starts_with('x')
+        {
+            return Err(self.emit_wrong_token_error("expected 'x' in dimension list"));
+        }
+
+        // If we had a prefix of 'x', lex the next token immediately after the 'x'.
+        if self.token_spelling().len() != 1 {
+            let offset = self.token().location().offset() + 1;
+            self.reset_token(offset);
+        } else {
+            // Consume the 'x'.
+            self.consume_token_of(TokenKind::BareIdentifier);
+        }
+        Ok(())
+    }
+}
@@ -5,8 +5,47 @@ files.
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Bytecode/Encoding.h>
 */
 
+// ----------------------------------------------------------------------
+// General constants
+// ----------------------------------------------------------------------
+
+/// The magic number identifying MLIR bytecode files, `ML\xefR`.
+pub const MAGIC: [u8; 4] = [b'M', b'L', 0xef, b'R'];
+
+/// The minimum supported version of the bytecode. Version 0 is the format written by MLIR 16.
+pub const MIN_SUPPORTED_VERSION: u64 = 0;
+
+/// Dialects may encode their version in the dialect section starting from this version.
+pub const DIALECT_VERSIONING: u64 = 1;
+
+/// The regions of operations that are isolated from above are encoded in their own IR section starting from this version, which allows them to be loaded lazily.
+pub const LAZY_LOADING: u64 = 2;
+
+/// The use-list orders of values are encoded starting from this version, so that they are preserved by a round trip through bytecode.
+pub const USE_LIST_ORDERING: u64 = 3;
+
+/// The unknown locations of block inputs are elided starting from this version, and the dialect section encodes the number of operation names.
+pub const ELIDE_UNKNOWN_BLOCK_ARG_LOCATION: u64 = 4;
+
+/// The properties of operations are encoded in the properties section starting from this version, and operation names encode whether they were registered.
+pub const NATIVE_PROPERTIES_ENCODING: u64 = 5;
+
+/// The operand segment sizes of operations are encoded natively within their properties starting from this version, instead of as an attribute.
+pub const NATIVE_PROPERTIES_ODS_SEGMENT_SIZE: u64 = 6;
+
+/// The current bytecode version.
+pub const VERSION: u64 = 6;
+
+/// An arbitrary value used to fill alignment padding.
+pub const ALIGNMENT_BYTE: u8 = 0xCB;
+
+// ----------------------------------------------------------------------
+// Sections
+// ----------------------------------------------------------------------
+
 #[repr(u8)]
-enum ID {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ID {
     /// This section contains strings referenced within the bytecode.
     String,
 
@@ -32,6 +71,81 @@ enum ID {
     /// section.
     ResourceOffset,
 
+    /// This section contains the version of a dialect. It is only encoded nested within the dialect section.
+    DialectVersions,
+
+    /// This section contains the properties of the operations.
+    Properties,
+
     /// The total number of section types.
     NumSections,
 }
+
+impl ID {
+    /// All of the section IDs, indexed by their value.
+    pub const ALL: [ID; ID::NumSections as usize] = [
+        ID::String,
+        ID::Dialect,
+        ID::AttrType,
+        ID::AttrTypeOffset,
+        ID::IR,
+        ID::Resource,
+        ID::ResourceOffset,
+        ID::DialectVersions,
+        ID::Properties
+    ];
+
+    /// Return the section with the given code, or None if the code is out of range.
+    pub fn from_code(code: u8) -> Option<Self> {
+        Self::ALL.get(code as usize).copied()
+    }
+
+    /// Returns true if the section is not required to be present in a bytecode file of the given version.
+    pub fn is_optional(self, version: u64) -> bool {
+        match self {
+            ID::Resource | ID::ResourceOffset | ID::DialectVersions => true,
+            ID::Properties => version < NATIVE_PROPERTIES_ENCODING,
+            _ => false
+        }
+    }
+}
+
+impl core::fmt::Display for ID {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let name = match self {
+            ID::String => "String",
+            ID::Dialect => "Dialect",
+            ID::AttrType => "AttrType",
+            ID::AttrTypeOffset => "AttrTypeOffset",
+            ID::IR => "IR",
+            ID::Resource => "Resource",
+            ID::ResourceOffset => "ResourceOffset",
+            ID::DialectVersions => "DialectVersions",
+            ID::Properties => "Properties",
+            ID::NumSections => "NumSections"
+        };
+        write!(f, "{} ({})", name, *self as u8)
+    }
+}
+
+// ----------------------------------------------------------------------
+// IR Section
+// ----------------------------------------------------------------------
+
+/// This enum represents a mask of all of the potential components of an operation. This mask is used when encoding an operation to indicate which components are present in the bytecode.
+pub struct OpEncodingMask;
+
+impl OpEncodingMask {
+    pub const HAS_ATTRS: u8           = 0b00000001;
+    pub const HAS_RESULTS: u8         = 0b00000010;
+    pub const HAS_OPERANDS: u8        = 0b00000100;
+    pub const HAS_SUCCESSORS: u8      = 0b00001000;
+    pub const HAS_INLINE_REGIONS: u8  = 0b00010000;
+    pub const HAS_USE_LIST_ORDERS: u8 = 0b00100000;
+    pub const HAS_PROPERTIES: u8      = 0b01000000;
+}
+
+/// Get the unique ID of a value use. We encode the unique ID combining an owner number and the input number such as if `owner_id(op1) < owner_id(op2)`, then `use_id(op1) < use_id(op2)`. If uses have the same owner, then `index(op1) < index(op2)` implies `use_id(op1) < use_id(op2)`.
+pub fn use_id(owner_id: usize, index: usize) -> u64 {
+    ((owner_id as u64) << 32) | index as u64
+}
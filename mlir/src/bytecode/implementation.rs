@@ -5,29 +5,341 @@ to hook into bytecode serialisation.
 - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Bytecode/BytecodeImplementation.h>
 */
 
+use std::{any::Any, error::Error, sync::Arc};
+
+use llvm::adt::ap_int::APInt;
 use crate::{
-    mlir::{
-        ir::{
-            attribute::Attribute,
-            diagnostics,
-            dialect::{
-                self,
-                interface
-            },
-            operation::implementation
-        }
+    ir::{
+        arena::OpId,
+        attribute::{Attribute, NamedAttribute},
+        location::Location,
+        mlir_context::MLIRContext,
+        operation::{
+            Operation,
+            implementation::AsmDialectResourceHandle,
+            support::OperationName
+        },
+        r#type::Type
     },
-    llvm::adt::twine::Twine
+    support::logical_result::LogicalResult
 };
 
+// ----------------------------------------------------------------------
+// DialectVersion
+// ----------------------------------------------------------------------
+
+/**
+This class is used to represent the version of a dialect, as read from the bytecode by `BytecodeDialectInterface::read_version`. Dialects downcast it back to their own version type.
+*/
+pub trait DialectVersion: Any {
+}
+
+// ----------------------------------------------------------------------
+// DialectBytecodeReader
+// ----------------------------------------------------------------------
+
 /**
 This class defines a virtual interface for reading a bytecode stream, providing hooks into the bytecode reader. As such, this class should only be derived and defined by the main bytecode reader, users (i.e. dialects) should generally only interact with this class via the BytecodeDialectInterface below.
 */
-pub struct DialectBytecodeReader {
+pub trait DialectBytecodeReader {
+    /// Return an error describing a failure to read the current entry.
+    fn emit_error(&self, message: &str) -> Box<dyn Error>;
+
+    /// Return the bytecode version being read.
+    fn bytecode_version(&self) -> u64;
+
+    /// Retrieve the dialect version by name if available, or None if the dialect is not referenced in the bytecode or did not encode a version.
+    fn dialect_version(&self, dialect: &str) -> Option<&dyn DialectVersion>;
+
+    /* IR */
+
+    /// Read a reference to the given attribute.
+    fn read_attribute(&mut self) -> Result<Arc<dyn Attribute>, Box<dyn Error>>;
+
+    /// Read an optional reference to the given attribute. Returns None if the attribute was not present.
+    fn read_optional_attribute(&mut self)
+        -> Result<Option<Arc<dyn Attribute>>, Box<dyn Error>>;
+
+    /// Read a reference to the given location. Locations share the table of attributes.
+    fn read_location(&mut self) -> Result<Location, Box<dyn Error>>;
+
+    /// Read a reference to the given type.
+    fn read_type(&mut self) -> Result<Arc<dyn Type>, Box<dyn Error>>;
+
+    /// Read a handle to a dialect resource.
+    fn read_resource_handle(&mut self)
+        -> Result<AsmDialectResourceHandle, Box<dyn Error>>;
+
+    /* Primitives */
+
+    /// Read a variable width integer.
+    fn read_var_int(&mut self) -> Result<u64, Box<dyn Error>>;
+
+    /// Read a signed variable width integer.
+    fn read_signed_var_int(&mut self) -> Result<i64, Box<dyn Error>>;
+
+    /// Read a bool, encoded as a single byte.
+    fn read_bool(&mut self) -> Result<bool, Box<dyn Error>>;
+
+    /// Read an APInt that is known to have been encoded with the given width.
+    fn read_ap_int_with_known_width(&mut self, bit_width: u32)
+        -> Result<APInt, Box<dyn Error>>;
+
+    /// Read a string from the bytecode.
+    fn read_string(&mut self) -> Result<String, Box<dyn Error>>;
+
+    /// Read a blob from the bytecode.
+    fn read_blob(&mut self) -> Result<Vec<u8>, Box<dyn Error>>;
+}
+
+impl<'a> dyn DialectBytecodeReader + 'a {
+    /// Read out a list of elements, invoking the provided callback for each element.
+    pub fn read_list<T>(
+        &mut self,
+        mut read: impl FnMut(&mut Self) -> Result<T, Box<dyn Error>>
+    ) -> Result<Vec<T>, Box<dyn Error>> {
+        let size = self.read_var_int()?;
+        (0..size).map(|_| read(self)).collect()
+    }
+
+    /// Read a reference to an attribute of the given kind.
+    pub fn read_attribute_of<T: Attribute + Clone>(&mut self)
+        -> Result<T, Box<dyn Error>>
+    {
+        let attribute = self.read_attribute()?;
+        attribute.downcast_ref::<T>().cloned().ok_or_else(|| {
+            let name = core::any::type_name::<T>().rsplit("::").next().unwrap();
+            self.emit_error(&format!(
+                "expected attribute of type: {}, but got: {}",
+                name, attribute.as_ref()))
+        })
+    }
+
+    pub fn read_attributes(&mut self)
+        -> Result<Vec<Arc<dyn Attribute>>, Box<dyn Error>>
+    {
+        self.read_list(|this| this.read_attribute())
+    }
+
+    pub fn read_locations(&mut self) -> Result<Vec<Location>, Box<dyn Error>> {
+        self.read_list(|this| this.read_location())
+    }
+
+    pub fn read_types(&mut self) -> Result<Vec<Arc<dyn Type>>, Box<dyn Error>> {
+        self.read_list(|this| this.read_type())
+    }
+
+    /// Parse a variable length encoded integer whose low bit is used to encode an unrelated flag, i.e: `(integerValue << 1) | (flag ? 1 : 0)`.
+    pub fn read_var_int_with_flag(&mut self)
+        -> Result<(u64, bool), Box<dyn Error>>
+    {
+        let value = self.read_var_int()?;
+        Ok((value >> 1, value & 1 == 1))
+    }
+
+    pub fn read_signed_var_ints(&mut self) -> Result<Vec<i64>, Box<dyn Error>> {
+        self.read_list(|this| this.read_signed_var_int())
+    }
 }
 
+// ----------------------------------------------------------------------
+// DialectBytecodeWriter
+// ----------------------------------------------------------------------
+
 /**
 This class defines a virtual interface for writing to a bytecode stream, providing hooks into the bytecode writer. As such, this class should only be derived and defined by the main bytecode writer, users (i.e. dialects) should generally only interact with this class via the BytecodeDialectInterface below.
 */
-pub struct DialectBytecodeWriter {
+pub trait DialectBytecodeWriter {
+    /// Return the bytecode version being emitted for.
+    fn bytecode_version(&self) -> u64;
+
+    /* IR */
+
+    /// Write a reference to the given attribute.
+    fn write_attribute(&mut self, attribute: &Arc<dyn Attribute>);
+
+    /// Write an optional reference to the given attribute, which is flagged with whether it is present.
+    fn write_optional_attribute(&mut self, attribute: Option<&Arc<dyn Attribute>>);
+
+    /// Write a reference to the given location. Locations share the table of attributes.
+    fn write_location(&mut self, location: &Location);
+
+    /// Write a reference to the given type.
+    fn write_type(&mut self, r#type: &Arc<dyn Type>);
+
+    /// Write the given handle to a dialect resource.
+    fn write_resource_handle(&mut self, resource: &AsmDialectResourceHandle);
+
+    /* Primitives */
+
+    /// Write a variable width integer to the output stream. This should be the preferred method for emitting integers whenever possible.
+    fn write_var_int(&mut self, value: u64);
+
+    /// Write a signed variable width integer to the output stream. This should be the preferred method for emitting signed integers whenever possible.
+    fn write_signed_var_int(&mut self, value: i64) {
+        self.write_var_int(((value as u64) << 1) ^ ((value >> 63) as u64));
+    }
+
+    /// Write a bool as a single byte.
+    fn write_owned_bool(&mut self, value: bool);
+
+    /// Write an APInt to the bytecode stream whose bitwidth will be known externally at read time. This method is useful for encoding APInt values when the width is known via external means, such as via a type. This method should generally only be invoked if you need an APInt, otherwise use the varint methods above. APInt values are generally encoded using zigzag encoding, to enable more efficient encodings for negative values.
+    fn write_ap_int_with_known_width(&mut self, value: &APInt);
+
+    /// Write a string to the bytecode, which is owned by the caller and is guaranteed to not die before the end of the bytecode process. This should only be called if such a guarantee can be made, such as when the string is owned by an attribute or type.
+    fn write_owned_string(&mut self, string: &str);
+
+    /// Write a blob to the bytecode, which is owned by the caller and is guaranteed to not die before the end of the bytecode process. The blob is written as-is, with no additional compression or compaction.
+    fn write_owned_blob(&mut self, blob: &[u8]);
+}
+
+impl<'a> dyn DialectBytecodeWriter + 'a {
+    /// Write out a list of elements, invoking the provided callback for each element.
+    pub fn write_list<T>(&mut self, items: &[T], mut write: impl FnMut(&mut Self, &T)) {
+        self.write_var_int(items.len() as u64);
+        for item in items {
+            write(self, item);
+        }
+    }
+
+    pub fn write_attributes(&mut self, attributes: &[Arc<dyn Attribute>]) {
+        self.write_list(attributes, |this, attribute| this.write_attribute(attribute));
+    }
+
+    pub fn write_locations(&mut self, locations: &[Location]) {
+        self.write_list(locations, |this, location| this.write_location(location));
+    }
+
+    pub fn write_types(&mut self, types: &[Arc<dyn Type>]) {
+        self.write_list(types, |this, r#type| this.write_type(r#type));
+    }
+
+    /// Write a variable length encoded integer whose low bit is used to encode an unrelated flag, i.e: `(integerValue << 1) | (flag ? 1 : 0)`.
+    pub fn write_var_int_with_flag(&mut self, value: u64, flag: bool) {
+        self.write_var_int((value << 1) | flag as u64);
+    }
+
+    pub fn write_signed_var_ints(&mut self, values: &[i64]) {
+        self.write_list(values, |this, value| this.write_signed_var_int(*value));
+    }
+}
+
+// ----------------------------------------------------------------------
+// BytecodeDialectInterface
+// ----------------------------------------------------------------------
+
+/**
+The interface a dialect implements to encode its attributes, locations and types in the bytecode format. Anything a dialect does not encode itself is emitted using its textual assembly format.
+*/
+pub trait BytecodeDialectInterface {
+    /// Return the namespace of the dialect this interface is registered to.
+    fn dialect(&self) -> &str;
+
+    /* Reading */
+
+    /// Read an attribute belonging to this dialect from the given reader. This method should return an error in the case of failure.
+    fn read_attribute(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Arc<dyn Attribute>, Box<dyn Error>>
+    {
+        Err(reader.emit_error(&format!(
+            "dialect '{}' does not implement the bytecode interface",
+            self.dialect())))
+    }
+
+    /// Read a location belonging to this dialect from the given reader.
+    fn read_location(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Location, Box<dyn Error>>
+    {
+        Err(reader.emit_error(&format!(
+            "dialect '{}' does not support reading locations from bytecode",
+            self.dialect())))
+    }
+
+    /// Read a type belonging to this dialect from the given reader. This method should return an error in the case of failure.
+    fn read_type(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Arc<dyn Type>, Box<dyn Error>>
+    {
+        Err(reader.emit_error(&format!(
+            "dialect '{}' does not implement the bytecode interface",
+            self.dialect())))
+    }
+
+    /**
+    Read a version of this dialect from the given reader. The version is read while loading the dialect section of bytecode of version `DIALECT_VERSIONING` or later, and only if `write_version` encoded one.
+    */
+    fn read_version(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Box<dyn DialectVersion>, Box<dyn Error>>
+    {
+        Err(reader.emit_error("dialect does not support versioning"))
+    }
+
+    /**
+    Hook invoked after parsing completed, if a version directive was present and included an entry for the current dialect. This hook offers the opportunity to the dialect to visit the IR nested within `top_level_op` and upgrade it to the current version of the dialect. `version` is the version read by `read_version`.
+    */
+    fn upgrade_from_version(
+        &self,
+        top_level_op: OpId,
+        context: &mut MLIRContext,
+        version: &dyn DialectVersion
+    ) -> LogicalResult {
+        Ok(())
+    }
+
+    /**
+    Read the properties of an operation of this dialect named `name`, as encoded by `write_properties` in bytecode of version `NATIVE_PROPERTIES_ENCODING` or later. The properties are returned as the inherent attributes of the operation, which are added to its attribute dictionary.
+    */
+    fn read_properties(
+        &self,
+        name: &OperationName,
+        reader: &mut dyn DialectBytecodeReader
+    ) -> Result<Vec<NamedAttribute>, Box<dyn Error>> {
+        Err(reader.emit_error(&format!(
+            "has properties but missing BytecodeOpInterface for {}", name.as_str())))
+    }
+
+    /* Writing */
+
+    /// Write the current version of this dialect to the given writer. Nothing is encoded if nothing is written, in which case no version is read back either.
+    fn write_version(&self, writer: &mut dyn DialectBytecodeWriter) {
+    }
+
+    /// Return the names of the inherent attributes that the registered operation `name` of this dialect stores as its properties, or None if the operation has no properties. From version `NATIVE_PROPERTIES_ENCODING`, these attributes are encoded by `write_properties` rather than in the attribute dictionary of the operation.
+    fn property_names(&self, name: &OperationName) -> Option<&'static [&'static str]> {
+        None
+    }
+
+    /// Write the properties of `operation`, an operation of this dialect for which `property_names` returned the names of its properties, to the given writer.
+    fn write_properties(
+        &self,
+        operation: &Operation,
+        writer: &mut dyn DialectBytecodeWriter
+    ) {
+    }
+
+    /// Write the given attribute, which belongs to this dialect, to the given writer. This method may return failure to indicate that the given attribute could not be encoded, in which case the textual format will be used to encode this attribute instead.
+    fn write_attribute(
+        &self,
+        attribute: &dyn Attribute,
+        writer: &mut dyn DialectBytecodeWriter
+    ) -> LogicalResult {
+        Err(format!("dialect '{}' has no custom bytecode encoding", self.dialect()).into())
+    }
+
+    /// Write the given location to the given writer, or return failure to fall back to the textual format.
+    fn write_location(
+        &self,
+        location: &Location,
+        writer: &mut dyn DialectBytecodeWriter
+    ) -> LogicalResult {
+        Err(format!("dialect '{}' has no custom bytecode encoding", self.dialect()).into())
+    }
+
+    /// Write the given type, which belongs to this dialect, to the given writer. This method may return failure to indicate that the given type could not be encoded, in which case the textual format will be used to encode this type instead.
+    fn write_type(
+        &self,
+        r#type: &dyn Type,
+        writer: &mut dyn DialectBytecodeWriter
+    ) -> LogicalResult {
+        Err(format!("dialect '{}' has no custom bytecode encoding", self.dialect()).into())
+    }
 }
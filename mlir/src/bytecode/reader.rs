@@ -5,62 +5,1870 @@ This header defines interfaces to read MLIR bytecode files/streams.
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Bytecode/Reader/BytecodeReader.cpp>
 */
 
+use std::{
+    collections::{HashMap, HashSet},
+    error::Error,
+    sync::Arc
+};
+
+use llvm::adt::ap_int::APInt;
 use crate::{
-    mlir::{
-        asm_parser::asm_parser,
-        bytecode::{
-            encoding,
-            // implementation
-        },
-        ir::{
-            asm_state::ParserConfig,
-            block::Block,
-            builtins::{
-                dialect,
-                operations
-            },
-            operation::implementation,
-            verifier
+    asm_parser::asm_parser::{parse_attribute, parse_location, parse_type},
+    bytecode::{
+        encoding::{
+            ALIGNMENT_BYTE, DIALECT_VERSIONING, ELIDE_UNKNOWN_BLOCK_ARG_LOCATION, ID,
+            LAZY_LOADING, MAGIC, MIN_SUPPORTED_VERSION, NATIVE_PROPERTIES_ENCODING,
+            OpEncodingMask, USE_LIST_ORDERING, VERSION, use_id
         },
-        support::llvm,
+        implementation::{
+            BytecodeDialectInterface, DialectBytecodeReader, DialectVersion
+        }
     },
-    llvm::{
-        adt::{
-            map_vector,
-            scope_exit,
-            small_string,
-            string_extras
+    ir::{
+        arena::{BlockId, IRArena, OpId, RegionId},
+        asm_state::{
+            AsmParsedResourceEntry, AsmResourceBlob, HeapAsmResourceBlob, ParserConfig,
+            SharedResourceBuffer
+        },
+        attribute::{Attribute, NamedAttribute},
+        builtins::{
+            attributes::{DictionaryAttribute, StringAttribute},
+            dialect::bytecode::BuiltinBytecodeInterface,
+            operations::ModuleOp,
+            types::NoneType
+        },
+        diagnostics::{Diagnostic, DiagnosticSeverity, emit_warning},
+        location::Location,
+        operation::{
+            Operation,
+            implementation::AsmDialectResourceHandle,
+            support::OperationName
         },
-        support::{
-            memory_buffer_ref::MemoryBufferRef,
-            save_and_restore,
-            source_manager::SourceManager
+        r#type::Type,
+        value::{Input, Output, Value}
+    },
+    support::logical_result::LogicalResult
+};
+
+type ReadResult<T = ()> = Result<T, Box<dyn Error>>;
+
+// ----------------------------------------------------------------------
+// EncodingReader
+// ----------------------------------------------------------------------
+
+/// This class is used to read the primitives of the bytecode encoding from a range of the bytecode buffer.
+#[derive(Clone, Copy)]
+pub(super) struct EncodingReader<'a> {
+    /// The data remaining to be read.
+    data: &'a [u8],
+    /// The offset of `data` from the start of the bytecode buffer. Alignment is computed relative to the start of the buffer.
+    offset: usize
+}
+
+impl<'a> EncodingReader<'a> {
+    pub(super) fn new(data: &'a [u8], offset: usize) -> Self {
+        Self { data, offset }
+    }
+
+    /// Returns true if the entire section has been read.
+    pub(super) fn empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Returns the remaining size of the bytecode.
+    fn size(&self) -> usize {
+        self.data.len()
+    }
+
+    /// Emit an error using the given arguments.
+    fn emit_error(&self, message: impl Into<String>) -> Box<dyn Error> {
+        message.into().into()
+    }
+
+    /// Align the current reader position to the specified alignment.
+    fn align_to(&mut self, alignment: u64) -> ReadResult {
+        if !alignment.is_power_of_two() {
+            return Err(self.emit_error("expected alignment to be a power-of-two"));
         }
+
+        // Shift the reader position to the next alignment boundary.
+        while self.offset as u64 & (alignment - 1) != 0 {
+            let padding = self.parse_byte()?;
+            if padding != ALIGNMENT_BYTE {
+                return Err(self.emit_error(format!(
+                    "expected {} as padding bytes, but got: {}",
+                    ALIGNMENT_BYTE, padding)));
+            }
+        }
+        Ok(())
     }
-};
+
+    /// Parse a single byte from the stream.
+    pub(super) fn parse_byte(&mut self) -> ReadResult<u8> {
+        let Some((byte, rest)) = self.data.split_first() else {
+            return Err(self.emit_error(
+                "attempting to parse a byte at the end of the bytecode"));
+        };
+        self.data = rest;
+        self.offset += 1;
+        Ok(*byte)
+    }
+
+    /// Parse a range of bytes of `length` into the given result.
+    fn parse_bytes(&mut self, length: usize) -> ReadResult<&'a [u8]> {
+        Ok(self.parse_reader(length)?.data)
+    }
+
+    /// Parse a range of bytes of `length`, returning a reader over them.
+    fn parse_reader(&mut self, length: usize) -> ReadResult<EncodingReader<'a>> {
+        if length > self.size() {
+            return Err(self.emit_error(format!(
+                "attempting to parse {} bytes when only {} remain",
+                length, self.size())));
+        }
+        let (data, rest) = self.data.split_at(length);
+        let reader = Self::new(data, self.offset);
+        self.data = rest;
+        self.offset += length;
+        Ok(reader)
+    }
+
+    /// Parse an aligned blob of data, where the alignment was encoded alongside the data.
+    pub(super) fn parse_blob_and_alignment(&mut self) -> ReadResult<(&'a [u8], u64)> {
+        let alignment = self.parse_var_int()?;
+        let data_size = self.parse_var_int()?;
+        self.align_to(alignment)?;
+        Ok((self.parse_bytes(data_size as usize)?, alignment))
+    }
+
+    /**
+    Parse a variable length encoded integer from the byte stream. The first encoded byte contains a prefix in the low bits indicating the encoded length of the value. This length prefix is a bit sequence of '0's followed by a '1'. The number of '0' bits indicate the number of _additional_ bytes (not including the prefix byte). All remaining bits in the first byte, along with all of the bits in additional bytes, provide the value of the integer encoded in little-endian order.
+    */
+    pub(super) fn parse_var_int(&mut self) -> ReadResult<u64> {
+        // Parse the first byte of the encoding, which contains the length prefix.
+        let result = self.parse_byte()?;
+
+        // Handle the overwhelmingly common case where the value is stored in a single byte. In this case, the first bit is the `1` marker bit.
+        if result & 1 != 0 {
+            return Ok(result as u64 >> 1);
+        }
+
+        // Handle the overwhelming uncommon case where the value required all 8 bytes (i.e. a really really big number). In this case, the marker byte is all zeros: `00000000`.
+        if result == 0 {
+            let bytes = self.parse_bytes(8)?;
+            return Ok(u64::from_le_bytes(bytes.try_into().unwrap()));
+        }
+        self.parse_multi_byte_var_int(result)
+    }
+
+    /// Parse a signed variable length encoded integer from the byte stream. A signed varint is encoded as a normal varint with zigzag encoding applied, i.e. the low bit of the value is used to indicate the sign.
+    pub(super) fn parse_signed_var_int(&mut self) -> ReadResult<i64> {
+        let result = self.parse_var_int()?;
+        // Essentially (but using unsigned): (x >> 1) ^ -(x & 1)
+        Ok(((result >> 1) ^ (!(result & 1)).wrapping_add(1)) as i64)
+    }
+
+    /// Parse a variable length encoded integer whose low bit is used to encode an unrelated flag, i.e: `(integerValue << 1) | (flag ? 1 : 0)`.
+    pub(super) fn parse_var_int_with_flag(&mut self) -> ReadResult<(u64, bool)> {
+        let result = self.parse_var_int()?;
+        Ok((result >> 1, result & 1 != 0))
+    }
+
+    /// Skip the first `length` bytes within the reader.
+    fn skip_bytes(&mut self, length: usize) -> ReadResult {
+        self.parse_reader(length).map(|_| ())
+    }
+
+    /// Parse a null-terminated string into `result` (without including the NUL terminator).
+    pub(super) fn parse_nul_terminated_string(&mut self) -> ReadResult<&'a str> {
+        let Some(nul) = self.data.iter().position(|byte| *byte == 0) else {
+            return Err(self.emit_error(
+                "malformed null-terminated string, no null character found"));
+        };
+        let string = self.parse_bytes(nul)?;
+        self.skip_bytes(1)?;
+        core::str::from_utf8(string).map_err(|_| self.emit_error(
+            "malformed null-terminated string, invalid UTF-8"))
+    }
+
+    /// Parse a section header, placing the kind of section in `section_id` and the contents of the section in `section_data`.
+    pub(super) fn parse_section(&mut self) -> ReadResult<(ID, EncodingReader<'a>)> {
+        let section_id_and_has_alignment = self.parse_byte()?;
+        let length = self.parse_var_int()?;
+
+        // Extract the section ID and whether the section is aligned. The high bit of the ID is the alignment flag.
+        let code = section_id_and_has_alignment & 0b01111111;
+        let has_alignment = section_id_and_has_alignment & 0b10000000 != 0;
+
+        // Check that the section is actually valid before trying to process its data.
+        let Some(section_id) = ID::from_code(code) else {
+            return Err(self.emit_error(format!("invalid section ID: {}", code)));
+        };
+
+        // Process the section alignment if present.
+        if has_alignment {
+            let alignment = self.parse_var_int()?;
+            self.align_to(alignment)?;
+        }
+
+        // Parse the actual section data.
+        Ok((section_id, self.parse_reader(length as usize)?))
+    }
+
+    /// Parse a variable length encoded integer from the byte stream. This method is a fallback when the number of bytes used to encode the value is greater than 1, but less than the max (9). The provided `result` value can be assumed to already contain the first byte of the value.
+    fn parse_multi_byte_var_int(&mut self, first: u8) -> ReadResult<u64> {
+        // Count the number of trailing zeros in the marker byte, this indicates the number of trailing bytes that are part of the value.
+        let num_bytes = first.trailing_zeros() as usize;
+        assert!(num_bytes > 0 && num_bytes <= 7,
+            "unexpected number of trailing zeros in varint encoding");
+
+        // Parse in the remaining bytes of the value.
+        let mut bytes = [0u8; 8];
+        bytes[0] = first;
+        bytes[1..=num_bytes].copy_from_slice(self.parse_bytes(num_bytes)?);
+
+        // Shift out the low-order bits that were used to mark how the value was encoded.
+        Ok(u64::from_le_bytes(bytes) >> (num_bytes + 1))
+    }
+}
+
+/// Resolve an index into the given entry list. `entry_str` is used to provide context in error messages.
+fn resolve_entry<'e, T>(
+    reader: &EncodingReader,
+    entries: &'e [T],
+    index: u64,
+    entry_str: &str
+) -> ReadResult<&'e T> {
+    usize::try_from(index).ok()
+        .and_then(|index| entries.get(index))
+        .ok_or_else(|| reader.emit_error(
+            format!("invalid {} index: {}", entry_str, index)))
+}
+
+/// Parse and resolve an index into the given entry list.
+fn parse_entry<'e, T>(
+    reader: &mut EncodingReader,
+    entries: &'e [T],
+    entry_str: &str
+) -> ReadResult<&'e T> {
+    let index = reader.parse_var_int()?;
+    resolve_entry(reader, entries, index, entry_str)
+}
+
+// ----------------------------------------------------------------------
+// StringSectionReader
+// ----------------------------------------------------------------------
+
+/// This class is used to read references to the string section from the bytecode.
+#[derive(Default)]
+pub(super) struct StringSectionReader<'a> {
+    /// The table of strings referenced within the bytecode file.
+    strings: Vec<&'a str>
+}
+
+impl<'a> StringSectionReader<'a> {
+    /// Initialize the string section reader with the given section data.
+    pub(super) fn initialize(&mut self, section: EncodingReader<'a>) -> ReadResult {
+        let section_data = section.data;
+        let mut string_reader = section;
+
+        // Parse the number of strings in the section.
+        let num_strings = string_reader.parse_var_int()?;
+        let mut strings = Vec::with_capacity(num_strings.min(section_data.len() as u64) as usize);
+
+        // Parse each of the strings. The sizes of the strings are encoded in reverse order, so that's the order we populate the table.
+        let mut string_data_end_offset = section_data.len();
+        for _ in 0..num_strings {
+            let string_size = string_reader.parse_var_int()? as usize;
+            if string_data_end_offset < string_size || string_size == 0 {
+                return Err(string_reader.emit_error(
+                    "string size exceeds the available data size"));
+            }
+
+            // Extract the string from the data, dropping the null character.
+            let string_offset = string_data_end_offset - string_size;
+            let string = &section_data[string_offset..string_data_end_offset - 1];
+            strings.push(core::str::from_utf8(string).map_err(|_|
+                string_reader.emit_error("malformed string, invalid UTF-8"))?);
+            string_data_end_offset = string_offset;
+        }
+        strings.reverse();
+        self.strings = strings;
+
+        // Check that the only remaining data was for the strings, i.e. the reader should be at the same offset as the first string.
+        if section_data.len() - string_reader.size() != string_data_end_offset {
+            return Err(string_reader.emit_error(
+                "unexpected trailing data between the offsets for strings and their data"));
+        }
+        Ok(())
+    }
+
+    /// Resolve the string at `index` of the string section.
+    fn parse_string_at_index(&self, reader: &EncodingReader, index: u64)
+        -> ReadResult<&'a str>
+    {
+        resolve_entry(reader, &self.strings, index, "string").copied()
+    }
+
+    /// Parse a shared string from the string section. The shared string is encoded using an index to a corresponding string in the string section.
+    pub(super) fn parse_string(&self, reader: &mut EncodingReader) -> ReadResult<&'a str> {
+        parse_entry(reader, &self.strings, "string").copied()
+    }
+
+    /// Parse a shared string from the string section. The shared string is encoded using an index to a corresponding string in the string section. This variant parses a flag compressed with the index.
+    pub(super) fn parse_string_with_flag(&self, reader: &mut EncodingReader)
+        -> ReadResult<(&'a str, bool)>
+    {
+        let (index, flag) = reader.parse_var_int_with_flag()?;
+        Ok((self.parse_string_at_index(reader, index)?, flag))
+    }
+}
+
+/// Parse the entries of a resource group, returning the key, kind, and data of each entry.
+pub(super) fn parse_resource_group<'a>(
+    string_reader: &StringSectionReader<'a>,
+    offset_reader: &mut EncodingReader<'a>,
+    resource_reader: &mut EncodingReader<'a>
+) -> ReadResult<Vec<(&'a str, u8, EncodingReader<'a>)>> {
+    let num_resources = offset_reader.parse_var_int()?;
+    (0..num_resources).map(|_| {
+        let key = string_reader.parse_string(offset_reader)?;
+        let resource_offset = offset_reader.parse_var_int()?;
+        let kind = offset_reader.parse_byte()?;
+        let data = resource_reader.parse_reader(resource_offset as usize)?;
+        Ok((key, kind, data))
+    }).collect()
+}
+
+// ----------------------------------------------------------------------
+// PropertiesSectionReader
+// ----------------------------------------------------------------------
+
+/// This class is used to read the properties section of the bytecode, whose entries are referenced by index from the operations.
+#[derive(Default)]
+pub(super) struct PropertiesSectionReader<'a> {
+    /// The encoded properties of each entry of the section.
+    pub(super) properties: Vec<EncodingReader<'a>>
+}
+
+impl<'a> PropertiesSectionReader<'a> {
+    /// Initialize the properties section reader with the given section data.
+    pub(super) fn initialize(&mut self, mut section: EncodingReader<'a>) -> ReadResult {
+        // Parse the number of properties in the section, followed by each of them prefixed with its size.
+        let count = section.parse_var_int()?;
+        for _ in 0..count {
+            let data_size = section.parse_var_int()?;
+            self.properties.push(section.parse_reader(data_size as usize)?);
+        }
+        if !section.empty() {
+            return Err(section.emit_error(
+                "Broken properties section: didn't exhaust the offsets table"));
+        }
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// BytecodeDialect
+// ----------------------------------------------------------------------
+
+/// This struct represents a dialect entry within the bytecode.
+struct BytecodeDialect<'a> {
+    /// The name of the dialect.
+    name: &'a str,
+    /// The bytecode interface of the dialect, or None if the dialect does not implement the bytecode interface.
+    interface: Option<Arc<dyn BytecodeDialectInterface>>,
+    /// The encoded version of the dialect, if the bytecode contained one.
+    version_buffer: Option<EncodingReader<'a>>,
+    /// The version of the dialect read from `version_buffer`.
+    loaded_version: Option<Box<dyn DialectVersion>>
+}
+
+/// This struct represents an operation name entry within the bytecode.
+#[derive(Clone)]
+struct BytecodeOperationName {
+    /// The index of the dialect that owns this operation name.
+    dialect: usize,
+    /// The name of the operation.
+    name: OperationName,
+    /// Whether this operation was registered when the bytecode was produced. This is None for bytecode older than `NATIVE_PROPERTIES_ENCODING`, which doesn't encode it.
+    was_registered: Option<bool>
+}
+
+// ----------------------------------------------------------------------
+// Attribute/Type Reader
+// ----------------------------------------------------------------------
+
+/// The value of a resolved entry of the attribute table. Locations are not attributes, but the bytecode format encodes them within the same table.
+#[derive(Clone)]
+enum AttrEntry {
+    Attribute(Arc<dyn Attribute>),
+    Location(Location)
+}
+
+/// This class represents a single attribute or type entry.
+struct Entry<'a, T> {
+    /// The entry, or None if it hasn't been resolved yet.
+    value: Option<T>,
+    /// The parent dialect of this entry.
+    dialect: usize,
+    /// A flag indicating if the entry was encoded using a custom encoding, instead of using the textual assembly format.
+    has_custom_encoding: bool,
+    /// The raw data of this entry in the bytecode.
+    data: EncodingReader<'a>
+}
+
+/// Parse the offsets of `num_entries` attribute or type entries of `section` from `offset_reader`.
+fn parse_attr_type_entries<'a, T>(
+    offset_reader: &mut EncodingReader<'a>,
+    section: &EncodingReader<'a>,
+    strings: &StringSectionReader<'a>,
+    dialects: &[BytecodeDialect<'a>],
+    num_entries: u64,
+    current_offset: &mut usize
+) -> ReadResult<Vec<Entry<'a, T>>> {
+    let mut entries = Vec::new();
+    while (entries.len() as u64) < num_entries {
+        // Parse the dialect and the number of entries in the group.
+        let dialect = parse_entry(offset_reader, dialects, "dialect")?;
+        let dialect = dialects.iter().position(|other| core::ptr::eq(other, dialect))
+            .unwrap();
+        let num_group_entries = offset_reader.parse_var_int()?;
+        for _ in 0..num_group_entries {
+            if entries.len() as u64 == num_entries {
+                return Err(offset_reader.emit_error(
+                    "Attribute or Type dialect grouping exceeds the number of entries"));
+            }
+            let (entry_size, has_custom_encoding) = offset_reader.parse_var_int_with_flag()?;
+            let entry_size = entry_size as usize;
+
+            // Verify that the offset is actually valid.
+            if *current_offset + entry_size > section.size() {
+                return Err(offset_reader.emit_error(
+                    "Attribute or Type entry offset points past the end of section"));
+            }
+            entries.push(Entry {
+                value: None,
+                dialect,
+                has_custom_encoding,
+                data: EncodingReader::new(
+                    &section.data[*current_offset..*current_offset + entry_size],
+                    section.offset + *current_offset)
+            });
+            *current_offset += entry_size;
+        }
+    }
+    Ok(entries)
+}
+
+// ----------------------------------------------------------------------
+// Bytecode Reader
+// ----------------------------------------------------------------------
+
+/// This struct represents the current read state of a range of regions. This struct is used to enable iterative parsing of regions.
+struct RegionReadState<'a> {
+    /// The regions being read.
+    regions: Vec<RegionId>,
+    /// The index of the current region being read.
+    cur_region: usize,
+    /// Flag indicating if the regions being read are isolated from above.
+    is_isolated_from_above: bool,
+    /// The number of operations remaining to be read from the current block being read.
+    num_ops_remaining: u64,
+    /// The blocks of the current region.
+    cur_blocks: Vec<BlockId>,
+    /// The index of the current block being read, or None if the header of the current region hasn't been read yet.
+    cur_block: Option<usize>,
+    /// The number of values defined immediately within this region.
+    num_values: usize,
+    /// The reader used to read the regions. This is a copy of the reader of the enclosing regions, whose position is passed back once the regions are read, unless the regions are encoded in their own section.
+    reader: EncodingReader<'a>,
+    /// Whether `reader` reads a section of its own, which happens for regions that are isolated from above from `LAZY_LOADING`.
+    owns_reader: bool
+}
+
+impl<'a> RegionReadState<'a> {
+    fn new(
+        regions: Vec<RegionId>,
+        is_isolated_from_above: bool,
+        reader: EncodingReader<'a>,
+        owns_reader: bool
+    ) -> Self {
+        Self {
+            regions,
+            cur_region: 0,
+            is_isolated_from_above,
+            num_ops_remaining: 0,
+            cur_blocks: Vec::new(),
+            cur_block: None,
+            num_values: 0,
+            reader,
+            owns_reader
+        }
+    }
+}
+
+/// The use-list order of a value as encoded in the bytecode.
+pub(super) struct UseListOrderStorage {
+    /// The indices of the uses, either as a permutation or as pairs of `(src, dst)` positions, depending on `is_index_pair_encoding`.
+    pub(super) indices: Vec<usize>,
+    /// Whether `indices` holds pairs of positions for the uses that are shuffled, rather than a position for every use.
+    pub(super) is_index_pair_encoding: bool
+}
+
+/// Parse the use-list orders encoded for a range of `num_values` values, returning them along with the index of their value in the range.
+pub(super) fn parse_use_list_order_for_range(reader: &mut EncodingReader, num_values: usize)
+    -> ReadResult<Vec<(usize, UseListOrderStorage)>>
+{
+    // The number of values and their indices are only encoded if the range has more than one value.
+    let num_values_to_read = if num_values > 1 {
+        reader.parse_var_int()?
+    } else {
+        1
+    };
+    let mut map = Vec::new();
+    for _ in 0..num_values_to_read {
+        let value_index = if num_values > 1 {
+            reader.parse_var_int()? as usize
+        } else {
+            0
+        };
+        let (num_indices, is_index_pair_encoding) = reader.parse_var_int_with_flag()?;
+        let indices = (0..num_indices)
+            .map(|_| reader.parse_var_int().map(|index| index as usize))
+            .collect::<ReadResult<Vec<usize>>>()?;
+        map.push((value_index, UseListOrderStorage { indices, is_index_pair_encoding }));
+    }
+    Ok(map)
+}
+
+/// This class represents a single value scope, in which a value scope is delimited by isolated from above regions.
+#[derive(Default)]
+struct ValueScope {
+    /// The set of values defined in this scope.
+    values: Vec<Option<Value>>,
+    /// The ID for the next defined value for each region current being processed in this scope.
+    next_value_ids: Vec<usize>
+}
+
+impl ValueScope {
+    /// Push a new region state onto this scope, reserving enough values for those defined within the current region of the provided state.
+    fn push(&mut self, read_state: &RegionReadState<'_>) {
+        self.next_value_ids.push(self.values.len());
+        self.values.resize(self.values.len() + read_state.num_values, None);
+    }
+
+    /// Pop the values defined for the current region within the provided region state.
+    fn pop(&mut self, read_state: &RegionReadState<'_>) {
+        self.values.truncate(self.values.len() - read_state.num_values);
+        self.next_value_ids.pop();
+    }
+}
+
+/// This class is used to read a bytecode buffer and translate it into MLIR.
+struct BytecodeReader<'a, 'c> {
+    /// The configuration of the parser.
+    config: &'a mut ParserConfig<'c>,
+
+    /// A location to use when emitting errors.
+    file_loc: Location,
+
+    /// The version and producer of the bytecode being read, used to annotate errors.
+    version: u64,
+    producer: Option<&'a str>,
+
+    /// The table of IR units referenced within the bytecode file.
+    dialects: Vec<BytecodeDialect<'a>>,
+    op_names: Vec<BytecodeOperationName>,
+
+    /// The reader used to process resources within the bytecode.
+    dialect_resources: Vec<AsmDialectResourceHandle>,
+
+    /// The attribute and type entries referenced within the bytecode file.
+    attributes: Vec<Entry<'a, AttrEntry>>,
+    types: Vec<Entry<'a, Arc<dyn Type>>>,
+
+    /// The reader used to process strings within the bytecode.
+    string_reader: StringSectionReader<'a>,
+
+    /// The reader used to process the properties of operations within the bytecode.
+    properties_reader: PropertiesSectionReader<'a>,
+
+    /// The buffer being read, if blobs may reference it rather than copying their data.
+    shared_buffer: Option<SharedResourceBuffer>,
+
+    /// The current set of available IR value scopes.
+    value_scopes: Vec<ValueScope>,
+    /// The operations created as placeholders for forward references that have not been resolved yet.
+    forward_ref_ops: HashSet<OpId>,
+
+    /// The use-list orders encoded for the values read, which are applied once the IR has been read.
+    use_list_orders: HashMap<Value, UseListOrderStorage>
+}
+
+impl<'a, 'c> BytecodeReader<'a, 'c> {
+    fn new(
+        config: &'a mut ParserConfig<'c>,
+        file_loc: Location,
+        shared_buffer: Option<SharedResourceBuffer>
+    ) -> Self {
+        Self {
+            config,
+            file_loc,
+            version: 0,
+            producer: None,
+            dialects: Vec::new(),
+            op_names: Vec::new(),
+            dialect_resources: Vec::new(),
+            attributes: Vec::new(),
+            types: Vec::new(),
+            string_reader: StringSectionReader::default(),
+            properties_reader: PropertiesSectionReader::default(),
+            shared_buffer,
+            value_scopes: Vec::new(),
+            forward_ref_ops: HashSet::new(),
+            use_list_orders: HashMap::new()
+        }
+    }
+
+    fn arena(&self) -> &IRArena {
+        self.config.context().arena()
+    }
+
+    fn arena_mut(&mut self) -> &mut IRArena {
+        self.config.context_mut().arena_mut()
+    }
+
+    /// Read the bytecode defined within `buffer` into the given block.
+    fn read(&mut self, buffer: &'a [u8], block: BlockId) -> LogicalResult {
+        self.read_impl(buffer, block).map_err(|error| self.report(error))
+    }
+
+    /// Emit the given error at the location of the file, annotated with the version and producer of the bytecode. Errors of the textual parser have already been emitted.
+    fn report(&self, error: Box<dyn Error>) -> Box<dyn Error> {
+        if error.is::<Diagnostic>() {
+            return error;
+        }
+        let mut diagnostic = Diagnostic::new(
+            self.file_loc.clone(), DiagnosticSeverity::Error, error.to_string());
+        if let Some(producer) = self.producer {
+            diagnostic.attach_note(None, format!(
+                "in bytecode version {} produced by: {}", self.version, producer));
+        }
+        self.config.context().diag_engine().emit(diagnostic.clone());
+        Box::new(diagnostic)
+    }
+
+    fn read_impl(&mut self, buffer: &'a [u8], block: BlockId) -> LogicalResult {
+        let mut reader = EncodingReader::new(buffer, 0);
+
+        // Skip over the bytecode header, this should have already been checked.
+        reader.skip_bytes(MAGIC.len())?;
+
+        // Parse the bytecode version and producer.
+        self.parse_version(&mut reader)?;
+        self.producer = Some(reader.parse_nul_terminated_string()?);
+
+        // Parse the raw data for each of the top-level sections of the bytecode.
+        let mut section_datas = [None; ID::NumSections as usize];
+        while !reader.empty() {
+            // Read the next section from the bytecode.
+            let (section_id, section_data) = reader.parse_section()?;
+
+            // Check for duplicate sections, we only expect one instance of each.
+            if section_datas[section_id as usize].is_some() {
+                return Err(reader.emit_error(format!(
+                    "duplicate top-level section: {}", section_id)));
+            }
+            section_datas[section_id as usize] = Some(section_data);
+        }
+        // Check that all of the required sections were found.
+        for section_id in ID::ALL {
+            if section_datas[section_id as usize].is_none()
+                && !section_id.is_optional(self.version)
+            {
+                return Err(reader.emit_error(format!(
+                    "missing data for top-level section: {}", section_id)));
+            }
+        }
+        let section = |id: ID| section_datas[id as usize];
+
+        // Process the string section first.
+        self.string_reader.initialize(section(ID::String).unwrap())?;
+
+        // Process the dialect section.
+        self.parse_dialect_section(section(ID::Dialect).unwrap())?;
+
+        // Process the resource section if present.
+        self.parse_resource_section(section(ID::Resource), section(ID::ResourceOffset))?;
+
+        // Process the attribute and type section.
+        self.parse_attr_type_section(
+            section(ID::AttrType).unwrap(), section(ID::AttrTypeOffset).unwrap())?;
+
+        // Read the versions of the dialects, now that the entries they may reference are available.
+        self.load_dialect_versions()?;
+
+        // Process the properties section if present.
+        if let Some(section_data) = section(ID::Properties) {
+            self.properties_reader.initialize(section_data)?;
+        }
+
+        // Finally, process the IR section.
+        self.parse_ir_section(section(ID::IR).unwrap(), block)
+    }
+
+    // ----------------------------------------------------------------------
+    // Version
+    // ----------------------------------------------------------------------
+
+    fn parse_version(&mut self, reader: &mut EncodingReader) -> ReadResult {
+        self.version = reader.parse_var_int()?;
+
+        // Validate the bytecode version.
+        if self.version < MIN_SUPPORTED_VERSION {
+            return Err(reader.emit_error(format!(
+                "bytecode version {} is older than the current version of {}, and upgrade is not supported",
+                self.version, VERSION)));
+        }
+        if self.version > VERSION {
+            return Err(reader.emit_error(format!(
+                "bytecode version {} is newer than the current version {}",
+                self.version, VERSION)));
+        }
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Dialect Section
+    // ----------------------------------------------------------------------
+
+    fn parse_dialect_section(&mut self, mut section_reader: EncodingReader<'a>)
+        -> ReadResult
+    {
+        // Parse the number of dialects in the section.
+        let num_dialects = section_reader.parse_var_int()?;
+
+        // Parse each of the dialects.
+        for _ in 0..num_dialects {
+            // Before `DIALECT_VERSIONING`, there wasn't any versioning available for dialects, and the entry is the index of the name itself.
+            let (name, version_buffer) = if self.version < DIALECT_VERSIONING {
+                (self.string_reader.parse_string(&mut section_reader)?, None)
+            } else {
+                // Parse the index of the name, flagged with whether a version is present.
+                let (name_index, version_available)
+                    = section_reader.parse_var_int_with_flag()?;
+                let name = self.string_reader.parse_string_at_index(
+                    &section_reader, name_index)?;
+                let version_buffer = if version_available {
+                    let (section_id, version_buffer) = section_reader.parse_section()?;
+                    if section_id != ID::DialectVersions {
+                        return Err(section_reader.emit_error(
+                            "expected dialect version section"));
+                    }
+                    Some(version_buffer)
+                } else {
+                    None
+                };
+                (name, version_buffer)
+            };
+            let interface = self.config.bytecode_interface(name).or_else(||
+                (name == "builtin").then(|| Arc::new(BuiltinBytecodeInterface)
+                    as Arc<dyn BytecodeDialectInterface>));
+            self.dialects.push(BytecodeDialect {
+                name,
+                interface,
+                version_buffer,
+                loaded_version: None
+            });
+        }
+
+        // From `ELIDE_UNKNOWN_BLOCK_ARG_LOCATION`, the number of operation names is known upfront.
+        if self.version >= ELIDE_UNKNOWN_BLOCK_ARG_LOCATION {
+            let num_ops = section_reader.parse_var_int()?;
+            self.op_names.reserve(num_ops.min(section_reader.size() as u64) as usize);
+        }
+
+        // Parse the operation names, which are grouped by dialect.
+        while !section_reader.empty() {
+            let dialect_index = section_reader.parse_var_int()?;
+            let dialect = resolve_entry(
+                &section_reader, &self.dialects, dialect_index, "dialect")?.name;
+            let num_entries = section_reader.parse_var_int()?;
+            for _ in 0..num_entries {
+                // Prior to `NATIVE_PROPERTIES_ENCODING`, whether an operation was registered wasn't encoded.
+                let (op_name, was_registered)
+                    = if self.version < NATIVE_PROPERTIES_ENCODING {
+                        (self.string_reader.parse_string(&mut section_reader)?, None)
+                    } else {
+                        let (op_name, was_registered)
+                            = self.string_reader.parse_string_with_flag(&mut section_reader)?;
+                        (op_name, Some(was_registered))
+                    };
+                let name = if dialect.is_empty() {
+                    op_name.to_string()
+                } else {
+                    format!("{}.{}", dialect, op_name)
+                };
+                self.op_names.push(BytecodeOperationName {
+                    dialect: dialect_index as usize,
+                    name: self.config.context().operation_name(&name),
+                    was_registered
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Read the versions encoded for the dialects of the bytecode.
+    fn load_dialect_versions(&mut self) -> ReadResult {
+        for index in 0..self.dialects.len() {
+            let Some(reader) = self.dialects[index].version_buffer else {
+                continue;
+            };
+            let Some(interface) = self.dialects[index].interface.clone() else {
+                return Err(reader.emit_error(format!(
+                    "dialect '{}' does not implement the bytecode interface, but found a version entry",
+                    self.dialects[index].name)));
+            };
+            let version = interface.read_version(
+                &mut DialectReader { bytecode_reader: self, reader })?;
+            self.dialects[index].loaded_version = Some(version);
+        }
+        Ok(())
+    }
+
+    /// Parse an operation name reference using the given reader.
+    fn parse_op_name(&self, reader: &mut EncodingReader)
+        -> ReadResult<BytecodeOperationName>
+    {
+        parse_entry(reader, &self.op_names, "operation name").cloned()
+    }
+
+    // ----------------------------------------------------------------------
+    // Resource Section
+    // ----------------------------------------------------------------------
+
+    fn parse_resource_section(
+        &mut self,
+        resource_data: Option<EncodingReader<'a>>,
+        resource_offset_data: Option<EncodingReader<'a>>
+    ) -> ReadResult {
+        // Ensure both sections are either present or not.
+        let (mut resource_reader, mut offset_reader)
+            = match (resource_data, resource_offset_data)
+        {
+            // If the resource sections are absent, there is nothing to do.
+            (None, None) => return Ok(()),
+            (None, Some(_)) => return Err(
+                "unexpected resource offset section when resource section is not present".into()),
+            (Some(_), None) => return Err(
+                "expected resource offset section when resource section is present".into()),
+            (Some(resource_reader), Some(offset_reader)) => (resource_reader, offset_reader)
+        };
+
+        // Read the number of external resource providers.
+        let num_external_resource_groups = offset_reader.parse_var_int()?;
+
+        // Read the external resources from the bytecode.
+        for _ in 0..num_external_resource_groups {
+            let key = self.string_reader.parse_string(&mut offset_reader)?;
+
+            // Get the resource handler for this group.
+            // TODO: Should we require handling external resources in some scenarios?
+            let is_handled = self.config.resource_parser(key).is_some()
+                || self.config.fallback_resource_map().is_some();
+            if !is_handled {
+                emit_warning(
+                    self.config.context(),
+                    self.file_loc.clone(),
+                    format!("ignoring unknown external resources for '{}'", key));
+            }
+
+            let entries = parse_resource_group(
+                &self.string_reader, &mut offset_reader, &mut resource_reader)?;
+            if !is_handled {
+                continue;
+            }
+            for (entry_key, kind, data) in entries {
+                let entry = self.parse_resource_entry(entry_key, kind, data)?;
+                match self.config.resource_parser(key) {
+                    Some(parser) => parser.parse_resource(&entry),
+                    None => self.config.fallback_resource_map().unwrap()
+                        .parse_resource(key, &entry)
+                }?;
+            }
+        }
+
+        // Read the dialect resources from the bytecode.
+        while !offset_reader.empty() {
+            let dialect = parse_entry(&mut offset_reader, &self.dialects, "dialect")?
+                .name;
+            if self.config.dialect_interface(dialect).is_none() {
+                return Err(resource_reader.emit_error(format!(
+                    "unexpected resources for dialect '{}'", dialect)));
+            }
+
+            // Parse the resources for this dialect. Empty resources are treated as declarations.
+            let entries = parse_resource_group(
+                &self.string_reader, &mut offset_reader, &mut resource_reader)?;
+            for (key, kind, data) in entries {
+                // Ensure that each resource is declared before being processed.
+                let interface = self.config.dialect_interface(dialect).unwrap();
+                let handle = interface.declare_resource(key).ok_or_else(||
+                    resource_reader.emit_error(format!(
+                        "unknown 'resource' key '{}' for dialect '{}'", key, dialect)))?;
+
+                // If the resource data is empty, the resource is only declared, so there is nothing to parse. The entry is parsed under the key of the handle, which the dialect may have renamed.
+                if !data.empty() {
+                    let entry = self.parse_resource_entry(handle.key(), kind, data)?;
+                    interface.parse_resource(&entry)?;
+                }
+                self.dialect_resources.push(handle);
+            }
+        }
+        Ok(())
+    }
+
+    /// Parse the value of a resource entry of the given kind, encoded in `data`.
+    fn parse_resource_entry(&self, key: &str, kind: u8, mut data: EncodingReader<'a>)
+        -> ReadResult<AsmParsedResourceEntry>
+    {
+        let entry = match kind {
+            // Blob
+            0 => {
+                let (blob, alignment) = data.parse_blob_and_alignment()?;
+                // Reference the data directly if the buffer being read is shared, instead of copying it.
+                let blob = match &self.shared_buffer {
+                    Some(buffer) => {
+                        let start = data.offset - blob.len();
+                        AsmResourceBlob::from_shared(
+                            buffer.clone(), start..data.offset, alignment as usize, false)
+                    }
+                    None => HeapAsmResourceBlob::allocate_and_copy(
+                        blob, alignment as usize, true)
+                };
+                AsmParsedResourceEntry::from_blob(key, blob)
+            }
+            // Bool
+            1 => AsmParsedResourceEntry::from_bool(key, data.parse_byte()? != 0),
+            // String
+            2 => AsmParsedResourceEntry::from_string(
+                key, self.string_reader.parse_string(&mut data)?.to_string()),
+            _ => return Err(data.emit_error(format!(
+                "invalid kind {} for resource entry '{}'", kind, key)))
+        };
+        if !data.empty() {
+            return Err(data.emit_error(format!(
+                "unexpected trailing bytes in resource entry '{}'", key)));
+        }
+        Ok(entry)
+    }
+
+    /// Parse a handle to a dialect resource using the given reader.
+    fn parse_resource_handle(&self, reader: &mut EncodingReader)
+        -> ReadResult<AsmDialectResourceHandle>
+    {
+        parse_entry(reader, &self.dialect_resources, "resource handle").cloned()
+    }
+
+    // ----------------------------------------------------------------------
+    // Attribute/Type Section
+    // ----------------------------------------------------------------------
+
+    /// Initialize the attribute and type information within the reader.
+    fn parse_attr_type_section(
+        &mut self,
+        section: EncodingReader<'a>,
+        mut offset_reader: EncodingReader<'a>
+    ) -> ReadResult {
+        // Parse the number of attribute and type entries.
+        let num_attributes = offset_reader.parse_var_int()?;
+        let num_types = offset_reader.parse_var_int()?;
+
+        // Process each of the attributes, and then the types.
+        let mut current_offset = 0;
+        self.attributes = parse_attr_type_entries(
+            &mut offset_reader, &section, &self.string_reader, &self.dialects,
+            num_attributes, &mut current_offset)?;
+        self.types = parse_attr_type_entries(
+            &mut offset_reader, &section, &self.string_reader, &self.dialects,
+            num_types, &mut current_offset)?;
+
+        // Ensure that we read everything from the section.
+        if !offset_reader.empty() {
+            return Err(offset_reader.emit_error(
+                "unexpected trailing data in the Attribute/Type offset section"));
+        }
+        Ok(())
+    }
+
+    /// Return the bytecode interface of the dialect of an entry with a custom encoding.
+    fn entry_interface(&self, dialect: usize, reader: &EncodingReader)
+        -> ReadResult<Arc<dyn BytecodeDialectInterface>>
+    {
+        let dialect = &self.dialects[dialect];
+        dialect.interface.clone().ok_or_else(|| reader.emit_error(format!(
+            "dialect '{}' does not implement the bytecode interface", dialect.name)))
+    }
+
+    /// Resolve the entry of the attribute table at `index`, as a location if `as_location` is set and as an attribute otherwise.
+    fn resolve_attribute_entry(&mut self, index: u64, as_location: bool)
+        -> ReadResult<AttrEntry>
+    {
+        let entry = resolve_entry(
+            &EncodingReader::new(&[], 0), &self.attributes, index, "Attribute")?;
+
+        // If the entry has already been resolved, there is nothing left to do.
+        if let Some(value) = &entry.value {
+            return Ok(value.clone());
+        }
+
+        // Parse the entry based on how it was encoded.
+        let (mut reader, dialect) = (entry.data, entry.dialect);
+        let value = if entry.has_custom_encoding {
+            let interface = self.entry_interface(dialect, &reader)?;
+            let mut dialect_reader = DialectReader { bytecode_reader: self, reader };
+            let value = if as_location {
+                AttrEntry::Location(interface.read_location(&mut dialect_reader)?)
+            } else {
+                AttrEntry::Attribute(interface.read_attribute(&mut dialect_reader)?)
+            };
+            reader = dialect_reader.reader;
+            value
+        } else {
+            let asm_str = reader.parse_nul_terminated_string()?;
+            let (value, num_read) = if as_location {
+                let (location, num_read) = parse_location(asm_str, self.config)?;
+                (AttrEntry::Location(location), num_read)
+            } else {
+                let (attribute, num_read) = parse_attribute(asm_str, self.config)?;
+                (AttrEntry::Attribute(attribute), num_read)
+            };
+
+            // Ensure there weren't dangling characters after the entry.
+            if num_read != asm_str.len() {
+                return Err(reader.emit_error(format!(
+                    "trailing characters found after Attribute assembly format: {}",
+                    &asm_str[num_read..])));
+            }
+            value
+        };
+        if !reader.empty() {
+            return Err(reader.emit_error("unexpected trailing bytes after Attribute entry"));
+        }
+        self.attributes[index as usize].value = Some(value.clone());
+        Ok(value)
+    }
+
+    /// Resolve the type entry at `index`.
+    fn resolve_type(&mut self, index: u64) -> ReadResult<Arc<dyn Type>> {
+        let entry = resolve_entry(
+            &EncodingReader::new(&[], 0), &self.types, index, "Type")?;
+
+        // If the entry has already been resolved, there is nothing left to do.
+        if let Some(value) = &entry.value {
+            return Ok(value.clone());
+        }
+
+        // Parse the entry based on how it was encoded.
+        let (mut reader, dialect) = (entry.data, entry.dialect);
+        let value = if entry.has_custom_encoding {
+            let interface = self.entry_interface(dialect, &reader)?;
+            let mut dialect_reader = DialectReader { bytecode_reader: self, reader };
+            let value = interface.read_type(&mut dialect_reader)?;
+            reader = dialect_reader.reader;
+            value
+        } else {
+            let asm_str = reader.parse_nul_terminated_string()?;
+            let (value, num_read) = parse_type(asm_str, self.config)?;
+
+            // Ensure there weren't dangling characters after the entry.
+            if num_read != asm_str.len() {
+                return Err(reader.emit_error(format!(
+                    "trailing characters found after Type assembly format: {}",
+                    &asm_str[num_read..])));
+            }
+            value
+        };
+        if !reader.empty() {
+            return Err(reader.emit_error("unexpected trailing bytes after Type entry"));
+        }
+        self.types[index as usize].value = Some(value.clone());
+        Ok(value)
+    }
+
+    /// Parse a reference to an attribute using the given reader.
+    fn parse_attribute(&mut self, reader: &mut EncodingReader)
+        -> ReadResult<Arc<dyn Attribute>>
+    {
+        let index = reader.parse_var_int()?;
+        match self.resolve_attribute_entry(index, false)? {
+            AttrEntry::Attribute(attribute) => Ok(attribute),
+            AttrEntry::Location(location) => Err(reader.emit_error(format!(
+                "expected attribute, but got location: loc({})", location)))
+        }
+    }
+
+    /// Parse an optional reference to an attribute using the given reader. The index of the attribute is flagged with whether it is present.
+    fn parse_optional_attribute(&mut self, reader: &mut EncodingReader)
+        -> ReadResult<Option<Arc<dyn Attribute>>>
+    {
+        let (index, flag) = reader.parse_var_int_with_flag()?;
+        if !flag {
+            return Ok(None);
+        }
+        match self.resolve_attribute_entry(index, false)? {
+            AttrEntry::Attribute(attribute) => Ok(Some(attribute)),
+            AttrEntry::Location(location) => Err(reader.emit_error(format!(
+                "expected attribute, but got location: loc({})", location)))
+        }
+    }
+
+    /// Parse a reference to an attribute of the given kind using the given reader.
+    fn parse_attribute_of<T: Attribute + Clone>(&mut self, reader: &mut EncodingReader)
+        -> ReadResult<T>
+    {
+        let attribute = self.parse_attribute(reader)?;
+        attribute.downcast_ref::<T>().cloned().ok_or_else(|| {
+            let name = core::any::type_name::<T>().rsplit("::").next().unwrap();
+            reader.emit_error(format!(
+                "expected attribute of type: {}, but got: {}", name, attribute.as_ref()))
+        })
+    }
+
+    /// Parse a reference to a location using the given reader.
+    fn parse_location(&mut self, reader: &mut EncodingReader) -> ReadResult<Location> {
+        let index = reader.parse_var_int()?;
+        match self.resolve_attribute_entry(index, true)? {
+            AttrEntry::Location(location) => Ok(location),
+            AttrEntry::Attribute(attribute) => Err(reader.emit_error(format!(
+                "expected attribute of type: LocationAttr, but got: {}",
+                attribute.as_ref())))
+        }
+    }
+
+    /// Parse a reference to a type using the given reader.
+    fn parse_type(&mut self, reader: &mut EncodingReader) -> ReadResult<Arc<dyn Type>> {
+        let index = reader.parse_var_int()?;
+        self.resolve_type(index)
+    }
+
+    // ----------------------------------------------------------------------
+    // IR Section
+    // ----------------------------------------------------------------------
+
+    /// Parse the IR section, and append the parsed operations to `block`.
+    fn parse_ir_section(&mut self, reader: EncodingReader<'a>, block: BlockId)
+        -> ReadResult
+    {
+        // Parse the top-level block using a temporary module operation.
+        let module_op = ModuleOp::build(
+            self.config.context_mut(), self.file_loc.clone(), None)?;
+        let result = self.parse_ir_section_into(reader, module_op);
+        let arena = self.arena_mut();
+        if result.is_err() {
+            // Erase everything created while reading. Forward references are erased after the operations that use them.
+            let _ = arena.erase(module_op.operation());
+            for op in core::mem::take(&mut self.forward_ref_ops) {
+                let _ = self.arena_mut().erase(op);
+            }
+            return result;
+        }
+
+        // Splice the parsed operations over to the provided top-level block.
+        let body = module_op.body(arena)?;
+        for op in arena.block(body)?.operations().to_vec() {
+            arena.remove(op)?;
+            arena.push_back(block, op)?;
+        }
+        arena.erase(module_op.operation())?;
+        Ok(())
+    }
+
+    fn parse_ir_section_into(&mut self, reader: EncodingReader<'a>, module_op: ModuleOp)
+        -> ReadResult
+    {
+        // A stack of operation regions currently being read from the bytecode.
+        let regions = self.arena().op(module_op.operation())?.regions().to_vec();
+        let mut top_level = RegionReadState::new(regions, true, reader, true);
+        top_level.cur_blocks.push(module_op.body(self.arena())?);
+        top_level.cur_block = Some(0);
+        let mut reader = top_level.reader;
+        self.parse_block(&mut reader, &mut top_level)?;
+        top_level.reader = reader;
+        let mut value_scope = ValueScope::default();
+        value_scope.push(&top_level);
+        self.value_scopes.push(value_scope);
+        let mut region_stack = vec![top_level];
+
+        // Iteratively parse regions until everything has been resolved.
+        while !region_stack.is_empty() {
+            self.parse_regions(&mut region_stack)?;
+        }
+        if !self.forward_ref_ops.is_empty() {
+            return Err(reader.emit_error(
+                "not all forward unresolved forward operand references"));
+        }
+
+        // Sort use-lists according to what specified in bytecode.
+        if self.version >= USE_LIST_ORDERING
+            && self.process_use_lists(module_op.operation()).is_err()
+        {
+            return Err(reader.emit_error(
+                "parsed use-list orders were invalid and could not be applied"));
+        }
+
+        // Run the upgrade hooks of the dialects whose versions were encoded.
+        for dialect in self.dialects.iter() {
+            if let (Some(interface), Some(version))
+                = (&dialect.interface, &dialect.loaded_version)
+            {
+                interface.upgrade_from_version(
+                    module_op.operation(), self.config.context_mut(), version.as_ref())?;
+            }
+        }
+
+        // TODO: Verify the parsed operations if `should_verify_after_parse` is set.
+        Ok(())
+    }
+
+    /// Parse the regions of the operation at the top of `region_stack`, pushing the first nested operation with regions to the stack.
+    fn parse_regions(&mut self, region_stack: &mut Vec<RegionReadState<'a>>) -> ReadResult {
+        let read_state = region_stack.last_mut().unwrap();
+        let mut reader = read_state.reader;
+
+        // Read the regions of this operation.
+        while read_state.cur_region != read_state.regions.len() {
+            // If the current block hasn't been setup yet, parse the header for this region.
+            if read_state.cur_block.is_none() {
+                self.parse_region(&mut reader, read_state)?;
+
+                // If the region is empty, there is nothing to more to do.
+                if read_state.cur_block.is_none() {
+                    read_state.cur_region += 1;
+                    continue;
+                }
+            }
+
+            // Parse the blocks within the region.
+            loop {
+                while read_state.num_ops_remaining != 0 {
+                    read_state.num_ops_remaining -= 1;
+
+                    // Read in the next operation. We don't read its regions directly, we handle those afterwards as necessary.
+                    let (op, is_isolated_from_above)
+                        = self.parse_op_without_regions(&mut reader, read_state)?;
+
+                    // If the op has regions, add it to the stack for processing.
+                    let regions = self.arena().op(op)?.regions().to_vec();
+                    if !regions.is_empty() {
+                        // If the op is isolated from above, push a new value scope.
+                        if is_isolated_from_above {
+                            self.value_scopes.push(ValueScope::default());
+                        }
+
+                        // Isolated regions are encoded in a section of their own from `LAZY_LOADING`, otherwise they continue the current reader.
+                        let owns_reader = is_isolated_from_above
+                            && self.version >= LAZY_LOADING;
+                        let region_reader = if owns_reader {
+                            let (section_id, section_data) = reader.parse_section()?;
+                            if section_id != ID::IR {
+                                return Err(reader.emit_error(
+                                    "expected IR section for region"));
+                            }
+                            section_data
+                        } else {
+                            reader
+                        };
+                        read_state.reader = reader;
+                        region_stack.push(RegionReadState::new(
+                            regions, is_isolated_from_above, region_reader, owns_reader));
+                        return Ok(());
+                    }
+                }
+
+                // Move to the next block of the region.
+                let next_block = read_state.cur_block.unwrap() + 1;
+                if next_block == read_state.cur_blocks.len() {
+                    break;
+                }
+                read_state.cur_block = Some(next_block);
+                self.parse_block(&mut reader, read_state)?;
+            }
+
+            // Reset the current block and any values reserved for this region.
+            read_state.cur_block = None;
+            self.value_scopes.last_mut().unwrap().pop(read_state);
+            read_state.cur_region += 1;
+        }
+
+        // When the regions have been fully parsed, pop them off of the read stack. If the regions were isolated from above, we also pop the last value scope.
+        if read_state.is_isolated_from_above {
+            self.value_scopes.pop();
+        }
+        let owns_reader = read_state.owns_reader;
+        region_stack.pop();
+
+        // Unless the regions were read from their own section, the enclosing regions continue where they left off.
+        if !owns_reader && let Some(parent) = region_stack.last_mut() {
+            parent.reader = reader;
+        }
+        Ok(())
+    }
+
+    /// Parse the header of the current region of `read_state`, creating its blocks, and parse the header of its entry block. An empty region leaves the current block unset.
+    fn parse_region(
+        &mut self,
+        reader: &mut EncodingReader<'a>,
+        read_state: &mut RegionReadState<'a>
+    ) -> ReadResult {
+        // Parse the number of blocks in the region.
+        let num_blocks = reader.parse_var_int()?;
+
+        // If the region is empty, there is nothing else to do.
+        if num_blocks == 0 {
+            return Ok(());
+        }
+
+        // Parse the number of values defined in this region.
+        read_state.num_values = reader.parse_var_int()? as usize;
+
+        // Create the blocks within this region. We do this before processing so that we can rely on the blocks existing when creating operations.
+        read_state.cur_blocks.clear();
+        let region = read_state.regions[read_state.cur_region];
+        for _ in 0..num_blocks {
+            let block = self.arena_mut().create_block();
+            self.arena_mut().push_back_block(region, block)?;
+            read_state.cur_blocks.push(block);
+        }
+
+        // Prepare the current value scope for this region.
+        self.value_scopes.last_mut().unwrap().push(read_state);
+
+        // Parse the entry block of the region.
+        read_state.cur_block = Some(0);
+        self.parse_block(reader, read_state)
+    }
+
+    /// Parse the header of the current block of `read_state`.
+    fn parse_block(
+        &mut self,
+        reader: &mut EncodingReader<'a>,
+        read_state: &mut RegionReadState<'a>
+    ) -> ReadResult {
+        let (num_ops, has_inputs) = reader.parse_var_int_with_flag()?;
+        read_state.num_ops_remaining = num_ops;
+
+        // Parse the inputs of the block.
+        let block = read_state.cur_blocks[read_state.cur_block.unwrap()];
+        if has_inputs {
+            self.parse_block_inputs(reader, block)?;
+        }
+
+        // We don't parse the operations of the block here, that's done elsewhere.
+
+        // Uselist orders are available since version 3 of the bytecode.
+        if self.version < USE_LIST_ORDERING || !has_inputs {
+            return Ok(());
+        }
+
+        // The inputs are followed by a mask indicating whether their use-list orders are encoded.
+        let has_use_list_orders = reader.parse_byte()?;
+        if has_use_list_orders == 0 {
+            return Ok(());
+        }
+        let num_inputs = self.arena().block(block)?.num_inputs();
+        for (index, order) in parse_use_list_order_for_range(reader, num_inputs)? {
+            self.use_list_orders.insert(Value::Input(Input::new(block, index)), order);
+        }
+        Ok(())
+    }
+
+    fn parse_block_inputs(&mut self, reader: &mut EncodingReader<'a>, block: BlockId)
+        -> ReadResult
+    {
+        // Parse the number of inputs.
+        let num_inputs = reader.parse_var_int()?;
+        let mut inputs = Vec::new();
+        for _ in 0..num_inputs {
+            // From `ELIDE_UNKNOWN_BLOCK_ARG_LOCATION`, the index of the type is flagged with whether a location follows, which is elided when unknown.
+            let (r#type, location) = if self.version >= ELIDE_UNKNOWN_BLOCK_ARG_LOCATION {
+                let (type_index, has_location) = reader.parse_var_int_with_flag()?;
+                let r#type = self.resolve_type(type_index)?;
+                let location = if has_location {
+                    self.parse_location(reader)?
+                } else {
+                    Location::unknown()
+                };
+                (r#type, location)
+            } else {
+                (self.parse_type(reader)?, self.parse_location(reader)?)
+            };
+            let input = self.arena_mut().block_mut(block)?.add_input(r#type, location);
+            inputs.push(Value::Input(input));
+        }
+        self.define_values(reader, inputs)
+    }
+
+    /// Parse an operation without its regions, returning it along with whether its regions are isolated from above.
+    fn parse_op_without_regions(
+        &mut self,
+        reader: &mut EncodingReader<'a>,
+        read_state: &RegionReadState<'a>
+    ) -> ReadResult<(OpId, bool)> {
+        // Parse the name of the operation.
+        let name = self.parse_op_name(reader)?;
+
+        // Parse the operation mask, which indicates which components of the operation are present.
+        let op_mask = reader.parse_byte()?;
+
+        // Parse the location.
+        let location = self.parse_location(reader)?;
+
+        // Parse the attributes of the operation.
+        let mut attributes = if op_mask & OpEncodingMask::HAS_ATTRS != 0 {
+            self.parse_attribute_of::<DictionaryAttribute>(reader)?
+        } else {
+            DictionaryAttribute::default()
+        };
+
+        // Parse the properties of the operation, which are added to its attributes.
+        if op_mask & OpEncodingMask::HAS_PROPERTIES != 0 {
+            // `HAS_PROPERTIES` wasn't emitted in older bytecode, we should never get there without also having the `was_registered` flag available.
+            let Some(was_registered) = name.was_registered else {
+                return Err(reader.emit_error(format!(
+                    "Unexpected missing `wasRegistered` opname flag at bytecode version {} with properties.",
+                    self.version)));
+            };
+            let properties = self.parse_properties(reader, &name, was_registered)?;
+            attributes = DictionaryAttribute::new(
+                attributes.value().iter().cloned().chain(properties).collect());
+        }
+
+        // Parse the results of the operation.
+        let mut output_types = Vec::new();
+        if op_mask & OpEncodingMask::HAS_RESULTS != 0 {
+            let num_results = reader.parse_var_int()?;
+            for _ in 0..num_results {
+                output_types.push(self.parse_type(reader)?);
+            }
+        }
+
+        // Parse the operands of the operation.
+        let mut inputs = Vec::new();
+        if op_mask & OpEncodingMask::HAS_OPERANDS != 0 {
+            let num_operands = reader.parse_var_int()?;
+            for _ in 0..num_operands {
+                inputs.push(self.parse_operand(reader)?);
+            }
+        }
+
+        // Parse the successors of the operation.
+        let mut successors = Vec::new();
+        if op_mask & OpEncodingMask::HAS_SUCCESSORS != 0 {
+            let num_successors = reader.parse_var_int()?;
+            for _ in 0..num_successors {
+                successors.push(
+                    *parse_entry(reader, &read_state.cur_blocks, "successor")?);
+            }
+        }
+
+        // Parse the use-list orders of the results of the operation.
+        let use_list_orders = if op_mask & OpEncodingMask::HAS_USE_LIST_ORDERS != 0 {
+            parse_use_list_order_for_range(reader, output_types.len())?
+        } else {
+            Vec::new()
+        };
+
+        // Parse the regions of the operation.
+        let (num_regions, is_isolated_from_above)
+            = if op_mask & OpEncodingMask::HAS_INLINE_REGIONS != 0 {
+                reader.parse_var_int_with_flag()?
+            } else {
+                (0, false)
+            };
+
+        // Create the operation at the back of the current block.
+        let operation = Operation::new(
+            location, name.name, output_types, inputs, attributes, &successors);
+        let arena = self.arena_mut();
+        let op = arena.create_operation(operation, num_regions as usize)?;
+        arena.push_back(read_state.cur_blocks[read_state.cur_block.unwrap()], op)?;
+
+        // If the operation had results, update the value references.
+        let num_outputs = arena.op(op)?.num_outputs();
+        if num_outputs != 0 {
+            let outputs = (0..num_outputs)
+                .map(|index| Value::Output(Output::new(op, index)))
+                .collect();
+            self.define_values(reader, outputs)?;
+        }
+
+        // Record the use-list orders of the results, which are applied once all the uses have been read.
+        for (index, order) in use_list_orders {
+            self.use_list_orders.insert(Value::Output(Output::new(op, index)), order);
+        }
+        Ok((op, is_isolated_from_above))
+    }
+
+    /// Parse a reference to the properties of an operation named `name`, returning them as the attributes to add to the operation.
+    fn parse_properties(
+        &mut self,
+        reader: &mut EncodingReader,
+        name: &BytecodeOperationName,
+        was_registered: bool
+    ) -> ReadResult<Vec<NamedAttribute>> {
+        let index = reader.parse_var_int()?;
+        let Some(&properties) = self.properties_reader.properties.get(index as usize) else {
+            return Err(reader.emit_error(format!(
+                "Properties idx out-of-bound for {}", name.name.as_str())));
+        };
+
+        // When an operation is emitted without being registered, its properties are stored as an attribute. Otherwise its dialect controls their encoding.
+        let interface = self.dialects[name.dialect].interface.clone();
+        let mut dialect_reader = DialectReader { bytecode_reader: self, reader: properties };
+        if !was_registered {
+            let attribute = dialect_reader.read_attribute()?;
+            return match attribute.downcast_ref::<DictionaryAttribute>() {
+                Some(dictionary) => Ok(dictionary.value().to_vec()),
+                None => Err(reader.emit_error(format!(
+                    "expected the properties of {} to be a dictionary, but got: {}",
+                    name.name.as_str(), attribute.as_ref())))
+            };
+        }
+        match interface {
+            Some(interface) => interface.read_properties(&name.name, &mut dialect_reader),
+            None => Err(reader.emit_error(format!(
+                "has properties but missing BytecodeOpInterface for {}",
+                name.name.as_str())))
+        }
+    }
+
+    // ----------------------------------------------------------------------
+    // Use-list Orders
+    // ----------------------------------------------------------------------
+
+    /**
+    Sort the use-lists of the values defined within `op` as encoded in the bytecode. Values without an encoded order had their uses in descending order of their ID when written, which is how they are sorted here.
+
+    As in the writer, the use-lists are considered in the order upstream keeps them, which is the reverse of the order of `IRArena::uses`.
+    */
+    fn process_use_lists(&mut self, op: OpId) -> ReadResult {
+        // Precompute operation IDs according to the pre-order walk of the IR. We can't do this while parsing since `parse_regions` ordering is not strictly equal to the pre-order walk.
+        let arena = self.arena();
+        let ops = arena.walk(op)?;
+        let operation_ids: HashMap<OpId, usize> = ops.iter().enumerate()
+            .map(|(id, op)| (*op, id))
+            .collect();
+
+        let mut values = Vec::new();
+        for op in ops.iter() {
+            for region in arena.op(*op)?.regions() {
+                for block in arena.region(*region)?.blocks() {
+                    values.extend(arena.block(*block)?.inputs().map(Value::Input));
+                }
+            }
+        }
+        for op in ops.iter() {
+            values.extend((0..arena.op(*op)?.num_outputs())
+                .map(|index| Value::Output(Output::new(*op, index))));
+        }
+        for value in values {
+            self.sort_use_list_order(value, &operation_ids)?;
+        }
+        Ok(())
+    }
+
+    /// Sort the use-list of `value` according to the order encoded for it, if any, or in descending order of the IDs of the uses otherwise.
+    fn sort_use_list_order(&mut self, value: Value, operation_ids: &HashMap<OpId, usize>)
+        -> ReadResult
+    {
+        // Early return for trivial use-lists.
+        let incoming_order = self.use_list_orders.remove(&value);
+        let uses = self.arena().uses(value)?;
+        let num_uses = uses.len();
+        if num_uses < 2 {
+            return Ok(());
+        }
+
+        // Compute the current order of the use-list with respect to the value creation order.
+        let mut current_order: Vec<(usize, u64)> = uses.iter().rev().enumerate()
+            .map(|(index, r#use)| (index, use_id(operation_ids[&r#use.owner], r#use.index)))
+            .collect();
+        let already_sorted = current_order.windows(2).all(|pair| pair[0].1 > pair[1].1);
+
+        // If the order is already sorted, and there wasn't a custom order to apply from the bytecode file, we are done.
+        if already_sorted && incoming_order.is_none() {
+            return Ok(());
+        }
+
+        // If not already sorted, sort the indices of the current order by descending use IDs.
+        if !already_sorted {
+            current_order.sort_by(|lhs, rhs| rhs.1.cmp(&lhs.1));
+        }
+
+        let shuffle = match incoming_order {
+            // If the bytecode file did not contain any custom use-list order, it means that the order was descending use ID. Hence, shuffle by the first index of the `current_order` pair.
+            None => current_order.iter().map(|(index, _)| *index).collect::<Vec<_>>(),
+            Some(order) => {
+                let mut shuffle = order.indices;
+
+                // If the encoding was a pair of indices `(src, dst)` for every permutation, reconstruct the shuffle vector for every use. Initialize the shuffle vector as identity, and then apply the mapping encoded in the indices.
+                if order.is_index_pair_encoding {
+                    // Return failure if the number of indices was not representing pairs.
+                    if shuffle.len() % 2 != 0 {
+                        return Err("use-list order indices do not form pairs".into());
+                    }
+                    let mut new_shuffle: Vec<usize> = (0..num_uses).collect();
+                    for pair in shuffle.chunks(2) {
+                        *new_shuffle.get_mut(pair[0]).ok_or("use-list order index out of range")?
+                            = pair[1];
+                    }
+                    shuffle = new_shuffle;
+                }
+
+                // Make sure that the indices represent a valid mapping. That is, the sum of all the values needs to be equal to `(num_uses - 1) * num_uses / 2`, and no duplicates are allowed in the list.
+                let mut set = HashSet::new();
+                let mut accumulator = 0;
+                for element in shuffle.iter() {
+                    if !set.insert(*element) {
+                        return Err("use-list order indices are not a permutation".into());
+                    }
+                    accumulator += *element;
+                }
+                if num_uses != shuffle.len()
+                    || accumulator != ((num_uses - 1) * num_uses) >> 1
+                {
+                    return Err("use-list order indices are not a permutation".into());
+                }
+
+                // Apply the current ordering map onto the shuffle vector to get the final use-list sorting indices before shuffling.
+                current_order.iter().map(|(index, _)| shuffle[*index]).collect()
+            }
+        };
+
+        // The shuffle moves the `i`th use in upstream order to position `shuffle[i]`, which is converted to the reversed order of the arena.
+        let mut indices = vec![0; num_uses];
+        for (index, position) in shuffle.into_iter().enumerate() {
+            indices[num_uses - 1 - index] = num_uses - 1 - position;
+        }
+        self.arena_mut().shuffle_use_list(value, &indices)?;
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Value Processing
+    // ----------------------------------------------------------------------
+
+    /// Parse an operand reference using the given reader. Returns a forward reference if the value has not been defined yet.
+    fn parse_operand(&mut self, reader: &mut EncodingReader) -> ReadResult<Value> {
+        let values = &self.value_scopes.last().unwrap().values;
+        let index = reader.parse_var_int()?;
+        if let Some(value) = resolve_entry(reader, values, index, "value")? {
+            return Ok(*value);
+        }
+
+        // Create a new forward reference if necessary.
+        let value = self.create_forward_ref()?;
+        self.value_scopes.last_mut().unwrap().values[index as usize] = Some(value);
+        Ok(value)
+    }
+
+    /// Sequentially define the given value range.
+    fn define_values(&mut self, reader: &EncodingReader, new_values: Vec<Value>)
+        -> ReadResult
+    {
+        let value_scope = self.value_scopes.last_mut().unwrap();
+        let value_id = *value_scope.next_value_ids.last().unwrap();
+        let value_id_end = value_id + new_values.len();
+        if value_id_end > value_scope.values.len() {
+            return Err(reader.emit_error(format!(
+                "value index range was outside of the expected range for the parent region, got [{}, {}), but the maximum index was {}",
+                value_id, value_id_end, value_scope.values.len().wrapping_sub(1))));
+        }
+        *value_scope.next_value_ids.last_mut().unwrap() = value_id_end;
+
+        // Assign the values and update any forward references.
+        for (index, new_value) in new_values.into_iter().enumerate() {
+            let old_value = self.value_scopes.last_mut().unwrap()
+                .values[value_id + index].replace(new_value);
+
+            // Check to see if a definition for this value already exists. Given how we compute definition ids (incrementally as we parse), it can only be a forward reference.
+            if let Some(old_value @ Value::Output(output)) = old_value {
+                assert!(self.forward_ref_ops.remove(&output.owner()),
+                    "value index was already defined?");
+                let arena = self.arena_mut();
+                arena.replace_all_uses_with(old_value, new_value)?;
+                arena.erase(output.owner())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Create a value to use for a forward reference.
+    fn create_forward_ref(&mut self) -> ReadResult<Value> {
+        // Forward references are always created as operations, because we just need something with a def/use chain.
+        let name = self.config.context()
+            .operation_name("builtin.unrealized_conversion_cast");
+        let operation = Operation::new(
+            Location::unknown(),
+            name,
+            [Arc::new(NoneType) as Arc<dyn Type>],
+            Vec::new(),
+            DictionaryAttribute::default(),
+            &[]
+        );
+        let op = self.arena_mut().create_operation(operation, 0)?;
+        self.forward_ref_ops.insert(op);
+        Ok(Value::Output(Output::new(op, 0)))
+    }
+}
+
+// ----------------------------------------------------------------------
+// DialectReader
+// ----------------------------------------------------------------------
+
+/// This class is used to read the custom encodings of attributes and types on behalf of the bytecode interfaces of dialects.
+struct DialectReader<'r, 'a, 'c> {
+    bytecode_reader: &'r mut BytecodeReader<'a, 'c>,
+    reader: EncodingReader<'a>
+}
+
+impl<'r, 'a, 'c> DialectBytecodeReader for DialectReader<'r, 'a, 'c> {
+    fn emit_error(&self, message: &str) -> Box<dyn Error> {
+        self.reader.emit_error(message)
+    }
+
+    fn bytecode_version(&self) -> u64 {
+        self.bytecode_reader.version
+    }
+
+    fn dialect_version(&self, dialect: &str) -> Option<&dyn DialectVersion> {
+        self.bytecode_reader.dialects.iter()
+            .find(|entry| entry.name == dialect)
+            .and_then(|entry| entry.loaded_version.as_deref())
+    }
+
+    /* IR */
+
+    fn read_attribute(&mut self) -> ReadResult<Arc<dyn Attribute>> {
+        self.bytecode_reader.parse_attribute(&mut self.reader)
+    }
+
+    fn read_optional_attribute(&mut self) -> ReadResult<Option<Arc<dyn Attribute>>> {
+        self.bytecode_reader.parse_optional_attribute(&mut self.reader)
+    }
+
+    fn read_location(&mut self) -> ReadResult<Location> {
+        self.bytecode_reader.parse_location(&mut self.reader)
+    }
+
+    fn read_type(&mut self) -> ReadResult<Arc<dyn Type>> {
+        self.bytecode_reader.parse_type(&mut self.reader)
+    }
+
+    fn read_resource_handle(&mut self) -> ReadResult<AsmDialectResourceHandle> {
+        self.bytecode_reader.parse_resource_handle(&mut self.reader)
+    }
+
+    /* Primitives */
+
+    fn read_var_int(&mut self) -> ReadResult<u64> {
+        self.reader.parse_var_int()
+    }
+
+    fn read_signed_var_int(&mut self) -> ReadResult<i64> {
+        self.reader.parse_signed_var_int()
+    }
+
+    fn read_bool(&mut self) -> ReadResult<bool> {
+        Ok(self.reader.parse_byte()? != 0)
+    }
+
+    fn read_ap_int_with_known_width(&mut self, bit_width: u32) -> ReadResult<APInt> {
+        // Small values are encoded using a single byte.
+        if bit_width <= 8 {
+            let value = self.reader.parse_byte()?;
+            return Ok(APInt::new(bit_width, value as u64, false));
+        }
+
+        // Large values up to 64 bits are encoded using a single varint.
+        if bit_width <= 64 {
+            let value = self.reader.parse_signed_var_int()?;
+            return Ok(APInt::new(bit_width, value as u64, false));
+        }
+
+        // Otherwise, for really big values we encode the array of active words in the value.
+        let num_active_words = self.reader.parse_var_int()?;
+        let words = (0..num_active_words)
+            .map(|_| self.reader.parse_signed_var_int().map(|word| word as u64))
+            .collect::<ReadResult<Vec<u64>>>()?;
+        Ok(APInt::from_words(bit_width, &words))
+    }
+
+    fn read_string(&mut self) -> ReadResult<String> {
+        self.bytecode_reader.string_reader.parse_string(&mut self.reader)
+            .map(str::to_string)
+    }
+
+    fn read_blob(&mut self) -> ReadResult<Vec<u8>> {
+        let data_size = self.reader.parse_var_int()?;
+        Ok(self.reader.parse_bytes(data_size as usize)?.to_vec())
+    }
+}
+
+// ----------------------------------------------------------------------
+// Entry Points
+// ----------------------------------------------------------------------
 
 /**
 Returns true if the given buffer starts with the magic bytes that signal MLIR bytecode.
 */
-pub fn is_bytecode(buffer: MemoryBufferRef) -> bool {
-    buffer.buffer().starts_with(r"ML\xefR")
+pub fn is_bytecode(buffer: &[u8]) -> bool {
+    buffer.starts_with(&MAGIC)
 }
 
 /**
-Read the operations defined within the given memory buffer, containing MLIR bytecode, into the provided block.
+Read the operations defined within the given memory buffer, containing MLIR bytecode, into the provided block. `source_name` names the buffer in the locations of the emitted diagnostics.
 */
 pub fn read_bytecode_file(
-    buffer: MemoryBufferRef, block: Option<Block>, config: &ParserConfig
-) -> Result<(), Box<dyn std::error::Error>>
-{
-    Err(())
+    buffer: &[u8],
+    source_name: &str,
+    block: BlockId,
+    config: &mut ParserConfig
+) -> LogicalResult {
+    read_bytecode_buffer(buffer, None, source_name, block, config)
 }
 
 /**
-An overload with a source manager whose main file buffer is used for parsing. The lifetime of the source manager may be freely extended during parsing such that the source manager is not destroyed before the parsed IR.
+Read the operations defined within the given shared buffer, containing MLIR bytecode, into the provided block. Resource blobs reference the buffer instead of copying their data, so reading a memory mapped file keeps large resources out of memory until they are accessed.
 */
-pub fn read_bytecode_file(
-    source_manager: &Box<SourceManager>, block: Option<Block>, config: &ParserConfig
-) -> Result<(), Box<dyn std::error::Error>> {
-    Err(())
+pub fn read_bytecode_shared_file(
+    buffer: SharedResourceBuffer,
+    source_name: &str,
+    block: BlockId,
+    config: &mut ParserConfig
+) -> LogicalResult {
+    let data = (*buffer).as_ref();
+    read_bytecode_buffer(data, Some(buffer.clone()), source_name, block, config)
+}
+
+fn read_bytecode_buffer(
+    buffer: &[u8],
+    shared_buffer: Option<SharedResourceBuffer>,
+    source_name: &str,
+    block: BlockId,
+    config: &mut ParserConfig
+) -> LogicalResult {
+    let source_file_loc = Location::file_line_col(
+        StringAttribute::new(source_name), 0, 0);
+    if !is_bytecode(buffer) {
+        let message = "input buffer is not an MLIR bytecode file";
+        let diagnostic = Diagnostic::new(
+            source_file_loc, DiagnosticSeverity::Error, message);
+        config.context().diag_engine().emit(diagnostic.clone());
+        return Err(Box::new(diagnostic));
+    }
+    BytecodeReader::new(config, source_file_loc, shared_buffer).read(buffer, block)
 }
@@ -1,44 +1,1501 @@
-//!  This header defines interfaces to write MLIR bytecode files/streams.
-//! 
-//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Bytecode/BytecodeWriter.h>
-//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Bytecode/Writer/BytecodeWriter.cpp>
+/*!
+This header defines interfaces to write MLIR bytecode files/streams.
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Bytecode/BytecodeWriter.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Bytecode/Writer/BytecodeWriter.cpp>
+*/
 
 pub mod ir_numbering;
 
-use llvm::{
-    adt::{
-        cached_hash_string,
-        map_vector,
-        small_string
-    },
-    support::{
-        debug,
-        raw_ostream::RawOStream
-    }
-};
+use std::{collections::HashMap, io::Write, sync::Arc};
+
+use llvm::adt::ap_int::APInt;
 use crate::{
     bytecode::{
-        encoding,
-        implementation
+        encoding::{
+            ALIGNMENT_BYTE, DIALECT_VERSIONING, ELIDE_UNKNOWN_BLOCK_ARG_LOCATION, ID,
+            LAZY_LOADING, MAGIC, MIN_SUPPORTED_VERSION, NATIVE_PROPERTIES_ENCODING,
+            OpEncodingMask, USE_LIST_ORDERING, VERSION, use_id
+        },
+        implementation::{BytecodeDialectInterface, DialectBytecodeWriter}
     },
     ir::{
-        asm_state,
-        builtins::dialect,
-        operation::{
-            Operation,
-            // implementation
+        arena::{BlockId, IRArena, OpId, RegionId},
+        asm_state::{
+            AsmResourceBuilder, AsmResourceEntryKind, AsmResourcePrinter,
+            AsmResourceValue, FallbackAsmResourceMap
         },
-    }
+        attribute::Attribute,
+        location::Location,
+        operation::implementation::{AsmDialectResourceHandle, OpAsmDialectInterface},
+        r#type::Type,
+        value::{Input, Output, Value}
+    },
+    support::logical_result::LogicalResult
 };
+use self::ir_numbering::{AttributeOrLocation, IRNumberingState};
 
-/// This class contains the configuration used for the bytecode writer. It
-/// controls various aspects of bytecode generation, and contains all of the
-/// various bytecode writer hooks.
+// ----------------------------------------------------------------------
+// BytecodeWriterConfig
+// ----------------------------------------------------------------------
+
+/**
+This class contains the configuration used for the bytecode writer. It controls various aspects of bytecode generation, and contains all of the various bytecode writer hooks.
+*/
 pub struct BytecodeWriterConfig {
+    /// The producer of the bytecode, embedded in the header of the file.
+    producer: String,
+    /// The version of the bytecode to emit.
+    bytecode_version: u64,
+    /// The bytecode interfaces of the dialects whose attributes and types may be encoded in bytecode, keyed by dialect namespace. The builtin dialect is always available.
+    bytecode_interfaces: HashMap<String, Box<dyn BytecodeDialectInterface>>,
+    /// The OpAsm interfaces of the dialects whose resources may be emitted, keyed by dialect namespace.
+    dialect_interfaces: HashMap<String, Box<dyn OpAsmDialectInterface>>,
+    /// A collection of non-dialect resource printers.
+    external_resource_printers: Vec<AsmResourcePrinter>
 }
 
-/// Write the bytecode for the given operation to the provided output stream.
-/// For streams where it matters, the given stream should be in "binary" mode.
-fn write_bytecode_to_file(op: Option<Operation>, os: &mut RawOStream,
-    config: Option<&BytecodeWriterConfig>) {
+impl BytecodeWriterConfig {
+    /// `producer` is an optional string that can be used to identify the producer of the bytecode when reading. It has no functional effect on the bytecode serialization.
+    pub fn new(producer: impl Into<String>) -> Self {
+        Self {
+            producer: producer.into(),
+            bytecode_version: VERSION,
+            bytecode_interfaces: HashMap::new(),
+            dialect_interfaces: HashMap::new(),
+            external_resource_printers: Vec::new()
+        }
+    }
+
+    /// Return the producer of the bytecode.
+    pub fn producer(&self) -> &str {
+        &self.producer
+    }
+
+    /// Set the desired bytecode version to emit. Version 0 is readable by MLIR 16. Writing fails if the version is not in the range supported by this writer.
+    pub fn set_desired_bytecode_version(&mut self, version: u64) {
+        self.bytecode_version = version;
+    }
+
+    /// Get the set desired bytecode version to emit.
+    pub fn desired_bytecode_version(&self) -> u64 {
+        self.bytecode_version
+    }
+
+    /// Attach the bytecode interface of a dialect, used to encode the attributes and types of that dialect.
+    pub fn attach_bytecode_interface(
+        &mut self,
+        interface: Box<dyn BytecodeDialectInterface>
+    ) {
+        self.bytecode_interfaces.insert(interface.dialect().to_string(), interface);
+    }
+
+    /// Return the bytecode interface attached for the dialect `namespace`, if any.
+    pub fn bytecode_interface(&self, namespace: &str)
+        -> Option<&dyn BytecodeDialectInterface>
+    {
+        self.bytecode_interfaces.get(namespace).map(|interface| interface.as_ref())
+    }
+
+    /// Attach the OpAsm interface of a dialect, used to emit the resources of that dialect referenced within the IR.
+    pub fn attach_dialect_interface(
+        &mut self,
+        interface: Box<dyn OpAsmDialectInterface>
+    ) {
+        self.dialect_interfaces.insert(interface.dialect().to_string(), interface);
+    }
+
+    /// Return the OpAsm interface attached for the dialect `namespace`, if any.
+    pub fn dialect_interface(&self, namespace: &str)
+        -> Option<&dyn OpAsmDialectInterface>
+    {
+        self.dialect_interfaces.get(namespace).map(|interface| interface.as_ref())
+    }
+
+    /// Attach the given resource printer to the writer configuration.
+    pub fn attach_resource_printer(&mut self, printer: AsmResourcePrinter) {
+        self.external_resource_printers.push(printer);
+    }
+
+    /// Attach resource printers to the AsmState for the fallback resources in the given map.
+    pub fn attach_fallback_resource_printer(&mut self, map: &FallbackAsmResourceMap) {
+        for printer in map.printers() {
+            self.attach_resource_printer(printer);
+        }
+    }
+}
+
+impl Default for BytecodeWriterConfig {
+    fn default() -> Self {
+        Self::new("MLIR18.0.0")
+    }
+}
+
+// ----------------------------------------------------------------------
+// EncodingEmitter
+// ----------------------------------------------------------------------
+
+/// This class functions as the underlying encoding emitter for the bytecode writer. Nested sections are encoded into their own emitters, which are merged into their parent along with their required alignment.
+#[derive(Default)]
+struct EncodingEmitter {
+    /// The current result buffer.
+    result: Vec<u8>,
+    /// The highest required alignment for the start of this section.
+    required_alignment: usize
+}
+
+impl EncodingEmitter {
+    /// Return the current size of the encoded buffer.
+    fn size(&self) -> usize {
+        self.result.len()
+    }
+
+    /// Write the current contents to the provided stream.
+    fn write_to(&self, os: &mut impl Write) -> std::io::Result<()> {
+        os.write_all(&self.result)
+    }
+
+    /// Discard any data emitted past `size`.
+    fn truncate(&mut self, size: usize) {
+        self.result.truncate(size);
+    }
+
+    /// Backpatch a byte in the result buffer at the given offset.
+    fn patch_byte(&mut self, offset: usize, value: u8) {
+        self.result[offset] = value;
+    }
+
+    /* Emission */
+
+    /// Emit the provided blob of data, which is owned by the caller and is guaranteed to not die before the end of the bytecode process.
+    fn emit_owned_blob(&mut self, data: &[u8]) {
+        self.emit_bytes(data);
+    }
+
+    /// Emit the provided blob of data that has the given alignment, which is owned by the caller and is guaranteed to not die before the end of the bytecode process. The alignment value is also encoded, making it available on load.
+    fn emit_owned_blob_and_alignment(&mut self, data: &[u8], alignment: usize) {
+        self.emit_var_int(alignment as u64);
+        self.emit_var_int(data.len() as u64);
+
+        self.align_to(alignment);
+        self.emit_owned_blob(data);
+    }
+
+    /// Align the emitter to the given alignment.
+    fn align_to(&mut self, alignment: usize) {
+        if alignment < 2 {
+            return;
+        }
+        assert!(alignment.is_power_of_two(), "expected valid alignment");
+
+        // Check to see if we need to emit any padding bytes to meet the desired alignment.
+        let padding_size = self.size().next_multiple_of(alignment) - self.size();
+        self.result.resize(self.size() + padding_size, ALIGNMENT_BYTE);
+
+        // Keep track of the maximum required alignment.
+        self.required_alignment = self.required_alignment.max(alignment);
+    }
+
+    /* Integer Emission */
+
+    /// Emit a single byte.
+    fn emit_byte(&mut self, byte: u8) {
+        self.result.push(byte);
+    }
+
+    /// Emit a range of bytes.
+    fn emit_bytes(&mut self, bytes: &[u8]) {
+        self.result.extend_from_slice(bytes);
+    }
+
+    /**
+    Emit a variable length integer. The first encoded byte contains a prefix in the low bits indicating the encoded length of the value. This length prefix is a bit sequence of '0's followed by a '1'. The number of '0' bits indicate the number of _additional_ bytes (not including the prefix byte). All remaining bits in the first byte, along with all of the bits in additional bytes, provide the value of the integer encoded in little-endian order.
+    */
+    fn emit_var_int(&mut self, value: u64) {
+        // In the most common case, the value can be represented in a single byte. Given how hot this case is, explicitly handle that here.
+        if (value >> 7) == 0 {
+            return self.emit_byte(((value << 1) | 0x1) as u8);
+        }
+        self.emit_multi_byte_var_int(value);
+    }
+
+    /// Emit a signed variable length integer. Signed varints are encoded using a varint with zigzag encoding, meaning that we use the low bit of the value to indicate the sign of the value. This allows for more efficient encoding of negative values by limiting the number of active bits
+    fn emit_signed_var_int(&mut self, value: u64) {
+        self.emit_var_int((value << 1) ^ (((value as i64) >> 63) as u64));
+    }
+
+    /// Emit a variable length integer whose low bit is used to encode the provided flag, i.e. encoded as: (value << 1) | (flag ? 1 : 0).
+    fn emit_var_int_with_flag(&mut self, value: u64, flag: bool) {
+        self.emit_var_int((value << 1) | flag as u64);
+    }
+
+    /* String Emission */
+
+    /// Emit the given string as a nul terminated string.
+    fn emit_nul_terminated_string(&mut self, string: &str) {
+        self.emit_bytes(string.as_bytes());
+        self.emit_byte(0);
+    }
+
+    /// Emit the given string without a nul terminator.
+    fn emit_string(&mut self, string: &str) {
+        self.emit_bytes(string.as_bytes());
+    }
+
+    /* Section Emission */
+
+    /// Emit a nested section of the given code, whose contents are encoded in the provided emitter.
+    fn emit_section(&mut self, code: ID, emitter: EncodingEmitter) {
+        // Emit the section code and length. The high bit of the code is used to indicate whether the section alignment is present, so save an offset to it.
+        let code_offset = self.size();
+        self.emit_byte(code as u8);
+        self.emit_var_int(emitter.size() as u64);
+
+        // Integrate the alignment of the section into this emitter if necessary.
+        let emitter_align = emitter.required_alignment;
+        if emitter_align > 1 {
+            if self.size() & (emitter_align - 1) != 0 {
+                self.emit_var_int(emitter_align as u64);
+                self.align_to(emitter_align);
+
+                // Indicate that we needed to align the section, the high bit of the code field is used for this.
+                self.result[code_offset] |= 0b10000000;
+            } else {
+                // Otherwise, if we happen to be at a compatible offset, we just remember that we need this alignment.
+                self.required_alignment = self.required_alignment.max(emitter_align);
+            }
+        }
+
+        // Merge the provided section body into ours.
+        self.result.extend(emitter.result);
+    }
+
+    /// Emit a variable length integer whose value requires more than one byte.
+    fn emit_multi_byte_var_int(&mut self, value: u64) {
+        // Compute the number of bytes needed to encode the value. Each byte can hold up to 7-bits of data. We only check up to the number of bits we can encode in the first byte (8).
+        let mut it = value >> 7;
+        for num_bytes in 2..9 {
+            it >>= 7;
+            if it == 0 {
+                let encoded_value = ((value << 1) | 0x1) << (num_bytes - 1);
+                self.emit_bytes(&encoded_value.to_le_bytes()[..num_bytes]);
+                return;
+            }
+        }
+
+        // If the value is too large to encode in a single byte, emit a special all zero marker byte and splat the value directly.
+        self.emit_byte(0);
+        self.emit_bytes(&value.to_le_bytes());
+    }
+}
+
+// ----------------------------------------------------------------------
+// StringSectionBuilder
+// ----------------------------------------------------------------------
+
+/// This class is used to simplify the process of emitting the string section.
+#[derive(Default)]
+struct StringSectionBuilder {
+    /// The strings in the order they were inserted.
+    strings: Vec<String>,
+    /// A mapping from string to the index of the string in `strings`.
+    indices: HashMap<String, usize>
+}
+
+impl StringSectionBuilder {
+    /// Add the given string to the string section, and return the index of the string within the section.
+    fn insert(&mut self, string: &str) -> u64 {
+        if let Some(index) = self.indices.get(string) {
+            return *index as u64;
+        }
+        let index = self.strings.len();
+        self.strings.push(string.to_string());
+        self.indices.insert(string.to_string(), index);
+        index as u64
+    }
+
+    /// Write the current set of strings to the given emitter.
+    fn write(&self, emitter: &mut EncodingEmitter) {
+        emitter.emit_var_int(self.strings.len() as u64);
+
+        // Emit the sizes in reverse order, so that we don't need to backpatch an offset to the string data or have a separate section.
+        for string in self.strings.iter().rev() {
+            emitter.emit_var_int(string.len() as u64 + 1);
+        }
+        // Emit the string data itself.
+        for string in self.strings.iter() {
+            emitter.emit_nul_terminated_string(string);
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// PropertiesSectionBuilder
+// ----------------------------------------------------------------------
+
+/// This class is used to simplify the process of emitting the properties section. Identical properties are only emitted once.
+#[derive(Default)]
+struct PropertiesSectionBuilder {
+    /// The encoded properties prefixed with their size, in the order they were inserted.
+    properties: Vec<Vec<u8>>,
+    /// A mapping from encoded properties to their index in `properties`.
+    indices: HashMap<Vec<u8>, usize>
+}
+
+impl PropertiesSectionBuilder {
+    /// Add the given encoded properties to the properties section, and return their index within the section.
+    fn insert(&mut self, raw_properties: &[u8]) -> u64 {
+        // Prefix the properties with their size, which is how they are stored within the section.
+        let mut emitter = EncodingEmitter::default();
+        emitter.emit_var_int(raw_properties.len() as u64);
+        emitter.emit_bytes(raw_properties);
+        if let Some(index) = self.indices.get(&emitter.result) {
+            return *index as u64;
+        }
+        let index = self.properties.len();
+        self.indices.insert(emitter.result.clone(), index);
+        self.properties.push(emitter.result);
+        index as u64
+    }
+
+    /// Return true if no properties were emitted.
+    fn is_empty(&self) -> bool {
+        self.properties.is_empty()
+    }
+
+    /// Write the current set of properties to the given emitter.
+    fn write(&self, emitter: &mut EncodingEmitter) {
+        emitter.emit_var_int(self.properties.len() as u64);
+        for properties in self.properties.iter() {
+            emitter.emit_bytes(properties);
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// DialectWriter
+// ----------------------------------------------------------------------
+
+/// The writer handed to the bytecode interfaces of dialects to encode their attributes and types.
+struct DialectWriter<'a, 'c> {
+    bytecode_version: u64,
+    emitter: &'a mut EncodingEmitter,
+    numbering_state: &'a IRNumberingState<'c>,
+    string_section: &'a mut StringSectionBuilder
+}
+
+impl<'a, 'c> DialectBytecodeWriter for DialectWriter<'a, 'c> {
+    fn bytecode_version(&self) -> u64 {
+        self.bytecode_version
+    }
+
+    /* IR */
+
+    fn write_attribute(&mut self, attribute: &Arc<dyn Attribute>) {
+        self.emitter.emit_var_int(
+            self.numbering_state.attribute_number(attribute.as_ref()) as u64);
+    }
+
+    fn write_optional_attribute(&mut self, attribute: Option<&Arc<dyn Attribute>>) {
+        match attribute {
+            Some(attribute) => self.emitter.emit_var_int_with_flag(
+                self.numbering_state.attribute_number(attribute.as_ref()) as u64, true),
+            None => self.emitter.emit_var_int(0)
+        }
+    }
+
+    fn write_location(&mut self, location: &Location) {
+        self.emitter.emit_var_int(
+            self.numbering_state.location_number(location) as u64);
+    }
+
+    fn write_type(&mut self, r#type: &Arc<dyn Type>) {
+        self.emitter.emit_var_int(
+            self.numbering_state.type_number(r#type.as_ref()) as u64);
+    }
+
+    fn write_resource_handle(&mut self, resource: &AsmDialectResourceHandle) {
+        self.emitter.emit_var_int(
+            self.numbering_state.resource_number(resource) as u64);
+    }
+
+    /* Primitives */
+
+    fn write_var_int(&mut self, value: u64) {
+        self.emitter.emit_var_int(value);
+    }
+
+    fn write_signed_var_int(&mut self, value: i64) {
+        self.emitter.emit_signed_var_int(value as u64);
+    }
+
+    fn write_owned_bool(&mut self, value: bool) {
+        self.emitter.emit_byte(value as u8);
+    }
+
+    fn write_ap_int_with_known_width(&mut self, value: &APInt) {
+        let bit_width = value.width();
+
+        // If the value is a single byte, just emit it directly without going through a varint.
+        if bit_width <= 8 {
+            return self.emitter.emit_byte(value.words()[0] as u8);
+        }
+
+        // If the value fits within a single varint, emit it directly.
+        if bit_width <= 64 {
+            return self.emitter.emit_signed_var_int(value.words()[0]);
+        }
+
+        // Otherwise, we need to encode a variable number of active words. We use active words instead of the number of total words under the observation that smaller values will be more common.
+        let num_active_words = value.active_bits().div_ceil(64).max(1) as usize;
+        self.emitter.emit_var_int(num_active_words as u64);
+
+        for word in &value.words()[..num_active_words] {
+            self.emitter.emit_signed_var_int(*word);
+        }
+    }
+
+    fn write_owned_string(&mut self, string: &str) {
+        self.emitter.emit_var_int(self.string_section.insert(string));
+    }
+
+    fn write_owned_blob(&mut self, blob: &[u8]) {
+        self.emitter.emit_var_int(blob.len() as u64);
+        self.emitter.emit_owned_blob(blob);
+    }
+}
+
+// ----------------------------------------------------------------------
+// Bytecode Writer
+// ----------------------------------------------------------------------
+
+struct BytecodeWriter<'a> {
+    arena: &'a IRArena,
+    /// The builder used for the string section.
+    string_section: StringSectionBuilder,
+    /// The builder used for the properties section.
+    properties_section: PropertiesSectionBuilder,
+    /// The IR numbering state generated for the root operation.
+    numbering_state: IRNumberingState<'a>,
+    /// Configuration dictating bytecode emission.
+    config: &'a BytecodeWriterConfig
+}
+
+impl<'a> BytecodeWriter<'a> {
+    fn write(&mut self, root_op: OpId, os: &mut impl Write) -> LogicalResult {
+        let mut emitter = EncodingEmitter::default();
+
+        // Emit the bytecode file header. This is how we identify the output as a bytecode file.
+        emitter.emit_bytes(&MAGIC);
+
+        // Emit the bytecode version.
+        emitter.emit_var_int(self.config.desired_bytecode_version());
+
+        // Emit the producer.
+        emitter.emit_nul_terminated_string(self.config.producer());
+
+        // Emit the dialect section.
+        self.write_dialect_section(&mut emitter);
+
+        // Emit the attributes and types section.
+        self.write_attr_type_section(&mut emitter);
+
+        // Emit the IR section.
+        self.write_ir_section(&mut emitter, root_op)?;
+
+        // Emit the resources section.
+        self.write_resource_section(root_op, &mut emitter)?;
+
+        // Emit the string section.
+        self.write_string_section(&mut emitter);
+
+        // Emit the properties section.
+        if self.config.desired_bytecode_version() >= NATIVE_PROPERTIES_ENCODING {
+            self.write_properties_section(&mut emitter);
+        } else if !self.properties_section.is_empty() {
+            return Err("unexpected properties emitted incompatible with bytecode <5".into());
+        }
+
+        // Write the generated bytecode to the provided output stream.
+        emitter.write_to(os)?;
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Dialects
+    // ----------------------------------------------------------------------
+
+    fn write_dialect_section(&mut self, emitter: &mut EncodingEmitter) {
+        let mut dialect_emitter = EncodingEmitter::default();
+
+        // Emit the referenced dialects.
+        let dialects = self.numbering_state.dialects();
+        dialect_emitter.emit_var_int(dialects.len() as u64);
+        for dialect in dialects {
+            let name_id = self.string_section.insert(&dialect.name);
+
+            // Before `DIALECT_VERSIONING`, there was no way to encode the version of a dialect.
+            if self.config.desired_bytecode_version() < DIALECT_VERSIONING {
+                dialect_emitter.emit_var_int(name_id);
+                continue;
+            }
+
+            // Try writing the version to the version emitter.
+            let mut version_emitter = EncodingEmitter::default();
+            if let Some(interface) = dialect.interface {
+                interface.write_version(&mut DialectWriter {
+                    bytecode_version: self.config.desired_bytecode_version(),
+                    emitter: &mut version_emitter,
+                    numbering_state: &self.numbering_state,
+                    string_section: &mut self.string_section
+                });
+            }
+
+            // If the version emitter is empty, the version is not available. This is encoded in the low bit of the name ID, so the version section is only written when there is a version.
+            let version_available = version_emitter.size() > 0;
+            dialect_emitter.emit_var_int_with_flag(name_id, version_available);
+            if version_available {
+                dialect_emitter.emit_section(ID::DialectVersions, version_emitter);
+            }
+        }
+
+        // Emit the referenced operation names grouped by dialect. From `ELIDE_UNKNOWN_BLOCK_ARG_LOCATION`, they are preceded by their number, and from `NATIVE_PROPERTIES_ENCODING` each name is flagged with whether it is registered, which tells how its properties are encoded.
+        let version = self.config.desired_bytecode_version();
+        if version >= ELIDE_UNKNOWN_BLOCK_ARG_LOCATION {
+            dialect_emitter.emit_var_int(self.numbering_state.op_names().len() as u64);
+        }
+        let string_section = &mut self.string_section;
+        write_dialect_grouping(
+            &mut dialect_emitter,
+            self.numbering_state.op_names(),
+            |name| name.dialect,
+            |emitter, name| {
+                let string_id = string_section.insert(name.name.strip_dialect());
+                if version < NATIVE_PROPERTIES_ENCODING {
+                    emitter.emit_var_int(string_id);
+                } else {
+                    emitter.emit_var_int_with_flag(string_id, name.name.is_registered());
+                }
+            });
+
+        emitter.emit_section(ID::Dialect, dialect_emitter);
+    }
+
+    // ----------------------------------------------------------------------
+    // Attributes and Types
+    // ----------------------------------------------------------------------
+
+    fn write_attr_type_section(&mut self, emitter: &mut EncodingEmitter) {
+        let mut attr_type_emitter = EncodingEmitter::default();
+        let mut offset_emitter = EncodingEmitter::default();
+        offset_emitter.emit_var_int(self.numbering_state.attributes().len() as u64);
+        offset_emitter.emit_var_int(self.numbering_state.types().len() as u64);
+
+        // A functor used to emit an attribute or type entry.
+        let config = self.config;
+        let numbering_state = &self.numbering_state;
+        let string_section = &mut self.string_section;
+        let mut prev_offset = 0;
+        let mut emit_attr_or_type = |
+            offset_emitter: &mut EncodingEmitter,
+            dialect: usize,
+            write: &dyn Fn(&dyn BytecodeDialectInterface, &mut dyn DialectBytecodeWriter) -> LogicalResult,
+            text: &dyn Fn() -> String
+        | {
+            let entry_offset = attr_type_emitter.size();
+
+            // First, try to emit this entry using the dialect bytecode interface.
+            let mut has_custom_encoding = false;
+            if let Some(interface) = numbering_state.dialects()[dialect].interface {
+                // The writer used when emitting using a custom bytecode encoding.
+                let mut dialect_writer = DialectWriter {
+                    bytecode_version: config.desired_bytecode_version(),
+                    emitter: &mut attr_type_emitter,
+                    numbering_state,
+                    string_section: &mut *string_section
+                };
+                has_custom_encoding = write(interface, &mut dialect_writer).is_ok();
+            }
+
+            // If the entry was not emitted using the dialect interface, emit it using the textual format. Discard anything the dialect emitted before giving up on the entry.
+            if !has_custom_encoding {
+                attr_type_emitter.truncate(entry_offset);
+                attr_type_emitter.emit_nul_terminated_string(&text());
+            }
+
+            // Record the offset of this entry.
+            let cur_offset = attr_type_emitter.size();
+            offset_emitter.emit_var_int_with_flag(
+                (cur_offset - prev_offset) as u64, has_custom_encoding);
+            prev_offset = cur_offset;
+        };
+
+        // Emit the attribute and type entries for each dialect.
+        write_dialect_grouping(
+            &mut offset_emitter,
+            numbering_state.attributes(),
+            |entry| entry.dialect,
+            |offset_emitter, entry| match &entry.value {
+                AttributeOrLocation::Attribute(attribute) => emit_attr_or_type(
+                    offset_emitter,
+                    entry.dialect,
+                    &|interface, writer| interface.write_attribute(attribute.as_ref(), writer),
+                    &|| attribute.to_string()),
+                AttributeOrLocation::Location(location) => emit_attr_or_type(
+                    offset_emitter,
+                    entry.dialect,
+                    &|interface, writer| interface.write_location(location, writer),
+                    &|| format!("loc({})", location))
+            });
+        write_dialect_grouping(
+            &mut offset_emitter,
+            numbering_state.types(),
+            |entry| entry.dialect,
+            |offset_emitter, entry| emit_attr_or_type(
+                offset_emitter,
+                entry.dialect,
+                &|interface, writer| interface.write_type(entry.value.as_ref(), writer),
+                &|| entry.value.to_string()));
+
+        // Emit the sections to the stream.
+        emitter.emit_section(ID::AttrTypeOffset, offset_emitter);
+        emitter.emit_section(ID::AttrType, attr_type_emitter);
+    }
+
+    // ----------------------------------------------------------------------
+    // Operations
+    // ----------------------------------------------------------------------
+
+    fn write_block(&mut self, emitter: &mut EncodingEmitter, block: BlockId)
+        -> LogicalResult
+    {
+        let arena = self.arena;
+        let block_ref = arena.block(block)?;
+        let has_inputs = block_ref.num_inputs() != 0;
+
+        // Emit the number of operations in this block, and if it has inputs. We use the low bit of the operation count to indicate if the block has inputs.
+        let num_ops = self.numbering_state.operation_count(block);
+        emitter.emit_var_int_with_flag(num_ops as u64, has_inputs);
+
+        // Emit the inputs of the block.
+        if has_inputs {
+            let version = self.config.desired_bytecode_version();
+            emitter.emit_var_int(block_ref.num_inputs() as u64);
+            for index in 0..block_ref.num_inputs() {
+                let input = block_ref.input_impl(index);
+                let type_number
+                    = self.numbering_state.type_number(input.value.r#type.as_ref()) as u64;
+                let location_number
+                    = self.numbering_state.location_number(&input.location) as u64;
+
+                // From `ELIDE_UNKNOWN_BLOCK_ARG_LOCATION`, the low bit of the type indicates whether the location is present, which it isn't when unknown.
+                if version >= ELIDE_UNKNOWN_BLOCK_ARG_LOCATION {
+                    let has_location = !input.location.is_unknown();
+                    emitter.emit_var_int_with_flag(type_number, has_location);
+                    if has_location {
+                        emitter.emit_var_int(location_number);
+                    }
+                } else {
+                    emitter.emit_var_int(type_number);
+                    emitter.emit_var_int(location_number);
+                }
+            }
+
+            // Emit the use-list orders of the inputs, behind a mask that is patched in when there are any.
+            if version >= USE_LIST_ORDERING {
+                let mask_offset = emitter.size();
+                let mut encoding_mask = 0;
+                emitter.emit_byte(0);
+                let inputs: Vec<Value> = (0..block_ref.num_inputs())
+                    .map(|index| Value::Input(Input::new(block, index)))
+                    .collect();
+                self.write_use_list_orders(emitter, &mut encoding_mask, &inputs)?;
+                if encoding_mask != 0 {
+                    emitter.patch_byte(mask_offset, encoding_mask);
+                }
+            }
+        }
+
+        // Emit the operations within the block.
+        for op in block_ref.operations() {
+            self.write_op(emitter, *op)?;
+        }
+        Ok(())
+    }
+
+    fn write_op(&mut self, emitter: &mut EncodingEmitter, op: OpId) -> LogicalResult {
+        let arena = self.arena;
+        let version = self.config.desired_bytecode_version();
+        let operation = arena.op(op)?;
+        emitter.emit_var_int(self.numbering_state.op_name_number(operation.name()) as u64);
+
+        // Emit a mask for the operation components. We need to fill this in later (when we actually know what needs to be emitted), so emit a placeholder for now.
+        let mask_offset = emitter.size();
+        let mut op_encoding_mask = 0;
+        emitter.emit_byte(0);
+
+        // Emit the location for this operation.
+        emitter.emit_var_int(
+            self.numbering_state.location_number(&operation.location()) as u64);
+
+        // Emit the attributes of this operation. The attributes encoded as properties are left out.
+        let attributes = self.numbering_state.attr_dictionary(operation);
+        if !attributes.is_empty() {
+            op_encoding_mask |= OpEncodingMask::HAS_ATTRS;
+            emitter.emit_var_int(
+                self.numbering_state.attribute_number(&attributes) as u64);
+        }
+
+        // Emit the properties of this operation, which are encoded in the properties section and referenced by index.
+        if let Some((interface, _)) = self.numbering_state.properties(operation) {
+            let mut properties_emitter = EncodingEmitter::default();
+            interface.write_properties(operation, &mut DialectWriter {
+                bytecode_version: version,
+                emitter: &mut properties_emitter,
+                numbering_state: &self.numbering_state,
+                string_section: &mut self.string_section
+            });
+            op_encoding_mask |= OpEncodingMask::HAS_PROPERTIES;
+            emitter.emit_var_int(self.properties_section.insert(&properties_emitter.result));
+        }
+
+        // Emit the result types of the operation.
+        let num_outputs = operation.num_outputs();
+        if num_outputs != 0 {
+            op_encoding_mask |= OpEncodingMask::HAS_RESULTS;
+            emitter.emit_var_int(num_outputs as u64);
+            for r#type in operation.output_types() {
+                emitter.emit_var_int(
+                    self.numbering_state.type_number(r#type.as_ref()) as u64);
+            }
+        }
+
+        // Emit the operands of the operation.
+        let inputs = operation.inputs();
+        if !inputs.is_empty() {
+            op_encoding_mask |= OpEncodingMask::HAS_OPERANDS;
+            emitter.emit_var_int(inputs.len() as u64);
+            for input in inputs {
+                emitter.emit_var_int(self.numbering_state.value_number(*input) as u64);
+            }
+        }
+
+        // Emit the successors of the operation.
+        let successors = operation.successors();
+        if !successors.is_empty() {
+            op_encoding_mask |= OpEncodingMask::HAS_SUCCESSORS;
+            emitter.emit_var_int(successors.len() as u64);
+            for successor in successors {
+                emitter.emit_var_int(self.numbering_state.block_number(*successor) as u64);
+            }
+        }
+
+        // Emit the use-list orders to bytecode, so we can reconstruct the same order at parsing.
+        if version >= USE_LIST_ORDERING {
+            let outputs: Vec<Value> = (0..num_outputs)
+                .map(|index| Value::Output(Output::new(op, index)))
+                .collect();
+            self.write_use_list_orders(emitter, &mut op_encoding_mask, &outputs)?;
+        }
+
+        // Check for regions.
+        let regions = operation.regions();
+        if !regions.is_empty() {
+            op_encoding_mask |= OpEncodingMask::HAS_INLINE_REGIONS;
+        }
+
+        // Update the mask for the operation.
+        emitter.patch_byte(mask_offset, op_encoding_mask);
+
+        // With the mask emitted, we can now emit the regions of the operation. We do this after mask emission to avoid offset complications that may arise by emitting the regions first (e.g. if the regions are huge, backpatching the op encoding mask is more annoying).
+        if !regions.is_empty() {
+            let is_isolated_from_above = self.numbering_state.is_isolated_from_above(op);
+            emitter.emit_var_int_with_flag(regions.len() as u64, is_isolated_from_above);
+
+            // If the regions are isolated from above, they are encoded in their own IR section from `LAZY_LOADING`, which allows the reader to load them lazily.
+            if is_isolated_from_above && version >= LAZY_LOADING {
+                let mut region_emitter = EncodingEmitter::default();
+                for region in regions {
+                    self.write_region(&mut region_emitter, *region)?;
+                }
+                emitter.emit_section(ID::IR, region_emitter);
+            } else {
+                for region in regions {
+                    self.write_region(emitter, *region)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /**
+    Emit the use-list orders of those of `values` whose uses would not be read back in the same order, setting `HAS_USE_LIST_ORDERS` in `op_encoding_mask` if any are emitted.
+
+    Upstream, a use is pushed on the front of the use-list of its value when it is created, so reading bytecode yields uses in descending order of their ID. Uses are appended here instead, so the use-list upstream sees is the reverse of ours, and that is the one encoded.
+    */
+    fn write_use_list_orders(
+        &self,
+        emitter: &mut EncodingEmitter,
+        op_encoding_mask: &mut u8,
+        values: &[Value]
+    ) -> LogicalResult {
+        // Loop over the values and store the use-list order per value index.
+        let mut map = Vec::new();
+        for (index, value) in values.iter().enumerate() {
+            // No need to store a custom use-list order if the value does not have multiple uses.
+            let uses = self.arena.uses(*value)?;
+            if uses.len() < 2 {
+                continue;
+            }
+
+            // Assemble the IDs of the uses in the order of the use-list. Uses by operations that are not being written can't be read back, so they are left as they are.
+            let Some(use_ids) = uses.iter().rev()
+                .map(|r#use| self.numbering_state.operation_number(r#use.owner)
+                    .map(|owner_id| use_id(owner_id, r#use.index)))
+                .collect::<Option<Vec<u64>>>() else {
+                continue;
+            };
+
+            // The use-list order achieved when building the IR at parsing always pushes new uses on front. Hence, if the order by unique ID is monotonically decreasing, a roundtrip to bytecode preserves such order.
+            if use_ids.windows(2).all(|pair| pair[0] > pair[1]) {
+                continue;
+            }
+
+            // Sort the use indices by the unique ID indices in descending order.
+            let mut use_list_order: Vec<usize> = (0..use_ids.len()).collect();
+            use_list_order.sort_by(|lhs, rhs| use_ids[*rhs].cmp(&use_ids[*lhs]));
+            map.push((index, use_list_order));
+        }
+        if map.is_empty() {
+            return Ok(());
+        }
+        *op_encoding_mask |= OpEncodingMask::HAS_USE_LIST_ORDERS;
+        write_use_list_order_map(emitter, values.len(), map);
+        Ok(())
+    }
+
+    fn write_region(&mut self, emitter: &mut EncodingEmitter, region: RegionId)
+        -> LogicalResult
+    {
+        // If the region is empty, we only need to emit the number of blocks (which is zero).
+        let arena = self.arena;
+        let blocks = arena.region(region)?.blocks();
+        if blocks.is_empty() {
+            emitter.emit_var_int(/* num_blocks */ 0);
+            return Ok(());
+        }
+
+        // Emit the number of blocks and values within the region.
+        let (num_blocks, num_values) = self.numbering_state.block_value_count(region);
+        emitter.emit_var_int(num_blocks as u64);
+        emitter.emit_var_int(num_values as u64);
+
+        // Emit the blocks within the region.
+        for block in blocks {
+            self.write_block(emitter, *block)?;
+        }
+        Ok(())
+    }
+
+    fn write_ir_section(&mut self, emitter: &mut EncodingEmitter, op: OpId)
+        -> LogicalResult
+    {
+        let mut ir_emitter = EncodingEmitter::default();
+
+        // Write the IR section the same way as a block with no arguments. Note that the low-bit of the operation count for a block is used to indicate if the block has arguments, which in this case is always false.
+        ir_emitter.emit_var_int_with_flag(/* num_ops */ 1, /* has_inputs */ false);
+
+        // Emit the operations.
+        self.write_op(&mut ir_emitter, op)?;
+
+        emitter.emit_section(ID::IR, ir_emitter);
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Resources
+    // ----------------------------------------------------------------------
+
+    fn write_resource_section(&mut self, op: OpId, emitter: &mut EncodingEmitter)
+        -> LogicalResult
+    {
+        let root_op = self.arena.op(op)?;
+        let mut resource_emitter = ResourceEmitter {
+            resource_emitter: EncodingEmitter::default(),
+            offset_emitter: EncodingEmitter::default(),
+            string_section: &mut self.string_section,
+            prev_offset: 0,
+            cur_resource_entries: Vec::new()
+        };
+
+        // Emit the external resource entries.
+        resource_emitter.offset_emitter.emit_var_int(
+            self.config.external_resource_printers.len() as u64);
+        for printer in self.config.external_resource_printers.iter() {
+            let mut builder = AsmResourceBuilder::new();
+            printer.build_resources(root_op, &mut builder);
+            resource_emitter.build_resources(&builder);
+            let key = resource_emitter.string_section.insert(printer.name());
+            resource_emitter.emit_resource_group(key);
+        }
+
+        // Emit the dialect resource entries.
+        for dialect in self.numbering_state.dialects() {
+            let Some(asm_interface) = dialect.asm_interface else {
+                continue;
+            };
+            let mut builder = AsmResourceBuilder::new();
+            asm_interface.build_resources(root_op, &dialect.resources, &mut builder);
+            resource_emitter.build_resources(&builder);
+
+            // Emit the declaration resources for this dialect, these didn't get emitted by the interface. These resources don't have data attached, so just use a "blob" kind as a placeholder.
+            for resource in dialect.resource_map.iter() {
+                if resource.is_declaration {
+                    resource_emitter.append_resource_offset(
+                        &resource.key, AsmResourceEntryKind::Blob);
+                }
+            }
+
+            // Emit the resource group for this dialect.
+            if !resource_emitter.cur_resource_entries.is_empty() {
+                resource_emitter.emit_resource_group(dialect.number as u64);
+            }
+        }
+
+        emitter.emit_section(ID::ResourceOffset, resource_emitter.offset_emitter);
+        emitter.emit_section(ID::Resource, resource_emitter.resource_emitter);
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Strings
+    // ----------------------------------------------------------------------
+
+    fn write_string_section(&self, emitter: &mut EncodingEmitter) {
+        let mut string_emitter = EncodingEmitter::default();
+        self.string_section.write(&mut string_emitter);
+        emitter.emit_section(ID::String, string_emitter);
+    }
+
+    // ----------------------------------------------------------------------
+    // Properties
+    // ----------------------------------------------------------------------
+
+    fn write_properties_section(&self, emitter: &mut EncodingEmitter) {
+        let mut properties_emitter = EncodingEmitter::default();
+        self.properties_section.write(&mut properties_emitter);
+        emitter.emit_section(ID::Properties, properties_emitter);
+    }
+}
+
+/**
+Order the entries of `map` by their key the way upstream iterates a `DenseMap<unsigned, ...>`, which is how it orders the use-list orders of the values of an operation. The table starts with 64 buckets and doubles when it would become three quarters full, keys are hashed as `key * 37`, and collisions are resolved by quadratic probing.
+*/
+fn dense_map_order<T>(map: Vec<(usize, T)>) -> Vec<(usize, T)> {
+    fn insert<T>(buckets: &mut [Option<(usize, T)>], entry: (usize, T)) {
+        let mask = buckets.len() - 1;
+        let mut bucket = (entry.0 as u32).wrapping_mul(37) as usize & mask;
+        let mut probe = 1;
+        while buckets[bucket].is_some() {
+            bucket = (bucket + probe) & mask;
+            probe += 1;
+        }
+        buckets[bucket] = Some(entry);
+    }
+
+    let mut buckets: Vec<Option<(usize, T)>> = Vec::new();
+    for (len, entry) in map.into_iter().enumerate() {
+        if (len + 1) * 4 >= buckets.len() * 3 {
+            let old_buckets = core::mem::take(&mut buckets);
+            buckets.resize_with((old_buckets.len() * 2).max(64), || None);
+            for entry in old_buckets.into_iter().flatten() {
+                insert(&mut buckets, entry);
+            }
+        }
+        insert(&mut buckets, entry);
+    }
+    buckets.into_iter().flatten().collect()
+}
+
+/// Emit the use-list orders of `map`, keyed by the index of their value within a range of `num_values` values.
+fn write_use_list_order_map(
+    emitter: &mut EncodingEmitter,
+    num_values: usize,
+    map: Vec<(usize, Vec<usize>)>
+) {
+    // Emit the number of values that have a custom use-list order if the number of values is greater than one.
+    if num_values != 1 {
+        emitter.emit_var_int(map.len() as u64);
+    }
+
+    for (value_index, use_list_order) in dense_map_order(map) {
+        // Compute the number of uses that are actually shuffled. If those are less than half of the total uses, encoding the index pair `(src, dst)` is more space efficient.
+        let shuffled_elements = use_list_order.iter().enumerate()
+            .filter(|(index, value)| index != *value)
+            .count();
+        let index_pair_encoding = shuffled_elements < use_list_order.len() / 2;
+
+        // For a single value, we don't need to store the value index.
+        if num_values != 1 {
+            emitter.emit_var_int(value_index as u64);
+        }
+
+        if index_pair_encoding {
+            emitter.emit_var_int_with_flag(
+                (shuffled_elements * 2) as u64, index_pair_encoding);
+            for (index, value) in use_list_order.iter().enumerate() {
+                if index != *value {
+                    emitter.emit_var_int(*value as u64);
+                    emitter.emit_var_int(index as u64);
+                }
+            }
+        } else {
+            emitter.emit_var_int_with_flag(
+                use_list_order.len() as u64, index_pair_encoding);
+            for index in use_list_order {
+                emitter.emit_var_int(index as u64);
+            }
+        }
+    }
+}
+
+/// Emit the entries of `entries` grouped by dialect, where `dialect` returns the number of the dialect of an entry. Each grouping is emitted as the dialect number and the number of entries within the group, followed by the entries themselves.
+fn write_dialect_grouping<T>(
+    emitter: &mut EncodingEmitter,
+    mut entries: &[T],
+    dialect: impl Fn(&T) -> usize,
+    mut callback: impl FnMut(&mut EncodingEmitter, &T)
+) {
+    while let Some(first) = entries.first() {
+        // The first entry of the group.
+        let group_dialect = dialect(first);
+        emitter.emit_var_int(group_dialect as u64);
+
+        // Find the end of the group.
+        let group_size = entries.iter()
+            .position(|entry| dialect(entry) != group_dialect)
+            .unwrap_or(entries.len());
+        emitter.emit_var_int(group_size as u64);
+        let (group, rest) = entries.split_at(group_size);
+        for entry in group {
+            callback(emitter, entry);
+        }
+        entries = rest;
+    }
+}
+
+// ----------------------------------------------------------------------
+// ResourceEmitter
+// ----------------------------------------------------------------------
+
+/// The state used to emit the resource sections, which accumulates the entries of the resource group currently being emitted.
+struct ResourceEmitter<'a> {
+    /// The emitter of the resource data.
+    resource_emitter: EncodingEmitter,
+    /// The emitter of the resource groups, holding the keys, kinds, and sizes of the entries.
+    offset_emitter: EncodingEmitter,
+    string_section: &'a mut StringSectionBuilder,
+    /// The offset of the end of the previous entry within the resource data.
+    prev_offset: usize,
+    /// The key, kind, and size of each entry of the current resource group.
+    cur_resource_entries: Vec<(String, AsmResourceEntryKind, usize)>
+}
+
+impl<'a> ResourceEmitter<'a> {
+    /// Emit the data of the entries built by `builder`.
+    fn build_resources(&mut self, builder: &AsmResourceBuilder) {
+        for (key, value) in builder.entries() {
+            match value {
+                AsmResourceValue::Blob(blob) => self.resource_emitter
+                    .emit_owned_blob_and_alignment(blob.data(), blob.data_alignment()),
+                AsmResourceValue::Bool(value) =>
+                    self.resource_emitter.emit_byte(*value as u8),
+                AsmResourceValue::String(value) => self.resource_emitter
+                    .emit_var_int(self.string_section.insert(value))
+            }
+            self.append_resource_offset(key, value.kind());
+        }
+    }
+
+    /// Record the offset for a resource of `kind` defined by `key`.
+    fn append_resource_offset(&mut self, key: &str, kind: AsmResourceEntryKind) {
+        let cur_offset = self.resource_emitter.size();
+        self.cur_resource_entries.push(
+            (key.to_string(), kind, cur_offset - self.prev_offset));
+        self.prev_offset = cur_offset;
+    }
+
+    /// Emit a resource group defined by `key`, consuming the current entries.
+    fn emit_resource_group(&mut self, key: u64) {
+        self.offset_emitter.emit_var_int(key);
+        self.offset_emitter.emit_var_int(self.cur_resource_entries.len() as u64);
+        for (key, kind, size) in core::mem::take(&mut self.cur_resource_entries) {
+            self.offset_emitter.emit_var_int(self.string_section.insert(&key));
+            self.offset_emitter.emit_var_int(size as u64);
+            self.offset_emitter.emit_byte(kind as u8);
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// Entry Points
+// ----------------------------------------------------------------------
+
+/**
+Write the bytecode for the given operation to the provided output stream. For streams where it matters, the given stream should be in "binary" mode.
+*/
+pub fn write_bytecode_to_file(
+    arena: &IRArena,
+    op: OpId,
+    os: &mut impl Write,
+    config: &BytecodeWriterConfig
+) -> LogicalResult {
+    let version = config.desired_bytecode_version();
+    if !(MIN_SUPPORTED_VERSION..=VERSION).contains(&version) {
+        return Err(format!(
+            "unsupported version requested {}, must be in range [{}, {}]",
+            version, MIN_SUPPORTED_VERSION, VERSION).into());
+    }
+    let mut writer = BytecodeWriter {
+        arena,
+        string_section: StringSectionBuilder::default(),
+        properties_section: PropertiesSectionBuilder::default(),
+        numbering_state: IRNumberingState::new(arena, op, config)?,
+        config
+    };
+    writer.write(op, os)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::error::Error;
+    use super::*;
+    use crate::{
+        bytecode::reader::{
+            EncodingReader, PropertiesSectionReader, StringSectionReader,
+            parse_resource_group, parse_use_list_order_for_range
+        },
+        ir::{
+            builtins::attributes::DictionaryAttribute,
+            operation::{Operation, support::OperationName}
+        }
+    };
+
+    /// Read the bytes remaining in `reader`.
+    fn remaining(mut reader: EncodingReader) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        while !reader.empty() {
+            bytes.push(reader.parse_byte().unwrap());
+        }
+        bytes
+    }
+
+    /// The bytecode interface of the `test` dialect, which encodes its version if it has one.
+    struct TestBytecodeInterface(Option<u64>);
+
+    impl BytecodeDialectInterface for TestBytecodeInterface {
+        fn dialect(&self) -> &str {
+            "test"
+        }
+
+        fn write_version(&self, writer: &mut dyn DialectBytecodeWriter) {
+            if let Some(version) = self.0 {
+                writer.write_var_int(version);
+            }
+        }
+    }
+
+    /// Write a lone `test.op` operation to bytecode using `config`.
+    fn write_test_op(config: &BytecodeWriterConfig) -> Result<Vec<u8>, Box<dyn Error>> {
+        let mut arena = IRArena::new();
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new("test.op"),
+            Vec::<Arc<dyn Type>>::new(), Vec::new(), DictionaryAttribute::default(), &[]);
+        let op = arena.create_operation(operation, 0)?;
+        let mut bytecode = Vec::new();
+        write_bytecode_to_file(&arena, op, &mut bytecode, config)?;
+        Ok(bytecode)
+    }
+
+    /// Check the header of `bytecode` and return its version along with its sections.
+    fn parse_sections(bytecode: &[u8]) -> (u64, Vec<(ID, EncodingReader<'_>)>) {
+        assert_eq!(bytecode[..4], MAGIC);
+        let mut reader = EncodingReader::new(&bytecode[4..], 4);
+        let version = reader.parse_var_int().unwrap();
+        assert_eq!(reader.parse_nul_terminated_string().unwrap(), "test");
+        let mut sections = Vec::new();
+        while !reader.empty() {
+            sections.push(reader.parse_section().unwrap());
+        }
+        (version, sections)
+    }
+
+    /// Return the section `id` of `sections`.
+    fn section<'a>(sections: &[(ID, EncodingReader<'a>)], id: ID) -> EncodingReader<'a> {
+        sections.iter().find(|(other, _)| *other == id).unwrap().1
+    }
+
+    /// Return the names of the dialects of `bytecode`, along with their encoded versions.
+    fn parse_dialect_versions(bytecode: &[u8]) -> Vec<(&str, Option<u64>)> {
+        let (version, sections) = parse_sections(bytecode);
+        let mut strings = StringSectionReader::default();
+        strings.initialize(section(&sections, ID::String)).unwrap();
+
+        let mut reader = section(&sections, ID::Dialect);
+        let num_dialects = reader.parse_var_int().unwrap();
+        (0..num_dialects).map(|_| {
+            if version < DIALECT_VERSIONING {
+                return (strings.parse_string(&mut reader).unwrap(), None);
+            }
+            let (name, version_available)
+                = strings.parse_string_with_flag(&mut reader).unwrap();
+            if !version_available {
+                return (name, None);
+            }
+            let (id, mut version_reader) = reader.parse_section().unwrap();
+            assert_eq!(id, ID::DialectVersions);
+            let version = version_reader.parse_var_int().unwrap();
+            assert!(version_reader.empty());
+            (name, Some(version))
+        }).collect()
+    }
+
+    #[test]
+    fn var_ints_round_trip() {
+        let values = [
+            0, 1, 127, 128, (1 << 14) - 1, 1 << 14, 1 << 35, (1 << 56) - 1, 1 << 56,
+            u64::MAX
+        ];
+        let mut emitter = EncodingEmitter::default();
+        for value in values {
+            emitter.emit_var_int(value);
+        }
+        // One byte up to 7 bits, one more byte per 7 bits, and a marker byte followed by the 8 bytes of the value past 56 bits.
+        assert_eq!(emitter.size(), 1 + 1 + 1 + 2 + 2 + 3 + 6 + 8 + 9 + 9);
+
+        let mut reader = EncodingReader::new(&emitter.result, 0);
+        for value in values {
+            assert_eq!(reader.parse_var_int().unwrap(), value);
+        }
+        assert!(reader.empty());
+    }
+
+    #[test]
+    fn signed_var_ints_round_trip() {
+        let values = [0, 1, -1, 63, -64, 64, -65, i64::MAX, i64::MIN];
+        let mut emitter = EncodingEmitter::default();
+        for value in values {
+            emitter.emit_signed_var_int(value as u64);
+        }
+        // Small magnitudes are zigzag encoded into a single byte, whatever their sign.
+        assert_eq!(&emitter.result[..5], &[0b1, 0b101, 0b11, 0b11111101, 0b11111111]);
+
+        let mut reader = EncodingReader::new(&emitter.result, 0);
+        for value in values {
+            assert_eq!(reader.parse_signed_var_int().unwrap(), value);
+        }
+        assert!(reader.empty());
+    }
+
+    #[test]
+    fn sections_keep_the_alignment_of_their_contents() {
+        let data = [1, 2, 3, 4, 5];
+        let mut section_emitter = EncodingEmitter::default();
+        section_emitter.emit_var_int(42);
+        section_emitter.emit_owned_blob_and_alignment(&data, 16);
+
+        // Misalign the start of the section, which then needs padding.
+        let mut emitter = EncodingEmitter::default();
+        emitter.emit_byte(0xFF);
+        emitter.emit_section(ID::Resource, section_emitter);
+        assert_eq!(emitter.required_alignment, 16);
+        assert_ne!(emitter.result[1] & 0b10000000, 0);
+
+        let buffer = &emitter.result;
+        let mut reader = EncodingReader::new(buffer, 0);
+        assert_eq!(reader.parse_byte().unwrap(), 0xFF);
+        let (id, mut section) = reader.parse_section().unwrap();
+        assert_eq!(id, ID::Resource);
+        assert!(reader.empty());
+
+        assert_eq!(section.parse_var_int().unwrap(), 42);
+        let (blob, alignment) = section.parse_blob_and_alignment().unwrap();
+        assert_eq!(blob, &data);
+        assert_eq!(alignment, 16);
+        assert_eq!((blob.as_ptr() as usize - buffer.as_ptr() as usize) % 16, 0);
+        assert!(section.empty());
+    }
+
+    #[test]
+    fn aligned_sections_only_record_alignment() {
+        let mut section_emitter = EncodingEmitter::default();
+        section_emitter.emit_owned_blob_and_alignment(&[7], 2);
+
+        // The contents of the section start at offset 2, so no padding is needed.
+        let mut emitter = EncodingEmitter::default();
+        emitter.emit_section(ID::Resource, section_emitter);
+        assert_eq!(emitter.result[0] & 0b10000000, 0);
+        assert_eq!(emitter.required_alignment, 2);
+
+        let (_, mut section) = EncodingReader::new(&emitter.result, 0)
+            .parse_section().unwrap();
+        assert_eq!(section.parse_blob_and_alignment().unwrap(), (&[7u8][..], 2));
+    }
+
+    #[test]
+    fn properties_round_trip() {
+        let mut builder = PropertiesSectionBuilder::default();
+        assert_eq!(builder.insert(&[1, 2, 3]), 0);
+        assert_eq!(builder.insert(&[4]), 1);
+        assert_eq!(builder.insert(&[]), 2);
+        // Identical properties are only emitted once.
+        assert_eq!(builder.insert(&[1, 2, 3]), 0);
+
+        let mut emitter = EncodingEmitter::default();
+        builder.write(&mut emitter);
+
+        let mut reader = PropertiesSectionReader::default();
+        reader.initialize(EncodingReader::new(&emitter.result, 0)).unwrap();
+        let properties: Vec<Vec<u8>> = reader.properties.into_iter().map(remaining).collect();
+        assert_eq!(properties, [vec![1, 2, 3], vec![4], vec![]]);
+    }
+
+    #[test]
+    fn truncated_properties_are_rejected() {
+        let mut builder = PropertiesSectionBuilder::default();
+        builder.insert(&[1, 2, 3]);
+        let mut emitter = EncodingEmitter::default();
+        builder.write(&mut emitter);
+        emitter.truncate(emitter.size() - 1);
+
+        let mut reader = PropertiesSectionReader::default();
+        assert!(reader.initialize(EncodingReader::new(&emitter.result, 0)).is_err());
+    }
+
+    #[test]
+    fn resources_round_trip() {
+        let mut string_section = StringSectionBuilder::default();
+        let mut resource_emitter = ResourceEmitter {
+            resource_emitter: EncodingEmitter::default(),
+            offset_emitter: EncodingEmitter::default(),
+            string_section: &mut string_section,
+            prev_offset: 0,
+            cur_resource_entries: Vec::new()
+        };
+        let mut builder = AsmResourceBuilder::new();
+        builder.build_bool("flag", true);
+        builder.build_blob("blob", &[1, 2, 3, 4], 8);
+        builder.build_string("name", "value");
+        resource_emitter.build_resources(&builder);
+        resource_emitter.emit_resource_group(/* key */ 3);
+        let ResourceEmitter { resource_emitter, offset_emitter, .. } = resource_emitter;
+
+        let mut string_emitter = EncodingEmitter::default();
+        string_section.write(&mut string_emitter);
+        let mut strings = StringSectionReader::default();
+        strings.initialize(EncodingReader::new(&string_emitter.result, 0)).unwrap();
+
+        let mut offset_reader = EncodingReader::new(&offset_emitter.result, 0);
+        let mut resource_reader = EncodingReader::new(&resource_emitter.result, 0);
+        assert_eq!(offset_reader.parse_var_int().unwrap(), 3);
+        let entries = parse_resource_group(
+            &strings, &mut offset_reader, &mut resource_reader).unwrap();
+        assert!(offset_reader.empty());
+        assert!(resource_reader.empty());
+
+        let [(flag_key, flag_kind, flag), (blob_key, blob_kind, mut blob),
+            (name_key, name_kind, mut name)] = entries.try_into().ok().unwrap();
+        assert_eq!((flag_key, flag_kind), ("flag", AsmResourceEntryKind::Bool as u8));
+        assert_eq!(remaining(flag), [1]);
+
+        assert_eq!((blob_key, blob_kind), ("blob", AsmResourceEntryKind::Blob as u8));
+        let (data, alignment) = blob.parse_blob_and_alignment().unwrap();
+        assert_eq!((data, alignment), (&[1u8, 2, 3, 4][..], 8));
+        assert_eq!((data.as_ptr() as usize - resource_emitter.result.as_ptr() as usize) % 8, 0);
+        assert!(blob.empty());
+
+        assert_eq!((name_key, name_kind), ("name", AsmResourceEntryKind::String as u8));
+        assert_eq!(strings.parse_string(&mut name).unwrap(), "value");
+        assert!(name.empty());
+    }
+
+    #[test]
+    fn use_list_orders_round_trip() {
+        // Few uses shuffled are encoded as `(src, dst)` pairs, otherwise as a permutation.
+        let map = vec![(2, vec![2, 0, 1]), (0, vec![1, 0, 2, 3, 4, 5])];
+        let mut emitter = EncodingEmitter::default();
+        write_use_list_order_map(&mut emitter, 3, map);
+
+        let mut reader = EncodingReader::new(&emitter.result, 0);
+        let mut orders = parse_use_list_order_for_range(&mut reader, 3).unwrap();
+        assert!(reader.empty());
+        orders.sort_by_key(|(index, _)| *index);
+        let orders: Vec<_> = orders.into_iter()
+            .map(|(index, order)| (index, order.indices, order.is_index_pair_encoding))
+            .collect();
+        assert_eq!(orders, [(0, vec![1, 0, 0, 1], true), (2, vec![2, 0, 1], false)]);
+    }
+
+    #[test]
+    fn use_list_order_of_single_value_omits_index() {
+        let mut emitter = EncodingEmitter::default();
+        write_use_list_order_map(&mut emitter, 1, vec![(0, vec![1, 0])]);
+        // Only the number of indices with the encoding flag, and the permutation.
+        assert_eq!(emitter.size(), 3);
+
+        let mut reader = EncodingReader::new(&emitter.result, 0);
+        let orders = parse_use_list_order_for_range(&mut reader, 1).unwrap();
+        assert!(reader.empty());
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].0, 0);
+        assert_eq!(orders[0].1.indices, [1, 0]);
+        assert!(!orders[0].1.is_index_pair_encoding);
+    }
+
+    #[test]
+    fn unsupported_versions_are_rejected() {
+        let mut config = BytecodeWriterConfig::new("test");
+        config.set_desired_bytecode_version(VERSION + 1);
+        let error = write_test_op(&config).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            format!("unsupported version requested {}, must be in range [0, {}]",
+                VERSION + 1, VERSION));
+    }
+
+    #[test]
+    fn header_records_the_desired_version() {
+        for desired_version in MIN_SUPPORTED_VERSION..=VERSION {
+            let mut config = BytecodeWriterConfig::new("test");
+            config.set_desired_bytecode_version(desired_version);
+            let bytecode = write_test_op(&config).unwrap();
+            let (version, sections) = parse_sections(&bytecode);
+            assert_eq!(version, desired_version);
+
+            // The properties section only exists from `NATIVE_PROPERTIES_ENCODING`.
+            let has_properties = sections.iter().any(|(id, _)| *id == ID::Properties);
+            assert_eq!(has_properties, version >= NATIVE_PROPERTIES_ENCODING);
+        }
+    }
+
+    #[test]
+    fn dialect_versions_are_encoded_from_dialect_versioning() {
+        let mut config = BytecodeWriterConfig::new("test");
+        config.attach_bytecode_interface(Box::new(TestBytecodeInterface(Some(42))));
+        let versions = parse_dialect_versions(&write_test_op(&config).unwrap());
+        assert!(versions.contains(&("test", Some(42))));
+
+        // Older bytecode has no way to encode the version.
+        config.set_desired_bytecode_version(DIALECT_VERSIONING - 1);
+        let versions = parse_dialect_versions(&write_test_op(&config).unwrap());
+        assert!(versions.contains(&("test", None)));
+    }
+
+    #[test]
+    fn dialects_without_version_are_not_flagged() {
+        let mut config = BytecodeWriterConfig::new("test");
+        config.attach_bytecode_interface(Box::new(TestBytecodeInterface(None)));
+        let versions = parse_dialect_versions(&write_test_op(&config).unwrap());
+        assert!(versions.contains(&("test", None)));
+    }
 }
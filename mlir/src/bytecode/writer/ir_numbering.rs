@@ -0,0 +1,780 @@
+/*!
+This file contains various utilities that number IR structures in preparation for bytecode emission.
+
+- lib
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Bytecode/Writer/IRNumbering.h>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Bytecode/Writer/IRNumbering.cpp>
+*/
+
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+
+use llvm::adt::ap_int::APInt;
+use crate::{
+    bytecode::{
+        encoding::{DIALECT_VERSIONING, NATIVE_PROPERTIES_ENCODING, USE_LIST_ORDERING},
+        implementation::{BytecodeDialectInterface, DialectBytecodeWriter},
+        writer::BytecodeWriterConfig
+    },
+    ir::{
+        arena::{ArenaResult, BlockId, IRArena, OpId, RegionId},
+        asm_printer::{print_attribute_with_state, print_type_with_state},
+        asm_state::{AsmResourceBuilder, AsmState},
+        attribute::Attribute,
+        builtins::{
+            attributes::{DictionaryAttribute, OpaqueAttribute},
+            dialect::bytecode::BuiltinBytecodeInterface,
+            types::OpaqueType
+        },
+        dialect::Dialect,
+        location::Location,
+        operation::{
+            Operation,
+            definition::IsIsolatedFromAbove,
+            implementation::{AsmDialectResourceHandle, OpAsmDialectInterface},
+            support::{OperationName, PrintingFlags}
+        },
+        r#type::Type,
+        value::{Input, Output, Value}
+    }
+};
+
+// ----------------------------------------------------------------------
+// Attribute and Type Numbering
+// ----------------------------------------------------------------------
+
+/// An entry of the attribute table. Locations are not attributes, but the bytecode format encodes them within the same table.
+#[derive(Clone)]
+pub enum AttributeOrLocation {
+    Attribute(Arc<dyn Attribute>),
+    Location(Location)
+}
+
+/// The key identifying an entry of the attribute table, as attributes are compared by their printed form.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum AttributeKey {
+    Attribute(String),
+    Location(String)
+}
+
+/// This class represents a numbering entry for an Attribute or Location.
+pub struct AttributeNumbering {
+    key: AttributeKey,
+    /// The attribute or location that this numbering represents.
+    pub value: AttributeOrLocation,
+    /// The number assigned to this entry.
+    pub number: usize,
+    /// The number of references to this value.
+    ref_count: usize,
+    /// The index of the dialect of this value.
+    pub dialect: usize
+}
+
+/// This class represents a numbering entry for a Type.
+pub struct TypeNumbering {
+    key: String,
+    /// The type that this numbering represents.
+    pub value: Arc<dyn Type>,
+    /// The number assigned to this type.
+    pub number: usize,
+    /// The number of references to this type.
+    ref_count: usize,
+    /// The index of the dialect of this type.
+    pub dialect: usize
+}
+
+// ----------------------------------------------------------------------
+// OpName Numbering
+// ----------------------------------------------------------------------
+
+/// This class represents the numbering entry of an operation name.
+pub struct OpNameNumbering {
+    /// The operation name.
+    pub name: OperationName,
+    /// The number assigned to this name.
+    pub number: usize,
+    /// The number of references to this name.
+    ref_count: usize,
+    /// The index of the dialect of this operation name.
+    pub dialect: usize
+}
+
+// ----------------------------------------------------------------------
+// Dialect Resource Numbering
+// ----------------------------------------------------------------------
+
+/// This class represents a numbering entry for a dialect resource.
+pub struct DialectResourceNumbering {
+    /// The key used to reference this resource.
+    pub key: String,
+    /// The number assigned to this resource.
+    pub number: usize,
+    /// A flag indicating if this resource is only a declaration, not a full definition.
+    pub is_declaration: bool
+}
+
+// ----------------------------------------------------------------------
+// Dialect Numbering
+// ----------------------------------------------------------------------
+
+/// This class represents a numbering entry for an Dialect.
+pub struct DialectNumbering<'c> {
+    /// The namespace of the dialect.
+    pub name: String,
+    /// The number assigned to the dialect.
+    pub number: usize,
+    /// The bytecode dialect interface of the dialect if defined.
+    pub interface: Option<&'c dyn BytecodeDialectInterface>,
+    /// The asm dialect interface of the dialect if defined.
+    pub asm_interface: Option<&'c dyn OpAsmDialectInterface>,
+    /// The referenced resources of this dialect.
+    pub resources: Vec<AsmDialectResourceHandle>,
+    /// A mapping from resource key to the corresponding resource numbering entry, in the order the resources were numbered.
+    pub resource_map: Vec<DialectResourceNumbering>
+}
+
+// ----------------------------------------------------------------------
+// IRNumberingState
+// ----------------------------------------------------------------------
+
+/**
+This class manages numbering IR entities in preparation of bytecode emission.
+*/
+pub struct IRNumberingState<'c> {
+    config: &'c BytecodeWriterConfig,
+
+    /// Mapping from IR to the respective numbering entries.
+    attribute_ids: HashMap<AttributeKey, usize>,
+    op_name_ids: HashMap<String, usize>,
+    type_ids: HashMap<String, usize>,
+    dialect_ids: HashMap<String, usize>,
+
+    /// The numbering entries, ordered by their number once numbering has finished.
+    attributes: Vec<AttributeNumbering>,
+    op_names: Vec<OpNameNumbering>,
+    types: Vec<TypeNumbering>,
+    dialects: Vec<DialectNumbering<'c>>,
+
+    /// The global numbering of dialect resources, keyed by dialect and resource key.
+    dialect_resources: HashMap<(String, String), usize>,
+
+    /// The numbering of blocks within their parent region.
+    block_ids: HashMap<BlockId, usize>,
+    /// The numbering of values within their isolated region.
+    value_ids: HashMap<Value, usize>,
+
+    /// A map from region to the number of blocks and values within that region.
+    region_block_value_counts: HashMap<RegionId, (usize, usize)>,
+
+    /// A map from block to the number of operations within that block.
+    block_operation_counts: HashMap<BlockId, usize>,
+
+    /// The numbering of operations in a pre-order walk of the root operation, used to order the uses of values.
+    operation_ids: HashMap<OpId, usize>,
+    /// The operations whose regions are numbered and encoded as isolated from above.
+    isolated_operations: HashSet<OpId>,
+
+    /// The next value ID to assign when numbering.
+    next_value_id: usize
+}
+
+impl<'c> IRNumberingState<'c> {
+    /// Number the IR nested within `op`.
+    pub fn new(arena: &IRArena, op: OpId, config: &'c BytecodeWriterConfig)
+        -> ArenaResult<Self>
+    {
+        let mut state = Self {
+            config,
+            attribute_ids: HashMap::new(),
+            op_name_ids: HashMap::new(),
+            type_ids: HashMap::new(),
+            dialect_ids: HashMap::new(),
+            attributes: Vec::new(),
+            op_names: Vec::new(),
+            types: Vec::new(),
+            dialects: Vec::new(),
+            dialect_resources: HashMap::new(),
+            block_ids: HashMap::new(),
+            value_ids: HashMap::new(),
+            region_block_value_counts: HashMap::new(),
+            block_operation_counts: HashMap::new(),
+            operation_ids: HashMap::new(),
+            isolated_operations: HashSet::new(),
+            next_value_id: 0
+        };
+        state.compute_isolated_operations(arena, op)?;
+
+        // Number the operations in a pre-order walk, which is how the reader orders them when sorting use-lists.
+        if config.desired_bytecode_version() >= USE_LIST_ORDERING {
+            state.operation_ids = arena.walk(op)?.into_iter()
+                .enumerate()
+                .map(|(number, op)| (op, number))
+                .collect();
+        }
+
+        // Number the root operation.
+        state.number_operation(arena, op)?;
+
+        // Push all of the regions of the root operation onto the worklist.
+        let mut number_context: Vec<(RegionId, usize)> = arena.op(op)?.regions()
+            .iter()
+            .map(|region| (*region, state.next_value_id))
+            .collect();
+
+        // Iteratively process each of the nested regions.
+        while let Some((region, next_value_id)) = number_context.pop() {
+            state.next_value_id = next_value_id;
+            state.number_region(arena, region)?;
+
+            // Traverse into nested regions.
+            for block in arena.region(region)?.blocks() {
+                for op in arena.block(*block)?.operations() {
+                    // Isolated regions don't share value numbers with their parent, so we can start numbering these regions at zero.
+                    let op_first_value_id = if state.is_isolated_from_above(*op) {
+                        0
+                    } else {
+                        state.next_value_id
+                    };
+                    number_context.extend(arena.op(*op)?.regions().iter()
+                        .map(|region| (*region, op_first_value_id)));
+                }
+            }
+        }
+
+        // Number the components referenced by the versions of the dialects. Numbering these may reference new dialects, which are versioned in turn.
+        if config.desired_bytecode_version() >= DIALECT_VERSIONING {
+            let mut index = 0;
+            while index < state.dialects.len() {
+                if let Some(interface) = state.dialects[index].interface {
+                    interface.write_version(&mut NumberingDialectWriter { state: &mut state });
+                }
+                index += 1;
+            }
+        }
+
+        // Number each of the dialects. For now this is just in the order they were found, given that the number of dialects on average is small enough to fit within a singly byte (128). If we ever have real world use cases that have a huge number of dialects, this could be made more intelligent.
+        // Dialects were numbered as they were found, so there is nothing left to do for them.
+
+        // Number each of the recorded components within each dialect.
+
+        // First sort by ref count so that the most referenced elements are first. We try to bias more heavily used elements to the front. This allows for more frequently referenced things to be encoded using smaller varints.
+        state.attributes.sort_by(|lhs, rhs| rhs.ref_count.cmp(&lhs.ref_count));
+        state.op_names.sort_by(|lhs, rhs| rhs.ref_count.cmp(&lhs.ref_count));
+        state.types.sort_by(|lhs, rhs| rhs.ref_count.cmp(&lhs.ref_count));
+
+        // After that, we apply a secondary ordering based on the parent dialect. This ordering is applied to sub-sections of the element list defined by how many bytes it takes to encode a varint index to that sub-section. This allows for more efficiently encoding components of the same dialect (e.g. we only have to encode the dialect reference once).
+        group_by_dialect_per_byte(&mut state.attributes, |entry| entry.dialect);
+        group_by_dialect_per_byte(&mut state.op_names, |entry| entry.dialect);
+        group_by_dialect_per_byte(&mut state.types, |entry| entry.dialect);
+
+        // Assign the entry numbers based on the sort order.
+        for (number, entry) in state.attributes.iter_mut().enumerate() {
+            entry.number = number;
+            state.attribute_ids.insert(entry.key.clone(), number);
+        }
+        for (number, entry) in state.op_names.iter_mut().enumerate() {
+            entry.number = number;
+            state.op_name_ids.insert(entry.name.as_str().to_string(), number);
+        }
+        for (number, entry) in state.types.iter_mut().enumerate() {
+            entry.number = number;
+            state.type_ids.insert(entry.key.clone(), number);
+        }
+
+        // Finalize the numbering of the dialect resources.
+        state.finalize_dialect_resource_numberings(arena.op(op)?);
+        Ok(state)
+    }
+
+    /* Accessors */
+
+    /// Return the numbered dialects.
+    pub fn dialects(&self) -> &[DialectNumbering<'c>] {
+        &self.dialects
+    }
+
+    /// Return the numbered attributes and locations, in order of their number.
+    pub fn attributes(&self) -> &[AttributeNumbering] {
+        &self.attributes
+    }
+
+    /// Return the numbered operation names, in order of their number.
+    pub fn op_names(&self) -> &[OpNameNumbering] {
+        &self.op_names
+    }
+
+    /// Return the numbered types, in order of their number.
+    pub fn types(&self) -> &[TypeNumbering] {
+        &self.types
+    }
+
+    /// Return the number for the given IR unit.
+    pub fn attribute_number(&self, attribute: &dyn Attribute) -> usize {
+        self.attribute_ids[&AttributeKey::Attribute(attribute.to_string())]
+    }
+
+    pub fn location_number(&self, location: &Location) -> usize {
+        self.attribute_ids[&AttributeKey::Location(location.to_string())]
+    }
+
+    pub fn block_number(&self, block: BlockId) -> usize {
+        *self.block_ids.get(&block).expect("block not numbered")
+    }
+
+    pub fn op_name_number(&self, name: &OperationName) -> usize {
+        self.op_name_ids[name.as_str()]
+    }
+
+    pub fn type_number(&self, r#type: &dyn Type) -> usize {
+        self.type_ids[&r#type.to_string()]
+    }
+
+    pub fn value_number(&self, value: Value) -> usize {
+        *self.value_ids.get(&value).expect("value not numbered")
+    }
+
+    pub fn resource_number(&self, resource: &AsmDialectResourceHandle) -> usize {
+        *self.dialect_resources
+            .get(&(resource.dialect().to_string(), resource.key().to_string()))
+            .expect("resource not numbered")
+    }
+
+    /// Return the block and value counts of the given region.
+    pub fn block_value_count(&self, region: RegionId) -> (usize, usize) {
+        *self.region_block_value_counts.get(&region).expect("region not numbered")
+    }
+
+    /// Return the number of operations in the given block.
+    pub fn operation_count(&self, block: BlockId) -> usize {
+        *self.block_operation_counts.get(&block).expect("block not numbered")
+    }
+
+    /// Return the number of the given operation in a pre-order walk of the root operation, or None if the operation is not nested within the root operation.
+    pub fn operation_number(&self, op: OpId) -> Option<usize> {
+        self.operation_ids.get(&op).copied()
+    }
+
+    /// Return true if the regions of the given operation are numbered and encoded as isolated from above.
+    pub fn is_isolated_from_above(&self, op: OpId) -> bool {
+        self.isolated_operations.contains(&op)
+    }
+
+    /// Return the bytecode interface encoding the properties of `operation` along with the names of its properties, if the operation has properties in the bytecode being emitted.
+    pub fn properties(&self, operation: &Operation)
+        -> Option<(&'c dyn BytecodeDialectInterface, &'static [&'static str])>
+    {
+        let name = operation.name();
+        if self.config.desired_bytecode_version() < NATIVE_PROPERTIES_ENCODING
+            || !name.is_registered()
+        {
+            return None;
+        }
+        let dialect = self.dialect_ids.get(name.dialect_namespace())?;
+        let interface = self.dialects[*dialect].interface?;
+        interface.property_names(name).map(|names| (interface, names))
+    }
+
+    /// Return the attribute dictionary encoded for `operation`, which leaves out the attributes encoded as its properties.
+    pub fn attr_dictionary(&self, operation: &Operation) -> DictionaryAttribute {
+        let dictionary = operation.attr_dictionary();
+        match self.properties(operation) {
+            Some((_, names)) => DictionaryAttribute::new(dictionary.value().iter()
+                .filter(|attribute|
+                    !names.iter().any(|name| *name == attribute.name().value()))
+                .cloned()
+                .collect()),
+            None => dictionary
+        }
+    }
+
+    /* Numbering */
+
+    /// Compute the operations whose regions are treated as isolated from above. These are the operations with the `IsIsolatedFromAbove` trait, along with those whose regions happen not to use any value defined above them, which lets the reader load their regions lazily as well.
+    fn compute_isolated_operations(&mut self, arena: &IRArena, root: OpId)
+        -> ArenaResult<()>
+    {
+        'operations: for op in arena.walk(root)? {
+            let operation = arena.op(op)?;
+            if operation.num_regions() == 0 {
+                continue;
+            }
+            if operation.has_trait::<dyn IsIsolatedFromAbove>() {
+                self.isolated_operations.insert(op);
+                continue;
+            }
+
+            // Look for a value used within the regions of the operation that is defined above it.
+            let nested = arena.walk(op)?;
+            let nested_set: HashSet<OpId> = nested.iter().copied().collect();
+            for nested_op in nested.iter().skip(1) {
+                for input in arena.op(*nested_op)?.inputs() {
+                    let container = match input.parent_region(arena)? {
+                        Some(region) => arena.region(region)?.parent_operation(),
+                        None => None
+                    };
+                    if !container.is_some_and(|container| nested_set.contains(&container)) {
+                        continue 'operations;
+                    }
+                }
+            }
+            self.isolated_operations.insert(op);
+        }
+        Ok(())
+    }
+
+    fn number_attribute(&mut self, attribute: &Arc<dyn Attribute>) {
+        let key = AttributeKey::Attribute(attribute.to_string());
+        if let Some(index) = self.attribute_ids.get(&key) {
+            self.attributes[*index].ref_count += 1;
+            return;
+        }
+
+        // Check for OpaqueAttribute, which is a dialect-specific attribute that didn't have a registered dialect when it got created. We don't want to encode this as the builtin OpaqueAttribute, we want to encode it as if the dialect was actually loaded.
+        let dialect = match attribute.downcast_ref::<OpaqueAttribute>() {
+            Some(opaque) => self.number_dialect(opaque.dialect_namespace()),
+            None => self.number_dialect(dialect_namespace(attribute.dialect()))
+        };
+        self.attribute_ids.insert(key.clone(), self.attributes.len());
+        self.attributes.push(AttributeNumbering {
+            key,
+            value: AttributeOrLocation::Attribute(attribute.clone()),
+            number: 0,
+            ref_count: 1,
+            dialect
+        });
+
+        // If this attribute will be emitted using the bytecode format, perform a dummy writing to number any nested components.
+        if let Some(interface) = self.dialects[dialect].interface
+            && interface.write_attribute(
+                attribute.as_ref(), &mut NumberingDialectWriter { state: self }).is_ok()
+        {
+            return;
+        }
+
+        // If this attribute will be emitted using the fallback, number the nested dialect resources. We don't number everything (e.g. no nested attributes/types), because we don't want to encode things we won't decode (the textual format can't really share much).
+        let mut state = AsmState::new(PrintingFlags::new());
+        print_attribute_with_state(attribute.as_ref(), &mut state);
+        self.number_printed_resources(&state);
+    }
+
+    fn number_location(&mut self, location: &Location) {
+        let key = AttributeKey::Location(location.to_string());
+        if let Some(index) = self.attribute_ids.get(&key) {
+            self.attributes[*index].ref_count += 1;
+            return;
+        }
+
+        // Locations always belong to the builtin dialect.
+        let dialect = self.number_dialect("builtin");
+        self.attribute_ids.insert(key.clone(), self.attributes.len());
+        self.attributes.push(AttributeNumbering {
+            key,
+            value: AttributeOrLocation::Location(location.clone()),
+            number: 0,
+            ref_count: 1,
+            dialect
+        });
+
+        // If this location will be emitted using the bytecode format, perform a dummy writing to number any nested components.
+        if let Some(interface) = self.dialects[dialect].interface {
+            let _ = interface.write_location(
+                location, &mut NumberingDialectWriter { state: self });
+        }
+    }
+
+    fn number_block(&mut self, arena: &IRArena, block: BlockId) -> ArenaResult<()> {
+        let block_ref = arena.block(block)?;
+
+        // Number the arguments of the block.
+        for index in 0..block_ref.num_inputs() {
+            self.value_ids.insert(
+                Value::Input(Input::new(block, index)), self.next_value_id);
+            self.next_value_id += 1;
+            let input = block_ref.input_impl(index);
+            self.number_location(&input.location);
+            self.number_type(&input.value.r#type);
+        }
+
+        // Number the operations in this block.
+        let operations = block_ref.operations().to_vec();
+        for op in operations.iter() {
+            self.number_operation(arena, *op)?;
+        }
+        self.block_operation_counts.insert(block, operations.len());
+        Ok(())
+    }
+
+    fn number_dialect(&mut self, namespace: &str) -> usize {
+        if let Some(number) = self.dialect_ids.get(namespace) {
+            return *number;
+        }
+        let number = self.dialects.len();
+        let config = self.config;
+        let interface = config.bytecode_interface(namespace).or_else(||
+            (namespace == "builtin").then_some(
+                &BuiltinBytecodeInterface as &dyn BytecodeDialectInterface));
+        self.dialects.push(DialectNumbering {
+            name: namespace.to_string(),
+            number,
+            interface,
+            asm_interface: config.dialect_interface(namespace),
+            resources: Vec::new(),
+            resource_map: Vec::new()
+        });
+        self.dialect_ids.insert(namespace.to_string(), number);
+        number
+    }
+
+    /// Number the regions of the given operation.
+    fn number_operation(&mut self, arena: &IRArena, op: OpId) -> ArenaResult<()> {
+        let operation: &Operation = arena.op(op)?;
+
+        // Number the components of an operation that won't be numbered elsewhere (e.g. we don't number operands, regions, or successors here).
+        self.number_op_name(operation.name());
+        for (index, r#type) in operation.output_types().enumerate() {
+            self.value_ids.insert(
+                Value::Output(Output::new(op, index)), self.next_value_id);
+            self.next_value_id += 1;
+            self.number_type(r#type);
+        }
+
+        // Only number the operation's dictionary if it isn't empty.
+        let dictionary = self.attr_dictionary(operation);
+        if !dictionary.is_empty() {
+            self.number_attribute(&(Arc::new(dictionary) as Arc<dyn Attribute>));
+        }
+
+        // Number the components referenced by the properties of the operation.
+        if let Some((interface, _)) = self.properties(operation) {
+            interface.write_properties(
+                operation, &mut NumberingDialectWriter { state: self });
+        }
+
+        self.number_location(&operation.location());
+        Ok(())
+    }
+
+    fn number_op_name(&mut self, name: &OperationName) {
+        if let Some(index) = self.op_name_ids.get(name.as_str()) {
+            self.op_names[*index].ref_count += 1;
+            return;
+        }
+        let dialect = self.number_dialect(name.dialect_namespace());
+        self.op_name_ids.insert(name.as_str().to_string(), self.op_names.len());
+        self.op_names.push(OpNameNumbering {
+            name: name.clone(),
+            number: 0,
+            ref_count: 1,
+            dialect
+        });
+    }
+
+    fn number_region(&mut self, arena: &IRArena, region: RegionId)
+        -> ArenaResult<()>
+    {
+        let blocks = arena.region(region)?.blocks().to_vec();
+        if blocks.is_empty() {
+            return Ok(());
+        }
+        let first_value_id = self.next_value_id;
+
+        // Number the blocks within this region.
+        for (index, block) in blocks.iter().enumerate() {
+            self.block_ids.insert(*block, index);
+            self.number_block(arena, *block)?;
+        }
+
+        // Remember the number of blocks and values in this region.
+        self.region_block_value_counts.insert(
+            region, (blocks.len(), self.next_value_id - first_value_id));
+        Ok(())
+    }
+
+    fn number_type(&mut self, r#type: &Arc<dyn Type>) {
+        let key = r#type.to_string();
+        if let Some(index) = self.type_ids.get(&key) {
+            self.types[*index].ref_count += 1;
+            return;
+        }
+
+        // Check for OpaqueType, which is a dialect-specific type that didn't have a registered dialect when it got created. We don't want to encode this as the builtin OpaqueType, we want to encode it as if the dialect was actually loaded.
+        let dialect = match r#type.downcast_ref::<OpaqueType>() {
+            Some(opaque) => self.number_dialect(opaque.dialect_namespace()),
+            None => self.number_dialect(dialect_namespace(r#type.dialect()))
+        };
+        self.type_ids.insert(key.clone(), self.types.len());
+        self.types.push(TypeNumbering {
+            key,
+            value: r#type.clone(),
+            number: 0,
+            ref_count: 1,
+            dialect
+        });
+
+        // If this type will be emitted using the bytecode format, perform a dummy writing to number any nested components.
+        if let Some(interface) = self.dialects[dialect].interface
+            && interface.write_type(
+                r#type.as_ref(), &mut NumberingDialectWriter { state: self }).is_ok()
+        {
+            return;
+        }
+
+        // If this type will be emitted using the fallback, number the nested dialect resources. We don't number everything (e.g. no nested attributes/types), because we don't want to encode things we won't decode (the textual format can't really share much).
+        let mut state = AsmState::new(PrintingFlags::new());
+        print_type_with_state(r#type.as_ref(), &mut state);
+        self.number_printed_resources(&state);
+    }
+
+    /// Number the dialect resources referenced while printing with `state`.
+    fn number_printed_resources(&mut self, state: &AsmState) {
+        for (_, resources) in state.dialect_resources() {
+            for resource in resources {
+                self.number_resource(resource);
+            }
+        }
+    }
+
+    /// Number the given dialect resource reference.
+    fn number_resource(&mut self, resource: &AsmDialectResourceHandle) {
+        let dialect = self.number_dialect(resource.dialect());
+        let numbering = &mut self.dialects[dialect];
+
+        // Check if this is a newly seen resource.
+        if numbering.resources.contains(resource) {
+            return;
+        }
+        numbering.resources.push(resource.clone());
+        if !numbering.resource_map.iter().any(|entry| entry.key == resource.key()) {
+            numbering.resource_map.push(DialectResourceNumbering {
+                key: resource.key().to_string(),
+                number: 0,
+                is_declaration: true
+            });
+        }
+    }
+
+    /**
+    Finalize the numberings of any dialect resources. The resources built by the OpAsm interface of a dialect are numbered first, in the order they were built, followed by the resources of the dialect that were referenced without backing data. This matches the order the resources are emitted in.
+    */
+    fn finalize_dialect_resource_numberings(&mut self, root_op: &Operation) {
+        let mut next_resource_id = 0;
+        for dialect in self.dialects.iter_mut() {
+            if let Some(asm_interface) = dialect.asm_interface {
+                let mut builder = AsmResourceBuilder::new();
+                asm_interface.build_resources(root_op, &dialect.resources, &mut builder);
+                for (key, _) in builder.entries() {
+                    match dialect.resource_map.iter_mut().find(|entry| &entry.key == key) {
+                        Some(entry) if !entry.is_declaration => continue,
+                        Some(entry) => {
+                            entry.is_declaration = false;
+                            entry.number = next_resource_id;
+                        }
+                        // Resources the dialect built without them being referenced are still emitted, so they take a number as well.
+                        None => dialect.resource_map.push(DialectResourceNumbering {
+                            key: key.clone(),
+                            number: next_resource_id,
+                            is_declaration: false
+                        })
+                    }
+                    next_resource_id += 1;
+                }
+            }
+
+            // Number any resources that weren't added by the dialect. This can happen if there was no backing data to the resource, but we still want these resource references to roundtrip, so we number them and indicate that the data is missing.
+            for entry in dialect.resource_map.iter_mut() {
+                if entry.is_declaration {
+                    entry.number = next_resource_id;
+                    next_resource_id += 1;
+                }
+            }
+            for entry in dialect.resource_map.iter() {
+                self.dialect_resources.insert(
+                    (dialect.name.clone(), entry.key.clone()), entry.number);
+            }
+        }
+    }
+}
+
+/// Return the namespace of the given dialect, with None being the builtin dialect.
+fn dialect_namespace(dialect: Option<&'static dyn Dialect>) -> &'static str {
+    dialect.map_or("builtin", |dialect| dialect.name())
+}
+
+/**
+Sort the given range using a secondary ordering based on the dialect of each element. The ordering is applied to sub-sections of the range defined by how many bytes it takes to encode a varint index to that sub-section.
+*/
+fn group_by_dialect_per_byte<T>(range: &mut [T], dialect: impl Fn(&T) -> usize) {
+    if range.is_empty() {
+        return;
+    }
+
+    let mut dialect_to_order_first = 0;
+    let mut elements_in_byte_group = 0;
+    let mut start = 0;
+    for i in 1..9 {
+        // Update the number of elements in the current byte grouping. Reminder that varint encodes 7-bits per byte, so that's how we compute the number of elements in each byte grouping.
+        elements_in_byte_group = (1usize << (7 * i)) - elements_in_byte_group;
+
+        // Slice out the sub-set of elements that are in the current byte grouping to be sorted.
+        let end = range.len().min(start + elements_in_byte_group);
+        let byte_sub_range = &mut range[start..end];
+        start = end;
+
+        // Sort the sub range for this byte, with the desired dialect ordered first to better enable sharing of dialects across byte groups.
+        byte_sub_range.sort_by_key(|entry| {
+            let number = dialect(entry);
+            (number != dialect_to_order_first, number)
+        });
+
+        // Update the dialect to order first to be the dialect at the end of the current grouping. This seeks to allow larger dialect groupings across byte boundaries.
+        dialect_to_order_first = dialect(byte_sub_range.last().unwrap());
+
+        // If the data range is now empty, we are done.
+        if start == range.len() {
+            break;
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// NumberingDialectWriter
+// ----------------------------------------------------------------------
+
+/// A dialect writer that numbers the components referenced by an attribute or type, without emitting anything.
+struct NumberingDialectWriter<'s, 'c> {
+    state: &'s mut IRNumberingState<'c>
+}
+
+impl<'s, 'c> DialectBytecodeWriter for NumberingDialectWriter<'s, 'c> {
+    fn bytecode_version(&self) -> u64 {
+        self.state.config.desired_bytecode_version()
+    }
+
+    fn write_attribute(&mut self, attribute: &Arc<dyn Attribute>) {
+        self.state.number_attribute(attribute);
+    }
+
+    fn write_optional_attribute(&mut self, attribute: Option<&Arc<dyn Attribute>>) {
+        if let Some(attribute) = attribute {
+            self.state.number_attribute(attribute);
+        }
+    }
+
+    fn write_location(&mut self, location: &Location) {
+        self.state.number_location(location);
+    }
+
+    fn write_type(&mut self, r#type: &Arc<dyn Type>) {
+        self.state.number_type(r#type);
+    }
+
+    fn write_resource_handle(&mut self, resource: &AsmDialectResourceHandle) {
+        self.state.number_resource(resource);
+    }
+
+    /// Stubbed out methods that are not used for numbering.
+    fn write_var_int(&mut self, _: u64) {}
+    fn write_owned_bool(&mut self, _: bool) {}
+    fn write_ap_int_with_known_width(&mut self, _: &APInt) {}
+    fn write_owned_string(&mut self, _: &str) {}
+    fn write_owned_blob(&mut self, _: &[u8]) {}
+}
@@ -2,3 +2,5 @@
 - include <https://github.com/llvm/llvm-project/tree/main/mlir/include/mlir/Dialect/Affine>
 - lib <https://github.com/llvm/llvm-project/tree/main/mlir/lib/Dialect/Affine>
 */
+
+pub mod affine_structures;
@@ -0,0 +1,66 @@
+/*!
+Structures for affine/polyhedral analysis of affine dialect ops: the conversion of affine expressions and integer sets to the constraint systems of the Presburger library.
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Dialect/Affine/Analysis/AffineStructures.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Dialect/Affine/Analysis/AffineStructures.cpp>
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Analysis/FlatLinearValueConstraints.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Analysis/FlatLinearValueConstraints.cpp>
+*/
+
+pub use crate::analysis::presburger::{
+    integer_relation::{BoundType, IntegerPolyhedron, IntegerRelation},
+    presburger_relation::{PresburgerRelation, PresburgerSet},
+    presburger_space::{PresburgerSpace, VarKind},
+    simplex::MaybeOptimum
+};
+
+use crate::ir::{
+    affine_expr::{
+        AffineExpr,
+        visitor::{AffineExprVisitor, SimpleAffineExprFlattener}
+    },
+    integer_set::IntegerSet
+};
+
+/**
+Flattens `exprs` into their coefficients over the dimensions, the symbols and the local variables introduced for their mod/floordiv/ceildiv's, followed by the constant term. The expressions are flattened together, so that they share their local variables. Returns the flattened expressions along with a polyhedron over the same variables holding the constraints that define the locals, or `None` if an expression is semi-affine.
+*/
+pub fn get_flattened_affine_exprs(
+    exprs: &[AffineExpr],
+    num_dims: usize,
+    num_symbols: usize
+) -> Option<(Vec<Vec<i64>>, IntegerPolyhedron)> {
+    if !exprs.iter().all(AffineExpr::is_pure_affine) {
+        return None;
+    }
+    let mut flattener = SimpleAffineExprFlattener::new(num_dims, num_symbols);
+    for expr in exprs {
+        flattener.walk_post_order(expr);
+    }
+    assert_eq!(flattener.operand_expr_stack.len(), exprs.len());
+
+    let mut polyhedron = IntegerPolyhedron::new(PresburgerSpace::set(num_dims, num_symbols, 0));
+    let local_offset = num_dims + num_symbols;
+    for (local, division) in flattener.local_divisions.iter().enumerate() {
+        let (dividend, divisor) = division.as_ref()?;
+        // A dividend only involves the locals introduced before its own.
+        let mut row = dividend[..local_offset + local].to_vec();
+        row.push(*dividend.last().unwrap());
+        polyhedron.add_local_floor_div(&row, *divisor);
+    }
+    Some((flattener.operand_expr_stack, polyhedron))
+}
+
+/// Construct the polyhedron of the points of `set`, with the locals introduced to flatten its constraints existentially quantified. Returns `None` if a constraint is semi-affine.
+pub fn polyhedron_from_integer_set(set: &IntegerSet) -> Option<IntegerPolyhedron> {
+    let (flattened, mut polyhedron) = get_flattened_affine_exprs(
+        set.constraints(), set.num_dims(), set.num_symbols())?;
+    for (row, is_eq) in flattened.iter().zip(set.eq_flags()) {
+        if *is_eq {
+            polyhedron.add_equality(row);
+        } else {
+            polyhedron.add_inequality(row);
+        }
+    }
+    Some(polyhedron)
+}
@@ -7,585 +7,817 @@
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Dialect/Index/IR/IndexOps.cpp>
 */
 
-//===----------------------------------------------------------------------===//
-// AddOp
-//===----------------------------------------------------------------------===//
-
-def Index_AddOp : IndexBinaryOp<"add"> {
-  let summary = "index addition";
-  let description = [{
-    The `index.add` operation takes two index values and computes their sum.
-
-    Example:
-
-    ```mlir
-    // c = a + b
-    %c = index.add %a, %b
-    ```
-  }];
+use core::fmt::{self, Write};
+
+use crate::{
+    asm_parser::parser::ParseResult,
+    ir::{
+        arena::{IRArena, OpId},
+        attr_type_base::AttrOrTypeDefinition,
+        attribute::Attribute,
+        builtins::{
+            attributes::IntegerAttribute,
+            types::{IndexType, IntegerType, Signedness}
+        },
+        dialect::implementation::{DialectAsmParser, DialectAsmPrinter},
+        enum_attribute::EnumAttribute,
+        mlir_context::MLIRContext,
+        operation::{
+            constraints,
+            definition::{ConstantLike, IsCommutative, Op},
+            implementation::AsmParser
+        }
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
+// Binary Operations
+// ----------------------------------------------------------------------
+
+/// Index addition.
+///
+/// The `index.add` operation takes two index values and computes their sum.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a + b
+/// %c = index.add %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.add",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct Add(OpId);
+
+/// Index subtraction.
+///
+/// The `index.sub` operation takes two index values and computes the difference of the first from the second operand.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a - b
+/// %c = index.sub %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.sub",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct Sub(OpId);
+
+/// Index multiplication.
+///
+/// The `index.mul` operation takes two index values and computes their product.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a * b
+/// %c = index.mul %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.mul",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct Mul(OpId);
+
+/// Index signed division.
+///
+/// The `index.divs` operation takes two index values and computes their signed quotient. Treats the leading bit as the sign and rounds towards zero, i.e. `6 / -2 = -3`.
+///
+/// Note: division by zero and signed division overflow are undefined behaviour.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a / b
+/// %c = index.divs %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.divs",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct DivS(OpId);
+
+/// Index unsigned division.
+///
+/// The `index.divu` operation takes two index values and computes their unsigned quotient. Treats the leading bit as the most significant and rounds towards zero, i.e. `6 / -2 = 0`.
+///
+/// Note: division by zero is undefined behaviour.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a / b
+/// %c = index.divu %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.divu",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct DivU(OpId);
+
+/// Index signed ceil division.
+///
+/// The `index.ceildivs` operation takes two index values and computes their signed quotient. Treats the leading bit as the sign and rounds towards positive infinity, i.e. `7 / -2 = -3`.
+///
+/// Note: division by zero and signed division overflow are undefined behaviour.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = ceil(a / b)
+/// %c = index.ceildivs %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.ceildivs",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct CeilDivS(OpId);
+
+/// Index unsigned ceil division.
+///
+/// The `index.ceildivu` operation takes two index values and computes their unsigned quotient. Treats the leading bit as the most significant and rounds towards positive infinity, i.e. `6 / -2 = 1`.
+///
+/// Note: division by zero is undefined behaviour.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = ceil(a / b)
+/// %c = index.ceildivu %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.ceildivu",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct CeilDivU(OpId);
+
+/// Index signed floor division.
+///
+/// The `index.floordivs` operation takes two index values and computes their signed quotient. Treats the leading bit as the sign and rounds towards negative infinity, i.e. `5 / -2 = -3`.
+///
+/// Note: division by zero and signed division overflow are undefined behaviour.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = floor(a / b)
+/// %c = index.floordivs %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.floordivs",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct FloorDivS(OpId);
+
+/// Index signed remainder.
+///
+/// The `index.rems` operation takes two index values and computes their signed remainder. Treats the leading bit as the sign, i.e. `6 % -2 = 0`.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a % b
+/// %c = index.rems %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.rems",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct RemS(OpId);
+
+/// Index unsigned remainder.
+///
+/// The `index.remu` operation takes two index values and computes their unsigned remainder. Treats the leading bit as the most significant, i.e. `6 % -2 = 6`.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a % b
+/// %c = index.remu %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.remu",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct RemU(OpId);
+
+/// Index signed maximum.
+///
+/// The `index.maxs` operation takes two index values and computes their signed maximum value. Treats the leading bit as the sign, i.e. `max(-2, 6) = 6`.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = max(a, b)
+/// %c = index.maxs %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.maxs",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct MaxS(OpId);
+
+/// Index unsigned maximum.
+///
+/// The `index.maxu` operation takes two index values and computes their unsigned maximum value. Treats the leading bit as the most significant, i.e. `max(15, 6) = 15` or `max(-2, 6) = -2`.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = max(a, b)
+/// %c = index.maxu %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.maxu",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct MaxU(OpId);
+
+/// Index signed minimum.
+///
+/// The `index.mins` operation takes two index values and computes their signed minimum value. Treats the leading bit as the sign, i.e. `min(-2, 6) = -2`.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = min(a, b)
+/// %c = index.mins %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.mins",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct MinS(OpId);
+
+/// Index unsigned minimum.
+///
+/// The `index.minu` operation takes two index values and computes their unsigned minimum value. Treats the leading bit as the most significant, i.e. `min(15, 6) = 6` or `min(-2, 6) = 6`.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = min(a, b)
+/// %c = index.minu %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.minu",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct MinU(OpId);
+
+/// Index shift left.
+///
+/// The `index.shl` operation shifts an index value to the left by a variable amount. The low order bits are filled with zeroes. The RHS operand is always treated as unsigned. If the RHS operand is equal to or greater than the index bitwidth, the operation is undefined.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a << b
+/// %c = index.shl %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.shl",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct Shl(OpId);
+
+/// Signed index shift right.
+///
+/// The `index.shrs` operation shifts an index value to the right by a variable amount. The LHS operand is treated as signed. The high order bits are filled with copies of the most significant bit. If the RHS operand is equal to or greater than the index bitwidth, the operation is undefined.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a >> b
+/// %c = index.shrs %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.shrs",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct ShrS(OpId);
+
+/// Unsigned index shift right.
+///
+/// The `index.shru` operation shifts an index value to the right by a variable amount. The LHS operand is treated as unsigned. The high order bits are filled with zeroes. If the RHS operand is equal to or greater than the index bitwidth, the operation is undefined.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a >> b
+/// %c = index.shru %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.shru",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct ShrU(OpId);
+
+/// Index bitwise and.
+///
+/// The `index.and` operation takes two index values and computes their bitwise and.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a & b
+/// %c = index.and %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.and",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct And(OpId);
+
+/// Index bitwise or.
+///
+/// The `index.or` operation takes two index values and computes their bitwise or.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a | b
+/// %c = index.or %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.or",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct Or(OpId);
+
+/// Index bitwise xor.
+///
+/// The `index.xor` operation takes two index values and computes their bitwise xor.
+///
+/// # Example
+///
+/// ```mlir
+/// // c = a ^ b
+/// %c = index.xor %a, %b
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.xor",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::index = IndexType::new()),
+    traits(IsCommutative),
+    assembly_format = "$lhs `,` $rhs attr-dict"
+)]
+pub struct Xor(OpId);
+
+// ----------------------------------------------------------------------
+// CastS
+// ----------------------------------------------------------------------
+
+/// Index signed cast.
+///
+/// The `index.casts` operation enables conversions between values of index type and concrete fixed-width integer types. If casting to a wider integer, the value is sign-extended. If casting to a narrower integer, the value is truncated.
+///
+/// # Example
+///
+/// ```mlir
+/// // Cast to i32
+/// %0 = index.casts %a : index to i32
+///
+/// // Cast from i64
+/// %1 = index.casts %b : i64 to index
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.casts",
+    operands(input: constraints::integer_or_index),
+    results(output: constraints::integer_or_index),
+    assembly_format = "$input attr-dict `:` type($input) `to` type($output)"
+)]
+pub struct CastS(OpId);
+
+// ----------------------------------------------------------------------
+// CastU
+// ----------------------------------------------------------------------
+
+/// Index unsigned cast.
+///
+/// The `index.castu` operation enables conversions between values of index type and concrete fixed-width integer types. If casting to a wider integer, the value is zero-extended. If casting to a narrower integer, the value is truncated.
+///
+/// # Example
+///
+/// ```mlir
+/// // Cast to i32
+/// %0 = index.castu %a : index to i32
+///
+/// // Cast from i64
+/// %1 = index.castu %b : i64 to index
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.castu",
+    operands(input: constraints::integer_or_index),
+    results(output: constraints::integer_or_index),
+    assembly_format = "$input attr-dict `:` type($input) `to` type($output)"
+)]
+pub struct CastU(OpId);
+
+// ----------------------------------------------------------------------
+// Cmp
+// ----------------------------------------------------------------------
+
+/// The comparison predicates of `index.cmp`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IndexCmpPredicate {
+    /// `eq`: equal
+    Eq,
+    /// `ne`: not equal
+    Ne,
+    /// `slt`: signed less than
+    Slt,
+    /// `sle`: signed less than or equal
+    Sle,
+    /// `sgt`: signed greater than
+    Sgt,
+    /// `sge`: signed greater than or equal
+    Sge,
+    /// `ult`: unsigned less than
+    Ult,
+    /// `ule`: unsigned less than or equal
+    Ule,
+    /// `ugt`: unsigned greater than
+    Ugt,
+    /// `uge`: unsigned greater than or equal
+    Uge
 }
 
-//===----------------------------------------------------------------------===//
-// SubOp
-//===----------------------------------------------------------------------===//
-
-def Index_SubOp : IndexBinaryOp<"sub"> {
-  let summary = "index subtraction";
-  let description = [{
-    The `index.sub` operation takes two index values and computes the difference
-    of the first from the second operand.
-
-    Example:
-
-    ```mlir
-    // c = a - b
-    %c = index.sub %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// MulOp
-//===----------------------------------------------------------------------===//
-
-def Index_MulOp : IndexBinaryOp<"mul"> {
-  let summary = "index multiplication";
-  let description = [{
-    The `index.mul` operation takes two index values and computes their product.
-
-    Example:
-
-    ```mlir
-    // c = a * b
-    %c = index.mul %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// DivSOp
-//===----------------------------------------------------------------------===//
-
-def Index_DivSOp : IndexBinaryOp<"divs"> {
-  let summary = "index signed division";
-  let description = [{
-    The `index.divs` operation takes two index values and computes their signed
-    quotient. Treats the leading bit as the sign and rounds towards zero, i.e.
-    `6 / -2 = -3`.
-
-    Note: division by zero and signed division overflow are undefined behaviour.
-
-    Example:
-
-    ```mlir
-    // c = a / b
-    %c = index.divs %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// DivUOp
-//===----------------------------------------------------------------------===//
-
-def Index_DivUOp : IndexBinaryOp<"divu"> {
-  let summary = "index unsigned division";
-  let description = [{
-    The `index.divu` operation takes two index values and computes their
-    unsigned quotient. Treats the leading bit as the most significant and rounds
-    towards zero, i.e. `6 / -2 = 0`.
-
-    Note: division by zero is undefined behaviour.
-
-    Example:
-
-    ```mlir
-    // c = a / b
-    %c = index.divu %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// CeilDivSOp
-//===----------------------------------------------------------------------===//
-
-def Index_CeilDivSOp : IndexBinaryOp<"ceildivs"> {
-  let summary = "index signed ceil division";
-  let description = [{
-    The `index.ceildivs` operation takes two index values and computes their
-    signed quotient. Treats the leading bit as the sign and rounds towards
-    positive infinity, i.e. `7 / -2 = -3`.
-
-    Note: division by zero and signed division overflow are undefined behaviour.
-
-    Example:
-
-    ```mlir
-    // c = ceil(a / b)
-    %c = index.ceildivs %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// CeilDivUOp
-//===----------------------------------------------------------------------===//
-
-def Index_CeilDivUOp : IndexBinaryOp<"ceildivu"> {
-  let summary = "index unsigned ceil division";
-  let description = [{
-    The `index.ceildivu` operation takes two index values and computes their
-    unsigned quotient. Treats the leading bit as the most significant and rounds
-    towards positive infinity, i.e. `6 / -2 = 1`.
-
-    Note: division by zero is undefined behaviour.
-
-    Example:
-
-    ```mlir
-    // c = ceil(a / b)
-    %c = index.ceildivu %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// FloorDivSOp
-//===----------------------------------------------------------------------===//
-
-def Index_FloorDivSOp : IndexBinaryOp<"floordivs"> {
-  let summary = "index signed floor division";
-  let description = [{
-    The `index.floordivs` operation takes two index values and computes their
-    signed quotient. Treats the leading bit as the sign and rounds towards
-    negative infinity, i.e. `5 / -2 = -3`.
-
-    Note: division by zero and signed division overflow are undefined behaviour.
-
-    Example:
-
-    ```mlir
-    // c = floor(a / b)
-    %c = index.floordivs %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// RemSOp
-//===----------------------------------------------------------------------===//
-
-def Index_RemSOp : IndexBinaryOp<"rems"> {
-  let summary = "index signed remainder";
-  let description = [{
-    The `index.rems` operation takes two index values and computes their signed
-    remainder. Treats the leading bit as the sign, i.e. `6 % -2 = 0`.
-
-    Example:
-
-    ```mlir
-    // c = a % b
-    %c = index.rems %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// RemUOp
-//===----------------------------------------------------------------------===//
-
-def Index_RemUOp : IndexBinaryOp<"remu"> {
-  let summary = "index unsigned remainder";
-  let description = [{
-    The `index.remu` operation takes two index values and computes their
-    unsigned remainder. Treats the leading bit as the most significant, i.e.
-    `6 % -2 = 6`.
-
-    Example:
-
-    ```mlir
-    // c = a % b
-    %c = index.remu %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// MaxSOp
-//===----------------------------------------------------------------------===//
-
-def Index_MaxSOp : IndexBinaryOp<"maxs"> {
-  let summary = "index signed maximum";
-  let description = [{
-    The `index.maxs` operation takes two index values and computes their signed
-    maximum value. Treats the leading bit as the sign, i.e. `max(-2, 6) = 6`.
-
-    Example:
-
-    ```mlir
-    // c = max(a, b)
-    %c = index.maxs %a, %b
-    ```
-  }];
+impl EnumAttribute for IndexCmpPredicate {
+    type Attribute = IndexCmpPredicateAttr;
+
+    const CASES: &'static [(Self, &'static str)] = &[
+        (Self::Eq, "eq"),
+        (Self::Ne, "ne"),
+        (Self::Slt, "slt"),
+        (Self::Sle, "sle"),
+        (Self::Sgt, "sgt"),
+        (Self::Sge, "sge"),
+        (Self::Ult, "ult"),
+        (Self::Ule, "ule"),
+        (Self::Ugt, "ugt"),
+        (Self::Uge, "uge")
+    ];
+
+    fn to_attribute(self) -> IndexCmpPredicateAttr {
+        IndexCmpPredicateAttr::new(self)
+    }
+
+    fn from_attribute(attribute: &IndexCmpPredicateAttr) -> Option<Self> {
+        Some(attribute.value())
+    }
 }
 
-//===----------------------------------------------------------------------===//
-// MaxUOp
-//===----------------------------------------------------------------------===//
-
-def Index_MaxUOp : IndexBinaryOp<"maxu"> {
-  let summary = "index unsigned maximum";
-  let description = [{
-    The `index.maxu` operation takes two index values and computes their
-    unsigned maximum value. Treats the leading bit as the most significant, i.e.
-    `max(15, 6) = 15` or `max(-2, 6) = -2`.
-
-    Example:
-
-    ```mlir
-    // c = max(a, b)
-    %c = index.maxu %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// MinSOp
-//===----------------------------------------------------------------------===//
-
-def Index_MinSOp : IndexBinaryOp<"mins"> {
-  let summary = "index signed minimum";
-  let description = [{
-    The `index.mins` operation takes two index values and computes their signed
-    minimum value. Treats the leading bit as the sign, i.e. `min(-2, 6) = -2`.
-
-    Example:
-
-    ```mlir
-    // c = min(a, b)
-    %c = index.mins %a, %b
-    ```
-  }];
+/// An index comparison predicate attribute, `#index<cmp_predicate slt>` in the generic form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IndexCmpPredicateAttr {
+    value: IndexCmpPredicate
 }
 
-//===----------------------------------------------------------------------===//
-// MinUOp
-//===----------------------------------------------------------------------===//
-
-def Index_MinUOp : IndexBinaryOp<"minu"> {
-  let summary = "index unsigned minimum";
-  let description = [{
-    The `index.minu` operation takes two index values and computes their
-    unsigned minimum value. Treats the leading bit as the most significant, i.e.
-    `min(15, 6) = 6` or `min(-2, 6) = 6`.
+impl Attribute for IndexCmpPredicateAttr {}
 
-    Example:
+impl IndexCmpPredicateAttr {
+    pub fn new(value: IndexCmpPredicate) -> Self {
+        Self { value }
+    }
 
-    ```mlir
-    // c = min(a, b)
-    %c = index.minu %a, %b
-    ```
-  }];
+    pub fn value(&self) -> IndexCmpPredicate {
+        self.value
+    }
 }
 
-//===----------------------------------------------------------------------===//
-// ShlOp
-//===----------------------------------------------------------------------===//
-
-def Index_ShlOp : IndexBinaryOp<"shl"> {
-  let summary = "index shift left";
-  let description = [{
-    The `index.shl` operation shifts an index value to the left by a variable
-    amount. The low order bits are filled with zeroes. The RHS operand is always
-    treated as unsigned. If the RHS operand is equal to or greater than the
-    index bitwidth, the operation is undefined.
-
-    Example:
-
-    ```mlir
-    // c = a << b
-    %c = index.shl %a, %b
-    ```
-  }];
+impl AttrOrTypeDefinition for IndexCmpPredicateAttr {
+    const MNEMONIC: &'static str = "cmp_predicate";
+
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        let location = parser.current_location();
+        let keyword = parser.parse_optional_any_keyword().unwrap_or_default();
+        IndexCmpPredicate::symbolise(&keyword).map(Self::new)
+            .ok_or_else(|| parser.emit_error(location, format!(
+                "expected one of [{}] for index comparison predicate kind",
+                IndexCmpPredicate::keywords())))
+    }
+
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        write!(printer, " {}", self.value.stringify())
+    }
 }
 
-//===----------------------------------------------------------------------===//
-// ShrSOp
-//===----------------------------------------------------------------------===//
-
-def Index_ShrSOp : IndexBinaryOp<"shrs"> {
-  let summary = "signed index shift right";
-  let description = [{
-    The `index.shrs` operation shifts an index value to the right by a variable
-    amount. The LHS operand is treated as signed. The high order bits are filled
-    with copies of the most significant bit. If the RHS operand is equal to or
-    greater than the index bitwidth, the operation is undefined.
-
-    Example:
-
-    ```mlir
-    // c = a >> b
-    %c = index.shrs %a, %b
-    ```
-  }];
+/// Index compare.
+///
+/// The `index.cmp` operation takes two index values and compares them according to the comparison predicate and returns an `i1`. The following comparisons are supported:
+///
+/// - `eq`:  equal
+/// - `ne`:  not equal
+/// - `slt`: signed less than
+/// - `sle`: signed less than or equal
+/// - `sgt`: signed greater than
+/// - `sge`: signed greater than or equal
+/// - `ult`: unsigned less than
+/// - `ule`: unsigned less than or equal
+/// - `ugt`: unsigned greater than
+/// - `uge`: unsigned greater than or equal
+///
+/// The result is `1` if the comparison is true and `0` otherwise.
+///
+/// # Example
+///
+/// ```mlir
+/// // Signed less than comparison.
+/// %0 = index.cmp slt(%a, %b)
+///
+/// // Unsigned greater than or equal comparison.
+/// %1 = index.cmp uge(%a, %b)
+///
+/// // Not equal comparison.
+/// %2 = index.cmp ne(%a, %b)
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.cmp",
+    operands(
+        lhs: constraints::index = IndexType::new(),
+        rhs: constraints::index = IndexType::new()
+    ),
+    results(result: constraints::i1 = IntegerType::new(1, Signedness::Signless)),
+    attributes(enum pred: IndexCmpPredicate),
+    assembly_format = "$pred `(` $lhs `,` $rhs `)` attr-dict"
+)]
+pub struct Cmp(OpId);
+
+// ----------------------------------------------------------------------
+// SizeOf
+// ----------------------------------------------------------------------
+
+/// Size in bits of the index type.
+///
+/// The `index.sizeof` operation produces an index-typed SSA value equal to the size in bits of the `index` type. For example, on 32-bit systems, the result is `32 : index`, and on 64-bit systems, the result is `64 : index`.
+///
+/// # Example
+///
+/// ```mlir
+/// %0 = index.sizeof
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.sizeof",
+    results(result: constraints::index = IndexType::new()),
+    assembly_format = "attr-dict"
+)]
+pub struct SizeOf(OpId);
+
+// ----------------------------------------------------------------------
+// Constant
+// ----------------------------------------------------------------------
+
+/// Index constant.
+///
+/// The `index.constant` operation produces an index-typed SSA value equal to some index-typed integer constant.
+///
+/// # Example
+///
+/// ```mlir
+/// %0 = index.constant 42 : index
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.constant",
+    results(result: constraints::index = IndexType::new()),
+    attributes(value: IntegerAttribute),
+    traits(ConstantLike),
+    verifier,
+    assembly_format = "attr-dict $value"
+)]
+pub struct Constant(OpId);
+
+impl Constant {
+    fn verify(&self, arena: &IRArena) -> LogicalResult {
+        if !constraints::index(&**self.value(arena)?.r#type()) {
+            let operation = arena.op(self.operation())?;
+            return Err(Box::new(operation.emit_op_error(
+                "attribute 'value' failed to satisfy constraint: index attribute")));
+        }
+        Ok(())
+    }
 }
 
-//===----------------------------------------------------------------------===//
-// ShrUOp
-//===----------------------------------------------------------------------===//
-
-def Index_ShrUOp : IndexBinaryOp<"shru"> {
-  let summary = "unsigned index shift right";
-  let description = [{
-    The `index.shru` operation shifts an index value to the right by a variable
-    amount. The LHS operand is treated as unsigned. The high order bits are
-    filled with zeroes. If the RHS operand is equal to or greater than the index
-    bitwidth, the operation is undefined.
-
-    Example:
-
-    ```mlir
-    // c = a >> b
-    %c = index.shru %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// AndOp
-//===----------------------------------------------------------------------===//
-
-def Index_AndOp : IndexBinaryOp<"and"> {
-  let summary = "index bitwise and";
-  let description = [{
-    The `index.and` operation takes two index values and computes their bitwise
-    and.
-
-    Example:
-
-    ```mlir
-    // c = a & b
-    %c = index.and %a, %b
-    ```
-  }];
+// ----------------------------------------------------------------------
+// BoolConstant
+// ----------------------------------------------------------------------
+
+/// Boolean constant.
+///
+/// The `index.bool.constant` operation produces an bool-typed SSA value equal to either `true` or `false`.
+///
+/// This operation is used to materialize bool constants that arise when folding `index.cmp`.
+///
+/// # Example
+///
+/// ```mlir
+/// %0 = index.bool.constant true
+/// ```
+#[derive(Op)]
+#[op(
+    name = "index.bool.constant",
+    results(result: constraints::i1 = IntegerType::new(1, Signedness::Signless)),
+    attributes(value: IntegerAttribute),
+    traits(ConstantLike),
+    verifier,
+    assembly_format = "attr-dict $value"
+)]
+pub struct BoolConstant(OpId);
+
+impl BoolConstant {
+    fn verify(&self, arena: &IRArena) -> LogicalResult {
+        if !constraints::i1(&**self.value(arena)?.r#type()) {
+            let operation = arena.op(self.operation())?;
+            return Err(Box::new(operation.emit_op_error(
+                "attribute 'value' failed to satisfy constraint: bool attribute")));
+        }
+        Ok(())
+    }
 }
 
-//===----------------------------------------------------------------------===//
-// OrOp
-//===----------------------------------------------------------------------===//
-
-def Index_OrOp : IndexBinaryOp<"or"> {
-  let summary = "index bitwise or";
-  let description = [{
-    The `index.or` operation takes two index values and computes their bitwise
-    or.
-
-    Example:
-
-    ```mlir
-    // c = a | b
-    %c = index.or %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// XorOp
-//===----------------------------------------------------------------------===//
-
-def Index_XOrOp : IndexBinaryOp<"xor"> {
-  let summary = "index bitwise xor";
-  let description = [{
-    The `index.xor` operation takes two index values and computes their bitwise
-    xor.
-
-    Example:
-
-    ```mlir
-    // c = a ^ b
-    %c = index.xor %a, %b
-    ```
-  }];
-}
-
-//===----------------------------------------------------------------------===//
-// CastSOp
-//===----------------------------------------------------------------------===//
-
-def Index_CastSOp : IndexOp<"casts",
-                           [DeclareOpInterfaceMethods<CastOpInterface>]> {
-  let summary = "index signed cast";
-  let description = [{
-    The `index.casts` operation enables conversions between values of index type
-    and concrete fixed-width integer types. If casting to a wider integer, the
-    value is sign-extended. If casting to a narrower integer, the value is
-    truncated.
-
-    Example:
-
-    ```mlir
-    // Cast to i32
-    %0 = index.casts %a : index to i32
-
-    // Cast from i64
-    %1 = index.casts %b : i64 to index
-    ```
-  }];
-
-  let arguments = (ins AnyTypeOf<[AnyInteger, Index]>:$input);
-  let results = (outs AnyTypeOf<[AnyInteger, Index]>:$output);
-  let assemblyFormat = "$input attr-dict `:` type($input) `to` type($output)";
-}
-
-//===----------------------------------------------------------------------===//
-// CastUOp
-//===----------------------------------------------------------------------===//
-
-def Index_CastUOp : IndexOp<"castu",
-                           [DeclareOpInterfaceMethods<CastOpInterface>]> {
-  let summary = "index unsigned cast";
-  let description = [{
-    The `index.castu` operation enables conversions between values of index type
-    and concrete fixed-width integer types. If casting to a wider integer, the
-    value is zero-extended. If casting to a narrower integer, the value is
-    truncated.
-
-    Example:
-
-    ```mlir
-    // Cast to i32
-    %0 = index.castu %a : index to i32
-
-    // Cast from i64
-    %1 = index.castu %b : i64 to index
-    ```
-  }];
-
-  let arguments = (ins AnyTypeOf<[AnyInteger, Index]>:$input);
-  let results = (outs AnyTypeOf<[AnyInteger, Index]>:$output);
-  let assemblyFormat = "$input attr-dict `:` type($input) `to` type($output)";
-}
-
-//===----------------------------------------------------------------------===//
-// CmpOp
-//===----------------------------------------------------------------------===//
-
-def Index_CmpOp : IndexOp<"cmp"> {
-  let summary = "index compare";
-  let description = [{
-    The `index.cmp` operation takes two index values and compares them according
-    to the comparison predicate and returns an `i1`. The following comparisons
-    are supported:
-
-    -   `eq`:  equal
-    -   `ne`:  not equal
-    -   `slt`: signed less than
-    -   `sle`: signed less than or equal
-    -   `sgt`: signed greater than
-    -   `sge`: signed greater than or equal
-    -   `ult`: unsigned less than
-    -   `ule`: unsigned less than or equal
-    -   `ugt`: unsigned greater than
-    -   `uge`: unsigned greater than or equal
-
-    The result is `1` if the comparison is true and `0` otherwise.
-
-    Example:
-
-    ```mlir
-    // Signed less than comparison.
-    %0 = index.cmp slt(%a, %b)
-
-    // Unsigned greater than or equal comparison.
-    %1 = index.cmp uge(%a, %b)
-
-    // Not equal comparison.
-    %2 = index.cmp ne(%a, %b)
-    ```
-  }];
-
-  let arguments = (ins IndexCmpPredicateAttr:$pred, Index:$lhs, Index:$rhs);
-  let results = (outs I1:$result);
-  let assemblyFormat = "`` $pred `(` $lhs `,` $rhs `)` attr-dict";
-  let hasFolder = 1;
-}
-
-//===----------------------------------------------------------------------===//
-// SizeOfOp
-//===----------------------------------------------------------------------===//
-
-def Index_SizeOfOp : IndexOp<"sizeof"> {
-  let summary = "size in bits of the index type";
-  let description = [{
-    The `index.sizeof` operation produces an index-typed SSA value equal to the
-    size in bits of the `index` type. For example, on 32-bit systems, the result
-    is `32 : index`, and on 64-bit systems, the result is `64 : index`.
-
-    Example:
-
-    ```mlir
-    %0 = index.sizeof
-    ```
-  }];
-
-  let results = (outs Index:$result);
-  let assemblyFormat = "attr-dict";
-}
-
-//===----------------------------------------------------------------------===//
-// ConstantOp
-//===----------------------------------------------------------------------===//
-
-def Index_ConstantOp : IndexOp<"constant", [
-    ConstantLike,
-    DeclareOpInterfaceMethods<OpAsmOpInterface, ["getAsmResultNames"]>
-  ]> {
-  let summary = "index constant";
-  let description = [{
-    The `index.constant` operation produces an index-typed SSA value equal to
-    some index-typed integer constant.
-
-    Example:
-
-    ```mlir
-    %0 = index.constant 42
-    ```
-  }];
-
-  let arguments = (ins IndexAttr:$value);
-  let results = (outs Index:$result);
-  let assemblyFormat = "attr-dict $value";
-  let hasFolder = 1;
-
-  let builders = [OpBuilder<(ins "int64_t":$value)>];
+// ----------------------------------------------------------------------
+// Registration
+// ----------------------------------------------------------------------
+
+/// Register the operations of the index dialect in `context`.
+pub fn register_operations(context: &mut MLIRContext) {
+    context.register_operation::<Add>();
+    context.register_operation::<Sub>();
+    context.register_operation::<Mul>();
+    context.register_operation::<DivS>();
+    context.register_operation::<DivU>();
+    context.register_operation::<CeilDivS>();
+    context.register_operation::<CeilDivU>();
+    context.register_operation::<FloorDivS>();
+    context.register_operation::<RemS>();
+    context.register_operation::<RemU>();
+    context.register_operation::<MaxS>();
+    context.register_operation::<MaxU>();
+    context.register_operation::<MinS>();
+    context.register_operation::<MinU>();
+    context.register_operation::<Shl>();
+    context.register_operation::<ShrS>();
+    context.register_operation::<ShrU>();
+    context.register_operation::<And>();
+    context.register_operation::<Or>();
+    context.register_operation::<Xor>();
+    context.register_operation::<CastS>();
+    context.register_operation::<CastU>();
+    context.register_operation::<Cmp>();
+    context.register_operation::<SizeOf>();
+    context.register_operation::<Constant>();
+    context.register_operation::<BoolConstant>();
 }
 
-//===----------------------------------------------------------------------===//
-// BoolConstantOp
-//===----------------------------------------------------------------------===//
-
-def Index_BoolConstantOp : IndexOp<"bool.constant", [
-    ConstantLike,
-    DeclareOpInterfaceMethods<OpAsmOpInterface, ["getAsmResultNames"]>
-  ]> {
-  let summary = "boolean constant";
-  let description = [{
-    The `index.bool.constant` operation produces an bool-typed SSA value equal
-    to either `true` or `false`.
-
-    This operation is used to materialize bool constants that arise when folding
-    `index.cmp`.
-
-    Example:
-
-    ```mlir
-    %0 = index.bool.constant true
-    ```
-  }];
-
-  let arguments = (ins BoolAttr:$value);
-  let results = (outs I1:$result);
-  let assemblyFormat = "attr-dict $value";
-  let hasFolder = 1;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cmp_predicate_keywords() {
+        for &(predicate, keyword) in IndexCmpPredicate::CASES {
+            assert_eq!(predicate.stringify(), keyword);
+            assert_eq!(IndexCmpPredicate::symbolise(keyword), Some(predicate));
+            assert_eq!(IndexCmpPredicate::from_attribute(&predicate.to_attribute()),
+                Some(predicate));
+        }
+        assert_eq!(IndexCmpPredicate::symbolise("lt"), None);
+        assert_eq!(IndexCmpPredicate::keywords(),
+            "eq, ne, slt, sle, sgt, sge, ult, ule, ugt, uge");
+    }
 }
@@ -1,2 +1,4 @@
 //! - include <https://github.com/llvm/llvm-project/tree/main/mlir/include/mlir/Dialect/LLVMIR>
 //! - lib <https://github.com/llvm/llvm-project/tree/main/mlir/lib/Dialect/LLVMIR>
+
+pub mod types;
@@ -0,0 +1,388 @@
+/*!
+# LLVM Dialect Types
+
+This file defines the types of the LLVM dialect, and the hooks parsing and printing them, of which only structure types are supported so far.
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Dialect/LLVMIR/LLVMTypes.h>
+- lib
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Dialect/LLVMIR/IR/LLVMTypes.cpp>
+  - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Dialect/LLVMIR/IR/LLVMTypeSyntax.cpp>
+*/
+
+use core::fmt;
+use std::sync::Arc;
+
+use crate::{
+    asm_parser::parser::ParseResult,
+    ir::{
+        dialect::implementation::{DialectAsmParser, DialectAsmPrinter},
+        mlir_context::MLIRContext,
+        operation::implementation::print_escaped_string,
+        r#type::{Type, detail::IdentifiedTypeStorage}
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
+// LLVMStructType
+// ----------------------------------------------------------------------
+
+/// The body of an LLVM structure type.
+#[derive(Clone)]
+pub struct StructBody {
+    elements: Vec<Arc<dyn Type>>,
+    packed: bool
+}
+
+impl StructBody {
+    pub fn new(elements: Vec<Arc<dyn Type>>, packed: bool) -> Self {
+        Self { elements, packed }
+    }
+
+    pub fn elements(&self) -> &[Arc<dyn Type>] {
+        &self.elements
+    }
+
+    pub fn is_packed(&self) -> bool {
+        self.packed
+    }
+}
+
+impl PartialEq for StructBody {
+    fn eq(&self, other: &Self) -> bool {
+        // Types are not uniqued, so compare them by their printed form.
+        self.packed == other.packed
+            && self.elements.len() == other.elements.len()
+            && self.elements.iter().zip(&other.elements).all(|(left, right)|
+                Arc::ptr_eq(left, right) || left.to_string() == right.to_string())
+    }
+}
+
+/// The storage of identified structure types, uniqued by name within a context.
+pub type LLVMStructTypeStorage = IdentifiedTypeStorage<StructBody>;
+
+/**
+LLVM dialect structure type representing a collection of different-typed elements manipulated together. Structures can optionally be packed, meaning that their elements immediately follow each other in memory without accounting for potential alignment.
+
+Structure types can be identified (named) or literal. Literal structures are uniquely represented by the list of types they contain and packedness. Literal structure types are immutable after construction.
+
+Identified structures are uniquely represented by their name, a string. They have a mutable component, consisting of the list of types they contain and the packedness. The body of an identified structure can be set once, after which it cannot change, so that the body can refer to the structure itself, e.g. through a pointer. Until then, the structure is opaque.
+
+# Syntax
+
+```text
+llvm-struct-type ::= `struct` `<` struct-body `>`
+                   | `struct` `<` string-literal (`,` struct-body)? `>`
+struct-body      ::= `opaque` | `packed`? `(` (llvm-type (`,` llvm-type)*)? `)`
+```
+
+The body of an identified structure is omitted when the structure is referenced from within its own body, e.g. `!llvm.struct<"node", (i32, !llvm.ptr<!llvm.struct<"node">>)>`.
+*/
+#[derive(Clone)]
+pub enum LLVMStructType {
+    Identified(Arc<LLVMStructTypeStorage>),
+    Literal(StructBody)
+}
+
+impl Type for LLVMStructType {}
+
+impl LLVMStructType {
+    /// Register the storage of identified structure types with the type uniquer of `context`. This must be done before identified structure types are created in `context`.
+    pub fn register(context: &mut MLIRContext) {
+        let uniquer = context.type_uniquer_mut();
+        if !uniquer.is_parametric_storage_initialised::<LLVMStructTypeStorage>() {
+            uniquer.register_parametric_storage_type::<LLVMStructTypeStorage>();
+        }
+    }
+
+    /// Get or create an identified structure type with the given name. The structure is opaque until its body is set with `set_body`.
+    pub fn identified(context: &MLIRContext, name: &str) -> Self {
+        Self::Identified(context.type_uniquer().get(name.to_string()))
+    }
+
+    /// Create a literal structure type with the given elements.
+    pub fn literal(elements: Vec<Arc<dyn Type>>, packed: bool) -> Self {
+        Self::Literal(StructBody::new(elements, packed))
+    }
+
+    /// Set the body of an identified structure type. Fails if the structure already has a different body.
+    pub fn set_body(
+        &self,
+        context: &MLIRContext,
+        elements: Vec<Arc<dyn Type>>,
+        packed: bool
+    ) -> LogicalResult {
+        match self {
+            Self::Identified(storage) => context.type_uniquer().mutate(
+                storage.as_ref(), StructBody::new(elements, packed)),
+            Self::Literal(_) => Err(
+                "only identified structs can be initialised".into())
+        }
+    }
+
+    /// Returns true if the structure is identified.
+    pub fn is_identified(&self) -> bool {
+        matches!(self, Self::Identified(_))
+    }
+
+    /// Returns true if the structure is opaque, i.e. it is identified and its body has not been set.
+    pub fn is_opaque(&self) -> bool {
+        matches!(self, Self::Identified(storage) if !storage.is_initialised())
+    }
+
+    /// Returns the name of an identified structure.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Self::Identified(storage) => Some(storage.name()),
+            Self::Literal(_) => None
+        }
+    }
+
+    /// Returns the body of the structure, or None if it is opaque.
+    pub fn body(&self) -> Option<&StructBody> {
+        match self {
+            Self::Identified(storage) => storage.body(),
+            Self::Literal(body) => Some(body)
+        }
+    }
+
+    /// Returns the list of element types contained in a non-opaque structure.
+    pub fn elements(&self) -> &[Arc<dyn Type>] {
+        self.body().map_or(&[], StructBody::elements)
+    }
+
+    /// Checks if a structure is packed.
+    pub fn is_packed(&self) -> bool {
+        self.body().map_or(false, StructBody::is_packed)
+    }
+}
+
+// ----------------------------------------------------------------------
+// Printing
+// ----------------------------------------------------------------------
+
+/// Print a type registered to the LLVM dialect, without the `!llvm` prefix.
+pub fn print_type(r#type: &dyn Type, printer: &mut dyn DialectAsmPrinter)
+    -> fmt::Result
+{
+    if let Some(r#type) = r#type.downcast_ref::<LLVMStructType>() {
+        printer.write_str("struct")?;
+        return print_struct_type(printer, r#type);
+    }
+    printer.write_str("<<UNKNOWN TYPE>>")
+}
+
+/**
+Print the body of a structure type. Identified structures print their name first. A self-reference of an identified structure, i.e. a reference from within its own body, prints the name only.
+*/
+fn print_struct_type(
+    printer: &mut dyn DialectAsmPrinter,
+    r#type: &LLVMStructType
+) -> fmt::Result {
+    printer.write_char('<')?;
+    let storage = match r#type {
+        LLVMStructType::Identified(storage) => storage,
+        LLVMStructType::Literal(body) => {
+            print_struct_body(printer, body)?;
+            return printer.write_char('>');
+        }
+    };
+    print_escaped_string(printer, storage.name())?;
+    if let Some(mut printer) = printer.try_start_cyclic_print(storage.as_ref()) {
+        printer.write_str(", ")?;
+        match storage.body() {
+            Some(body) => print_struct_body(&mut *printer, body)?,
+            None => printer.write_str("opaque")?
+        }
+    }
+    printer.write_char('>')
+}
+
+fn print_struct_body(printer: &mut dyn DialectAsmPrinter, body: &StructBody)
+    -> fmt::Result
+{
+    if body.is_packed() {
+        printer.write_str("packed ")?;
+    }
+    printer.write_char('(')?;
+    printer.print_type_list(body.elements())?;
+    printer.write_char(')')
+}
+
+// ----------------------------------------------------------------------
+// Parsing
+// ----------------------------------------------------------------------
+
+/// Parse a type registered to the LLVM dialect, to be attached to the parser configuration with `ParserConfig::attach_type_parser`. The storage of identified structures must have been registered with `LLVMStructType::register`.
+pub fn parse_type(parser: &mut dyn DialectAsmParser)
+    -> ParseResult<Arc<dyn Type>>
+{
+    if parser.parse_optional_keyword("struct") {
+        return parse_struct_type(parser).map(|r#type| Arc::new(r#type) as _);
+    }
+    Err(parser.emit_error(parser.name_location(), format!(
+        "unknown LLVM type: {}", parser.full_symbol_spec())))
+}
+
+/**
+Parse a structure type. An identified structure type is created, or looked up, as soon as its name is parsed, so that it can be referenced from within its body. Such a reference consists of the name only.
+*/
+fn parse_struct_type(parser: &mut dyn DialectAsmParser)
+    -> ParseResult<LLVMStructType>
+{
+    parser.parse_less()?;
+
+    let name_location = parser.current_location();
+    let Some(name) = parser.parse_optional_string() else {
+        // A literal structure.
+        let packed = parser.parse_optional_keyword("packed");
+        let elements = parse_struct_body(parser)?;
+        parser.parse_greater()?;
+        return Ok(LLVMStructType::literal(elements, packed));
+    };
+
+    let r#type = LLVMStructType::identified(parser.context(), &name);
+    let LLVMStructType::Identified(storage) = &r#type else { unreachable!() };
+    if let Some(mut parser) = parser.try_start_cyclic_parse(storage.as_ref()) {
+        parser.parse_comma()?;
+
+        // An opaque structure, whose body is left unset.
+        if parser.parse_optional_keyword("opaque") {
+            parser.parse_greater()?;
+            if !r#type.is_opaque() {
+                return Err(parser.emit_error(name_location, format!(
+                    "identified type '{}' is already initialised", name)));
+            }
+            return Ok(r#type);
+        }
+
+        let packed = parser.parse_optional_keyword("packed");
+        let elements = parse_struct_body(&mut *parser)?;
+        parser.parse_greater()?;
+        if r#type.set_body(parser.context(), elements, packed).is_err() {
+            return Err(parser.emit_error(name_location, format!(
+                "identified type '{}' already used with a different body", name)));
+        }
+        return Ok(r#type);
+    }
+
+    // The body of the structure is being parsed, so this is a reference to the structure itself.
+    parser.parse_greater()?;
+    Ok(r#type)
+}
+
+/// Parse the parenthesised list of element types of a structure.
+fn parse_struct_body(parser: &mut dyn DialectAsmParser)
+    -> ParseResult<Vec<Arc<dyn Type>>>
+{
+    parser.parse_l_paren()?;
+    let mut elements = Vec::new();
+    if parser.parse_optional_r_paren() {
+        return Ok(elements);
+    }
+    loop {
+        elements.push(parser.parse_type()?);
+        if !parser.parse_optional_comma() {
+            break;
+        }
+    }
+    parser.parse_r_paren()?;
+    Ok(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ir::builtins::types::{IntegerType, Signedness},
+        support::storage_uniquer::StorageUniquer
+    };
+
+    fn integer(width: usize) -> Arc<dyn Type> {
+        Arc::new(IntegerType::new(width, Signedness::Signless))
+    }
+
+    /// Return a uniquer holding the storage of identified structures, standing in for the type uniquer of a context.
+    fn uniquer() -> StorageUniquer {
+        let mut uniquer = StorageUniquer::new();
+        uniquer.register_parametric_storage_type::<LLVMStructTypeStorage>();
+        uniquer
+    }
+
+    fn identified(uniquer: &StorageUniquer, name: &str) -> LLVMStructType {
+        LLVMStructType::Identified(uniquer.get(name.to_string()))
+    }
+
+    /// Set the body of the identified structure `r#type`.
+    fn set_body(uniquer: &StorageUniquer, r#type: &LLVMStructType, body: StructBody)
+        -> LogicalResult
+    {
+        let LLVMStructType::Identified(storage) = r#type else {
+            panic!("Expected an identified structure");
+        };
+        uniquer.mutate(storage.as_ref(), body)
+    }
+
+    fn print(r#type: LLVMStructType) -> String {
+        (Arc::new(r#type) as Arc<dyn Type>).to_string()
+    }
+
+    #[test]
+    fn literal_structs_are_printed() {
+        let r#type = LLVMStructType::literal(vec![integer(32), integer(64)], false);
+        assert!(!r#type.is_identified() && !r#type.is_opaque());
+        assert_eq!(print(r#type), "!llvm.struct<(i32, i64)>");
+        assert_eq!(print(LLVMStructType::literal(vec![integer(8)], true)),
+                   "!llvm.struct<packed (i8)>");
+        assert_eq!(print(LLVMStructType::literal(Vec::new(), false)), "!llvm.struct<()>");
+    }
+
+    #[test]
+    fn identified_structs_are_uniqued_by_name() {
+        let uniquer = uniquer();
+        let (LLVMStructType::Identified(first), LLVMStructType::Identified(second))
+            = (identified(&uniquer, "node"), identified(&uniquer, "node"))
+        else {
+            unreachable!()
+        };
+        assert!(Arc::ptr_eq(&first, &second));
+
+        let node = LLVMStructType::Identified(first);
+        assert!(node.is_identified() && node.is_opaque());
+        assert_eq!(node.name(), Some("node"));
+        assert!(node.body().is_none() && node.elements().is_empty() && !node.is_packed());
+        assert_eq!(print(node), "!llvm.struct<\"node\", opaque>");
+    }
+
+    #[test]
+    fn bodies_are_set_once() {
+        let uniquer = uniquer();
+        let pair = identified(&uniquer, "pair");
+        let body = || StructBody::new(vec![integer(32), integer(32)], false);
+        set_body(&uniquer, &pair, body()).unwrap();
+        assert!(!pair.is_opaque());
+        assert_eq!(pair.elements().len(), 2);
+
+        // Redeclaring the same body is allowed, but not a different one.
+        set_body(&uniquer, &pair, body()).unwrap();
+        assert!(set_body(&uniquer, &pair, StructBody::new(vec![integer(32)], false)).is_err());
+        assert!(set_body(&uniquer, &pair, StructBody::new(body().elements, true)).is_err());
+        assert_eq!(print(pair), "!llvm.struct<\"pair\", (i32, i32)>");
+    }
+
+    #[test]
+    fn self_references_print_the_name_only() {
+        let uniquer = uniquer();
+        let node = identified(&uniquer, "node");
+        let body = StructBody::new(vec![integer(32), Arc::new(node.clone())], false);
+        set_body(&uniquer, &node, body).unwrap();
+        assert_eq!(print(node.clone()),
+                   "!llvm.struct<\"node\", (i32, !llvm.struct<\"node\">)>");
+
+        // The body is printed again outside of the printing of the structure.
+        let wrapper = LLVMStructType::literal(vec![Arc::new(node.clone()), Arc::new(node)], false);
+        assert_eq!(print(wrapper), "!llvm.struct<(\
+            !llvm.struct<\"node\", (i32, !llvm.struct<\"node\">)>, \
+            !llvm.struct<\"node\", (i32, !llvm.struct<\"node\">)>)>");
+    }
+}
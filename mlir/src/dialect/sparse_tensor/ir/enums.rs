@@ -63,7 +63,7 @@ This enum defines all the sparse representations supportable by the SparseTensor
 The `Undef` 'format' is a special value used internally for cases where we need to store an undefined or indeterminate `DimLevelType`.
 It should not be used externally, since it does not indicate an actual/representable format.
 */
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 #[repr(align(8))]
 pub enum DimLevelType {
     Undef = 0,           // 0b000_00
@@ -78,6 +78,24 @@ pub enum DimLevelType {
     SingletonNuNo = 19,  // 0b100_11
 }
 
+impl DimLevelType {
+    /// Returns string representation of the given dimension level type.
+    pub const fn to_mlir_string(self) -> &'static str {
+        match self {
+            Self::Undef => "undef",
+            Self::Dense => "dense",
+            Self::Compressed => "compressed",
+            Self::CompressedNu => "compressed-nu",
+            Self::CompressedNo => "compressed-no",
+            Self::CompressedNuNo => "compressed-nu-no",
+            Self::Singleton => "singleton",
+            Self::SingletonNu => "singleton-nu",
+            Self::SingletonNo => "singleton-no",
+            Self::SingletonNuNo => "singleton-nu-no"
+        }
+    }
+}
+
 /// This enum defines all the storage formats supported by the sparse compiler,
 /// without the level properties.
 #[repr(align(8))]
@@ -2,14 +2,23 @@
 # Attributes definitions
 
 - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Dialect/SparseTensor/IR/SparseTensorAttrDefs.td>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Dialect/SparseTensor/IR/SparseTensorDialect.cpp>
 */
 
-use crate::mlir::{
-    dialect::sparse_tensor::ir::sparse_tensor_base,
+use core::fmt;
+use std::sync::Arc;
+
+use crate::{
+    asm_parser::parser::ParseResult,
+    dialect::sparse_tensor::ir::enums::DimLevelType,
     ir::{
-        attr_type_base,
-        enum_attr,
-        tensor_encoding
+        affine_map::AffineMap,
+        attr_type_base::{AttrOrTypeDef, AttrOrTypeDefinition},
+        attribute::Attribute,
+        dialect::implementation::{
+            DialectAsmParser, DialectAsmPrinter, FieldParser, FieldPrinter
+        },
+        operation::implementation::{AsmParser, print_escaped_string}
     }
 };
 
@@ -131,8 +140,93 @@ Examples:
 ... tensor<?x?xf64, #ELL> ...
 ```
 */
-pub trait SparseTensorEncodingAttr {
-    
+#[derive(Clone, PartialEq, AttrOrTypeDef)]
+#[attr_or_type(
+    mnemonic = "encoding",
+    assembly_format = "`<` `{` struct(params) `}` `>`"
+)]
+pub struct SparseTensorEncodingAttr {
+    #[param(name = "dimLevelType")]
+    dim_level_type: Vec<DimLevelType>,
+    #[param(name = "dimOrdering")]
+    dim_ordering: Option<AffineMap>,
+    #[param(name = "higherOrdering")]
+    higher_ordering: Option<AffineMap>,
+    #[param(name = "pointerBitWidth", default)]
+    pointer_bit_width: u32,
+    #[param(name = "indexBitWidth", default)]
+    index_bit_width: u32
+}
+
+impl Attribute for SparseTensorEncodingAttr {}
+
+impl SparseTensorEncodingAttr {
+    pub fn new(
+        dim_level_type: Vec<DimLevelType>,
+        dim_ordering: Option<AffineMap>,
+        higher_ordering: Option<AffineMap>,
+        pointer_bit_width: u32,
+        index_bit_width: u32
+    ) -> Self {
+        Self {
+            dim_level_type,
+            dim_ordering,
+            higher_ordering,
+            pointer_bit_width,
+            index_bit_width
+        }
+    }
+
+    pub fn dim_level_type(&self) -> &[DimLevelType] {
+        &self.dim_level_type
+    }
+
+    pub fn dim_ordering(&self) -> Option<&AffineMap> {
+        self.dim_ordering.as_ref()
+    }
+
+    pub fn higher_ordering(&self) -> Option<&AffineMap> {
+        self.higher_ordering.as_ref()
+    }
+
+    /// Returns the bit width of pointers, or 0 for the native bit width.
+    pub fn pointer_bit_width(&self) -> u32 {
+        self.pointer_bit_width
+    }
+
+    /// Returns the bit width of indices, or 0 for the native bit width.
+    pub fn index_bit_width(&self) -> u32 {
+        self.index_bit_width
+    }
+}
+
+// Parse a dimension level type, written as a string, e.g. `"compressed-nu"`.
+impl FieldParser for DimLevelType {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        let location = parser.current_location();
+        let string = parser.parse_string()?;
+        [
+            DimLevelType::Dense,
+            DimLevelType::Compressed,
+            DimLevelType::CompressedNu,
+            DimLevelType::CompressedNo,
+            DimLevelType::CompressedNuNo,
+            DimLevelType::Singleton,
+            DimLevelType::SingletonNu,
+            DimLevelType::SingletonNo,
+            DimLevelType::SingletonNuNo
+        ]
+        .into_iter()
+        .find(|r#type| r#type.to_mlir_string() == string)
+        .ok_or_else(|| parser.emit_error(location, format!(
+            "unexpected dimension level type: {}", string)))
+    }
+}
+
+impl FieldPrinter for DimLevelType {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        print_escaped_string(printer, self.to_mlir_string())
+    }
 }
 
 /// The C++ enum for Storage Specifier kind.
@@ -144,3 +238,31 @@ pub enum SparseTensorStorageSpecifierKindEnum {
 pub struct SparseTensorStorageSpecifierKindAttr {
 
 }
+
+// ----------------------------------------------------------------------
+// Parsing and Printing
+// ----------------------------------------------------------------------
+
+/// Parse an attribute registered to the sparse tensor dialect, without the `#sparse_tensor.` prefix.
+pub fn parse_attribute(parser: &mut dyn DialectAsmParser)
+    -> ParseResult<Arc<dyn Attribute>>
+{
+    let location = parser.current_location();
+    match parser.parse_optional_any_keyword().as_deref() {
+        Some(SparseTensorEncodingAttr::MNEMONIC) => SparseTensorEncodingAttr::parse(parser)
+            .map(|attribute| Arc::new(attribute) as _),
+        _ => Err(parser.emit_error(location, format!(
+            "unknown sparse tensor attribute: {}", parser.full_symbol_spec())))
+    }
+}
+
+/// Print an attribute registered to the sparse tensor dialect, without the `#sparse_tensor.` prefix.
+pub fn print_attribute(attribute: &dyn Attribute, printer: &mut dyn DialectAsmPrinter)
+    -> fmt::Result
+{
+    if let Some(attribute) = attribute.downcast_ref::<SparseTensorEncodingAttr>() {
+        printer.write_str(SparseTensorEncodingAttr::MNEMONIC)?;
+        return attribute.print(printer);
+    }
+    printer.write_str("<<UNKNOWN ATTRIBUTE>>")
+}
@@ -1872,16 +1872,16 @@ impl AssemblyFormat for OuterProduct {
           return parser.emit_error(parser.name_loc(),
                                   "Expected vector type for operand #1");
       
-        let num_scalable_dims = v_lhs.num_scalable_dims();
+        let scalable_dims = vec![v_lhs.scalable_dims()[0]];
         Vector res_type;
         if (v_rhs) {
-          num_scalable_dims += v_rhs.num_scalable_dims();
+          scalable_dims.push(v_rhs.scalable_dims()[0]);
           res_type = Vector::new({v_lhs.dim_size(0), v_rhs.dim_size(0)},
-                                    v_lhs.element_type, num_scalable_dims);
+                                    v_lhs.element_type, scalable_dims);
         } else {
           // Scalar RHS operand
           res_type = Vector::new({v_lhs.dim_size(0)}, v_lhs.element_type,
-                                    num_scalable_dims);
+                                    scalable_dims);
         }
       
         if (!result.attributes.get("kind")) {
@@ -19,6 +19,7 @@ use crate::{
             builders,
             builtins::{
                 attributes,
+                type_interfaces::is_dynamic,
                 types
             },
             operation::{
@@ -109,17 +110,17 @@ pub trait OffsetSizeAndStrideOpInterface: OpInterface {
 
     /// Returns true if the offset `index` is dynamic.
     fn is_dynamic_offset(&self, index: usize) -> bool {
-        ShapedType::is_dynamic(self.static_offsets()[index])
+        is_dynamic(self.static_offsets()[index])
     }
 
     /// Returns true if the size `index` is dynamic.
     fn is_dynamic_size(&self, index: usize) -> bool {
-        ShapedType::is_dynamic(self.static_sizes()[index])
+        is_dynamic(self.static_sizes()[index])
     }
 
     /// Returns true if the stride `index` is dynamic.
     fn is_dynamic_stride(&self, index: usize) -> bool {
-        ShapedType::is_dynamic(self.static_strides()[index])
+        is_dynamic(self.static_strides()[index])
     }
 
     /// Assert the offset `index` is a static constant and return its value.
@@ -147,7 +148,7 @@ pub trait OffsetSizeAndStrideOpInterface: OpInterface {
         assert!(self.is_dynamic_offset(index), "Expected dynamic offset");
         let num_dynamic = num_dynamic_entries_up_to_idx(
             static_offsets(),
-            ShapedType::is_dynamic,
+            is_dynamic,
             index);
         self.offset_size_and_stride_start_operand_index() + num_dynamic
     }
@@ -158,7 +159,7 @@ pub trait OffsetSizeAndStrideOpInterface: OpInterface {
     fn index_of_dynamic_size(&self, index: usize) -> usize {
         assert!(self.is_dynamic_size(index), "Expected dynamic size");
         let num_dynamic = num_dynamic_entries_up_to_idx(
-            static_sizes(), ShapedType::is_dynamic, index);
+            static_sizes(), is_dynamic, index);
         self.offset_size_and_stride_start_operand_index() +
           offsets().size() + num_dynamic
     }
@@ -170,7 +171,7 @@ pub trait OffsetSizeAndStrideOpInterface: OpInterface {
         assert!(self.is_dynamic_stride(index), "Expected dynamic stride");
         let num_dynamic = num_dynamic_entries_up_to_idx(
             self.static_strides(),
-            ShapedType::is_dynamic,
+            is_dynamic,
             index);
         self.offset_size_and_stride_start_operand_index() +
             self.offsets().size() + self.sizes().size() + num_dynamic
@@ -3,6 +3,7 @@
 
 pub mod affine_expr;
 pub mod affine_map;
+pub mod arena;
 pub mod asm_printer;
 pub mod asm_state;
 pub mod attr_type_base;
@@ -12,29 +12,20 @@ use core::{
     cmp::PartialEq,
     fmt::Display,
     hash::Hash,
-    ops::{Add, Div, Mul, Neg, Rem, Sub},
-};
-use llvm::{
-    adt::{
-        dense_map::DenseMap,
-        dense_map_info,
-        hashing,
-        small_vector::SmallVector
-    },
-    support::casting
+    ops::{Add, Mul, Neg, Rem, Sub},
 };
+use std::collections::HashMap;
+
 use crate::{
     ir::{
+        affine_expr::visitor::{AffineExprVisitor, SimpleAffineExprFlattener},
         affine_map::AffineMap,
         mlir_context::MLIRContext
     },
-    support::{
-        llvm,
-        storage_uniquer::{Storage, StorageAllocator}
-    }
+    support::math_extras::{ceil_div, floor_div, gcd, r#mod}
 };
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Kind {
     Add,
     /// RHS of mul is always a constant or a symbolic expression.
@@ -47,7 +38,7 @@ pub enum Kind {
     CeilDiv,
 }
 
-#[derive(Hash, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub enum AffineExpr {
     /// Affine binary operation expression. An affine binary operation could be an add, mul, floordiv, ceildiv, or a modulo operation. (Subtraction is represented through a multiply by -1 and add.) These expressions are always constructed in a simplified form. For eg., the LHS and RHS operands can't both be constants. There are additional canonicalising rules depending on the op type: see checks in the constructor.
     BinaryOperation {
@@ -57,7 +48,6 @@ pub enum AffineExpr {
     },
     /// A dimensional identifier appearing in an affine expression.
     Dimension {
-        kind: Kind,
         index: usize
     },
     /// A symbolic identifier appearing in an affine expression.
@@ -92,21 +82,26 @@ impl AffineExpr {
   
     // void print(raw_ostream &os) const;
     // void dump() const;
+
+    /// Build the binary operation `lhs kind rhs` as is, without simplifying it.
+    fn raw(kind: Kind, lhs: Self, rhs: Self) -> Self {
+        BinaryOperation { kind, lhs: Box::new(lhs), rhs: Box::new(rhs) }
+    }
   
     /// Returns true if this expression is made out of only symbols and constants, i.e., it does not involve dimensional identifiers.
-    fn is_symbolic_or_constant(&self) -> bool {
+    pub fn is_symbolic_or_constant(&self) -> bool {
         match self {
             BinaryOperation { lhs, rhs, .. } =>
                    lhs.is_symbolic_or_constant()
                 && rhs.is_symbolic_or_constant(),
-            Dimension => false,
-            Symbol => true,
-            Constant => true
+            Dimension { .. } => false,
+            Symbol { .. } => true,
+            Constant { .. } => true
         }
     }
   
     /// Returns true if this is a pure affine expression, i.e., multiplication, floordiv, ceildiv, and mod is only allowed w.r.t constants.
-    fn is_pure_affine(&self) -> bool {
+    pub fn is_pure_affine(&self) -> bool {
         match self {
             BinaryOperation { kind, lhs, rhs } => {
                 use Kind::*;
@@ -119,22 +114,23 @@ impl AffineExpr {
                            lhs.is_pure_affine()
                         && rhs.is_pure_affine()
                         && (
-                               matches!(lhs, Constant) 
-                            || matches!(rhs, Constant) 
+                               matches!(lhs.as_ref(), Constant { .. })
+                            || matches!(rhs.as_ref(), Constant { .. })
                         )
                     ,
                     FloorDiv | CeilDiv | Mod =>
-                        lhs.is_pure_affine() && matches!(rhs, Constant) 
+                        lhs.is_pure_affine()
+                        && matches!(rhs.as_ref(), Constant { .. })
                 }
             },
-            Dimension => true,
-            Symbol => true,
-            Constant => true
+            Dimension { .. } => true,
+            Symbol { .. } => true,
+            Constant { .. } => true
         }
     }
   
     /// Returns the greatest known integral divisor of this affine expression. The result is always positive.
-    fn largest_known_divisor(&self) -> i64 {
+    pub fn largest_known_divisor(&self) -> i64 {
         match self {
             BinaryOperation { kind, lhs, rhs } => {
                 use Kind::*;
@@ -142,31 +138,30 @@ impl AffineExpr {
                     CeilDiv | FloorDiv => {
                         // If the RHS is a constant and divides the known divisor on the LHS, the quotient is a known divisor of the expression.
                         // Leave alone undefined expressions.
-                        if let Constant { value } = rhs && value != 0 {
+                        if let Constant { value } = rhs.as_ref() {
                             let lhs_div = lhs.largest_known_divisor();
-                            if lhs_div % value == 0 {
-                                return lhs_div / value;
+                            if *value != 0 && lhs_div % value == 0 {
+                                return (lhs_div / value).abs();
                             }
                         }
-                        return 1;
+                        1
                     }
                     Mul =>
                           lhs.largest_known_divisor()
                         * rhs.largest_known_divisor(),
-                    Add | Mod => {
-                        std::gcd(lhs.largest_known_divisor() as u64,
-                                 rhs.largest_known_divisor() as u64)
-                    }
+                    Add | Mod =>
+                        gcd(lhs.largest_known_divisor(),
+                            rhs.largest_known_divisor())
                 }
             },
-            Dimension => 1,
-            Symbol => 1,
+            Dimension { .. } => 1,
+            Symbol { .. } => 1,
             Constant { value } => value.abs()
         }
     }
   
     /// Return true if the affine expression is a multiple of 'factor'.
-    fn is_multiple_of(&self, factor: i64) -> bool {
+    pub fn is_multiple_of(&self, factor: i64) -> bool {
         match self {
             BinaryOperation { kind, lhs, rhs } => {
                 use Kind::*;
@@ -182,114 +177,101 @@ impl AffineExpr {
                         || (l * u) % factor == 0
                     },
                     Add | FloorDiv | CeilDiv | Mod =>
-                        std::gcd(lhs.largest_known_divisor() as u64,
-                                 rhs.largest_known_divisor() as u64)
-                        % factor == 0
+                        self.largest_known_divisor() % factor == 0
                 }
             },
-            Dimension => factor * factor == 1,
-            Symbol => factor * factor == 1,
+            Dimension { .. } => factor * factor == 1,
+            Symbol { .. } => factor * factor == 1,
             Constant { value } => value % factor == 0
         }
     }
   
     /// Return true if the affine expression involves AffineDimExpr `index`.
-    pub fn is_function_of_dim(self, index: usize) -> bool {
+    pub fn is_function_of_dim(&self, index: usize) -> bool {
         match self {
             BinaryOperation { lhs, rhs, .. } =>
                    lhs.is_function_of_dim(index)
                 || rhs.is_function_of_dim(index),
-            Dimension => self == dimension(index, self.context()),
-            Symbol => false,
-            Constant => false
+            Dimension { index: position } => *position == index,
+            Symbol { .. } => false,
+            Constant { .. } => false
         }
     }
   
     /// Return true if the affine expression involves AffineSymbolExpr `index`.
-    pub fn is_function_of_symbol(self, index: usize) -> bool {
+    pub fn is_function_of_symbol(&self, index: usize) -> bool {
         match self {
-            BinaryOperation { lhs, rhs, .. } => {
+            BinaryOperation { lhs, rhs, .. } =>
                    lhs.is_function_of_symbol(index)
-                || rhs.is_function_of_symbol(index)
-            },
-            Dimension => false,
-            Symbol => self == symbol(index, self.context()),
-            Constant => false
+                || rhs.is_function_of_symbol(index),
+            Dimension { .. } => false,
+            Symbol { index: position } => *position == index,
+            Constant { .. } => false
         }
     }
   
     /// Walk all of the AffineExpr's in this expression in postorder.
-    pub fn walk(&self, callback: fn(Self)) {
-        AffineExprWalker::new(callback).walk_post_order(&self);
+    pub fn walk(&self, callback: impl FnMut(&Self)) {
+        AffineExprWalker::new(callback).walk_post_order(self);
     }
   
     /// This method substitutes any uses of dimensions and symbols (e.g. dim#0 with dim_replacements[0]) and returns the modified expression tree.
     /// This is a dense replacement method: a replacement must be specified for every single dim and symbol.
     pub fn replace_dims_and_symbols(
-        self,
-        dim_replacements: [&Self],
-        sym_replacements: [&Self]
+        &self,
+        dim_replacements: &[Self],
+        sym_replacements: &[Self]
     ) -> Self
     {
         match self {
             BinaryOperation { kind, lhs, rhs } => {
                 let new_lhs = lhs.replace_dims_and_symbols(dim_replacements, sym_replacements);
                 let new_rhs = rhs.replace_dims_and_symbols(dim_replacements, sym_replacements);
-                if new_lhs == lhs && new_rhs == rhs {
-                    return self;
-                }
-                binary_operation(kind, new_lhs, new_rhs)
-            },
-            Dimension { index, .. } => {
-                if index >= dim_replacements.len() {
-                    self
-                } else {
-                    dim_replacements[index]
-                }
-            },
-            Symbol { index } => {
-                if index >= dim_replacements.len() {
-                    self
-                } else {
-                    dim_replacements[index]
+                if new_lhs == **lhs && new_rhs == **rhs {
+                    return self.clone();
                 }
+                binary_operation(*kind, new_lhs, new_rhs)
             },
-            Constant => self
+            Dimension { index } =>
+                dim_replacements.get(*index).unwrap_or(self).clone(),
+            Symbol { index } =>
+                sym_replacements.get(*index).unwrap_or(self).clone(),
+            Constant { .. } => self.clone()
         }
     }   
   
     /// Dim-only version of replace_dims_and_symbols.
     pub fn replace_dims(&self, dim_replacements: &[Self]) -> Self {
-        self.replace_dims_and_symbols(dim_replacements, [])
+        self.replace_dims_and_symbols(dim_replacements, &[])
     }
   
     /// Symbol-only version of replace_dims_and_symbols.
     pub fn replace_symbols(&self, sym_replacements: &[Self]) -> Self {
-        self.replace_dims_and_symbols([], sym_replacements)
+        self.replace_dims_and_symbols(&[], sym_replacements)
     }
   
     /// Sparse replace method. Replace `expr` by `replacement` and return the modified expression tree.
     pub fn replace(&self, expr: Self, replacement: Self) -> Self {
-        let mut map = DenseMap::<Self, Self>::new();
+        let mut map = HashMap::<Self, Self>::new();
         map.insert(expr, replacement);
-        self.replace_(map)
+        self.replace_(&map)
     }
   
     /// Sparse replace method. If `*this` appears in `map` replaces it by `map[*this]` and return the modified expression tree. Otherwise traverse `*this` and apply replace with `map` on its subexpressions.
-    pub fn replace_(self, map: &DenseMap<Self, Self>) -> Self {
+    pub fn replace_(&self, map: &HashMap<Self, Self>) -> Self {
+        if let Some(expr) = map.get(self) {
+            return expr.clone();
+        }
         match self {
-            BinaryOperation { lhs, rhs, .. } => {
-                if let Some(expr) = map.find(&self) {
-                    return expr;
-                }
+            BinaryOperation { kind, lhs, rhs } => {
                 let new_lhs = lhs.replace_(map);
                 let new_rhs = rhs.replace_(map);
-                if new_lhs == lhs && new_rhs == rhs {
-                    return self
+                if new_lhs == **lhs && new_rhs == **rhs {
+                    return self.clone();
                 }
-                binary_operation(self.kind(), new_lhs, new_rhs)
+                binary_operation(*kind, new_lhs, new_rhs)
             },
-            Dimension { .. } | Symbol { .. } | Constant { .. } => self
+            Dimension { .. } | Symbol { .. } | Constant { .. } => self.clone()
         }
     }
   
@@ -301,28 +283,24 @@ impl AffineExpr {
         offset: usize /* 0 */
     ) -> Self
     {
-        let mut dims = SmallVector::<[Self; 4]>::new();
-        for index in 0..offset {
-            dims.push(dimension(index, self.context()));
-        }
-        for index in offset..num_dims {
-            dims.push(dimension(index + shift, self.context()));
-        }
-        self.replace_dims_and_symbols(dims.into(), &[])
+        let dims = (0..num_dims)
+            .map(|index| Dimension {
+                index: if index < offset { index } else { index + shift }
+            })
+            .collect::<Vec<_>>();
+        self.replace_dims_and_symbols(&dims, &[])
     }
   
     /// Replace symbols[offset ... num_symbols) by symbols[offset + shift ... shift + num_symbols).
     pub fn shift_symbols(&self, num_symbols: usize, shift: usize, offset: usize /* 0 */
     ) -> Self
     {
-        let mut symbols = SmallVector::<[Self; 4]>::new();
-        for index in 0..offset { 
-            symbols.push(symbol(index, self.context()));
-        }
-        for index in offset..num_symbols {
-            symbols.push(symbol(index + shift, self.context()));
-        }
-        self.replace_dims_and_symbols(&[], symbols.into())
+        let symbols = (0..num_symbols)
+            .map(|index| Symbol {
+                index: if index < offset { index } else { index + shift }
+            })
+            .collect::<Vec<_>>();
+        self.replace_dims_and_symbols(&[], &symbols)
     }
 
     /// Compose with an AffineMap.
@@ -335,37 +313,62 @@ impl AffineExpr {
     ///   expr: `d0 + d2`
     ///   map:  `(d0, d1, d2)[s0, s1] -> (d0 + s1, d1 + s0, d0 + d1 + d2)`
     ///   returned expr: `d0 * 2 + d1 + d2 + s1`
-    pub fn compose(&self, map: AffineMap) -> Self {
-        // let dim_replacements: SmallVector::<[Self; 8]> = ;
+    pub fn compose(&self, map: &AffineMap) -> Self {
         self.replace_dims_and_symbols(map.outputs(), &[])
     }
+
+    /// Returns `self floordiv other`, simplified where possible.
+    pub fn floor_div(self, other: impl Into<Self>) -> Self {
+        let other = other.into();
+        match simplify_floor_div(&self, &other) {
+            Some(simplified) => simplified,
+            None => Self::raw(Kind::FloorDiv, self, other)
+        }
+    }
+
+    /// Returns `self ceildiv other`, simplified where possible.
+    pub fn ceil_div(self, other: impl Into<Self>) -> Self {
+        let other = other.into();
+        match simplify_ceil_div(&self, &other) {
+            Some(simplified) => simplified,
+            None => Self::raw(Kind::CeilDiv, self, other)
+        }
+    }
+}
+
+impl From<i64> for AffineExpr {
+    fn from(value: i64) -> Self {
+        Constant { value }
+    }
 }
 
 // : AffineExprVisitor
-pub struct AffineExprWalker {
-    callback: fn(AffineExpr)  // std::function
+pub struct AffineExprWalker<F: FnMut(&AffineExpr)> {
+    callback: F  // std::function
 }
 
-impl AffineExprWalker {
-    pub fn new(callback: Fn(AffineExpr) /* std::function */) -> Self {
+impl<F: FnMut(&AffineExpr)> AffineExprWalker<F> {
+    pub fn new(callback: F) -> Self {
         Self { callback }
     }
+}
 
-    // pub fn visit_affine_binary_op_expr(&self, expr: BinaryOperation) {
-    //     self.callback(expr);
-    // }
+impl<F: FnMut(&AffineExpr)> AffineExprVisitor for AffineExprWalker<F> {
+    fn visit_affine_binary_op_expr(&mut self, expr: &AffineExpr) {
+        (self.callback)(expr);
+    }
 
-    // pub fn visit_constant_expr(&self, expr: Constant) {
-    //     self.callback(expr);
-    // }
+    fn visit_constant_expr(&mut self, expr: &AffineExpr) {
+        (self.callback)(expr);
+    }
 
-    // pub fn visit_dim_expr(&self, expr: Dimension) {
-    //     self.callback(expr);
-    // }
+    fn visit_dim_expr(&mut self, expr: &AffineExpr) {
+        (self.callback)(expr);
+    }
 
-    // pub fn visit_symbol_expr(&self, expr: Symbol) {
-    //     self.callback(expr);
-    // }
+    fn visit_symbol_expr(&mut self, expr: &AffineExpr) {
+        (self.callback)(expr);
+    }
 }
 
 // inline AffineExpr operator+(int64_t val, AffineExpr expr) { return expr + val; }
@@ -377,8 +380,8 @@ impl AffineExprWalker {
 impl Add<i64> for AffineExpr {
     type Output = Self;
 
-    fn add(self, rhs: i16) -> Self::Output {
-        self + constant(rhs, self.context())
+    fn add(self, rhs: i64) -> Self::Output {
+        self + Constant { value: rhs }
     }
 }
 
@@ -386,150 +389,94 @@ impl Add for AffineExpr {
     type Output = Self;
 
     fn add(self, other: Self) -> Self::Output {
-        if let Some(simplified) = simplify_add(self, other) {
-            return simplified;
+        match simplify_add(&self, &other) {
+            Some(simplified) => simplified,
+            None => Self::raw(Kind::Add, self, other)
         }
-
-        let uniquer = self.context().affine_uniquer();
-        uniquer.get::<BinaryOperation>(
-            /*initFn=*/{}, static_cast::<usize>(Kind::Add), self, other)
     }
 }
 
-/// Simplify add expression. Return nullptr if it can't be simplified.
-pub const fn simplify_add(lhs: AffineExpr, rhs: AffineExpr) -> AffineExpr {
-    match (lhs, rhs) {
-        // Fold if both LHS, RHS are a constant.
-        (Constant { value: lhs_value }, Constant { value: rhs_value })
-            =>  constant(lhs_value + rhs_value, lhs.context()),
-        // Canonicalise so that only the RHS is a constant. (4 + d0 becomes d0 + 4).
-        // If only one of them is a symbolic expressions, make it the RHS.
-        (Constant, rhs) => rhs + lhs,
-        (lhs, rhs)
-        if lhs.is_symbolic_or_constant() && !rhs.is_symbolic_or_constant()
-        => rhs + lhs,
-        // Addition with a zero is a noop, return the other input.
-        (lhs, Constant { value }) if value == 0 => lhs,
-        // Fold successive additions like (d0 + 2) + 3 into d0 + 5.
-        (
-            BinaryOperation {
-                kind: Kind::Add,
-                lhs,
-                rhs: Constant { value: lrhs_value }
-            },
-            Constant { value: rhs_value }
-        ) => lhs + (lrhs_value + rhs_value)
+/// Simplify add expression. Return `None` if it can't be simplified.
+pub fn simplify_add(lhs: &AffineExpr, rhs: &AffineExpr) -> Option<AffineExpr> {
+    // Fold if both LHS, RHS are a constant.
+    if let (Constant { value: lhs_value }, Constant { value: rhs_value }) = (lhs, rhs) {
+        return Some(Constant { value: lhs_value + rhs_value });
     }
-    // At this point, if there was a constant, it would be on the right.
-    
-    // Detect "c1 * expr + c_2 * expr" as "(c1 + c2) * expr".
-    // c1 is rRhsConst, c2 is rLhsConst; first_expr, second_expr are their
-    // respective multiplicands.
-    let rLhsConst: i64;
-    let rRhsConst: u64;
-    let first_expr: AffineExpr;
-    let second_expr: AffineExpr;
-    if let BinaryOperation {
-        kind: Kind::Mul,
-        lhs,
-        rhs: Constant { value: lrhs_value }
-    } = lhs {
-      rLhsConst = lrhs_value;
-      first_expr = lhs;
-    } else {
-      rLhsConst = 1;
-      first_expr = lhs;
+    // Canonicalise so that only the RHS is a constant. (4 + d0 becomes d0 + 4).
+    // If only one of them is a symbolic expressions, make it the RHS.
+    if matches!(lhs, Constant { .. })
+        || (lhs.is_symbolic_or_constant() && !rhs.is_symbolic_or_constant())
+    {
+        return Some(rhs.clone() + lhs.clone());
     }
-  
-    if let BinaryOperation {
-        kind: Kind::Mul,
-        lhs,
-        rhs: Constant { value: rrhs_value }
-    } = rhs {
-      rRhsConst = rrhs_value;
-      second_expr = lhs;
-    } else {
-      rRhsConst = 1;
-      second_expr = rhs;
+
+    // At this point, if there was a constant, it would be on the right.
+
+    if let Constant { value: rhs_value } = rhs {
+        // Addition with a zero is a noop, return the other input.
+        if *rhs_value == 0 {
+            return Some(lhs.clone());
+        }
+        // Fold successive additions like (d0 + 2) + 3 into d0 + 5.
+        if let BinaryOperation { kind: Kind::Add, lhs: llhs, rhs: lrhs } = lhs {
+            if let Constant { value: lrhs_value } = lrhs.as_ref() {
+                return Some(*llhs.clone() + (lrhs_value + rhs_value));
+            }
+        }
     }
-  
-    if rLhsConst && rRhsConst && first_expr == second_expr {
-      return binary_operation(
-          Kind::Mul, first_expr,
-          constant(*rLhsConst + *rRhsConst, lhs.getContext()));
+
+    // Detect "c1 * expr + c2 * expr" as "(c1 + c2) * expr".
+    let split_constant_factor = |expr: &AffineExpr| -> (i64, AffineExpr) {
+        if let BinaryOperation { kind: Kind::Mul, lhs, rhs } = expr {
+            if let Constant { value } = rhs.as_ref() {
+                return (*value, *lhs.clone());
+            }
         }
-  
+        (1, expr.clone())
+    };
+    let (lhs_factor, first_expr) = split_constant_factor(lhs);
+    let (rhs_factor, second_expr) = split_constant_factor(rhs);
+    if first_expr == second_expr {
+        return Some(first_expr * (lhs_factor + rhs_factor));
+    }
+
     // When doing successive additions, bring constant to the right: turn (d0 + 2) + d1 into (d0 + d1) + 2.
-    if let BinaryOperation {
-        kind: Kind::Add,
-        lhs,
-        rhs: lrhs
-    } = lhs
-    && matches!(lrhs, Constant)
-    {
-        return lhs + rhs + lrhs;
+    if let BinaryOperation { kind: Kind::Add, lhs: llhs, rhs: lrhs } = lhs {
+        if matches!(lrhs.as_ref(), Constant { .. }) {
+            return Some(*llhs.clone() + rhs.clone() + *lrhs.clone());
+        }
     }
-  
-    // Detect and transform "expr - q * (expr floordiv q)" to "expr mod q", where
-    // q may be a constant or symbolic expression. This leads to a much more
-    // efficient form when 'c' is a power of two, and in general a more compact
-    // and readable form.
-  
-    // Process '(expr floordiv c) * (-c)'.
-    if !matches!(rhs, BinaryOperation) {
+
+    /*
+    Detect and transform "expr - q * (expr floordiv q)" to "expr mod q", where q may be a constant or symbolic expression. This leads to a much more efficient form when 'c' is a power of two, and in general a more compact and readable form.
+    */
+    let BinaryOperation { kind: Kind::Mul, lhs: rlhs, rhs: rrhs } = rhs else {
         return None;
-    }
-  
-    if let BinaryOperation {
-        // Check if rlhs is of the form (expr floordiv q) * q, where q is a
-        // symbolic expression.
-        lhs: BinaryOperation {
-            kind: Kind::Mul,
-            lhs: rllhs,
-            rhs: rlrhs
-        },
-        rhs: Constant { value: rrhs_value },
-        ..
-    } = rhs
-    && rrhs_value == -1
-    {
-        // Check rllhs = expr floordiv q.
-        if let BinaryOperation {
-            kind,
-            lhs: rlllhs,
-            rhs: rllrhs
-        } = rllhs {
-            if !matches!(kind, Kind::FloorDiv)
-            // Check rlrhs = q.
-            && rllrhs = rlrhs
-            && lhs == rlllhs
-            {
-                return lhs % rlrhs;
-            } else {
-                return None;
+    };
+
+    // Process '((expr floordiv q) * q) * (-1)', where q is a symbolic expression.
+    if let (
+        BinaryOperation { kind: Kind::Mul, lhs: rllhs, rhs: rlrhs },
+        Constant { value: -1 }
+    ) = (rlhs.as_ref(), rrhs.as_ref()) {
+        // Check rllhs = expr floordiv q, and rlrhs = q.
+        if let BinaryOperation { kind: Kind::FloorDiv, lhs: dividend, rhs: divisor }
+            = rllhs.as_ref()
+        {
+            if divisor == rlrhs && dividend.as_ref() == lhs {
+                return Some(lhs.clone() % *rlrhs.clone());
             }
-        } else {
-            return None;
         }
+        return None;
     }
 
-    // Process rlhs, which is 'expr floordiv c'.
-    if let BinaryOperation {
-        lhs: BinaryOperation {
-            kind: Kind::FloorDiv,
-            lhs: rllhs,
-            rhs: rlrhs
-        },
-        rhs: rrhs,
-        ..
-    } = rhs
-    && lhs == rllhs && rlrhs == -rrhs
-    {
-        return lhs % rlrhs;
-    } else {
-        return None;
+    // Process '(expr floordiv c) * (-c)'.
+    if let BinaryOperation { kind: Kind::FloorDiv, lhs: rllhs, rhs: rlrhs } = rlhs.as_ref() {
+        if rllhs.as_ref() == lhs && *rlrhs.clone() == -*rrhs.clone() {
+            return Some(lhs.clone() % *rlrhs.clone());
+        }
     }
-    
+
     None
 }
 
@@ -537,14 +484,14 @@ impl Neg for AffineExpr {
     type Output = Self;
 
     fn neg(self) -> Self::Output {
-        self * constant(-1, self.context())
+        self * Constant { value: -1 }
     }
 }
 
 impl Sub<i64> for AffineExpr {
     type Output = Self;
 
-    fn sub(self, rhs: i16) -> Self::Output {
+    fn sub(self, rhs: i64) -> Self::Output {
         self + (-rhs)
     }
 }
@@ -560,8 +507,8 @@ impl Sub for AffineExpr {
 impl Mul<i64> for AffineExpr {
     type Output = Self;
 
-    fn mul(self, rhs: i16) -> Self::Output {
-        self * constant(rhs, self.context())
+    fn mul(self, rhs: i64) -> Self::Output {
+        self * Constant { value: rhs }
     }
 }
 
@@ -569,67 +516,126 @@ impl Mul for AffineExpr {
     type Output = Self;
 
     fn mul(self, other: Self) -> Self::Output {
-        if let Some(simplified) = simplify_mul(self, other) {
-            return simplified;
+        match simplify_mul(&self, &other) {
+            Some(simplified) => simplified,
+            None => Self::raw(Kind::Mul, self, other)
         }
-
-        let uniquer = self.context().affine_uniquer();
-        uniquer.get::<BinaryOperation>(
-            /*initFn=*/{}, static_cast::<usize>(Kind::Mul), self, other)
     }
 }
 
-/// Simplify a multiply expression. Return nullptr if it can't be simplified.
-pub const fn simplify_mul(lhs: AffineExpr, rhs: AffineExpr) -> Option<AffineExpr> {
-    if let Constant { value: lhs_value } = lhs
-    && let Constant { value: rhs_value } = rhs
-    {
-        return Some(constant(lhs_value * rhs_value, lhs.context()));
-    };
+/// Simplify a multiply expression. Return `None` if it can't be simplified.
+pub fn simplify_mul(lhs: &AffineExpr, rhs: &AffineExpr) -> Option<AffineExpr> {
+    if let (Constant { value: lhs_value }, Constant { value: rhs_value }) = (lhs, rhs) {
+        return Some(Constant { value: lhs_value * rhs_value });
+    }
 
-    assert!(lhs.is_symbolic_or_constant() || rhs.is_symbolic_or_constant());
+    // A product of two non-symbolic expressions is semi-affine, and kept as is.
+    if !lhs.is_symbolic_or_constant() && !rhs.is_symbolic_or_constant() {
+        return None;
+    }
   
     // Canonicalise the mul expression so that the constant/symbolic term is the RHS. If both the lhs and rhs are symbolic, swap them if the lhs is a constant. (Note that a constant is trivially symbolic).
-    if !rhs.is_symbolic_or_constant()
-    || matches!(lhs, Constant) {
+    if !rhs.is_symbolic_or_constant() || matches!(lhs, Constant { .. }) {
         // At least one of them has to be symbolic.
-        return Some(rhs * lhs);
+        return Some(rhs.clone() * lhs.clone());
     }
   
     // At this point, if there was a constant, it would be on the right.
   
     // Multiplication with a one is a noop, return the other input.
     match rhs {
-        Constant { value } if value == 1 => return Some(lhs),
-        Constant { value } if value == 0 => return Some(value),
+        Constant { value: 1 } => return Some(lhs.clone()),
+        Constant { value: 0 } => return Some(rhs.clone()),
         _ => {}
     };
 
-    match lhs {
-        BinaryOperation {
-            kind: Kind::Mul,
-            lhs: llhs,
-            rhs: Constant { value: lrhs_value }
-        } => {
+    if let BinaryOperation { kind: Kind::Mul, lhs: llhs, rhs: lrhs } = lhs {
+        if let Constant { value: lrhs_value } = lrhs.as_ref() {
             // Fold successive multiplications: eg: (d0 * 2) * 3 into d0 * 6.
             if let Constant { value: rhs_value } = rhs {
-                return llhs * (lrhs_value * rhs_value);
-            }
-            // When doing successive multiplication, bring constant to the right: turn (d0d * 2) * d1 into (d0 * d1) * 2.
-            else {
-                return Some((lhs * rhs) * Constant { value: lrhs_value })
+                return Some(*llhs.clone() * (lrhs_value * rhs_value));
             }
+            // When doing successive multiplication, bring constant to the right: turn (d0 * 2) * d1 into (d0 * d1) * 2.
+            return Some((*llhs.clone() * rhs.clone()) * *lrhs.clone());
         }
     }
   
     None
 }
 
+/// Simplify a floordiv expression. Return `None` if it can't be simplified.
+pub fn simplify_floor_div(lhs: &AffineExpr, rhs: &AffineExpr) -> Option<AffineExpr> {
+    let rhs_value = match rhs {
+        // floordiv by zero or negative numbers is undefined and preserved as is.
+        Constant { value } if *value >= 1 => *value,
+        _ => return None
+    };
+    if let Constant { value } = lhs {
+        return Some(Constant { value: floor_div(*value, rhs_value) });
+    }
+
+    if rhs_value == 1 {
+        return Some(lhs.clone());
+    }
+
+    match lhs {
+        // Simplify (expr * const) floordiv divConst when const is known to be a multiple of divConst. Eg: (i * 128) floordiv 64 = i * 2.
+        BinaryOperation { kind: Kind::Mul, lhs: llhs, rhs: lrhs } => {
+            if let Constant { value: lrhs_value } = lrhs.as_ref() {
+                // rhs_value is known to be a positive constant.
+                if lrhs_value % rhs_value == 0 {
+                    return Some(*llhs.clone() * (lrhs_value / rhs_value));
+                }
+            }
+        },
+        // Simplify (expr1 + expr2) floordiv divConst when either expr1 or expr2 is known to be a multiple of divConst.
+        BinaryOperation { kind: Kind::Add, lhs: llhs, rhs: lrhs } => {
+            if llhs.largest_known_divisor() % rhs_value == 0
+                || lrhs.largest_known_divisor() % rhs_value == 0
+            {
+                return Some(llhs.as_ref().clone().floor_div(rhs_value)
+                    + lrhs.as_ref().clone().floor_div(rhs_value));
+            }
+        },
+        _ => {}
+    }
+
+    None
+}
+
+/// Simplify a ceildiv expression. Return `None` if it can't be simplified.
+pub fn simplify_ceil_div(lhs: &AffineExpr, rhs: &AffineExpr) -> Option<AffineExpr> {
+    let rhs_value = match rhs {
+        // ceildiv by zero or negative numbers is undefined and preserved as is.
+        Constant { value } if *value >= 1 => *value,
+        _ => return None
+    };
+    if let Constant { value } = lhs {
+        return Some(Constant { value: ceil_div(*value, rhs_value) });
+    }
+
+    if rhs_value == 1 {
+        return Some(lhs.clone());
+    }
+
+    // Simplify (expr * const) ceildiv divConst when const is known to be a multiple of divConst.
+    if let BinaryOperation { kind: Kind::Mul, lhs: llhs, rhs: lrhs } = lhs {
+        if let Constant { value: lrhs_value } = lrhs.as_ref() {
+            // rhs_value is known to be a positive constant.
+            if lrhs_value % rhs_value == 0 {
+                return Some(*llhs.clone() * (lrhs_value / rhs_value));
+            }
+        }
+    }
+
+    None
+}
+
 impl Rem<i64> for AffineExpr {
     type Output = Self;
 
-    fn rem(self, rhs: i16) -> Self::Output {
-        self % constant(rhs, self.context())
+    fn rem(self, rhs: i64) -> Self::Output {
+        self % Constant { value: rhs }
     }
 }
 
@@ -637,53 +643,46 @@ impl Rem for AffineExpr {
     type Output = Self;
 
     fn rem(self, other: Self) -> Self::Output {
-        if let Some(simplified) = simplify_mod(self, other) {
-            return simplified;
+        match simplify_mod(&self, &other) {
+            Some(simplified) => simplified,
+            None => Self::raw(Kind::Mod, self, other)
         }
-
-        let uniquer = self.context().affine_uniquer();
-        uniquer.get::<BinaryOperation>(
-            /*initFn=*/{}, static_cast::<usize>(Kind::Mod), self, other)
     }
 }
 
-pub const fn simplify_mod(lhs: AffineExpr, rhs: AffineExpr)
-    -> Option<AffineExpr>
-{
+/// Simplify a mod expression. Return `None` if it can't be simplified.
+pub fn simplify_mod(lhs: &AffineExpr, rhs: &AffineExpr) -> Option<AffineExpr> {
     let rhs_value = match rhs {
         // mod w.r.t zero or negative numbers is undefined and preserved as is.
-        Constant { value } if value >= 1 => value,
+        Constant { value } if *value >= 1 => *value,
         _ => return None
     };
     if let Constant { value } = lhs {
-        constant(r#mod(value, rhs_value), lhs.context())
+        return Some(Constant { value: r#mod(*value, rhs_value) });
     }
 
     // Fold modulo of an expression that is known to be a multiple of a constant to zero if that constant is a multiple of the modulo factor. Eg: (i * 128) mod 64 is folded to 0, and less trivially, (i*(j*4*(k*32))) mod 128 = 0.
     if lhs.largest_known_divisor() % rhs_value == 0 {
-        return Some(constant(0, lhs.context()));
+        return Some(Constant { value: 0 });
     }
-  
-    // Simplify (expr1 + expr2) mod divConst when either expr1 or expr2 is known to be a multiple of divConst.
+
     match lhs {
-        BinaryOperation { kind: Kind::Add, lhs, rhs } => {
-            let llhs_div = lhs.largest_known_divisor();
-            let lrhs_div = rhs.largest_known_divisor();
-            // rhsConst is known to be a positive constant.
-            if llhs_div % rhs_value == 0 {
-                return rhs % rhs_value;
+        // Simplify (expr1 + expr2) mod divConst when either expr1 or expr2 is known to be a multiple of divConst.
+        BinaryOperation { kind: Kind::Add, lhs: llhs, rhs: lrhs } => {
+            // rhs_value is known to be a positive constant.
+            if llhs.largest_known_divisor() % rhs_value == 0 {
+                return Some(*lrhs.clone() % rhs_value);
             }
-            if lrhs_div % rhs_value == 0 {
-                return lhs % rhs_value;
+            if lrhs.largest_known_divisor() % rhs_value == 0 {
+                return Some(*llhs.clone() % rhs_value);
             }
         },
-        BinaryOperation {
-            kind: Kind::Mod,
-            lhs,
-            rhs: Constant { value }
-        } if value >= 1 => {
-            if r#mod(value, rhs_value) == 0 {
-                return lhs % rhs_value;
+        // Simplify (expr mod a) mod b when b divides a.
+        BinaryOperation { kind: Kind::Mod, lhs: llhs, rhs: lrhs } => {
+            if let Constant { value } = lrhs.as_ref() {
+                if *value >= 1 && r#mod(*value, rhs_value) == 0 {
+                    return Some(*llhs.clone() % rhs_value);
+                }
             }
         },
         _ => {}
@@ -692,43 +691,115 @@ pub const fn simplify_mod(lhs: AffineExpr, rhs: AffineExpr)
     None
 }
 
-// impl Display for AffineExpr {
-//     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+/// The binding strength of the context an expression is printed in, used to decide where parentheses are needed.
+#[derive(Clone, Copy, PartialEq)]
+enum BindingStrength {
+    Weak,   // + and -
+    Strong, // All other binary operators.
+}
 
-//     }
-// }
+impl AffineExpr {
+    fn fmt_with_strength(
+        &self,
+        f: &mut core::fmt::Formatter<'_>,
+        enclosing: BindingStrength
+    ) -> core::fmt::Result {
+        match self {
+            Dimension { index, .. } => write!(f, "d{}", index),
+            Symbol { index } => write!(f, "s{}", index),
+            Constant { value } => write!(f, "{}", value),
+            BinaryOperation { kind: Kind::Add, lhs, rhs } => {
+                if enclosing == BindingStrength::Strong {
+                    f.write_str("(")?;
+                }
+                lhs.fmt_with_strength(f, BindingStrength::Weak)?;
+                // The term and the negated factor of a product with a negative constant.
+                let negated_product = match rhs.as_ref() {
+                    BinaryOperation { kind: Kind::Mul, lhs: term, rhs: factor } =>
+                        match factor.as_ref() {
+                            Constant { value } if *value < 0 =>
+                                Some((term, value.unsigned_abs())),
+                            _ => None
+                        },
+                    _ => None
+                };
+                match (negated_product, rhs.as_ref()) {
+                    // Pretty print addition to a product with a negative constant as a subtraction, where only a negated sum needs parentheses.
+                    (Some((term, 1)), _) => {
+                        f.write_str(" - ")?;
+                        let strength = match term.as_ref() {
+                            BinaryOperation { kind: Kind::Add, .. } => BindingStrength::Strong,
+                            _ => BindingStrength::Weak
+                        };
+                        term.fmt_with_strength(f, strength)?;
+                    },
+                    (Some((term, factor)), _) => {
+                        f.write_str(" - ")?;
+                        term.fmt_with_strength(f, BindingStrength::Strong)?;
+                        write!(f, " * {}", factor)?;
+                    },
+                    // Pretty print addition to a negative number as a subtraction.
+                    (None, Constant { value }) if *value < 0 =>
+                        write!(f, " - {}", value.unsigned_abs())?,
+                    (None, _) => {
+                        f.write_str(" + ")?;
+                        rhs.fmt_with_strength(f, BindingStrength::Weak)?;
+                    }
+                }
+                if enclosing == BindingStrength::Strong {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            },
+            BinaryOperation { kind, lhs, rhs } => {
+                if enclosing == BindingStrength::Strong {
+                    f.write_str("(")?;
+                }
+                // Pretty print multiplication with -1.
+                if *kind == Kind::Mul
+                    && matches!(rhs.as_ref(), Constant { value: -1 })
+                {
+                    f.write_str("-")?;
+                    lhs.fmt_with_strength(f, BindingStrength::Strong)?;
+                } else {
+                    let operator = match kind {
+                        Kind::Mul => " * ",
+                        Kind::Mod => " mod ",
+                        Kind::FloorDiv => " floordiv ",
+                        Kind::CeilDiv => " ceildiv ",
+                        Kind::Add => unreachable!()
+                    };
+                    lhs.fmt_with_strength(f, BindingStrength::Strong)?;
+                    f.write_str(operator)?;
+                    rhs.fmt_with_strength(f, BindingStrength::Strong)?;
+                }
+                if enclosing == BindingStrength::Strong {
+                    f.write_str(")")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
 
-// impl BinaryOperation {
-//     pub fn construct(
-//         allocator: &StorageUniquer::StorageAllocator,
-//         kind: Kind,
-//         lhs: AffineExpr,
-//         rhs: AffineExpr
-//     ) -> Self {
-//         let result = allocator.allocate<BinaryOperation>();
-//         Self { kind, lhs, rhs }
-//         result.context = result.lhs.context();
-//         return result;
-//     }
-// }
+impl Display for AffineExpr {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.fmt_with_strength(f, BindingStrength::Weak)
+    }
+}
 
 // These free functions allow clients of the API not to use classes in detail.
 
 pub fn dimension(index: usize, context: *mut MLIRContext) -> AffineExpr {
-
+    Dimension { index }
 }
 
 pub fn symbol(index: usize, context: *mut MLIRContext) -> AffineExpr {
-
+    Symbol { index }
 }
 
 pub fn constant(constant: i64, context: *mut MLIRContext) -> AffineExpr {
-    let assign_ctx = (AffineConstantExprStorage *storage) {
-        storage.context = context;
-    };
-    
-    letuniquer = context.affine_uniquer();
-    return uniquer.get::<Constant { constant }>(assign_ctx, constant);
+    Constant { value: constant }
 }
 
 pub fn binary_operation(
@@ -756,7 +827,177 @@ pub fn get_affine_expr_from_flat_form(
     context: *mut MLIRContext
 ) -> AffineExpr
 {
+    assert_eq!(flat_exprs.len(), num_dims + num_symbols + local_exprs.len() + 1,
+        "Unexpected number of local expressions");
+    let mut expr = constant(0, context);
+    // Dimensions and symbols.
+    for (index, coefficient) in flat_exprs[..num_dims + num_symbols].iter().enumerate() {
+        if *coefficient == 0 {
+            continue;
+        }
+        let id = if index < num_dims {
+            dimension(index, context)
+        } else {
+            symbol(index - num_dims, context)
+        };
+        expr = expr + id * *coefficient;
+    }
+    // Local identifiers.
+    let locals = &flat_exprs[num_dims + num_symbols..flat_exprs.len() - 1];
+    for (local_expr, coefficient) in local_exprs.iter().zip(locals) {
+        if *coefficient == 0 {
+            continue;
+        }
+        expr = expr + local_expr.clone() * *coefficient;
+    }
+    // Constant term.
+    let constant_term = flat_exprs[flat_exprs.len() - 1];
+    if constant_term != 0 {
+        expr = expr + constant_term;
+    }
+    expr
+}
+
+/**
+Constructs a semi-affine expression from its flattened form, in the same layout as `get_affine_expr_from_flat_form`. The terms are summed in the order of the positions of the dimensions and symbols they involve, so that the result looks like `d0 + d0 floordiv 4 + d0 * s0 + s0 + d1 + ...`: each dimension comes first, then the local expressions dividing it by a constant, then its products and quotients with other identifiers, then the symbol at the same position and the local expressions over that symbol. Local expressions of any other form come after them, followed by the constant term.
+*/
+fn get_semi_affine_expr_from_flat_form(
+    flat_exprs: &[i64],
+    num_dims: usize,
+    num_symbols: usize,
+    local_exprs: &[AffineExpr],
+    context: *mut MLIRContext
+) -> AffineExpr
+{
+    assert_eq!(flat_exprs.len(), num_dims + num_symbols + local_exprs.len() + 1,
+        "Unexpected number of local expressions");
+    // The position of an identifier among the dimensions and then the symbols.
+    let position = |expr: &AffineExpr| match expr {
+        Dimension { index } => Some(*index),
+        Symbol { index } => Some(num_dims + *index),
+        _ => None
+    };
 
+    // Each term is sorted by (position, group, secondary position, index).
+    let mut terms = Vec::new();
+    for (index, coefficient) in flat_exprs[..num_dims + num_symbols].iter().enumerate() {
+        if *coefficient == 0 {
+            continue;
+        }
+        let (key, id) = if index < num_dims {
+            ((index, 0, 0, 0), dimension(index, context))
+        } else {
+            ((index - num_dims, 3, 0, 0), symbol(index - num_dims, context))
+        };
+        terms.push((key, id * *coefficient));
+    }
+    let locals = &flat_exprs[num_dims + num_symbols..flat_exprs.len() - 1];
+    for (index, (local_expr, coefficient)) in local_exprs.iter().zip(locals).enumerate() {
+        if *coefficient == 0 {
+            continue;
+        }
+        let key = match local_expr {
+            BinaryOperation { lhs, rhs, .. } => match (lhs.as_ref(), rhs.as_ref()) {
+                (Dimension { index: lhs_index }, Constant { .. }) =>
+                    (*lhs_index, 1, 0, index),
+                (Dimension { index: lhs_index }, rhs) if position(rhs).is_some() =>
+                    (*lhs_index, 2, position(rhs).unwrap(), index),
+                (Symbol { index: lhs_index }, rhs) =>
+                    (*lhs_index, 4, position(rhs).unwrap_or(usize::MAX), index),
+                _ => (usize::MAX, 0, 0, index)
+            },
+            _ => (usize::MAX, 0, 0, index)
+        };
+        terms.push((key, local_expr.clone() * *coefficient));
+    }
+    terms.sort_by_key(|(key, _)| *key);
+
+    let mut expr = constant(0, context);
+    for (_, term) in terms {
+        expr = expr + term;
+    }
+    // Constant term.
+    let constant_term = flat_exprs[flat_exprs.len() - 1];
+    if constant_term != 0 {
+        expr = expr + constant_term;
+    }
+    expr
+}
+
+/**
+Returns true if `expr` is a multiple of the symbol at position `symbol_position`, i.e. if dividing it by the symbol is exact.
+
+Only sums, products and modulos are looked through: the quotient of a division is not known to be a multiple of the symbol, even if its dividend is.
+*/
+fn is_divisible_by_symbol(expr: &AffineExpr, symbol_position: usize) -> bool {
+    match expr {
+        Constant { value } => *value == 0,
+        Dimension { .. } => false,
+        Symbol { index } => *index == symbol_position,
+        BinaryOperation { kind, lhs, rhs } => match kind {
+            // Checks divisibility by the given symbol for both operands. A modulo `a mod b` is `a - b * (a floordiv b)`, which is divisible when both `a` and `b` are.
+            Kind::Add | Kind::Mod =>
+                   is_divisible_by_symbol(lhs, symbol_position)
+                && is_divisible_by_symbol(rhs, symbol_position),
+            // Checks if any of the operands is divisible by the given symbol.
+            Kind::Mul =>
+                   is_divisible_by_symbol(lhs, symbol_position)
+                || is_divisible_by_symbol(rhs, symbol_position),
+            Kind::FloorDiv | Kind::CeilDiv => false
+        }
+    }
+}
+
+/// Divides `expr` by the symbol at position `symbol_position`, which is expected to be positive. Returns `None` if `expr` is not known to be a multiple of the symbol.
+fn symbolic_divide(expr: &AffineExpr, symbol_position: usize) -> Option<AffineExpr> {
+    match expr {
+        Constant { value: 0 } => Some(expr.clone()),
+        Symbol { index } if *index == symbol_position => Some(Constant { value: 1 }),
+        BinaryOperation { kind, lhs, rhs } => match kind {
+            // Dividing a sum divides both of its operands, and `(a mod b) / s` is `(a / s) mod (b / s)` for a positive s.
+            Kind::Add | Kind::Mod => Some(binary_operation(
+                *kind,
+                symbolic_divide(lhs, symbol_position)?,
+                symbolic_divide(rhs, symbol_position)?)),
+            // Dividing a product divides the operand which is a multiple of the symbol.
+            Kind::Mul => match symbolic_divide(lhs, symbol_position) {
+                Some(lhs) => Some(lhs * *rhs.clone()),
+                None => Some(*lhs.clone() * symbolic_divide(rhs, symbol_position)?)
+            },
+            Kind::FloorDiv | Kind::CeilDiv => None
+        },
+        _ => None
+    }
+}
+
+/**
+Simplify a semi-affine expression by handling modulo, floordiv, and ceildiv operations when the second operand simplifies to a symbol and the first operand is divisible by that symbol. It can be applied to any semi-affine expression. Returned expression can either be a semi-affine or pure affine expression.
+
+```text
+(s0 * d0 + s0 * 4) floordiv s0    simplified to    d0 + 4
+(s0 * d0) mod s0                  simplified to    0
+```
+*/
+fn simplify_semi_affine(expr: &AffineExpr) -> AffineExpr {
+    let BinaryOperation { kind, lhs, rhs } = expr else {
+        return expr.clone();
+    };
+    let lhs = simplify_semi_affine(lhs);
+    let rhs = simplify_semi_affine(rhs);
+    let symbol_position = match (kind, &rhs) {
+        (Kind::FloorDiv | Kind::CeilDiv | Kind::Mod, Symbol { index }) => *index,
+        _ => return binary_operation(*kind, lhs, rhs)
+    };
+    if !is_divisible_by_symbol(&lhs, symbol_position) {
+        return binary_operation(*kind, lhs, rhs);
+    }
+    if *kind == Kind::Mod {
+        return Constant { value: 0 };
+    }
+    match symbolic_divide(&lhs, symbol_position) {
+        Some(quotient) => quotient,
+        None => binary_operation(*kind, lhs, rhs)
+    }
 }
 
 /// Simplify an affine expression by flattening and some amount of simple analysis. This has complexity linear in the number of nodes in 'expr'.
@@ -767,5 +1008,140 @@ pub fn simplify_affine_expr(
     num_symbols: usize
 ) -> AffineExpr
 {
+    // Simplify semi-affine expressions separately.
+    let expr = if expr.is_pure_affine() {
+        expr
+    } else {
+        simplify_semi_affine(&expr)
+    };
+
+    let mut flattener = SimpleAffineExprFlattener::new(num_dims, num_symbols);
+    flattener.walk_post_order(&expr);
+    let flattened_expr = flattener.operand_expr_stack.pop()
+        .expect("The flattened expression is left on the stack");
+    assert!(flattener.operand_expr_stack.is_empty());
 
+    let context = core::ptr::null_mut();
+    if expr.is_pure_affine() {
+        return get_affine_expr_from_flat_form(
+            &flattened_expr, num_dims, num_symbols, &flattener.local_exprs, context);
+    }
+    if expr == get_affine_expr_from_flat_form(
+        &flattened_expr, num_dims, num_symbols, &flattener.local_exprs, context)
+    {
+        return expr;
+    }
+    get_semi_affine_expr_from_flat_form(
+        &flattened_expr, num_dims, num_symbols, &flattener.local_exprs, context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(index: usize) -> AffineExpr {
+        Dimension { index }
+    }
+
+    fn s(index: usize) -> AffineExpr {
+        Symbol { index }
+    }
+
+    #[test]
+    fn nested_binary_operations_are_parenthesised() {
+        assert_eq!((d(0).floor_div(4) * 4).to_string(), "(d0 floordiv 4) * 4");
+        assert_eq!(d(0).floor_div(2).floor_div(4).to_string(), "(d0 floordiv 2) floordiv 4");
+        assert_eq!(((d(0) + 2).floor_div(3)).to_string(), "(d0 + 2) floordiv 3");
+        assert_eq!(((d(0) * s(0)) % s(1)).to_string(), "(d0 * s0) mod s1");
+        assert_eq!(((d(0) + d(1)) * s(0)).to_string(), "(d0 + d1) * s0");
+        assert_eq!((d(0) * 3 + d(1)).to_string(), "d0 * 3 + d1");
+    }
+
+    #[test]
+    fn negated_terms_are_printed_as_subtractions() {
+        assert_eq!((d(0) - d(1)).to_string(), "d0 - d1");
+        assert_eq!((d(0) - 5).to_string(), "d0 - 5");
+        assert_eq!((d(0) - d(1) * 3).to_string(), "d0 - d1 * 3");
+        assert_eq!((d(0) - (d(1) + d(2))).to_string(), "d0 - (d1 + d2)");
+        assert_eq!((d(0) - d(0).floor_div(4) * 3).to_string(), "d0 - (d0 floordiv 4) * 3");
+        assert_eq!((d(1) - d(0) * s(0)).to_string(), "d1 - d0 * s0");
+        assert_eq!((-d(0)).to_string(), "-d0");
+        assert_eq!((-(d(0) * s(0))).to_string(), "-(d0 * s0)");
+    }
+
+    #[test]
+    fn floordiv_and_mod_by_a_constant_cancel_out() {
+        let expr = d(0).floor_div(4) * 4 + d(0) % 4;
+        assert_eq!(simplify_affine_expr(expr, 1, 0), d(0));
+        let expr = d(0) % 4 + d(0).floor_div(4);
+        assert_eq!(simplify_affine_expr(expr, 1, 0).to_string(), "d0 - (d0 floordiv 4) * 3");
+    }
+
+    #[test]
+    fn flattening_cancels_common_divisors() {
+        let expr = (d(0) * 8 + 4).ceil_div(4);
+        assert_eq!(simplify_affine_expr(expr, 1, 0).to_string(), "d0 * 2 + 1");
+        let expr = (d(0) * 8 + d(1) * 4).ceil_div(4);
+        assert_eq!(simplify_affine_expr(expr, 2, 0).to_string(), "d0 * 2 + d1");
+        let expr = (d(0) * 6) % 4;
+        assert_eq!(simplify_affine_expr(expr, 1, 0).to_string(),
+            "d0 * 6 - ((d0 * 3) floordiv 2) * 4");
+    }
+
+    #[test]
+    fn inexact_divisions_are_kept() {
+        let expr = (d(0) * 4 + d(1) * 8 + 3).ceil_div(4);
+        assert_eq!(simplify_affine_expr(expr.clone(), 2, 0), expr);
+        let expr = d(0).floor_div(2).floor_div(4);
+        assert_eq!(simplify_affine_expr(expr.clone(), 1, 0), expr);
+    }
+
+    #[test]
+    fn flat_form_recovers_mod() {
+        let expr = get_affine_expr_from_flat_form(
+            &[1, -4, 0], 1, 0, &[d(0).floor_div(4)], core::ptr::null_mut());
+        assert_eq!(expr.to_string(), "d0 mod 4");
+        let expr = get_affine_expr_from_flat_form(
+            &[2, 0, -1, 1, 5], 1, 2, &[d(0).ceil_div(3)], core::ptr::null_mut());
+        assert_eq!(expr.to_string(), "d0 * 2 - s1 + d0 ceildiv 3 + 5");
+    }
+
+    #[test]
+    fn terms_are_ordered_by_position() {
+        let expr = d(1) + d(0) * 2 + 3 + d(0);
+        assert_eq!(simplify_affine_expr(expr, 2, 0).to_string(), "d0 * 3 + d1 + 3");
+        let expr = s(0) + d(1) + d(0);
+        assert_eq!(simplify_affine_expr(expr, 2, 1).to_string(), "d0 + d1 + s0");
+    }
+
+    #[test]
+    fn semi_affine_divisions_by_a_symbol_are_simplified() {
+        let expr = (d(0) * s(0)).floor_div(s(0));
+        assert_eq!(simplify_affine_expr(expr, 1, 1), d(0));
+        let expr = (d(0) * s(0)).ceil_div(s(0));
+        assert_eq!(simplify_affine_expr(expr, 1, 1), d(0));
+        let expr = (d(0) * s(0) + s(0) * 4).floor_div(s(0));
+        assert_eq!(simplify_affine_expr(expr, 1, 1).to_string(), "d0 + 4");
+        let expr = (d(0) * s(0) + d(1) * s(0)).floor_div(s(0));
+        assert_eq!(simplify_affine_expr(expr, 2, 1).to_string(), "d0 + d1");
+        let expr = (d(0) * s(0)) % s(0) + d(1);
+        assert_eq!(simplify_affine_expr(expr, 2, 1), d(1));
+    }
+
+    #[test]
+    fn semi_affine_divisions_by_another_symbol_are_kept() {
+        let expr = d(0).floor_div(s(0));
+        assert_eq!(simplify_affine_expr(expr.clone(), 1, 1), expr);
+        let expr = (d(0) * s(1)).floor_div(s(0));
+        assert_eq!(simplify_affine_expr(expr.clone(), 1, 2), expr);
+    }
+
+    #[test]
+    fn semi_affine_terms_are_ordered_by_position() {
+        let expr = d(1) * s(0) + d(0);
+        assert_eq!(simplify_affine_expr(expr, 2, 1).to_string(), "d0 + d1 * s0");
+        let expr = d(0) * s(0) + d(0).floor_div(s(0)) + d(0) * d(1) + d(0);
+        assert_eq!(simplify_affine_expr(expr, 2, 1).to_string(),
+            "d0 + d0 * d1 + d0 * s0 + d0 floordiv s0");
+    }
 }
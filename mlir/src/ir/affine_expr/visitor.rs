@@ -7,8 +7,10 @@ This file defines the AffineExpr visitor class.
 */
 
 use crate::{
-    mlir::ir::affine_expr::AffineExpr,
-    llvm::adt::small_vector::SmallVector
+    ir::affine_expr::{
+        AffineExpr, Kind, constant, get_affine_expr_from_flat_form
+    },
+    support::math_extras
 };
 
 use AffineExpr::*;
@@ -51,59 +53,33 @@ Note that if you don't implement visitXXX for some affine expression type, the v
 
 Note that this class is specifically designed as a template to avoid virtual function call overhead. Defining and using a AffineExprVisitor is just as efficient as having your own switch instruction over the instruction opcode.
 */
-pub trait AffineExprVisitor<T> {
+pub trait AffineExprVisitor<T: Default = ()> {
     /*
     Interface code - This is the public interface of the AffineExprVisitor that you use to visit affine expressions...
     */
 
     /// Function to walk an AffineExpr (in post order).
-    fn walk_post_order(&self, expr: AffineExpr) -> T {
-        match expr {
-            BinaryOperation { kind, lhs, rhs } => {
-                match kind {
-                    Add => {
-                        self.walk_operands_post_order(expr);
-                        self.visit_add_expr(expr)
-                    },
-                    Mul => {
-                        self.walk_operands_post_order(expr);
-                        self.visit_mul_expr(expr)
-                    },
-                    Mod => {
-                        self.walk_operands_post_order(expr);
-                        self.visit_mod_expr(expr)
-                    },
-                    FloorDiv => {
-                        self.walk_operands_post_order(expr);
-                        self.visit_floor_div_expr(expr)
-                    },
-                    CeilDev => {
-                        self.walk_operands_post_order(expr);
-                        self.visit_ceil_div_expr(expr)
-                    }
-                }
-            },
-            Constant => self.visit_constant_expr(expr),
-            Dimension => self.visit_dim_expr(expr),
-            Symbol => self.visit_symbol_expr(expr),
+    fn walk_post_order(&mut self, expr: &AffineExpr) -> T {
+        if let BinaryOperation { lhs, rhs, .. } = expr {
+            self.walk_post_order(lhs);
+            self.walk_post_order(rhs);
         }
+        self.visit(expr)
     }
 
     /// Function to visit an AffineExpr.
-    fn visit(&self, expr: AffineExpr) -> T {
+    fn visit(&mut self, expr: &AffineExpr) -> T {
         match expr {
-            BinaryOperation { kind, lhs, rhs } => {
-                match kind {
-                    Add => self.visit_add_expr(expr),
-                    Mul => self.visit_mul_expr(expr),
-                    Mod => self.visit_mod_expr(expr),
-                    FloorDiv => self.visit_floor_div_expr(expr),
-                    CeilDev => self.visit_ceil_div_expr(expr)
-                }
+            BinaryOperation { kind, .. } => match kind {
+                Kind::Add => self.visit_add_expr(expr),
+                Kind::Mul => self.visit_mul_expr(expr),
+                Kind::Mod => self.visit_mod_expr(expr),
+                Kind::FloorDiv => self.visit_floor_div_expr(expr),
+                Kind::CeilDiv => self.visit_ceil_div_expr(expr)
             },
-            Constant => self.visit_constant_expr(expr),
-            Dimension => self.visit_dim_expr(expr),
-            Symbol => self.visit_symbol_expr(expr),
+            Constant { .. } => self.visit_constant_expr(expr),
+            Dimension { .. } => self.visit_dim_expr(expr),
+            Symbol { .. } => self.visit_symbol_expr(expr),
         }
     }
 
@@ -114,28 +90,36 @@ pub trait AffineExprVisitor<T> {
 
     // Default visit methods. Note that the default op-specific binary op visit
     // methods call the general visit_affine_binary_op_expr visit method.
-    fn visit_affine_binary_op_expr(&self, expr: AffineExpr) -> T;
+    fn visit_affine_binary_op_expr(&mut self, _expr: &AffineExpr) -> T {
+        T::default()
+    }
 
-    fn visit_add_expr(&self, expr: AffineExpr) {
-        self.visit_affine_binary_op_expr(expr);
+    fn visit_add_expr(&mut self, expr: &AffineExpr) -> T {
+        self.visit_affine_binary_op_expr(expr)
+    }
+    fn visit_mul_expr(&mut self, expr: &AffineExpr) -> T {
+        self.visit_affine_binary_op_expr(expr)
     }
-    fn visit_mul_expr(&self, expr: AffineExpr) {
-        self.visit_affine_binary_op_expr(expr);
+    fn visit_mod_expr(&mut self, expr: &AffineExpr) -> T {
+        self.visit_affine_binary_op_expr(expr)
     }
-    fn visit_mod_expr(&self, expr: AffineExpr) {
-        self.visit_affine_binary_op_expr(expr);
+    fn visit_floor_div_expr(&mut self, expr: &AffineExpr) -> T {
+        self.visit_affine_binary_op_expr(expr)
     }
-    fn visit_floor_div_expr(&self, expr: AffineExpr) {
-        self.visit_affine_binary_op_expr(expr);
+    fn visit_ceil_div_expr(&mut self, expr: &AffineExpr) -> T {
+        self.visit_affine_binary_op_expr(expr)
     }
-    fn visit_ceil_div_expr(&self, expr: AffineExpr) {
-        self.visit_affine_binary_op_expr(expr);
+    fn visit_constant_expr(&mut self, _expr: &AffineExpr) -> T {
+        T::default()
     }
-    fn visit_constant_expr(&self, expr: AffineExpr);
 
-    fn visit_dim_expr(&self, expr: AffineExpr);
+    fn visit_dim_expr(&mut self, _expr: &AffineExpr) -> T {
+        T::default()
+    }
 
-    fn visit_symbol_expr(&self, expr: AffineExpr);
+    fn visit_symbol_expr(&mut self, _expr: &AffineExpr) -> T {
+        T::default()
+    }
 }
 
 /**
@@ -160,9 +144,9 @@ pub struct SimpleAffineExprFlattener {
     /**
     Flattend expression layout: [dims, symbols, locals, constant]
     Stack that holds the LHS and RHS operands while visiting a binary op expr.
-    In future, consider adding a prepass to determine how big the SmallVector's will be, and linearise this to `Vec<i64>` to prevent SmallVector moves on re-allocation.
+    When the walk is completed, the flattened form of the top-level expression is left on the stack.
     */
-    operand_expr_stack: Vec<SmallVector<[i64; 8]>>,
+    pub operand_expr_stack: Vec<Vec<i64>>,
 
     num_dims: usize,
     num_symbols: usize,
@@ -173,58 +157,92 @@ pub struct SimpleAffineExprFlattener {
     /**
     AffineExpr's corresponding to the floordiv/ceildiv/mod expressions for which new identifiers were introduced; if the latter do not get canceled out, these expressions can be readily used to reconstruct the AffineExpr (tree) form. Note that these expressions themselves would have been simplified (recursively) by this pass. Eg. d0 + (d0 + 2*d1 + d0) ceildiv 4 will be simplified to d0 + q, where q = (d0 + d1) ceildiv 2. (d0 + d1) ceildiv 2 would be the local expression stored for q.
     */
-    local_exprs: SmallVector<[AffineExpr; 4]>
+    pub local_exprs: Vec<AffineExpr>,
+
+    /// The division represented by each local identifier, as the coefficients of its dividend over all the identifiers and the constant term, and a positive constant divisor. The semi-affine local identifiers have none.
+    pub local_divisions: Vec<Option<(Vec<i64>, i64)>>
 }
 
 impl SimpleAffineExprFlattener {
+    pub fn new(num_dims: usize, num_symbols: usize) -> Self {
+        Self {
+            operand_expr_stack: Vec::with_capacity(8),
+            num_dims,
+            num_symbols,
+            num_locals: 0,
+            local_exprs: Vec::new(),
+            local_divisions: Vec::new()
+        }
+    }
+
     /**
     Add a local identifier (needed to flatten a mod, floordiv, ceildiv expr).
     The local identifier added is always a floordiv of a pure add/mul affine function of other identifiers, coefficients of which are specified in dividend and with respect to a positive constant divisor. local_expr is the simplified tree expression (AffineExpr) corresponding to the quantifier.
     */
-    // virtual
-    fn add_local_floor_div_id(
-        &self, dividend: &[i64], divisor: i64, local_expr: AffineExpr
+    pub fn add_local_floor_div_id(
+        &mut self, dividend: &[i64], divisor: i64, local_expr: AffineExpr
     ) {
         assert!(divisor > 0, "Positive constant divisor expected.");
-        for sub_expr in self.operand_expr_stack {
-            sub_expr.insert(self.local_var_start_index() + self.num_locals, 0);
-        }
-        self.local_exprs.push(local_expr);
-        self.num_locals += 1;
-        // dividend and divisor are not used here; an override of this method uses it.
+        let index = self.add_local_id(local_expr);
+        let mut dividend = dividend.to_vec();
+        dividend.insert(index, 0);
+        self.local_divisions.push(Some((dividend, divisor)));
     }
 
     /**
     Add a local identifier (needed to flatten a mod, floordiv, ceildiv, mul expr) when the rhs is a symbolic expression. The local identifier added may be a floordiv, ceildiv, mul or mod of a pure affine/semi-affine function of other identifiers, coefficients of which are specified in the lhs of the mod, floordiv, ceildiv or mul expression and with respect to a symbolic rhs expression. `local_expr` is the simplified tree expression (AffineExpr) corresponding to the quantifier.
     */
-    //virtual
-    fn add_local_id_semi_affine(&mut self, local_expr: AffineExpr) {
-        for sub_expr in self.operand_expr_stack {
-            sub_expr.insert(self.local_var_start_index() + self.num_locals, 0);
+    pub fn add_local_id_semi_affine(&mut self, local_expr: AffineExpr) {
+        self.add_local_id(local_expr);
+        self.local_divisions.push(None);
+    }
+
+    /// Insert the column of a new local identifier in every expression of the stack and every division, and return the index of the column.
+    fn add_local_id(&mut self, local_expr: AffineExpr) -> usize {
+        let index = self.local_var_start_index() + self.num_locals;
+        for sub_expr in self.operand_expr_stack.iter_mut() {
+            sub_expr.insert(index, 0);
+        }
+        for (dividend, _) in self.local_divisions.iter_mut().flatten() {
+            dividend.insert(index, 0);
         }
         self.local_exprs.push(local_expr);
         self.num_locals += 1;
+        index
     }
 
-// private:
     /**
-    Adds `expr`, which may be mod, ceildiv, floordiv or mod expression representing the affine expression corresponding to the quantifier introduced as the local variable corresponding to `expr`. If the quantifier is already present, we put the coefficient in the proper index of `result`, otherwise we add a new local variable and put the coefficient there.
+    Adds `expr`, which may be mod, ceildiv, floordiv or mod expression representing the affine expression corresponding to the quantifier introduced as the local variable corresponding to `expr`. If the quantifier is already present, we put the coefficient in the proper index of the expression on top of the stack, otherwise we add a new local variable and put the coefficient there.
     */
-    fn add_local_variable_semi_affine(
-        &self,
-        expr: AffineExpr,
-        result: &SmallVector<[i64]>,
-    ) {
-        let loc = self.find_local_id(expr);
-        if loc.is_none() {
-            self.add_local_id_semi_affine(expr);
-        }
+    fn add_local_variable_semi_affine(&mut self, expr: AffineExpr) {
+        let index = match self.find_local_id(&expr) {
+            Some(index) => index,
+            None => {
+                self.add_local_id_semi_affine(expr);
+                self.num_locals - 1
+            }
+        };
+        let column = self.local_var_start_index() + index;
+        let result = self.operand_expr_stack.last_mut().unwrap();
         result.fill(0);
-        if let Some(index) = loc {
-            result[self.local_var_start_index() + index] = 1;
-        } else {
-            result[self.local_var_start_index() + self.num_locals - 1] = 1;
-        }
+        result[column] = 1;
+    }
+
+    /// Pop the flattened RHS operand off the stack, and return the constant it flattens to, if any.
+    fn pop_constant_operand(&mut self) -> (Vec<i64>, Option<i64>) {
+        assert!(self.operand_expr_stack.len() >= 2);
+        let rhs = self.operand_expr_stack.pop().unwrap();
+        let constant_index = self.constant_index();
+        let value = rhs[..constant_index].iter().all(|coefficient| *coefficient == 0)
+            .then_some(rhs[constant_index]);
+        (rhs, value)
+    }
+
+    /// Return the AffineExpr form of the flattened expression `flat_expr`.
+    fn expr_from_flat_form(&self, flat_expr: &[i64]) -> AffineExpr {
+        get_affine_expr_from_flat_form(
+            flat_expr, self.num_dims, self.num_symbols, &self.local_exprs,
+            core::ptr::null_mut())
     }
 
     /**
@@ -234,19 +252,61 @@ impl SimpleAffineExprFlattener {
 
     A ceildiv is similarly flattened:
     t = expr ceildiv c  <=> t = (expr + c - 1) floordiv c
-    */
-    fn visit_div_expr(&self, expr: AffineBinaryOpExpr, is_ceil: bool) {
-        assert!(self.operand_expr_stack.len() >= 2);
 
-        let context = expr.context();
-        let rhs = self.operand_expr_stack.last().unwrap();
-        self.operand_expr_stack.pop();
-        let lhs = self.operand_expr_stack.last().unwrap();
+    In case of semi affine division expressions, t = expr floordiv symbolic_expr or t = expr ceildiv symbolic_expr, introduce a local variable q (= expr floordiv/ceildiv symbolic_expr), and the affine floordiv/ceildiv is added to `local_exprs`.
+    */
+    fn visit_div_expr(&mut self, is_ceil: bool) {
+        let (rhs, rhs_value) = self.pop_constant_operand();
+        let lhs = self.operand_expr_stack.last().unwrap().clone();
+
+        // Flatten semi affine division expressions by introducing a local variable in place of the quotient, and the affine expression corresponding to the quantifier is added to `local_exprs`. Divisions by a non-positive constant are undefined, and kept as is in the same way.
+        let rhs_value = match rhs_value {
+            Some(value) if value >= 1 => value,
+            _ => {
+                let a = self.expr_from_flat_form(&lhs);
+                let b = self.expr_from_flat_form(&rhs);
+                let div_expr = if is_ceil { a.ceil_div(b) } else { a.floor_div(b) };
+                self.add_local_variable_semi_affine(div_expr);
+                return;
+            }
+        };
+
+        // Simplify the floordiv, ceildiv if possible by canceling out the greatest common divisors of the numerator and denominator.
+        let gcd = lhs.iter().fold(rhs_value, |gcd, coefficient|
+            math_extras::gcd(gcd, *coefficient));
+        // Simplify the numerator and the denominator.
+        let lhs = lhs.iter().map(|coefficient| coefficient / gcd).collect::<Vec<_>>();
+        let divisor = rhs_value / gcd;
+        // If the divisor becomes 1, the updated LHS is the result. (The divisor can't be negative since rhs_value is positive).
+        if divisor == 1 {
+            *self.operand_expr_stack.last_mut().unwrap() = lhs;
+            return;
+        }
 
-        // TODO
+        // If the divisor cannot be simplified to one, we will have to retain the ceil/floor expr (simplified up until here). Add an existential quantifier to express its result, i.e., expr1 div expr2 is replaced by a new identifier, q.
+        let a = self.expr_from_flat_form(&lhs);
+        let b = constant(divisor, core::ptr::null_mut());
+        let div_expr = if is_ceil { a.ceil_div(b) } else { a.floor_div(b) };
+        let index = match self.find_local_id(&div_expr) {
+            Some(index) => index,
+            None => {
+                let mut dividend = lhs;
+                if is_ceil {
+                    // lhs ceildiv c <=>  (lhs + c - 1) floordiv c
+                    *dividend.last_mut().unwrap() += divisor - 1;
+                }
+                self.add_local_floor_div_id(&dividend, divisor, div_expr);
+                self.num_locals - 1
+            }
+        };
+        // Set the expression on stack to the local var introduced to capture the result of the division (floor or ceil).
+        let column = self.local_var_start_index() + index;
+        let result = self.operand_expr_stack.last_mut().unwrap();
+        result.fill(0);
+        result[column] = 1;
     }
 
-    fn find_local_id(&self, local_expr: AffineExpr) -> Option<usize> {
+    fn find_local_id(&self, local_expr: &AffineExpr) -> Option<usize> {
         self.local_exprs.iter().position(|expr| expr == local_expr)
     }
 
@@ -265,48 +325,96 @@ impl SimpleAffineExprFlattener {
         self.num_dims + self.num_symbols
     }
 
-    // inline unsigned get_symbol_start_index(&self) const { return num_dims; }
+    #[inline]
+    const fn symbol_start_index(&self) -> usize {
+        self.num_dims
+    }
 
-    // inline unsigned get_dim_start_index(&self) const { return 0; }
+    #[inline]
+    const fn dim_start_index(&self) -> usize {
+        0
+    }
 }
 
 impl AffineExprVisitor for SimpleAffineExprFlattener {
     // Visitor method overrides.
-    fn visit_mul_expr(&self, expr: AffineExpr) {
 
+    /**
+    In pure affine t = expr * c, we multiply each coefficient of lhs with c.
+
+    In case of semi affine multiplication expressions, t = expr * symbolic_expr, introduce a local variable p (= expr * symbolic_expr), and the affine expression expr * symbolic_expr is added to `local_exprs`.
+    */
+    fn visit_mul_expr(&mut self, _expr: &AffineExpr) {
+        let (rhs, rhs_value) = self.pop_constant_operand();
+        let lhs = self.operand_expr_stack.last_mut().unwrap();
+
+        if let Some(rhs_value) = rhs_value {
+            for coefficient in lhs.iter_mut() {
+                *coefficient *= rhs_value;
+            }
+            return;
+        }
+
+        // A constant LHS scales the RHS in the same way.
+        let constant_index = lhs.len() - 1;
+        if lhs[..constant_index].iter().all(|coefficient| *coefficient == 0) {
+            let lhs_value = lhs[constant_index];
+            *lhs = rhs.iter().map(|coefficient| coefficient * lhs_value).collect();
+            return;
+        }
+
+        // Flatten semi-affine multiplication expressions by introducing a local variable in place of the product; the affine expression corresponding to the quantifier is added to `local_exprs`.
+        let a = self.expr_from_flat_form(self.operand_expr_stack.last().unwrap());
+        let b = self.expr_from_flat_form(&rhs);
+        self.add_local_variable_semi_affine(a * b);
     }
 
-    fn visit_add_expr(&self, expr: AffineExpr) {
+    fn visit_add_expr(&mut self, _expr: &AffineExpr) {
         assert!(self.operand_expr_stack.len() >= 2);
-        let rhs = self.operand_expr_stack.last().unwrap();
-        let lhs = self.operand_expr_stack[self.operand_expr_stack.len() - 2];
+        let rhs = self.operand_expr_stack.pop().unwrap();
+        let lhs = self.operand_expr_stack.last_mut().unwrap();
         assert!(lhs.len() == rhs.len());
         // Update the LHS in place.
-        for i in 0..rhs.len() {
-            lhs[i] += rhs[i];
+        for (lhs, rhs) in lhs.iter_mut().zip(rhs) {
+            *lhs += rhs;
         }
-        // Pop off the RHS.
-        self.operand_expr_stack.pop_back();
     }
 
-    fn visit_dim_expr(&self, expr: AffineExpr) {
-
+    fn visit_dim_expr(&mut self, expr: &AffineExpr) {
+        let Dimension { index } = expr else {
+            unreachable!()
+        };
+        assert!(*index < self.num_dims, "Inconsistent number of dims");
+        let mut eq = vec![0; self.num_cols()];
+        eq[self.dim_start_index() + index] = 1;
+        self.operand_expr_stack.push(eq);
     }
 
-    fn visit_symbol_expr(&self, expr: AffineExpr) {
-
+    fn visit_symbol_expr(&mut self, expr: &AffineExpr) {
+        let Symbol { index } = expr else {
+            unreachable!()
+        };
+        assert!(*index < self.num_symbols, "Inconsistent number of symbols");
+        let mut eq = vec![0; self.num_cols()];
+        eq[self.symbol_start_index() + index] = 1;
+        self.operand_expr_stack.push(eq);
     }
 
-    fn visit_constant_expr(&self, expr: AffineExpr) {
-
+    fn visit_constant_expr(&mut self, expr: &AffineExpr) {
+        let Constant { value } = expr else {
+            unreachable!()
+        };
+        let mut eq = vec![0; self.num_cols()];
+        eq[self.constant_index()] = *value;
+        self.operand_expr_stack.push(eq);
     }
 
-    fn visit_ceil_div_expr(&self, expr: AffineExpr) {
-        self.visit_div_expr(expr, true)
+    fn visit_ceil_div_expr(&mut self, _expr: &AffineExpr) {
+        self.visit_div_expr(true)
     }
 
-    fn visit_floor_div_expr(&self, expr: AffineExpr) {
-        self.visit_div_expr(expr, false)
+    fn visit_floor_div_expr(&mut self, _expr: &AffineExpr) {
+        self.visit_div_expr(false)
     }
 
     /**
@@ -315,6 +423,50 @@ impl AffineExprVisitor for SimpleAffineExprFlattener {
     A mod expression "expr mod c" is thus flattened by introducing a new local
     variable q (= expr floordiv c), such that expr mod c is replaced with
     'expr - c * q' and c * q <= expr <= c * q + c - 1 are added to localVarCst.
+
+    In case of semi-affine modulo expressions, t = expr mod symbolic_expr, introduce a local variable m (= expr mod symbolic_expr), and the affine expression expr mod symbolic_expr is added to `local_exprs`.
     */
-    fn visit_mod_expr(&self, expr: AffineExpr);
+    fn visit_mod_expr(&mut self, _expr: &AffineExpr) {
+        let (rhs, rhs_value) = self.pop_constant_operand();
+        let lhs = self.operand_expr_stack.last().unwrap().clone();
+
+        // Flatten semi affine modulo expressions by introducing a local variable in place of the modulo value, and the affine expression corresponding to the quantifier is added to `local_exprs`. Modulos by a non-positive constant are undefined, and kept as is in the same way.
+        let rhs_value = match rhs_value {
+            Some(value) if value >= 1 => value,
+            _ => {
+                let dividend_expr = self.expr_from_flat_form(&lhs);
+                let divisor_expr = self.expr_from_flat_form(&rhs);
+                self.add_local_variable_semi_affine(dividend_expr % divisor_expr);
+                return;
+            }
+        };
+
+        // Check if the LHS expression is a multiple of modulo factor. If yes, modulo expression here simplifies to zero.
+        if lhs.iter().all(|coefficient| coefficient % rhs_value == 0) {
+            self.operand_expr_stack.last_mut().unwrap().fill(0);
+            return;
+        }
+
+        // Add a local variable for the quotient, i.e., expr % c is replaced by (expr - q * c) where q = expr floordiv c. Do this while canceling out the GCD of expr and c.
+        let gcd = lhs.iter().fold(rhs_value, |gcd, coefficient|
+            math_extras::gcd(gcd, *coefficient));
+        // Simplify the numerator and the denominator.
+        let floor_dividend = lhs.iter().map(|coefficient| coefficient / gcd)
+            .collect::<Vec<_>>();
+        let floor_divisor = rhs_value / gcd;
+
+        // Construct the AffineExpr form of the floordiv to store in local_exprs.
+        let dividend_expr = self.expr_from_flat_form(&floor_dividend);
+        let floor_div_expr = dividend_expr.floor_div(floor_divisor);
+        let index = match self.find_local_id(&floor_div_expr) {
+            Some(index) => index,
+            None => {
+                self.add_local_floor_div_id(&floor_dividend, floor_divisor, floor_div_expr);
+                self.num_locals - 1
+            }
+        };
+        // Set result at top of stack to "lhs - rhs_value * q".
+        let column = self.local_var_start_index() + index;
+        self.operand_expr_stack.last_mut().unwrap()[column] = -rhs_value;
+    }
 }
@@ -22,7 +22,7 @@ use llvm::{
 };
 use crate::{
     ir::{
-        affine_expr::{AffineExpr, dimension},
+        affine_expr::{AffineExpr, constant, dimension, simplify_affine_expr, symbol},
         attribute::Attribute,
         builtins::{
             attributes::IntegerAttribute,
@@ -52,19 +52,58 @@ For exaple:
 
 The names used above, `d0` and `d1`, don't matter - it's the mathematical function that is unique to this affine map.
 */
-pub struct AffineMap<
-    const D: usize,
-    const S: usize,
-    const O: usize
-> {
+#[derive(Clone, PartialEq)]
+pub struct AffineMap {
     // 
     // AffineMapStorage
     // 
 
-    outputs: [AffineExpr; O],
-    context: *mut MLIRContext,
-    _num_dims: core::marker::PhantomData<D>,
-    _num_symbols: core::marker::PhantomData<S>
+    num_dims: usize,
+    num_symbols: usize,
+    outputs: Vec<AffineExpr>,
+    context: *mut MLIRContext
+}
+
+impl core::fmt::Display for AffineMap {
+    /// Prints the map as `(d0, d1)[s0] -> (d0 + s0, d1)`.
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        print_dims_and_symbols(f, self.num_dims, self.num_symbols)?;
+        f.write_str(" -> (")?;
+        for (index, output) in self.outputs.iter().enumerate() {
+            if index != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{}", output)?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Print the dimension and symbol lists of an affine map or integer set, e.g. `(d0, d1)[s0]`. The symbol list is omitted when there are no symbols.
+pub(crate) fn print_dims_and_symbols(
+    f: &mut core::fmt::Formatter<'_>,
+    num_dims: usize,
+    num_symbols: usize
+) -> core::fmt::Result {
+    f.write_str("(")?;
+    for index in 0..num_dims {
+        if index != 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "d{}", index)?;
+    }
+    f.write_str(")")?;
+    if num_symbols != 0 {
+        f.write_str("[")?;
+        for index in 0..num_symbols {
+            if index != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "s{}", index)?;
+        }
+        f.write_str("]")?;
+    }
+    Ok(())
 }
 
 /**
@@ -74,40 +113,35 @@ Returns a single constant output affine map.
 () -> (%value)
 ```
 */
-pub const fn constant_map(value: i64, context: *mut MLIRContext)
--> AffineMap<0, 0, 1> {
-    AffineMap::<0, 0, 1>::new({constant(value, context)})
+pub fn constant_map(value: i64, context: *mut MLIRContext) -> AffineMap {
+    AffineMap::new(0, 0, vec![constant(value, context)], context)
 }
 
 /**
-Returns an AffineMap with `D` identity output dim exprs.
+Returns an AffineMap with `num_dims` identity output dim exprs.
 
 ```mlir
 (d0, ..., dn) -> (d0, ..., dn)
 ```
 */
-pub const fn multi_dim_identity_map<const D: usize> (
-    context: *mut MLIRContext
-) -> AffineMap<D, 0, D> {
-    let dims: [AffineExpr; D] = from_fn(|index| dimension(index, context));
-    AffineMap::<D, 0, D>::new(dims, context)
+pub fn multi_dim_identity_map(num_dims: usize, context: *mut MLIRContext)
+    -> AffineMap
+{
+    let dims = (0..num_dims).map(|index| dimension(index, context)).collect();
+    AffineMap::new(num_dims, 0, dims, context)
 }
 
 /**
 Returns an identity affine map `(d0, ..., dn) -> (dp, ..., dn)` on the most minor dimensions.
 */
-// static
-pub const fn minor_identity_map<
-    const D: usize,
-    const O: usize
->(
+pub fn minor_identity_map(
+    num_dims: usize,
     outputs: usize,
     context: *mut MLIRContext
-) -> AffineMap<D, 0, _>
+) -> AffineMap
 {
-    assert!(D >= O, "Dimension mismatch");
-    let id = multi_dim_identity_map::<D>(context);
-    AffineMap::<D, 0, _>::new(id.outputs().take_back(outputs), context)
+    assert!(num_dims >= outputs, "Dimension mismatch");
+    multi_dim_identity_map(num_dims, context).minor_sub_map(outputs)
 }
 
 /**
@@ -115,21 +149,52 @@ Returns an AffineMap representing a permutation.
 The permutation is expressed as a non-empty vector of integers.
 E.g. the permutation `(i, j, k) -> (j, k, i)` will be expressed with `permutation = [1, 2, 0]`. All values in `permutation` must be integers, in the range `0..(permutation.len() - 1)` without duplications (i.e. `[1, 1, 2]` is an invalid permutation).
 */
-// static
-pub fn permutation_map<const N: usize>(
-    permutation: &[usize; N],
-    context: *mut MLIRContext
-) -> AffineMap<N, 0, _> {
+pub fn permutation_map(permutation: &[usize], context: *mut MLIRContext)
+    -> AffineMap
+{
     assert!(
         !permutation.is_empty(),
         "Cannot create permutation map from empty permutation vector");
-    let exprs = from_fn(|index| dimension(index, context));
-    let permutation_map = AffineMap::<N, 0, _>::new(exprs, context);
+    let exprs = permutation.iter()
+        .map(|index| dimension(*index, context)).collect();
+    let permutation_map
+        = AffineMap::new(permutation.len(), 0, exprs, context);
     assert!(permutation_map.is_permutation(), "Invalid permutation vector");
     permutation_map
 }
 
-impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
+impl AffineMap {
+    /// Returns an affine map with `num_dims` dimensions and `num_symbols` symbols mapping to `outputs`.
+    pub fn new(
+        num_dims: usize,
+        num_symbols: usize,
+        outputs: Vec<AffineExpr>,
+        context: *mut MLIRContext
+    ) -> Self {
+        Self { num_dims, num_symbols, outputs, context }
+    }
+
+    /// Returns a zero result affine map with no dimensions or symbols: () -> ().
+    pub fn empty(context: *mut MLIRContext) -> Self {
+        Self::new(0, 0, Vec::new(), context)
+    }
+
+    pub const fn num_dims(&self) -> usize {
+        self.num_dims
+    }
+
+    pub const fn num_symbols(&self) -> usize {
+        self.num_symbols
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.num_dims + self.num_symbols
+    }
+
     /// Returns a vector of AffineMaps; each with as many outputs as `exprs.size()`, as many dims as the largest dim in `exprs` and as many symbols as the largest symbol in `exprs`.
     // static
     pub fn infer_from_expr_list(exprs_list: &[&[AffineExpr]])
@@ -145,7 +210,7 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
     /// Returns true if this affine map is an identity affine map.
     /// An identity affine map corresponds to an identity affine function on the dimensional identifiers.
     pub const fn is_identity(&self) -> bool {
-        if D != O {
+        if self.num_dims != self.num_outputs() {
             return false;
         }
         let outputs = self.outputs();
@@ -160,8 +225,9 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
 
     /// Returns true if this affine map is a minor identity, i.e. an identity affine map (d0, ..., dn) -> (dp, ..., dn) on the most minor dimensions.
     pub const fn is_minor_identity(&self) -> bool {
-        D >= O
-        && self == self.minor_identity_map(D, O, self.context())
+        self.num_dims >= self.num_outputs()
+        && *self == minor_identity_map(
+            self.num_dims, self.num_outputs(), self.context)
     }
 
     /// Returns true if this affine map is a minor identity up to broadcasted dimensions which are indicated by value 0 in the output. If `broadcasted_dims` is not null, it will be populated with the indices of the broadcasted dimensions in the output array.
@@ -175,10 +241,10 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
         if !broadcasted_dims.is_null() {
             broadcasted_dims.clear();
         }
-        if D < O {
+        if self.num_dims < self.num_outputs() {
             return false;
         }
-        let suffix_start = D - O;
+        let suffix_start = self.num_dims - self.num_outputs();
         for (index, output) in self.outputs().iter().enumerate() {
             match output {
                 Constant { value } => {
@@ -218,24 +284,24 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
         &self,
         permuted_dims: &mut SmallVector<[usize]>
     ) -> bool {
-        let projection_start = if O < D + S {
-            D + S - O
+        let projection_start = if self.num_outputs() < self.num_inputs() {
+            self.num_inputs() - self.num_outputs()
         } else {
             0
         };
         permuted_dims.clear();
         let broadcast_dims = SmallVector::<[usize]>::new();
-        permuted_dims.resize(O, 0);
+        permuted_dims.resize(self.num_outputs(), 0);
         /*
         If there are more outputs than input dimensions we want the new map to start with broadcast dimensions in order to be a minor identity with broadcasting.
         */
-        let leading_broadcast = if O > D + S {
-            O - D + S
+        let leading_broadcast = if self.num_outputs() > self.num_inputs() {
+            self.num_outputs() - self.num_inputs()
         } else {
             0
         };
         let dim_found = SmallBitVector::new(
-            max(D + S, O), false);
+            max(self.num_inputs(), self.num_outputs()), false);
         for (index, expr) in self.outputs().iter().enumerate() {
             /*
             Each output may be either a constant 0 (broadcast dimension) or a dimension.
@@ -275,12 +341,12 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
 
     /// Returns true if this affine map is an empty map, i.e., () -> ().
     pub const fn is_empty(&self) -> bool {
-        D == 0 && S == 0 && O == 0
+        self.num_dims == 0 && self.num_symbols == 0 && self.num_outputs() == 0
     }
 
     /// Returns true if this affine map is a single output constant function.
     pub const fn is_single_constant(&self) -> bool {
-        O == 1 && matches!(self.output(0), Constant)
+        self.num_outputs() == 1 && matches!(self.output(0), Constant)
     }
 
     /// Returns true if this affine map has only constant outputs.
@@ -307,12 +373,12 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
     }
 
     /// The affine expressions for this (multi-dimensional) map.
-    pub const fn outputs<const N: usize>(&self) -> &[AffineExpr; N] {
-        {getTrailingObjects<AffineExpr>(), O}
+    pub fn outputs(&self) -> &[AffineExpr] {
+        &self.outputs
     }
 
-    pub const fn output(&self, index: usize) -> AffineExpr {
-        self.outputs()[index]
+    pub fn output(&self, index: usize) -> &AffineExpr {
+        &self.outputs[index]
     }
 
     /// Extracts the position of the dimensional expression at the given output, when the caller knows it is safe to do so.
@@ -353,80 +419,72 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
     }
 
     /// This method substitutes any uses of dimensions and symbols (e.g. dim#0 with dim_replacements[0]) in subexpressions and returns the modified expression mapping. Because this can be used to eliminate dims and symbols, the client needs to specify the number of dims and symbols in the output. The returned map always has the same number of outputs.
-    pub const fn replace_dims_and_symbols<
-        const D_: usize,
-        const S_: usize
-    >(
+    pub fn replace_dims_and_symbols(
         &self,
-        dim_replacements: [&AffineExpr; D_],
-        sym_replacements: [&AffineExpr; S_],
-    ) -> AffineMap<D_, S_, O>
+        dim_replacements: &[AffineExpr],
+        sym_replacements: &[AffineExpr],
+        num_result_dims: usize,
+        num_result_syms: usize
+    ) -> AffineMap
     {
-        let exprs = self.outputs().map(
+        let exprs = self.outputs.iter().map(
             |output| output.replace_dims_and_symbols(
-                dim_replacements, sym_replacements));
-        AffineMap::<D_, S_, O>::new(exprs, self.context())
+                dim_replacements, sym_replacements)).collect();
+        AffineMap::new(num_result_dims, num_result_syms, exprs, self.context)
     }
 
     /// Sparse replace method. Apply AffineExpr::replace(`expr`, `replacement`) to each of the outputs and return a new AffineMap with the new outputs and with the specified number of dims and symbols.
-    pub const fn replace<const D_: usize, const S_: usize>(
+    pub fn replace(
         &self,
-        expr: AffineExpr,
-        replacement: AffineExpr,
-    ) ->  AffineMap::<D_, S_, O>
+        expr: &AffineExpr,
+        replacement: &AffineExpr,
+        num_result_dims: usize,
+        num_result_syms: usize
+    ) -> AffineMap
     {
-        let exprs = self.outputs().map(|output| output.replace(expr, replacement));
-        AffineMap::<D_, S_, O>::get(exprs, self.context())
+        let exprs = self.outputs.iter().map(
+            |output| output.replace(expr.clone(), replacement.clone()))
+            .collect();
+        AffineMap::new(num_result_dims, num_result_syms, exprs, self.context)
     }
-    
-    /// Replace dims[offset..numDims] by dims[(offset + shift)..(shift + numDims)].
-    pub const fn shift_dims<const SHIFT: usize>(
-        &self, shift: usize, offset: usize /* 0 */
-    ) -> AffineMap<{D + SHIFT}, S, _>
-    {
-        assert!(offset <= D);
-        AffineMap::<{D + SHIFT}, S, _>::get(
-            self.outputs().iter().map(
-                |expr: AffineExpr|
-                    expr.shift_dims(D, shift, offset)
-            ).collect::<SmallVector<[AffineExpr; 4]>>(),
-            self.context())
+
+    /// Replace dims[offset..num_dims] by dims[(offset + shift)..(shift + num_dims)].
+    pub fn shift_dims(&self, shift: usize, offset: usize /* 0 */) -> Self {
+        assert!(offset <= self.num_dims);
+        let exprs = self.outputs.iter().map(
+            |expr| expr.shift_dims(self.num_dims, shift, offset)).collect();
+        AffineMap::new(
+            self.num_dims + shift, self.num_symbols, exprs, self.context)
     }
 
     /// Replace symbols[offset..num_symbols] by symbols[(offset + shift)..(shift + num_symbols)].
-    pub const fn shift_symbols<const SHIFT: usize>(&self, shift: usize, offset: usize /* 0 */) -> Self {
-        AffineMap::<D, {S + SHIFT}, _>::get(
-            self.outputs().iter().map(
-                |expr: AffineExpr|
-                    expr.shift_symbols(S, shift, offset)
-            ).collect::<SmallVector<[AffineExpr; 4]>>(),
-            self.context())
+    pub fn shift_symbols(&self, shift: usize, offset: usize /* 0 */) -> Self {
+        let exprs = self.outputs.iter().map(
+            |expr| expr.shift_symbols(self.num_symbols, shift, offset))
+            .collect();
+        AffineMap::new(
+            self.num_dims, self.num_symbols + shift, exprs, self.context)
     }
 
     /// Returns a new AffineMap with the same number of dims and symbols and one less output at `index`, dropped.
-    pub fn drop_output(&mut self, index: i64) -> Self {
-        self.drop_outputs([index])
+    pub fn drop_output(&self, index: usize) -> Self {
+        self.drop_outputs(&[index])
     }
 
     /// Returns a new AffineMap with the same number of dims and symbols, but all indices in `indices` dropped from outputs.
-    pub fn drop_outputs(&mut self, indices: [i64]) -> Self {
-        let reverse_sorted_indices: SmallVector<[i64]> = indices;
-        reverse_sorted_indices.sort();
-
-        let exprs: SmallVector<[AffineExpr; 4]> = self.outputs();
-        for index in reverse_sorted_indices {
-            exprs.erase(index);
-        }
-        AffineMap::<D, S, _>::get(exprs, self.context())
+    pub fn drop_outputs(&self, indices: &[usize]) -> Self {
+        let exprs = self.outputs.iter().enumerate()
+            .filter(|(index, _)| !indices.contains(index))
+            .map(|(_, expr)| expr.clone())
+            .collect();
+        AffineMap::new(self.num_dims, self.num_symbols, exprs, self.context)
     }
 
     /// Returns a new AffineMap with the same number of dims and symbols and an extra output inserted at `index`.
-    pub fn insert_output(&mut self, expr: AffineExpr, index: usize)
-    -> AffineMap::<D, S, {O + 1}>
-    {
-        let exprs: SmallVector<[AffineExpr; 4]> = self.outputs();
+    pub fn insert_output(&self, expr: AffineExpr, index: usize) -> Self {
+        let mut exprs = self.outputs.clone();
         exprs.insert(index, expr);
-        AffineMap::<D, S, {O + 1}>::get(exprs, self.context())
+        AffineMap::new(self.num_dims, self.num_symbols, exprs, self.context)
     }
 
     /// Folds the outputs of the application of an affine map on the provided operands to a constant if possible.
@@ -454,13 +512,13 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
     /// Propagates the constant operands into this affine map. Operands are allowed to be null, at which point they are treated as non-constant. This does not change the number of symbols and dimensions. Returns a new map, which may be equal to the old map if no folding happened. If `outputs` is provided and if all expressions in the map were folded to constants, `outputs` will contain the values of these constants.
     pub const fn partial_constant_fold(
         &self,
-        operand_constants: &[impl Attribute; D + S],
+        operand_constants: &[impl Attribute],
         outputs: *mut SmallVector<[i64]> //= nullptr
-    ) -> AffineMap::<D, S, O>
+    ) -> AffineMap
     {
         // Fold each of the result expressions.
         let expr_folder = ConstantFolder::new(
-            D, operand_constants);
+            self.num_dims, operand_constants);
         let exprs = self.outputs().map(|expr| {
             let folded = expr_folder.constant_fold(expr);
             /*
@@ -481,7 +539,7 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
                 expr
             }
         });
-        AffineMap::<D, S, O>::get(exprs, self.context())
+        AffineMap::new(self.num_dims, self.num_symbols, exprs, self.context)
     }
 
     /**
@@ -500,18 +558,21 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
       map1.compose(map2):
         `(d0)[s0, s1, s2] -> (d0 + s1 + s2 + 1, d0 - s0 - s2 - 1)`
     */
-    pub const fn compose(&self, other: AffineMap<D_, S_, D>)
-    -> AffineMap<D_, {S + S_}, O>
-    {
+    pub fn compose(&self, other: &AffineMap) -> AffineMap {
         // Prepare `other` by concatenating the symbols and rewriting its exprs.
-        let new_dims: [AffineExpr; D_]
-            = from_fn(|index| dimension(index, self.context()));
-        let new_symbols: [AffineExpr; S_]
-            = from_fn(|index| symbol(index + S, self.context()));
-        let new_map
-            = other.replace_dims_and_symbols(new_dims, new_symbols);
-        let exprs = self.outputs().map(|output| output.compose(new_map));
-        AffineMap::<D_, {S + S_}, O>::get(exprs, other.context())
+        let new_dims: Vec<AffineExpr> = (0..other.num_dims)
+            .map(|index| dimension(index, self.context)).collect();
+        let new_symbols: Vec<AffineExpr> = (0..other.num_symbols)
+            .map(|index| symbol(index + self.num_symbols, self.context))
+            .collect();
+        let new_map = other.replace_dims_and_symbols(
+            &new_dims, &new_symbols,
+            other.num_dims, self.num_symbols + other.num_symbols);
+        let exprs = self.outputs.iter()
+            .map(|output| output.compose(&new_map)).collect();
+        AffineMap::new(
+            other.num_dims, self.num_symbols + other.num_symbols, exprs,
+            other.context)
     }
 
     /// Returns true if the AffineMap represents a subset (i.e. a projection) of a symbol-less permutation map. `allow_zero_in_results` allows projected permutation maps with constant zero output expressions.
@@ -519,16 +580,16 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
     pub const fn is_projected_permutation(
         &self, allow_zero_in_results: bool /* false */
     ) -> bool {
-        if S > 0 {
+        if self.num_symbols > 0 {
             return false;
         }
     
         // Having more results than inputs means that results have duplicated dims or zeros that can't be mapped to input dims.
-        if O > D + S {
+        if self.num_outputs() > self.num_inputs() {
             return false;
         }
     
-        let mut seen = [false; D + S];
+        let mut seen = vec![false; self.num_inputs()];
         // A projected permutation can have, at most, only one instance of each input dimension in the result expressions. Zeros are allowed as long as the number of result expressions is lower or equal than the number of input expressions.
         for output in self.outputs() {
             match output {
@@ -553,50 +614,50 @@ impl<const D: usize, const S: usize, const O: usize> AffineMap<D, S, O> {
 
     /// Returns true if the AffineMap represents a symbol-less permutation map.
     pub const fn is_permutation(&self) -> bool {
-        if D != O {
+        if self.num_dims != self.num_outputs() {
             return false;
         }
         self.is_projected_permutation(false)
     }
 
     /// Returns the map consisting of the `indices` subset.
-    pub const fn sub_map<const O_: usize>(&self, indices: &[usize; O_])
-    -> AffineMap<D, S, O_>
-    {
-        let exprs = indices.map(|index| self.output(index));
-        AffineMap::<D, S, O_>::get(exprs, self.context())
+    pub fn sub_map(&self, indices: &[usize]) -> AffineMap {
+        let exprs = indices.iter()
+            .map(|index| self.outputs[*index].clone()).collect();
+        AffineMap::new(self.num_dims, self.num_symbols, exprs, self.context)
     }
 
     /// Returns the map consisting of `length` expressions starting from `start`.
-    pub const fn slice_map(&self, start: usize, length: usize) -> Self {
-        AffineMap::<D, S, _>::get(
-            self.outputs()[start..(start + length)], self.context())
+    pub fn slice_map(&self, start: usize, length: usize) -> Self {
+        AffineMap::new(
+            self.num_dims, self.num_symbols,
+            self.outputs[start..(start + length)].to_vec(), self.context)
     }
 
     /// Returns the map consisting of the most major `num_outputs` outputs.
-    /// Returns the null AffineMap if `num_outputs` == 0.
-    /// Returns `*this` if `num_outputs` >= `this->O`.
-    pub const fn major_sub_map(self, num_outputs: usize) -> Self {
+    /// Returns the empty AffineMap if `num_outputs` == 0.
+    /// Returns `self` if `num_outputs` >= the number of outputs.
+    pub fn major_sub_map(&self, num_outputs: usize) -> Self {
         if num_outputs == 0 {
-            return AffineMap::new();
+            return AffineMap::empty(self.context);
         }
-        if num_outputs > O {
-            return self;
+        if num_outputs > self.num_outputs() {
+            return self.clone();
         }
         self.slice_map(0, num_outputs)
     }
 
     /// Returns the map consisting of the most minor `num_outputs` outputs.
-    /// Returns the null AffineMap if `num_outputs` == 0.
-    /// Returns `*this` if `num_outputs` >= `this->O`.
-    pub const fn minor_sub_map(self, num_outputs: usize) -> Self {
+    /// Returns the empty AffineMap if `num_outputs` == 0.
+    /// Returns `self` if `num_outputs` >= the number of outputs.
+    pub fn minor_sub_map(&self, num_outputs: usize) -> Self {
         if num_outputs == 0 {
-            return AffineMap::new();
+            return AffineMap::empty(self.context);
         }
-        if num_outputs > O {
-            return self;
+        if num_outputs > self.num_outputs() {
+            return self.clone();
         }
-        self.slice_map(O - num_outputs, num_outputs)
+        self.slice_map(self.num_outputs() - num_outputs, num_outputs)
     }
 
     /// Get the largest known divisor of all map expressions.
@@ -622,7 +683,7 @@ pub const fn infer_from_expr_list<I: Iterator<Item = AffineExpr>>(
     maps.reserve(exprs_list.len());
     for exprs in exprs_list {
         maps.push(
-            AffineMap::<>::get(
+            AffineMap::new(
                 /*dimCount=*/max_dim + 1,
                 /*symbolCount=*/max_sym + 1, exprs, context));
     }
@@ -631,12 +692,10 @@ pub const fn infer_from_expr_list<I: Iterator<Item = AffineExpr>>(
 
 /// Simplifies an affine map by simplifying its underlying AffineExpr results.
 pub fn simplify_affine_map(map: AffineMap) -> AffineMap {
-    let mut exprs = SmallVector::<[AffineExpr; 8]>::new();
-    for e in map.outputs() {
-        exprs.push(
-            simplify_affine_expr(e, map.num_dims, map.num_symbols));
-    }
-    AffineMap::get(map.num_dims, map.num_symbols, exprs, map.context())
+    let exprs = map.outputs().iter()
+        .map(|e| simplify_affine_expr(e.clone(), map.num_dims, map.num_symbols))
+        .collect();
+    AffineMap::new(map.num_dims, map.num_symbols, exprs, map.context())
 }
 
 /// Drop the dims that are not used.
@@ -664,14 +723,14 @@ pub fn compress_unused_impl(
         llvm::append_range(all_exprs, m.outputs());
     }
     let unified_map = compression_fun(
-        AffineMap::get(num_dims, num_symbols, all_exprs, maps.front().context()));
+        AffineMap::new(num_dims, num_symbols, all_exprs, maps.front().context()));
     let unified_num_dims = unified_map.num_dims;
     let unified_num_symbols = unified_map.num_symbols;
     let unified_results = unified_map.outputs();
     let mut res = SmallVector::<[AffineMap]>::new();
     res.reserve(maps.len());
     for m in maps {
-        res.push(AffineMap::get(unified_num_dims, unified_num_symbols,
+        res.push(AffineMap::new(unified_num_dims, unified_num_symbols,
                                     unified_results.take_front(m.num_outputs),
                                     m.context()));
         unified_results = unified_results.drop_front(m.num_outputs);
@@ -689,9 +748,9 @@ pub fn compress_unused_impl(
 
 /// Drop the dims that are not listed in `unused_dims`.
 pub fn compress_dims(
-    map: AffineMap<D, S, O>,
+    map: AffineMap,
     unused_dims: &SmallBitVector
-) -> AffineMap<_, S, _>
+) -> AffineMap
 {
     let mut num_dims = 0;
     let mut dim_replacements = SmallVector::<[AffineExpr]>::new();
@@ -711,11 +770,11 @@ pub fn compress_dims(
     for e in map.outputs() {
         result_exprs.push(e.replace_dims(dim_replacements));
     }
-    AffineMap::<_, S, _>::get(num_dims, result_exprs, context)
+    AffineMap::new(num_dims, result_exprs, context)
 }
 
 /// Drop the symbols that are not used.
-pub fn compress_unused_symbols(map: AffineMap<D, S, O>) -> AffineMap {
+pub fn compress_unused_symbols(map: AffineMap) -> AffineMap {
     let unused_symbols = SmallBitVector::new(S, true);
     map.walk_exprs(|expr: AffineExpr| {
         match expr {
@@ -737,9 +796,9 @@ pub fn compress_unused_symbols(map: AffineMap<D, S, O>) -> AffineMap {
 
 /// Drop the symbols that are not listed in `unused_symbols`.
 pub fn compress_symbols(
-    map: AffineMap<D, S, O>,
+    map: AffineMap,
     unused_symbols: &SmallBitVector
-) -> AffineMap<D, _, O>
+) -> AffineMap
 {
     let mut num_symbols = 0;
     let sym_replacements = SmallVector::<[AffineExpr]>::new();
@@ -758,16 +817,16 @@ pub fn compress_symbols(
     for e in map.outputs() {
         result_exprs.push(e.replace_symbols(sym_replacements));
     }
-    AffineMap::<D, _, O>::get(num_symbols, result_exprs, context)
+    AffineMap::new(num_symbols, result_exprs, context)
 }
 
 /// Returns a map with the same dimension and symbol count as `map`, but whose results are the unique affine expressions of `map`.
-pub fn remove_duplicate_exprs(map: AffineMap<D, S, O>) -> AffineMap<D, S, _> {
+pub fn remove_duplicate_exprs(map: AffineMap) -> AffineMap {
     let results = map.outputs();
     let unique_exprs: SmallVector::<[AffineExpr; 4]> = results;
     unique_exprs.erase(std::unique(unique_exprs.begin(), unique_exprs.end()),
                         unique_exprs.end());
-    AffineMap::<D, S, _>::get(unique_exprs, map.context())
+    AffineMap::new(unique_exprs, map.context())
 }
 
 /**
@@ -804,7 +863,7 @@ returns:
 (d0, d1, d2, d3, d4, d5, d6, d7) -> (d2, d0, d3)
 ```
 */
-pub fn inverse_permutation(map: AffineMap<D, 0, O>) -> AffineMap {
+pub fn inverse_permutation(map: AffineMap) -> AffineMap {
     if map.is_empty() {
         return map;
     }
@@ -831,7 +890,7 @@ pub fn inverse_permutation(map: AffineMap<D, 0, O>) -> AffineMap {
     if seen_exprs.len() != D { 
         return AffineMap::new();
     }
-    AffineMap::<O, 0, _>::get(seen_exprs, map.context())
+    AffineMap::new(seen_exprs, map.context())
 }
 
 /**
@@ -888,7 +947,7 @@ affine_map<(d0, d1) -> (d0, 0, 0)>
 ```
 */
 pub fn inverse_and_broadcast_projected_permutation(
-    map: AffineMap<D, S, O>
+    map: AffineMap
 ) -> AffineMap
 {
     assert!(map.is_projected_permutation(/*allowZeroInResults=*/true));
@@ -908,7 +967,7 @@ pub fn inverse_and_broadcast_projected_permutation(
         // Reverse each dimension existing in the original map result.
         exprs[map.dim_index(i)] = dimension(i, context);
     }
-    AffineMap::<O, 0, _>::get(exprs, context)
+    AffineMap::new(exprs, context)
 }
 
 /**
@@ -954,7 +1013,7 @@ pub fn concat_affine_maps(
         num_symbols += m.num_symbols;
         num_dims = std::max(m.num_dims, num_dims);
     }
-    AffineMap::get(num_dims, num_symbols, results, maps.front().context())
+    AffineMap::new(num_dims, num_symbols, results, maps.front().context())
 }
 
 /**
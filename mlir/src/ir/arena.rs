@@ -0,0 +1,1366 @@
+//! IR Arena
+//!
+//! This file defines the storage that owns every `Operation`, `Block` and `Region` of an `MLIRContext`, along with the typed handles `OpId`, `BlockId` and `RegionId` that refer into it.
+//!
+//! Upstream heap-allocates operations and links them through intrusive lists of raw pointers. Here each IR object lives in a slot of an [`IRArena`], and handles carry the generation of the slot they were created for. Erasing an object bumps the generation of its slot, so a handle that outlives its object is reported as an [`ArenaError`] instead of reading freed memory.
+//!
+//! There is no upstream counterpart to this file.
+
+use core::fmt::{Display, Formatter, Result as FmtResult};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU32, Ordering}
+    }
+};
+
+use crate::ir::{
+    block::Block,
+    block_and_value_mapping::BlockAndValueMapping,
+    location::Location,
+    operation::Operation,
+    region::Region,
+    r#type::Type,
+    value::{Input, InputImpl, Output, Use, Value, ValueImpl}
+};
+
+/// Result type of the checked arena accessors.
+pub type ArenaResult<T> = Result<T, ArenaError>;
+
+macro_rules! handle {
+    ($(#[$meta:meta])* $name:ident) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, Debug, Eq, PartialEq, Hash, PartialOrd, Ord)]
+        pub struct $name {
+            index: u32,
+            generation: u32
+        }
+
+        impl $name {
+            /// Returns the slot this handle refers to. Slots are reused after erasure, so this is only unique among live objects.
+            pub const fn index(&self) -> usize {
+                self.index as usize
+            }
+
+            /// A handle that never refers to a live object, used by objects that have not been handed to an arena yet.
+            pub(crate) const DANGLING: Self = Self {
+                index: u32::MAX,
+                generation: u32::MAX
+            };
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+                write!(f, "{}({}v{})", stringify!($name), self.index, self.generation)
+            }
+        }
+    };
+}
+
+handle!(
+    /// Handle to an `Operation` stored in an `IRArena`.
+    OpId
+);
+handle!(
+    /// Handle to a `Block` stored in an `IRArena`.
+    BlockId
+);
+handle!(
+    /// Handle to a `Region` stored in an `IRArena`.
+    RegionId
+);
+
+/// Errors reported by the checked arena accessors and mutators.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ArenaError {
+    /// The operation referred to by the handle has been erased.
+    ErasedOperation(OpId),
+    /// The block referred to by the handle has been erased.
+    ErasedBlock(BlockId),
+    /// The region referred to by the handle has been erased.
+    ErasedRegion(RegionId),
+    /// The operation is already inserted into a block and must be removed first.
+    OperationAlreadyLinked(OpId),
+    /// The block is already inserted into a region and must be removed first.
+    BlockAlreadyLinked(BlockId),
+    /// The operation is not inserted into any block.
+    OperationNotLinked(OpId),
+    /// The block is not inserted into any region.
+    BlockNotLinked(BlockId),
+    /// The move would nest an operation inside one of its own regions.
+    Cycle(OpId),
+    /// The operation is not a proper descendant of the operation it was expected to be nested in.
+    NotADescendant {
+        op: OpId,
+        ancestor: OpId
+    },
+    /// The operation cannot be erased because values it defines are still used outside of it.
+    HasUses(OpId),
+    /// The operation is only visible through an overlay that doesn't own it, and cannot be modified there.
+    ReadOnlyOperation(OpId),
+    /// The block is only visible through an overlay that doesn't own it, and cannot be modified there.
+    ReadOnlyBlock(BlockId),
+    /// The region is only visible through an overlay that doesn't own it, and cannot be modified there.
+    ReadOnlyRegion(RegionId),
+    /// The block input cannot be erased because it still has uses.
+    InputHasUses {
+        block: BlockId,
+        index: usize
+    },
+    /// An input, output, region or successor index is out of range.
+    IndexOutOfBounds {
+        index: usize,
+        len: usize
+    }
+}
+
+impl Display for ArenaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::ErasedOperation(id) =>
+                write!(f, "Use of erased operation {}", id),
+            Self::ErasedBlock(id) => write!(f, "Use of erased block {}", id),
+            Self::ErasedRegion(id) => write!(f, "Use of erased region {}", id),
+            Self::OperationAlreadyLinked(id) =>
+                write!(f, "Operation {} is already inserted into a block", id),
+            Self::BlockAlreadyLinked(id) =>
+                write!(f, "Block {} is already inserted into a region", id),
+            Self::OperationNotLinked(id) =>
+                write!(f, "Operation {} has no parent block", id),
+            Self::BlockNotLinked(id) =>
+                write!(f, "Block {} has no parent region", id),
+            Self::Cycle(id) =>
+                write!(f, "Cannot move operation {} into its own regions", id),
+            Self::NotADescendant { op, ancestor } => write!(
+                f, "Operation {} is not a proper descendant of operation {}", op, ancestor),
+            Self::HasUses(id) => write!(
+                f, "Cannot erase operation {} while its values still have uses", id),
+            Self::ReadOnlyOperation(id) =>
+                write!(f, "Operation {} is read-only in this overlay", id),
+            Self::ReadOnlyBlock(id) =>
+                write!(f, "Block {} is read-only in this overlay", id),
+            Self::ReadOnlyRegion(id) =>
+                write!(f, "Region {} is read-only in this overlay", id),
+            Self::InputHasUses { block, index } => write!(
+                f, "Cannot erase input {} of block {} while it still has uses",
+                index, block),
+            Self::IndexOutOfBounds { index, len } =>
+                write!(f, "Index {} is out of bounds for length {}", index, len)
+        }
+    }
+}
+
+impl std::error::Error for ArenaError {}
+
+/// A slot of a `Slots` table. `value` is `None` once the object has been erased, and `generation` is bumped so that stale handles no longer match.
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>
+}
+
+/// The slots of a `Slots` table. An arena stores all of its slots, while an overlay only stores those it owns, keyed by their index.
+enum SlotStorage<T> {
+    Dense(Vec<Slot<T>>),
+    Sparse {
+        slots: HashMap<u32, Slot<T>>,
+        /// The index of the next slot to allocate, shared by the overlays of an arena so that they never allocate the same slot.
+        next_index: Arc<AtomicU32>
+    }
+}
+
+/// Generational storage for one kind of IR object.
+struct Slots<T> {
+    storage: SlotStorage<T>,
+    /// Indices of erased slots available for reuse.
+    free: Vec<u32>,
+    len: usize
+}
+
+impl<T> Default for Slots<T> {
+    fn default() -> Self {
+        Self { storage: SlotStorage::Dense(Vec::new()), free: Vec::new(), len: 0 }
+    }
+}
+
+impl<T> Slots<T> {
+    /// Creates the empty table of an overlay, which allocates the slots from `next_index`.
+    fn sparse(next_index: Arc<AtomicU32>) -> Self {
+        Self {
+            storage: SlotStorage::Sparse { slots: HashMap::new(), next_index },
+            free: Vec::new(),
+            len: 0
+        }
+    }
+
+    fn slot(&self, index: u32) -> Option<&Slot<T>> {
+        match &self.storage {
+            SlotStorage::Dense(slots) => slots.get(index as usize),
+            SlotStorage::Sparse { slots, .. } => slots.get(&index)
+        }
+    }
+
+    fn slot_mut(&mut self, index: u32) -> Option<&mut Slot<T>> {
+        match &mut self.storage {
+            SlotStorage::Dense(slots) => slots.get_mut(index as usize),
+            SlotStorage::Sparse { slots, .. } => slots.get_mut(&index)
+        }
+    }
+
+    /// Returns true if the slot at `index` is stored in this table. Only the slots of an overlay may be stored elsewhere.
+    fn owns(&self, index: u32) -> bool {
+        match &self.storage {
+            SlotStorage::Dense(_) => true,
+            SlotStorage::Sparse { slots, .. } => slots.contains_key(&index)
+        }
+    }
+
+    /// Reserves a slot and returns its `(index, generation)` pair. `init` receives that pair so objects can record their own handle.
+    fn insert_with(&mut self, init: impl FnOnce(u32, u32) -> T) -> (u32, u32) {
+        self.len += 1;
+        let index = match (self.free.pop(), &mut self.storage) {
+            (Some(index), _) => index,
+            (None, SlotStorage::Dense(slots)) => {
+                slots.push(Slot { generation: 0, value: None });
+                slots.len() as u32 - 1
+            }
+            (None, SlotStorage::Sparse { slots, next_index }) => {
+                let index = next_index.fetch_add(1, Ordering::Relaxed);
+                slots.insert(index, Slot { generation: 0, value: None });
+                index
+            }
+        };
+        let slot = self.slot_mut(index).unwrap();
+        slot.value = Some(init(index, slot.generation));
+        (index, slot.generation)
+    }
+
+    fn get(&self, index: u32, generation: u32) -> Option<&T> {
+        self.slot(index)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.value.as_ref())
+    }
+
+    fn get_mut(&mut self, index: u32, generation: u32) -> Option<&mut T> {
+        self.slot_mut(index)
+            .filter(|slot| slot.generation == generation)
+            .and_then(|slot| slot.value.as_mut())
+    }
+
+    fn remove(&mut self, index: u32, generation: u32) -> Option<T> {
+        let slot = self.slot_mut(index)
+            .filter(|slot| slot.generation == generation)?;
+        let value = slot.value.take()?;
+        // Retire the slot once its generations are exhausted rather than wrapping around, which would let stale handles match again. The last generation is reserved for `DANGLING` handles, and is never handed out.
+        match slot.generation.checked_add(1).filter(|generation| *generation != u32::MAX) {
+            Some(generation) => {
+                slot.generation = generation;
+                self.free.push(index);
+            }
+            None => slot.generation = u32::MAX
+        }
+        self.len -= 1;
+        Some(value)
+    }
+
+    /// Returns the index of the next slot allocated by a table that has no free slot.
+    fn next_index(&self) -> u32 {
+        match &self.storage {
+            SlotStorage::Dense(slots) => slots.len() as u32,
+            SlotStorage::Sparse { next_index, .. } => next_index.load(Ordering::Relaxed)
+        }
+    }
+
+    /// Moves the slot at `index` of this dense table to the overlay table `overlay`. The slot is left empty here, without being freed, until `join` moves it back.
+    fn lend(&mut self, index: u32, overlay: &mut Self) {
+        let (SlotStorage::Dense(slots), SlotStorage::Sparse { slots: overlay_slots, .. })
+            = (&mut self.storage, &mut overlay.storage)
+        else {
+            unreachable!("Slots are lent from an arena to an overlay");
+        };
+        let slot = &mut slots[index as usize];
+        let value = slot.value.take();
+        if value.is_some() {
+            self.len -= 1;
+            overlay.len += 1;
+        }
+        overlay_slots.insert(index, Slot { generation: slot.generation, value });
+    }
+
+    /// Moves the slots of the overlay table `overlay` back to this dense table, along with those it allocated.
+    fn join(&mut self, overlay: Self) {
+        let (SlotStorage::Dense(slots), SlotStorage::Sparse { slots: overlay_slots, .. })
+            = (&mut self.storage, overlay.storage)
+        else {
+            unreachable!("Slots are joined from an overlay to an arena");
+        };
+        for (index, slot) in overlay_slots {
+            let index = index as usize;
+            // The slots in between were allocated by other overlays, which fill them when they are joined.
+            if index >= slots.len() {
+                slots.resize_with(index + 1, || Slot { generation: 0, value: None });
+            }
+            slots[index] = slot;
+        }
+        self.free.extend(overlay.free);
+        self.len += overlay.len;
+    }
+}
+
+/// Owner of all the operations, blocks and regions created within an `MLIRContext`.
+///
+/// Structural queries and mutations go through the arena so that parent links, operand use lists and operation order are kept consistent. Every accessor validates its handles and returns an `ArenaError` for a handle whose object has been erased.
+#[derive(Default)]
+pub struct IRArena {
+    operations: Slots<Operation>,
+    blocks: Slots<Block>,
+    regions: Slots<Region>,
+    /// The arena this arena is an overlay of, if any. The objects this arena doesn't own are read from it, and cannot be modified. See `IRArena::with_overlays`.
+    parent: Option<Arc<IRArena>>
+}
+
+impl IRArena {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the number of live operations.
+    pub fn num_operations(&self) -> usize {
+        self.operations.len
+    }
+
+    /// Returns the number of live blocks.
+    pub fn num_blocks(&self) -> usize {
+        self.blocks.len
+    }
+
+    /// Returns the number of live regions.
+    pub fn num_regions(&self) -> usize {
+        self.regions.len
+    }
+
+    /* Accessors */
+
+    pub fn contains_op(&self, id: OpId) -> bool {
+        self.op(id).is_ok()
+    }
+
+    pub fn contains_block(&self, id: BlockId) -> bool {
+        self.block(id).is_ok()
+    }
+
+    pub fn contains_region(&self, id: RegionId) -> bool {
+        self.region(id).is_ok()
+    }
+
+    pub fn op(&self, id: OpId) -> ArenaResult<&Operation> {
+        match (self.operations.get(id.index, id.generation), &self.parent) {
+            (Some(operation), _) => Ok(operation),
+            (None, Some(parent)) if !self.operations.owns(id.index) => parent.op(id),
+            (None, _) => Err(ArenaError::ErasedOperation(id))
+        }
+    }
+
+    pub fn op_mut(&mut self, id: OpId) -> ArenaResult<&mut Operation> {
+        if !self.operations.owns(id.index) && self.contains_op(id) {
+            return Err(ArenaError::ReadOnlyOperation(id));
+        }
+        self.operations.get_mut(id.index, id.generation)
+            .ok_or(ArenaError::ErasedOperation(id))
+    }
+
+    pub fn block(&self, id: BlockId) -> ArenaResult<&Block> {
+        match (self.blocks.get(id.index, id.generation), &self.parent) {
+            (Some(block), _) => Ok(block),
+            (None, Some(parent)) if !self.blocks.owns(id.index) => parent.block(id),
+            (None, _) => Err(ArenaError::ErasedBlock(id))
+        }
+    }
+
+    pub fn block_mut(&mut self, id: BlockId) -> ArenaResult<&mut Block> {
+        if !self.blocks.owns(id.index) && self.contains_block(id) {
+            return Err(ArenaError::ReadOnlyBlock(id));
+        }
+        self.blocks.get_mut(id.index, id.generation)
+            .ok_or(ArenaError::ErasedBlock(id))
+    }
+
+    pub fn region(&self, id: RegionId) -> ArenaResult<&Region> {
+        match (self.regions.get(id.index, id.generation), &self.parent) {
+            (Some(region), _) => Ok(region),
+            (None, Some(parent)) if !self.regions.owns(id.index) => parent.region(id),
+            (None, _) => Err(ArenaError::ErasedRegion(id))
+        }
+    }
+
+    pub fn region_mut(&mut self, id: RegionId) -> ArenaResult<&mut Region> {
+        if !self.regions.owns(id.index) && self.contains_region(id) {
+            return Err(ArenaError::ReadOnlyRegion(id));
+        }
+        self.regions.get_mut(id.index, id.generation)
+            .ok_or(ArenaError::ErasedRegion(id))
+    }
+
+    /* Creation */
+
+    /// Takes ownership of a detached operation and attaches `num_regions` empty regions to it. The inputs of the operation are registered as uses of their values.
+    pub fn create_operation(
+        &mut self,
+        mut operation: Operation,
+        num_regions: usize
+    ) -> ArenaResult<OpId> {
+        let inputs = operation.inputs().to_vec();
+        for value in inputs.iter() {
+            self.value_impl(*value)?;
+        }
+        let (index, generation) = self.operations.insert_with(|index, generation| {
+            operation.id = OpId { index, generation };
+            operation
+        });
+        let id = OpId { index, generation };
+        for (index, value) in inputs.into_iter().enumerate() {
+            self.value_impl_mut(value)?.uses.push(Use { owner: id, index });
+        }
+        let regions = (0..num_regions)
+            .map(|_| self.create_region(Some(id))).collect();
+        self.op_mut(id)?.regions = regions;
+        Ok(id)
+    }
+
+    /// Creates a new block that is not inserted into any region.
+    pub fn create_block(&mut self) -> BlockId {
+        let (index, generation) = self.blocks.insert_with(|index, generation|
+            Block::new(BlockId { index, generation }));
+        BlockId { index, generation }
+    }
+
+    /// Creates a new empty region attached to `container`, or detached if `container` is `None`.
+    pub fn create_region(&mut self, container: Option<OpId>) -> RegionId {
+        let (index, generation) = self.regions.insert_with(|index, generation|
+            Region::new(RegionId { index, generation }, container));
+        RegionId { index, generation }
+    }
+
+    /* Navigation */
+
+    /// Returns the block containing `op`, if any.
+    pub fn parent_block(&self, op: OpId) -> ArenaResult<Option<BlockId>> {
+        Ok(self.op(op)?.block())
+    }
+
+    /// Returns the region containing `block`, if any.
+    pub fn parent_region_of_block(&self, block: BlockId)
+    -> ArenaResult<Option<RegionId>>
+    {
+        Ok(self.block(block)?.parent())
+    }
+
+    /// Returns the region containing `op`, if any.
+    pub fn parent_region(&self, op: OpId) -> ArenaResult<Option<RegionId>> {
+        match self.op(op)?.block() {
+            None => Ok(None),
+            Some(block) => self.parent_region_of_block(block)
+        }
+    }
+
+    /// Returns the closest operation that contains `op`, or `None` if it is a top-level operation.
+    pub fn parent_op(&self, op: OpId) -> ArenaResult<Option<OpId>> {
+        match self.parent_region(op)? {
+            None => Ok(None),
+            Some(region) => Ok(self.region(region)?.parent_operation())
+        }
+    }
+
+    /// Returns the operation that contains `block`, if any.
+    pub fn block_parent_op(&self, block: BlockId) -> ArenaResult<Option<OpId>> {
+        match self.block(block)?.parent() {
+            None => Ok(None),
+            Some(region) => Ok(self.region(region)?.parent_operation())
+        }
+    }
+
+    /// Return true if `ancestor` is a proper ancestor of `op`.
+    pub fn is_proper_ancestor(&self, ancestor: OpId, op: OpId)
+    -> ArenaResult<bool>
+    {
+        self.op(ancestor)?;
+        let mut current = self.parent_op(op)?;
+        while let Some(parent) = current {
+            if parent == ancestor {
+                return Ok(true);
+            }
+            current = self.parent_op(parent)?;
+        }
+        Ok(false)
+    }
+
+    /// Return true if `ancestor` is `op` or a proper ancestor of it.
+    pub fn is_ancestor(&self, ancestor: OpId, op: OpId) -> ArenaResult<bool> {
+        Ok(ancestor == op || self.is_proper_ancestor(ancestor, op)?)
+    }
+
+    /// Returns `op` if it lies in `block`, otherwise the ancestor of `op` that lies in `block`, or `None` if there is none.
+    pub fn find_ancestor_op_in_block(&self, block: BlockId, op: OpId)
+    -> ArenaResult<Option<OpId>>
+    {
+        let mut current = op;
+        loop {
+            if self.op(current)?.block() == Some(block) {
+                return Ok(Some(current));
+            }
+            match self.parent_op(current)? {
+                None => return Ok(None),
+                Some(parent) => current = parent
+            }
+        }
+    }
+
+    /// Given two operations within the same block, return whether `op` comes before `other`.
+    ///
+    /// NOTE: This has an amortised complexity of O(1) but may take O(N), where N is the number of operations in the block, when the cached order of the block has been invalidated.
+    pub fn is_before_in_block(&mut self, op: OpId, other: OpId)
+    -> ArenaResult<bool>
+    {
+        let block = self.op(op)?.block()
+            .ok_or(ArenaError::OperationNotLinked(op))?;
+        if self.op(other)?.block() != Some(block) {
+            return Err(ArenaError::OperationNotLinked(other));
+        }
+        if !self.block(block)?.is_op_order_valid() {
+            self.recompute_op_order(block)?;
+        }
+        Ok(self.op(op)?.order_index < self.op(other)?.order_index)
+    }
+
+    /// Recomputes the cached position of each operation within `block`.
+    pub fn recompute_op_order(&mut self, block: BlockId) -> ArenaResult<()> {
+        let operations = self.block(block)?.operations().to_vec();
+        for (position, op) in operations.into_iter().enumerate() {
+            self.op_mut(op)?.order_index = (position + 1) * Operation::ORDER_STRIDE;
+        }
+        self.block_mut(block)?.valid_op_order = true;
+        Ok(())
+    }
+
+    /* Operation List Management */
+
+    /// Appends the detached `op` to the end of `block`.
+    pub fn push_back(&mut self, block: BlockId, op: OpId) -> ArenaResult<()> {
+        let len = self.block(block)?.operations().len();
+        self.insert_op(block, len, op)
+    }
+
+    /// Prepends the detached `op` to the start of `block`.
+    pub fn push_front(&mut self, block: BlockId, op: OpId) -> ArenaResult<()> {
+        self.insert_op(block, 0, op)
+    }
+
+    /// Inserts the detached `op` right before `anchor`.
+    pub fn insert_before(&mut self, anchor: OpId, op: OpId) -> ArenaResult<()> {
+        let (block, position) = self.position_of(anchor)?;
+        self.insert_op(block, position, op)
+    }
+
+    /// Inserts the detached `op` right after `anchor`.
+    pub fn insert_after(&mut self, anchor: OpId, op: OpId) -> ArenaResult<()> {
+        let (block, position) = self.position_of(anchor)?;
+        self.insert_op(block, position + 1, op)
+    }
+
+    /// Inserts the detached `op` into `block` at `position`.
+    pub fn insert_op(&mut self, block: BlockId, position: usize, op: OpId)
+    -> ArenaResult<()>
+    {
+        if self.op(op)?.block().is_some() {
+            return Err(ArenaError::OperationAlreadyLinked(op));
+        }
+        if let Some(parent) = self.block_parent_op(block)? {
+            if self.is_ancestor(op, parent)? {
+                return Err(ArenaError::Cycle(op));
+            }
+        }
+        let len = self.block(block)?.operations().len();
+        if position > len {
+            return Err(ArenaError::IndexOutOfBounds { index: position, len });
+        }
+
+        // Appending keeps the cached order valid; anything else defers to a recomputation on the next query.
+        let order_index = if !self.block(block)?.is_op_order_valid()
+            || position != len
+        {
+            None
+        } else {
+            match len.checked_sub(1) {
+                None => Some(Operation::ORDER_STRIDE),
+                Some(last) => {
+                    let last = self.block(block)?.operations()[last];
+                    Some(self.op(last)?.order_index + Operation::ORDER_STRIDE)
+                }
+            }
+        };
+        let block_ref = self.block_mut(block)?;
+        block_ref.operations.insert(position, op);
+        if order_index.is_none() {
+            block_ref.valid_op_order = false;
+        }
+        let op_ref = self.op_mut(op)?;
+        op_ref.block = Some(block);
+        op_ref.order_index = order_index.unwrap_or(Operation::INVALID_ORDER_INDEX);
+        Ok(())
+    }
+
+    /// Unlinks `op` from its parent block without erasing it. The operation keeps its uses and can be inserted elsewhere.
+    pub fn remove(&mut self, op: OpId) -> ArenaResult<()> {
+        let (block, position) = self.position_of(op)?;
+        self.block_mut(block)?.operations.remove(position);
+        let op_ref = self.op_mut(op)?;
+        op_ref.block = None;
+        op_ref.order_index = Operation::INVALID_ORDER_INDEX;
+        Ok(())
+    }
+
+    /// Unlinks `op` from its current block and inserts it right before `anchor`, which may be in the same or another block.
+    pub fn move_before(&mut self, op: OpId, anchor: OpId) -> ArenaResult<()> {
+        if op == anchor {
+            return Ok(());
+        }
+        self.check_can_move_to(op, anchor)?;
+        self.remove(op)?;
+        self.insert_before(anchor, op)
+    }
+
+    /// Unlinks `op` from its current block and inserts it right after `anchor`, which may be in the same or another block.
+    pub fn move_after(&mut self, op: OpId, anchor: OpId) -> ArenaResult<()> {
+        if op == anchor {
+            return Ok(());
+        }
+        self.check_can_move_to(op, anchor)?;
+        self.remove(op)?;
+        self.insert_after(anchor, op)
+    }
+
+    /// Checks that `op` can be moved next to `anchor` before unlinking it, so that a failed move leaves the IR untouched.
+    fn check_can_move_to(&self, op: OpId, anchor: OpId) -> ArenaResult<()> {
+        self.position_of(op)?;
+        self.position_of(anchor)?;
+        if self.is_proper_ancestor(op, anchor)? {
+            return Err(ArenaError::Cycle(op));
+        }
+        Ok(())
+    }
+
+    /// Returns the block containing `op` and the position of `op` within it.
+    fn position_of(&self, op: OpId) -> ArenaResult<(BlockId, usize)> {
+        let block = self.op(op)?.block()
+            .ok_or(ArenaError::OperationNotLinked(op))?;
+        let position = self.block(block)?.operations().iter()
+            .position(|other| *other == op)
+            .ok_or(ArenaError::OperationNotLinked(op))?;
+        Ok((block, position))
+    }
+
+    /* Block List Management */
+
+    /// Appends the detached `block` to the end of `region`.
+    pub fn push_back_block(&mut self, region: RegionId, block: BlockId)
+    -> ArenaResult<()>
+    {
+        let len = self.region(region)?.blocks().len();
+        self.insert_block(region, len, block)
+    }
+
+    /// Inserts the detached `block` right before `anchor`.
+    pub fn insert_block_before(&mut self, anchor: BlockId, block: BlockId)
+    -> ArenaResult<()>
+    {
+        let (region, position) = self.block_position_of(anchor)?;
+        self.insert_block(region, position, block)
+    }
+
+    /// Inserts the detached `block` into `region` at `position`.
+    pub fn insert_block(
+        &mut self, region: RegionId, position: usize, block: BlockId
+    ) -> ArenaResult<()> {
+        if self.block(block)?.parent().is_some() {
+            return Err(ArenaError::BlockAlreadyLinked(block));
+        }
+        if let Some(container) = self.region(region)?.parent_operation() {
+            for op in self.block(block)?.operations().to_vec() {
+                if self.is_ancestor(op, container)? {
+                    return Err(ArenaError::Cycle(op));
+                }
+            }
+        }
+        let len = self.region(region)?.blocks().len();
+        if position > len {
+            return Err(ArenaError::IndexOutOfBounds { index: position, len });
+        }
+        self.region_mut(region)?.blocks.insert(position, block);
+        self.block_mut(block)?.parent = Some(region);
+        Ok(())
+    }
+
+    /// Unlinks `block` from its parent region without erasing it.
+    pub fn remove_block(&mut self, block: BlockId) -> ArenaResult<()> {
+        let (region, position) = self.block_position_of(block)?;
+        self.region_mut(region)?.blocks.remove(position);
+        self.block_mut(block)?.parent = None;
+        Ok(())
+    }
+
+    /// Unlinks `block` from its current region and inserts it right before `anchor`.
+    pub fn move_block_before(&mut self, block: BlockId, anchor: BlockId)
+    -> ArenaResult<()>
+    {
+        if block == anchor {
+            return Ok(());
+        }
+        self.block_position_of(anchor)?;
+        self.remove_block(block)?;
+        self.insert_block_before(anchor, block)
+    }
+
+    /// Moves all blocks of `other` to the end of `region`, after clearing `region`. `other` is left empty.
+    pub fn take_body(&mut self, region: RegionId, other: RegionId)
+    -> ArenaResult<()>
+    {
+        self.region(other)?;
+        for block in self.region(region)?.blocks().to_vec() {
+            self.erase_block(block)?;
+        }
+        for block in self.region(other)?.blocks().to_vec() {
+            self.remove_block(block)?;
+            self.push_back_block(region, block)?;
+        }
+        Ok(())
+    }
+
+    fn block_position_of(&self, block: BlockId)
+    -> ArenaResult<(RegionId, usize)>
+    {
+        let region = self.block(block)?.parent()
+            .ok_or(ArenaError::BlockNotLinked(block))?;
+        let position = self.region(region)?.blocks().iter()
+            .position(|other| *other == block)
+            .ok_or(ArenaError::BlockNotLinked(block))?;
+        Ok((region, position))
+    }
+
+    /* Values and Uses */
+
+    /// Returns the storage of `value`.
+    pub fn value_impl(&self, value: Value) -> ArenaResult<&ValueImpl> {
+        match value {
+            Value::Output(output) => {
+                let op = self.op(output.owner())?;
+                op.outputs.get(output.index()).ok_or(
+                    ArenaError::IndexOutOfBounds {
+                        index: output.index(), len: op.outputs.len() })
+            }
+            Value::Input(input) => {
+                let block = self.block(input.owner())?;
+                block.inputs.get(input.index()).map(|input| &input.value).ok_or(
+                    ArenaError::IndexOutOfBounds {
+                        index: input.index(), len: block.inputs.len() })
+            }
+        }
+    }
+
+    fn value_impl_mut(&mut self, value: Value) -> ArenaResult<&mut ValueImpl> {
+        match value {
+            Value::Output(output) => {
+                let op = self.op_mut(output.owner())?;
+                let len = op.outputs.len();
+                op.outputs.get_mut(output.index()).ok_or(
+                    ArenaError::IndexOutOfBounds { index: output.index(), len })
+            }
+            Value::Input(input) => {
+                let block = self.block_mut(input.owner())?;
+                let len = block.inputs.len();
+                block.inputs.get_mut(input.index()).map(|input| &mut input.value)
+                    .ok_or(ArenaError::IndexOutOfBounds { index: input.index(), len })
+            }
+        }
+    }
+
+    /// Inserts a new input of type `r#type` at position `index` of `block`. Uses of the inputs after it are renumbered.
+    pub fn insert_block_input(
+        &mut self,
+        block: BlockId,
+        index: usize,
+        r#type: Arc<dyn Type>,
+        location: Location
+    ) -> ArenaResult<Input> {
+        let len = self.block(block)?.num_inputs();
+        if index > len {
+            return Err(ArenaError::IndexOutOfBounds { index, len });
+        }
+        self.block_mut(block)?.inputs.insert(index, InputImpl::new(r#type, location));
+        self.renumber_block_inputs(block, index + 1)?;
+        Ok(Input::new(block, index))
+    }
+
+    /// Erases the input at position `index` of `block`, which must have no uses. Uses of the inputs after it are renumbered.
+    pub fn erase_block_input(&mut self, block: BlockId, index: usize)
+    -> ArenaResult<()>
+    {
+        let len = self.block(block)?.num_inputs();
+        if index >= len {
+            return Err(ArenaError::IndexOutOfBounds { index, len });
+        }
+        if !self.uses(Value::Input(Input::new(block, index)))?.is_empty() {
+            return Err(ArenaError::InputHasUses { block, index });
+        }
+        self.block_mut(block)?.inputs.remove(index);
+        self.renumber_block_inputs(block, index)
+    }
+
+    /// Points the users of the inputs of `block` starting at `start` at the current position of each input.
+    fn renumber_block_inputs(&mut self, block: BlockId, start: usize)
+    -> ArenaResult<()>
+    {
+        for index in start..self.block(block)?.num_inputs() {
+            let value = Value::Input(Input::new(block, index));
+            for r#use in self.uses(value)?.to_vec() {
+                self.op_mut(r#use.owner)?.inputs[r#use.index] = value;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the uses of `value`.
+    pub fn uses(&self, value: Value) -> ArenaResult<&[Use]> {
+        Ok(self.value_impl(value)?.uses.as_slice())
+    }
+
+    /// Reorders the uses of `value`, moving the use at position `i` to position `indices[i]`. Fails with `ArenaError::IndexOutOfBounds` if `indices` is not a permutation of the positions of the uses.
+    pub fn shuffle_use_list(&mut self, value: Value, indices: &[usize])
+    -> ArenaResult<()>
+    {
+        let uses = &mut self.value_impl_mut(value)?.uses;
+        let len = uses.len();
+        if indices.len() != len {
+            return Err(ArenaError::IndexOutOfBounds { index: indices.len(), len });
+        }
+        let mut shuffled = vec![None; len];
+        for (r#use, index) in uses.iter().zip(indices) {
+            match shuffled.get_mut(*index) {
+                Some(slot @ None) => *slot = Some(*r#use),
+                _ => return Err(ArenaError::IndexOutOfBounds { index: *index, len })
+            }
+        }
+        *uses = shuffled.into_iter().map(Option::unwrap).collect();
+        Ok(())
+    }
+
+    /// Sets the `index`th input of `op` to `value`, updating the use lists of the old and new values.
+    pub fn set_input(&mut self, op: OpId, index: usize, value: Value)
+    -> ArenaResult<()>
+    {
+        self.value_impl(value)?;
+        let inputs = &self.op(op)?.inputs;
+        let old = *inputs.get(index).ok_or(
+            ArenaError::IndexOutOfBounds { index, len: inputs.len() })?;
+        let r#use = Use { owner: op, index };
+        if let Ok(old) = self.value_impl_mut(old) {
+            old.uses.retain(|other| *other != r#use);
+        }
+        self.op_mut(op)?.inputs[index] = value;
+        self.value_impl_mut(value)?.uses.push(r#use);
+        Ok(())
+    }
+
+    /// Replaces any uses of `from` with `to` within the inputs of `op`.
+    pub fn replace_uses_of_with(&mut self, op: OpId, from: Value, to: Value)
+    -> ArenaResult<()>
+    {
+        let inputs = self.op(op)?.inputs().to_vec();
+        for (index, input) in inputs.into_iter().enumerate() {
+            if input == from {
+                self.set_input(op, index, to)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Replaces all uses of `from` with `to`. When this returns there are zero uses of `from`.
+    pub fn replace_all_uses_with(&mut self, from: Value, to: Value)
+    -> ArenaResult<()>
+    {
+        if from == to {
+            return Ok(());
+        }
+        for r#use in self.uses(from)?.to_vec() {
+            self.set_input(r#use.owner, r#use.index, to)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces all uses of the outputs of `op` with `values`.
+    pub fn replace_op_uses_with(&mut self, op: OpId, values: &[Value])
+    -> ArenaResult<()>
+    {
+        let num_outputs = self.op(op)?.num_outputs();
+        if values.len() != num_outputs {
+            return Err(ArenaError::IndexOutOfBounds {
+                index: values.len(), len: num_outputs });
+        }
+        for (index, value) in values.iter().enumerate() {
+            self.replace_all_uses_with(
+                Value::Output(Output::new(op, index)), *value)?;
+        }
+        Ok(())
+    }
+
+    /// Drops all input uses held by `op` and the operations nested in it, which breaks cyclic references before erasure.
+    pub fn drop_all_references(&mut self, op: OpId) -> ArenaResult<()> {
+        for nested in self.walk(op)? {
+            let inputs = core::mem::take(&mut self.op_mut(nested)?.inputs);
+            for (index, value) in inputs.into_iter().enumerate() {
+                let r#use = Use { owner: nested, index };
+                if let Ok(value) = self.value_impl_mut(value) {
+                    value.uses.retain(|other| *other != r#use);
+                }
+            }
+            self.op_mut(nested)?.successors.clear();
+        }
+        Ok(())
+    }
+
+    /* Erasure */
+
+    /// Unlinks `op` from its parent block, if any, and erases it along with its regions. Fails with `ArenaError::HasUses` if a value defined by `op` or within its regions is still used outside of it.
+    pub fn erase(&mut self, op: OpId) -> ArenaResult<()> {
+        let nested = self.walk(op)?;
+        let nested_set: HashSet<OpId> = nested.iter().copied().collect();
+        for nested_op in nested.iter() {
+            for value in self.defined_values(*nested_op)? {
+                if self.uses(value)?.iter()
+                    .any(|r#use| !nested_set.contains(&r#use.owner))
+                {
+                    return Err(ArenaError::HasUses(op));
+                }
+            }
+        }
+
+        if self.op(op)?.block().is_some() {
+            self.remove(op)?;
+        }
+        self.drop_all_references(op)?;
+        for nested_op in nested.into_iter().rev() {
+            let operation = self.operations.remove(nested_op.index, nested_op.generation)
+                .ok_or(ArenaError::ErasedOperation(nested_op))?;
+            for region in operation.regions {
+                let region = self.regions.remove(region.index, region.generation)
+                    .ok_or(ArenaError::ErasedRegion(region))?;
+                for block in region.blocks {
+                    self.blocks.remove(block.index, block.generation);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Unlinks `block` from its parent region, if any, and erases it along with the operations it contains. Fails with `ArenaError::HasUses` or `ArenaError::InputHasUses` if a value defined within the block is still used outside of it, in which case the block is left unchanged.
+    pub fn erase_block(&mut self, block: BlockId) -> ArenaResult<()> {
+        // Check every use from outside of the block before mutating anything, so that a failure doesn't leave the block half-erased.
+        let mut nested = Vec::new();
+        for op in self.block(block)?.operations().iter() {
+            nested.extend(self.walk(*op)?.into_iter().map(|nested_op| (*op, nested_op)));
+        }
+        let nested_set: HashSet<OpId> = nested.iter()
+            .map(|(_, nested_op)| *nested_op).collect();
+        let is_outside = |uses: &[Use]| uses.iter()
+            .any(|r#use| !nested_set.contains(&r#use.owner));
+        for index in 0..self.block(block)?.num_inputs() {
+            if is_outside(self.uses(Value::Input(Input::new(block, index)))?) {
+                return Err(ArenaError::InputHasUses { block, index });
+            }
+        }
+        for (op, nested_op) in nested.iter() {
+            for value in self.defined_values(*nested_op)? {
+                if is_outside(self.uses(value)?) {
+                    return Err(ArenaError::HasUses(*op));
+                }
+            }
+        }
+
+        if self.block(block)?.parent().is_some() {
+            self.remove_block(block)?;
+        }
+        // Erase in reverse order so that uses are destroyed before their defs.
+        for op in self.block(block)?.operations().to_vec().into_iter().rev() {
+            self.drop_all_references(op)?;
+        }
+        for op in self.block(block)?.operations().to_vec().into_iter().rev() {
+            self.erase(op)?;
+        }
+        self.blocks.remove(block.index, block.generation);
+        Ok(())
+    }
+
+    /// Erases a region that is not attached to an operation, along with the blocks it contains.
+    pub fn erase_region(&mut self, region: RegionId) -> ArenaResult<()> {
+        assert!(self.region(region)?.parent_operation().is_none(),
+            "Cannot erase a region attached to an operation");
+        for block in self.region(region)?.blocks().to_vec().into_iter().rev() {
+            self.erase_block(block)?;
+        }
+        self.regions.remove(region.index, region.generation);
+        Ok(())
+    }
+
+    /// Returns the outputs of `op` and the inputs of the blocks directly nested in its regions.
+    fn defined_values(&self, op: OpId) -> ArenaResult<Vec<Value>> {
+        let operation = self.op(op)?;
+        let mut values: Vec<Value> = (0..operation.num_outputs())
+            .map(|index| Value::Output(Output::new(op, index))).collect();
+        for region in operation.regions() {
+            for block in self.region(*region)?.blocks() {
+                let num_inputs = self.block(*block)?.num_inputs();
+                values.extend((0..num_inputs)
+                    .map(|index| Value::Input(Input::new(*block, index))));
+            }
+        }
+        Ok(values)
+    }
+
+    /* Walkers */
+
+    /// Returns `op` followed by all the operations nested in its regions, in pre-order.
+    pub fn walk(&self, op: OpId) -> ArenaResult<Vec<OpId>> {
+        let mut output = Vec::new();
+        let mut worklist = vec![op];
+        while let Some(current) = worklist.pop() {
+            output.push(current);
+            let mut children = Vec::new();
+            for region in self.op(current)?.regions() {
+                for block in self.region(*region)?.blocks() {
+                    children.extend_from_slice(self.block(*block)?.operations());
+                }
+            }
+            worklist.extend(children.into_iter().rev());
+        }
+        Ok(output)
+    }
+
+    /* Cloning */
+
+    /// Creates a deep copy of `op`, its regions and the operations nested in them. Inputs that are defined outside of `op` are remapped through `mapping` when present there and left unchanged otherwise. The results, blocks and block inputs of the original are mapped to their copies in `mapping`.
+    pub fn clone(&mut self, op: OpId, mapping: &mut BlockAndValueMapping)
+    -> ArenaResult<OpId>
+    {
+        let clone = self.clone_without_regions(op, mapping)?;
+        let regions = self.op(op)?.regions().to_vec();
+        let new_regions = self.op(clone)?.regions().to_vec();
+        for (region, new_region) in regions.into_iter().zip(new_regions) {
+            self.clone_region_into(region, new_region, mapping)?;
+        }
+        Ok(clone)
+    }
+
+    /// Creates a copy of `op` with empty regions.
+    pub fn clone_without_regions(
+        &mut self, op: OpId, mapping: &mut BlockAndValueMapping
+    ) -> ArenaResult<OpId> {
+        let operation = self.op(op)?;
+        let num_regions = operation.num_regions();
+        let mut copy = operation.clone_detached();
+        copy.inputs = copy.inputs.iter()
+            .map(|value| mapping.lookup_or_default(*value)).collect();
+        copy.successors = copy.successors.iter()
+            .map(|block| mapping.lookup_block_or_default(*block)).collect();
+        let clone = self.create_operation(copy, num_regions)?;
+        for index in 0..self.op(op)?.num_outputs() {
+            mapping.map(
+                Value::Output(Output::new(op, index)),
+                Value::Output(Output::new(clone, index)));
+        }
+        Ok(clone)
+    }
+
+    /// Clones the blocks of `region` into the end of `dest`, mapping blocks and values through `mapping`.
+    pub fn clone_region_into(
+        &mut self,
+        region: RegionId,
+        dest: RegionId,
+        mapping: &mut BlockAndValueMapping
+    ) -> ArenaResult<()> {
+        let blocks = self.region(region)?.blocks().to_vec();
+        // Create all blocks and their inputs first so that forward references to blocks and block inputs are remapped.
+        for block in blocks.iter() {
+            let new_block = self.create_block();
+            let inputs: Vec<InputImpl> = self.block(*block)?.inputs.iter()
+                .map(InputImpl::without_uses).collect();
+            for (index, input) in inputs.into_iter().enumerate() {
+                self.block_mut(new_block)?.inputs.push(input);
+                mapping.map(
+                    Value::Input(Input::new(*block, index)),
+                    Value::Input(Input::new(new_block, index)));
+            }
+            mapping.map_block(*block, new_block);
+            self.push_back_block(dest, new_block)?;
+        }
+        let mut clones = Vec::new();
+        for block in blocks.iter() {
+            let new_block = mapping.lookup_block_or_default(*block);
+            for op in self.block(*block)?.operations().to_vec() {
+                let clone = self.clone(op, mapping)?;
+                self.push_back(new_block, clone)?;
+                clones.push(clone);
+            }
+        }
+        // Now that all values have been cloned, remap the inputs that referred to values defined later in the region, as upstream `Region::cloneInto` does.
+        for clone in clones {
+            for nested in self.walk(clone)? {
+                for (index, value) in self.op(nested)?.inputs().to_vec().into_iter().enumerate() {
+                    if let Some(mapped) = mapping.lookup(value)
+                        && mapped != value
+                    {
+                        self.set_input(nested, index, mapped)?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the blocks whose terminators have `block` as a successor. A block branching to `block` more than once is listed once for each edge.
+    pub fn predecessors(&self, block: BlockId) -> ArenaResult<Vec<BlockId>> {
+        let region = match self.block(block)?.parent() {
+            None => return Ok(Vec::new()),
+            Some(region) => region
+        };
+        let mut output = Vec::new();
+        for other in self.region(region)?.blocks() {
+            if let Some(terminator) = self.block(*other)?.back() {
+                for successor in self.op(terminator)?.successors() {
+                    if *successor == block {
+                        output.push(*other);
+                    }
+                }
+            }
+        }
+        Ok(output)
+    }
+
+    /* Overlays */
+
+    /// Runs `function` on overlays of this arena, one for each operation of `roots`. Each overlay owns its root, the regions of the root and everything nested in them, which it can modify freely, while the rest of the arena stays visible but read-only. Objects created in an overlay belong to it. Once `function` returns, the objects of the overlays are moved back into this arena, so handles taken through an overlay stay valid.
+    ///
+    /// The roots must not be nested in one another. The overlays must not outlive `function`, as they share this arena while it runs.
+    pub fn with_overlays<R>(
+        &mut self, roots: &[OpId], function: impl FnOnce(&mut [IRArena]) -> R)
+    -> ArenaResult<R>
+    {
+        let mut subtrees = Vec::with_capacity(roots.len());
+        let mut nested = HashSet::new();
+        for root in roots {
+            let subtree = self.walk(*root)?;
+            for op in &subtree {
+                assert!(nested.insert(*op), "Overlay roots must not be nested in one another");
+            }
+            subtrees.push(subtree);
+        }
+
+        // The overlays allocate their new objects past the slots of this arena, from counters they share.
+        let next_operation = Arc::new(AtomicU32::new(self.operations.next_index()));
+        let next_block = Arc::new(AtomicU32::new(self.blocks.next_index()));
+        let next_region = Arc::new(AtomicU32::new(self.regions.next_index()));
+        let mut overlays = Vec::with_capacity(roots.len());
+        for subtree in subtrees {
+            let mut overlay = Self {
+                operations: Slots::sparse(next_operation.clone()),
+                blocks: Slots::sparse(next_block.clone()),
+                regions: Slots::sparse(next_region.clone()),
+                parent: None
+            };
+            for op in subtree {
+                for region in self.op(op)?.regions().to_vec() {
+                    for block in self.region(region)?.blocks().to_vec() {
+                        self.blocks.lend(block.index, &mut overlay.blocks);
+                    }
+                    self.regions.lend(region.index, &mut overlay.regions);
+                }
+                self.operations.lend(op.index, &mut overlay.operations);
+            }
+            overlays.push(overlay);
+        }
+
+        let parent = Arc::new(core::mem::take(self));
+        for overlay in &mut overlays {
+            overlay.parent = Some(parent.clone());
+        }
+        let output = function(&mut overlays);
+        for overlay in &mut overlays {
+            overlay.parent = None;
+        }
+        *self = Arc::into_inner(parent)
+            .expect("An overlay outlived `IRArena::with_overlays`");
+
+        for overlay in overlays {
+            self.operations.join(overlay.operations);
+            self.blocks.join(overlay.blocks);
+            self.regions.join(overlay.regions);
+        }
+        Ok(output)
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{
+        builtins::attributes::DictionaryAttribute,
+        operation::support::OperationName
+    };
+
+    /// Create an operation with `num_regions` regions, each holding an empty block, and insert it at the end of the first block of `parent` if given.
+    fn create_op(arena: &mut IRArena, parent: Option<OpId>, num_regions: usize) -> OpId {
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new("test.op"),
+            Vec::<Arc<dyn Type>>::new(), Vec::new(), DictionaryAttribute::default(), &[]);
+        let op = arena.create_operation(operation, num_regions).unwrap();
+        for region in arena.op(op).unwrap().regions().to_vec() {
+            let block = arena.create_block();
+            arena.push_back_block(region, block).unwrap();
+        }
+        if let Some(parent) = parent {
+            let region = arena.op(parent).unwrap().region(0);
+            let block = arena.region(region).unwrap().blocks()[0];
+            arena.push_back(block, op).unwrap();
+        }
+        op
+    }
+
+    #[test]
+    fn erased_slots_are_reused_with_a_new_generation() {
+        let mut slots = Slots::default();
+        let (index, generation) = slots.insert_with(|_, _| 'a');
+        assert_eq!(slots.remove(index, generation), Some('a'));
+        assert_eq!(slots.remove(index, generation), None);
+
+        let (new_index, new_generation) = slots.insert_with(|_, _| 'b');
+        assert_eq!((new_index, new_generation), (index, generation + 1));
+        assert_eq!(slots.get(index, generation), None);
+        assert_eq!(slots.get(index, new_generation), Some(&'b'));
+        assert_eq!(slots.len, 1);
+    }
+
+    #[test]
+    fn slots_with_exhausted_generations_are_retired() {
+        let mut slots = Slots::default();
+        let (index, _) = slots.insert_with(|_, _| 'a');
+        slots.slot_mut(index).unwrap().generation = u32::MAX - 2;
+        assert_eq!(slots.remove(index, u32::MAX - 2), Some('a'));
+
+        // The last generation handed out.
+        let (reused, generation) = slots.insert_with(|_, _| 'b');
+        assert_eq!((reused, generation), (index, u32::MAX - 1));
+        assert_eq!(slots.remove(index, generation), Some('b'));
+
+        // The slot is never reused, so no handle ever matches it again.
+        assert!(slots.free.is_empty());
+        let (other, generation) = slots.insert_with(|_, _| 'c');
+        assert_ne!(other, index);
+        assert_eq!(generation, 0);
+        for generation in [0, u32::MAX - 2, u32::MAX - 1, u32::MAX] {
+            assert_eq!(slots.get(index, generation), None);
+        }
+    }
+
+    #[test]
+    fn lent_slots_are_joined_back() {
+        let mut slots = Slots::default();
+        let (first, _) = slots.insert_with(|_, _| 'a');
+        let (second, second_generation) = slots.insert_with(|_, _| 'b');
+        slots.remove(second, second_generation);
+
+        let next_index = Arc::new(AtomicU32::new(slots.next_index()));
+        let mut overlay = Slots::sparse(next_index.clone());
+        slots.lend(first, &mut overlay);
+        assert_eq!(slots.get(first, 0), None);
+        assert_eq!(overlay.get(first, 0), Some(&'a'));
+        assert!(overlay.owns(first) && !overlay.owns(second));
+
+        // Overlays allocate past the slots of the arena rather than reusing its free slots.
+        let (created, generation) = overlay.insert_with(|_, _| 'c');
+        assert_eq!((created, generation), (2, 0));
+        assert_eq!(next_index.load(Ordering::Relaxed), 3);
+
+        slots.join(overlay);
+        assert_eq!(slots.get(first, 0), Some(&'a'));
+        assert_eq!(slots.get(created, 0), Some(&'c'));
+        assert_eq!(slots.free, vec![second]);
+        assert_eq!(slots.len, 2);
+    }
+
+    #[test]
+    fn handles_of_erased_operations_are_rejected() {
+        let mut arena = IRArena::new();
+        let parent = create_op(&mut arena, None, 1);
+        let child = create_op(&mut arena, Some(parent), 0);
+        let region = arena.op(parent).unwrap().region(0);
+        let block = arena.region(region).unwrap().blocks()[0];
+
+        arena.erase(parent).unwrap();
+        assert_eq!(arena.op(parent).err(), Some(ArenaError::ErasedOperation(parent)));
+        assert_eq!(arena.op(child).err(), Some(ArenaError::ErasedOperation(child)));
+        assert_eq!(arena.region(region).err(), Some(ArenaError::ErasedRegion(region)));
+        assert_eq!(arena.block(block).err(), Some(ArenaError::ErasedBlock(block)));
+        assert_eq!(arena.erase(child), Err(ArenaError::ErasedOperation(child)));
+        assert_eq!(arena.num_operations(), 0);
+
+        // The slots are reused by new operations, which the stale handles don't refer to.
+        let other = create_op(&mut arena, None, 0);
+        assert!([parent.index(), child.index()].contains(&other.index()));
+        assert_ne!(other, parent);
+        assert_ne!(other, child);
+        assert!(arena.contains_op(other));
+        assert!(!arena.contains_op(parent) && !arena.contains_op(child));
+    }
+
+    #[test]
+    fn overlays_own_their_subtrees() {
+        let mut arena = IRArena::new();
+        let parent = create_op(&mut arena, None, 1);
+        let first = create_op(&mut arena, Some(parent), 1);
+        let second = create_op(&mut arena, Some(parent), 1);
+        let nested = create_op(&mut arena, Some(first), 0);
+
+        let (lhs_op, rhs_op) = arena.with_overlays(&[first, second], |overlays| {
+            let [lhs, rhs] = overlays else {
+                panic!("expected an overlay per root");
+            };
+            // The rest of the arena is visible but read-only.
+            assert_eq!(lhs.op(parent).unwrap().regions().len(), 1);
+            assert_eq!(lhs.op_mut(parent).err(), Some(ArenaError::ReadOnlyOperation(parent)));
+            assert_eq!(lhs.op_mut(second).err(), Some(ArenaError::ReadOnlyOperation(second)));
+            assert!(rhs.op(nested).is_ok());
+            assert!(lhs.op_mut(nested).is_ok());
+
+            // The objects created by different overlays have distinct handles.
+            let lhs_op = create_op(lhs, Some(first), 0);
+            let rhs_op = create_op(rhs, Some(second), 0);
+            assert_ne!(lhs_op.index(), rhs_op.index());
+            lhs.erase(nested).unwrap();
+            (lhs_op, rhs_op)
+        }).unwrap();
+
+        // The objects of the overlays are moved back, and their handles stay valid.
+        assert!(arena.contains_op(lhs_op) && arena.contains_op(rhs_op));
+        assert!(!arena.contains_op(nested));
+        assert_eq!(arena.walk(parent).unwrap().len(), 5);
+        assert!(arena.op_mut(parent).is_ok());
+    }
+
+    #[test]
+    #[should_panic(expected = "Overlay roots must not be nested in one another")]
+    fn overlay_roots_are_disjoint() {
+        let mut arena = IRArena::new();
+        let parent = create_op(&mut arena, None, 1);
+        let child = create_op(&mut arena, Some(parent), 0);
+        let _ = arena.with_overlays(&[parent, child], |_| ());
+    }
+}
@@ -4,3 +4,1666 @@
 //!
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/AsmPrinter.cpp>
 
+use core::fmt::{self, Display, Write};
+use std::{collections::HashMap, sync::Arc};
+
+use llvm::adt::ap_int::APInt;
+use crate::dialect::llvm_ir::types::{self as llvm_types, LLVMStructType};
+use crate::ir::{
+    arena::{ArenaError, ArenaResult, BlockId, IRArena, OpId, RegionId},
+    asm_state::{AsmResourceBuilder, AsmState},
+    attribute::{Attribute, NamedAttribute},
+    builtins::{
+        attributes::{
+            AffineMapAttribute, ArrayAttribute, DenseArrayAttribute,
+            DenseIntOrFPElementsAttribute, DenseResourceElementsAttribute,
+            DictionaryAttribute, FloatAttribute, IntegerAttribute,
+            IntegerSetAttribute, OpaqueAttribute, StridedLayoutAttribute,
+            StringAttribute, SymbolRefAttribute, TypeAttribute, UnitAttribute
+        },
+        type_interfaces::DYNAMIC,
+        types::{
+            ComplexType, FloatType, FunctionType, IndexType, IntegerType,
+            MemRefType, NoneType, OpaqueType, RankedTensorType, Signedness,
+            TupleType, UnrankedMemRefType, UnrankedTensorType, VectorType
+        }
+    },
+    dialect::implementation::DialectAsmPrinter,
+    extensible_dialect::{DynamicAttr, DynamicType},
+    location::Location,
+    operation::{
+        Operation,
+        definition::{IsIsolatedFromAbove, IsTerminator},
+        implementation::{
+            AsmDialectResourceHandle, AsmPrinter, AttrTypeElision,
+            OpAsmPrinter, is_bare_identifier, print_escaped_string
+        },
+        support::PrintingFlags
+    },
+    r#type::Type,
+    value::{Input, Output, Value}
+};
+
+/// The number of spaces each nesting level is indented by.
+const INDENT_WIDTH: usize = 2;
+
+/// Non-splat elements attributes with more elements than this are printed as a hex string.
+const HEX_ELEMENTS_THRESHOLD: i64 = 100;
+
+// ----------------------------------------------------------------------
+// Entry Points
+// ----------------------------------------------------------------------
+
+/// Print `op` with the given flags. Unless local scope is requested, values are numbered as they would be when printing the top-level ancestor of `op`.
+pub fn print_operation(arena: &IRArena, op: OpId, flags: PrintingFlags)
+    -> ArenaResult<String>
+{
+    print_operation_with_state(arena, op, &mut AsmState::new(flags))
+}
+
+/// Print `op` using the given state, which collects the referenced resources and, if requested, the location map.
+pub fn print_operation_with_state(
+    arena: &IRArena,
+    op: OpId,
+    state: &mut AsmState
+) -> ArenaResult<String> {
+    let mut printer = OperationPrinter::new(arena, op, state)?;
+    match printer.print_top_level_operation(op) {
+        Ok(()) => Ok(printer.finish()),
+        Err(_) => Err(printer.error.take()
+            .expect("Printing may only fail on a stale handle"))
+    }
+}
+
+/// Print `block`, numbering its values within the scope of its parent operation.
+pub fn print_block(arena: &IRArena, block: BlockId, flags: PrintingFlags)
+    -> ArenaResult<String>
+{
+    let scope = match arena.block_parent_op(block)? {
+        Some(parent) => parent,
+        None => return Ok("<<UNLINKED BLOCK>>\n".to_string())
+    };
+    let mut state = AsmState::new(flags);
+    let mut printer = OperationPrinter::new(arena, scope, &mut state)?;
+    match printer.print_block(block, true, true) {
+        Ok(()) => Ok(printer.finish()),
+        Err(_) => Err(printer.error.take()
+            .expect("Printing may only fail on a stale handle"))
+    }
+}
+
+/// Print `value`: the operation defining it for an output, and a description of the argument for a block input.
+pub fn print_value(arena: &IRArena, value: Value, flags: PrintingFlags)
+    -> ArenaResult<String>
+{
+    match value {
+        Value::Output(output) => print_operation(arena, output.owner(), flags),
+        Value::Input(input) => Ok(format!(
+            "<block argument> of type '{}' at index: {}",
+            value.r#type(arena)?.as_ref(),
+            input.index()
+        ))
+    }
+}
+
+/// Print `attribute` using the given state, which collects the dialect resources it references.
+pub fn print_attribute_with_state(attribute: &dyn Attribute, state: &mut AsmState)
+    -> String
+{
+    let mut output = String::new();
+    // Writing to a string cannot fail.
+    let _ = AttrTypePrinter::new(&mut output, state).print_attribute(attribute);
+    output
+}
+
+/// Print `type` using the given state, which collects the dialect resources it references.
+pub fn print_type_with_state(r#type: &dyn Type, state: &mut AsmState) -> String {
+    let mut output = String::new();
+    let _ = AttrTypePrinter::new(&mut output, state).print_type(r#type);
+    output
+}
+
+impl Display for dyn Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut state = AsmState::new(PrintingFlags::new());
+        AttrTypePrinter::new(f, &mut state).print_type(self)
+    }
+}
+
+impl Display for dyn Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut state = AsmState::new(PrintingFlags::new());
+        AttrTypePrinter::new(f, &mut state).print_attribute(self)
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut state = AsmState::new(PrintingFlags::new());
+        AttrTypePrinter::new(f, &mut state).print_location(self, false)
+    }
+}
+
+// ----------------------------------------------------------------------
+// OutputBuffer
+// ----------------------------------------------------------------------
+
+/// An output string that tracks the line and column the next character is printed at, both starting at 1.
+struct OutputBuffer {
+    buffer: String,
+    line: usize,
+    column: usize
+}
+
+impl OutputBuffer {
+    fn new() -> Self {
+        Self { buffer: String::new(), line: 1, column: 1 }
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        match string.rfind('\n') {
+            Some(index) => {
+                self.line += string.matches('\n').count();
+                self.column = string.len() - index;
+            },
+            None => self.column += string.len()
+        }
+        self.buffer.push_str(string);
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// AttrTypePrinter
+// ----------------------------------------------------------------------
+
+/// Prints builtin types, attributes and locations, and defers to the owning dialect for the others.
+struct AttrTypePrinter<'a, W: Write> {
+    output: W,
+    state: &'a mut AsmState
+}
+
+impl<'a, W: Write> AttrTypePrinter<'a, W> {
+    fn new(output: W, state: &'a mut AsmState) -> Self {
+        Self { output, state }
+    }
+
+    /// Print a dialect type or attribute as `{prefix}{namespace}.{body}` if the body printed by `print_body` is simple enough, or `{prefix}{namespace}<{body}>` otherwise.
+    fn print_dialect_symbol(
+        &mut self,
+        prefix: char,
+        namespace: &str,
+        print_body: impl FnOnce(&mut dyn DialectAsmPrinter) -> fmt::Result
+    ) -> fmt::Result {
+        let mut body = AttrTypePrinter::new(String::new(), &mut *self.state);
+        print_body(&mut body)?;
+        let body = body.output;
+        write!(self, "{}{}", prefix, namespace)?;
+        if is_pretty_dialect_symbol(&body) {
+            write!(self, ".{}", body)
+        } else {
+            write!(self, "<{}>", body)
+        }
+    }
+
+    fn print_attribute_impl(
+        &mut self,
+        attribute: &dyn Attribute,
+        elision: AttrTypeElision
+    ) -> fmt::Result {
+        let print_type = !matches!(elision, AttrTypeElision::Must);
+
+        if let Some(attribute) = attribute.downcast_ref::<IntegerAttribute>() {
+            let r#type = attribute.r#type();
+            let integer_type = r#type.downcast_ref::<IntegerType>();
+            // Boolean integer attributes always elide the type.
+            if let Some(integer_type) = integer_type
+                && integer_type.width() == 1
+                && integer_type.is_signless()
+            {
+                return self.write_str(
+                    if attribute.value().is_zero() { "false" } else { "true" });
+            }
+            let is_unsigned = integer_type.map_or(false, IntegerType::is_unsigned);
+            self.write_str(&attribute.value().to_string(10, !is_unsigned))?;
+            // The default type of an integer attribute is i64.
+            let is_i64 = integer_type.map_or(false, |r#type|
+                r#type.width() == 64 && r#type.is_signless());
+            if print_type
+                && !(matches!(elision, AttrTypeElision::May) && is_i64)
+            {
+                self.write_str(" : ")?;
+                self.print_type(r#type.as_ref())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<FloatAttribute>() {
+            let r#type = attribute.r#type();
+            print_float_value(self, attribute.bits())?;
+            let is_f64 = attribute.bits().width() == 64;
+            if print_type
+                && !(matches!(elision, AttrTypeElision::May) && is_f64)
+            {
+                self.write_str(" : ")?;
+                self.print_type(r#type.as_ref())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<StringAttribute>() {
+            print_escaped_string(self, attribute.value())?;
+            if let Some(r#type) = attribute.r#type() && print_type {
+                self.write_str(" : ")?;
+                self.print_type(r#type.as_ref())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<ArrayAttribute>() {
+            self.write_char('[')?;
+            for (index, element) in attribute.value().iter().enumerate() {
+                if index != 0 {
+                    self.write_str(", ")?;
+                }
+                self.print_attribute(element.as_ref())?;
+            }
+            return self.write_char(']');
+        }
+        if let Some(attribute) = attribute.downcast_ref::<DictionaryAttribute>() {
+            self.write_char('{')?;
+            for (index, named) in attribute.value().iter().enumerate() {
+                if index != 0 {
+                    self.write_str(", ")?;
+                }
+                self.print_named_attribute(named)?;
+            }
+            return self.write_char('}');
+        }
+        if let Some(attribute) = attribute.downcast_ref::<TypeAttribute>() {
+            return self.print_type(attribute.value().as_ref());
+        }
+        if attribute.downcast_ref::<UnitAttribute>().is_some() {
+            return self.write_str("unit");
+        }
+        if let Some(attribute) = attribute.downcast_ref::<SymbolRefAttribute>() {
+            self.print_symbol_name(attribute.root_reference().value())?;
+            for nested in attribute.nested_references() {
+                self.write_str("::")?;
+                self.print_symbol_name(nested.value())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<AffineMapAttribute>() {
+            return write!(self, "affine_map<{}>", attribute.value());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<IntegerSetAttribute>() {
+            return write!(self, "affine_set<{}>", attribute.value());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<OpaqueAttribute>() {
+            write!(self, "#{}<{}>",
+                   attribute.dialect_namespace(), attribute.attr_data())?;
+            if let Some(r#type) = attribute.r#type() && print_type {
+                self.write_str(" : ")?;
+                self.print_type(r#type.as_ref())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<DenseArrayAttribute>() {
+            return self.print_dense_array_attribute(attribute, print_type);
+        }
+        if let Some(attribute)
+            = attribute.downcast_ref::<DenseIntOrFPElementsAttribute>()
+        {
+            self.print_dense_elements_attribute(attribute)?;
+            if print_type {
+                self.write_str(" : ")?;
+                self.print_type(attribute.r#type().as_ref())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute)
+            = attribute.downcast_ref::<DenseResourceElementsAttribute>()
+        {
+            self.write_str("dense_resource<")?;
+            self.print_resource_handle(attribute.raw_handle())?;
+            self.write_char('>')?;
+            if print_type {
+                self.write_str(" : ")?;
+                self.print_type(attribute.r#type().as_ref())?;
+            }
+            return Ok(());
+        }
+        if let Some(attribute) = attribute.downcast_ref::<StridedLayoutAttribute>() {
+            self.write_str("strided<[")?;
+            for (index, stride) in attribute.strides().iter().enumerate() {
+                if index != 0 {
+                    self.write_str(", ")?;
+                }
+                print_dimension(self, *stride)?;
+            }
+            self.write_char(']')?;
+            if attribute.offset() != 0 {
+                self.write_str(", offset: ")?;
+                print_dimension(self, attribute.offset())?;
+            }
+            return self.write_char('>');
+        }
+        if let Some(attribute) = attribute.downcast_ref::<DynamicAttr>() {
+            return self.print_dialect_symbol(
+                '#', attribute.definition().dialect(),
+                |printer| attribute.print(printer));
+        }
+
+        match attribute.dialect() {
+            Some(dialect) => self.print_dialect_symbol(
+                '#', dialect.name(),
+                |printer| dialect.print_attribute(attribute, printer)),
+            None => self.write_str("<<UNKNOWN ATTRIBUTE>>")
+        }
+    }
+
+    /// Print a named attribute as `name = value`, or just `name` for a unit value.
+    fn print_named_attribute(&mut self, named: &NamedAttribute) -> fmt::Result {
+        self.print_keyword_or_string(named.name().value())?;
+        if named.value().downcast_ref::<UnitAttribute>().is_some() {
+            return Ok(());
+        }
+        self.write_str(" = ")?;
+        self.print_attribute(named.value().as_ref())
+    }
+
+    fn print_dense_array_attribute(
+        &mut self,
+        attribute: &DenseArrayAttribute,
+        print_type: bool
+    ) -> fmt::Result {
+        let element_type = attribute.element_type();
+        if print_type {
+            self.write_str("array<")?;
+            self.print_type(element_type.as_ref())?;
+            if attribute.size() != 0 {
+                self.write_str(": ")?;
+            }
+        } else {
+            self.write_char('[')?;
+        }
+        let width = element_bit_width(element_type.as_ref());
+        // Bool elements are stored as bytes.
+        let stride = width.max(8);
+        for index in 0..attribute.size() as usize {
+            if index != 0 {
+                self.write_str(", ")?;
+            }
+            let element = read_element(attribute.raw_data(), index * stride, width);
+            print_element(self, element_type.as_ref(), &element)?;
+        }
+        self.write_char(if print_type { '>' } else { ']' })
+    }
+
+    fn print_dense_elements_attribute(
+        &mut self,
+        attribute: &DenseIntOrFPElementsAttribute
+    ) -> fmt::Result {
+        let (shape, element_type) = match shaped_type_parts(
+            attribute.r#type().as_ref())
+        {
+            Some(parts) => parts,
+            None => return self.write_str("dense<<<INVALID TYPE>>>")
+        };
+        let num_elements: i64 = shape.iter().product();
+        if self.state.printer_flags().should_elide_elements_attr(num_elements) {
+            return self.write_str("dense_resource<__elided__>");
+        }
+        self.write_str("dense<")?;
+        if !attribute.is_splat() && num_elements > HEX_ELEMENTS_THRESHOLD {
+            self.write_str("\"0x")?;
+            for byte in attribute.raw_data() {
+                write!(self, "{:02X}", byte)?;
+            }
+            return self.write_str("\">");
+        }
+
+        let (scalar_type, num_parts) = match element_type
+            .downcast_ref::<ComplexType>()
+        {
+            Some(complex) => (complex.element_type().as_ref(), 2),
+            None => (element_type, 1)
+        };
+        let width = element_bit_width(scalar_type);
+        // Elements are rounded up to whole bytes, except `i1` elements which are packed.
+        let stride = if width == 1 { 1 } else { width.div_ceil(8) * 8 };
+        let print_one = |printer: &mut Self, index: usize| -> fmt::Result {
+            if num_parts == 2 {
+                printer.write_char('(')?;
+            }
+            for part in 0..num_parts {
+                if part != 0 {
+                    printer.write_char(',')?;
+                }
+                let element = read_element(
+                    attribute.raw_data(),
+                    (index * num_parts + part) * stride,
+                    width
+                );
+                print_element(printer, scalar_type, &element)?;
+            }
+            if num_parts == 2 {
+                printer.write_char(')')?;
+            }
+            Ok(())
+        };
+
+        if attribute.is_splat() || shape.is_empty() {
+            print_one(self, 0)?;
+            return self.write_char('>');
+        }
+
+        // Print the elements nested by dimension, e.g. `[[1, 2], [3, 4]]`. Each element opens a bracket for every dimension it is the first element of, and closes one for every dimension it is the last element of.
+        let mut strides = vec![1i64; shape.len()];
+        for dimension in (0..shape.len() - 1).rev() {
+            strides[dimension] = strides[dimension + 1] * shape[dimension + 1];
+        }
+        let spans: Vec<i64> = strides.iter().zip(&shape)
+            .map(|(stride, size)| stride * size)
+            .collect();
+        let count = |index: i64| spans.iter()
+            .rev()
+            .take_while(|span| index % **span == 0)
+            .count();
+        for index in 0..num_elements {
+            if index != 0 {
+                self.write_str(", ")?;
+            }
+            for _ in 0..count(index) {
+                self.write_char('[')?;
+            }
+            print_one(self, index as usize)?;
+            for _ in 0..count(index + 1) {
+                self.write_char(']')?;
+            }
+        }
+        self.write_char('>')
+    }
+
+    /// Print a location. In the pretty form, file names are printed without quotes, which is not parsable.
+    fn print_location(&mut self, location: &Location, pretty: bool)
+        -> fmt::Result
+    {
+        match location {
+            Location::Unknown => self.write_str("unknown"),
+            Location::FileLineCol(location) => {
+                if pretty {
+                    self.write_str(location.filename())?;
+                } else {
+                    print_escaped_string(self, location.filename())?;
+                }
+                write!(self, ":{}:{}", location.line(), location.column())
+            },
+            Location::Name(location) => {
+                print_escaped_string(self, location.name())?;
+                // Print the child if it isn't unknown.
+                let child = location.child_location();
+                if !child.is_unknown() {
+                    self.write_char('(')?;
+                    self.print_location(child, pretty)?;
+                    self.write_char(')')?;
+                }
+                Ok(())
+            },
+            Location::CallSite(location) => {
+                self.write_str("callsite(")?;
+                self.print_location(location.callee(), pretty)?;
+                self.write_str(" at ")?;
+                self.print_location(location.caller(), pretty)?;
+                self.write_char(')')
+            },
+            Location::Fused(location) => {
+                self.write_str("fused")?;
+                if let Some(metadata) = location.metadata() {
+                    self.write_char('<')?;
+                    self.print_attribute(metadata.as_ref())?;
+                    self.write_char('>')?;
+                }
+                self.write_char('[')?;
+                for (index, location) in location.locations().iter().enumerate() {
+                    if index != 0 {
+                        self.write_str(", ")?;
+                    }
+                    self.print_location(location, pretty)?;
+                }
+                self.write_char(']')
+            },
+            Location::Opaque(location) =>
+                self.print_location(location.fallback_location(), pretty)
+        }
+    }
+}
+
+impl<'a, W: Write> Write for AttrTypePrinter<'a, W> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.output.write_str(string)
+    }
+}
+
+impl<'a, W: Write> AsmPrinter for AttrTypePrinter<'a, W> {
+    fn print_type(&mut self, r#type: &dyn Type) -> fmt::Result {
+        if let Some(r#type) = r#type.downcast_ref::<IntegerType>() {
+            let prefix = match r#type.signedness() {
+                Signedness::Signless => "i",
+                Signedness::Signed => "si",
+                Signedness::Unsigned => "ui"
+            };
+            return write!(self, "{}{}", prefix, r#type.width());
+        }
+        if r#type.downcast_ref::<IndexType>().is_some() {
+            return self.write_str("index");
+        }
+        if let Some(r#type) = r#type.downcast_ref::<FloatType>() {
+            return self.write_str(r#type.keyword());
+        }
+        if r#type.downcast_ref::<NoneType>().is_some() {
+            return self.write_str("none");
+        }
+        if let Some(r#type) = r#type.downcast_ref::<ComplexType>() {
+            self.write_str("complex<")?;
+            self.print_type(r#type.element_type().as_ref())?;
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<FunctionType>() {
+            return self.print_functional_type(r#type.inputs(), r#type.outputs());
+        }
+        if let Some(r#type) = r#type.downcast_ref::<TupleType>() {
+            self.write_str("tuple<")?;
+            self.print_type_list(r#type.types())?;
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<RankedTensorType>() {
+            self.write_str("tensor<")?;
+            for dimension in r#type.shape() {
+                print_dimension(self, *dimension)?;
+                self.write_char('x')?;
+            }
+            self.print_type(r#type.element_type().as_ref())?;
+            if let Some(encoding) = r#type.encoding() {
+                self.write_str(", ")?;
+                self.print_attribute(encoding.as_ref())?;
+            }
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<UnrankedTensorType>() {
+            self.write_str("tensor<*x")?;
+            self.print_type(r#type.element_type().as_ref())?;
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<VectorType>() {
+            self.write_str("vector<")?;
+            // Scalable dimensions are enclosed in square brackets.
+            for (dimension, scalable) in r#type.shape().iter().zip(r#type.scalable_dims()) {
+                if *scalable {
+                    write!(self, "[{}]x", dimension)?;
+                } else {
+                    write!(self, "{}x", dimension)?;
+                }
+            }
+            self.print_type(r#type.element_type().as_ref())?;
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<MemRefType>() {
+            self.write_str("memref<")?;
+            for dimension in r#type.shape() {
+                print_dimension(self, *dimension)?;
+                self.write_char('x')?;
+            }
+            self.print_type(r#type.element_type().as_ref())?;
+            if let Some(layout) = r#type.layout() {
+                self.write_str(", ")?;
+                self.print_attribute_impl(layout.as_ref(), AttrTypeElision::May)?;
+            }
+            // Memory spaces are usually integers, whose default `i64` type is elided.
+            if let Some(memory_space) = r#type.memory_space() {
+                self.write_str(", ")?;
+                self.print_attribute_impl(memory_space.as_ref(), AttrTypeElision::May)?;
+            }
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<UnrankedMemRefType>() {
+            self.write_str("memref<*x")?;
+            self.print_type(r#type.element_type().as_ref())?;
+            if let Some(memory_space) = r#type.memory_space() {
+                self.write_str(", ")?;
+                self.print_attribute_impl(memory_space.as_ref(), AttrTypeElision::May)?;
+            }
+            return self.write_char('>');
+        }
+        if let Some(r#type) = r#type.downcast_ref::<OpaqueType>() {
+            return write!(self, "!{}<{}>",
+                          r#type.dialect_namespace(), r#type.type_data());
+        }
+        if let Some(r#type) = r#type.downcast_ref::<DynamicType>() {
+            return self.print_dialect_symbol(
+                '!', r#type.definition().dialect(),
+                |printer| r#type.print(printer));
+        }
+        // The LLVM dialect has no `Dialect` instance yet, so its types are dispatched here.
+        if r#type.downcast_ref::<LLVMStructType>().is_some() {
+            return self.print_dialect_symbol(
+                '!', "llvm", |printer| llvm_types::print_type(r#type, printer));
+        }
+
+        match r#type.dialect() {
+            Some(dialect) => self.print_dialect_symbol(
+                '!', dialect.name(),
+                |printer| dialect.print_type(r#type, printer)),
+            None => self.write_str("<<UNKNOWN TYPE>>")
+        }
+    }
+
+    fn print_attribute(&mut self, attribute: &dyn Attribute) -> fmt::Result {
+        self.print_attribute_impl(attribute, AttrTypeElision::Never)
+    }
+
+    fn print_attribute_without_type(&mut self, attribute: &dyn Attribute)
+        -> fmt::Result
+    {
+        self.print_attribute_impl(attribute, AttrTypeElision::Must)
+    }
+
+    fn print_resource_handle(&mut self, resource: &AsmDialectResourceHandle)
+        -> fmt::Result
+    {
+        self.state.record_dialect_resource(resource);
+        self.print_keyword_or_string(resource.key())
+    }
+}
+
+impl<'a, W: Write> DialectAsmPrinter for AttrTypePrinter<'a, W> {
+    fn push_cyclic_printing(&mut self, id: *const ()) -> bool {
+        self.state.push_cyclic_printing(id)
+    }
+
+    fn pop_cyclic_printing(&mut self) {
+        self.state.pop_cyclic_printing()
+    }
+}
+
+/// Returns true if a dialect symbol body can be printed in the pretty form `!dialect.body`, i.e. it is an identifier optionally followed by a `<...>` parameter list.
+fn is_pretty_dialect_symbol(body: &str) -> bool {
+    let (name, rest) = match body.find('<') {
+        Some(index) => body.split_at(index),
+        None => (body, "")
+    };
+    if !name.starts_with(|char: char| char.is_ascii_alphabetic())
+        || !name.chars().all(|char| char.is_ascii_alphanumeric()
+                                    || matches!(char, '_' | '.'))
+    {
+        return false;
+    }
+    rest.is_empty() || rest.ends_with('>')
+}
+
+/// Print a dimension size, using `?` for dynamic sizes.
+fn print_dimension<W: Write + ?Sized>(output: &mut W, dimension: i64)
+    -> fmt::Result
+{
+    if dimension == DYNAMIC {
+        output.write_char('?')
+    } else {
+        write!(output, "{}", dimension)
+    }
+}
+
+/// Print the bit pattern of a float with the semantics implied by its width. Values are printed in exponential form with six digits after the point if that is lossless, e.g. `1.500000e+00`, with all their digits otherwise, and in hex if they cannot be printed in decimal form losslessly.
+fn print_float_value<W: Write + ?Sized>(output: &mut W, bits: &APInt)
+    -> fmt::Result
+{
+    let word = bits.words()[0];
+    let (value, precision) = match bits.width() {
+        32 => (Some(f64::from(f32::from_bits(word as u32))), 9),
+        64 => (Some(f64::from_bits(word)), 17),
+        _ => (None, 0)
+    };
+    if let Some(value) = value.filter(|value| value.is_finite()) {
+        let is_lossless = |decimal: &str| match bits.width() {
+            32 => decimal.parse::<f32>().is_ok_and(|parsed| parsed.to_bits() as u64 == word),
+            _ => decimal.parse::<f64>().is_ok_and(|parsed| parsed.to_bits() == word)
+        };
+        // We would like to output the value in exponential notation, but we cannot do this if doing so will lose precision.
+        let decimal = format_float(value, 6, 0, false);
+        if is_lossless(&decimal) {
+            return output.write_str(&decimal);
+        }
+        // If it is not, use the default format of APFloat instead, as long as it can be parsed as a float.
+        let decimal = format_float(value, precision, 3, true);
+        if decimal.contains('.') {
+            return output.write_str(&decimal);
+        }
+    }
+    // Print special values in hexadecimal format.
+    output.write_str(&bits.to_string(16, false).to_uppercase().replacen("0X", "0x", 1))
+}
+
+/**
+Format `value` like `APFloat::toString`, rounded to `precision` significant digits. The value is printed in exponential form, unless `max_padding` is non-zero and the plain form needs at most that many zeros between its digits and the point. With `truncate_zero`, the exponential form is as short as possible, e.g. `1.0E+20`, and otherwise it is padded to `precision` digits after the point, e.g. `1.000000e+20`.
+*/
+fn format_float(value: f64, precision: usize, max_padding: usize, truncate_zero: bool)
+    -> String
+{
+    let sign = if value.is_sign_negative() { "-" } else { "" };
+    if value == 0.0 {
+        return match (max_padding, truncate_zero) {
+            (0, true) => format!("{}0.0E+0", sign),
+            (0, false) => format!("{}0.{}e+00", sign, "0".repeat(precision.max(1))),
+            _ => format!("{}0", sign)
+        };
+    }
+
+    // The significant digits without trailing zeros, and the exponent of the first one.
+    let scientific = format!("{:.*e}", precision - 1, value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i64 = exponent.parse().unwrap();
+    let digits: String = mantissa.chars().filter(char::is_ascii_digit).collect();
+    let digits = digits.trim_end_matches('0');
+    let num_digits = digits.len() as i64;
+    // The exponent of the last digit.
+    let last_exponent = exponent - (num_digits - 1);
+
+    let is_scientific = max_padding == 0 || if last_exponent >= 0 {
+        last_exponent > max_padding as i64 || num_digits + last_exponent > precision as i64
+    } else {
+        -exponent > max_padding as i64
+    };
+    if is_scientific {
+        let mut output = format!("{}{}.", sign, &digits[..1]);
+        if num_digits == 1 && truncate_zero {
+            output.push('0');
+        }
+        output.push_str(&digits[1..]);
+        if !truncate_zero {
+            output.push_str(&"0".repeat(precision.saturating_sub(digits.len() - 1)));
+        }
+        let sign = if exponent < 0 { '-' } else { '+' };
+        return match truncate_zero {
+            true => format!("{}E{}{}", output, sign, exponent.abs()),
+            false => format!("{}e{}{:02}", output, sign, exponent.abs())
+        };
+    }
+    if last_exponent >= 0 {
+        format!("{}{}{}", sign, digits, "0".repeat(last_exponent as usize))
+    } else if exponent >= 0 {
+        let (integral, fractional) = digits.split_at(exponent as usize + 1);
+        format!("{}{}.{}", sign, integral, fractional)
+    } else {
+        format!("{}0.{}{}", sign, "0".repeat((-exponent - 1) as usize), digits)
+    }
+}
+
+/// Returns the shape and element type of a shaped type with a static shape.
+fn shaped_type_parts(r#type: &dyn Type) -> Option<(Vec<i64>, &dyn Type)> {
+    if let Some(r#type) = r#type.downcast_ref::<RankedTensorType>() {
+        return Some((r#type.shape().to_vec(), r#type.element_type().as_ref()));
+    }
+    if let Some(r#type) = r#type.downcast_ref::<VectorType>() {
+        return Some((r#type.shape().to_vec(), r#type.element_type().as_ref()));
+    }
+    None
+}
+
+/// Returns the bit width of an integer, index or float element type.
+fn element_bit_width(r#type: &dyn Type) -> usize {
+    if let Some(r#type) = r#type.downcast_ref::<IntegerType>() {
+        return r#type.width();
+    }
+    if let Some(r#type) = r#type.downcast_ref::<FloatType>() {
+        return r#type.width();
+    }
+    // Index elements are stored with 64 bits.
+    64
+}
+
+/// Read the element of `width` bits starting at bit `offset` of little-endian `data`.
+fn read_element(data: &[u8], offset: usize, width: usize) -> APInt {
+    if width == 1 {
+        let bit = data.get(offset / 8).map_or(0, |byte| byte >> (offset % 8) & 1);
+        return APInt::new(1, bit as u64, false);
+    }
+    let start = offset / 8;
+    let num_bytes = width.div_ceil(8);
+    let mut words = vec![0u64; num_bytes.div_ceil(8)];
+    for (index, byte) in data.iter().skip(start).take(num_bytes).enumerate() {
+        words[index / 8] |= (*byte as u64) << (index % 8 * 8);
+    }
+    APInt::from_words(width as u32, &words)
+}
+
+/// Print a single element of a dense attribute with the given scalar type.
+fn print_element<W: Write + ?Sized>(
+    output: &mut W,
+    r#type: &dyn Type,
+    element: &APInt
+) -> fmt::Result {
+    if r#type.downcast_ref::<FloatType>().is_some() {
+        return print_float_value(output, element);
+    }
+    match r#type.downcast_ref::<IntegerType>() {
+        Some(integer) if integer.width() == 1 && integer.is_signless() =>
+            output.write_str(if element.is_zero() { "false" } else { "true" }),
+        Some(integer) =>
+            output.write_str(&element.to_string(10, !integer.is_unsigned())),
+        None => output.write_str(&element.to_string(10, true))
+    }
+}
+
+// ----------------------------------------------------------------------
+// SSANameState
+// ----------------------------------------------------------------------
+
+/// The number assigned to a value.
+#[derive(Clone, Copy)]
+enum ValueId {
+    /// A value printed as `%N`. For operations, the number is assigned to the first output and shared by the output group.
+    Number(usize),
+    /// An argument of an entry block, printed as `%argN`.
+    Argument(usize)
+}
+
+/// The counters used when numbering the values of a scope.
+#[derive(Clone, Copy, Default)]
+struct Counters {
+    next_value: usize,
+    next_argument: usize
+}
+
+/// This class manages the state of SSA value names.
+#[derive(Default)]
+struct SSANameState {
+    value_ids: HashMap<Value, ValueId>,
+    /// The number of each block, unique within its region.
+    block_ids: HashMap<BlockId, usize>
+}
+
+impl SSANameState {
+    /// Number the values and blocks nested under `scope`, including the outputs of `scope` itself.
+    fn new(arena: &IRArena, scope: OpId) -> ArenaResult<Self> {
+        let mut state = Self::default();
+        state.number_operation(arena, scope, &mut Counters::default())?;
+        Ok(state)
+    }
+
+    fn number_operation(
+        &mut self,
+        arena: &IRArena,
+        op: OpId,
+        counters: &mut Counters
+    ) -> ArenaResult<()> {
+        let operation = arena.op(op)?;
+        if operation.num_outputs() != 0 {
+            self.value_ids.insert(
+                Value::Output(Output::new(op, 0)),
+                ValueId::Number(counters.next_value));
+            counters.next_value += 1;
+        }
+        // Regions of an operation isolated from above start numbering from scratch. Other regions continue from the current numbering, which the values following the operation reuse as they are not visible within the regions.
+        let is_isolated = operation.has_trait::<dyn IsIsolatedFromAbove>();
+        for region in operation.regions() {
+            let mut nested = if is_isolated {
+                Counters::default()
+            } else {
+                *counters
+            };
+            self.number_region(arena, *region, &mut nested)?;
+        }
+        Ok(())
+    }
+
+    fn number_region(
+        &mut self,
+        arena: &IRArena,
+        region: RegionId,
+        counters: &mut Counters
+    ) -> ArenaResult<()> {
+        for (index, block) in arena.region(region)?.blocks().iter().enumerate() {
+            self.block_ids.insert(*block, index);
+            let block_ref = arena.block(*block)?;
+            for input in block_ref.inputs() {
+                let id = if index == 0 {
+                    counters.next_argument += 1;
+                    ValueId::Argument(counters.next_argument - 1)
+                } else {
+                    counters.next_value += 1;
+                    ValueId::Number(counters.next_value - 1)
+                };
+                self.value_ids.insert(Value::Input(input), id);
+            }
+            for op in block_ref.operations() {
+                self.number_operation(arena, *op, counters)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Print the name of `value`. Outputs of operations with several outputs are printed as `%N#index`.
+    fn print_value_id<W: Write + ?Sized>(
+        &self,
+        arena: &IRArena,
+        value: Value,
+        output: &mut W
+    ) -> fmt::Result {
+        let (key, index) = match value {
+            Value::Output(result) =>
+                (Value::Output(Output::new(result.owner(), 0)), Some(result.index())),
+            Value::Input(_) => (value, None)
+        };
+        match self.value_ids.get(&key) {
+            None => output.write_str("<<UNKNOWN SSA VALUE>>"),
+            Some(ValueId::Argument(id)) => write!(output, "%arg{}", id),
+            Some(ValueId::Number(id)) => {
+                write!(output, "%{}", id)?;
+                match index {
+                    Some(index) if arena.op(key_owner(key)).map_or(
+                        false, |op| op.num_outputs() > 1) =>
+                        write!(output, "#{}", index),
+                    _ => Ok(())
+                }
+            }
+        }
+    }
+
+    /// Print the label of `block`, e.g. `^bb0`.
+    fn print_block_name<W: Write + ?Sized>(&self, block: BlockId, output: &mut W)
+        -> fmt::Result
+    {
+        match self.block_ids.get(&block) {
+            Some(id) => write!(output, "^bb{}", id),
+            None => output.write_str("^INVALIDBLOCK")
+        }
+    }
+}
+
+/// Returns the operation defining an output key of `SSANameState::value_ids`.
+fn key_owner(key: Value) -> OpId {
+    match key {
+        Value::Output(output) => output.owner(),
+        Value::Input(_) => unreachable!("Block inputs do not have an owner operation")
+    }
+}
+
+// ----------------------------------------------------------------------
+// OperationPrinter
+// ----------------------------------------------------------------------
+
+/// This class contains the logic for printing operations, regions, and blocks.
+pub struct OperationPrinter<'a> {
+    arena: &'a IRArena,
+    printer: AttrTypePrinter<'a, OutputBuffer>,
+    names: SSANameState,
+    /// The current indentation.
+    indent: usize,
+    /// The first stale handle hit while printing.
+    error: Option<ArenaError>
+}
+
+impl<'a> OperationPrinter<'a> {
+    /// Create a printer for `op` and the operations nested under it. Values are numbered from `op` when local scope is requested, and from its top-level ancestor otherwise.
+    pub fn new(arena: &'a IRArena, op: OpId, state: &'a mut AsmState)
+        -> ArenaResult<Self>
+    {
+        let mut scope = op;
+        if !state.printer_flags().should_use_local_scope() {
+            while let Some(parent) = arena.parent_op(scope)? {
+                scope = parent;
+            }
+        }
+        Ok(Self {
+            arena,
+            names: SSANameState::new(arena, scope)?,
+            printer: AttrTypePrinter::new(OutputBuffer::new(), state),
+            indent: 0,
+            error: None
+        })
+    }
+
+    /// Return the printed output.
+    pub fn finish(self) -> String {
+        self.printer.output.buffer
+    }
+
+    fn flags(&self) -> &PrintingFlags {
+        self.printer.state.printer_flags()
+    }
+
+    /// Convert a stale handle error into a formatting error, remembering the cause.
+    fn check<T>(&mut self, result: ArenaResult<T>) -> Result<T, fmt::Error> {
+        result.map_err(|error| {
+            self.error.get_or_insert(error);
+            fmt::Error
+        })
+    }
+
+    /// Print `op` followed by the resource section, if any resources were referenced.
+    pub fn print_top_level_operation(&mut self, op: OpId) -> fmt::Result {
+        self.print_full_operation(op)?;
+        self.write_char('\n')?;
+        let operation = self.check(self.arena.op(op))?;
+        self.print_resource_section(operation)
+    }
+
+    /// Print the operation, its outputs and its trailing location, at the current indentation.
+    fn print_full_operation(&mut self, op: OpId) -> fmt::Result {
+        let arena = self.arena;
+        let operation = self.check(arena.op(op))?;
+        let (line, column) = (self.printer.output.line, self.printer.output.column);
+        self.printer.state.record_location(op, line, column);
+
+        let num_outputs = operation.num_outputs();
+        if num_outputs != 0 {
+            self.print_operand(Value::Output(Output::new(op, 0)))?;
+            if num_outputs > 1 {
+                write!(self, ":{}", num_outputs)?;
+            }
+            self.write_str(" = ")?;
+        }
+
+        // Custom printers assume the invariants of their operation hold, so invalid operations fall back to the generic form.
+        if operation.name().has_custom_assembly_form()
+            && !self.flags().should_print_generic_op_form()
+            && (self.flags().should_assume_verified()
+                || operation.name().verify_invariants(arena, operation).is_ok())
+        {
+            // Elide the `builtin.` prefix of builtin operations.
+            let name = operation.name().as_str();
+            self.write_str(name.strip_prefix("builtin.").unwrap_or(name))?;
+            operation.name().print_assembly(operation, self)
+                .unwrap_or(Ok(()))?;
+        } else {
+            self.print_generic_op(operation, true)?;
+        }
+
+        self.print_trailing_location(&operation.location())?;
+        if self.flags().should_print_value_users() {
+            self.print_users_comment(operation)?;
+        }
+        Ok(())
+    }
+
+    /// Print ` loc(...)` if debug information is requested.
+    fn print_trailing_location(&mut self, location: &Location) -> fmt::Result {
+        if !self.flags().should_print_debug_info() {
+            return Ok(());
+        }
+        let pretty = self.flags().should_print_debug_info_pretty_form();
+        self.write_str(" loc(")?;
+        self.printer.print_location(location, pretty)?;
+        self.write_char(')')
+    }
+
+    /// Print a comment listing the users of the outputs of `operation`.
+    fn print_users_comment(&mut self, operation: &Operation) -> fmt::Result {
+        let mut users = Vec::new();
+        for output in operation.outputs() {
+            for user in self.check(Value::Output(output).users(self.arena))? {
+                if !users.contains(&user) {
+                    users.push(user);
+                }
+            }
+        }
+        if users.is_empty() {
+            return Ok(());
+        }
+        self.write_str(" // users: ")?;
+        for (index, user) in users.into_iter().enumerate() {
+            if index != 0 {
+                self.write_str(", ")?;
+            }
+            let user_op = self.check(self.arena.op(user))?;
+            if user_op.num_outputs() == 0 {
+                print_escaped_string(self, user_op.name().as_str())?;
+            } else {
+                self.print_operand(Value::Output(Output::new(user, 0)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Print a block, with its label and arguments if `print_block_args` is true, and its terminator if `print_block_terminator` is true.
+    fn print_block(
+        &mut self,
+        block: BlockId,
+        print_block_args: bool,
+        print_block_terminator: bool
+    ) -> fmt::Result {
+        let arena = self.arena;
+        let block_ref = self.check(arena.block(block))?;
+        if print_block_args {
+            self.write_indent()?;
+            self.names.print_block_name(block, &mut self.printer)?;
+            if !block_ref.inputs_empty() {
+                self.write_char('(')?;
+                for (index, input) in block_ref.inputs().enumerate() {
+                    if index != 0 {
+                        self.write_str(", ")?;
+                    }
+                    self.print_region_argument(input, &[], false)?;
+                }
+                self.write_char(')')?;
+            }
+            self.write_char(':')?;
+
+            // Print out some context information about the predecessors of this block.
+            let is_entry = self.check(block_ref.is_entry_block(arena))?;
+            let predecessors = self.check(block_ref.predecessors(arena))?;
+            if predecessors.is_empty() {
+                if !is_entry {
+                    self.write_str("  // no predecessors")?;
+                }
+            } else {
+                let mut unique = Vec::new();
+                for predecessor in predecessors {
+                    if !unique.contains(&predecessor) {
+                        unique.push(predecessor);
+                    }
+                }
+                if unique.len() == 1 {
+                    self.write_str("  // pred: ")?;
+                } else {
+                    write!(self, "  // {} preds: ", unique.len())?;
+                }
+                for (index, predecessor) in unique.into_iter().enumerate() {
+                    if index != 0 {
+                        self.write_str(", ")?;
+                    }
+                    self.names.print_block_name(predecessor, &mut self.printer)?;
+                }
+            }
+            self.write_char('\n')?;
+        }
+
+        self.indent += INDENT_WIDTH;
+        let mut operations = block_ref.operations();
+        if !print_block_terminator
+            && let Some(back) = block_ref.back()
+            && self.check(arena.op(back))?.might_have_trait::<dyn IsTerminator>()
+        {
+            operations = &operations[..operations.len() - 1];
+        }
+        for op in operations {
+            self.write_indent()?;
+            self.print_full_operation(*op)?;
+            self.write_char('\n')?;
+        }
+        self.indent -= INDENT_WIDTH;
+        Ok(())
+    }
+
+    fn write_indent(&mut self) -> fmt::Result {
+        write!(self, "{:indent$}", "", indent = self.indent)
+    }
+
+    /// Print the `{-# ... #-}` section holding the dialect and external resources referenced while printing `top_level_op`.
+    fn print_resource_section(&mut self, top_level_op: &Operation) -> fmt::Result {
+        let sections = [
+            ("dialect_resources",
+             self.printer.state.build_dialect_resources(top_level_op)),
+            ("external_resources",
+             self.printer.state.build_external_resources(top_level_op))
+        ];
+        let sections: Vec<_> = sections.into_iter()
+            .map(|(name, groups)| (name, groups.into_iter()
+                .filter(|(_, builder)| !builder.entries().is_empty())
+                .collect::<Vec<(String, AsmResourceBuilder)>>()))
+            .filter(|(_, groups)| !groups.is_empty())
+            .collect();
+        if sections.is_empty() {
+            return Ok(());
+        }
+
+        self.write_str("\n{-#\n")?;
+        for (index, (name, groups)) in sections.iter().enumerate() {
+            if index != 0 {
+                self.write_str(",\n")?;
+            }
+            write!(self, "  {}: {{\n", name)?;
+            for (index, (group, builder)) in groups.iter().enumerate() {
+                if index != 0 {
+                    self.write_str(",\n")?;
+                }
+                self.write_str("    ")?;
+                self.print_keyword_or_string(group)?;
+                self.write_str(": {\n")?;
+                for (index, (key, value)) in builder.entries().iter().enumerate() {
+                    if index != 0 {
+                        self.write_str(",\n")?;
+                    }
+                    self.write_str("      ")?;
+                    self.print_keyword_or_string(key)?;
+                    write!(self, ": {}", value)?;
+                }
+                self.write_str("\n    }")?;
+            }
+            self.write_str("\n  }")?;
+        }
+        self.write_str("\n#-}\n")
+    }
+
+    fn print_attr_dict(
+        &mut self,
+        attributes: &[NamedAttribute],
+        elided_attrs: &[&str],
+        with_keyword: bool
+    ) -> fmt::Result {
+        let mut filtered = attributes.iter()
+            .filter(|attribute| !elided_attrs.contains(&attribute.name().value()))
+            .peekable();
+        if filtered.peek().is_none() {
+            return Ok(());
+        }
+        if with_keyword {
+            self.write_str(" attributes")?;
+        }
+        self.write_str(" {")?;
+        for (index, attribute) in filtered.enumerate() {
+            if index != 0 {
+                self.write_str(", ")?;
+            }
+            self.printer.print_named_attribute(attribute)?;
+        }
+        self.write_char('}')
+    }
+}
+
+impl<'a> Write for OperationPrinter<'a> {
+    fn write_str(&mut self, string: &str) -> fmt::Result {
+        self.printer.write_str(string)
+    }
+}
+
+impl<'a> AsmPrinter for OperationPrinter<'a> {
+    fn print_type(&mut self, r#type: &dyn Type) -> fmt::Result {
+        self.printer.print_type(r#type)
+    }
+
+    fn print_attribute(&mut self, attribute: &dyn Attribute) -> fmt::Result {
+        self.printer.print_attribute(attribute)
+    }
+
+    fn print_attribute_without_type(&mut self, attribute: &dyn Attribute)
+        -> fmt::Result
+    {
+        self.printer.print_attribute_without_type(attribute)
+    }
+
+    fn print_resource_handle(&mut self, resource: &AsmDialectResourceHandle)
+        -> fmt::Result
+    {
+        self.printer.print_resource_handle(resource)
+    }
+}
+
+impl<'a> OpAsmPrinter for OperationPrinter<'a> {
+    fn arena(&self) -> &IRArena {
+        self.arena
+    }
+
+    fn print_new_line(&mut self) -> fmt::Result {
+        self.write_char('\n')?;
+        self.write_indent()
+    }
+
+    fn increase_indent(&mut self) {
+        self.indent += INDENT_WIDTH;
+    }
+
+    fn decrease_indent(&mut self) {
+        self.indent -= INDENT_WIDTH;
+    }
+
+    fn print_operand(&mut self, value: Value) -> fmt::Result {
+        self.names.print_value_id(self.arena, value, &mut self.printer)
+    }
+
+    fn print_successor(&mut self, successor: BlockId) -> fmt::Result {
+        self.names.print_block_name(successor, &mut self.printer)
+    }
+
+    fn print_region_argument(
+        &mut self,
+        input: Input,
+        attributes: &[NamedAttribute],
+        omit_type: bool
+    ) -> fmt::Result {
+        let value = Value::Input(input);
+        self.print_operand(value)?;
+        if !omit_type {
+            self.write_str(": ")?;
+            let r#type = self.check(value.r#type(self.arena))?;
+            self.print_type(r#type.as_ref())?;
+        }
+        self.print_attr_dict(attributes, &[], false)?;
+        let location = self.check(value.location(self.arena))?;
+        self.print_trailing_location(&location)
+    }
+
+    fn print_optional_attr_dict(
+        &mut self,
+        attributes: &[NamedAttribute],
+        elided_attrs: &[&str]
+    ) -> fmt::Result {
+        self.print_attr_dict(attributes, elided_attrs, false)
+    }
+
+    fn print_optional_attr_dict_with_keyword(
+        &mut self,
+        attributes: &[NamedAttribute],
+        elided_attrs: &[&str]
+    ) -> fmt::Result {
+        self.print_attr_dict(attributes, elided_attrs, true)
+    }
+
+    fn print_region(
+        &mut self,
+        region: RegionId,
+        print_entry_block_args: bool,
+        print_block_terminators: bool,
+        print_empty_block: bool
+    ) -> fmt::Result {
+        let arena = self.arena;
+        let blocks = self.check(arena.region(region))?.blocks();
+        self.write_str("{\n")?;
+        if let Some((entry, rest)) = blocks.split_first() {
+            let entry_ref = self.check(arena.block(*entry))?;
+            // Force printing the block header if `print_empty_block` is set and the block is empty or if `print_entry_block_args` is set and there are arguments to print.
+            let always_print_header
+                = (print_empty_block && entry_ref.is_empty())
+                || (print_entry_block_args && !entry_ref.inputs_empty());
+            self.print_block(*entry, always_print_header, print_block_terminators)?;
+            for block in rest {
+                self.print_block(*block, true, true)?;
+            }
+        }
+        self.write_indent()?;
+        self.write_char('}')
+    }
+
+    fn print_generic_op(&mut self, operation: &Operation, print_op_name: bool)
+        -> fmt::Result
+    {
+        if print_op_name {
+            print_escaped_string(self, operation.name().as_str())?;
+        }
+        self.write_char('(')?;
+        self.print_operands(operation.inputs())?;
+        self.write_char(')')?;
+
+        if operation.has_successors() {
+            self.write_char('[')?;
+            for (index, successor) in operation.successors().iter().enumerate() {
+                if index != 0 {
+                    self.write_str(", ")?;
+                }
+                self.print_successor(*successor)?;
+            }
+            self.write_char(']')?;
+        }
+
+        if operation.num_regions() != 0 {
+            self.write_str(" (")?;
+            for (index, region) in operation.regions().iter().enumerate() {
+                if index != 0 {
+                    self.write_str(", ")?;
+                }
+                self.print_region(*region, true, true, true)?;
+            }
+            self.write_char(')')?;
+        }
+
+        self.print_optional_attr_dict(operation.attributes(), &[])?;
+
+        // Print the type signature of the operation.
+        let mut input_types = Vec::with_capacity(operation.num_inputs());
+        for input in operation.inputs() {
+            input_types.push(self.check(input.r#type(self.arena))?.clone());
+        }
+        let output_types: Vec<Arc<dyn Type>>
+            = operation.output_types().cloned().collect();
+        self.write_str(" : ")?;
+        self.print_functional_type(&input_types, &output_types)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{
+        builtins::{attributes::UnitAttribute, types::FloatTypeKind},
+        operation::support::OperationName
+    };
+
+    fn i32() -> Arc<dyn Type> {
+        Arc::new(IntegerType::new(32, Signedness::Signless))
+    }
+
+    fn f32() -> Arc<dyn Type> {
+        Arc::new(FloatType::new(FloatTypeKind::Float32Type))
+    }
+
+    fn integer(r#type: Arc<dyn Type>, value: i64) -> Arc<dyn Attribute> {
+        let width = element_bit_width(r#type.as_ref()) as u32;
+        Arc::new(IntegerAttribute::new(r#type, APInt::new(width, value as u64, true)))
+    }
+
+    fn i64_attribute(value: i64) -> Arc<dyn Attribute> {
+        integer(Arc::new(IntegerType::new(64, Signedness::Signless)), value)
+    }
+
+    fn float(width: u32, bits: u64) -> String {
+        let mut output = String::new();
+        print_float_value(&mut output, &APInt::new(width, bits, false)).unwrap();
+        output
+    }
+
+    fn f32_value(value: f32) -> String {
+        float(32, value.to_bits() as u64)
+    }
+
+    fn f64_value(value: f64) -> String {
+        float(64, value.to_bits())
+    }
+
+    /// Create an operation named `name` and insert it at the end of `block` if given.
+    fn create_op(
+        arena: &mut IRArena,
+        block: Option<BlockId>,
+        name: &str,
+        inputs: Vec<Value>,
+        output_types: Vec<Arc<dyn Type>>,
+        num_regions: usize,
+        successors: &[BlockId]
+    ) -> OpId {
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new(name), output_types, inputs,
+            DictionaryAttribute::default(), successors);
+        let op = arena.create_operation(operation, num_regions).unwrap();
+        if let Some(block) = block {
+            arena.push_back(block, op).unwrap();
+        }
+        op
+    }
+
+    #[test]
+    fn builtin_types_are_printed() {
+        let types: [(Arc<dyn Type>, &str); 12] = [
+            (i32(), "i32"),
+            (Arc::new(IntegerType::new(8, Signedness::Signed)), "si8"),
+            (Arc::new(IntegerType::new(16, Signedness::Unsigned)), "ui16"),
+            (Arc::new(IndexType::new()), "index"),
+            (Arc::new(ComplexType::new(f32())), "complex<f32>"),
+            (Arc::new(TupleType::new(vec![i32(), f32()])), "tuple<i32, f32>"),
+            (Arc::new(FunctionType::new(vec![i32(), i32()], vec![i32()])),
+             "(i32, i32) -> i32"),
+            (Arc::new(FunctionType::new(
+                Vec::new(), vec![Arc::new(FunctionType::new(vec![i32()], vec![i32()]))])),
+             "() -> ((i32) -> i32)"),
+            (Arc::new(RankedTensorType::new(vec![DYNAMIC, 4], f32(), None)), "tensor<?x4xf32>"),
+            (Arc::new(UnrankedTensorType::new(i32())), "tensor<*xi32>"),
+            (Arc::new(MemRefType::new(
+                vec![2, DYNAMIC], f32(),
+                Some(Arc::new(StridedLayoutAttribute::new(DYNAMIC, vec![DYNAMIC, 1]))), None)),
+             "memref<2x?xf32, strided<[?, 1], offset: ?>>"),
+            (Arc::new(MemRefType::new(vec![4], f32(), None, Some(i64_attribute(1)))),
+             "memref<4xf32, 1>")
+        ];
+        for (r#type, expected) in types {
+            assert_eq!(r#type.to_string(), expected);
+        }
+        let r#type: Arc<dyn Type> = Arc::new(UnrankedMemRefType::new(f32(), Some(i64_attribute(2))));
+        assert_eq!(r#type.to_string(), "memref<*xf32, 2>");
+    }
+
+    #[test]
+    fn builtin_attributes_are_printed() {
+        let unit: Arc<dyn Attribute> = Arc::new(UnitAttribute::new());
+        let attributes: [(Arc<dyn Attribute>, &str); 9] = [
+            (integer(i32(), 42), "42 : i32"),
+            (i64_attribute(-3), "-3 : i64"),
+            (integer(Arc::new(IntegerType::new(8, Signedness::Unsigned)), 200), "200 : ui8"),
+            (integer(Arc::new(IntegerType::new(1, Signedness::Signless)), 1), "true"),
+            (Arc::new(StringAttribute::new("a\"b\n")), "\"a\\\"b\\0A\""),
+            (Arc::new(FloatAttribute::from_f64(f32(), 1.5)), "1.500000e+00 : f32"),
+            (Arc::new(ArrayAttribute::new(vec![
+                integer(i32(), 1), Arc::new(StringAttribute::new("x")), unit.clone()])),
+             "[1 : i32, \"x\", unit]"),
+            (Arc::new(DictionaryAttribute::new(vec![
+                NamedAttribute::new(StringAttribute::new("b"), unit),
+                NamedAttribute::new(StringAttribute::new("a"), integer(i32(), 1))])),
+             "{a = 1 : i32, b}"),
+            (Arc::new(DenseArrayAttribute::new(i32(), 3, vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0])),
+             "array<i32: 1, 2, 3>")
+        ];
+        for (attribute, expected) in attributes {
+            assert_eq!(attribute.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn dense_elements_are_nested_by_dimension() {
+        let r#type: Arc<dyn Type> = Arc::new(RankedTensorType::new(vec![2, 2], i32(), None));
+        let data = vec![1, 0, 0, 0, 2, 0, 0, 0, 3, 0, 0, 0, 4, 0, 0, 0];
+        let attribute: Arc<dyn Attribute>
+            = Arc::new(DenseIntOrFPElementsAttribute::new(r#type.clone(), data, false));
+        assert_eq!(attribute.to_string(), "dense<[[1, 2], [3, 4]]> : tensor<2x2xi32>");
+        let splat: Arc<dyn Attribute>
+            = Arc::new(DenseIntOrFPElementsAttribute::new(r#type, vec![7, 0, 0, 0], true));
+        assert_eq!(splat.to_string(), "dense<7> : tensor<2x2xi32>");
+    }
+
+    #[test]
+    fn floats_are_printed_in_exponential_form_if_lossless() {
+        assert_eq!(f64_value(1.5), "1.500000e+00");
+        assert_eq!(f64_value(-42.75), "-4.275000e+01");
+        assert_eq!(f64_value(0.0), "0.000000e+00");
+        assert_eq!(f64_value(-0.0), "-0.000000e+00");
+        assert_eq!(f64_value(1e20), "1.000000e+20");
+        assert_eq!(f64_value(2.5e-300), "2.500000e-300");
+        assert_eq!(f32_value(0.1), "1.000000e-01");
+    }
+
+    #[test]
+    fn floats_needing_more_digits_are_printed_in_full() {
+        assert_eq!(f64_value(0.1 + 0.2), "0.30000000000000004");
+        assert_eq!(f64_value(1.0 / 3.0), "0.33333333333333331");
+        assert_eq!(f64_value(12345678.9), "12345678.9");
+        assert_eq!(f64_value(1.23456789e-4), "1.23456789E-4");
+        assert_eq!(f32_value(1.0 / 3.0), "0.333333343");
+        assert_eq!(f32_value(123456.7), "123456.703");
+    }
+
+    #[test]
+    fn floats_without_a_lossless_decimal_form_are_printed_in_hex() {
+        assert_eq!(f64_value(16777217.0), "0x4170000010000000");
+        assert_eq!(f64_value(f64::INFINITY), "0x7FF0000000000000");
+        assert_eq!(f32_value(f32::NAN), "0x7FC00000");
+        assert_eq!(float(16, 0x3C00), "0x3C00");
+    }
+
+    #[test]
+    fn locations_are_printed() {
+        let file = Location::file_line_col(StringAttribute::new("a.mlir"), 3, 4);
+        let name = Location::name(StringAttribute::new("x"), Location::unknown());
+        assert_eq!(file.to_string(), "\"a.mlir\":3:4");
+        assert_eq!(Location::name(StringAttribute::new("x"), file.clone()).to_string(),
+                   "\"x\"(\"a.mlir\":3:4)");
+        assert_eq!(Location::call_site(name.clone(), file.clone()).to_string(),
+                   "callsite(\"x\" at \"a.mlir\":3:4)");
+        assert_eq!(Location::fused([file, name], None).to_string(),
+                   "fused[\"a.mlir\":3:4, \"x\"]");
+    }
+
+    #[test]
+    fn operations_are_printed_in_generic_form() {
+        let mut arena = IRArena::new();
+        let outer = create_op(&mut arena, None, "test.outer", Vec::new(), Vec::new(), 1, &[]);
+        let region = arena.op(outer).unwrap().region(0);
+        let block = arena.create_block();
+        arena.push_back_block(region, block).unwrap();
+        let input = arena.insert_block_input(block, 0, i32(), Location::unknown()).unwrap();
+        let inner = create_op(
+            &mut arena, Some(block), "test.inner", vec![Value::Input(input)], vec![i32()], 0, &[]);
+        let attribute = NamedAttribute::new(StringAttribute::new("value"), integer(i32(), 42));
+        arena.op_mut(inner).unwrap().set_attr_dictionary(DictionaryAttribute::new(vec![attribute]));
+        create_op(&mut arena, Some(block), "test.term",
+                  vec![Value::Output(Output::new(inner, 0))], Vec::new(), 0, &[]);
+
+        assert_eq!(print_operation(&arena, outer, PrintingFlags::new()).unwrap(),
+"\"test.outer\"() ({
+^bb0(%arg0: i32):
+  %0 = \"test.inner\"(%arg0) {value = 42 : i32} : (i32) -> i32
+  \"test.term\"(%0) : (i32) -> ()
+}) : () -> ()
+");
+        // Values are numbered from the top-level operation unless local scope is requested.
+        assert_eq!(print_operation(&arena, inner, PrintingFlags::new()).unwrap(),
+                   "%0 = \"test.inner\"(%arg0) {value = 42 : i32} : (i32) -> i32\n");
+    }
+
+    #[test]
+    fn successors_and_output_groups_are_printed() {
+        let mut arena = IRArena::new();
+        let outer = create_op(&mut arena, None, "test.outer", Vec::new(), Vec::new(), 1, &[]);
+        let region = arena.op(outer).unwrap().region(0);
+        let entry = arena.create_block();
+        let exit = arena.create_block();
+        arena.push_back_block(region, entry).unwrap();
+        arena.push_back_block(region, exit).unwrap();
+        let input = arena.insert_block_input(exit, 0, i32(), Location::unknown()).unwrap();
+
+        let pair = create_op(
+            &mut arena, Some(entry), "test.pair", Vec::new(), vec![i32(), i32()], 0, &[]);
+        create_op(&mut arena, Some(entry), "test.br",
+                  vec![Value::Output(Output::new(pair, 1))], Vec::new(), 0, &[exit]);
+        create_op(&mut arena, Some(exit), "test.ret",
+                  vec![Value::Input(input), Value::Output(Output::new(pair, 0))],
+                  Vec::new(), 0, &[]);
+
+        assert_eq!(print_operation(&arena, outer, PrintingFlags::new()).unwrap(),
+"\"test.outer\"() ({
+  %0:2 = \"test.pair\"() : () -> (i32, i32)
+  \"test.br\"(%0#1)[^bb1] : (i32) -> ()
+^bb1(%1: i32):  // pred: ^bb0
+  \"test.ret\"(%1, %0#0) : (i32, i32) -> ()
+}) : () -> ()
+");
+    }
+
+    #[test]
+    fn locations_are_printed_with_debug_info() {
+        let mut arena = IRArena::new();
+        let op = create_op(&mut arena, None, "test.op", Vec::new(), Vec::new(), 0, &[]);
+        arena.op_mut(op).unwrap()
+            .set_location(Location::file_line_col(StringAttribute::new("a.mlir"), 3, 4));
+        let flags = PrintingFlags::new().enable_debug_info(true, false);
+        assert_eq!(print_operation(&arena, op, flags).unwrap(),
+                   "\"test.op\"() : () -> () loc(\"a.mlir\":3:4)\n");
+        let flags = PrintingFlags::new().enable_debug_info(true, true);
+        assert_eq!(print_operation(&arena, op, flags).unwrap(),
+                   "\"test.op\"() : () -> () loc(a.mlir:3:4)\n");
+    }
+}
@@ -4,50 +4,207 @@
 //!
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/AsmState.h>
 
-use llvm::adt::{
-    dense_map::DenseMap,
-    map_vector::MapVector,
-    string_map::StringMap,
+use core::fmt::{self, Write};
+use std::{
+    collections::HashMap,
+    error::Error,
+    ops::Range,
+    sync::Arc
 };
+
 use crate::{
+    bytecode::implementation::BytecodeDialectInterface,
     ir::{
+        arena::OpId,
+        dialect::implementation::DialectTypeParser,
+        dialect_resource_blob_manager::ResourceBlobManagerDialectInterface,
         mlir_context::MLIRContext,
-        operation::support
+        operation::{
+            Operation,
+            implementation::{
+                AsmDialectResourceHandle, OpAsmDialectInterface,
+                print_escaped_string
+            },
+            support::{self, PrintingFlags}
+        }
     },
-    support::llvm,
+    support::logical_result::LogicalResult
 };
 
-/// This class represents a processed binary blob of data. A resource blob is essentially a collection of data, potentially mutable, with an associated deleter function (used if the data needs to be destroyed).
+/// A buffer of resource data shared between resource blobs, such as a heap allocation or a memory mapped file. The buffer is released once the last blob referencing it is dropped.
+pub type SharedResourceBuffer = Arc<dyn AsRef<[u8]> + Send + Sync>;
+
+/// This class represents a processed binary blob of data. A resource blob is essentially a collection of data, potentially mutable, with an associated alignment. Copies of a blob share the underlying data.
+#[derive(Clone)]
 pub struct AsmResourceBlob {
-    /// The raw, properly aligned, blob data.
-    data: &'static [u8],
+    /// The buffer holding the raw blob data.
+    buffer: SharedResourceBuffer,
+
+    /// The range of `buffer` containing the blob data.
+    range: Range<usize>,
 
     /// The alignment of the data.
     data_alignment: usize,  // = 0
 
-    /// An optional deleter function used to deallocate the underlying data when
-    /// necessary.
-    deleter: DeleterFn,
-
     /// Whether the data is mutable.
     data_is_mutable: bool
 }
 
-type DeleterFn = fn(Option<()>, usize, usize);  // unique_function
+impl AsmResourceBlob {
+    pub fn new(
+        data: impl AsRef<[u8]> + Send + Sync + 'static,
+        data_alignment: usize,
+        data_is_mutable: bool
+    ) -> Self {
+        let range = 0..data.as_ref().len();
+        Self::from_shared(Arc::new(data), range, data_alignment, data_is_mutable)
+    }
+
+    /// Create a blob referencing the given range of a shared buffer, without copying it.
+    pub fn from_shared(
+        buffer: SharedResourceBuffer,
+        range: Range<usize>,
+        data_alignment: usize,
+        data_is_mutable: bool
+    ) -> Self {
+        assert!(range.end <= (*buffer).as_ref().len(),
+            "blob range exceeds the size of the buffer");
+        Self { buffer, range, data_alignment, data_is_mutable }
+    }
+
+    /// Return the raw underlying data of this blob.
+    pub fn data(&self) -> &[u8] {
+        &(*self.buffer).as_ref()[self.range.clone()]
+    }
+
+    /// Return the alignment of the underlying data.
+    pub fn data_alignment(&self) -> usize {
+        self.data_alignment
+    }
+
+    /// Return if the data of this blob is mutable.
+    pub fn is_mutable(&self) -> bool {
+        self.data_is_mutable
+    }
+}
+
+impl fmt::Debug for AsmResourceBlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsmResourceBlob")
+            .field("size", &self.range.len())
+            .field("data_alignment", &self.data_alignment)
+            .field("data_is_mutable", &self.data_is_mutable)
+            .finish()
+    }
+}
 
 /// This class provides a simple utility wrapper for creating heap allocated AsmResourceBlobs.
-pub struct HeapAsmResourceBlob {
+pub struct HeapAsmResourceBlob;
+
+impl HeapAsmResourceBlob {
+    /// Create a new heap allocated blob holding a copy of `data`.
+    pub fn allocate_and_copy(
+        data: &[u8],
+        alignment: usize,
+        data_is_mutable: bool  // = true
+    ) -> AsmResourceBlob {
+        AsmResourceBlob::new(data.to_vec(), alignment, data_is_mutable)
+    }
 }
 
 /// This class provides a simple utility wrapper for creating 'unmanaged' AsmResourceBlobs. The lifetime of the data provided to these blobs is guaranteed to persist beyond the lifetime of this reference.
-pub struct UnmanagedAsmResourceBlob {
+pub struct UnmanagedAsmResourceBlob;
+
+impl UnmanagedAsmResourceBlob {
+    /// Create a new unmanaged resource directly referencing the provided data. `data_is_mutable` indicates if the allocated data can be mutated.
+    pub fn allocate_with_align(
+        data: &'static [u8],
+        alignment: usize,
+        data_is_mutable: bool  // = false
+    ) -> AsmResourceBlob {
+        AsmResourceBlob::new(data, alignment, data_is_mutable)
+    }
 }
 
 /// This class is used to build resource entries for use by the printer. Each resource entry is represented using a key/value pair. The provided key must be unique within the current context, which allows for a client to provide resource entries without worrying about overlap with other clients.
 pub struct AsmResourceBuilder {
+    /// The entries built so far, as keys paired with their values.
+    entries: Vec<(String, AsmResourceValue)>
+}
+
+impl AsmResourceBuilder {
+    pub fn new() -> Self {
+        Self { entries: Vec::new() }
+    }
+
+    /// Build a resource entry represented by the given bool.
+    pub fn build_bool(&mut self, key: &str, data: bool) {
+        self.entries.push((key.to_string(), AsmResourceValue::Bool(data)));
+    }
+
+    /// Build a resource entry represented by the given human-readable string value.
+    pub fn build_string(&mut self, key: &str, data: &str) {
+        self.entries.push(
+            (key.to_string(), AsmResourceValue::String(data.to_string())));
+    }
+
+    /// Build a resource entry represented by the given binary blob data.
+    pub fn build_blob(&mut self, key: &str, data: &[u8], data_alignment: u32) {
+        let blob = HeapAsmResourceBlob::allocate_and_copy(
+            data, data_alignment as usize, false);
+        self.entries.push((key.to_string(), AsmResourceValue::Blob(blob)));
+    }
+
+    /// Build a resource entry represented by the given resource blob.
+    pub fn build_resource_blob(&mut self, key: &str, blob: &AsmResourceBlob) {
+        self.entries.push((key.to_string(), AsmResourceValue::Blob(blob.clone())));
+    }
+
+    /// Return the entries built so far, as keys paired with their values.
+    pub fn entries(&self) -> &[(String, AsmResourceValue)] {
+        &self.entries
+    }
+}
+
+/// The value of a resource entry, whose variants align 1-1 with the kinds defined in AsmResourceEntryKind.
+#[derive(Clone, Debug)]
+pub enum AsmResourceValue {
+    Blob(AsmResourceBlob),
+    Bool(bool),
+    String(String)
+}
+
+impl AsmResourceValue {
+    /// Return the kind of this value.
+    pub fn kind(&self) -> AsmResourceEntryKind {
+        match self {
+            Self::Blob(_) => AsmResourceEntryKind::Blob,
+            Self::Bool(_) => AsmResourceEntryKind::Bool,
+            Self::String(_) => AsmResourceEntryKind::String
+        }
+    }
+}
+
+/// Prints the value as it is spelled in the textual format. Blobs are printed as a hex string prefixed by their alignment, a little-endian `u32`.
+impl fmt::Display for AsmResourceValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Blob(blob) => {
+                f.write_str("\"0x")?;
+                let alignment = (blob.data_alignment() as u32).to_le_bytes();
+                for byte in alignment.iter().chain(blob.data()) {
+                    write!(f, "{:02X}", byte)?;
+                }
+                f.write_char('"')
+            }
+            Self::Bool(value) => write!(f, "{}", value),
+            Self::String(value) => print_escaped_string(f, value)
+        }
+    }
 }
 
 /// This enum represents the different kinds of resource values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AsmResourceEntryKind {
     /// A blob of data with an accompanying alignment.
     Blob,
@@ -59,134 +216,510 @@ pub enum AsmResourceEntryKind {
 
 /// This class represents a single parsed resource entry.
 pub struct AsmParsedResourceEntry {
+    /// The key of the resource entry.
+    key: String,
+    /// The parsed value. Blobs are spelled as hex strings in the textual format, so they are held as strings until parsed.
+    value: ParsedResourceValue
+}
+
+enum ParsedResourceValue {
+    Blob(AsmResourceBlob),
+    Bool(bool),
+    String(String),
+    /// A string literal of the textual format, which holds a hex blob if it starts with `0x`.
+    Literal(String)
+}
+
+impl AsmParsedResourceEntry {
+    pub(crate) fn from_bool(key: impl Into<String>, value: bool) -> Self {
+        Self { key: key.into(), value: ParsedResourceValue::Bool(value) }
+    }
+
+    /// Create an entry from a string literal of the textual format.
+    pub(crate) fn from_literal(key: impl Into<String>, value: String) -> Self {
+        Self { key: key.into(), value: ParsedResourceValue::Literal(value) }
+    }
+
+    pub(crate) fn from_string(key: impl Into<String>, value: String) -> Self {
+        Self { key: key.into(), value: ParsedResourceValue::String(value) }
+    }
+
+    pub(crate) fn from_blob(key: impl Into<String>, value: AsmResourceBlob) -> Self {
+        Self { key: key.into(), value: ParsedResourceValue::Blob(value) }
+    }
+
+    /// Return the key of the resource entry.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Return the kind of this value.
+    pub fn kind(&self) -> AsmResourceEntryKind {
+        match &self.value {
+            ParsedResourceValue::Blob(_) => AsmResourceEntryKind::Blob,
+            ParsedResourceValue::Bool(_) => AsmResourceEntryKind::Bool,
+            ParsedResourceValue::Literal(value) if value.starts_with("0x")
+                => AsmResourceEntryKind::Blob,
+            ParsedResourceValue::String(_) | ParsedResourceValue::Literal(_)
+                => AsmResourceEntryKind::String
+        }
+    }
+
+    /// Parse the resource entry represented by a boolean.
+    pub fn parse_as_bool(&self) -> Result<bool, Box<dyn Error>> {
+        match &self.value {
+            ParsedResourceValue::Bool(value) => Ok(*value),
+            _ => Err("expected a bool resource entry".into())
+        }
+    }
+
+    /// Parse the resource entry represented by a human-readable string.
+    pub fn parse_as_string(&self) -> Result<String, Box<dyn Error>> {
+        match &self.value {
+            ParsedResourceValue::String(value) | ParsedResourceValue::Literal(value)
+                => Ok(value.clone()),
+            _ => Err("expected a string resource entry".into())
+        }
+    }
+
+    /// Parse the resource entry represented by a binary blob. In the textual format, blobs are spelled as a hex string whose first four bytes are the alignment of the data as a little-endian `u32`.
+    pub fn parse_as_blob(&self) -> Result<AsmResourceBlob, Box<dyn Error>> {
+        let hex = match &self.value {
+            ParsedResourceValue::Blob(blob) => return Ok(blob.clone()),
+            ParsedResourceValue::Literal(value) => value.strip_prefix("0x"),
+            _ => None
+        }.ok_or("expected a hex string resource entry")?;
+        if hex.len() % 2 != 0 {
+            return Err("expected hex string to have an even number of digits".into());
+        }
+        let bytes = (0..hex.len()).step_by(2)
+            .map(|index| u8::from_str_radix(&hex[index..index + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| "expected a valid hex string")?;
+        if bytes.len() < 4 {
+            return Err("expected hex string blob to have a leading 4-byte alignment".into());
+        }
+        let alignment = u32::from_le_bytes(bytes[..4].try_into().unwrap());
+        if !alignment.is_power_of_two() {
+            return Err(format!(
+                "expected hex string blob for key '{}' to encode alignment in first 4 bytes, but got non-power-of-2 value: {}",
+                self.key, alignment).into());
+        }
+        Ok(HeapAsmResourceBlob::allocate_and_copy(
+            &bytes[4..], alignment as usize, true))
+    }
 }
 
 /// This class represents an instance of a resource parser. This class should be implemented by non-dialect clients that want to inject additional resources into MLIR assembly formats.
 pub struct AsmResourceParser {
-    name: String
+    name: String,
+    parse_resource_fn: Box<dyn Fn(&AsmParsedResourceEntry) -> LogicalResult>
+}
+
+impl AsmResourceParser {
+    /// Return a resource parser implemented via the given callable, whose form should match that of `parse_resource`.
+    pub fn from_callable(
+        name: impl Into<String>,
+        parse_resource_fn: impl Fn(&AsmParsedResourceEntry) -> LogicalResult + 'static
+    ) -> Self {
+        Self {
+            name: name.into(),
+            parse_resource_fn: Box::new(parse_resource_fn)
+        }
+    }
+
+    /// Return the name of this parser, which keys its entries in the `external_resources` section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Parse the given resource entry. Returns failure if the key/data were not valid, or could otherwise not be processed correctly. Any necessary errors should be emitted with the provided entry.
+    pub fn parse_resource(&self, entry: &AsmParsedResourceEntry)
+        -> LogicalResult
+    {
+        (self.parse_resource_fn)(entry)
+    }
 }
 
 /// This class represents an instance of a resource printer. This class should be implemented by non-dialect clients that want to inject additional resources into MLIR assembly formats.
 pub struct AsmResourcePrinter {
-    name: String
+    name: String,
+    build_resources_fn: Box<dyn Fn(&Operation, &mut AsmResourceBuilder)>
+}
+
+impl AsmResourcePrinter {
+    /// Return a resource printer implemented via the given callable, whose form should match that of `build_resources`.
+    pub fn from_callable(
+        name: impl Into<String>,
+        build_resources_fn: impl Fn(&Operation, &mut AsmResourceBuilder) + 'static
+    ) -> Self {
+        Self {
+            name: name.into(),
+            build_resources_fn: Box::new(build_resources_fn)
+        }
+    }
+
+    /// Return the name of this resource printer, which keys its entries in the `external_resources` section.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Build any resources to include during printing, utilising the given top-level root operation to help determine what information to include.
+    pub fn build_resources(
+        &self,
+        operation: &Operation,
+        builder: &mut AsmResourceBuilder
+    ) {
+        (self.build_resources_fn)(operation, builder)
+    }
 }
 
 /// A fallback map containing external resources not explicitly handled by another parser/printer.
+#[derive(Default)]
 pub struct FallbackAsmResourceMap {
-    /// The set of opaque resources.
-    key_to_resources: MapVector<
-        String, Box<ResourceCollection>,
-        StringMap<usize>
-    >
+    /// The set of opaque resources, grouped by the name of the section they were parsed from.
+    key_to_resources: Vec<(String, ResourceCollection)>
+}
+
+impl FallbackAsmResourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `entry`, parsed from the resource group `name`, as an opaque resource.
+    pub fn parse_resource(
+        &mut self,
+        name: &str,
+        entry: &AsmParsedResourceEntry
+    ) -> LogicalResult {
+        let value = match entry.kind() {
+            AsmResourceEntryKind::Blob => AsmResourceValue::Blob(entry.parse_as_blob()?),
+            AsmResourceEntryKind::Bool => AsmResourceValue::Bool(entry.parse_as_bool()?),
+            AsmResourceEntryKind::String =>
+                AsmResourceValue::String(entry.parse_as_string()?)
+        };
+        let index = match self.key_to_resources.iter()
+            .position(|(key, _)| key == name)
+        {
+            Some(index) => index,
+            None => {
+                self.key_to_resources.push(
+                    (name.to_string(), ResourceCollection::default()));
+                self.key_to_resources.len() - 1
+            }
+        };
+        self.key_to_resources[index].1.resources.push(OpaqueAsmResource {
+            key: entry.key().to_string(),
+            value
+        });
+        Ok(())
+    }
+
+    /// Build a set of resource printers to print the resources within this map.
+    pub fn printers(&self) -> Vec<AsmResourcePrinter> {
+        self.key_to_resources.iter()
+            .map(|(name, collection)| {
+                let resources = collection.resources.clone();
+                AsmResourcePrinter::from_callable(
+                    name.clone(),
+                    move |_, builder| for resource in &resources {
+                        match &resource.value {
+                            AsmResourceValue::Blob(blob) =>
+                                builder.build_resource_blob(&resource.key, blob),
+                            AsmResourceValue::Bool(value) =>
+                                builder.build_bool(&resource.key, *value),
+                            AsmResourceValue::String(value) =>
+                                builder.build_string(&resource.key, value)
+                        }
+                    })
+            })
+            .collect()
+    }
 }
 
 /// This class represents an opaque resource.
+#[derive(Clone)]
 struct OpaqueAsmResource {
     /// The key identifying the resource.
     key: String,
-    /// An opaque value for the resource, whose variant values align 1-1 with the kinds defined in AsmResourceEntryKind.
-    value: OpaqueAsmResourceValue
-}
-
-enum OpaqueAsmResourceValue {
-    AsmResourceBlob(AsmResourceBlob),
-    Bool(bool),
-    String(String)
+    /// An opaque value for the resource.
+    value: AsmResourceValue
 }
 
+#[derive(Default)]
 struct ResourceCollection {
     /// The set of resources parsed into this collection.
     resources: Vec<OpaqueAsmResource>
 }
 
 /// This class represents a configuration for the MLIR assembly parser. It contains all of the necessary state to parse a MLIR source file.
-pub struct ParserConfig {
-    context: *mut MLIRContext,
+pub struct ParserConfig<'a> {
+    context: &'a mut MLIRContext,
     verify_after_parse: bool,
-    resource_parsers: DenseMap<&'static str, Box<AsmResourceParser>>,
-    fallback_resource_map: *mut FallbackAsmResourceMap
+    /// The OpAsm interfaces of the dialects whose resources may be parsed, keyed by dialect namespace.
+    dialect_interfaces: HashMap<String, Box<dyn OpAsmDialectInterface>>,
+    /// The bytecode interfaces of the dialects whose attributes and types may be read from bytecode, keyed by dialect namespace. The builtin dialect is always available.
+    bytecode_interfaces: HashMap<String, Arc<dyn BytecodeDialectInterface>>,
+    resource_parsers: HashMap<String, AsmResourceParser>,
+    /// The hooks parsing the types of dialects, keyed by dialect namespace. Types of dialects without a hook are parsed as opaque types.
+    type_parsers: HashMap<String, DialectTypeParser>,
+    fallback_resource_map: Option<&'a mut FallbackAsmResourceMap>
+}
+
+impl<'a> ParserConfig<'a> {
+    /// Construct a parser configuration with the given context.
+    /// `verify_after_parse` indicates if the IR should be verified after parsing.
+    /// `fallback_resource_map` is an optional fallback handler that can be used to parse external resources not explicitly handled by another parser.
+    /// The resources of the builtin dialect are parsed into the blob manager of `context`.
+    pub fn new(
+        context: &'a mut MLIRContext,
+        verify_after_parse: bool,  // = true
+        fallback_resource_map: Option<&'a mut FallbackAsmResourceMap>
+    ) -> Self {
+        let builtin_interface: Box<dyn OpAsmDialectInterface>
+            = Box::new(ResourceBlobManagerDialectInterface::builtin(context));
+        Self {
+            context,
+            verify_after_parse,
+            dialect_interfaces: HashMap::from([
+                ("builtin".to_string(), builtin_interface)
+            ]),
+            bytecode_interfaces: HashMap::new(),
+            resource_parsers: HashMap::new(),
+            type_parsers: HashMap::new(),
+            fallback_resource_map
+        }
+    }
+
+    /// Return the MLIRContext to be used when parsing.
+    pub fn context(&self) -> &MLIRContext {
+        self.context
+    }
+
+    pub fn context_mut(&mut self) -> &mut MLIRContext {
+        self.context
+    }
+
+    /// Returns if the parser should verify the IR after parsing.
+    pub fn should_verify_after_parse(&self) -> bool {
+        self.verify_after_parse
+    }
+
+    /// Attach the OpAsm interface of a dialect, used to parse the entries of that dialect in the `dialect_resources` section and to resolve its `dense_resource` handles.
+    pub fn attach_dialect_interface(
+        &mut self,
+        interface: Box<dyn OpAsmDialectInterface>
+    ) {
+        self.dialect_interfaces.insert(interface.dialect().to_string(), interface);
+    }
+
+    /// Return the OpAsm interface attached for the dialect `namespace`, if any.
+    pub fn dialect_interface(&self, namespace: &str)
+        -> Option<&dyn OpAsmDialectInterface>
+    {
+        self.dialect_interfaces.get(namespace).map(|interface| interface.as_ref())
+    }
+
+    /// Attach the bytecode interface of a dialect, used to read the attributes and types of that dialect encoded in bytecode.
+    pub fn attach_bytecode_interface(
+        &mut self,
+        interface: Box<dyn BytecodeDialectInterface>
+    ) {
+        self.bytecode_interfaces.insert(
+            interface.dialect().to_string(), Arc::from(interface));
+    }
+
+    /// Return the bytecode interface attached for the dialect `namespace`, if any.
+    pub fn bytecode_interface(&self, namespace: &str)
+        -> Option<Arc<dyn BytecodeDialectInterface>>
+    {
+        self.bytecode_interfaces.get(namespace).cloned()
+    }
+
+    /// Return the resource parser registered to the given name, if any.
+    pub fn resource_parser(&self, name: &str) -> Option<&AsmResourceParser> {
+        self.resource_parsers.get(name)
+    }
+
+    /// Attach the given resource parser.
+    pub fn attach_resource_parser(&mut self, parser: AsmResourceParser) {
+        let name = parser.name().to_string();
+        assert!(!self.resource_parsers.contains_key(&name),
+            "resource parser already registered with the given name");
+        self.resource_parsers.insert(name, parser);
+    }
+
+    /// Attach the hook parsing the types of the dialect `namespace`.
+    pub fn attach_type_parser(
+        &mut self,
+        namespace: impl Into<String>,
+        parser: DialectTypeParser
+    ) {
+        self.type_parsers.insert(namespace.into(), parser);
+    }
+
+    /// Return the hook parsing the types of the dialect `namespace`, if any.
+    pub fn type_parser(&self, namespace: &str) -> Option<DialectTypeParser> {
+        self.type_parsers.get(namespace).cloned()
+    }
+
+    /// Return the fallback map used for external resources without a registered parser, if any.
+    pub fn fallback_resource_map(&mut self)
+        -> Option<&mut FallbackAsmResourceMap>
+    {
+        self.fallback_resource_map.as_deref_mut()
+    }
 }
 
 /// This class provides management for the lifetime of the state used when printing the IR. It allows for alleviating the cost of recomputing the internal state of the asm printer.
 ///
 /// The IR should not be mutated in-between invocations using this state, and the IR being printed must not be a parent of the IR originally used to initialise this state. This means that if a child operation is provided, a parent operation cannot reuse this state.
 pub struct AsmState {
-    // 
-    // AsmStateImpl (https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/AsmPrinter.cpp#L1686)
-    //
- 
-    /// Collection of OpAsm interfaces implemented in the context.
-    interfaces: DialectInterfaceCollection<OpAsmDialectInterface>,
+    /// The OpAsm interfaces of the dialects whose resources may be printed, keyed by dialect namespace.
+    interfaces: HashMap<String, Box<dyn OpAsmDialectInterface>>,
 
     /// A collection of non-dialect resource printers.
-    external_resource_printers: SmallVector<std::unique_ptr<AsmResourcePrinter>>,
-
-    /// A set of dialect resources that were referenced during printing.
-    dialect_resources: DenseMap<*mut Dialect, SetVector<AsmDialectResourceHandle>>,
+    external_resource_printers: Vec<AsmResourcePrinter>,
 
-    /// The state used for attribute and type aliases.
-    alias_state: AliasState,
-
-    /// The state used for SSA value names.
-    name_state: SSANameState,
+    /// The dialect resources that were referenced during printing, grouped by dialect namespace in the order they were first referenced.
+    dialect_resources: Vec<(String, Vec<AsmDialectResourceHandle>)>,
 
     /// Flags that control op output.
-    printer_flags: OpPrintingFlags,
+    printer_flags: PrintingFlags,
 
     /// An optional location map to be populated.
-    location_map: *mut AsmState::LocationMap
+    location_map: Option<LocationMap>,
+
+    /// The storages of the attributes and types currently being printed, used to print a self-reference by its identifying parameters only.
+    cyclic_printing_stack: Vec<*const ()>
 }
 
+/**
+This map represents the raw locations of operations within the output stream. This maps the handle of the operation, to a pair of line and column in the output stream.
+*/
+pub type LocationMap = HashMap<OpId, (usize, usize)>;
+
 impl AsmState {
-    /**
-    This map represents the raw locations of operations within the output
-    stream. This maps the original pointer to the operation, to a pair of line
-    and column in the output stream.
-    */
-    using LocationMap = DenseMap<*mut Operation, (usize, usize)>;
-
-    /// Initialize the asm state at the level of the given operation. A location
-    /// map may optionally be provided to be populated when printing. `map` is an
-    /// optional fallback resource map, which when provided will attach resource
-    /// printers for the fallback resources within the map.
-    AsmState(Operation *op,
-            const OpPrintingFlags &printerFlags = OpPrintingFlags(),
-            LocationMap *locationMap = nullptr,
-            FallbackAsmResourceMap *map = nullptr);
-    AsmState(MLIRContext *ctx,
-            const OpPrintingFlags &printerFlags = OpPrintingFlags(),
-            LocationMap *locationMap = nullptr,
-            FallbackAsmResourceMap *map = nullptr);
-    ~AsmState();
+    pub fn new(printer_flags: PrintingFlags) -> Self {
+        Self {
+            interfaces: HashMap::new(),
+            external_resource_printers: Vec::new(),
+            dialect_resources: Vec::new(),
+            printer_flags,
+            location_map: None,
+            cyclic_printing_stack: Vec::new()
+        }
+    }
+
+    /// Request the location of each printed operation to be recorded in a location map, available through `location_map` after printing.
+    pub fn with_location_map(mut self) -> Self {
+        self.location_map = Some(LocationMap::new());
+        self
+    }
 
     /// Get the printer flags.
-    const OpPrintingFlags &getPrinterFlags() const;
+    pub fn printer_flags(&self) -> &PrintingFlags {
+        &self.printer_flags
+    }
 
-    // --------------------------------------------------------------------===//
-    // Resources
-    // --------------------------------------------------------------------===//
+    /* Resources */
 
     /// Attach the given resource printer to the AsmState.
-    void attach_resource_printer(std::unique_ptr<AsmResourcePrinter> printer);
+    pub fn attach_resource_printer(&mut self, printer: AsmResourcePrinter) {
+        self.external_resource_printers.push(printer);
+    }
 
-    /// Attach an resource printer, in the form of a callable, to the AsmState.
-    template <typename CallableT>
-    std::enable_if_t<std::is_convertible<
-        CallableT, function_ref<void(Operation *, AsmResourceBuilder &)>>::value>
-    attach_resource_printer(StringRef name, CallableT &&printFn) {
-        attach_resource_printer(AsmResourcePrinter::fromCallable(
-            name, std::forward<CallableT>(printFn)));
+    /// Attach the OpAsm interface of a dialect, used to print the resources of that dialect referenced during printing.
+    pub fn attach_dialect_interface(
+        &mut self,
+        interface: Box<dyn OpAsmDialectInterface>
+    ) {
+        self.interfaces.insert(interface.dialect().to_string(), interface);
     }
 
-    /// Attach resource printers to the AsmState for the fallback resources
-    /// in the given map.
-    void attach_fallback_resource_printer(FallbackAsmResourceMap &map) {
-        for (auto &printer : map.getPrinters())
-            attach_resource_printer(std::move(printer));
+    /// Returns the dialect resources that were referenced when using this state to print IR, grouped by dialect namespace.
+    pub fn dialect_resources(&self)
+        -> &[(String, Vec<AsmDialectResourceHandle>)]
+    {
+        &self.dialect_resources
     }
 
-    /// Returns a map of dialect resources that were referenced when using this
-    /// state to print IR.
-    DenseMap<Dialect *, SetVector<AsmDialectResourceHandle>> &
-    get_dialect_resources() const;
+    /// Record that `handle` was referenced during printing.
+    pub(crate) fn record_dialect_resource(
+        &mut self,
+        handle: &AsmDialectResourceHandle
+    ) {
+        let index = match self.dialect_resources.iter()
+            .position(|(dialect, _)| dialect == handle.dialect())
+        {
+            Some(index) => index,
+            None => {
+                self.dialect_resources.push(
+                    (handle.dialect().to_string(), Vec::new()));
+                self.dialect_resources.len() - 1
+            }
+        };
+        let handles = &mut self.dialect_resources[index].1;
+        if !handles.contains(handle) {
+            handles.push(handle.clone());
+        }
+    }
+
+    /// Build the entries of the `dialect_resources` section for the resources referenced while printing `top_level_op`. Dialects without an attached interface are skipped.
+    pub(crate) fn build_dialect_resources(&self, top_level_op: &Operation)
+        -> Vec<(String, AsmResourceBuilder)>
+    {
+        self.dialect_resources.iter()
+            .filter_map(|(dialect, handles)| {
+                let interface = self.interfaces.get(dialect)?;
+                let mut builder = AsmResourceBuilder::new();
+                interface.build_resources(top_level_op, handles, &mut builder);
+                Some((dialect.clone(), builder))
+            })
+            .collect()
+    }
+
+    /// Build the entries of the `external_resources` section.
+    pub(crate) fn build_external_resources(&self, top_level_op: &Operation)
+        -> Vec<(String, AsmResourceBuilder)>
+    {
+        self.external_resource_printers.iter()
+            .map(|printer| {
+                let mut builder = AsmResourceBuilder::new();
+                printer.build_resources(top_level_op, &mut builder);
+                (printer.name().to_string(), builder)
+            })
+            .collect()
+    }
+
+    /// Push `id` onto the stack of attributes and types being printed, returning false if it is already on the stack.
+    pub(crate) fn push_cyclic_printing(&mut self, id: *const ()) -> bool {
+        if self.cyclic_printing_stack.contains(&id) {
+            return false;
+        }
+        self.cyclic_printing_stack.push(id);
+        true
+    }
+
+    /// Pop the last attribute or type pushed by `push_cyclic_printing`.
+    pub(crate) fn pop_cyclic_printing(&mut self) {
+        self.cyclic_printing_stack.pop();
+    }
+
+    /// Returns the location map populated during printing, if one was requested.
+    pub fn location_map(&self) -> Option<&LocationMap> {
+        self.location_map.as_ref()
+    }
+
+    /// Record that `operation` was printed starting at `line` and `column`, if a location map was requested.
+    pub(crate) fn record_location(
+        &mut self,
+        operation: OpId,
+        line: usize,
+        column: usize
+    ) {
+        if let Some(location_map) = &mut self.location_map {
+            location_map.insert(operation, (line, column));
+        }
+    }
 }
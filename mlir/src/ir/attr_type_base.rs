@@ -4,7 +4,15 @@
 //!
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/AttrTypeBase.td>
 
-use crate::mlir::ir::operation::base;
+use core::fmt;
+
+use crate::mlir::{
+    asm_parser::parser::ParseResult,
+    ir::{
+        dialect::implementation::{DialectAsmParser, DialectAsmPrinter},
+        operation::base
+    }
+};
 
 // -------------------------------------------------------------------------
 // AttrTrait definitions
@@ -72,10 +80,22 @@ pub trait AttrOrTypeBuilder {
 Definitions
 */
 
-/// Define a new attribute or type, named `name`, that inherits from the given C++ base class.
-pub trait AttrOrTypeDefinition {
+/**
+Define a new attribute or type of a dialect, identified by its mnemonic and parsed and printed in the custom assembly form `#dialect.mnemonic<...>` or `!dialect.mnemonic<...>`. Usually derived from a declarative assembly format with `#[derive(AttrOrTypeDef)]`, otherwise implemented by hand.
+*/
+pub trait AttrOrTypeDefinition: Sized {
+    /// The name of the attribute or type, following the namespace of its dialect.
+    const MNEMONIC: &'static str;
+
+    /// Parse the parameters of the attribute or type, following its mnemonic.
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self>;
+
+    /// Print the parameters of the attribute or type, following its mnemonic.
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result;
 }
 
+pub use mlir_macros::AttrOrTypeDef;
+
 /*
 Parameters
 */
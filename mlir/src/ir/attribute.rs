@@ -14,6 +14,7 @@ use core::{
     fmt::Display,
     hash::Hash
 };
+use std::sync::Arc;
 use llvm::{
     adt::folding_set::FoldingSet,
     support::pointer_like_type_traits
@@ -27,12 +28,17 @@ use crate::{
     support::interface_support::{Interface, InterfaceMap},
 };
 
-pub type HasTraitFn = Box<dyn Fn(TypeId) -> bool>;  // unique_function;
+pub type HasTraitFn = Box<dyn Fn(TypeId) -> bool + Send + Sync>;  // unique_function;
 
 /// Attributes are known-constant values of operations.
 ///
 /// Instances of the Attribute class are references to immortal key-value pairs with immutable, uniqued keys owned by MLIRContext. As such, an Attribute is a thin wrapper around an underlying storage pointer. Attributes are usually passed by value.
-pub trait Attribute: Any {
+pub trait Attribute: Any + Send + Sync {
+    /// Return the dialect this attribute is registered to, or None for builtin attributes, which the printer and parser handle directly.
+    fn dialect(&self) -> Option<&'static dyn Dialect> {
+        None
+    }
+
     /*
     ====================================================================
     AbstractAttribute
@@ -82,24 +88,23 @@ pub trait Attribute: Any {
 // Display + Hash + PartialEq
 
 /// NamedAttribute represents a combination of a name and an Attribute value.
-#[derive(PartialEq)]
+#[derive(Clone)]
 pub struct NamedAttribute {
-    /// The name of the attribute. This is represented as a StringAttribute, but type-erased to Attribute in the field.
-    name: Box<dyn Attribute>,
+    /// The name of the attribute.
+    name: StringAttribute,
     /// The value of the attribute.
-    value: Box<dyn Attribute>
+    value: Arc<dyn Attribute>
 }
 
 impl NamedAttribute {
-    pub fn new(name: impl Attribute, value: impl Attribute) -> Self {
-        assert!(name && value, "Expected valid attribute name and value");
-        assert!(name.len() != 0, "Expected valid attribute name");
+    pub fn new(name: StringAttribute, value: Arc<dyn Attribute>) -> Self {
+        assert!(!name.value().is_empty(), "Expected valid attribute name");
         Self { name, value }
     }
 
     /// Return the name of the attribute.
-    pub const fn name(&self) -> StringAttribute {
-        self.name.cast::<StringAttribute>()
+    pub fn name(&self) -> &StringAttribute {
+        &self.name
     }
 
     /// Return the dialect of the name of this attribute, if the name is prefixed by a dialect namespace. For example, `llvm.fast_math` would return the LLVM dialect (if it is loaded). Returns nullptr if the dialect isn't loaded, or if the name is not prefixed by a dialect namespace.
@@ -108,19 +113,18 @@ impl NamedAttribute {
     }
 
     /// Return the value of the attribute.
-    pub const fn value(&self) -> impl Attribute {
-        self.value
+    pub fn value(&self) -> &Arc<dyn Attribute> {
+        &self.value
     }
 
     /// Set the name of this attribute.
     pub fn set_name(&mut self, name: StringAttribute) {
-        assert!(self.name, "Expected valid attribute name");
+        assert!(!name.value().is_empty(), "Expected valid attribute name");
         self.name = name;
     }
 
     /// Set the value of this attribute.
-    pub fn set_value(&mut self, value: impl Attribute) {
-        assert!(self.value, "Expected valid attribute value");
+    pub fn set_value(&mut self, value: Arc<dyn Attribute>) {
         self.value = value;
     }
 }
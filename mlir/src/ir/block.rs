@@ -7,383 +7,236 @@
 
 pub mod support;
 
-use core::{
-    ptr::null,
-    slice::IterMut
-};
-use rustc_data_structures::graph::{DirectedGraph, With};
-use rustc_index::vec::Idx;
-use llvm::adt::{
-    bit_vector,
-    ilist::{IList, IListIterator},
-    iterator_range::IteratorRange,
-    pointer_int_pair::PointerIntPair
-};
+use std::sync::Arc;
+
 use crate::ir::{
-    block::support::Successors,
+    arena::{ArenaError, ArenaResult, BlockId, IRArena, OpId, RegionId},
     builders,
     location::Location,
-    operation::Operation,
-    region::Region,
-    type_range::{TypeRange, ValueTypeRange},
+    operation::{Operation, definition::IsTerminator},
     r#type::Type,
-    value::Input,
+    value::{Input, InputImpl},
     visitors
 };
 
-//! [`Block`] represents an ordered list of [`Operation`]s.
-#[derive(Copy, Debug, Eq, PartialEq, Hash)]
+/// [`Block`] represents an ordered list of [`Operation`]s.
 pub struct Block {
-    //! Pair of the parent object that owns this block and a bit that signifies if the operations within this block have a valid ordering.
-    parent_valid_op_order_pair: PointerIntPair<*mut Region, 1, bool>,
+    /// The handle of this block within the arena of its context.
+    id: BlockId,
 
-    //! This is the list of operations in the block.
-    operations: IList<Operation>,
+    /// The region that owns this block, if any.
+    pub(crate) parent: Option<RegionId>,
 
-    //! This is the list of inputs to the block.
-    inputs: Vec<Input>
-}
+    /// Whether the cached order indices of the operations within this block are valid.
+    pub(crate) valid_op_order: bool,
 
-impl Block {
-    pub fn clear(&mut self) {
-        // Drop all references from within this block.
-        self.drop_all_references();
-    
-        // Clear operations in the reverse order so that uses are destroyed before their defs.
-        while !self.is_empty() {
-           self.operations.pop_back();
-        }
-    }
+    /// This is the list of operations in the block.
+    pub(crate) operations: Vec<OpId>,
 
-    //! Provide a 'parent' method for ilist_node_with_parent methods.
-    //! We mark it as a const function because ilist_node_with_parent specifically requires a 'parent() const' method. Once ilist_node removes this constraint, we should drop the const to fit the rest of the MLIR const model.
-    pub const fn parent(&self) -> *mut Region {
-        self.parent_valid_op_order_pair.pointer()
-    }
+    /// This is the list of inputs to the block.
+    pub(crate) inputs: Vec<InputImpl>
+}
 
-    //! Returns the closest surrounding operation that contains this block.
-    pub fn parent_operation(&self) -> *mut Operation {
-        if self.parent().is_null() {
-            return null();
-        }
-        unsafe {
-            (*self.parent()).parent_operation()
+impl Block {
+    /// Create an empty block. Blocks are created through `IRArena::create_block`, which assigns their handle.
+    pub(crate) fn new(id: BlockId) -> Self {
+        Self {
+            id,
+            parent: None,
+            valid_op_order: true,
+            operations: Vec::new(),
+            inputs: Vec::new()
         }
     }
 
-    //! Returns if this block is the entry block in the parent region.
-    pub fn is_entry_block(&self) -> bool {
-        self == self.parent().front()
+    /// Returns the handle of this block.
+    pub fn id(&self) -> BlockId {
+        self.id
     }
 
-    //! Insert this block (which must not already be in a region) right before the specified block.
-    pub fn insert_before(&self, block: *mut Block) {
-        assert!(self.parent().is_null(), "Already inserted into a block!");
-        assert!(
-            !block.parent().is_null(),
-            "Cannot insert before a block without a parent");
-        block.parent().blocks().insert(block.iterator(), self);
+    /// Returns the region that owns this block, if any.
+    pub fn parent(&self) -> Option<RegionId> {
+        self.parent
     }
 
-    //! Unlink this block from its current region and insert it right before the specific block.
-    pub fn move_before(&self, block: *mut Block) {
-        assert!(
-            !block.parent().is_null(),
-            "Cannot insert before a block without a parent");
-        block.parent().blocks().splice(
-            block.iterator(), self.parent().blocks(), self.iterator());
+    /// Returns the closest surrounding operation that contains this block.
+    pub fn parent_operation(&self, arena: &IRArena) -> ArenaResult<Option<OpId>> {
+        arena.block_parent_op(self.id)
     }
 
-    //! Unlink this Block from its parent region and delete it.
-    pub fn erase(&self) {
-        assert!(!self.parent().is_null(), "Block has no parent");
-        self.parent().block().erase(self);
+    /// Returns if this block is the entry block in the parent region.
+    pub fn is_entry_block(&self, arena: &IRArena) -> ArenaResult<bool> {
+        match self.parent {
+            None => Ok(false),
+            Some(region) =>
+                Ok(arena.region(region)?.blocks().first() == Some(&self.id))
+        }
     }
 
     // --------------------------------------------------------------------
     // Block Inputs Management
     // --------------------------------------------------------------------
 
-    pub fn inputs(&self) -> &mut [Input] {
-        self.inputs.as_mut_slice()
+    /// Returns the inputs of this block.
+    pub fn inputs(&self) -> impl Iterator<Item = Input> + '_ {
+        (0..self.inputs.len()).map(|index| Input::new(self.id, index))
     }
 
-    //! Return a range containing the types of the inputs for this block.
-    pub fn input_types(&self) -> ValueTypeRange<&mut [Input]> {
-        ValueTypeRange::<&mut [Input]>::new(self.inputs())
+    /// Return the types of the inputs for this block.
+    pub fn input_types(&self) -> impl Iterator<Item = &Arc<dyn Type>> {
+        self.inputs.iter().map(|input| &input.value.r#type)
     }
 
     pub fn inputs_empty(&self) -> bool {
-
+        self.inputs.is_empty()
     }
 
-    //! Add one value to the input list.
-    pub fn add_input(&mut self, r#type: impl Type, location: Location)
+    /// Add one value to the input list.
+    pub fn add_input(&mut self, r#type: Arc<dyn Type>, location: Location)
     -> Input
     {
-        let input = Input::create(r#type, self, self.inputs.len(), location);
-        self.inputs.push(input);
-        input
+        self.inputs.push(InputImpl::new(r#type, location));
+        Input::new(self.id, self.inputs.len() - 1)
     }
 
-    // /**
-    // Insert one value to the position in the input list indicated by the
-    // given iterator. The existing inputs are shifted. The block is expected
-    // not to have predecessors.
-    // */
-    // pub fn insert_input() {}
-
-    //! Add one input to the input list for each type specified in the list.
-    //! `locations` is required to have the same number of elements as `types`.
+    /// Add one input to the input list for each type specified in the list.
+    /// `locations` is required to have the same number of elements as `types`.
     pub fn add_inputs(
         &mut self,
-        types: TypeRange,
+        types: &[Arc<dyn Type>],
         locations: &[Location]
-    ) -> IterMut<Input>
+    ) -> Vec<Input>
     {
         assert!(types.len() == locations.len(),
                 "Incorrect number of block argument locations");
-        let initial_size = self.inputs.len();
-        self.inputs.reserve(initial_size + types.len());
-
-        for (r#type, location) in llvm::zip(types, locations) {
-            self.add_input(r#type, location);
-        }
-        self.inputs.iter_mut()[initial_size..self.inputs.len()]
+        self.inputs.reserve(types.len());
+        types.iter().zip(locations)
+            .map(|(r#type, location)| self.add_input(r#type.clone(), location.clone()))
+            .collect()
     }
 
-    //! Add one value to the input list at the specified position.
-    pub fn insert_input(
-        &mut self, index: usize, r#type: impl Type, location: Location
-    ) -> Input
-    {
-        assert!(index <= self.inputs.len(), "Invalid insertion index");
-
-        let input = Input::create(r#type, self, index, location);
-        self.inputs.insert(self.inputs.begin() + index, self.inputs);
-        // Update the cached position for all the inputs after the newly inserted one.
-        index += 1;
-        for input in self.inputs.iter().skip(index) {
-            input.index = index;
-            index += 1;
-        }
-        input
-    }
-
-    //! Erase the input at `index` and remove it from the input list.
-    pub fn erase_input(&mut self, index: usize) {
-        assert!(index < self.inputs.len());
-        drop(self.inputs[index]);
-        self.inputs.erase(index);
-        for input in self.inputs.iter().skip(index) {
-            input.index = index;
-            index += 1;
-        }
-    }
-
-    //! Erases `n` inputs from the index `start`.
-    pub fn erase_inputs(&mut self, start: usize, n: usize) {
-        assert!(start + n <= self.inputs.len());
-        for i in 0..n {
-            drop(self.inputs[start + i]);
-        }
-        self.inputs.drain(start..(start + n));
-        for input in self.inputs.iter().skip(start) {
-            input.input = start;
-            start += 1;
-        }
-    }
+    // NOTE: Inserting and erasing inputs in the middle of the list renumbers the inputs after them, which requires updating their users; see `IRArena::insert_block_input` and `IRArena::erase_block_input`.
 
     pub fn num_inputs(&self) -> usize {
         self.inputs.len()
     }
 
     pub fn input(&self, index: usize) -> Input {
-        self.inputs[index]
+        assert!(index < self.inputs.len(), "Invalid input index.");
+        Input::new(self.id, index)
+    }
+
+    /// Returns the storage of the `index`th input.
+    pub fn input_impl(&self, index: usize) -> &InputImpl {
+        &self.inputs[index]
     }
 
     // --------------------------------------------------------------------
     // Operation List Management
     // --------------------------------------------------------------------
 
-    pub fn operations(&self) -> &IList<Operation> {
-        &self.operations
+    pub fn operations(&self) -> &[OpId] {
+        self.operations.as_slice()
     }
 
     pub fn is_empty(&self) -> bool {
         self.operations.is_empty()
     }
 
-    pub fn push_back(&mut self, operation: *mut Operation) {
-        self.operations.push_back(operation);
+    pub fn back(&self) -> Option<OpId> {
+        self.operations.last().copied()
     }
 
-    pub fn push_front(&mut self, operation: *mut Operation) {
-        self.operations.push_front(operation);
+    pub fn front(&self) -> Option<OpId> {
+        self.operations.first().copied()
     }
 
-    pub fn back(&self) -> &Operation {
-        self.operations.back()
-    }
-
-    pub fn front(&self) -> &Operation {
-        self.operations.front()
-    }
-
-    //! Returns 'op' if 'op' lies in this block, or otherwise finds the ancestor operation of 'op' that lies in this block. Returns nullptr if the latter fails.
-    //! TODO: This is very specific functionality that should live somewhere else, probably in Dominance.cpp.
-    pub fn find_ancestor_op_in_block(&self, operation: &Operation)
-        -> *mut Operation
-    {
-        // Traverse up the operation hierarchy starting from the owner of operand to find the ancestor operation that resides in the block of 'forOp'.
-        let current: *mut Operation = operation;
-        while current.block() != self {
-            current = current.parent();
-            if !current {
-                return null();
-            }
-        }
-        current
-    }
-
-    //! This drops all operand uses from operations within this block, which is an essential step in breaking cyclic dependences between references when they are to be deleted.
-    pub fn drop_all_references(&mut self) {
-        for operation in *self {
-            operation.drop_all_references();
-        }
-    }
-
-    //! This drops all uses of values defined in this block or in the blocks of nested regions wherever the uses are located.
-    pub fn drop_all_defined_value_uses(&mut self) {
-        for input in self.inputs() {
-            input.drop_all_uses();
-        }
-        for operation in *self {
-            operation.drop_all_defined_value_uses();
-        }
-        self.drop_all_uses();
-    }
-
-    //! Returns true if the ordering of the child operations is valid, false otherwise.
+    /// Returns true if the ordering of the child operations is valid, false otherwise.
     pub fn is_op_order_valid(&self) -> bool {
-        self.parent_valid_op_order_pair.int()
-    }
-
-    //! Invalidates the current ordering of operations.
-    pub fn invalidate_op_order(&self) {
-        // Validate the current ordering.
-        assert!(!self.verify_op_order());
-        self.parent_valid_op_order_pair.set_int(false);
+        self.valid_op_order
     }
 
-    //! Verifies the current ordering of child operations matches the validOpOrder flag. Returns false if the order is valid, true otherwise.
-    pub fn verify_op_order(&self) -> bool {
-        // The order is already known to be invalid.
-        if !self.is_op_order_valid() {
-            return false;
-        }
-        // The order is valid if there are less than 2 operations.
-        if self.operations.len() < 2 {
-            return false;
-        }
-        let prev = null();
-        for operation in *self {
-            // The previous operation must have a smaller order index than the next as it appears earlier in the list.
-            if !prev.is_nul()
-            && prev.order_index != Operation::kInvalidOrderIdx
-            && prev.order_index >= operation.order_index
-            {
-                return true;
-            }
-            prev = operation;
-        }
-        false
+    /// Invalidates the current ordering of operations.
+    pub fn invalidate_op_order(&mut self) {
+        self.valid_op_order = false;
     }
 
-    //! Recomputes the ordering of child operations within the block.
-    pub fn recompute_op_order(&self) {
-        self.parent_valid_op_order_pair.set_int(true);
+    /* Terminator Management */
 
-        let order_index = 0;
-        for operation in *self {
-            order_index += Operation::kOrderStride;
-            operation.order_index = order_index;
+    /// Get the terminator operation of this block, or `None` if the block is empty or its last operation cannot be a terminator.
+    pub fn terminator(&self, arena: &IRArena) -> ArenaResult<Option<OpId>> {
+        match self.back() {
+            None => Ok(None),
+            Some(back) if arena.op(back)?.might_have_trait::<dyn IsTerminator>() =>
+                Ok(Some(back)),
+            Some(_) => Ok(None)
         }
     }
 
-    /* Terminator Management */
+    /* Predecessors and Successors */
 
-    //! Get the terminator operation of this block. This function asserts that
-    //! the block has a valid terminator operation.
-    pub fn terminator(&self) -> *mut Operation {
-        assert(
-            !self.is_empty()
-            && back().might_have_trait::<IsTerminator>());
-        self.back()
+    /// Return the blocks branching to this block, once for each edge.
+    pub fn predecessors(&self, arena: &IRArena) -> ArenaResult<Vec<BlockId>> {
+        arena.predecessors(self.id)
     }
 
-    /* Predecessors and Successors */
-
-    //! Return true if this block has no predecessors.
-    pub fn has_no_predecessors(&self) -> bool {
-        self.predecessors().is_empty()
+    /// Return true if this block has no predecessors.
+    pub fn has_no_predecessors(&self, arena: &IRArena) -> ArenaResult<bool> {
+        Ok(self.predecessors(arena)?.is_empty())
     }
 
-    //! Returns true if this blocks has no successors.
-    pub fn has_no_successors(&self) -> bool {
-        self.successors().is_empty()
+    /// Returns true if this blocks has no successors.
+    pub fn has_no_successors(&self, arena: &IRArena) -> ArenaResult<bool> {
+        Ok(self.num_successors(arena)? == 0)
     }
 
-    //! If this block has exactly one predecessor, return it.  Otherwise, return null.
-    //!
-    //! Note that if a block has duplicate predecessors from a single block (e.g. if you have a conditional branch with the same block as the true/false destinations) is not considered to be a single predecessor.
-    pub fn single_predecessor(&self) -> *mut Block {
-        let predecessors = self.predecessors();
-        match predecessors.next() {
-            None => null(),
-            Some(predecessor) => {
-                match predecessors.next() {
-                    None => predecessor,
-                    Some(_) => null()
-                }
-            }
+    /// If this block has exactly one predecessor, return it.  Otherwise, return `None`.
+    ///
+    /// Note that if a block has duplicate predecessors from a single block (e.g. if you have a conditional branch with the same block as the true/false destinations) is not considered to be a single predecessor.
+    pub fn single_predecessor(&self, arena: &IRArena)
+    -> ArenaResult<Option<BlockId>>
+    {
+        match self.predecessors(arena)?.as_slice() {
+            [predecessor] => Ok(Some(*predecessor)),
+            _ => Ok(None)
         }
     }
 
-    //! If this block has a unique predecessor, i.e., all incoming edges originate from one block, return it. Otherwise, return null.
-    pub fn unique_predecessor(&self) -> *mut Block {
-        let predecessors = self.predecessors();
-        match predecessors.next() {
-            None => null(),
-            Some(first_predecessor) => {
-                // Check for any conflicting predecessors.
-                if predecessors.all(
-                    |predecessor| predecessor == first_predecessor)
-                {
-                    first_predecessor
-                } else {
-                    null()
-                }
-            }
+    /// If this block has a unique predecessor, i.e., all incoming edges originate from one block, return it. Otherwise, return `None`.
+    pub fn unique_predecessor(&self, arena: &IRArena)
+    -> ArenaResult<Option<BlockId>>
+    {
+        let predecessors = self.predecessors(arena)?;
+        match predecessors.split_first() {
+            Some((first, rest)) if rest.iter().all(|other| other == first) =>
+                Ok(Some(*first)),
+            _ => Ok(None)
         }
     }
 
     // Indexed successor access.
-    pub fn num_successors(&self) -> usize {
-        if self.is_empty() {
-            0
-        } else {
-            self.back().num_successors()
+    pub fn num_successors(&self, arena: &IRArena) -> ArenaResult<usize> {
+        match self.back() {
+            None => Ok(0),
+            Some(back) => Ok(arena.op(back)?.num_successors())
         }
     }
 
-    pub fn successor(&self, index: usize) -> *mut Block {
-        assert!(index < self.num_successors());
-        self.terminator().successor(index)
+    pub fn successor(&self, arena: &IRArena, index: usize) -> ArenaResult<BlockId> {
+        let back = self.back().ok_or(ArenaError::IndexOutOfBounds { index, len: 0 })?;
+        let terminator = arena.op(back)?;
+        if index >= terminator.num_successors() {
+            return Err(ArenaError::IndexOutOfBounds {
+                index, len: terminator.num_successors() });
+        }
+        Ok(terminator.successor(index))
     }
 
-    pub fn successors(&self) -> SuccessorRange {
-        SuccessorRange::new(self)
+    pub fn successors<'a>(&self, arena: &'a IRArena) -> ArenaResult<&'a [BlockId]> {
+        match self.back() {
+            None => Ok(&[]),
+            Some(back) => Ok(arena.op(back)?.successors())
+        }
     }
 
     /* Operation Walkers */
@@ -401,29 +254,3 @@ impl Block {
 
     // }
 }
-
-impl Idx for Block {
-    fn new(index: usize) -> Self {
-    }
-
-    fn index(self) -> usize {
-    }
-}
-
-impl DirectedGraph for Block {
-    type Node = Self;
-}
-
-impl GraphSuccessors<'graph> for Block {
-    type Item = Self;
-    type Iter = Successors;
-}
-
-impl WithSuccessors for Block {
-    fn successors(&self, node: Self::Node) -> Self::Iter {
-    }
-}
-
-pub type Operations = IListIterator;
-
-/* Predecessors and Successors */
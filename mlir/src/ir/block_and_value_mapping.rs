@@ -2,17 +2,65 @@
 //!
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/BlockAndValueMapping.h>
 
-use crate::{
-    mlir::ir::{
-        block::Block,
-        value::Value
-    },
-    llvm::adt::dense_map::DenseMap
+use std::collections::HashMap;
+
+use crate::ir::{
+    arena::BlockId,
+    value::Value
 };
 
 // This is a utility class for mapping one set of values to another. New mappings can be inserted via 'map'. Existing mappings can be found via the 'lookup*' functions. There are two variants that differ only in return value when an existing is not found for the provided key.
-// 'lookupOrNull' returns nullptr where as 'lookupOrDefault' will return the lookup key.
+// 'lookup' returns None where as 'lookup_or_default' will return the lookup key.
+#[derive(Default)]
 pub struct BlockAndValueMapping {
-    value_map: DenseMap<Value, Value>,
-    block_map: DenseMap<*mut Block, *mut Block>
+    value_map: HashMap<Value, Value>,
+    block_map: HashMap<BlockId, BlockId>
+}
+
+impl BlockAndValueMapping {
+    /// Inserts a new mapping for `from` to `to`. If there is an existing mapping, it is overwritten.
+    pub fn map(&mut self, from: Value, to: Value) {
+        self.value_map.insert(from, to);
+    }
+
+    /// Inserts a new mapping for the block `from` to `to`.
+    pub fn map_block(&mut self, from: BlockId, to: BlockId) {
+        self.block_map.insert(from, to);
+    }
+
+    /// Lookup a mapped value within the map. If a mapping for the provided value does not exist then return None.
+    pub fn lookup(&self, from: Value) -> Option<Value> {
+        self.value_map.get(&from).copied()
+    }
+
+    /// Lookup a mapped value within the map. If a mapping for the provided value does not exist then return the provided value.
+    pub fn lookup_or_default(&self, from: Value) -> Value {
+        self.lookup(from).unwrap_or(from)
+    }
+
+    /// Lookup a mapped block within the map.
+    pub fn lookup_block(&self, from: BlockId) -> Option<BlockId> {
+        self.block_map.get(&from).copied()
+    }
+
+    /// Lookup a mapped block within the map. If a mapping for the provided block does not exist then return the provided block.
+    pub fn lookup_block_or_default(&self, from: BlockId) -> BlockId {
+        self.lookup_block(from).unwrap_or(from)
+    }
+
+    /// Checks to see if a mapping for `from` exists.
+    pub fn contains(&self, from: Value) -> bool {
+        self.value_map.contains_key(&from)
+    }
+
+    /// Erases a mapping for `from`.
+    pub fn erase(&mut self, from: Value) {
+        self.value_map.remove(&from);
+    }
+
+    /// Clears all mappings held by the mapper.
+    pub fn clear(&mut self) {
+        self.value_map.clear();
+        self.block_map.clear();
+    }
 }
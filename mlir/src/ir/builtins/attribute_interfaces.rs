@@ -33,6 +33,17 @@ use crate::{
     }
 };
 
+use std::sync::Arc;
+
+use crate::ir::{
+    attribute::Attribute,
+    builtins::attributes::{
+        DenseIntOrFPElementsAttribute, DenseResourceElementsAttribute, FloatAttribute,
+        IntegerAttribute, OpaqueAttribute, StringAttribute
+    },
+    r#type::Type
+};
+
 /**
 This class provides support for indexing into the element range of an `ElementsAttr`. It is used to opaquely wrap either a contiguous range, via `ElementsAttrIndexer::contiguous`, or a non-contiguous range, via `ElementsAttrIndexer::nonContiguous`, A contiguous range is an array-like range, where all of the elements are layed out sequentially in memory. A non-contiguous range implies no contiguity, and elements may even be materialised when indexing, such as the case for a `mapped_range`.
 */
@@ -61,3 +72,26 @@ struct NonContiguousState {
 pub struct ElementsAttrIterator<T> {
     _unimplemented: std::marker::PhantomData<T>
 }
+
+// ----------------------------------------------------------------------
+// TypedAttr
+// ----------------------------------------------------------------------
+
+/// Return the type of `attribute` if it is an attribute with a type, implementing `TypedAttr` upstream, None otherwise. String and opaque attributes only have a type when one is given.
+pub fn typed_attribute_type(attribute: &dyn Attribute) -> Option<Arc<dyn Type>> {
+    if let Some(attribute) = attribute.downcast_ref::<IntegerAttribute>() {
+        Some(attribute.r#type().clone())
+    } else if let Some(attribute) = attribute.downcast_ref::<FloatAttribute>() {
+        Some(attribute.r#type().clone())
+    } else if let Some(attribute) = attribute.downcast_ref::<DenseIntOrFPElementsAttribute>() {
+        Some(attribute.r#type().clone())
+    } else if let Some(attribute) = attribute.downcast_ref::<DenseResourceElementsAttribute>() {
+        Some(attribute.r#type().clone())
+    } else if let Some(attribute) = attribute.downcast_ref::<StringAttribute>() {
+        attribute.r#type().cloned()
+    } else if let Some(attribute) = attribute.downcast_ref::<OpaqueAttribute>() {
+        attribute.r#type().cloned()
+    } else {
+        None
+    }
+}
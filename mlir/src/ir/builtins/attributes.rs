@@ -7,16 +7,20 @@
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/BuiltinAttributes.cpp>
 */
 
+use std::sync::Arc;
+
 use crate::{
     mlir::ir::{
         affine_map::AffineMap,
+        asm_state::AsmResourceBlob,
         attribute::{
             Attribute, NamedAttribute,
             detail
         },
         builtins::{
             attribute_interfaces,
-            dialect
+            dialect,
+            types::{FloatType, make_strided_linear_layout_map}
         },
         dialect::Dialect,
         dialect_resource_blob_manager,
@@ -24,7 +28,7 @@ use crate::{
         mlir_context::MLIRContext,
         operation::{
             self,
-            implementation
+            implementation::{self, AsmDialectResourceHandle}
         },
         sub_element_interfaces,
         symbol_table,
@@ -33,6 +37,7 @@ use crate::{
     llvm::{
         adt::{
             ap_float,
+            ap_int::APInt,
             aps_int,
             sequence,
             type_switch
@@ -69,6 +74,16 @@ impl Attribute for AffineMapAttribute {
     type ValueType = AffineMap;
 }
 
+impl AffineMapAttribute {
+    pub fn new(value: AffineMap) -> Self {
+        Self { value }
+    }
+
+    pub fn value(&self) -> &AffineMap {
+        &self.value
+    }
+}
+
 // ----------------------------------------------------------------------
 // ArrayAttribute
 // ----------------------------------------------------------------------
@@ -93,11 +108,29 @@ An array attribute is an attribute that represents a collection of attribute val
 ```
 */
 pub struct ArrayAttribute {
-    value: &'static [dyn Attribute]
+    value: Vec<Arc<dyn Attribute>>
 }
 
 impl Attribute for ArrayAttribute {
-    type ValueType = &'static [dyn Attribute];
+    type ValueType = [Arc<dyn Attribute>];
+}
+
+impl ArrayAttribute {
+    pub fn new(value: Vec<Arc<dyn Attribute>>) -> Self {
+        Self { value }
+    }
+
+    pub fn value(&self) -> &[Arc<dyn Attribute>] {
+        &self.value
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -133,8 +166,30 @@ When a specific subclass is used as argument of an operation, the declarative as
 ```
 */
 pub struct DenseArrayAttribute {
-    element_type: Box<dyn Type>,
-    size: i64
+    element_type: Arc<dyn Type>,
+    size: i64,
+    /// The elements stored contiguously in little-endian order, one byte per bool element.
+    raw_data: Vec<u8>
+}
+
+impl Attribute for DenseArrayAttribute {}
+
+impl DenseArrayAttribute {
+    pub fn new(element_type: Arc<dyn Type>, size: i64, raw_data: Vec<u8>) -> Self {
+        Self { element_type, size, raw_data }
+    }
+
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    pub fn size(&self) -> i64 {
+        self.size
+    }
+
+    pub fn raw_data(&self) -> &[u8] {
+        &self.raw_data
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -142,9 +197,53 @@ pub struct DenseArrayAttribute {
 // ----------------------------------------------------------------------
 
 /**
+An Attribute containing a dense multi-dimensional array of integer or floating-point values.
 
+# Syntax
+
+```text
+tensor-literal ::= integer-literal | float-literal | bool-literal | [] | [tensor-literal (, tensor-literal)* ]
+dense-intorfloat-elements-attribute ::= `dense` `<` tensor-literal `>` `:`
+                                          ( tensor-type | vector-type )
+```
+
+A dense int-or-float elements attribute is an elements attribute containing a densely packed vector or tensor of integer or floating-point values. The element type of this attribute is required to be either an `IntegerType` or a `FloatType`.
+
+# Examples
+
+```mlir
+// A splat tensor of integer values.
+dense<10> : tensor<2xi32>
+// A tensor of 2 float32 elements.
+dense<[10.0, 11.0]> : tensor<2xf32>
+```
 */
 pub struct DenseIntOrFPElementsAttribute {
+    /// The shaped type of the attribute.
+    r#type: Arc<dyn Type>,
+    /// The elements stored contiguously in little-endian order. Elements are rounded up to whole bytes, except `i1` elements which are packed one bit each. A splat stores a single element.
+    raw_data: Vec<u8>,
+    is_splat: bool
+}
+
+impl Attribute for DenseIntOrFPElementsAttribute {}
+
+impl DenseIntOrFPElementsAttribute {
+    pub fn new(r#type: Arc<dyn Type>, raw_data: Vec<u8>, is_splat: bool) -> Self {
+        Self { r#type, raw_data, is_splat }
+    }
+
+    pub fn r#type(&self) -> &Arc<dyn Type> {
+        &self.r#type
+    }
+
+    pub fn raw_data(&self) -> &[u8] {
+        &self.raw_data
+    }
+
+    pub fn is_splat(&self) -> bool {
+        self.is_splat
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -201,7 +300,48 @@ A dense resource elements attribute is an elements attribute backed by a handle
 dense_resource<resource_1> : tensor<2xui32>
 ```
 */
-pub struct DenseResourceElementsAttribute;
+pub struct DenseResourceElementsAttribute {
+    r#type: Arc<dyn Type>,
+    raw_handle: AsmDialectResourceHandle
+}
+
+impl Attribute for DenseResourceElementsAttribute {}
+
+impl DenseResourceElementsAttribute {
+    pub fn new(r#type: Arc<dyn Type>, raw_handle: AsmDialectResourceHandle)
+        -> Self
+    {
+        Self { r#type, raw_handle }
+    }
+
+    pub fn r#type(&self) -> &Arc<dyn Type> {
+        &self.r#type
+    }
+
+    pub fn raw_handle(&self) -> &AsmDialectResourceHandle {
+        &self.raw_handle
+    }
+
+    /// Return a new attribute referencing `blob`, which is inserted into the builtin blob manager of `context` under `blob_name`. The name is uniqued if a blob with that name already exists.
+    pub fn with_blob(
+        context: &MLIRContext,
+        r#type: Arc<dyn Type>,
+        blob_name: &str,
+        blob: AsmResourceBlob
+    ) -> Self {
+        let interface = dialect_resource_blob_manager
+            ::ResourceBlobManagerDialectInterface::builtin(context);
+        Self::new(r#type, interface.insert(blob_name, Some(blob)))
+    }
+
+    /// Return the blob referenced by this attribute within `context`, or None if the resource has no data yet.
+    pub fn blob(&self, context: &MLIRContext) -> Option<AsmResourceBlob> {
+        context.resource_blob_manager(self.raw_handle.dialect())
+            .lookup(self.raw_handle.key())?
+            .blob()
+            .cloned()
+    }
+}
 
 // ----------------------------------------------------------------------
 // DictionaryAttribute
@@ -226,12 +366,41 @@ A dictionary attribute is an attribute that represents a sorted collection of na
 {int_attr = 10, "string attr name" = "string attribute"}
 ```
 */
+#[derive(Clone, Default)]
 pub struct DictionaryAttribute {
-    value: &'static [NamedAttribute]
+    value: Vec<NamedAttribute>
 }
 
 impl Attribute for DictionaryAttribute {
-    type ValueType = &'static [NamedAttribute];
+    type ValueType = [NamedAttribute];
+}
+
+impl DictionaryAttribute {
+    /// Create a dictionary from `value`, sorting the entries by name.
+    pub fn new(mut value: Vec<NamedAttribute>) -> Self {
+        value.sort_by(|lhs, rhs| lhs.name().value().cmp(rhs.name().value()));
+        Self { value }
+    }
+
+    pub fn value(&self) -> &[NamedAttribute] {
+        &self.value
+    }
+
+    /// Return the attribute with the given name, if present.
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Attribute>> {
+        self.value
+            .binary_search_by(|attribute| attribute.name().value().cmp(name))
+            .ok()
+            .map(|index| self.value[index].value())
+    }
+
+    pub fn len(&self) -> usize {
+        self.value.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.value.is_empty()
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -261,12 +430,47 @@ A float attribute is a literal attribute that represents a floating point value
 ```
 */
 pub struct FloatAttribute {
-    r#type: AttributeSelfTypeParameter<"">,
-    value: APFloatParameter<"">
+    r#type: Arc<dyn Type>,
+    /// The bit pattern of the value, interpreted with the semantics of `r#type`.
+    value: APInt
 }
 
 impl Attribute for FloatAttribute {
-    type ValueType = APFloat;
+    type ValueType = APInt;
+}
+
+impl FloatAttribute {
+    pub fn new(r#type: Arc<dyn Type>, value: APInt) -> Self {
+        Self { r#type, value }
+    }
+
+    pub fn from_f64(r#type: Arc<dyn Type>, value: f64) -> Self {
+        let width = r#type.downcast_ref::<FloatType>()
+            .map_or(64, |r#type| r#type.width());
+        let bits = match width {
+            32 => (value as f32).to_bits() as u64,
+            _ => value.to_bits()
+        };
+        Self::new(r#type, APInt::new(width as u32, bits, false))
+    }
+
+    pub fn r#type(&self) -> &Arc<dyn Type> {
+        &self.r#type
+    }
+
+    /// Return the bit pattern of the value.
+    pub fn bits(&self) -> &APInt {
+        &self.value
+    }
+
+    /// Return the value as a double, if its type is `f32` or `f64`.
+    pub fn value_as_f64(&self) -> Option<f64> {
+        match self.value.width() {
+            32 => Some(f32::from_bits(self.value.words()[0] as u32) as f64),
+            64 => Some(f64::from_bits(self.value.words()[0])),
+            _ => None
+        }
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -299,7 +503,7 @@ An Attribute containing a integer value.
     ```
 */
 pub struct IntegerAttribute {
-    r#type: AttributeSelfTypeParameter<"">,
+    r#type: Arc<dyn Type>,
     value: APInt
 }
 
@@ -307,6 +511,20 @@ impl Attribute for IntegerAttribute {
     type ValueType = APInt;
 }
 
+impl IntegerAttribute {
+    pub fn new(r#type: Arc<dyn Type>, value: APInt) -> Self {
+        Self { r#type, value }
+    }
+
+    pub fn r#type(&self) -> &Arc<dyn Type> {
+        &self.r#type
+    }
+
+    pub fn value(&self) -> &APInt {
+        &self.value
+    }
+}
+
 // ----------------------------------------------------------------------
 // IntegerSetAttribute
 // ----------------------------------------------------------------------
@@ -336,6 +554,16 @@ impl Attribute for IntegerSetAttribute {
     type ValueType = IntegerSet;
 }
 
+impl IntegerSetAttribute {
+    pub fn new(value: IntegerSet) -> Self {
+        Self { value }
+    }
+
+    pub fn value(&self) -> &IntegerSet {
+        &self.value
+    }
+}
+
 // ----------------------------------------------------------------------
 // OpaqueAttribute
 // ----------------------------------------------------------------------
@@ -362,8 +590,32 @@ Syntax:
 */
 pub struct OpaqueAttribute {
     dialect_namespace: StringAttribute,
-    attr_data: StringRefParameter<"">,
-    r#type: AttributeSelfTypeParameter<"">
+    attr_data: String,
+    r#type: Option<Arc<dyn Type>>
+}
+
+impl Attribute for OpaqueAttribute {}
+
+impl OpaqueAttribute {
+    pub fn new(
+        dialect_namespace: StringAttribute,
+        attr_data: String,
+        r#type: Option<Arc<dyn Type>>
+    ) -> Self {
+        Self { dialect_namespace, attr_data, r#type }
+    }
+
+    pub fn dialect_namespace(&self) -> &str {
+        self.dialect_namespace.value()
+    }
+
+    pub fn attr_data(&self) -> &str {
+        &self.attr_data
+    }
+
+    pub fn r#type(&self) -> Option<&Arc<dyn Type>> {
+        self.r#type.as_ref()
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -432,7 +684,29 @@ See [Dialects/Builtin.md#memreftype](MemRef type) for more information.
 */
 pub struct StridedLayoutAttribute {
     offset: i64,
-    strides: ArrayRefParameter<i64>
+    strides: Vec<i64>
+}
+
+impl Attribute for StridedLayoutAttribute {}
+
+impl StridedLayoutAttribute {
+    pub fn new(offset: i64, strides: Vec<i64>) -> Self {
+        Self { offset, strides }
+    }
+
+    pub fn offset(&self) -> i64 {
+        self.offset
+    }
+
+    pub fn strides(&self) -> &[i64] {
+        &self.strides
+    }
+
+    /// Returns the linearised affine map form of this layout, `(d0, ..., dN)[offset, stride0, ..., strideN] -> (offset + d0 * stride0 + ... dN * strideN)`, where only the dynamic offset and strides become symbols.
+    pub fn affine_map(&self) -> AffineMap {
+        make_strided_linear_layout_map(
+            &self.strides, self.offset, core::ptr::null_mut())
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -457,16 +731,34 @@ A string attribute is an attribute that represents a string literal value.
 "string with a type" : !dialect.string
 ```
 */
+#[derive(Clone)]
 pub struct StringAttribute {
-    value: &'static str,
-    r#type: AttributeSelfTypeParameter<"">
+    value: String,
+    /// The type of the string, `None` for the default `NoneType`.
+    r#type: Option<Arc<dyn Type>>
 }
 
 impl Attribute for StringAttribute {
-    type ValueType = &'static str;
+    type ValueType = str;
 }
 
 impl StringAttribute {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self { value: value.into(), r#type: None }
+    }
+
+    pub fn with_type(value: impl Into<String>, r#type: Arc<dyn Type>) -> Self {
+        Self { value: value.into(), r#type: Some(r#type) }
+    }
+
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    pub fn r#type(&self) -> Option<&Arc<dyn Type>> {
+        self.r#type.as_ref()
+    }
+
     /**
     If the value of this string is prefixed with a dialect namespace,
     returns the dialect corresponding to that namespace if it is loaded,
@@ -506,7 +798,7 @@ impl StringAttribute {
 }
 
 /// Enable conversion to StringRef.
-impl From<StringAttribute> for &str {
+impl From<StringAttribute> for String {
     fn from(value: StringAttribute) -> Self {
         value.value
     }
@@ -556,9 +848,30 @@ See [`Symbols And SymbolTables`](../SymbolsAndSymbolTables.md) for more informat
 @parent_reference::@nested_reference
 ```
 */
+#[derive(Clone)]
 pub struct SymbolRefAttribute {
     root_reference: StringAttribute,
-    nested_references: &'static [FlatSymbolRefAttribute]
+    nested_references: Vec<StringAttribute>
+}
+
+impl Attribute for SymbolRefAttribute {}
+
+impl SymbolRefAttribute {
+    pub fn new(
+        root_reference: StringAttribute,
+        nested_references: Vec<StringAttribute>
+    ) -> Self {
+        Self { root_reference, nested_references }
+    }
+
+    pub fn root_reference(&self) -> &StringAttribute {
+        &self.root_reference
+    }
+
+    /// Returns the nested references, each of which is a flat symbol reference.
+    pub fn nested_references(&self) -> &[StringAttribute] {
+        &self.nested_references
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -584,11 +897,21 @@ i32
 ```
  */
 pub struct TypeAttribute {
-    value: Box<dyn Type>
+    value: Arc<dyn Type>
 }
 
 impl Attribute for TypeAttribute {
-    type ValueType = dyn Type;
+    type ValueType = Arc<dyn Type>;
+}
+
+impl TypeAttribute {
+    pub fn new(value: Arc<dyn Type>) -> Self {
+        Self { value }
+    }
+
+    pub fn value(&self) -> &Arc<dyn Type> {
+        &self.value
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -623,8 +946,10 @@ func.func @simple_form() attributes {dialectName.unitAttr}
 */
 pub struct UnitAttribute;
 
+impl Attribute for UnitAttribute {}
+
 impl UnitAttribute {
-    fn new(context: *mut MLIRContext) -> Self {
-        
+    pub fn new() -> Self {
+        Self
     }
 }
@@ -6,22 +6,39 @@
   - <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/BuiltinDialectBytecode.cpp>
 */
 
+use std::{error::Error, sync::Arc};
+
 use crate::{
-    mlir::{
-        bytecode::implementation,
-        ir::{
-            builtins::{
-                dialect,
-                types
+    bytecode::implementation::{
+        BytecodeDialectInterface, DialectBytecodeReader, DialectBytecodeWriter
+    },
+    ir::{
+        affine_map::multi_dim_identity_map,
+        attribute::{Attribute, NamedAttribute},
+        builtins::{
+            attributes::{
+                AffineMapAttribute, ArrayAttribute, DenseArrayAttribute,
+                DenseIntOrFPElementsAttribute, DenseResourceElementsAttribute,
+                DictionaryAttribute, FloatAttribute, IntegerAttribute,
+                StringAttribute, SymbolRefAttribute, TypeAttribute,
+                UnitAttribute
             },
-            diagnostics,
-            dialect_resource_blob_manager
+            operations::ModuleOp,
+            types::{
+                ComplexType, FloatType, FloatTypeKind, FunctionType, IndexType,
+                IntegerType, MemRefType, NoneType, RankedTensorType, Signedness,
+                TupleType, UnrankedMemRefType, UnrankedTensorType, VectorType
+            }
         },
+        location::Location,
+        operation::{Operation, support::OperationName},
+        r#type::Type
     },
-    llvm::adt::type_switch
+    support::logical_result::LogicalResult
 };
 
 /// This enum contains marker codes used to indicate which attribute is currently being decoded, and how it should be decoded. The order of these codes should generally be unchanged, as any changes will inevitably break compatibility with older bytecode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum AttributeCode {
   ///   ArrayAttr {
   ///     elements: Attribute[]
@@ -121,7 +138,8 @@ pub enum AttributeCode {
     DenseResourceElementsAttr = 16,
 
   ///   DenseArrayAttr {
-  ///     type: RankedTensorType,
+  ///     elementType: Type,
+  ///     size: varint,
   ///     data: blob
   ///   }
     DenseArrayAttr = 17,
@@ -151,7 +169,8 @@ pub enum AttributeCode {
 /// being decoded, and how it should be decoded. The order of these codes should
 /// generally be unchanged, as any changes will inevitably break compatibility
 /// with older bytecode.
-enum TypeCode {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypeCode {
   ///   IntegerType {
   ///     widthAndSignedness: varint // (width << 2) | (signedness)
   ///   }
@@ -249,14 +268,14 @@ enum TypeCode {
     TupleType = 15,
 
   ///   UnrankedMemRefType {
-  ///     shape: svarint[]
+  ///     elementType: Type
   ///   }
   ///
     UnrankedMemRefType = 16,
 
   ///   UnrankedMemRefTypeWithMemSpace {
   ///     memorySpace: Attribute,
-  ///     shape: svarint[]
+  ///     elementType: Type
   ///   }
   /// Variant of UnrankedMemRefType with non-default memory space.
     UnrankedMemRefTypeWithMemSpace = 17,
@@ -275,10 +294,635 @@ enum TypeCode {
     VectorType = 19,
 
   ///   VectorTypeWithScalableDims {
-  ///     numScalableDims: varint,
+  ///     scalableDims: bool[],
   ///     shape: svarint[],
   ///     elementType: Type
   ///   }
   /// Variant of VectorType with scalable dimensions.
     VectorTypeWithScalableDims = 20,
 }
+
+impl AttributeCode {
+    const ALL: [AttributeCode; 21] = [
+        Self::ArrayAttr, Self::DictionaryAttr, Self::StringAttribute,
+        Self::StringAttrWithType, Self::FlatSymbolRefAttr, Self::SymbolRefAttr,
+        Self::TypeAttr, Self::UnitAttr, Self::IntegerAttr, Self::FloatAttr,
+        Self::CallSiteLoc, Self::FileLineColLoc, Self::FusedLoc,
+        Self::FusedLocWithMetadata, Self::NameLoc, Self::UnknownLoc,
+        Self::DenseResourceElementsAttr, Self::DenseArrayAttr,
+        Self::DenseIntOrFPElementsAttr, Self::DenseStringElementsAttr,
+        Self::SparseElementsAttr
+    ];
+
+    /// Return the attribute code with the given value, if any.
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ALL.get(usize::try_from(code).ok()?).copied()
+    }
+}
+
+impl TypeCode {
+    const ALL: [TypeCode; 21] = [
+        Self::IntegerType, Self::IndexType, Self::FunctionType,
+        Self::BFloat16Type, Self::Float16Type, Self::Float32Type,
+        Self::Float64Type, Self::Float80Type, Self::Float128Type,
+        Self::ComplexType, Self::MemRef, Self::MemRefTypeWithMemSpace,
+        Self::NoneType, Self::RankedTensorType,
+        Self::RankedTensorTypeWithEncoding, Self::TupleType,
+        Self::UnrankedMemRefType, Self::UnrankedMemRefTypeWithMemSpace,
+        Self::UnrankedTensorType, Self::VectorType,
+        Self::VectorTypeWithScalableDims
+    ];
+
+    /// Return the type code with the given value, if any.
+    pub fn from_code(code: u64) -> Option<Self> {
+        Self::ALL.get(usize::try_from(code).ok()?).copied()
+    }
+}
+
+// ----------------------------------------------------------------------
+// Utility
+// ----------------------------------------------------------------------
+
+/// The bit width used to store the values of `index` typed attributes.
+const INDEX_STORAGE_BIT_WIDTH: u32 = 64;
+
+/// Returns the shape and element type of `type` if it is a shaped type whose elements may be densely stored.
+fn shape_and_element_type(r#type: &dyn Type) -> Option<(&[i64], &Arc<dyn Type>)> {
+    if let Some(r#type) = r#type.downcast_ref::<RankedTensorType>() {
+        return Some((r#type.shape(), r#type.element_type()));
+    }
+    r#type.downcast_ref::<VectorType>()
+        .map(|r#type| (r#type.shape(), r#type.element_type()))
+}
+
+/// Returns the number of bits used to store one element of `type` in dense elements data. `i1` elements are packed one bit each; all other elements are rounded up to whole bytes.
+fn dense_element_storage_width(r#type: &dyn Type) -> Option<usize> {
+    if let Some(r#type) = r#type.downcast_ref::<IntegerType>() {
+        return Some(match r#type.width() {
+            1 => 1,
+            width => width.next_multiple_of(8)
+        });
+    }
+    if r#type.downcast_ref::<IndexType>().is_some() {
+        return Some(INDEX_STORAGE_BIT_WIDTH as usize);
+    }
+    if let Some(r#type) = r#type.downcast_ref::<FloatType>() {
+        return Some(r#type.width().next_multiple_of(8));
+    }
+    let r#type = r#type.downcast_ref::<ComplexType>()?;
+    dense_element_storage_width(r#type.element_type().as_ref()).map(|width| width * 2)
+}
+
+/**
+Returns whether `raw_data` holds valid data for dense elements of the shaped `type`, along with whether the data is a splat.
+*/
+fn is_valid_raw_buffer(r#type: &dyn Type, raw_data: &[u8]) -> Option<bool> {
+    let (shape, element_type) = shape_and_element_type(r#type)?;
+    let storage_width = dense_element_storage_width(element_type.as_ref())?;
+    let raw_buffer_width = raw_data.len() * 8;
+    let num_elements = shape.iter().product::<i64>();
+    let num_elements = usize::try_from(num_elements).ok()?;
+
+    // Storage width of 1 is special as it is packed by the bit.
+    if storage_width == 1 {
+        // Check for a splat, or a buffer equal to the number of elements which consists of either all 0's or all 1's.
+        if let [0x00 | 0xff] = raw_data {
+            return Some(true);
+        }
+        // This is a valid non-splat buffer if it has the right size.
+        return (raw_buffer_width == num_elements.next_multiple_of(8))
+            .then_some(num_elements == 1);
+    }
+
+    // All other types are 8-bit aligned, so we can just check the buffer width to know if only a single initializer element was passed in.
+    if raw_buffer_width == storage_width {
+        return Some(true);
+    }
+
+    // The raw buffer is valid if it has the right size.
+    (raw_buffer_width == storage_width * num_elements).then_some(num_elements == 1)
+}
+
+/// Return the type of a float type of the given kind.
+fn float_type(kind: FloatTypeKind) -> Arc<dyn Type> {
+    Arc::new(FloatType::new(kind))
+}
+
+// ----------------------------------------------------------------------
+// BuiltinBytecodeInterface
+// ----------------------------------------------------------------------
+
+/**
+This class implements the bytecode interface for the builtin dialect. It is always available to the bytecode reader and writer.
+*/
+pub struct BuiltinBytecodeInterface;
+
+impl BytecodeDialectInterface for BuiltinBytecodeInterface {
+    fn dialect(&self) -> &str {
+        "builtin"
+    }
+
+    // ----------------------------------------------------------------------
+    // Attributes: Reader
+    // ----------------------------------------------------------------------
+
+    fn read_attribute(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Arc<dyn Attribute>, Box<dyn Error>>
+    {
+        let code = reader.read_var_int()?;
+        let Some(code) = AttributeCode::from_code(code) else {
+            return Err(reader.emit_error(&format!(
+                "unknown builtin attribute code: {}", code)));
+        };
+        Ok(match code {
+            AttributeCode::ArrayAttr =>
+                Arc::new(ArrayAttribute::new(reader.read_attributes()?)),
+            AttributeCode::DictionaryAttr => {
+                let attributes = reader.read_list(|reader| {
+                    let name = reader.read_attribute_of::<StringAttribute>()?;
+                    let value = reader.read_attribute()?;
+                    Ok(NamedAttribute::new(name, value))
+                })?;
+                Arc::new(DictionaryAttribute::new(attributes))
+            }
+            AttributeCode::StringAttribute =>
+                Arc::new(StringAttribute::new(reader.read_string()?)),
+            AttributeCode::StringAttrWithType => {
+                let value = reader.read_string()?;
+                let r#type = reader.read_type()?;
+                Arc::new(StringAttribute::with_type(value, r#type))
+            }
+            AttributeCode::FlatSymbolRefAttr => {
+                let root = reader.read_attribute_of::<StringAttribute>()?;
+                Arc::new(SymbolRefAttribute::new(root, Vec::new()))
+            }
+            AttributeCode::SymbolRefAttr => {
+                let root = reader.read_attribute_of::<StringAttribute>()?;
+                let nested = reader.read_list(|reader| {
+                    let reference = reader.read_attribute_of::<SymbolRefAttribute>()?;
+                    if !reference.nested_references().is_empty() {
+                        return Err(reader.emit_error(
+                            "expected a flat symbol reference in SymbolRefAttr"));
+                    }
+                    Ok(reference.root_reference().clone())
+                })?;
+                Arc::new(SymbolRefAttribute::new(root, nested))
+            }
+            AttributeCode::TypeAttr =>
+                Arc::new(TypeAttribute::new(reader.read_type()?)),
+            AttributeCode::UnitAttr => Arc::new(UnitAttribute::new()),
+            AttributeCode::IntegerAttr => {
+                let r#type = reader.read_type()?;
+                // Extract the value storage width from the type.
+                let bit_width = if let Some(integer) = r#type.downcast_ref::<IntegerType>() {
+                    integer.width() as u32
+                } else if r#type.downcast_ref::<IndexType>().is_some() {
+                    INDEX_STORAGE_BIT_WIDTH
+                } else {
+                    return Err(reader.emit_error(&format!(
+                        "expected integer or index type for IntegerAttr, but got: {}",
+                        r#type.as_ref())));
+                };
+                let value = reader.read_ap_int_with_known_width(bit_width)?;
+                Arc::new(IntegerAttribute::new(r#type, value))
+            }
+            AttributeCode::FloatAttr => {
+                let r#type = reader.read_type()?;
+                let Some(float) = r#type.downcast_ref::<FloatType>() else {
+                    return Err(reader.emit_error(&format!(
+                        "expected float type for FloatAttr, but got: {}",
+                        r#type.as_ref())));
+                };
+                let bits = reader.read_ap_int_with_known_width(float.width() as u32)?;
+                Arc::new(FloatAttribute::new(r#type, bits))
+            }
+            AttributeCode::DenseResourceElementsAttr => {
+                let r#type = reader.read_type()?;
+                let handle = reader.read_resource_handle()?;
+                Arc::new(DenseResourceElementsAttribute::new(r#type, handle))
+            }
+            AttributeCode::DenseArrayAttr => {
+                let element_type = reader.read_type()?;
+                let size = reader.read_var_int()?;
+                let data = reader.read_blob()?;
+                Arc::new(DenseArrayAttribute::new(element_type, size as i64, data))
+            }
+            AttributeCode::DenseIntOrFPElementsAttr => {
+                let r#type = reader.read_type()?;
+                let data = reader.read_blob()?;
+                let Some(is_splat) = is_valid_raw_buffer(r#type.as_ref(), &data) else {
+                    return Err(reader.emit_error(&format!(
+                        "invalid data for DenseIntOrFPElementsAttr of type: {}",
+                        r#type.as_ref())));
+                };
+                Arc::new(DenseIntOrFPElementsAttribute::new(r#type, data, is_splat))
+            }
+            // TODO: Read dense string and sparse elements once their attributes can be constructed.
+            AttributeCode::DenseStringElementsAttr
+            | AttributeCode::SparseElementsAttr => return Err(reader.emit_error(
+                &format!("unsupported builtin attribute code: {:?}", code))),
+            AttributeCode::CallSiteLoc
+            | AttributeCode::FileLineColLoc
+            | AttributeCode::FusedLoc
+            | AttributeCode::FusedLocWithMetadata
+            | AttributeCode::NameLoc
+            | AttributeCode::UnknownLoc => return Err(reader.emit_error(
+                "expected an attribute, but got a location")),
+        })
+    }
+
+    fn read_location(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Location, Box<dyn Error>>
+    {
+        let code = reader.read_var_int()?;
+        Ok(match AttributeCode::from_code(code) {
+            Some(AttributeCode::CallSiteLoc) => {
+                let callee = reader.read_location()?;
+                let caller = reader.read_location()?;
+                Location::call_site(callee, caller)
+            }
+            Some(AttributeCode::FileLineColLoc) => {
+                let filename = reader.read_attribute_of::<StringAttribute>()?;
+                let line = reader.read_var_int()?;
+                let column = reader.read_var_int()?;
+                Location::file_line_col(filename, line as usize, column as usize)
+            }
+            Some(AttributeCode::FusedLoc) =>
+                Location::fused(reader.read_locations()?, None),
+            Some(AttributeCode::FusedLocWithMetadata) => {
+                let locations = reader.read_locations()?;
+                let metadata = reader.read_attribute()?;
+                Location::fused(locations, Some(metadata))
+            }
+            Some(AttributeCode::NameLoc) => {
+                let name = reader.read_attribute_of::<StringAttribute>()?;
+                let child = reader.read_location()?;
+                Location::name(name, child)
+            }
+            Some(AttributeCode::UnknownLoc) => Location::unknown(),
+            _ => return Err(reader.emit_error(&format!(
+                "unknown builtin location code: {}", code)))
+        })
+    }
+
+    // ----------------------------------------------------------------------
+    // Attributes: Writer
+    // ----------------------------------------------------------------------
+
+    fn write_attribute(
+        &self,
+        attribute: &dyn Attribute,
+        writer: &mut dyn DialectBytecodeWriter
+    ) -> LogicalResult {
+        if let Some(attribute) = attribute.downcast_ref::<ArrayAttribute>() {
+            writer.write_var_int(AttributeCode::ArrayAttr as u64);
+            writer.write_attributes(attribute.value());
+        } else if let Some(attribute) = attribute.downcast_ref::<DictionaryAttribute>() {
+            writer.write_var_int(AttributeCode::DictionaryAttr as u64);
+            writer.write_list(attribute.value(), |writer, named| {
+                writer.write_attribute(&(Arc::new(named.name().clone()) as Arc<dyn Attribute>));
+                writer.write_attribute(named.value());
+            });
+        } else if let Some(attribute) = attribute.downcast_ref::<StringAttribute>() {
+            // We only encode the type if it isn't the default NoneType.
+            match attribute.r#type()
+                .filter(|r#type| r#type.downcast_ref::<NoneType>().is_none())
+            {
+                None => {
+                    writer.write_var_int(AttributeCode::StringAttribute as u64);
+                    writer.write_owned_string(attribute.value());
+                }
+                Some(r#type) => {
+                    writer.write_var_int(AttributeCode::StringAttrWithType as u64);
+                    writer.write_owned_string(attribute.value());
+                    writer.write_type(r#type);
+                }
+            }
+        } else if let Some(attribute) = attribute.downcast_ref::<SymbolRefAttribute>() {
+            let root: Arc<dyn Attribute> = Arc::new(attribute.root_reference().clone());
+            let nested = attribute.nested_references();
+            // Check for a flat symbol reference.
+            if nested.is_empty() {
+                writer.write_var_int(AttributeCode::FlatSymbolRefAttr as u64);
+                writer.write_attribute(&root);
+            } else {
+                writer.write_var_int(AttributeCode::SymbolRefAttr as u64);
+                writer.write_attribute(&root);
+                writer.write_list(nested, |writer, reference| {
+                    let reference: Arc<dyn Attribute> = Arc::new(
+                        SymbolRefAttribute::new(reference.clone(), Vec::new()));
+                    writer.write_attribute(&reference);
+                });
+            }
+        } else if let Some(attribute) = attribute.downcast_ref::<TypeAttribute>() {
+            writer.write_var_int(AttributeCode::TypeAttr as u64);
+            writer.write_type(attribute.value());
+        } else if attribute.downcast_ref::<UnitAttribute>().is_some() {
+            writer.write_var_int(AttributeCode::UnitAttr as u64);
+        } else if let Some(attribute) = attribute.downcast_ref::<IntegerAttribute>() {
+            writer.write_var_int(AttributeCode::IntegerAttr as u64);
+            writer.write_type(attribute.r#type());
+            writer.write_ap_int_with_known_width(attribute.value());
+        } else if let Some(attribute) = attribute.downcast_ref::<FloatAttribute>() {
+            writer.write_var_int(AttributeCode::FloatAttr as u64);
+            writer.write_type(attribute.r#type());
+            writer.write_ap_int_with_known_width(attribute.bits());
+        } else if let Some(attribute)
+            = attribute.downcast_ref::<DenseResourceElementsAttribute>()
+        {
+            writer.write_var_int(AttributeCode::DenseResourceElementsAttr as u64);
+            writer.write_type(attribute.r#type());
+            writer.write_resource_handle(attribute.raw_handle());
+        } else if let Some(attribute) = attribute.downcast_ref::<DenseArrayAttribute>() {
+            writer.write_var_int(AttributeCode::DenseArrayAttr as u64);
+            writer.write_type(attribute.element_type());
+            writer.write_var_int(attribute.size() as u64);
+            writer.write_owned_blob(attribute.raw_data());
+        } else if let Some(attribute)
+            = attribute.downcast_ref::<DenseIntOrFPElementsAttribute>()
+        {
+            writer.write_var_int(AttributeCode::DenseIntOrFPElementsAttr as u64);
+            writer.write_type(attribute.r#type());
+            writer.write_owned_blob(attribute.raw_data());
+        } else {
+            return Err("attribute has no builtin bytecode encoding".into());
+        }
+        Ok(())
+    }
+
+    fn write_location(
+        &self,
+        location: &Location,
+        writer: &mut dyn DialectBytecodeWriter
+    ) -> LogicalResult {
+        match location {
+            Location::CallSite(location) => {
+                writer.write_var_int(AttributeCode::CallSiteLoc as u64);
+                writer.write_location(location.callee());
+                writer.write_location(location.caller());
+            }
+            Location::FileLineCol(location) => {
+                writer.write_var_int(AttributeCode::FileLineColLoc as u64);
+                writer.write_attribute(
+                    &(Arc::new(StringAttribute::new(location.filename())) as Arc<dyn Attribute>));
+                writer.write_var_int(location.line() as u64);
+                writer.write_var_int(location.column() as u64);
+            }
+            Location::Fused(location) => match location.metadata() {
+                Some(metadata) => {
+                    writer.write_var_int(AttributeCode::FusedLocWithMetadata as u64);
+                    writer.write_locations(location.locations());
+                    writer.write_attribute(metadata);
+                }
+                None => {
+                    writer.write_var_int(AttributeCode::FusedLoc as u64);
+                    writer.write_locations(location.locations());
+                }
+            },
+            Location::Name(location) => {
+                writer.write_var_int(AttributeCode::NameLoc as u64);
+                writer.write_attribute(
+                    &(Arc::new(StringAttribute::new(location.name())) as Arc<dyn Attribute>));
+                writer.write_location(location.child_location());
+            }
+            Location::Unknown => writer.write_var_int(AttributeCode::UnknownLoc as u64),
+            Location::Opaque(_) =>
+                return Err("opaque locations have no builtin bytecode encoding".into())
+        }
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Types: Reader
+    // ----------------------------------------------------------------------
+
+    fn read_type(&self, reader: &mut dyn DialectBytecodeReader)
+        -> Result<Arc<dyn Type>, Box<dyn Error>>
+    {
+        let code = reader.read_var_int()?;
+        let Some(code) = TypeCode::from_code(code) else {
+            return Err(reader.emit_error(&format!(
+                "unknown builtin type code: {}", code)));
+        };
+        Ok(match code {
+            TypeCode::IntegerType => {
+                let width_and_signedness = reader.read_var_int()?;
+                let signedness = match width_and_signedness & 0x3 {
+                    0 => Signedness::Signless,
+                    1 => Signedness::Signed,
+                    2 => Signedness::Unsigned,
+                    _ => return Err(reader.emit_error(
+                        "invalid signedness for IntegerType"))
+                };
+                Arc::new(IntegerType::new(
+                    (width_and_signedness >> 2) as usize, signedness))
+            }
+            TypeCode::IndexType => Arc::new(IndexType::new()),
+            TypeCode::FunctionType => {
+                let inputs = reader.read_types()?;
+                let outputs = reader.read_types()?;
+                Arc::new(FunctionType::new(inputs, outputs))
+            }
+            TypeCode::BFloat16Type => float_type(FloatTypeKind::BFloat16Type),
+            TypeCode::Float16Type => float_type(FloatTypeKind::Float16Type),
+            TypeCode::Float32Type => float_type(FloatTypeKind::Float32Type),
+            TypeCode::Float64Type => float_type(FloatTypeKind::Float64Type),
+            TypeCode::Float80Type => float_type(FloatTypeKind::Float80Type),
+            TypeCode::Float128Type => float_type(FloatTypeKind::Float128Type),
+            TypeCode::ComplexType =>
+                Arc::new(ComplexType::new(reader.read_type()?)),
+            TypeCode::NoneType => Arc::new(NoneType),
+            TypeCode::RankedTensorType => {
+                let shape = reader.read_signed_var_ints()?;
+                let element_type = reader.read_type()?;
+                Arc::new(RankedTensorType::new(shape, element_type, None))
+            }
+            TypeCode::RankedTensorTypeWithEncoding => {
+                let encoding = reader.read_attribute()?;
+                let shape = reader.read_signed_var_ints()?;
+                let element_type = reader.read_type()?;
+                Arc::new(RankedTensorType::new(shape, element_type, Some(encoding)))
+            }
+            TypeCode::TupleType => Arc::new(TupleType::new(reader.read_types()?)),
+            TypeCode::UnrankedTensorType =>
+                Arc::new(UnrankedTensorType::new(reader.read_type()?)),
+            TypeCode::MemRef => {
+                let shape = reader.read_signed_var_ints()?;
+                let element_type = reader.read_type()?;
+                let layout = reader.read_attribute()?;
+                Arc::new(MemRefType::new(shape, element_type, Some(layout), None))
+            }
+            TypeCode::MemRefTypeWithMemSpace => {
+                let memory_space = reader.read_attribute()?;
+                let shape = reader.read_signed_var_ints()?;
+                let element_type = reader.read_type()?;
+                let layout = reader.read_attribute()?;
+                Arc::new(MemRefType::new(
+                    shape, element_type, Some(layout), Some(memory_space)))
+            }
+            TypeCode::UnrankedMemRefType =>
+                Arc::new(UnrankedMemRefType::new(reader.read_type()?, None)),
+            TypeCode::UnrankedMemRefTypeWithMemSpace => {
+                let memory_space = reader.read_attribute()?;
+                let element_type = reader.read_type()?;
+                Arc::new(UnrankedMemRefType::new(element_type, Some(memory_space)))
+            }
+            TypeCode::VectorType => {
+                let shape = reader.read_signed_var_ints()?;
+                let element_type = reader.read_type()?;
+                Arc::new(VectorType::new(shape, element_type, Vec::new()))
+            }
+            TypeCode::VectorTypeWithScalableDims => {
+                let scalable_dims = reader.read_list(|reader| reader.read_bool())?;
+                let shape = reader.read_signed_var_ints()?;
+                if scalable_dims.len() != shape.len() {
+                    return Err(reader.emit_error(
+                        "invalid number of scalable dimensions for VectorType"));
+                }
+                let element_type = reader.read_type()?;
+                Arc::new(VectorType::new(shape, element_type, scalable_dims))
+            }
+        })
+    }
+
+    // ----------------------------------------------------------------------
+    // Types: Writer
+    // ----------------------------------------------------------------------
+
+    fn write_type(
+        &self,
+        r#type: &dyn Type,
+        writer: &mut dyn DialectBytecodeWriter
+    ) -> LogicalResult {
+        if let Some(r#type) = r#type.downcast_ref::<IntegerType>() {
+            let signedness = match r#type.signedness() {
+                Signedness::Signless => 0,
+                Signedness::Signed => 1,
+                Signedness::Unsigned => 2
+            };
+            writer.write_var_int(TypeCode::IntegerType as u64);
+            writer.write_var_int(((r#type.width() as u64) << 2) | signedness);
+        } else if r#type.downcast_ref::<IndexType>().is_some() {
+            writer.write_var_int(TypeCode::IndexType as u64);
+        } else if let Some(r#type) = r#type.downcast_ref::<FunctionType>() {
+            writer.write_var_int(TypeCode::FunctionType as u64);
+            writer.write_types(r#type.inputs());
+            writer.write_types(r#type.outputs());
+        } else if let Some(r#type) = r#type.downcast_ref::<FloatType>() {
+            let code = match r#type.kind() {
+                FloatTypeKind::BFloat16Type => TypeCode::BFloat16Type,
+                FloatTypeKind::Float16Type => TypeCode::Float16Type,
+                FloatTypeKind::Float32Type => TypeCode::Float32Type,
+                FloatTypeKind::Float64Type => TypeCode::Float64Type,
+                FloatTypeKind::Float80Type => TypeCode::Float80Type,
+                FloatTypeKind::Float128Type => TypeCode::Float128Type,
+                // The 8-bit float types have no builtin encoding, and use the textual format.
+                FloatTypeKind::Float8E5M2Type | FloatTypeKind::Float8E4M3FNType =>
+                    return Err("type has no builtin bytecode encoding".into())
+            };
+            writer.write_var_int(code as u64);
+        } else if let Some(r#type) = r#type.downcast_ref::<ComplexType>() {
+            writer.write_var_int(TypeCode::ComplexType as u64);
+            writer.write_type(r#type.element_type());
+        } else if r#type.downcast_ref::<NoneType>().is_some() {
+            writer.write_var_int(TypeCode::NoneType as u64);
+        } else if let Some(r#type) = r#type.downcast_ref::<RankedTensorType>() {
+            match r#type.encoding() {
+                Some(encoding) => {
+                    writer.write_var_int(TypeCode::RankedTensorTypeWithEncoding as u64);
+                    writer.write_attribute(encoding);
+                }
+                None => writer.write_var_int(TypeCode::RankedTensorType as u64)
+            }
+            writer.write_signed_var_ints(r#type.shape());
+            writer.write_type(r#type.element_type());
+        } else if let Some(r#type) = r#type.downcast_ref::<TupleType>() {
+            writer.write_var_int(TypeCode::TupleType as u64);
+            writer.write_types(r#type.types());
+        } else if let Some(r#type) = r#type.downcast_ref::<UnrankedTensorType>() {
+            writer.write_var_int(TypeCode::UnrankedTensorType as u64);
+            writer.write_type(r#type.element_type());
+        } else if let Some(r#type) = r#type.downcast_ref::<MemRefType>() {
+            match r#type.memory_space() {
+                Some(memory_space) => {
+                    writer.write_var_int(TypeCode::MemRefTypeWithMemSpace as u64);
+                    writer.write_attribute(memory_space);
+                }
+                None => writer.write_var_int(TypeCode::MemRef as u64)
+            }
+            writer.write_signed_var_ints(r#type.shape());
+            writer.write_type(r#type.element_type());
+            // The layout is always encoded, the identity layout as an identity affine map.
+            let layout = r#type.layout().cloned().unwrap_or_else(||
+                Arc::new(AffineMapAttribute::new(
+                    multi_dim_identity_map(r#type.rank(), core::ptr::null_mut()))));
+            writer.write_attribute(&layout);
+        } else if let Some(r#type) = r#type.downcast_ref::<UnrankedMemRefType>() {
+            match r#type.memory_space() {
+                Some(memory_space) => {
+                    writer.write_var_int(
+                        TypeCode::UnrankedMemRefTypeWithMemSpace as u64);
+                    writer.write_attribute(memory_space);
+                }
+                None => writer.write_var_int(TypeCode::UnrankedMemRefType as u64)
+            }
+            writer.write_type(r#type.element_type());
+        } else if let Some(r#type) = r#type.downcast_ref::<VectorType>() {
+            if r#type.is_scalable() {
+                writer.write_var_int(TypeCode::VectorTypeWithScalableDims as u64);
+                writer.write_list(r#type.scalable_dims(),
+                    |writer, scalable| writer.write_owned_bool(*scalable));
+            } else {
+                writer.write_var_int(TypeCode::VectorType as u64);
+            }
+            writer.write_signed_var_ints(r#type.shape());
+            writer.write_type(r#type.element_type());
+        } else {
+            return Err("type has no builtin bytecode encoding".into());
+        }
+        Ok(())
+    }
+
+    // ----------------------------------------------------------------------
+    // Properties
+    // ----------------------------------------------------------------------
+
+    fn read_properties(
+        &self,
+        name: &OperationName,
+        reader: &mut dyn DialectBytecodeReader
+    ) -> Result<Vec<NamedAttribute>, Box<dyn Error>> {
+        let Some(property_names) = self.property_names(name) else {
+            return Err(reader.emit_error(&format!(
+                "has properties but missing BytecodeOpInterface for {}", name.as_str())));
+        };
+
+        // The properties of the builtin operations are all optional attributes, which are encoded in the order of their names.
+        let mut attributes = Vec::new();
+        for property_name in property_names {
+            if let Some(attribute) = reader.read_optional_attribute()? {
+                attributes.push(NamedAttribute::new(
+                    StringAttribute::new(*property_name), attribute));
+            }
+        }
+        Ok(attributes)
+    }
+
+    fn property_names(&self, name: &OperationName) -> Option<&'static [&'static str]> {
+        match name.as_str() {
+            ModuleOp::OPERATION_NAME => Some(&["sym_name", "sym_visibility"]),
+            _ => None
+        }
+    }
+
+    fn write_properties(
+        &self,
+        operation: &Operation,
+        writer: &mut dyn DialectBytecodeWriter
+    ) {
+        let attributes = operation.attr_dictionary();
+        for property_name in self.property_names(operation.name()).unwrap_or_default() {
+            writer.write_optional_attribute(attributes.get(property_name));
+        }
+    }
+}
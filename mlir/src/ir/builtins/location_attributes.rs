@@ -10,6 +10,7 @@ use core::{
     any::TypeId,
     convert::From
 };
+use std::sync::Arc;
 
 use crate::mlir::ir::{
     attribute::Attribute,
@@ -45,6 +46,20 @@ pub struct CallSiteLocation {
     caller: Location
 }
 
+impl CallSiteLocation {
+    pub fn new(callee: Location, caller: Location) -> Self {
+        Self { callee, caller }
+    }
+
+    pub fn callee(&self) -> &Location {
+        &self.callee
+    }
+
+    pub fn caller(&self) -> &Location {
+        &self.caller
+    }
+}
+
 /// A file:line:column source location.
 ///
 /// # Syntax
@@ -67,6 +82,24 @@ pub struct FileLineColLocation {
     column: usize
 }
 
+impl FileLineColLocation {
+    pub fn new(filename: StringAttribute, line: usize, column: usize) -> Self {
+        Self { filename, line, column }
+    }
+
+    pub fn filename(&self) -> &str {
+        self.filename.value()
+    }
+
+    pub fn line(&self) -> usize {
+        self.line
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+}
+
 /// A tuple of other source locations.
 ///
 /// # Syntax
@@ -84,9 +117,26 @@ pub struct FileLineColLocation {
 /// loc(fused["mysource.cc":10:8, "mysource.cc":22:8)
 /// loc(fused<"CSE">["mysource.cc":10:8, "mysource.cc":22:8])
 /// ```
-pub struct FusedLocation<M: Attribute> {
-    locations: &'static [Location],
-    metadata: M
+pub struct FusedLocation {
+    locations: Vec<Location>,
+    metadata: Option<Arc<dyn Attribute>>
+}
+
+impl FusedLocation {
+    pub fn new(
+        locations: Vec<Location>,
+        metadata: Option<Arc<dyn Attribute>>
+    ) -> Self {
+        Self { locations, metadata }
+    }
+
+    pub fn locations(&self) -> &[Location] {
+        &self.locations
+    }
+
+    pub fn metadata(&self) -> Option<&Arc<dyn Attribute>> {
+        self.metadata.as_ref()
+    }
 }
 
 /// A named source location.
@@ -109,6 +159,20 @@ pub struct NameLocation {
     child_location: Location
 }
 
+impl NameLocation {
+    pub fn new(name: StringAttribute, child_location: Location) -> Self {
+        Self { name, child_location }
+    }
+
+    pub fn name(&self) -> &str {
+        self.name.value()
+    }
+
+    pub fn child_location(&self) -> &Location {
+        &self.child_location
+    }
+}
+
 /// An opaque source location.
 ///
 /// An instance of this location essentially contains a pointer to some data structure that is external to MLIR and an optional location that can be used if the first one is not suitable. Since it contains an external structure, only the optional location is used during serialisation.
@@ -119,6 +183,11 @@ pub struct OpaqueLocation {
 }
 
 impl OpaqueLocation {
+    /// Returns the location used when the underlying location cannot be interpreted, e.g. when printing.
+    pub fn fallback_location(&self) -> &Location {
+        &self.fallback_location
+    }
+
     // /**
     // Returns an instance of opaque location which contains a given pointer to
     // an object. The corresponding MLIR location is set to UnknownLoc.
@@ -180,5 +249,7 @@ impl OpaqueLocation {
 pub struct UnknownLocation;
 
 impl UnknownLocation {
-    pub fn get(context: Option<MLIRContext>) -> Self;
+    pub fn get(context: Option<MLIRContext>) -> Location {
+        Location::Unknown
+    }
 }
@@ -8,10 +8,17 @@
 //!   - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/BuiltinOps.h>
 //!   - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/BuiltinOps.td>
 
+use std::sync::Arc;
+
 use llvm::support::pointer_like_type_traits;
 use crate::{
     ir::{
-        operation::implementation,
+        arena::{ArenaResult, BlockId, IRArena, OpId},
+        attribute::NamedAttribute,
+        builtins::attributes::{DictionaryAttribute, StringAttribute},
+        location::Location,
+        mlir_context::MLIRContext,
+        operation::{Operation, implementation},
         owning_op_ref,
         region_kind_interface,
         symbol_table
@@ -34,6 +41,66 @@ use crate::{
 ///     func.func @foo()
 /// }
 /// ```
-pub struct Module {
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ModuleOp {
+    operation: OpId
+}
+
+impl ModuleOp {
+    pub const OPERATION_NAME: &'static str = "builtin.module";
+
+    /// The name of the attribute holding the symbol name of the module.
+    const SYM_NAME: &'static str = "sym_name";
 
+    /// Create an empty module with an optional symbol `name`. The module holds a single region with a single block.
+    pub fn build(
+        context: &mut MLIRContext,
+        location: Location,
+        name: Option<&str>
+    ) -> ArenaResult<Self> {
+        let attributes = name.map(|name| NamedAttribute::new(
+            StringAttribute::new(Self::SYM_NAME),
+            Arc::new(StringAttribute::new(name))));
+        let operation = Operation::new(
+            location,
+            context.operation_name(Self::OPERATION_NAME),
+            [],
+            Vec::new(),
+            DictionaryAttribute::new(attributes.into_iter().collect()),
+            &[]
+        );
+        let arena = context.arena_mut();
+        let operation = arena.create_operation(operation, 1)?;
+        let body = arena.create_block();
+        let region = arena.op(operation)?.region(0);
+        arena.push_back_block(region, body)?;
+        Ok(Self { operation })
+    }
+
+    /// Wrap `operation` if it is a `builtin.module`.
+    pub fn from_operation(arena: &IRArena, operation: OpId)
+        -> ArenaResult<Option<Self>>
+    {
+        Ok((arena.op(operation)?.name().as_str() == Self::OPERATION_NAME)
+            .then_some(Self { operation }))
+    }
+
+    /// Return the handle of the underlying operation.
+    pub fn operation(&self) -> OpId {
+        self.operation
+    }
+
+    /// Return the single block of the body of the module.
+    pub fn body(&self, arena: &IRArena) -> ArenaResult<BlockId> {
+        let region = arena.op(self.operation)?.region(0);
+        Ok(arena.region(region)?.front()
+            .expect("Expected the body of a module to hold a block"))
+    }
+
+    /// Return the symbol name of the module, if it has one.
+    pub fn name(&self, arena: &IRArena) -> ArenaResult<Option<String>> {
+        Ok(arena.op(self.operation)?.attr_dictionary().get(Self::SYM_NAME)
+            .and_then(|name| name.downcast_ref::<StringAttribute>())
+            .map(|name| name.value().to_string()))
+    }
 }
@@ -7,9 +7,13 @@
 //!   - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/BuiltinTypeInterfaces.td>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/BuiltinTypeInterfaces.cpp>
 
-use llvm::adt::sequence;
+use std::sync::Arc;
+
 use crate::ir::{
-    builtins::types::ComplexType,
+    builtins::types::{
+        ComplexType, FloatType, IntegerType, MemRefType, RankedTensorType,
+        UnrankedMemRefType, UnrankedTensorType, VectorType
+    },
     diagnostics,
     operation::base::TypeInterface,
     r#type::Type
@@ -37,7 +41,31 @@ pub trait MemRefElementTypeInterface: TypeInterface {
 // ShapedType
 // ----------------------------------------------------------------------
 
-const DYNAMIC: i64 = i64::MIN;
+pub const DYNAMIC: i64 = i64::MIN;
+
+/// Whether the given dimension size indicates a dynamic dimension.
+pub const fn is_dynamic(size: i64) -> bool {
+    size == DYNAMIC
+}
+
+/// Whether the given shape has any size that indicates a dynamic dimension.
+pub fn is_dynamic_shape(shape: &[i64]) -> bool {
+    shape.iter().any(|&size| is_dynamic(size))
+}
+
+/// Whether the given shape has no size that indicates a dynamic dimension.
+pub fn is_static_shape(shape: &[i64]) -> bool {
+    !is_dynamic_shape(shape)
+}
+
+/// Returns the number of elements present in the given static shape.
+pub fn num_elements(shape: &[i64]) -> i64 {
+    shape.iter().fold(1i64, |num, &size| {
+        assert!(!is_dynamic(size), "Cannot count the elements of a dynamic shape");
+        num.checked_mul(size)
+            .expect("Integer overflow in element count computation.")
+    })
+}
 
 /// This interface provides a common API for interacting with multi-dimensional container types. These types contain a shape and an element type.
 ///
@@ -45,13 +73,12 @@ const DYNAMIC: i64 = i64::MIN;
 /// If the number of dimensions in the shape is unknown, the shape is 'unranked'.
 /// If the number of dimensions is known, the shape 'ranked'. The sizes of the dimensions of the shape must be positive, or kDynamic (in which case the size of the dimension is dynamic, or not statically known).
 pub trait ShapedType: TypeInterface {
-    // const ELEMENT: Type;
-
     /// Returns a clone of this type with the given shape and element type. If a shape is not provided, the current shape of the type is used.
-    fn clone_with(&self, shape: Option<&[i64]>, element_type: Type) -> Self;
+    fn clone_with(&self, shape: Option<&[i64]>, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>;
 
     /// Returns the element type of this shaped type.
-    fn element_type(&self) -> Type;
+    fn element_type(&self) -> &Arc<dyn Type>;
 
     /// Returns if this type is ranked, i.e. it has a known number of dimensions.
     fn has_rank(&self) -> bool;
@@ -59,114 +86,119 @@ pub trait ShapedType: TypeInterface {
     /// Returns the shape of this type if it is ranked, otherwise asserts.
     fn shape(&self) -> &[i64];
 
-    /// Whether the given dimension size indicates a dynamic dimension.
-    fn is_dynamic(dValue: i64) -> bool{
-        dValue == DYNAMIC
-    }
-        
-    /// Whether the given shape has any size that indicates a dynamic dimension.
-    fn is_dynamic_shape(&self, dSizes: &[i64]) -> bool {
-        dSizes.any(|dSize: i64| { self.is_dynamic(dSize) })
-    }
-
-    /// Returns the number of elements present in the given shape.
-    fn num_elements(shape: &[i64]) -> i64 {
-        let mut num = 1;
-        for dim in shape {
-            num *= dim;
-            assert!(
-                num >= 0,
-                "Integer overflow in element count computation.");
-        }
-        num
-    }
-
     /// Returns the total amount of bits occupied by a value of this type. This does not take into account any memory layout or widening constraints, e.g. a vector<3xi57> may report to occupy 3x57=171 bit, even though in practice it will likely be stored as in a 4xi64 vector register. Fails with an assertion if the size cannot be computed statically, e.g. if the type has a dynamic shape or if its elemental type does not have a known bit width.
     fn size_in_bits(&self) -> i64 {
         assert!(
             self.has_static_shape(),
             "Cannot get the bit size of an aggregate with a dynamic shape");
 
-        let mut element_type = self.element_type();
-        if element_type.is_int_or_float() {
-            return element_type.get_int_or_float_bit_width() * self.num_elements();
+        let element_type = self.element_type();
+        if let Some(width) = int_or_float_bit_width(&**element_type) {
+            return width as i64 * self.num_elements();
         }
-
-        if let complex_type = element_type.dyn_cast::<ComplexType>() {
-            element_type = complex_type.element_type();
-            return element_type.get_int_or_float_bit_width() * self.num_elements() * 2;
+        if let Some(complex_type) = element_type.downcast_ref::<ComplexType>() {
+            let width = int_or_float_bit_width(&**complex_type.element_type())
+                .expect("Complex element type must be an integer or a float");
+            return width as i64 * self.num_elements() * 2;
         }
-        self.num_elements() * <element_type as ShapedType>.size_in_bits()
+        let shaped_type = as_shaped_type(&**element_type)
+            .expect("Element type must have a known bit width");
+        self.num_elements() * shaped_type.size_in_bits()
     }
 
-    /// Return a clone of this type with the given new shape and element type.
-    fn clone(&self, shape: &[i64], element_type: Type) -> Self {
-        self.clone_with(shape, element_type)
+    /// Return a clone of this type with the given new shape.
+    fn clone_with_shape(&self, shape: &[i64]) -> Arc<dyn Type> {
+        self.clone_with(Some(shape), self.element_type().clone())
     }
 
-    // /// Return a clone of this type with the given new shape.
-    // fn clone(&self, shape: &[i64]) -> Self {
-    //     self.clone_with(shape, self.element_type())
-    // }
+    /// Return a clone of this type with the given new element type.
+    fn clone_with_element_type(&self, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>
+    {
+        self.clone_with(None, element_type)
+    }
 
-    // /// Return a clone of this type with the given new element type.
-    // fn clone(&self, element_type: impl Type) -> Self {
-    //     self.clone_with(None, element_type)
-    // }
-    
     /// If an element type is an integer or a float, return its width. Otherwise, abort.
     fn element_type_bit_width(&self) -> usize {
-        return self.element_type().get_int_or_float_bit_width();
+        int_or_float_bit_width(&**self.element_type())
+            .expect("Element type must be an integer or a float")
     }
-    
+
     /// If this is a ranked type, return the rank. Otherwise, abort.
-    fn rank(&self) -> i64 {
+    fn rank(&self) -> usize {
         assert!(
             self.has_rank(),
             "Cannot query rank of unranked shaped type.");
-        self.shape().size();
+        self.shape().len()
     }
-    
+
     /// If it has static shape, return the number of elements. Otherwise, abort.
     fn num_elements(&self) -> i64 {
         assert!(
             self.has_static_shape(),
             "Cannot get element count of dynamic shaped type");
-        Self::num_elements(self.shape())
+        num_elements(self.shape())
     }
-    
+
     /// Returns true if this dimension has a dynamic size (for ranked types); aborts for unranked types.
     fn is_dynamic_dim(&self, index: usize) -> bool {
         assert!(index < self.rank(), "Invalid index for shaped type");
-        Self::is_dynamic(self.shape()[index])
+        is_dynamic(self.shape()[index])
     }
-    
+
     /// Returns if this type has a static shape, i.e. if the type is ranked and all dimensions have known size (>= 0).
     fn has_static_shape(&self) -> bool {
-        self.has_rank() && !Self::is_dynamic_shape(self.shape())
-    }
-    
-    // /// Returns if this type has a static shape and the shape is equal to `shape` return true.
-    // fn has_static_shape(&self, shape: &[i64]) -> bool {
-    //     self.has_static_shape() && self.shape() == shape
-    // }
-    
+        self.has_rank() && is_static_shape(self.shape())
+    }
+
+    /// Returns if this type has a static shape and the shape is equal to `shape` return true.
+    fn has_static_shape_of(&self, shape: &[i64]) -> bool {
+        self.has_static_shape() && self.shape() == shape
+    }
+
     /// If this is a ranked type, return the number of dimensions with dynamic size. Otherwise, abort.
-    fn num_dynamic_dims(&self) -> i64 {
-        llvm::count_if(self.shape(), Self::is_dynamic)
+    fn num_dynamic_dims(&self) -> usize {
+        self.shape().iter().filter(|&&size| is_dynamic(size)).count()
     }
-    
+
     /// If this is ranked type, return the size of the specified dimension. Otherwise, abort.
     fn dim_size(&self, index: usize) -> i64 {
         assert!(index < self.rank(), "Invalid index for shaped type");
         self.shape()[index]
     }
-    
+
     /// Returns the position of the dynamic dimension relative to just the dynamic dimensions, given its `index` within the shape.
     fn dynamic_dim_index(&self, index: usize) -> usize {
         assert!(index < self.rank(), "Invalid index");
-        assert!(Self::is_dynamic(self.dim_size(index)), "invalid index");
-        return llvm::count_if(self.shape().take_front(index),
-                                Self::is_dynamic);
+        assert!(is_dynamic(self.dim_size(index)), "invalid index");
+        self.shape()[..index].iter().filter(|&&size| is_dynamic(size)).count()
+    }
+}
+
+/// Returns the shaped type interface of `r#type` if it is one of the builtin shaped types.
+pub fn as_shaped_type(r#type: &dyn Type) -> Option<&dyn ShapedType> {
+    if let Some(r#type) = r#type.downcast_ref::<RankedTensorType>() {
+        return Some(r#type);
+    }
+    if let Some(r#type) = r#type.downcast_ref::<UnrankedTensorType>() {
+        return Some(r#type);
+    }
+    if let Some(r#type) = r#type.downcast_ref::<VectorType>() {
+        return Some(r#type);
+    }
+    if let Some(r#type) = r#type.downcast_ref::<MemRefType>() {
+        return Some(r#type);
+    }
+    if let Some(r#type) = r#type.downcast_ref::<UnrankedMemRefType>() {
+        return Some(r#type);
+    }
+    None
+}
+
+/// Returns the bit width of `r#type` if it is an integer or a float.
+fn int_or_float_bit_width(r#type: &dyn Type) -> Option<usize> {
+    if let Some(r#type) = r#type.downcast_ref::<IntegerType>() {
+        return Some(r#type.width());
     }
+    r#type.downcast_ref::<FloatType>().map(FloatType::width)
 }
@@ -9,34 +9,41 @@
 
 use core::{
     any::Any,
+    fmt::{Display, Formatter, Result as FmtResult},
     iter::IntoIterator,
     slice::Iter
 };
+use std::{
+    collections::BTreeSet,
+    sync::Arc
+};
 
 use llvm::adt::{
     ap_float::*,
     bit_vector::BitVector,
-    dense_set::SmallDenseSet,
     sequence,
     small_vector::SmallVector,
     twine,
     type_switch,
 };
 use crate::ir::{
-    affine_expr::AffineExpr,
-    affine_map,
+    affine_expr::{self, AffineExpr, Kind, simplify_affine_expr},
+    affine_map::{self, AffineMap},
     attribute::Attribute,
     builtins::{
         attribute_interfaces,
-        attributes::StringAttribute,
+        attributes::{
+            AffineMapAttribute, IntegerAttribute, StridedLayoutAttribute,
+            StringAttribute
+        },
         // dialect,
-        type_interfaces::ShapedType
+        type_interfaces::{DYNAMIC, ShapedType, is_dynamic}
     },
     diagnostics,
     dialect,
     function::interfaces,
     mlir_context::MLIRContext,
-    operation::implementation,
+    operation::{base::TypeInterface, implementation},
     sub_element_interfaces,
     tensor_encoding,
     r#type::{
@@ -72,8 +79,21 @@ pub enum TypeKind {
 /// complex<f32>
 /// complex<i32>
 /// ```
+#[derive(Clone)]
 pub struct ComplexType {
-    element_type: Box<dyn Type>
+    element_type: Arc<dyn Type>
+}
+
+impl Type for ComplexType {}
+
+impl ComplexType {
+    pub fn new(element_type: Arc<dyn Type>) -> Self {
+        Self { element_type }
+    }
+
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -94,12 +114,17 @@ pub struct ComplexType {
 /// # Rationale
 ///
 /// Integers of platform-specific bit widths are practical to express sizes, dimensionalities and subscripts.
+#[derive(Clone)]
 pub struct IndexType {
 
 }
 
+impl Type for IndexType {}
+
 impl IndexType {
-    pub fn new(context: *mut MLIRContext) -> Self;
+    pub fn new() -> Self {
+        Self {}
+    }
 
     // /// Storage bit width used for IndexType by internal compiler data
     // /// structures.
@@ -129,15 +154,22 @@ impl IndexType {
 /// # Rationale
 ///
 /// Low precision integers (like `i2`, `i4` etc) are useful for low-precision inference chips, and arbitrary precision integers are useful for hardware synthesis (where a 13 bit multiplier is a lot cheaper/smaller than a 16 bit one).
+#[derive(Clone)]
 pub struct IntegerType {
     width: usize,
     signedness: Signedness
 }
 
+impl Type for IntegerType {}
+
 impl IntegerType {
+    pub fn new(width: usize, signedness: Signedness) -> Self {
+        Self { width, signedness }
+    }
+
     /// Integer representation maximal bitwidth.
     /// Note: This is aligned with the maximum width of IntegerType.
-    const MAX_WIDTH: usize = (1 << 24) - 1;
+    pub const MAX_WIDTH: usize = (1 << 24) - 1;
 
     pub const fn width(&self) -> usize {
         self.width
@@ -173,7 +205,7 @@ impl IntegerType {
 }
 
 /// Signedness semantics.
-#[repr(align(32))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Signedness {
     /// No signedness semantics
     Signless,
@@ -187,9 +219,36 @@ pub enum Signedness {
 // FloatType
 // ----------------------------------------------------------------------
 
-pub struct FloatType;
+#[derive(Clone)]
+pub struct FloatType {
+    kind: FloatTypeKind
+}
+
+impl Type for FloatType {}
 
 impl FloatType {
+    pub fn new(kind: FloatTypeKind) -> Self {
+        Self { kind }
+    }
+
+    pub fn kind(&self) -> FloatTypeKind {
+        self.kind
+    }
+
+    /// Return the keyword this type is spelled with in the assembly format.
+    pub fn keyword(&self) -> &'static str {
+        match self.kind {
+            FloatTypeKind::Float8E5M2Type => "f8E5M2",
+            FloatTypeKind::Float8E4M3FNType => "f8E4M3FN",
+            FloatTypeKind::Float16Type => "f16",
+            FloatTypeKind::BFloat16Type => "bf16",
+            FloatTypeKind::Float32Type => "f32",
+            FloatTypeKind::Float64Type => "f64",
+            FloatTypeKind::Float80Type => "f80",
+            FloatTypeKind::Float128Type => "f128"
+        }
+    }
+
     // Convenience factories.
     // static FloatType get_bf16(MLIRContext *ctx);
     // static FloatType get_f16(MLIRContext *ctx);
@@ -202,7 +261,16 @@ impl FloatType {
 
     /// Return the bitwidth of this float type.
     pub fn width(&self) -> usize {
-
+        match self.kind {
+            FloatTypeKind::Float8E5M2Type
+            | FloatTypeKind::Float8E4M3FNType => 8,
+            FloatTypeKind::Float16Type
+            | FloatTypeKind::BFloat16Type => 16,
+            FloatTypeKind::Float32Type => 32,
+            FloatTypeKind::Float64Type => 64,
+            FloatTypeKind::Float80Type => 80,
+            FloatTypeKind::Float128Type => 128
+        }
     }
 
     // /// Return the width of the mantissa of this type.
@@ -258,6 +326,7 @@ impl FloatType {
     // }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum FloatTypeKind {
     Float8E5M2Type,
     Float8E4M3FNType,
@@ -285,23 +354,41 @@ pub enum FloatTypeKind {
 ///
 /// The function type can be thought of as a function signature. It consists of a list of formal parameter types and a list of formal result types.
 pub struct FunctionType {
-    inputs: &'static [dyn Type],
-    outputs: &'static [dyn Type]
+    inputs: Vec<Arc<dyn Type>>,
+    outputs: Vec<Arc<dyn Type>>
 }
 
+impl Type for FunctionType {}
+
 impl FunctionType {
-    // /// Input types.
-    // pub const fn num_inputs(&self) -> usize;
+    pub fn new(inputs: Vec<Arc<dyn Type>>, outputs: Vec<Arc<dyn Type>>) -> Self {
+        Self { inputs, outputs }
+    }
+
+    /// Input types.
+    pub fn inputs(&self) -> &[Arc<dyn Type>] {
+        &self.inputs
+    }
+
+    pub fn num_inputs(&self) -> usize {
+        self.inputs.len()
+    }
 
-    pub const fn input(&self, index: usize) -> impl Type {
-        self.inputs[index]
+    pub fn input(&self, index: usize) -> &Arc<dyn Type> {
+        &self.inputs[index]
     }
 
-    // /// Output types.
-    // pub const fn num_outputs(&self) -> usize;
+    /// Output types.
+    pub fn outputs(&self) -> &[Arc<dyn Type>] {
+        &self.outputs
+    }
+
+    pub fn num_outputs(&self) -> usize {
+        self.outputs.len()
+    }
 
-    pub const fn output(&self, index: usize) -> impl Type {
-        self.outputs[index]
+    pub fn output(&self, index: usize) -> &Arc<dyn Type> {
+        &self.outputs[index]
     }
 
     // /// Returns a clone of this function type with the given argument and result types.
@@ -337,7 +424,23 @@ impl FunctionType {
 
 pub struct OpaqueType {
     dialect_namespace: StringAttribute,
-    type_data: &'static str
+    type_data: String
+}
+
+impl Type for OpaqueType {}
+
+impl OpaqueType {
+    pub fn new(dialect_namespace: StringAttribute, type_data: String) -> Self {
+        Self { dialect_namespace, type_data }
+    }
+
+    pub fn dialect_namespace(&self) -> &str {
+        self.dialect_namespace.value()
+    }
+
+    pub fn type_data(&self) -> &str {
+        &self.type_data
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -351,11 +454,12 @@ pub struct OpaqueType {
 /// ```text
 /// vector-type ::= `vector` `<` vector-dim-list vector-element-type `>`
 /// vector-element-type ::= float-type | integer-type | index-type
-/// vector-dim-list := (static-dim-list `x`)? (`[` static-dim-list `]` `x`)?
-/// static-dim-list ::= decimal-literal (`x` decimal-literal)*
+/// vector-dim-list := (static-dim-list `x`)?
+/// static-dim-list ::= static-dim (`x` static-dim)*
+/// static-dim ::= (decimal-literal | `[` decimal-literal `]`)
 /// ```
 ///
-/// The vector type represents a SIMD style vector used by target-specific operation sets like AVX or SVE. While the most common use is for 1D vectors (e.g. `vector<16xf32>`) we also support multidimensional registers on targets that support them (like TPUs). The dimensions of a vector type can be fixed-length, scalable, or a combination of the two. The scalable dimensions in a vector are indicated between square brackets ([ ]), and all fixed-length dimensions, if present, must precede the set of scalable dimensions. That is, a `vector<2x[4]xf32>` is valid, but `vector<[4]x2xf32>` is not.
+/// The vector type represents a SIMD style vector used by target-specific operation sets like AVX or SVE. While the most common use is for 1D vectors (e.g. `vector<16xf32>`) we also support multidimensional registers on targets that support them (like TPUs). The dimensions of a vector type can be fixed-length, scalable, or a combination of the two. Each scalable dimension is indicated between square brackets ([ ]), in any position, e.g. `vector<2x[4]xf32>` or `vector<[4]x2xf32>`.
 ///
 /// Vector shapes must be positive decimal integers. 0D vectors are allowed by omitting the dimension: `vector<f32>`.
 ///
@@ -371,7 +475,7 @@ pub struct OpaqueType {
 /// vector<[4]xf32>
 ///
 /// // A 2D scalable-length vector that contains a multiple of 2x8 i8 elements.
-/// vector<[2x8]xf32>
+/// vector<[2]x[8]xf32>
 ///
 /// // A 2D mixed fixed/scalable vector that contains 4 scalable vectors of 4 f32 elements.
 /// vector<4x[4]xf32>
@@ -385,7 +489,8 @@ pub struct Vector<T: Type, const N: usize> {
     pub shape: [i64; N],
     // storage: SmallVector<[i64]>,
     pub element_type: T,
-    num_scalable_dims: Box<dyn Attribute>
+    /// Whether each dimension is scalable.
+    pub scalable_dims: [bool; N]
 }
 
 // D : {n, m : N} -> (Fin n) -> (Fin m);
@@ -404,8 +509,8 @@ impl Vector {
     // class Builder;
 
     /// Returns true if the vector contains scalable dimensions.
-    pub const fn is_scalable(&self) -> bool {
-        self.num_scalable_dims > 0
+    pub fn is_scalable(&self) -> bool {
+        self.scalable_dims.contains(&true)
     }
 
     /// Get or create a new Vector with the same shape as `this` and an element type of bitwidth scaled by `scale`.
@@ -420,29 +525,123 @@ impl Vector {
         {
             let scaled_et = self.element_type.scale_element_bitwidth(scale);
             if scaled_et {
-                return Self::get(self.shape, scaled_et, self.num_scalable_dims);
+                return Self::get(self.shape, scaled_et, self.scalable_dims);
             }
         }
         Self::new()
     }
 }
 
-impl const ShapedType for Vector {
+// ----------------------------------------------------------------------
+// VectorType
+// ----------------------------------------------------------------------
+
+/// A vector type whose rank is only known at runtime, such as one parsed from text. The static-rank `Vector<T, N>` converts into it, and back with a check of the rank and element type.
+#[derive(Clone)]
+pub struct VectorType {
+    shape: Vec<i64>,
+    element_type: Arc<dyn Type>,
+    /// Whether each dimension is scalable.
+    scalable_dims: Vec<bool>
+}
+
+impl Type for VectorType {}
+
+impl VectorType {
+    /// `scalable_dims` holds whether each dimension is scalable, an empty list standing for a vector of fixed-length dimensions only.
+    pub fn new(
+        shape: Vec<i64>,
+        element_type: Arc<dyn Type>,
+        scalable_dims: Vec<bool>
+    ) -> Self {
+        let scalable_dims = if scalable_dims.is_empty() {
+            vec![false; shape.len()]
+        } else {
+            scalable_dims
+        };
+        assert_eq!(scalable_dims.len(), shape.len(),
+                   "Expected a scalable flag per dimension");
+        Self { shape, element_type, scalable_dims }
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    /// Returns whether each dimension is scalable.
+    pub fn scalable_dims(&self) -> &[bool] {
+        &self.scalable_dims
+    }
+
+    /// Returns the number of scalable dimensions.
+    pub fn num_scalable_dims(&self) -> usize {
+        self.scalable_dims.iter().filter(|scalable| **scalable).count()
+    }
+
+    /// Returns true if the vector contains scalable dimensions.
+    pub fn is_scalable(&self) -> bool {
+        self.scalable_dims.contains(&true)
+    }
+
+    /// Returns true if the given type can be used as an element of a vector type.
+    pub fn is_valid_element_type(r#type: &dyn Type) -> bool {
+        r#type.downcast_ref::<IntegerType>().is_some()
+        || r#type.downcast_ref::<IndexType>().is_some()
+        || r#type.downcast_ref::<FloatType>().is_some()
+    }
+}
+
+impl TypeInterface for VectorType {}
+
+impl ShapedType for VectorType {
     /// Clone this vector type with the given shape and element type. If the provided shape is `None`, the current shape of the type is used.
-    fn clone_with(
-        &self,
-        shape: Option<&[i64]>,
-        element_type: impl Type
-    ) -> Self
+    fn clone_with(&self, shape: Option<&[i64]>, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>
     {
-        Self::new(shape.value_or(self.shape), element_type,
-            self.num_scalable_dims)
+        let shape = shape.map_or_else(|| self.shape.clone(), <[i64]>::to_vec);
+        Arc::new(Self::new(shape, element_type, self.scalable_dims.clone()))
+    }
+
+    fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
     }
 
     /// Returns if this type is ranked (always true).
     fn has_rank(&self) -> bool {
         true
     }
+
+    fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+}
+
+impl<T: Type, const N: usize> From<Vector<T, N>> for VectorType {
+    fn from(vector: Vector<T, N>) -> Self {
+        Self::new(vector.shape.to_vec(), Arc::new(vector.element_type),
+                  vector.scalable_dims.to_vec())
+    }
+}
+
+impl<T: Type + Clone, const N: usize> TryFrom<&VectorType> for Vector<T, N> {
+    type Error = ShapedTypeConversionError;
+
+    fn try_from(vector: &VectorType) -> Result<Self, Self::Error> {
+        let (shape, element_type) = static_shape_and_element_type(
+            vector.shape(), vector.element_type())?;
+        // The rank is checked along with the shape.
+        let scalable_dims = vector.scalable_dims.as_slice().try_into()
+            .expect("Expected a scalable flag per dimension");
+        Ok(Self { shape, element_type, scalable_dims })
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -517,62 +716,9 @@ impl const ShapedType for Vector {
 /// Tensor types represent multi-dimensional arrays, and have two variants: `RankedTensorType` and `UnrankedTensorType`.
 /// Note: This class attaches the ShapedType trait to act as a mixin to provide many useful utility functions. This inheritance has no effect on derived tensor types.
 pub struct Tensor<T, const N: usize> {
-    shape: [i64; N],
-    element_type: T,
-    encoding: Box<dyn Attribute>
-}
-
-// impl Type for Tensor {
-
-// }
-
-impl const ShapedType for Tensor {
-    /// Clone this type with the given shape and element type. If the provided shape is `None`, the current shape of the type is used.
-    fn clone_with(
-        &self,
-        shape: Option<&[i64]>,
-        element_type: Type
-    ) -> Self
-    {
-        match self {
-            UnrankedTensorType => {
-                match shape {
-                    None => UnrankedTensorType::get(element_type),
-                    Some(shape) => RankedTensorType::get(shape, element_type)
-                }
-            },
-            RankedTensorType => {
-                match shape {
-                    None => RankedTensorType::get(
-                        self.shape(), element_type, self.encoding()),
-                    Some(shape) => RankedTensorType::get(
-                        shape.value_or(self.shape()),
-                        element_type,
-                        self.encoding())
-                }
-            }
-        }
-    }
-
-    // /// Returns the element type of this tensor type.
-    // fn element_type(&self) -> Type {
-    //     return TypeSwitch<Tensor, Type>(*self)
-    //     .Case<RankedTensorType, UnrankedTensorType>(
-    //         |r#type: auto| { r#type.element_type(); });
-    // }
-
-    /// Returns if this type is ranked, i.e. it has a known number of dimensions.
-    fn has_rank(&self) -> bool {
-        match self {
-            UnrankedTensorType => false,
-            RankedTensorType => true
-        }
-    }
-
-    // /// Returns the shape of this tensor type.
-    // fn shape(&self) -> &[i64] {
-    //     cast::<RankedTensorType>().shape()
-    // }
+    pub shape: [i64; N],
+    pub element_type: T,
+    pub encoding: Option<Arc<dyn Attribute>>
 }
 
 //
@@ -593,18 +739,130 @@ pub enum TensorElementType {
 // ----------------------------------------------------------------------
 
 /// This is a builder type that keeps local references to arguments. Arguments that are passed into the builder must outlive the builder.
+///
+/// The rank of a RankedTensorType is only known at runtime, as for a tensor parsed from text. The static-rank `Tensor<T, N>` converts into it, and back with a check of the rank and element type.
+#[derive(Clone)]
 pub struct RankedTensorType {
-    shape: &'static [i64],
-    // storage: SmallVector<[i64]>,
-    element_type: Box<dyn Type>,
-    encoding: Box<dyn Attribute>
+    shape: Vec<i64>,
+    element_type: Arc<dyn Type>,
+    encoding: Option<Arc<dyn Attribute>>
+}
+
+impl Type for RankedTensorType {}
+
+impl RankedTensorType {
+    pub fn new(
+        shape: Vec<i64>,
+        element_type: Arc<dyn Type>,
+        encoding: Option<Arc<dyn Attribute>>
+    ) -> Self {
+        Self { shape, element_type, encoding }
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
+
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    pub fn encoding(&self) -> Option<&Arc<dyn Attribute>> {
+        self.encoding.as_ref()
+    }
+}
+
+impl TypeInterface for RankedTensorType {}
+
+impl ShapedType for RankedTensorType {
+    /// Clone this type with the given shape and element type. If the provided shape is `None`, the current shape of the type is used.
+    fn clone_with(&self, shape: Option<&[i64]>, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>
+    {
+        let shape = shape.map_or_else(|| self.shape.clone(), <[i64]>::to_vec);
+        Arc::new(Self::new(shape, element_type, self.encoding.clone()))
+    }
+
+    fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    fn has_rank(&self) -> bool {
+        true
+    }
+
+    fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+}
+
+impl<T: Type, const N: usize> From<Tensor<T, N>> for RankedTensorType {
+    fn from(tensor: Tensor<T, N>) -> Self {
+        Self::new(tensor.shape.to_vec(), Arc::new(tensor.element_type),
+                  tensor.encoding)
+    }
+}
+
+impl<T: Type + Clone, const N: usize> TryFrom<&RankedTensorType> for Tensor<T, N> {
+    type Error = ShapedTypeConversionError;
+
+    fn try_from(tensor: &RankedTensorType) -> Result<Self, Self::Error> {
+        let (shape, element_type) = static_shape_and_element_type(
+            tensor.shape(), tensor.element_type())?;
+        Ok(Self { shape, element_type, encoding: tensor.encoding.clone() })
+    }
 }
 
 // ----------------------------------------------------------------------
 // UnrankedTensorType
 // ----------------------------------------------------------------------
 
+#[derive(Clone)]
 pub struct UnrankedTensorType {
+    element_type: Arc<dyn Type>
+}
+
+impl Type for UnrankedTensorType {}
+
+impl UnrankedTensorType {
+    pub fn new(element_type: Arc<dyn Type>) -> Self {
+        Self { element_type }
+    }
+
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+}
+
+impl TypeInterface for UnrankedTensorType {}
+
+impl ShapedType for UnrankedTensorType {
+    /// Clone this type with the given element type, and with the given shape as a ranked tensor if one is provided.
+    fn clone_with(&self, shape: Option<&[i64]>, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>
+    {
+        match shape {
+            None => Arc::new(Self::new(element_type)),
+            Some(shape) => Arc::new(
+                RankedTensorType::new(shape.to_vec(), element_type, None))
+        }
+    }
+
+    fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    fn has_rank(&self) -> bool {
+        false
+    }
+
+    fn shape(&self) -> &[i64] {
+        panic!("Cannot query the shape of an unranked tensor type")
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -799,56 +1057,180 @@ pub struct UnrankedTensorType {
 ///
 /// This is a builder type that keeps local references to arguments. Arguments that are passed into the builder must outlive the builder.
 pub struct MemRef<T: Type, const N: usize> {
-    shape: &'static [i64; N],
+    pub shape: [i64; N],
     pub element_type: T,
-    pub layout: MemRefLayoutAttrInterface,
-    pub memory_space: Box<dyn Attribute>
+    pub layout: Option<Arc<dyn Attribute>>,
+    pub memory_space: Option<Arc<dyn Attribute>>
 }
 
-impl MemRef {
-    /**
-    Returns the memory space in which data referred to by this memref resides.
-    */
-    pub const fn memory_space(&self) -> Attribute {
-
+impl<T: Type, const N: usize> MemRef<T, N> {
+    /// Returns the memory space in which data referred to by this memref resides.
+    pub fn memory_space(&self) -> Option<&Arc<dyn Attribute>> {
+        self.memory_space.as_ref()
     }
 }
 
-impl const ShapedType for MemRef {
-    /// Clone this type with the given shape and element type. If the provided shape is `None`, the current shape of the type is used.
-    fn clone_with(&self, shape: Option<&[i64]>, element_type: Type) -> Self {
+// ----------------------------------------------------------------------
+// MemRefType
+// ----------------------------------------------------------------------
 
+/// A ranked memref type whose rank is only known at runtime, such as one parsed from text. The static-rank `MemRef<T, N>` converts into it, and back with a check of the rank and element type.
+#[derive(Clone)]
+pub struct MemRefType {
+    shape: Vec<i64>,
+    element_type: Arc<dyn Type>,
+    /// The layout, either a `StridedLayoutAttribute` or a non-identity `AffineMapAttribute`. None stands for the identity layout.
+    layout: Option<Arc<dyn Attribute>>,
+    /// The memory space, None for the default one.
+    memory_space: Option<Arc<dyn Attribute>>
+}
+
+impl Type for MemRefType {}
+
+impl MemRefType {
+    /// Identity affine map layouts are discarded, as they do not contribute to the identity of the type.
+    pub fn new(
+        shape: Vec<i64>,
+        element_type: Arc<dyn Type>,
+        layout: Option<Arc<dyn Attribute>>,
+        memory_space: Option<Arc<dyn Attribute>>
+    ) -> Self {
+        let layout = layout.filter(|layout|
+            !layout.downcast_ref::<AffineMapAttribute>()
+            .is_some_and(|map| map.value().is_identity()));
+        let memory_space = skip_default_memory_space(memory_space);
+        Self { shape, element_type, layout, memory_space }
+    }
+
+    pub fn shape(&self) -> &[i64] {
+        &self.shape
     }
 
-    /// Returns the element type of this memref type.
-    fn element_type(&self) -> Type {
+    pub fn rank(&self) -> usize {
+        self.shape.len()
+    }
 
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
     }
 
-    /// Returns if this type is ranked, i.e. it has a known number of dimensions.
-    fn has_rank(&self) -> bool {
+    pub fn layout(&self) -> Option<&Arc<dyn Attribute>> {
+        self.layout.as_ref()
+    }
+
+    /// Returns the layout as an affine map. The identity layout is the row-major identity map and a strided layout is its linearised form.
+    pub fn layout_map(&self) -> AffineMap {
+        let Some(layout) = &self.layout else {
+            return affine_map::multi_dim_identity_map(
+                self.rank(), core::ptr::null_mut());
+        };
+        if let Some(map) = layout.downcast_ref::<AffineMapAttribute>() {
+            return map.value().clone();
+        }
+        if let Some(strided) = layout.downcast_ref::<StridedLayoutAttribute>() {
+            return strided.affine_map();
+        }
+        unreachable!("Memref layout must be an affine map or a strided layout")
+    }
+
+    /// Returns the memory space in which data referred to by this memref resides.
+    pub fn memory_space(&self) -> Option<&Arc<dyn Attribute>> {
+        self.memory_space.as_ref()
+    }
+
+    /// Returns the memory space as an integer, 0 for the default one, or None if it is not an integer attribute.
+    pub fn memory_space_as_int(&self) -> Option<u64> {
+        memory_space_as_int(self.memory_space())
+    }
+
+    /// Returns true if the given type can be used as an element of a memref type.
+    pub fn is_valid_element_type(r#type: &dyn Type) -> bool {
+        r#type.downcast_ref::<IntegerType>().is_some()
+        || r#type.downcast_ref::<IndexType>().is_some()
+        || r#type.downcast_ref::<FloatType>().is_some()
+        || r#type.downcast_ref::<ComplexType>().is_some()
+        || r#type.downcast_ref::<VectorType>().is_some()
+        || r#type.downcast_ref::<MemRefType>().is_some()
+        || r#type.downcast_ref::<UnrankedMemRefType>().is_some()
+    }
+}
+
+impl TypeInterface for MemRefType {}
+
+impl ShapedType for MemRefType {
+    /// Clone this type with the given shape and element type, keeping its layout and memory space. If the provided shape is `None`, the current shape of the type is used.
+    fn clone_with(&self, shape: Option<&[i64]>, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>
+    {
+        let shape = shape.map_or_else(|| self.shape.clone(), <[i64]>::to_vec);
+        Arc::new(Self::new(shape, element_type, self.layout.clone(),
+                           self.memory_space.clone()))
+    }
 
+    fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    fn has_rank(&self) -> bool {
+        true
     }
 
-    /// Returns the shape of this memref type.
     fn shape(&self) -> &[i64] {
+        &self.shape
+    }
+}
 
+impl<T: Type, const N: usize> From<MemRef<T, N>> for MemRefType {
+    fn from(memref: MemRef<T, N>) -> Self {
+        Self::new(memref.shape.to_vec(), Arc::new(memref.element_type),
+                  memref.layout, memref.memory_space)
     }
+}
 
-    // /// Return true if the specified element type is ok in a memref.
-    // static bool is_valid_element_type(r#type: Type);
+impl<T: Type + Clone, const N: usize> TryFrom<&MemRefType> for MemRef<T, N> {
+    type Error = ShapedTypeConversionError;
+
+    fn try_from(memref: &MemRefType) -> Result<Self, Self::Error> {
+        let (shape, element_type) = static_shape_and_element_type(
+            memref.shape(), memref.element_type())?;
+        Ok(Self {
+            shape,
+            element_type,
+            layout: memref.layout.clone(),
+            memory_space: memref.memory_space.clone()
+        })
+    }
 }
 
-/// Given an `original_shape` and a `reduced_shape` assumed to be a subset of `original_shape` with some `1` entries erased, return the set of indices that specifies which of the entries of `original_shape` are dropped to obtain `reduced_shape`. The returned mask can be applied as a projection to `original_shape` to obtain the `reduced_shape`. This mask is useful to track which dimensions must be kept when e.g. compute MemRef strides under rank-reducing operations. Return std::nullopt if reduced_shape cannot be obtained by dropping only `1` entries in `original_shape`.
+/// Given an `original_shape` and a `reduced_shape` assumed to be a subset of `original_shape` with some `1` entries erased, return the set of indices that specifies which of the entries of `original_shape` are dropped to obtain `reduced_shape`. The returned mask can be applied as a projection to `original_shape` to obtain the `reduced_shape`. This mask is useful to track which dimensions must be kept when e.g. compute MemRef strides under rank-reducing operations. Return None if reduced_shape cannot be obtained by dropping only `1` entries in `original_shape`.
 pub fn compute_rank_reduction_mask(
     original_shape: &[i64],
     reduced_shape: &[i64]
-) -> Option<SmallDenseSet<usize>>
+) -> Option<BTreeSet<usize>>
 {
-
+    let mut unused_dims = BTreeSet::new();
+    let mut reduced_index = 0;
+    for (original_index, &size) in original_shape.iter().enumerate() {
+        // Greedily match `original_index` against the next reduced dimension.
+        if reduced_shape.get(reduced_index) == Some(&size) {
+            reduced_index += 1;
+            continue;
+        }
+        // Without a match, the dimension must be a unit one to be dropped.
+        if size != 1 {
+            return None;
+        }
+        unused_dims.insert(original_index);
+    }
+    // The whole of `reduced_shape` must have been matched.
+    if reduced_index != reduced_shape.len() {
+        return None;
+    }
+    Some(unused_dims)
 }
 
 /// Enum that captures information related to verifier error conditions on slice insert/extract type of ops.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum SliceVerificationResult {
     Success,
     RankTooLarge,
@@ -864,45 +1246,119 @@ pub enum SliceVerificationResult {
 /// Return `SliceVerificationResult::Success` on success or an appropriate error
 /// code.
 pub fn is_rank_reduced_type(
-    original_type: ShapedType,
-    candidate_reduced_type: ShapedType
+    original_type: &dyn ShapedType,
+    candidate_reduced_type: &dyn ShapedType
 ) -> SliceVerificationResult
 {
-    if (original_type == candidate_reduced_type){
-    return SliceVerificationResult::Success;}
-
-    let original_shaped_type = original_type.cast::<ShapedType>();
-    let candidate_reduced_shaped_type =
-        candidate_reduced_type.cast::<ShapedType>();
-
     // Rank and size logic is valid for all ShapedTypes.
-    let original_shape = original_shaped_type.shape();
-    let candidate_reduced_shape = candidate_reduced_shaped_type.shape();
-    let original_rank = original_shape.size();
-    let candidate_reduced_rank = candidate_reduced_shape.size();
-    if candidate_reduced_rank > original_rank {
+    let original_shape = original_type.shape();
+    let candidate_reduced_shape = candidate_reduced_type.shape();
+    if candidate_reduced_shape.len() > original_shape.len() {
         return SliceVerificationResult::RankTooLarge;
     }
 
-    let optional_unused_dims_mask =
-        compute_rank_reduction_mask(original_shape, candidate_reduced_shape);
-
-    // Sizes cannot be matched in case empty vector is returned.
-    if !optional_unused_dims_mask {
+    // Sizes cannot be matched in case no mask is returned.
+    if compute_rank_reduction_mask(original_shape, candidate_reduced_shape)
+        .is_none()
+    {
         return SliceVerificationResult::SizeMismatch;
     }
-    if original_shaped_type.get_element_type() !=
-        candidate_reduced_shaped_type.get_element_type() {
+
+    let original_element_type = original_type.element_type();
+    let candidate_element_type = candidate_reduced_type.element_type();
+    if !Arc::ptr_eq(original_element_type, candidate_element_type)
+        && original_element_type.to_string() != candidate_element_type.to_string()
+    {
         return SliceVerificationResult::ElemTypeMismatch;
     }
 
-    return SliceVerificationResult::Success;
+    SliceVerificationResult::Success
 }
 
 // ----------------------------------------------------------------------
 // UnrankedMemRefType
 // ----------------------------------------------------------------------
 
+/// A memref type of unknown rank, written `memref<*xf32>`.
+#[derive(Clone)]
+pub struct UnrankedMemRefType {
+    element_type: Arc<dyn Type>,
+    /// The memory space, None for the default one.
+    memory_space: Option<Arc<dyn Attribute>>
+}
+
+impl Type for UnrankedMemRefType {}
+
+impl UnrankedMemRefType {
+    pub fn new(
+        element_type: Arc<dyn Type>,
+        memory_space: Option<Arc<dyn Attribute>>
+    ) -> Self {
+        let memory_space = skip_default_memory_space(memory_space);
+        Self { element_type, memory_space }
+    }
+
+    pub fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    /// Returns the memory space in which data referred to by this memref resides.
+    pub fn memory_space(&self) -> Option<&Arc<dyn Attribute>> {
+        self.memory_space.as_ref()
+    }
+
+    /// Returns the memory space as an integer, 0 for the default one, or None if it is not an integer attribute.
+    pub fn memory_space_as_int(&self) -> Option<u64> {
+        memory_space_as_int(self.memory_space())
+    }
+}
+
+impl TypeInterface for UnrankedMemRefType {}
+
+impl ShapedType for UnrankedMemRefType {
+    /// Clone this type with the given element type, keeping its memory space, and with the given shape as a ranked memref if one is provided.
+    fn clone_with(&self, shape: Option<&[i64]>, element_type: Arc<dyn Type>)
+        -> Arc<dyn Type>
+    {
+        match shape {
+            None => Arc::new(Self::new(element_type, self.memory_space.clone())),
+            Some(shape) => Arc::new(MemRefType::new(
+                shape.to_vec(), element_type, None, self.memory_space.clone()))
+        }
+    }
+
+    fn element_type(&self) -> &Arc<dyn Type> {
+        &self.element_type
+    }
+
+    fn has_rank(&self) -> bool {
+        false
+    }
+
+    fn shape(&self) -> &[i64] {
+        panic!("Cannot query the shape of an unranked memref type")
+    }
+}
+
+/// The integer memory space 0 is the default one, and is dropped so that it compares equal to an absent memory space.
+fn skip_default_memory_space(memory_space: Option<Arc<dyn Attribute>>)
+    -> Option<Arc<dyn Attribute>>
+{
+    memory_space.filter(|memory_space|
+        !memory_space.downcast_ref::<IntegerAttribute>()
+        .is_some_and(|integer| integer.value().is_zero()))
+}
+
+fn memory_space_as_int(memory_space: Option<&Arc<dyn Attribute>>)
+    -> Option<u64>
+{
+    match memory_space {
+        None => Some(0),
+        Some(memory_space) => memory_space.downcast_ref::<IntegerAttribute>()
+            .and_then(|integer| integer.value().zext_value())
+    }
+}
+
 // ----------------------------------------------------------------------
 // UnitType
 // ----------------------------------------------------------------------
@@ -912,15 +1368,42 @@ pub fn is_rank_reduced_type(
 /// UnitType is a unit type, i.e. a type with exactly one possible value, where its value does not have a defined dynamic representation.
 pub struct UnitType;
 
+// ----------------------------------------------------------------------
+// NoneType
+// ----------------------------------------------------------------------
+
+/// A unit type.
+///
+/// # Syntax
+///
+/// ```text
+/// none-type ::= `none`
+/// ```
+///
+/// NoneType is a unit type, i.e. a type with exactly one possible value, where its value does not have a defined dynamic representation.
+pub struct NoneType;
+
+impl Type for NoneType {}
+
 // ----------------------------------------------------------------------
 // TupleType
 // ----------------------------------------------------------------------
 
 pub struct TupleType {
-    types: &'static [dyn Type]
+    types: Vec<Arc<dyn Type>>
 }
 
+impl Type for TupleType {}
+
 impl TupleType {
+    pub fn new(types: Vec<Arc<dyn Type>>) -> Self {
+        Self { types }
+    }
+
+    pub fn types(&self) -> &[Arc<dyn Type>] {
+        &self.types
+    }
+
     /**
     Accumulate the types contained in this tuple and tuples nested within it. Note that this only flattens nested tuples, not any other container type, e.g. a tuple<i32, tensor<i32>, tuple<f32, tuple<i64>>> is flattened to (i32, tensor<i32>, f32, i64)
     */
@@ -935,14 +1418,14 @@ impl TupleType {
     }
 
     /// Return the number of held types.
-    pub const fn len(&self) -> usize {
-        1  // TODO
+    pub fn len(&self) -> usize {
+        self.types.len()
     }
 
     /// Return the element type at index `index`.
-    pub const fn r#type(&self, index: usize) -> impl Type {
+    pub fn r#type(&self, index: usize) -> &Arc<dyn Type> {
         assert!(index < self.len(), "Invalid index for tuple type");
-        self.types[index]
+        &self.types[index]
     }
 }
 
@@ -955,82 +1438,552 @@ impl<'a> IntoIterator for TupleType {
     }
 }
 
+// ----------------------------------------------------------------------
+// Static Rank Conversions
+// ----------------------------------------------------------------------
+
+/// Error returned when converting a shaped type of runtime rank to its static-rank form.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ShapedTypeConversionError {
+    /// The rank of the type differs from the static rank.
+    RankMismatch {
+        expected: usize,
+        found: usize
+    },
+    /// The element type is not of the static element type.
+    ElementTypeMismatch
+}
+
+impl Display for ShapedTypeConversionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> FmtResult {
+        match self {
+            Self::RankMismatch { expected, found } =>
+                write!(f, "Expected a shaped type of rank {}, found rank {}",
+                       expected, found),
+            Self::ElementTypeMismatch =>
+                write!(f, "Shaped type has an unexpected element type")
+        }
+    }
+}
+
+impl std::error::Error for ShapedTypeConversionError {}
+
+/// Check that `shape` has the static rank `N` and that `element_type` is a `T`.
+fn static_shape_and_element_type<T: Type + Clone, const N: usize>(
+    shape: &[i64],
+    element_type: &Arc<dyn Type>
+) -> Result<([i64; N], T), ShapedTypeConversionError> {
+    let shape = <[i64; N]>::try_from(shape).map_err(|_|
+        ShapedTypeConversionError::RankMismatch { expected: N, found: shape.len() })?;
+    let element_type = element_type.downcast_ref::<T>()
+        .ok_or(ShapedTypeConversionError::ElementTypeMismatch)?;
+    Ok((shape, element_type.clone()))
+}
+
 // ----------------------------------------------------------------------
 // Type Utilities
 // ----------------------------------------------------------------------
 
-// /**
-// Returns the strides of the MemRef if the layout map is in strided form.
-// MemRefs with a layout map in strided form include:
-
-// 1. empty or identity layout map, in which case the stride information is
-//     the canonical form computed from sizes;
-// 2. a StridedLayoutAttr layout;
-// 3. any other layout that be converted into a single affine map layout of
-//     the form `K + k0 * d0 + ... kn * dn`, where K and ki's are constants or
-//     symbols.
-
-// A stride specification is a list of integer values that are either static or dynamic (encoded with ShapedType::kDynamic). Strides encode the distance in the number of elements between successive entries along a particular dimension.
-// */
-// pub fn get_strides_and_offset(
-//     t: MemRef,
-//     strides: &SmallVector<[i64]>,
-//     offset: &i64
-// ) -> Result<(), Box<dyn std::error::Error>>
-// {
-//     Err(())
-// }
-
-// /**
-// Wrapper around get_strides_and_offset(MemRef, SmallVectorImpl<i64>,
-// i64) that will assert if the logical result is not succeeded.
-// */
-// pub fn get_strides_and_offset(t: MemRef) -> (SmallVector<[i64]>, i64) {
-
-// }
-
-// /**
-// Return a version of `t` with identity layout if it can be determined statically that the layout is the canonical contiguous strided layout.
-// Otherwise pass `t`'s layout into `simplifyAffineMap` and return a copy of `t` with simplified layout.
-// */
-// pub fn canonicalise_strided_layout(t: MemRef) -> MemRef {
-
-// }
-
-// /**
-// Given MemRef `sizes` that are either static or dynamic, returns the canonical 'contiguous' strides AffineExpr. Strides are multiplicative and once a dynamic dimension is encountered, all canonical strides become dynamic and need to be encoded with a different symbol.
-// For canonical strides expressions, the offset is always 0 and and fastest varying stride is always `1`.
-
-// Examples:
-
-//   - memref<3x4x5xf32> has canonical stride expression
-//         `20*exprs[0] + 5*exprs[1] + exprs[2]`.
-//   - memref<3x?x5xf32> has canonical stride expression
-//         `s0*exprs[0] + 5*exprs[1] + exprs[2]`.
-//   - memref<3x4x?xf32> has canonical stride expression
-//         `s1*exprs[0] + s0*exprs[1] + exprs[2]`.
-// */
-// pub fn make_canonical_strided_layout_expr(
-//     sizes: &[i64],
-//     exprs: &[AffineExpr],
-//     context: *mut MLIRContext
-// ) -> AffineExpr
-// {
-
-// }
-
-// /**
-// Return the result of makeCanonicalStrudedLayoutExpr for the common case where `exprs` is {d0, d1, .., d_(sizes.size()-1)}
-// */
-// pub fn make_canonical_strided_layout_expr(
-//     sizes: &[i64],
-//     context: *mut MLIRContext
-// ) -> AffineExpr
-// {
-
-// }
-
-// /// Return true if the layout for `t` is compatible with strided semantics.
-// pub fn is_strided(t: MemRef) -> bool {
-
-// }
+/**
+Returns the strides of the MemRef if the layout map is in strided form.
+MemRefs with a layout map in strided form include:
+
+1. empty or identity layout map, in which case the stride information is
+    the canonical form computed from sizes;
+2. a StridedLayoutAttribute layout;
+3. any other layout that be converted into a single affine map layout of
+    the form `K + k0 * d0 + ... kn * dn`, where K and ki's are constants or
+    symbols.
+
+A stride specification is a list of integer values that are either static or dynamic (encoded with `DYNAMIC`). Strides encode the distance in the number of elements between successive entries along a particular dimension.
+*/
+pub fn get_strides_and_offset(r#type: &MemRefType) -> Option<(Vec<i64>, i64)> {
+    // Happy path: the type uses the strided layout directly.
+    if let Some(strided) = r#type.layout()
+        .and_then(|layout| layout.downcast_ref::<StridedLayoutAttribute>())
+    {
+        return Some((strided.strides().to_vec(), strided.offset()));
+    }
+    let (strides, offset) = get_strides_and_offset_exprs(r#type)?;
+    let value = |expr: &AffineExpr| match expr {
+        AffineExpr::Constant { value } => *value,
+        _ => DYNAMIC
+    };
+    Some((strides.iter().map(value).collect(), value(&offset)))
+}
+
+/**
+Returns the strides and the offset of the MemRef as affine expressions of the dimensions and symbols of its layout map, if the layout map is in strided form. See `get_strides_and_offset`.
+*/
+pub fn get_strides_and_offset_exprs(r#type: &MemRefType)
+    -> Option<(Vec<AffineExpr>, AffineExpr)>
+{
+    let map = r#type.layout_map();
+    if map.num_outputs() != 1 && !map.is_identity() {
+        return None;
+    }
+    let context = map.context();
+    let mut offset = affine_expr::constant(0, context);
+    let mut strides = vec![affine_expr::constant(0, context); r#type.rank()];
+
+    // Canonical case for empty map.
+    if map.is_identity() {
+        // 0-D corner case, offset is already 0.
+        if r#type.rank() == 0 {
+            return Some((strides, offset));
+        }
+        let strided_expr
+            = make_canonical_strided_layout_expr(r#type.shape(), context);
+        let one = affine_expr::constant(1, context);
+        assert!(extract_strides(&strided_expr, one, &mut strides, &mut offset),
+                "Unexpected failure: extract strides in canonical layout");
+        return Some((strides, offset));
+    }
+
+    // Non-canonical case requires more work.
+    let num_dims = map.num_dims();
+    let num_symbols = map.num_symbols();
+    let strided_expr
+        = simplify_affine_expr(map.output(0).clone(), num_dims, num_symbols);
+    let one = affine_expr::constant(1, context);
+    if !extract_strides(&strided_expr, one, &mut strides, &mut offset) {
+        return None;
+    }
+
+    // Simplify results to allow folding to constants and simple checks.
+    let offset = simplify_affine_expr(offset, num_dims, num_symbols);
+    let strides: Vec<_> = strides.into_iter()
+        .map(|stride| simplify_affine_expr(stride, num_dims, num_symbols))
+        .collect();
+
+    // In practice, a strided memref must be internally non-aliasing. Test against 0 as a proxy.
+    if strides.iter().any(|stride| *stride == AffineExpr::Constant { value: 0 }) {
+        return None;
+    }
+    Some((strides, offset))
+}
+
+/// Accumulate the strides and the offset of the sum of products `expr`, each term of which is scaled by `multiplicative_factor`. Fails on semi-affine terms that cannot be expressed as strides.
+fn extract_strides(
+    expr: &AffineExpr,
+    multiplicative_factor: AffineExpr,
+    strides: &mut [AffineExpr],
+    offset: &mut AffineExpr
+) -> bool
+{
+    let AffineExpr::BinaryOperation { kind, lhs, rhs } = expr else {
+        if let AffineExpr::Dimension { index } = expr {
+            strides[*index] = strides[*index].clone() + multiplicative_factor;
+        } else {
+            *offset = offset.clone() + expr.clone() * multiplicative_factor;
+        }
+        return true;
+    };
+    match kind {
+        Kind::CeilDiv | Kind::FloorDiv | Kind::Mod => false,
+        Kind::Mul => {
+            if let AffineExpr::Dimension { index } = **lhs {
+                strides[index] = strides[index].clone()
+                    + (**rhs).clone() * multiplicative_factor;
+                return true;
+            }
+            // LHS and RHS may both contain complex expressions of dims. Try one path and if it fails try the other. This is guaranteed to succeed because we only allow pure affine here.
+            if lhs.is_symbolic_or_constant() {
+                return extract_strides(
+                    rhs, multiplicative_factor * (**lhs).clone(), strides, offset);
+            }
+            extract_strides(
+                lhs, multiplicative_factor * (**rhs).clone(), strides, offset)
+        },
+        Kind::Add => {
+            let lhs_extracted = extract_strides(
+                lhs, multiplicative_factor.clone(), strides, offset);
+            let rhs_extracted = extract_strides(
+                rhs, multiplicative_factor, strides, offset);
+            lhs_extracted && rhs_extracted
+        }
+    }
+}
+
+/**
+Return a version of `t` with identity layout if it can be determined statically that the layout is the canonical contiguous strided layout.
+Otherwise pass `t`'s layout into `simplify_affine_map` and return a copy of `t` with simplified layout.
+*/
+pub fn canonicalise_strided_layout(r#type: &MemRefType) -> MemRefType {
+    let map = r#type.layout_map();
+
+    // Already in canonical form.
+    if map.is_identity() {
+        return r#type.clone();
+    }
+
+    // Can't reduce to canonical identity form, return in canonical form.
+    if map.num_outputs() > 1 {
+        return r#type.clone();
+    }
+
+    let with_layout = |layout: Option<Arc<dyn Attribute>>| MemRefType::new(
+        r#type.shape().to_vec(), r#type.element_type().clone(), layout,
+        r#type.memory_space().cloned());
+
+    // Corner-case for 0-D affine maps.
+    if map.num_dims() == 0 && map.num_symbols() == 0 {
+        if *map.output(0) == (AffineExpr::Constant { value: 0 }) {
+            return with_layout(None);
+        }
+        return r#type.clone();
+    }
+
+    // 0-D corner case for empty shape that still have an affine map. Example: `memref<f32, affine_map<()[s0] -> (s0)>>`. This is a 1 element memref whose offset needs to remain, just return t.
+    if r#type.shape().is_empty() {
+        return r#type.clone();
+    }
+
+    // If the canonical strided layout for the sizes of `t` is equal to the simplified layout of `t` we can just return an empty layout. Otherwise, just simplify the existing layout.
+    let expr = make_canonical_strided_layout_expr(r#type.shape(), map.context());
+    let simplified_layout_expr = simplify_affine_expr(
+        map.output(0).clone(), map.num_dims(), map.num_symbols());
+    if expr != simplified_layout_expr {
+        let simplified_map = AffineMap::new(
+            map.num_dims(), map.num_symbols(), vec![simplified_layout_expr],
+            map.context());
+        return with_layout(Some(Arc::new(AffineMapAttribute::new(simplified_map))));
+    }
+    with_layout(None)
+}
+
+/**
+Given MemRef `sizes` that are either static or dynamic, returns the canonical 'contiguous' strides AffineExpr. Strides are multiplicative and once a dynamic dimension is encountered, all canonical strides become dynamic and need to be encoded with a different symbol.
+For canonical strides expressions, the offset is always 0 and and fastest varying stride is always `1`.
+
+Examples:
+
+  - memref<3x4x5xf32> has canonical stride expression
+        `20*exprs[0] + 5*exprs[1] + exprs[2]`.
+  - memref<3x?x5xf32> has canonical stride expression
+        `s0*exprs[0] + 5*exprs[1] + exprs[2]`.
+  - memref<3x4x?xf32> has canonical stride expression
+        `s1*exprs[0] + s0*exprs[1] + exprs[2]`.
+*/
+pub fn make_canonical_strided_layout_expr_with_exprs(
+    sizes: &[i64],
+    exprs: &[AffineExpr],
+    context: *mut MLIRContext
+) -> AffineExpr
+{
+    // Size 0 corner case is useful for canonicalisations.
+    if sizes.is_empty() {
+        return affine_expr::constant(0, context);
+    }
+    assert!(!exprs.is_empty(), "Expected exprs");
+    let (num_dims, mut num_symbols) = num_dims_and_symbols(exprs);
+
+    let mut expr: Option<AffineExpr> = None;
+    let mut dynamic_poison_bit = false;
+    let mut running_size = 1i64;
+    for (dim_expr, &size) in exprs.iter().rev().zip(sizes.iter().rev()) {
+        let stride = if dynamic_poison_bit {
+            num_symbols += 1;
+            affine_expr::symbol(num_symbols - 1, context)
+        } else {
+            affine_expr::constant(running_size, context)
+        };
+        let term = dim_expr.clone() * stride;
+        expr = Some(match expr {
+            Some(expr) => expr + term,
+            None => term
+        });
+        if size > 0 {
+            running_size = running_size.checked_mul(size)
+                .expect("Integer overflow in size computation");
+        } else {
+            dynamic_poison_bit = true;
+        }
+    }
+    simplify_affine_expr(expr.unwrap(), num_dims, num_symbols)
+}
+
+/**
+Return the result of `make_canonical_strided_layout_expr_with_exprs` for the common case where `exprs` is {d0, d1, .., d_(sizes.size()-1)}
+*/
+pub fn make_canonical_strided_layout_expr(
+    sizes: &[i64],
+    context: *mut MLIRContext
+) -> AffineExpr
+{
+    let exprs: Vec<_> = (0..sizes.len())
+        .map(|dim| affine_expr::dimension(dim, context))
+        .collect();
+    make_canonical_strided_layout_expr_with_exprs(sizes, &exprs, context)
+}
+
+/**
+Given a list of strides (in which `DYNAMIC` represents a dynamic value), return the single result AffineMap which represents the linearised strided layout map. Dimensions correspond to the offset followed by the strides in order. Symbols are inserted for each dynamic dimension in order. A stride cannot take value `0`.
+
+Examples:
+
+- `[DYNAMIC, DYNAMIC]` with offset `DYNAMIC` produces
+    `(d0, d1)[s0, s1, s2] -> (s0 + d0 * s1 + d1 * s2)`.
+- `[8, 1]` with offset `4` produces `(d0, d1) -> (4 + d0 * 8 + d1)`.
+*/
+pub fn make_strided_linear_layout_map(
+    strides: &[i64],
+    offset: i64,
+    context: *mut MLIRContext
+) -> AffineMap
+{
+    let mut num_symbols = 0;
+    let mut next_or_constant = |value: i64| if is_dynamic(value) {
+        num_symbols += 1;
+        affine_expr::symbol(num_symbols - 1, context)
+    } else {
+        affine_expr::constant(value, context)
+    };
+    // AffineExpr for offset.
+    let mut expr = next_or_constant(offset);
+    // AffineExpr for strides.
+    for (dim, &stride) in strides.iter().enumerate() {
+        assert!(stride != 0, "Invalid stride specification");
+        expr = expr
+            + affine_expr::dimension(dim, context) * next_or_constant(stride);
+    }
+    AffineMap::new(strides.len(), num_symbols, vec![expr], context)
+}
+
+/// Return true if the layout for `t` is compatible with strided semantics.
+pub fn is_strided(r#type: &MemRefType) -> bool {
+    get_strides_and_offset(r#type).is_some()
+}
+
+/// Returns one past the largest dimension and symbol positions occurring in `exprs`.
+fn num_dims_and_symbols(exprs: &[AffineExpr]) -> (usize, usize) {
+    let mut num_dims = 0;
+    let mut num_symbols = 0;
+    for expr in exprs {
+        expr.walk(|expr| match expr {
+            AffineExpr::Dimension { index } => num_dims = num_dims.max(index + 1),
+            AffineExpr::Symbol { index } => num_symbols = num_symbols.max(index + 1),
+            _ => {}
+        });
+    }
+    (num_dims, num_symbols)
+}
+
+#[cfg(test)]
+mod tests {
+    use llvm::adt::ap_int::APInt;
+
+    use super::*;
+
+    fn f32() -> Arc<dyn Type> {
+        Arc::new(FloatType::new(FloatTypeKind::Float32Type))
+    }
+
+    fn i32() -> Arc<dyn Type> {
+        Arc::new(IntegerType::new(32, Signedness::Signless))
+    }
+
+    fn i64_attribute(value: i64) -> Arc<dyn Attribute> {
+        Arc::new(IntegerAttribute::new(
+            Arc::new(IntegerType::new(64, Signedness::Signless)),
+            APInt::new(64, value as u64, true)))
+    }
+
+    fn d(index: usize) -> AffineExpr {
+        AffineExpr::Dimension { index }
+    }
+
+    fn s(index: usize) -> AffineExpr {
+        AffineExpr::Symbol { index }
+    }
+
+    fn memref(shape: Vec<i64>, layout: Option<AffineMap>) -> MemRefType {
+        let layout = layout.map(|map|
+            Arc::new(AffineMapAttribute::new(map)) as Arc<dyn Attribute>);
+        MemRefType::new(shape, f32(), layout, None)
+    }
+
+    fn map(num_dims: usize, num_symbols: usize, output: AffineExpr) -> AffineMap {
+        AffineMap::new(num_dims, num_symbols, vec![output], core::ptr::null_mut())
+    }
+
+    #[test]
+    fn vector_scalable_dims_in_any_position() {
+        let vector: Arc<dyn Type>
+            = Arc::new(VectorType::new(vec![4, 8], f32(), vec![true, false]));
+        assert_eq!(vector.to_string(), "vector<[4]x8xf32>");
+        let vector: Arc<dyn Type>
+            = Arc::new(VectorType::new(vec![2, 4, 8], f32(), vec![false, true, true]));
+        assert_eq!(vector.to_string(), "vector<2x[4]x[8]xf32>");
+        let vector = vector.downcast_ref::<VectorType>().unwrap();
+        assert_eq!(vector.num_scalable_dims(), 2);
+        assert!(vector.is_scalable());
+    }
+
+    #[test]
+    fn vector_without_scalable_flags_is_fixed_length() {
+        let vector = VectorType::new(vec![3, 42], f32(), Vec::new());
+        assert_eq!(vector.scalable_dims(), &[false, false]);
+        assert!(!vector.is_scalable());
+        assert_eq!((&vector as &dyn Type).to_string(), "vector<3x42xf32>");
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected a scalable flag per dimension")]
+    fn vector_scalable_flags_must_match_rank() {
+        VectorType::new(vec![4, 8], f32(), vec![true]);
+    }
+
+    #[test]
+    fn vector_clone_keeps_scalable_dims() {
+        let vector = VectorType::new(vec![4, 8], f32(), vec![true, false]);
+        let i1 = Arc::new(IntegerType::new(1, Signedness::Signless));
+        assert_eq!(vector.clone_with(None, i1).to_string(), "vector<[4]x8xi1>");
+
+        let r#static = Vector::<FloatType, 2>::try_from(&vector).unwrap();
+        assert_eq!(r#static.scalable_dims, [true, false]);
+        assert_eq!(VectorType::from(r#static).scalable_dims(), &[true, false]);
+    }
+
+    #[test]
+    fn static_rank_conversions_are_checked() {
+        let tensor = RankedTensorType::new(vec![2, DYNAMIC], f32(), None);
+        let r#static = Tensor::<FloatType, 2>::try_from(&tensor).unwrap();
+        assert_eq!(r#static.shape, [2, DYNAMIC]);
+        let tensor: Arc<dyn Type> = Arc::new(RankedTensorType::from(r#static));
+        assert_eq!(tensor.to_string(), "tensor<2x?xf32>");
+        let tensor = tensor.downcast_ref::<RankedTensorType>().unwrap();
+        assert_eq!(Tensor::<FloatType, 3>::try_from(tensor).err(),
+                   Some(ShapedTypeConversionError::RankMismatch { expected: 3, found: 2 }));
+        assert_eq!(Tensor::<IntegerType, 2>::try_from(tensor).err(),
+                   Some(ShapedTypeConversionError::ElementTypeMismatch));
+
+        let memref = MemRefType::new(vec![4], f32(), None, Some(i64_attribute(1)));
+        let r#static = MemRef::<FloatType, 1>::try_from(&memref).unwrap();
+        assert!(r#static.memory_space().is_some());
+        let r#static: Arc<dyn Type> = Arc::new(MemRefType::from(r#static));
+        assert_eq!(r#static.to_string(), "memref<4xf32, 1>");
+        assert!(MemRef::<FloatType, 0>::try_from(&memref).is_err());
+    }
+
+    #[test]
+    fn memref_defaults_are_dropped() {
+        let identity = MemRefType::new(
+            vec![4, 8], f32(),
+            Some(Arc::new(AffineMapAttribute::new(
+                affine_map::multi_dim_identity_map(2, core::ptr::null_mut())))),
+            Some(i64_attribute(0)));
+        assert!(identity.layout().is_none());
+        assert!(identity.memory_space().is_none());
+        assert_eq!(identity.memory_space_as_int(), Some(0));
+
+        let global = MemRefType::new(vec![4], f32(), None, Some(i64_attribute(3)));
+        assert_eq!(global.memory_space_as_int(), Some(3));
+        let named = UnrankedMemRefType::new(
+            f32(), Some(Arc::new(StringAttribute::new("shared"))));
+        assert!(named.memory_space().is_some());
+        assert_eq!(named.memory_space_as_int(), None);
+    }
+
+    #[test]
+    fn canonical_strides_follow_the_shape() {
+        assert_eq!(get_strides_and_offset(&memref(vec![3, 4, 5], None)),
+                   Some((vec![20, 5, 1], 0)));
+        assert_eq!(get_strides_and_offset(&memref(vec![3, DYNAMIC, 5], None)),
+                   Some((vec![DYNAMIC, 5, 1], 0)));
+        assert_eq!(get_strides_and_offset(&memref(vec![3, 4, DYNAMIC], None)),
+                   Some((vec![DYNAMIC, DYNAMIC, 1], 0)));
+        assert_eq!(get_strides_and_offset(&memref(Vec::new(), None)),
+                   Some((Vec::new(), 0)));
+    }
+
+    #[test]
+    fn strides_are_extracted_from_layouts() {
+        let strided = MemRefType::new(
+            vec![2, DYNAMIC], f32(),
+            Some(Arc::new(StridedLayoutAttribute::new(DYNAMIC, vec![DYNAMIC, 1]))), None);
+        assert_eq!(get_strides_and_offset(&strided), Some((vec![DYNAMIC, 1], DYNAMIC)));
+
+        let affine = memref(vec![4, 8], Some(map(2, 0, d(0) * 8 + d(1) + 4)));
+        assert_eq!(get_strides_and_offset(&affine), Some((vec![8, 1], 4)));
+
+        let linear = memref(vec![4, 8], Some(make_strided_linear_layout_map(
+            &[DYNAMIC, DYNAMIC], DYNAMIC, core::ptr::null_mut())));
+        assert_eq!(linear.layout_map().num_symbols(), 3);
+        assert_eq!(get_strides_and_offset_exprs(&linear), Some((vec![s(1), s(2)], s(0))));
+        assert_eq!(get_strides_and_offset(&linear),
+                   Some((vec![DYNAMIC, DYNAMIC], DYNAMIC)));
+    }
+
+    #[test]
+    fn non_strided_layouts_are_rejected() {
+        let semi_affine = memref(vec![8], Some(map(1, 0, d(0).floor_div(2))));
+        assert_eq!(get_strides_and_offset(&semi_affine), None);
+        assert!(!is_strided(&semi_affine));
+        // A dimension with a zero stride aliases its elements.
+        let aliasing = memref(vec![4, 8], Some(map(2, 0, d(0) * 8)));
+        assert!(!is_strided(&aliasing));
+    }
+
+    #[test]
+    fn canonical_layouts_are_dropped() {
+        let contiguous = memref(vec![4, 8], Some(map(2, 0, d(1) + d(0) * 8)));
+        assert!(canonicalise_strided_layout(&contiguous).layout().is_none());
+
+        let padded = memref(vec![4, 8], Some(map(2, 0, d(1) + d(0) * 16)));
+        let canonical = canonicalise_strided_layout(&padded);
+        assert!(canonical.layout().is_some());
+        assert_eq!(get_strides_and_offset(&canonical), Some((vec![16, 1], 0)));
+
+        let scalar = memref(Vec::new(), Some(map(0, 0, AffineExpr::Constant { value: 0 })));
+        assert!(canonicalise_strided_layout(&scalar).layout().is_none());
+        let offset = memref(Vec::new(), Some(map(0, 1, s(0))));
+        assert!(canonicalise_strided_layout(&offset).layout().is_some());
+    }
+
+    #[test]
+    fn rank_reduction_drops_unit_dimensions() {
+        assert_eq!(compute_rank_reduction_mask(&[1, 4, 1, 8], &[4, 8]),
+                   Some(BTreeSet::from([0, 2])));
+        assert_eq!(compute_rank_reduction_mask(&[1, 1], &[1]), Some(BTreeSet::from([1])));
+        assert_eq!(compute_rank_reduction_mask(&[4, 8], &[8]), None);
+        assert_eq!(compute_rank_reduction_mask(&[4], &[4, 1]), None);
+
+        let original = MemRefType::new(vec![1, 4, 1], f32(), None, None);
+        let reduced = |shape: Vec<i64>, element_type|
+            RankedTensorType::new(shape, element_type, None);
+        assert_eq!(is_rank_reduced_type(&original, &reduced(vec![4], f32())),
+                   SliceVerificationResult::Success);
+        assert_eq!(is_rank_reduced_type(&original, &reduced(vec![4], i32())),
+                   SliceVerificationResult::ElemTypeMismatch);
+        assert_eq!(is_rank_reduced_type(&original, &reduced(vec![2], f32())),
+                   SliceVerificationResult::SizeMismatch);
+        assert_eq!(is_rank_reduced_type(&original, &reduced(vec![1, 4, 1, 1], f32())),
+                   SliceVerificationResult::RankTooLarge);
+    }
+
+    #[test]
+    fn shaped_type_queries() {
+        let tensor = RankedTensorType::new(vec![2, DYNAMIC, 4, DYNAMIC], f32(), None);
+        assert!(!tensor.has_static_shape());
+        assert!(tensor.is_dynamic_dim(1));
+        assert_eq!(tensor.num_dynamic_dims(), 2);
+        assert_eq!(tensor.dynamic_dim_index(3), 1);
+
+        let vector = VectorType::new(vec![4, 8], i32(), Vec::new());
+        assert!(vector.has_static_shape_of(&[4, 8]));
+        assert_eq!(vector.num_elements(), 32);
+        assert_eq!(vector.size_in_bits(), 1024);
+        let complex = RankedTensorType::new(
+            vec![2], Arc::new(ComplexType::new(f32())), None);
+        assert_eq!(complex.size_in_bits(), 128);
+        let nested = MemRefType::new(
+            vec![2], Arc::new(VectorType::new(vec![4], f32(), Vec::new())), None, None);
+        assert_eq!(nested.size_in_bits(), 256);
+
+        let unranked = UnrankedMemRefType::new(f32(), Some(i64_attribute(1)));
+        assert!(!unranked.has_rank());
+        assert_eq!(unranked.clone_with_shape(&[4]).to_string(), "memref<4xf32, 1>");
+        assert_eq!(unranked.clone_with_element_type(i32()).to_string(), "memref<*xi32, 1>");
+    }
+}
@@ -5,128 +5,1242 @@
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/Diagnostics.h>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/Diagnostics.cpp>
 
-use core::{
-    default::Default,
-    fmt::Arguments
-};
-use rustc_errors::diagnostic::{Diagnostic, Level};
-use std::backtrace;
-use llvm::{
-    adt::{
-        map_vector,
-        small_string,
-        small_vector::SmallVector,
-        string_map,
-        twine::Twine,
-        type_switch,
+pub mod structured;
+
+use core::fmt;
+use std::{
+    collections::HashMap,
+    error::Error,
+    io::{self, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicUsize, Ordering}
     },
-    support::{
-        mutex,
-        pretty_stack_trace,
-        signals,
-        source_manager,
-        raw_ostream
-    }
+    thread::{self, ThreadId}
 };
+use llvm::support::{
+    sm_loc::{SMLoc, SMRange},
+    source_manager::{DiagKind, SMDiagnostic, SMFixIt, SourceManager}
+};
+use regex::Regex;
+use serde::Serialize;
 use crate::{
     ir::{
-        attribute,
+        arena::{IRArena, OpId},
+        asm_printer::print_operation,
+        builtins::location_attributes::FileLineColLocation,
         location::Location,
-        mlir_context,
-        operation::{
-            Operation,
-            support::PrintingFlags
-        },
-        r#type
+        mlir_context::MLIRContext,
+        operation::support::PrintingFlags
     },
     support::logical_result::LogicalResult
 };
 
-/// Append an operation with the given printing flags.
-pub fn append_op(operation: &Operation, flags: &PrintingFlags, level: Level) -> String {
-    let mut string = String::new();
-    operation.print(string, adjust_printing_flags(flags, level));
+/// Defines the different supported severity of a diagnostic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    Note,
+    Warning,
+    Error,
+    Remark
+}
+
+impl fmt::Display for DiagnosticSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Note => "note",
+            Self::Warning => "warning",
+            Self::Error => "error",
+            Self::Remark => "remark"
+        })
+    }
+}
+
+// ----------------------------------------------------------------------
+// Diagnostic
+// ----------------------------------------------------------------------
+
+/// This class contains all of the information necessary to report a diagnostic to the DiagnosticEngine. It should generally not be constructed directly, and instead used transitively via emit_error, emit_warning, and emit_remark.
+#[derive(Clone)]
+pub struct Diagnostic {
+    /// The source location.
+    location: Location,
+    /// The severity of this diagnostic.
+    severity: DiagnosticSeverity,
+    /// The message of this diagnostic.
+    message: String,
+    /// A list of attached notes.
+    notes: Vec<Diagnostic>,
+    /// The operations appended to the message.
+    operations: Vec<OpId>,
+    /// The printed form of each of the operations appended to the message.
+    printed_operations: Vec<String>,
+    /// The fix-its suggested to resolve this diagnostic.
+    fix_its: Vec<FixIt>
+}
+
+impl Diagnostic {
+    pub fn new(
+        location: Location,
+        severity: DiagnosticSeverity,
+        message: impl Into<String>
+    ) -> Self {
+        Self {
+            location,
+            severity,
+            message: message.into(),
+            notes: Vec::new(),
+            operations: Vec::new(),
+            printed_operations: Vec::new(),
+            fix_its: Vec::new()
+        }
+    }
+
+    /// Returns the severity of this diagnostic.
+    pub fn severity(&self) -> DiagnosticSeverity {
+        self.severity
+    }
+
+    /// Returns the source location for this diagnostic.
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn set_message(&mut self, message: impl Into<String>) {
+        self.message = message.into();
+    }
+
+    /// Append `message` to the message of this diagnostic.
+    pub fn append(&mut self, message: impl fmt::Display) -> &mut Self {
+        self.message.push_str(&message.to_string());
+        self
+    }
+
+    /// Attaches a note to this diagnostic. A new location may be optionally provided, if not, then the location defaults to the one specified for this diagnostic. Notes may not be attached to other notes.
+    pub fn attach_note(
+        &mut self,
+        location: Option<Location>,
+        message: impl Into<String>
+    ) -> &mut Diagnostic {
+        assert!(self.severity != DiagnosticSeverity::Note,
+            "Cannot attach a note to a note");
+        let location = location.unwrap_or_else(|| self.location.clone());
+        self.notes.push(
+            Diagnostic::new(location, DiagnosticSeverity::Note, message));
+        self.notes.last_mut().unwrap()
+    }
+
+    /// Returns the notes held by this diagnostic.
+    pub fn notes(&self) -> &[Diagnostic] {
+        &self.notes
+    }
+
+    /// Append the printed form of `op` to the message of this diagnostic, and attach the operation to it.
+    pub fn append_op(&mut self, arena: &IRArena, op: OpId, flags: PrintingFlags)
+        -> &mut Self
+    {
+        let printed = append_op(arena, op, flags, self.severity);
+        self.message.push_str(&printed);
+        self.operations.push(op);
+        self.printed_operations.push(printed.trim_start_matches('\n').to_string());
+        self
+    }
+
+    /// Returns the operations appended to this diagnostic.
+    pub fn operations(&self) -> &[OpId] {
+        &self.operations
+    }
+
+    /// Returns the printed form of the operations appended to this diagnostic, as they were when they were appended.
+    pub fn printed_operations(&self) -> &[String] {
+        &self.printed_operations
+    }
+
+    /// Attaches a fix-it suggesting to replace the `length` characters at `location` with `replacement`. Handlers only render fix-its whose location is a FileLineColLocation.
+    pub fn attach_fix_it(
+        &mut self,
+        location: Location,
+        length: usize,
+        replacement: impl Into<String>
+    ) -> &mut Self {
+        self.fix_its.push(FixIt { location, length, replacement: replacement.into() });
+        self
+    }
+
+    /// Returns the fix-its attached to this diagnostic.
+    pub fn fix_its(&self) -> &[FixIt] {
+        &self.fix_its
+    }
+}
+
+/// A suggested replacement of the source text at a location, attached to a diagnostic.
+#[derive(Clone)]
+pub struct FixIt {
+    location: Location,
+    length: usize,
+    replacement: String
+}
+
+impl FixIt {
+    /// Returns the location of the first character to replace.
+    pub fn location(&self) -> &Location {
+        &self.location
+    }
+
+    /// Returns the number of characters to replace. Fix-its of length 0 are insertions.
+    pub fn length(&self) -> usize {
+        self.length
+    }
+
+    pub fn replacement(&self) -> &str {
+        &self.replacement
+    }
+}
+
+/// Print `location` as the `file:line:col: ` prefix of a diagnostic. Unknown locations print nothing, and other locations print their assembly form.
+fn print_location_prefix(f: &mut fmt::Formatter<'_>, location: &Location)
+    -> fmt::Result
+{
+    match location {
+        Location::Unknown => Ok(()),
+        Location::FileLineCol(location) => write!(
+            f, "{}:{}:{}: ",
+            location.filename(), location.line(), location.column()),
+        location => write!(f, "{}: ", location)
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    /// Prints the diagnostic as `file:line:col: error: message`, followed by each of its notes on its own line.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        print_location_prefix(f, &self.location)?;
+        write!(f, "{}: {}", self.severity, self.message)?;
+        for note in &self.notes {
+            f.write_str("\n")?;
+            note.fmt(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+impl Error for Diagnostic {}
+
+/// Print `op` for attaching it to a diagnostic of the given severity.
+pub fn append_op(
+    arena: &IRArena,
+    op: OpId,
+    flags: PrintingFlags,
+    severity: DiagnosticSeverity
+) -> String {
+    let mut string = print_operation(arena, op, adjust_printing_flags(flags, severity))
+        .unwrap_or_else(|error| format!("<<invalid operation: {}>>", error));
     // Print on a new line for better readability if the op will be printed on multiple lines.
-    if string.find('\n').is_some() {
-        string.push("\n");
-        string
+    if string.contains('\n') {
+        string.insert(0, '\n');
     }
+    string
 }
 
 /// Adjusts operation printing flags used in diagnostics for the given severity level.
-pub fn adjust_printing_flags(flags: PrintingFlags, level: Level)
+pub fn adjust_printing_flags(flags: PrintingFlags, severity: DiagnosticSeverity)
     -> PrintingFlags
 {
-    flags.use_local_scope();
-    flags.elide_large_elements_attrs();
-    if level == Level::Error {
-        flags.print_generic_op_form();
+    let flags = flags.use_local_scope().elide_large_elements_attrs(16);
+    if severity == DiagnosticSeverity::Error {
+        flags.print_generic_op_form()
+    } else {
+        flags
     }
-    flags
 }
 
+// ----------------------------------------------------------------------
+// DiagnosticEngine
+// ----------------------------------------------------------------------
+
+/*
+Diagnostic handler registration and use. MLIR supports the ability for the
+IR to carry arbitrary metadata about operation location information. If a
+problem is detected by the compiler, it can invoke the emitError /
+emitWarning / emitRemark method on an Operation and have it get reported
+through this interface.
+
+Tools using MLIR are encouraged to register error handlers and define a
+schema for their location information.  If they don't, then warnings and
+notes will be dropped and errors will be emitted to errs.
+*/
+
+/// The handler type for MLIR diagnostics. This function takes a diagnostic as input, and returns success if the handler has fully processed this diagnostic. Returns failure otherwise.
+///
+/// Handlers are reference counted so that the engine can call them without holding its lock.
+pub type Handler = Arc<dyn Fn(&Diagnostic) -> LogicalResult + Send + Sync>;
+
+/// A handle to a specific registered handler object.
+pub type HandlerId = u64;
+
 /// This class is the main interface for diagnostics. The DiagnosticEngine manages the registration of diagnostic handlers as well as the core API for diagnostic emission. This class should not be constructed directly, but instead interfaced with via an MLIRContext instance.
+///
+/// Clones of an engine share its handlers, which lets scoped handlers unregister themselves without borrowing the context.
+#[derive(Clone, Default)]
 pub struct DiagnosticEngine {
     /// A mutex to ensure that diagnostics emission is thread-safe.
-    mutex: SmartMutex<true>,
+    inner: Arc<Mutex<DiagnosticEngineImpl>>
+}
 
+#[derive(Default)]
+struct DiagnosticEngineImpl {
     /// These are the handlers used to report diagnostics.
-    handlers: SmallMapVector<u64, Fn(&Diagnostic) -> LogicalResult, 2>,
+    handlers: Vec<(HandlerId, Handler)>,
 
     /// This is a unique identifier counter for diagnostic handlers in the context. This id starts at 1 to allow for 0 to be used as a sentinel.
-    unique_handler_id: u64  //  = 1;
+    unique_handler_id: HandlerId
 }
 
 impl DiagnosticEngine {
-    /// Create a new inflight diagnostic with the given location and severity.
-    pub fn emit(&self, location: Location, level: Level) -> Diagnostic {
-        assert!(level != Level::Note,
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new handler for diagnostics to the engine. Diagnostics are process by handlers in stack-like order, meaning that the last added handlers will process diagnostics first. This function returns a unique identifier for the registered handler, which can be used to unregister this handler at a later time.
+    pub fn register_handler(&self, handler: Handler) -> HandlerId {
+        let mut inner = self.inner.lock().unwrap();
+        inner.unique_handler_id += 1;
+        let id = inner.unique_handler_id;
+        inner.handlers.push((id, handler));
+        id
+    }
+
+    /// Erase the registered diagnostic handler with the given identifier.
+    pub fn erase_handler(&self, id: HandlerId) {
+        self.inner.lock().unwrap().handlers
+            .retain(|(handler_id, _)| *handler_id != id);
+    }
+
+    /// Emit a diagnostic using the registered issue handler if present, or with the default behaviour if not. The diagnostic instance is consumed in the process.
+    pub fn emit(&self, diagnostic: Diagnostic) {
+        assert!(diagnostic.severity() != DiagnosticSeverity::Note,
             "Notes should not be emitted directly");
-            Diagnostic::new(location, level)
+        // Release the lock before calling the handlers, so that a handler may emit a diagnostic, or register or erase a handler, as upstream allows with a recursive mutex. Handlers registered or erased by a handler take effect from the next diagnostic.
+        let handlers: Vec<Handler> = self.inner.lock().unwrap().handlers.iter()
+            .map(|(_, handler)| handler.clone()).collect();
+        // Try to process the given diagnostic on one of the registered handlers. Handlers are walked in reverse order, so that the most recent handler is processed first.
+        for handler in handlers.iter().rev() {
+            if handler(&diagnostic).is_ok() {
+                return;
+            }
+        }
+        // Otherwise, if this is an error we emit it to stderr.
+        if diagnostic.severity() == DiagnosticSeverity::Error {
+            eprintln!("{}", diagnostic);
+        }
     }
 }
 
-/// Helper function used to emit a diagnostic with an optionally empty twine message. If the message is empty, then it is not inserted into the diagnostic.
-pub fn emit_diag(location: Location, severity: Level, message: &Twine) -> Diagnostic {
-    let context = location.context();
-    let diag_engine = context.diag_engine();
-    let diag = diag_engine.emit(location, severity);
-    if !message.is_trivially_empty() {
-        diag << message;
-    }
+/// Utility method to emit an error message using this location.
+pub fn emit_error(
+    context: &MLIRContext,
+    location: Location,
+    message: impl Into<String>
+) {
+    emit_diag(context, location, DiagnosticSeverity::Error, message)
+}
+
+/// Utility method to emit a warning message using this location.
+pub fn emit_warning(
+    context: &MLIRContext,
+    location: Location,
+    message: impl Into<String>
+) {
+    emit_diag(context, location, DiagnosticSeverity::Warning, message)
+}
+
+/// Utility method to emit a remark message using this location.
+pub fn emit_remark(
+    context: &MLIRContext,
+    location: Location,
+    message: impl Into<String>
+) {
+    emit_diag(context, location, DiagnosticSeverity::Remark, message)
+}
+
+/// Helper function used to emit a diagnostic through the diagnostic engine of `context`.
+fn emit_diag(
+    context: &MLIRContext,
+    location: Location,
+    severity: DiagnosticSeverity,
+    message: impl Into<String>
+) {
+    let mut diagnostic = Diagnostic::new(location, severity, message);
     // Add the stack trace as a note if necessary.
     if context.should_print_stack_trace_on_diagnostic() {
-        let mut bt = String::new();
+        let trace = std::backtrace::Backtrace::force_capture().to_string();
+        if !trace.is_empty() {
+            diagnostic.attach_note(
+                None, format!("diagnostic emitted with trace:\n{}", trace));
+        }
+    }
+    context.diag_engine().emit(diagnostic);
+}
+
+/// Report `diagnostic`, which was emitted about `op`, through the diagnostic engine of `context`, attaching the operation when the context is configured to print it on diagnostics.
+pub fn emit_op_diagnostic(
+    context: &MLIRContext,
+    op: OpId,
+    diagnostic: Diagnostic
+) {
+    emit_op_diagnostic_in(context, context.arena(), op, diagnostic)
+}
+
+/// Same as `emit_op_diagnostic`, for an operation of `arena` rather than of the arena of `context`, e.g. while a pass manager owns the IR.
+pub fn emit_op_diagnostic_in(
+    context: &MLIRContext,
+    arena: &IRArena,
+    op: OpId,
+    mut diagnostic: Diagnostic
+) {
+    if context.should_print_op_on_diagnostic() {
+        let location = diagnostic.location().clone();
+        diagnostic.attach_note(Some(location), "see current operation: ")
+            .append_op(arena, op, PrintingFlags::new().print_generic_op_form());
+    }
+    context.diag_engine().emit(diagnostic);
+}
+
+// ----------------------------------------------------------------------
+// ScopedDiagnosticHandler
+// ----------------------------------------------------------------------
+
+/// This diagnostic handler is a simple RAII class that registers and erases a diagnostic handler on a given context. This class can be either be used directly, or in conjunction with a derived diagnostic handler.
+pub struct ScopedDiagnosticHandler {
+    /// The engine the handler is registered to.
+    engine: DiagnosticEngine,
+    /// The unique id for the scoped handler.
+    id: HandlerId
+}
+
+impl ScopedDiagnosticHandler {
+    /// Register `handler` on the diagnostic engine of `context` until the returned object is dropped.
+    pub fn new(
+        context: &MLIRContext,
+        handler: impl Fn(&Diagnostic) -> LogicalResult + Send + Sync + 'static
+    ) -> Self {
+        let engine = context.diag_engine().clone();
+        let id = engine.register_handler(Arc::new(handler));
+        Self { engine, id }
+    }
+}
+
+impl Drop for ScopedDiagnosticHandler {
+    fn drop(&mut self) {
+        self.engine.erase_handler(self.id);
+    }
+}
+
+// ----------------------------------------------------------------------
+// SourceMgrDiagnosticHandler
+// ----------------------------------------------------------------------
+
+/// Convert the severity of a diagnostic to the kind of message printed by the source manager.
+fn diag_kind(severity: DiagnosticSeverity) -> DiagKind {
+    match severity {
+        DiagnosticSeverity::Note => DiagKind::Note,
+        DiagnosticSeverity::Warning => DiagKind::Warning,
+        DiagnosticSeverity::Error => DiagKind::Error,
+        DiagnosticSeverity::Remark => DiagKind::Remark
+    }
+}
+
+/// Return the location that should be shown for `location`, i.e. the innermost location with a file, or any other known location if there is none.
+fn find_loc_to_show(location: &Location) -> Option<Location> {
+    match location {
+        Location::Unknown => None,
+        Location::FileLineCol(_) => Some(location.clone()),
+        // CallSiteLoc's callee is the first location to show.
+        Location::CallSite(call_site) => find_loc_to_show(call_site.callee()),
+        // Fused location is unique in that we try to find a sub-location to show, rather than the top-level location itself.
+        Location::Fused(fused) => fused.locations().iter().find_map(find_loc_to_show),
+        Location::Name(name) => find_loc_to_show(name.child_location())
+            .or_else(|| Some(location.clone())),
+        Location::Opaque(opaque) => find_loc_to_show(opaque.fallback_location())
+    }
+}
+
+/// Return the first FileLineColLocation nested in `location`, or the location itself.
+fn find_file_line_col(location: &Location) -> Option<&FileLineColLocation> {
+    match location {
+        Location::Unknown => None,
+        Location::FileLineCol(file_line_col) => Some(file_line_col),
+        Location::CallSite(call_site) => find_file_line_col(call_site.callee())
+            .or_else(|| find_file_line_col(call_site.caller())),
+        Location::Fused(fused) => fused.locations().iter().find_map(find_file_line_col),
+        Location::Name(name) => find_file_line_col(name.child_location()),
+        Location::Opaque(opaque) => find_file_line_col(opaque.fallback_location())
+    }
+}
+
+/// Return a call site location for `location`, looking through name locations.
+fn call_site_location(location: &Location) -> Option<(&Location, &Location)> {
+    match location {
+        Location::CallSite(call_site) => Some((call_site.callee(), call_site.caller())),
+        Location::Name(name) => call_site_location(name.child_location()),
+        _ => None
+    }
+}
+
+/// The state shared by the source manager handlers and the handler they register: the source manager, and the stream diagnostics are printed to.
+struct SourceMgrDiagnosticPrinter {
+    /// The source manager of the files diagnostics point into. Files that locations refer to are loaded into it on demand.
+    source_manager: Mutex<SourceManager>,
+    /// The output stream to use when printing diagnostics.
+    output: Mutex<Box<dyn Write + Send>>,
+    /// The maximum depth that a call stack will be printed.
+    call_stack_limit: AtomicUsize
+}
+
+impl SourceMgrDiagnosticPrinter {
+    fn new(source_manager: SourceManager, output: Box<dyn Write + Send>) -> Self {
+        Self {
+            source_manager: Mutex::new(source_manager),
+            output: Mutex::new(output),
+            call_stack_limit: AtomicUsize::new(10)
+        }
+    }
+
+    /// Emit the given diagnostic, with its call stack and notes.
+    fn emit_diagnostic(&self, diagnostic: &Diagnostic) {
+        let mut call_stack: Vec<(Location, &str)> = Vec::new();
+        let mut add_loc_to_stack = |location: &Location, loc_context| {
+            if let Some(location) = find_loc_to_show(location) {
+                call_stack.push((location, loc_context));
+            }
+        };
+
+        // Add locations to display for this diagnostic.
+        let mut location = diagnostic.location();
+        add_loc_to_stack(location, "");
+
+        // If the diagnostic location was a call site location, add the call stack as well. Print the call stack while valid, or until the limit is reached.
+        if let Some((_, caller)) = call_site_location(location) {
+            location = caller;
+            for _ in 0..self.call_stack_limit.load(Ordering::Relaxed) {
+                add_loc_to_stack(location, "called from");
+                match call_site_location(location) {
+                    Some((_, caller)) => location = caller,
+                    None => break
+                }
+            }
+        }
+
+        // If the location stack is empty, use the initial location.
+        match call_stack.split_first() {
+            None => self.emit(
+                diagnostic.location(), diagnostic.message(), diagnostic.severity(), true,
+                diagnostic.fix_its()),
+            // Otherwise, use the location stack.
+            Some(((location, _), callers)) => {
+                self.emit(location, diagnostic.message(), diagnostic.severity(), true,
+                    diagnostic.fix_its());
+                for (location, loc_context) in callers {
+                    self.emit(location, loc_context, DiagnosticSeverity::Note, true, &[]);
+                }
+            }
+        }
+
+        // Emit each of the notes. Only display the source code if the location is different from the previous location.
+        let mut previous = diagnostic.location().to_string();
+        for note in diagnostic.notes() {
+            let location = note.location().to_string();
+            self.emit(note.location(), note.message(), note.severity(), location != previous,
+                note.fix_its());
+            previous = location;
+        }
+    }
+
+    /// Emit a diagnostic with `message` at `location`, printing the source line of the location and `fix_its` if `display_source_line` is set and the location is in a known file.
+    fn emit(
+        &self,
+        location: &Location,
+        message: &str,
+        severity: DiagnosticSeverity,
+        display_source_line: bool,
+        fix_its: &[FixIt]
+    ) {
+        let diagnostic = match find_file_line_col(location) {
+            // If there is no file location, then print the raw message without a source location.
+            None => {
+                let message = match location {
+                    Location::Unknown => message.to_string(),
+                    location => format!("{}: {}", location, message)
+                };
+                SMDiagnostic::new("", diag_kind(severity), message)
+            },
+            Some(file_loc) => {
+                // If we are displaying the source line, try to convert the file location to a location in a buffer.
+                let source_message = display_source_line.then(||
+                    self.with_source_loc(file_loc, |source_manager, id, loc| {
+                        let fix_its: Vec<_> = fix_its.iter()
+                            .filter_map(|fix_it| Self::convert_fix_it(source_manager, id, fix_it))
+                            .collect();
+                        source_manager.get_message(
+                            id, loc, diag_kind(severity), message, &[], &fix_its)
+                    })).flatten();
+                // If the conversion was unsuccessful, create a diagnostic with the file information.
+                source_message.unwrap_or_else(|| SMDiagnostic::new(
+                    format!("{}:{}:{}", file_loc.filename(), file_loc.line(), file_loc.column()),
+                    diag_kind(severity),
+                    message))
+            }
+        };
+        self.print(&diagnostic);
+    }
+
+    /// Print `diagnostic` to the output stream.
+    fn print(&self, diagnostic: &SMDiagnostic) {
+        let mut output = self.output.lock().unwrap();
+        // There is nowhere left to report failing to write a diagnostic.
+        let _ = writeln!(output, "{}", diagnostic);
+    }
+
+    /// Call `function` with the source manager, the buffer of `file_loc` and its position in it, loading the file if needed. Return `None` if the file can't be loaded or the position is out of it.
+    fn with_source_loc<R>(
+        &self,
+        file_loc: &FileLineColLocation,
+        function: impl FnOnce(&SourceManager, usize, SMLoc) -> R
+    ) -> Option<R> {
+        let mut source_manager = self.source_manager.lock().unwrap();
+        let id = match source_manager.find_buffer(file_loc.filename()) {
+            Some(id) => id,
+            // Otherwise, try to load the source file.
+            None => {
+                let buffer = std::fs::read_to_string(file_loc.filename()).ok()?;
+                source_manager.add_new_source_buffer(buffer, file_loc.filename())
+            }
+        };
+        let loc = source_manager.find_loc_for_line_and_column(
+            id, file_loc.line(), file_loc.column())?;
+        Some(function(&source_manager, id, loc))
+    }
+
+    /// Convert `fix_it` to a fix-it in the buffer `id`, if it is located in it.
+    fn convert_fix_it(source_manager: &SourceManager, id: usize, fix_it: &FixIt)
+        -> Option<SMFixIt>
+    {
+        let Location::FileLineCol(file_loc) = fix_it.location() else {
+            return None;
+        };
+        if file_loc.filename() != source_manager.buffer_identifier(id) {
+            return None;
+        }
+        let start = source_manager.find_loc_for_line_and_column(
+            id, file_loc.line(), file_loc.column())?;
+        let end = SMLoc::from_offset(start.offset() + fix_it.length());
+        Some(SMFixIt::new(SMRange::new(start, end), fix_it.replacement()))
+    }
+}
+
+/// This class is a utility diagnostic handler for use with llvm::SourceManager.
+///
+/// Diagnostics are printed as `file:line:col: error: message`, followed by the line of the location with a caret under the column.
+pub struct SourceMgrDiagnosticHandler {
+    printer: Arc<SourceMgrDiagnosticPrinter>,
+    _handler: ScopedDiagnosticHandler
+}
+
+impl SourceMgrDiagnosticHandler {
+    /// Register a handler printing the diagnostics of `context` to stderr.
+    pub fn new(source_manager: SourceManager, context: &MLIRContext) -> Self {
+        Self::with_output(source_manager, context, Box::new(io::stderr()))
+    }
+
+    /// Register a handler printing the diagnostics of `context` to `output`.
+    pub fn with_output(
+        source_manager: SourceManager,
+        context: &MLIRContext,
+        output: Box<dyn Write + Send>
+    ) -> Self {
+        let printer = Arc::new(SourceMgrDiagnosticPrinter::new(source_manager, output));
+        let handler_printer = printer.clone();
+        let handler = ScopedDiagnosticHandler::new(context, move |diagnostic| {
+            handler_printer.emit_diagnostic(diagnostic);
+            Ok(())
+        });
+        Self { printer, _handler: handler }
+    }
+
+    /// Emit the given diagnostic. This is not meant to be called directly, but is used by the handler registered on the context.
+    pub fn emit_diagnostic(&self, diagnostic: &Diagnostic) {
+        self.printer.emit_diagnostic(diagnostic);
+    }
+
+    /// Set the maximum depth that a call stack will be printed. Defaults to 10.
+    pub fn set_call_stack_limit(&self, limit: usize) {
+        self.printer.call_stack_limit.store(limit, Ordering::Relaxed);
+    }
+}
+
+// ----------------------------------------------------------------------
+// SourceMgrDiagnosticVerifierHandler
+// ----------------------------------------------------------------------
+
+/// This class represents an expected output diagnostic.
+struct ExpectedDiag {
+    /// The severity of the diagnosic expected.
+    kind: DiagnosticSeverity,
+    /// The line number the expected diagnostic should be on, or `None` for diagnostics expected at an unknown location.
+    line_no: Option<usize>,
+    /// The location of the expected diagnostic within the input file.
+    file_loc: SMLoc,
+    /// A flag indicating if the expected diagnostic has been matched yet.
+    matched: bool,
+    /// The substring that is expected to be within the diagnostic.
+    substring: String,
+    /// An optional regex matcher, if the expected diagnostic sub-string was a regex string.
+    regex: Option<Regex>
+}
+
+impl ExpectedDiag {
+    /// Return true if this diagnostic matches the given string.
+    fn matches(&self, message: &str) -> bool {
+        // If this isn't a regex diagnostic, we simply check if the string was contained.
+        match &self.regex {
+            Some(regex) => regex.is_match(message),
+            None => message.contains(&self.substring)
+        }
+    }
+
+    /// Compute the regex matcher for this diagnostic, using the provided stream and manager to emit diagnostics as necessary.
+    fn compute_regex(&mut self) -> Result<(), String> {
+        // Regex matches are delimited by '{{' '}}'. Literal text is escaped.
+        let mut regex = String::new();
+        let mut remaining = self.substring.as_str();
+        while !remaining.is_empty() {
+            let Some(start) = remaining.find("{{") else {
+                regex.push_str(&regex::escape(remaining));
+                break;
+            };
+            regex.push_str(&regex::escape(&remaining[..start]));
+            remaining = &remaining[start + 2..];
+            let Some(end) = remaining.find("}}") else {
+                return Err("found start of regex with no end '}}'".to_string());
+            };
+            let sub_regex = &remaining[..end];
+            remaining = &remaining[end + 2..];
+            // Validate that the regex is actually valid.
+            if let Err(error) = Regex::new(sub_regex) {
+                return Err(format!("invalid regex: {}", error));
+            }
+            regex.push('(');
+            regex.push_str(sub_regex);
+            regex.push(')');
+        }
+        self.regex = Some(Regex::new(&regex).map_err(|error| format!("invalid regex: {}", error))?);
+        Ok(())
+    }
+}
+
+/// The state shared by the verifier handler and the handler it registers.
+struct SourceMgrDiagnosticVerifier {
+    printer: SourceMgrDiagnosticPrinter,
+    /// The expected diagnostics of each file, keyed by buffer identifier.
+    expected_diags: Mutex<HashMap<String, Vec<ExpectedDiag>>>,
+    /// Set once a diagnostic did not match the expectations.
+    failed: AtomicBool
+}
+
+/// The regex matching `expected-*` comments.
+const EXPECTED_DIAG_PATTERN: &str =
+    r"expected-(error|note|remark|warning)(-re)? *(@([+-][0-9]+|above|below|unknown))? *\{\{(.*)\}\}$";
+
+impl SourceMgrDiagnosticVerifier {
+    /// Parse the expected diagnostics of the buffer `id`.
+    fn compute_expected_diags(&self, source_manager: &SourceManager, id: usize)
+        -> Vec<ExpectedDiag>
+    {
+        let expected = Regex::new(EXPECTED_DIAG_PATTERN).unwrap();
+        let buffer = source_manager.buffer(id);
+        let mut expected_diags: Vec<ExpectedDiag> = Vec::new();
+
+        // The indices of the expected diagnostics that apply to the next non-designator line.
+        let mut designators_for_next_line: Vec<usize> = Vec::new();
+        // The line number of the last line that did not correspond to a designator.
+        let mut last_non_designator_line = 0;
+
+        let mut line_start = 0;
+        let lines: Vec<&str> = buffer.split('\n').collect();
+        for (index, line) in lines.iter().enumerate() {
+            let line_no = index + 1;
+            let line_offset = line_start;
+            line_start += line.len() + 1;
+            let Some(captures) = expected.captures(line.trim_end()) else {
+                // Check for designators that apply to this line.
+                for index in designators_for_next_line.drain(..) {
+                    expected_diags[index].line_no = Some(line_no);
+                }
+                last_non_designator_line = line_no;
+                continue;
+            };
+
+            // Point to the start of expected-*.
+            let file_loc = SMLoc::from_offset(line_offset + captures.get(0).unwrap().start());
+            let kind = match &captures[1] {
+                "error" => DiagnosticSeverity::Error,
+                "warning" => DiagnosticSeverity::Warning,
+                "remark" => DiagnosticSeverity::Remark,
+                _ => DiagnosticSeverity::Note
+            };
+            let mut record = ExpectedDiag {
+                kind,
+                line_no: Some(line_no),
+                file_loc,
+                matched: false,
+                substring: captures[5].to_string(),
+                regex: None
+            };
+            if captures.get(2).is_some() {
+                if let Err(message) = record.compute_regex() {
+                    self.print_at(source_manager, id, file_loc, &message);
+                    self.failed.store(true, Ordering::Relaxed);
+                    continue;
+                }
+            }
+
+            if let Some(offset) = captures.get(4) {
+                match offset.as_str() {
+                    // If the designator applies 'above' we add it to the last non designator line.
+                    "above" => record.line_no = Some(last_non_designator_line),
+                    // Otherwise, this is a 'below' designator and applies to the next non-designator diagnostic. Set the line number to the last in the case that this designator ends up dangling.
+                    "below" => {
+                        designators_for_next_line.push(expected_diags.len());
+                        record.line_no = Some(lines.len());
+                    },
+                    // This is matching unknown locations.
+                    "unknown" => record.line_no = None,
+                    offset => {
+                        let value: usize = offset[1..].parse().unwrap_or(usize::MAX);
+                        let target = if offset.starts_with('+') {
+                            line_no.checked_add(value)
+                        } else {
+                            line_no.checked_sub(value)
+                        };
+                        match target.filter(|target| *target >= 1) {
+                            Some(target) => record.line_no = Some(target),
+                            None => {
+                                self.print_at(source_manager, id, file_loc,
+                                    "expected diagnostic offset points outside of the file");
+                                self.failed.store(true, Ordering::Relaxed);
+                                continue;
+                            }
+                        }
+                    }
+                }
+            }
+            expected_diags.push(record);
+        }
+        expected_diags
+    }
+
+    /// Print an error with `message` at `loc` in the buffer `id`.
+    fn print_at(&self, source_manager: &SourceManager, id: usize, loc: SMLoc, message: &str) {
+        self.printer.print(&source_manager.get_message(id, loc, DiagKind::Error, message, &[], &[]));
+    }
+
+    /// Process a single diagnostic.
+    fn process(&self, diagnostic: &Diagnostic) {
+        let kind = diagnostic.severity();
+        let file_loc = find_file_line_col(diagnostic.location());
+        let filename = file_loc.map(FileLineColLocation::filename);
+        let line_no = file_loc.map(FileLineColLocation::line);
+
+        // Search for a matching expected diagnostic. If we find something that is close then emit a more specific error.
+        let mut near_miss = None;
         {
-            PrintStackTrace(bt);
+            let mut expected_diags = self.expected_diags.lock().unwrap();
+            let candidates = expected_diags.iter_mut()
+                .filter(|(file, _)| filename.map_or(true, |filename| filename == file.as_str()))
+                .flat_map(|(file, diags)| diags.iter_mut().map(move |diag| (file, diag)));
+            for (file, expected) in candidates {
+                if expected.line_no != line_no || !expected.matches(diagnostic.message()) {
+                    continue;
+                }
+                // If this was an expected error, remember that we saw it and return.
+                if expected.kind == kind {
+                    expected.matched = true;
+                    return;
+                }
+                // If this only differs based on the diagnostic kind, then consider it to be a near miss.
+                near_miss = Some((file.clone(), expected.file_loc, expected.kind));
+            }
         }
-        if !bt.is_empty() {
-            diag.attach_note() << "diagnostic emitted with trace:\n" << bt;
+
+        // Otherwise, emit an error for the near miss.
+        match near_miss {
+            Some((file, loc, expected_kind)) => {
+                let source_manager = self.printer.source_manager.lock().unwrap();
+                let id = source_manager.find_buffer(&file).unwrap();
+                self.print_at(&source_manager, id, loc, &format!(
+                    "'{}' diagnostic emitted when expecting a '{}'", kind, expected_kind));
+            },
+            None => self.printer.emit(
+                diagnostic.location(),
+                &format!("unexpected {}: {}", kind, diagnostic.message()),
+                DiagnosticSeverity::Error,
+                true,
+                &[])
         }
+        self.failed.store(true, Ordering::Relaxed);
     }
-    diag
 }
 
-pub fn emit_error(location: Location, message: &Twine) -> Diagnostic {
-    emit_diag(location, Level::Error, message)
+/**
+This class is a utility diagnostic handler for use with llvm::SourceManager that verifies that emitted diagnostics match 'expected-*' lines on the corresponding line of the source file.
+
+The expectations are comments of the form `// expected-error {{message}}`, where `error` may also be `warning`, `remark` or `note`. The message only needs to be contained in the one of the diagnostic, and with `expected-error-re`, the parts within `{{` and `}}` are regexes. An expectation applies to its own line, or with `@+N`/`@-N` to the line N lines below or above, with `@above`/`@below` to the previous or next line without an expectation, and with `@unknown` to diagnostics without a file location.
+*/
+pub struct SourceMgrDiagnosticVerifierHandler {
+    verifier: Arc<SourceMgrDiagnosticVerifier>,
+    _handler: ScopedDiagnosticHandler
 }
 
-/*
-Diagnostic handler registration and use. MLIR supports the ability for the
-IR to carry arbitrary metadata about operation location information. If a
-problem is detected by the compiler, it can invoke the emitError /
-emitWarning / emitRemark method on an Operation and have it get reported
-through this interface.
+impl SourceMgrDiagnosticVerifierHandler {
+    /// Register a handler checking the diagnostics of `context` against the expectations of the buffers of `source_manager`, and printing mismatches to stderr.
+    pub fn new(source_manager: SourceManager, context: &MLIRContext) -> Self {
+        Self::with_output(source_manager, context, Box::new(io::stderr()))
+    }
 
-Tools using MLIR are encouraged to register error handlers and define a
-schema for their location information.  If they don't, then warnings and
-notes will be dropped and errors will be emitted to errs.
+    /// Register a handler checking the diagnostics of `context` against the expectations of the buffers of `source_manager`, and printing mismatches to `output`.
+    pub fn with_output(
+        source_manager: SourceManager,
+        context: &MLIRContext,
+        output: Box<dyn Write + Send>
+    ) -> Self {
+        let verifier = Arc::new(SourceMgrDiagnosticVerifier {
+            printer: SourceMgrDiagnosticPrinter::new(SourceManager::new(), output),
+            expected_diags: Mutex::new(HashMap::new()),
+            failed: AtomicBool::new(false)
+        });
+        // Compute the expected diagnostics for each of the current files in the source manager.
+        let mut expected_diags = HashMap::new();
+        for id in 1..=source_manager.num_buffers() {
+            expected_diags.insert(
+                source_manager.buffer_identifier(id).to_string(),
+                verifier.compute_expected_diags(&source_manager, id));
+        }
+        *verifier.expected_diags.lock().unwrap() = expected_diags;
+        *verifier.printer.source_manager.lock().unwrap() = source_manager;
+
+        // Register a handler to verify the diagnostics.
+        let handler_verifier = verifier.clone();
+        let handler = ScopedDiagnosticHandler::new(context, move |diagnostic| {
+            // Process the main diagnostics.
+            handler_verifier.process(diagnostic);
+            // Process each of the notes.
+            for note in diagnostic.notes() {
+                handler_verifier.process(note);
+            }
+            Ok(())
+        });
+        Self { verifier, _handler: handler }
+    }
+
+    /// Returns the status of the handler and verifies that all expected diagnostics were emitted. This return success if all diagnostics were verified correctly, failure otherwise.
+    pub fn verify(&self) -> LogicalResult {
+        // Verify that all expected errors were seen.
+        let expected_diags = core::mem::take(&mut *self.verifier.expected_diags.lock().unwrap());
+        let source_manager = self.verifier.printer.source_manager.lock().unwrap();
+        for (file, diags) in expected_diags {
+            let id = source_manager.find_buffer(&file).unwrap();
+            for diag in diags.iter().filter(|diag| !diag.matched) {
+                self.verifier.print_at(&source_manager, id, diag.file_loc, &format!(
+                    "expected {} \"{}\" was not produced", diag.kind, diag.substring));
+                self.verifier.failed.store(true, Ordering::Relaxed);
+            }
+        }
+        if self.verifier.failed.load(Ordering::Relaxed) {
+            Err("emitted diagnostics did not match the expected diagnostics".into())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// ParallelDiagnosticHandler
+// ----------------------------------------------------------------------
+
+#[derive(Default)]
+struct ParallelDiagnosticHandlerImpl {
+    /// A mapping between the thread ids of the active threads and their order ids.
+    thread_to_order_id: Mutex<HashMap<ThreadId, usize>>,
+    /// The diagnostics captured from the active threads, along with the order id of the thread that emitted them.
+    diagnostics: Mutex<Vec<(usize, Diagnostic)>>
+}
+
+/**
+This class is a utility diagnostic handler for use when multi-threading some part of the compiler where diagnostics may be emitted. This handler ensures a deterministic ordering to the emitted diagnostics that mirrors that of a single-threaded compilation.
+
+Each thread sets its order id with `set_order_id_for_thread` before the work it runs in parallel, e.g. to the index of the element it processes, and erases it with `erase_order_id_for_thread` afterwards. The diagnostics emitted by a thread with an order id are captured, and emitted in the order of their ids, then in the order they were emitted, when the handler is dropped. The diagnostics of the other threads go through to the previous handlers.
 */
+pub struct ParallelDiagnosticHandler {
+    handler_impl: Arc<ParallelDiagnosticHandlerImpl>,
+    /// The engine the captured diagnostics are emitted to.
+    engine: DiagnosticEngine,
+    /// The handler registered on the context, which is erased before the captured diagnostics are emitted.
+    handler: Option<ScopedDiagnosticHandler>
+}
 
-/// The handler type for MLIR diagnostics. This function takes a diagnostic as input, and returns success if the handler has fully processed this diagnostic. Returns failure otherwise.
-//llvm::unique_function<Result(&Diagnostic)>;
-type Handler = Box<dyn Fn(&Diagnostic) -> Result>; 
+impl ParallelDiagnosticHandler {
+    /// Register a handler capturing the diagnostics emitted to `context` by the threads with an order id.
+    pub fn new(context: &MLIRContext) -> Self {
+        let handler_impl = Arc::new(ParallelDiagnosticHandlerImpl::default());
+        let captured = handler_impl.clone();
+        let handler = ScopedDiagnosticHandler::new(context, move |diagnostic| {
+            let order_id = captured.thread_to_order_id.lock().unwrap()
+                .get(&thread::current().id()).copied();
+            // Leave the diagnostics of the threads without an order id to the previous handlers.
+            let Some(order_id) = order_id else {
+                return Err("the thread has no order id".into());
+            };
+            captured.diagnostics.lock().unwrap()
+                .push((order_id, diagnostic.clone()));
+            Ok(())
+        });
+        Self {
+            handler_impl,
+            engine: context.diag_engine().clone(),
+            handler: Some(handler)
+        }
+    }
 
-/// This diagnostic handler is a simple RAII class that registers and erases a diagnostic handler on a given context. This class can be either be used directly, or in conjunction with a derived diagnostic handler.
-pub struct ScopedDiagnosticHandler {
+    /// Set the order id for the current thread. This is required to be set by each thread that will be emitting diagnostics to this handler. The order id corresponds to the order in which diagnostics would be emitted when executing synchronously: when processing the operations [a, b, c], the thread processing `a` should set it to 0, the thread processing `b` to 1, and so on.
+    pub fn set_order_id_for_thread(&self, order_id: usize) {
+        self.handler_impl.thread_to_order_id.lock().unwrap()
+            .insert(thread::current().id(), order_id);
+    }
+
+    /// Remove the order id for the current thread. This removes the thread from diagnostics tracking.
+    pub fn erase_order_id_for_thread(&self) {
+        self.handler_impl.thread_to_order_id.lock().unwrap()
+            .remove(&thread::current().id());
+    }
+}
+
+impl Drop for ParallelDiagnosticHandler {
+    /// Emit the captured diagnostics to the engine, ordered by the order id of the thread that emitted them.
+    fn drop(&mut self) {
+        // Erase the handler first, so that the diagnostics reach the previous handlers.
+        self.handler = None;
+        let mut diagnostics = core::mem::take(
+            &mut *self.handler_impl.diagnostics.lock().unwrap());
+        // The sort is stable, which keeps the diagnostics of each thread in the order they were emitted.
+        diagnostics.sort_by_key(|(order_id, _)| *order_id);
+        for (_, diagnostic) in diagnostics {
+            self.engine.emit(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::builtins::attributes::StringAttribute;
+
+    /// An output stream whose contents can be read once written to.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    const INPUT: &str = "\
+func.func @f() {
+  // expected-error @+1 {{operand #0}}
+  test.op
+  // expected-warning-re @above {{{{[0-9]+}} uses}}
+  // expected-note @below {{defined here}}
+  // expected-remark @below {{dangling}}
+  test.def
+  // expected-error @unknown {{unknown location}}
+}
+";
+
+    fn location(line: usize) -> Location {
+        Location::file_line_col(StringAttribute::new("input.mlir"), line, 3)
+    }
+
+    fn expected(substring: &str, is_regex: bool) -> Result<ExpectedDiag, String> {
+        let mut expected = ExpectedDiag {
+            kind: DiagnosticSeverity::Error,
+            line_no: Some(1),
+            file_loc: SMLoc::from_offset(0),
+            matched: false,
+            substring: substring.to_string(),
+            regex: None
+        };
+        if is_regex {
+            expected.compute_regex()?;
+        }
+        Ok(expected)
+    }
+
+    /// Return a verifier of the expectations of `INPUT`, printing to `output`.
+    fn verifier(output: &Output) -> SourceMgrDiagnosticVerifier {
+        let mut source_manager = SourceManager::new();
+        let id = source_manager.add_new_source_buffer(INPUT, "input.mlir");
+        let verifier = SourceMgrDiagnosticVerifier {
+            printer: SourceMgrDiagnosticPrinter::new(SourceManager::new(), Box::new(output.clone())),
+            expected_diags: Mutex::new(HashMap::new()),
+            failed: AtomicBool::new(false)
+        };
+        let expected_diags = verifier.compute_expected_diags(&source_manager, id);
+        verifier.expected_diags.lock().unwrap().insert("input.mlir".into(), expected_diags);
+        *verifier.printer.source_manager.lock().unwrap() = source_manager;
+        verifier
+    }
+
+    #[test]
+    fn handlers_are_walked_from_the_most_recent() {
+        let engine = DiagnosticEngine::new();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let handler = |name: &'static str, result: fn() -> LogicalResult| -> Handler {
+            let seen = seen.clone();
+            Arc::new(move |diagnostic: &Diagnostic| {
+                seen.lock().unwrap().push(format!("{}: {}", name, diagnostic.message()));
+                result()
+            })
+        };
+        engine.register_handler(handler("first", || Ok(())));
+        let second = engine.register_handler(handler("second", || Err("declined".into())));
+        let third = engine.register_handler(handler("third", || Ok(())));
+        engine.emit(Diagnostic::new(Location::unknown(), DiagnosticSeverity::Error, "a"));
+        engine.erase_handler(third);
+        engine.emit(Diagnostic::new(Location::unknown(), DiagnosticSeverity::Warning, "b"));
+        engine.erase_handler(second);
+        engine.emit(Diagnostic::new(Location::unknown(), DiagnosticSeverity::Remark, "c"));
+        assert_eq!(*seen.lock().unwrap(), ["third: a", "second: b", "first: b", "first: c"]);
+    }
+
+    #[test]
+    fn notes_and_fix_its_are_attached() {
+        let mut diagnostic = Diagnostic::new(location(3), DiagnosticSeverity::Error, "bad");
+        diagnostic.append(" operand");
+        diagnostic.attach_note(Some(location(7)), "defined here");
+        diagnostic.attach_fix_it(location(3), 4, "good");
+        assert_eq!(diagnostic.message(), "bad operand");
+        let [note] = diagnostic.notes() else {
+            panic!("expected a single note");
+        };
+        assert_eq!(note.severity(), DiagnosticSeverity::Note);
+        assert_eq!(note.message(), "defined here");
+        let [fix_it] = diagnostic.fix_its() else {
+            panic!("expected a single fix-it");
+        };
+        assert_eq!((fix_it.length(), fix_it.replacement()), (4, "good"));
+    }
+
+    #[test]
+    fn regexes_are_delimited_by_braces() {
+        let diag = expected("operand #{{[0-9]+}} isn't {{.*}}.", true).unwrap();
+        assert!(diag.matches("error: operand #12 isn't an i32."));
+        assert!(!diag.matches("error: operand #x isn't an i32."));
+        // The text outside the braces is literal.
+        assert!(!diag.matches("operand #1 isn't an i32!"));
+
+        let diag = expected("a.b", false).unwrap();
+        assert!(diag.matches("in a.b"));
+        assert!(!diag.matches("in axb"));
+
+        assert_eq!(expected("{{[0-9]", true).err().unwrap(),
+            "found start of regex with no end '}}'");
+        assert!(expected("{{(}}", true).err().unwrap().starts_with("invalid regex"));
+    }
+
+    #[test]
+    fn expectations_are_read_from_comments() {
+        let output = Output::default();
+        let verifier = verifier(&output);
+        let expected_diags = verifier.expected_diags.lock().unwrap();
+        let lines: Vec<_> = expected_diags["input.mlir"].iter()
+            .map(|expected| (expected.kind, expected.line_no, expected.substring.as_str()))
+            .collect();
+        assert_eq!(lines, [
+            (DiagnosticSeverity::Error, Some(3), "operand #0"),
+            (DiagnosticSeverity::Warning, Some(3), "{{[0-9]+}} uses"),
+            (DiagnosticSeverity::Note, Some(7), "defined here"),
+            (DiagnosticSeverity::Remark, Some(7), "dangling"),
+            (DiagnosticSeverity::Error, None, "unknown location")
+        ]);
+        assert!(expected_diags["input.mlir"][1].matches("12 uses"));
+        assert!(output.contents().is_empty());
+    }
+
+    #[test]
+    fn invalid_expectations_are_reported() {
+        let output = Output::default();
+        let mut source_manager = SourceManager::new();
+        let id = source_manager.add_new_source_buffer(
+            "// expected-error @-2 {{outside}}\n// expected-error-re {{{{(}}}}\n", "input.mlir");
+        let verifier = SourceMgrDiagnosticVerifier {
+            printer: SourceMgrDiagnosticPrinter::new(SourceManager::new(), Box::new(output.clone())),
+            expected_diags: Mutex::new(HashMap::new()),
+            failed: AtomicBool::new(false)
+        };
+        assert!(verifier.compute_expected_diags(&source_manager, id).is_empty());
+        assert!(verifier.failed.load(Ordering::Relaxed));
+        let contents = output.contents();
+        assert!(contents.contains("expected diagnostic offset points outside of the file"));
+        assert!(contents.contains("invalid regex"));
+    }
+
+    #[test]
+    fn diagnostics_are_matched_against_expectations() {
+        let output = Output::default();
+        let verifier = verifier(&output);
+        let mut diagnostic = Diagnostic::new(
+            location(3), DiagnosticSeverity::Error, "operand #0 does not dominate this use");
+        diagnostic.attach_note(Some(location(7)), "operand defined here");
+        verifier.process(&diagnostic);
+        verifier.process(&diagnostic.notes()[0]);
+        verifier.process(&Diagnostic::new(
+            location(3), DiagnosticSeverity::Warning, "value has 2 uses"));
+        verifier.process(&Diagnostic::new(
+            Location::unknown(), DiagnosticSeverity::Error, "at an unknown location"));
+        assert!(!verifier.failed.load(Ordering::Relaxed));
+        assert!(output.contents().is_empty());
+        let unmatched: Vec<_> = verifier.expected_diags.lock().unwrap()["input.mlir"].iter()
+            .filter(|expected| !expected.matched)
+            .map(|expected| expected.substring.clone())
+            .collect();
+        assert_eq!(unmatched, ["dangling"]);
+
+        // A diagnostic of another severity is a near miss.
+        verifier.process(&Diagnostic::new(location(7), DiagnosticSeverity::Error, "dangling"));
+        assert!(verifier.failed.load(Ordering::Relaxed));
+        assert!(output.contents().contains(
+            "'error' diagnostic emitted when expecting a 'remark'"));
+        verifier.process(&Diagnostic::new(location(1), DiagnosticSeverity::Error, "surprise"));
+        assert!(output.contents().contains("unexpected error: surprise"));
+    }
 }
@@ -0,0 +1,529 @@
+/*!
+# Structured Diagnostics
+
+This file defines a diagnostic handler emitting diagnostics in a machine-readable form, for tools such as CI dashboards and editors rather than for people. Two formats are supported:
+
+- JSON lines, where each diagnostic is written as a JSON object on its own line as soon as it is emitted.
+- SARIF, where the diagnostics are collected and written as a single SARIF log once the handler is finished.
+
+Each diagnostic carries its severity, message, location, notes, fix-its and the printed form of the operations appended to it. Locations are kept whole, so that call stacks and fused locations can be inspected:
+
+```json
+{"severity":"error","message":"'test.op' op requires one result","location":{"kind":"call_site","callee":{"kind":"file_line_col","file":"callee.mlir","line":3,"column":8},"caller":{"kind":"file_line_col","file":"input.mlir","line":10,"column":3}},"notes":[],"operations":[],"fix_its":[]}
+```
+
+- SARIF <https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html>
+*/
+
+use std::{
+    io::{self, Write},
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering}
+    }
+};
+
+use serde::Serialize;
+use serde_json::{Value as Json, json};
+
+use crate::ir::{
+    diagnostics::{
+        Diagnostic, DiagnosticSeverity, FixIt, ScopedDiagnosticHandler,
+        call_site_location, find_file_line_col
+    },
+    location::Location,
+    mlir_context::MLIRContext
+};
+
+/// The machine-readable formats diagnostics can be emitted in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StructuredFormat {
+    /// One JSON object per diagnostic, each on its own line.
+    JsonLines,
+    /// A SARIF 2.1.0 log holding all of the diagnostics.
+    Sarif
+}
+
+// ----------------------------------------------------------------------
+// Records
+// ----------------------------------------------------------------------
+
+/// The structured form of a location, preserving the whole location chain.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LocationRecord {
+    FileLineCol {
+        file: String,
+        line: usize,
+        column: usize
+    },
+    CallSite {
+        callee: Box<LocationRecord>,
+        caller: Box<LocationRecord>
+    },
+    Fused {
+        locations: Vec<LocationRecord>,
+        /// The printed form of the metadata attribute, if any.
+        metadata: Option<String>
+    },
+    Name {
+        name: String,
+        child: Box<LocationRecord>
+    },
+    Opaque {
+        fallback: Box<LocationRecord>
+    },
+    Unknown
+}
+
+impl From<&Location> for LocationRecord {
+    fn from(location: &Location) -> Self {
+        match location {
+            Location::FileLineCol(location) => Self::FileLineCol {
+                file: location.filename().to_string(),
+                line: location.line(),
+                column: location.column()
+            },
+            Location::CallSite(location) => Self::CallSite {
+                callee: Box::new(location.callee().into()),
+                caller: Box::new(location.caller().into())
+            },
+            Location::Fused(location) => Self::Fused {
+                locations: location.locations().iter().map(Self::from).collect(),
+                metadata: location.metadata().map(|metadata| metadata.to_string())
+            },
+            Location::Name(location) => Self::Name {
+                name: location.name().to_string(),
+                child: Box::new(location.child_location().into())
+            },
+            Location::Opaque(location) => Self::Opaque {
+                fallback: Box::new(location.fallback_location().into())
+            },
+            Location::Unknown => Self::Unknown
+        }
+    }
+}
+
+/// The structured form of a fix-it.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FixItRecord {
+    pub location: LocationRecord,
+    pub length: usize,
+    pub replacement: String
+}
+
+impl From<&FixIt> for FixItRecord {
+    fn from(fix_it: &FixIt) -> Self {
+        Self {
+            location: fix_it.location().into(),
+            length: fix_it.length(),
+            replacement: fix_it.replacement().to_string()
+        }
+    }
+}
+
+/// The structured form of a diagnostic, which is written as a line of the JSON lines format.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct DiagnosticRecord {
+    pub severity: DiagnosticSeverity,
+    pub message: String,
+    pub location: LocationRecord,
+    pub notes: Vec<DiagnosticRecord>,
+    /// The printed form of the operations appended to the message.
+    pub operations: Vec<String>,
+    pub fix_its: Vec<FixItRecord>
+}
+
+impl From<&Diagnostic> for DiagnosticRecord {
+    fn from(diagnostic: &Diagnostic) -> Self {
+        Self {
+            severity: diagnostic.severity(),
+            message: diagnostic.message().to_string(),
+            location: diagnostic.location().into(),
+            notes: diagnostic.notes().iter().map(Self::from).collect(),
+            operations: diagnostic.printed_operations().to_vec(),
+            fix_its: diagnostic.fix_its().iter().map(FixItRecord::from).collect()
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// SARIF
+// ----------------------------------------------------------------------
+
+const SARIF_SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// Return the SARIF level of a result of the given severity. SARIF has no level for remarks, so they are reported as notes.
+fn sarif_level(severity: DiagnosticSeverity) -> &'static str {
+    match severity {
+        DiagnosticSeverity::Error => "error",
+        DiagnosticSeverity::Warning => "warning",
+        DiagnosticSeverity::Remark | DiagnosticSeverity::Note => "note"
+    }
+}
+
+/// Return the SARIF physical location of `location`, if it is in a file.
+fn sarif_physical_location(location: &Location) -> Option<Json> {
+    let file_loc = find_file_line_col(location)?;
+    let mut physical_location = json!({
+        "artifactLocation": { "uri": file_loc.filename() }
+    });
+    // SARIF lines and columns are 1-based, so a zero line or column means the position is unknown.
+    if file_loc.line() != 0 {
+        let mut region = json!({ "startLine": file_loc.line() });
+        if file_loc.column() != 0 {
+            region["startColumn"] = file_loc.column().into();
+        }
+        physical_location["region"] = region;
+    }
+    Some(physical_location)
+}
+
+/// Return the SARIF location of `location`, with the whole location chain kept in its property bag.
+fn sarif_location(location: &Location) -> Json {
+    let mut sarif_location = json!({
+        "properties": { "location": LocationRecord::from(location) }
+    });
+    if let Some(physical_location) = sarif_physical_location(location) {
+        sarif_location["physicalLocation"] = physical_location;
+    }
+    sarif_location
+}
+
+/// Return the frames of the call stack of `location`, innermost first, if it is a call site location.
+fn call_stack(mut location: &Location) -> Vec<&Location> {
+    let mut frames = Vec::new();
+    while let Some((callee, caller)) = call_site_location(location) {
+        frames.push(callee);
+        location = caller;
+    }
+    if !frames.is_empty() {
+        frames.push(location);
+    }
+    frames
+}
+
+/// Return the SARIF fix of `fix_it`, if it is located in a file.
+fn sarif_fix(fix_it: &FixIt) -> Option<Json> {
+    let Location::FileLineCol(file_loc) = fix_it.location() else {
+        return None;
+    };
+    Some(json!({
+        "artifactChanges": [{
+            "artifactLocation": { "uri": file_loc.filename() },
+            "replacements": [{
+                "deletedRegion": {
+                    "startLine": file_loc.line(),
+                    "startColumn": file_loc.column(),
+                    "endColumn": file_loc.column() + fix_it.length()
+                },
+                "insertedContent": { "text": fix_it.replacement() }
+            }]
+        }]
+    }))
+}
+
+/// Return the SARIF result reporting `diagnostic`. Notes become related locations, and the call stack of a call site location becomes a stack.
+fn sarif_result(diagnostic: &Diagnostic) -> Json {
+    let locations: Vec<Json> = match diagnostic.location() {
+        Location::Unknown => Vec::new(),
+        location => vec![sarif_location(location)]
+    };
+    let related_locations: Vec<Json> = diagnostic.notes().iter().enumerate()
+        .map(|(index, note)| {
+            let mut location = sarif_location(note.location());
+            location["id"] = index.into();
+            location["message"] = json!({ "text": note.message() });
+            location["properties"]["operations"] = note.printed_operations().into();
+            location
+        })
+        .collect();
+    let frames: Vec<Json> = call_stack(diagnostic.location()).into_iter()
+        .map(|location| json!({ "location": sarif_location(location) }))
+        .collect();
+    let fixes: Vec<Json> = diagnostic.fix_its().iter().filter_map(sarif_fix).collect();
+
+    let mut result = json!({
+        "level": sarif_level(diagnostic.severity()),
+        "message": { "text": diagnostic.message() },
+        "locations": locations,
+        "relatedLocations": related_locations,
+        "properties": {
+            "severity": diagnostic.severity(),
+            "operations": diagnostic.printed_operations()
+        }
+    });
+    if !frames.is_empty() {
+        result["stacks"] = json!([{ "frames": frames }]);
+    }
+    if !fixes.is_empty() {
+        result["fixes"] = fixes.into();
+    }
+    result
+}
+
+// ----------------------------------------------------------------------
+// StructuredDiagnosticHandler
+// ----------------------------------------------------------------------
+
+/// The state shared by the structured handler and the handler it registers.
+struct StructuredDiagnosticEmitter {
+    format: StructuredFormat,
+    /// The output stream to use when writing diagnostics.
+    output: Mutex<Box<dyn Write + Send>>,
+    /// The name of the tool reported in SARIF logs.
+    tool_name: Mutex<String>,
+    /// The SARIF results collected so far.
+    results: Mutex<Vec<Json>>,
+    /// Whether the SARIF log has been written.
+    finished: AtomicBool
+}
+
+impl StructuredDiagnosticEmitter {
+    fn emit_diagnostic(&self, diagnostic: &Diagnostic) {
+        match self.format {
+            StructuredFormat::JsonLines => {
+                let record = DiagnosticRecord::from(diagnostic);
+                let mut output = self.output.lock().unwrap();
+                // There is nowhere left to report failing to write a diagnostic.
+                let _ = serde_json::to_writer(&mut *output, &record)
+                    .map_err(io::Error::from)
+                    .and_then(|_| writeln!(output));
+            },
+            StructuredFormat::Sarif =>
+                self.results.lock().unwrap().push(sarif_result(diagnostic))
+        }
+    }
+
+    fn finish(&self) -> io::Result<()> {
+        let mut output = self.output.lock().unwrap();
+        if self.format == StructuredFormat::Sarif
+            && !self.finished.swap(true, Ordering::Relaxed)
+        {
+            let results = std::mem::take(&mut *self.results.lock().unwrap());
+            let log = json!({
+                "$schema": SARIF_SCHEMA,
+                "version": "2.1.0",
+                "runs": [{
+                    "tool": {
+                        "driver": {
+                            "name": self.tool_name.lock().unwrap().clone(),
+                            "informationUri": "https://mlir.llvm.org"
+                        }
+                    },
+                    "results": results
+                }]
+            });
+            serde_json::to_writer_pretty(&mut *output, &log)?;
+            writeln!(output)?;
+        }
+        output.flush()
+    }
+}
+
+/**
+This class is a diagnostic handler writing diagnostics in a machine-readable format, as JSON lines or as a SARIF log.
+
+JSON lines are written as the diagnostics are emitted. A SARIF log is written when `finish` is called, or when the handler is dropped.
+*/
+pub struct StructuredDiagnosticHandler {
+    emitter: Arc<StructuredDiagnosticEmitter>,
+    _handler: ScopedDiagnosticHandler
+}
+
+impl StructuredDiagnosticHandler {
+    /// Register a handler writing the diagnostics of `context` to stderr in the given format.
+    pub fn new(context: &MLIRContext, format: StructuredFormat) -> Self {
+        Self::with_output(context, format, Box::new(io::stderr()))
+    }
+
+    /// Register a handler writing the diagnostics of `context` to `output` in the given format.
+    pub fn with_output(
+        context: &MLIRContext,
+        format: StructuredFormat,
+        output: Box<dyn Write + Send>
+    ) -> Self {
+        let emitter = Arc::new(StructuredDiagnosticEmitter {
+            format,
+            output: Mutex::new(output),
+            tool_name: Mutex::new(env!("CARGO_PKG_NAME").to_string()),
+            results: Mutex::new(Vec::new()),
+            finished: AtomicBool::new(false)
+        });
+        let handler_emitter = emitter.clone();
+        let handler = ScopedDiagnosticHandler::new(context, move |diagnostic| {
+            handler_emitter.emit_diagnostic(diagnostic);
+            Ok(())
+        });
+        Self { emitter, _handler: handler }
+    }
+
+    /// Emit the given diagnostic. This is not meant to be called directly, but is used by the handler registered on the context.
+    pub fn emit_diagnostic(&self, diagnostic: &Diagnostic) {
+        self.emitter.emit_diagnostic(diagnostic);
+    }
+
+    /// Set the name of the tool reported in SARIF logs. Defaults to the name of this crate.
+    pub fn set_tool_name(&self, name: impl Into<String>) {
+        *self.emitter.tool_name.lock().unwrap() = name.into();
+    }
+
+    /// Write the SARIF log of the diagnostics emitted so far, and flush the output. The log is only written once, so diagnostics emitted afterwards are dropped.
+    pub fn finish(&self) -> io::Result<()> {
+        self.emitter.finish()
+    }
+}
+
+impl Drop for StructuredDiagnosticHandler {
+    fn drop(&mut self) {
+        let _ = self.emitter.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::builtins::attributes::StringAttribute;
+
+    /// An output stream whose contents can be read once written to.
+    #[derive(Clone, Default)]
+    struct Output(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for Output {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Output {
+        fn contents(&self) -> String {
+            String::from_utf8(self.0.lock().unwrap().clone()).unwrap()
+        }
+    }
+
+    fn file(name: &str, line: usize, column: usize) -> Location {
+        Location::file_line_col(StringAttribute::new(name), line, column)
+    }
+
+    /// An error in `callee.mlir` inlined into `input.mlir`, with a note and a fix-it.
+    fn diagnostic() -> Diagnostic {
+        let caller = Location::name(StringAttribute::new("inlined"), file("input.mlir", 10, 3));
+        let location = Location::call_site(file("callee.mlir", 3, 8), caller);
+        let mut diagnostic = Diagnostic::new(
+            location, DiagnosticSeverity::Error, "'test.op' op requires one result");
+        diagnostic.attach_note(Some(file("callee.mlir", 1, 1)), "see the callee");
+        diagnostic.attach_fix_it(file("callee.mlir", 3, 8), 7, "test.other");
+        diagnostic
+    }
+
+    fn emitter(format: StructuredFormat, output: &Output) -> StructuredDiagnosticEmitter {
+        StructuredDiagnosticEmitter {
+            format,
+            output: Mutex::new(Box::new(output.clone())),
+            tool_name: Mutex::new("mlir-opt".to_string()),
+            results: Mutex::new(Vec::new()),
+            finished: AtomicBool::new(false)
+        }
+    }
+
+    #[test]
+    fn records_keep_the_location_chain() {
+        let record = serde_json::to_value(DiagnosticRecord::from(&diagnostic())).unwrap();
+        assert_eq!(record, json!({
+            "severity": "error",
+            "message": "'test.op' op requires one result",
+            "location": {
+                "kind": "call_site",
+                "callee": { "kind": "file_line_col", "file": "callee.mlir", "line": 3, "column": 8 },
+                "caller": {
+                    "kind": "name",
+                    "name": "inlined",
+                    "child": { "kind": "file_line_col", "file": "input.mlir", "line": 10, "column": 3 }
+                }
+            },
+            "notes": [{
+                "severity": "note",
+                "message": "see the callee",
+                "location": { "kind": "file_line_col", "file": "callee.mlir", "line": 1, "column": 1 },
+                "notes": [],
+                "operations": [],
+                "fix_its": []
+            }],
+            "operations": [],
+            "fix_its": [{
+                "location": { "kind": "file_line_col", "file": "callee.mlir", "line": 3, "column": 8 },
+                "length": 7,
+                "replacement": "test.other"
+            }]
+        }));
+    }
+
+    #[test]
+    fn sarif_results_report_notes_stacks_and_fixes() {
+        let result = sarif_result(&diagnostic());
+        assert_eq!(result["level"], "error");
+        let physical_location = &result["locations"][0]["physicalLocation"];
+        assert_eq!(physical_location["artifactLocation"]["uri"], "callee.mlir");
+        assert_eq!(physical_location["region"], json!({ "startLine": 3, "startColumn": 8 }));
+        assert_eq!(result["locations"][0]["properties"]["location"]["kind"], "call_site");
+
+        let note = &result["relatedLocations"][0];
+        assert_eq!(note["id"], 0);
+        assert_eq!(note["message"]["text"], "see the callee");
+
+        // The call stack goes from the callee to the caller, looking through the name location.
+        let frames = result["stacks"][0]["frames"].as_array().unwrap();
+        let uris: Vec<_> = frames.iter()
+            .map(|frame| &frame["location"]["physicalLocation"]["artifactLocation"]["uri"])
+            .collect();
+        assert_eq!(uris, ["callee.mlir", "input.mlir"]);
+
+        let replacement = &result["fixes"][0]["artifactChanges"][0]["replacements"][0];
+        assert_eq!(replacement["deletedRegion"],
+            json!({ "startLine": 3, "startColumn": 8, "endColumn": 15 }));
+        assert_eq!(replacement["insertedContent"]["text"], "test.other");
+
+        // Remarks are notes in SARIF, and diagnostics without a location have no locations.
+        let result = sarif_result(&Diagnostic::new(
+            Location::unknown(), DiagnosticSeverity::Remark, "remark"));
+        assert_eq!(result["level"], "note");
+        assert_eq!(result["locations"], json!([]));
+        assert!(result.get("stacks").is_none() && result.get("fixes").is_none());
+    }
+
+    #[test]
+    fn json_lines_are_written_as_emitted() {
+        let output = Output::default();
+        let emitter = emitter(StructuredFormat::JsonLines, &output);
+        emitter.emit_diagnostic(&diagnostic());
+        emitter.emit_diagnostic(&Diagnostic::new(
+            file("input.mlir", 2, 1), DiagnosticSeverity::Warning, "unused"));
+        emitter.finish().unwrap();
+        let lines: Vec<Json> = output.contents().lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], serde_json::to_value(DiagnosticRecord::from(&diagnostic())).unwrap());
+        assert_eq!(lines[1]["severity"], "warning");
+    }
+
+    #[test]
+    fn sarif_logs_are_written_once() {
+        let output = Output::default();
+        let emitter = emitter(StructuredFormat::Sarif, &output);
+        emitter.emit_diagnostic(&diagnostic());
+        assert!(output.contents().is_empty());
+        emitter.finish().unwrap();
+        emitter.emit_diagnostic(&diagnostic());
+        emitter.finish().unwrap();
+        let log: Json = serde_json::from_str(&output.contents()).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(log["$schema"], SARIF_SCHEMA);
+        assert_eq!(log["runs"][0]["tool"]["driver"]["name"], "mlir-opt");
+        assert_eq!(log["runs"][0]["results"].as_array().unwrap().len(), 1);
+    }
+}
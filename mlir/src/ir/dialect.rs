@@ -12,7 +12,8 @@ pub mod registry;
 
 use core::{
     any::{Any, TypeId},
-    default::Default
+    default::Default,
+    fmt
 };
 use regex::Regex;
 use llvm::{
@@ -46,7 +47,7 @@ use crate::{
         operation::{
             Operation,
             implementation::OpAsmPrinter,
-            support::{OperationName, OperationState}
+            support::{OperationName, OperationState, ParseAssemblyFn}
         },
         pattern_match::RewritePatternSet,
         r#type::Type
@@ -59,13 +60,12 @@ use crate::{
 
 /// Type for a callback provided by the dialect to parse a custom operation.
 /// This is used for the dialect to provide an alternative way to parse custom operations, including unregistered ones.
-type ParseOpHook = fn(&OpAsmParser, &OperationState) -> LogicalResult;
-//    function_ref
+type ParseOpHook = ParseAssemblyFn;
 
 /// Dialects are groups of MLIR operations, types and attributes, as well as behaviour associated with the entire group. For example, hooks into other systems for constant folding, interfaces, default named types for asm printing, etc.
 ///
 /// Instances of the dialect object are loaded in a specific MLIRContext.
-pub trait Dialect: Any {
+pub trait Dialect: Any + Send + Sync {
     /// The name(space) of this dialect.
     const NAME: &'static str;
 
@@ -126,7 +126,10 @@ pub trait Dialect: Any {
 
     /// Print an attribute registered to this dialect. Note: The type of the attribute need not be printed by this method as it is always printed by the caller.
     fn print_attribute(
-        &self, attribute: Attribute, printer: &DialectAsmPrinter);
+        &self,
+        attribute: &dyn Attribute,
+        printer: &mut dyn DialectAsmPrinter
+    ) -> fmt::Result;
 
     // /// Parse a type registered to this dialect.
     // // virtual
@@ -143,7 +146,8 @@ pub trait Dialect: Any {
     // }
 
     /// Print a type registered to this dialect.
-    fn print_type(&self, r#type: Type, printer: &DialectAsmPrinter);
+    fn print_type(&self, r#type: &dyn Type, printer: &mut dyn DialectAsmPrinter)
+        -> fmt::Result;
 
     /// Return the hook to parse an operation registered to this dialect, if any.
     /// By default this will lookup for registered operations and return the `parse()` method registered on the RegisteredOperationName. Dialects can override this behaviour and handle unregistered operations as well.
@@ -153,7 +157,7 @@ pub trait Dialect: Any {
     fn operation_printer(
         &self,
         operation: *mut Operation
-    ) -> fn(&Operation, &OpAsmPrinter);  // unique_function
+    ) -> fn(&Operation, &mut dyn OpAsmPrinter) -> fmt::Result;  // unique_function
 
     /* Verification Hooks */
 
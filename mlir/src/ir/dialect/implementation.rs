@@ -4,51 +4,282 @@ This file contains utilities classes for implementing dialect attributes and typ
 - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/DialectImplementation.h>
 */
 
-use crate::mlir::ir::{
-    attribute::Attribute,
-    operation::implementation::{AsmParser, AsmPrinter}
+use core::{
+    fmt::{self, Write},
+    ops::{Deref, DerefMut}
+};
+use std::sync::Arc;
+
+use crate::{
+    asm_parser::{parser::ParseResult, token::TokenKind},
+    ir::{
+        affine_map::AffineMap,
+        attribute::Attribute,
+        builtins::attributes::AffineMapAttribute,
+        operation::implementation::{AsmParser, AsmPrinter, print_escaped_string},
+        r#type::Type
+    }
 };
 
 /**
 This is a pure-virtual base class that exposes the asmprinter hooks necessary to implement a custom printAttribute/printType() method on a dialect.
 */
 pub trait DialectAsmPrinter: AsmPrinter {
+    /// Push `id`, the address of the storage of an attribute or type, onto the stack of attributes and types being printed. Returns false, leaving the stack unchanged, if it is already on the stack.
+    fn push_cyclic_printing(&mut self, id: *const ()) -> bool;
+
+    /// Pop the last attribute or type pushed by `push_cyclic_printing`.
+    fn pop_cyclic_printing(&mut self);
+}
+
+impl<'a> dyn DialectAsmPrinter + 'a {
+    /**
+    Attempt to start the printing of the attribute or type with the given storage, to guard against infinite recursion when printing a self-referential attribute or type. Returns a guard, through which the printing continues and which ends it when dropped, or None if `storage` is already being printed. In that case, only the parameters identifying the attribute or type should be printed, e.g. the name of an identified struct, so that the parser can resolve the reference.
+    */
+    pub fn try_start_cyclic_print<T>(&mut self, storage: &T)
+        -> Option<CyclicPrintReset<'_, 'a>>
+    {
+        if !self.push_cyclic_printing(storage as *const T as *const ()) {
+            return None;
+        }
+        Some(CyclicPrintReset { printer: self })
+    }
+}
+
+/// Guard returned by `try_start_cyclic_print`, ending the printing of the attribute or type when dropped.
+pub struct CyclicPrintReset<'p, 'a> {
+    printer: &'p mut (dyn DialectAsmPrinter + 'a)
+}
+
+impl<'p, 'a> Deref for CyclicPrintReset<'p, 'a> {
+    type Target = dyn DialectAsmPrinter + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        self.printer
+    }
+}
 
+impl<'p, 'a> DerefMut for CyclicPrintReset<'p, 'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.printer
+    }
 }
 
+impl<'p, 'a> Drop for CyclicPrintReset<'p, 'a> {
+    fn drop(&mut self) {
+        self.printer.pop_cyclic_printing();
+    }
+}
 
 /**
 The DialectAsmParser has methods for interacting with the asm parser when parsing attributes and types.
 */
 pub trait DialectAsmParser: AsmParser {
+    /// Returns the full specification of the symbol being parsed. This allows for using a separate parser if necessary.
+    fn full_symbol_spec(&self) -> &str;
+
+    /// Push `id`, the address of the storage of an attribute or type, onto the stack of attributes and types being parsed. Returns false, leaving the stack unchanged, if it is already on the stack.
+    fn push_cyclic_parsing(&mut self, id: *const ()) -> bool;
+
+    /// Pop the last attribute or type pushed by `push_cyclic_parsing`.
+    fn pop_cyclic_parsing(&mut self);
+}
+
+impl<'a> dyn DialectAsmParser + 'a {
+    /**
+    Attempt to start the parsing of the body of the attribute or type with the given storage, to resolve self-references. Returns a guard, through which the parsing continues and which ends it when dropped, or None if the body of `storage` is already being parsed. In that case, the symbol being parsed is a reference to the enclosing attribute or type, and only its identifying parameters are expected.
+    */
+    pub fn try_start_cyclic_parse<T>(&mut self, storage: &T)
+        -> Option<CyclicParseReset<'_, 'a>>
+    {
+        if !self.push_cyclic_parsing(storage as *const T as *const ()) {
+            return None;
+        }
+        Some(CyclicParseReset { parser: self })
+    }
+}
+
+/// Guard returned by `try_start_cyclic_parse`, ending the parsing of the attribute or type when dropped.
+pub struct CyclicParseReset<'p, 'a> {
+    parser: &'p mut (dyn DialectAsmParser + 'a)
+}
+
+impl<'p, 'a> Deref for CyclicParseReset<'p, 'a> {
+    type Target = dyn DialectAsmParser + 'a;
+
+    fn deref(&self) -> &Self::Target {
+        self.parser
+    }
+}
 
+impl<'p, 'a> DerefMut for CyclicParseReset<'p, 'a> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.parser
+    }
+}
+
+impl<'p, 'a> Drop for CyclicParseReset<'p, 'a> {
+    fn drop(&mut self) {
+        self.parser.pop_cyclic_parsing();
+    }
 }
 
 /**
-Provide a template class that can be specialised by users to dispatch to parsers. Auto-generated parsers generate calls to `FieldParser<T>::parse`, where `T` is the parameter storage type, to parse custom types. Parse an attribute.
+Hook parsing the types of a dialect, attached to the `ParserConfig` for the namespace of the dialect. The parser is positioned at the start of the symbol data, i.e. after `!dialect.` in the pretty form and after `!dialect<` in the verbose form, and the hook must consume all of it.
 */
-pub trait FieldParser {
-    fn parse(parser: &impl AsmParser) -> Result<Self>;
+pub type DialectTypeParser
+    = Arc<dyn Fn(&mut dyn DialectAsmParser) -> ParseResult<Arc<dyn Type>> + Send + Sync>;
+
+// ----------------------------------------------------------------------
+// Parameters
+// ----------------------------------------------------------------------
+
+/**
+Parser of a parameter of an attribute or type. The parsers generated by `#[derive(AttrOrTypeDef)]` call `FieldParser::parse` for each parameter, so parameters of custom types are supported by implementing this trait, along with `FieldPrinter`.
+*/
+pub trait FieldParser: Sized {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self>;
+}
+
+/// Printer of a parameter of an attribute or type, the counterpart of `FieldParser`.
+pub trait FieldPrinter {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result;
 }
 
 // Parse an attribute.
-impl<T: Attribute> FieldParser for T {
-  fn parse(parser: &impl AsmParser) -> Result<Self> {
-        let value = Attribute::new();
-        if parser.parse_custom_attribute_with_fallback(value) {
-            Err(())
+impl FieldParser for Arc<dyn Attribute> {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        parser.parse_attribute()
+    }
+}
+
+impl FieldPrinter for Arc<dyn Attribute> {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        printer.print_attribute(self.as_ref())
+    }
+}
+
+// Parse a type.
+impl FieldParser for Arc<dyn Type> {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        parser.parse_type()
+    }
+}
+
+impl FieldPrinter for Arc<dyn Type> {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        printer.print_type(self.as_ref())
+    }
+}
+
+// Parse an affine map, written as an affine map attribute.
+impl FieldParser for AffineMap {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        let location = parser.current_location();
+        let attribute = parser.parse_attribute()?;
+        match attribute.downcast_ref::<AffineMapAttribute>() {
+            Some(attribute) => Ok(attribute.value().clone()),
+            None => Err(parser.emit_error(location, "expected affine map".into()))
         }
-        Ok(value)
     }
 }
 
-// Parse an attribute.
-impl<T: Attribute> FieldParser for T {
-    fn parse(parser: &impl AsmParser) -> Result<Self> {
-        let value = Attribute::new();
-        if parser.parse_custom_attribute_with_fallback(value) {
-            Err(())
+impl FieldPrinter for AffineMap {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        printer.print_attribute(&AffineMapAttribute::new(self.clone()))
+    }
+}
+
+// Parse a quoted string.
+impl FieldParser for String {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        parser.parse_string()
+    }
+}
+
+impl FieldPrinter for String {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        print_escaped_string(printer, self)
+    }
+}
+
+// Parse `true` or `false`.
+impl FieldParser for bool {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        if parser.parse_optional_keyword("true") {
+            return Ok(true);
+        }
+        if parser.parse_optional_keyword("false") {
+            return Ok(false);
+        }
+        Err(parser.emit_error(
+            parser.current_location(), "expected 'true' or 'false'".into()))
+    }
+}
+
+impl FieldPrinter for bool {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        printer.write_str(if *self { "true" } else { "false" })
+    }
+}
+
+// Parse an integer, which must fit in the type of the parameter.
+macro_rules! impl_integer_field {
+    ($($type:ty),*) => {
+        $(
+            impl FieldParser for $type {
+                fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+                    let location = parser.current_location();
+                    let value = parser.parse_integer()?;
+                    let value = if value.is_negative() {
+                        value.sext_value().and_then(|value| Self::try_from(value).ok())
+                    } else {
+                        value.zext_value().and_then(|value| Self::try_from(value).ok())
+                    };
+                    value.ok_or_else(|| parser.emit_error(location, format!(
+                        "integer value out of range for '{}'", stringify!($type))))
+                }
+            }
+
+            impl FieldPrinter for $type {
+                fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+                    write!(printer, "{}", self)
+                }
+            }
+        )*
+    };
+}
+
+impl_integer_field!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+// Parse a list of parameters between square brackets, e.g. `[1, 2, 3]`.
+impl<T: FieldParser> FieldParser for Vec<T> {
+    fn parse(parser: &mut dyn DialectAsmParser) -> ParseResult<Self> {
+        parser.parse_token(TokenKind::LSquare)?;
+        let mut elements = Vec::new();
+        if parser.parse_optional_token(TokenKind::RSquare) {
+            return Ok(elements);
+        }
+        loop {
+            elements.push(T::parse(parser)?);
+            if !parser.parse_optional_comma() {
+                break;
+            }
+        }
+        parser.parse_token(TokenKind::RSquare)?;
+        Ok(elements)
+    }
+}
+
+impl<T: FieldPrinter> FieldPrinter for Vec<T> {
+    fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        printer.write_char('[')?;
+        for (index, element) in self.iter().enumerate() {
+            if index != 0 {
+                printer.write_str(", ")?;
+            }
+            element.print(printer)?;
         }
-        Ok(value)
+        printer.write_char(']')
     }
 }
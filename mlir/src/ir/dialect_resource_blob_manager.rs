@@ -0,0 +1,326 @@
+//! Dialect Resource Blob Management
+//!
+//! This file defines utility classes for referencing and managing asm resource blobs. These classes are intended to more easily facilitate the sharing of large blobs, and their definition.
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/DialectResourceBlobManager.h>
+//! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/DialectResourceBlobManager.cpp>
+
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    fs::File,
+    io,
+    path::Path,
+    sync::{Arc, RwLock}
+};
+
+use memmap2::{Mmap, MmapOptions};
+
+use crate::{
+    ir::{
+        asm_state::{
+            AsmParsedResourceEntry, AsmResourceBlob, AsmResourceBuilder,
+            SharedResourceBuffer
+        },
+        builtins::attributes::DenseResourceElementsAttribute,
+        mlir_context::MLIRContext,
+        operation::{
+            Operation,
+            implementation::{AsmDialectResourceHandle, OpAsmDialectInterface}
+        }
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
+// DialectResourceBlobManager
+// ----------------------------------------------------------------------
+
+/// The class represents an individual entry of a blob.
+#[derive(Clone, Debug)]
+pub struct BlobEntry {
+    /// The key used for this blob.
+    key: String,
+    /// The blob that is referenced by this entry if it is valid.
+    blob: Option<AsmResourceBlob>
+}
+
+impl BlobEntry {
+    /// Return the key used to reference this blob.
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// Return the blob owned by this entry if one has been initialized. Returns None otherwise.
+    pub fn blob(&self) -> Option<&AsmResourceBlob> {
+        self.blob.as_ref()
+    }
+}
+
+/**
+This class defines a manager for dialect resource blobs. Blobs are uniqued by a given key, and represented using AsmResourceBlobs. Entries are looked up and updated under a lock, so the manager may be shared between threads.
+*/
+#[derive(Debug, Default)]
+pub struct DialectResourceBlobManager {
+    /// A map of key to blob entries.
+    blob_map: RwLock<HashMap<String, BlobEntry>>
+}
+
+impl DialectResourceBlobManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the blob registered for the given name, or None if no blob is registered.
+    pub fn lookup(&self, name: &str) -> Option<BlobEntry> {
+        self.blob_map.read().unwrap().get(name).cloned()
+    }
+
+    /// Update the blob for the entry defined by the provided name. This method asserts that an entry for the given name exists in the manager.
+    pub fn update(&self, name: &str, new_blob: AsmResourceBlob) {
+        let mut blob_map = self.blob_map.write().unwrap();
+        let entry = blob_map.get_mut(name)
+            .expect("`update` expects an existing entry for the provided name");
+        entry.blob = Some(new_blob);
+    }
+
+    /// Insert a new entry with the provided name and optional blob data. The name may be modified during insertion if another entry already exists with that name. Returns the key of the inserted entry.
+    pub fn insert(&self, name: &str, blob: Option<AsmResourceBlob>) -> String {
+        let mut blob_map = self.blob_map.write().unwrap();
+
+        // If an entry already exists for the user provided name, tweak the name and re-attempt insertion until we find one that is unique.
+        let mut key = name.to_string();
+        let mut name_counter = 1;
+        while blob_map.contains_key(&key) {
+            key = format!("{}_{}", name, name_counter);
+            name_counter += 1;
+        }
+        blob_map.insert(key.clone(), BlobEntry { key: key.clone(), blob });
+        key
+    }
+}
+
+// ----------------------------------------------------------------------
+// ResourceBlobManagerDialectInterface
+// ----------------------------------------------------------------------
+
+/**
+This class implements a dialect interface that provides common functionality for interacting with a resource blob manager. Resources declared while parsing are inserted into the manager, renamed if their key is already in use, and the blobs of the referenced resources are built from the manager when printing.
+*/
+pub struct ResourceBlobManagerDialectInterface {
+    /// The namespace of the dialect owning the resources.
+    dialect: String,
+    /// The type of the resources, used to identify the kind of the handles.
+    opaque_id: TypeId,
+    /// The blob manager owned by the dialect implementing this interface.
+    blob_manager: Arc<DialectResourceBlobManager>
+}
+
+impl ResourceBlobManagerDialectInterface {
+    pub fn new(
+        dialect: impl Into<String>,
+        opaque_id: TypeId,
+        blob_manager: Arc<DialectResourceBlobManager>
+    ) -> Self {
+        Self { dialect: dialect.into(), opaque_id, blob_manager }
+    }
+
+    /// Return the interface for the resources of the builtin dialect, i.e. those referenced by `dense_resource` attributes, backed by the manager of `context`.
+    pub fn builtin(context: &MLIRContext) -> Self {
+        Self::new(
+            "builtin",
+            TypeId::of::<DenseResourceElementsAttribute>(),
+            context.resource_blob_manager("builtin"))
+    }
+
+    /// Return the blob manager held by this interface.
+    pub fn blob_manager(&self) -> &Arc<DialectResourceBlobManager> {
+        &self.blob_manager
+    }
+
+    /// Insert the given blob into the blob manager, returning a handle to the inserted entry. The key of the handle may differ from `name` if it was already in use.
+    pub fn insert(&self, name: &str, blob: Option<AsmResourceBlob>)
+        -> AsmDialectResourceHandle
+    {
+        let key = self.blob_manager.insert(name, blob);
+        AsmDialectResourceHandle::new(key, self.opaque_id, self.dialect.as_str())
+    }
+}
+
+impl OpAsmDialectInterface for ResourceBlobManagerDialectInterface {
+    fn dialect(&self) -> &str {
+        &self.dialect
+    }
+
+    fn build_resources(
+        &self,
+        top_level_op: &Operation,
+        referenced: &[AsmDialectResourceHandle],
+        builder: &mut AsmResourceBuilder
+    ) {
+        for handle in referenced {
+            if let Some(entry) = self.blob_manager.lookup(handle.key())
+                && let Some(blob) = entry.blob()
+            {
+                builder.build_resource_blob(handle.key(), blob);
+            }
+        }
+    }
+
+    fn declare_resource(&self, key: &str) -> Option<AsmDialectResourceHandle> {
+        Some(self.insert(key, None))
+    }
+
+    fn parse_resource(&self, entry: &AsmParsedResourceEntry) -> LogicalResult {
+        let blob = entry.parse_as_blob()?;
+        self.blob_manager.update(entry.key(), blob);
+        Ok(())
+    }
+}
+
+// ----------------------------------------------------------------------
+// Memory Mapped Blobs
+// ----------------------------------------------------------------------
+
+/// This class provides a simple utility wrapper for creating AsmResourceBlobs backed by memory mapped files. The data is paged in on access and never copied, and the mapping is released once the last blob referencing it is dropped.
+pub struct MappedAsmResourceBlob;
+
+impl MappedAsmResourceBlob {
+    /// Map the entire file at `path` into memory, returning a buffer that blobs may reference through `AsmResourceBlob::from_shared`.
+    pub fn map_file(path: impl AsRef<Path>) -> io::Result<SharedResourceBuffer> {
+        let file = File::open(path)?;
+        // SAFETY: The mapping is read-only. As with any file mapping, the file must not be truncated or modified while it is mapped.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Arc::new(map))
+    }
+
+    /// Create a read-only blob of `length` bytes starting at `offset` of the file at `path`. The start of the data must satisfy `alignment`.
+    pub fn map_file_region(
+        path: impl AsRef<Path>,
+        offset: u64,
+        length: usize,
+        alignment: usize
+    ) -> io::Result<AsmResourceBlob> {
+        let file = File::open(path)?;
+        // SAFETY: See `map_file`.
+        let map = unsafe { MmapOptions::new().offset(offset).len(length).map(&file)? };
+        if alignment != 0 && map.as_ptr() as usize % alignment != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, format!(
+                "mapped data at offset {} is not aligned to {} bytes",
+                offset, alignment)));
+        }
+        Ok(AsmResourceBlob::new(map, alignment, false))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs, process};
+    use super::*;
+    use crate::ir::{
+        asm_state::AsmResourceValue,
+        builtins::attributes::DictionaryAttribute,
+        location::Location,
+        operation::support::OperationName,
+        r#type::Type
+    };
+
+    /// Return a blob holding a copy of `data`.
+    fn blob(data: &[u8]) -> AsmResourceBlob {
+        AsmResourceBlob::new(data.to_vec(), 1, false)
+    }
+
+    /// Return an interface for the resources of a `test` dialect, with an empty manager.
+    fn test_interface() -> ResourceBlobManagerDialectInterface {
+        ResourceBlobManagerDialectInterface::new(
+            "test", TypeId::of::<BlobEntry>(), Arc::new(DialectResourceBlobManager::new()))
+    }
+
+    #[test]
+    fn inserted_names_are_uniqued() {
+        let manager = DialectResourceBlobManager::new();
+        assert_eq!(manager.insert("blob", Some(blob(&[1]))), "blob");
+        assert_eq!(manager.insert("blob", None), "blob_1");
+        assert_eq!(manager.insert("blob", Some(blob(&[3]))), "blob_2");
+        // A name that happens to look uniqued is uniqued in turn.
+        assert_eq!(manager.insert("blob_1", None), "blob_1_1");
+
+        assert_eq!(manager.lookup("blob").unwrap().blob().unwrap().data(), [1]);
+        assert!(manager.lookup("blob_1").unwrap().blob().is_none());
+        assert_eq!(manager.lookup("blob_2").unwrap().key(), "blob_2");
+        assert!(manager.lookup("blob_3").is_none());
+    }
+
+    #[test]
+    fn updated_entries_hold_the_new_blob() {
+        let manager = DialectResourceBlobManager::new();
+        let key = manager.insert("blob", None);
+        manager.update(&key, blob(&[1, 2]));
+        manager.update(&key, blob(&[3]));
+        assert_eq!(manager.lookup(&key).unwrap().blob().unwrap().data(), [3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "`update` expects an existing entry")]
+    fn updating_a_missing_entry_panics() {
+        DialectResourceBlobManager::new().update("blob", blob(&[1]));
+    }
+
+    #[test]
+    fn parsed_resources_fill_declared_entries() {
+        let interface = test_interface();
+        let handle = interface.declare_resource("blob").unwrap();
+        assert_eq!((handle.key(), handle.dialect()), ("blob", "test"));
+        assert_eq!(handle.type_id(), TypeId::of::<BlobEntry>());
+
+        // The alignment is the leading little-endian `u32` of the hex string.
+        let entry = AsmParsedResourceEntry::from_literal("blob", "0x08000000CAFE".to_string());
+        interface.parse_resource(&entry).unwrap();
+        let blob = interface.blob_manager().lookup("blob").unwrap().blob().cloned().unwrap();
+        assert_eq!((blob.data(), blob.data_alignment()), (&[0xCA, 0xFE][..], 8));
+
+        let entry = AsmParsedResourceEntry::from_bool("blob", true);
+        assert!(interface.parse_resource(&entry).is_err());
+    }
+
+    #[test]
+    fn only_resources_with_data_are_built() {
+        let interface = test_interface();
+        let declared = interface.declare_resource("declared").unwrap();
+        let filled = interface.insert("filled", Some(blob(&[0xAB])));
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new("test.op"),
+            Vec::<Arc<dyn Type>>::new(), Vec::new(), DictionaryAttribute::default(), &[]);
+
+        let mut builder = AsmResourceBuilder::new();
+        interface.build_resources(&operation, &[declared, filled], &mut builder);
+        let [(key, value)] = builder.entries() else {
+            panic!("Expected a single resource entry");
+        };
+        assert_eq!(key, "filled");
+        assert!(matches!(value, AsmResourceValue::Blob(_)));
+        assert_eq!(value.to_string(), "\"0x01000000AB\"");
+    }
+
+    #[test]
+    fn mapped_blobs_reference_the_file() {
+        let path = std::env::temp_dir()
+            .join(format!("mlir-mapped-blob-{}.bin", process::id()));
+        let data: Vec<u8> = (0..16).collect();
+        fs::write(&path, &data).unwrap();
+
+        let buffer = MappedAsmResourceBlob::map_file(&path).unwrap();
+        let blob = AsmResourceBlob::from_shared(buffer.clone(), 2..6, 1, false);
+        assert_eq!(blob.data(), [2, 3, 4, 5]);
+        assert_eq!(blob.data().as_ptr(), (*buffer).as_ref()[2..].as_ptr());
+
+        // Mappings start on a page boundary, so the alignment of the data follows from its offset.
+        let region = MappedAsmResourceBlob::map_file_region(&path, 4, 8, 4).unwrap();
+        assert_eq!(region.data(), &data[4..12]);
+        assert_eq!(region.data_alignment(), 4);
+        let error = MappedAsmResourceBlob::map_file_region(&path, 4, 8, 8).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -8,21 +8,1208 @@
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/Dominance.h>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/Dominance.cpp>
 
-use std::cell::Cell;
-use llvm::adt::{
-    dense_map::DenseMap,
-    pointer_int_pair::PointerIntPair
+use std::{
+    cell::{Ref, RefCell},
+    collections::{BinaryHeap, HashMap, HashSet}
 };
-use crate::ir::region::Region;
 
-pub struct DominanceInfoBase {
-    //! A mapping of regions to their base dominator tree and a cached "hasSSADominance" bit. This map does not contain dominator trees for single block CFG regions, but we do want to cache the "hasSSADominance" bit for them. We may also not have computed the DomTree yet. In either case, the DomTree is just null.
-    dominance_infos: Cell<
-        DenseMap<Option<Region>, PointerIntPair<Option<DomTree>, 1, bool>>>
+use crate::{
+    ir::{
+        arena::{ArenaResult, BlockId, IRArena, OpId, RegionId},
+        region_kind_interface,
+        value::Value
+    },
+    pass::analysis_manager::{Analysis, AnalysisManager}
+};
+
+// ----------------------------------------------------------------------
+// DominatorTreeBase
+// ----------------------------------------------------------------------
+
+/// A node of a dominator tree: a block of the region, or `None` for the virtual root of post-dominator trees, which leads to the exit blocks of the region.
+type Node = Option<BlockId>;
+
+/// The dominator tree of the blocks of a region, or with `IS_POST_DOM` its post-dominator tree, rooted at a virtual node leading to the exit blocks (the blocks without successors) of the region.
+///
+/// The tree is computed with the iterative algorithm of Cooper, Harvey and Kennedy, "A Simple, Fast Dominance Algorithm", and kept up to date across changes of the CFG with the incremental algorithms of Georgiadis et al., "An Experimental Study of Dynamic Dominators", as LLVM does. It keeps its own copy of the edges of the region, so that an update only has to describe the change, and may be applied before or after the IR is changed.
+///
+/// Blocks that are unreachable from the entry block, or for post-dominators that reach no exit block, are not in the tree. They are dominated by every block, and dominate none.
+///
+/// - include
+///   - <https://github.com/llvm/llvm-project/blob/main/llvm/include/llvm/Support/GenericDomTree.h>
+///   - <https://github.com/llvm/llvm-project/blob/main/llvm/include/llvm/Support/GenericDomTreeConstruction.h>
+pub struct DominatorTreeBase<const IS_POST_DOM: bool> {
+    /// The entry block of the region.
+    entry: BlockId,
+    /// The successors of each block of the region, once per edge.
+    successors: HashMap<BlockId, Vec<BlockId>>,
+    /// The predecessors of each block of the region, once per edge.
+    predecessors: HashMap<BlockId, Vec<BlockId>>,
+    /// The immediate dominator of each node of the tree. The root is its own immediate dominator.
+    idoms: HashMap<Node, Node>,
+    /// The children of each node of the tree, in no particular order.
+    children: HashMap<Node, Vec<Node>>,
+    /// The depth of each node of the tree, the root being at level 0.
+    levels: HashMap<Node, usize>
+}
+
+/// The dominator tree of a region.
+pub type DomTree = DominatorTreeBase<false>;
+/// The post-dominator tree of a region.
+pub type PostDomTree = DominatorTreeBase<true>;
+
+impl<const IS_POST_DOM: bool> DominatorTreeBase<IS_POST_DOM> {
+    /// Compute the tree of `region`, which must not be empty.
+    pub fn new(arena: &IRArena, region: RegionId) -> ArenaResult<Self> {
+        let region = arena.region(region)?;
+        let entry = region.front()
+            .expect("Cannot compute the dominator tree of an empty region");
+        let mut tree = Self {
+            entry,
+            successors: HashMap::new(),
+            predecessors: HashMap::new(),
+            idoms: HashMap::new(),
+            children: HashMap::new(),
+            levels: HashMap::new()
+        };
+        for &block in region.blocks() {
+            tree.successors.insert(block, Vec::new());
+            tree.predecessors.insert(block, Vec::new());
+        }
+        for &block in region.blocks() {
+            for &successor in arena.block(block)?.successors(arena)? {
+                tree.add_edge(block, successor);
+            }
+        }
+        tree.recalculate();
+        Ok(tree)
+    }
+
+    /// Return the blocks at the top of the tree: the entry block, or for post-dominators the exit blocks.
+    pub fn roots(&self) -> Vec<BlockId> {
+        if IS_POST_DOM {
+            self.graph_successors(None).into_iter().flatten().collect()
+        } else {
+            vec![self.entry]
+        }
+    }
+
+    /// Return true if `block` is a block of the region, reachable or not.
+    pub fn contains(&self, block: BlockId) -> bool {
+        self.successors.contains_key(&block)
+    }
+
+    /// Return true if `block` is in the tree, i.e. if it is reachable from the entry block, or for post-dominators if it reaches an exit block.
+    pub fn is_reachable(&self, block: BlockId) -> bool {
+        self.idoms.contains_key(&Some(block))
+    }
+
+    /// Return the immediate dominator of `block`, or `None` for the roots of the tree and unreachable blocks.
+    pub fn idom(&self, block: BlockId) -> Option<BlockId> {
+        self.idoms.get(&Some(block)).copied().flatten().filter(|idom| *idom != block)
+    }
+
+    /// Return the blocks immediately dominated by `block`, in no particular order.
+    pub fn children(&self, block: BlockId) -> Vec<BlockId> {
+        self.children.get(&Some(block)).into_iter().flatten().flatten().copied().collect()
+    }
+
+    /// Return the depth of `block` in the tree, or `None` if it is unreachable.
+    pub fn level(&self, block: BlockId) -> Option<usize> {
+        self.levels.get(&Some(block)).copied()
+    }
+
+    /// Return true if `a` dominates `b`. Note that blocks dominate themselves.
+    pub fn dominates(&self, a: BlockId, b: BlockId) -> bool {
+        a == b || self.properly_dominates(a, b)
+    }
+
+    /// Return true if `a` dominates `b` and `a != b`.
+    pub fn properly_dominates(&self, a: BlockId, b: BlockId) -> bool {
+        if a == b {
+            return false;
+        }
+        // An unreachable block is dominated by anything, and dominates nothing.
+        if !self.is_reachable(b) {
+            return true;
+        }
+        self.is_reachable(a) && self.node_dominates(Some(a), Some(b))
+    }
+
+    /// Find the nearest block dominating both `a` and `b`. Return `None` if either is unreachable, or for post-dominators if their paths only meet at the exits of the region.
+    pub fn find_nearest_common_dominator(&self, a: BlockId, b: BlockId) -> Option<BlockId> {
+        if !self.is_reachable(a) || !self.is_reachable(b) {
+            return None;
+        }
+        self.nearest_common_dominator(Some(a), Some(b))
+    }
+
+    /// Update the tree for the insertion of an edge from `from` to `to`. Edges to or from blocks of other regions are ignored.
+    pub fn insert_edge(&mut self, from: BlockId, to: BlockId) {
+        let was_exit = self.successors.get(&from).is_some_and(Vec::is_empty);
+        if !self.add_edge(from, to) {
+            return;
+        }
+        if !IS_POST_DOM {
+            self.insert_node_edge(Some(from), Some(to));
+        } else if was_exit {
+            // `from` is no longer an exit block, so the edge from the virtual root to it is gone as well.
+            self.recalculate();
+        } else {
+            self.insert_node_edge(Some(to), Some(from));
+        }
+    }
+
+    /// Update the tree for the deletion of an edge from `from` to `to`. If there are several such edges, only one of them is deleted.
+    pub fn delete_edge(&mut self, from: BlockId, to: BlockId) {
+        if !self.remove_edge(from, to) {
+            return;
+        }
+        if !IS_POST_DOM {
+            self.delete_node_edge(Some(from), Some(to));
+        } else if self.successors[&from].is_empty() {
+            // `from` becomes an exit block, so an edge from the virtual root to it appears as well.
+            self.recalculate();
+        } else {
+            self.delete_node_edge(Some(to), Some(from));
+        }
+    }
+
+    /// Update the tree for the insertion of `block` in the region, with edges from `predecessors` and to `successors`. Self-loops are taken from `successors`.
+    pub fn insert_block(&mut self, block: BlockId, predecessors: &[BlockId], successors: &[BlockId]) {
+        if self.contains(block) {
+            return;
+        }
+        self.successors.insert(block, Vec::new());
+        self.predecessors.insert(block, Vec::new());
+        let predecessors = predecessors.iter().copied().filter(|predecessor| *predecessor != block);
+        // Add the edges leading to the block in the direction of the tree first, so that it is reachable by the time the edges leaving it are added.
+        if IS_POST_DOM {
+            for &successor in successors {
+                self.add_edge(block, successor);
+            }
+        } else {
+            for predecessor in predecessors.clone() {
+                self.add_edge(predecessor, block);
+            }
+        }
+        for node in self.graph_predecessors(Some(block)) {
+            self.insert_node_edge(node, Some(block));
+        }
+        if IS_POST_DOM {
+            for predecessor in predecessors {
+                self.insert_edge(predecessor, block);
+            }
+        } else {
+            for &successor in successors {
+                self.insert_edge(block, successor);
+            }
+        }
+    }
+
+    /// Update the tree for the erasure of `block` from the region, along with its edges. The entry block can't be erased.
+    pub fn erase_block(&mut self, block: BlockId) {
+        if !self.contains(block) {
+            return;
+        }
+        assert_ne!(block, self.entry, "Cannot erase the entry block of a region");
+        // Delete the edges leaving the block in the direction of the tree. The block is then at most a leaf of the tree, and removing it along with its other edges doesn't change the dominators of any other block.
+        if IS_POST_DOM {
+            for predecessor in self.predecessors[&block].clone() {
+                self.delete_edge(predecessor, block);
+            }
+        } else {
+            for successor in self.successors[&block].clone() {
+                self.delete_edge(block, successor);
+            }
+        }
+        let node = Some(block);
+        if let Some(idom) = self.idoms.remove(&node) {
+            self.levels.remove(&node);
+            self.children.remove(&node);
+            if let Some(children) = self.children.get_mut(&idom) {
+                children.retain(|child| *child != node);
+            }
+        }
+        for successor in self.successors.remove(&block).unwrap_or_default() {
+            if let Some(predecessors) = self.predecessors.get_mut(&successor) {
+                predecessors.retain(|predecessor| *predecessor != block);
+            }
+        }
+        for predecessor in self.predecessors.remove(&block).unwrap_or_default() {
+            if let Some(successors) = self.successors.get_mut(&predecessor) {
+                successors.retain(|successor| *successor != block);
+            }
+        }
+    }
+
+    /// Add an edge from `from` to `to` to the copy of the CFG, unless either block is not in the region. Return whether it was added.
+    fn add_edge(&mut self, from: BlockId, to: BlockId) -> bool {
+        if !self.contains(from) || !self.contains(to) {
+            return false;
+        }
+        self.successors.get_mut(&from).unwrap().push(to);
+        self.predecessors.get_mut(&to).unwrap().push(from);
+        true
+    }
+
+    /// Remove an edge from `from` to `to` from the copy of the CFG. Return whether there was one.
+    fn remove_edge(&mut self, from: BlockId, to: BlockId) -> bool {
+        let Some(successors) = self.successors.get_mut(&from) else {
+            return false;
+        };
+        let Some(index) = successors.iter().position(|successor| *successor == to) else {
+            return false;
+        };
+        successors.remove(index);
+        let predecessors = self.predecessors.get_mut(&to).unwrap();
+        let index = predecessors.iter().position(|predecessor| *predecessor == from).unwrap();
+        predecessors.remove(index);
+        true
+    }
+
+    /// Return the root of the tree.
+    fn root(&self) -> Node {
+        if IS_POST_DOM { None } else { Some(self.entry) }
+    }
+
+    /// Return the nodes the edges from `node` lead to in the direction of the tree: the successors of blocks for dominators, and their predecessors for post-dominators, whose virtual root leads to the exit blocks.
+    fn graph_successors(&self, node: Node) -> Vec<Node> {
+        match node {
+            None => self.successors.iter()
+                .filter(|(_, successors)| successors.is_empty())
+                .map(|(block, _)| Some(*block))
+                .collect(),
+            Some(block) => {
+                let edges = if IS_POST_DOM { &self.predecessors } else { &self.successors };
+                edges[&block].iter().map(|block| Some(*block)).collect()
+            }
+        }
+    }
+
+    /// Return the nodes with an edge to `node` in the direction of the tree.
+    fn graph_predecessors(&self, node: Node) -> Vec<Node> {
+        match node {
+            None => Vec::new(),
+            Some(block) if IS_POST_DOM && self.successors[&block].is_empty() => vec![None],
+            Some(block) => {
+                let edges = if IS_POST_DOM { &self.successors } else { &self.predecessors };
+                edges[&block].iter().map(|block| Some(*block)).collect()
+            }
+        }
+    }
+
+    /// Return true if `a` dominates `b`, both being nodes of the tree.
+    fn node_dominates(&self, a: Node, mut b: Node) -> bool {
+        let level = self.levels[&a];
+        while self.levels[&b] > level {
+            b = self.idoms[&b];
+        }
+        a == b
+    }
+
+    /// Return the nearest common dominator of `a` and `b`, both being nodes of the tree.
+    fn nearest_common_dominator(&self, mut a: Node, mut b: Node) -> Node {
+        while a != b {
+            if self.levels[&a] < self.levels[&b] {
+                b = self.idoms[&b];
+            } else {
+                a = self.idoms[&a];
+            }
+        }
+        a
+    }
+
+    /// Compute the immediate dominators of the nodes reachable from `root` through nodes accepted by `filter`, `root` being its own. Return the nodes in post-order along with them.
+    fn compute(&self, root: Node, filter: impl Fn(Node) -> bool)
+        -> (Vec<Node>, HashMap<Node, Node>)
+    {
+        // Number the nodes in post-order.
+        let mut post_order = Vec::new();
+        let mut visited = HashSet::from([root]);
+        let mut stack = vec![(root, self.graph_successors(root), 0)];
+        while let Some((node, successors, index)) = stack.last_mut() {
+            match successors.get(*index) {
+                Some(&successor) => {
+                    *index += 1;
+                    if filter(successor) && visited.insert(successor) {
+                        stack.push((successor, self.graph_successors(successor), 0));
+                    }
+                },
+                None => {
+                    post_order.push(*node);
+                    stack.pop();
+                }
+            }
+        }
+        let order: HashMap<Node, usize> = post_order.iter().enumerate()
+            .map(|(index, node)| (*node, index))
+            .collect();
+
+        // Iterate in reverse post-order until the immediate dominators are stable.
+        let mut idoms = HashMap::from([(root, root)]);
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for &node in post_order.iter().rev().skip(1) {
+                let mut new_idom = None;
+                for predecessor in self.graph_predecessors(node) {
+                    if !idoms.contains_key(&predecessor) {
+                        continue;
+                    }
+                    new_idom = Some(match new_idom {
+                        None => predecessor,
+                        Some(other) => intersect(&idoms, &order, predecessor, other)
+                    });
+                }
+                let new_idom = new_idom.expect("Reachable node without processed predecessor");
+                if idoms.insert(node, new_idom) != Some(new_idom) {
+                    changed = true;
+                }
+            }
+        }
+        (post_order, idoms)
+    }
+
+    /// Recompute the whole tree from the copy of the CFG.
+    fn recalculate(&mut self) {
+        let root = self.root();
+        let (_, idoms) = self.compute(root, |_| true);
+        self.idoms = HashMap::from([(root, root)]);
+        self.children = HashMap::new();
+        self.levels = HashMap::from([(root, 0)]);
+        self.attach(root, idoms);
+    }
+
+    /// Recompute the subtree of `root` after an edge between its nodes was deleted. The nodes of the subtree that are no longer reachable from `root` leave the tree.
+    fn rebuild(&mut self, root: Node) {
+        let nodes: HashSet<Node> = self.subtree(root).into_iter().collect();
+        let (_, idoms) = self.compute(root, |node| nodes.contains(&node));
+        for node in &nodes {
+            self.children.remove(node);
+            if *node != root {
+                self.idoms.remove(node);
+                self.levels.remove(node);
+            }
+        }
+        self.attach(root, idoms);
+    }
+
+    /// Add the nodes of `idoms` but `root`, which is already in the tree, under their immediate dominator, and update the levels of the subtree of `root`.
+    fn attach(&mut self, root: Node, idoms: HashMap<Node, Node>) {
+        for (node, idom) in idoms {
+            if node != root {
+                self.idoms.insert(node, idom);
+                self.children.entry(idom).or_default().push(node);
+            }
+        }
+        self.update_levels(root);
+    }
+
+    /// Make `idom` the immediate dominator of `node`.
+    fn set_idom(&mut self, node: Node, idom: Node) {
+        if let Some(old) = self.idoms.insert(node, idom) {
+            if let Some(children) = self.children.get_mut(&old) {
+                children.retain(|child| *child != node);
+            }
+        }
+        self.children.entry(idom).or_default().push(node);
+    }
+
+    /// Recompute the levels of the nodes below `node`.
+    fn update_levels(&mut self, node: Node) {
+        let mut worklist = vec![node];
+        while let Some(node) = worklist.pop() {
+            let level = self.levels[&node] + 1;
+            for &child in self.children.get(&node).into_iter().flatten() {
+                self.levels.insert(child, level);
+                worklist.push(child);
+            }
+        }
+    }
+
+    /// Return the nodes of the subtree of `node`.
+    fn subtree(&self, node: Node) -> Vec<Node> {
+        let mut nodes = vec![node];
+        let mut index = 0;
+        while let Some(&node) = nodes.get(index) {
+            index += 1;
+            nodes.extend(self.children.get(&node).into_iter().flatten().copied());
+        }
+        nodes
+    }
+
+    /// Update the tree for a new edge from `a` to `b` in the direction of the tree.
+    fn insert_node_edge(&mut self, a: Node, b: Node) {
+        // Edges from unreachable nodes don't change the tree.
+        if !self.idoms.contains_key(&a) {
+            return;
+        }
+        if self.idoms.contains_key(&b) {
+            self.insert_reachable(a, b);
+        } else {
+            self.insert_unreachable(a, b);
+        }
+    }
+
+    /// Update the tree for a new edge from `a` to `b`, which becomes reachable along with the unreachable nodes it leads to.
+    fn insert_unreachable(&mut self, a: Node, b: Node) {
+        // The newly reachable nodes can only be entered through `b`, so their dominators are the ones computed from it.
+        let (new_nodes, idoms) = self.compute(b, |node| !self.idoms.contains_key(&node));
+        let level = self.levels[&a] + 1;
+        self.set_idom(b, a);
+        self.levels.insert(b, level);
+        self.attach(b, idoms);
+        // The edges from the newly reachable nodes to the rest of the tree are new edges between reachable nodes.
+        let new_nodes: HashSet<Node> = new_nodes.into_iter().collect();
+        let edges: Vec<(Node, Node)> = new_nodes.iter()
+            .flat_map(|&node| self.graph_successors(node).into_iter()
+                .filter(|successor| !new_nodes.contains(successor))
+                .map(move |successor| (node, successor)))
+            .collect();
+        for (node, successor) in edges {
+            self.insert_reachable(node, successor);
+        }
+    }
+
+    /// Update the tree for a new edge from `a` to `b`, both reachable, with the depth-based search of Georgiadis et al.
+    fn insert_reachable(&mut self, a: Node, b: Node) {
+        let nca = self.nearest_common_dominator(a, b);
+        // If the nearest common dominator already dominated `b` immediately, nothing changes.
+        if nca == b || self.idoms[&b] == nca {
+            return;
+        }
+        let nca_level = self.levels[&nca];
+        // The affected nodes are those deeper than the children of the nearest common dominator that are reachable from `b` through nodes no shallower than themselves. Visit them from the deepest, walking down from each through the unaffected deeper nodes.
+        let mut affected = Vec::new();
+        let mut visited = HashSet::from([b]);
+        let mut bucket = BinaryHeap::from([(self.levels[&b], b)]);
+        while let Some((level, node)) = bucket.pop() {
+            affected.push(node);
+            let mut unaffected = vec![node];
+            while let Some(current) = unaffected.pop() {
+                for successor in self.graph_successors(current) {
+                    let Some(&successor_level) = self.levels.get(&successor) else {
+                        continue;
+                    };
+                    // Nodes dominated by the children of the nearest common dominator are unaffected.
+                    if successor_level <= nca_level + 1 || !visited.insert(successor) {
+                        continue;
+                    }
+                    if successor_level > level {
+                        unaffected.push(successor);
+                    } else {
+                        bucket.push((successor_level, successor));
+                    }
+                }
+            }
+        }
+        // The affected nodes are now immediately dominated by the nearest common dominator.
+        for &node in &affected {
+            self.set_idom(node, nca);
+        }
+        for node in affected {
+            self.levels.insert(node, nca_level + 1);
+            self.update_levels(node);
+        }
+    }
+
+    /// Update the tree for the deletion of an edge from `a` to `b` in the direction of the tree.
+    fn delete_node_edge(&mut self, a: Node, b: Node) {
+        if !self.idoms.contains_key(&a) || !self.idoms.contains_key(&b) {
+            return;
+        }
+        // If `b` dominates `a`, no path from the root to another node needs the edge.
+        let nca = self.nearest_common_dominator(a, b);
+        if nca == b {
+            return;
+        }
+        if self.idoms[&b] != a || self.has_proper_support(b) {
+            // `b` remains reachable, and only the subtree of the nearest common dominator can change.
+            self.rebuild(nca);
+        } else {
+            // `b` becomes unreachable, which can change the dominators of any node it led to.
+            self.recalculate();
+        }
+    }
+
+    /// Return true if `node` has a reachable predecessor it doesn't dominate, i.e. if it is reachable without going through itself.
+    fn has_proper_support(&self, node: Node) -> bool {
+        self.graph_predecessors(node).into_iter().any(|predecessor|
+            self.idoms.contains_key(&predecessor)
+            && self.nearest_common_dominator(predecessor, node) != node)
+    }
+}
+
+/// Walk up the tree from `a` and `b` to their nearest common dominator, given the post-order number of each node.
+fn intersect(
+    idoms: &HashMap<Node, Node>,
+    order: &HashMap<Node, usize>,
+    mut a: Node,
+    mut b: Node
+) -> Node {
+    while a != b {
+        while order[&a] < order[&b] {
+            a = idoms[&a];
+        }
+        while order[&b] < order[&a] {
+            b = idoms[&b];
+        }
+    }
+    a
+}
+
+// ----------------------------------------------------------------------
+// DominanceInfoBase
+// ----------------------------------------------------------------------
+
+/// A class for computing basic dominance information, or with `IS_POST_DOM` post-dominance information. Note that this class is aware of different types of regions and returns a region-kind specific concept of dominance. See RegionKindInterface.
+///
+/// The tree of a region is computed the first time the region is queried, and cached. Passes changing the CFG of a region must then either keep its tree up to date with the update methods, or invalidate it.
+#[derive(Default)]
+pub struct DominanceInfoBase<const IS_POST_DOM: bool> {
+    /// A mapping of regions to their dominator tree.
+    dom_trees: RefCell<HashMap<RegionId, DominatorTreeBase<IS_POST_DOM>>>
+}
+
+/// A class for computing basic dominance information.
+pub type DominanceInfo = DominanceInfoBase<false>;
+/// A class for computing basic postdominance information.
+pub type PostDominanceInfo = DominanceInfoBase<true>;
+
+impl<const IS_POST_DOM: bool> DominanceInfoBase<IS_POST_DOM> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Return the dominator tree of `region`, computing it if needed. `region` must not be empty.
+    pub fn dom_tree(&self, arena: &IRArena, region: RegionId)
+        -> ArenaResult<Ref<'_, DominatorTreeBase<IS_POST_DOM>>>
+    {
+        if !self.dom_trees.borrow().contains_key(&region) {
+            let dom_tree = DominatorTreeBase::new(arena, region)?;
+            self.dom_trees.borrow_mut().insert(region, dom_tree);
+        }
+        Ok(Ref::map(self.dom_trees.borrow(), |dom_trees| &dom_trees[&region]))
+    }
+
+    /// Invalidate all the dominance information.
+    pub fn invalidate(&mut self) {
+        self.dom_trees.get_mut().clear();
+    }
+
+    /// Invalidate the dominance information of `region`.
+    pub fn invalidate_region(&mut self, region: RegionId) {
+        self.dom_trees.get_mut().remove(&region);
+    }
+
+    /// Return true if the region containing `block` requires the SSA-Dominance property. Blocks of regions that are not attached to an operation always do.
+    pub fn has_ssa_dominance(&self, arena: &IRArena, block: BlockId)
+        -> ArenaResult<bool>
+    {
+        let Some(region) = arena.block(block)?.parent() else {
+            return Ok(true);
+        };
+        let region = arena.region(region)?;
+        match (region.parent_operation(), region.region_number(arena)?) {
+            (Some(operation), Some(index)) => Ok(region_kind_interface::has_ssa_dominance(
+                arena.op(operation)?, index)),
+            _ => Ok(true)
+        }
+    }
+
+    /// Return true if `block` is reachable from the entry block of its region, or for post-dominance if it reaches an exit block of its region.
+    pub fn is_reachable_from_entry(&self, arena: &IRArena, block: BlockId)
+        -> ArenaResult<bool>
+    {
+        let Some(region) = arena.block(block)?.parent() else {
+            return Ok(true);
+        };
+        // If this is the first block in its region, then it is obviously reachable.
+        if !IS_POST_DOM && arena.region(region)?.front() == Some(block) {
+            return Ok(true);
+        }
+        // Otherwise this is some block in a multi-block region. Check DomTree.
+        Ok(self.dom_tree(arena, region)?.is_reachable(block))
+    }
+
+    /// Find the nearest common dominator block of `a` and `b`, or `None` if there is none. If they are not in the same region, the blocks containing them in their innermost common region are compared.
+    pub fn find_nearest_common_dominator(&self, arena: &IRArena, a: BlockId, b: BlockId)
+        -> ArenaResult<Option<BlockId>>
+    {
+        // If they are the same block, then we are done.
+        if a == b {
+            return Ok(Some(a));
+        }
+        // Try to find blocks that are in the same region.
+        let Some((a, b)) = blocks_in_same_region(arena, a, b)? else {
+            return Ok(None);
+        };
+        // If the common ancestor in a common region is the same block, then return it.
+        if a == b {
+            return Ok(Some(a));
+        }
+        // Otherwise, there must be multiple blocks in the region, check the DomTree.
+        let region = arena.block(a)?.parent().expect("Block found in a region without a parent");
+        Ok(self.dom_tree(arena, region)?.find_nearest_common_dominator(a, b))
+    }
+
+    /// Return true if block `a` dominates block `b`. Note that blocks dominate themselves.
+    pub fn dominates_block(&self, arena: &IRArena, a: BlockId, b: BlockId)
+        -> ArenaResult<bool>
+    {
+        Ok(a == b || self.properly_dominates_block(arena, a, b)?)
+    }
+
+    /// Return true if block `a` properly dominates block `b`. If `b` is nested in an operation of a block of the region of `a`, that block is compared instead, so `a` properly dominates the blocks nested in its own operations.
+    pub fn properly_dominates_block(&self, arena: &IRArena, a: BlockId, b: BlockId)
+        -> ArenaResult<bool>
+    {
+        // A block dominates itself but does not properly dominate itself.
+        if a == b {
+            return Ok(false);
+        }
+        let Some(region) = arena.block(a)?.parent() else {
+            return Ok(false);
+        };
+        // If both blocks are not in the same region, `a` properly dominates `b` if `b` is defined in an operation region that (recursively) ends up being dominated by `a`. Walk up the list of containers enclosing `b`.
+        let mut b = b;
+        if arena.block(b)?.parent() != Some(region) {
+            match arena.region(region)?.find_ancestor_block_in_region(arena, b)? {
+                // If we could not find a valid block `b` then it is not a dominator.
+                None => return Ok(false),
+                // Check to see if the ancestor of `b` is the same block as `a`. `a` properly dominates `b` if it contains an op that contains the `b` block.
+                Some(ancestor) if ancestor == a => return Ok(true),
+                Some(ancestor) => b = ancestor
+            }
+        }
+        // Otherwise, they are two different blocks in the same region, use DomTree.
+        Ok(self.dom_tree(arena, region)?.properly_dominates(a, b))
+    }
+
+    /// Update the cached tree of the region of `from` for the insertion of an edge from `from` to `to`.
+    pub fn insert_edge(&mut self, from: BlockId, to: BlockId) {
+        if let Some(dom_tree) = self.dom_tree_containing(from) {
+            dom_tree.insert_edge(from, to);
+        }
+    }
+
+    /// Update the cached tree of the region of `from` for the deletion of an edge from `from` to `to`.
+    pub fn delete_edge(&mut self, from: BlockId, to: BlockId) {
+        if let Some(dom_tree) = self.dom_tree_containing(from) {
+            dom_tree.delete_edge(from, to);
+        }
+    }
+
+    /// Update the cached tree of the region of `block`, which was just inserted in it, for the block and the edges from and to it.
+    pub fn insert_block(&mut self, arena: &IRArena, block: BlockId) -> ArenaResult<()> {
+        let Some(region) = arena.block(block)?.parent() else {
+            return Ok(());
+        };
+        // A new entry block changes the root of the tree.
+        if arena.region(region)?.front() == Some(block) {
+            self.invalidate_region(region);
+            return Ok(());
+        }
+        let Some(dom_tree) = self.dom_trees.get_mut().get_mut(&region) else {
+            return Ok(());
+        };
+        let predecessors = arena.predecessors(block)?;
+        dom_tree.insert_block(block, &predecessors, arena.block(block)?.successors(arena)?);
+        Ok(())
+    }
+
+    /// Update the cached tree of the region of `block` for the erasure of the block and of the edges from and to it. This may be done before or after the block is erased.
+    pub fn erase_block(&mut self, block: BlockId) {
+        let dom_trees = self.dom_trees.get_mut();
+        let Some((&region, dom_tree)) = dom_trees.iter_mut()
+            .find(|(_, dom_tree)| dom_tree.contains(block))
+        else {
+            return;
+        };
+        // Erasing the entry block changes the root of the tree.
+        if dom_tree.entry == block {
+            dom_trees.remove(&region);
+        } else {
+            dom_tree.erase_block(block);
+        }
+    }
+
+    /// Return the cached tree containing `block`, if any.
+    fn dom_tree_containing(&mut self, block: BlockId)
+        -> Option<&mut DominatorTreeBase<IS_POST_DOM>>
+    {
+        self.dom_trees.get_mut().values_mut().find(|dom_tree| dom_tree.contains(block))
+    }
+
+    /// Return true if operation `a` properly dominates operation `b`. `enclosing_op_ok` specifies whether `a` properly dominates the operations nested in its own regions.
+    fn properly_dominates_impl(
+        &self,
+        arena: &IRArena,
+        a: OpId,
+        b: OpId,
+        enclosing_op_ok: bool
+    ) -> ArenaResult<bool> {
+        let (Some(a_block), Some(mut b_block)) = (arena.op(a)?.block(), arena.op(b)?.block())
+        else {
+            return Ok(false);
+        };
+        // An operation dominates, but does not properly dominate, itself unless this is a graph region.
+        if a == b {
+            return Ok(!self.has_ssa_dominance(arena, a_block)?);
+        }
+        let a_region = arena.block(a_block)?.parent();
+        // If these ops are in different regions, then normalise one into the other.
+        let mut b = b;
+        if a_region != arena.block(b_block)?.parent() {
+            // Scoot up b's region tree until we find an operation in a's region that encloses it. If this fails, then we know there is no dominance relation.
+            let ancestor = match a_region {
+                None => None,
+                Some(region) => arena.region(region)?.find_ancestor_op_in_region(arena, b)?
+            };
+            let Some(ancestor) = ancestor else {
+                return Ok(false);
+            };
+            // If `a` encloses `b`, then we consider it to dominate.
+            if ancestor == a {
+                return Ok(enclosing_op_ok);
+            }
+            b = ancestor;
+            b_block = arena.op(b)?.block().expect("Operation in a region without a block");
+        }
+        // Ok, they are in the same region now.
+        if a_block == b_block {
+            // Dominance changes based on the region type. In a region with SSA dominance, uses inside the same block must follow defs, and post-dominance goes the other way. In other regions kinds, uses and defs can come in any order inside a block.
+            if self.has_ssa_dominance(arena, a_block)? {
+                return if IS_POST_DOM {
+                    is_before_in_block(arena, b, a)
+                } else {
+                    is_before_in_block(arena, a, b)
+                };
+            }
+            return Ok(true);
+        }
+        // If the blocks are different, use DomTree to resolve the query.
+        match a_region {
+            None => Ok(false),
+            Some(region) => Ok(self.dom_tree(arena, region)?.properly_dominates(a_block, b_block))
+        }
+    }
+}
+
+/// Dominance information is cached by the analysis manager like any analysis, its trees being computed lazily as regions are queried.
+impl<const IS_POST_DOM: bool> Analysis for DominanceInfoBase<IS_POST_DOM> {
+    fn new(_arena: &IRArena, _op: OpId, _am: &mut AnalysisManager) -> Self {
+        Self::default()
+    }
+}
+
+// ----------------------------------------------------------------------
+// DominanceInfo
+// ----------------------------------------------------------------------
+
+impl DominanceInfo {
+    /// Return true if operation `a` dominates operation `b`, i.e. if `a` is `b` or properly dominates it.
+    pub fn dominates(&self, arena: &IRArena, a: OpId, b: OpId) -> ArenaResult<bool> {
+        Ok(a == b || self.properly_dominates_op(arena, a, b)?)
+    }
+
+    /// Return true if operation `a` properly dominates operation `b`. An operation encloses, and so properly dominates, the operations nested in its regions.
+    pub fn properly_dominates_op(&self, arena: &IRArena, a: OpId, b: OpId)
+        -> ArenaResult<bool>
+    {
+        self.properly_dominates_impl(arena, a, b, true)
+    }
+
+    /// Return true if the value `a` dominates operation `b`.
+    pub fn dominates_value(&self, arena: &IRArena, a: Value, b: OpId)
+        -> ArenaResult<bool>
+    {
+        Ok(a.defining_operation() == Some(b) || self.properly_dominates(arena, a, b)?)
+    }
+
+    /// Return true if the value `a` properly dominates operation `b`, i.e. if `b` may use `a`.
+    pub fn properly_dominates(&self, arena: &IRArena, a: Value, b: OpId)
+        -> ArenaResult<bool>
+    {
+        match a {
+            // Block inputs properly dominate all operations in their own block, so we use a dominates check here, not a properly dominates check.
+            Value::Input(input) => match arena.op(b)?.block() {
+                None => Ok(false),
+                Some(block) => self.dominates_block(arena, input.owner(), block)
+            },
+            // `a` properly dominates `b` if the operation defining `a` properly dominates `b`, but `a` does not itself enclose `b` in one of its regions.
+            Value::Output(output) => self.properly_dominates_impl(arena, output.owner(), b, false)
+        }
+    }
+}
+
+// ----------------------------------------------------------------------
+// PostDominanceInfo
+// ----------------------------------------------------------------------
+
+impl PostDominanceInfo {
+    /// Return true if operation `a` post-dominates operation `b`.
+    pub fn post_dominates(&self, arena: &IRArena, a: OpId, b: OpId) -> ArenaResult<bool> {
+        Ok(a == b || self.properly_post_dominates(arena, a, b)?)
+    }
+
+    /// Return true if operation `a` properly post-dominates operation `b`. An operation encloses, and so properly post-dominates, the operations nested in its regions.
+    pub fn properly_post_dominates(&self, arena: &IRArena, a: OpId, b: OpId)
+        -> ArenaResult<bool>
+    {
+        self.properly_dominates_impl(arena, a, b, true)
+    }
+
+    /// Return true if block `a` post-dominates block `b`.
+    pub fn post_dominates_block(&self, arena: &IRArena, a: BlockId, b: BlockId)
+        -> ArenaResult<bool>
+    {
+        self.dominates_block(arena, a, b)
+    }
+
+    /// Return true if block `a` properly post-dominates block `b`.
+    pub fn properly_post_dominates_block(&self, arena: &IRArena, a: BlockId, b: BlockId)
+        -> ArenaResult<bool>
+    {
+        self.properly_dominates_block(arena, a, b)
+    }
+}
+
+/// Return the ancestors of `a` and `b` (possibly themselves) that lie in the innermost region containing both, if any.
+fn blocks_in_same_region(arena: &IRArena, a: BlockId, b: BlockId)
+    -> ArenaResult<Option<(BlockId, BlockId)>>
+{
+    let mut b = Some(b);
+    while let Some(current) = b {
+        if let Some(region) = arena.block(current)?.parent() {
+            if let Some(ancestor) = arena.region(region)?.find_ancestor_block_in_region(arena, a)? {
+                return Ok(Some((ancestor, current)));
+            }
+        }
+        b = match arena.block(current)?.parent_operation(arena)? {
+            None => None,
+            Some(operation) => arena.op(operation)?.block()
+        };
+    }
+    Ok(None)
 }
 
-//! A class for computing basic dominance information. Note that this
-//! class is aware of different types of regions and returns a
-//! region-kind specific concept of dominance. See RegionKindInterface.
-pub struct DominanceInfo {
+/// Given two operations within the same block, return whether `a` comes before `b`. Unlike `IRArena::is_before_in_block`, this doesn't repair the cached order of the block when it was invalidated, so it can be used on a shared arena.
+fn is_before_in_block(arena: &IRArena, a: OpId, b: OpId) -> ArenaResult<bool> {
+    let block = arena.block(arena.op(a)?.block().expect("Operation without a block"))?;
+    if block.is_op_order_valid() {
+        return Ok(arena.op(a)?.order_index < arena.op(b)?.order_index);
+    }
+    let operations = block.operations();
+    let position = |op| operations.iter().position(|other| *other == op);
+    Ok(position(a) < position(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ir::{
+        builtins::{
+            attributes::DictionaryAttribute,
+            types::{IntegerType, Signedness}
+        },
+        location::Location,
+        operation::{Operation, support::OperationName},
+        r#type::Type,
+        value::Output
+    };
+
+    /// Create the unregistered operation `name` with a single result, `num_regions` regions holding an empty block each, and the given successors, at the end of `block` if given. Returns the operation and the blocks of its regions.
+    fn create(
+        arena: &mut IRArena,
+        block: Option<BlockId>,
+        name: &str,
+        num_regions: usize,
+        successors: &[BlockId]
+    ) -> (OpId, Vec<BlockId>) {
+        let result_type: Arc<dyn Type> = Arc::new(IntegerType::new(32, Signedness::Signless));
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new(name), [result_type], Vec::new(),
+            DictionaryAttribute::default(), successors);
+        let op = arena.create_operation(operation, num_regions).unwrap();
+        if let Some(block) = block {
+            arena.push_back(block, op).unwrap();
+        }
+        let bodies = (0..num_regions).map(|index| {
+            let region = arena.op(op).unwrap().region(index);
+            let body = arena.create_block();
+            arena.push_back_block(region, body).unwrap();
+            body
+        }).collect();
+        (op, bodies)
+    }
+
+    /// Create a detached region of blocks ending with a branch to the blocks listed in `edges`, and return its tree along with the blocks.
+    fn cfg<const IS_POST_DOM: bool>(arena: &mut IRArena, edges: &[&[usize]])
+        -> (DominatorTreeBase<IS_POST_DOM>, Vec<BlockId>)
+    {
+        let region = arena.create_region(None);
+        let blocks: Vec<_> = edges.iter().map(|_| {
+            let block = arena.create_block();
+            arena.push_back_block(region, block).unwrap();
+            block
+        }).collect();
+        for (&block, successors) in blocks.iter().zip(edges) {
+            let successors: Vec<_> = successors.iter().map(|index| blocks[*index]).collect();
+            create(arena, Some(block), "test.br", 0, &successors);
+        }
+        (DominatorTreeBase::new(arena, region).unwrap(), blocks)
+    }
+
+    /// Return the tree of `tree`'s copy of the CFG, recomputed from scratch.
+    fn recalculated<const IS_POST_DOM: bool>(tree: &DominatorTreeBase<IS_POST_DOM>)
+        -> DominatorTreeBase<IS_POST_DOM>
+    {
+        let mut expected = DominatorTreeBase {
+            entry: tree.entry,
+            successors: tree.successors.clone(),
+            predecessors: tree.predecessors.clone(),
+            idoms: HashMap::new(),
+            children: HashMap::new(),
+            levels: HashMap::new()
+        };
+        expected.recalculate();
+        expected
+    }
+
+    fn blocks<const IS_POST_DOM: bool>(tree: &DominatorTreeBase<IS_POST_DOM>) -> Vec<BlockId> {
+        let mut blocks: Vec<_> = tree.successors.keys().copied().collect();
+        blocks.sort();
+        blocks
+    }
+
+    fn sorted(mut blocks: Vec<BlockId>) -> Vec<BlockId> {
+        blocks.sort();
+        blocks
+    }
+
+    fn assert_up_to_date<const IS_POST_DOM: bool>(tree: &DominatorTreeBase<IS_POST_DOM>) {
+        let expected = recalculated(tree);
+        assert_eq!(sorted(tree.roots()), sorted(expected.roots()));
+        for block in blocks(tree) {
+            assert_eq!(tree.is_reachable(block), expected.is_reachable(block));
+            assert_eq!(tree.idom(block), expected.idom(block));
+            assert_eq!(tree.level(block), expected.level(block));
+            assert_eq!(sorted(tree.children(block)), sorted(expected.children(block)));
+        }
+    }
+
+    #[test]
+    fn dominators_of_a_diamond() {
+        let mut arena = IRArena::new();
+        // Block 4 is unreachable.
+        let (tree, b) = cfg::<false>(&mut arena, &[&[1, 2], &[3], &[3], &[], &[3]]);
+        assert_eq!(tree.roots(), [b[0]]);
+        assert_eq!(tree.idom(b[0]), None);
+        assert_eq!(tree.idom(b[1]), Some(b[0]));
+        assert_eq!(tree.idom(b[2]), Some(b[0]));
+        assert_eq!(tree.idom(b[3]), Some(b[0]));
+        assert_eq!(tree.level(b[3]), Some(1));
+        assert_eq!(sorted(tree.children(b[0])), [b[1], b[2], b[3]]);
+        assert!(tree.properly_dominates(b[0], b[3]));
+        assert!(!tree.properly_dominates(b[1], b[3]));
+        assert!(tree.dominates(b[3], b[3]) && !tree.properly_dominates(b[3], b[3]));
+        assert_eq!(tree.find_nearest_common_dominator(b[1], b[2]), Some(b[0]));
+
+        // Unreachable blocks are dominated by every block, and dominate none.
+        assert!(tree.contains(b[4]) && !tree.is_reachable(b[4]));
+        assert_eq!(tree.idom(b[4]), None);
+        assert!(tree.properly_dominates(b[1], b[4]));
+        assert!(!tree.properly_dominates(b[4], b[1]));
+        assert_eq!(tree.find_nearest_common_dominator(b[1], b[4]), None);
+    }
+
+    #[test]
+    fn post_dominators_of_a_diamond() {
+        let mut arena = IRArena::new();
+        let (tree, b) = cfg::<true>(&mut arena, &[&[1, 2], &[3], &[3], &[], &[3]]);
+        assert_eq!(tree.roots(), [b[3]]);
+        assert_eq!(tree.idom(b[3]), None);
+        assert_eq!(tree.idom(b[0]), Some(b[3]));
+        assert_eq!(tree.idom(b[4]), Some(b[3]));
+        assert!(tree.properly_dominates(b[3], b[0]));
+        assert!(!tree.properly_dominates(b[1], b[0]));
+        assert_eq!(tree.find_nearest_common_dominator(b[1], b[2]), Some(b[3]));
+    }
+
+    #[test]
+    fn post_dominators_with_several_exits() {
+        let mut arena = IRArena::new();
+        // Block 3 loops forever and reaches no exit.
+        let (tree, b) = cfg::<true>(&mut arena, &[&[1, 2, 3], &[], &[], &[3]]);
+        assert_eq!(sorted(tree.roots()), [b[1], b[2]]);
+        assert!(tree.is_reachable(b[0]));
+        assert_eq!(tree.idom(b[0]), None);
+        assert_eq!(tree.find_nearest_common_dominator(b[1], b[2]), None);
+        assert!(!tree.is_reachable(b[3]));
+    }
+
+    #[test]
+    fn dominators_of_a_loop() {
+        let mut arena = IRArena::new();
+        let edges: &[&[usize]] = &[&[1], &[2], &[1, 3], &[]];
+        let (tree, b) = cfg::<false>(&mut arena, edges);
+        assert_eq!(tree.idom(b[1]), Some(b[0]));
+        assert_eq!(tree.idom(b[2]), Some(b[1]));
+        assert_eq!(tree.idom(b[3]), Some(b[2]));
+        assert_eq!(tree.level(b[3]), Some(3));
+        let (tree, b) = cfg::<true>(&mut arena, edges);
+        assert_eq!(tree.idom(b[0]), Some(b[1]));
+        assert_eq!(tree.idom(b[1]), Some(b[2]));
+        assert_eq!(tree.idom(b[2]), Some(b[3]));
+    }
+
+    #[test]
+    fn updates_of_edges() {
+        let mut arena = IRArena::new();
+        let (mut tree, b) = cfg::<false>(&mut arena, &[&[1], &[2], &[3], &[], &[3]]);
+        assert_eq!(tree.idom(b[3]), Some(b[2]));
+        // A shortcut makes the entry block the immediate dominator of 3.
+        tree.insert_edge(b[0], b[3]);
+        assert_eq!(tree.idom(b[3]), Some(b[0]));
+        // Block 4 becomes reachable, without changing the dominator of 3.
+        tree.insert_edge(b[1], b[4]);
+        assert_eq!(tree.idom(b[4]), Some(b[1]));
+        assert_eq!(tree.idom(b[3]), Some(b[0]));
+        tree.delete_edge(b[0], b[3]);
+        assert_eq!(tree.idom(b[3]), Some(b[1]));
+        tree.delete_edge(b[0], b[1]);
+        assert!(!tree.is_reachable(b[1]) && !tree.is_reachable(b[3]));
+        assert_up_to_date(&tree);
+    }
+
+    /// A linear congruential generator, to generate the same updates on every run.
+    struct Random(u64);
+
+    impl Random {
+        fn below(&mut self, bound: usize) -> usize {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 33) as usize % bound
+        }
+
+        fn pick(&mut self, blocks: &[BlockId]) -> BlockId {
+            blocks[self.below(blocks.len())]
+        }
+    }
+
+    /// Apply random updates to a random CFG, checking the tree against one recomputed from scratch after each of them.
+    fn check_random_updates<const IS_POST_DOM: bool>(seed: u64) {
+        let mut random = Random(seed);
+        let mut arena = IRArena::new();
+        let edges: Vec<Vec<usize>> = (0..6)
+            .map(|_| (0..random.below(3)).map(|_| random.below(6)).collect())
+            .collect();
+        let edges: Vec<&[usize]> = edges.iter().map(Vec::as_slice).collect();
+        let (mut tree, _) = cfg::<IS_POST_DOM>(&mut arena, &edges);
+        assert_up_to_date(&tree);
+        for _ in 0..200 {
+            let blocks = blocks(&tree);
+            match random.below(4) {
+                0 => tree.insert_edge(random.pick(&blocks), random.pick(&blocks)),
+                1 => {
+                    let from = random.pick(&blocks);
+                    let successors = tree.successors[&from].clone();
+                    if !successors.is_empty() {
+                        tree.delete_edge(from, random.pick(&successors));
+                    }
+                },
+                2 => {
+                    let block = arena.create_block();
+                    let predecessors: Vec<_> = (0..random.below(3)).map(|_| random.pick(&blocks)).collect();
+                    let mut successors: Vec<_> = (0..random.below(3)).map(|_| random.pick(&blocks)).collect();
+                    if random.below(4) == 0 {
+                        successors.push(block);
+                    }
+                    tree.insert_block(block, &predecessors, &successors);
+                },
+                _ => {
+                    let block = random.pick(&blocks);
+                    if block != tree.entry && blocks.len() > 2 {
+                        tree.erase_block(block);
+                    }
+                }
+            }
+            assert_up_to_date(&tree);
+        }
+    }
+
+    #[test]
+    fn random_updates_match_recalculation() {
+        for seed in 0..20 {
+            check_random_updates::<false>(seed);
+            check_random_updates::<true>(seed);
+        }
+    }
+
+    #[test]
+    fn dominance_of_operations_and_values() {
+        let mut arena = IRArena::new();
+        let (_, bodies) = create(&mut arena, None, "test.func", 1, &[]);
+        let body = bodies[0];
+        let argument = arena.insert_block_input(body, 0, Arc::new(
+            IntegerType::new(32, Signedness::Signless)), Location::unknown()).unwrap();
+        let (def, _) = create(&mut arena, Some(body), "test.def", 0, &[]);
+        let (r#loop, bodies) = create(&mut arena, Some(body), "test.loop", 1, &[]);
+        let (nested, _) = create(&mut arena, Some(bodies[0]), "test.use", 0, &[]);
+        let (last, _) = create(&mut arena, Some(body), "test.use", 0, &[]);
+
+        let info = DominanceInfo::new();
+        assert!(info.dominates(&arena, def, def).unwrap());
+        assert!(!info.properly_dominates_op(&arena, def, def).unwrap());
+        assert!(info.properly_dominates_op(&arena, def, last).unwrap());
+        assert!(!info.properly_dominates_op(&arena, last, def).unwrap());
+        // Operations of nested regions are compared through their ancestor in the region.
+        assert!(info.properly_dominates_op(&arena, def, nested).unwrap());
+        assert!(!info.properly_dominates_op(&arena, last, nested).unwrap());
+        // An operation encloses the operations nested in it, but its results don't dominate them.
+        assert!(info.properly_dominates_op(&arena, r#loop, nested).unwrap());
+        let result = Value::Output(Output::new(r#loop, 0));
+        assert!(!info.properly_dominates(&arena, result, nested).unwrap());
+        assert!(info.properly_dominates(&arena, result, last).unwrap());
+        assert!(info.properly_dominates(&arena, Value::Input(argument), def).unwrap());
+        assert!(info.properly_dominates_block(&arena, body, bodies[0]).unwrap());
+        assert!(!info.properly_dominates_block(&arena, bodies[0], body).unwrap());
+
+        let info = PostDominanceInfo::new();
+        assert!(info.properly_post_dominates(&arena, last, def).unwrap());
+        assert!(!info.properly_post_dominates(&arena, def, last).unwrap());
+    }
+
+    #[test]
+    fn cached_trees_are_updated() {
+        let mut arena = IRArena::new();
+        let (_, bodies) = create(&mut arena, None, "test.func", 1, &[]);
+        let entry = bodies[0];
+        let region = arena.block(entry).unwrap().parent().unwrap();
+        let exit = arena.create_block();
+        arena.push_back_block(region, exit).unwrap();
+        create(&mut arena, Some(exit), "test.return", 0, &[]);
+        create(&mut arena, Some(entry), "test.br", 0, &[exit]);
+
+        let mut info = DominanceInfo::new();
+        assert!(info.properly_dominates_block(&arena, entry, exit).unwrap());
+
+        // Branch through a new block inserted between the entry and exit blocks.
+        let middle = arena.create_block();
+        arena.insert_block_before(exit, middle).unwrap();
+        create(&mut arena, Some(middle), "test.br", 0, &[exit]);
+        info.insert_block(&arena, middle).unwrap();
+        assert!(!info.is_reachable_from_entry(&arena, middle).unwrap());
+        info.insert_edge(entry, middle);
+        assert_eq!(info.dom_tree(&arena, region).unwrap().idom(exit), Some(entry));
+        info.delete_edge(entry, exit);
+        assert_eq!(info.dom_tree(&arena, region).unwrap().idom(exit), Some(middle));
+        assert_eq!(
+            info.find_nearest_common_dominator(&arena, middle, exit).unwrap(), Some(middle));
+        info.erase_block(middle);
+        assert!(!info.dom_tree(&arena, region).unwrap().contains(middle));
+    }
 }
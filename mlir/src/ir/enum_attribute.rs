@@ -1,39 +1,102 @@
-//! Enum Attributes
-//!
-//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/EnumAttr.td>
-
-use crate::mlir::ir::attr_type_base::AttributeParameter;
-
-/// A C++ enum as an attribute parameter. The parameter implements a parser and printer for the enum by dispatching calls to `stringToSymbol` and `symbolToString`.
-pub struct EnumParameter;
-
-/**
-An attribute backed by a C++ enum. The attribute contains a single parameter `value` whose type is the C++ enum class.
-
-# Example
-
-```text
-def MyEnum : I32EnumAttr<"MyEnum", "a simple enum", [
-                           I32EnumAttrCase<"First", 0, "first">,
-                           I32EnumAttrCase<"Second", 1, "second>]> {
-  let genSpecializedAttr = 0;
-}
-
-type MyEnumEnumAttribute = EnumAttribute<MyDialect, MyEnum, "enum">;
-```
-
-By default, the assembly format of the attribute works best with operation assembly formats. For example:
-
-```text
-def MyOp : Op<MyDialect, "my_op"> {
-  let arguments = (enum: MyEnumAttr);
-  let assembly_format = "$enum attr-dict";
-}
-```
-
-The op will appear in the IR as `my_dialect.my_op first`. However, the generic format of the attribute will be `#my_dialect<"enum first">`. Override the attribute's assembly format as required.
-*/
-pub struct EnumAttribute {
-    /// The enum attribute has one parameter: the C++ enum value.
-    value: EnumParameter  // <enumInfo>
-}
+//! Enum Attributes
+//!
+//! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/EnumAttr.td>
+
+use std::sync::Arc;
+
+use llvm::adt::ap_int::APInt;
+
+use crate::ir::{
+    attribute::Attribute,
+    builtins::{
+        attributes::IntegerAttribute,
+        types::{IntegerType, Signedness}
+    }
+};
+
+/**
+An enum stored in an attribute, whose cases are printed as keywords in the assembly format of operations. The attribute is either an attribute wrapping the enum, the `EnumAttr` of ODS, or an integer attribute holding the value of the case, the `I64EnumAttr` of ODS.
+
+# Example
+
+```ignore
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MyEnum {
+    First,
+    Second
+}
+
+impl EnumAttribute for MyEnum {
+    type Attribute = MyEnumAttr;
+    const CASES: &'static [(Self, &'static str)]
+        = &[(Self::First, "first"), (Self::Second, "second")];
+    ...
+}
+```
+
+The attribute is declared with `enum` in the definition of an operation:
+
+```ignore
+#[derive(Op)]
+#[op(
+    name = "my_dialect.my_op",
+    attributes(enum value: MyEnum),
+    assembly_format = "$value attr-dict"
+)]
+pub struct MyOp(OpId);
+```
+
+The op will appear in the IR as `my_dialect.my_op first`, and its builder and accessor take and return a `MyEnum`.
+*/
+pub trait EnumAttribute: Copy + PartialEq + Send + Sync + 'static {
+    /// The attribute storing the cases of the enum.
+    type Attribute: Attribute;
+
+    /// The cases of the enum, along with their keywords.
+    const CASES: &'static [(Self, &'static str)];
+
+    /// Return the attribute storing this case.
+    fn to_attribute(self) -> Self::Attribute;
+
+    /// Return the case stored in `attribute`, or None if it doesn't hold a case of the enum.
+    fn from_attribute(attribute: &Self::Attribute) -> Option<Self>;
+
+    /// Return the case whose keyword is `keyword`, `symbolizeEnum` in ODS.
+    fn symbolise(keyword: &str) -> Option<Self> {
+        Self::CASES.iter()
+            .find(|(_, case_keyword)| *case_keyword == keyword)
+            .map(|(case, _)| *case)
+    }
+
+    /// Return the keyword of this case, `stringifyEnum` in ODS.
+    fn stringify(self) -> &'static str {
+        Self::CASES.iter()
+            .find(|(case, _)| *case == self)
+            .map_or("", |(_, keyword)| keyword)
+    }
+
+    /// Return the keywords of the cases separated by commas, as listed by the errors of the parsers.
+    fn keywords() -> String {
+        Self::CASES.iter().map(|(_, keyword)| *keyword).collect::<Vec<_>>().join(", ")
+    }
+}
+
+/// Return the `i64` integer attribute holding `case`, for enums stored as the `I64EnumAttr` of ODS, whose cases are numbered in the order of `CASES`.
+pub fn i64_enum_attribute<E: EnumAttribute>(case: E) -> IntegerAttribute {
+    let value = E::CASES.iter().position(|(other, _)| *other == case)
+        .expect("Expected a case of the enum");
+    IntegerAttribute::new(
+        Arc::new(IntegerType::new(64, Signedness::Signless)),
+        APInt::new(64, value as u64, true))
+}
+
+/// Return the case held by the `i64` integer attribute `attribute`, or None if it isn't an `i64` or its value isn't the number of a case.
+pub fn i64_enum_case<E: EnumAttribute>(attribute: &IntegerAttribute) -> Option<E> {
+    let is_i64 = attribute.r#type().downcast_ref::<IntegerType>()
+        .is_some_and(|r#type| r#type.is_signless() && r#type.width() == 64);
+    if !is_i64 {
+        return None;
+    }
+    let value = usize::try_from(attribute.value().zext_value()?).ok()?;
+    E::CASES.get(value).map(|(case, _)| *case)
+}
@@ -7,70 +7,775 @@
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/ExtensibleDialect.h>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/ExtensibleDialect.cpp>
 
-use core;
+pub mod expression;
+pub mod spec;
 
-/// The definition of a dynamic attribute. A dynamic attribute is an attribute that is defined at runtime, and that can be registered at runtime by an extensible dialect (a dialect inheriting ExtensibleDialect). This class stores the parser, the printer, and the verifier of the attribute. Each dynamic attribute definition refers to one instance of this class.
+use core::{
+    fmt::{self, Display},
+    ops::{Deref, Range}
+};
+use std::{
+    collections::HashMap,
+    error::Error,
+    sync::{Arc, RwLock}
+};
+
+use crate::{
+    asm_parser::parser::ParseResult,
+    ir::{
+        arena::IRArena,
+        attribute::Attribute,
+        dialect::implementation::{DialectAsmParser, DialectAsmPrinter},
+        extensible_dialect::expression::{Environment, Expression},
+        operation::Operation,
+        r#type::Type
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
+// Dynamic Attributes and Types
+// ----------------------------------------------------------------------
+
+/// The definition of a dynamic attribute. A dynamic attribute is an attribute that is defined at runtime, and that can be registered at runtime by an extensible dialect. This class stores the name and the parameters of the attribute. Each dynamic attribute definition refers to one instance of this class.
+#[derive(Debug)]
 pub struct DynamicAttrDefinition {
-    /// The name should be prefixed with the dialect name followed by '.'.
+    /// The name of the attribute, without the dialect namespace.
     name: String,
 
-    /// Dialect in which this attribute is defined.
-    dialect: *mut ExtensibleDialect,
+    /// The namespace of the dialect in which this attribute is defined.
+    dialect: String,
+
+    /// The names of the parameters of the attribute.
+    parameters: Vec<String>
+}
+
+impl DynamicAttrDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        dialect: impl Into<String>,
+        parameters: Vec<String>
+    ) -> Self {
+        Self { name: name.into(), dialect: dialect.into(), parameters }
+    }
 
-    /// The attribute verifier. It checks that the attribute parameters satisfy the invariants.
-    verifier: Verifier,
+    pub fn name(&self) -> &str {
+        &self.name
+    }
 
-    /// The attribute parameters parser. It parses only the parameters, and expects the attribute name to have already been parsed.
-    parser: Parser,
+    pub fn dialect(&self) -> &str {
+        &self.dialect
+    }
 
-    /// The attribute parameters printer. It prints only the parameters, and expects the attribute name to have already been printed.
-    printer: Printer,
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
 
-    /// Context in which the concrete attributes are uniqued.
-    context: *mut MLIRContext
+    /// Check that `parameters` satisfy the invariants of the attribute.
+    pub fn verify(&self, parameters: &[Arc<dyn Attribute>]) -> LogicalResult {
+        verify_parameters("attribute", &self.dialect, &self.name,
+                          &self.parameters, parameters)
+    }
 }
 
 /// A dynamic attribute instance. This is an attribute whose definition is defined at runtime.
-/// It is possible to check if an attribute is a dynamic attribute using `my_attr.isa<DynamicAttr>()`, and getting the attribute definition of a dynamic attribute using the `DynamicAttr::getAttrDef` method.
+/// It is possible to check if an attribute is a dynamic attribute using `attribute.downcast_ref::<DynamicAttr>()`, and getting the attribute definition of a dynamic attribute using the `DynamicAttr::definition` method.
 /// All dynamic attributes have the same storage, which is an array of attributes.
 pub struct DynamicAttr {
+    definition: Arc<DynamicAttrDefinition>,
+    parameters: Vec<Arc<dyn Attribute>>
+}
+
+impl Attribute for DynamicAttr {}
+
+impl DynamicAttr {
+    /// Return an instance of the dynamic attribute defined by `definition`, failing if the parameters do not satisfy its invariants.
+    pub fn new(
+        definition: Arc<DynamicAttrDefinition>,
+        parameters: Vec<Arc<dyn Attribute>>
+    ) -> Result<Self, Box<dyn Error>> {
+        definition.verify(&parameters)?;
+        Ok(Self { definition, parameters })
+    }
 
+    /// Return the attribute definition of the concrete attribute.
+    pub fn definition(&self) -> &Arc<DynamicAttrDefinition> {
+        &self.definition
+    }
+
+    /// Return the attribute parameters.
+    pub fn parameters(&self) -> &[Arc<dyn Attribute>] {
+        &self.parameters
+    }
+
+    /// Print the attribute, without the `#dialect` prefix.
+    pub fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        print_parameters(&self.definition.name, &self.parameters, printer)
+    }
 }
 
-/// The definition of a dynamic type. A dynamic type is a type that is defined at runtime, and that can be registered at runtime by an extensible dialect (a dialect inheriting ExtensibleDialect). This class stores the parser, the printer, and the verifier of the type. Each dynamic type definition refers to one instance of this class.
+/// The definition of a dynamic type. A dynamic type is a type that is defined at runtime, and that can be registered at runtime by an extensible dialect. This class stores the name and the parameters of the type. Each dynamic type definition refers to one instance of this class.
+#[derive(Debug)]
 pub struct DynamicTypeDefinition {
+    /// The name of the type, without the dialect namespace.
+    name: String,
 
+    /// The namespace of the dialect in which this type is defined.
+    dialect: String,
+
+    /// The names of the parameters of the type.
+    parameters: Vec<String>
+}
+
+impl DynamicTypeDefinition {
+    pub fn new(
+        name: impl Into<String>,
+        dialect: impl Into<String>,
+        parameters: Vec<String>
+    ) -> Self {
+        Self { name: name.into(), dialect: dialect.into(), parameters }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn dialect(&self) -> &str {
+        &self.dialect
+    }
+
+    pub fn parameters(&self) -> &[String] {
+        &self.parameters
+    }
+
+    /// Check that `parameters` satisfy the invariants of the type.
+    pub fn verify(&self, parameters: &[Arc<dyn Attribute>]) -> LogicalResult {
+        verify_parameters("type", &self.dialect, &self.name,
+                          &self.parameters, parameters)
+    }
 }
 
 /// A dynamic type instance. This is a type whose definition is defined at runtime.
-/// It is possible to check if a type is a dynamic type using `my_type.isa<DynamicType>()`, and getting the type definition of a dynamic type using the `DynamicType::getTypeDef` method.
+/// It is possible to check if a type is a dynamic type using `type.downcast_ref::<DynamicType>()`, and getting the type definition of a dynamic type using the `DynamicType::definition` method.
 /// All dynamic types have the same storage, which is an array of attributes.
 pub struct DynamicType {
+    definition: Arc<DynamicTypeDefinition>,
+    parameters: Vec<Arc<dyn Attribute>>
+}
+
+impl Type for DynamicType {}
+
+impl DynamicType {
+    /// Return an instance of the dynamic type defined by `definition`, failing if the parameters do not satisfy its invariants.
+    pub fn new(
+        definition: Arc<DynamicTypeDefinition>,
+        parameters: Vec<Arc<dyn Attribute>>
+    ) -> Result<Self, Box<dyn Error>> {
+        definition.verify(&parameters)?;
+        Ok(Self { definition, parameters })
+    }
+
+    /// Return the type definition of the concrete type.
+    pub fn definition(&self) -> &Arc<DynamicTypeDefinition> {
+        &self.definition
+    }
+
+    /// Return the type parameters.
+    pub fn parameters(&self) -> &[Arc<dyn Attribute>] {
+        &self.parameters
+    }
 
+    /// Print the type, without the `!dialect` prefix.
+    pub fn print(&self, printer: &mut dyn DialectAsmPrinter) -> fmt::Result {
+        print_parameters(&self.definition.name, &self.parameters, printer)
+    }
 }
 
-/// The definition of a dynamic op. A dynamic op is an op that is defined at runtime, and that can be registered at runtime by an extensible dialect (a dialect inheriting ExtensibleDialect). This class stores the functions that are in the OperationName class, and in addition defines the TypeID of the op that will be defined.
+fn verify_parameters(
+    kind: &str,
+    dialect: &str,
+    name: &str,
+    expected: &[String],
+    parameters: &[Arc<dyn Attribute>]
+) -> LogicalResult {
+    if parameters.len() != expected.len() {
+        return Err(format!(
+            "{} '{}.{}' expects {} parameters, but got {}",
+            kind, dialect, name, expected.len(), parameters.len()).into());
+    }
+    Ok(())
+}
+
+/// Print the name of a dynamic attribute or type, followed by its parameters between `<>` if it has any.
+fn print_parameters(
+    name: &str,
+    parameters: &[Arc<dyn Attribute>],
+    printer: &mut dyn DialectAsmPrinter
+) -> fmt::Result {
+    printer.write_str(name)?;
+    if parameters.is_empty() {
+        return Ok(());
+    }
+    printer.write_char('<')?;
+    for (index, parameter) in parameters.iter().enumerate() {
+        if index != 0 {
+            printer.write_str(", ")?;
+        }
+        printer.print_attribute(parameter.as_ref())?;
+    }
+    printer.write_char('>')
+}
+
+/// Parse the parameters of a dynamic attribute or type, between `<>` if there are any.
+fn parse_parameters(parser: &mut dyn DialectAsmParser)
+    -> ParseResult<Vec<Arc<dyn Attribute>>>
+{
+    let mut parameters = Vec::new();
+    if !parser.parse_optional_less() {
+        return Ok(parameters);
+    }
+    loop {
+        parameters.push(parser.parse_attribute()?);
+        if !parser.parse_optional_comma() {
+            break;
+        }
+    }
+    parser.parse_greater()?;
+    Ok(parameters)
+}
+
+// ----------------------------------------------------------------------
+// Dynamic Operations
+// ----------------------------------------------------------------------
+
+/// A constraint on the type of an operand or result of a dynamic operation.
+#[derive(Clone, Debug, PartialEq)]
+pub enum TypeConstraint {
+    /// Any type.
+    Any,
+    /// The type with the given printed form. A type given without its parameters, e.g. `!cmath.complex`, also matches all of its parameterisations.
+    Is(String),
+    /// A type satisfying any of the given constraints.
+    AnyOf(Vec<TypeConstraint>)
+}
+
+impl TypeConstraint {
+    /// Parse a constraint, which is either `any`, a type, or constraints separated by `|`.
+    pub fn parse(source: &str) -> Self {
+        let mut alternatives = Vec::new();
+        let mut depth = 0usize;
+        let mut start = 0;
+        for (index, char) in source.char_indices() {
+            match char {
+                '<' | '(' | '[' | '{' => depth += 1,
+                '>' | ')' | ']' | '}' => depth = depth.saturating_sub(1),
+                '|' if depth == 0 => {
+                    alternatives.push(&source[start..index]);
+                    start = index + 1;
+                },
+                _ => {}
+            }
+        }
+        alternatives.push(&source[start..]);
+
+        let mut alternatives: Vec<Self> = alternatives.into_iter()
+            .map(|alternative| match alternative.trim() {
+                "any" => Self::Any,
+                r#type => Self::Is(remove_whitespace(r#type))
+            })
+            .collect();
+        match alternatives.len() {
+            1 => alternatives.pop().unwrap(),
+            _ => Self::AnyOf(alternatives)
+        }
+    }
+
+    /// Returns true if `type` satisfies this constraint.
+    pub fn is_satisfied_by(&self, r#type: &dyn Type) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Is(expected) => {
+                let printed = remove_whitespace(&r#type.to_string());
+                printed == *expected
+                    || printed.strip_prefix(expected.as_str())
+                        .map_or(false, |rest| rest.starts_with('<'))
+            },
+            Self::AnyOf(constraints) => constraints.iter()
+                .any(|constraint| constraint.is_satisfied_by(r#type))
+        }
+    }
+}
+
+impl Display for TypeConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Any => f.write_str("any type"),
+            Self::Is(r#type) => write!(f, "'{}'", r#type),
+            Self::AnyOf(constraints) => {
+                for (index, constraint) in constraints.iter().enumerate() {
+                    if index != 0 {
+                        f.write_str(" or ")?;
+                    }
+                    write!(f, "{}", constraint)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn remove_whitespace(string: &str) -> String {
+    string.chars().filter(|char| !char.is_whitespace()).collect()
+}
+
+/// The definition of an operand or a result of a dynamic operation.
+#[derive(Clone, Debug)]
+pub struct ValueDefinition {
+    pub name: String,
+    pub constraint: TypeConstraint,
+    /// Whether the definition stands for any number of values rather than exactly one.
+    pub variadic: bool
+}
+
+/// The definition of an attribute of a dynamic operation.
+#[derive(Clone, Debug)]
+pub struct AttributeDefinition {
+    pub name: String,
+    pub optional: bool
+}
+
+/// The definition of a dynamic op. A dynamic op is an op that is defined at runtime, and that can be registered at runtime by an extensible dialect. This class stores the constraints on the operands, results, attributes and regions of the op, and its custom verifier.
+/// Dynamic ops have no custom assembly format and are printed and parsed in the generic form.
 /// Each dynamic operation definition refers to one instance of this class.
+#[derive(Debug)]
 pub struct DynamicOpDefinition {
+    /// The name of the operation, prefixed with the dialect namespace.
+    name: String,
+    operands: Vec<ValueDefinition>,
+    results: Vec<ValueDefinition>,
+    attributes: Vec<AttributeDefinition>,
+    /// The number of regions of the operation, or None if it may have any number of regions.
+    num_regions: Option<usize>,
+    /// The custom verifier, which must evaluate to true for valid operations.
+    verifier: Option<Expression>
+}
 
+impl DynamicOpDefinition {
+    /// Create the definition of the operation `name`, which must be prefixed with the dialect namespace. At most one of the operands, and one of the results, may be variadic.
+    pub fn new(
+        name: impl Into<String>,
+        operands: Vec<ValueDefinition>,
+        results: Vec<ValueDefinition>
+    ) -> Self {
+        Self {
+            name: name.into(),
+            operands,
+            results,
+            attributes: Vec::new(),
+            num_regions: None,
+            verifier: None
+        }
+    }
+
+    pub fn with_attributes(mut self, attributes: Vec<AttributeDefinition>) -> Self {
+        self.attributes = attributes;
+        self
+    }
+
+    pub fn with_num_regions(mut self, num_regions: usize) -> Self {
+        self.num_regions = Some(num_regions);
+        self
+    }
+
+    pub fn with_verifier(mut self, verifier: Expression) -> Self {
+        self.verifier = Some(verifier);
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn operands(&self) -> &[ValueDefinition] {
+        &self.operands
+    }
+
+    pub fn results(&self) -> &[ValueDefinition] {
+        &self.results
+    }
+
+    pub fn attributes(&self) -> &[AttributeDefinition] {
+        &self.attributes
+    }
+
+    pub fn num_regions(&self) -> Option<usize> {
+        self.num_regions
+    }
+
+    pub fn verifier(&self) -> Option<&Expression> {
+        self.verifier.as_ref()
+    }
+
+    /// Verify the invariants of `operation`, an instance of this definition.
+    pub fn verify(&self, arena: &IRArena, operation: &Operation) -> LogicalResult {
+        let error = |message: String| -> Box<dyn Error> {
+            Box::new(operation.emit_op_error(message))
+        };
+
+        let operand_types = operation.inputs().iter()
+            .map(|input| input.r#type(arena))
+            .collect::<Result<Vec<_>, _>>()?;
+        let result_types: Vec<_> = operation.output_types().collect();
+
+        let mut named_types = HashMap::new();
+        for (kind, definitions, types) in [
+            ("operand", &self.operands, &operand_types),
+            ("result", &self.results, &result_types)
+        ] {
+            let segments = value_segments(definitions, types.len())
+                .map_err(|expected| error(format!(
+                    "expects {} {}s, but got {}", expected, kind, types.len())))?;
+            for (definition, segment) in definitions.iter().zip(segments) {
+                for index in segment.clone() {
+                    if !definition.constraint.is_satisfied_by(types[index].as_ref()) {
+                        return Err(error(format!(
+                            "{} #{} ('{}') must be {}, but got '{}'",
+                            kind, index, definition.name, definition.constraint,
+                            types[index].as_ref())));
+                    }
+                }
+                named_types.insert(definition.name.as_str(), types[segment].to_vec());
+            }
+        }
+
+        for attribute in &self.attributes {
+            if !attribute.optional
+                && !operation.attributes().iter()
+                    .any(|named| named.name().value() == attribute.name)
+            {
+                return Err(error(format!("requires attribute '{}'", attribute.name)));
+            }
+        }
+
+        if let Some(num_regions) = self.num_regions
+            && operation.num_regions() != num_regions
+        {
+            return Err(error(format!(
+                "requires {} regions, but got {}",
+                num_regions, operation.num_regions())));
+        }
+
+        if let Some(verifier) = &self.verifier {
+            let environment = Environment {
+                operand_types,
+                result_types,
+                named_types,
+                attributes: operation.attributes(),
+                num_regions: operation.num_regions(),
+                num_successors: operation.num_successors()
+            };
+            match verifier.evaluate(&environment) {
+                Ok(true) => {},
+                Ok(false) => return Err(error(
+                    "failed to satisfy its custom verifier".to_string())),
+                Err(message) => return Err(error(format!(
+                    "failed to evaluate its custom verifier: {}", message)))
+            }
+        }
+        Ok(())
+    }
 }
 
-/// A dialect that can be extended with new operations/types/attributes at runtime.
+/**
+Split `count` values between `definitions`, at most one of which is variadic, returning the range of values of each definition. On failure, returns a description of the expected number of values.
+*/
+fn value_segments(definitions: &[ValueDefinition], count: usize)
+    -> Result<Vec<Range<usize>>, String>
+{
+    let num_fixed = definitions.iter().filter(|definition| !definition.variadic).count();
+    let has_variadic = num_fixed != definitions.len();
+    if count < num_fixed || (!has_variadic && count != num_fixed) {
+        return Err(match has_variadic {
+            true => format!("at least {}", num_fixed),
+            false => num_fixed.to_string()
+        });
+    }
+    let mut start = 0;
+    Ok(definitions.iter()
+        .map(|definition| {
+            let length = if definition.variadic { count - num_fixed } else { 1 };
+            start += length;
+            start - length..start
+        })
+        .collect())
+}
+
+// ----------------------------------------------------------------------
+// ExtensibleDialect
+// ----------------------------------------------------------------------
+
+/// A dialect that can be extended with new operations/types/attributes at runtime. Definitions may be registered while the dialect is shared, e.g. by a parser running on another thread.
+#[derive(Debug, Default)]
 pub struct ExtensibleDialect {
-    /// The set of all dynamic types registered.
-    dyn_types: DenseMap<TypeId, std::unique_ptr<DynamicTypeDefinition>>,
+    /// The dynamic types registered, keyed by name.
+    dyn_types: RwLock<HashMap<String, Arc<DynamicTypeDefinition>>>,
+
+    /// The dynamic attributes registered, keyed by name.
+    dyn_attrs: RwLock<HashMap<String, Arc<DynamicAttrDefinition>>>,
+
+    /// The dynamic operations registered, keyed by their full name.
+    dyn_ops: RwLock<HashMap<String, Arc<DynamicOpDefinition>>>
+}
+
+impl ExtensibleDialect {
+    /// Add a new type defined at runtime to the dialect.
+    pub fn register_dynamic_type(&self, definition: DynamicTypeDefinition) {
+        let mut dyn_types = self.dyn_types.write().unwrap();
+        assert!(!dyn_types.contains_key(definition.name()),
+            "type '{}' is already registered", definition.name());
+        dyn_types.insert(definition.name().to_string(), Arc::new(definition));
+    }
+
+    /// Add a new attribute defined at runtime to the dialect.
+    pub fn register_dynamic_attr(&self, definition: DynamicAttrDefinition) {
+        let mut dyn_attrs = self.dyn_attrs.write().unwrap();
+        assert!(!dyn_attrs.contains_key(definition.name()),
+            "attribute '{}' is already registered", definition.name());
+        dyn_attrs.insert(definition.name().to_string(), Arc::new(definition));
+    }
+
+    /// Add a new operation defined at runtime to the dialect.
+    pub fn register_dynamic_op(&self, definition: DynamicOpDefinition) {
+        let mut dyn_ops = self.dyn_ops.write().unwrap();
+        assert!(!dyn_ops.contains_key(definition.name()),
+            "operation '{}' is already registered", definition.name());
+        dyn_ops.insert(definition.name().to_string(), Arc::new(definition));
+    }
 
-    /// This structure allows to get in O(1) a dynamic type given its name.
-    name_to_dyn_types: StringMap<*mut DynamicTypeDefinition>,
+    /// Returns the dynamic type definition with the given name, if it exists.
+    pub fn lookup_type_definition(&self, name: &str)
+        -> Option<Arc<DynamicTypeDefinition>>
+    {
+        self.dyn_types.read().unwrap().get(name).cloned()
+    }
 
-    /// The set of all dynamic attributes registered.
-    dyn_attrs: DenseMap<TypeId, std::unique_ptr<DynamicAttrDefinition>>,
+    /// Returns the dynamic attribute definition with the given name, if it exists.
+    pub fn lookup_attr_definition(&self, name: &str)
+        -> Option<Arc<DynamicAttrDefinition>>
+    {
+        self.dyn_attrs.read().unwrap().get(name).cloned()
+    }
 
-    /// This structure allows to get in O(1) a dynamic attribute given its name.
-    name_to_dyn_attrs: StringMap<*mut DynamicAttrDefinition>
+    /// Returns the dynamic operation definition with the given full name, if it exists.
+    pub fn lookup_op_definition(&self, name: &str)
+        -> Option<Arc<DynamicOpDefinition>>
+    {
+        self.dyn_ops.read().unwrap().get(name).cloned()
+    }
+
+    /// Parse a dynamic type of this dialect.
+    pub fn parse_type(&self, parser: &mut dyn DialectAsmParser)
+        -> ParseResult<Arc<dyn Type>>
+    {
+        let location = parser.current_location();
+        let name = parser.parse_optional_any_keyword().ok_or_else(||
+            parser.emit_error(location, "expected dynamic type name".into()))?;
+        let definition = self.lookup_type_definition(&name).ok_or_else(||
+            parser.emit_error(location, format!(
+                "unknown type '{}' in dialect", name)))?;
+        let parameters = parse_parameters(parser)?;
+        DynamicType::new(definition, parameters)
+            .map(|r#type| Arc::new(r#type) as _)
+            .map_err(|error| parser.emit_error(location, error.to_string()))
+    }
+
+    /// Parse a dynamic attribute of this dialect.
+    pub fn parse_attribute(&self, parser: &mut dyn DialectAsmParser)
+        -> ParseResult<Arc<dyn Attribute>>
+    {
+        let location = parser.current_location();
+        let name = parser.parse_optional_any_keyword().ok_or_else(||
+            parser.emit_error(location, "expected dynamic attribute name".into()))?;
+        let definition = self.lookup_attr_definition(&name).ok_or_else(||
+            parser.emit_error(location, format!(
+                "unknown attribute '{}' in dialect", name)))?;
+        let parameters = parse_parameters(parser)?;
+        DynamicAttr::new(definition, parameters)
+            .map(|attribute| Arc::new(attribute) as _)
+            .map_err(|error| parser.emit_error(location, error.to_string()))
+    }
 }
 
+// ----------------------------------------------------------------------
+// DynamicDialect
+// ----------------------------------------------------------------------
+
 /// A dialect that can be defined at runtime. It can be extended with new operations, types, and attributes at runtime.
+#[derive(Debug)]
 pub struct DynamicDialect {
+    namespace: String,
+    dialect: ExtensibleDialect
+}
+
+impl DynamicDialect {
+    pub fn new(namespace: impl Into<String>) -> Self {
+        Self { namespace: namespace.into(), dialect: ExtensibleDialect::default() }
+    }
+
+    pub fn namespace(&self) -> &str {
+        &self.namespace
+    }
+
+    /// Verify `operation`, which belongs to this dialect, against its dynamic definition. Dynamic dialects do not allow unknown operations.
+    pub fn verify_operation(&self, arena: &IRArena, operation: &Operation)
+        -> LogicalResult
+    {
+        match self.lookup_op_definition(operation.name().as_str()) {
+            Some(definition) => definition.verify(arena, operation),
+            None => Err(Box::new(operation.emit_op_error(format!(
+                "is unregistered in dynamic dialect '{}'", self.namespace))))
+        }
+    }
+}
+
+impl Deref for DynamicDialect {
+    type Target = ExtensibleDialect;
+
+    fn deref(&self) -> &ExtensibleDialect {
+        &self.dialect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{
+        arena::OpId,
+        attribute::NamedAttribute,
+        builtins::{
+            attributes::{DictionaryAttribute, StringAttribute, TypeAttribute},
+            types::{FloatType, FloatTypeKind, IntegerType, Signedness}
+        },
+        location::Location,
+        operation::support::OperationName,
+        value::{Output, Value}
+    };
+
+    fn integer(width: usize) -> Arc<dyn Type> {
+        Arc::new(IntegerType::new(width, Signedness::Signless))
+    }
+
+    fn complex(element_type: Arc<dyn Type>) -> Arc<dyn Type> {
+        let definition = DynamicTypeDefinition::new(
+            "complex", "cmath", vec!["element_type".to_string()]);
+        let parameter = Arc::new(TypeAttribute::new(element_type));
+        Arc::new(DynamicType::new(Arc::new(definition), vec![parameter]).unwrap())
+    }
+
+    fn value(name: &str, constraint: &str, variadic: bool) -> ValueDefinition {
+        ValueDefinition {
+            name: name.to_string(),
+            constraint: TypeConstraint::parse(constraint),
+            variadic
+        }
+    }
+
+    /// Create an operation `name` whose operands are the results of a `test.source` operation of the given types.
+    fn create_op(
+        arena: &mut IRArena,
+        name: &str,
+        operand_types: &[Arc<dyn Type>],
+        result_types: &[Arc<dyn Type>],
+        attributes: &[&str]
+    ) -> OpId {
+        let source = Operation::new(
+            Location::unknown(), OperationName::new("test.source"),
+            operand_types.to_vec(), Vec::new(), DictionaryAttribute::default(), &[]);
+        let source = arena.create_operation(source, 0).unwrap();
+        let inputs = (0..operand_types.len())
+            .map(|index| Value::Output(Output::new(source, index)))
+            .collect();
+        let attributes = attributes.iter()
+            .map(|name| NamedAttribute::new(
+                StringAttribute::new(*name), Arc::new(StringAttribute::new("value"))))
+            .collect();
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new(name), result_types.to_vec(), inputs,
+            DictionaryAttribute::new(attributes), &[]);
+        arena.create_operation(operation, 0).unwrap()
+    }
+
+    #[test]
+    fn type_constraints_are_split_at_the_top_level() {
+        assert_eq!(TypeConstraint::parse(" any "), TypeConstraint::Any);
+        assert_eq!(TypeConstraint::parse("f32 | any"), TypeConstraint::AnyOf(vec![
+            TypeConstraint::Is("f32".to_string()), TypeConstraint::Any]));
+        assert_eq!(TypeConstraint::parse("!cmath.complex<f32 | f64>"),
+                   TypeConstraint::Is("!cmath.complex<f32|f64>".to_string()));
+        assert_eq!(TypeConstraint::parse("i32 | tuple<i32, i64>").to_string(),
+                   "'i32' or 'tuple<i32,i64>'");
+    }
+
+    #[test]
+    fn type_constraints_match_printed_types() {
+        let f32: Arc<dyn Type> = Arc::new(FloatType::new(FloatTypeKind::Float32Type));
+        let constraint = TypeConstraint::parse("i32 | f32");
+        assert!(constraint.is_satisfied_by(integer(32).as_ref()));
+        assert!(constraint.is_satisfied_by(f32.as_ref()));
+        assert!(!constraint.is_satisfied_by(integer(64).as_ref()));
+        assert!(TypeConstraint::Any.is_satisfied_by(integer(64).as_ref()));
+
+        // A type without parameters matches all of its parameterisations.
+        let complex = complex(f32);
+        assert_eq!(complex.to_string(), "!cmath.complex<f32>");
+        assert!(TypeConstraint::parse("!cmath.complex").is_satisfied_by(complex.as_ref()));
+        assert!(TypeConstraint::parse("!cmath.complex<f32>").is_satisfied_by(complex.as_ref()));
+        assert!(!TypeConstraint::parse("!cmath.complex<f64>").is_satisfied_by(complex.as_ref()));
+        assert!(!TypeConstraint::parse("!cmath.comp").is_satisfied_by(complex.as_ref()));
+    }
+
+    #[test]
+    fn dynamic_types_check_their_number_of_parameters() {
+        let definition = Arc::new(DynamicTypeDefinition::new(
+            "complex", "cmath", vec!["element_type".to_string()]));
+        let error = DynamicType::new(definition, Vec::new()).err().unwrap();
+        assert_eq!(error.to_string(), "type 'cmath.complex' expects 1 parameters, but got 0");
+    }
+
+    #[test]
+    fn operations_are_verified_against_their_definition() {
+        let dialect = DynamicDialect::new("test");
+        dialect.register_dynamic_op(DynamicOpDefinition::new(
+                "test.add",
+                vec![value("lhs", "i32", false), value("rest", "any", true)],
+                vec![value("result", "i32 | i64", false)])
+            .with_attributes(vec![AttributeDefinition { name: "kind".to_string(), optional: false }])
+            .with_num_regions(0)
+            .with_verifier(Expression::parse("count(rest) <= 1").unwrap()));
+
+        let mut arena = IRArena::new();
+        let (i32, i64) = (integer(32), integer(64));
+        let mut verify = |name: &str, operands: &[Arc<dyn Type>], attributes: &[&str]| {
+            let op = create_op(&mut arena, name, operands, &[i64.clone()], attributes);
+            dialect.verify_operation(&arena, arena.op(op).unwrap())
+                .map_err(|error| error.to_string())
+        };
+
+        assert!(verify("test.add", &[i32.clone()], &["kind"]).is_ok());
+        assert!(verify("test.add", &[i32.clone(), i64.clone()], &["kind"]).is_ok());
 
+        let cases = [
+            (verify("test.add", &[], &["kind"]), "'test.add' op expects at least 1 operands, but got 0"),
+            (verify("test.add", &[i64.clone()], &["kind"]),
+             "'test.add' op operand #0 ('lhs') must be 'i32', but got 'i64'"),
+            (verify("test.add", &[i32.clone()], &[]), "'test.add' op requires attribute 'kind'"),
+            (verify("test.add", &[i32.clone(), i32.clone(), i32.clone()], &["kind"]),
+             "'test.add' op failed to satisfy its custom verifier"),
+            (verify("test.sub", &[], &[]), "'test.sub' op is unregistered in dynamic dialect 'test'")
+        ];
+        for (result, expected) in cases {
+            let error = result.unwrap_err();
+            assert!(error.ends_with(expected), "{}", error);
+        }
+    }
 }
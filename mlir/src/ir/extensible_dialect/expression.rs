@@ -0,0 +1,468 @@
+/*!
+# Verifier Expressions
+
+This file implements the expressions used as custom verifiers of dynamic operations. An expression is evaluated against an operation, and the operation is valid if it evaluates to `true`.
+
+```text
+expression ::= or-expr
+or-expr    ::= and-expr (`||` and-expr)*
+and-expr   ::= cmp-expr (`&&` cmp-expr)*
+cmp-expr   ::= unary-expr ((`==` | `!=` | `<` | `<=` | `>` | `>=`) unary-expr)?
+unary-expr ::= `!` unary-expr | primary
+primary    ::= integer-literal | string-literal | `true` | `false`
+             | query | `(` expression `)`
+query      ::= `num_operands` | `num_results` | `num_regions` | `num_successors`
+             | `operand_type` `(` integer-literal `)`
+             | `result_type` `(` integer-literal `)`
+             | `type` `(` value-name `)` | `count` `(` value-name `)`
+             | `attr` `(` attr-name `)` | `has_attr` `(` attr-name `)`
+```
+
+Types and attributes evaluate to their printed form, so they compare against each other and against string literals, e.g. `type(lhs) == type(rhs) && attr(kind) != "\"none\""`.
+*/
+
+use std::{collections::HashMap, error::Error, sync::Arc};
+
+use crate::ir::{
+    attribute::NamedAttribute,
+    r#type::Type
+};
+
+/// A parsed verifier expression.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Expression {
+    Integer(i64),
+    Bool(bool),
+    String(String),
+    Query(Query),
+    Not(Box<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>)
+}
+
+/// A property of the operation being verified.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Query {
+    NumOperands,
+    NumResults,
+    NumRegions,
+    NumSuccessors,
+    /// The type of the operand at the given index.
+    OperandType(usize),
+    /// The type of the result at the given index.
+    ResultType(usize),
+    /// The type of the single value of the named operand or result.
+    Type(String),
+    /// The number of values of the named operand or result.
+    Count(String),
+    /// The value of the named attribute.
+    Attr(String),
+    /// Whether the named attribute is present.
+    HasAttr(String)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual
+}
+
+/// The operation an expression is evaluated against.
+pub struct Environment<'a> {
+    pub operand_types: Vec<&'a Arc<dyn Type>>,
+    pub result_types: Vec<&'a Arc<dyn Type>>,
+    /// The types of the values of each named operand and result.
+    pub named_types: HashMap<&'a str, Vec<&'a Arc<dyn Type>>>,
+    pub attributes: &'a [NamedAttribute],
+    pub num_regions: usize,
+    pub num_successors: usize
+}
+
+/// The value of an evaluated expression.
+#[derive(Debug, PartialEq)]
+enum Value {
+    Integer(i64),
+    Bool(bool),
+    String(String)
+}
+
+impl Expression {
+    /// Parse an expression from `source`.
+    pub fn parse(source: &str) -> Result<Self, Box<dyn Error>> {
+        let mut parser = ExpressionParser { source, offset: 0 };
+        let result = parser.parse_or().and_then(|expression| {
+            parser.skip_whitespace();
+            match parser.offset == source.len() {
+                true => Ok(expression),
+                false => Err("unexpected trailing characters".to_string())
+            }
+        });
+        result.map_err(|message| format!(
+            "invalid verifier expression '{}' at offset {}: {}",
+            source, parser.offset, message).into())
+    }
+
+    /// Evaluate this expression against `environment`, which must produce a boolean.
+    pub fn evaluate(&self, environment: &Environment) -> Result<bool, String> {
+        match self.evaluate_value(environment)? {
+            Value::Bool(value) => Ok(value),
+            value => Err(format!("expected a boolean, but got {:?}", value))
+        }
+    }
+
+    fn evaluate_value(&self, environment: &Environment) -> Result<Value, String> {
+        Ok(match self {
+            Self::Integer(value) => Value::Integer(*value),
+            Self::Bool(value) => Value::Bool(*value),
+            Self::String(value) => Value::String(value.clone()),
+            Self::Query(query) => query.evaluate(environment)?,
+            Self::Not(operand) => Value::Bool(!operand.evaluate(environment)?),
+            Self::Binary(BinaryOperator::Or, lhs, rhs) => Value::Bool(
+                lhs.evaluate(environment)? || rhs.evaluate(environment)?),
+            Self::Binary(BinaryOperator::And, lhs, rhs) => Value::Bool(
+                lhs.evaluate(environment)? && rhs.evaluate(environment)?),
+            Self::Binary(operator, lhs, rhs) => {
+                let lhs = lhs.evaluate_value(environment)?;
+                let rhs = rhs.evaluate_value(environment)?;
+                Value::Bool(match (operator, &lhs, &rhs) {
+                    (BinaryOperator::Equal, _, _) => lhs == rhs,
+                    (BinaryOperator::NotEqual, _, _) => lhs != rhs,
+                    (_, Value::Integer(lhs), Value::Integer(rhs)) => match operator {
+                        BinaryOperator::Less => lhs < rhs,
+                        BinaryOperator::LessEqual => lhs <= rhs,
+                        BinaryOperator::Greater => lhs > rhs,
+                        _ => lhs >= rhs
+                    },
+                    _ => return Err(format!(
+                        "cannot order {:?} and {:?}", lhs, rhs))
+                })
+            }
+        })
+    }
+}
+
+impl Query {
+    fn evaluate(&self, environment: &Environment) -> Result<Value, String> {
+        let named_types = |name: &str| environment.named_types.get(name)
+            .ok_or_else(|| format!("unknown operand or result '{}'", name));
+        Ok(match self {
+            Self::NumOperands => Value::Integer(environment.operand_types.len() as i64),
+            Self::NumResults => Value::Integer(environment.result_types.len() as i64),
+            Self::NumRegions => Value::Integer(environment.num_regions as i64),
+            Self::NumSuccessors => Value::Integer(environment.num_successors as i64),
+            Self::OperandType(index) => Value::String(
+                environment.operand_types.get(*index)
+                    .ok_or_else(|| format!("operand #{} does not exist", index))?
+                    .to_string()),
+            Self::ResultType(index) => Value::String(
+                environment.result_types.get(*index)
+                    .ok_or_else(|| format!("result #{} does not exist", index))?
+                    .to_string()),
+            Self::Type(name) => match named_types(name)?.as_slice() {
+                [r#type] => Value::String(r#type.to_string()),
+                types => return Err(format!(
+                    "'{}' has {} values, expected exactly one", name, types.len()))
+            },
+            Self::Count(name) => Value::Integer(named_types(name)?.len() as i64),
+            Self::Attr(name) => Value::String(
+                environment.attributes.iter()
+                    .find(|attribute| attribute.name().value() == name)
+                    .ok_or_else(|| format!("attribute '{}' does not exist", name))?
+                    .value().to_string()),
+            Self::HasAttr(name) => Value::Bool(environment.attributes.iter()
+                .any(|attribute| attribute.name().value() == name))
+        })
+    }
+}
+
+// ----------------------------------------------------------------------
+// ExpressionParser
+// ----------------------------------------------------------------------
+
+struct ExpressionParser<'a> {
+    source: &'a str,
+    offset: usize
+}
+
+impl<'a> ExpressionParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.offset += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `token` if it is next.
+    fn consume_if(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if !self.rest().starts_with(token) {
+            return false;
+        }
+        self.offset += token.len();
+        true
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), String> {
+        match self.consume_if(token) {
+            true => Ok(()),
+            false => Err(format!("expected '{}'", token))
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.parse_and()?;
+        while self.consume_if("||") {
+            let rhs = self.parse_and()?;
+            lhs = Expression::Binary(BinaryOperator::Or, lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn parse_and(&mut self) -> Result<Expression, String> {
+        let mut lhs = self.parse_comparison()?;
+        while self.consume_if("&&") {
+            let rhs = self.parse_comparison()?;
+            lhs = Expression::Binary(BinaryOperator::And, lhs.into(), rhs.into());
+        }
+        Ok(lhs)
+    }
+
+    fn parse_comparison(&mut self) -> Result<Expression, String> {
+        let lhs = self.parse_unary()?;
+        // Two character operators are checked first so that `<=` is not read as `<`.
+        let operators = [
+            ("==", BinaryOperator::Equal),
+            ("!=", BinaryOperator::NotEqual),
+            ("<=", BinaryOperator::LessEqual),
+            (">=", BinaryOperator::GreaterEqual),
+            ("<", BinaryOperator::Less),
+            (">", BinaryOperator::Greater)
+        ];
+        for (token, operator) in operators {
+            if self.consume_if(token) {
+                let rhs = self.parse_unary()?;
+                return Ok(Expression::Binary(operator, lhs.into(), rhs.into()));
+            }
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(&mut self) -> Result<Expression, String> {
+        // `!=` is never at the start of an operand, so a `!` here is a negation.
+        if self.consume_if("!") {
+            return Ok(Expression::Not(self.parse_unary()?.into()));
+        }
+        self.parse_primary()
+    }
+
+    fn parse_primary(&mut self) -> Result<Expression, String> {
+        self.skip_whitespace();
+        if self.consume_if("(") {
+            let expression = self.parse_or()?;
+            self.expect(")")?;
+            return Ok(expression);
+        }
+        if self.rest().starts_with('"') {
+            return self.parse_string().map(Expression::String);
+        }
+        if self.rest().starts_with(|char: char| char.is_ascii_digit()) {
+            return self.parse_integer().map(Expression::Integer);
+        }
+
+        let identifier = self.parse_identifier()?;
+        Ok(match identifier {
+            "true" => Expression::Bool(true),
+            "false" => Expression::Bool(false),
+            "num_operands" => Expression::Query(Query::NumOperands),
+            "num_results" => Expression::Query(Query::NumResults),
+            "num_regions" => Expression::Query(Query::NumRegions),
+            "num_successors" => Expression::Query(Query::NumSuccessors),
+            "operand_type" | "result_type" => {
+                self.expect("(")?;
+                self.skip_whitespace();
+                let index = self.parse_integer()? as usize;
+                self.expect(")")?;
+                Expression::Query(match identifier {
+                    "operand_type" => Query::OperandType(index),
+                    _ => Query::ResultType(index)
+                })
+            },
+            "type" | "count" | "attr" | "has_attr" => {
+                self.expect("(")?;
+                self.skip_whitespace();
+                let name = self.parse_identifier()?.to_string();
+                self.expect(")")?;
+                Expression::Query(match identifier {
+                    "type" => Query::Type(name),
+                    "count" => Query::Count(name),
+                    "attr" => Query::Attr(name),
+                    _ => Query::HasAttr(name)
+                })
+            },
+            _ => return Err(format!("unknown identifier '{}'", identifier))
+        })
+    }
+
+    fn parse_identifier(&mut self) -> Result<&'a str, String> {
+        let rest = self.rest();
+        let length = rest.find(|char: char| !char.is_ascii_alphanumeric() && char != '_')
+            .unwrap_or(rest.len());
+        if length == 0 || rest.starts_with(|char: char| char.is_ascii_digit()) {
+            return Err("expected identifier".to_string());
+        }
+        self.offset += length;
+        Ok(&rest[..length])
+    }
+
+    fn parse_integer(&mut self) -> Result<i64, String> {
+        let rest = self.rest();
+        let length = rest.find(|char: char| !char.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let value = rest[..length].parse()
+            .map_err(|_| "expected integer literal".to_string())?;
+        self.offset += length;
+        Ok(value)
+    }
+
+    /// Parse a string literal, in which `\"` and `\\` escape a quote and a backslash.
+    fn parse_string(&mut self) -> Result<String, String> {
+        let mut chars = self.rest().char_indices().skip(1);
+        let mut value = String::new();
+        while let Some((index, char)) = chars.next() {
+            match char {
+                '"' => {
+                    self.offset += index + 1;
+                    return Ok(value);
+                },
+                '\\' => match chars.next() {
+                    Some((_, escaped @ ('"' | '\\'))) => value.push(escaped),
+                    _ => return Err("invalid escape in string literal".to_string())
+                },
+                _ => value.push(char)
+            }
+        }
+        Err("unterminated string literal".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::builtins::{
+        attributes::StringAttribute,
+        types::{IntegerType, Signedness}
+    };
+
+    fn binary(operator: BinaryOperator, lhs: Expression, rhs: Expression) -> Expression {
+        Expression::Binary(operator, lhs.into(), rhs.into())
+    }
+
+    fn query(query: Query) -> Expression {
+        Expression::Query(query)
+    }
+
+    fn integer(width: usize) -> Arc<dyn Type> {
+        Arc::new(IntegerType::new(width, Signedness::Signless))
+    }
+
+    #[test]
+    fn operators_bind_by_precedence() {
+        // `&&` binds tighter than `||`, and comparisons tighter than both.
+        let expression = Expression::parse(
+            "num_operands == 2 || !has_attr(kind) && num_results <= 1").unwrap();
+        assert_eq!(expression, binary(
+            BinaryOperator::Or,
+            binary(BinaryOperator::Equal, query(Query::NumOperands), Expression::Integer(2)),
+            binary(
+                BinaryOperator::And,
+                Expression::Not(query(Query::HasAttr("kind".to_string())).into()),
+                binary(BinaryOperator::LessEqual, query(Query::NumResults), Expression::Integer(1)))));
+
+        let expression = Expression::parse("(true || false) && !(1 != 2)").unwrap();
+        assert_eq!(expression, binary(
+            BinaryOperator::And,
+            binary(BinaryOperator::Or, Expression::Bool(true), Expression::Bool(false)),
+            Expression::Not(binary(
+                BinaryOperator::NotEqual, Expression::Integer(1), Expression::Integer(2)).into())));
+    }
+
+    #[test]
+    fn queries_and_literals_are_parsed() {
+        let cases = [
+            ("operand_type( 1 )", query(Query::OperandType(1))),
+            ("result_type(0)", query(Query::ResultType(0))),
+            ("type(lhs)", query(Query::Type("lhs".to_string()))),
+            ("count(inputs)", query(Query::Count("inputs".to_string()))),
+            ("attr(kind)", query(Query::Attr("kind".to_string()))),
+            ("num_regions > num_successors", binary(
+                BinaryOperator::Greater, query(Query::NumRegions), query(Query::NumSuccessors))),
+            (r#""a \"quoted\" \\ string""#, Expression::String(r#"a "quoted" \ string"#.to_string()))
+        ];
+        for (source, expected) in cases {
+            assert_eq!(Expression::parse(source).unwrap(), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn invalid_expressions_report_their_offset() {
+        let cases = [
+            ("num_operands ==", "at offset 15: expected identifier"),
+            ("num_operands 2", "at offset 13: unexpected trailing characters"),
+            ("(true", "at offset 5: expected ')'"),
+            ("size(x)", "at offset 4: unknown identifier 'size'"),
+            (r#""open"#, "at offset 0: unterminated string literal"),
+            (r#""\n""#, "at offset 0: invalid escape in string literal")
+        ];
+        for (source, expected) in cases {
+            let error = Expression::parse(source).unwrap_err().to_string();
+            assert!(error.ends_with(expected), "{}: {}", source, error);
+        }
+    }
+
+    #[test]
+    fn expressions_are_evaluated_against_the_operation() {
+        let (i32, i64) = (integer(32), integer(64));
+        let attributes = [
+            NamedAttribute::new(StringAttribute::new("kind"), Arc::new(StringAttribute::new("none"))),
+            NamedAttribute::new(StringAttribute::new("size"), Arc::new(StringAttribute::new("4")))
+        ];
+        let environment = Environment {
+            operand_types: vec![&i32, &i32],
+            result_types: vec![&i64],
+            named_types: HashMap::from([
+                ("lhs", vec![&i32]),
+                ("rhs", vec![&i32]),
+                ("result", vec![&i64]),
+                ("none", vec![])
+            ]),
+            attributes: &attributes,
+            num_regions: 1,
+            num_successors: 0
+        };
+        let evaluate = |source: &str| Expression::parse(source).unwrap().evaluate(&environment);
+
+        assert_eq!(evaluate("num_operands == 2 && num_regions >= 1 && num_successors < 1"), Ok(true));
+        assert_eq!(evaluate("type(lhs) == type(rhs) && type(lhs) != result_type(0)"), Ok(true));
+        assert_eq!(evaluate(r#"operand_type(1) == "i32" && count(none) == 0"#), Ok(true));
+        assert_eq!(evaluate(r#"has_attr(size) && !has_attr(other) && attr(kind) == "\"none\"""#), Ok(true));
+        assert_eq!(evaluate("num_results > 1"), Ok(false));
+        // The right operand is only evaluated when needed.
+        assert_eq!(evaluate("true || attr(other) == 1"), Ok(true));
+
+        assert!(evaluate("num_operands").unwrap_err().starts_with("expected a boolean"));
+        assert!(evaluate("type(lhs) < type(rhs)").unwrap_err().starts_with("cannot order"));
+        assert_eq!(evaluate("type(none) == 0").unwrap_err(),
+                   "'none' has 0 values, expected exactly one");
+        assert_eq!(evaluate("count(other) == 0").unwrap_err(),
+                   "unknown operand or result 'other'");
+        assert_eq!(evaluate("operand_type(2) == 0").unwrap_err(), "operand #2 does not exist");
+        assert_eq!(evaluate("attr(other) == 0").unwrap_err(), "attribute 'other' does not exist");
+    }
+}
@@ -0,0 +1,517 @@
+/*!
+# Declarative Dialect Specifications
+
+This file defines the specification from which a dynamic dialect is loaded at runtime. A specification is either written in JSON or TOML, or is obtained from an IRDL description of the dialect.
+
+```toml
+name = "cmath"
+
+[[types]]
+name = "complex"
+parameters = ["element_type"]
+
+[[operations]]
+name = "norm"
+operands = [{ name = "value", constraint = "!cmath.complex" }]
+results = [{ name = "result", constraint = "f32 | f64" }]
+verifier = "num_regions == 0"
+```
+
+Operands and results without a constraint accept any type. The verifier is an expression, as described in the `expression` module, which must evaluate to true for valid operations.
+
+- IRDL <https://mlir.llvm.org/docs/Dialects/IRDL/>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Dialect/IRDL/IRDLLoading.cpp>
+*/
+
+use std::{error::Error, fs, path::Path, sync::Arc};
+
+use serde::Deserialize;
+
+use crate::ir::{
+    arena::{IRArena, OpId},
+    attribute::Attribute,
+    builtins::attributes::{StringAttribute, SymbolRefAttribute, TypeAttribute},
+    extensible_dialect::{
+        AttributeDefinition, DynamicAttrDefinition, DynamicDialect,
+        DynamicOpDefinition, DynamicTypeDefinition, TypeConstraint,
+        ValueDefinition, expression::Expression
+    },
+    mlir_context::MLIRContext,
+    operation::Operation,
+    value::Value
+};
+
+/// The specification of a dialect defined at runtime.
+#[derive(Clone, Debug, Deserialize)]
+pub struct DialectSpec {
+    /// The namespace of the dialect.
+    pub name: String,
+    #[serde(default)]
+    pub types: Vec<SymbolSpec>,
+    #[serde(default)]
+    pub attributes: Vec<SymbolSpec>,
+    #[serde(default)]
+    pub operations: Vec<OperationSpec>
+}
+
+/// The specification of a type or an attribute of a dynamic dialect.
+#[derive(Clone, Debug, Deserialize)]
+pub struct SymbolSpec {
+    /// The name of the type or attribute, without the dialect namespace.
+    pub name: String,
+    #[serde(default)]
+    pub parameters: Vec<String>
+}
+
+/// The specification of an operation of a dynamic dialect.
+#[derive(Clone, Debug, Deserialize)]
+pub struct OperationSpec {
+    /// The name of the operation, without the dialect namespace.
+    pub name: String,
+    #[serde(default)]
+    pub operands: Vec<ValueSpec>,
+    #[serde(default)]
+    pub results: Vec<ValueSpec>,
+    #[serde(default)]
+    pub attributes: Vec<AttributeSpec>,
+    /// The number of regions of the operation, any number if omitted.
+    #[serde(default)]
+    pub regions: Option<usize>,
+    /// The custom verifier expression.
+    #[serde(default)]
+    pub verifier: Option<String>
+}
+
+/// The specification of an operand or a result of an operation.
+#[derive(Clone, Debug, Deserialize)]
+pub struct ValueSpec {
+    pub name: String,
+    /// The constraint on the type of the value, parsed with `TypeConstraint::parse`.
+    #[serde(default = "any_constraint")]
+    pub constraint: String,
+    #[serde(default)]
+    pub variadic: bool
+}
+
+/// The specification of an attribute of an operation.
+#[derive(Clone, Debug, Deserialize)]
+pub struct AttributeSpec {
+    pub name: String,
+    #[serde(default)]
+    pub optional: bool
+}
+
+fn any_constraint() -> String {
+    "any".to_string()
+}
+
+impl DialectSpec {
+    /// Parse a specification written in JSON.
+    pub fn from_json(source: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(source)?)
+    }
+
+    /// Parse a specification written in TOML.
+    pub fn from_toml(source: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(toml::from_str(source)?)
+    }
+
+    /// Read a specification from a file, written in JSON if its extension is `json` and in TOML otherwise.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let path = path.as_ref();
+        let source = fs::read_to_string(path)?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&source),
+            _ => Self::from_toml(&source)
+        }
+    }
+
+    /**
+    Extract a specification from `dialect`, an `irdl.dialect` operation, e.g. parsed from a file with unregistered dialects allowed.
+
+    The supported constraints are `irdl.any`, `irdl.is`, `irdl.any_of`, and `irdl.parametric` whose parameters are either all unconstrained or all exact.
+    */
+    pub fn from_irdl(arena: &IRArena, dialect: OpId) -> Result<Self, Box<dyn Error>> {
+        let operation = arena.op(dialect)?;
+        if operation.name().as_str() != "irdl.dialect" {
+            return Err(Box::new(operation.emit_op_error("expected 'irdl.dialect'")));
+        }
+        let mut spec = Self {
+            name: symbol_name(operation)?,
+            types: Vec::new(),
+            attributes: Vec::new(),
+            operations: Vec::new()
+        };
+        for child in body(arena, operation)? {
+            let child = arena.op(*child)?;
+            match child.name().as_str() {
+                "irdl.type" => spec.types.push(SymbolSpec {
+                    name: symbol_name(child)?,
+                    parameters: irdl_parameters(arena, child)?
+                }),
+                "irdl.attribute" => spec.attributes.push(SymbolSpec {
+                    name: symbol_name(child)?,
+                    parameters: irdl_parameters(arena, child)?
+                }),
+                "irdl.operation" => {
+                    let mut operation = OperationSpec {
+                        name: symbol_name(child)?,
+                        operands: Vec::new(),
+                        results: Vec::new(),
+                        attributes: Vec::new(),
+                        regions: None,
+                        verifier: None
+                    };
+                    for nested in body(arena, child)? {
+                        let nested = arena.op(*nested)?;
+                        let (prefix, values) = match nested.name().as_str() {
+                            "irdl.operands" => ("operand", &mut operation.operands),
+                            "irdl.results" => ("result", &mut operation.results),
+                            _ => continue
+                        };
+                        for (index, input) in nested.inputs().iter().enumerate() {
+                            values.push(ValueSpec {
+                                name: format!("{}{}", prefix, index),
+                                constraint: irdl_constraint(arena, &spec.name, *input)?,
+                                variadic: false
+                            });
+                        }
+                    }
+                    spec.operations.push(operation);
+                },
+                _ => {}
+            }
+        }
+        Ok(spec)
+    }
+
+    /// Build the definitions of the specification, and load them into the dynamic dialect of the same name in `context`. The definitions are only registered if the dialect was not loaded yet.
+    pub fn load(&self, context: &MLIRContext)
+        -> Result<Arc<DynamicDialect>, Box<dyn Error>>
+    {
+        let operations = self.operations.iter()
+            .map(|operation| self.op_definition(operation))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(context.get_or_load_dynamic_dialect(&self.name, |dialect| {
+            for r#type in &self.types {
+                dialect.register_dynamic_type(DynamicTypeDefinition::new(
+                    &r#type.name, &self.name, r#type.parameters.clone()));
+            }
+            for attribute in &self.attributes {
+                dialect.register_dynamic_attr(DynamicAttrDefinition::new(
+                    &attribute.name, &self.name, attribute.parameters.clone()));
+            }
+            for operation in operations {
+                dialect.register_dynamic_op(operation);
+            }
+        }))
+    }
+
+    fn op_definition(&self, spec: &OperationSpec)
+        -> Result<DynamicOpDefinition, Box<dyn Error>>
+    {
+        let values = |specs: &[ValueSpec]| specs.iter()
+            .map(|value| ValueDefinition {
+                name: value.name.clone(),
+                constraint: TypeConstraint::parse(&value.constraint),
+                variadic: value.variadic
+            })
+            .collect::<Vec<_>>();
+        for (kind, specs) in [("operands", &spec.operands), ("results", &spec.results)] {
+            if specs.iter().filter(|value| value.variadic).count() > 1 {
+                return Err(format!(
+                    "operation '{}.{}' has more than one variadic {}",
+                    self.name, spec.name, kind).into());
+            }
+        }
+
+        let mut definition = DynamicOpDefinition::new(
+                format!("{}.{}", self.name, spec.name),
+                values(&spec.operands),
+                values(&spec.results))
+            .with_attributes(spec.attributes.iter()
+                .map(|attribute| AttributeDefinition {
+                    name: attribute.name.clone(),
+                    optional: attribute.optional
+                })
+                .collect());
+        if let Some(regions) = spec.regions {
+            definition = definition.with_num_regions(regions);
+        }
+        if let Some(verifier) = &spec.verifier {
+            definition = definition.with_verifier(Expression::parse(verifier)?);
+        }
+        Ok(definition)
+    }
+}
+
+// ----------------------------------------------------------------------
+// IRDL
+// ----------------------------------------------------------------------
+
+/// Returns the attribute `name` of `operation`.
+fn attribute<'a>(operation: &'a Operation, name: &str)
+    -> Option<&'a Arc<dyn Attribute>>
+{
+    operation.attributes().iter()
+        .find(|attribute| attribute.name().value() == name)
+        .map(|attribute| attribute.value())
+}
+
+fn symbol_name(operation: &Operation) -> Result<String, Box<dyn Error>> {
+    attribute(operation, "sym_name")
+        .and_then(|name| name.downcast_ref::<StringAttribute>())
+        .map(|name| name.value().to_string())
+        .ok_or_else(|| Box::new(operation.emit_op_error(
+            "requires string attribute 'sym_name'")) as _)
+}
+
+/// Returns the operations of the single-block region of `operation`.
+fn body<'a>(arena: &'a IRArena, operation: &Operation)
+    -> Result<&'a [OpId], Box<dyn Error>>
+{
+    if operation.num_regions() != 1 {
+        return Err(Box::new(operation.emit_op_error("expects one region")));
+    }
+    match arena.region(operation.region(0))?.blocks() {
+        [] => Ok(&[]),
+        [block] => Ok(arena.block(*block)?.operations()),
+        _ => Err(Box::new(operation.emit_op_error(
+            "expects a region with at most one block")))
+    }
+}
+
+/// Returns the names of the parameters of an `irdl.type` or `irdl.attribute` operation.
+fn irdl_parameters(arena: &IRArena, operation: &Operation)
+    -> Result<Vec<String>, Box<dyn Error>>
+{
+    for nested in body(arena, operation)? {
+        let nested = arena.op(*nested)?;
+        if nested.name().as_str() == "irdl.parameters" {
+            return Ok((0..nested.inputs().len())
+                .map(|index| format!("param{}", index))
+                .collect());
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// Returns the constraint defined by `value`, in the form parsed by `TypeConstraint::parse`.
+fn irdl_constraint(arena: &IRArena, dialect: &str, value: Value)
+    -> Result<String, Box<dyn Error>>
+{
+    let Some(op) = value.defining_operation() else {
+        return Err("IRDL constraints must be defined by operations".into());
+    };
+    let operation = arena.op(op)?;
+    match operation.name().as_str() {
+        "irdl.any" => Ok("any".to_string()),
+        "irdl.is" => attribute(operation, "expected")
+            .and_then(|expected| expected.downcast_ref::<TypeAttribute>())
+            .map(|expected| expected.value().to_string())
+            .ok_or_else(|| Box::new(operation.emit_op_error(
+                "requires type attribute 'expected'")) as _),
+        "irdl.any_of" => Ok(operation.inputs().iter()
+            .map(|input| irdl_constraint(arena, dialect, *input))
+            .collect::<Result<Vec<_>, _>>()?
+            .join(" | ")),
+        "irdl.parametric" => {
+            let base = attribute(operation, "base_type")
+                .and_then(|base| base.downcast_ref::<SymbolRefAttribute>())
+                .ok_or_else(|| Box::new(operation.emit_op_error(
+                    "requires symbol reference attribute 'base_type'")) as Box<dyn Error>)?;
+            let name = base.nested_references().last()
+                .unwrap_or(base.root_reference()).value();
+            let base = format!("!{}.{}", dialect, name);
+            let parameters = operation.inputs().iter()
+                .map(|input| irdl_constraint(arena, dialect, *input))
+                .collect::<Result<Vec<_>, _>>()?;
+            if parameters.iter().all(|parameter| parameter == "any") {
+                return Ok(base);
+            }
+            if parameters.iter().any(|parameter|
+                parameter == "any" || parameter.contains('|'))
+            {
+                return Err(Box::new(operation.emit_op_error(
+                    "only supports parameters that are either all unconstrained or all exact")));
+            }
+            Ok(format!("{}<{}>", base, parameters.join(", ")))
+        },
+        name => Err(Box::new(operation.emit_op_error(format!(
+            "is not a supported IRDL constraint ('{}')", name))))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ir::{
+        arena::BlockId,
+        attribute::NamedAttribute,
+        builtins::{
+            attributes::DictionaryAttribute,
+            types::{FloatType, FloatTypeKind, IntegerType, Signedness}
+        },
+        location::Location,
+        operation::support::OperationName,
+        r#type::Type,
+        value::Output
+    };
+
+    const CMATH: &str = r#"
+        name = "cmath"
+
+        [[types]]
+        name = "complex"
+        parameters = ["element_type"]
+
+        [[operations]]
+        name = "norm"
+        operands = [{ name = "value", constraint = "!cmath.complex" }]
+        results = [{ name = "result", constraint = "f32 | f64" }]
+        regions = 0
+        verifier = "num_regions == 0"
+    "#;
+
+    /// Create the operation `name` with a single result at the end of `block` if given, along with a region holding an empty block if `has_body`. Returns the operation and the block of its region.
+    fn create(
+        arena: &mut IRArena,
+        block: Option<BlockId>,
+        name: &str,
+        inputs: Vec<Value>,
+        attributes: Vec<(&str, Arc<dyn Attribute>)>,
+        has_body: bool
+    ) -> (OpId, Option<BlockId>) {
+        let attributes = attributes.into_iter()
+            .map(|(name, value)| NamedAttribute::new(StringAttribute::new(name), value))
+            .collect();
+        let result_type: Arc<dyn Type> = Arc::new(IntegerType::new(32, Signedness::Signless));
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new(name), [result_type], inputs,
+            DictionaryAttribute::new(attributes), &[]);
+        let op = arena.create_operation(operation, has_body as usize).unwrap();
+        if let Some(block) = block {
+            arena.push_back(block, op).unwrap();
+        }
+        let body = has_body.then(|| {
+            let region = arena.op(op).unwrap().region(0);
+            let body = arena.create_block();
+            arena.push_back_block(region, body).unwrap();
+            body
+        });
+        (op, body)
+    }
+
+    fn result(op: OpId) -> Value {
+        Value::Output(Output::new(op, 0))
+    }
+
+    fn symbol(name: &str) -> (&'static str, Arc<dyn Attribute>) {
+        ("sym_name", Arc::new(StringAttribute::new(name)))
+    }
+
+    fn expected(kind: FloatTypeKind) -> (&'static str, Arc<dyn Attribute>) {
+        ("expected", Arc::new(TypeAttribute::new(Arc::new(FloatType::new(kind)))))
+    }
+
+    #[test]
+    fn specifications_are_read_from_toml() {
+        let spec = DialectSpec::from_toml(CMATH).unwrap();
+        assert_eq!(spec.name, "cmath");
+        assert!(spec.attributes.is_empty());
+        let [complex] = spec.types.as_slice() else { panic!("Expected a single type") };
+        assert_eq!((complex.name.as_str(), complex.parameters.as_slice()),
+                   ("complex", &["element_type".to_string()][..]));
+
+        let [norm] = spec.operations.as_slice() else { panic!("Expected a single operation") };
+        assert_eq!((norm.operands.len(), norm.results.len()), (1, 1));
+        assert_eq!(norm.operands[0].constraint, "!cmath.complex");
+        assert_eq!(norm.results[0].constraint, "f32 | f64");
+        assert_eq!(norm.regions, Some(0));
+        assert_eq!(norm.verifier.as_deref(), Some("num_regions == 0"));
+    }
+
+    #[test]
+    fn omitted_fields_of_json_specifications_are_defaulted() {
+        let spec = DialectSpec::from_json(r#"{
+            "name": "test",
+            "operations": [{ "name": "op", "operands": [{ "name": "inputs", "variadic": true }] }]
+        }"#).unwrap();
+        assert!(spec.types.is_empty() && spec.attributes.is_empty());
+        let operation = &spec.operations[0];
+        assert!(operation.results.is_empty() && operation.attributes.is_empty());
+        assert_eq!((operation.regions, operation.verifier.as_ref()), (None, None));
+        let inputs = &operation.operands[0];
+        assert_eq!((inputs.name.as_str(), inputs.constraint.as_str(), inputs.variadic),
+                   ("inputs", "any", true));
+
+        assert!(DialectSpec::from_json(r#"{ "types": [] }"#).is_err());
+    }
+
+    #[test]
+    fn operation_definitions_are_checked() {
+        let spec = DialectSpec::from_toml(CMATH).unwrap();
+        let definition = spec.op_definition(&spec.operations[0]).unwrap();
+        assert_eq!(definition.name(), "cmath.norm");
+        assert_eq!(definition.operands()[0].constraint,
+                   TypeConstraint::Is("!cmath.complex".to_string()));
+        assert_eq!(definition.num_regions(), Some(0));
+        assert!(definition.verifier().is_some());
+
+        let mut operation = spec.operations[0].clone();
+        operation.verifier = Some("num_regions ==".to_string());
+        let error = spec.op_definition(&operation).err().unwrap();
+        assert!(error.to_string().starts_with("invalid verifier expression"));
+
+        operation.verifier = None;
+        operation.results = vec![operation.results[0].clone(); 2];
+        operation.results.iter_mut().for_each(|result| result.variadic = true);
+        let error = spec.op_definition(&operation).err().unwrap();
+        assert_eq!(error.to_string(), "operation 'cmath.norm' has more than one variadic results");
+    }
+
+    #[test]
+    fn specifications_are_extracted_from_irdl() {
+        let mut arena = IRArena::new();
+        let (dialect, body) = create(
+            &mut arena, None, "irdl.dialect", Vec::new(), vec![symbol("cmath")], true);
+
+        let (_, r#type) = create(
+            &mut arena, body, "irdl.type", Vec::new(), vec![symbol("complex")], true);
+        let (any, _) = create(&mut arena, r#type, "irdl.any", Vec::new(), Vec::new(), false);
+        create(&mut arena, r#type, "irdl.parameters", vec![result(any)], Vec::new(), false);
+
+        let (_, operation) = create(
+            &mut arena, body, "irdl.operation", Vec::new(), vec![symbol("norm")], true);
+        let base = SymbolRefAttribute::new(
+            StringAttribute::new("cmath"), vec![StringAttribute::new("complex")]);
+        let (any, _) = create(&mut arena, operation, "irdl.any", Vec::new(), Vec::new(), false);
+        let (complex, _) = create(
+            &mut arena, operation, "irdl.parametric", vec![result(any)],
+            vec![("base_type", Arc::new(base) as Arc<dyn Attribute>)], false);
+        let (f32, _) = create(
+            &mut arena, operation, "irdl.is", Vec::new(),
+            vec![expected(FloatTypeKind::Float32Type)], false);
+        let (f64, _) = create(
+            &mut arena, operation, "irdl.is", Vec::new(),
+            vec![expected(FloatTypeKind::Float64Type)], false);
+        let (float, _) = create(
+            &mut arena, operation, "irdl.any_of", vec![result(f32), result(f64)], Vec::new(), false);
+        create(&mut arena, operation, "irdl.operands", vec![result(complex)], Vec::new(), false);
+        create(&mut arena, operation, "irdl.results", vec![result(float)], Vec::new(), false);
+
+        let spec = DialectSpec::from_irdl(&arena, dialect).unwrap();
+        assert_eq!(spec.name, "cmath");
+        assert_eq!(spec.types[0].name, "complex");
+        assert_eq!(spec.types[0].parameters, ["param0"]);
+        let norm = &spec.operations[0];
+        assert_eq!(norm.name, "norm");
+        assert_eq!((norm.operands[0].name.as_str(), norm.operands[0].constraint.as_str()),
+                   ("operand0", "!cmath.complex"));
+        assert_eq!((norm.results[0].name.as_str(), norm.results[0].constraint.as_str()),
+                   ("result0", "f32 | f64"));
+
+        let error = DialectSpec::from_irdl(&arena, any).err().unwrap();
+        assert!(error.to_string().ends_with("'irdl.any' op expected 'irdl.dialect'"));
+    }
+}
@@ -11,27 +11,37 @@ This class is not meant for affine analysis and operations like set operations,
 
 pub mod detail;
 
-use crate::mlir::ir::affine_expr::AffineExpr;
+use core::fmt;
 
-use super::affine_expr::constant;
+use crate::mlir::ir::{
+    affine_expr::{AffineExpr, constant},
+    affine_map::print_dims_and_symbols,
+    mlir_context::MLIRContext
+};
 
 /// An integer set representing a conjunction of one or more affine equalities and inequalities. An integer set in the IR is immutable like the affine map, but integer sets are not unique'd unless the number of constraints in them is below `kUniquingThreshold`. The affine expressions that make up the equalities and inequalities of an integer set are themselves unique and are allocated by the bump pointer allocator.
-pub struct IntegerSet<const N: usize> {
+#[derive(Clone, PartialEq)]
+pub struct IntegerSet {
+    num_dims: usize,
+    num_symbols: usize,
+
     /// Array of affine constraints: a constraint is either an equality (affine_expr == 0) or an inequality (affine_expr >= 0).
-    constraints: &'static [AffineExpr; N],
+    constraints: Vec<AffineExpr>,
 
     // Bits to check whether a constraint is an equality or an inequality.
-    kinds: [bool; N]
+    eq_flags: Vec<bool>
 }
 
-impl<const N: usize> IntegerSet<N> {
+impl IntegerSet {
     pub fn new(
         num_dims: usize,
         num_symbols: usize,
-        constraints: &[AffineExpr; N],
-        kinds: &[bool; N]
+        constraints: Vec<AffineExpr>,
+        eq_flags: Vec<bool>
     ) -> Self {
-
+        assert_eq!(constraints.len(), eq_flags.len(),
+            "Expected one equality flag per constraint");
+        Self { num_dims, num_symbols, constraints, eq_flags }
     }
 
     // Returns the canonical empty IntegerSet (i.e. a set with no integer points).
@@ -42,6 +52,52 @@ impl<const N: usize> IntegerSet<N> {
     ) -> Self {
         let one = constant(1, context);
         // 1 == 0.
-        Self::new(numDims, num_symbols, one, true)
+        Self::new(num_dims, num_symbols, vec![one], vec![true])
+    }
+
+    pub fn num_dims(&self) -> usize {
+        self.num_dims
+    }
+
+    pub fn num_symbols(&self) -> usize {
+        self.num_symbols
+    }
+
+    pub fn num_constraints(&self) -> usize {
+        self.constraints.len()
+    }
+
+    pub fn constraints(&self) -> &[AffineExpr] {
+        &self.constraints
+    }
+
+    /// Returns the equality bits, which specify whether each of the constraints is an equality or inequality.
+    pub fn eq_flags(&self) -> &[bool] {
+        &self.eq_flags
+    }
+
+    /// Returns true if the `index`th constraint is an equality, false if it is an inequality.
+    pub fn is_eq(&self, index: usize) -> bool {
+        self.eq_flags[index]
+    }
+}
+
+impl fmt::Display for IntegerSet {
+    /// Prints the set as `(d0)[s0] : (d0 - s0 >= 0, d0 == 0)`. A set without constraints prints the canonical empty constraint `1 == 0`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        print_dims_and_symbols(f, self.num_dims, self.num_symbols)?;
+        f.write_str(" : (")?;
+        if self.constraints.is_empty() {
+            f.write_str("1 == 0")?;
+        }
+        for (index, (constraint, is_eq)) in self.constraints.iter()
+            .zip(&self.eq_flags).enumerate()
+        {
+            if index != 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{} {} 0", constraint, if *is_eq { "==" } else { ">=" })?;
+        }
+        f.write_str(")")
     }
 }
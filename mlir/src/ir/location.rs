@@ -6,13 +6,18 @@
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/Location.cpp>
 
 use core::ptr::Pointee;
+use std::sync::Arc;
 
 use crate::{
     mlir::ir::{
         attribute::Attribute,
         builtins::{
+            attributes::StringAttribute,
             dialect,
-            location_attributes::FusedLocation
+            location_attributes::{
+                CallSiteLocation, FileLineColLocation, FusedLocation,
+                NameLocation, OpaqueLocation
+            }
         },
         location,
         sub_element_interfaces,
@@ -57,8 +62,63 @@ impl LocationAttribute {
 }
 
 /// This class defines the main interface for locations in MLIR and acts as a non-nullable wrapper around a LocationAttribute.
-#[derive(Clone, Copy)]
-pub struct Location;
+///
+/// Each variant shares its builtin location attribute, so cloning a location is cheap.
+#[derive(Clone, Default)]
+pub enum Location {
+    CallSite(Arc<CallSiteLocation>),
+    FileLineCol(Arc<FileLineColLocation>),
+    Fused(Arc<FusedLocation>),
+    Name(Arc<NameLocation>),
+    Opaque(Arc<OpaqueLocation>),
+    #[default]
+    Unknown
+}
+
+impl Location {
+    pub fn unknown() -> Self {
+        Self::Unknown
+    }
+
+    pub fn file_line_col(
+        filename: StringAttribute,
+        line: usize,
+        column: usize
+    ) -> Self {
+        Self::FileLineCol(Arc::new(
+            FileLineColLocation::new(filename, line, column)))
+    }
+
+    pub fn call_site(callee: Location, caller: Location) -> Self {
+        Self::CallSite(Arc::new(CallSiteLocation::new(callee, caller)))
+    }
+
+    pub fn name(name: StringAttribute, child_location: Location) -> Self {
+        Self::Name(Arc::new(NameLocation::new(name, child_location)))
+    }
+
+    /// Returns a location fusing `locations`. Unknown locations are dropped, and a single remaining location without metadata is returned as is.
+    pub fn fused(
+        locations: impl IntoIterator<Item = Location>,
+        metadata: Option<Arc<dyn Attribute>>
+    ) -> Self {
+        let mut locations: Vec<_> = locations.into_iter()
+            .filter(|location| !location.is_unknown())
+            .collect();
+        if metadata.is_none() {
+            match locations.len() {
+                0 => return Self::Unknown,
+                1 => return locations.pop().unwrap(),
+                _ => {}
+            }
+        }
+        Self::Fused(Arc::new(FusedLocation::new(locations, metadata)))
+    }
+
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Self::Unknown)
+    }
+}
 
 /* LLVM Utilities */
 
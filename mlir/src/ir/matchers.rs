@@ -49,7 +49,7 @@ impl<T: Attribute> AttributeValueBinder<T> {
 pub const fn is_constant_like(operation: *mut Operation) -> bool {
        operation.num_inputs() == 0
     && operation.num_outputs() == 1
-    && operation.has_trait::<ConstantLike>()
+    && operation.has_trait::<dyn ConstantLike>()
 }
 
 /// The matcher that matches operations that have the `ConstantLike` trait.
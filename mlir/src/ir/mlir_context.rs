@@ -8,8 +8,10 @@ type unsigned = u16;
 
 use std::{
     any::TypeId,
+    collections::HashMap,
     hash::{Hash, Hasher},
-    ptr::null
+    ptr::null,
+    sync::{Arc, Mutex}
 };
 use llvm::{
     adt::{
@@ -34,10 +36,12 @@ use llvm::{
 use crate::{
     ir::{
         affine_expr,
+        arena::IRArena,
         affine_map,
         attribute,
         builtins::dialect,
         diagnostics::DiagnosticEngine,
+        dialect_resource_blob_manager::DialectResourceBlobManager,
         dialect::{
             Dialect,
             registry::DialectRegistry,
@@ -47,6 +51,7 @@ use crate::{
         location,
         mlir_context,
         operation::{
+            definition::Op,
             implementation,
             support::{OperationName, RegisteredOperationName},
         },
@@ -100,11 +105,8 @@ pub struct MLIRContext {
     /* Other */
 
     /// This points to the ThreadPool used when processing MLIR tasks in parallel.
-    /// It can't be nullptr when multi-threading is enabled. Otherwise if multi-threading is disabled, and the threadpool wasn't externally provided using `set_thread_pool`, this will be nullptr.
-    thread_pool: Option<ThreadPool>, // = nullptr;
-
-    /// In case where the thread pool is owned by the context, this ensures destruction with the context.
-    owned_thread_pool: Box<ThreadPool>,
+    /// It can't be None when multi-threading is enabled. Otherwise if multi-threading is disabled, and the threadpool wasn't externally provided using `set_thread_pool`, this will be None. The pool may be shared with other contexts.
+    thread_pool: Option<Arc<ThreadPool>>,
 
     /// This is a list of dialects that are created referring to this context.
     /// The MLIRContext owns the objects.
@@ -125,6 +127,23 @@ pub struct MLIRContext {
 
     /// A mutex used when accessing operation information.
     operation_info_mutex: SmartRWMutex<true>,
+
+    /// The storage of all operations, blocks and regions created in this context.
+    arena: IRArena,
+
+    /// The engine reporting diagnostics emitted in this context.
+    diag_engine: DiagnosticEngine,
+
+    /// The uniquers owning the storage of affine constructs, types and attributes.
+    affine_uniquer: StorageUniquer,
+    type_uniquer: StorageUniquer,
+    attribute_uniquer: StorageUniquer,
+
+    /// The managers of the resource blobs of each dialect, keyed by dialect namespace.
+    resource_blob_managers: Mutex<HashMap<String, Arc<DialectResourceBlobManager>>>,
+
+    /// The dialects defined at runtime, keyed by dialect namespace.
+    dynamic_dialects: Mutex<HashMap<String, Arc<DynamicDialect>>>,
 }
 
 // impl<'a> Default for MLIRContext<'a> {
@@ -217,13 +236,28 @@ impl MLIRContext {
     //     load_dialect<OtherDialect, MoreDialects...>();
     // }
 
-    /// Get (or create) a dynamic dialect for the given name.
+    /// Get (or create) a dynamic dialect for the given name. `ctor` is called to populate the dialect with its types, attributes and operations only when the dialect is created.
     pub fn get_or_load_dynamic_dialect(
+        &self,
         dialect_namespace: &str,
-        ctor: fn(Option<DynamicDialect>)  //function_ref<void>
-    ) -> Option<DynamicDialect>
+        ctor: impl FnOnce(&DynamicDialect)
+    ) -> Arc<DynamicDialect>
     {
-
+        self.dynamic_dialects.lock().unwrap()
+            .entry(dialect_namespace.to_string())
+            .or_insert_with(|| {
+                let dialect = DynamicDialect::new(dialect_namespace);
+                ctor(&dialect);
+                Arc::new(dialect)
+            })
+            .clone()
+    }
+
+    /// Return the dynamic dialect loaded for the given namespace, if any.
+    pub fn dynamic_dialect(&self, dialect_namespace: &str)
+        -> Option<Arc<DynamicDialect>>
+    {
+        self.dynamic_dialects.lock().unwrap().get(dialect_namespace).cloned()
     }
 
     /// Load all dialects available in the registry in this context.
@@ -266,37 +300,44 @@ impl MLIRContext {
 
     /// Return true if multi-threading is enabled by the context.
     pub fn is_multithreading_enabled(&self) -> bool {
-        self.threading_is_enabled && llvm::llvm_is_multithreaded()
+        self.threading_is_enabled
     }
 
     /// Set the flag specifying if multi-threading is disabled by the context.
     /// The command line debugging flag `--mlir-disable-threading` is overriding
     /// this call and making it a no-op!
-    pub fn disable_multithreading(&self, disable: bool /* true */) {
+    pub fn disable_multithreading(&mut self, disable: bool /* true */) {
+        self.threading_is_enabled = !disable;
+
+        // Create the thread pool of the context on first use, unless one was provided.
+        if !disable && self.thread_pool.is_none() {
+            self.thread_pool = Some(Arc::new(ThreadPool::default()));
+        }
 
+        // Update the threading mode for each of the uniquers.
+        self.affine_uniquer.disable_multithreading(disable);
+        self.attribute_uniquer.disable_multithreading(disable);
+        self.type_uniquer.disable_multithreading(disable);
     }
 
-    pub fn enable_multithreading(&self, enable: bool /* true */) {
+    pub fn enable_multithreading(&mut self, enable: bool /* true */) {
         self.disable_multithreading(!enable);
     }
 
-    /// Set a new thread pool to be used in this context. This method requires that multithreading is disabled for this context prior to the call. This allows to share a thread pool across multiple contexts, as well as decoupling the lifetime of the threads from the contexts. The thread pool must outlive the context. Multi-threading will be enabled as part of this method.
+    /// Set a new thread pool to be used in this context. This method requires that multithreading is disabled for this context prior to the call. This allows to share a thread pool across multiple contexts, as well as decoupling the lifetime of the threads from the contexts. Multi-threading will be enabled as part of this method.
     /// The command line debugging flag `--mlir-disable-threading` will still prevent threading from being enabled and threading won't be enabled after this call in this case.
-    pub fn set_thread_pool(&mut self, thread_pool: &ThreadPool) {
+    pub fn set_thread_pool(&mut self, thread_pool: Arc<ThreadPool>) {
         assert!(
             !self.is_multithreading_enabled(),
             "Expected multi-threading to be disabled when setting a ThreadPool.");
         self.thread_pool = Some(thread_pool);
-        self.owned_thread_pool.reset();
         self.enable_multithreading(true);
     }
 
     /// Return the number of threads used by the thread pool in this context. The number of computed hardware threads can change over the lifetime of a process based on affinity changes, so users should use the number of threads actually in the thread pool for dispatching work. Returns 1 if multithreading is disabled.
     pub fn num_threads(&self) -> usize {
         if self.is_multithreading_enabled() {
-            assert!(self.thread_pool,
-                   "Multi-threading is enabled but threadpool not set.");
-            self.thread_pool.thread_count()
+            return self.thread_pool().thread_count();
         }
         // No multithreading or active thread pool. Return 1 thread.
         1
@@ -306,9 +347,8 @@ impl MLIRContext {
     pub fn thread_pool(&self) -> &ThreadPool {
         assert!(self.is_multithreading_enabled(),
                 "Expected multi-threading to be enabled within the context.");
-        assert!(self.thread_pool,
-                "Multi-threading is enabled but threadpool not set.");
-        *self.thread_pool
+        self.thread_pool.as_deref()
+            .expect("Multi-threading is enabled but threadpool not set.")
     }
 
     /// Return true if we should attach the operation to diagnostics emitted via Operation::emit.
@@ -342,30 +382,71 @@ impl MLIRContext {
         self.sorted_registered_operations.into()
     }
 
+    /// Return the name of the operation `name`, which is registered if an operation by that name was registered in this context.
+    pub fn operation_name(&self, name: &str) -> OperationName {
+        match self.operations.get(name) {
+            Some(operation_name) => operation_name.clone(),
+            None => OperationName::new(name)
+        }
+    }
+
     /// Return true if this operation name is registered in this context.
     pub fn is_operation_registered(&self, name: &str) -> bool {
-        RegisteredOperationName::lookup(name, self).has_value()
+        self.operations.get(name).map_or(false, OperationName::is_registered)
+    }
+
+    /// Register the operation `T`, so that operations by its name are verified, parsed and printed with the hooks of `T`.
+    pub fn register_operation<T: Op>(&mut self) {
+        self.operations.insert(
+            T::OPERATION_NAME.to_string(),
+            OperationName::registered(
+                Arc::new(RegisteredOperationName::of::<T>(None))));
     }
 
     /// Returns the diagnostic engine for this context.
     pub fn diag_engine(&self) -> &DiagnosticEngine {
-        self.diag_engine
+        &self.diag_engine
     }
 
     /// Returns the storage uniquer used for creating affine constructs.
     pub fn affine_uniquer(&self) -> &StorageUniquer {
-        self.affine_uniquer
+        &self.affine_uniquer
     }
 
     /// Returns the storage uniquer used for constructing type storage instances.
     /// This should not be used directly.
     pub fn type_uniquer(&self) -> &StorageUniquer {
-        self.type_uniquer
+        &self.type_uniquer
+    }
+
+    /// Returns the storage uniquer used for constructing type storage instances, to register the storage classes of the types of a dialect.
+    pub fn type_uniquer_mut(&mut self) -> &mut StorageUniquer {
+        &mut self.type_uniquer
     }
 
     /// Returns the storage uniquer used for constructing attribute storage instances. This should not be used directly.
     pub fn attribute_uniquer(&self) -> &StorageUniquer {
-        self.attribute_uniquer
+        &self.attribute_uniquer
+    }
+
+    /// Returns the arena owning the operations, blocks and regions of this context.
+    pub fn arena(&self) -> &IRArena {
+        &self.arena
+    }
+
+    /// Returns the arena owning the operations, blocks and regions of this context, for creating and mutating IR.
+    pub fn arena_mut(&mut self) -> &mut IRArena {
+        &mut self.arena
+    }
+
+    /// Returns the manager of the resource blobs of the dialect `namespace`, creating it on first use. The manager is shared by everything referencing the resources of that dialect within this context.
+    pub fn resource_blob_manager(&self, namespace: &str)
+        -> Arc<DialectResourceBlobManager>
+    {
+        self.resource_blob_managers.lock().unwrap()
+            .entry(namespace.to_string())
+            .or_default()
+            .clone()
     }
 
     /// Returns the manager of debug actions within the context.
@@ -7,27 +7,26 @@
 
 pub mod asm_interface;
 pub mod base;
+pub mod constraints;
 pub mod definition;
 pub mod implementation;
 pub mod support;
 
-use core::cell::Cell;
-use proc_macro::{Diagnostic, Level};
+use core::fmt;
+use std::sync::Arc;
 
 use crate::{
     mlir::{
         ir::{
+            arena::{ArenaResult, BlockId, IRArena, OpId, RegionId},
             attribute::{Attribute, NamedAttribute},
-            block::{
-                Block,
-                support::BlockOperand,
-            },
+            block::Block,
             block_and_value_mapping,
             builtins::{
                 attributes::DictionaryAttribute,
                 types
             },
-            diagnostics::append_op,
+            diagnostics::{Diagnostic, DiagnosticSeverity},
             dialect::Dialect,
             location::Location,
             mlir_context::MLIRContext,
@@ -35,28 +34,22 @@ use crate::{
                 definition::FoldResult,
                 // implementation,
                 support::{
-                    OperandStorage, OperationName, PrintingFlags,
-                    RegisteredOperationName,
+                    OperationName, PrintingFlags, RegisteredOperationName,
                 }
             },
             pattern_match,
             region::Region,
             type_range::TypeRange,
             type_utilities,
-            use_def_lists::ValueUserIterator,
-            value::{
-                InlineOutput, Operand, Output, OutOfLineOutput, Value},
-            value_range::{OutputRange, UseIterator, UseRange, ValueRange}
+            r#type::Type,
+            value::{Output, Value, ValueImpl},
         },
         interfaces::fold_interfaces, support::logical_result::LogicalResult
     },
-    llvm::{
-        adt::{
-            small_vector::SmallVector,
-            string_extras,
-            twine::Twine, stl_extras::has_single_element
-        },
-        support::trailing_objects::TrailingObjects
+    llvm::adt::{
+        small_vector::SmallVector,
+        string_extras,
+        twine::Twine
     }
 };
 
@@ -69,31 +62,22 @@ The following documentation are recommended to understand this class:
 
 An Operation is defined first by its name, which is a unique string. The name is interpreted so that if it contains a '.' character, the part before is the dialect name this operation belongs to, and everything that follows is this operation name within the dialect.
 
-An Operation defines zero or more SSA `Value` that we refer to as the Operation outputs. This array of Value is actually stored in memory before the Operation itself in reverse order. That is for an Operation with 3 outputs we allocate the following memory layout:
-
- [Result2, Result1, Result0, Operation]
-                             ^ this is where `Operation*` pointer points to.
-
-A consequence of this is that this class must be heap allocated, which is handled by the various `create` methods. Each result contains:
-
-- one pointer to the first use (see `Operand`)
-- the type of the SSA Value this result defines.
-- the index for this result in the array.
-
-The outputs are defined as subclass of `ValueImpl`, and more precisely as the only two subclasses of `OpResultImpl`: `InlineOutput` and `OutOfLineOutput`. The former is used for the first 5 outputs and the latter for the subsequent ones. They differ in how they store their index: the first 5 outputs only need 3 bits and thus are packed with the Type pointer, while the subsequent one have an extra `usize` value and thus need more space.
+An Operation defines zero or more SSA `Value` that we refer to as the Operation outputs. The storage of each output (its type and its use list) is held in the `outputs` vector of the operation, and an `Output` handle is simply the `OpId` of the operation paired with the index of the output.
 
-An Operation also has zero or more inputs: these are uses of SSA Value, which can be the outputs of other operations or Block arguments. Each of these uses is an instance of `Operand`. This optional array is initially tail allocated with the operation class itself, but can be dynamically moved out-of-line in a dynamic allocation as needed.
+An Operation also has zero or more inputs: these are uses of SSA Value, which can be the outputs of other operations or Block arguments. Updating an input goes through `IRArena::set_input` so that the use list of the used value stays consistent.
 
-An Operation may contain optionally one or multiple `Region`s, stored in a tail allocated array. Each `Region` is a list of `Block`s. Each `Block` is itself a list of `Operation`s. This structure is effectively forming a tree.
+An Operation may contain optionally one or multiple `Region`s, referred to by `RegionId`. Each `Region` is a list of `Block`s. Each `Block` is itself a list of `Operation`s. This structure is effectively forming a tree. All three live in the `IRArena` owned by the context, so none of the links between them are raw pointers.
 
-Some operations like branches also refer to other `Block`, in which case they would have an array of `BlockOperand`.
+Some operations like branches also refer to other `Block`, in which case they hold a list of successor `BlockId`s.
 
-Finally an Operation also contain an optional `DictionaryAttribute`, a `Location`, and a pointer to its parent `Block` (if any).
+Finally an Operation also contain an optional `DictionaryAttribute`, a `Location`, and the handle of its parent `Block` (if any).
 */
-#[repr(align(8))]
 pub struct Operation {
+    /// The handle of this operation within the arena of its context.
+    pub(crate) id: OpId,
+
     /// The operation block that contains this operation.
-    block: *mut Block,  // = nullptr;
+    pub(crate) block: Option<BlockId>,
 
     /**
     This holds information about the source location the operation was defined or derived from.
@@ -101,35 +85,74 @@ pub struct Operation {
     pub location: Location,
 
     /// Relative order of this operation in its parent block. Used for O(1) local dominance checks between operations.
-    order_index: Cell<usize>,  // = 0;
-
-    num_outputs: usize,  // const
-    num_succs: usize,  // const
-    num_regions: usize,  // const : 31;
-
-    /// This bit signals whether this operation has an operand storage or not. The operand storage may be elided for operations that are known to never have inputs.
-    has_operand_storage: bool,  // : 1;
+    pub(crate) order_index: usize,
 
     /// This holds the name of the operation.
     name: OperationName,
 
     /// This holds general named attributes for the operation.
-    attributes: DictionaryAttribute
+    attributes: DictionaryAttribute,
+
+    /// The values used by this operation.
+    pub(crate) inputs: Vec<Value>,
+
+    /// The storage of the values defined by this operation.
+    pub(crate) outputs: SmallVector<[ValueImpl; 1]>,
+
+    /// The blocks this operation may transfer control to.
+    pub(crate) successors: SmallVector<[BlockId; 1]>,
+
+    /// The regions held by this operation.
+    pub(crate) regions: SmallVector<[RegionId; 1]>
 }
 
 impl Operation {
-    fn new(
+    /// The order index of operations whose position in their block is not known yet.
+    pub const INVALID_ORDER_INDEX: usize = usize::MAX;
+
+    /// The distance between the order indices of consecutive operations.
+    pub const ORDER_STRIDE: usize = 5;
+
+    /// Create a detached operation. The operation must be handed to `IRArena::create_operation`, which assigns its handle, registers its inputs as uses and attaches `num_regions` empty regions.
+    pub fn new(
         location: Location,
         name: OperationName,
-        num_outputs: usize,
-        num_successors: usize,
-        num_regions: usize,
+        output_types: impl IntoIterator<Item = Arc<dyn Type>>,
+        inputs: Vec<Value>,
         attributes: DictionaryAttribute,
-        has_operand_storage: bool
+        successors: &[BlockId]
     ) -> Self {
-        
+        Self {
+            id: OpId::DANGLING,
+            block: None,
+            location,
+            order_index: Self::INVALID_ORDER_INDEX,
+            name,
+            attributes,
+            inputs,
+            outputs: output_types.into_iter().map(ValueImpl::new).collect(),
+            successors: successors.iter().copied().collect(),
+            regions: SmallVector::new()
+        }
+    }
+
+    /// Returns a copy of this operation that is detached, has no regions, and whose outputs have no uses. The handle of the copy is assigned when it is given to the arena.
+    pub(crate) fn clone_detached(&self) -> Self {
+        Self::new(
+            self.location.clone(),
+            self.name.clone(),
+            self.outputs.iter().map(|output| output.r#type.clone()),
+            self.inputs.clone(),
+            self.attributes.clone(),
+            &self.successors
+        )
     }
-    
+
+    /// Returns the handle of this operation.
+    pub fn id(&self) -> OpId {
+        self.id
+    }
+
     /// The name of an operation is the key identifier for it.
     pub fn name(&self) -> &OperationName {
         &self.name
@@ -139,7 +162,7 @@ impl Operation {
     If this operation has a registered operation description, return it.
     Otherwise return std::nullopt.
     */
-    pub fn registered_info(&self) -> *mut RegisteredOperationName {
+    pub fn registered_info(&self) -> Option<&RegisteredOperationName> {
         self.name().registered_info()
     }
 
@@ -150,7 +173,7 @@ impl Operation {
     }
 
     /// Returns the operation block that contains this operation.
-    pub fn block(&self) -> *mut Block {
+    pub fn block(&self) -> Option<BlockId> {
         self.block
     }
 
@@ -162,13 +185,13 @@ impl Operation {
     /**
     Return the dialect this operation is associated with, or nullptr if the associated dialect is not loaded.
     */
-    pub fn dialect(&self) -> *mut Dialect {
+    pub fn dialect(&self) -> Option<&'static dyn Dialect> {
         self.name().dialect()
     }
 
     /// The source location the operation was defined or derived from.
     pub fn location(&self) -> Location {
-        self.location
+        self.location.clone()
     }
 
     /// Set the source location the operation was defined or derived from.
@@ -176,228 +199,76 @@ impl Operation {
         self.location = location;
     }
 
-    /// Returns the region to which the instruction belongs. Returns nullptr if the instruction is unlinked.
-    pub fn parent_region(&self) -> *mut Region {
-        match self.block {
-            None => None,
-            Some(block) => block.parent()
-        }
-    }
-
-    /// Returns the closest surrounding operation that contains this operation or nullptr if this is a top-level operation.
-    pub fn parent(&self) -> *mut Operation {
-        match self.block {
-            None => None,
-            Some(block) => block.parent_operation()
-        }
+    /// Returns the region to which the instruction belongs. Returns `None` if the instruction is unlinked.
+    pub fn parent_region(&self, arena: &IRArena) -> ArenaResult<Option<RegionId>> {
+        arena.parent_region(self.id)
     }
 
-    /// Return the closest surrounding parent operation that is of type `OpTy`.
-    pub fn parent_of_type<OpTy>(&self) -> OpTy {
-        let operation = self;
-        while let Some(operation) = operation.parent() {
-            let parent = dyn_cast::<OpTy>(operation);
-            if parent {
-                return parent;
-            }
-        }
-        OpTy::new()
+    /// Returns the closest surrounding operation that contains this operation or `None` if this is a top-level operation.
+    pub fn parent(&self, arena: &IRArena) -> ArenaResult<Option<OpId>> {
+        arena.parent_op(self.id)
     }
 
     /// Returns the closest surrounding parent operation with trait `Trait`.
     // template <typename T> class Trait>
-    pub fn parent_with_trait<Trait>(&self) -> *mut Operation {
-        let operation = self;
-        while let Some(operation) = operation.parent() {
-            if operation.has_trait::<Trait>() {
-                return Some(operation);
+    pub fn parent_with_trait<Trait: ?Sized + 'static>(&self, arena: &IRArena)
+    -> ArenaResult<Option<OpId>>
+    {
+        let mut current = self.parent(arena)?;
+        while let Some(operation) = current {
+            if arena.op(operation)?.has_trait::<Trait>() {
+                return Ok(Some(operation));
             }
+            current = arena.parent_op(operation)?;
         }
-        None
+        Ok(None)
     }
 
     /// Return true if this operation is a proper ancestor of the `other` operation.
-    pub fn is_proper_ancestor(&self, other: &Operation) -> bool {
-        while let Some(other) = other.parent() {
-            if self == other {
-                return true;
-            }
-        }
-        return false;
+    pub fn is_proper_ancestor(&self, arena: &IRArena, other: OpId)
+    -> ArenaResult<bool>
+    {
+        arena.is_proper_ancestor(self.id, other)
     }
 
     /// Return true if this operation is an ancestor of the `other` operation. An operation is considered as its own ancestor, use `is_proper_ancestor` to avoid this.
-    pub fn is_ancestor(&self, other: &Operation) -> bool {
-        self == other || self.is_proper_ancestor(other)
-    }
-
-    /// Replace any uses of 'from' with 'to' within this operation.
-    pub fn replace_uses_of_with(&self, from: Value, to: Value) {
-
-    }
-
-    /// Replace all uses of outputs of this operation with the provided `values`.
-    pub fn replace_all_uses_with<ValuesT>(&self, values: &&ValuesT) {
-        self.outputs().replace_all_uses_with(std::forward::<ValuesT>(values));
-    }
-
-    /// Destroys this operation and its subclass data.
-    pub fn destroy(&self) {
-
-    }
-
-    // TODO Rename input_uses
-    /// This drops all input uses from this operation, which is an essential step in breaking cyclic dependences between references when they are to be deleted.
-    pub fn drop_all_references(&self) {
-        for input in self.inputs() {
-            input.drop();
-        }
-        for region in self.regions() {
-            region.drop_all_references();
-        }
-        for dest in self.block_operands() {
-            dest.drop();
-        }
-    }
-
-    // TODO Rename output_uses
-    /// Drop uses of all values outputted by this operation or its nested regions.
-    pub fn drop_all_defined_value_uses(&self) {
-        self.drop_all_uses();
-        for region in self.regions() {
-            for block in region {
-                block.drop_all_defined_value_uses();
-            }
-        }  
-    }
-
-    /// Unlink this operation from its current block and insert it right before `operation` which may be in the same or another block in the same function.
-    pub fn move_before(&self, operation: *mut Operation) {
-        self.move_before_(operation.block(), operation.iterator());
-    }
-
-    /// Unlink this operation from its current block and insert it right before
-    /// `iterator` in the specified block.
-    pub fn move_before_(&self, block: *mut Block, iterator: IList<Operation>::iterator) {
-        block.operations().splice(iterator, self.block().operations(),
-                                self.iterator());
-    }
-
-    /// Unlink this operation from its current block and insert it right after `operation` which may be in the same or another block in the same function.
-    pub fn move_after(&self, operation: *mut Operation) {
-        self.move_after_(operation.block(), operation.iterator());
-    }
-
-    /// Unlink this operation from its current block and insert it right after `iterator` in the specified block.
-    pub fn move_after_(&self, block: *mut Block, iterator: IList<Operation>::iterator) {
-        assert!(iterator != block.end(), "Cannot move after end of block");
-        self.move_before_(block, iterator.next());
-    }
-
-    /// Given an operation `other` that is within the same parent block, return whether the current operation is before `other` in the operation list of the parent block.
-    ///
-    /// NOTE: This function has an average complexity of O(1), but worst case may take O(N) where N is the number of operations within the parent block.
-    pub fn is_before_in_block(&self, other: *mut Operation) -> bool {
-        assert!(
-            !self.block.is_null(),
-            "Operations without parent blocks have no order.");
-        assert!(
-            !other.is_null() && other.block == self.block,
-            "Expected other operation to have the same parent block.");
-        // If the order of the block is already invalid, directly recompute the
-        // parent.
-        if !self.block.is_op_order_valid() {
-            self.block.recompute_op_order();
-        } else {
-            // Update the order either operation if necessary.
-            self.update_order_if_necessary();
-            other.update_order_if_necessary();
-        }
-      
-        self.order_index < other.order_index
+    pub fn is_ancestor(&self, arena: &IRArena, other: OpId) -> ArenaResult<bool> {
+        arena.is_ancestor(self.id, other)
     }
 
     /* Inputs/Operands */
 
-    /// Insert the given inputs into the input list at the given `index`.
-    pub fn insert_inputs(&mut self, index: usize, inputs: ValueRange) {
-        if likely(self.has_operand_storage) {
-            self.set_inputs(index, 0, inputs);
-        }
-        assert!(inputs.is_empty(), "Inserting inputs without an operand storage");
-    }
-
     pub fn num_inputs(&self) -> usize {
-        if self.has_operand_storage {
-            self.operand_storage().len()
-        } else {
-            0
-        }
+        self.inputs.len()
     }
 
     pub fn input(&self, index: usize) -> Value {
-        self.op_operand(index).get()
-    }
-
-
-    pub fn set_input(&self, index: usize, value: Value) {
-        self.input(index).set(value);
-    }
-
-    /// Removes the input at position `index`.
-    pub fn remove_input(&self, index: usize) {
-        self.remove_inputs(index, 1);
+        self.inputs[index]
     }
 
-    /// Removes the inputs starting at position `index` and ending at position
-    /// `index + length`.
-    pub fn remove_inputs(&self, index: usize, length: usize /* 1 */) {
-        self.operand_storage().remove_inputs(index, length);
-    }
-
-    pub fn inputs(&self) -> &mut [Operand] {
-        if core::intrinsics::likely(self.has_operand_storage) {
-            self.operand_storage().inputs()
-        } else {
-            &mut []
-        }
-    }
-    
-    pub fn op_operand(&self, index: usize) -> &Operand {
-        self.operand_storage().inputs()[index]
-    }
-
-    pub fn input_types(&self) -> TypeRange {
-        self.inputs().types()
+    pub fn inputs(&self) -> &[Value] {
+        self.inputs.as_slice()
     }
 
     /* Outputs/Results */
 
     /// Return the number of outputs held by this operation.
     pub fn num_outputs(&self) -> usize {
-        self.num_outputs
+        self.outputs.len()
     }
 
     /// Get the `index`th output of this operation.
-    /// Returns a pointer to the use list for the given result, which may be either inline or out-of-line.
     pub fn output(&self, index: usize) -> Output {
-        let max_inline_results = Output::max_inline_results();
-        if index < max_inline_results {
-            return self.inline_output(index);
-        }
-        return self.out_of_line_output(index - max_inline_results);
+        assert!(index < self.outputs.len(), "Invalid output index.");
+        Output::new(self.id, index)
     }
 
-    pub fn outputs(&self) -> OutputRange {
-        if self.num_outputs == 0 {
-            OutputRange::new(None, 0)
-        } else {
-            OutputRange::new(self.inline_output(0), self.num_outputs)
-        }
+    pub fn outputs(&self) -> impl Iterator<Item = Output> + '_ {
+        (0..self.outputs.len()).map(|index| Output::new(self.id, index))
     }
 
-    pub fn output_types(&self) -> TypeRange<OutputRange> {  // TODO Creat the type
-        self.outputs().types()
+    pub fn output_types(&self) -> impl Iterator<Item = &Arc<dyn Type>> {
+        self.outputs.iter().map(|output| &output.r#type)
     }
 
     /* Attributes */
@@ -413,54 +284,54 @@ impl Operation {
 
     /// Return all of the attributes on this operation as a DictionaryAttribute.
     pub fn attr_dictionary(&self) -> DictionaryAttribute {
-        self.attributes
+        self.attributes.clone()
+    }
+
+    /// Set the attribute dictionary on this operation.
+    pub fn set_attr_dictionary(&mut self, attributes: DictionaryAttribute) {
+        self.attributes = attributes;
     }
 
     /* Blocks */
 
     /// Returns the number of regions held by this operation.
     pub fn num_regions(&self) -> usize {
-        self.num_regions
+        self.regions.len()
     }
 
     /// Returns the regions held by this operation.
-    pub fn regions(&self) -> &mut [Region] {
-        // Check the count first, as computing the trailing objects can be slow.
-        if self.num_regions == 0 {
-            return &mut [];
-        }
-        
-        let regions = TrailingObjects::<Region>::new();
-        regions as &mut [Region]  // ; self.num_regions
+    pub fn regions(&self) -> &[RegionId] {
+        self.regions.as_slice()
     }
 
     /// Returns the region held by this operation at position `index`.
-    pub fn region(&self, index: usize) -> &Region {
-        assert!(index < self.num_regions, "Invalid region index.");
-        &self.regions()[index]
+    pub fn region(&self, index: usize) -> RegionId {
+        assert!(index < self.regions.len(), "Invalid region index.");
+        self.regions[index]
     }
 
     /* Successors */
 
-    pub fn block_operands(&self) -> &mut [BlockOperand] {
-        TrailingObjects::<BlockOperand>::new().into()
+    pub fn successors(&self) -> &[BlockId] {
+        self.successors.as_slice()
     }
 
     pub fn has_successors(&self) -> bool {
-        self.num_succs != 0
+        !self.successors.is_empty()
     }
+
     pub fn num_successors(&self) -> usize {
-        self.num_succs
+        self.successors.len()
     }
 
-    pub fn successor(&self, index: usize) -> *mut Block {
+    pub fn successor(&self, index: usize) -> BlockId {
         assert!(index < self.num_successors());
-        self.block_operands()[index].get()
+        self.successors[index]
     }
 
-    pub fn set_successor(&mut self, block: *mut Block, index: usize) {
-        assert(index < getNumSuccessors());
-        self.block_operands()[index].set(block);
+    pub fn set_successor(&mut self, block: BlockId, index: usize) {
+        assert!(index < self.num_successors());
+        self.successors[index] = block;
     }
 
     /* Accessors for various properties of operations */
@@ -496,7 +367,7 @@ impl Operation {
 
     /// Returns true if the operation was registered with a particular trait, e.g. has_trait<OperandsAreSignlessIntegerLike>().
     // template <template <typename T> class Trait>
-    pub fn has_trait<Trait>(&self) -> bool {
+    pub fn has_trait<Trait: ?Sized + 'static>(&self) -> bool {
         self.name.has_trait::<Trait>()
     }
 
@@ -504,120 +375,42 @@ impl Operation {
     /// means that either the operation is unregistered, or it was registered with
     /// the provide trait.
     // template <template <typename T> class Trait>
-    pub fn might_have_trait<Trait>(&self) -> bool {
+    pub fn might_have_trait<Trait: ?Sized + 'static>(&self) -> bool {
         self.name.might_have_trait::<Trait>()
     }
 
-    /* Operation Walkers */
-
     /* Uses */
 
-    /// Drop all uses of outputs of this operation.
-    pub fn drop_all_uses(&self) {
-        for output in self.outputs() {
-            output.drop_all_uses();
-        }
-    }
-
-    /// Returns a range of all uses, which is useful for iterating over all uses.
-    pub fn uses(&self) -> UseRange {
-        self.outputs().uses()
+    /// Returns true if no output of this operation has a use.
+    pub fn use_empty(&self) -> bool {
+        self.outputs.iter().all(|output| output.uses.is_empty())
     }
 
-
     /// Returns true if this operation has exactly one use.
     pub fn has_one_use(&self) -> bool {
-        has_single_element(self.uses())
-    }
-
-    /// Returns true if this operation has no uses.
-    pub fn use_empty(&self) -> bool {
-        self.outputs().use_empty()
-    }
-
-    /// Returns true if the outputs of this operation are used outside of the given block.
-    pub fn is_used_outside_of_block(&self, block: *mut Block) -> bool {
-        self.outputs().any(
-            |output: Output| output.is_used_outside_of_block(block))
-    }
-
-    /* Users */
-    
-    /// Returns a range of all users.
-    pub fn users(&self) -> ValueUserIterator<UseIterator, Operand> {
-        ValueUserIterator
+        self.outputs.iter().map(|output| output.uses.len()).sum::<usize>() == 1
     }
 
     /* Other */
 
     /// Emit an error with the op name prefixed, like `'dim' op ` which is convenient for verifiers.
-    pub fn emit_op_error(&self, message: &Twine) -> Diagnostic {
-        let diagnostic = self.emit_error("");
-        let message = format!("{}'{}' op {}", diagnostic.message(), self.name(), message);
-        diagnostic.set_message(message);
-        diagnostic
-    }
-
-    /// Emit an error about fatal conditions with this operation, reporting up to any diagnostic handlers that may be listening.
-    pub fn emit_error<T: Into<String>>(&self, message: T) -> Diagnostic {
-        let diagnostic = Diagnostic::spanned(self.location, Level::Error, message);
-        if self.context().should_print_op_on_diagnostic() {
-            let message = format!(
-                "See current operation: {}",
-                append_op(
-                    &self,
-                    PrintingFlags::new().print_generic_op_form(),
-                    Level::Error
-                )
-            );
-            diagnostic.span_note(self.location, message);
-        }
-        diagnostic
-    }
-
-    /// Emit a warning about this operation, reporting up to any diagnostic handlers that may be listening.
-    pub fn emit_warning(&self, message: &Twine) -> Diagnostic {
+    pub fn emit_op_error(&self, message: impl fmt::Display) -> Diagnostic {
+        self.emit_error(format!("'{}' op {}", self.name(), message))
     }
 
-    /// Emit a remark about this operation, reporting up to any diagnostic handlers that may be listening.
-    pub fn emit_remark(&self, message: &Twine) -> Diagnostic {
+    /// Create an error about fatal conditions with this operation. The diagnostic is reported to the handlers listening on the context with `emit_op_diagnostic`.
+    pub fn emit_error(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(self.location(), DiagnosticSeverity::Error, message)
     }
 
-    /* Ordering */
-
-    // --
-    // 
-    // --
-
-    /// Returns the operand storage object.
-    fn operand_storage(&self) -> &OperandStorage {
-        assert!(
-            self.has_operand_storage,
-            "Expected operation to have operand storage"
-        );
-        *TrailingObjects::<OperandStorage>::new()
+    /// Create a warning about this operation. The diagnostic is reported to the handlers listening on the context with `emit_op_diagnostic`.
+    pub fn emit_warning(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(self.location(), DiagnosticSeverity::Warning, message)
     }
 
-    /// Returns a pointer to the use list for the given out-of-line output.
-    fn out_of_line_output(&self, index: usize) -> *mut OutOfLineOutput {
-        // Out-of-line outputs are stored in reverse order after (before in memory) the inline outputs.
-        return reinterpret_cast::<*mut OutOfLineOutput>(
-            self.inline_output(Output::max_inline_results() - 1)
-            )
-            - (index + 1);  // ++output_number
-    }
-
-    /// Returns a pointer to the use list for the given inline output.
-    fn inline_output(&self, index: usize) -> *mut InlineOutput {
-        // Inline outputs are stored in reverse order before the operation in
-        // memory.
-        reinterpret_cast::<*mut InlineOutput>(self) - (index + 1)  // ++output_number
-    }
-
-    /// Provide a 'get_parent' method for ilist_node_with_parent methods.
-    /// We mark it as a const function because ilist_node_with_parent specifically requires a 'get_parent() const' method. Once ilist_node removes this constraint, we should drop the const to fit the rest of the MLIR const model.
-    const fn parent(&self) -> *mut Block {
-        self.block
+    /// Create a remark about this operation. The diagnostic is reported to the handlers listening on the context with `emit_op_diagnostic`.
+    pub fn emit_remark(&self, message: impl Into<String>) -> Diagnostic {
+        Diagnostic::new(self.location(), DiagnosticSeverity::Remark, message)
     }
 }
 
@@ -0,0 +1,92 @@
+/*!
+# Type Constraints
+
+This file defines the predicates on types that operation definitions constrain their operands and results with, e.g. `operands(lhs: signless_integer_like)`. The name of a predicate, with `_` replaced by `-`, describes it in verification errors.
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/OpBase.td>
+*/
+
+use crate::ir::{
+    builtins::types::{
+        FloatType, IndexType, IntegerType, RankedTensorType, UnrankedTensorType
+    },
+    r#type::Type
+};
+
+/// Return the element type of `r#type` if it is a tensor, `r#type` otherwise.
+fn element_type_or_self(r#type: &dyn Type) -> &dyn Type {
+    if let Some(tensor) = r#type.downcast_ref::<RankedTensorType>() {
+        return &**tensor.element_type();
+    }
+    if let Some(tensor) = r#type.downcast_ref::<UnrankedTensorType>() {
+        return &**tensor.element_type();
+    }
+    r#type
+}
+
+/// Any type.
+pub fn any_type(r#type: &dyn Type) -> bool {
+    true
+}
+
+/// `index`.
+pub fn index(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<IndexType>().is_some()
+}
+
+/// An integer of any width and signedness.
+pub fn any_integer(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<IntegerType>().is_some()
+}
+
+/// A signless integer of any width, e.g. `i32`.
+pub fn signless_integer(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<IntegerType>()
+        .map_or(false, IntegerType::is_signless)
+}
+
+/// A signless integer or `index`.
+pub fn signless_integer_or_index(r#type: &dyn Type) -> bool {
+    signless_integer(r#type) || index(r#type)
+}
+
+/// `i1`.
+pub fn i1(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<IntegerType>()
+        .map_or(false, |r#type| r#type.is_signless() && r#type.width() == 1)
+}
+
+/// A floating point type.
+pub fn float(r#type: &dyn Type) -> bool {
+    r#type.downcast_ref::<FloatType>().is_some()
+}
+
+/// An integer of any signedness or `index`.
+pub fn integer_or_index(r#type: &dyn Type) -> bool {
+    any_integer(r#type) || index(r#type)
+}
+
+/// A signless integer or `index`, or a tensor of them.
+pub fn signless_integer_like(r#type: &dyn Type) -> bool {
+    signless_integer_or_index(element_type_or_self(r#type))
+}
+
+/// A signless integer, excluding `index`, or a tensor of them.
+pub fn signless_fixed_width_integer_like(r#type: &dyn Type) -> bool {
+    signless_integer(element_type_or_self(r#type))
+}
+
+/// A floating point type, or a tensor of them.
+pub fn float_like(r#type: &dyn Type) -> bool {
+    float(element_type_or_self(r#type))
+}
+
+/// A signless integer, `index` or a floating point type, or a tensor of them.
+pub fn signless_integer_or_float_like(r#type: &dyn Type) -> bool {
+    signless_integer_like(r#type) || float_like(r#type)
+}
+
+/// `i1`, or a tensor of them.
+pub fn bool_like(r#type: &dyn Type) -> bool {
+    i1(element_type_or_self(r#type))
+}
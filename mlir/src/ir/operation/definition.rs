@@ -9,22 +9,36 @@ The purpose of these types are to allow light-weight implementation of concrete
 */
 
 use core::{
-    any::Any,
+    any::{Any, TypeId},
     cmp::PartialEq,
-    fmt::Display
+    fmt::{self, Display},
+    ops::Range
 };
+use std::sync::Arc;
 
 use crate::{
     mlir::{
+        asm_parser::parser::ParseResult,
         ir::{
-            attribute::Attribute,
-            builtins::types::{FloatType, Tensor, VectorType},
+            arena::{ArenaResult, IRArena, OpId},
+            attribute::{Attribute, NamedAttribute},
+            builtins::{
+                attribute_interfaces::typed_attribute_type,
+                types::{FloatType, Tensor, VectorType}
+            },
             dialect,
-            operation::Operation,
+            enum_attribute::EnumAttribute,
+            operation::{
+                Operation,
+                implementation::{OpAsmParser, OpAsmPrinter},
+                support::OperationState
+            },
+            region_kind_interface::{HasOnlyGraphRegion, RegionKind},
             r#type::Type,
             type_utilities::{
                 element_type_or_self, verify_compatible_shapes
-            }
+            },
+            value::Value
         },
         support::interface_support::Interface
     },
@@ -702,17 +716,17 @@ pub trait VariadicResults: MultiResult {
 
 
 /// This class indicates that the regions associated with this operation don't have terminators.
-pub trait NoTerminator<Concrete>: Trait {
+pub trait NoTerminator: Trait {
 
 }
 
 /// This class provides the API for ops that are known to be terminators.
-pub trait IsTerminator<C>: Trait {
+pub trait IsTerminator: Trait {
 
 }
 
 /// This class provides verification for ops that are known to have zero successors.
-pub trait ZeroSuccessors<C>: Trait {
+pub trait ZeroSuccessors: Trait {
 
 }
 
@@ -723,7 +737,7 @@ pub trait MultiSuccessor: Trait {
 }
 
 /// This class provides APIs for ops that are known to have a single successor.
-pub trait OneSuccessor<C>: Trait {
+pub trait OneSuccessor: Trait {
 
 }
 
@@ -738,13 +752,13 @@ pub struct AtLeastNSuccessors<const N: usize> {
 }
 
 /// This class provides the API for ops which have an unknown number of successors.
-pub trait VariadicSuccessors<C>: MultiSuccessor {
+pub trait VariadicSuccessors: MultiSuccessor {
 
 }
 
 
 /// This class provides APIs and verifiers for ops with regions having a single block.
-pub trait SingleBlock<C>: Trait {
+pub trait SingleBlock: Trait {
 
 }
 
@@ -814,28 +828,28 @@ pub trait SameTypeOperands: Trait {
 /**
 This class provides the API for a sub-set of ops that are known to be constant-like. These are non-side effecting operations with one result and zero operands that can always be folded to a specific attribute value.
 */
-pub trait ConstantLike<T>: Trait {
+pub trait ConstantLike: Trait {
 
 }
 
 /**
 This class provides the API for ops that are known to be isolated from above.
 */
-pub trait IsIsolatedFromAbove<T>: Trait {
+pub trait IsIsolatedFromAbove: Trait {
 
 }
 
 /**
 A trait of region holding operations that defines a new scope for polyhedral optimisation purposes. Any SSA values of 'index' type that either dominate such an operation or are used at the top-level of such an operation automatically become valid symbols for the polyhedral scope defined by that operation. For more details, see `Traits.md#AffineScope`.
 */
-pub trait AffineScope<T>: Trait {
+pub trait AffineScope: Trait {
 
 }
 
 /**
 A trait of region holding operations that define a new scope for automatic allocations, i.e., allocations that are freed when control is transferred back from the operation's region. Any operations performing such allocations (for eg. memref.alloca) will have their allocations automatically freed at their closest enclosing operation with this trait.
 */
-pub trait AutomaticAllocationScope<T>: Trait {
+pub trait AutomaticAllocationScope: Trait {
 
 }
 
@@ -854,19 +868,19 @@ Certain operations can have multiple variadic operands and their size relationsh
 
 This trait verifies the attribute for specifying operand segments has the correct type (1D vector) and values (non-negative), etc.
 */
-pub trait AttrSizedOperandSegments<T>: Trait {
+pub trait AttrSizedOperandSegments: Trait {
 
 }
 
 /// Similar to `AttrSizedOperandSegments` but used for results.
-pub trait AttrSizedResultSegments<T>: Trait {
+pub trait AttrSizedResultSegments: Trait {
     
 }
 
 /**
 This trait provides a verifier for ops that are expecting their regions to not have any arguments
 */
-pub trait NoRegionArguments<T>: Trait {
+pub trait NoRegionArguments: Trait {
     
 }
 
@@ -875,7 +889,7 @@ This trait is used to flag operations that consume or produce values of `MemRef`
 TODO: Right now, the operands of an operation are either all normalisable, or not. In the future, we may want to allow some of the operands to be
 normalisable.
 */
-pub trait MemRefsNormalisable<T>: Trait {
+pub trait MemRefsNormalisable: Trait {
     
 }
 
@@ -912,7 +926,7 @@ which the operation is elementwise.
   should reify any needed guards or error handling code before lowering to
   an `Elementwise` operation.
 */
-pub trait Elementwise<T>: Trait {
+pub trait Elementwise: Trait {
 
 }
 
@@ -938,7 +952,7 @@ can be scalarized to
                      : (i1, f32, f32) -> f32
 ```
 */
-pub trait Scalarisable<T>: Trait {
+pub trait Scalarisable: Trait {
 
 }
 
@@ -950,7 +964,7 @@ This trait tags `Elementwise` operatons that can be systematically vectorised. A
 Provide the reverse to `Scalarisable` which, when chained together, allows reasoning about the relationship between the tensor and vector case.
 Additionally, it permits reasoning about promoting scalars to vectors via broadcasting in cases like `%select_scalar_pred` below.
 */
-pub trait Vectorisable<T>: Trait {
+pub trait Vectorisable: Trait {
 
 }
 
@@ -988,7 +1002,7 @@ can be tensorised to
                    -> tensor<?xf32>
 ```
 */
-pub trait Tensorisable<T>: Trait {
+pub trait Tensorisable: Trait {
     
 }
 
@@ -997,168 +1011,239 @@ pub trait Tensorisable<T>: Trait {
 // ----------------------------------------------------------------------
 
 /**
-This provides public APIs that all operations should have. The template argument `Self` should be the concrete type by CRTP and the others are base classes by the policy pattern.
+This provides public APIs that all operations should have. Concrete operations are thin handles to an `Operation` stored in an `IRArena`, and are usually defined with `#[derive(Op)]`, which generates the implementation of this trait along with builders and accessors.
 */
-pub trait Op {  // State
-    // /**
-    // A hook for static assertion that the external interface model T is targeting the concrete type of this op. The model can also be a fallback model that works for every op.
-    // */
-    // fn check_interface_target<T>() {
-    //     assert!(std::is_same<InterfaceTargetOrOpT<T>::type,
-    //                             Self>::value,
-    //                 "Attaching an interface to the wrong op kind");
-    // }
-
-    // /**
-    // Returns an interface map containing the interfaces registered to this
-    // operation.
-    // */
-    // fn interface_map() -> InterfaceMap {
-    //     InterfaceMap::get::<Traits<Self>>();
-    // }
-
-    // /**
-    // Return the internal implementations of each of the OperationName hooks.
-    // Implementation of `FoldHookFn` OperationName hook.
-    // */
-    // fn fold_hook_fn() -> OperationName::FoldHookFn {
-    //     // If the operation is single result and defines a `fold` method.
-    //     if constexpr (llvm::is_one_of<OpTrait::OneResult<Self>,
-    //                                 Traits<Self>>::value &&
-    //                 detect_has_single_result_fold<Self>::value){
-    //     return [](operation: *mut Operation, operands: &[impl Attribute],
-    //                 results: &SmallVector<[FoldResult]>) {
-    //         return fold_single_result_hook<Self>(op, operands, results);
-    //     };}
-
-    //     // The operation is not single result and defines a `fold` method.
-    //     if detect_has_fold<Self>::value {
-    //         return
-    //             |operation: *mut Operation,
-    //              operands: &[impl Attribute],
-    //              results: &SmallVector<[FoldResult]>|
-    //                  fold_hook::<Self>(op, operands, results)
-    //         ;
-    //     }
-
-    //     // The operation does not define a `fold` method.
-    //     return |operation: *mut Operation, operands: &[impl Attribute],
-    //             results: &SmallVector<[FoldResult]>| {
-    //         // In this case, we only need to fold the traits of the operation.
-    //         return op_definition_impl::fold_traits<Traits<Self>>(
-    //             op, operands, results);
-    //     };
-    // }
-    // /**
-    // Return the result of folding a single result operation that defines a `fold` method.
-    // */
-    // template <typename ConcreteOpT>
-    
-    // fn fold_single_result_hook(
-    //     operation: *mut Operation, operands: &[impl Attribute],
-    //     results: &SmallVector<[FoldResult]>
-    // ) -> LogicalResult
-    // {
-    //     let result = cast<ConcreteOpT>(operation).fold(operands);
-
-    //     // If the fold failed or was in-place, try to fold the traits of the
-    //     // operation.
-    //     if (!result || result.template dyn_cast<Value>() == operation.output(0)) {
-    //     if (succeeded(op_definition_impl::fold_traits<Traits<Self>...>(
-    //         operation, operands, results)))
-    //         return Ok(());
-    //     return success(static_cast<bool>(result));
-    //     }
-    //     results.push(result);
-    //     return Ok(());
-    // }
-    // /// Return the result of folding an operation that defines a `fold` method.
-    // template <typename ConcreteOpT>
-    // pub fn fold_hook(
-    //     operation: *mut Operation,
-    //     operands: &[impl Attribute],
-    //     results: &SmallVector<[FoldResult]>
-    // ) -> LogicalResult
-    // {
-    //     let result = cast<ConcreteOpT>(op).fold(operands, results);
-
-    //     // If the fold failed or was in-place, try to fold the traits of the
-    //     // operation.
-    //     if (failed(result) || results.is_empty()) {
-    //         if (succeeded(op_definition_impl::fold_traits<Traits<Self>...>(
-    //             op, operands, results))) {
-    //             return Ok(());
-    //         }
-    //     }
-    //     return result;
-    // }
-
-    // /// Implementation of `GetCanonicalisationPatternsFn` OperationName hook.
-    // pub fn get_get_canonicalisation_patterns_fn()
-    // -> OperationName::GetCanonicalisationPatternsFn {
-    //     &Self::get_canonicalisation_patterns
-    // }
-
-    // /// Implementation of `GetHasTraitFn`
-    // pub fn get_has_trait_fn() -> OperationName::HasTraitFn {
-    //     return
-    //         |type_id: TypeId| op_definition_impl::has_trait<Traits>(id);
-    // }
-    // /// Implementation of `ParseAssemblyFn` OperationName hook.
-    // pub fn get_parse_assembly_fn() -> OperationName::ParseAssemblyFn {
-    //     &Self::parse
-    // }
-
-    // /// Implementation of `PrintAssemblyFn` OperationName hook.
-    // pub fn get_print_assembly_fn() -> OperationName::PrintAssemblyFn {
-    //     if (detect_has_print::<Self>::value) {
-    //         return |operation: *mut Operation, p: &OpAsmPrinter, &str default_dialect| {
-    //             OpState::print_op_name(op, p, default_dialect);
-    //             return cast<Self>(op).print(p);
-    //     };}
-    //     return |operation: *mut Operation, printer: &OpAsmPrinter, default_dialect: &str|
-    //      OpState::print(op, printer, default_dialect)
-    //     ;
-    // }
-
-    // /// Implementation of `PopulateDefaultAttrsFn` OperationName hook.
-    // pub fn get_populate_default_attrs_fn() -> OperationName::PopulateDefaultAttrsFn {
-    //     Self::populateDefaultAttrs
-    // }
-
-    // /// Implementation of `VerifyInvariantsFn` OperationName hook.
-    // pub fn verify_invariants(operation: *mut Operation) -> LogicalResult {
-    //     assert!(has_no_data_members(),
-    //             "Op class shouldn't define new data members");
-    //     return failure(
-    //         failed(op_definition_impl::verifyTraits<Traits<Self>...>(op)) ||
-    //         failed(cast<Self>(op).verify()));
-    // }
-
-    // static OperationName::VerifyInvariantsFn get_verify_invariants_fn() {
-    //     return static_cast<LogicalResult (*)(Operation *)>(&verify_invariants);
-    // }
-
-    // /// Implementation of `VerifyRegionInvariantsFn` OperationName hook.
-    // static LogicalResult verify_region_invariants(operation: *mut Operation) {
-    //     assert!(has_no_data_members(),
-    //                 "Op class shouldn't define new data members");
-    //     return failure(
-    //         failed(op_definition_impl::verifyRegionTraits<Traits<Self>...>(
-    //             op)) ||
-    //         failed(cast<Self>(op).verifyRegions()));
-    // }
-
-    // static OperationName::VerifyRegionInvariantsFn get_verify_region_invariants_fn() {
-    //     return static_cast<LogicalResult (*)(Operation *)>(&verify_region_invariants);
-    // }
-
-    // static constexpr bool has_no_data_members() {
-    //     // Checking that the derived class does not define any member by comparing
-    //     // its size to an ad-hoc EmptyOp.
-    //     class EmptyOp : public Op<EmptyOp, Traits...> {};
-    //     return sizeof(Self) == sizeof(EmptyOp);
-    // }
+pub trait Op: Sized + 'static {
+    /// The name of the operation, e.g. `arith.addi`.
+    const OPERATION_NAME: &'static str;
+
+    /// Whether the operation defines `parse` and `print` for a custom assembly form.
+    const HAS_CUSTOM_ASSEMBLY_FORM: bool = false;
+
+    /// Wrap `operation` without checking that it is an instance of this operation.
+    fn from_operation_unchecked(operation: OpId) -> Self;
+
+    /// Return the handle of the operation.
+    fn operation(&self) -> OpId;
+
+    /// Return the operation `op` as this operation, or None if it is another operation.
+    fn from_operation(arena: &IRArena, op: OpId) -> ArenaResult<Option<Self>> {
+        Ok((arena.op(op)?.name().as_str() == Self::OPERATION_NAME)
+            .then(|| Self::from_operation_unchecked(op)))
+    }
+
+    /// Implementation of `HasTraitFn`, returns true if the operation has the trait whose `dyn` type has the given identifier.
+    fn has_trait(id: TypeId) -> bool {
+        false
+    }
+
+    /// Return the names of the attributes of this operation.
+    fn attribute_names() -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Implementation of `VerifyInvariantsFn`, verifying the invariants of the operation that don't depend on its regions, e.g. the number and types of its operands.
+    fn verify_invariants(arena: &IRArena, operation: &Operation) -> LogicalResult {
+        Ok(())
+    }
+
+    /// Implementation of `VerifyRegionInvariantsFn`, verifying the invariants of the operation that depend on the operations nested in its regions.
+    fn verify_region_invariants(arena: &IRArena, operation: &Operation)
+        -> LogicalResult
+    {
+        Ok(())
+    }
+
+    /// Implementation of `RegionKindFn`, returning the kind of the region `index` of the operation. Operations with the `HasOnlyGraphRegion` trait have graph regions, and the others SSACFG regions unless they implement `RegionKindInterface`.
+    fn region_kind(index: usize) -> RegionKind {
+        if Self::has_trait(TypeId::of::<dyn HasOnlyGraphRegion>()) {
+            RegionKind::Graph
+        } else {
+            RegionKind::SSACFG
+        }
+    }
+
+    /// Implementation of `PrintAssemblyFn`, printing the operation after its name. The default prints the generic form.
+    fn print(operation: &Operation, printer: &mut dyn OpAsmPrinter) -> fmt::Result {
+        printer.print_generic_op(operation, false)
+    }
+
+    /// Implementation of `ParseAssemblyFn`, parsing the operation after its name into `state`.
+    fn parse<'a>(parser: &mut dyn OpAsmParser<'a>, state: &mut OperationState)
+        -> ParseResult
+    {
+        Err(parser.emit_error(parser.name_location(), format!(
+            "operation '{}' has no custom assembly form", Self::OPERATION_NAME)))
+    }
+}
+
+pub use mlir_macros::Op;
+
+// ----------------------------------------------------------------------
+// Verification Helpers
+// ----------------------------------------------------------------------
+
+/*
+These functions are called by the verifiers generated by `#[derive(Op)]`.
+*/
+
+/// Return the range of the values of the group at `index`, given whether each group is variadic and the total number of values. There is at most one variadic group, which takes the values the other groups don't. The range is clamped to `count`, so it is valid even before the number of values is verified.
+pub fn value_segment(variadic: &[bool], count: usize, index: usize) -> Range<usize> {
+    let num_fixed = variadic.iter().filter(|variadic| !**variadic).count();
+    let variadic_len = count.saturating_sub(num_fixed);
+    let start = variadic[..index].iter()
+        .map(|variadic| if *variadic { variadic_len } else { 1 })
+        .sum::<usize>();
+    let len = if variadic[index] { variadic_len } else { 1 };
+    start.min(count)..(start + len).min(count)
+}
+
+/// Verify that `operation` has `count` operands, results, regions or successors, as required by groups of `kind` that are variadic or not.
+pub fn verify_count(operation: &Operation, kind: &str, variadic: &[bool], count: usize)
+    -> LogicalResult
+{
+    let num_fixed = variadic.iter().filter(|variadic| !**variadic).count();
+    if variadic.contains(&true) {
+        if count < num_fixed {
+            return Err(Box::new(operation.emit_op_error(format!(
+                "expected {} or more {}s, but found {}", num_fixed, kind, count))));
+        }
+    } else if count != num_fixed {
+        return Err(Box::new(operation.emit_op_error(format!(
+            "expected {} {}s, but found {}", num_fixed, kind, count))));
+    }
+    Ok(())
+}
+
+/// Verify that the type of the operand or result `#index` satisfies `constraint`, described by `description`.
+pub fn verify_type_constraint(
+    operation: &Operation,
+    kind: &str,
+    index: usize,
+    r#type: &dyn Type,
+    constraint: fn(&dyn Type) -> bool,
+    description: &str
+) -> LogicalResult {
+    if constraint(r#type) {
+        return Ok(());
+    }
+    Err(Box::new(operation.emit_op_error(format!(
+        "{} #{} must be {}, but got '{}'", kind, index, description, r#type))))
+}
+
+/// Verify that the attribute `name` of `operation` is a `T`, and that it is present unless it is `optional`.
+pub fn verify_attribute<T: Attribute>(
+    operation: &Operation,
+    name: &str,
+    optional: bool,
+    description: &str
+) -> LogicalResult {
+    match named_attribute(operation, name) {
+        None if optional => Ok(()),
+        None => Err(Box::new(operation.emit_op_error(format!(
+            "requires attribute '{}'", name)))),
+        Some(attribute) if attribute.downcast_ref::<T>().is_some() => Ok(()),
+        Some(_) => Err(Box::new(operation.emit_op_error(format!(
+            "attribute '{}' failed to satisfy constraint: {}", name, description))))
+    }
+}
+
+/// Verify that the attribute `name` of `operation` holds a case of the enum `E`, and that it is present unless it is `optional`.
+pub fn verify_enum_attribute<E: EnumAttribute>(
+    operation: &Operation,
+    name: &str,
+    optional: bool,
+    description: &str
+) -> LogicalResult {
+    match named_attribute(operation, name) {
+        None if optional => Ok(()),
+        None => Err(Box::new(operation.emit_op_error(format!(
+            "requires attribute '{}'", name)))),
+        Some(attribute) if attribute.downcast_ref::<E::Attribute>()
+            .and_then(E::from_attribute).is_some() => Ok(()),
+        Some(_) => Err(Box::new(operation.emit_op_error(format!(
+            "attribute '{}' failed to satisfy constraint: {}", name, description))))
+    }
+}
+
+/// Verify that the attribute `name` of `operation` has a type, the `TypedAttr` constraint of ODS, and that it is present unless it is `optional`.
+pub fn verify_typed_attribute(operation: &Operation, name: &str, optional: bool)
+    -> LogicalResult
+{
+    match named_attribute(operation, name) {
+        None if optional => Ok(()),
+        None => Err(Box::new(operation.emit_op_error(format!(
+            "requires attribute '{}'", name)))),
+        Some(attribute) if typed_attribute_type(&**attribute).is_some() => Ok(()),
+        Some(_) => Err(Box::new(operation.emit_op_error(format!(
+            "attribute '{}' failed to satisfy constraint: TypedAttr instance", name))))
+    }
+}
+
+/// Verify that all of `values` and the typed attributes `attributes` of `operation` have the same type, reporting `message` otherwise. Missing attributes are skipped, as they are reported by their own verifier.
+pub fn verify_types_match(
+    arena: &IRArena,
+    operation: &Operation,
+    values: &[Value],
+    attributes: &[&str],
+    message: &str
+) -> LogicalResult {
+    let mut types = value_types(arena, values)?;
+    types.extend(attributes.iter()
+        .filter_map(|name| named_attribute(operation, name))
+        .filter_map(|attribute| typed_attribute_type(&**attribute)));
+    if let Some(first) = types.first()
+        && types.iter().any(|r#type|
+            !Arc::ptr_eq(first, r#type) && first.to_string() != r#type.to_string())
+    {
+        return Err(Box::new(operation.emit_op_error(message)));
+    }
+    Ok(())
+}
+
+/// Verify that the type of `to` is the type of `from` mapped by `transformer`, the `TypesMatchWith` constraint of ODS, reporting `message` otherwise.
+pub fn verify_types_match_with(
+    arena: &IRArena,
+    operation: &Operation,
+    from: Value,
+    to: Value,
+    transformer: fn(&dyn Type) -> Arc<dyn Type>,
+    message: &str
+) -> LogicalResult {
+    let expected = transformer(&**from.r#type(arena)?);
+    if expected.to_string() != to.r#type(arena)?.to_string() {
+        return Err(Box::new(operation.emit_op_error(message)));
+    }
+    Ok(())
+}
+
+/// Return the attribute `name` of `operation`, if it has one.
+pub fn named_attribute<'a>(operation: &'a Operation, name: &str)
+    -> Option<&'a Arc<dyn Attribute>>
+{
+    operation.attributes().iter()
+        .find(|attribute| attribute.name().value() == name)
+        .map(NamedAttribute::value)
+}
+
+/// Return the attribute `name` of `operation`, if it has one and it is a `T`.
+pub fn attribute_of<'a, T: Attribute>(operation: &'a Operation, name: &str)
+    -> Option<&'a T>
+{
+    named_attribute(operation, name)
+        .and_then(|attribute| attribute.downcast_ref::<T>())
+}
+
+/// Return the case of the enum `E` held by the attribute `name` of `operation`, if it has one.
+pub fn enum_attribute_of<E: EnumAttribute>(operation: &Operation, name: &str) -> Option<E> {
+    attribute_of::<E::Attribute>(operation, name).and_then(E::from_attribute)
+}
+
+/// Return the types of `values`.
+pub fn value_types(arena: &IRArena, values: &[Value])
+    -> ArenaResult<Vec<Arc<dyn Type>>>
+{
+    values.iter().map(|value| value.r#type(arena).cloned()).collect()
 }
 
 /**
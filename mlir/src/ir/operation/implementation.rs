@@ -5,22 +5,34 @@
 use core::{
     any::TypeId,
     cmp::PartialEq,
-    hash::{Hash, Hasher},
-    io::Write
+    fmt::{self, Write},
+    hash::{Hash, Hasher}
 };
+use std::{error::Error, sync::Arc};
 
 use crate::{
     mlir::{
+        asm_parser::{
+            parser::{ParseResult, UnresolvedOperand},
+            token::TokenKind
+        },
         ir::{
+            arena::{BlockId, IRArena, RegionId},
+            asm_state::{AsmParsedResourceEntry, AsmResourceBuilder},
             attr_type_base::AttrOrType,
-            attribute::Attribute,
-            builtins::types,
+            attribute::{Attribute, NamedAttribute},
+            builtins::types::FunctionType,
             dialect::{
                 Dialect,
                 interface
             },
-            operation::definition,
-            r#type::Type
+            mlir_context::MLIRContext,
+            operation::{
+                Operation,
+                definition
+            },
+            r#type::Type,
+            value::{Input, Value}
         },
         support::{
             logical_result::LogicalResult,
@@ -28,6 +40,7 @@ use crate::{
     },
     llvm::{
         adt::{
+            ap_int::APInt,
             stl_extras::{interleave_comma, has_single_element},
             twine
         },
@@ -36,191 +49,162 @@ use crate::{
 };
 
 /// This class represents an opaque handle to a dialect resource entry.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 pub struct AsmDialectResourceHandle {
-    /// The opaque handle to the dialect resource.
-    resource: Option<()>,  // = nullptr;
+    /// The key of the resource, unique within the owning dialect.
+    key: String,
     /// The type of the resource referenced.
     opaque_id: TypeId,
-    /// The dialect owning the given resource.
-    dialect: Option<dyn Dialect>
+    /// The namespace of the dialect owning the given resource.
+    dialect: String
 }
 
-impl PartialEq for AsmDialectResourceHandle {
-    fn eq(&self, other: &Self) -> bool {
-        self.resource == other.resource
+impl AsmDialectResourceHandle {
+    pub fn new(
+        key: impl Into<String>,
+        opaque_id: TypeId,
+        dialect: impl Into<String>
+    ) -> Self {
+        Self { key: key.into(), opaque_id, dialect: dialect.into() }
     }
-}
 
-impl AsmDialectResourceHandle {
-    /// Return an opaque pointer to the referenced resource.
-    pub const fn resource(&self) -> *mut () {
-        self.resource
+    /// Return the key identifying the referenced resource.
+    pub fn key(&self) -> &str {
+        &self.key
     }
 
     /// Return the type ID of the resource.
-    pub const fn type_id(&self) -> TypeId {
+    pub fn type_id(&self) -> TypeId {
         self.opaque_id
     }
 
-    /// Return the dialect that owns the resource.
-    pub const fn dialect(&self) -> Option<Dialect> {
-        self.dialect
+    /// Return the namespace of the dialect that owns the resource.
+    pub fn dialect(&self) -> &str {
+        &self.dialect
     }
 }
 
-impl Hash for AsmDialectResourceHandle {
-    fn hash<H: Hasher>(&self, state: &mut H) {
-        state.write_u8(self.resource());
-    }
-}
-
-
 /// This base class exposes generic asm printer hooks, usable across the various derived printers.
 pub trait AsmPrinter: Write {
-    // 
-    // AsmPrinterImpl
-    //
-
-    /// The output stream for the printer.
-    raw_ostream &os;
-
-    /// An underlying assembly printer state.
-    AsmStateImpl &state;
-
-    /// A set of flags to control the printer's behaviour.
-    OpPrintingFlags printerFlags;
-
-    /// A tracker for the number of new lines emitted during printing.
-    NewLineCounter newLine;
-
-//   /// Return the raw output stream used by this printer.
-//   virtual raw_ostream &stream() const;
-
-    /**
-    Print the given floating point value in a stabilized form that can be roundtripped through the IR. This is the companion to the `parse_float` hook on the AsmParser.
-    */
-    fn print_float(&self, value: &APFloat);
+    fn print_type(&mut self, r#type: &dyn Type) -> fmt::Result;
 
-    fn print_type(&self, r#type: impl Type);
-
-    fn print_attribute(&self, attribute: impl Attribute);
-
-    // /// Trait to check if `AttrType` provides a `print` method.
-    // template <typename AttrOrType>
-    // using has_print_method =
-    //     decltype(std::declval<AttrOrType>().print(std::declval<AsmPrinter &>()));
-    // template <typename AttrOrType>
-    // using detect_has_print_method =
-    //     llvm::is_detected<has_print_method, AttrOrType>;
+    fn print_attribute(&mut self, attribute: &dyn Attribute) -> fmt::Result;
 
     /**
-    Print the provided attribute in the context of an operation custom printer/parser: this will invoke directly the print method on the attribute class and skip the `#dialect.mnemonic` prefix in most cases.
+    Print the given attribute without its type. The corresponding parser must provide a valid type for the attribute.
     */
-    // template <
-    //     std::enable_if_t<detect_has_print_method<AttrOrType>::value>
-    //         *sfinae = nullptr>
-    fn print_stripped_attr_or_type<AttrOrType>(
-        &self, attr_or_type: AttrOrType
-    ) {
-        match print_alias(attr_or_type) {
-            Ok(_) => {},
-            Err(_) => attr_or_type.print(*self)
-        };
-    }
+    fn print_attribute_without_type(&mut self, attribute: &dyn Attribute)
+        -> fmt::Result;
 
-    /**
-    Print the provided array of attributes or types in the context of an operation custom printer/parser: this will invoke directly the print method on the attribute class and skip the `#dialect.mnemonic` prefix in most cases.
-    */
-    // template <
-    //     std::enable_if_t<detect_has_print_method<AttrOrType>::value>
-    //         *sfinae = nullptr>
-    fn print_stripped_attr_or_type<ArrayOrType: IntoIterator>
-        (&self, attr_or_types: &[AttrOrType])
-    {
-        interleave_comma(
-            attr_or_types,
-            self.stream(),
-            |attr_or_type: AttrOrType| {
-                self.print_stripped_attr_or_type(attr_or_type);
-            }
-        );
-    }
+    /// Print a handle to the given dialect resource.
+    fn print_resource_handle(&mut self, resource: &AsmDialectResourceHandle)
+        -> fmt::Result;
 
     /**
-    SFINAE for printing the provided attribute in the context of an operation custom printer in the case where the attribute does not define a print method.
+    Print the given floating point value in a stabilized form that can be roundtripped through the IR. This is the companion to the `parse_float` hook on the AsmParser.
     */
-    // template <
-    //     std::enable_if_t<!detect_has_print_method<AttrOrType>::value>
-    //         *sfinae = nullptr>
-    fn print_stripped_attr_or_type<AttrOrType>(
-        &self, attr_or_type: AttrOrType
-    ) {
-        *self << attr_or_type;
+    fn print_float(&mut self, value: f64) -> fmt::Result {
+        if value.is_finite() {
+            // `{:?}` prints the shortest representation that reads back to the same value and always keeps a `.` or exponent.
+            write!(self, "{:?}", value)
+        } else {
+            write!(self, "0x{:X}", value.to_bits())
+        }
     }
 
-    /**
-    Print the given attribute without its type. The corresponding parser must provide a valid type for the attribute.
-    */
-    fn print_attribute_without_type(&self, attribute: Attribute);
-
     /**
     Print the given string as a keyword, or a quoted and escaped string if it
     has any special or non-printable characters in it.
     */
-    fn print_keyword_or_string(&self, keyword: &str);
+    fn print_keyword_or_string(&mut self, keyword: &str) -> fmt::Result {
+        if is_bare_identifier(keyword) {
+            self.write_str(keyword)
+        } else {
+            print_escaped_string(self, keyword)
+        }
+    }
 
     /**
     Print the given string as a symbol reference, i.e. a form representable by a SymbolRefAttr. A symbol reference is represented as a string prefixed with '@'. The reference is surrounded with `""`'s and escaped if it has any special or non-printable characters in it.
     */
-    fn print_symbol_name(&self, symbol_ref: &Self);
-
-    /// Print a handle to the given dialect resource.
-    fn print_resource_handle(&self, resource: &AsmDialectResourceHandle);
+    fn print_symbol_name(&mut self, symbol_ref: &str) -> fmt::Result {
+        self.write_char('@')?;
+        self.print_keyword_or_string(symbol_ref)
+    }
 
     /// Print an optional arrow followed by a type list.
-    fn print_optional_arrow_type_list<I: Iterator>(&self, types: &I) {
-        if types.count() > 0 {
-            self.print_arrow_type_list(types);
+    fn print_optional_arrow_type_list(&mut self, types: &[Arc<dyn Type>])
+        -> fmt::Result
+    {
+        if types.is_empty() {
+            return Ok(());
         }
+        self.print_arrow_type_list(types)
     }
-    
-    fn print_arrow_type_list<I: Iterator>(&self, types: &I) {
-        let os = self.stream() << " -> ";
 
-        let wrapped = !has_single_element(types) ||
-                    matches!(types.next(), FunctionType);
+    fn print_arrow_type_list(&mut self, types: &[Arc<dyn Type>])
+        -> fmt::Result
+    {
+        self.write_str(" -> ")?;
+        let wrapped = types.len() != 1
+            || types[0].downcast_ref::<FunctionType>().is_some();
         if wrapped {
-            os << '(';
+            self.write_char('(')?;
         }
-        interleave_comma(types, *this);
+        self.print_type_list(types)?;
         if wrapped {
-            os << ')';
+            self.write_char(')')?;
         }
+        Ok(())
     }
 
     /// Print the two given type ranges in a functional form.
-    fn print_functional_type<
-        I: Iterator,
-        O: Iterator
-    >(&self, inputs: &I, outputs: &O) {
-        let os = self.stream();
-        os << '(';
-        interleave_comma(inputs, *self);
-        os << ')';
-        self.print_arrow_type_list(outputs);
-    }
-
-    // private
-
-    // /**
-    // Print the alias for the given attribute, return failure if no alias could be printed.
-    // */
-    // virtual Result<(), Box<dyn std::error::Error>> print_alias(attribute: Attribute);
-
-    // /**
-    // Print the alias for the given type, return failure if no alias could be printed.
-    // */
-    // virtual Result<(), Box<dyn std::error::Error>> print_alias(r#type: Type);
+    fn print_functional_type(
+        &mut self,
+        inputs: &[Arc<dyn Type>],
+        outputs: &[Arc<dyn Type>]
+    ) -> fmt::Result {
+        self.write_char('(')?;
+        self.print_type_list(inputs)?;
+        self.write_char(')')?;
+        self.print_arrow_type_list(outputs)
+    }
+
+    /// Print the given types separated by commas.
+    fn print_type_list(&mut self, types: &[Arc<dyn Type>]) -> fmt::Result {
+        for (index, r#type) in types.iter().enumerate() {
+            if index != 0 {
+                self.write_str(", ")?;
+            }
+            self.print_type(r#type.as_ref())?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns true if `name` can be printed as a bare identifier, i.e. it matches `(letter|[_]) (letter|digit|[_$.])*`.
+pub fn is_bare_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) if first.is_ascii_alphabetic() || first == '_' => {},
+        _ => return false
+    }
+    chars.all(|char| char.is_ascii_alphanumeric() || matches!(char, '_' | '$' | '.'))
+}
+
+/// Print `string` surrounded by quotes, escaping quotes, backslashes and non-printable characters as `\XX` hex pairs.
+pub fn print_escaped_string<W: Write + ?Sized>(output: &mut W, string: &str)
+    -> fmt::Result
+{
+    output.write_char('"')?;
+    for byte in string.bytes() {
+        match byte {
+            b'"' | b'\\' => write!(output, "\\{}", byte as char)?,
+            0x20..=0x7E => output.write_char(byte as char)?,
+            _ => write!(output, "\\{:02X}", byte)?
+        }
+    }
+    output.write_char('"')
 }
 
 /// This enum describes the different kinds of elision for the type of an attribute when printing it.
@@ -284,16 +268,214 @@ pub struct AliasState {
 This is a pure-virtual base class that exposes the asmprinter hooks necessary to implement a custom print() method.
 */
 pub trait OpAsmPrinter: AsmPrinter {
+    /// Return the arena holding the operation being printed, so that custom printers can inspect its operands, blocks and regions.
+    fn arena(&self) -> &IRArena;
+
+    /// Print a newline and indent the printer to the start of the current operation.
+    fn print_new_line(&mut self) -> fmt::Result;
+
+    /// Increase indentation.
+    fn increase_indent(&mut self);
+
+    /// Decrease indentation.
+    fn decrease_indent(&mut self);
+
+    /// Print the SSA name of `value`, e.g. `%0`, `%arg1` or `%2#1`.
+    fn print_operand(&mut self, value: Value) -> fmt::Result;
+
+    /// Print a comma separated list of operands.
+    fn print_operands(&mut self, values: &[Value]) -> fmt::Result {
+        for (index, value) in values.iter().enumerate() {
+            if index != 0 {
+                self.write_str(", ")?;
+            }
+            self.print_operand(*value)?;
+        }
+        Ok(())
+    }
+
+    /// Print the label of the given successor block, e.g. `^bb1`.
+    fn print_successor(&mut self, successor: BlockId) -> fmt::Result;
+
+    /// Print a block argument in the usual format of: `%ssaName : type {attr1=42} loc("here")` where location printing is controlled by the standard internal option.
+    fn print_region_argument(
+        &mut self,
+        input: Input,
+        attributes: &[NamedAttribute],
+        omit_type: bool
+    ) -> fmt::Result;
 
+    /**
+    If the specified operation has attributes, print out an attribute dictionary with their values. `elided_attrs` allows the client to ignore specific well known attributes, commonly used if the attribute value is printed some other way (like as a fixed operand).
+    */
+    fn print_optional_attr_dict(
+        &mut self,
+        attributes: &[NamedAttribute],
+        elided_attrs: &[&str]
+    ) -> fmt::Result;
+
+    /// If the specified operation has attributes, print out an attribute dictionary prefixed with `attributes`.
+    fn print_optional_attr_dict_with_keyword(
+        &mut self,
+        attributes: &[NamedAttribute],
+        elided_attrs: &[&str]
+    ) -> fmt::Result;
+
+    /**
+    Prints a region. If `print_entry_block_args` is false, the arguments of the entry block are not printed. If `print_block_terminators` is false, the terminator operation of each block is not printed. If `print_empty_block` is true, an empty entry block is printed anyway.
+    */
+    fn print_region(
+        &mut self,
+        region: RegionId,
+        print_entry_block_args: bool,
+        print_block_terminators: bool,
+        print_empty_block: bool
+    ) -> fmt::Result;
+
+    /// Print the entire operation with the default generic assembly form. If `print_op_name` is true, then the operation name is printed (the default) otherwise it is omitted and the print will start with the operand list.
+    fn print_generic_op(&mut self, operation: &Operation, print_op_name: bool)
+        -> fmt::Result;
 }
 
 // L456
 /// This base class exposes generic asm parser hooks, usable across the various derived parsers.
 pub trait AsmParser {
+    /// Return the context in which attributes and types are created.
+    fn context(&self) -> &MLIRContext;
+
+    /// Return the location of the next token.
+    fn current_location(&self) -> SMLoc;
+
+    /// Return the location of the original name token.
+    fn name_location(&self) -> SMLoc;
+
+    /// Emit an error at the given location, returning it to be propagated.
+    fn emit_error(&self, location: SMLoc, message: String) -> Box<dyn Error>;
+
+    /* Token Parsing */
+
+    /// Consume the next token if it has the given kind, returning whether it did.
+    fn parse_optional_token(&mut self, kind: TokenKind) -> bool;
+
+    /// Consume the next token, which must have the given kind.
+    fn parse_token(&mut self, kind: TokenKind) -> ParseResult {
+        if self.parse_optional_token(kind) {
+            return Ok(());
+        }
+        Err(self.emit_error(
+            self.current_location(),
+            format!("expected '{}'", kind.spelling())))
+    }
+
+    /// Parse a `<` token.
+    fn parse_less(&mut self) -> ParseResult {
+        self.parse_token(TokenKind::Less)
+    }
+
+    /// Parse a `<` token if present.
+    fn parse_optional_less(&mut self) -> bool {
+        self.parse_optional_token(TokenKind::Less)
+    }
+
+    /// Parse a `>` token.
+    fn parse_greater(&mut self) -> ParseResult {
+        self.parse_token(TokenKind::Greater)
+    }
+
+    /// Parse a `>` token if present.
+    fn parse_optional_greater(&mut self) -> bool {
+        self.parse_optional_token(TokenKind::Greater)
+    }
+
+    /// Parse a `(` token.
+    fn parse_l_paren(&mut self) -> ParseResult {
+        self.parse_token(TokenKind::LParen)
+    }
+
+    /// Parse a `)` token.
+    fn parse_r_paren(&mut self) -> ParseResult {
+        self.parse_token(TokenKind::RParen)
+    }
+
+    /// Parse a `)` token if present.
+    fn parse_optional_r_paren(&mut self) -> bool {
+        self.parse_optional_token(TokenKind::RParen)
+    }
+
+    /// Parse a `,` token.
+    fn parse_comma(&mut self) -> ParseResult {
+        self.parse_token(TokenKind::Comma)
+    }
+
+    /// Parse a `,` token if present.
+    fn parse_optional_comma(&mut self) -> bool {
+        self.parse_optional_token(TokenKind::Comma)
+    }
+
+    /// Parse the given keyword if present.
+    fn parse_optional_keyword(&mut self, keyword: &str) -> bool;
+
+    /// Parse the given keyword.
+    fn parse_keyword(&mut self, keyword: &str) -> ParseResult {
+        if self.parse_optional_keyword(keyword) {
+            return Ok(());
+        }
+        Err(self.emit_error(
+            self.current_location(), format!("expected '{}'", keyword)))
+    }
+
+    /// Parse a keyword, whichever it is, if present.
+    fn parse_optional_any_keyword(&mut self) -> Option<String>;
+
+    /// Parse a quoted string token if present.
+    fn parse_optional_string(&mut self) -> Option<String>;
+
+    /// Parse a quoted string token.
+    fn parse_string(&mut self) -> ParseResult<String> {
+        self.parse_optional_string().ok_or_else(||
+            self.emit_error(self.current_location(), "expected string".into()))
+    }
+
+    /// Parse an integer value if present. The returned integer is wide enough to hold the literal as a signed value.
+    fn parse_optional_integer(&mut self) -> Option<ParseResult<APInt>>;
+
+    /// Parse an integer value.
+    fn parse_integer(&mut self) -> ParseResult<APInt> {
+        self.parse_optional_integer().unwrap_or_else(||
+            Err(self.emit_error(
+                self.current_location(), "expected integer value".into())))
+    }
+
+    /* Attribute Parsing */
+
+    /// Parse an arbitrary attribute.
+    fn parse_attribute(&mut self) -> ParseResult<Arc<dyn Attribute>>;
+
+    /* Type Parsing */
+
+    /// Parse a type.
+    fn parse_type(&mut self) -> ParseResult<Arc<dyn Type>>;
+
+    /// Parse a type if present.
+    fn parse_optional_type(&mut self) -> Option<ParseResult<Arc<dyn Type>>>;
+
+    /// Parse a comma separated list of zero or more types.
+    fn parse_optional_type_list(&mut self) -> ParseResult<Vec<Arc<dyn Type>>> {
+        let mut types = Vec::new();
+        let Some(r#type) = self.parse_optional_type() else {
+            return Ok(types);
+        };
+        types.push(r#type?);
+        while self.parse_optional_comma() {
+            types.push(self.parse_type()?);
+        }
+        Ok(types)
+    }
 }
 
 /// These are the supported delimiters around operand lists and region argument lists, used by parseOperandList.
-enum Delimiter {
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Delimiter {
     /// Zero or more operands with no delimiters.
     None,
     /// Parens surrounding zero or more operands.
@@ -343,13 +525,116 @@ For example, consider an op like this:
 
 The `%x = load` tokens are already parsed and therefore invisible to the custom op parser.  This can be supported by calling `parse_operand_list` to parse the %p, then calling `parse_operand_list` with a `square_delimiter` to parse the indices, then calling `parseColonTypeList` to parse the result type.
 */
-pub struct OpAsmParser {
+pub trait OpAsmParser<'a>: AsmParser {
+    /* Operand Parsing */
+
+    /// Parse a single SSA value operand name along with a result number, e.g. `%x#1`, if present.
+    fn parse_optional_operand(&mut self)
+        -> Option<ParseResult<UnresolvedOperand<'a>>>;
+
+    /// Parse a single SSA value operand name along with a result number.
+    fn parse_operand(&mut self) -> ParseResult<UnresolvedOperand<'a>> {
+        match self.parse_optional_operand() {
+            Some(operand) => operand,
+            None => Err(self.emit_error(
+                self.current_location(), "expected SSA operand".into()))
+        }
+    }
 
+    /// Parse a comma separated list of zero or more operands.
+    fn parse_operand_list(&mut self) -> ParseResult<Vec<UnresolvedOperand<'a>>> {
+        let mut operands = Vec::new();
+        let Some(operand) = self.parse_optional_operand() else {
+            return Ok(operands);
+        };
+        operands.push(operand?);
+        while self.parse_optional_comma() {
+            operands.push(self.parse_operand()?);
+        }
+        Ok(operands)
+    }
+
+    /// Resolve an operand to an SSA value of the given type, emitting an error on failure.
+    fn resolve_operand(
+        &mut self,
+        operand: UnresolvedOperand<'a>,
+        r#type: Arc<dyn Type>
+    ) -> ParseResult<Value>;
+
+    /// Resolve a list of operands to SSA values of the corresponding types, appending them to `result`. `location` is where an error about mismatching lengths is reported.
+    fn resolve_operands(
+        &mut self,
+        operands: &[UnresolvedOperand<'a>],
+        types: &[Arc<dyn Type>],
+        location: SMLoc,
+        result: &mut Vec<Value>
+    ) -> ParseResult {
+        if operands.len() != types.len() {
+            return Err(self.emit_error(location, format!(
+                "{} operands present, but expected {}",
+                operands.len(), types.len())));
+        }
+        for (operand, r#type) in operands.iter().zip(types) {
+            result.push(self.resolve_operand(*operand, r#type.clone())?);
+        }
+        Ok(())
+    }
+
+    /* Attribute Parsing */
+
+    /// Parse a named dictionary into `attributes` if it is present.
+    fn parse_optional_attr_dict(&mut self, attributes: &mut Vec<NamedAttribute>)
+        -> ParseResult;
+
+    /// Parse a named dictionary into `attributes` if the `attributes` keyword is present.
+    fn parse_optional_attr_dict_with_keyword(
+        &mut self,
+        attributes: &mut Vec<NamedAttribute>
+    ) -> ParseResult {
+        if self.parse_optional_keyword("attributes") {
+            return self.parse_optional_attr_dict(attributes);
+        }
+        Ok(())
+    }
+
+    /* Region Parsing */
+
+    /// Parse a region that takes no arguments. The region is created without a parent and attached to the operation being parsed when it is created.
+    fn parse_region(&mut self) -> ParseResult<RegionId>;
+
+    /* Successor Parsing */
+
+    /// Parse a single operation successor, e.g. `^bb1`.
+    fn parse_successor(&mut self) -> ParseResult<BlockId>;
 }
 
 // L1546
-pub struct OpAsmDialectInterface {
+/// Dialect hooks used by the printer. The printer asks the dialect owning each resource referenced during printing to serialise it into the `dialect_resources` section.
+pub trait OpAsmDialectInterface {
+    /// Return the dialect this interface is registered to.
+    fn dialect(&self) -> &str;
 
+    /**
+    Hook for building resources to use during printing. `referenced` contains the handles of the resources of this dialect that were referenced within `top_level_op`, in the order they were first referenced.
+    */
+    fn build_resources(
+        &self,
+        top_level_op: &Operation,
+        referenced: &[AsmDialectResourceHandle],
+        builder: &mut AsmResourceBuilder
+    ) {
+    }
+
+    /// Declare a resource with the given key, returning a handle to use for any references of this resource key within the IR during parsing. The result of `key` on the returned handle is permitted to be different than `key`. Returns None if the dialect does not support resources.
+    fn declare_resource(&self, key: &str) -> Option<AsmDialectResourceHandle> {
+        None
+    }
+
+    /// Hook for parsing resource entries of this dialect from the `dialect_resources` section. Returns failure if the entry was not valid, or could otherwise not be processed correctly.
+    fn parse_resource(&self, entry: &AsmParsedResourceEntry) -> LogicalResult {
+        Err(format!("unknown 'resource' key '{}' for dialect '{}'",
+            entry.key(), self.dialect()).into())
+    }
 }
 
 /// Holds the result of `getAlias` hook call.
@@ -10,14 +10,18 @@ use core::{
     any::TypeId,
     cell::Cell,
     cmp::PartialEq,
+    fmt,
     mem::{drop, size_of},
     slice::from_raw_parts_mut
 };
+use std::sync::Arc;
 
 use crate::{
     mlir::{
+        asm_parser::parser::ParseResult,
         execution_engine::c_runner_utils::next_power_of2,
         ir::{
+            arena::{BlockId, IRArena, RegionId},
             attribute::{Attribute, NamedAttribute},
             block::{
                 Block,
@@ -29,9 +33,11 @@ use crate::{
             mlir_context::MLIRContext,
             operation::{
                 Operation,
-                definition::FoldResult
+                definition::{FoldResult, Op},
+                implementation::{OpAsmParser, OpAsmPrinter}
             },
             region::Region,
+            region_kind_interface::RegionKind,
             type_range,
             r#type::Type,
             value::{Operand, Value},
@@ -63,52 +69,202 @@ type FoldHookFn
     = fn(*mut Operation, &[Attribute], &SmallVector<FoldResult>)
         -> LogicalResult; // unique_function< const>;
 type HasTraitFn = fn(TypeId) -> bool; // unique_function
-type ParseAssemblyFn
-    = fn(&OpAsmParser, &OperationState) -> LogicalResult;  // unique_function
+pub type ParseAssemblyFn
+    = for<'a> fn(&mut dyn OpAsmParser<'a>, &mut OperationState) -> ParseResult;  // unique_function
 // Note: RegisteredOperationName is passed as reference here as the derived
 // class is defined below.
 type PopulateDefaultAttrsFn
     = fn(&RegisteredOperationName, &NamedAttrList);  // unique_function
-type PrintAssemblyFn
-    = fn(*mut Operation, &OpAsmPrinter, &str);  // unique_function
-type VerifyInvariantsFn
-    = fn(*mut Operation) -> LogicalResult;  // unique_function
-type VerifyRegionInvariantsFn
-    = fn(*mut Operation) -> LogicalResult;  // unique_function
-
+pub type PrintAssemblyFn
+    = fn(&Operation, &mut dyn OpAsmPrinter) -> fmt::Result;  // unique_function
+pub type RegionKindFn = fn(usize) -> RegionKind;
+pub type VerifyInvariantsFn
+    = fn(&IRArena, &Operation) -> LogicalResult;  // unique_function
+pub type VerifyRegionInvariantsFn
+    = fn(&IRArena, &Operation) -> LogicalResult;  // unique_function
+
+#[derive(Clone)]
 pub struct OperationName {
+    /// The name of the operation.
+    name: StringAttribute,
+    /// The description of the operation, if it is registered.
+    info: Option<Arc<RegisteredOperationName>>
+}
+
+impl OperationName {
+    /// Create the name of an unregistered operation.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: StringAttribute::new(name), info: None }
+    }
+
+    /// Create the name of an operation registered with `info`.
+    pub fn registered(info: Arc<RegisteredOperationName>) -> Self {
+        Self { name: info.name.clone(), info: Some(info) }
+    }
+
+    /// Return the name of this operation, e.g. `arith.addi`.
+    pub fn as_str(&self) -> &str {
+        self.name.value()
+    }
+
+    /// Return the name of the dialect this operation is registered to, i.e. the prefix of the name up to the first `.`.
+    pub fn dialect_namespace(&self) -> &str {
+        self.as_str().split_once('.').map_or("", |(namespace, _)| namespace)
+    }
+
+    /// Return the operation name with the dialect namespace stripped.
+    pub fn strip_dialect(&self) -> &str {
+        self.as_str().split_once('.').map_or(self.as_str(), |(_, name)| name)
+    }
+
+    /// Returns the description of this operation, if it is registered.
+    pub fn registered_info(&self) -> Option<&RegisteredOperationName> {
+        self.info.as_deref()
+    }
+
+    /// Returns true if this operation has a registered description.
+    pub fn is_registered(&self) -> bool {
+        self.info.is_some()
+    }
+
+    /// Return the dialect this operation is registered to, if any.
+    pub fn dialect(&self) -> Option<&'static dyn Dialect> {
+        self.info.as_ref().and_then(|info| info.dialect)
+    }
+
+    /// Returns true if the operation was registered with a particular trait, e.g. has_trait<IsTerminator>().
+    pub fn has_trait<Trait: ?Sized + 'static>(&self) -> bool {
+        self.info.as_ref()
+            .map_or(false, |info| (info.has_trait_fn)(TypeId::of::<Trait>()))
+    }
+
+    /// Returns true if the operation *might* have the provided trait, i.e. it is unregistered or it was registered with the trait.
+    pub fn might_have_trait<Trait: ?Sized + 'static>(&self) -> bool {
+        self.info.as_ref()
+            .map_or(true, |info| (info.has_trait_fn)(TypeId::of::<Trait>()))
+    }
+
+    /// Returns true if this operation is registered with a custom assembly form.
+    pub fn has_custom_assembly_form(&self) -> bool {
+        self.info.as_ref().map_or(false, |info| info.print_assembly_fn.is_some())
+    }
+
+    /// Return the names of the attributes this operation is registered with.
+    pub fn attribute_names(&self) -> &'static [&'static str] {
+        self.info.as_ref().map_or(&[], |info| info.attribute_names)
+    }
+
+    /// Verify the invariants of `operation` registered for it, e.g. its number of operands and their types. Unregistered operations are always valid.
+    pub fn verify_invariants(&self, arena: &IRArena, operation: &Operation)
+        -> LogicalResult
+    {
+        match &self.info {
+            Some(info) => (info.verify_invariants_fn)(arena, operation),
+            None => Ok(())
+        }
+    }
+
+    /// Verify the invariants of `operation` that depend on the operations nested in its regions. This runs after the regions are verified.
+    pub fn verify_region_invariants(&self, arena: &IRArena, operation: &Operation)
+        -> LogicalResult
+    {
+        match &self.info {
+            Some(info) => (info.verify_region_invariants_fn)(arena, operation),
+            None => Ok(())
+        }
+    }
+
+    /// Return the kind of the region `index` of operations with this name. Regions of unregistered operations are assumed to be SSACFG regions.
+    pub fn region_kind(&self, index: usize) -> RegionKind {
+        match &self.info {
+            Some(info) => (info.region_kind_fn)(index),
+            None => RegionKind::SSACFG
+        }
+    }
+
+    /// Print `operation` with the custom assembly form registered for it. Returns None if the operation has no custom assembly form.
+    pub fn print_assembly(
+        &self,
+        operation: &Operation,
+        printer: &mut dyn OpAsmPrinter
+    ) -> Option<fmt::Result> {
+        self.info.as_ref()
+            .and_then(|info| info.print_assembly_fn)
+            .map(|print| print(operation, printer))
+    }
+
+    /// Return the hook parsing the custom assembly form of this operation, if it has one.
+    pub fn parse_assembly_fn(&self) -> Option<ParseAssemblyFn> {
+        self.info.as_ref().and_then(|info| info.parse_assembly_fn)
+    }
+}
+
+impl PartialEq for OperationName {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+/**
+This is a 'type erased' representation of a registered operation. This should only be used by things like the AsmPrinter and other things that need to be parameterized by generic operation hooks. Most user code should use the concrete operation types.
+*/
+pub struct RegisteredOperationName {
     /// The name of the operation.
     name: StringAttribute,
     /// This is the dialect that this operation belongs to.
-    dialect: &'static dyn Dialect,
+    dialect: Option<&'static dyn Dialect>,
 
     /// The unique identifier of the derived Op class.
     type_id: TypeId,
 
-    /// A map of interfaces that were registered to this operation.
-    interface_map: InterfaceMap,
-
     /// Internal callback hooks provided by the op implementation.
-    fold_hook_fn: FoldHookFn,
-    get_canonicalisation_patterns_fn: GetCanonicalisationPatternsFn,
     has_trait_fn: HasTraitFn,
-    parse_assembly_fn: ParseAssemblyFn,
-    populate_default_attrs_fn: PopulateDefaultAttrsFn,
-    print_assembly_fn: PrintAssemblyFn,
+    /// The parser of the custom assembly form, if the operation has one.
+    parse_assembly_fn: Option<ParseAssemblyFn>,
+    /// The printer of the custom assembly form, if the operation has one.
+    print_assembly_fn: Option<PrintAssemblyFn>,
     verify_invariants_fn: VerifyInvariantsFn,
     verify_region_invariants_fn: VerifyRegionInvariantsFn,
+    region_kind_fn: RegionKindFn,
 
     /**
-    A list of attribute names registered to this operation in StringAttribute form. This allows for operation classes to use StringAttribute for attribute lookup/creation/etc., as opposed to raw strings.
+    A list of attribute names registered to this operation. This allows for operation classes to look attributes up by name without building the names again.
     */
-    attribute_names: &'static [StringAttribute]
+    attribute_names: &'static [&'static str]
 }
 
-/**
-This is a 'type erased' representation of a registered operation. This should only be used by things like the AsmPrinter and other things that need to be parameterized by generic operation hooks. Most user code should use the concrete operation types.
-*/
-pub struct RegisteredOperationName {
+impl RegisteredOperationName {
+    /// Describe the operation `T`, which belongs to `dialect`.
+    pub fn of<T: Op>(dialect: Option<&'static dyn Dialect>) -> Self {
+        let (parse_assembly_fn, print_assembly_fn): (Option<ParseAssemblyFn>, Option<PrintAssemblyFn>)
+            = if T::HAS_CUSTOM_ASSEMBLY_FORM {
+                (Some(T::parse), Some(T::print))
+            } else {
+                (None, None)
+            };
+        Self {
+            name: StringAttribute::new(T::OPERATION_NAME),
+            dialect,
+            type_id: TypeId::of::<T>(),
+            has_trait_fn: T::has_trait,
+            parse_assembly_fn,
+            print_assembly_fn,
+            verify_invariants_fn: T::verify_invariants,
+            verify_region_invariants_fn: T::verify_region_invariants,
+            region_kind_fn: T::region_kind,
+            attribute_names: T::attribute_names()
+        }
+    }
 
+    /// Return the name of this operation.
+    pub fn name(&self) -> &str {
+        self.name.value()
+    }
+
+    /// Return the unique identifier of the concrete operation type.
+    pub fn type_id(&self) -> TypeId {
+        self.type_id
+    }
 }
 
 // ----------------------------------------------------------------------
@@ -140,69 +296,59 @@ pub struct NamedAttrList {
 This represents an operation in an abstracted form, suitable for use with the builder APIs. This object is a large and heavy weight object meant to be used as a temporary object on the stack. It is generally unwise to put this in a collection.
 */
 pub struct OperationState {
-    location: Location,
-    name: OperationName,
-    inputs: SmallVector<[Value; 4]>,
+    pub location: Location,
+    pub name: OperationName,
+    pub inputs: Vec<Value>,
     /// Types of the results of this operation.
-    types: SmallVector<[dyn Type; 4]>,
-    attributes: NamedAttrList,
-    /// Successors of this operation and their respective operands.
-    successors: SmallVector<[*mut Block; 1]>>,
+    pub types: Vec<Arc<dyn Type>>,
+    pub attributes: Vec<NamedAttribute>,
+    /// Successors of this operation.
+    pub successors: Vec<BlockId>,
     /// Regions that the operation will hold.
-    regions: SmallVector<[Region; 1]>
+    pub regions: Vec<RegionId>
 }
 
 impl OperationState {
-    pub fn add_inputs(&self, inputs: ValueRange) {
-        self.inputs.append(input);
+    pub fn new(location: Location, name: OperationName) -> Self {
+        Self {
+            location,
+            name,
+            inputs: Vec::new(),
+            types: Vec::new(),
+            attributes: Vec::new(),
+            successors: Vec::new(),
+            regions: Vec::new()
+        }
     }
 
-    pub fn add_types(&self, types: &[impl Type]) {
-        self.types.append(types);
+    pub fn add_inputs(&mut self, inputs: impl IntoIterator<Item = Value>) {
+        self.inputs.extend(inputs);
     }
 
-    /// Add an attribute with the specified name.
-    pub fn add_attribute(&self, name: &str, attribute: impl Attribute) {
-        self.add_attribute(StringAttribute::get(self.context(), name), attribute);
+    pub fn add_types(&mut self, types: impl IntoIterator<Item = Arc<dyn Type>>) {
+        self.types.extend(types);
     }
 
     /// Add an attribute with the specified name.
-    pub fn add_attribute(&self, name: StringAttribute, attribute: impl Attribute) {
-        self.attributes.append(name, attribute);
+    pub fn add_attribute(&mut self, name: &str, attribute: Arc<dyn Attribute>) {
+        self.attributes.push(
+            NamedAttribute::new(StringAttribute::new(name), attribute));
     }
 
     /// Add an array of named attributes.
-    pub fn add_attributes(&self, attributes: &[NamedAttribute]) {
-        self.attributes.append(attributes);
+    pub fn add_attributes(&mut self, attributes: impl IntoIterator<Item = NamedAttribute>) {
+        self.attributes.extend(attributes);
     }
 
-    pub fn add_successors(&self, successor: &Block) {
+    pub fn add_successor(&mut self, successor: BlockId) {
         self.successors.push(successor);
     }
-    pub fn add_successors(&self, new_successors: BlockRange);
-
-    /**
-    Create a region that should be attached to the operation. These regions can be filled in immediately without waiting for Operation to be created.  When it is, the region bodies will be transferred.
-    */
-    pub fn add_region(&self) -> *mut Region {
-
-    }
 
     /**
-    Take a region that should be attached to the Operation.  The body of the region will be transferred when the Operation is constructed.  If the region is null, a new empty region will be attached to the Operation.
+    Take a region that should be attached to the Operation. The region must not have a parent yet; it is moved under the operation when it is created.
     */
-    pub fn add_region(&self, region: &&std::unique_ptr<Region>) {
-        
-    }
-
-    /**
-    Take ownership of a set of regions that should be attached to the Operation.
-    */
-    pub fn add_regions(&self, regions: &mut [Region]);
-
-    /// Get the context held by this operation state.
-    pub const fn context(&self) -> Option<MLIRContext> {
-        self.location.context()
+    pub fn add_region(&mut self, region: RegionId) {
+        self.regions.push(region);
     }
 }
 
@@ -400,6 +546,7 @@ impl InputStorage {
 /**
 Set of flags used to control the behaviour of the various IR print methods (e.g. Operation::Print).
 */
+#[derive(Clone, Debug, Default)]
 pub struct PrintingFlags {
     /**
     Elide large elements attributes if the number of elements is larger than the upper limit.
@@ -424,63 +571,97 @@ pub struct PrintingFlags {
 }
 
 impl PrintingFlags {
-    // /**
-    // Enables the elision of large elements attributes by printing a lexically
-    // valid but otherwise meaningless form instead of the element data. The
-    // `largeElementLimit` is used to configure what is considered to be a
-    // "large" ElementsAttr by providing an upper limit to the number of
-    // elements.
-    // */
-    // pub fnelide_large_elements_attrs(int64_t largeElementLimit = 16) -> &Self;
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-    // /**
-    // Enable or disable printing of debug information (based on `enable`). If
-    // 'prettyForm' is set to true, debug information is printed in a more
-    // readable 'pretty' form. Note: The IR generated with 'prettyForm' is not
-    // parsable.
-    // */
-    // pub fn enable_debug_info(bool enable = true, bool prettyForm = false) -> &Self;
+    /**
+    Enables the elision of large elements attributes by printing a lexically valid but otherwise meaningless form instead of the element data. The `large_element_limit` is used to configure what is considered to be a "large" ElementsAttr by providing an upper limit to the number of elements.
+    */
+    pub fn elide_large_elements_attrs(mut self, large_element_limit: i64)
+        -> Self
+    {
+        self.elements_attr_element_limit = Some(large_element_limit);
+        self
+    }
 
-    // /// Always print operations in the generic form.
-    // pub fn print_generic_op_form() -> &Self;
+    /**
+    Enable or disable printing of debug information (based on `enable`). If `pretty_form` is set to true, debug information is printed in a more readable 'pretty' form. Note: The IR generated with `pretty_form` is not parsable.
+    */
+    pub fn enable_debug_info(mut self, enable: bool, pretty_form: bool)
+        -> Self
+    {
+        self.print_debug_info_flag = enable;
+        self.print_debug_info_pretty_form_flag = pretty_form;
+        self
+    }
 
-    // /// Do not verify the operation when using custom operation printers.
-    // pub fn assume_verified() -> &Self;
+    /// Always print operations in the generic form.
+    pub fn print_generic_op_form(mut self) -> Self {
+        self.print_generic_op_form_flag = true;
+        self
+    }
 
-    // /**
-    // Use local scope when printing the operation. This allows for using the
-    // printer in a more localized and thread-safe setting, but may not
-    // necessarily be identical to what the IR will look like when dumping
-    // the full module.
-    // */
-    // pub fn use_local_scope() -> &Self;
+    /// Do not verify the operation when using custom operation printers.
+    pub fn assume_verified(mut self) -> Self {
+        self.assume_verified_flag = true;
+        self
+    }
 
-    // /// Print users of values as comments.
-    // pub fn print_value_users() -> &Self;
+    /**
+    Use local scope when printing the operation. This allows for using the printer in a more localized and thread-safe setting, but may not necessarily be identical to what the IR will look like when dumping the full module.
+    */
+    pub fn use_local_scope(mut self) -> Self {
+        self.print_local_scope = true;
+        self
+    }
 
-    // /// Return if the given ElementsAttr should be elided.
-    // pub const fn should_elide_elements_attr(attr: ElementsAttr) -> bool;
+    /// Print users of values as comments.
+    pub fn print_value_users(mut self) -> Self {
+        self.print_value_users_flag = true;
+        self
+    }
 
-    // /// Return the size limit for printing large ElementsAttr.
-    // pub const fn get_large_elements_attr_limit() -> Option<i64>;
+    /// Return if an elements attribute with `num_elements` elements should be elided.
+    pub fn should_elide_elements_attr(&self, num_elements: i64) -> bool {
+        self.elements_attr_element_limit
+            .map_or(false, |limit| num_elements > limit)
+    }
 
-    // /// Return if debug information should be printed.
-    // pub const fn should_print_debug_info() -> bool;
+    /// Return the size limit for printing large ElementsAttr.
+    pub fn large_elements_attr_limit(&self) -> Option<i64> {
+        self.elements_attr_element_limit
+    }
 
-    // /// Return if debug information should be printed in the pretty form.
-    // pub const fn should_print_debug_info_pretty_form() -> bool;
+    /// Return if debug information should be printed.
+    pub fn should_print_debug_info(&self) -> bool {
+        self.print_debug_info_flag
+    }
 
-    // /// Return if operations should be printed in the generic form.
-    // pub const fn should_print_generic_op_form() -> bool;
+    /// Return if debug information should be printed in the pretty form.
+    pub fn should_print_debug_info_pretty_form(&self) -> bool {
+        self.print_debug_info_pretty_form_flag
+    }
+
+    /// Return if operations should be printed in the generic form.
+    pub fn should_print_generic_op_form(&self) -> bool {
+        self.print_generic_op_form_flag
+    }
 
-    // /// Return if operation verification should be skipped.
-    // pub const fn should_assume_verified() -> bool;
+    /// Return if operation verification should be skipped.
+    pub fn should_assume_verified(&self) -> bool {
+        self.assume_verified_flag
+    }
 
-    // /// Return if the printer should use local scope when dumping the IR.
-    // pub const fn should_use_local_scope() -> bool;
+    /// Return if the printer should use local scope when dumping the IR.
+    pub fn should_use_local_scope(&self) -> bool {
+        self.print_local_scope
+    }
 
-    // /// Return if the printer should print users of values.
-    // pub const fn should_print_value_users() -> bool;
+    /// Return if the printer should print users of values.
+    pub fn should_print_value_users(&self) -> bool {
+        self.print_value_users_flag
+    }
 }
 
 /**
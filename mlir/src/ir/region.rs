@@ -5,54 +5,52 @@
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/Region.h>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/Region.cpp>
 
-use core::{
-    default::Default,
-    ops::Drop,
-    ptr::null,
-    slice::IterMut
-};
-
-use crate::{
-    mlir::ir::{
-        block::Block,
-        block_and_value_mapping,
-        location::Location,
-        mlir_context::MLIRContext,
-        operation::Operation,
-        r#type::Type,
-        type_range::ValueTypeRange,
-        value::Input
-    },
-    llvm::adt::ilist::IList
+use std::sync::Arc;
+
+use crate::ir::{
+    arena::{ArenaResult, BlockId, IRArena, OpId, RegionId},
+    block_and_value_mapping,
+    location::Location,
+    mlir_context::MLIRContext,
+    r#type::Type,
+    value::Input
 };
 
 /// This class contains a list of basic blocks and a link to the parent operation it is attached to.
-#[derive(Default)]
 pub struct Region {
-    blocks: IList<Block>,
+    /// The handle of this region within the arena of its context.
+    id: RegionId,
+
+    pub(crate) blocks: Vec<BlockId>,
 
     /// This is the object we are part of.
-    container: *mut Operation
+    container: Option<OpId>
 }
 
 impl Region {
-    pub fn new(container: *mut Operation) -> Self {
-        Self { container, ..Default::default() }
+    /// Create an empty region. Regions are created through `IRArena::create_region`, which assigns their handle.
+    pub(crate) fn new(id: RegionId, container: Option<OpId>) -> Self {
+        Self { id, blocks: Vec::new(), container }
+    }
+
+    /// Returns the handle of this region.
+    pub fn id(&self) -> RegionId {
+        self.id
     }
 
     /// Return the context this region is inserted in.  The region must have a valid parent container.
-    pub fn context(&self) -> Option<MLIRContext> {
+    pub fn context(&self, arena: &IRArena) -> ArenaResult<*mut MLIRContext> {
         match self.container {
             None => panic!("Region is not attached to a container"),
-            Some(operation) => operation.context()
+            Some(operation) => Ok(arena.op(operation)?.context())
         }
     }
 
     /// Return a location for this region. This is the location attached to the parent container. The region must have a valid parent container.
-    pub fn location(&self) -> Location {
+    pub fn location(&self, arena: &IRArena) -> ArenaResult<Location> {
         match self.container {
             None => panic!("Region is not attached to a container"),
-            Some(operation) => operation.location()
+            Some(operation) => Ok(arena.op(operation)?.location())
         }
     }
 
@@ -60,32 +58,20 @@ impl Region {
     Block List Management
     */
 
-    pub fn blocks(&self) -> &IList<Block> {
-        &self.blocks
-    }
-
-    pub fn emplace_block(&self) -> &Block {
-        self.push_back(Block::new());
-        self.back()
+    pub fn blocks(&self) -> &[BlockId] {
+        self.blocks.as_slice()
     }
 
     pub fn is_empty(&self) -> bool {
         self.blocks.is_empty()
     }
 
-    pub fn push_back(&mut self, block: Block) {
-        self.blocks.push_back(block);
-    }
-
-    pub fn push_front(&mut self, block: Block) {
-        self.blocks.push_front(block);
+    pub fn back(&self) -> Option<BlockId> {
+        self.blocks.last().copied()
     }
 
-    pub fn back(&self) -> &Block {
-        self.blocks.back()
-    }
-    pub fn front(&self) -> &Block {
-        self.blocks.front()
+    pub fn front(&self) -> Option<BlockId> {
+        self.blocks.first().copied()
     }
 
     /// Return true if this region has exactly one block.
@@ -97,219 +83,125 @@ impl Region {
     Argument Handling
     */
 
-    pub fn arguments(&self) -> &mut [Input] {
-        if self.is_empty() {
-            &mut []
-        } else {
-            self.front().arguments()
+    /// Returns the inputs of the first block within the region.
+    pub fn inputs(&self, arena: &IRArena) -> ArenaResult<Vec<Input>> {
+        match self.front() {
+            None => Ok(Vec::new()),
+            Some(front) => Ok(arena.block(front)?.inputs().collect())
         }
     }
 
-    /// Returns the argument types of the first block within the region.
-    pub fn input_types(&self) -> ValueTypeRange<&mut [Input]> {
-        self.arguments().into()
-    }
-
-    /// Add one value to the argument list.
-    pub fn add_input(&mut self, r#type: impl Type, location: Location) -> Input
-    {
-        self.front().add_input(r#type, location)
-    }
-
-    /// Insert one value to the position in the argument list indicated by the given iterator. The existing arguments are shifted. The block is expected not to have predecessors.
-    pub fn insert_input(
-        &mut self, it: IterMut<Input>, r#type: impl Type, location: Location
-    ) -> Input {
-        self.front().insert_input(it, r#type, location);
-    }
-
-    // /// Add one argument to the argument list for each type specified in the list.
-    // /// `locs` contains the locations for each of the new arguments, and must be of equal size to `types`.
-    // pub fn add_inputs(&mut self, types: TypeRange, locations: &[Location]) -> iterator_range<args_iterator>;
-    
-    /// Add one value to the argument list at the specified position.
-    pub fn insert_input(
-        &mut self, index: usize, r#type: impl Type, location: Location
-    ) -> Input {
-        self.front().insert_input(index, r#type, location)
-    }
-
-    /// Erase the argument at 'index' and remove it from the argument list.
-    pub fn erase_argument(&mut self, index: usize) {
-        self.front().erase_argument(index);
+    /// Returns the input types of the first block within the region.
+    pub fn input_types(&self, arena: &IRArena) -> ArenaResult<Vec<Arc<dyn Type>>> {
+        match self.front() {
+            None => Ok(Vec::new()),
+            Some(front) => Ok(arena.block(front)?.input_types().cloned().collect())
+        }
     }
 
-    pub fn num_inputs(&self) -> usize {
-        self.arguments().len()
-    }
-    
-    pub fn argument(&self, index: usize) -> Input {
-        self.arguments()[index]
+    pub fn num_inputs(&self, arena: &IRArena) -> ArenaResult<usize> {
+        match self.front() {
+            None => Ok(0),
+            Some(front) => Ok(arena.block(front)?.num_inputs())
+        }
     }
 
-    /*
-    Operation List Utilities
-    */
-    
     /*
     Misc. Utilities
     */
-    
-    /// Return the region containing this region or nullptr if the region is attached to a top-level operation.
-    pub fn parent_region(&self) -> *mut Region {
+
+    /// Return the region containing this region or `None` if the region is attached to a top-level operation.
+    pub fn parent_region(&self, arena: &IRArena) -> ArenaResult<Option<RegionId>> {
+        match self.container {
+            None => Ok(None),
+            Some(operation) => arena.parent_region(operation)
+        }
     }
 
     /// Return the parent operation this region is attached to.
-    pub fn parent_operation(&self) -> *mut Operation {
+    pub fn parent_operation(&self) -> Option<OpId> {
         self.container
     }
-    
-//     /// Find the first parent operation of the given type, or nullptr if there is no ancestor operation.
-//     pub fn parent_of_type<Parent>(&self) -> Parent {
-//         auto *region = self;
-//         do {
-//             if (auto parent = dyn_cast_or_null<ParentT>(region->container)) {
-//                 return parent;
-//             }
-//         } while (region = region.parent_region());
-//         parent::new();
-//     }
 
-    // /// Return the number of this region in the parent operation.
-    // pub fn region_number(&self) -> unsigned {
-    //     // Regions are always stored consecutively, so use pointer subtraction to igure out what number this is.
-    //     self - &self.parent_operation().regions()[0];
-    // }
+    /// Return the number of this region in the parent operation.
+    pub fn region_number(&self, arena: &IRArena) -> ArenaResult<Option<usize>> {
+        match self.container {
+            None => Ok(None),
+            Some(operation) => Ok(arena.op(operation)?.regions().iter()
+                .position(|region| *region == self.id))
+        }
+    }
 
     /// Return true if this region is a proper ancestor of the `other` region.
-    pub fn is_proper_ancestor(&self, other: *mut Self) -> bool {
-        if self == other {
-            return false;
-        }
-  
-        while let Some(other) = other.parent_region() {
-            if self == other {
-                return true;
+    pub fn is_proper_ancestor(&self, arena: &IRArena, other: RegionId)
+    -> ArenaResult<bool>
+    {
+        let mut current = arena.region(other)?.parent_region(arena)?;
+        while let Some(region) = current {
+            if region == self.id {
+                return Ok(true);
             }
+            current = arena.region(region)?.parent_region(arena)?;
         }
-        false
+        Ok(false)
     }
 
-    /// Return true if this region is ancestor of the `other` region.  A region is considered as its own ancestor, use `isProperAncestor` to avoid this.
-    pub fn is_ancestor(&self, other: *mut Self) -> bool {
-        self == other || self.is_proper_ancestor(other)
-    }
-    
-    /// Takes body of another region (that region will have no body after this operation completes).  The current body of this region is cleared.
-    pub fn take_body(&self, other: &Self) {
-        self.drop_all_references();
-        self.blocks.clear();
-        self.blocks.splice(self.blocks.end(), other.blocks());
+    /// Return true if this region is ancestor of the `other` region.  A region is considered as its own ancestor, use `is_proper_ancestor` to avoid this.
+    pub fn is_ancestor(&self, arena: &IRArena, other: RegionId) -> ArenaResult<bool> {
+        Ok(self.id == other || self.is_proper_ancestor(arena, other)?)
     }
 
-    /// Returns 'block' if 'block' lies in this region, or otherwise finds the ancestor of 'block' that lies in this region. Returns nullptr if the latter fails.
-    pub fn find_ancestor_block_in_region(&self, block: &Block) -> *mut Block {
-        let mut current_block = block;
-        while Some(self) != current_block.parent() {
-            let parent_operation = current_block.parent_operation();
-            if parent_operation.is_none()
-            || parent_operation.unwrap().block().is_none() {
-                return None;
+    /// Returns `block` if `block` lies in this region, or otherwise finds the ancestor of `block` that lies in this region. Returns `None` if the latter fails.
+    pub fn find_ancestor_block_in_region(&self, arena: &IRArena, block: BlockId)
+    -> ArenaResult<Option<BlockId>>
+    {
+        let mut current = block;
+        loop {
+            let parent = arena.block(current)?.parent();
+            if parent == Some(self.id) {
+                return Ok(Some(current));
+            }
+            let parent_block = match arena.block_parent_op(current)? {
+                None => return Ok(None),
+                Some(operation) => arena.op(operation)?.block()
+            };
+            match parent_block {
+                None => return Ok(None),
+                Some(parent_block) => current = parent_block
             }
-            current_block = &parent_operation.block();
         }
-        current_block
     }
 
-    /// Returns 'operation' if 'operation' lies in this region, or otherwise finds the ancestor of 'operation' that lies in this region. Returns nullptr if the latter fails.
-    pub fn find_ancestor_op_in_region(&self, operation: &Operation)
-        -> *mut Operation
+    /// Returns `operation` if `operation` lies in this region, or otherwise finds the ancestor of `operation` that lies in this region. Returns `None` if the latter fails.
+    pub fn find_ancestor_op_in_region(&self, arena: &IRArena, operation: OpId)
+    -> ArenaResult<Option<OpId>>
     {
-        let mut current: *mut Operation = operation;
-        while !current.parent_region().is_null() {
-            if current.parent_region() == self {
-                return current;
-            }
-
-            current = current.parent_region().parent_operation();
-            if current.is_none() {
-                return null();
+        let mut current = operation;
+        loop {
+            match arena.parent_region(current)? {
+                None => return Ok(None),
+                Some(region) if region == self.id => return Ok(Some(current)),
+                Some(_) => match arena.parent_op(current)? {
+                    None => return Ok(None),
+                    Some(parent) => current = parent
+                }
             }
         }
-        null()
     }
 
-    /// Drop all operand uses from operations within this region, which is an essential step in breaking cyclic dependences between references when they are to be deleted.
-    pub fn drop_all_references(&mut self) {
-        for block in *self {
-            block.drop_all_references();
-        }
-    }
-    
     /*
     Operation Walkers
     */
-    
+
     /*
     CFG View Utilities
     */
 }
 
-impl Drop for Region {
-    fn drop(&mut self) {
-        // Operations may have cyclic references, which need to be dropped before we can start deleting them.
-        self.drop_all_references();
-    }
-}
-
-// impl IList for Block {
-//     fn parent_region(&self) -> *mut Region {
-//         let offset = 
-//             size_t(&((*mut Region)nullptr->*Region::get_sublist_access(nullptr)));
-//         let anchor = static_cast<iplist<Block> *>(self);
-//         reinterpret_cast<*mut Region>(reinterpret_cast<char *>(anchor) - offset)
-//     }
-
-//     /// This is a trait method invoked when a basic block is added to a region.
-//     /// We keep the region pointer up to date.
-//     fn add_node_to_list(&mut self, block: Option<Block>) {
-//         assert!(!block.parent(), "Already in a region!");
-//         block.parent_valid_op_order_pair.set_pointer(self.parent_region());
-//     }
-  
-//     /// This is a trait method invoked when an operation is removed from a
-//     /// region.  We keep the region pointer up to date.
-//     fn remove_node_from_list(&mut self, block: Option<Block>) {
-//         assert!(block.parent(), "Not already in a region!");
-//         block.parent_valid_op_order_pair.set_pointer(nullptr);
-//     }
-  
-//     /// This is a trait method invoked when an operation is moved from one block
-//     /// to another.  We keep the block pointer up to date.
-//     fn transfer_nodes_from_list(
-//         &self,
-//         other: &Self,
-//         first: block_iterator,
-//         last: block_iterator
-//     ) {
-//         // If we are transferring operations within the same function, the parent pointer doesn't need to be updated.
-//         let cur_parent = self.parent_region();
-//         if cur_parent == other.parent_region() {
-//             return;
-//         }
-    
-//         // Update the 'parent' member of each Block.
-//         while first != last {
-//             first.parent_valid_op_order_pair.set_pointer(cur_parent);
-//             first += 1;
-//         }
-//     }
-// }
-
 /// This class provides iteration over the held operations of blocks directly within a region.
 pub struct OpIterator {
     /// The region whose operations are being iterated over.
-    region: *mut Region,
+    region: RegionId,
     // /// The block of `region` whose operations are being iterated over.
     // block: Region::iterator,
     // /// The current operation within `block`.
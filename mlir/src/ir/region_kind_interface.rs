@@ -7,21 +7,43 @@
 //!   - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/RegionKindInterface.td>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/RegionKindInterface.cpp>
 
-use crate::mlir::ir::operation::base::OpInterface;
+use crate::ir::operation::{
+    Operation,
+    definition::{Op, Trait}
+};
 
 /// The kinds of regions contained in an operation. SSACFG regions require the SSA-Dominance property to hold. Graph regions do not require SSA-Dominance. If a registered operation does not implement RegionKindInterface, then any regions it contains are assumed to be SSACFG regions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum RegionKind {
     SSACFG,
     Graph,
 }
 
+/// Indicates that this operation has only graph regions, i.e. that it implements `RegionKindInterface` with every region of kind `RegionKind::Graph`. Operations with this trait don't need to implement the interface themselves.
+pub trait HasOnlyGraphRegion: Trait {
+
+}
+
 /// Interface for operations to describe the abstract semantics of their regions. Currently, two kinds of regions are supported. RegionKind::Graph represents a graph region without control flow semantics. RegionKind::SSACFG represents an [SSA-style control flow](../LangRef.md/#modeling-control-flow) region with basic blocks, sequential semantics, and reachability.
+///
+/// Operations declaring `interfaces(RegionKindInterface)` with `#[derive(Op)]` have their `Op::region_kind` hook forward to this interface.
 // OpInterface to query the properties of regions in an operation
-pub trait RegionKindInterface: OpInterface {
+pub trait RegionKindInterface: Op {
     /// Return the kind of the region with the given index inside this operation.
     fn region_kind(index: usize) -> RegionKind;
 
     /// Return true if the kind of the given region requires the SSA-Dominance property
     fn has_ssa_dominance(index: usize) -> bool {
+        <Self as RegionKindInterface>::region_kind(index) == RegionKind::SSACFG
     }
 }
+
+/// Return the kind of the region `index` of `operation`. Regions of unregistered operations are assumed to be SSACFG regions.
+pub fn region_kind(operation: &Operation, index: usize) -> RegionKind {
+    operation.name().region_kind(index)
+}
+
+/// Return true if the region `index` of `operation` requires the SSA-Dominance property.
+pub fn has_ssa_dominance(operation: &Operation, index: usize) -> bool {
+    region_kind(operation, index) == RegionKind::SSACFG
+}
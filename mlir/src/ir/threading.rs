@@ -6,13 +6,65 @@
 //!
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/Threading.h>
 
-use crate::{
-    mlir::ir::{
-        diagnostics,
-        mlir_context::MLIRContext
-    },
-    llvm::{
-        adt::sequence,
-        support::thread_pool
-    }
+use std::sync::{
+    Mutex,
+    atomic::{AtomicBool, AtomicUsize, Ordering}
+};
+
+use crate::ir::{
+    diagnostics::ParallelDiagnosticHandler,
+    mlir_context::MLIRContext
 };
+
+/// Invoke the given function on the elements of `items`. This function will process the elements in parallel on the thread pool of `context` if multi-threading is enabled, and sequentially in order otherwise. Once an invocation fails, no further element is processed, and the error of the failing element that comes first in `items` is returned, so that the reported error doesn't depend on the order in which the elements complete. Likewise, the diagnostics emitted while processing the elements are reported in the order of the elements.
+pub fn failable_parallel_for_each<T: Sync, E: Send>(
+    context: &MLIRContext,
+    items: &[T],
+    function: impl Fn(&T) -> Result<(), E> + Sync
+) -> Result<(), E> {
+    // If multithreading is disabled or there is a small number of elements, process the elements directly on this thread.
+    if !context.is_multithreading_enabled() || items.len() <= 1 {
+        return items.iter().try_for_each(function);
+    }
+
+    // Process the elements in parallel, each worker taking the next unprocessed element. The diagnostics are ordered by the index of the element they were emitted for.
+    let handler = ParallelDiagnosticHandler::new(context);
+    let next = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let first_error: Mutex<Option<(usize, E)>> = Mutex::new(None);
+    context.thread_pool().broadcast(items.len(), |_| {
+        while !failed.load(Ordering::Relaxed) {
+            let index = next.fetch_add(1, Ordering::Relaxed);
+            let Some(item) = items.get(index) else {
+                break;
+            };
+            handler.set_order_id_for_thread(index);
+            let result = function(item);
+            handler.erase_order_id_for_thread();
+            if let Err(error) = result {
+                failed.store(true, Ordering::Relaxed);
+                let mut first_error = first_error.lock().unwrap();
+                if first_error.as_ref().map_or(true, |(first, _)| index < *first) {
+                    *first_error = Some((index, error));
+                }
+            }
+        }
+    });
+    drop(handler);
+    match first_error.into_inner().unwrap() {
+        None => Ok(()),
+        Some((_, error)) => Err(error)
+    }
+}
+
+/// Invoke the given function on the elements of `items`. This function will process the elements in parallel on the thread pool of `context` if multi-threading is enabled, and sequentially in order otherwise.
+pub fn parallel_for_each<T: Sync>(
+    context: &MLIRContext,
+    items: &[T],
+    function: impl Fn(&T) + Sync
+) {
+    let _ = failable_parallel_for_each(context, items, |item| -> Result<(), ()> {
+        function(item);
+        Ok(())
+    });
+}
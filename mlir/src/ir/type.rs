@@ -30,7 +30,7 @@ use crate::{
     }
 };
 
-pub type HasTraitFn = Box<dyn Fn(TypeId) -> bool>;  // unique_function
+pub type HasTraitFn = Box<dyn Fn(TypeId) -> bool + Send + Sync>;  // unique_function
 
 /**
 Instances of the Type class are uniqued, have an immutable identifier and an optional mutable component. They wrap a pointer to the storage object owned by MLIRContext. Therefore, instances of Type are passed around by value.
@@ -68,14 +68,19 @@ For non-parametric types, a convenience `DefaultTypeStorage` is provided. Parame
 
 - Provide a static construction method: `DerivedStorage *construct(TypeStorageAllocator &, const KeyTy &key)` that builds a unique instance of the derived storage. The arguments to this function are an allocator to store any uniqued data within the context and the key type for this storage.
 
-- If they have a mutable component, this component must not be a part of the key.
+- If they have a mutable component, this component must not be a part of the key. Since types are shared between the threads of the context, it must also be synchronised.
 */
-pub trait Type: Any {
+pub trait Type: Any + Send + Sync {
     /*
     ====================================================================
     Type
     ====================================================================
     */
+
+    /// Return the dialect this type is registered to, or None for builtin types, which the printer and parser handle directly.
+    fn dialect(&self) -> Option<&'static dyn Dialect> {
+        None
+    }
 }
 
 // Display + Hash + PartialEq
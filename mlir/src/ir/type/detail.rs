@@ -6,18 +6,18 @@ This holds implementation details of Type.
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/TypeDetail.h>
 */
 
+use std::sync::{Arc, OnceLock};
+
 use crate::{
-    mlir::ir::{
-        affine_map,
+    ir::{
         builtins::types::Signedness,
-        mlir_context,
-        operation::support,
-        type_range,
         r#type::Type
     },
-    llvm::{
-        adt::bit,
-        support::trailing_objects
+    support::{
+        logical_result::LogicalResult,
+        storage_uniquer::{
+            MutableStorage, ParametricStorage, Storage, StorageAllocator
+        }
     }
 };
 
@@ -44,3 +44,72 @@ pub struct FunctionTypeStorage {
 pub struct TupleTypeStorage {
 
 }
+
+// ----------------------------------------------------------------------
+// IdentifiedTypeStorage
+// ----------------------------------------------------------------------
+
+/**
+Storage of a type that is uniqued by its name rather than by its structure, and whose body is the mutable component of the type. The type is created without a body, which is then set once with `StorageUniquer::mutate`. This allows the body to refer to the type itself, directly or through other types, as LLVM identified structs or linked list types do.
+
+Setting the body again with an equal body succeeds, so that a type may be redeclared as long as the declarations agree. Setting a different body fails.
+*/
+pub struct IdentifiedTypeStorage<B> {
+    name: Arc<str>,
+    body: OnceLock<B>
+}
+
+impl<B> IdentifiedTypeStorage<B> {
+    /// Return the name identifying the type.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Return the body of the type, or None if it has not been set yet.
+    pub fn body(&self) -> Option<&B> {
+        self.body.get()
+    }
+
+    /// Returns true if the body of the type has been set.
+    pub fn is_initialised(&self) -> bool {
+        self.body.get().is_some()
+    }
+}
+
+impl<B: PartialEq + Send + Sync + 'static> Storage for IdentifiedTypeStorage<B> {}
+
+impl<B: PartialEq + Send + Sync + 'static> ParametricStorage
+    for IdentifiedTypeStorage<B>
+{
+    type Key = String;
+
+    fn matches(&self, key: &String) -> bool {
+        *self.name == **key
+    }
+
+    fn construct(allocator: &mut StorageAllocator, key: String) -> Self {
+        Self { name: allocator.copy_str(&key), body: OnceLock::new() }
+    }
+}
+
+impl<B: PartialEq + Send + Sync + 'static> MutableStorage
+    for IdentifiedTypeStorage<B>
+{
+    type Mutation = B;
+
+    fn mutate(&self, _allocator: &mut StorageAllocator, body: B)
+        -> LogicalResult
+    {
+        // Mutations are serialised by the uniquer, so the body cannot be set concurrently between the check and the assignment.
+        match self.body.get() {
+            Some(existing) if *existing == body => Ok(()),
+            Some(_) => Err(format!(
+                "identified type '{}' already has a different body",
+                self.name).into()),
+            None => {
+                let _ = self.body.set(body);
+                Ok(())
+            }
+        }
+    }
+}
@@ -9,7 +9,7 @@ use crate::{
     mlir::{
         ir::{
             builtins::{
-                type_interfaces::ShapedType,
+                type_interfaces::{ShapedType, is_dynamic},
                 types::TupleType
             },
             r#type::Type,
@@ -59,7 +59,7 @@ pub fn verify_compatible_shape(shape1: &[i64], shape2: &[i64])
          return Err(());
      }
     for (dim1, dim2) in shape1.zip(shape2) {
-        if !ShapedType::is_dynamic(dim1) && !ShapedType::is_dynamic(dim2) &&
+        if !is_dynamic(dim1) && !is_dynamic(dim2) &&
             dim1 != dim2 {
             return Err(());
         }
@@ -93,7 +93,7 @@ pub fn verify_compatible_dims(dims: &[i64]) -> LogicalResult {
     }
     let static_dim = std::accumulate(
         dims, dims.front(), |fold, dim|
-            if ShapedType::is_dynamic(dim) { fold } else { dim }
+            if is_dynamic(dim) { fold } else { dim }
     );
-    success(dims.all(|dim| ShapedType::is_dynamic(dim) || dim == static_dim))
+    success(dims.all(|dim| is_dynamic(dim) || dim == static_dim))
 }
@@ -1,4 +1,4 @@
-//! Verifier Analysis for MLIR Structures 
+//! Verifier Analysis for MLIR Structures
 //!
 //! This file implements the verify() methods on the various IR types, performing (potentially expensive) checks on the holistic structure of the code. This can be used for detecting bugs in compiler transformations and hand written .mlir files.
 //!
@@ -9,120 +9,512 @@
 //! - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/IR/Verifier.h>
 //! - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/IR/Verifier.cpp>
 
-use llvm::{
-    adt::{
-        small_vector::SmallVector,
-        string_map
-    },
-    support::{
-        format_variadic,
-        pretty_stack_trace,
-        regex
-    }
-};
+use std::error::Error;
+
 use crate::{
     ir::{
-        attribute,
-        block::Block,
-        dialect,
-        dominance,
-        operation::Operation,
-        region_kind_interface,
-        threading,
+        arena::{ArenaResult, BlockId, IRArena, OpId, RegionId},
+        diagnostics::{
+            Diagnostic, DiagnosticSeverity, append_op, emit_error, emit_op_diagnostic_in
+        },
+        dominance::DominanceInfo,
+        location::Location,
+        mlir_context::MLIRContext,
+        operation::{
+            Operation,
+            definition::{IsIsolatedFromAbove, IsTerminator, NoTerminator},
+            support::PrintingFlags
+        },
+        region_kind_interface::{self, RegionKind},
+        threading::failable_parallel_for_each,
+        value::Value
     },
     support::logical_result::LogicalResult
 };
 
 /// Perform (potentially expensive) checks of invariants, used to detect compiler bugs, on this operation and any nested operations. On error, this reports the error through the MLIRContext and returns failure. If `verify_recursively` is false, this assumes that nested operations have already been properly verified, and does not recursively invoke the verifier on nested operations.
-pub fn verify(operation: *mut Operation, verify_recursively: bool /* true */) -> LogicalResult {
-    let verifier = OperationVerifier::new(verify_recursively);
-    verifier.verify_op_and_dominance(operation)
+pub fn verify(
+    context: &MLIRContext,
+    operation: OpId,
+    verify_recursively: bool /* true */
+) -> LogicalResult {
+    verify_in(context, context.arena(), operation, verify_recursively)
 }
 
+/// Same as `verify`, for an operation of `arena` rather than of the arena of `context`, e.g. while a pass manager owns the IR.
+pub fn verify_in(
+    context: &MLIRContext,
+    arena: &IRArena,
+    operation: OpId,
+    verify_recursively: bool /* true */
+) -> LogicalResult {
+    OperationVerifier::new(context, arena, verify_recursively).verify(operation)
+}
+
+/// A broken invariant: the error describing it and the operation it was found on. The error is reported once verification stops.
+struct Failure {
+    operation: OpId,
+    error: Box<dyn Error>,
+    /// Whether the error was already reported, by the verification of an operation isolated from above.
+    reported: bool
+}
+
+type VerifyResult = Result<(), Failure>;
+
 /// This class encapsulates all the state used to verify an operation region.
-pub struct OperationVerifier {
+pub struct OperationVerifier<'a> {
+    /// The context of the verified operations.
+    context: &'a MLIRContext,
+
+    /// The arena owning the verified operations.
+    arena: &'a IRArena,
+
     /// A flag indicating if this verifier should recursively verify nested operations.
     verify_recursively: bool
 }
 
-impl OperationVerifier {
-    /// Verify the given operation.
-    pub fn verify_op_and_dominance(&self, operation: &Operation) -> LogicalResult {
-        // Verify the operation first, collecting any IsolatedFromAbove operations.
-        if self.verify_operation(operation).is_err() {
-            return Err(());
+impl<'a> OperationVerifier<'a> {
+    pub fn new(context: &'a MLIRContext, arena: &'a IRArena, verify_recursively: bool)
+        -> Self
+    {
+        Self { context, arena, verify_recursively }
+    }
+
+    /// Verify the given operation, reporting the first broken invariant through the diagnostic engine of the context.
+    pub fn verify(&self, operation: OpId) -> LogicalResult {
+        let Err(Failure { operation, error, reported })
+            = self.verify_op_and_dominance(operation)
+        else {
+            return Ok(());
+        };
+        if reported {
+            return Err(error);
         }
-        // Since everything looks structurally ok to this point, we do a dominance check for any nested regions. We do this as a second pass since malformed CFG's can cause dominator analysis construction to crash and we want the verifier to be resilient to malformed code.
-        if operation.num_regions() != 0 {
-            let dom_info = DominanceInfo;
-            if self.verify_dominance_of_contained_regions(
-                operation, dom_info).is_err()
-            {
-                return Err(());
+        match error.downcast_ref::<Diagnostic>() {
+            Some(diagnostic) =>
+                emit_op_diagnostic_in(
+                    self.context, self.arena, operation, diagnostic.clone()),
+            None => {
+                let location = self.arena().op(operation)
+                    .map_or(Location::Unknown, Operation::location);
+                emit_error(self.context, location, error.to_string());
             }
         }
+        Err(error)
+    }
+
+    fn arena(&self) -> &'a IRArena {
+        self.arena
+    }
 
+    /// Verify the given operation, then the dominance relations within its regions.
+    fn verify_op_and_dominance(&self, operation: OpId) -> VerifyResult {
+        // Verify the operation first, collecting any IsolatedFromAbove operations.
+        self.verify_operation(operation)?;
+
+        // Since everything looks structurally ok to this point, we do a dominance check for any nested regions. We do this as a second pass since malformed CFG's can cause dominator analysis construction to crash and we want the verifier to be resilient to malformed code.
+        if self.check(operation, self.arena().op(operation))?.num_regions() != 0 {
+            let dom_info = DominanceInfo::new();
+            self.verify_dominance_of_contained_regions(operation, &dom_info)?;
+        }
         Ok(())
     }
 
-    /// Any ops that have regions and are marked as "isolated from above" will be returned in the ops_with_isolated_regions vector.
+    /// Verify the properties of this operation and, if verifying recursively, of the operations nested in it. The operations that are isolated from above are verified in parallel.
+    fn verify_operation(&self, operation: OpId) -> VerifyResult {
+        self.verify_on_entrance(operation)?;
+        let regions = self.check(operation, self.arena().op(operation))?.regions();
+        let mut ops_with_isolated_regions = Vec::new();
+        for &region in regions {
+            for &block in self.check(operation, self.arena().region(region))?.blocks() {
+                self.verify_block(operation, block, &mut ops_with_isolated_regions)?;
+            }
+        }
+        self.verify_on_exit(operation, &ops_with_isolated_regions)
+    }
+
+    /// Verify the structure of `block` of a region of `parent`. Nested operations are verified if verifying recursively, except those with regions that are isolated from above, which are added to `ops_with_isolated_regions` instead.
     fn verify_block(
         &self,
-        block: &Block,
-        ops_with_isolated_regions: &SmallVector<*mut Operation>
-    ) -> LogicalResult {
+        parent: OpId,
+        block: BlockId,
+        ops_with_isolated_regions: &mut Vec<OpId>
+    ) -> VerifyResult {
+        let arena = self.arena();
+        let operations = self.check(parent, arena.block(block))?.operations();
+
+        // Verify that this block has a terminator.
+        let Some((&back, _)) = operations.split_last() else {
+            if self.check(parent, may_be_valid_without_terminator(arena, block))? {
+                return Ok(());
+            }
+            return Err(self.failure(parent, |operation| operation.emit_error(
+                "empty block: expect at least a terminator")));
+        };
+
+        for &operation in operations {
+            // Only the last instructions is allowed to have successors.
+            let op = self.check(operation, arena.op(operation))?;
+            if op.num_successors() != 0 && operation != back {
+                return Err(self.failure(operation, |operation| operation.emit_error(
+                    "operation with block successors must terminate its parent block")));
+            }
+            if !self.verify_recursively {
+                continue;
+            }
+            if op.num_regions() != 0 && op.has_trait::<dyn IsIsolatedFromAbove>() {
+                ops_with_isolated_regions.push(operation);
+            } else {
+                self.verify_operation(operation)?;
+            }
+        }
+
+        // Verify that this block is not branching to a block of a different region.
+        let region = self.check(parent, arena.block(block))?.parent();
+        for &successor in self.check(back, arena.op(back))?.successors() {
+            if self.check(back, arena.block(successor))?.parent() != region {
+                return Err(self.failure(back, |operation| operation.emit_op_error(
+                    "branching to block of a different region")));
+            }
+        }
+
+        // If this block doesn't have to have a terminator, don't require it.
+        if self.check(parent, may_be_valid_without_terminator(arena, block))? {
+            return Ok(());
+        }
+        let terminator = self.check(back, arena.op(back))?;
+        if !terminator.might_have_trait::<dyn IsTerminator>() {
+            let printed = append_op(
+                arena, back, PrintingFlags::new(), DiagnosticSeverity::Error);
+            return Err(self.failure(back, |operation| operation.emit_error(
+                format!("block with no terminator, has {}", printed))));
+        }
+        Ok(())
+    }
+
+    /// Verify the properties of `operation` that don't depend on the operations nested in it.
+    fn verify_on_entrance(&self, operation: OpId) -> VerifyResult {
+        let arena = self.arena();
+        let op = self.check(operation, arena.op(operation))?;
+
+        // Check that operands are structurally ok.
+        for (index, input) in op.inputs().iter().enumerate() {
+            if arena.value_impl(*input).is_err() {
+                return Err(self.failure(operation, |operation| operation.emit_op_error(
+                    format!("operand #{} refers to an erased value", index))));
+            }
+        }
+        for (index, successor) in op.successors().iter().enumerate() {
+            if !arena.contains_block(*successor) {
+                return Err(self.failure(operation, |operation| operation.emit_op_error(
+                    format!("successor #{} refers to an erased block", index))));
+            }
+        }
+
+        // If we can get operation info for this, check the custom hook.
+        op.name().verify_invariants(arena, op)
+            .map_err(|error| Failure { operation, error, reported: false })?;
+
+        for (index, &region) in op.regions().iter().enumerate() {
+            let blocks = self.check(operation, arena.region(region))?.blocks();
+            // Check that Graph Regions only have a single basic block. This is similar to the code in SingleBlockImplicitTerminator, but doesn't require the trait to be specified. This arbitrary limitation is designed to limit the number of cases that have to be handled by transforms and conversions.
+            if op.is_registered()
+                && region_kind_interface::region_kind(op, index) == RegionKind::Graph
+                && blocks.len() > 1
+            {
+                return Err(self.failure(operation, |operation| operation.emit_op_error(
+                    format!("expects graph region #{} to have 0 or 1 blocks", index))));
+            }
+
+            // Verify the first block has no predecessors.
+            if let Some(&entry) = blocks.first()
+                && !self.check(operation, arena.predecessors(entry))?.is_empty()
+            {
+                return Err(self.failure(operation, |operation| operation.emit_error(
+                    "entry block of region may not have predecessors")));
+            }
+        }
         Ok(())
     }
 
-    /// Verify the properties and dominance relationships of this operation.
-    fn verify_operation(&self, operation: &Operation) -> LogicalResult {
+    /// Verify the properties of `operation` that depend on the operations nested in it, once the blocks of its regions are verified. This first verifies `ops_with_isolated_regions`, the operations of these blocks that are isolated from above, in parallel.
+    fn verify_on_exit(&self, operation: OpId, ops_with_isolated_regions: &[OpId])
+        -> VerifyResult
+    {
+        // A failure is reported by the worker that finds it, so that the diagnostics are ordered by the operations they were emitted for. Only its message is moved out of the worker, since the error itself need not be `Send`.
+        failable_parallel_for_each(
+            self.context,
+            ops_with_isolated_regions,
+            |&operation| self.verify(operation)
+                .map_err(|error| (operation, error.to_string()))
+        ).map_err(|(operation, message)| Failure {
+            operation,
+            error: message.into(),
+            reported: true
+        })?;
+
+        // After the region ops are verified, run the verifiers that have additional region invariants need to verify.
+        let arena = self.arena();
+        let op = self.check(operation, arena.op(operation))?;
+        op.name().verify_region_invariants(arena, op)
+            .map_err(|error| Failure { operation, error, reported: false })?;
+
+        // If this is a registered operation, there is nothing left to do.
+        if op.is_registered() || op.name().dialect_namespace() == "builtin" {
+            return Ok(());
+        }
+
+        // Operations of dynamic dialects are verified against their definition.
+        if let Some(dialect) = self.context.dynamic_dialect(op.name().dialect_namespace()) {
+            return dialect.verify_operation(arena, op)
+                .map_err(|error| Failure { operation, error, reported: false });
+        }
+
+        // Otherwise, verify that unregistered dialects are allowed.
+        if !self.context.allows_unregistered_dialects() {
+            return Err(self.failure(operation, |operation| operation.emit_op_error(
+                "created with unregistered dialect. If this is intended, please call allow_unregistered_dialects() on the MLIRContext, or use -allow-unregistered-dialect with the MLIR opt tool used")));
+        }
         Ok(())
     }
 
-    /// Verify the dominance property of regions contained within the given
-    /// Operation.
+    /// Verify the dominance property of regions contained within the given operation.
     fn verify_dominance_of_contained_regions(
         &self,
-        operation: &Operation,
+        operation: OpId,
         dom_info: &DominanceInfo
-    ) -> LogicalResult
-    {
-        for region in operation.regions() {
+    ) -> VerifyResult {
+        let arena = self.arena();
+        for &region in self.check(operation, arena.op(operation))?.regions() {
             // Verify the dominance of each of the held operations.
-            for block in region {
+            for &block in self.check(operation, arena.region(region))?.blocks() {
                 // Dominance is only meaningful inside reachable blocks.
-                let is_reachable = dom_info.is_reachable_from_entry(&block);
+                let is_reachable = self.check(
+                    operation, dom_info.is_reachable_from_entry(arena, block))?;
 
-                for operation in block {
+                for &nested in self.check(operation, arena.block(block))?.operations() {
+                    let op = self.check(nested, arena.op(nested))?;
                     if is_reachable {
                         // Check that operands properly dominate this use.
-                        for operand in operation.operands() {
-                            if dom_info.properly_dominates(
-                                operand.value(), &operation) {
+                        for (index, input) in op.inputs().iter().enumerate() {
+                            if self.check(nested, dom_info.properly_dominates(arena, *input, nested))? {
                                 continue;
                             }
-
-                            diagnose_invalid_operand_dominance(
-                                operation, operand.index());
-                            return Err(());
+                            let diagnostic = self.check(
+                                nested, diagnose_invalid_operand_dominance(arena, op, index))?;
+                            return Err(Failure {
+                                operation: nested,
+                                error: Box::new(diagnostic),
+                                reported: false
+                            });
                         }
                     }
 
                     // Recursively verify dominance within each operation in the block, even if the block itself is not reachable, or we are in a region which doesn't respect dominance.
-                    if self.verify_recursively && operation.num_regions() != 0 {
+                    if self.verify_recursively && op.num_regions() != 0 {
                         // If this operation is IsolatedFromAbove, then we'll handle it in the outer verification loop.
-                        if operation.has_trait::<IsIsolatedFromAbove>() {
+                        if op.has_trait::<dyn IsIsolatedFromAbove>() {
                             continue;
                         }
-                        if self.verify_dominance_of_contained_regions(
-                            operation, dom_info).is_err()
-                        {
-                            return Err(());
-                        }
+                        self.verify_dominance_of_contained_regions(nested, dom_info)?;
                     }
                 }
             }
         }
         Ok(())
     }
+
+    /// Return a failure on `operation` with the diagnostic built by `diagnose`, or the error reported by the arena if the operation was erased.
+    fn failure(&self, operation: OpId, diagnose: impl FnOnce(&Operation) -> Diagnostic)
+        -> Failure
+    {
+        let error: Box<dyn Error> = match self.arena().op(operation) {
+            Ok(op) => Box::new(diagnose(op)),
+            Err(error) => Box::new(error)
+        };
+        Failure { operation, error, reported: false }
+    }
+
+    /// Turn an error of the arena, such as the use of an erased block, into a failure on `operation`.
+    fn check<T>(&self, operation: OpId, result: ArenaResult<T>) -> Result<T, Failure> {
+        result.map_err(|error| Failure { operation, error: Box::new(error), reported: false })
+    }
+}
+
+/// Return true if `block` may be valid without a terminator, i.e. if it is not attached to a region, or if it is the only block of a region of an operation that might have the `NoTerminator` trait.
+fn may_be_valid_without_terminator(arena: &IRArena, block: BlockId)
+    -> ArenaResult<bool>
+{
+    let Some(region) = arena.block(block)?.parent() else {
+        return Ok(true);
+    };
+    let region = arena.region(region)?;
+    if !region.has_one_block() {
+        return Ok(false);
+    }
+    match region.parent_operation() {
+        None => Ok(true),
+        Some(operation) => Ok(arena.op(operation)?.might_have_trait::<dyn NoTerminator>())
+    }
+}
+
+/// Return the error reported when the operand `index` of `operation` does not dominate it, with a note pointing at the definition of the operand.
+fn diagnose_invalid_operand_dominance(
+    arena: &IRArena,
+    operation: &Operation,
+    index: usize
+) -> ArenaResult<Diagnostic> {
+    let mut diagnostic = operation.emit_error(
+        format!("operand #{} does not dominate this use", index));
+    let block1 = operation.block().expect("Verified operation without a block");
+    let region1 = arena.block(block1)?.parent();
+
+    // Describe where `region2` is relative to the region of `operation`.
+    let relation = |region2: Option<RegionId>| -> ArenaResult<&'static str> {
+        let (Some(region1), Some(region2)) = (region1, region2) else {
+            return Ok("neither in a parent nor in a child region");
+        };
+        Ok(if region1 == region2 {
+            "in the same region"
+        } else if arena.region(region2)?.is_proper_ancestor(arena, region1)? {
+            "in a parent region"
+        } else if arena.region(region1)?.is_proper_ancestor(arena, region2)? {
+            "in a child region"
+        } else {
+            "neither in a parent nor in a child region"
+        })
+    };
+
+    match operation.input(index) {
+        // Attach a note that provides more information about where the operand is defined.
+        Value::Output(output) => {
+            let definition = arena.op(output.owner())?;
+            let block2 = definition.block();
+            let position = if block2 == Some(block1) {
+                "in the same block"
+            } else {
+                let region2 = match block2 {
+                    None => None,
+                    Some(block2) => arena.block(block2)?.parent()
+                };
+                relation(region2)?
+            };
+            diagnostic.attach_note(
+                Some(definition.location()),
+                format!("operand defined here (op {})", position));
+        },
+        // Block argument case.
+        Value::Input(input) => {
+            let block2 = arena.block(input.owner())?;
+            let location = match block2.parent_operation(arena)? {
+                None => Location::Unknown,
+                Some(parent) => arena.op(parent)?.location()
+            };
+            let message = match block2.parent() {
+                None => "operand defined as a block argument (block without parent)"
+                    .to_string(),
+                Some(region2) => {
+                    let position = arena.region(region2)?.blocks().iter()
+                        .position(|block| *block == input.owner())
+                        .unwrap_or_default();
+                    format!("operand defined as a block argument (block #{} {})",
+                        position, relation(Some(region2))?)
+                }
+            };
+            diagnostic.attach_note(Some(location), message);
+        }
+    }
+    Ok(diagnostic)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ir::{
+        builtins::{
+            attributes::DictionaryAttribute,
+            types::{IntegerType, Signedness}
+        },
+        operation::support::OperationName,
+        r#type::Type,
+        value::Output
+    };
+
+    fn i32() -> Arc<dyn Type> {
+        Arc::new(IntegerType::new(32, Signedness::Signless))
+    }
+
+    /// Create the unregistered operation `name` with a single result and `num_regions` regions holding an empty block each, at the end of `block` if given. Returns the operation and the blocks of its regions.
+    fn create(
+        arena: &mut IRArena,
+        block: Option<BlockId>,
+        name: &str,
+        inputs: Vec<Value>,
+        num_regions: usize
+    ) -> (OpId, Vec<BlockId>) {
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new(name), [i32()], inputs,
+            DictionaryAttribute::default(), &[]);
+        let op = arena.create_operation(operation, num_regions).unwrap();
+        if let Some(block) = block {
+            arena.push_back(block, op).unwrap();
+        }
+        let bodies = (0..num_regions).map(|index| {
+            let region = arena.op(op).unwrap().region(index);
+            let body = arena.create_block();
+            arena.push_back_block(region, body).unwrap();
+            body
+        }).collect();
+        (op, bodies)
+    }
+
+    fn notes(diagnostic: &Diagnostic) -> Vec<&str> {
+        diagnostic.notes().iter().map(Diagnostic::message).collect()
+    }
+
+    #[test]
+    fn blocks_without_terminator() {
+        let mut arena = IRArena::new();
+        let detached = arena.create_block();
+        assert!(may_be_valid_without_terminator(&arena, detached).unwrap());
+
+        // Unregistered operations might have the `NoTerminator` trait.
+        let (op, bodies) = create(&mut arena, None, "test.graph", Vec::new(), 1);
+        assert!(may_be_valid_without_terminator(&arena, bodies[0]).unwrap());
+
+        let region = arena.op(op).unwrap().region(0);
+        let second = arena.create_block();
+        arena.push_back_block(region, second).unwrap();
+        assert!(!may_be_valid_without_terminator(&arena, bodies[0]).unwrap());
+        assert!(!may_be_valid_without_terminator(&arena, second).unwrap());
+    }
+
+    #[test]
+    fn operand_dominance_notes_locate_the_definition() {
+        let mut arena = IRArena::new();
+        let (_, bodies) = create(&mut arena, None, "test.func", Vec::new(), 1);
+        let outer = bodies[0];
+        let argument = arena.insert_block_input(outer, 0, i32(), Location::unknown()).unwrap();
+        let (definition, _) = create(&mut arena, Some(outer), "test.def", Vec::new(), 0);
+        let result = Value::Output(Output::new(definition, 0));
+        let (sibling, _) = create(&mut arena, Some(outer), "test.use", vec![result], 0);
+        let (_, bodies) = create(&mut arena, Some(outer), "test.loop", Vec::new(), 1);
+        let (nested, _) = create(
+            &mut arena, Some(bodies[0]), "test.use", vec![result, Value::Input(argument)], 0);
+
+        let diagnostic = diagnose_invalid_operand_dominance(
+            &arena, arena.op(sibling).unwrap(), 0).unwrap();
+        assert_eq!(diagnostic.message(), "operand #0 does not dominate this use");
+        assert_eq!(notes(&diagnostic), ["operand defined here (op in the same block)"]);
+
+        let nested = arena.op(nested).unwrap();
+        let diagnostic = diagnose_invalid_operand_dominance(&arena, nested, 0).unwrap();
+        assert_eq!(notes(&diagnostic), ["operand defined here (op in a parent region)"]);
+        let diagnostic = diagnose_invalid_operand_dominance(&arena, nested, 1).unwrap();
+        assert_eq!(diagnostic.message(), "operand #1 does not dominate this use");
+        assert_eq!(notes(&diagnostic),
+            ["operand defined as a block argument (block #0 in a parent region)"]);
+    }
 }
@@ -16,6 +16,9 @@
 // TODO Remove when translation work is done.
 #![allow(unused)]
 
+// Lets the code generated by `mlir-macros` refer to this crate as `::mlir` from within it.
+extern crate self as mlir;
+
 pub mod analysis;
 pub mod asm_parser;
 pub mod bytecode;
@@ -7,325 +7,136 @@
 //! - include <https://github.com/llvm/llvm-project/tree/main/mlir/include/mlir/Parser/Parser.h>
 //! - lib <https://github.com/llvm/llvm-project/tree/main/mlir/lib/Parser/Parser.cpp>
 
-use llvm::{
-    adt::stl_extras::has_single_element,
-    support::{
-        memory_buffer::MemoryBuffer,
-        sm_loc::SMLoc,
-        source_manager::SourceManager
-    }
-};
 use crate::{
     asm_parser::asm_parser::parse_asm_source_file,
-    bytecode::reader::{is_bytecode, read_bytecode_file},
+    bytecode::reader::{is_bytecode, read_bytecode_shared_file},
     ir::{
+        arena::{ArenaResult, BlockId},
         asm_state::ParserConfig,
-        block::Block,
-        builders::Builder,
-        builtins::location_attributes::{
-            FileLineColLocation, UnknownLocation
+        builtins::{
+            attributes::StringAttribute,
+            operations::ModuleOp
         },
-        location::{Location, LocationAttribute},
-        mlir_context::MLIRContext,
-        operation::Operation,
-        owning_op_ref::OwningOpRef
+        diagnostics::emit_error,
+        dialect_resource_blob_manager::MappedAsmResourceBlob,
+        location::Location,
+        mlir_context::MLIRContext
     },
     support::logical_result::LogicalResult
 };
 
-
-// /**
-// Given a block containing operations that have just been parsed, if the block contains a single operation of `ContainerOpT` type then remove it from the block and return it. If the block does not contain just that operation, create a new operation instance of `ContainerOpT` and move all of the operations within `parsed_block` into the first block of the first region.
-// `ContainerOpT` is required to have a single region containing a single block, and must implement the `SingleBlockImplicitTerminator` trait.
-// */
-// #[inline] 
-// pub fn construct_container_op_for_parser_if_necessary<ContainerOpT>(
-//     parsed_block: *mut Block,
-//     context: *mut MLIRContext,
-//     source_file_loc: Location
-// ) -> OwningOpRef<ContainerOpT>
-// {
-
-//     // Check to see if we parsed a single instance of this operation.
-//     if has_single_element(*parsed_block) {
-//         let op = dyn_cast::<ContainerOpT>(&parsed_block.front());
-//         if op {
-//             op.remove();
-//             return op;
-//         }
-//     }
-
-//     /*
-//     If not, then build a new top-level op if a concrete operation type was specified.
-//     */
-//     if std::is_same_v<ContainerOpT, Option<Operation>> {
-//         (void)context;
-//         return emit_error(source_file_loc)
-//                 << "Source must contain a single top-level operation, found: "
-//                 << parsed_block.operations().len(),
-//             nullptr;
-//     } else {
-//         assert!(
-//             ContainerOpT::template has_trait::<OneRegion>()
-//             && (
-//                 ContainerOpT::template has_trait<NoTerminator>()
-//                 || template hasSingleBlockImplicitTerminator<
-//                     ContainerOpT>::value
-//             ),
-//             "Expected `ContainerOpT` to have a single region with a single "
-//             "block that has an implicit terminator or does not require one");
-
-//         let builder = Builder::new(context);
-//         let op = builder.create::<ContainerOpT>(source_file_loc);
-//         let op_ref = OwningOpRef::<ContainerOpT>::new(op);
-//         assert!(
-//             op.num_regions() == 1 && has_single_element(op.region(0)),
-//             "Expected generated operation to have a single region with a single block");
-//         let op_block = &op.region(0).front();
-//         op_block.operations().splice(op_block.begin(),
-//                                      parsed_block.operations());
-
-//         /*
-//         After splicing, verify just this operation to ensure it can properly contain the operations inside of it.
-//         */
-//         if let Err(()) = op.verify_invariants() {
-//             return OwningOpRef::<ContainerOpT>::new();
-//         }
-        
-//         return op_ref;
-//     }
-// }
-
-// /**
-// This parses the file specified by the indicated SourceManager and appends parsed operations to the given block. If the block is non-empty, the operations are placed before the current terminator. If parsing is successful, success is returned. Otherwise, an error message is emitted through the error handler registered in the context, and failure is returned. If `source_file_loc` is non-null, it is populated with a file location representing the start of the source file that is being parsed.
-// */
-// pub fn parse_source_file(
-//     source_manager: &SourceManager,
-//     block: *mut Block,
-//     config: &ParserConfig,
-//     source_file_loc: *mut LocationAttribute  // = nullptr
-// ) -> Result<(), Box<dyn std::error::Error>>
-// {
-//     let source_buf = source_manager.memory_buffer(source_manager.main_file_id());
-//     if source_file_loc.is_some() {
-//         *source_file_loc
-//             = FileLineColLocation::get(
-//                 config.context(),
-//                 source_buf.get_buffer_identifier(),
-//                 /*line=*/0, /*column=*/0);
-//     }
-//     if is_bytecode(*source_buf) {
-//         return read_bytecode_file(*source_buf, block, config);
-//     }
-//     return parse_asm_source_file(source_manager, block, config);
-// }
-
-// /**
-// An overload with a source manager that may have references taken during the parsing process, and whose lifetime can be freely extended (such that the source manager is not destroyed before the parsed IR). This is useful, for example, to avoid copying some large resources into the MLIRContext and instead referencing the data directly from the input buffers.
-// */
-// pub fn parse_source_file(
-//     source_manager: &Box<SourceManager>,
-//     block: Option<Block>,
-//     config: &ParserConfig,
-//     source_file_loc: Option<LocationAttribute>  //= nullptr
-// ) -> Result<(), Box<dyn std::error::Error>>
-// {
-//     let source_buf =
-//         source_manager.get_memory_buffer(source_manager.get_main_file_id());
-//     if source_file_loc.is_some() {
-//         *source_file_loc = FileLineColLocation::get(config.context(),
-//                                             source_buf.get_buffer_identifier(),
-//                                             /*line=*/0, /*column=*/0);
-//     }
-//     if is_bytecode(*source_buf) {
-//         return read_bytecode_file(source_manager, block, config);
-//     }
-//     return parse_asm_source_file(*source_manager, block, config);
-// }
-
-// /**
-// This parses the file specified by the indicated filename and appends parsed operations to the given block. If the block is non-empty, the operations are placed before the current terminator. If parsing is successful, success is returned. Otherwise, an error message is emitted through the error handler registered in the context, and failure is returned. If `source_file_loc` is non-null, it is populated with a file location representing the start of the source file that is being parsed.
-// */
-// pub fn parse_source_file(
-//     filename: &str,
-//     block: Option<Block>,
-//     config: &ParserConfig,
-//     source_file_loc: Option<LocationAttribute>  // = nullptr
-// ) -> Result<(), Box<dyn std::error::Error>>
-// {
-//     let source_manager = std::make_shared::<SourceManager>();
-//     return parse_source_file(filename, source_manager, block, config, source_file_loc);
-// } 
-
-// /**
-// This parses the file specified by the indicated filename using the provided SourceManager and appends parsed operations to the given block. If the block is non-empty, the operations are placed before the current terminator. If parsing is successful, success is returned. Otherwise, an error message is emitted through the error handler registered in the context, and failure is returned. If `source_file_loc` is non-null, it is populated with a file location representing the start of the source file that is being parsed.
-// */
-// pub fn parse_source_file(
-//     filename: &str,
-//     source_manager: &SourceManager, block: Option<Block>,
-//     config: &ParserConfig,
-//     source_file_loc: Option<LocationAttribute>  //..= nullptr
-// ) -> Result<(), Box<dyn std::error::Error>>
-// {
-//     load_source_file_buffer(filename, source_manager, config.context())?;
-//     parse_source_file(source_manager, block, config, source_file_loc)
-// }
-
-// /**
-// An overload with a source manager that may have references taken during the parsing process, and whose lifetime can be freely extended (such that the source manager is not destroyed before the parsed IR). This is useful, for example, to avoid copying some large resources into the MLIRContext and instead referencing the data directly from the input buffers.
-// */
-// pub fn parse_source_file(
-//     filename: &str,
-//     source_manager: &Box<SourceManager>,
-//     block: Option<Block>,
-//     config: &ParserConfig,
-//     source_file_loc: Option<LocationAttribute>  //= nullptr
-// ) -> Result<(), Box<dyn std::error::Error>>
-// {
-//     load_source_file_buffer(filename, *source_manager, config.context())?;
-//     parse_source_file(source_manager, block, config, source_file_loc)
-// }
-
 /**
-This parses the IR string and appends parsed operations to the given block.
-If the block is non-empty, the operations are placed before the current terminator. If parsing is successful, success is returned. Otherwise, an error message is emitted through the error handler registered in the context, and failure is returned. If `source_file_loc` is non-null, it is populated with a file location representing the start of the source file that is being parsed.
+Given a block containing operations that have just been parsed, if the block contains a single `builtin.module` then remove it from the block and return it. If the block does not contain just that operation, create a new module and move all of the operations within `parsed_block` into its body.
 */
-pub fn parse_source_string(
-    source_str: &str,
-    block: Option<Block>,
-    config: &ParserConfig,
-    source_file_loc: Option<LocationAttribute> //= nullptr
-) -> Result<(), Box<dyn std::error::Error>>
-{
-    let mem_buffer = MemoryBuffer::mem_buffer(source_str);
-    if !mem_buffer {
-        return Err(());
+pub fn construct_container_op_for_parser_if_necessary(
+    parsed_block: BlockId,
+    context: &mut MLIRContext,
+    source_file_loc: Location
+) -> ArenaResult<ModuleOp> {
+    let arena = context.arena_mut();
+    let parsed_ops = arena.block(parsed_block)?.operations().to_vec();
+
+    // Check to see if we parsed a single instance of this operation.
+    if let [op] = parsed_ops[..]
+        && let Some(module) = ModuleOp::from_operation(arena, op)?
+    {
+        arena.remove(op)?;
+        return Ok(module);
     }
 
-  let source_manager = SourceManager::new();
-  source_manager.add_new_source_buffer(mem_buffer, SMLoc::new());  // std::move(mum_buffer)
-  return parse_source_file(source_manager, block, config, source_file_loc);
+    // If not, then build a new top-level module.
+    let module = ModuleOp::build(context, source_file_loc, None)?;
+    let arena = context.arena_mut();
+    let body = module.body(arena)?;
+    for op in parsed_ops {
+        arena.remove(op)?;
+        arena.push_back(body, op)?;
+    }
+    // TODO: Verify the module to ensure it can properly contain the operations inside of it.
+    Ok(module)
 }
 
-// /**
-// The internal implementation of the templated `parse_source_file` methods below, that simply forwards to the non-templated version.
-// */
-// #[inline]
-// pub fn parse_source_file<ContainerOpT, ParserArgs>(
-//     config: &ParserConfig,
-//     args: &&ParserArgs
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     let source_file_loc = LocationAttribute::new();
-//     let block = Block::new();
-//     match parse_source_file(
-//         std::forward::<ParserArgs>(args), &block, config, &source_file_loc)
-//     {
-//         Ok(()) =>
-//             construct_container_op_for_parser_if_necessary::<ContainerOpT>(
-//                 &block, config.context(), source_file_loc),
-//         Err(()) => OwningOpRef::<ContainerOpT>::new()
-//     }
-// }
-
-// /**
-// This parses the file specified by the indicated SourceManager. If the source IR contained a single instance of `ContainerOpT`, it is returned. Otherwise, a new instance of `ContainerOpT` is constructed containing all of the parsed operations. If parsing was not successful, null is returned and an error message is emitted through the error handler registered in the context, and failure is returned. `ContainerOpT` is required to have a single region containing a single block, and must implement the `SingleBlockImplicitTerminator` trait.
-// */
-// #[inline]
-// pub fn parse_source_file<ContainerOpT = Option<Operation>>(
-//     source_manager: &SourceManager, config: &ParserConfig
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     parse_source_file::<ContainerOpT>(config, source_manager)
-// }
-
-// /**
-// An overload with a source manager that may have references taken during the parsing process, and whose lifetime can be freely extended (such that the source manager is not destroyed before the parsed IR). This is useful, for example, to avoid copying some large resources into the MLIRContext and instead referencing the data directly from the input buffers.
-// */
-// #[inline] 
-// pub fn parse_source_file<ContainerOpT = Option<Operation>>(
-//     source_manager: &Box<SourceManager>,
-//     config: &ParserConfig
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     parse_source_file::<ContainerOpT>(config, source_manager)
-// }
+/**
+This parses the IR string and appends parsed operations to the given block. `source_name` names the buffer in the locations of the parsed IR. If parsing is successful, success is returned. Otherwise, an error message is emitted through the diagnostic engine of the context, and failure is returned.
+*/
+pub fn parse_source_string_into(
+    source: &str,
+    block: BlockId,
+    config: &mut ParserConfig,
+    source_name: &str
+) -> LogicalResult {
+    parse_asm_source_file(source, source_name, block, config)
+}
 
-// /**
-// This parses the file specified by the indicated filename. If the source IR contained a single instance of `ContainerOpT`, it is returned. Otherwise, a new instance of `ContainerOpT` is constructed containing all of the parsed operations. If parsing was not successful, null is returned and an error message is emitted through the error handler registered in the context, and failure is returned. `ContainerOpT` is required to have a single region containing a single block, and must implement the `SingleBlockImplicitTerminator` trait.
-// */
-// #[inline] 
-// pub fn parse_source_file<ContainerOpT = Option<Operation>>(
-//     filename: &str,
-//     config: &ParserConfig
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     parse_source_file::<ContainerOpT>(config, filename)
-// }
+/**
+This parses the file specified by the indicated filename and appends parsed operations to the given block. The file may contain either the textual format or MLIR bytecode. If parsing is successful, success is returned. Otherwise, an error message is emitted through the diagnostic engine of the context, and failure is returned.
+*/
+pub fn parse_source_file_into(
+    filename: &str,
+    block: BlockId,
+    config: &mut ParserConfig
+) -> LogicalResult {
+    // Map the file rather than reading it, so that the resource blobs of bytecode files reference the file instead of copying their data.
+    let buffer = MappedAsmResourceBlob::map_file(filename).map_err(|_| {
+        let message = format!("could not open input file {}", filename);
+        emit_error(config.context(), Location::unknown(), message.clone());
+        Box::<dyn std::error::Error>::from(message)
+    })?;
+    if is_bytecode((*buffer).as_ref()) {
+        return read_bytecode_shared_file(buffer, filename, block, config);
+    }
+    let source = core::str::from_utf8((*buffer).as_ref()).map_err(|_| {
+        let message = format!("input file {} is not valid UTF-8", filename);
+        emit_error(config.context(), Location::unknown(), message.clone());
+        Box::<dyn std::error::Error>::from(message)
+    })?;
+    parse_source_string_into(source, block, config, filename)
+}
 
-// /**
-// This parses the file specified by the indicated filename using the provided SourceManager. If the source IR contained a single instance of `ContainerOpT`, it is returned. Otherwise, a new instance of `ContainerOpT` is constructed containing all of the parsed operations. If parsing was not successful, null is returned and an error message is emitted through the error handler registered in the context, and failure is returned. `ContainerOpT` is required to have a single region containing a single block, and must implement the `SingleBlockImplicitTerminator` trait.
-// */
-// #[inline] 
-// pub fn parse_source_file<ContainerOpT = Option<Operation>>(
-//     filename: &str,
-//     source_manager: &SourceManager,
-//     config: &ParserConfig
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     parse_source_file::<ContainerOpT>(config, filename, source_manager)
-// }
+/**
+This parses the provided string containing MLIR. If the source IR contained a single `builtin.module`, it is returned. Otherwise, a new module is constructed containing all of the parsed operations. If parsing was not successful, `None` is returned and an error message is emitted through the diagnostic engine of the context.
+*/
+pub fn parse_source_string(source: &str, config: &mut ParserConfig)
+    -> Option<ModuleOp>
+{
+    parse_into_container_op(config, "", |block, config|
+        parse_source_string_into(source, block, config, ""))
+}
 
-// /**
-// An overload with a source manager that may have references taken during the parsing process, and whose lifetime can be freely extended (such that the source manager is not destroyed before the parsed IR). This is useful, for example, to avoid copying some large resources into the MLIRContext and instead referencing the data directly from the input buffers.
-// */
-// #[inline]
-// pub fn parse_source_file<ContainerOpT = Option<Operation>>(
-//     filename: &str,
-//     source_manager: &Box<SourceManager>,
-//     config: &ParserConfig
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     parse_source_file::<ContainerOpT>(config, filename, source_manager)
-// }
+/**
+This parses the file specified by the indicated filename. If the source IR contained a single `builtin.module`, it is returned. Otherwise, a new module is constructed containing all of the parsed operations. If parsing was not successful, `None` is returned and an error message is emitted through the diagnostic engine of the context.
+*/
+pub fn parse_source_file(filename: &str, config: &mut ParserConfig)
+    -> Option<ModuleOp>
+{
+    parse_into_container_op(config, filename, |block, config|
+        parse_source_file_into(filename, block, config))
+}
 
-// /**
-// This parses the provided string containing MLIR. If the source IR contained a single instance of `ContainerOpT`, it is returned. Otherwise, a new instance of `ContainerOpT` is constructed containing all of the parsed operations. If parsing was not successful, null is returned and an error message is emitted through the error handler registered in the context, and failure is returned. `ContainerOpT` is required to have a single region containing a single block, and must implement the `SingleBlockImplicitTerminator` trait.
-// */
-// #[inline] 
-// pub fn parse_source_string<ContainerOpT = Option<Operation>>(
-//     source_str: &str,
-//     config: &ParserConfig
-// ) -> OwningOpRef<ContainerOpT>
-// {
-//     let source_file_loc = LocationAttribute::new;
-//     let block = Block::new();
-//     match parse_source_string(source_str, &block, config, &source_file_loc) {
-//         Ok(()) =>
-//             construct_container_op_for_parser_if_necessary::<ContainerOpT>(
-//                 &block, config.context(), source_file_loc),
-//         Err(()) => OwningOpRef::<ContainerOpT>::new()
-//     }
-// }
+/// The shared implementation of `parse_source_string` and `parse_source_file`, which parses with `parse` into a temporary block and wraps the result in a module.
+fn parse_into_container_op(
+    config: &mut ParserConfig,
+    source_name: &str,
+    parse: impl FnOnce(BlockId, &mut ParserConfig) -> LogicalResult
+) -> Option<ModuleOp> {
+    let source_file_loc = Location::file_line_col(
+        StringAttribute::new(source_name), 0, 0);
+    let block = config.context_mut().arena_mut().create_block();
+    let module = parse(block, config).ok().and_then(|_|
+        construct_container_op_for_parser_if_necessary(
+            block, config.context_mut(), source_file_loc).ok());
+    // On failure the parser already erased everything it created, so the block is empty either way.
+    let _ = config.context_mut().arena_mut().erase_block(block);
+    module
+}
 
-pub fn load_source_file_buffer(
-    filename: &str,
-    source_manager: &SourceManager,
-    context: Option<MLIRContext>
-) -> LogicalResult
+/// Read the contents of `filename`, emitting an error if it could not be opened.
+pub fn load_source_file(filename: &str, context: &MLIRContext)
+    -> Result<String, Box<dyn std::error::Error>>
 {
-    if source_manager.num_buffers() != 0 {
-        // TODO: Extend to support multiple buffers.
-        return emit_error(UnknownLocation::get(context),
-            "Only main buffer parsed at the moment");
-    }
-    match MemoryBuffer::file_or_stdin(filename, false, true, None) {
-        Ok(file) => {
-            // Load the MLIR source file.
-            source_manager.add_new_source_buffer(file, SMLoc::new());  // std::move(*file_or_err)
-            Ok(())
-        },
-        Err(error) => emit_error(UnknownLocation::get(context),
-        "Could not open input file " + filename)
-    }
+    std::fs::read_to_string(filename).map_err(|_| {
+        let message = format!("could not open input file {}", filename);
+        emit_error(context, Location::unknown(), message.clone());
+        message.into()
+    })
 }
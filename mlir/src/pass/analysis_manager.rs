@@ -1,58 +1,492 @@
 /*!
+# Analysis Manager
+
+Analyses are computed on demand for an operation through the `AnalysisManager` of the pass running on it, and cached by their type until a pass invalidates them. A pass that doesn't modify the IR can mark the analyses it keeps valid as preserved so that the passes after it reuse them:
+
+```ignore
+impl Pass for MyPass {
+    fn run_on_operation(&mut self, state: &mut PassExecutionState) -> LogicalResult {
+        let dominance = state.get_analysis::<DominanceInfo>();
+        ...
+        state.mark_analyses_preserved::<DominanceInfo>();
+        Ok(())
+    }
+}
+```
+
 - include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Pass/AnalysisManager.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/Pass.cpp>
 */
 
-// nclude "mlir/IR/Operation.h"
-// #include "mlir/Pass/PassInstrumentation.h"
-// #include "mlir/Support/LLVM.h"
-// #include "llvm/ADT/DenseMap.h"
-// #include "llvm/ADT/MapVector.h"
-// #include "llvm/ADT/SmallPtrSet.h"
-// #include "llvm/Support/TypeName.h"
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, HashSet}
+};
+
+use crate::ir::arena::{ArenaError, ArenaResult, IRArena, OpId};
+
+// ----------------------------------------------------------------------
+// PreservedAnalyses
+// ----------------------------------------------------------------------
 
 /// A utility class to represent the analyses that are known to be preserved.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct PreservedAnalyses {
+    /// Whether all analyses are preserved.
+    all: bool,
+    /// The set of analyses that are known to be preserved.
+    preserved_ids: HashSet<TypeId>
 }
 
-/// The abstract polymorphic base class representing an analysis.
-struct AnalysisConcept {
-  
+impl PreservedAnalyses {
+    /// Mark all analyses as preserved.
+    pub fn preserve_all(&mut self) {
+        self.all = true;
+    }
+
+    /// Returns true if all analyses were marked preserved.
+    pub fn is_all(&self) -> bool {
+        self.all
+    }
+
+    /// Returns true if no analyses were marked preserved.
+    pub fn is_none(&self) -> bool {
+        !self.all && self.preserved_ids.is_empty()
+    }
+
+    /// Preserve the analysis `T`.
+    pub fn preserve<T: Analysis>(&mut self) {
+        self.preserve_id(TypeId::of::<T>());
+    }
+
+    /// Preserve the analysis with the given type id.
+    pub fn preserve_id(&mut self, id: TypeId) {
+        self.preserved_ids.insert(id);
+    }
+
+    /// Returns true if the analysis `T` was marked preserved.
+    pub fn is_preserved<T: Analysis>(&self) -> bool {
+        self.is_preserved_id(TypeId::of::<T>())
+    }
+
+    /// Returns true if the analysis with the given type id was marked preserved.
+    pub fn is_preserved_id(&self, id: TypeId) -> bool {
+        self.all || self.preserved_ids.contains(&id)
+    }
+
+    /// Remove the analysis with the given type id from the preserved set. This is used when an analysis is invalidated even though it was preserved, so that the analyses depending on it are invalidated as well.
+    fn unpreserve(&mut self, id: TypeId) {
+        self.preserved_ids.remove(&id);
+    }
 }
 
-/// A derived analysis model used to hold a specific analysis object.
-struct AnalysisModel<Analysis> {
+// ----------------------------------------------------------------------
+// Analysis
+// ----------------------------------------------------------------------
+
+/**
+An analysis of an operation, computed on demand by an `AnalysisManager` and cached until a pass invalidates it.
 
+Analyses are constructed with the operation they analyse and the analysis manager of that operation, through which they can query the analyses they depend on. An analysis constructed while constructing another is cached first, so dependencies are always invalidated before the analyses using them.
+*/
+pub trait Analysis: Any {
+    /// Compute the analysis of `op`.
+    fn new(arena: &IRArena, op: OpId, am: &mut AnalysisManager) -> Self
+    where
+        Self: Sized;
+
+    /// Returns true if this analysis is invalidated given the set of analyses that were preserved by a pass. An analysis depending on others can override this to also report itself invalidated when they aren't preserved. By default, an analysis is invalidated unless it is explicitly preserved.
+    fn is_invalidated(&self, preserved: &PreservedAnalyses) -> bool {
+        !preserved.is_preserved_id(self.type_id())
+    }
 }
 
-/// This class represents a cache of analyses for a single operation. All
-/// computation, caching, and invalidation of analyses takes place here.
+impl dyn Analysis {
+    /// Return true if the concrete type of this analysis is `T`.
+    pub fn is<T: Analysis>(&self) -> bool {
+        self.type_id() == TypeId::of::<T>()
+    }
+
+    /// Return this analysis as an instance of `T`, or None if it is another analysis.
+    pub fn downcast_ref<T: Analysis>(&self) -> Option<&T> {
+        // SAFETY: The concrete type of the analysis is `T`.
+        self.is::<T>().then(|| unsafe { &*(self as *const dyn Analysis as *const T) })
+    }
+
+    /// Return this analysis as an instance of `T`, or None if it is another analysis.
+    pub fn downcast_mut<T: Analysis>(&mut self) -> Option<&mut T> {
+        // SAFETY: The concrete type of the analysis is `T`.
+        self.is::<T>().then(|| unsafe { &mut *(self as *mut dyn Analysis as *mut T) })
+    }
+}
+
+// ----------------------------------------------------------------------
+// AnalysisMap
+// ----------------------------------------------------------------------
+
+/// This class represents a cache of analyses for a single operation. All computation, caching, and invalidation of analyses takes place here.
 pub struct AnalysisMap {
+    /// The operation the analyses are computed on.
+    op: OpId,
+    /// The cached analyses, in the order they were computed.
+    analyses: Vec<(TypeId, Box<dyn Analysis>)>
+}
+
+impl AnalysisMap {
+    pub fn new(op: OpId) -> Self {
+        Self { op, analyses: Vec::new() }
+    }
+
+    /// Returns the operation that this analysis map represents.
+    pub fn op(&self) -> OpId {
+        self.op
+    }
+
+    /// Get a cached analysis instance if one exists, otherwise return None.
+    pub fn get_cached_analysis<T: Analysis>(&self) -> Option<&T> {
+        let id = TypeId::of::<T>();
+        self.analyses.iter()
+            .find(|(analysis_id, _)| *analysis_id == id)
+            .and_then(|(_, analysis)| analysis.downcast_ref())
+    }
+
+    /// Returns the position of the cached analysis `T`, if any.
+    fn position<T: Analysis>(&self) -> Option<usize> {
+        let id = TypeId::of::<T>();
+        self.analyses.iter().position(|(analysis_id, _)| *analysis_id == id)
+    }
+
+    /// Clear any held analyses.
+    pub fn clear(&mut self) {
+        self.analyses.clear();
+    }
 
+    /// Invalidate any cached analyses based upon the given set of preserved analyses.
+    pub fn invalidate(&mut self, preserved: &PreservedAnalyses) {
+        let mut preserved = preserved.clone();
+        // Remove any analyses that were invalidated. The analyses are kept in the order they were computed, so dependencies always go before users and a single iteration suffices.
+        self.analyses.retain(|(id, analysis)| {
+            let invalidated = analysis.is_invalidated(&preserved);
+            if invalidated {
+                preserved.unpreserve(*id);
+            }
+            !invalidated
+        });
+    }
 }
 
-/// An analysis map that contains a map for the current operation, and a set of
-/// maps for any child operations.
+// ----------------------------------------------------------------------
+// NestedAnalysisMap
+// ----------------------------------------------------------------------
+
+/// An analysis map that contains a map for the current operation, and a set of maps for any child operations.
 pub struct NestedAnalysisMap {
+    /// The cached analyses for the current operation.
+    analyses: AnalysisMap,
+    /// The cached analyses for nested operations.
+    child_analyses: HashMap<OpId, NestedAnalysisMap>
+}
+
+impl NestedAnalysisMap {
+    pub fn new(op: OpId) -> Self {
+        Self { analyses: AnalysisMap::new(op), child_analyses: HashMap::new() }
+    }
+
+    /// Get the operation for this analysis map.
+    pub fn op(&self) -> OpId {
+        self.analyses.op()
+    }
+
+    /// Invalidate any non preserved analyses, on the current operation and on its children.
+    pub fn invalidate(&mut self, preserved: &PreservedAnalyses) {
+        // If all analyses were preserved, then there is nothing to do here.
+        if preserved.is_all() {
+            return;
+        }
+
+        // Invalidate the analyses for the current operation directly.
+        self.analyses.invalidate(preserved);
+
+        // If no analyses were preserved, then just simply clear out the child analysis results.
+        if preserved.is_none() {
+            self.child_analyses.clear();
+            return;
+        }
+
+        // Otherwise, invalidate each child analysis map.
+        for child in self.child_analyses.values_mut() {
+            child.invalidate(preserved);
+        }
+    }
+
+    /// Return the analysis `T` of the current operation, computing and caching it if it isn't cached.
+    fn get_analysis<T: Analysis>(&mut self, arena: &IRArena) -> &T {
+        let index = match self.analyses.position::<T>() {
+            Some(index) => index,
+            None => {
+                // Compute the analysis before caching it, so that the analyses it depends on are cached before it.
+                let op = self.op();
+                let analysis = T::new(arena, op, &mut AnalysisManager::new(self));
+                self.analyses.analyses.push((TypeId::of::<T>(), Box::new(analysis)));
+                self.analyses.analyses.len() - 1
+            }
+        };
+        self.analyses.analyses[index].1.downcast_ref().unwrap()
+    }
 
+    /// Clear the analyses of the current operation and of its children.
+    pub fn clear(&mut self) {
+        self.analyses.clear();
+        self.child_analyses.clear();
+    }
 }
 
+// ----------------------------------------------------------------------
+// AnalysisManager
+// ----------------------------------------------------------------------
+
 /**
-This class represents an analysis manager for a particular operation
-instance. It is used to manage and cache analyses on the operation as well
-as those for child operations, via nested AnalysisManager instances
-accessible via 'slice'. This class is intended to be passed around by value,
-and cannot be constructed directly.
+This class represents an analysis manager for a particular operation instance. It is used to manage and cache analyses on the operation as well as those for child operations, via nested `AnalysisManager` instances accessible via `nest`. This class is intended to be passed around by value, and cannot be constructed directly outside of the pass manager.
 */
-pub struct AnalysisManager {
+pub struct AnalysisManager<'a> {
+    impl_: &'a mut NestedAnalysisMap
+}
+
+impl<'a> AnalysisManager<'a> {
+    pub(crate) fn new(impl_: &'a mut NestedAnalysisMap) -> Self {
+        Self { impl_ }
+    }
+
+    /// Return an analysis manager for the same operation, borrowing this one.
+    pub(crate) fn reborrow(&mut self) -> AnalysisManager<'_> {
+        AnalysisManager { impl_: self.impl_ }
+    }
+
+    /// Returns the operation that this analysis manager represents.
+    pub fn op(&self) -> OpId {
+        self.impl_.op()
+    }
+
+    /// Query for the given analysis for the current operation, computing it if it isn't cached.
+    pub fn get_analysis<T: Analysis>(&mut self, arena: &IRArena) -> &T {
+        self.impl_.get_analysis(arena)
+    }
+
+    /// Query for a cached instance of the given analysis for the current operation, returning None if it isn't cached.
+    pub fn get_cached_analysis<T: Analysis>(&self) -> Option<&T> {
+        self.impl_.analyses.get_cached_analysis()
+    }
+
+    /// Query for an analysis of a child operation, computing it if it isn't cached.
+    pub fn get_child_analysis<T: Analysis>(&mut self, arena: &IRArena, child: OpId)
+        -> ArenaResult<&T>
+    {
+        Ok(self.child_map(arena, child)?.get_analysis(arena))
+    }
+
+    /// Query for a cached analysis of a child operation, or return None.
+    pub fn get_cached_child_analysis<T: Analysis>(&self, child: OpId) -> Option<&T> {
+        self.impl_.child_analyses.get(&child)?.analyses.get_cached_analysis()
+    }
+
+    /// Get an analysis manager for the given operation, which must be a proper descendant of the current operation represented by this analysis manager. Fails with `ArenaError::NotADescendant` otherwise.
+    pub fn nest(&mut self, arena: &IRArena, op: OpId) -> ArenaResult<AnalysisManager<'_>> {
+        Ok(AnalysisManager { impl_: self.child_map(arena, op)? })
+    }
 
+    /// Return the analysis map of `op`, a proper descendant of the current operation, creating the maps of it and of its ancestors below the current operation if needed.
+    fn child_map(&mut self, arena: &IRArena, op: OpId) -> ArenaResult<&mut NestedAnalysisMap> {
+        let current = self.op();
+        // Collect the ancestors of `op` up to the current operation, then nest through each of them from the outermost.
+        let mut ancestors = vec![op];
+        let mut parent = arena.parent_op(op)?;
+        while parent != Some(current) {
+            let Some(ancestor) = parent else {
+                return Err(ArenaError::NotADescendant { op, ancestor: current });
+            };
+            ancestors.push(ancestor);
+            parent = arena.parent_op(ancestor)?;
+        }
+        let mut map = &mut *self.impl_;
+        for ancestor in ancestors.into_iter().rev() {
+            map = map.child_analyses.entry(ancestor)
+                .or_insert_with(|| NestedAnalysisMap::new(ancestor));
+        }
+        Ok(map)
+    }
+
+    /// Invalidate any non preserved analyses.
+    pub fn invalidate(&mut self, preserved: &PreservedAnalyses) {
+        self.impl_.invalidate(preserved);
+    }
+
+    /// Clear any held analyses.
+    pub fn clear(&mut self) {
+        self.impl_.clear();
+    }
 }
 
+// ----------------------------------------------------------------------
+// ModuleAnalysisManager
+// ----------------------------------------------------------------------
+
 /**
-An analysis manager class specifically for the top-level operation. This
-class contains the memory allocations for all nested analysis managers, and
-provides an anchor point. This is necessary because AnalysisManager is
-designed to be a thin wrapper around an existing analysis map instance.
+An analysis manager class specifically for the top-level operation. This class contains the memory allocations for all nested analysis managers, and provides an anchor point. This is necessary because `AnalysisManager` is designed to be a thin wrapper around an existing analysis map instance.
 */
 pub struct ModuleAnalysisManager {
+    impl_: NestedAnalysisMap
+}
+
+impl ModuleAnalysisManager {
+    pub fn new(op: OpId) -> Self {
+        Self { impl_: NestedAnalysisMap::new(op) }
+    }
+
+    /// Returns an analysis manager for the current top-level module.
+    pub fn analysis_manager(&mut self) -> AnalysisManager<'_> {
+        AnalysisManager::new(&mut self.impl_)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::ir::{
+        arena::RegionId,
+        builtins::attributes::DictionaryAttribute,
+        location::Location,
+        operation::{Operation, support::OperationName},
+        r#type::Type
+    };
+
+    /// Create an operation with `num_regions` regions, each holding an empty block, and insert it at the end of the first block of `parent` if given.
+    fn create_op(arena: &mut IRArena, parent: Option<OpId>, num_regions: usize) -> OpId {
+        let operation = Operation::new(
+            Location::unknown(), OperationName::new("test.op"),
+            Vec::<Arc<dyn Type>>::new(), Vec::new(), DictionaryAttribute::default(), &[]);
+        let op = arena.create_operation(operation, num_regions).unwrap();
+        let regions: Vec<RegionId> = arena.op(op).unwrap().regions().to_vec();
+        for region in regions {
+            let block = arena.create_block();
+            arena.push_back_block(region, block).unwrap();
+        }
+        if let Some(parent) = parent {
+            let region = arena.op(parent).unwrap().region(0);
+            let block = arena.region(region).unwrap().blocks()[0];
+            arena.push_back(block, op).unwrap();
+        }
+        op
+    }
+
+    /// The number of operations nested in an operation, itself included.
+    struct NumOperations(usize);
+
+    impl Analysis for NumOperations {
+        fn new(arena: &IRArena, op: OpId, _am: &mut AnalysisManager) -> Self {
+            Self(arena.walk(op).unwrap().len())
+        }
+    }
+
+    /// Whether an operation has nested operations, an analysis depending on `NumOperations`.
+    struct HasNestedOperations(bool);
+
+    impl Analysis for HasNestedOperations {
+        fn new(arena: &IRArena, _op: OpId, am: &mut AnalysisManager) -> Self {
+            Self(am.get_analysis::<NumOperations>(arena).0 > 1)
+        }
+    }
+
+    #[test]
+    fn nest_creates_the_maps_of_intermediate_operations() {
+        let mut arena = IRArena::new();
+        let root = create_op(&mut arena, None, 1);
+        let child = create_op(&mut arena, Some(root), 1);
+        let grandchild = create_op(&mut arena, Some(child), 0);
+
+        let mut map = NestedAnalysisMap::new(root);
+        let mut am = AnalysisManager::new(&mut map);
+        assert_eq!(am.nest(&arena, grandchild).unwrap().op(), grandchild);
+        assert_eq!(map.child_analyses[&child].op(), child);
+        assert_eq!(map.child_analyses[&child].child_analyses[&grandchild].op(), grandchild);
+    }
+
+    #[test]
+    fn nest_rejects_operations_that_are_not_proper_descendants() {
+        let mut arena = IRArena::new();
+        let root = create_op(&mut arena, None, 1);
+        let child = create_op(&mut arena, Some(root), 1);
+        let other = create_op(&mut arena, None, 0);
+
+        let mut map = NestedAnalysisMap::new(child);
+        let mut am = AnalysisManager::new(&mut map);
+        assert_eq!(am.nest(&arena, other).err(),
+            Some(ArenaError::NotADescendant { op: other, ancestor: child }));
+        assert_eq!(am.nest(&arena, child).err(),
+            Some(ArenaError::NotADescendant { op: child, ancestor: child }));
+        assert_eq!(am.nest(&arena, root).err(),
+            Some(ArenaError::NotADescendant { op: root, ancestor: child }));
+        assert!(map.child_analyses.is_empty());
+    }
+
+    #[test]
+    fn analyses_are_invalidated_with_their_dependencies() {
+        let mut arena = IRArena::new();
+        let root = create_op(&mut arena, None, 1);
+        let child = create_op(&mut arena, Some(root), 1);
+        create_op(&mut arena, Some(child), 0);
+
+        let mut map = NestedAnalysisMap::new(root);
+        let mut am = AnalysisManager::new(&mut map);
+        assert!(am.get_child_analysis::<HasNestedOperations>(&arena, child).unwrap().0);
+        assert_eq!(am.get_cached_child_analysis::<NumOperations>(child).unwrap().0, 2);
+
+        let mut preserved = PreservedAnalyses::default();
+        preserved.preserve::<HasNestedOperations>();
+        preserved.preserve::<NumOperations>();
+        am.invalidate(&preserved);
+        assert!(am.get_cached_child_analysis::<HasNestedOperations>(child).is_some());
+
+        let mut preserved = PreservedAnalyses::default();
+        preserved.preserve::<HasNestedOperations>();
+        am.invalidate(&preserved);
+        assert!(am.get_cached_child_analysis::<NumOperations>(child).is_none());
+        assert!(am.get_cached_child_analysis::<HasNestedOperations>(child).is_none());
+    }
+
+    #[test]
+    fn analyses_are_cached_until_invalidated() {
+        let mut arena = IRArena::new();
+        let root = create_op(&mut arena, None, 1);
+        let child = create_op(&mut arena, Some(root), 1);
+
+        let mut mam = ModuleAnalysisManager::new(root);
+        let mut am = mam.analysis_manager();
+        assert!(am.get_cached_analysis::<NumOperations>().is_none());
+        assert_eq!(am.get_analysis::<NumOperations>(&arena).0, 2);
+        assert_eq!(am.get_child_analysis::<NumOperations>(&arena, child).unwrap().0, 1);
+
+        // The cached analyses are returned even though the IR changed.
+        create_op(&mut arena, Some(child), 0);
+        assert_eq!(am.get_analysis::<NumOperations>(&arena).0, 2);
+
+        let mut preserved = PreservedAnalyses::default();
+        assert!(preserved.is_none());
+        preserved.preserve_all();
+        assert!(preserved.is_all() && preserved.is_preserved::<NumOperations>());
+        am.invalidate(&preserved);
+        assert_eq!(am.get_cached_analysis::<NumOperations>().unwrap().0, 2);
+
+        // Without preserved analyses, the analyses of the children are dropped along with them.
+        am.invalidate(&PreservedAnalyses::default());
+        assert!(am.get_cached_analysis::<NumOperations>().is_none());
+        assert!(am.get_cached_child_analysis::<NumOperations>(child).is_none());
+        assert_eq!(am.get_analysis::<NumOperations>(&arena).0, 3);
 
+        am.get_child_analysis::<NumOperations>(&arena, child).unwrap();
+        am.clear();
+        assert!(am.get_cached_analysis::<NumOperations>().is_none());
+        assert!(am.get_cached_child_analysis::<NumOperations>(child).is_none());
+    }
 }
@@ -0,0 +1,568 @@
+/*!
+# Pass Options
+
+Passes declare their options as the fields of a struct deriving `PassOptions`, which parses them from the text between the braces following the argument of the pass in a textual pipeline, e.g. `my-pass{max-iterations=3 top-down=false}`, and prints them back in the same form.
+
+```ignore
+/// The options of `my-pass`.
+#[derive(Clone, Debug, PassOptions)]
+pub struct MyPassOptions {
+    /// Seed the worklist in general top-down order.
+    #[option(default = true)]
+    top_down: bool,
+    /// Maximum number of iterations to run the pass for.
+    #[option(default = 10)]
+    max_iterations: i64,
+    /// How aggressively to simplify regions.
+    #[option(value_enum, default = SimplificationLevel::Normal)]
+    region_simplify: SimplificationLevel,
+    /// Labels of patterns that should be filtered out during application.
+    #[option(argument = "disable-patterns")]
+    disabled_patterns: Vec<String>
+}
+```
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Pass/PassOptions.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/PassRegistry.cpp>
+*/
+
+use core::fmt;
+use std::error::Error;
+
+use clap::{Arg, ValueEnum};
+
+use crate::support::logical_result::LogicalResult;
+
+pub use mlir_macros::PassOptions;
+
+/**
+This class represents a specific pass option, with a provided data type, or a list of them.
+
+Implemented with `#[derive(PassOptions)]`, which also implements `Default` from the defaults of the options. Each field is an option named after the field, with underscores replaced by dashes, whose value implements `OptionValue`. Fields of type `Vec<T>` are list options, given as comma-separated values, and fields marked `value_enum` hold an enum implementing `clap::ValueEnum`.
+*/
+pub trait PassOptions: Default {
+    /// Parse options out as key=value pairs that can then be handed off to the options of this struct. Options that are not given keep their current value.
+    fn parse_from_string(&mut self, options: &str) -> LogicalResult;
+
+    /// Print the options held by this struct in a form that can be parsed via `parse_from_string`, i.e. `{key=value ...}` with the options sorted by argument.
+    fn print(&self, os: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// Return the description of each option, with its default value, to show in the help of tools.
+    fn option_infos() -> Vec<OptionInfo>;
+}
+
+// ----------------------------------------------------------------------
+// Option Values
+// ----------------------------------------------------------------------
+
+/// A type that can be the value of a pass option, or of the elements of a list option.
+pub trait OptionValue: Sized {
+    /// The name of the values shown in the help of tools, e.g. `uint`.
+    const VALUE_NAME: &'static str;
+
+    /// Parse a value out of `value`, whose escaping braces or quotes have been removed.
+    fn parse_value(value: &str) -> Result<Self, String>;
+
+    /// Print the value in a form `parse_value` accepts.
+    fn print_value(&self, os: &mut dyn fmt::Write) -> fmt::Result;
+
+    /// The value of an option given without `=value`, if it can be, e.g. `true` for boolean options.
+    fn implicit_value() -> Option<Self> {
+        None
+    }
+
+    /// The values an option of this type accepts, shown in the help of tools. Empty if every value of the type is accepted.
+    fn possible_values() -> Vec<String> {
+        Vec::new()
+    }
+}
+
+impl OptionValue for bool {
+    const VALUE_NAME: &'static str = "boolean";
+
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "true" | "TRUE" | "True" | "1" => Ok(true),
+            "false" | "FALSE" | "False" | "0" => Ok(false),
+            _ => Err("expected 'true' or 'false'".to_string())
+        }
+    }
+
+    fn print_value(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        write!(os, "{}", self)
+    }
+
+    fn implicit_value() -> Option<Self> {
+        Some(true)
+    }
+}
+
+macro_rules! impl_option_value {
+    ($($type:ty => $name:literal),*) => {
+        $(
+            impl OptionValue for $type {
+                const VALUE_NAME: &'static str = $name;
+
+                fn parse_value(value: &str) -> Result<Self, String> {
+                    value.parse().map_err(|error| format!("{}", error))
+                }
+
+                fn print_value(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+                    write!(os, "{}", self)
+                }
+            }
+        )*
+    };
+}
+
+impl_option_value!(
+    i8 => "int", i16 => "int", i32 => "int", i64 => "long", isize => "long",
+    u8 => "uint", u16 => "uint", u32 => "uint", u64 => "ulong", usize => "ulong",
+    f32 => "number", f64 => "number"
+);
+
+impl OptionValue for String {
+    const VALUE_NAME: &'static str = "string";
+
+    fn parse_value(value: &str) -> Result<Self, String> {
+        Ok(value.to_string())
+    }
+
+    fn print_value(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        // Escape the strings that would otherwise be split or unescaped when parsed back.
+        let needs_escape = self.starts_with(['"', '\''])
+            || self.contains(|c: char| c.is_whitespace() || matches!(c, ',' | '{' | '}'));
+        if needs_escape {
+            write!(os, "{{{}}}", self)
+        } else {
+            os.write_str(self)
+        }
+    }
+}
+
+/// The value of an option holding an enum implementing `clap::ValueEnum`, which is parsed from and printed as the names of its variants. This is used for the fields marked `value_enum`.
+#[derive(Clone, Debug)]
+pub struct EnumValue<T>(pub T);
+
+impl<T: ValueEnum> OptionValue for EnumValue<T> {
+    const VALUE_NAME: &'static str = "value";
+
+    fn parse_value(value: &str) -> Result<Self, String> {
+        T::from_str(value, false).map(EnumValue)
+    }
+
+    fn print_value(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        // Skipped variants have no name, and can't be printed back.
+        match self.0.to_possible_value() {
+            Some(value) => os.write_str(value.get_name()),
+            None => Err(fmt::Error)
+        }
+    }
+
+    fn possible_values() -> Vec<String> {
+        T::value_variants().iter()
+            .filter_map(|variant| variant.to_possible_value())
+            .map(|value| value.get_name().to_string())
+            .collect()
+    }
+}
+
+// ----------------------------------------------------------------------
+// Parsing
+// ----------------------------------------------------------------------
+
+/// The `key=value` pairs of the options of a pass, with the value None for keys given alone.
+pub type OptionPairs<'a> = Vec<(&'a str, Option<&'a str>)>;
+
+/**
+Split `options`, the text between the braces following a pass in a textual pipeline, into `key=value` pairs separated by whitespace. The value is None if the key is given alone. A value wrapped in braces or quotes may contain whitespace, and is returned without them.
+*/
+pub fn split_options(options: &str) -> Result<OptionPairs<'_>, Box<dyn Error>> {
+    let mut output = Vec::new();
+    let mut rest = options.trim_start();
+    while !rest.is_empty() {
+        // Split the option from the value.
+        let key_end = rest.find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+        let mut value = None;
+        // Check for a value.
+        if let Some(text) = rest.strip_prefix('=') {
+            let value_end = value_end(text).ok_or_else(|| format!(
+                "unbalanced braces or quotes in the value of option '{}'", key))?;
+            value = Some(unescape(&text[..value_end]));
+            rest = &text[value_end..];
+        }
+        if key.is_empty() {
+            return Err(format!("expected an option name in '{}'", options).into());
+        }
+        output.push((key, value));
+        rest = rest.trim_start();
+    }
+    Ok(output)
+}
+
+/// Return the offset of the end of the value at the start of `text`, i.e. of the first whitespace outside of braces and quotes, or None if they are unbalanced.
+fn value_end(text: &str) -> Option<usize> {
+    find_outside_escapes(text, false, char::is_whitespace)
+}
+
+/// Split the value of a list option into its comma-separated elements, skipping the commas in braces, quotes and parentheses, e.g. in the nested pipeline of `func.func(my-pass,snapshot-op-locations)`, and unescape them.
+fn split_list(value: &str) -> Option<Vec<&str>> {
+    let mut output = Vec::new();
+    let mut rest = value;
+    while !rest.trim().is_empty() {
+        let end = find_outside_escapes(rest, true, |c| c == ',')?;
+        output.push(unescape(&rest[..end]));
+        rest = rest[end..].strip_prefix(',').unwrap_or("");
+    }
+    Some(output)
+}
+
+/// Return the offset of the first character of `text` outside of braces and quotes, and parentheses if `parentheses` is set, that matches `predicate`, or the length of `text` if there is none. Returns None if they are unbalanced.
+fn find_outside_escapes(text: &str, parentheses: bool, predicate: impl Fn(char) -> bool)
+    -> Option<usize>
+{
+    let mut depth = 0usize;
+    let mut quote = None;
+    for (offset, c) in text.char_indices() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), _) => {}
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '{') => depth += 1,
+            (None, '}') => depth = depth.checked_sub(1)?,
+            (None, '(') if parentheses => depth += 1,
+            (None, ')') if parentheses => depth = depth.checked_sub(1)?,
+            (None, c) if depth == 0 && predicate(c) => return Some(offset),
+            _ => {}
+        }
+    }
+    (depth == 0 && quote.is_none()).then_some(text.len())
+}
+
+/// Trim `value` and drop the braces or quotes wrapping it, if any.
+fn unescape(value: &str) -> &str {
+    let value = value.trim();
+    for (open, close) in [('{', '}'), ('"', '"'), ('\'', '\'')] {
+        if value.len() >= 2 && value.starts_with(open) && value.ends_with(close) {
+            return value[1..value.len() - 1].trim();
+        }
+    }
+    value
+}
+
+/// Parse the value of the option `argument` out of `value`, None if the option was given without a value.
+pub fn parse_option<T: OptionValue>(argument: &str, value: Option<&str>)
+    -> Result<T, Box<dyn Error>>
+{
+    let Some(value) = value else {
+        return T::implicit_value().ok_or_else(|| format!(
+            "missing value for option '{}'", argument).into());
+    };
+    T::parse_value(value).map_err(|error| format!(
+        "invalid value '{}' for option '{}': {}", value, argument, error).into())
+}
+
+/// Parse the comma-separated values of the list option `argument` out of `value`. An option given without a value is an empty list.
+pub fn parse_list_option<T: OptionValue>(argument: &str, value: Option<&str>)
+    -> Result<Vec<T>, Box<dyn Error>>
+{
+    let value = value.unwrap_or("");
+    let elements = split_list(value).ok_or_else(|| format!(
+        "unbalanced braces, quotes or parentheses in the value of option '{}'", argument))?;
+    elements.into_iter().map(|element| parse_option(argument, Some(element))).collect()
+}
+
+// ----------------------------------------------------------------------
+// Printing
+// ----------------------------------------------------------------------
+
+/// A printer of options in the form `{key=value ...}`, used by the implementations of `PassOptions::print`.
+pub struct OptionsPrinter<'a> {
+    os: &'a mut dyn fmt::Write,
+    /// Whether an option has already been printed, and the next one needs a separator.
+    separate: bool
+}
+
+impl<'a> OptionsPrinter<'a> {
+    /// Start printing options to `os`.
+    pub fn new(os: &'a mut dyn fmt::Write) -> Result<Self, fmt::Error> {
+        os.write_char('{')?;
+        Ok(Self { os, separate: false })
+    }
+
+    /// Print the option `argument` holding `value`.
+    pub fn option<T: OptionValue>(&mut self, argument: &str, value: &T) -> fmt::Result {
+        self.key(argument)?;
+        value.print_value(self.os)
+    }
+
+    /// Print the list option `argument` holding `values`. Empty lists are not printed, as an empty value would be parsed as an empty element.
+    pub fn list_option<T: OptionValue>(&mut self, argument: &str, values: &[T])
+        -> fmt::Result
+    {
+        if values.is_empty() {
+            return Ok(());
+        }
+        self.key(argument)?;
+        print_list(values, self.os)
+    }
+
+    /// Finish printing the options.
+    pub fn finish(self) -> fmt::Result {
+        self.os.write_char('}')
+    }
+
+    fn key(&mut self, argument: &str) -> fmt::Result {
+        if self.separate {
+            self.os.write_char(' ')?;
+        }
+        self.separate = true;
+        write!(self.os, "{}=", argument)
+    }
+}
+
+/// Print `values` separated by commas.
+fn print_list<T: OptionValue>(values: &[T], os: &mut dyn fmt::Write) -> fmt::Result {
+    for (index, value) in values.iter().enumerate() {
+        if index != 0 {
+            os.write_char(',')?;
+        }
+        value.print_value(os)?;
+    }
+    Ok(())
+}
+
+// ----------------------------------------------------------------------
+// Command Line
+// ----------------------------------------------------------------------
+
+/// The description of an option, shown in the help of tools.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionInfo {
+    /// The name of the option in textual pipelines.
+    pub argument: &'static str,
+    pub description: &'static str,
+    /// The name of the values of the option, e.g. `uint`.
+    pub value_name: &'static str,
+    /// The values the option accepts, or empty if it accepts any value of its type.
+    pub possible_values: Vec<String>,
+    /// The default value of the option, in the form it is parsed from.
+    pub default: String,
+    /// Whether the option holds a comma-separated list of values.
+    pub is_list: bool
+}
+
+impl OptionInfo {
+    /// Describe the option `argument` holding a value of type `T`, which is `default` by default.
+    pub fn scalar<T: OptionValue>(
+        argument: &'static str,
+        description: &'static str,
+        default: &T
+    ) -> Self {
+        let mut printed = String::new();
+        // Printing to a string can only fail on skipped enum variants, which have no name to show anyway.
+        let _ = default.print_value(&mut printed);
+        Self {
+            argument,
+            description,
+            value_name: T::VALUE_NAME,
+            possible_values: T::possible_values(),
+            default: printed,
+            is_list: false
+        }
+    }
+
+    /// Describe the list option `argument` holding values of type `T`, which are `default` by default.
+    pub fn list<T: OptionValue>(
+        argument: &'static str,
+        description: &'static str,
+        default: &[T]
+    ) -> Self {
+        let mut printed = String::new();
+        let _ = print_list(default, &mut printed);
+        Self {
+            argument,
+            description,
+            value_name: T::VALUE_NAME,
+            possible_values: T::possible_values(),
+            default: printed,
+            is_list: true
+        }
+    }
+}
+
+impl fmt::Display for OptionInfo {
+    /// Print the option as a line of help, e.g. `--max-iterations=<long> - Max. iterations between applying patterns [default: 10]`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--{}=", self.argument)?;
+        match self.possible_values.is_empty() {
+            true => write!(f, "<{}>", self.value_name)?,
+            false => write!(f, "<{}>", self.possible_values.join("|"))?
+        }
+        if self.is_list {
+            f.write_str(",...")?;
+        }
+        write!(f, " - {}", self.description)?;
+        if !self.default.is_empty() {
+            write!(f, " [default: {}]", self.default)?;
+        }
+        Ok(())
+    }
+}
+
+/**
+Return the command line flag of the pass or pass pipeline `argument`, whose help lists `options`. The flag takes the options of the pass in the form of textual pipelines, without the braces, e.g. `--snapshot-op-locations='filename=snapshot.mlir print-debuginfo'`.
+*/
+pub fn pass_arg(argument: &str, description: &str, options: &[OptionInfo]) -> Arg {
+    let mut arg = Arg::new(argument.to_string())
+        .long(argument.to_string())
+        .help(description.to_string())
+        .num_args(0..=1)
+        .require_equals(true)
+        .default_missing_value("")
+        .value_name("options");
+    if !options.is_empty() {
+        let mut help = format!("{}\n\nOptions:", description);
+        for option in options {
+            help += &format!("\n  {}", option);
+        }
+        arg = arg.long_help(help);
+    }
+    arg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
+    enum SimplificationLevel {
+        Disabled,
+        Normal,
+        Aggressive
+    }
+
+    /// The options of `my-pass`.
+    #[derive(Clone, Debug, PartialEq, PassOptions)]
+    struct MyPassOptions {
+        /// Seed the worklist in general top-down order.
+        #[option(default = true)]
+        top_down: bool,
+        /// Maximum number of iterations to run the pass for.
+        #[option(default = 10)]
+        max_iterations: i64,
+        /// How aggressively to simplify regions.
+        #[option(value_enum, default = SimplificationLevel::Normal)]
+        region_simplify: SimplificationLevel,
+        /// Labels of patterns that should be filtered out during application.
+        #[option(argument = "disable-patterns")]
+        disabled_patterns: Vec<String>,
+        #[option(description = "The pipeline to run on callables.")]
+        pipeline: String
+    }
+
+    fn print(options: &MyPassOptions) -> String {
+        let mut output = String::new();
+        options.print(&mut output).unwrap();
+        output
+    }
+
+    #[test]
+    fn options_are_split_on_whitespace() {
+        assert_eq!(split_options("  a=1\tb  c=x ").unwrap(),
+                   [("a", Some("1")), ("b", None), ("c", Some("x"))]);
+        assert_eq!(split_options("").unwrap(), []);
+        assert_eq!(split_options("pipeline={cse, canonicalize} name=\"a b\" label='c'").unwrap(),
+                   [("pipeline", Some("cse, canonicalize")), ("name", Some("a b")),
+                    ("label", Some("c"))]);
+        assert_eq!(split_options("nested={a={b c}}").unwrap(), [("nested", Some("a={b c}"))]);
+        assert_eq!(split_options("empty=").unwrap(), [("empty", Some(""))]);
+    }
+
+    #[test]
+    fn unbalanced_options_are_rejected() {
+        for options in ["a={b", "a=b}", "a=\"b", "=1"] {
+            assert!(split_options(options).is_err(), "{}", options);
+        }
+    }
+
+    #[test]
+    fn lists_are_split_outside_of_escapes() {
+        assert_eq!(split_list("a, b,c").unwrap(), ["a", "b", "c"]);
+        assert_eq!(split_list("func.func(cse,canonicalize),{x,y},'p,q'").unwrap(),
+                   ["func.func(cse,canonicalize)", "x,y", "p,q"]);
+        assert_eq!(split_list("").unwrap(), Vec::<&str>::new());
+        assert_eq!(split_list("a,"), Some(vec!["a"]));
+        assert_eq!(split_list("f(a,b"), None);
+    }
+
+    #[test]
+    fn values_are_parsed() {
+        assert!(parse_option::<bool>("flag", None).unwrap());
+        assert!(!parse_option::<bool>("flag", Some("0")).unwrap());
+        assert_eq!(parse_option::<u32>("count", Some("42")).unwrap(), 42);
+        assert_eq!(parse_option::<u32>("count", Some("-1")).unwrap_err().to_string(),
+                   "invalid value '-1' for option 'count': invalid digit found in string");
+        assert_eq!(parse_option::<i64>("count", None).unwrap_err().to_string(),
+                   "missing value for option 'count'");
+        assert_eq!(parse_list_option::<i64>("sizes", Some("1, 2,3")).unwrap(), [1, 2, 3]);
+        assert_eq!(parse_list_option::<i64>("sizes", None).unwrap(), []);
+    }
+
+    #[test]
+    fn derived_options_are_parsed_and_printed() {
+        let mut options = MyPassOptions::default();
+        assert_eq!(options, MyPassOptions {
+            top_down: true,
+            max_iterations: 10,
+            region_simplify: SimplificationLevel::Normal,
+            disabled_patterns: Vec::new(),
+            pipeline: String::new()
+        });
+        assert_eq!(print(&options), "{max-iterations=10 pipeline= region-simplify=normal top-down=true}");
+
+        options.parse_from_string(
+            "max-iterations=3 top-down=false region-simplify=aggressive \
+             disable-patterns=a,{b,c} pipeline={func.func(cse, canonicalize)}").unwrap();
+        assert_eq!(options.max_iterations, 3);
+        assert!(!options.top_down);
+        assert_eq!(options.region_simplify, SimplificationLevel::Aggressive);
+        assert_eq!(options.disabled_patterns, ["a", "b,c"]);
+        assert_eq!(options.pipeline, "func.func(cse, canonicalize)");
+
+        let printed = print(&options);
+        assert_eq!(printed, "{disable-patterns=a,{b,c} max-iterations=3 \
+            pipeline={func.func(cse, canonicalize)} region-simplify=aggressive top-down=false}");
+        let mut reparsed = MyPassOptions::default();
+        reparsed.parse_from_string(&printed[1..printed.len() - 1]).unwrap();
+        assert_eq!(reparsed, options);
+    }
+
+    #[test]
+    fn invalid_derived_options_are_reported() {
+        let mut options = MyPassOptions::default();
+        assert!(options.parse_from_string("unknown=1").is_err());
+        assert!(options.parse_from_string("region-simplify=maximal").is_err());
+        assert!(options.parse_from_string("max-iterations").is_err());
+        // Options that are given keep their value when a later one fails.
+        assert!(options.parse_from_string("top-down=false max-iterations=x").is_err());
+        assert!(!options.top_down);
+    }
+
+    #[test]
+    fn options_are_described() {
+        let infos = MyPassOptions::option_infos();
+        let help: Vec<_> = infos.iter().map(ToString::to_string).collect();
+        // Options are described sorted by argument, as they are printed.
+        assert_eq!(help, [
+            "--disable-patterns=<string>,... - Labels of patterns that should be filtered out during application.",
+            "--max-iterations=<long> - Maximum number of iterations to run the pass for. [default: 10]",
+            "--pipeline=<string> - The pipeline to run on callables.",
+            "--region-simplify=<disabled|normal|aggressive> - How aggressively to simplify regions. [default: normal]",
+            "--top-down=<boolean> - Seed the worklist in general top-down order. [default: true]"
+        ]);
+    }
+}
@@ -3,66 +3,235 @@
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/Pass.cpp>
 */
 
+use core::fmt;
+use std::any::{Any, TypeId};
+
 use crate::{
-    mlir::{
-        ir::{
-            diagnostics,
-            dialect,
-            operation::{
-                Operation,
-                definition
-            },
-            threading,
-            verifier
-        },
-        pass::{
-            analysis_manager::{AnalysisManager, PreservedAnalyses},
-            pass_detail,
-            pass_registry
-        },
-        support::file_utilities
+    ir::{
+        arena::{ArenaResult, IRArena, OpId},
+        mlir_context::MLIRContext,
+        operation::support::OperationName
     },
-    llvm::{
-        adt::{
-            pointer_int_pair::PointerIntPair,
-            statistic,
-            stl_extras,
-            scope_exit
-        },
-        support::{
-            command_line,
-            crash_recovery_context,
-            mutex,
-            signals,
-            // threading,
-            tool_output_file
-        }
-    }
+    pass::{
+        analysis_manager::{Analysis, AnalysisManager, PreservedAnalyses},
+        options::OptionInfo,
+        pass_detail::OpToOpPassAdaptor,
+        pass_manager::OpPassManager
+    },
+    support::logical_result::LogicalResult
 };
 
-/// The state for a single execution of a pass. This provides a unified
-/// interface for accessing and initializing necessary state for pass execution.
-pub struct PassExecutionState {
-    /// The current operation being transformed and a bool for if the pass
-    /// signaled a failure.
-    ir_and_pass_failed: PointerIntPair<Option<Operation>, 1, bool>,
+/// A callback of the pass manager, used to run a dynamic pipeline on an operation of an arena, given the analysis manager of the operation the pass is processing.
+pub type PipelineExecutor<'a>
+    = dyn Fn(&mut OpPassManager, &mut IRArena, AnalysisManager, OpId) -> LogicalResult + 'a;
+
+/// The state for a single execution of a pass. This provides a unified interface for accessing and initialising necessary state for pass execution.
+pub struct PassExecutionState<'a> {
+    /// The context of the operation being transformed.
+    context: &'a MLIRContext,
 
-    /// The analysis manager for the operation.
-    analysis_manager: AnalysisManager,
+    /// The arena owning the operation being transformed. While a pass manager runs, the IR is owned by the pass manager rather than by the context, and operations that are isolated from above may be moved to their own arena to be processed in parallel.
+    arena: &'a mut IRArena,
+
+    /// The current operation being transformed.
+    op: OpId,
 
     /// The set of preserved analyses for the current execution.
     preserved_analyses: PreservedAnalyses,
 
-    /// This is a callback in the PassManager that allows to schedule dynamic
-    /// pipelines that will be rooted at the provided operation.
-    // function_ref
-    pipeline_executor: fn((&OpPassManager, Option<Operation>)) -> Result<(), Box<dyn std::error::Error>>
+    /// The analysis manager for the operation being transformed.
+    analysis_manager: AnalysisManager<'a>,
+
+    /// This is a callback in the PassManager that allows to schedule dynamic pipelines that will be rooted at the provided operation.
+    pipeline_executor: &'a PipelineExecutor<'a>
+}
+
+impl<'a> PassExecutionState<'a> {
+    pub(crate) fn new(
+        context: &'a MLIRContext,
+        arena: &'a mut IRArena,
+        op: OpId,
+        analysis_manager: AnalysisManager<'a>,
+        pipeline_executor: &'a PipelineExecutor<'a>
+    ) -> Self {
+        Self {
+            context,
+            arena,
+            op,
+            preserved_analyses: PreservedAnalyses::default(),
+            analysis_manager,
+            pipeline_executor
+        }
+    }
+
+    /// Return the set of analyses the pass marked preserved, ending this execution.
+    pub(crate) fn into_preserved_analyses(self) -> PreservedAnalyses {
+        self.preserved_analyses
+    }
+
+    /// Return the MLIR context for the current operation being transformed.
+    pub fn context(&self) -> &'a MLIRContext {
+        self.context
+    }
+
+    /// Return the arena owning the current operation being transformed.
+    pub fn arena(&self) -> &IRArena {
+        self.arena
+    }
+
+    /// Return the arena owning the current operation being transformed, for mutating it.
+    pub fn arena_mut(&mut self) -> &mut IRArena {
+        self.arena
+    }
+
+    /// Return the current operation being transformed.
+    pub fn op(&self) -> OpId {
+        self.op
+    }
+
+    /**
+    Schedule an arbitrary pass pipeline on the provided operation. This can be invoke any time in a pass to dynamic schedule more passes. The provided operation must be the current one or one nested below.
+    */
+    pub fn run_pipeline(&mut self, pipeline: &mut OpPassManager, op: OpId)
+        -> LogicalResult
+    {
+        (self.pipeline_executor)(
+            pipeline, self.arena, self.analysis_manager.reborrow(), op)
+    }
+
+    /// Returns the current analysis manager.
+    pub fn analysis_manager(&mut self) -> AnalysisManager<'_> {
+        self.analysis_manager.reborrow()
+    }
+
+    /// Query an analysis for the current operation, computing it if it isn't cached.
+    pub fn get_analysis<T: Analysis>(&mut self) -> &T {
+        self.analysis_manager.get_analysis(self.arena)
+    }
+
+    /// Query a cached instance of an analysis for the current operation if it exists.
+    pub fn get_cached_analysis<T: Analysis>(&self) -> Option<&T> {
+        self.analysis_manager.get_cached_analysis()
+    }
+
+    /// Query an analysis for a child operation, computing it if it isn't cached. The child must be nested below the current operation.
+    pub fn get_child_analysis<T: Analysis>(&mut self, child: OpId) -> ArenaResult<&T> {
+        self.analysis_manager.get_child_analysis(self.arena, child)
+    }
+
+    /// Query a cached instance of an analysis for a child operation if it exists.
+    pub fn get_cached_child_analysis<T: Analysis>(&self, child: OpId) -> Option<&T> {
+        self.analysis_manager.get_cached_child_analysis(child)
+    }
+
+    /// Mark all analyses as preserved.
+    pub fn mark_all_analyses_preserved(&mut self) {
+        self.preserved_analyses.preserve_all();
+    }
+
+    /// Mark the analysis `T` as preserved.
+    pub fn mark_analyses_preserved<T: Analysis>(&mut self) {
+        self.preserved_analyses.preserve::<T>();
+    }
 }
 
-/// The abstract base pass class. This class contains information describing the
-/// derived pass object, e.g its kind and abstract TypeID.
-pub struct Pass {
+/**
+The abstract base pass class. This class contains information describing the derived pass object, e.g its kind and abstract TypeID.
+
+Passes are run by an `OpPassManager` on the operations with the name they are anchored on, or on any operation that they can be scheduled on if they are op-agnostic. A pass manager of a context with multithreading enabled runs a copy of the passes on each thread, so passes must be `Send` and provide `clone_pass`.
+*/
+pub trait Pass: Any + Send {
+    /// Returns the derived pass name.
+    fn name(&self) -> &str {
+        std::any::type_name::<Self>()
+    }
+
+    /// Return the command line argument used when registering this pass. Return an empty string if one does not exist.
+    fn argument(&self) -> &str {
+        ""
+    }
+
+    /// Return the command line description used when registering this pass. Return an empty string if one does not exist.
+    fn description(&self) -> &str {
+        ""
+    }
+
+    /// Returns the name of the operation that this pass operates on, or None if this is a generic OperationPass.
+    fn op_name(&self) -> Option<&str> {
+        None
+    }
+
+    /// Indicate if the current pass can be scheduled on the given operation type. For passes anchored on an operation, this is true for the operations with that name. Op-agnostic passes can be scheduled on any operation by default.
+    fn can_schedule_on(&self, name: &OperationName) -> bool {
+        self.op_name().map_or(true, |op_name| op_name == name.as_str())
+    }
+
+    /// Attempt to initialise the options of this pass from the given string, the contents of the braces following the argument of the pass in a textual pipeline. Passes without options only accept an empty string.
+    fn initialise_options(&mut self, options: &str) -> LogicalResult {
+        match options.trim() {
+            "" => Ok(()),
+            options => Err(format!(
+                "pass '{}' has no options, but got '{}'", self.argument(), options).into())
+        }
+    }
+
+    /// Print the options of this pass in the form `{key=value ...}` of textual pipelines, or nothing if the pass has no options.
+    fn print_options(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        Ok(())
+    }
+
+    /// Return the description of the options of this pass, with their default values, to show in the help of tools.
+    fn option_infos(&self) -> Vec<OptionInfo> {
+        Vec::new()
+    }
+
+    /// Initialise any complex state necessary for running this pass. This hook should not rely on any state accessible during the execution of a pass. For example, `context` should be used rather than the context of the operation being executed.
+    fn initialise(&mut self, context: &MLIRContext) -> LogicalResult {
+        Ok(())
+    }
+
+    /// The polymorphic API that runs the pass over the currently held operation.
+    fn run_on_operation(&mut self, state: &mut PassExecutionState) -> LogicalResult;
+
+    /// Create a copy of this pass, ignoring statistics.
+    fn clone_pass(&self) -> Box<dyn Pass>;
 }
 
-struct OpPassManager {
+impl dyn Pass {
+    /// Prints out the pass in the textual representation of pipelines. If this is an adaptor pass, print its pass managers.
+    pub fn print_as_textual_pipeline(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        // Special case for adaptors to print its pass managers.
+        if let Some(adaptor) = self.downcast_ref::<OpToOpPassAdaptor>() {
+            for (index, manager) in adaptor.managers().iter().enumerate() {
+                if index != 0 {
+                    os.write_char(',')?;
+                }
+                manager.print_as_textual_pipeline(os)?;
+            }
+            return Ok(());
+        }
+        // Otherwise, print the pass argument followed by its options. If the pass doesn't have an argument, print its name instead.
+        match self.argument() {
+            "" => write!(os, "unknown<{}>", self.name())?,
+            argument => os.write_str(argument)?
+        }
+        self.print_options(os)
+    }
+
+    /// Return true if this pass is an instance of `T`.
+    pub fn is<T: Pass>(&self) -> bool {
+        self.type_id() == TypeId::of::<T>()
+    }
+
+    /// Return this pass as an instance of `T`, or None if it is another pass.
+    pub fn downcast_ref<T: Pass>(&self) -> Option<&T> {
+        // SAFETY: The concrete type of the pass is `T`.
+        self.is::<T>().then(|| unsafe { &*(self as *const dyn Pass as *const T) })
+    }
+
+    /// Return this pass as an instance of `T`, or None if it is another pass.
+    pub fn downcast_mut<T: Pass>(&mut self) -> Option<&mut T> {
+        // SAFETY: The concrete type of the pass is `T`.
+        self.is::<T>().then(|| unsafe { &mut *(self as *mut dyn Pass as *mut T) })
+    }
 }
@@ -0,0 +1,382 @@
+/*!
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/PassDetail.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/Pass.cpp>
+*/
+
+use core::fmt::Display;
+use std::{
+    collections::HashMap,
+    sync::Mutex
+};
+
+use crate::{
+    ir::{
+        arena::{ArenaResult, IRArena, OpId},
+        builtins::operations::ModuleOp,
+        diagnostics::{ParallelDiagnosticHandler, emit_op_diagnostic_in},
+        mlir_context::MLIRContext,
+        operation::{
+            definition::IsIsolatedFromAbove,
+            support::OperationName
+        },
+        verifier::verify_in
+    },
+    pass::{
+        analysis_manager::{AnalysisManager, NestedAnalysisMap, PreservedAnalyses},
+        pass::{Pass, PassExecutionState},
+        pass_manager::OpPassManager
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
+// OpToOpPassAdaptor
+// ----------------------------------------------------------------------
+
+/// An adaptor pass used to run operation passes over nested operations.
+pub struct OpToOpPassAdaptor {
+    /// The set of OpPassManagers to use when scheduling passes over nested operations.
+    managers: Vec<OpPassManager>,
+
+    /// A set of executors, cloned from the main executor, that run asynchronously on different threads. This is used when threading is enabled.
+    async_executors: Vec<Vec<OpPassManager>>
+}
+
+impl OpToOpPassAdaptor {
+    pub fn new(manager: OpPassManager) -> Self {
+        Self { managers: vec![manager], async_executors: Vec::new() }
+    }
+
+    /// Returns the pass managers held by this adaptor.
+    pub fn managers(&self) -> &[OpPassManager] {
+        &self.managers
+    }
+
+    /// Returns the pass managers held by this adaptor, for adding passes to them.
+    pub fn managers_mut(&mut self) -> &mut [OpPassManager] {
+        &mut self.managers
+    }
+
+    /// Try to merge the current pass adaptor into `rhs`. This will try to append the pass managers of this adaptor into those within `rhs`, or return false if merging isn't possible. The main situation in which merging is not possible is if one of the adaptors has an `any` pipeline that is not compatible with a pass manager in the other adaptor. For example, if this adaptor has a `func.func` pipeline and `rhs` has an `any` pipeline that operates on FunctionOpInterface. In this situation the pipelines have a conflict (they both want to run on the same operations), so we can't merge.
+    pub(crate) fn try_merge_into(&mut self, context: &MLIRContext, rhs: &mut Self)
+        -> bool
+    {
+        // Return true if the op-agnostic pass manager `generic` will have a potential schedule conflict with `others`.
+        let has_schedule_conflict_with = |generic: &OpPassManager, others: &[OpPassManager]|
+            others.iter().any(|manager| match manager.op_name() {
+                // If this is a non-generic pass manager, a conflict will arise if a non-generic pass manager's operation name can be scheduled on the generic pass manager.
+                Some(name) => generic.can_schedule_on(&context.operation_name(name)),
+                // Otherwise, this is a generic pass manager. We current can't determine when generic pass managers can be merged, so conservatively assume they conflict.
+                None => true
+            });
+
+        // Check that if either adaptor has a generic pass manager, that pm is compatible within any non-generic pass managers.
+        let lhs_generic = self.managers.iter().find(|manager| manager.op_name().is_none());
+        if lhs_generic.map_or(false, |generic|
+            has_schedule_conflict_with(generic, &rhs.managers))
+        {
+            return false;
+        }
+        let rhs_generic = rhs.managers.iter().find(|manager| manager.op_name().is_none());
+        if rhs_generic.map_or(false, |generic|
+            has_schedule_conflict_with(generic, &self.managers))
+        {
+            return false;
+        }
+
+        for mut manager in self.managers.drain(..) {
+            // If an existing pass manager exists, then merge the given pass manager into it. Otherwise, add the given pass manager to the list.
+            match rhs.managers.iter_mut()
+                .find(|other| other.op_anchor_name() == manager.op_anchor_name())
+            {
+                Some(existing) => manager.merge_into(existing),
+                None => rhs.managers.push(manager)
+            }
+        }
+
+        // After coalescing, sort the pass managers within rhs by name. Op-specific pass managers come first and op-agnostic ones last.
+        rhs.managers.sort_by(|lhs, rhs| match (lhs.op_name(), rhs.op_name()) {
+            (Some(lhs), Some(rhs)) => lhs.cmp(rhs),
+            (lhs, rhs) => rhs.is_some().cmp(&lhs.is_some())
+        });
+        rhs.async_executors.clear();
+        true
+    }
+
+    /// Finalise the pass lists of the pass managers held by this adaptor.
+    pub(crate) fn finalise(&mut self, context: &MLIRContext) -> LogicalResult {
+        for manager in self.managers.iter_mut() {
+            manager.finalise_pass_list(context)?;
+        }
+        Ok(())
+    }
+
+    /// Run this pass adaptor synchronously or asynchronously, depending on whether multithreading is enabled on `context`.
+    fn run_on_operation_impl(
+        &mut self,
+        context: &MLIRContext,
+        arena: &mut IRArena,
+        op: OpId,
+        am: AnalysisManager,
+        verify_passes: bool
+    ) -> LogicalResult {
+        if context.is_multithreading_enabled() {
+            self.run_on_operation_async_impl(context, arena, op, am, verify_passes)
+        } else {
+            self.run_on_operation_sync_impl(context, arena, op, am, verify_passes)
+        }
+    }
+
+    /// Run this pass adaptor synchronously.
+    fn run_on_operation_sync_impl(
+        &mut self,
+        context: &MLIRContext,
+        arena: &mut IRArena,
+        op: OpId,
+        mut am: AnalysisManager,
+        verify_passes: bool
+    ) -> LogicalResult {
+        for nested in nested_operations(arena, op)? {
+            let name = arena.op(nested)?.name();
+            let Some(manager) = self.managers.iter_mut()
+                .find(|manager| manager.can_schedule_on(name))
+            else {
+                continue;
+            };
+            // Run the held pipeline over the current operation.
+            let generation = manager.initialisation_generation;
+            let am = am.nest(arena, nested)?;
+            Self::run_pipeline(manager, context, arena, nested, am, verify_passes, generation)?;
+        }
+        Ok(())
+    }
+
+    /**
+    Run this pass adaptor asynchronously.
+
+    Each nested operation is processed in an overlay of `arena` that owns it along with the IR nested in it, while the rest of the IR stays visible but read-only, so that the workers of the thread pool of `context` can transform the operations independently. The scheduling is deterministic: the operations are dealt in order to the workers, each running its own copy of the pass managers, the diagnostics of the workers are reported in the order of the operations, and when several operations fail, the error of the first one is returned.
+
+    The analyses of an operation are computed in an analysis map of its own. As for the synchronous case, they are cleared once its pipeline ran, and the analyses cached for the nested operations in `am` are invalidated along with those of `op` after this adaptor.
+    */
+    fn run_on_operation_async_impl(
+        &mut self,
+        context: &MLIRContext,
+        arena: &mut IRArena,
+        op: OpId,
+        mut am: AnalysisManager,
+        verify_passes: bool
+    ) -> LogicalResult {
+        // Collect the operations to schedule, along with the index of the pass manager to run on each.
+        let mut jobs = Vec::new();
+        let mut known_managers: HashMap<String, Option<usize>> = HashMap::new();
+        for nested in nested_operations(arena, op)? {
+            let name = arena.op(nested)?.name();
+            let manager = *known_managers.entry(name.as_str().to_string())
+                .or_insert_with(|| self.managers.iter()
+                    .position(|manager| manager.can_schedule_on(name)));
+            if let Some(manager) = manager {
+                jobs.push((manager, nested));
+            }
+        }
+
+        // If there is a single worker, there is no need for overlays.
+        let num_workers = jobs.len().min(context.num_threads());
+        if num_workers <= 1 {
+            for (manager, nested) in jobs {
+                let manager = &mut self.managers[manager];
+                let generation = manager.initialisation_generation;
+                let am = am.nest(arena, nested)?;
+                Self::run_pipeline(
+                    manager, context, arena, nested, am, verify_passes, generation)?;
+            }
+            return Ok(());
+        }
+
+        // Create the async executors if they haven't been created, or if the main pipeline has changed.
+        if self.async_executors.len() < num_workers
+            || has_size_mismatch(&self.async_executors[0], &self.managers)
+        {
+            self.async_executors = (0..context.num_threads())
+                .map(|_| self.managers.clone()).collect();
+        }
+
+        // Run the workers on overlays of the arena, each owning one of the operations and the IR nested in it, while the rest of the arena is only visible. The operations keep their handles, so there is nothing to map back once they are processed.
+        let executors: Vec<Mutex<&mut Vec<OpPassManager>>>
+            = self.async_executors.iter_mut().map(Mutex::new).collect();
+        // The failures are reported through the diagnostics of the workers, so only their messages are moved out of the workers.
+        let errors: Vec<Mutex<Option<String>>>
+            = jobs.iter().map(|_| Mutex::new(None)).collect();
+        let roots: Vec<OpId> = jobs.iter().map(|(_, nested)| *nested).collect();
+        arena.with_overlays(&roots, |overlays| {
+            let overlays: Vec<Mutex<&mut IRArena>> = overlays.iter_mut().map(Mutex::new).collect();
+            // Order the diagnostics of the workers by the index of the operation they were emitted for, as if the operations were processed sequentially.
+            let diagnostic_handler = ParallelDiagnosticHandler::new(context);
+            // Each operation, overlay, executor and error slot is only accessed by the worker it is dealt to.
+            context.thread_pool().broadcast(num_workers, |worker| {
+                let mut managers = executors[worker].lock().unwrap();
+                for index in (worker..jobs.len()).step_by(num_workers) {
+                    let (manager, nested) = jobs[index];
+                    let manager = &mut managers[manager];
+                    let generation = manager.initialisation_generation;
+                    let mut arena = overlays[index].lock().unwrap();
+                    let mut analyses = NestedAnalysisMap::new(nested);
+                    diagnostic_handler.set_order_id_for_thread(index);
+                    if let Err(error) = Self::run_pipeline(
+                        manager, context, &mut arena, nested, AnalysisManager::new(&mut analyses),
+                        verify_passes, generation)
+                    {
+                        *errors[index].lock().unwrap() = Some(error.to_string());
+                    }
+                    diagnostic_handler.erase_order_id_for_thread();
+                }
+            });
+        })?;
+        match errors.into_iter().find_map(|error| error.into_inner().unwrap()) {
+            None => Ok(()),
+            Some(message) => Err(message.into())
+        }
+    }
+
+    /**
+    Run the given operation on a single pass.
+
+    The operation must be registered and isolated from above. After the pass, the operation is verified if `verify_passes` is set, unless the pass failed, and the analyses of `am` that the pass didn't preserve are invalidated.
+    */
+    pub(crate) fn run(
+        pass: &mut dyn Pass,
+        context: &MLIRContext,
+        arena: &mut IRArena,
+        op: OpId,
+        mut am: AnalysisManager,
+        verify_passes: bool,
+        parent_initialisation_generation: usize
+    ) -> LogicalResult {
+        let name = arena.op(op)?.name().clone();
+        // `builtin.module` is isolated from above but isn't registered by the builtin dialect yet.
+        if name.as_str() != ModuleOp::OPERATION_NAME {
+            if !name.is_registered() {
+                return emit_op_failure(context, arena, op,
+                    "trying to schedule a pass on an unregistered operation");
+            }
+            if !name.has_trait::<dyn IsIsolatedFromAbove>() {
+                return emit_op_failure(context, arena, op,
+                    "trying to schedule a pass on an operation not marked as 'IsolatedFromAbove'");
+            }
+        }
+        if !pass.can_schedule_on(&name) {
+            let message = format!(
+                "trying to schedule pass '{}' on an unsupported operation", pass.name());
+            return emit_op_failure(context, arena, op, message);
+        }
+
+        // Invoke the pass, with a callback for it to dynamically execute a pipeline on the currently visited operation.
+        let is_adaptor = pass.is::<OpToOpPassAdaptor>();
+        let (result, preserved) = match pass.downcast_mut::<OpToOpPassAdaptor>() {
+            // Adaptors don't preserve any analyses, since the passes they run may have modified the nested operations.
+            Some(adaptor) => (
+                adaptor.run_on_operation_impl(context, arena, op, am.reborrow(), verify_passes),
+                PreservedAnalyses::default()
+            ),
+            None => {
+                let pipeline_executor = |
+                    pipeline: &mut OpPassManager,
+                    arena: &mut IRArena,
+                    mut am: AnalysisManager,
+                    root: OpId
+                | -> LogicalResult {
+                    if !arena.is_ancestor(op, root)? {
+                        return emit_op_failure(context, arena, op,
+                            "Trying to schedule a dynamic pipeline on an operation that isn't nested under the current operation the pass is processing");
+                    }
+                    let root_name = arena.op(root)?.name();
+                    if !pipeline.can_schedule_on(root_name) {
+                        let message = format!(
+                            "can't run '{}' pass manager on '{}' op",
+                            pipeline.op_anchor_name(), root_name.as_str());
+                        return emit_op_failure(context, arena, root, message);
+                    }
+                    // Before running, finalise the passes held by the pipeline, then initialise it.
+                    pipeline.finalise_pass_list(context)?;
+                    pipeline.initialise(context, parent_initialisation_generation)?;
+                    // The dynamic pipeline uses the analyses of `root`, nested in those of the current operation.
+                    let am = match root == op {
+                        true => am,
+                        false => am.nest(arena, root)?
+                    };
+                    Self::run_pipeline(pipeline, context, arena, root, am, verify_passes,
+                        parent_initialisation_generation)
+                };
+                let mut state = PassExecutionState::new(
+                    context, arena, op, am.reborrow(), &pipeline_executor);
+                let result = pass.run_on_operation(&mut state);
+                (result, state.into_preserved_analyses())
+            }
+        };
+
+        // Invalidate any non preserved analyses.
+        am.invalidate(&preserved);
+
+        // Run the verifier if this pass didn't fail already. Adaptors already verified the operations nested in `op` after running their passes, so only `op` itself is left.
+        if result.is_ok() && verify_passes {
+            verify_in(context, arena, op, !is_adaptor)?;
+        }
+        result
+    }
+
+    /// Run the given operation on the pipeline held by `manager`, then clear the analyses of `am`, which are only kept while the pipeline runs.
+    pub(crate) fn run_pipeline(
+        manager: &mut OpPassManager,
+        context: &MLIRContext,
+        arena: &mut IRArena,
+        op: OpId,
+        mut am: AnalysisManager,
+        verify_passes: bool,
+        parent_initialisation_generation: usize
+    ) -> LogicalResult {
+        let result = manager.passes.iter_mut().try_for_each(|pass|
+            Self::run(pass.as_mut(), context, arena, op, am.reborrow(), verify_passes,
+                parent_initialisation_generation));
+        // Clear out any computed operation analyses. These analyses won't be used any more in this pipeline, and this helps reduce the current memory usage.
+        am.clear();
+        result
+    }
+}
+
+impl Pass for OpToOpPassAdaptor {
+    fn run_on_operation(&mut self, state: &mut PassExecutionState) -> LogicalResult {
+        unreachable!("Unexpected call to Pass::run_on_operation for OpToOpPassAdaptor");
+    }
+
+    fn clone_pass(&self) -> Box<dyn Pass> {
+        Box::new(Self { managers: self.managers.clone(), async_executors: Vec::new() })
+    }
+}
+
+/// Return the operations directly nested in the regions of `op`, in order.
+fn nested_operations(arena: &IRArena, op: OpId) -> ArenaResult<Vec<OpId>> {
+    let mut operations = Vec::new();
+    for region in arena.op(op)?.regions() {
+        for block in arena.region(*region)?.blocks() {
+            operations.extend_from_slice(arena.block(*block)?.operations());
+        }
+    }
+    Ok(operations)
+}
+
+/// Utility function to return if two pass managers lists have a size mismatch, in which case the async executors need to be recreated.
+fn has_size_mismatch(lhs: &[OpPassManager], rhs: &[OpPassManager]) -> bool {
+    lhs.len() != rhs.len()
+        || lhs.iter().zip(rhs).any(|(lhs, rhs)| lhs.len() != rhs.len())
+}
+
+/// Emit an error about `op` through the diagnostic engine of `context`, and return it as a failure.
+pub(crate) fn emit_op_failure(
+    context: &MLIRContext,
+    arena: &IRArena,
+    op: OpId,
+    message: impl Display
+) -> LogicalResult {
+    let diagnostic = arena.op(op)?.emit_op_error(message);
+    emit_op_diagnostic_in(context, arena, op, diagnostic.clone());
+    Err(Box::new(diagnostic))
+}
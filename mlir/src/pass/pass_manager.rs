@@ -2,428 +2,504 @@
 # Pass Management Interface
 
 - include <https://github.com/llvm/llvm-project/tree/main/mlir/include/mlir/Pass/PassManager.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/Pass.cpp>
 */
 
-//===----------------------------------------------------------------------===//
+use core::{
+    fmt,
+    ops::{Deref, DerefMut}
+};
+
+use crate::{
+    ir::{
+        arena::{IRArena, OpId},
+        builtins::operations::ModuleOp,
+        diagnostics::emit_error,
+        location::Location,
+        mlir_context::MLIRContext,
+        operation::{
+            definition::{IsIsolatedFromAbove, Op},
+            support::OperationName
+        }
+    },
+    pass::{
+        analysis_manager::ModuleAnalysisManager,
+        pass::Pass,
+        pass_detail::{OpToOpPassAdaptor, emit_op_failure}
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
 // OpPassManager
-//===----------------------------------------------------------------------===//
-
-/// This class represents a pass manager that runs passes on either a specific
-/// operation type, or any isolated operation. This pass manager can not be run
-/// on an operation directly, but must be run either as part of a top-level
-/// `PassManager`(e.g. when constructed via `nest` calls), or dynamically within
-/// a pass by using the `Pass::runPipeline` API.
-class OpPassManager {
-public:
-  /// This enum represents the nesting behavior of the pass manager.
-  enum class Nesting {
-    /// Implicit nesting behavior. This allows for adding passes operating on
-    /// operations different from this pass manager, in which case a new pass
-    /// manager is implicitly nested for the operation type of the new pass.
+// ----------------------------------------------------------------------
+
+/// This enum represents the nesting behaviour of the pass manager.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Nesting {
+    /// Implicit nesting behaviour. This allows for adding passes operating on operations different from this pass manager, in which case a new pass manager is implicitly nested for the operation type of the new pass.
     Implicit,
-    /// Explicit nesting behavior. This requires that any passes added to this
-    /// pass manager support its operation type.
+    /// Explicit nesting behaviour. This requires that any passes added to this pass manager support its operation type.
+    #[default]
     Explicit
-  };
-
-  /// Construct a new op-agnostic ("any") pass manager with the given operation
-  /// type and nesting behavior. This is the same as invoking:
-  /// `OpPassManager(getAnyOpAnchorName(), nesting)`.
-  OpPassManager(Nesting nesting = Nesting::Explicit);
-
-  /// Construct a new pass manager with the given anchor operation type and
-  /// nesting behavior.
-  OpPassManager(StringRef name, Nesting nesting = Nesting::Explicit);
-  OpPassManager(OperationName name, Nesting nesting = Nesting::Explicit);
-  OpPassManager(OpPassManager &&rhs);
-  OpPassManager(const OpPassManager &rhs);
-  ~OpPassManager();
-  OpPassManager &operator=(const OpPassManager &rhs);
-  OpPassManager &operator=(OpPassManager &&rhs);
-
-  /// Iterator over the passes in this pass manager.
-  using pass_iterator =
-      llvm::pointee_iterator<MutableArrayRef<std::unique_ptr<Pass>>::iterator>;
-  pass_iterator begin();
-  pass_iterator end();
-  iterator_range<pass_iterator> getPasses() { return {begin(), end()}; }
-
-  using const_pass_iterator =
-      llvm::pointee_iterator<ArrayRef<std::unique_ptr<Pass>>::const_iterator>;
-  const_pass_iterator begin() const;
-  const_pass_iterator end() const;
-  iterator_range<const_pass_iterator> getPasses() const {
-    return {begin(), end()};
-  }
-
-  /// Returns true if the pass manager has no passes.
-  bool empty() const { return begin() == end(); }
-
-  /// Nest a new operation pass manager for the given operation kind under this
-  /// pass manager.
-  OpPassManager &nest(OperationName nestedName);
-  OpPassManager &nest(StringRef nestedName);
-  template <typename OpT>
-  OpPassManager &nest() {
-    return nest(OpT::getOperationName());
-  }
-
-  /// Nest a new op-agnostic ("any") pass manager under this pass manager.
-  /// Note: This is the same as invoking `nest(getAnyOpAnchorName())`.
-  OpPassManager &nestAny();
-
-  /// Add the given pass to this pass manager. If this pass has a concrete
-  /// operation type, it must be the same type as this pass manager.
-  void addPass(std::unique_ptr<Pass> pass);
-
-  /// Clear the pipeline, but not the other options set on this OpPassManager.
-  void clear();
-
-  /// Add the given pass to a nested pass manager for the given operation kind
-  /// `OpT`.
-  template <typename OpT>
-  void addNestedPass(std::unique_ptr<Pass> pass) {
-    nest<OpT>().addPass(std::move(pass));
-  }
-
-  /// Returns the number of passes held by this manager.
-  size_t size() const;
-
-  /// Return the operation name that this pass manager operates on, or
-  /// std::nullopt if this is an op-agnostic pass manager.
-  Option<OperationName> getOpName(MLIRContext &context) const;
-
-  /// Return the operation name that this pass manager operates on, or
-  /// std::nullopt if this is an op-agnostic pass manager.
-  Option<StringRef> getOpName() const;
-
-  /// Return the name used to anchor this pass manager. This is either the name
-  /// of an operation, or the result of `getAnyOpAnchorName()` in the case of an
-  /// op-agnostic pass manager.
-  StringRef getOpAnchorName() const;
-
-  /// Return the string name used to anchor op-agnostic pass managers that
-  /// operate generically on any viable operation.
-  static StringRef getAnyOpAnchorName() { return "any"; }
-
-  /// Returns the internal implementation instance.
-  detail::OpPassManagerImpl &getImpl();
-
-  /// Prints out the passes of the pass manager as the textual representation
-  /// of pipelines.
-  /// Note: The quality of the string representation depends entirely on the
-  /// the correctness of per-pass overrides of Pass::printAsTextualPipeline.
-  void printAsTextualPipeline(raw_ostream &os) const;
-
-  /// Raw dump of the pass manager to llvm::errs().
-  void dump();
-
-  /// Merge the pass statistics of this class into 'other'.
-  void mergeStatisticsInto(OpPassManager &other);
-
-  /// Register dependent dialects for the current pass manager.
-  /// This is forwarding to every pass in this PassManager, see the
-  /// documentation for the same method on the Pass class.
-  void getDependentDialects(DialectRegistry &dialects) const;
-
-  /// Enable or disable the implicit nesting on this particular PassManager.
-  /// This will also apply to any newly nested PassManager built from this
-  /// instance.
-  void setNesting(Nesting nesting);
-
-  /// Return the current nesting mode.
-  Nesting getNesting();
-
-private:
-  /// Initialize all of the passes within this pass manager with the given
-  /// initialization generation. The initialization generation is used to detect
-  /// if a pass manager has already been initialized.
-  contextL *mut LogicalResult initialize(MLIRContext, unsigned newInitGeneration);
-
-  /// A pointer to an internal implementation instance.
-  std::unique_ptr<detail::OpPassManagerImpl> impl;
-
-  /// Allow access to initialize.
-  friend detail::OpToOpPassAdaptor;
-
-  /// Allow access to the constructor.
-  friend class PassManager;
-  friend class Pass;
-
-  /// Allow access.
-  friend detail::OpPassManagerImpl;
-};
+}
 
-//===----------------------------------------------------------------------===//
-// PassManager
-//===----------------------------------------------------------------------===//
-
-/// An enum describing the different display modes for the information within
-/// the pass manager.
-enum class PassDisplayMode {
-  // In this mode the results are displayed in a list sorted by total,
-  // with each pass/analysis instance aggregated into one unique result.
-  List,
-
-  // In this mode the results are displayed in a nested pipeline view that
-  // mirrors the internal pass pipeline that is being executed in the pass
-  // manager.
-  Pipeline,
-};
+/**
+This class represents a pass manager that runs passes on either a specific operation type, or any isolated operation. This pass manager can not be run on an operation directly, but must be run either as part of a top-level `PassManager`(e.g. when constructed via `nest` calls), or dynamically within a pass by using the `PassExecutionState::run_pipeline` API.
+*/
+pub struct OpPassManager {
+    /// The name of the operation that passes of this pass manager operate on, or `ANY_OP_ANCHOR_NAME` for an op-agnostic pass manager.
+    name: String,
 
-/// The main pass manager and pipeline builder.
-pub struct PassManager {
-    /// Context this PassManager was initialized with.
-    contextL *mut MLIRContext,
+    /// The set of passes to run as part of this pass manager.
+    pub(crate) passes: Vec<Box<dyn Pass>>,
 
-    /// Flag that specifies if pass statistics should be dumped.
-    passStatisticsModeL Option<PassDisplayMode>,
+    /// Control the implicit nesting of passes that mismatch the name set for this OpPassManager.
+    nesting: Nesting,
 
-    /// A manager for pass instrumentations.
-    instrumentor: std::unique_ptr<PassInstrumentor>,
+    /// The current initialisation generation of this pass manager. This is used to indicate when a pass manager should be reinitialised.
+    pub(crate) initialisation_generation: usize
+}
 
-    /// An optional crash reproducer generator, if this pass manager is setup to
-    /// generate reproducers.
-    crashReproGenerator: std::unique_ptr<detail::PassCrashReproducerGenerator>,
+impl OpPassManager {
+    /// The anchor name of op-agnostic pass managers.
+    pub const ANY_OP_ANCHOR_NAME: &'static str = "any";
+
+    /// Construct a new pass manager with the given anchor operation type and nesting behaviour.
+    pub fn new(name: impl Into<String>, nesting: Nesting /* Explicit */) -> Self {
+        Self {
+            name: name.into(),
+            passes: Vec::new(),
+            nesting,
+            initialisation_generation: 0
+        }
+    }
 
-    /// A hash key used to detect when reinitialization is necessary.
-    initializationKey: llvm::hash_code,
+    /// Construct a new op-agnostic ("any") pass manager with the given nesting behaviour. This is the same as invoking: `OpPassManager::new(OpPassManager::ANY_OP_ANCHOR_NAME, nesting)`.
+    pub fn any(nesting: Nesting /* Explicit */) -> Self {
+        Self::new(Self::ANY_OP_ANCHOR_NAME, nesting)
+    }
 
-    /// Flag that specifies if pass timing is enabled.
-    passTiming: bool, // : 1;
+    /// Returns the passes of this pass manager, in the order they are run.
+    pub fn passes(&self) -> &[Box<dyn Pass>] {
+        &self.passes
+    }
 
-    /// A flag that indicates if the IR should be verified in between passes.
-    verifyPasses: bool // : 1;
-}
+    /// Returns true if the pass manager has no passes.
+    pub fn is_empty(&self) -> bool {
+        self.passes.is_empty()
+    }
+
+    /// Returns the number of passes held by this manager.
+    pub fn len(&self) -> usize {
+        self.passes.len()
+    }
+
+    /// Nest a new operation pass manager for the given operation kind under this pass manager.
+    pub fn nest<T: Op>(&mut self) -> &mut OpPassManager {
+        self.nest_name(T::OPERATION_NAME)
+    }
+
+    /// Nest a new operation pass manager for the operation named `name` under this pass manager.
+    pub fn nest_name(&mut self, name: &str) -> &mut OpPassManager {
+        let nested = OpPassManager::new(name, self.nesting);
+        self.nest_manager(nested)
+    }
+
+    /// Nest a new op-agnostic ("any") pass manager under this pass manager. Note: This is the same as invoking `nest_name(OpPassManager::ANY_OP_ANCHOR_NAME)`.
+    pub fn nest_any(&mut self) -> &mut OpPassManager {
+        self.nest_name(Self::ANY_OP_ANCHOR_NAME)
+    }
 
-impl PassManager : public OpPassManager {
-public:
-    /// Create a new pass manager under the given context with a specific nesting
-    /// style. The created pass manager can schedule operations that match
-    /// `operationName`.
-    /// FIXME: We should make the specification of `builtin.module` explicit here,
-    /// so that we can have top-level op-agnostic pass managers.
-    PassManager(MLIRContext *ctx, Nesting nesting = Nesting::Explicit,
-                StringRef operationName = "builtin.module");
-    PassManager(MLIRContext *ctx, StringRef operationName)
-        : PassManager(ctx, Nesting::Explicit, operationName) {}
-    ~PassManager();
-
-    /// Run the passes within this manager on the provided operation. The
-    /// specified operation must have the same name as the one provided the pass
-    /// manager on construction.
-    LogicalResult run(Operation *op);
-
-    /// Return an instance of the context.
-    MLIRContext *getContext() const { return context; }
+    /// Nest the given pass manager under this pass manager, through an adaptor running it on the operations nested in those of this pass manager.
+    fn nest_manager(&mut self, nested: OpPassManager) -> &mut OpPassManager {
+        self.passes.push(Box::new(OpToOpPassAdaptor::new(nested)));
+        self.passes.last_mut()
+            .and_then(|pass| pass.downcast_mut::<OpToOpPassAdaptor>())
+            .and_then(|adaptor| adaptor.managers_mut().first_mut())
+            .unwrap()
+    }
 
     /**
-    Enable support for the pass manager to generate a reproducer on the event
-    of a crash or a pass failure. `outputFile` is a .mlir filename used to
-    write the generated reproducer. If `genLocalReproducer` is true, the pass
-    manager will attempt to generate a local reproducer that contains the
-    smallest pipeline.
+    Add the given pass to this pass manager. If this pass has a concrete operation type, it must be the same type as this pass manager.
+
+    # Panics
+
+    If the pass is anchored on another operation than this pass manager, and the nesting behaviour is explicit.
     */
-    void enableCrashReproducerGeneration(
-        StringRef outputFile,
-        genLocalReproducer: bool = false);
-
-    /// Method type for constructing ReproducerStream.
-    using ReproducerStreamFactory =
-        std::function<std::unique_ptr<ReproducerStream>(std::string &error)>;
-
-    /// Enable support for the pass manager to generate a reproducer on the event
-    /// of a crash or a pass failure. `factory` is used to construct the streams
-    /// to write the generated reproducer to. If `genLocalReproducer` is true, the
-    /// pass manager will attempt to generate a local reproducer that contains the
-    /// smallest pipeline.
-    void enableCrashReproducerGeneration(ReproducerStreamFactory factory,
-                                        genLocalReproducer: bool = false);
+    pub fn add_pass(&mut self, pass: Box<dyn Pass>) {
+        // If this pass runs on a different operation than this pass manager, then implicitly nest a pass manager for this operation if enabled.
+        if let (Some(name), Some(pass_name)) = (self.op_name(), pass.op_name()) {
+            if name != pass_name {
+                if self.nesting == Nesting::Implicit {
+                    let pass_name = pass_name.to_string();
+                    return self.nest_name(&pass_name).add_pass(pass);
+                }
+                panic!(
+                    "Can't add pass '{}' restricted to '{}' on a PassManager intended to run on '{}', did you intend to nest?",
+                    pass.name(), pass_name, self.op_anchor_name());
+            }
+        }
+        self.passes.push(pass);
+    }
 
-    /// Runs the verifier after each individual pass.
-    void enableVerifier(enabled: bool = true);
+    /// Add the given pass to a nested pass manager for the given operation kind `T`.
+    pub fn add_nested_pass<T: Op>(&mut self, pass: Box<dyn Pass>) {
+        self.nest::<T>().add_pass(pass)
+    }
 
-    //===--------------------------------------------------------------------===//
-    // Instrumentations
-    //===--------------------------------------------------------------------===//
+    /// Clear the pipeline, but not the other options set on this OpPassManager.
+    pub fn clear(&mut self) {
+        self.passes.clear();
+    }
 
-    /// Add the provided instrumentation to the pass manager.
-    void addInstrumentation(std::unique_ptr<PassInstrumentation> pi);
+    /// Return the operation name that this pass manager operates on, or None if this is an op-agnostic pass manager.
+    pub fn op_name(&self) -> Option<&str> {
+        (self.name != Self::ANY_OP_ANCHOR_NAME).then_some(self.name.as_str())
+    }
 
-    //===--------------------------------------------------------------------===//
-    // IR Printing
+    /// Return the name used to anchor this pass manager. This is either the name of an operation, or the result of `ANY_OP_ANCHOR_NAME` in the case of an op-agnostic pass manager.
+    pub fn op_anchor_name(&self) -> &str {
+        &self.name
+    }
 
-    /// Add an instrumentation to print the IR before and after pass execution,
-    /// using the provided configuration.
-    void enableIRPrinting(std::unique_ptr<IRPrinterConfig> config);
+    /// Enable or disable the implicit nesting on this particular PassManager. This will also apply to any newly nested PassManager built from this instance.
+    pub fn set_nesting(&mut self, nesting: Nesting) {
+        self.nesting = nesting;
+    }
 
-    /**
-    Add an instrumentation to print the IR before and after pass execution,
-    using the provided fields to generate a default configuration:
-
-    - 'shouldPrintBeforePass' and 'shouldPrintAfterPass' correspond to filter functions that take a 'Pass *' and `Operation *`. These function should return true if the IR should be printed or not.
-    - 'printModuleScope' signals if the module IR should be printed, even for non module passes.
-    - 'printAfterOnlyOnChange' signals that when printing the IR after a pass, in the case of a non-failure, we should first check if any potential mutations were made.
-    - 'printAfterOnlyOnFailure' signals that when printing the IR after a pass, we only print in the case of a failure.
-        - This option should *not* be used with the other `printAfter` flags above.
-    - 'out' corresponds to the stream to output the printed IR to.
-    - 'opPrintingFlags' sets up the printing flags to use when printing the IR.
-    */
-    pub fn enableIRPrinting(
-        std::function<bool(Pass *, Operation *)> shouldPrintBeforePass =
-            [](Pass *, Operation *) { return true; },
-        std::function<bool(Pass *, Operation *)> shouldPrintAfterPass =
-            [](Pass *, Operation *) { return true; },
-        printModuleScope: bool = true,
-        printAfterOnlyOnChange: bool = true,
-        printAfterOnlyOnFailure: bool = false,
-        raw_ostream &out = llvm::errs(),
-        OpPrintingFlags opPrintingFlags = OpPrintingFlags()
-    );
-
-    //===--------------------------------------------------------------------===//
-    // Pass Timing
+    /// Return the current nesting mode.
+    pub fn nesting(&self) -> Nesting {
+        self.nesting
+    }
 
-    /**
-    Add an instrumentation to time the execution of passes and the computation of analyses. Timing will be reported by nesting timers into the provided `timingScope`.
+    /// Prints out the passes of the pass manager as the textual representation of pipelines, e.g. `builtin.module(func.func(snapshot-op-locations),my-pass)`. This is the form parsed by `parse_pass_pipeline`.
+    pub fn print_as_textual_pipeline(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        write!(os, "{}(", self.op_anchor_name())?;
+        for (index, pass) in self.passes.iter().enumerate() {
+            if index != 0 {
+                os.write_char(',')?;
+            }
+            pass.print_as_textual_pipeline(os)?;
+        }
+        os.write_char(')')
+    }
 
-    NOTE: Timing should be enabled after all other instrumentations to avoid any potential "ghost" timing from other instrumentations being unintentionally included in the timing results.
-    */
-    void enableTiming(TimingScope &timingScope);
+    /// Raw dump of the pass manager to stderr.
+    pub fn dump(&self) {
+        eprintln!("Pass Manager with {} passes:", self.len());
+        eprintln!("{}", self);
+    }
 
-    /**
-    Add an instrumentation to time the execution of passes and the computation of analyses. The pass manager will take ownership of the timing manager passed to the function and timing will be reported by nesting timers into the timing manager's root scope.
+    /// Merge the passes of this pass manager into the one provided. The passes of this manager run first.
+    pub(crate) fn merge_into(&mut self, other: &mut OpPassManager) {
+        assert_eq!(self.name, other.name,
+            "Expected pass managers with the same anchor to be merged");
+        other.passes.splice(0..0, self.passes.drain(..));
+    }
 
-    NOTE: Timing should be enabled after all other instrumentations to avoid any potential "ghost" timing from other instrumentations being unintentionally included in the timing results.
-    */
-    void enableTiming(std::unique_ptr<TimingManager> tm);
+    /// Indicate if this pass manager can be scheduled on operations named `name`. An op-specific pass manager can only be scheduled on the operations it is anchored on, and an op-agnostic one on the operations that are isolated from above that all of its passes can be scheduled on.
+    pub(crate) fn can_schedule_on(&self, name: &OperationName) -> bool {
+        if let Some(op_name) = self.op_name() {
+            return op_name == name.as_str();
+        }
+        // `builtin.module` is isolated from above but isn't registered by the builtin dialect yet.
+        (name.has_trait::<dyn IsIsolatedFromAbove>()
+            || name.as_str() == ModuleOp::OPERATION_NAME)
+            && self.passes.iter().all(|pass| pass.can_schedule_on(name))
+    }
 
-    /**
-    Add an instrumentation to time the execution of passes and the computation of analyses. Creates a temporary TimingManager owned by this PassManager which will be used to report timing.
+    /// Finalise the pass list in preparation for execution. This includes coalescing adjacent pass managers when possible, verifying scheduled passes, etc.
+    pub(crate) fn finalise_pass_list(&mut self, context: &MLIRContext) -> LogicalResult {
+        // Walk the pass list and merge adjacent adaptors.
+        let mut keep = vec![true; self.passes.len()];
+        let mut last_adaptor: Option<usize> = None;
+        for index in 0..self.passes.len() {
+            if self.passes[index].is::<OpToOpPassAdaptor>() {
+                // If it is the first adaptor in a possible chain, remember it and continue.
+                let Some(last) = last_adaptor else {
+                    last_adaptor = Some(index);
+                    continue;
+                };
+                // Otherwise, try to merge into the existing adaptor and delete the current one. If merging fails, just remember this as the last adaptor.
+                let (head, tail) = self.passes.split_at_mut(index);
+                let last = head[last].downcast_mut::<OpToOpPassAdaptor>().unwrap();
+                let current = tail[0].downcast_mut::<OpToOpPassAdaptor>().unwrap();
+                if current.try_merge_into(context, last) {
+                    keep[index] = false;
+                } else {
+                    last_adaptor = Some(index);
+                }
+            } else if let Some(last) = last_adaptor.take() {
+                // If this pass isn't an adaptor, finalise it and forget the last adaptor.
+                self.passes[last].downcast_mut::<OpToOpPassAdaptor>().unwrap()
+                    .finalise(context)?;
+            }
+        }
+        // If there was an adaptor at the end of the manager, finalise it as well.
+        if let Some(last) = last_adaptor {
+            self.passes[last].downcast_mut::<OpToOpPassAdaptor>().unwrap()
+                .finalise(context)?;
+        }
+        // Now that the adaptors have been merged, erase the ones that were merged into the adaptors before them.
+        let mut keep = keep.into_iter();
+        self.passes.retain(|_| keep.next().unwrap());
+
+        // If this is a op-agnostic pass manager, there is nothing left to do.
+        let Some(op_name) = self.op_name() else {
+            return Ok(());
+        };
+        // Otherwise, verify that all of the passes are valid for the current operation anchor.
+        let name = context.operation_name(op_name);
+        if !name.is_registered() {
+            return Ok(());
+        }
+        for pass in self.passes.iter() {
+            if !pass.can_schedule_on(&name) {
+                let message = format!(
+                    "unable to schedule pass '{}' on a PassManager intended to run on '{}'!",
+                    pass.name(), self.op_anchor_name());
+                emit_error(context, Location::Unknown, message.clone());
+                return Err(message.into());
+            }
+        }
+        Ok(())
+    }
 
-    NOTE: Timing should be enabled after all other instrumentations to avoid any potential "ghost" timing from other instrumentations being unintentionally included in the timing results.
-    */
-    void enableTiming();
+    /// Initialise all of the passes within this pass manager with the given initialisation generation. The initialisation generation is used to detect if a pass manager has already been initialised.
+    pub(crate) fn initialise(&mut self, context: &MLIRContext, generation: usize)
+        -> LogicalResult
+    {
+        if self.initialisation_generation == generation {
+            return Ok(());
+        }
+        self.initialisation_generation = generation;
+
+        // If this pass manager contains adaptors, initialise the nested pass managers, otherwise the pass itself.
+        for pass in self.passes.iter_mut() {
+            match pass.downcast_mut::<OpToOpPassAdaptor>() {
+                Some(adaptor) => for manager in adaptor.managers_mut() {
+                    manager.initialise(context, generation)?;
+                },
+                None => pass.initialise(context)?
+            }
+        }
+        Ok(())
+    }
+}
 
-    //===--------------------------------------------------------------------===//
-    // Pass Statistics
+impl Clone for OpPassManager {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            passes: self.passes.iter().map(|pass| pass.clone_pass()).collect(),
+            nesting: self.nesting,
+            initialisation_generation: self.initialisation_generation
+        }
+    }
+}
 
-    /**
-    Prompts the pass manager to print the statistics collected for each of the held passes after each call to 'run'.
-    */
-    void
-    enableStatistics(PassDisplayMode displayMode = PassDisplayMode::Pipeline);
-
-// private:
-   /// Dump the statistics of the passes within this pass manager.
-   void dumpStatistics();
-
-   /// Run the pass manager with crash recovery enabled.
-   LogicalResult runWithCrashRecovery(Operation *op, AnalysisManager am);
- 
-   /// Run the passes of the pass manager, and return the result.
-   LogicalResult runPasses(Operation *op, AnalysisManager am);
+impl fmt::Display for OpPassManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.print_as_textual_pipeline(f)
+    }
 }
 
-/// Streams on which to output crash reproducer.
-pub struct ReproducerStream {
-    virtual ~ReproducerStream() = default;
+impl Default for OpPassManager {
+    fn default() -> Self {
+        Self::any(Nesting::Explicit)
+    }
+}
+
+// ----------------------------------------------------------------------
+// PassManager
+// ----------------------------------------------------------------------
 
-    /// Description of the reproducer stream.
-    virtual StringRef description() = 0;
+/// The main pass manager and pipeline builder.
+pub struct PassManager {
+    /// The pass manager of the passes run on the top-level operation.
+    manager: OpPassManager,
 
-    /// Stream on which to output reproducer.
-    virtual raw_ostream &os() = 0;
+    /// Flag that specifies if the IR should be verified after each pass has run.
+    verify_passes: bool
 }
 
-/// A configuration struct provided to the IR printer instrumentation.
-pub struct IRPrinterConfig {
-    /// A flag that indicates if the IR should be printed at module scope.
-    printModuleScope: bool,
+impl PassManager {
+    /// Create a new pass manager under the given context with a specific nesting style. The created pass manager can schedule operations that match `operation_name`, usually `ModuleOp::OPERATION_NAME`.
+    pub fn new(
+        operation_name: impl Into<String>,
+        nesting: Nesting /* Explicit */
+    ) -> Self {
+        Self {
+            manager: OpPassManager::new(operation_name, nesting),
+            verify_passes: true
+        }
+    }
+
+    /// Create a new pass manager for the top-level operation kind `T`.
+    pub fn on<T: Op>(nesting: Nesting /* Explicit */) -> Self {
+        Self::new(T::OPERATION_NAME, nesting)
+    }
 
     /**
-    A flag that indicates that the IR after a pass should only be printed if a change is detected.
-    */
-    printAfterOnlyOnChange: bool,
+    Run the passes within this manager on the provided operation of the arena of `context`. The passes should not use the arena of the context while they run: the pass manager holds the IR, which is handed to them through their `PassExecutionState`.
 
-    /**
-    A flag that indicates that the IR after a pass should only be printed if the pass failed.
+    If multithreading is enabled on `context`, the operations that are isolated from above are processed in parallel on its thread pool.
     */
-    printAfterOnlyOnFailure: bool,
+    pub fn run(&mut self, context: &mut MLIRContext, op: OpId) -> LogicalResult {
+        let mut arena = core::mem::take(context.arena_mut());
+        let result = self.run_in(context, &mut arena, op);
+        *context.arena_mut() = arena;
+        result
+    }
+
+    /// Same as `run`, for an operation of `arena` rather than of the arena of `context`.
+    pub fn run_in(&mut self, context: &MLIRContext, arena: &mut IRArena, op: OpId)
+        -> LogicalResult
+    {
+        // Before running, make sure that the pass manager can be run on the operation.
+        if let Some(anchor) = self.manager.op_name() {
+            if anchor != arena.op(op)?.name().as_str() {
+                let message = format!(
+                    "can't run '{}' pass manager on '{}' op",
+                    anchor, arena.op(op)?.name().as_str());
+                return emit_op_failure(context, arena, op, message);
+            }
+        }
+
+        // Finalise the pipeline pass list, then initialise all of the passes within the pass manager with a new generation. The context doesn't expose a hash of its registry to detect whether it changed, so the passes are reinitialised on each run.
+        self.manager.finalise_pass_list(context)?;
+        let generation = self.manager.initialisation_generation + 1;
+        self.manager.initialise(context, generation)?;
+
+        // Construct a top level analysis manager for the pipeline.
+        let mut am = ModuleAnalysisManager::new(op);
+        OpToOpPassAdaptor::run_pipeline(&mut self.manager, context, arena, op,
+            am.analysis_manager(), self.verify_passes, generation)
+    }
 
-    /// Flags to control printing behavior.
-    opPrintingFlags: OpPrintingFlags
+    /// Runs the verifier after each individual pass.
+    pub fn enable_verifier(&mut self, enabled: bool /* true */) {
+        self.verify_passes = enabled;
+    }
 }
 
-impl IRPrinterConfig {
-// public:
-    using PrintCallbackFn = function_ref<void(raw_ostream &)>;
-
-    /// Initialize the configuration.
-    /// * 'printModuleScope' signals if the top-level module IR should always be
-    ///   printed. This should only be set to true when multi-threading is
-    ///   disabled, otherwise we may try to print IR that is being modified
-    ///   asynchronously.
-    /// * 'printAfterOnlyOnChange' signals that when printing the IR after a
-    ///   pass, in the case of a non-failure, we should first check if any
-    ///   potential mutations were made. This allows for reducing the number of
-    ///   logs that don't contain meaningful changes.
-    /// * 'printAfterOnlyOnFailure' signals that when printing the IR after a
-    ///   pass, we only print in the case of a failure.
-    ///     - This option should *not* be used with the other `printAfter` flags
-    ///       above.
-    /// * 'opPrintingFlags' sets up the printing flags to use when printing the
-    ///   IR.
-    explicit IRPrinterConfig(
-        printModuleScope: bool = false, printAfterOnlyOnChange: bool = false,
-        printAfterOnlyOnFailure: bool = false,
-        OpPrintingFlags opPrintingFlags = OpPrintingFlags());
-    virtual ~IRPrinterConfig();
-
-    /// A hook that may be overridden by a derived config that checks if the IR
-    /// of 'operation' should be dumped *before* the pass 'pass' has been
-    /// executed. If the IR should be dumped, 'printCallback' should be invoked
-    /// with the stream to dump into.
-    virtual void printBeforeIfEnabled(Pass *pass, Operation *operation,
-                                    PrintCallbackFn printCallback);
-
-    /// A hook that may be overridden by a derived config that checks if the IR
-    /// of 'operation' should be dumped *after* the pass 'pass' has been
-    /// executed. If the IR should be dumped, 'printCallback' should be invoked
-    /// with the stream to dump into.
-    virtual void printAfterIfEnabled(Pass *pass, Operation *operation,
-                                    PrintCallbackFn printCallback);
-
-    /// Returns true if the IR should always be printed at the top-level scope.
-    bool shouldPrintAtModuleScope() const { return printModuleScope; }
-
-    /// Returns true if the IR should only printed after a pass if the IR
-    /// "changed".
-    bool shouldPrintAfterOnlyOnChange() const { return printAfterOnlyOnChange; }
-
-    /// Returns true if the IR should only printed after a pass if the pass
-    /// "failed".
-    bool shouldPrintAfterOnlyOnFailure() const {
-    return printAfterOnlyOnFailure;
-    }
-
-    /// Returns the printing flags to be used to print the IR.
-    OpPrintingFlags getOpPrintingFlags() const { return opPrintingFlags; }
-  
-// private:
+impl Deref for PassManager {
+    type Target = OpPassManager;
+
+    fn deref(&self) -> &Self::Target {
+        &self.manager
+    }
 }
 
-/**
-Register a set of useful command-line options that can be used to configure a pass manager. The values of these options can be applied via the 'applyPassManagerCLOptions' method below.
-*/
-void registerPassManagerCLOptions();
+impl DerefMut for PassManager {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.manager
+    }
+}
 
-/**
-Apply any values provided to the pass manager options that were registered with 'registerPassManagerOptions'.
-*/
-void applyPassManagerCLOptions(PassManager &pm);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pass::pass::PassExecutionState;
 
-/**
-Apply any values provided to the timing manager options that were registered with `registerDefaultTimingManagerOptions`. This is a handy helper function if you do not want to bother creating your own timing manager and passing it to the pass manager.
-*/
-void applyDefaultTimingPassManagerCLOptions(PassManager &pm);
+    /// An op-agnostic pass.
+    #[derive(Clone)]
+    struct AnyPass;
+
+    impl Pass for AnyPass {
+        fn argument(&self) -> &str {
+            "any-pass"
+        }
+
+        fn run_on_operation(&mut self, _state: &mut PassExecutionState) -> LogicalResult {
+            Ok(())
+        }
+
+        fn clone_pass(&self) -> Box<dyn Pass> {
+            Box::new(self.clone())
+        }
+    }
+
+    /// A pass anchored on `func.func`.
+    #[derive(Clone)]
+    struct FuncPass;
+
+    impl Pass for FuncPass {
+        fn argument(&self) -> &str {
+            "func-pass"
+        }
+
+        fn op_name(&self) -> Option<&str> {
+            Some("func.func")
+        }
+
+        fn run_on_operation(&mut self, _state: &mut PassExecutionState) -> LogicalResult {
+            Ok(())
+        }
+
+        fn clone_pass(&self) -> Box<dyn Pass> {
+            Box::new(self.clone())
+        }
+    }
+
+    #[test]
+    fn passes_are_nested_by_anchor() {
+        let mut pm = PassManager::new("builtin.module", Nesting::Explicit);
+        pm.nest_name("func.func").add_pass(Box::new(FuncPass));
+        pm.add_pass(Box::new(AnyPass));
+        pm.nest_any().add_pass(Box::new(AnyPass));
+        assert_eq!(pm.len(), 3);
+        assert_eq!(pm.op_name(), Some("builtin.module"));
+        assert_eq!(pm.to_string(), "builtin.module(func.func(func-pass),any-pass,any(any-pass))");
+
+        let adaptor = pm.passes()[0].downcast_ref::<OpToOpPassAdaptor>().unwrap();
+        let [nested] = adaptor.managers() else {
+            panic!("expected a single nested pass manager");
+        };
+        assert_eq!(nested.op_name(), Some("func.func"));
+        assert!(nested.can_schedule_on(&OperationName::new("func.func")));
+        assert!(!nested.can_schedule_on(&OperationName::new("builtin.module")));
+        assert_eq!(OpPassManager::default().op_name(), None);
+    }
+
+    #[test]
+    fn anchored_passes_are_nested_implicitly() {
+        let mut pm = OpPassManager::new("builtin.module", Nesting::Implicit);
+        pm.add_pass(Box::new(FuncPass));
+        pm.add_pass(Box::new(AnyPass));
+        assert_eq!(pm.to_string(), "builtin.module(func.func(func-pass),any-pass)");
+
+        // Nested pass managers inherit the nesting behaviour.
+        let nested = pm.nest_name("test.op");
+        assert_eq!(nested.nesting(), Nesting::Implicit);
+        nested.add_pass(Box::new(FuncPass));
+        assert_eq!(pm.to_string(),
+                   "builtin.module(func.func(func-pass),any-pass,test.op(func.func(func-pass)))");
+    }
+
+    #[test]
+    #[should_panic(expected = "did you intend to nest?")]
+    fn anchored_passes_are_not_nested_explicitly() {
+        let mut pm = OpPassManager::new("builtin.module", Nesting::Explicit);
+        pm.add_pass(Box::new(FuncPass));
+    }
+
+    #[test]
+    fn pass_managers_are_cloned_and_merged() {
+        let mut pm = OpPassManager::new("builtin.module", Nesting::Explicit);
+        pm.nest_name("func.func").add_pass(Box::new(FuncPass));
+        let copy = pm.clone();
+        pm.clear();
+        assert!(pm.is_empty());
+        assert_eq!(copy.to_string(), "builtin.module(func.func(func-pass))");
+
+        let mut first = OpPassManager::any(Nesting::Explicit);
+        first.add_pass(Box::new(FuncPass));
+        let mut second = OpPassManager::any(Nesting::Explicit);
+        second.add_pass(Box::new(AnyPass));
+        first.merge_into(&mut second);
+        assert!(first.is_empty());
+        assert_eq!(second.to_string(), "any(func-pass,any-pass)");
+    }
+}
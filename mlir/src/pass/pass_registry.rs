@@ -0,0 +1,960 @@
+/*!
+# Pass Registry
+
+Passes and pass pipelines are registered with an argument, which refers to them in textual pipelines and on the command line of tools, a description and options. Registering at link time lets the passes of every linked crate show up in drivers like `mlir-opt` without being listed there:
+
+```ignore
+#[derive(Clone, Default)]
+pub struct MyPass;
+
+impl Pass for MyPass {
+    fn argument(&self) -> &str {
+        "my-pass"
+    }
+    ...
+}
+
+mlir::register_pass!(MyPass);
+
+mlir::register_pass_pipeline!(
+    "my-pipeline", "Run my pass and snapshot the locations of its output",
+    |pm: &mut OpPassManager| parse_pass_pipeline_elements("my-pass,snapshot-op-locations", pm));
+```
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Pass/PassRegistry.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Pass/PassRegistry.cpp>
+*/
+
+use core::fmt::{self, Display};
+use std::{
+    collections::BTreeMap,
+    error::Error,
+    sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard}
+};
+
+use clap::{Arg, ArgAction, ArgMatches, Command};
+
+use crate::{
+    pass::{
+        options::{OptionInfo, OptionValue, pass_arg},
+        pass::Pass,
+        pass_manager::{Nesting, OpPassManager}
+    },
+    support::logical_result::LogicalResult
+};
+
+pub use inventory;
+
+/// A function creating an instance of a registered pass.
+pub type PassAllocatorFunction = fn() -> Box<dyn Pass>;
+
+/// A function adding a registered pass pipeline to a pass manager, configured with the given options, i.e. the text between the braces following the argument of the pipeline in a textual pipeline.
+pub type PassPipelineFunction = fn(&mut OpPassManager, &str) -> LogicalResult;
+
+// ----------------------------------------------------------------------
+// PassRegistry
+// ----------------------------------------------------------------------
+
+/// A pass registered at link time with `register_pass!`.
+#[derive(Clone, Copy)]
+pub struct PassRegistration(pub PassAllocatorFunction);
+
+inventory::collect!(PassRegistration);
+
+/// A registry entry for a pass, created from an allocator of the pass.
+#[derive(Clone)]
+pub struct PassInfo {
+    argument: String,
+    description: String,
+    allocator: PassAllocatorFunction
+}
+
+impl PassInfo {
+    /// Register a specific dialect pass allocator function with the system. The argument and the description of the pass are queried from an instance of it.
+    pub fn new(allocator: PassAllocatorFunction) -> Self {
+        let pass = allocator();
+        Self {
+            argument: pass.argument().to_string(),
+            description: pass.description().to_string(),
+            allocator
+        }
+    }
+
+    /// Returns the command line option that may be passed to `mlir-opt` that will cause this pass to run or None if there is no such argument.
+    pub fn argument(&self) -> &str {
+        &self.argument
+    }
+
+    /// Returns a description for the pass.
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Create a new instance of the pass, with its default options.
+    pub fn create_pass(&self) -> Box<dyn Pass> {
+        (self.allocator)()
+    }
+
+    /// Return the description of the options of the pass.
+    pub fn option_infos(&self) -> Vec<OptionInfo> {
+        self.create_pass().option_infos()
+    }
+
+    /// Adds this pass registry entry to the given pass manager, configured with `options`.
+    pub fn add_to_pipeline(&self, pm: &mut OpPassManager, options: &str) -> LogicalResult {
+        let mut pass = self.create_pass();
+        pass.initialise_options(options)?;
+        pm.add_pass(pass);
+        Ok(())
+    }
+}
+
+/// A registry entry for a pass pipeline, registered at link time with `register_pass_pipeline!`, or at runtime with `register_pass_pipeline`.
+#[derive(Clone, Copy)]
+pub struct PassPipelineInfo {
+    argument: &'static str,
+    description: &'static str,
+    options: fn() -> Vec<OptionInfo>,
+    builder: PassPipelineFunction
+}
+
+inventory::collect!(PassPipelineInfo);
+
+impl PassPipelineInfo {
+    /// Register the pipeline `argument`, which is added to pass managers by `builder`, and whose options are described by `options`.
+    pub const fn new(
+        argument: &'static str,
+        description: &'static str,
+        options: fn() -> Vec<OptionInfo>,
+        builder: PassPipelineFunction
+    ) -> Self {
+        Self { argument, description, options, builder }
+    }
+
+    /// Returns the command line option that may be passed to `mlir-opt` that will cause this pipeline to run.
+    pub fn argument(&self) -> &'static str {
+        self.argument
+    }
+
+    /// Returns a description for the pass pipeline.
+    pub fn description(&self) -> &'static str {
+        self.description
+    }
+
+    /// Return the description of the options of the pipeline.
+    pub fn option_infos(&self) -> Vec<OptionInfo> {
+        (self.options)()
+    }
+
+    /// Adds this pass pipeline to the given pass manager, configured with `options`.
+    pub fn add_to_pipeline(&self, pm: &mut OpPassManager, options: &str) -> LogicalResult {
+        (self.builder)(pm, options)
+    }
+}
+
+/**
+The registry of the passes and pass pipelines that can be referred to by their argument in textual pipelines and on the command line of tools.
+
+The global registry holds the passes and pipelines registered at link time by every linked crate with `register_pass!` and `register_pass_pipeline!`, and the ones registered since with `register_pass` and `register_pass_pipeline`.
+*/
+#[derive(Clone, Default)]
+pub struct PassRegistry {
+    passes: BTreeMap<String, PassInfo>,
+    pipelines: BTreeMap<&'static str, PassPipelineInfo>
+}
+
+impl PassRegistry {
+    /// Return the global registry.
+    pub fn global() -> RwLockReadGuard<'static, PassRegistry> {
+        Self::global_lock().read().unwrap()
+    }
+
+    fn global_mut() -> RwLockWriteGuard<'static, PassRegistry> {
+        Self::global_lock().write().unwrap()
+    }
+
+    fn global_lock() -> &'static RwLock<PassRegistry> {
+        static REGISTRY: OnceLock<RwLock<PassRegistry>> = OnceLock::new();
+        REGISTRY.get_or_init(|| {
+            let mut registry = PassRegistry::default();
+            for registration in inventory::iter::<PassRegistration> {
+                registry.register_pass(registration.0);
+            }
+            for info in inventory::iter::<PassPipelineInfo> {
+                registry.register_pass_pipeline(*info);
+            }
+            RwLock::new(registry)
+        })
+    }
+
+    /// Register the pass created by `allocator`. Panics if the pass has no argument, or if a pass or pass pipeline is already registered with it.
+    pub fn register_pass(&mut self, allocator: PassAllocatorFunction) {
+        let info = PassInfo::new(allocator);
+        if info.argument().is_empty() {
+            panic!("Attempting to register a pass that does not have an argument: {}",
+                info.create_pass().name());
+        }
+        self.check_unique(info.argument());
+        self.passes.insert(info.argument.clone(), info);
+    }
+
+    /// Register the pass pipeline `info`. Panics if a pass or pass pipeline is already registered with its argument.
+    pub fn register_pass_pipeline(&mut self, info: PassPipelineInfo) {
+        self.check_unique(info.argument());
+        self.pipelines.insert(info.argument(), info);
+    }
+
+    fn check_unique(&self, argument: &str) {
+        if self.passes.contains_key(argument) || self.pipelines.contains_key(argument) {
+            panic!("Pass pipeline or pass registration with the same argument '{}' already exists",
+                argument);
+        }
+    }
+
+    /// Returns the pass info for the specified pass argument or None if unknown.
+    pub fn lookup_pass_info(&self, argument: &str) -> Option<&PassInfo> {
+        self.passes.get(argument)
+    }
+
+    /// Returns the pass pipeline info for the specified pass pipeline argument or None if unknown.
+    pub fn lookup_pass_pipeline_info(&self, argument: &str) -> Option<&PassPipelineInfo> {
+        self.pipelines.get(argument)
+    }
+
+    /// Return the registered passes, sorted by argument.
+    pub fn passes(&self) -> impl Iterator<Item = &PassInfo> {
+        self.passes.values()
+    }
+
+    /// Return the registered pass pipelines, sorted by argument.
+    pub fn pass_pipelines(&self) -> impl Iterator<Item = &PassPipelineInfo> {
+        self.pipelines.values()
+    }
+}
+
+/// Register the pass created by `allocator` in the global registry. Panics if the pass has no argument, or if a pass or pass pipeline is already registered with it.
+pub fn register_pass(allocator: PassAllocatorFunction) {
+    PassRegistry::global_mut().register_pass(allocator);
+}
+
+/// Register the pass pipeline `info` in the global registry. Panics if a pass or pass pipeline is already registered with its argument.
+pub fn register_pass_pipeline(info: PassPipelineInfo) {
+    PassRegistry::global_mut().register_pass_pipeline(info);
+}
+
+/**
+Register the pass `$pass` in the global registry at link time, created with `Default::default()`, or with the given allocator.
+
+```ignore
+mlir::register_pass!(LocationSnapshotPass);
+mlir::register_pass!(|| Box::new(LocationSnapshotPass::new("snapshot.mlir", None)));
+```
+*/
+#[macro_export]
+macro_rules! register_pass {
+    (|| $allocator:expr) => {
+        $crate::pass::pass_registry::inventory::submit! {
+            $crate::pass::pass_registry::PassRegistration(|| $allocator)
+        }
+    };
+    ($pass:ty) => {
+        $crate::pass::pass_registry::inventory::submit! {
+            $crate::pass::pass_registry::PassRegistration(
+                || ::std::boxed::Box::new(<$pass as ::core::default::Default>::default()))
+        }
+    };
+}
+
+/**
+Register a pass pipeline in the global registry at link time. The pipeline is built by `$builder`, a function taking the pass manager to add the pipeline to, and the options of the pipeline if their type is given, which derives `PassOptions`.
+
+```ignore
+mlir::register_pass_pipeline!(
+    "my-pipeline", "Run my pass and snapshot the locations of its output",
+    |pm: &mut OpPassManager| parse_pass_pipeline_elements("my-pass,snapshot-op-locations", pm));
+mlir::register_pass_pipeline!(
+    "my-tuned-pipeline", "Run my pass a few times", MyPipelineOptions,
+    |pm: &mut OpPassManager, options: &MyPipelineOptions| { ... });
+```
+*/
+#[macro_export]
+macro_rules! register_pass_pipeline {
+    ($argument:expr, $description:expr, $builder:expr) => {
+        $crate::pass::pass_registry::inventory::submit! {
+            $crate::pass::pass_registry::PassPipelineInfo::new(
+                $argument, $description, ::std::vec::Vec::new,
+                |pm: &mut $crate::pass::pass_manager::OpPassManager, options: &str| {
+                    if !options.trim().is_empty() {
+                        return ::core::result::Result::Err(::std::format!(
+                            "pass pipeline '{}' has no options, but got '{}'",
+                            $argument, options.trim()).into());
+                    }
+                    ($builder)(pm)
+                })
+        }
+    };
+    ($argument:expr, $description:expr, $options:ty, $builder:expr) => {
+        $crate::pass::pass_registry::inventory::submit! {
+            $crate::pass::pass_registry::PassPipelineInfo::new(
+                $argument, $description,
+                <$options as $crate::pass::options::PassOptions>::option_infos,
+                |pm: &mut $crate::pass::pass_manager::OpPassManager, options: &str| {
+                    let mut parsed = <$options as ::core::default::Default>::default();
+                    $crate::pass::options::PassOptions::parse_from_string(&mut parsed, options)?;
+                    ($builder)(pm, &parsed)
+                })
+        }
+    };
+}
+
+// ----------------------------------------------------------------------
+// TextualPassPipeline Parser
+// ----------------------------------------------------------------------
+
+/**
+This function parses the textual representation of a pass pipeline, and adds the result to `pm` on success. The resolved pass pipeline is not anchored on an operation, e.g. `func.func(my-pass,snapshot-op-locations),my-pass`. The passes and pass pipelines are looked up by argument in the global registry, and configured with the options between the braces following it, if any. On failure, `pm` is left unchanged.
+*/
+pub fn parse_pass_pipeline_elements(pipeline: &str, pm: &mut OpPassManager)
+    -> LogicalResult
+{
+    let elements = TextualPipeline::parse(pipeline, 0, pipeline.len())?;
+    let mut staged = OpPassManager::new(pm.op_anchor_name(), pm.nesting());
+    elements.add_to_pipeline(&elements.elements, &mut staged)?;
+    pm.passes.append(&mut staged.passes);
+    Ok(())
+}
+
+/**
+This function parses the textual representation of a pass pipeline, and returns an OpPassManager anchored on the operation type of the pipeline, e.g. `builtin.module(func.func(my-pass,snapshot-op-locations{filename=snapshot.mlir}))`. This is the form printed by `OpPassManager::print_as_textual_pipeline`.
+*/
+pub fn parse_pass_pipeline(pipeline: &str) -> Result<OpPassManager, Box<dyn Error>>
+{
+    // Pipelines are expected to be of the form `<op-name>(<pipeline>)`.
+    let content = pipeline.trim_end();
+    let start = pipeline.find('(')
+        .filter(|start| !pipeline[..*start].trim().is_empty() && content.ends_with(')'));
+    let Some(start) = start else {
+        return Err(pipeline_error(pipeline, 0,
+            "expected pass pipeline to be wrapped with the anchor operation type, e.g. 'builtin.module(...)'"));
+    };
+    let elements = TextualPipeline::parse(pipeline, start + 1, content.len() - 1)?;
+    let mut pm = OpPassManager::new(pipeline[..start].trim(), Nesting::Explicit);
+    elements.add_to_pipeline(&elements.elements, &mut pm)?;
+    Ok(pm)
+}
+
+/**
+Parse the textual representation of a pass pipeline anchored on an operation type, and add its passes to `pm`, which must be anchored on the same operation type. This is used to apply the pipelines given on the command line of tools, e.g. `--pass-pipeline='builtin.module(func.func(snapshot-op-locations))'`. On failure, `pm` is left unchanged.
+*/
+pub fn parse_pass_pipeline_into(pipeline: &str, pm: &mut OpPassManager) -> LogicalResult {
+    let mut parsed = parse_pass_pipeline(pipeline)?;
+    if parsed.op_anchor_name() != pm.op_anchor_name() {
+        return Err(format!(
+            "expected the pass pipeline to be anchored on '{}', but got '{}'",
+            pm.op_anchor_name(), parsed.op_anchor_name()).into());
+    }
+    pm.passes.append(&mut parsed.passes);
+    Ok(())
+}
+
+/// A pass, a pass pipeline or an operation anchoring a nested pipeline in the textual representation of a pipeline.
+struct PipelineElement<'a> {
+    /// The argument of the pass or pipeline, or the name of the operation anchoring the nested pipeline.
+    name: &'a str,
+    /// The offset of `name` in the text of the pipeline, used to report errors.
+    position: usize,
+    /// The text between the braces following the name, if any.
+    options: &'a str,
+    /// The nested pipeline between the parentheses following the name, if any.
+    inner_pipeline: Option<Vec<PipelineElement<'a>>>
+}
+
+/// This class represents a textual description of a pass pipeline.
+struct TextualPipeline<'a> {
+    /// The text of the pipeline, including the anchor of an anchored pipeline.
+    text: &'a str,
+    /// The parsed elements of the pipeline.
+    elements: Vec<PipelineElement<'a>>
+}
+
+impl<'a> TextualPipeline<'a> {
+    /**
+    Parse the elements of the pipeline between `start` and `end` of `text`. The structure of the pipeline is:
+
+    ```text
+    pipeline     ::= (pipeline-element (`,` pipeline-element)*)?
+    pipeline-element ::= op-anchor `(` pipeline `)`
+                       | pass-or-pipeline-name (`{` options `}`)?
+    ```
+    */
+    fn parse(text: &'a str, start: usize, end: usize) -> Result<Self, Box<dyn Error>> {
+        let mut stack: Vec<Vec<PipelineElement<'a>>> = vec![Vec::new()];
+        let mut position = skip_whitespace(text, start, end);
+        // An empty pipeline has no element.
+        while position < end {
+            // Parse the name of the element, which goes up to the next separator.
+            let name_end = text[position..end]
+                .find([',', '(', ')', '{'])
+                .map_or(end, |offset| position + offset);
+            stack.last_mut().unwrap().push(PipelineElement {
+                name: text[position..name_end].trim(),
+                position,
+                options: "",
+                inner_pipeline: None
+            });
+            position = name_end;
+            if position == end {
+                break;
+            }
+
+            // Handle pulling the options of the element, skipping over everything until the matching closing '}'.
+            if text[position..].starts_with('{') {
+                let mut depth = 0;
+                let close = text[position..end].char_indices().find_map(|(offset, c)| {
+                    match c {
+                        '{' => depth += 1,
+                        '}' => depth -= 1,
+                        _ => return None
+                    }
+                    (depth == 0).then_some(position + offset)
+                });
+                let Some(close) = close else {
+                    return Err(pipeline_error(text, position,
+                        "missing closing '}' while processing pass options"));
+                };
+                stack.last_mut().unwrap().last_mut().unwrap().options
+                    = &text[position + 1..close];
+                position = skip_whitespace(text, close + 1, end);
+                if position == end {
+                    break;
+                }
+            }
+
+            // Handle the start of a nested pipeline. An empty nested pipeline, e.g. `func.func()`, has no element.
+            if text[position..].starts_with('(') {
+                stack.push(Vec::new());
+                position = skip_whitespace(text, position + 1, end);
+                if !text[position..end].starts_with(')') {
+                    continue;
+                }
+            }
+
+            // When handling the close parenthesis, we greedily consume them to avoid empty strings in the pipeline.
+            while text[position..end].starts_with(')') {
+                // If we try to pop the outer pipeline we have unbalanced parentheses.
+                if stack.len() == 1 {
+                    return Err(pipeline_error(text, position,
+                        "encountered extra closing ')' creating unbalanced parentheses while parsing pipeline"));
+                }
+                let inner_pipeline = stack.pop().unwrap();
+                stack.last_mut().unwrap().last_mut().unwrap().inner_pipeline
+                    = Some(inner_pipeline);
+                position = skip_whitespace(text, position + 1, end);
+            }
+
+            // Check if we've finished parsing.
+            if position == end {
+                break;
+            }
+
+            // Otherwise, the end of an element always has to be followed by a comma, and then we can continue.
+            if !text[position..end].starts_with(',') {
+                return Err(pipeline_error(text, position,
+                    "expected ',' after parsing pipeline"));
+            }
+            position = skip_whitespace(text, position + 1, end);
+            if position == end {
+                return Err(pipeline_error(text, position,
+                    "expected pass pipeline element after ','"));
+            }
+        }
+
+        // Check for unbalanced parentheses.
+        if stack.len() > 1 {
+            return Err(pipeline_error(text, end,
+                "encountered unbalanced parentheses while parsing pipeline"));
+        }
+        Ok(Self { text, elements: stack.pop().unwrap() })
+    }
+
+    /// Add the given pipeline elements to the provided pass manager, looking up their arguments in the global registry.
+    fn add_to_pipeline(&self, elements: &[PipelineElement<'a>], pm: &mut OpPassManager)
+        -> LogicalResult
+    {
+        for element in elements {
+            if element.name.is_empty() {
+                return Err(pipeline_error(self.text, element.position,
+                    "expected pass pipeline element name"));
+            }
+
+            // Nested pipelines are anchored on the operation named by the element.
+            if let Some(inner_pipeline) = &element.inner_pipeline {
+                if !element.options.is_empty() {
+                    return Err(pipeline_error(self.text, element.position, format!(
+                        "unexpected options on the pipeline nested on '{}'", element.name)));
+                }
+                self.add_to_pipeline(inner_pipeline, pm.nest_name(element.name))?;
+                continue;
+            }
+
+            // The entries are copied out of the registry, whose lock must not be held while pipelines are built, as they may parse pipelines themselves.
+            let (pipeline_info, pass_info) = {
+                let registry = PassRegistry::global();
+                (registry.lookup_pass_pipeline_info(element.name).copied(),
+                 registry.lookup_pass_info(element.name).cloned())
+            };
+            if let Some(pipeline_info) = pipeline_info {
+                pipeline_info.add_to_pipeline(pm, element.options)
+                    .map_err(|error| pipeline_error(self.text, element.position, error))?;
+                continue;
+            }
+            let Some(pass_info) = pass_info else {
+                return Err(pipeline_error(self.text, element.position, format!(
+                    "'{}' does not refer to a registered pass or pass pipeline",
+                    element.name)));
+            };
+            let mut pass = pass_info.create_pass();
+            pass.initialise_options(element.options)
+                .map_err(|error| pipeline_error(self.text, element.position, error))?;
+            if let (Some(anchor), Some(pass_anchor)) = (pm.op_name(), pass.op_name())
+                && anchor != pass_anchor && pm.nesting() == Nesting::Explicit
+            {
+                return Err(pipeline_error(self.text, element.position, format!(
+                    "can't add pass '{}' restricted to '{}' on a PassManager intended to run on '{}', did you intend to nest?",
+                    element.name, pass_anchor, anchor)));
+            }
+            pm.add_pass(pass);
+        }
+        Ok(())
+    }
+}
+
+/// Return the offset of the first character at or after `position` in `text` that is not a whitespace, or `end` if there is none.
+fn skip_whitespace(text: &str, position: usize, end: usize) -> usize {
+    text[position..end].find(|c: char| !c.is_whitespace())
+        .map_or(end, |offset| position + offset)
+}
+
+/// Create an error at offset `position` of the textual pipeline `text`, showing the pipeline with a caret under the error as source manager diagnostics do.
+fn pipeline_error(text: &str, position: usize, message: impl Display)
+    -> Box<dyn Error>
+{
+    let column = text[..position].chars().count();
+    format!("{}\n{}\n{}^", message, text, " ".repeat(column)).into()
+}
+
+// ----------------------------------------------------------------------
+// OpPassManager Options
+// ----------------------------------------------------------------------
+
+/// Pass managers are the values of options holding nested pipelines, e.g. the pipelines the inliner runs on the callables of each operation type, given as anchored textual pipelines such as `op-pipelines=func.func(cse,canonicalize)`.
+impl OptionValue for OpPassManager {
+    const VALUE_NAME: &'static str = "pass-manager";
+
+    fn parse_value(value: &str) -> Result<Self, String> {
+        parse_pass_pipeline(value).map_err(|error| error.to_string())
+    }
+
+    fn print_value(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        self.print_as_textual_pipeline(os)
+    }
+}
+
+// ----------------------------------------------------------------------
+// PassPipelineCLParser
+// ----------------------------------------------------------------------
+
+/**
+This class implements a command-line parser for MLIR passes. It registers a flag for each registered pass and pass pipeline, which adds it to the pipeline in the order the flags are given, e.g. `--my-pass --snapshot-op-locations='filename=snapshot.mlir'`, and a `--pass-pipeline` flag taking a textual pipeline anchored on an operation.
+*/
+#[derive(Clone, Debug, Default)]
+pub struct PassPipelineCLParser {
+    /// The textual pipeline given with `--pass-pipeline`, if any.
+    pass_pipeline: Option<String>,
+    /// The arguments of the passes and pass pipelines given with their own flags, in order, with their options.
+    passes: Vec<(String, String)>
+}
+
+impl PassPipelineCLParser {
+    const PASS_PIPELINE_ARGUMENT: &'static str = "pass-pipeline";
+
+    /// Add the flags of the passes and pass pipelines of the global registry to `command`, listed under `description` in its help.
+    pub fn augment_args(command: Command, description: &str) -> Command {
+        let registry = PassRegistry::global();
+        let mut command = command
+            .arg(Arg::new(Self::PASS_PIPELINE_ARGUMENT)
+                .long(Self::PASS_PIPELINE_ARGUMENT)
+                .value_name("pipeline")
+                .help("Textual description of the pass pipeline to run, e.g. `builtin.module(func.func(snapshot-op-locations))`"))
+            .next_help_heading(description.to_string());
+        let flags = registry.pass_pipelines()
+            .map(|info| pass_arg(info.argument(), info.description(), &info.option_infos()))
+            .chain(registry.passes()
+                .map(|info| pass_arg(info.argument(), info.description(), &info.option_infos())));
+        for flag in flags {
+            command = command.arg(flag.action(ArgAction::Append));
+        }
+        command
+    }
+
+    /// Collect the passes and pass pipelines given on the command line parsed by a command augmented with `augment_args`.
+    pub fn from_arg_matches(matches: &ArgMatches) -> Result<Self, Box<dyn Error>> {
+        let registry = PassRegistry::global();
+        let arguments = registry.pass_pipelines().map(|info| info.argument())
+            .chain(registry.passes().map(|info| info.argument()));
+        let mut passes = Vec::new();
+        for argument in arguments {
+            let (Some(indices), Ok(Some(values)))
+                = (matches.indices_of(argument), matches.try_get_many::<String>(argument))
+            else {
+                continue;
+            };
+            passes.extend(indices.zip(values)
+                .map(|(index, options)| (index, argument.to_string(), options.clone())));
+        }
+        passes.sort_by_key(|(index, ..)| *index);
+        let pass_pipeline = matches.get_one::<String>(Self::PASS_PIPELINE_ARGUMENT).cloned();
+        if pass_pipeline.is_some() && !passes.is_empty() {
+            return Err("'--pass-pipeline' option can't be used with individual pass options".into());
+        }
+        let passes = passes.into_iter()
+            .map(|(_, argument, options)| (argument, options))
+            .collect();
+        Ok(Self { pass_pipeline, passes })
+    }
+
+    /// Returns true if this parser contains any valid options to add.
+    pub fn has_any_occurrences(&self) -> bool {
+        self.pass_pipeline.is_some() || !self.passes.is_empty()
+    }
+
+    /// Adds the passes defined by this parser entry to the given pass manager.
+    pub fn add_to_pipeline(&self, pm: &mut OpPassManager) -> LogicalResult {
+        if let Some(pass_pipeline) = &self.pass_pipeline {
+            return parse_pass_pipeline_into(pass_pipeline, pm);
+        }
+        // The flags are added as a single textual pipeline, so that nothing is added on failure.
+        let pipeline = self.passes.iter()
+            .map(|(argument, options)| match options.is_empty() {
+                true => argument.clone(),
+                false => format!("{}{{{}}}", argument, options)
+            })
+            .collect::<Vec<_>>()
+            .join(",");
+        parse_pass_pipeline_elements(&pipeline, pm)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pass::{
+        options::{PassOptions, parse_list_option},
+        pass::PassExecutionState
+    };
+
+    /// An op-agnostic pass without options.
+    #[derive(Clone, Default)]
+    struct TestPass;
+
+    impl Pass for TestPass {
+        fn argument(&self) -> &str {
+            "test-pass"
+        }
+
+        fn run_on_operation(&mut self, _state: &mut PassExecutionState) -> LogicalResult {
+            Ok(())
+        }
+
+        fn clone_pass(&self) -> Box<dyn Pass> {
+            Box::new(self.clone())
+        }
+    }
+
+    crate::register_pass!(TestPass);
+
+    /// A pass anchored on `func.func`.
+    #[derive(Clone, Default)]
+    struct TestFuncPass;
+
+    impl Pass for TestFuncPass {
+        fn argument(&self) -> &str {
+            "test-func-pass"
+        }
+
+        fn op_name(&self) -> Option<&str> {
+            Some("func.func")
+        }
+
+        fn run_on_operation(&mut self, _state: &mut PassExecutionState) -> LogicalResult {
+            Ok(())
+        }
+
+        fn clone_pass(&self) -> Box<dyn Pass> {
+            Box::new(self.clone())
+        }
+    }
+
+    crate::register_pass!(TestFuncPass);
+
+    #[derive(Clone, Debug, PartialEq, PassOptions)]
+    struct TestOptions {
+        /// The depth of the test.
+        #[option(default = 1)]
+        depth: u32
+    }
+
+    /// A pass with options.
+    #[derive(Clone, Default)]
+    struct TestOptionsPass {
+        options: TestOptions
+    }
+
+    impl Pass for TestOptionsPass {
+        fn argument(&self) -> &str {
+            "test-options-pass"
+        }
+
+        fn initialise_options(&mut self, options: &str) -> LogicalResult {
+            self.options.parse_from_string(options)
+        }
+
+        fn print_options(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+            self.options.print(os)
+        }
+
+        fn option_infos(&self) -> Vec<OptionInfo> {
+            TestOptions::option_infos()
+        }
+
+        fn run_on_operation(&mut self, _state: &mut PassExecutionState) -> LogicalResult {
+            Ok(())
+        }
+
+        fn clone_pass(&self) -> Box<dyn Pass> {
+            Box::new(self.clone())
+        }
+    }
+
+    crate::register_pass!(TestOptionsPass);
+
+    crate::register_pass_pipeline!(
+        "test-pipeline", "Run the test pass twice",
+        |pm: &mut OpPassManager| parse_pass_pipeline_elements("test-pass,test-pass", pm));
+
+    crate::register_pass_pipeline!(
+        "test-options-pipeline", "Run the test pass with options at a depth", TestOptions,
+        |pm: &mut OpPassManager, options: &TestOptions|
+            parse_pass_pipeline_elements(&format!("test-options-pass{{depth={}}}", options.depth), pm));
+
+    fn round_trip(pipeline: &str) -> String {
+        parse_pass_pipeline(pipeline).unwrap().to_string()
+    }
+
+    fn error(pipeline: &str) -> String {
+        parse_pass_pipeline(pipeline).err().unwrap().to_string()
+    }
+
+    #[test]
+    fn pipelines_round_trip() {
+        let pipeline = "builtin.module(func.func(test-func-pass,test-options-pass{depth=3}),test-pass)";
+        assert_eq!(round_trip(pipeline), pipeline);
+        assert_eq!(round_trip(" builtin.module( func.func( ) , test-pass ) "),
+                   "builtin.module(func.func(),test-pass)");
+        assert_eq!(round_trip("builtin.module()"), "builtin.module()");
+        assert_eq!(round_trip("any(test-options-pass{})"), "any(test-options-pass{depth=1})");
+    }
+
+    #[test]
+    fn registered_pipelines_are_expanded() {
+        assert_eq!(round_trip("builtin.module(test-pipeline)"), "builtin.module(test-pass,test-pass)");
+        assert_eq!(round_trip("builtin.module(test-options-pipeline{depth=4})"),
+                   "builtin.module(test-options-pass{depth=4})");
+        assert!(error("builtin.module(test-pipeline{depth=4})")
+            .starts_with("pass pipeline 'test-pipeline' has no options, but got 'depth=4'"));
+        assert!(error("builtin.module(test-options-pipeline{width=4})")
+            .starts_with("no such option 'width'"));
+    }
+
+    #[test]
+    fn errors_point_at_the_element() {
+        assert_eq!(error("builtin.module(test-pass,unknown-pass)"),
+            "'unknown-pass' does not refer to a registered pass or pass pipeline\n\
+             builtin.module(test-pass,unknown-pass)\n\
+             \x20                        ^");
+        assert_eq!(error("test-pass"),
+            "expected pass pipeline to be wrapped with the anchor operation type, e.g. 'builtin.module(...)'\n\
+             test-pass\n\
+             ^");
+        assert_eq!(error("builtin.module(test-pass{depth=1})"),
+            "pass 'test-pass' has no options, but got 'depth=1'\n\
+             builtin.module(test-pass{depth=1})\n\
+             \x20              ^");
+        assert_eq!(error("builtin.module(test-pass))"),
+            "encountered extra closing ')' creating unbalanced parentheses while parsing pipeline\n\
+             builtin.module(test-pass))\n\
+             \x20                       ^");
+        assert!(error("builtin.module(func.func(test-pass)")
+            .starts_with("encountered unbalanced parentheses while parsing pipeline"));
+        assert!(error("builtin.module(test-options-pass{depth=1)")
+            .starts_with("missing closing '}' while processing pass options"));
+        assert!(error("builtin.module(test-pass,)")
+            .starts_with("expected pass pipeline element after ','"));
+        assert!(error("builtin.module(func.func(test-pass)test-pass)")
+            .starts_with("expected ',' after parsing pipeline"));
+        assert!(error("builtin.module(,test-pass)")
+            .starts_with("expected pass pipeline element name"));
+        assert!(error("builtin.module(func.func{depth=1}(test-pass))")
+            .starts_with("unexpected options on the pipeline nested on 'func.func'"));
+        assert!(error("builtin.module(test-func-pass)").starts_with(
+            "can't add pass 'test-func-pass' restricted to 'func.func' on a PassManager intended to run on 'builtin.module', did you intend to nest?"));
+    }
+
+    #[test]
+    fn failed_parses_leave_the_pass_manager_unchanged() {
+        let mut pm = OpPassManager::new("builtin.module", Nesting::Explicit);
+        assert!(parse_pass_pipeline_elements("test-pass,unknown-pass", &mut pm).is_err());
+        assert!(pm.is_empty());
+        parse_pass_pipeline_elements("test-pass,func.func(test-func-pass)", &mut pm).unwrap();
+        assert_eq!(pm.to_string(), "builtin.module(test-pass,func.func(test-func-pass))");
+
+        assert_eq!(parse_pass_pipeline_into("func.func(test-pass)", &mut pm).unwrap_err().to_string(),
+                   "expected the pass pipeline to be anchored on 'builtin.module', but got 'func.func'");
+        parse_pass_pipeline_into("builtin.module(test-options-pass)", &mut pm).unwrap();
+        assert_eq!(pm.len(), 3);
+    }
+
+    #[test]
+    fn implicit_nesting_nests_anchored_passes() {
+        let mut pm = OpPassManager::new("builtin.module", Nesting::Implicit);
+        parse_pass_pipeline_elements("test-func-pass,test-pass", &mut pm).unwrap();
+        assert_eq!(pm.to_string(), "builtin.module(func.func(test-func-pass),test-pass)");
+    }
+
+    #[test]
+    fn pass_managers_are_option_values() {
+        let pm = OpPassManager::parse_value("func.func(test-pass, test-options-pass{depth=2})").unwrap();
+        let mut printed = String::new();
+        pm.print_value(&mut printed).unwrap();
+        assert_eq!(printed, "func.func(test-pass,test-options-pass{depth=2})");
+        assert!(OpPassManager::parse_value("test-pass").is_err());
+
+        let pms = parse_list_option::<OpPassManager>(
+            "op-pipelines", Some("func.func(test-pass,test-pass),any(test-options-pass{depth=2})")).unwrap();
+        let printed: Vec<_> = pms.iter().map(ToString::to_string).collect();
+        assert_eq!(printed, ["func.func(test-pass,test-pass)", "any(test-options-pass{depth=2})"]);
+    }
+
+    fn test_pass() -> Box<dyn Pass> {
+        Box::new(TestPass)
+    }
+
+    fn test_pipeline(pm: &mut OpPassManager, _options: &str) -> LogicalResult {
+        parse_pass_pipeline_elements("test-pass", pm)
+    }
+
+    #[test]
+    fn passes_are_registered_by_argument() {
+        let mut registry = PassRegistry::default();
+        registry.register_pass(|| Box::new(TestOptionsPass::default()));
+        registry.register_pass(test_pass);
+        registry.register_pass_pipeline(PassPipelineInfo::new(
+            "test-pipeline", "Run the test pass", Vec::new, test_pipeline));
+
+        let arguments: Vec<_> = registry.passes().map(PassInfo::argument).collect();
+        assert_eq!(arguments, ["test-options-pass", "test-pass"]);
+        let info = registry.lookup_pass_info("test-options-pass").unwrap();
+        assert_eq!(info.option_infos(), TestOptions::option_infos());
+        assert!(registry.lookup_pass_info("test-pipeline").is_none());
+        let info = registry.lookup_pass_pipeline_info("test-pipeline").unwrap();
+        assert_eq!(info.description(), "Run the test pass");
+
+        let mut pm = OpPassManager::any(Nesting::Explicit);
+        info.add_to_pipeline(&mut pm, "").unwrap();
+        registry.lookup_pass_info("test-options-pass").unwrap()
+            .add_to_pipeline(&mut pm, "depth=2").unwrap();
+        assert_eq!(pm.to_string(), "any(test-pass,test-options-pass{depth=2})");
+    }
+
+    #[test]
+    #[should_panic(expected = "Pass pipeline or pass registration with the same argument 'test-pass' already exists")]
+    fn arguments_are_unique() {
+        let mut registry = PassRegistry::default();
+        registry.register_pass(test_pass);
+        registry.register_pass_pipeline(PassPipelineInfo::new(
+            "test-pass", "Run the test pass", Vec::new, test_pipeline));
+    }
+
+    #[test]
+    #[should_panic(expected = "Attempting to register a pass that does not have an argument")]
+    fn passes_need_an_argument() {
+        #[derive(Clone)]
+        struct UnnamedPass;
+
+        impl Pass for UnnamedPass {
+            fn run_on_operation(&mut self, _state: &mut PassExecutionState) -> LogicalResult {
+                Ok(())
+            }
+
+            fn clone_pass(&self) -> Box<dyn Pass> {
+                Box::new(self.clone())
+            }
+        }
+
+        let mut registry = PassRegistry::default();
+        registry.register_pass(|| Box::new(UnnamedPass));
+    }
+
+    #[test]
+    fn passes_are_registered_at_link_time() {
+        let registry = PassRegistry::global();
+        for argument in ["snapshot-op-locations", "test-pass", "test-options-pass"] {
+            assert!(registry.lookup_pass_info(argument).is_some(), "{}", argument);
+        }
+        let info = registry.lookup_pass_pipeline_info("test-options-pipeline").unwrap();
+        assert_eq!(info.option_infos(), TestOptions::option_infos());
+    }
+
+    fn command() -> Command {
+        PassPipelineCLParser::augment_args(Command::new("test-opt"), "Passes")
+    }
+
+    fn parse_flags(arguments: &[&str]) -> Result<OpPassManager, Box<dyn Error>> {
+        let matches = command().try_get_matches_from(
+            ["test-opt"].iter().chain(arguments))?;
+        let parser = PassPipelineCLParser::from_arg_matches(&matches)?;
+        let mut pm = OpPassManager::new("builtin.module", Nesting::Implicit);
+        parser.add_to_pipeline(&mut pm)?;
+        Ok(pm)
+    }
+
+    #[test]
+    fn pass_flags_are_added_in_order() {
+        let pm = parse_flags(
+            &["--test-pass", "--test-options-pass=depth=2", "--test-func-pass", "--test-pass"]).unwrap();
+        assert_eq!(pm.to_string(),
+                   "builtin.module(test-pass,test-options-pass{depth=2},func.func(test-func-pass),test-pass)");
+        let pm = parse_flags(&["--test-pipeline"]).unwrap();
+        assert_eq!(pm.to_string(), "builtin.module(test-pass,test-pass)");
+
+        let matches = command().try_get_matches_from(["test-opt"]).unwrap();
+        assert!(!PassPipelineCLParser::from_arg_matches(&matches).unwrap().has_any_occurrences());
+    }
+
+    #[test]
+    fn pass_pipeline_flag_is_exclusive() {
+        let pm = parse_flags(&["--pass-pipeline=builtin.module(func.func(test-func-pass))"]).unwrap();
+        assert_eq!(pm.to_string(), "builtin.module(func.func(test-func-pass))");
+        assert_eq!(parse_flags(&["--pass-pipeline=builtin.module(test-pass)", "--test-pass"])
+                       .err().unwrap().to_string(),
+                   "'--pass-pipeline' option can't be used with individual pass options");
+        assert!(parse_flags(&["--test-options-pass=width=2"]).is_err());
+    }
+
+    #[test]
+    fn pass_flags_list_their_options() {
+        let help = command().render_long_help().to_string();
+        assert!(help.contains("--test-options-pass[=<options>]"));
+        assert!(help.contains("  --depth=<uint> - The depth of the test. [default: 1]"));
+    }
+}
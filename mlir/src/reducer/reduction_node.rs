@@ -12,7 +12,7 @@ use std::collections::VecDeque;
 use crate::{
     mlir::{
         ir::{
-            builtins::operations::Module,
+            builtins::operations::ModuleOp,
             owning_op_ref::OwningOpRef,
             region::Region
         },
@@ -40,7 +40,7 @@ ReductionTreePass will build a reduction tree during module reduction and the Re
 pub struct ReductionNode<'a> {
     /// This is a copy of module from parent node. All the reducer patterns will
     /// be applied to this instance.
-    module: OwningOpRef<Module>,
+    module: OwningOpRef<ModuleOp>,
 
     /// The region of certain operation we're reducing in the module
     region: *mut Region,  // = nullptr;
@@ -0,0 +1,50 @@
+/*!
+# Math Utility Functions
+
+This file contains math utility functions used by the affine expression and map utilities.
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Support/MathExtras.h>
+*/
+
+/// Returns the result of MLIR's ceildiv operation on constants. The RHS is expected to be non-zero.
+pub fn ceil_div(lhs: i64, rhs: i64) -> i64 {
+    assert!(rhs != 0, "Division by zero");
+    let quotient = lhs / rhs;
+    if lhs % rhs != 0 && (lhs < 0) == (rhs < 0) {
+        quotient + 1
+    } else {
+        quotient
+    }
+}
+
+/// Returns the result of MLIR's floordiv operation on constants. The RHS is expected to be non-zero.
+pub fn floor_div(lhs: i64, rhs: i64) -> i64 {
+    assert!(rhs != 0, "Division by zero");
+    let quotient = lhs / rhs;
+    if lhs % rhs != 0 && (lhs < 0) != (rhs < 0) {
+        quotient - 1
+    } else {
+        quotient
+    }
+}
+
+/// Returns MLIR's mod operation on constants. MLIR's mod operation yields the remainder of the Euclidean division of `lhs` by `rhs`, and is always non-negative. The RHS is expected to be positive.
+pub fn r#mod(lhs: i64, rhs: i64) -> i64 {
+    assert!(rhs >= 1, "Modulo by a non-positive value");
+    lhs.rem_euclid(rhs)
+}
+
+/// Returns the greatest common divisor of the absolute values of `lhs` and `rhs`, which is zero only if both are.
+pub fn gcd(lhs: i64, rhs: i64) -> i64 {
+    let (mut a, mut b) = (lhs.unsigned_abs(), rhs.unsigned_abs());
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a as i64
+}
+
+/// Returns the least common multiple of `lhs` and `rhs`, which are expected to be positive.
+pub fn lcm(lhs: i64, rhs: i64) -> i64 {
+    assert!(lhs > 0 && rhs > 0, "Expected positive arguments");
+    lhs / gcd(lhs, rhs) * rhs
+}
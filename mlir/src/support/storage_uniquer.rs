@@ -5,161 +5,598 @@
 - lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Support/StorageUniquer.cpp>
 */
 
-#[allow(non_camel_case_types)]
-type unsigned = u16;
-
-use std::any::TypeId;
-
-use crate::{
-    mlir::support::{
-        llvm,
-        thread_local_cache::ThreadLocalCache,
-    },
-    llvm::{
-        adt::{
-            dense_map::DenseMap,
-            dense_set::DenseSet,
-        },
-        support::{
-            allocator::BumpPtrAllocator,
-            rw_mutex::SmartRWMutex
-        }
-    }
+use core::{
+    hash::{Hash, Hasher},
+    ptr::null_mut,
+    sync::atomic::{AtomicPtr, Ordering}
+};
+use std::{
+    any::{Any, TypeId},
+    collections::{HashMap, hash_map::DefaultHasher},
+    sync::{Arc, Mutex, OnceLock},
+    thread::available_parallelism
 };
 
+use crate::support::logical_result::LogicalResult;
+
 /**
-A utility class to get or create instances of 'storage classes'. These storage classes must derive from 'StorageUniquer::Storage'.
-
-For non-parametric storage classes, i.e. singleton classes, nothing else is needed. Instances of these classes can be created by calling `get` without trailing arguments.
-
-Otherwise, the parametric storage classes may be created with `get`, and must respect the following:
-
-- Define a type alias, KeyTy, to a type that uniquely identifies the
-     instance of the storage class.
-     * The key type must be constructible from the values passed into the
-       getComplex call.
-     * If the KeyTy does not have an llvm::DenseMapInfo specialisation, the
-       storage class must define a hashing method:
-        'static unsigned hashKey(const KeyTy &)'
-
-- Provide a method, 'bool operator==(const KeyTy &) const', to
-     compare the storage instance against an instance of the key type.
-
-- Provide a static construction method:
-       `DerivedStorage *construct(StorageAllocator &, const KeyTy &key)`
-     that builds a unique instance of the derived storage. The arguments to
-     this function are an allocator to store any uniqued data and the key
-     type for this storage.
-
-- Provide a cleanup method:
-       `void cleanup()`
-     that is called when erasing a storage instance. This should cleanup any
-     fields of the storage as necessary and not attempt to free the memory
-     of the storage itself.
-
-Storage classes may have an optional mutable component, which must not take part in the unique immutable key. In this case, storage classes may be mutated with `mutate` and must additionally respect the following:
-
-- Provide a mutation method:
-       `LogicalResult mutate(StorageAllocator &, <...>)`
-     that is called when mutating a storage instance. The first argument is
-     an allocator to store any mutable data, and the remaining arguments are
-     forwarded from the call site. The storage can be mutated at any time
-     after creation. Care must be taken to avoid excessive mutation since
-     the allocated storage can keep containing previous states. The return
-     value of the function is used to indicate whether the mutation was
-     successful, e.g., to limit the number of mutations or enable deferred
-     one-time assignment of the mutable component.
-
-All storage classes must be registered with the uniquer via `registerParametricStorageType` or `registerSingletonStorageType` using an appropriate unique `TypeId` for the storage class.
+A utility class to get or create instances of 'storage classes'. These storage classes must implement `Storage`.
+
+For non-parametric storage classes, i.e. singleton classes, nothing else is needed. Instances of these classes are registered with `register_singleton_storage_type` and retrieved with `get_singleton`.
+
+Otherwise, the parametric storage classes may be created with `get`, and must implement `ParametricStorage`:
+
+- Define an associated type, `Key`, that uniquely identifies the instance of the storage class. If the default hash of the key is not appropriate, the storage class may override `hash_key`.
+- Provide `matches`, comparing the storage instance against an instance of the key type.
+- Provide `construct`, building a unique instance of the storage from its key. The allocator can be used to store any uniqued data.
+
+Storage classes may have an optional mutable component, which must not take part in the unique immutable key. In this case, storage classes may be mutated with `mutate` and must additionally implement `MutableStorage`:
+
+- Provide `mutate`, called when mutating a storage instance. The storage can be mutated at any time after creation, so the mutable component must be kept behind interior mutability. The uniquer serialises the mutations of all instances of a storage class. The return value indicates whether the mutation was successful, e.g., to limit the number of mutations or enable deferred one-time assignment of the mutable component.
+
+All storage classes must be registered with the uniquer via `register_parametric_storage_type` or `register_singleton_storage_type` before use.
+
+Lookup of existing parametric instances is lock-free: the instances are bucketed into shards by hash, and each shard is an insert-only open addressing table that readers probe with atomic loads. Only the creation of new instances takes the lock of the shard they fall into.
 */
 pub struct StorageUniquer {
-    // ==================
-    // StorageUniquerImpl
-    // ==================
-    
     /// Map of type ids to the storage uniquer to use for registered objects.
-    parametric_uniquers: DenseMap<TypeId, std::unique_ptr<ParametricStorageUniquer>>,
+    parametric_uniquers: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
 
     /**
     Map of type ids to a singleton instance when the storage class is a singleton.
     */
-    singleton_instances: DenseMap<TypeId, Option<Storage>>,
-
-    /// Allocator used for uniquing singleton instances.
-    singleton_allocator: StorageAllocator,
+    singleton_instances: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
 
     /// Flag specifying if multi-threading is enabled within the uniquer.
-    threading_is_enabled: bool,  // = true;
+    threading_is_enabled: bool  // = true
+}
+
+impl StorageUniquer {
+    pub fn new() -> Self {
+        Self {
+            parametric_uniquers: HashMap::new(),
+            singleton_instances: HashMap::new(),
+            threading_is_enabled: true
+        }
+    }
+
+    /// Set the flag specifying if multi-threading is disabled within the uniquer. Storage types registered afterwards use a single shard when threading is disabled.
+    pub fn disable_multithreading(&mut self, disable: bool /* true */) {
+        self.threading_is_enabled = !disable;
+    }
+
+    /// Register a new parametric storage class, this is necessary to create instances of this class type.
+    pub fn register_parametric_storage_type<T: ParametricStorage>(&mut self) {
+        let num_shards = if self.threading_is_enabled {
+            available_parallelism().map_or(1, |num| num.get()).next_power_of_two()
+        } else {
+            1
+        };
+        self.parametric_uniquers.entry(TypeId::of::<T>()).or_insert_with(||
+            Box::new(ParametricStorageUniquer::<T>::new(num_shards)));
+    }
+
+    /// Register a new singleton storage class, this is necessary to get the singleton instance.
+    pub fn register_singleton_storage_type<T: Storage>(&mut self, storage: T) {
+        self.singleton_instances.insert(TypeId::of::<T>(), Arc::new(storage));
+    }
+
+    /// Test if there is a parametric storage registered for the given storage class.
+    pub fn is_parametric_storage_initialised<T: ParametricStorage>(&self) -> bool {
+        self.parametric_uniquers.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Test if there is a singleton storage registered for the given storage class.
+    pub fn is_singleton_storage_initialised<T: Storage>(&self) -> bool {
+        self.singleton_instances.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Gets a uniqued instance of `T` identified by `key`, creating it if it doesn't exist yet.
+    pub fn get<T: ParametricStorage>(&self, key: T::Key) -> Arc<T> {
+        self.get_with(key, |_| {})
+    }
+
+    /// Gets a uniqued instance of `T` identified by `key`. If the instance is newly created, `init` is invoked on it before it is published to other threads.
+    pub fn get_with<T: ParametricStorage>(
+        &self,
+        key: T::Key,
+        init: impl FnOnce(&mut T)
+    ) -> Arc<T> {
+        self.parametric_uniquer::<T>().get_or_create(key, init)
+    }
+
+    /// Gets the uniqued instance of `T` identified by `key`, or None if it hasn't been created yet. This never blocks.
+    pub fn lookup<T: ParametricStorage>(&self, key: &T::Key) -> Option<Arc<T>> {
+        let uniquer = self.parametric_uniquer::<T>();
+        uniquer.lookup(T::hash_key(key), key)
+    }
+
+    /// Gets a uniqued instance of the singleton storage class `T`.
+    pub fn get_singleton<T: Storage>(&self) -> Arc<T> {
+        let instance = self.singleton_instances.get(&TypeId::of::<T>())
+            .expect("expected singleton storage to be initialised")
+            .clone();
+        instance.downcast::<T>().unwrap()
+    }
+
+    /**
+    Changes the mutable component of `storage` by forwarding the trailing arguments to its `mutate` method. The mutations of all instances of `T` are serialised by the uniquer.
+    */
+    pub fn mutate<T: MutableStorage>(&self, storage: &T, args: T::Mutation)
+        -> LogicalResult
+    {
+        let uniquer = self.parametric_uniquer::<T>();
+        let _guard = uniquer.mutation_mutex.lock().unwrap();
+        storage.mutate(&mut StorageAllocator, args)
+    }
+
+    fn parametric_uniquer<T: ParametricStorage>(&self) -> &ParametricStorageUniquer<T> {
+        self.parametric_uniquers.get(&TypeId::of::<T>())
+            .expect("expected parametric storage to be initialised")
+            .downcast_ref::<ParametricStorageUniquer<T>>()
+            .unwrap()
+    }
 }
 
+impl Default for StorageUniquer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ----------------------------------------------------------------------
+// Storage
+// ----------------------------------------------------------------------
+
 /**
-This class acts as the base storage that all storage classes must derived from.
+This class acts as the base storage that all storage classes must implement. Instances are shared between threads once uniqued.
 */
-#[repr(align(8))]
-pub struct Storage {
+pub trait Storage: Any + Send + Sync {
+}
+
+/// A storage class whose instances are uniqued by a key.
+pub trait ParametricStorage: Storage + Sized {
+    /// The type that uniquely identifies an instance of the storage class.
+    type Key: Hash;
+
+    /// Compute the hash of the given key. Instances whose keys are equal must hash the same.
+    fn hash_key(key: &Self::Key) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        key.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Returns true if this instance is identified by `key`.
+    fn matches(&self, key: &Self::Key) -> bool;
+
+    /// Build a unique instance of the storage class from `key`.
+    fn construct(allocator: &mut StorageAllocator, key: Self::Key) -> Self;
 }
 
-/// This is a utility allocator used to allocate memory for instances of derived types.
-pub struct StorageAllocator {
-    /// The raw allocator for type storage objects.
-    allocator: BumpPtrAllocator
+/// A parametric storage class with a mutable component that does not take part in its key.
+pub trait MutableStorage: ParametricStorage {
+    /// The arguments of a mutation.
+    type Mutation;
+
+    /// Mutate this instance with the given arguments. The storage is shared, so the mutable component must be kept behind interior mutability.
+    fn mutate(&self, allocator: &mut StorageAllocator, args: Self::Mutation)
+        -> LogicalResult;
+}
+
+/// This is a utility allocator used to allocate memory for instances of derived types. Storage instances own their data, so uniqued data is handed out as shared copies.
+pub struct StorageAllocator;
+
+impl StorageAllocator {
+    /// Copy the specified array of elements into memory managed by the allocator.
+    pub fn copy_into<T: Clone>(&mut self, elements: &[T]) -> Arc<[T]> {
+        Arc::from(elements)
+    }
+
+    /// Copy the provided string into memory managed by the allocator.
+    pub fn copy_str(&mut self, string: &str) -> Arc<str> {
+        Arc::from(string)
+    }
 }
 
+// ----------------------------------------------------------------------
+// ParametricStorageUniquer
+// ----------------------------------------------------------------------
+
 /**
 This class represents a uniquer for storage instances of a specific type that has parametric storage. It contains all of the necessary data to unique storage instances in a thread safe way. This allows for the main uniquer to bucket each of the individual sub-types removing the need to lock the main uniquer itself.
 */
-pub struct ParametricStorageUniquer {
+struct ParametricStorageUniquer<T: ParametricStorage> {
     /**
-    A thread local cache for storage objects. This helps to reduce the lock contention when an object already existing in the cache.
+    A set of uniquer shards to allow for further bucketing accesses for instances of this storage type. Each shard is lazily initialised to reduce the overhead when only a small amount of shards are in use.
     */
-    local_cache: ThreadLocalCache<StorageTypeSet>,
+    shards: Box<[OnceLock<Shard<T>>]>,
+
+    /// A mutex serialising the mutations of the instances of this storage type.
+    mutation_mutex: Mutex<()>
+}
 
-    // /**
-    // A set of uniquer shards to allow for further bucketing accesses for instances of this storage type. Each shard is lazily initialised to reduce the overhead when only a small amount of shards are in use.
-    // */
-    // shards: std::unique_ptr<std::atomic<Shard *>[]>,
+impl<T: ParametricStorage> ParametricStorageUniquer<T> {
+    fn new(num_shards: usize) -> Self {
+        assert!(num_shards.is_power_of_two(), "the number of shards is expected to be a power of two");
+        Self {
+            shards: (0..num_shards).map(|_| OnceLock::new()).collect(),
+            mutation_mutex: Mutex::new(())
+        }
+    }
 
-    /// The number of available shards.
-    num_shards: usize,
+    /// Return the shard used for the given hash value.
+    fn shard(&self, hash_value: u64) -> &Shard<T> {
+        // The low bits of the hash select the shard, and the high bits the bucket within it.
+        let index = hash_value as usize & (self.shards.len() - 1);
+        self.shards[index].get_or_init(Shard::new)
+    }
 
-    /// Function to used to destruct any allocated storage instances.
-    destructor_fn: fn(Option<Storage>)  // function_ref,
-}
+    /// Look up an instance for the given key, without locking.
+    fn lookup(&self, hash_value: u64, key: &T::Key) -> Option<Arc<T>> {
+        self.shard(hash_value).lookup(hash_value, key)
+    }
 
-/// A lookup key for derived instances of storage objects.
-pub struct LookupKey {
-    /// The known hash value of the key.
-    hash_value: unsigned,
+    /// Get or create an instance of a parametric type.
+    fn get_or_create(&self, key: T::Key, init: impl FnOnce(&mut T)) -> Arc<T> {
+        let hash_value = T::hash_key(&key);
+        let shard = self.shard(hash_value);
 
-    /// An equality function for comparing with an existing storage instance.
-    is_equal: fn(Option<Storage>) -> bool  // function_ref
+        // Check for an existing instance first, which only takes atomic loads.
+        if let Some(storage) = shard.lookup(hash_value, &key) {
+            return storage;
+        }
+        shard.insert(hash_value, key, init)
+    }
 }
 
 /**
 A utility wrapper object representing a hashed storage object. This class contains a storage object and an existing computed hash value.
 */
-struct HashedStorage {
-    hash_value: unsigned,
-    storage: Option<Storage>
+struct HashedStorage<T> {
+    hash_value: u64,
+    storage: Arc<T>
 }
 
-/// Storage info for derived TypeStorage objects.
-struct StorageKeyInfo {
+/// An open addressing table of hashed storage instances. Buckets are only ever filled, never cleared, so readers may probe a table while it is being inserted into.
+struct Table<T> {
+    buckets: Box<[AtomicPtr<HashedStorage<T>>]>
 }
 
-type StorageTypeSet = DenseSet<HashedStorage, StorageKeyInfo>;
+impl<T> Table<T> {
+    fn with_capacity(capacity: usize) -> Self {
+        Self { buckets: (0..capacity).map(|_| AtomicPtr::new(null_mut())).collect() }
+    }
+
+    /// Return the index of the first bucket to probe for the given hash value.
+    fn first_bucket(&self, hash_value: u64) -> usize {
+        (hash_value >> 32) as usize & (self.buckets.len() - 1)
+    }
+}
 
 /**
 This class represents a single shard of the uniquer. The uniquer uses a set of shards to allow for multiple threads to create instances with less lock contention.
 */
-struct Shard {
-    /// The set containing the allocated storage instances.
-    instances: StorageTypeSet,
+struct Shard<T: ParametricStorage> {
+    /// The current table of instances. It is replaced by a larger copy when it fills up, which readers pick up on their next lookup.
+    table: AtomicPtr<Table<T>>,
+
+    /// A mutex serialising insertions, holding the bookkeeping of the shard.
+    state: Mutex<ShardState<T>>
+}
+
+struct ShardState<T> {
+    /// The number of instances in the shard.
+    len: usize,
+    /// Tables replaced by a larger copy. Readers may still be probing them, so they are only freed along with the shard.
+    retired: Vec<*mut Table<T>>
+}
+
+impl<T: ParametricStorage> Shard<T> {
+    /// The initial number of buckets of a shard.
+    const INITIAL_CAPACITY: usize = 16;
+
+    fn new() -> Self {
+        let table = Box::new(Table::with_capacity(Self::INITIAL_CAPACITY));
+        Self {
+            table: AtomicPtr::new(Box::into_raw(table)),
+            state: Mutex::new(ShardState { len: 0, retired: Vec::new() })
+        }
+    }
+
+    fn lookup(&self, hash_value: u64, key: &T::Key) -> Option<Arc<T>> {
+        // SAFETY: Tables are only freed when the shard is dropped.
+        let table = unsafe { &*self.table.load(Ordering::Acquire) };
+        let mask = table.buckets.len() - 1;
+        let mut index = table.first_bucket(hash_value);
+        loop {
+            let entry = table.buckets[index].load(Ordering::Acquire);
+            if entry.is_null() {
+                return None;
+            }
+            // SAFETY: Entries are published fully initialised, and only freed when the shard is dropped.
+            let entry = unsafe { &*entry };
+            if entry.hash_value == hash_value && entry.storage.matches(key) {
+                return Some(entry.storage.clone());
+            }
+            index = (index + 1) & mask;
+        }
+    }
+
+    fn insert(&self, hash_value: u64, key: T::Key, init: impl FnOnce(&mut T)) -> Arc<T> {
+        let mut state = self.state.lock().unwrap();
+
+        // Another thread may have inserted the instance while we were waiting for the lock.
+        if let Some(storage) = self.lookup(hash_value, &key) {
+            return storage;
+        }
+
+        // Otherwise, construct and initialise a new instance of the storage.
+        let mut storage = T::construct(&mut StorageAllocator, key);
+        init(&mut storage);
+        let storage = Arc::new(storage);
+
+        // Keep the load factor at most a half, so that probing always terminates quickly.
+        // SAFETY: Only the holder of the lock replaces the table.
+        let mut table = unsafe { &*self.table.load(Ordering::Acquire) };
+        if (state.len + 1) * 2 > table.buckets.len() {
+            let grown = Box::new(Table::with_capacity(table.buckets.len() * 2));
+            for bucket in table.buckets.iter() {
+                let entry = bucket.load(Ordering::Acquire);
+                if !entry.is_null() {
+                    // SAFETY: See `lookup`.
+                    Self::insert_into(&grown, unsafe { (*entry).hash_value }, entry);
+                }
+            }
+            let grown = Box::into_raw(grown);
+            let old = self.table.swap(grown, Ordering::AcqRel);
+            state.retired.push(old);
+            // SAFETY: The table was just published, and is freed along with the shard.
+            table = unsafe { &*grown };
+        }
+
+        let entry = Box::into_raw(Box::new(HashedStorage {
+            hash_value,
+            storage: storage.clone()
+        }));
+        Self::insert_into(table, hash_value, entry);
+        state.len += 1;
+        storage
+    }
+
+    /// Publish `entry` in the first empty bucket for `hash_value`. The caller must hold the lock of the shard.
+    fn insert_into(table: &Table<T>, hash_value: u64, entry: *mut HashedStorage<T>) {
+        let mask = table.buckets.len() - 1;
+        let mut index = table.first_bucket(hash_value);
+        while !table.buckets[index].load(Ordering::Relaxed).is_null() {
+            index = (index + 1) & mask;
+        }
+        table.buckets[index].store(entry, Ordering::Release);
+    }
+}
+
+impl<T: ParametricStorage> Drop for Shard<T> {
+    fn drop(&mut self) {
+        let state = self.state.get_mut().unwrap();
+        // SAFETY: The shard is no longer shared, and every entry lives in the current table.
+        unsafe {
+            let table = Box::from_raw(*self.table.get_mut());
+            for bucket in table.buckets.iter() {
+                let entry = bucket.load(Ordering::Relaxed);
+                if !entry.is_null() {
+                    drop(Box::from_raw(entry));
+                }
+            }
+            for retired in state.retired.drain(..) {
+                drop(Box::from_raw(retired));
+            }
+        }
+    }
+}
+
+// SAFETY: The raw table pointers are owned by the shard, and the storage instances they reference are `Send` and `Sync`.
+unsafe impl<T: ParametricStorage> Send for Shard<T> {}
+unsafe impl<T: ParametricStorage> Sync for Shard<T> {}
+
+#[cfg(test)]
+mod tests {
+    use core::sync::atomic::AtomicUsize;
+    use std::thread;
+    use super::*;
+
+    #[derive(Debug)]
+    struct Integer {
+        value: u64,
+        initialised: bool
+    }
+
+    impl Storage for Integer {}
+
+    impl ParametricStorage for Integer {
+        type Key = u64;
+
+        fn matches(&self, key: &u64) -> bool {
+            self.value == *key
+        }
+
+        fn construct(_: &mut StorageAllocator, key: u64) -> Self {
+            Self { value: key, initialised: false }
+        }
+    }
+
+    /// The number of `Counted` instances constructed.
+    static NUM_COUNTED: AtomicUsize = AtomicUsize::new(0);
+
+    /// A storage counting its constructions, only used by a single test.
+    struct Counted(u64);
+
+    impl Storage for Counted {}
+
+    impl ParametricStorage for Counted {
+        type Key = u64;
+
+        fn matches(&self, key: &u64) -> bool {
+            self.0 == *key
+        }
+
+        fn construct(_: &mut StorageAllocator, key: u64) -> Self {
+            NUM_COUNTED.fetch_add(1, Ordering::Relaxed);
+            Self(key)
+        }
+    }
+
+    /// A storage whose keys all hash the same, so every instance collides.
+    struct Colliding(String);
+
+    impl Storage for Colliding {}
 
-    /// Allocator to use when constructing derived instances.
-    allocator: StorageAllocator,
+    impl ParametricStorage for Colliding {
+        type Key = String;
 
-    // #[cfg(LLVM_ENABLE_THREADS)]
-    /// A mutex to keep uniquing thread-safe.
-    mutex: SmartRWMutex<true>
+        fn hash_key(_: &String) -> u64 {
+            42
+        }
+
+        fn matches(&self, key: &String) -> bool {
+            self.0 == *key
+        }
+
+        fn construct(allocator: &mut StorageAllocator, key: String) -> Self {
+            Self(allocator.copy_str(&key).to_string())
+        }
+    }
+
+    /// A storage whose body can be assigned once after creation, as done for recursive types.
+    struct Named {
+        name: String,
+        body: Mutex<Option<u64>>
+    }
+
+    impl Storage for Named {}
+
+    impl ParametricStorage for Named {
+        type Key = String;
+
+        fn matches(&self, key: &String) -> bool {
+            self.name == *key
+        }
+
+        fn construct(_: &mut StorageAllocator, key: String) -> Self {
+            Self { name: key, body: Mutex::new(None) }
+        }
+    }
+
+    impl MutableStorage for Named {
+        type Mutation = u64;
+
+        fn mutate(&self, _: &mut StorageAllocator, body: u64) -> LogicalResult {
+            let mut current = self.body.lock().unwrap();
+            if current.is_some_and(|current| current != body) {
+                return Err("body is already set".into());
+            }
+            *current = Some(body);
+            Ok(())
+        }
+    }
+
+    struct Singleton(u64);
+
+    impl Storage for Singleton {}
+
+    #[test]
+    fn instances_are_uniqued_by_key() {
+        let mut uniquer = StorageUniquer::new();
+        assert!(!uniquer.is_parametric_storage_initialised::<Integer>());
+        uniquer.register_parametric_storage_type::<Integer>();
+        assert!(uniquer.is_parametric_storage_initialised::<Integer>());
+
+        assert!(uniquer.lookup::<Integer>(&1).is_none());
+        let one = uniquer.get::<Integer>(1);
+        let two = uniquer.get::<Integer>(2);
+        assert_eq!((one.value, two.value), (1, 2));
+        assert!(!Arc::ptr_eq(&one, &two));
+        assert!(Arc::ptr_eq(&one, &uniquer.get::<Integer>(1)));
+        assert!(Arc::ptr_eq(&one, &uniquer.lookup::<Integer>(&1).unwrap()));
+    }
+
+    #[test]
+    fn instances_are_only_initialised_when_created() {
+        let mut uniquer = StorageUniquer::new();
+        uniquer.register_parametric_storage_type::<Integer>();
+        let created = uniquer.get_with::<Integer>(1, |storage| storage.initialised = true);
+        assert!(created.initialised);
+        let existing = uniquer.get_with::<Integer>(1, |_| panic!("Expected no initialisation"));
+        assert!(Arc::ptr_eq(&created, &existing));
+    }
+
+    #[test]
+    fn colliding_instances_are_probed() {
+        let mut uniquer = StorageUniquer::new();
+        uniquer.register_parametric_storage_type::<Colliding>();
+        // Enough instances to grow the table of their shard several times.
+        let instances: Vec<_> = (0..100)
+            .map(|index| uniquer.get::<Colliding>(index.to_string()))
+            .collect();
+        for (index, instance) in instances.iter().enumerate() {
+            assert_eq!(instance.0, index.to_string());
+            let found = uniquer.lookup::<Colliding>(&index.to_string()).unwrap();
+            assert!(Arc::ptr_eq(instance, &found));
+        }
+        assert!(uniquer.lookup::<Colliding>(&"100".to_string()).is_none());
+    }
+
+    #[test]
+    fn concurrent_creation_yields_one_instance_per_key() {
+        let mut uniquer = StorageUniquer::new();
+        uniquer.register_parametric_storage_type::<Counted>();
+        let instances: Vec<Vec<Arc<Counted>>> = thread::scope(|scope| {
+            let threads: Vec<_> = (0..8).map(|_| scope.spawn(||
+                (0..1000).map(|key| uniquer.get::<Counted>(key)).collect()
+            )).collect();
+            threads.into_iter().map(|thread| thread.join().unwrap()).collect()
+        });
+        for thread_instances in instances.iter() {
+            for (instance, first) in thread_instances.iter().zip(instances[0].iter()) {
+                assert!(Arc::ptr_eq(instance, first));
+            }
+        }
+        assert_eq!(NUM_COUNTED.load(Ordering::Relaxed), 1000);
+        for (key, instance) in instances[0].iter().enumerate() {
+            assert_eq!(instance.0, key as u64);
+        }
+    }
+
+    #[test]
+    fn disabling_multithreading_uses_a_single_shard() {
+        let mut uniquer = StorageUniquer::new();
+        uniquer.disable_multithreading(true);
+        uniquer.register_parametric_storage_type::<Integer>();
+        assert_eq!(uniquer.parametric_uniquer::<Integer>().shards.len(), 1);
+        let instances: Vec<_> = (0..100).map(|key| uniquer.get::<Integer>(key)).collect();
+        for instance in instances.iter() {
+            assert!(Arc::ptr_eq(instance, &uniquer.get::<Integer>(instance.value)));
+        }
+    }
+
+    #[test]
+    fn mutations_are_forwarded_to_the_storage() {
+        let mut uniquer = StorageUniquer::new();
+        uniquer.register_parametric_storage_type::<Named>();
+        let named = uniquer.get::<Named>("list".to_string());
+        uniquer.mutate(named.as_ref(), 1).unwrap();
+        uniquer.mutate(named.as_ref(), 1).unwrap();
+        assert!(uniquer.mutate(named.as_ref(), 2).is_err());
+        let found = uniquer.lookup::<Named>(&"list".to_string()).unwrap();
+        assert_eq!(*found.body.lock().unwrap(), Some(1));
+    }
+
+    #[test]
+    fn singletons_are_shared() {
+        let mut uniquer = StorageUniquer::new();
+        assert!(!uniquer.is_singleton_storage_initialised::<Singleton>());
+        uniquer.register_singleton_storage_type(Singleton(7));
+        assert!(uniquer.is_singleton_storage_initialised::<Singleton>());
+        let singleton = uniquer.get_singleton::<Singleton>();
+        assert_eq!(singleton.0, 7);
+        assert!(Arc::ptr_eq(&singleton, &uniquer.get_singleton::<Singleton>()));
+    }
 }
@@ -459,7 +459,7 @@ fn insertion_region(
         if
             let Some(parent_operation) = region.parent_op()
             && (
-                parent_operation.might_have_trait::<IsIsolatedFromAbove>()
+                parent_operation.might_have_trait::<dyn IsIsolatedFromAbove>()
                 || !parent_operation.block()
             )
         {
@@ -0,0 +1,505 @@
+/*!
+# Location Snapshot
+
+This file contains utilities that allow for printing IR to a file, and rewriting the locations of the operations to point into that file. This is useful to debug multi-stage lowerings: the locations of the operations of a later stage point into the snapshot of an earlier stage, instead of the original input.
+
+Alongside the rewrite, a source map is built, which maps the path of each operation to its position in the snapshot and to the file locations it had before the rewrite. Exporting the source map of every snapshot of a pipeline allows tracing an operation back through every intermediate dump.
+
+```json
+{
+  "file": "lowered.mlir",
+  "entries": [
+    {
+      "path": "0.0.1",
+      "name": "func.func",
+      "line": 2,
+      "column": 3,
+      "original": [{ "file": "input.mlir", "line": 4, "column": 3 }]
+    }
+  ]
+}
+```
+
+- include <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Transforms/LocationSnapshot.h>
+- lib <https://github.com/llvm/llvm-project/blob/main/mlir/lib/Transforms/LocationSnapshot.cpp>
+*/
+
+use core::fmt;
+use std::{
+    error::Error,
+    fs,
+    io::Write
+};
+
+use serde::Serialize;
+
+use crate::{
+    ir::{
+        arena::{ArenaResult, IRArena, OpId},
+        asm_printer::print_operation_with_state,
+        asm_state::AsmState,
+        builtins::attributes::StringAttribute,
+        location::Location,
+        operation::support::PrintingFlags
+    },
+    pass::{
+        options::{OptionInfo, PassOptions},
+        pass::{Pass, PassExecutionState}
+    },
+    support::logical_result::LogicalResult
+};
+
+// ----------------------------------------------------------------------
+// Source Map
+// ----------------------------------------------------------------------
+
+/// A file location, as recorded in a source map.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct FileLineCol {
+    pub file: String,
+    pub line: usize,
+    pub column: usize
+}
+
+/// The entry of a source map for a single operation.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+pub struct SourceMapEntry {
+    /**
+    The path of the operation from the snapshot root. Each operation below the root adds a `region.block.operation` segment of indices, and segments are separated by `/`. The root has an empty path.
+    */
+    pub path: String,
+    /// The name of the operation.
+    pub name: String,
+    /// The line of the operation in the snapshot.
+    pub line: usize,
+    /// The column of the operation in the snapshot.
+    pub column: usize,
+    /// The file locations nested in the location of the operation before it was rewritten.
+    pub original: Vec<FileLineCol>
+}
+
+/// A map from the operations of a snapshot to the file locations they had before the snapshot was taken.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct SourceMap {
+    /// The name of the snapshot file.
+    pub file: String,
+    pub entries: Vec<SourceMapEntry>
+}
+
+impl SourceMap {
+    /// Find the entry of the operation at `path`.
+    pub fn find(&self, path: &str) -> Option<&SourceMapEntry> {
+        self.entries.iter().find(|entry| entry.path == path)
+    }
+
+    /// Return the source map as a JSON string.
+    pub fn to_json(&self) -> String {
+        // The source map only holds strings and integers, which always serialise.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Write the source map as JSON to the file `file_name`.
+    pub fn export(&self, file_name: &str) -> LogicalResult {
+        fs::write(file_name, self.to_json() + "\n").map_err(|error|
+            format!("failed to write source map '{}': {}", file_name, error))?;
+        Ok(())
+    }
+}
+
+/// Collect the file locations nested in `location` into `output`, in order.
+fn collect_file_line_cols(location: &Location, output: &mut Vec<FileLineCol>) {
+    match location {
+        Location::FileLineCol(location) => output.push(FileLineCol {
+            file: location.filename().to_string(),
+            line: location.line(),
+            column: location.column()
+        }),
+        Location::CallSite(location) => {
+            collect_file_line_cols(location.callee(), output);
+            collect_file_line_cols(location.caller(), output);
+        },
+        Location::Fused(location) => for location in location.locations() {
+            collect_file_line_cols(location, output);
+        },
+        Location::Name(location) =>
+            collect_file_line_cols(location.child_location(), output),
+        Location::Opaque(location) =>
+            collect_file_line_cols(location.fallback_location(), output),
+        Location::Unknown => {}
+    }
+}
+
+/// Return `op` and the operations nested in it in pre-order, with their path from `op`.
+fn operation_paths(arena: &IRArena, op: OpId) -> ArenaResult<Vec<(OpId, String)>> {
+    let mut output = Vec::new();
+    let mut worklist = vec![(op, String::new())];
+    while let Some((current, path)) = worklist.pop() {
+        let mut children = Vec::new();
+        for (region_index, region) in arena.op(current)?.regions().iter().enumerate() {
+            for (block_index, block) in arena.region(*region)?.blocks().iter().enumerate() {
+                for (op_index, child) in arena.block(*block)?.operations().iter().enumerate() {
+                    let segment = format!("{}.{}.{}", region_index, block_index, op_index);
+                    let child_path = if path.is_empty() {
+                        segment
+                    } else {
+                        format!("{}/{}", path, segment)
+                    };
+                    children.push((*child, child_path));
+                }
+            }
+        }
+        output.push((current, path));
+        worklist.extend(children.into_iter().rev());
+    }
+    Ok(output)
+}
+
+// ----------------------------------------------------------------------
+// Location Generation
+// ----------------------------------------------------------------------
+
+/**
+Generate new locations from the given IR by snapshotting it to `output`, using the provided flags. `file_name` is used as the file of the new locations.
+
+If `tag` is provided, the new locations are named with it and fused with the original locations of the operations. Otherwise, the original locations are replaced.
+
+Returns the source map of the snapshot, holding the original locations of the operations.
+*/
+pub fn generate_locations_from_ir(
+    output: &mut dyn Write,
+    file_name: &str,
+    tag: Option<&str>,
+    arena: &mut IRArena,
+    op: OpId,
+    flags: PrintingFlags
+) -> Result<SourceMap, Box<dyn Error>> {
+    // Print the IR to the stream, and collect the raw line+column information.
+    let mut state = AsmState::new(flags).with_location_map();
+    let snapshot = print_operation_with_state(arena, op, &mut state)?;
+    output.write_all(snapshot.as_bytes())?;
+    let location_map = state.location_map()
+        .expect("A location map was requested");
+
+    let mut source_map = SourceMap { file: file_name.to_string(), entries: Vec::new() };
+    for (op, path) in operation_paths(arena, op)? {
+        let Some(&(line, column)) = location_map.get(&op) else {
+            continue;
+        };
+        let operation = arena.op_mut(op)?;
+        let original = operation.location();
+        let mut original_file_line_cols = Vec::new();
+        collect_file_line_cols(&original, &mut original_file_line_cols);
+        source_map.entries.push(SourceMapEntry {
+            path,
+            name: operation.name().as_str().to_string(),
+            line,
+            column,
+            original: original_file_line_cols
+        });
+
+        let location = Location::file_line_col(
+            StringAttribute::new(file_name), line, column);
+        operation.set_location(match tag {
+            None => location,
+            Some(tag) => Location::fused(
+                [original, Location::name(StringAttribute::new(tag), location)],
+                None)
+        });
+    }
+    Ok(source_map)
+}
+
+/// This variant of `generate_locations_from_ir` snapshots the IR to the file `file_name`.
+pub fn generate_locations_from_ir_to_file(
+    file_name: &str,
+    tag: Option<&str>,
+    arena: &mut IRArena,
+    op: OpId,
+    flags: PrintingFlags
+) -> Result<SourceMap, Box<dyn Error>> {
+    if file_name.is_empty() {
+        return Err("expected valid output file name for the location snapshot".into());
+    }
+    let mut file = fs::File::create(file_name).map_err(|error|
+        format!("failed to open location snapshot '{}': {}", file_name, error))?;
+    generate_locations_from_ir(&mut file, file_name, tag, arena, op, flags)
+}
+
+// ----------------------------------------------------------------------
+// LocationSnapshotPass
+// ----------------------------------------------------------------------
+
+/// The options of `LocationSnapshotPass`, as given in textual pipelines, e.g. `snapshot-op-locations{filename=snapshot.mlir tag=lowered}`.
+#[derive(Clone, Debug, PartialEq, Eq, PassOptions)]
+pub struct LocationSnapshotOptions {
+    /// The filename to print the generated IR.
+    #[option(argument = "filename")]
+    pub file_name: String,
+    /// A tag to use when fusing the new locations with the original. If unset, the locations are replaced.
+    pub tag: String,
+    /// Print debug info in MLIR output.
+    #[option(argument = "print-debuginfo")]
+    pub print_debug_info: bool,
+    /// Print the generic op form.
+    #[option(argument = "print-op-generic")]
+    pub print_generic_op_form: bool,
+    /// Print with local scope and inline information (eliding aliases for attributes, types, and locations).
+    pub print_local_scope: bool,
+    /// Print pretty debug info in MLIR output.
+    #[option(argument = "pretty-debuginfo")]
+    pub pretty_debug_info: bool,
+    /// The file to export the source map of the snapshot to as JSON. If unset, no source map is exported.
+    #[option(argument = "source-map")]
+    pub source_map_file_name: String
+}
+
+/// This pass generates new locations for the operations it is run on, by snapshotting the IR to a file. If requested, the source map of the snapshot is exported next to it.
+#[derive(Clone, Debug, Default)]
+pub struct LocationSnapshotPass {
+    options: LocationSnapshotOptions,
+    /// The flags to print the snapshot with, on top of the ones enabled by the options.
+    flags: PrintingFlags
+}
+
+impl LocationSnapshotPass {
+    pub fn new(file_name: impl Into<String>, tag: Option<String>) -> Self {
+        let options = LocationSnapshotOptions {
+            file_name: file_name.into(),
+            tag: tag.unwrap_or_default(),
+            ..LocationSnapshotOptions::default()
+        };
+        Self { options, flags: PrintingFlags::default() }
+    }
+
+    /// Create the pass from its options.
+    pub fn with_options(options: LocationSnapshotOptions) -> Self {
+        Self { options, flags: PrintingFlags::default() }
+    }
+
+    /// Print the snapshot with the given flags.
+    pub fn with_flags(mut self, flags: PrintingFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Export the source map of the snapshot as JSON to the file `file_name`.
+    pub fn with_source_map(mut self, file_name: impl Into<String>) -> Self {
+        self.options.source_map_file_name = file_name.into();
+        self
+    }
+
+    pub fn options(&self) -> &LocationSnapshotOptions {
+        &self.options
+    }
+
+    /// Return the flags to print the snapshot with, enabling the ones requested by the options.
+    fn printing_flags(&self) -> PrintingFlags {
+        let options = &self.options;
+        let mut flags = self.flags.clone();
+        if options.print_debug_info || options.pretty_debug_info {
+            flags = flags.enable_debug_info(true, options.pretty_debug_info);
+        }
+        if options.print_generic_op_form {
+            flags = flags.print_generic_op_form();
+        }
+        if options.print_local_scope {
+            flags = flags.use_local_scope();
+        }
+        flags
+    }
+}
+
+impl Pass for LocationSnapshotPass {
+    fn argument(&self) -> &str {
+        "snapshot-op-locations"
+    }
+
+    fn description(&self) -> &str {
+        "Generate new locations from the current IR"
+    }
+
+    fn initialise_options(&mut self, options: &str) -> LogicalResult {
+        self.options.parse_from_string(options)
+    }
+
+    fn print_options(&self, os: &mut dyn fmt::Write) -> fmt::Result {
+        self.options.print(os)
+    }
+
+    fn option_infos(&self) -> Vec<OptionInfo> {
+        LocationSnapshotOptions::option_infos()
+    }
+
+    fn run_on_operation(&mut self, state: &mut PassExecutionState) -> LogicalResult {
+        let op = state.op();
+        let tag = Some(self.options.tag.as_str()).filter(|tag| !tag.is_empty());
+        let source_map = generate_locations_from_ir_to_file(
+            &self.options.file_name, tag, state.arena_mut(), op,
+            self.printing_flags())?;
+        match self.options.source_map_file_name.as_str() {
+            "" => Ok(()),
+            file_name => source_map.export(file_name)
+        }
+    }
+
+    fn clone_pass(&self) -> Box<dyn Pass> {
+        Box::new(self.clone())
+    }
+}
+
+crate::register_pass!(LocationSnapshotPass);
+
+/// Create a pass to generate new locations by snapshotting the IR to the file `file_name`, naming the new locations with `tag` if provided.
+pub fn create_location_snapshot_pass(
+    file_name: impl Into<String>,
+    tag: Option<String>,
+    flags: PrintingFlags
+) -> LocationSnapshotPass {
+    LocationSnapshotPass::new(file_name, tag).with_flags(flags)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process;
+
+    use super::*;
+    use crate::ir::{
+        arena::BlockId,
+        builtins::attributes::DictionaryAttribute,
+        operation::{Operation, support::OperationName}
+    };
+
+    /// Create the operation `name` with `location` at the end of `block` if given, along with a region holding an empty block if `has_body`. Returns the operation and the block of its region.
+    fn create(
+        arena: &mut IRArena,
+        block: Option<BlockId>,
+        name: &str,
+        location: Location,
+        has_body: bool
+    ) -> (OpId, Option<BlockId>) {
+        let operation = Operation::new(
+            location, OperationName::new(name), [], Vec::new(),
+            DictionaryAttribute::default(), &[]);
+        let op = arena.create_operation(operation, has_body as usize).unwrap();
+        if let Some(block) = block {
+            arena.push_back(block, op).unwrap();
+        }
+        let body = has_body.then(|| {
+            let region = arena.op(op).unwrap().region(0);
+            let body = arena.create_block();
+            arena.push_back_block(region, body).unwrap();
+            body
+        });
+        (op, body)
+    }
+
+    fn file(name: &str, line: usize, column: usize) -> Location {
+        Location::file_line_col(StringAttribute::new(name), line, column)
+    }
+
+    /// Create an operation holding a loop, located in `input.mlir`, and a terminator without a location.
+    fn module(arena: &mut IRArena) -> (OpId, OpId, OpId, OpId) {
+        let (outer, body) = create(arena, None, "test.outer", file("input.mlir", 1, 1), true);
+        let location = Location::name(StringAttribute::new("loop"), file("input.mlir", 2, 3));
+        let (r#loop, loop_body) = create(arena, body, "test.loop", location, true);
+        let (r#yield, _) = create(
+            arena, loop_body, "test.yield", file("input.mlir", 3, 5), false);
+        let (term, _) = create(arena, body, "test.term", Location::unknown(), false);
+        (outer, r#loop, r#yield, term)
+    }
+
+    #[test]
+    fn locations_are_replaced() {
+        let mut arena = IRArena::new();
+        let (outer, r#loop, r#yield, term) = module(&mut arena);
+        let mut output = Vec::new();
+        let source_map = generate_locations_from_ir(
+            &mut output, "snapshot.mlir", None, &mut arena, outer, PrintingFlags::new()).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(),
+"\"test.outer\"() ({
+  \"test.loop\"() ({
+    \"test.yield\"() : () -> ()
+  }) : () -> ()
+  \"test.term\"() : () -> ()
+}) : () -> ()
+");
+        let snapshot = |op| arena.op(op).unwrap().location().to_string();
+        assert_eq!(snapshot(outer), "\"snapshot.mlir\":1:1");
+        assert_eq!(snapshot(r#loop), "\"snapshot.mlir\":2:3");
+        assert_eq!(snapshot(r#yield), "\"snapshot.mlir\":3:5");
+        assert_eq!(snapshot(term), "\"snapshot.mlir\":5:3");
+
+        assert_eq!(source_map.file, "snapshot.mlir");
+        let paths: Vec<_> = source_map.entries.iter()
+            .map(|entry| (entry.path.as_str(), entry.name.as_str(), entry.line, entry.column))
+            .collect();
+        assert_eq!(paths, [
+            ("", "test.outer", 1, 1),
+            ("0.0.0", "test.loop", 2, 3),
+            ("0.0.0/0.0.0", "test.yield", 3, 5),
+            ("0.0.1", "test.term", 5, 3)
+        ]);
+        let original = FileLineCol { file: "input.mlir".to_string(), line: 2, column: 3 };
+        assert_eq!(source_map.find("0.0.0").unwrap().original, [original]);
+        assert!(source_map.find("0.0.1").unwrap().original.is_empty());
+        assert!(source_map.find("1.0.0").is_none());
+    }
+
+    #[test]
+    fn tagged_locations_are_fused_with_the_original() {
+        let mut arena = IRArena::new();
+        let (outer, r#loop, _, term) = module(&mut arena);
+        generate_locations_from_ir(
+            &mut Vec::new(), "snapshot.mlir", Some("lowered"), &mut arena, outer,
+            PrintingFlags::new()).unwrap();
+        assert_eq!(arena.op(r#loop).unwrap().location().to_string(),
+            "fused[\"loop\"(\"input.mlir\":2:3), \"lowered\"(\"snapshot.mlir\":2:3)]");
+        // Unknown locations are dropped when fusing.
+        assert_eq!(arena.op(term).unwrap().location().to_string(),
+            "\"lowered\"(\"snapshot.mlir\":5:3)");
+    }
+
+    #[test]
+    fn source_maps_are_exported_as_json() {
+        let mut arena = IRArena::new();
+        let (outer, ..) = module(&mut arena);
+        let source_map = generate_locations_from_ir(
+            &mut Vec::new(), "snapshot.mlir", None, &mut arena, outer,
+            PrintingFlags::new()).unwrap();
+        let path = std::env::temp_dir()
+            .join(format!("mlir-source-map-{}.json", process::id()));
+        source_map.export(path.to_str().unwrap()).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&fs::read_to_string(&path).unwrap())
+            .unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(json["file"], "snapshot.mlir");
+        assert_eq!(json["entries"][1], serde_json::json!({
+            "path": "0.0.0",
+            "name": "test.loop",
+            "line": 2,
+            "column": 3,
+            "original": [{ "file": "input.mlir", "line": 2, "column": 3 }]
+        }));
+    }
+
+    #[test]
+    fn snapshots_need_a_file_name() {
+        let mut arena = IRArena::new();
+        let (outer, ..) = module(&mut arena);
+        let error = generate_locations_from_ir_to_file(
+            "", None, &mut arena, outer, PrintingFlags::new()).unwrap_err();
+        assert_eq!(error.to_string(), "expected valid output file name for the location snapshot");
+    }
+
+    #[test]
+    fn options_are_parsed() {
+        let mut pass = LocationSnapshotPass::new("a.mlir", None);
+        pass.initialise_options(
+            "filename=snapshot.mlir tag=lowered print-debuginfo source-map=map.json").unwrap();
+        let options = pass.options();
+        assert_eq!(options.file_name, "snapshot.mlir");
+        assert_eq!(options.tag, "lowered");
+        assert!(options.print_debug_info && !options.pretty_debug_info);
+        assert_eq!(options.source_map_file_name, "map.json");
+    }
+}
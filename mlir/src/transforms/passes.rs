@@ -6,29 +6,9 @@
   - <https://github.com/llvm/llvm-project/blob/main/mlir/include/mlir/Transforms/Passes.td>
 */
 
-/// Creates an instance of the Canonicaliser pass, configured with default
-/// settings (which can be overridden by pass options on the command line).
-std::unique_ptr<Pass> createCanonicaliserPass();
-
-/// Creates an instance of the Canonicaliser pass with the specified config.
-/// `disabledPatterns` is a set of labels used to filter out input patterns with
-/// a debug label or debug name in this set. `enabledPatterns` is a set of
-/// labels used to filter out input patterns that do not have one of the labels
-/// in this set. Debug labels must be set explicitly on patterns or when adding
-/// them with `RewritePatternSet::addWithLabel`. Debug names may be empty, but
-/// patterns created with `RewritePattern::create` have their default debug name
-/// set to their type name.
-std::unique_ptr<Pass>
-createCanonicaliserPass(const GreedyRewriteConfig &config,
-                        ArrayRef<std::string> disabledPatterns = std::nullopt,
-                        ArrayRef<std::string> enabledPatterns = std::nullopt);
-
 /// Creates a pass to perform control-flow sinking.
 std::unique_ptr<Pass> createControlFlowSinkPass();
 
-/// Creates a pass to perform common sub expression elimination.
-std::unique_ptr<Pass> createCSEPass();
-
 /// Creates a pass that generates IR to verify ops at runtime.
 std::unique_ptr<Pass> createGenerateRuntimeVerificationPass();
 